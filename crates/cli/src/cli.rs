@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+use uuid::Uuid;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "dashboard-cli",
+    version = "0.1.0",
+    about = "Command-line client of the Dashboard API"
+)]
+pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        help = "File of the stored API token, `~/.config/dashboard/credentials.json` by default",
+        env = "DASHBOARD_CREDENTIALS"
+    )]
+    pub credentials: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        help = "Prints the responses as JSON, for scripts"
+    )]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Subcommands of the client.
+///
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Logs in and stores the API token for the following commands.
+    Login(LoginArgs),
+    /// Manages the servers of the logged in user.
+    #[command(subcommand)]
+    Servers(ServersCommand),
+    /// Follows the background tasks of the servers.
+    #[command(subcommand)]
+    Tasks(TasksCommand),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct LoginArgs {
+    #[arg(short, long, help = "URL of the Dashboard API", env = "DASHBOARD_URL")]
+    pub url: String,
+    #[arg(short, long, help = "Email address of the user")]
+    pub email: String,
+    #[arg(
+        short,
+        long,
+        help = "Password of the user, asked for if missing",
+        env = "DASHBOARD_PASSWORD"
+    )]
+    pub password: Option<String>,
+}
+
+/// Server subcommands.
+///
+#[derive(Debug, clap::Subcommand)]
+pub enum ServersCommand {
+    /// Lists the servers.
+    List,
+    /// Orders a new server, set up in the background.
+    Create(CreateArgs),
+    /// Starts a server.
+    Start(ServerArgs),
+    /// Stops a server.
+    Stop(ServerArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CreateArgs {
+    #[arg(short, long, help = "ID of the product")]
+    pub product: Uuid,
+    #[arg(long, help = "Host name of the server")]
+    pub host_name: String,
+    #[arg(long, help = "Operating system, like `debian-12`")]
+    pub os: String,
+    #[arg(short, long, help = "Datacenter to place the server in")]
+    pub datacenter: String,
+    #[arg(long, help = "Number of CPU cores, the product's by default")]
+    pub cpu_cores: Option<i32>,
+    #[arg(long, help = "RAM in GB, the product's by default")]
+    pub ram_gb: Option<i32>,
+    #[arg(long, help = "Application of the marketplace to install")]
+    pub app: Option<String>,
+    #[arg(
+        long,
+        help = "ID of the server in your tooling, an order repeated with it isn't placed again"
+    )]
+    pub external_id: Option<String>,
+    #[arg(long = "tag", help = "Tag of the server, can be repeated")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ServerArgs {
+    #[arg(help = "ID of the server")]
+    pub server_id: Uuid,
+}
+
+/// Task subcommands.
+///
+#[derive(Debug, clap::Subcommand)]
+pub enum TasksCommand {
+    /// Follows the provisioning and the actions of a server until it settles.
+    Watch(WatchArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WatchArgs {
+    #[arg(help = "ID of the server")]
+    pub server_id: Uuid,
+    #[arg(
+        short,
+        long,
+        default_value_t = 2,
+        help = "Sets the seconds between the polls"
+    )]
+    pub interval: u64,
+}
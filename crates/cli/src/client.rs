@@ -0,0 +1,269 @@
+use dashboard_common::api::{
+    ApiProvisioningStep, ApiServer, ApiServerDetail, ApiToken, Response, TokenResponse,
+};
+use dashboard_common::prelude::{Error, Result};
+use reqwest::{RequestBuilder, StatusCode};
+use secrecy::{ExposeSecret, SecretString};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+/// Name of the API tokens created by `login`.
+const TOKEN_NAME: &str = "dashboard-cli";
+
+/// Client of the REST API, authorized with the stored token.
+///
+pub struct ApiClient {
+    http: reqwest::Client,
+    url: String,
+    token: SecretString,
+}
+
+impl ApiClient {
+    /// Creates a client of the API at the URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: URL of the API.
+    /// * `token`: Token of the logged in user.
+    ///
+    pub fn new(url: &str, token: SecretString) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_owned(),
+            token,
+        }
+    }
+
+    /// Logs in with the email address and the password of a user, and creates
+    /// an API token with the short-lived session. The password and the session
+    /// aren't kept, the API token stays valid until it is revoked.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: URL of the API.
+    /// * `email`: Email address of the user.
+    /// * `password`: Password of the user.
+    ///
+    /// # Returns
+    ///
+    /// Client authorized with the created API token.
+    ///
+    pub async fn login(url: &str, email: &str, password: &str) -> Result<Self> {
+        let client = Self::new(url, SecretString::default());
+        let payload = json!({ "email": email, "password": password });
+        let response = client
+            .http
+            .post(format!("{}/login", client.url))
+            .json(&payload);
+        let session = send::<TokenResponse>(response).await?.result.token;
+        let session = Self {
+            token: SecretString::from(session),
+            ..client
+        };
+
+        let response = session
+            .post("/me/api-tokens")
+            .json(&json!({ "name": TOKEN_NAME }));
+        let token = send::<Response<ApiToken>>(response)
+            .await?
+            .result
+            .secret
+            .ok_or_else(|| Error::Any("API token missing in the response".to_owned()))?;
+
+        Ok(Self {
+            token: SecretString::from(token),
+            ..session
+        })
+    }
+
+    /// Returns the URL of the API.
+    ///
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the token the client is authorized with.
+    ///
+    pub fn token(&self) -> &SecretString {
+        &self.token
+    }
+
+    /// Lists the servers of the user.
+    ///
+    pub async fn list_servers(&self) -> Result<Vec<ApiServer>> {
+        let response = self.get("/servers");
+
+        Ok(send::<Response<Vec<ApiServer>>>(response).await?.result)
+    }
+
+    /// Returns a server of the user.
+    ///
+    pub async fn get_server(&self, server_id: Uuid) -> Result<ApiServer> {
+        let response = self.get(&format!("/servers/{server_id}"));
+
+        Ok(send::<Response<ApiServerDetail>>(response)
+            .await?
+            .result
+            .server)
+    }
+
+    /// Returns the provisioning steps of a server of the user.
+    ///
+    pub async fn get_provisioning(&self, server_id: Uuid) -> Result<Vec<ApiProvisioningStep>> {
+        let response = self.get(&format!("/servers/{server_id}/provisioning"));
+
+        Ok(send::<Response<Vec<ApiProvisioningStep>>>(response)
+            .await?
+            .result)
+    }
+
+    /// Orders a server, set up in the background.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload`: Order of the server, like the `POST /servers` body.
+    ///
+    /// # Returns
+    ///
+    /// Server ordered before with the same external ID, `None` if the order
+    /// was placed.
+    ///
+    pub async fn create_server(&self, payload: &Value) -> Result<Option<ApiServer>> {
+        let response = self.post("/servers").json(payload).send().await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json::<Response<ApiServer>>().await?.result)),
+            status if status.is_success() => Ok(None),
+            _ => Err(api_error(response).await),
+        }
+    }
+
+    /// Starts an action on a server, done in the background.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_id`: ID of the server.
+    /// * `action`: Name of the action, like `start`.
+    ///
+    pub async fn run_action(&self, server_id: Uuid, action: &str) -> Result<()> {
+        let response = self
+            .post(&format!("/servers/{server_id}/actions"))
+            .json(&json!({ "action": action }));
+
+        accept(response).await
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.http
+            .get(format!("{}{path}", self.url))
+            .bearer_auth(self.token.expose_secret())
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.http
+            .post(format!("{}{path}", self.url))
+            .bearer_auth(self.token.expose_secret())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Sends the request and decodes the body of a successful response.
+///
+async fn send<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(api_error(response).await);
+    }
+
+    Ok(response.json::<T>().await?)
+}
+
+/// Sends the request, which the API answers without a body.
+///
+async fn accept(request: RequestBuilder) -> Result<()> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(api_error(response).await);
+    }
+
+    Ok(())
+}
+
+/// Reads the message of the error envelope of a failed response.
+///
+async fn api_error(response: reqwest::Response) -> Error {
+    let status = response.status();
+    let message = response
+        .json::<Value>()
+        .await
+        .ok()
+        .and_then(|error| error["message"].as_str().map(str::to_owned));
+
+    match (status, message) {
+        (StatusCode::UNAUTHORIZED, None) => {
+            Error::Any("Not authorized, run `dashboard-cli login` again".to_owned())
+        }
+        (status, Some(message)) => Error::Any(format!("{status}: {message}")),
+        (status, None) => Error::Any(format!("{status}")),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn client_should_send_token_and_report_api_errors() {
+        // Arrange
+        let api = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/login"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "result": { "token": "jwt" } })),
+            )
+            .mount(&api)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/me/api-tokens"))
+            .and(header("authorization", "Bearer jwt"))
+            .and(body_json(json!({ "name": "dashboard-cli" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "result": {
+                "id": Uuid::new_v4(),
+                "name": "dashboard-cli",
+                "secret": "dash_token",
+                "created_at": "2026-10-16T12:00:00Z",
+                "last_used_at": null,
+            } })))
+            .mount(&api)
+            .await;
+        let server_id = Uuid::new_v4();
+        Mock::given(method("POST"))
+            .and(path(format!("/servers/{server_id}/actions")))
+            .and(header("authorization", "Bearer dash_token"))
+            .and(body_json(json!({ "action": "start" })))
+            .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+                "code": "invalid_transition",
+                "message": "Can't start a running server",
+            })))
+            .mount(&api)
+            .await;
+
+        // Act
+        let client = ApiClient::login(&api.uri(), "john@example.com", "password")
+            .await
+            .unwrap();
+        let error = client.run_action(server_id, "start").await.unwrap_err();
+
+        // Assert
+        assert_eq!(client.token().expose_secret(), "dash_token");
+        assert_eq!(
+            error.to_string(),
+            "Error: 409 Conflict: Can't start a running server"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Runs the subcommands of the client.
+
+use crate::cli::{
+    Cli, Command, CreateArgs, LoginArgs, ServerArgs, ServersCommand, TasksCommand, WatchArgs,
+};
+use crate::client::ApiClient;
+use crate::credentials::{self, Credentials};
+use dashboard_common::api::{ApiProvisioningStep, ApiServer, ProvisioningStepStatus, ServerStatus};
+use dashboard_common::prelude::{Error, Result};
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+/// Runs the parsed subcommand.
+///
+/// # Arguments
+///
+/// * `cli`: Parsed command line, with the global flags.
+///
+pub async fn execute(cli: Cli) -> Result<()> {
+    let path = credentials::path(cli.credentials)?;
+    match cli.command {
+        Command::Login(args) => login(args, &path).await,
+        Command::Servers(command) => {
+            let client = connect(&path)?;
+            match command {
+                ServersCommand::List => list(&client, cli.json).await,
+                ServersCommand::Create(args) => create(&client, args, cli.json).await,
+                ServersCommand::Start(ServerArgs { server_id }) => {
+                    client.run_action(server_id, "start").await?;
+                    println!("Server {server_id} is starting.");
+                    Ok(())
+                }
+                ServersCommand::Stop(ServerArgs { server_id }) => {
+                    client.run_action(server_id, "stop").await?;
+                    println!("Server {server_id} is stopping.");
+                    Ok(())
+                }
+            }
+        }
+        Command::Tasks(TasksCommand::Watch(args)) => watch(&connect(&path)?, args, cli.json).await,
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Logs in, asking for the password if it wasn't passed, and stores the
+/// created API token.
+///
+async fn login(args: LoginArgs, path: &Path) -> Result<()> {
+    let password = match args.password {
+        Some(password) => password,
+        None => rpassword::prompt_password("Password: ")?,
+    };
+    let client = ApiClient::login(&args.url, &args.email, &password).await?;
+
+    Credentials {
+        url: client.url().to_owned(),
+        token: client.token().clone(),
+    }
+    .save(path)?;
+    println!(
+        "Logged in as {}, token stored in {}.",
+        args.email,
+        path.display()
+    );
+
+    Ok(())
+}
+
+/// Creates a client authorized with the stored token.
+///
+fn connect(path: &Path) -> Result<ApiClient> {
+    let credentials = Credentials::load(path)?;
+
+    Ok(ApiClient::new(&credentials.url, credentials.token))
+}
+
+/// Prints the servers as a table, or as JSON.
+///
+async fn list(client: &ApiClient, json: bool) -> Result<()> {
+    let servers = client.list_servers().await?;
+    if json {
+        return print_json(&servers);
+    }
+
+    println!(
+        "{:<36}  {:<12}  {:<15}  {:<6}  TAGS",
+        "ID", "STATUS", "IP", "VM"
+    );
+    for ApiServer {
+        server_id,
+        status,
+        ip_address,
+        vm_id,
+        tags,
+        ..
+    } in servers
+    {
+        let vm_id = vm_id.map(|id| id.to_string()).unwrap_or_default();
+        let status = status.to_string();
+        println!(
+            "{server_id:<36}  {status:<12}  {ip_address:<15}  {vm_id:<6}  {}",
+            tags.join(",")
+        );
+    }
+
+    Ok(())
+}
+
+/// Orders a server, set up in the background.
+///
+async fn create(client: &ApiClient, args: CreateArgs, json: bool) -> Result<()> {
+    let payload = json!({
+        "product_id": args.product,
+        "host_name": args.host_name,
+        "cpu_cores": args.cpu_cores,
+        "ram_gb": args.ram_gb,
+        "os": args.os,
+        "datacenter": args.datacenter,
+        "app": args.app,
+        "external_id": args.external_id,
+        "tags": args.tags,
+    });
+    match client.create_server(&payload).await? {
+        Some(server) if json => print_json(&server)?,
+        Some(server) => println!(
+            "Server {} was already ordered with this external ID.",
+            server.server_id
+        ),
+        None => println!(
+            "Server {} ordered, follow its setup with `dashboard-cli tasks watch`.",
+            args.host_name
+        ),
+    }
+
+    Ok(())
+}
+
+/// Polls the server and its provisioning steps, printing every change, until
+/// the server settles.
+///
+/// # Errors
+///
+/// `Error::Any` if the server or one of its steps failed.
+///
+async fn watch(client: &ApiClient, args: WatchArgs, json: bool) -> Result<()> {
+    let mut last_status = None;
+    let mut last_steps: Vec<ApiProvisioningStep> = Vec::new();
+
+    loop {
+        let server = client.get_server(args.server_id).await?;
+        let steps = client.get_provisioning(args.server_id).await?;
+
+        if last_status != Some(server.status) {
+            match json {
+                true => print_json(&json!({ "status": server.status }))?,
+                false => println!("Server: {}", server.status),
+            }
+            last_status = Some(server.status);
+        }
+        for step in &steps {
+            let changed = last_steps
+                .iter()
+                .find(|last| last.step == step.step)
+                .is_none_or(|last| last.status != step.status);
+            if !changed {
+                continue;
+            }
+            match (json, &step.error) {
+                (true, _) => print_json(step)?,
+                (false, Some(error)) => println!("  {}: {} ({error})", step.step, step.status),
+                (false, None) => println!("  {}: {}", step.step, step.status),
+            }
+        }
+        last_steps = steps;
+
+        let busy = last_steps.iter().any(|step| {
+            matches!(
+                step.status,
+                ProvisioningStepStatus::Pending | ProvisioningStepStatus::Running
+            )
+        });
+        let failed = last_steps
+            .iter()
+            .any(|step| step.status == ProvisioningStepStatus::Failed);
+        match server.status {
+            ServerStatus::Failed => {
+                return Err(Error::Any(format!("Server {} failed", args.server_id)));
+            }
+            _ if failed => {
+                return Err(Error::Any(format!(
+                    "Provisioning of server {} failed",
+                    args.server_id
+                )));
+            }
+            ServerStatus::Running | ServerStatus::Stopped if !busy => return Ok(()),
+            _ => tokio::time::sleep(Duration::from_secs(args.interval)).await,
+        }
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let line = serde_json::to_string(value)
+        .map_err(|error| Error::Any(format!("Can't encode response: {error}")))?;
+    println!("{line}");
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use dashboard_common::prelude::{Error, Result};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize, Serializer};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// API token stored by `login`, with the URL of the API it was issued by.
+///
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Credentials {
+    pub url: String,
+    #[serde(serialize_with = "expose")]
+    pub token: SecretString,
+}
+
+impl Credentials {
+    /// Reads the stored credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: File of the credentials.
+    ///
+    /// # Returns
+    ///
+    /// Stored credentials, an error if nobody logged in yet.
+    ///
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::Any(
+                    "Not logged in, run `dashboard-cli login` first".to_owned(),
+                ));
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        serde_json::from_str(&content)
+            .map_err(|error| Error::Any(format!("Invalid credentials file: {error}")))
+    }
+
+    /// Stores the credentials, readable by the current user only. A new file
+    /// is created with these permissions, so the token is never exposed, and
+    /// an existing one is restricted before it is overwritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: File of the credentials, created with its directory.
+    ///
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|error| Error::Any(format!("Can't encode credentials: {error}")))?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            if path.exists() {
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        options.open(path)?.write_all(content.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Returns the file of the credentials, `~/.config/dashboard/credentials.json`
+/// unless configured.
+///
+/// # Arguments
+///
+/// * `configured`: File passed on the command line, if any.
+///
+pub fn path(configured: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(path) = configured {
+        return Ok(path);
+    }
+    let home = std::env::var("HOME")?;
+
+    Ok(PathBuf::from(home).join(".config/dashboard/credentials.json"))
+}
+
+// -----------------------------------------------------------------------------
+
+fn expose<S: Serializer>(
+    token: &SecretString,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(token.expose_secret())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credentials_should_be_stored_and_loaded() {
+        // Arrange
+        let dir = std::env::temp_dir().join(format!("dashboard-cli-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("credentials.json");
+        let credentials = Credentials {
+            url: "https://api.example.com".to_owned(),
+            token: SecretString::from("token"),
+        };
+
+        // Act
+        let missing = Credentials::load(&path);
+        credentials.save(&path).unwrap();
+        let loaded = Credentials::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Assert
+        assert!(missing.is_err());
+        assert_eq!(loaded.url, "https://api.example.com");
+        assert_eq!(loaded.token.expose_secret(), "token");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn credentials_should_be_readable_by_owner_only() {
+        // Arrange
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("dashboard-cli-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("credentials.json");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "{}").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let credentials = Credentials {
+            url: "https://api.example.com".to_owned(),
+            token: SecretString::from("token"),
+        };
+
+        // Act
+        credentials.save(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Assert
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,21 @@
+//! Command-line client of the Dashboard API for operators and scripts: logs
+//! in once, stores the issued token and manages the servers of the user with
+//! it.
+
+mod cli;
+mod client;
+mod commands;
+mod credentials;
+
+use clap::Parser;
+use cli::Cli;
+use dashboard_common::prelude::Result;
+
+/// The main entry point for the client.
+///
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    commands::execute(cli).await
+}
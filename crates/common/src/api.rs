@@ -0,0 +1,327 @@
+//! Types of the public API shared by the server and its clients.
+
+use crate::prelude::{Error, Result};
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// API response with JWT inside.
+///
+pub type TokenResponse = Response<TokenPayload>;
+
+/// Generic API response.
+///
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Response<T> {
+    pub result: T,
+}
+
+impl<T> Response<T> {
+    /// Creates a new instance of the API response.
+    ///
+    pub fn new(result: T) -> Self {
+        Self { result }
+    }
+}
+
+/// Payload for successful registration or login, containing `JWT`.
+///
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TokenPayload {
+    pub token: String,
+}
+
+impl From<String> for TokenPayload {
+    fn from(token: String) -> Self {
+        Self { token }
+    }
+}
+
+/// Represents a long-lived token a user authorizes scripts with.
+///
+/// # Fields
+///
+/// * `secret`: The token itself, only returned when it is created.
+/// * `last_used_at`: Time of the last request authorized with the token.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Represents the status from the `servers` table.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerStatus {
+    // Stable statuses.
+    Running,
+    Stopped,
+    Failed,
+    // Lifecycle.
+    SettingUp,
+    Deleting,
+    // Progress statuses.
+    Starting,
+    Stopping,
+    Rebooting,
+    ShuttingDown,
+    Restoring,
+    Resizing,
+    Configuring,
+}
+
+/// Parses a status case-insensitively. The `servers` table holds the
+/// `Display` form, e.g. `SettingUp`, which lowercases to `settingup`, while the
+/// API uses the snake case form, e.g. `setting_up`, so both are accepted.
+///
+impl From<&str> for ServerStatus {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "running" => ServerStatus::Running,
+            "stopped" => ServerStatus::Stopped,
+            "setting_up" | "settingup" => ServerStatus::SettingUp,
+            "deleting" => ServerStatus::Deleting,
+            "starting" => ServerStatus::Starting,
+            "stopping" => ServerStatus::Stopping,
+            "rebooting" => ServerStatus::Rebooting,
+            "shutting_down" | "shuttingdown" => ServerStatus::ShuttingDown,
+            "restoring" => ServerStatus::Restoring,
+            "resizing" => ServerStatus::Resizing,
+            "configuring" => ServerStatus::Configuring,
+            _ => ServerStatus::Failed,
+        }
+    }
+}
+
+impl From<String> for ServerStatus {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl ServerStatus {
+    /// Returns the name of the operation in flight on a server in a transient
+    /// status, `None` for the stable statuses.
+    ///
+    pub fn operation(self) -> Option<&'static str> {
+        match self {
+            ServerStatus::Running | ServerStatus::Stopped | ServerStatus::Failed => None,
+            ServerStatus::SettingUp => Some("setup"),
+            ServerStatus::Deleting => Some("delete"),
+            ServerStatus::Starting => Some("start"),
+            ServerStatus::Stopping => Some("stop"),
+            ServerStatus::Rebooting => Some("reboot"),
+            ServerStatus::ShuttingDown => Some("shutdown"),
+            ServerStatus::Restoring => Some("restore"),
+            ServerStatus::Resizing => Some("resize"),
+            ServerStatus::Configuring => Some("configure"),
+        }
+    }
+}
+
+/// Represents the possible actions that can be performed on a server.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerAction {
+    #[display("start")]
+    Start,
+    #[display("stop")]
+    Stop,
+    // Shutting VM down, and starting it again.
+    #[display("reboot")]
+    Reboot,
+    // This is similar to pressing the power button on a physical machine.
+    #[display("shutdown")]
+    Shutdown,
+}
+
+/// Combined struct for the public API response.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiServer {
+    pub service_id: Uuid,
+    pub server_id: Uuid,
+    pub vm_id: Option<i32>,
+    pub node_name: Option<String>,
+    pub ip_address: String,
+    pub status: ServerStatus,
+    pub net_rate_mbps: Option<i32>,
+    pub additional_ips: Vec<String>,
+    /// Labels of the owner, sorted.
+    pub tags: Vec<String>,
+    /// Free-text notes of the owner.
+    pub notes: Option<String>,
+    /// ID the server is known by in the owner's tooling, such as the address
+    /// of a Terraform resource.
+    pub external_id: Option<String>,
+}
+
+/// Details of a server, completed with what the QEMU guest agent reports from
+/// inside the VM.
+///
+/// # Fields
+///
+/// * `guest`: System and addresses detected in the guest, `None` if the
+///   server isn't running or its agent doesn't respond.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiServerDetail {
+    #[serde(flatten)]
+    pub server: ApiServer,
+    pub guest: Option<ApiGuestInfo>,
+}
+
+/// System and network of a server as seen from inside the guest.
+///
+/// # Fields
+///
+/// * `os_name`: Full name of the operating system, e.g. `Ubuntu 24.04.1 LTS`.
+/// * `kernel_release`: Release of the running kernel.
+/// * `ip_addresses`: Addresses configured in the guest, without the loopback
+///   and link-local ones.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiGuestInfo {
+    pub os_name: Option<String>,
+    pub kernel_release: Option<String>,
+    pub ip_addresses: Vec<String>,
+}
+
+/// Named step of a server provisioning. Steps run in the order of `ALL`, each
+/// one only after the previous one has completed.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvisioningStep {
+    /// Clone the VM from the template of the chosen OS or application.
+    CloneVm,
+    /// Apply IP, CPU, RAM and network rate to the cloned VM.
+    ConfigureVm,
+    /// Boot the VM and run the post-install script of its application, if
+    /// the template has one.
+    InstallApp,
+    /// Mark the server and its service as ready.
+    Activate,
+}
+
+impl ProvisioningStep {
+    /// Every step of the provisioning, in execution order.
+    pub const ALL: [ProvisioningStep; 4] = [
+        ProvisioningStep::CloneVm,
+        ProvisioningStep::ConfigureVm,
+        ProvisioningStep::InstallApp,
+        ProvisioningStep::Activate,
+    ];
+}
+
+impl FromStr for ProvisioningStep {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "CloneVm" => Ok(ProvisioningStep::CloneVm),
+            "ConfigureVm" => Ok(ProvisioningStep::ConfigureVm),
+            "InstallApp" => Ok(ProvisioningStep::InstallApp),
+            "Activate" => Ok(ProvisioningStep::Activate),
+            _ => Err(Error::Validation(format!(
+                "Unknown provisioning step {value}"
+            ))),
+        }
+    }
+}
+
+/// Status of a single provisioning step.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvisioningStepStatus {
+    /// Step waits for the previous steps or for a retry.
+    Pending,
+    Running,
+    Completed,
+    /// Step failed, the provisioning stops until it is retried.
+    Failed,
+}
+
+impl FromStr for ProvisioningStepStatus {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "Pending" => Ok(ProvisioningStepStatus::Pending),
+            "Running" => Ok(ProvisioningStepStatus::Running),
+            "Completed" => Ok(ProvisioningStepStatus::Completed),
+            "Failed" => Ok(ProvisioningStepStatus::Failed),
+            _ => Err(Error::Validation(format!(
+                "Unknown provisioning step status {value}"
+            ))),
+        }
+    }
+}
+
+/// Represents the progress of a provisioning step that is safe to expose to
+/// the public API.
+///
+/// # Fields
+///
+/// * `attempts`: Number of times the step was started.
+/// * `error`: Reason of the last failure of the step.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiProvisioningStep {
+    pub step: ProvisioningStep,
+    pub status: ProvisioningStepStatus,
+    pub attempts: i32,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_status_should_parse_its_stored_and_api_forms() {
+        // Arrange
+        let statuses = [
+            ServerStatus::Running,
+            ServerStatus::Stopped,
+            ServerStatus::Failed,
+            ServerStatus::SettingUp,
+            ServerStatus::Deleting,
+            ServerStatus::Starting,
+            ServerStatus::Stopping,
+            ServerStatus::Rebooting,
+            ServerStatus::ShuttingDown,
+            ServerStatus::Restoring,
+            ServerStatus::Resizing,
+            ServerStatus::Configuring,
+        ];
+
+        for status in statuses {
+            // Act
+            let stored = ServerStatus::from(status.to_string());
+            let api = serde_json::to_value(status).unwrap();
+            let api = ServerStatus::from(api.as_str().unwrap());
+
+            // Assert
+            assert_eq!(stored, status);
+            assert_eq!(api, status);
+        }
+    }
+}
@@ -1,7 +1,13 @@
+use crate::api::{ServerAction, ServerStatus};
+use axum::Json;
+use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use derive_more::Display;
+use serde::Serialize;
+use serde_json::json;
 use std::num::ParseIntError;
 use thiserror::Error;
+use utoipa::ToSchema;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -23,6 +29,26 @@ pub enum Error {
     Auth(AuthError),
     #[error("Proxmox API error: {0} failed: status {1}, body: {2}")]
     Proxmox(ProxmoxError, reqwest::StatusCode, String),
+    #[error("Payment provider error: status {0}, body: {1}")]
+    Payment(reqwest::StatusCode, String),
+    #[error("Secret provider error: {0}")]
+    Secret(String),
+    #[error("Validation error: {0}")]
+    Validation(String),
+    #[error("Insufficient capacity: {0}")]
+    Capacity(String),
+    #[error("Quota exceeded: {0}")]
+    Quota(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Cannot {action} a server that is {status}, allowed actions: [{}]", join(.allowed))]
+    Transition {
+        status: ServerStatus,
+        action: ServerAction,
+        allowed: Vec<ServerAction>,
+    },
     #[error("Header convert error: {0}")]
     Header(#[from] axum::http::header::InvalidHeaderValue),
 
@@ -48,36 +74,199 @@ pub enum Error {
     ParseAddr(#[from] std::net::AddrParseError),
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
+    #[error("Redis error: {0}")]
+    Redis(#[from] redis::RedisError),
 }
 
 impl IntoResponse for Error {
+    /// Responds with the JSON error envelope. The envelope is also kept in the
+    /// response extensions, so a middleware can complete it with the ID of the
+    /// request.
+    ///
     fn into_response(self) -> Response {
-        use axum::http::StatusCode;
+        let (status, error) = self.into_api_error();
+        let mut response = (status, Json(&error)).into_response();
+        response.extensions_mut().insert(error);
 
-        match self {
+        response
+    }
+}
+
+impl Error {
+    /// Converts the error into the HTTP status code and the message that is
+    /// safe to show to the client.
+    ///
+    pub fn into_status_message(self) -> (StatusCode, String) {
+        let (status, error) = self.into_api_error();
+
+        (status, error.message)
+    }
+
+    /// Converts the error into the HTTP status code and the error envelope
+    /// that is safe to show to the client. Internal errors are logged, as the
+    /// client only gets an opaque message for them.
+    ///
+    pub fn into_api_error(self) -> (StatusCode, ApiError) {
+        let (status, code, message, details, message_key) = match self {
             Error::Auth(AuthError::Token) => (
                 StatusCode::UNAUTHORIZED,
+                "unauthorized",
                 "Authorization token is missing or invalid!".to_owned(),
+                None,
+                Some("error.unauthorized"),
             ),
             Error::Auth(AuthError::Login) | Error::Hash(_) => (
                 StatusCode::UNAUTHORIZED,
+                "invalid_credentials",
                 "Incorrect email or password!".to_owned(),
+                None,
+                Some("error.invalid_credentials"),
+            ),
+            Error::Auth(AuthError::Forbidden) => (
+                StatusCode::FORBIDDEN,
+                "forbidden",
+                "Insufficient permissions!".to_owned(),
+                None,
+                Some("error.forbidden"),
+            ),
+            Error::Auth(AuthError::Captcha) => (
+                StatusCode::UNAUTHORIZED,
+                "captcha_required",
+                "CAPTCHA verification required!".to_owned(),
+                None,
+                Some("error.captcha_required"),
+            ),
+            Error::NotFound(message) => (
+                StatusCode::NOT_FOUND,
+                "not_found",
+                message,
+                None,
+                Some("error.not_found_resource"),
+            ),
+            Error::Database(sqlx::Error::RowNotFound) => (
+                StatusCode::NOT_FOUND,
+                "not_found",
+                "Resource not found!".to_owned(),
+                None,
+                Some("error.not_found"),
+            ),
+            Error::Validation(message) => (
+                StatusCode::BAD_REQUEST,
+                "validation_error",
+                message,
+                None,
+                Some("error.validation_error"),
+            ),
+            Error::Capacity(message) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "insufficient_capacity",
+                message,
+                None,
+                Some("error.insufficient_capacity"),
             ),
-            _ => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Internal server error!".to_owned(),
+            Error::Timeout(_) => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "timeout",
+                "Request timed out, try again later!".to_owned(),
+                None,
+                Some("error.timeout"),
             ),
-        }
-        .into_response()
+            Error::Quota(message) => (
+                StatusCode::FORBIDDEN,
+                "quota_exceeded",
+                message,
+                None,
+                Some("error.quota_exceeded"),
+            ),
+            Error::Conflict(message) => (
+                StatusCode::CONFLICT,
+                "conflict",
+                message,
+                None,
+                Some("error.conflict"),
+            ),
+            Error::Forbidden(message) => (StatusCode::FORBIDDEN, "forbidden", message, None, None),
+            Error::Transition {
+                ref status,
+                ref action,
+                ref allowed,
+            } => {
+                let details = json!({ "status": status, "action": action, "allowed": allowed });
+                (
+                    StatusCode::CONFLICT,
+                    "invalid_transition",
+                    self.to_string(),
+                    Some(details),
+                    Some("error.invalid_transition"),
+                )
+            }
+            Error::Proxmox(ref operation, status, _) => {
+                tracing::error!(error = %self, "Proxmox request failed!");
+                let details =
+                    json!({ "operation": operation.to_string(), "status": status.as_u16() });
+                (
+                    StatusCode::BAD_GATEWAY,
+                    "proxmox_error",
+                    format!("Proxmox {operation} request failed!"),
+                    Some(details),
+                    Some("error.proxmox_error"),
+                )
+            }
+            _ => {
+                tracing::error!(error = %self, "Internal server error!");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal_error",
+                    "Internal server error!".to_owned(),
+                    None,
+                    Some("error.internal_error"),
+                )
+            }
+        };
+
+        let error = ApiError {
+            code,
+            message,
+            details,
+            request_id: None,
+            message_key,
+        };
+
+        (status, error)
     }
 }
 
+/// JSON envelope of the API error responses.
+///
+/// # Fields
+///
+/// * `code`: Stable machine-readable error code.
+/// * `message`: Human-readable message, safe to show to the client.
+/// * `details`: Structured details of the error, if any.
+/// * `request_id`: ID of the failed request, to correlate it with the logs.
+/// * `message_key`: Catalog key of the message, `None` if the message can't be
+///   translated. The placeholders of the translation are filled from the
+///   details, and `{reason}` with the untranslated message, for the messages
+///   specific to the failure.
+///
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiError {
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    #[serde(skip)]
+    pub message_key: Option<&'static str>,
+}
+
 /// Represents authentication-related errors.
 ///
 #[derive(Debug, Display)]
 pub enum AuthError {
     Token,
     Login,
+    Forbidden,
+    Captcha,
 }
 
 /// Represents errors related to Proxmox API operations.
@@ -91,4 +280,21 @@ pub enum ProxmoxError {
     Create,
     Delete,
     Status,
+    Firewall,
+    Backup,
+    Restore,
+    Agent,
+    Disk,
+    Iso,
+    Traffic,
+}
+
+/// Lists the actions of a transition error, separated by commas.
+///
+fn join(actions: &[ServerAction]) -> String {
+    actions
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
@@ -1,6 +1,7 @@
+pub mod api;
 pub mod error;
 pub mod telemetry;
 
 pub mod prelude {
-    pub use crate::error::{AuthError, Error, ProxmoxError, Result};
+    pub use crate::error::{ApiError, AuthError, Error, ProxmoxError, Result};
 }
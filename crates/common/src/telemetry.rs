@@ -1,9 +1,22 @@
-﻿use crate::error::Result;
+﻿use crate::error::{Error, Result};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
 use tracing::subscriber::set_global_default;
 use tracing::{Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_log::LogTracer;
 use tracing_subscriber::fmt::format::FmtSpan;
-use tracing_subscriber::{EnvFilter, fmt::MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{EnvFilter, Layer, Registry, fmt, fmt::MakeWriter, reload};
+
+/// Replaces the log filter of the latest composed subscriber.
+type FilterReload = Box<dyn Fn(EnvFilter) -> Result<()> + Send + Sync>;
+
+/// Reload of the log filter, set once a subscriber is composed.
+static FILTER_RELOAD: Mutex<Option<FilterReload>> = Mutex::new(None);
 
 /// Composes and returns a tracing subscriber for application logging.
 ///
@@ -33,16 +46,103 @@ where
     #[cfg(not(debug_assertions))]
     let subscriber_builder = tracing_subscriber::fmt().json().with_current_span(true);
 
-    subscriber_builder
+    let subscriber_builder = subscriber_builder
         .with_env_filter(env_filter)
-        .with_max_level(max_level)
         .with_file(true)
         .with_line_number(true)
         .with_thread_ids(true)
         .with_target(true)
         .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
         .with_writer(writer)
-        .finish()
+        .with_filter_reloading();
+
+    let handle = subscriber_builder.reload_handle();
+    let reload: FilterReload = Box::new(move |filter| {
+        handle
+            .reload(filter)
+            .map_err(|error| Error::Any(error.to_string()))
+    });
+    *FILTER_RELOAD.lock().unwrap_or_else(PoisonError::into_inner) = Some(reload);
+
+    subscriber_builder.finish()
+}
+
+/// Replaces the log filter of the subscriber at runtime.
+///
+/// # Arguments
+///
+/// * `directives`: Filter directives in the `RUST_LOG` format, like
+///   `info,sqlx=warn`.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the filter is replaced, or if no subscriber has been
+/// composed.
+///
+pub fn reload_filter(directives: &str) -> Result<()> {
+    let filter = parse_filter(directives)?;
+
+    match &*FILTER_RELOAD.lock().unwrap_or_else(PoisonError::into_inner) {
+        Some(reload) => reload(filter),
+        None => Ok(()),
+    }
+}
+
+/// Composes a tracing subscriber from the log settings, writing to the
+/// standard output, to a rotated file, or to both.
+///
+/// The `RUST_LOG` environment variable takes precedence over the configured
+/// filter.
+///
+/// # Arguments
+///
+/// * `settings`: Filter, format and destinations of the logs.
+///
+/// # Returns
+///
+/// `Subscriber` instance and the guard of the file writer, which flushes the
+/// remaining logs when dropped, so it must live until the application exits.
+///
+pub fn compose_subscriber(
+    settings: &LogSettings,
+) -> Result<(impl Subscriber + Sync + Send, Option<WorkerGuard>)> {
+    let env_filter = match EnvFilter::try_from_default_env() {
+        Ok(env_filter) => env_filter,
+        Err(_) => parse_filter(&settings.filter)?,
+    };
+    let (env_filter, handle) = reload::Layer::new(env_filter);
+    let reload: FilterReload = Box::new(move |filter| {
+        handle
+            .reload(filter)
+            .map_err(|error| Error::Any(error.to_string()))
+    });
+    *FILTER_RELOAD.lock().unwrap_or_else(PoisonError::into_inner) = Some(reload);
+
+    let mut layers = Vec::new();
+    if settings.stdout {
+        layers.push(format_layer(settings.format, std::io::stdout, true));
+    }
+
+    let mut guard = None;
+    if let Some(file) = &settings.file {
+        let mut appender = RollingFileAppender::builder()
+            .rotation(file.rotation.into())
+            .filename_prefix(&file.prefix);
+        if let Some(max_files) = file.max_files {
+            appender = appender.max_log_files(max_files);
+        }
+        let appender = appender
+            .build(&file.directory)
+            .map_err(|error| Error::Any(format!("Failed to open log file: {error}")))?;
+
+        let (writer, file_guard) = tracing_appender::non_blocking(appender);
+        layers.push(format_layer(file.format, writer, false));
+        guard = Some(file_guard);
+    }
+
+    let subscriber = Registry::default().with(env_filter).with(layers);
+
+    Ok((subscriber, guard))
 }
 
 /// Register a subscriber as global default to process span data.
@@ -65,3 +165,132 @@ where
     set_global_default(subscriber)?;
     Ok(())
 }
+
+// -----------------------------------------------------------------------------
+
+/// Settings of the application logs.
+///
+/// `filter` takes directives in the `RUST_LOG` format, so the level may be set
+/// per target, like `info,dashboard_server=debug,sqlx=warn`. The logs are
+/// written to the standard output in `format`, and to `file` when set.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    pub filter: String,
+    pub format: LogFormat,
+    pub stdout: bool,
+    pub file: Option<LogFileSettings>,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            filter: "info".to_owned(),
+            format: LogFormat::default(),
+            stdout: true,
+            file: None,
+        }
+    }
+}
+
+/// Format of the logs. Compact logs are the default in debug builds, JSON logs
+/// in release builds.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Compact,
+    Pretty,
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        match cfg!(debug_assertions) {
+            true => LogFormat::Compact,
+            false => LogFormat::Json,
+        }
+    }
+}
+
+/// Settings of the log file.
+///
+/// A new file named `prefix` with the date suffix is started every `rotation`
+/// period in `directory`, and the oldest files beyond `max_files` are removed.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LogFileSettings {
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub rotation: LogRotation,
+    pub max_files: Option<usize>,
+    pub format: LogFormat,
+}
+
+impl Default for LogFileSettings {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("logs"),
+            prefix: "dashboard.log".to_owned(),
+            rotation: LogRotation::Daily,
+            max_files: Some(14),
+            format: LogFormat::Json,
+        }
+    }
+}
+
+/// How often the log file is rotated.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Parses the filter directives, reporting invalid ones as a validation error.
+///
+fn parse_filter(directives: &str) -> Result<EnvFilter> {
+    EnvFilter::try_new(directives)
+        .map_err(|error| Error::Validation(format!("Invalid log filter {directives}: {error}")))
+}
+
+/// Creates the formatting layer writing the logs in the format to the writer.
+///
+fn format_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + 'static,
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = fmt::layer()
+        .with_file(true)
+        .with_line_number(true)
+        .with_thread_ids(true)
+        .with_target(true)
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+        .with_ansi(ansi)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer.json().with_current_span(true).boxed(),
+    }
+}
@@ -0,0 +1,211 @@
+//! Expands `#[derive(PgBulkInsert)]`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, LitStr, Path, Type};
+
+/// Name of the attribute configuring the derive.
+const ATTRIBUTE: &str = "bulk_insert";
+
+/// Path of the crate defining `PgBulkInsert`, unless the struct names another.
+const DEFAULT_CRATE: &str = "::migration_utility";
+
+/// Table, conflict key and crate path read from the struct attribute.
+///
+struct Table {
+    name: LitStr,
+    conflict_key: LitStr,
+    krate: Path,
+}
+
+/// Column of the table, inserted from a field.
+///
+struct Column {
+    field: Ident,
+    name: LitStr,
+    ty: Type,
+    updated: bool,
+}
+
+/// Generates the implementation of `PgBulkInsert` for a struct, together with
+/// a `sqlx::query!` inserting a row, which checks the columns and their types
+/// against the database schema at compile time.
+///
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Table {
+        name: table,
+        conflict_key,
+        krate,
+    } = table_attributes(&input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "PgBulkInsert can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "PgBulkInsert needs a struct with named fields",
+        ));
+    };
+
+    let mut columns = Vec::<Column>::with_capacity(fields.named.len());
+    for field in &fields.named {
+        let ident = field.ident.clone().expect("named fields have an ident");
+        let (name, insert_only) = column_attributes(&field.attrs)?;
+        let name = name.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+        if columns
+            .iter()
+            .any(|column| column.name.value() == name.value())
+        {
+            return Err(syn::Error::new_spanned(
+                &name,
+                format!("column `{}` is inserted twice", name.value()),
+            ));
+        }
+        columns.push(Column {
+            field: ident,
+            name,
+            ty: field.ty.clone(),
+            updated: !insert_only,
+        });
+    }
+    if !columns
+        .iter()
+        .any(|column| column.name.value() == conflict_key.value())
+    {
+        return Err(syn::Error::new_spanned(
+            &conflict_key,
+            format!("conflict key `{}` is not a column", conflict_key.value()),
+        ));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let bulk = quote!(#krate::etl::bulk);
+    let fields = columns
+        .iter()
+        .map(|column| &column.field)
+        .collect::<Vec<_>>();
+    let names = columns.iter().map(|column| &column.name);
+    let types = columns.iter().map(|column| &column.ty).collect::<Vec<_>>();
+    let updated = columns.iter().map(|column| column.updated);
+    let arrays = fields
+        .iter()
+        .map(|field| format_ident!("{field}_array"))
+        .collect::<Vec<_>>();
+    let check = check_statement(&table, &columns);
+
+    Ok(quote! {
+        impl #impl_generics #bulk::PgBulkInsert for #ident #ty_generics #where_clause {
+            const TABLE: &'static str = #table;
+            const CONFLICT_KEY: &'static str = #conflict_key;
+            const COLUMNS: &'static [#bulk::BulkColumn] = &[
+                #(#bulk::BulkColumn {
+                    name: #names,
+                    sql_type: <#types as #bulk::PgColumn>::SQL_TYPE,
+                    updated: #updated,
+                }),*
+            ];
+
+            fn bind_arrays<'q>(rows: Vec<Self>, query: #bulk::BulkQuery<'q>) -> #bulk::BulkQuery<'q> {
+                #(let mut #arrays = Vec::<#types>::with_capacity(rows.len());)*
+                for row in rows {
+                    #(#arrays.push(row.#fields);)*
+                }
+                query #(.bind(#arrays))*
+            }
+
+            fn encode_row(
+                &self,
+                row: &mut #krate::etl::copy::CopyRow<'_>,
+            ) -> ::sqlx::Result<()> {
+                #(row.push(&self.#fields)?;)*
+                Ok(())
+            }
+        }
+
+        const _: () = {
+            #[allow(dead_code)]
+            fn check_schema #impl_generics(row: &#ident #ty_generics) #where_clause {
+                let _ = ::sqlx::query!(#check, #(row.#fields),*);
+            }
+        };
+    })
+}
+
+/// Builds the single row `INSERT` checked by `sqlx::query!`. The database
+/// infers the type of every parameter from its column, so a missing column or
+/// a field of another type doesn't compile.
+///
+fn check_statement(table: &LitStr, columns: &[Column]) -> LitStr {
+    let names = columns
+        .iter()
+        .map(|column| column.name.value())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let values = (1..=columns.len())
+        .map(|index| format!("${index}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    LitStr::new(
+        &format!("INSERT INTO {} ({names}) VALUES ({values})", table.value()),
+        table.span(),
+    )
+}
+
+/// Reads the table, the conflict key and the crate path of the struct.
+///
+fn table_attributes(input: &DeriveInput) -> syn::Result<Table> {
+    let (mut table, mut conflict_key) = (None, None);
+    let mut krate = syn::parse_str::<Path>(DEFAULT_CRATE)?;
+    for attribute in input.attrs.iter().filter(|a| a.path().is_ident(ATTRIBUTE)) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident("conflict_key") {
+                conflict_key = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident("crate") {
+                krate = meta.value()?.parse::<LitStr>()?.parse::<Path>()?;
+            } else {
+                return Err(meta.error("expected `table`, `conflict_key` or `crate`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    match (table, conflict_key) {
+        (Some(name), Some(conflict_key)) => Ok(Table {
+            name,
+            conflict_key,
+            krate,
+        }),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "PgBulkInsert needs #[bulk_insert(table = \"...\", conflict_key = \"...\")]",
+        )),
+    }
+}
+
+/// Reads the column a field is inserted into, if it isn't named after it,
+/// and whether the column is only written when the row is inserted.
+///
+fn column_attributes(attributes: &[Attribute]) -> syn::Result<(Option<LitStr>, bool)> {
+    let (mut column, mut insert_only) = (None, false);
+    for attribute in attributes.iter().filter(|a| a.path().is_ident(ATTRIBUTE)) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("column") {
+                column = Some(meta.value()?.parse::<LitStr>()?);
+            } else if meta.path.is_ident("insert_only") {
+                insert_only = true;
+            } else {
+                return Err(meta.error("expected `column` or `insert_only`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok((column, insert_only))
+}
@@ -0,0 +1,43 @@
+//! Derive macros of the Dashboard crates.
+
+mod bulk_insert;
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+/// Derives `migration_utility::etl::bulk::PgBulkInsert` for a struct with
+/// named fields, inserting every field into the column of the same name.
+///
+/// The struct names its table and the unique column matching the migrated
+/// rows, and a field can name another column. An `insert_only` field keeps
+/// the value of an existing row when the rows are updated:
+///
+/// ```ignore
+/// #[derive(PgBulkInsert)]
+/// #[bulk_insert(table = "products", conflict_key = "whmcs_id")]
+/// pub struct ProductRow {
+///     #[bulk_insert(insert_only)]
+///     pub group_id: Uuid,
+///     pub name: String,
+///     #[bulk_insert(column = "whmcs_id")]
+///     pub id: i32,
+/// }
+/// ```
+///
+/// The SQL type of every column follows from the type of its field, so a
+/// field of a type without a `PgColumn` implementation doesn't compile, and a
+/// conflict key that isn't a column or a column named twice is rejected. The
+/// derive also checks a single row `INSERT` with `sqlx::query!`, so a column
+/// missing from the table or of another type than its field fails to compile,
+/// like any other query of the crate.
+///
+/// The implementation names `::migration_utility`, unless the struct names
+/// the crate defining `PgBulkInsert` with `#[bulk_insert(crate = "...")]`.
+///
+#[proc_macro_derive(PgBulkInsert, attributes(bulk_insert))]
+pub fn derive_pg_bulk_insert(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    bulk_insert::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
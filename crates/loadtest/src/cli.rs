@@ -0,0 +1,31 @@
+use secrecy::SecretString;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "Load Test",
+    version = "0.1.0",
+    about = "Drives concurrent API flows against the server with Proxmox mocked"
+)]
+pub struct Cli {
+    #[arg(
+        short,
+        long,
+        default_value_t = 20,
+        help = "Sets the number of concurrent virtual users"
+    )]
+    pub users: usize,
+    #[arg(
+        short,
+        long,
+        default_value_t = 10,
+        help = "Sets the number of server listings per user"
+    )]
+    pub iterations: usize,
+    #[arg(
+        short,
+        long,
+        help = "PostgreSQL URL, the seeded database is created next to it",
+        env = "DATABASE_URL"
+    )]
+    pub database_url: SecretString,
+}
@@ -0,0 +1,74 @@
+use dashboard_common::prelude::Result;
+use dashboard_testing::{CatalogBuilder, database};
+use sqlx::PgPool;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Database created for a single load test run and dropped afterwards, so the
+/// seeded users and servers never mix with real data.
+///
+/// # Fields
+///
+/// * `admin`: Pool of the database named in the URL, which creates and drops
+///   the scratch database.
+/// * `name`: Name of the scratch database.
+/// * `pool`: Pool of the scratch database.
+///
+pub struct ScratchDatabase {
+    admin: PgPool,
+    name: String,
+    pub pool: PgPool,
+}
+
+impl ScratchDatabase {
+    /// Creates and migrates a new database on the server of the URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: PostgreSQL URL.
+    /// * `connections`: Maximum number of connections to the new database.
+    ///
+    pub async fn create(url: &str, connections: u32) -> Result<Self> {
+        let options = PgConnectOptions::from_str(url)?;
+        let admin = PgPool::connect_with(options.clone()).await?;
+        let name = format!("loadtest_{}", Uuid::new_v4().simple());
+        sqlx::query(&format!(r#"CREATE DATABASE "{name}""#))
+            .execute(&admin)
+            .await?;
+
+        let pool = PgPoolOptions::new()
+            .max_connections(connections)
+            .connect_with(options.database(&name))
+            .await?;
+        database::migrate(&pool).await;
+
+        Ok(Self { admin, name, pool })
+    }
+
+    /// Inserts the catalog with a free IP address for every user.
+    ///
+    /// # Returns
+    ///
+    /// ID of the product.
+    ///
+    pub async fn seed(&self, users: usize) -> Uuid {
+        (0..users)
+            .fold(CatalogBuilder::new(), |catalog, user| {
+                catalog.ip_address(&format!("10.0.{}.{}", user / 250, user % 250 + 1))
+            })
+            .build(&self.pool)
+            .await
+    }
+
+    /// Closes the connections and drops the database.
+    ///
+    pub async fn drop(self) -> Result<()> {
+        self.pool.close().await;
+        sqlx::query(&format!(r#"DROP DATABASE "{}" WITH (FORCE)"#, self.name))
+            .execute(&self.admin)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use dashboard_server::web::types::TokenResponse;
+use dashboard_testing::{ServerBuilder, TestApp, UserBuilder, requests};
+use serde_json::json;
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// API flow driven by the virtual users.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Flow {
+    /// Registration of a new user.
+    Register,
+    /// Login of the registered user.
+    Login,
+    /// Order of a server, set up in the background.
+    CreateServer,
+    /// Listing of the servers of the user.
+    ListServers,
+}
+
+impl Display for Flow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Flow::Register => "register",
+            Flow::Login => "login",
+            Flow::CreateServer => "create-server",
+            Flow::ListServers => "list-servers",
+        };
+        f.pad(name)
+    }
+}
+
+/// Latency of a single request.
+///
+/// # Fields
+///
+/// * `flow`: Flow of the request.
+/// * `elapsed`: Time until the response arrived.
+/// * `success`: Whether the response status was successful.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub flow: Flow,
+    pub elapsed: Duration,
+    pub success: bool,
+}
+
+/// Runs the flows of a single virtual user: registers, logs in, orders a
+/// server and lists the servers repeatedly. Stops early when the user can't
+/// be registered or logged in, as the following flows need the token.
+///
+/// # Arguments
+///
+/// * `app`: Running server.
+/// * `product_id`: Product of the ordered server.
+/// * `user`: Number of the user, making its email and host name unique.
+/// * `iterations`: Number of server listings.
+///
+/// # Returns
+///
+/// Latencies of all sent requests.
+///
+pub async fn run_user(
+    app: &TestApp,
+    product_id: Uuid,
+    user: usize,
+    iterations: usize,
+) -> Vec<Sample> {
+    let mut samples = Vec::with_capacity(iterations + 3);
+    let email = format!("loadtest.{user}@example.com");
+    let builder = UserBuilder::new().email(&email);
+
+    let endpoint = format!("{}/register", &app.url);
+    let response = timed(
+        &mut samples,
+        Flow::Register,
+        requests::post_response(app, &endpoint, "", &builder.payload()),
+    )
+    .await;
+    let Ok(registered) = response.json::<TokenResponse>().await else {
+        return samples;
+    };
+    let endpoint = format!("{}/auth/verify", &app.url);
+    let verify_payload = json!({ "token": app.email_token(&email) });
+    requests::post_response(app, &endpoint, &registered.result.token, &verify_payload).await;
+
+    let endpoint = format!("{}/login", &app.url);
+    let response = timed(
+        &mut samples,
+        Flow::Login,
+        requests::post_response(app, &endpoint, "", &builder.login_payload()),
+    )
+    .await;
+    let Ok(logged_in) = response.json::<TokenResponse>().await else {
+        return samples;
+    };
+    let token = logged_in.result.token;
+
+    let endpoint = format!("{}/servers", &app.url);
+    let payload = ServerBuilder::new(product_id)
+        .host_name(&format!("load-{user}.example.com"))
+        .payload();
+    timed(
+        &mut samples,
+        Flow::CreateServer,
+        requests::post_response(app, &endpoint, &token, &payload),
+    )
+    .await;
+
+    for _ in 0..iterations {
+        timed(
+            &mut samples,
+            Flow::ListServers,
+            requests::get_response(app, &endpoint, &token),
+        )
+        .await;
+    }
+
+    samples
+}
+
+/// Sends the request and records its latency.
+///
+async fn timed(
+    samples: &mut Vec<Sample>,
+    flow: Flow,
+    request: impl Future<Output = reqwest::Response>,
+) -> reqwest::Response {
+    let start = Instant::now();
+    let response = request.await;
+    samples.push(Sample {
+        flow,
+        elapsed: start.elapsed(),
+        success: response.status().is_success(),
+    });
+
+    response
+}
@@ -0,0 +1,52 @@
+//! Load test of the API: runs the server against a freshly seeded database
+//! with Proxmox, payments and mail mocked, and drives concurrent virtual users
+//! through the register, login, create-server and list-servers flows.
+
+mod cli;
+mod database;
+mod flows;
+mod report;
+
+use clap::Parser;
+use cli::Cli;
+use dashboard_common::prelude::{Error, Result};
+use dashboard_testing::TestApp;
+use database::ScratchDatabase;
+use report::LoadReport;
+use secrecy::ExposeSecret;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::task::JoinSet;
+
+/// The main entry point for the load test.
+///
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    let cli = Cli::parse();
+
+    let connections = (cli.users as u32).clamp(5, 100);
+    let database = ScratchDatabase::create(cli.database_url.expose_secret(), connections).await?;
+    let product_id = database.seed(cli.users).await;
+    let app = Arc::new(TestApp::new(database.pool.clone()).await);
+    println!(
+        "Load test started: {} users, {} listings each.",
+        cli.users, cli.iterations
+    );
+
+    let start = Instant::now();
+    let mut users = JoinSet::new();
+    for user in 0..cli.users {
+        let app = app.clone();
+        users.spawn(async move { flows::run_user(&app, product_id, user, cli.iterations).await });
+    }
+    let samples = users.join_all().await.concat();
+    let report = LoadReport::new(&samples, start.elapsed());
+    println!("{report}");
+
+    database.drop().await?;
+    match report.passed() {
+        true => Ok(()),
+        false => Err(Error::Any("Load test requests failed".to_owned())),
+    }
+}
@@ -0,0 +1,175 @@
+use crate::flows::{Flow, Sample};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Latency statistics of a single flow.
+///
+/// # Fields
+///
+/// * `flow`: Measured flow.
+/// * `requests`: Number of sent requests.
+/// * `errors`: Number of requests answered with an error status.
+/// * `p50`: Median latency.
+/// * `p95`: 95th percentile latency.
+/// * `max`: Highest latency.
+///
+#[derive(Debug, PartialEq)]
+pub struct FlowStats {
+    pub flow: Flow,
+    pub requests: usize,
+    pub errors: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+/// Latencies of a load test run, per flow.
+///
+/// # Fields
+///
+/// * `flows`: Statistics of every flow with at least one request.
+/// * `elapsed`: Wall time of the run.
+///
+#[derive(Debug)]
+pub struct LoadReport {
+    pub flows: Vec<FlowStats>,
+    pub elapsed: Duration,
+}
+
+impl LoadReport {
+    /// Groups the samples by flow and computes their statistics.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples`: Latencies recorded by all virtual users.
+    /// * `elapsed`: Wall time of the run.
+    ///
+    pub fn new(samples: &[Sample], elapsed: Duration) -> Self {
+        let mut by_flow = BTreeMap::<Flow, Vec<&Sample>>::new();
+        for sample in samples {
+            by_flow.entry(sample.flow).or_default().push(sample);
+        }
+
+        let flows = by_flow
+            .into_iter()
+            .map(|(flow, samples)| {
+                let mut latencies = samples
+                    .iter()
+                    .map(|sample| sample.elapsed)
+                    .collect::<Vec<_>>();
+                latencies.sort();
+
+                FlowStats {
+                    flow,
+                    requests: samples.len(),
+                    errors: samples.iter().filter(|sample| !sample.success).count(),
+                    p50: percentile(&latencies, 50),
+                    p95: percentile(&latencies, 95),
+                    max: latencies.last().copied().unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Self { flows, elapsed }
+    }
+
+    /// Checks whether every request succeeded.
+    ///
+    pub fn passed(&self) -> bool {
+        self.flows.iter().all(|stats| stats.errors == 0)
+    }
+}
+
+impl Display for LoadReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:>8} {:>8} {:>10} {:>10} {:>10}",
+            "flow", "requests", "errors", "p50 ms", "p95 ms", "max ms"
+        )?;
+        for stats in &self.flows {
+            writeln!(
+                f,
+                "{:<14} {:>8} {:>8} {:>10.1} {:>10.1} {:>10.1}",
+                stats.flow,
+                stats.requests,
+                stats.errors,
+                millis(stats.p50),
+                millis(stats.p95),
+                millis(stats.max)
+            )?;
+        }
+
+        let requests = self.flows.iter().map(|stats| stats.requests).sum::<usize>();
+        write!(
+            f,
+            "{requests} requests in {:.1} s, {:.1} requests/s",
+            self.elapsed.as_secs_f64(),
+            requests as f64 / self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+/// Returns the nearest-rank percentile of sorted latencies, zero if there are
+/// none.
+///
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied().unwrap_or_default()
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(flow: Flow, millis: u64, success: bool) -> Sample {
+        Sample {
+            flow,
+            elapsed: Duration::from_millis(millis),
+            success,
+        }
+    }
+
+    #[test]
+    fn report_should_compute_percentiles_per_flow() {
+        // Arrange
+        let mut samples = (1..=20)
+            .map(|millis| sample(Flow::ListServers, millis, true))
+            .collect::<Vec<_>>();
+        samples.push(sample(Flow::Login, 7, false));
+
+        // Act
+        let report = LoadReport::new(&samples, Duration::from_secs(1));
+
+        // Assert
+        assert_eq!(
+            report.flows,
+            vec![
+                FlowStats {
+                    flow: Flow::Login,
+                    requests: 1,
+                    errors: 1,
+                    p50: Duration::from_millis(7),
+                    p95: Duration::from_millis(7),
+                    max: Duration::from_millis(7),
+                },
+                FlowStats {
+                    flow: Flow::ListServers,
+                    requests: 20,
+                    errors: 0,
+                    p50: Duration::from_millis(10),
+                    p95: Duration::from_millis(19),
+                    max: Duration::from_millis(20),
+                },
+            ]
+        );
+        assert!(!report.passed());
+    }
+}
@@ -1,5 +1,6 @@
 ﻿use criterion::{Bencher, Criterion, criterion_group, criterion_main};
-use migration_utility::cli::Cli;
+use migration_utility::cli::Databases;
+use migration_utility::etl::copy::set_copy_threshold;
 use migration_utility::etl::loaders::*;
 use migration_utility::etl::migration::Migration;
 use migration_utility::etl::types::*;
@@ -8,6 +9,9 @@ use tokio::runtime::Runtime;
 
 const BATCH_SIZE: i32 = 100;
 
+/// Rows of the chunks loaded with both the `UNNEST` and the `COPY` paths.
+const LARGE_BATCH_SIZE: i32 = 20_000;
+
 /// Defines and runs all migration benchmarks.
 ///
 /// # Arguments
@@ -19,14 +23,11 @@ fn migration_benchmarks(criterion: &mut Criterion) {
     dotenv::dotenv().ok();
     let runtime = Runtime::new().unwrap();
     let migration = runtime.block_on(async {
-        Migration::new(&Cli {
-            dry_run: true,
-            chunk_size: 1024,
-            source_url: std::env::var("SOURCE_URL").unwrap().into(),
+        let databases = Databases {
+            source_url: Some(std::env::var("SOURCE_URL").unwrap().into()),
             target_url: std::env::var("TARGET_URL").unwrap().into(),
-        })
-        .await
-        .unwrap()
+        };
+        Migration::new(&databases, 1024).await.unwrap()
     });
 
     // Group for the slow, full migration test.
@@ -78,6 +79,20 @@ fn migration_benchmarks(criterion: &mut Criterion) {
             insert_config_values_bench(b, &runtime, &migration)
         });
     loaders_group.finish();
+
+    // Group comparing both load paths on a large chunk.
+    let mut paths_group = criterion.benchmark_group("Load Paths");
+    paths_group.sample_size(10);
+    paths_group
+        .bench_function(
+            format!("Insert {} Clients with UNNEST", LARGE_BATCH_SIZE),
+            |b| insert_large_clients_bench(b, &runtime, &migration, usize::MAX),
+        )
+        .bench_function(
+            format!("Insert {} Clients with COPY", LARGE_BATCH_SIZE),
+            |b| insert_large_clients_bench(b, &runtime, &migration, 1),
+        );
+    paths_group.finish();
 }
 
 criterion_group!(benches, migration_benchmarks);
@@ -87,13 +102,13 @@ fn full_migration_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &Mi
     bencher.to_async(runtime).iter(|| {
         let mut value = migration.clone();
         async move {
-            value.run().await.ok();
+            value.dry_run().await.ok();
         }
     })
 }
 
-fn insert_clients_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &Migration) {
-    let users = (0..BATCH_SIZE)
+fn clients(count: i32) -> Vec<Client> {
+    (0..count)
         .map(|index| Client {
             id: index,
             firstname: "John".to_owned(),
@@ -107,11 +122,47 @@ fn insert_clients_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &Mi
             phonenumber: "555-1234".to_owned(),
             password: "password123".to_owned(),
         })
-        .collect::<Vec<_>>();
+        .collect()
+}
+
+fn insert_clients_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &Migration) {
+    let users = clients(BATCH_SIZE);
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_users(&mut tx, users.clone()).await.unwrap();
+        insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            users.clone(),
+            DuplicateEmails::Skip,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
+        tx.rollback().await.unwrap();
+    });
+}
+
+fn insert_large_clients_bench(
+    bencher: &mut Bencher,
+    runtime: &Runtime,
+    migration: &Migration,
+    copy_threshold: usize,
+) {
+    let users = clients(LARGE_BATCH_SIZE);
+    set_copy_threshold(copy_threshold);
+
+    bencher.to_async(runtime).iter(|| async {
+        let mut tx = migration.target_pool.begin().await.unwrap();
+        insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            users.clone(),
+            DuplicateEmails::Skip,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -126,7 +177,7 @@ fn insert_product_groups_bench(bencher: &mut Bencher, runtime: &Runtime, migrati
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_product_groups(&mut tx, product_groups.clone())
+        insert_product_groups(&mut tx, OnConflict::Skip, product_groups.clone())
             .await
             .unwrap();
         tx.rollback().await.unwrap();
@@ -144,9 +195,15 @@ fn insert_products_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &M
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_products(&mut tx, products.clone(), &HashMap::new())
-            .await
-            .unwrap();
+        insert_products(
+            &mut tx,
+            OnConflict::Skip,
+            products.clone(),
+            &HashMap::new(),
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -162,9 +219,15 @@ fn insert_custom_fields_bench(bencher: &mut Bencher, runtime: &Runtime, migratio
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_custom_fields(&mut tx, custom_fields.clone(), &HashMap::new())
-            .await
-            .unwrap();
+        insert_custom_fields(
+            &mut tx,
+            OnConflict::Skip,
+            custom_fields.clone(),
+            &HashMap::new(),
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -178,7 +241,7 @@ fn insert_config_options_bench(bencher: &mut Bencher, runtime: &Runtime, migrati
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_config_options(&mut tx, config_options.clone())
+        insert_config_options(&mut tx, OnConflict::Skip, config_options.clone())
             .await
             .unwrap();
         tx.rollback().await.unwrap();
@@ -197,7 +260,9 @@ fn insert_servers_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &Mi
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_servers(&mut tx, servers.clone()).await.unwrap();
+        insert_servers(&mut tx, OnConflict::Skip, servers.clone())
+            .await
+            .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -213,7 +278,9 @@ fn insert_networks_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &M
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_networks(&mut tx, networks.clone()).await.unwrap();
+        insert_networks(&mut tx, OnConflict::Skip, networks.clone())
+            .await
+            .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -230,9 +297,16 @@ fn insert_ip_addresses_bench(bencher: &mut Bencher, runtime: &Runtime, migration
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
         let dummy = &HashMap::new();
-        insert_ip_addresses(&mut tx, ip_addresses.clone(), &dummy, &dummy)
-            .await
-            .unwrap();
+        insert_ip_addresses(
+            &mut tx,
+            OnConflict::Skip,
+            ip_addresses.clone(),
+            dummy,
+            dummy,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -246,7 +320,9 @@ fn insert_templates_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &
 
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
-        insert_templates(&mut tx, templates.clone()).await.unwrap();
+        insert_templates(&mut tx, OnConflict::Skip, templates.clone())
+            .await
+            .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -264,9 +340,18 @@ fn insert_services_bench(bencher: &mut Bencher, runtime: &Runtime, migration: &M
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
         let dummy = &HashMap::new();
-        insert_services(&mut tx, services.clone(), &dummy, &dummy, &dummy, &dummy)
-            .await
-            .unwrap();
+        insert_services(
+            &mut tx,
+            OnConflict::Skip,
+            services.clone(),
+            dummy,
+            dummy,
+            dummy,
+            dummy,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -284,9 +369,16 @@ fn insert_custom_values_bench(bencher: &mut Bencher, runtime: &Runtime, migratio
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
         let dummy = &HashMap::new();
-        insert_custom_values(&mut tx, custom_values.clone(), &dummy, &dummy)
-            .await
-            .unwrap();
+        insert_custom_values(
+            &mut tx,
+            OnConflict::Skip,
+            custom_values.clone(),
+            dummy,
+            dummy,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -304,9 +396,16 @@ fn insert_config_values_bench(bencher: &mut Bencher, runtime: &Runtime, migratio
     bencher.to_async(runtime).iter(|| async {
         let mut tx = migration.target_pool.begin().await.unwrap();
         let dummy = &HashMap::new();
-        insert_config_values(&mut tx, config_values.clone(), &dummy, &dummy)
-            .await
-            .unwrap();
+        insert_config_values(
+            &mut tx,
+            OnConflict::Skip,
+            config_values.clone(),
+            dummy,
+            dummy,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
         tx.rollback().await.unwrap();
     });
 }
@@ -1,4 +1,4 @@
-use migration_utility::cli::Cli;
+use migration_utility::cli::Databases;
 use migration_utility::etl::migration::Migration;
 
 #[global_allocator]
@@ -12,20 +12,17 @@ fn main() {
     dotenv::dotenv().ok();
     let runtime = tokio::runtime::Runtime::new().unwrap();
     let mut migration = runtime.block_on(async {
-        Migration::new(&Cli {
-            dry_run: true,
-            chunk_size: 1024,
-            source_url: std::env::var("SOURCE_URL").unwrap().into(),
+        let databases = Databases {
+            source_url: Some(std::env::var("SOURCE_URL").unwrap().into()),
             target_url: std::env::var("TARGET_URL").unwrap().into(),
-        })
-        .await
-        .unwrap()
+        };
+        Migration::new(&databases, 1024).await.unwrap()
     });
 
     println!("dhat: Memory benchmark started.");
 
     runtime.block_on(async {
-        migration.run().await.unwrap();
+        migration.dry_run().await.unwrap();
     });
 
     // Explicitly destroy the profiler to ensure that we get a report.
@@ -0,0 +1,396 @@
+//! This module exports the Dashboard data to a portable bundle and imports it
+//! back, to clone an environment or to rehearse a recovery.
+//!
+//! A bundle is a directory with a `manifest.json` and one file per table, as
+//! a JSON array of rows or as CSV with a header. The manifest records the
+//! version of the bundle layout and of the database schema, and a bundle is
+//! only imported into a database with the same schema. Tables are imported in
+//! the order of their references, and rows whose key already exists are kept.
+
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::{Error, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use sqlx::types::Json;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Version of the bundle layout, raised on every incompatible change.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Tables of a bundle, each one after the tables it refers to.
+pub const BUNDLE_TABLES: [&str; 20] = [
+    "datacenters",
+    "nodes",
+    "product_groups",
+    "products",
+    "product_datacenters",
+    "custom_fields",
+    "config_options",
+    "templates",
+    "users",
+    "user_aliases",
+    "organizations",
+    "organization_members",
+    "networks",
+    "isos",
+    "servers",
+    "ip_addresses",
+    "services",
+    "custom_values",
+    "config_values",
+    "quotas",
+];
+
+/// Name of the manifest file of a bundle.
+const MANIFEST: &str = "manifest.json";
+
+/// Represents the formats of the table files.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleFormat {
+    Json,
+    Csv,
+}
+
+/// Describes the content of a bundle.
+///
+/// # Fields
+///
+/// * `version`: Version of the bundle layout.
+/// * `schema_version`: Latest migration applied to the exported database.
+/// * `format`: Format of the table files.
+/// * `exported_at`: Time of the export.
+/// * `tables`: Exported tables with their number of rows.
+///
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub schema_version: i64,
+    pub format: BundleFormat,
+    pub exported_at: DateTime<Utc>,
+    pub tables: Vec<BundleTable>,
+}
+
+/// Exported table of a bundle.
+///
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundleTable {
+    pub name: String,
+    pub rows: u64,
+}
+
+impl BundleFormat {
+    /// Returns the extension of the table files.
+    ///
+    fn extension(self) -> &'static str {
+        match self {
+            BundleFormat::Json => "json",
+            BundleFormat::Csv => "csv",
+        }
+    }
+}
+
+impl Manifest {
+    /// Reads the manifest of a bundle and checks that its layout is supported.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: Directory of the bundle.
+    ///
+    pub fn read(dir: &Path) -> Result<Self> {
+        let content = std::fs::read(dir.join(MANIFEST))?;
+        let manifest = serde_json::from_slice::<Self>(&content).map_err(std::io::Error::from)?;
+        if manifest.version != BUNDLE_VERSION {
+            return Err(Error::Any(format!(
+                "Bundle version {} is not supported, expected {BUNDLE_VERSION}",
+                manifest.version
+            )));
+        }
+        if let Some(table) = manifest
+            .tables
+            .iter()
+            .find(|table| !BUNDLE_TABLES.contains(&table.name.as_str()))
+        {
+            return Err(Error::Any(format!("Unknown bundle table {}", table.name)));
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// Exports the bundle tables from a consistent snapshot of the database.
+///
+/// # Arguments
+///
+/// * `pool`: Pool of the Dashboard database.
+/// * `dir`: Directory of the bundle, created if missing.
+/// * `format`: Format of the table files.
+///
+/// # Returns
+///
+/// Manifest written to the bundle.
+///
+pub async fn export(pool: &PgPool, dir: &Path, format: BundleFormat) -> Result<Manifest> {
+    std::fs::create_dir_all(dir)?;
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut tables = Vec::with_capacity(BUNDLE_TABLES.len());
+    for name in BUNDLE_TABLES {
+        let path = dir.join(format!("{name}.{}", format.extension()));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        let rows = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM {name}"))
+            .fetch_one(&mut *tx)
+            .await? as u64;
+
+        match format {
+            BundleFormat::Json => {
+                let query = format!("SELECT row_to_json(t) FROM {name} AS t");
+                let mut stream = sqlx::query_scalar::<_, Json<serde_json::Value>>(&query)
+                    .fetch(&mut *tx)
+                    .enumerate();
+                writer.write_all(b"[")?;
+                while let Some((index, row)) = stream.next().await {
+                    if index > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    writer.write_all(b"\n")?;
+                    serde_json::to_writer(&mut writer, &row?.0).map_err(std::io::Error::from)?;
+                }
+                writer.write_all(b"\n]\n")?;
+            }
+            BundleFormat::Csv => {
+                let query = format!("COPY {name} TO STDOUT WITH (FORMAT csv, HEADER)");
+                let mut stream = tx.copy_out_raw(&query).await?;
+                while let Some(bytes) = stream.next().await {
+                    writer.write_all(&bytes?)?;
+                }
+            }
+        }
+        writer.flush()?;
+
+        tracing::debug!(table = name, rows, "Table exported.");
+        tables.push(BundleTable {
+            name: name.to_owned(),
+            rows,
+        });
+    }
+
+    let manifest = Manifest {
+        version: BUNDLE_VERSION,
+        schema_version: schema_version(&mut *tx).await?,
+        format,
+        exported_at: Utc::now(),
+        tables,
+    };
+    tx.commit().await?;
+
+    let content = serde_json::to_vec_pretty(&manifest).map_err(std::io::Error::from)?;
+    std::fs::write(dir.join(MANIFEST), content)?;
+
+    Ok(manifest)
+}
+
+/// Imports a bundle in a single transaction. Rows whose key or unique values
+/// already exist in the database are kept as they are.
+///
+/// # Arguments
+///
+/// * `pool`: Pool of the Dashboard database.
+/// * `dir`: Directory of the bundle.
+/// * `chunk_size`: Number of JSON rows inserted per statement.
+///
+/// # Returns
+///
+/// Number of rows inserted per table.
+///
+pub async fn import(pool: &PgPool, dir: &Path, chunk_size: usize) -> Result<Vec<BundleTable>> {
+    let manifest = Manifest::read(dir)?;
+    let mut tx = pool.begin().await?;
+    let schema_version = schema_version(&mut *tx).await?;
+    if manifest.schema_version != schema_version {
+        return Err(Error::Any(format!(
+            "Bundle schema version {} doesn't match the database schema version {schema_version}",
+            manifest.schema_version
+        )));
+    }
+
+    // Tables are imported in the order of their references, whatever the
+    // order of the manifest.
+    let mut inserted = Vec::with_capacity(manifest.tables.len());
+    for name in BUNDLE_TABLES {
+        if !manifest.tables.iter().any(|table| table.name == name) {
+            continue;
+        }
+        let path = dir.join(format!("{name}.{}", manifest.format.extension()));
+
+        let mut rows = 0;
+        match manifest.format {
+            BundleFormat::Json => {
+                let content = std::fs::read(&path)?;
+                let values = serde_json::from_slice::<Vec<serde_json::Value>>(&content)
+                    .map_err(std::io::Error::from)?;
+                let query = format!(
+                    "INSERT INTO {name} SELECT * FROM jsonb_populate_recordset(NULL::{name}, $1) \
+                     ON CONFLICT DO NOTHING"
+                );
+                for chunk in values.chunks(chunk_size.max(1)) {
+                    rows += sqlx::query(&query)
+                        .bind(Json(chunk))
+                        .execute(&mut *tx)
+                        .await?
+                        .rows_affected();
+                }
+            }
+            BundleFormat::Csv => {
+                sqlx::query(&format!(
+                    "CREATE TEMPORARY TABLE bundle_rows (LIKE {name}) ON COMMIT DROP"
+                ))
+                .execute(&mut *tx)
+                .await?;
+                let mut copy = tx
+                    .copy_in_raw("COPY bundle_rows FROM STDIN WITH (FORMAT csv, HEADER)")
+                    .await?;
+                copy.send(std::fs::read(&path)?).await?;
+                copy.finish().await?;
+                rows = sqlx::query(&format!(
+                    "INSERT INTO {name} SELECT * FROM bundle_rows ON CONFLICT DO NOTHING"
+                ))
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+                sqlx::query("DROP TABLE bundle_rows")
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        tracing::debug!(table = name, rows, "Table imported.");
+        inserted.push(BundleTable {
+            name: name.to_owned(),
+            rows,
+        });
+    }
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+/// Returns the latest migration applied to the database.
+///
+async fn schema_version(tx: &mut sqlx::PgConnection) -> Result<i64> {
+    Ok(
+        sqlx::query_scalar::<_, Option<i64>>("SELECT MAX(version) FROM _sqlx_migrations")
+            .fetch_one(tx)
+            .await?
+            .unwrap_or_default(),
+    )
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty directory for the bundle of a test.
+    ///
+    fn bundle_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("dashboard-bundle-{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    async fn populate(pool: &PgPool) {
+        sqlx::query("INSERT INTO datacenters (code, display_name) VALUES ('fra', 'Frankfurt')")
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO product_groups (name) VALUES ('VPS'), ('Dedicated, legacy')")
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn bundle_tables_should_follow_their_references(pool: PgPool) {
+        // Arrange
+        let references = sqlx::query_as::<_, (String, String)>(
+            r#"
+SELECT DISTINCT tc.table_name::TEXT, ccu.table_name::TEXT
+FROM information_schema.table_constraints AS tc
+JOIN information_schema.constraint_column_usage AS ccu
+    ON ccu.constraint_schema = tc.constraint_schema AND ccu.constraint_name = tc.constraint_name
+WHERE tc.table_schema = 'public' AND tc.constraint_type = 'FOREIGN KEY'
+            "#,
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+        // Act
+        let position = |table: &str| BUNDLE_TABLES.iter().position(|name| *name == table);
+        let misplaced = references
+            .into_iter()
+            .filter(|(table, referenced)| match position(table) {
+                Some(index) => position(referenced).is_none_or(|other| other > index),
+                None => false,
+            })
+            .collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(misplaced, Vec::<(String, String)>::new());
+    }
+
+    async fn count(pool: &PgPool, table: &str) -> i64 {
+        sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM {table}"))
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn bundle_should_round_trip_in_both_formats(pool: PgPool) {
+        for format in [BundleFormat::Json, BundleFormat::Csv] {
+            // Arrange
+            populate(&pool).await;
+            let dir = bundle_dir(format.extension());
+            let manifest = export(&pool, &dir, format).await.unwrap();
+            sqlx::query("TRUNCATE datacenters, product_groups CASCADE")
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            // Act
+            let inserted = import(&pool, &dir, 1).await.unwrap();
+            let repeated = import(&pool, &dir, 1).await.unwrap();
+
+            // Assert
+            assert_eq!(Manifest::read(&dir).unwrap(), manifest);
+            assert!(
+                manifest
+                    .tables
+                    .iter()
+                    .any(|t| t.name == "product_groups" && t.rows == 2)
+            );
+            assert!(
+                inserted
+                    .iter()
+                    .any(|t| t.name == "product_groups" && t.rows == 2)
+            );
+            assert!(repeated.iter().all(|t| t.rows == 0));
+            assert_eq!(count(&pool, "datacenters").await, 1);
+            assert_eq!(count(&pool, "product_groups").await, 2);
+            sqlx::query("TRUNCATE datacenters, product_groups CASCADE")
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+    }
+}
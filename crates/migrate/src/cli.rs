@@ -1,15 +1,104 @@
-﻿use secrecy::SecretString;
+﻿use crate::bundle::BundleFormat;
+use crate::etl::copy::DEFAULT_COPY_THRESHOLD;
+use crate::etl::loaders::DuplicateEmails;
+use crate::etl::migration::DEFAULT_BUFFERED_CHUNKS;
+use crate::etl::source::SourceKind;
+use crate::etl::types::DashboardTable;
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::{Error, Result};
+use secrecy::SecretString;
 use serde::Deserialize;
+use std::path::PathBuf;
 
-#[derive(Debug, Deserialize, clap::Parser)]
+#[derive(Debug, clap::Parser)]
 #[command(
     name = "Migration Utility",
     version = "0.1.0",
     about = "Command-line tool for WHMCS-to-PostgreSQL data transfer"
 )]
 pub struct Cli {
-    #[arg(short, long, help("Perform the dry run without changing the database"))]
-    pub dry_run: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Subcommands of the utility.
+///
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Migrates all tables and commits the result.
+    Run(RunArgs),
+    /// Compares the migrated tables with WHMCS and writes a JSON report,
+    /// failing on any discrepancy.
+    Validate(ValidateArgs),
+    /// Reads the source only and reports the rows each step would insert and
+    /// the references that can't be mapped, without touching the target
+    /// database.
+    Plan(PlanArgs),
+    /// Reports the rows of each table that were migrated from WHMCS.
+    Status(StatusArgs),
+    /// Continues an interrupted run from the given table.
+    Resume(ResumeArgs),
+    /// Dumps the Dashboard data to a versioned bundle of JSON or CSV files.
+    Export(ExportArgs),
+    /// Loads a bundle written by `export` into a Dashboard database with the
+    /// same schema.
+    Import(ImportArgs),
+}
+
+/// Connections to both databases. The source database is optional for runs
+/// reading from a directory of exports.
+///
+#[derive(Debug, Deserialize, clap::Args)]
+pub struct Databases {
+    #[arg(
+        short,
+        long,
+        help = "Source database URL (WHMCS or Virtualizor MySQL)",
+        env = "SOURCE_URL"
+    )]
+    pub source_url: Option<SecretString>,
+    #[arg(
+        short,
+        long,
+        help = "Target database URL (PostgreSQL)",
+        env = "TARGET_URL"
+    )]
+    pub target_url: SecretString,
+}
+
+impl Databases {
+    /// Returns the URL of the source database, which is required unless the
+    /// records are read from a directory.
+    ///
+    pub fn required_source_url(&self) -> Result<&SecretString> {
+        self.source_url
+            .as_ref()
+            .ok_or_else(|| Error::Any("Source database URL is required".to_owned()))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub databases: Databases,
+    #[arg(
+        long,
+        env = "SOURCE_DIR",
+        help = "Reads the records from a directory of CSV or JSON exports instead of WHMCS"
+    )]
+    pub source_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SourceKind::Whmcs,
+        help = "Kind of the source database"
+    )]
+    pub source_kind: SourceKind,
+    #[arg(
+        long,
+        help = "Proxmox VMID of the template the services of the Virtualizor VMs are linked to"
+    )]
+    pub template_vmid: Option<i32>,
     #[arg(
         short,
         long,
@@ -17,13 +106,218 @@ pub struct Cli {
         env = "CHUNK_SIZE"
     )]
     pub chunk_size: usize,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_COPY_THRESHOLD,
+        help = "Rows of a chunk from which it is loaded with COPY instead of UNNEST arrays"
+    )]
+    pub copy_threshold: usize,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_BUFFERED_CHUNKS,
+        help = "Chunks read from the source ahead of the one being loaded, bounding the memory"
+    )]
+    pub buffered_chunks: usize,
+    #[arg(
+        long,
+        help = "Commits every table on its own, so a failed run can be resumed"
+    )]
+    pub commit_each_table: bool,
+    #[arg(
+        long,
+        conflicts_with = "commit_each_table",
+        help = "Commits every chunk on its own, keeping transactions short on large tables"
+    )]
+    pub commit_each_chunk: bool,
     #[arg(
         short,
         long,
-        help = "Source database URL (WHMCS MySQL)",
+        conflicts_with_all = ["commit_each_table", "commit_each_chunk"],
+        help = "Rolls the migration back, reporting the rows that would be inserted"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        help = "Migrates only the rows changed since the time (RFC 3339), updating the migrated ones"
+    )]
+    pub since: Option<DateTime<Utc>>,
+    #[arg(
+        long,
+        env = "MAX_READ_RATE",
+        help = "Source rows read per second at most, to spare a live source database"
+    )]
+    pub max_read_rate: Option<u32>,
+    #[arg(
+        long,
+        default_value_t = 4,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Connections opened to the source database at most"
+    )]
+    pub source_connections: u32,
+    #[arg(
+        long,
+        help = "Backs off from the source database while its latency is higher than usual"
+    )]
+    pub adaptive_backoff: bool,
+    #[arg(
+        long,
+        help = "Skips checking the target migrations and the source tables before migrating"
+    )]
+    pub skip_schema_check: bool,
+    #[arg(
+        long,
+        default_value = "skipped-rows.json",
+        help = "Path of the report of the skipped rows, written as CSV for a `.csv` extension and as JSON otherwise"
+    )]
+    pub skip_report: PathBuf,
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Number of WHMCS rows that can't be decoded to skip before the run fails"
+    )]
+    pub max_errors: usize,
+    #[arg(
+        long,
+        help = "Path of the TOML file with the cleaning rules applied before loading"
+    )]
+    pub rules: Option<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = DuplicateEmails::Skip,
+        help = "Handling of the clients sharing the email of an earlier one"
+    )]
+    pub duplicate_emails: DuplicateEmails,
+    #[arg(
+        long,
+        requires = "anonymize_key",
+        help = "Replaces names, emails, addresses, phone numbers and password hashes with pseudonyms"
+    )]
+    pub anonymize: bool,
+    #[arg(
+        long,
+        env = "ANONYMIZE_KEY",
+        hide_env_values = true,
+        help = "Secret the pseudonyms are derived with, equal keys give equal pseudonyms"
+    )]
+    pub anonymize_key: Option<SecretString>,
+    #[arg(long, help = "Hides the progress bars, leaving only the logs")]
+    pub quiet: bool,
+    #[arg(
+        long,
+        conflicts_with = "quiet",
+        help = "Reports the progress as JSON lines on stderr instead of progress bars"
+    )]
+    pub json_progress: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ValidateArgs {
+    #[command(flatten)]
+    pub databases: Databases,
+    #[arg(
+        short,
+        long,
+        default_value = "reconciliation.json",
+        help = "Path of the JSON report"
+    )]
+    pub report: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PlanArgs {
+    #[arg(
+        short,
+        long,
+        help = "Source database URL (WHMCS or Virtualizor MySQL)",
         env = "SOURCE_URL"
     )]
-    pub source_url: SecretString,
+    pub source_url: Option<SecretString>,
+    #[arg(
+        long,
+        env = "SOURCE_DIR",
+        help = "Reads the records from a directory of CSV or JSON exports instead of WHMCS"
+    )]
+    pub source_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SourceKind::Whmcs,
+        help = "Kind of the source database"
+    )]
+    pub source_kind: SourceKind,
+    #[arg(
+        long,
+        help = "Proxmox VMID of the template the services of the Virtualizor VMs are linked to"
+    )]
+    pub template_vmid: Option<i32>,
+    #[arg(
+        short,
+        long,
+        default_value = "plan.json",
+        help = "Path of the JSON report"
+    )]
+    pub report: PathBuf,
+}
+
+impl PlanArgs {
+    /// Returns the URL of the source database, which is required unless the
+    /// records are read from a directory.
+    ///
+    pub fn required_source_url(&self) -> Result<&SecretString> {
+        self.source_url
+            .as_ref()
+            .ok_or_else(|| Error::Any("Source database URL is required".to_owned()))
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct StatusArgs {
+    #[arg(
+        short,
+        long,
+        help = "Target database URL (PostgreSQL)",
+        env = "TARGET_URL"
+    )]
+    pub target_url: SecretString,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ResumeArgs {
+    #[command(flatten)]
+    pub run: RunArgs,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        help = "First table to migrate, the earlier ones are skipped"
+    )]
+    pub from: DashboardTable,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    #[arg(
+        short,
+        long,
+        help = "Target database URL (PostgreSQL)",
+        env = "TARGET_URL"
+    )]
+    pub target_url: SecretString,
+    #[arg(short, long, help = "Directory the bundle is written to")]
+    pub bundle: PathBuf,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value_t = BundleFormat::Json,
+        help = "Format of the table files"
+    )]
+    pub format: BundleFormat,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ImportArgs {
     #[arg(
         short,
         long,
@@ -31,4 +325,257 @@ pub struct Cli {
         env = "TARGET_URL"
     )]
     pub target_url: SecretString,
+    #[arg(short, long, help = "Directory of the bundle to load")]
+    pub bundle: PathBuf,
+    #[arg(
+        short,
+        long,
+        default_value_t = 1024,
+        help = "Sets the number of records to process per batch",
+        env = "CHUNK_SIZE"
+    )]
+    pub chunk_size: usize,
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn resume_should_parse_its_own_flags() {
+        // Arrange
+        let args = [
+            "migration_utility",
+            "resume",
+            "--source-url",
+            "mysql://source",
+            "--target-url",
+            "postgres://target",
+            "--chunk-size",
+            "512",
+            "--commit-each-table",
+            "--from",
+            "ip_addresses",
+        ];
+
+        // Act
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        // Assert
+        let Command::Resume(resume) = cli.command else {
+            panic!("Expected the resume subcommand");
+        };
+        assert_eq!(resume.run.chunk_size, 512);
+        assert!(resume.run.commit_each_table);
+        assert_eq!(resume.from, DashboardTable::IpAddresses);
+    }
+
+    #[test]
+    fn run_should_parse_since_timestamp() {
+        // Arrange
+        let args = [
+            "migration_utility",
+            "run",
+            "--source-url",
+            "mysql://source",
+            "--target-url",
+            "postgres://target",
+            "--chunk-size",
+            "512",
+            "--since",
+            "2025-10-01T00:00:00Z",
+        ];
+
+        // Act
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        // Assert
+        let Command::Run(run) = cli.command else {
+            panic!("Expected the run subcommand");
+        };
+        assert_eq!(run.since, "2025-10-01T00:00:00Z".parse().ok());
+    }
+
+    #[test]
+    fn run_should_read_from_a_directory() {
+        // Arrange
+        let args = [
+            "migration_utility",
+            "run",
+            "--source-dir",
+            "exports",
+            "--target-url",
+            "postgres://target",
+            "--chunk-size",
+            "512",
+        ];
+
+        // Act
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        // Assert
+        let Command::Run(run) = cli.command else {
+            panic!("Expected the run subcommand");
+        };
+        assert_eq!(run.source_dir, Some(PathBuf::from("exports")));
+    }
+
+    #[test]
+    fn run_should_parse_max_errors() {
+        // Arrange
+        let args = [
+            "migration_utility",
+            "run",
+            "--source-dir",
+            "exports",
+            "--target-url",
+            "postgres://target",
+            "--chunk-size",
+            "512",
+            "--max-errors",
+            "10",
+        ];
+
+        // Act
+        let cli = Cli::try_parse_from(args).unwrap();
+        let defaults = Cli::try_parse_from(&args[..8]).unwrap();
+
+        // Assert
+        let (Command::Run(run), Command::Run(defaults)) = (cli.command, defaults.command) else {
+            panic!("Expected the run subcommand");
+        };
+        assert_eq!(run.max_errors, 10);
+        assert_eq!(defaults.max_errors, 0);
+    }
+
+    #[test]
+    fn plan_should_read_from_a_directory() {
+        // Arrange
+        let args = ["migration_utility", "plan", "--source-dir", "exports"];
+
+        // Act
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        // Assert
+        let Command::Plan(plan) = cli.command else {
+            panic!("Expected the plan subcommand");
+        };
+        assert_eq!(plan.source_dir, Some(PathBuf::from("exports")));
+        assert_eq!(plan.report, PathBuf::from("plan.json"));
+    }
+
+    #[test]
+    fn run_should_parse_source_kind() {
+        // Arrange
+        let args = [
+            "migration_utility",
+            "run",
+            "--source-url",
+            "mysql://virtualizor",
+            "--source-kind",
+            "virtualizor",
+            "--target-url",
+            "postgres://target",
+            "--chunk-size",
+            "512",
+        ];
+
+        // Act
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        // Assert
+        let Command::Run(run) = cli.command else {
+            panic!("Expected the run subcommand");
+        };
+        assert_eq!(run.source_kind, SourceKind::Virtualizor);
+    }
+
+    #[test]
+    fn run_should_parse_read_limits() {
+        // Arrange
+        let args = |connections: &'static str| {
+            [
+                "migration_utility",
+                "run",
+                "--target-url",
+                "postgres://target",
+                "--chunk-size",
+                "512",
+                "--max-read-rate",
+                "2000",
+                "--source-connections",
+                connections,
+                "--adaptive-backoff",
+            ]
+        };
+
+        // Act
+        let cli = Cli::try_parse_from(args("2")).unwrap();
+        let none = Cli::try_parse_from(args("0"));
+
+        // Assert
+        let Command::Run(run) = cli.command else {
+            panic!("Expected the run subcommand");
+        };
+        assert_eq!(run.max_read_rate, Some(2000));
+        assert_eq!(run.source_connections, 2);
+        assert!(run.adaptive_backoff);
+        assert!(none.is_err());
+    }
+
+    #[test]
+    fn anonymize_should_require_a_key() {
+        // Arrange
+        let args = [
+            "migration_utility",
+            "run",
+            "--source-url",
+            "mysql://source",
+            "--target-url",
+            "postgres://target",
+            "--chunk-size",
+            "512",
+            "--anonymize",
+        ];
+        let with_key = [&args[..], &["--anonymize-key", "staging"]].concat();
+
+        // Act
+        let without_key = Cli::try_parse_from(args);
+        let cli = Cli::try_parse_from(with_key).unwrap();
+
+        // Assert
+        assert!(without_key.is_err());
+        let Command::Run(run) = cli.command else {
+            panic!("Expected the run subcommand");
+        };
+        assert!(run.anonymize && run.anonymize_key.is_some());
+    }
+
+    #[test]
+    fn export_should_parse_bundle_format() {
+        // Arrange
+        let args = [
+            "migration_utility",
+            "export",
+            "--target-url",
+            "postgres://target",
+            "--bundle",
+            "backup",
+            "--format",
+            "csv",
+        ];
+
+        // Act
+        let cli = Cli::try_parse_from(args).unwrap();
+
+        // Assert
+        let Command::Export(export) = cli.command else {
+            panic!("Expected the export subcommand");
+        };
+        assert_eq!(export.bundle, PathBuf::from("backup"));
+        assert_eq!(export.format, BundleFormat::Csv);
+    }
 }
@@ -0,0 +1,299 @@
+//! Runs the subcommands of the utility.
+
+use crate::bundle;
+use crate::cli::{
+    Command, ExportArgs, ImportArgs, PlanArgs, ResumeArgs, RunArgs, StatusArgs, ValidateArgs,
+};
+use crate::etl::anonymize::Anonymizer;
+use crate::etl::copy;
+use crate::etl::migration::{self, Commit, Migration};
+use crate::etl::plan::{self, Plan};
+use crate::etl::progress::{Progress, ProgressMode};
+use crate::etl::reconcile;
+use crate::etl::rules::Transformer;
+use crate::etl::source::{DirectorySource, Source, SourceKind, VirtualizorSource, WhmcsSource};
+use crate::etl::throttle::Throttle;
+use crate::etl::types::{DashboardTable, SkippedRow};
+use dashboard_common::prelude::{Error, Result};
+use secrecy::ExposeSecret;
+use sqlx::MySqlPool;
+use sqlx::mysql::MySqlPoolOptions;
+use sqlx::postgres::PgPoolOptions;
+use std::path::Path;
+
+/// Runs the parsed subcommand.
+///
+/// # Arguments
+///
+/// * `command`: Subcommand with its flags.
+///
+pub async fn execute(command: Command) -> Result<()> {
+    match command {
+        Command::Run(args) => run(args, DashboardTable::Users).await,
+        Command::Validate(args) => validate(args).await,
+        Command::Plan(args) => plan(args).await,
+        Command::Status(args) => status(args).await,
+        Command::Resume(ResumeArgs { run: args, from }) => run(args, from).await,
+        Command::Export(args) => export(args).await,
+        Command::Import(args) => import(args).await,
+    }
+}
+
+/// Migrates the tables from the given one on, reading from the directory of
+/// exports when one is given and from the source database of the given kind
+/// otherwise.
+///
+async fn run(args: RunArgs, from: DashboardTable) -> Result<()> {
+    let target_url = &args.databases.target_url;
+    let throttle = Throttle::new(args.max_read_rate, args.adaptive_backoff);
+    match (&args.source_dir, args.source_kind) {
+        (Some(dir), _) => {
+            let source = DirectorySource::new(dir);
+            let migration = Migration::with_source(source, target_url, args.chunk_size).await?;
+            migrate(migration, &args, from).await
+        }
+        (None, SourceKind::Whmcs) => {
+            let source = WhmcsSource::new(connect_source(&args).await?).with_throttle(throttle);
+            let migration = Migration::with_source(source, target_url, args.chunk_size).await?;
+            migrate(migration, &args, from).await
+        }
+        (None, SourceKind::Virtualizor) => {
+            let source = VirtualizorSource::new(connect_source(&args).await?)
+                .with_throttle(throttle)
+                .with_template(args.template_vmid);
+            let migration = Migration::with_source(source, target_url, args.chunk_size).await?;
+            migrate(migration, &args, from).await
+        }
+    }
+}
+
+/// Connects to the source database, opening at most the given number of
+/// connections.
+///
+async fn connect_source(args: &RunArgs) -> Result<MySqlPool> {
+    Ok(MySqlPoolOptions::new()
+        .max_connections(args.source_connections)
+        .connect(args.databases.required_source_url()?.expose_secret())
+        .await?)
+}
+
+/// Migrates the tables from the given one on and commits them, then writes
+/// the report of the skipped rows, even when the migration failed.
+///
+async fn migrate<Src: Source>(
+    migration: Migration<Src>,
+    args: &RunArgs,
+    from: DashboardTable,
+) -> Result<()> {
+    let commit = match (args.commit_each_table, args.commit_each_chunk, args.dry_run) {
+        (true, _, _) => Commit::EachTable,
+        (_, true, _) => Commit::EachChunk,
+        (_, _, true) => Commit::Never,
+        _ => Commit::Atomic,
+    };
+    let rules = match &args.rules {
+        Some(path) => Transformer::from_file(path)?,
+        None => Transformer::default(),
+    };
+    let progress = match (args.quiet, args.json_progress) {
+        (true, _) => ProgressMode::Quiet,
+        (_, true) => ProgressMode::Json,
+        _ => ProgressMode::Bars,
+    };
+    let anonymizer = match (args.anonymize, &args.anonymize_key) {
+        (true, Some(key)) => Anonymizer::new(key.clone()),
+        _ => Anonymizer::default(),
+    };
+    if !args.skip_schema_check {
+        migration.preflight().await?;
+    }
+    copy::set_copy_threshold(args.copy_threshold);
+    let mut migration = migration
+        .with_since(args.since)
+        .with_max_errors(args.max_errors)
+        .with_rules(rules)
+        .with_duplicate_emails(args.duplicate_emails)
+        .with_anonymizer(anonymizer)
+        .with_progress(Progress::new(progress))
+        .with_buffered_chunks(args.buffered_chunks);
+    let result = migration.migrate(from, commit).await;
+
+    let skipped = migration.take_skipped();
+    write_skip_report(&args.skip_report, &skipped)?;
+    tracing::info!(report = %args.skip_report.display(), rows = skipped.len(), "Skip report written.");
+
+    let statistic = result?;
+    tracing::info!(?statistic, "Rows inserted or updated.");
+
+    Ok(())
+}
+
+/// Writes the skipped rows as CSV when the path ends with `.csv`, and as JSON
+/// otherwise.
+///
+/// # Arguments
+///
+/// * `path`: Path of the report.
+/// * `skipped`: Rows left out of the migration.
+///
+fn write_skip_report(path: &Path, skipped: &[SkippedRow]) -> Result<()> {
+    let report = match path.extension().is_some_and(|ext| ext == "csv") {
+        true => skip_report_csv(skipped).into_bytes(),
+        false => serde_json::to_vec_pretty(skipped).map_err(std::io::Error::from)?,
+    };
+
+    Ok(std::fs::write(path, report)?)
+}
+
+/// Formats the skipped rows as CSV with a header line.
+///
+fn skip_report_csv(skipped: &[SkippedRow]) -> String {
+    let mut csv = String::from("table,source_id,reason\n");
+    for row in skipped {
+        let reason = row.reason.replace('"', "\"\"");
+        csv.push_str(&format!("{},{},\"{reason}\"\n", row.table, row.source_id));
+    }
+
+    csv
+}
+
+/// Compares the migrated tables with WHMCS and writes the JSON report.
+///
+async fn validate(args: ValidateArgs) -> Result<()> {
+    let source_pool = MySqlPoolOptions::new()
+        .connect(args.databases.required_source_url()?.expose_secret())
+        .await?;
+    let target_pool = PgPoolOptions::new()
+        .connect(args.databases.target_url.expose_secret())
+        .await?;
+    let reconciliation = reconcile::reconcile(&source_pool, &target_pool).await?;
+
+    let report = serde_json::to_vec_pretty(&reconciliation).map_err(std::io::Error::from)?;
+    std::fs::write(&args.report, report)?;
+    tracing::info!(report = %args.report.display(), passed = reconciliation.passed, "Reconciliation report written.");
+
+    match reconciliation.passed {
+        true => Ok(()),
+        false => Err(Error::Any("Reconciliation found discrepancies".to_owned())),
+    }
+}
+
+/// Reads the source only and reports the rows each step would insert, then
+/// writes the JSON report with the references that can't be mapped.
+///
+async fn plan(args: PlanArgs) -> Result<()> {
+    let plan = match (&args.source_dir, args.source_kind) {
+        (Some(dir), _) => plan::plan(&DirectorySource::new(dir)).await?,
+        (None, kind) => {
+            let source_pool = MySqlPoolOptions::new()
+                .connect(args.required_source_url()?.expose_secret())
+                .await?;
+            match kind {
+                SourceKind::Whmcs => plan::plan(&WhmcsSource::new(source_pool)).await?,
+                SourceKind::Virtualizor => {
+                    let source =
+                        VirtualizorSource::new(source_pool).with_template(args.template_vmid);
+                    plan::plan(&source).await?
+                }
+            }
+        }
+    };
+    for table in &plan.tables {
+        tracing::info!(
+            table = %table.table,
+            source_rows = table.source_rows,
+            undecodable = table.undecodable,
+            unmapped = table.unmapped,
+            to_insert = table.to_insert,
+            "Rows to migrate."
+        );
+    }
+    write_plan_report(&args.report, &plan)?;
+    tracing::info!(report = %args.report.display(), unmapped = plan.unmapped.len(), "Plan report written.");
+
+    Ok(())
+}
+
+/// Writes the plan as JSON.
+///
+fn write_plan_report(path: &Path, plan: &Plan) -> Result<()> {
+    let report = serde_json::to_vec_pretty(plan).map_err(std::io::Error::from)?;
+
+    Ok(std::fs::write(path, report)?)
+}
+
+/// Reports the rows of each table that were migrated from WHMCS.
+///
+async fn status(args: StatusArgs) -> Result<()> {
+    let target_pool = PgPoolOptions::new()
+        .connect(args.target_url.expose_secret())
+        .await?;
+    for (table, rows) in migration::status(&target_pool).await? {
+        tracing::info!(%table, rows, "Rows migrated.");
+    }
+
+    Ok(())
+}
+
+/// Dumps the Dashboard data to a bundle.
+///
+async fn export(args: ExportArgs) -> Result<()> {
+    let target_pool = PgPoolOptions::new()
+        .connect(args.target_url.expose_secret())
+        .await?;
+    let manifest = bundle::export(&target_pool, &args.bundle, args.format).await?;
+    for table in &manifest.tables {
+        tracing::info!(table = %table.name, rows = table.rows, "Rows exported.");
+    }
+    tracing::info!(bundle = %args.bundle.display(), schema_version = manifest.schema_version, "Bundle written.");
+
+    Ok(())
+}
+
+/// Loads a bundle into the Dashboard database.
+///
+async fn import(args: ImportArgs) -> Result<()> {
+    let target_pool = PgPoolOptions::new()
+        .connect(args.target_url.expose_secret())
+        .await?;
+    for table in bundle::import(&target_pool, &args.bundle, args.chunk_size).await? {
+        tracing::info!(table = %table.name, rows = table.rows, "Rows imported.");
+    }
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_report_csv_should_quote_reasons() {
+        // Arrange
+        let skipped = [
+            SkippedRow::new(
+                DashboardTable::Products,
+                7,
+                "Product group 3 was not migrated",
+            ),
+            SkippedRow::new(
+                DashboardTable::CustomValues,
+                12u32,
+                r#"Field "os" was not migrated"#,
+            ),
+        ];
+
+        // Act
+        let csv = skip_report_csv(&skipped);
+
+        // Assert
+        assert_eq!(
+            csv,
+            "table,source_id,reason\n\
+             products,7,\"Product group 3 was not migrated\"\n\
+             custom_values,12,\"Field \"\"os\"\" was not migrated\"\n"
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! This module pseudonymizes the personal data of the source rows right
+//! before they are loaded, so staging environments can be built from
+//! production data.
+//!
+//! Every pseudonym is derived from the original value with a keyed hash, so
+//! runs with the same key produce the same users, clients sharing an email
+//! still share one, and the originals can't be recovered without the key.
+
+use crate::etl::types;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+
+/// Password hash no password matches, so anonymized users can't log in
+/// before they reset their password.
+pub const LOCKED_PASSWORD: &str = "!";
+
+/// Text replacing the free text of tickets and replies, which can't be
+/// scrubbed reliably.
+pub const REDACTED_TEXT: &str = "[redacted]";
+
+const FIRST_NAMES: [&str; 16] = [
+    "Alex", "Sam", "Robin", "Kim", "Jordan", "Taylor", "Casey", "Morgan", "Jamie", "Avery",
+    "Riley", "Quinn", "Charlie", "Drew", "Emery", "Rowan",
+];
+
+const LAST_NAMES: [&str; 16] = [
+    "Smith",
+    "Miller",
+    "Meyer",
+    "Novak",
+    "Garcia",
+    "Rossi",
+    "Jensen",
+    "Dubois",
+    "Kowalski",
+    "Silva",
+    "Berg",
+    "Horvat",
+    "Walsh",
+    "Costa",
+    "Lindqvist",
+    "Petrov",
+];
+
+const STREETS: [&str; 8] = [
+    "Main", "Oak", "Station", "Park", "Church", "Mill", "River", "Hill",
+];
+
+/// Replaces the personal data of the source rows with pseudonyms.
+///
+/// # Fields
+///
+/// * `key`: Secret the pseudonyms are derived with, `None` to load the rows
+///   as they are.
+///
+#[derive(Debug, Default)]
+pub struct Anonymizer {
+    key: Option<SecretString>,
+}
+
+impl Anonymizer {
+    /// Creates new `Anonymizer` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: Secret the pseudonyms are derived with.
+    ///
+    pub fn new(key: SecretString) -> Self {
+        Self { key: Some(key) }
+    }
+
+    /// Replaces the names, emails, street addresses, postcodes, phone numbers
+    /// and password hashes of a chunk of clients. The city, state and country
+    /// are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `clients`: Chunk of clients read from the source.
+    ///
+    pub fn anonymize_clients(&self, clients: Vec<types::Client>) -> Vec<types::Client> {
+        let Some(key) = &self.key else {
+            return clients;
+        };
+
+        clients
+            .into_iter()
+            .map(|mut client| {
+                let name = digest(key, "name", &client.id.to_string());
+                client.firstname = pick(&FIRST_NAMES, name[0]).to_owned();
+                client.lastname = pick(&LAST_NAMES, name[1]).to_owned();
+
+                // Emails are compared as they are cleaned, so the pseudonyms
+                // of equal emails are equal as well.
+                let email = digest(key, "email", client.email.trim());
+                client.email = format!("user-{}@example.com", hex::encode(&email[..6]));
+
+                let address = digest(key, "address", &client.id.to_string());
+                let number = u16::from_be_bytes([address[0], address[1]]) % 200 + 1;
+                client.address1 = format!("{number} {} Street", pick(&STREETS, address[2]));
+                client.postcode = format!("{:05}", digits(&address[3..7]) % 100_000);
+
+                let phone = digest(key, "phone", &client.id.to_string());
+                client.phonenumber = format!("+1555{:07}", digits(&phone[..4]) % 10_000_000);
+                client.password = LOCKED_PASSWORD.to_owned();
+
+                client
+            })
+            .collect()
+    }
+
+    /// Replaces the hostnames of a chunk of servers, which often carry the
+    /// name of the client or of their company.
+    ///
+    /// # Arguments
+    ///
+    /// * `servers`: Chunk of servers read from the source.
+    ///
+    pub fn anonymize_servers(&self, servers: Vec<types::VmRecord>) -> Vec<types::VmRecord> {
+        let Some(key) = &self.key else {
+            return servers;
+        };
+
+        servers
+            .into_iter()
+            .map(|mut server| {
+                let hostname = digest(key, "hostname", &server.id.to_string());
+                server.hostname = format!("host-{}.example.com", hex::encode(&hostname[..6]));
+
+                server
+            })
+            .collect()
+    }
+
+    /// Replaces the subjects of a chunk of tickets with pseudonyms and redacts
+    /// their messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `tickets`: Chunk of tickets read from the source.
+    ///
+    pub fn anonymize_tickets(&self, tickets: Vec<types::Ticket>) -> Vec<types::Ticket> {
+        let Some(key) = &self.key else {
+            return tickets;
+        };
+
+        tickets
+            .into_iter()
+            .map(|mut ticket| {
+                let title = digest(key, "ticket", &ticket.id.to_string());
+                ticket.title = format!("Ticket {}", hex::encode(&title[..4]));
+                ticket.message = REDACTED_TEXT.to_owned();
+
+                ticket
+            })
+            .collect()
+    }
+
+    /// Replaces the names clients signed their ticket replies with and redacts
+    /// the messages of every reply, as staff replies quote the clients. The
+    /// names of staff members are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `replies`: Chunk of ticket replies read from the source.
+    ///
+    pub fn anonymize_replies(&self, replies: Vec<types::TicketReply>) -> Vec<types::TicketReply> {
+        let Some(key) = &self.key else {
+            return replies;
+        };
+
+        replies
+            .into_iter()
+            .map(|mut reply| {
+                if !reply.from_staff() && !reply.name.is_empty() {
+                    let name = digest(key, "reply", reply.name.trim());
+                    reply.name = format!(
+                        "{} {}",
+                        pick(&FIRST_NAMES, name[0]),
+                        pick(&LAST_NAMES, name[1])
+                    );
+                }
+                reply.message = REDACTED_TEXT.to_owned();
+
+                reply
+            })
+            .collect()
+    }
+}
+
+/// Hashes a value with the key, separated by the kind of the value so equal
+/// values of different kinds get unrelated pseudonyms.
+///
+fn digest(key: &SecretString, kind: &str, value: &str) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(kind.as_bytes());
+    mac.update(b":");
+    mac.update(value.as_bytes());
+
+    mac.finalize().into_bytes().into()
+}
+
+fn pick<'a>(values: &[&'a str], byte: u8) -> &'a str {
+    values[byte as usize % values.len()]
+}
+
+fn digits(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | byte as u32)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(id: i32, email: &str) -> types::Client {
+        types::Client {
+            id,
+            firstname: "John".to_owned(),
+            lastname: "Doe".to_owned(),
+            email: email.to_owned(),
+            address1: "Main Street 1".to_owned(),
+            city: "Berlin".to_owned(),
+            state: "Berlin".to_owned(),
+            postcode: "10115".to_owned(),
+            country: "DE".to_owned(),
+            phonenumber: "+493012345".to_owned(),
+            password: "hash".to_owned(),
+        }
+    }
+
+    #[test]
+    fn anonymize_clients_should_be_deterministic_per_key() {
+        // Arrange
+        let clients = || {
+            vec![
+                client(1, "john@example.org"),
+                client(2, "john@example.org"),
+                client(3, "jane@example.org"),
+            ]
+        };
+        let anonymizer = Anonymizer::new("staging".into());
+
+        // Act
+        let first = anonymizer.anonymize_clients(clients());
+        let second = anonymizer.anonymize_clients(clients());
+        let other = Anonymizer::new("other".into()).anonymize_clients(clients());
+
+        // Assert
+        let emails = |clients: &[types::Client]| {
+            clients
+                .iter()
+                .map(|client| client.email.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(emails(&first), emails(&second));
+        assert_ne!(emails(&first), emails(&other));
+        assert_eq!(first[0].email, first[1].email);
+        assert_ne!(first[0].email, first[2].email);
+        assert!(first[0].email.starts_with("user-") && first[0].email.ends_with("@example.com"));
+        assert_ne!(first[0].address1, "Main Street 1");
+        assert_eq!(first[0].postcode.len(), 5);
+        assert!(first[0].phonenumber.starts_with("+1555") && first[0].phonenumber.len() == 12);
+        assert_eq!(first[0].password, LOCKED_PASSWORD);
+        assert_eq!(
+            (first[0].city.as_str(), first[0].country.as_str()),
+            ("Berlin", "DE")
+        );
+    }
+
+    #[test]
+    fn anonymize_replies_should_keep_staff_names() {
+        // Arrange
+        let mut client_reply = types::TicketReply::new(1, 1, "", "Thanks");
+        client_reply.name = "John Doe".to_owned();
+        let staff_reply = types::TicketReply::new(2, 1, "Support Bot", "Done");
+        let anonymizer = Anonymizer::new("staging".into());
+
+        // Act
+        let replies = anonymizer.anonymize_replies(vec![client_reply, staff_reply]);
+        let unchanged = Anonymizer::default().anonymize_clients(vec![client(1, "a@b.c")]);
+
+        // Assert
+        assert_ne!(replies[0].name, "John Doe");
+        assert_eq!(replies[1].author(), "Support Bot");
+        assert!(replies.iter().all(|reply| reply.message == REDACTED_TEXT));
+        assert_eq!(unchanged[0].email, "a@b.c");
+    }
+
+    #[test]
+    fn anonymize_tickets_should_scrub_subject_and_message() {
+        // Arrange
+        let mut ticket = types::Ticket::new(1, "ABC-123", 1, "Invoice for John Doe");
+        ticket.message = "Call me at +49 30 12345".to_owned();
+        let anonymizer = Anonymizer::new("staging".into());
+
+        // Act
+        let first = anonymizer.anonymize_tickets(vec![ticket.clone()]);
+        let second = anonymizer.anonymize_tickets(vec![ticket]);
+
+        // Assert
+        assert_eq!(first[0].title, second[0].title);
+        assert!(first[0].title.starts_with("Ticket "));
+        assert_eq!(first[0].message, REDACTED_TEXT);
+        assert_eq!(first[0].tid, "ABC-123");
+    }
+
+    #[test]
+    fn anonymize_servers_should_replace_hostnames() {
+        // Arrange
+        let servers = vec![
+            types::VmRecord::new(1, 100, Some("pve"), "john-doe.example.org", "Running"),
+            types::VmRecord::new(2, 101, Some("pve"), "acme-shop.example.org", "Running"),
+        ];
+        let anonymizer = Anonymizer::new("staging".into());
+
+        // Act
+        let servers = anonymizer.anonymize_servers(servers);
+
+        // Assert
+        assert!(servers[0].hostname.starts_with("host-"));
+        assert!(servers[0].hostname.ends_with(".example.com"));
+        assert_ne!(servers[0].hostname, servers[1].hostname);
+        assert_eq!(servers[0].vmid, 100);
+    }
+}
@@ -0,0 +1,305 @@
+//! This module inserts the rows of the Dashboard tables in bulk.
+//!
+//! A row type derives `PgBulkInsert`, naming its table, the unique column
+//! matching the migrated rows, and the column of every field. The SQL type of
+//! every column follows from the Rust type of its field through `PgColumn`,
+//! so a field of a type without one doesn't compile, and the arrays are always
+//! bound in the order of the columns.
+
+use crate::etl::copy::{self, CopyRow};
+use crate::etl::loaders::OnConflict;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgArguments, PgHasArrayType};
+use sqlx::query::Query;
+use sqlx::{Encode, PgConnection, Postgres, Type};
+use uuid::Uuid;
+
+pub use dashboard_derive::PgBulkInsert;
+
+/// Query the arrays of the columns are bound to.
+pub type BulkQuery<'q> = Query<'q, Postgres, PgArguments>;
+
+/// Column of a table inserted in bulk.
+///
+/// # Fields
+///
+/// * `name`: Name of the column.
+/// * `sql_type`: SQL type of the values, cast by the database to the type of
+///   the column if it differs.
+/// * `updated`: Whether the column of an existing row is updated, `false` for
+///   the columns the Dashboard manages once the row is migrated.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkColumn {
+    pub name: &'static str,
+    pub sql_type: &'static str,
+    pub updated: bool,
+}
+
+/// Rust types of the inserted values, with their SQL type.
+///
+pub trait PgColumn: for<'q> Encode<'q, Postgres> + Type<Postgres> + PgHasArrayType + Send {
+    const SQL_TYPE: &'static str;
+}
+
+impl PgColumn for bool {
+    const SQL_TYPE: &'static str = "bool";
+}
+
+impl PgColumn for i32 {
+    const SQL_TYPE: &'static str = "int4";
+}
+
+impl PgColumn for i64 {
+    const SQL_TYPE: &'static str = "int8";
+}
+
+impl PgColumn for String {
+    const SQL_TYPE: &'static str = "text";
+}
+
+impl PgColumn for Uuid {
+    const SQL_TYPE: &'static str = "uuid";
+}
+
+impl PgColumn for DateTime<Utc> {
+    const SQL_TYPE: &'static str = "timestamptz";
+}
+
+impl<T: PgColumn> PgColumn for Option<T>
+where
+    Option<T>: for<'q> Encode<'q, Postgres>,
+{
+    const SQL_TYPE: &'static str = T::SQL_TYPE;
+}
+
+/// Rows of a table inserted in bulk, derived with `#[derive(PgBulkInsert)]`.
+///
+/// A row naming a column the table doesn't have doesn't compile:
+///
+/// ```compile_fail
+/// use migration_utility::etl::bulk::PgBulkInsert;
+///
+/// #[derive(PgBulkInsert)]
+/// #[bulk_insert(table = "product_groups", conflict_key = "whmcs_id")]
+/// struct GroupRow {
+///     whmcs_id: i32,
+///     title: String,
+/// }
+/// ```
+///
+/// Nor does a field of another type than its column:
+///
+/// ```compile_fail
+/// use migration_utility::etl::bulk::PgBulkInsert;
+///
+/// #[derive(PgBulkInsert)]
+/// #[bulk_insert(table = "product_groups", conflict_key = "whmcs_id")]
+/// struct GroupRow {
+///     whmcs_id: i64,
+///     name: String,
+/// }
+/// ```
+///
+/// Nor a field of a type without a `PgColumn` implementation:
+///
+/// ```compile_fail
+/// use migration_utility::etl::bulk::PgBulkInsert;
+///
+/// #[derive(PgBulkInsert)]
+/// #[bulk_insert(table = "product_groups", conflict_key = "whmcs_id")]
+/// struct GroupRow {
+///     whmcs_id: i32,
+///     name: std::net::IpAddr,
+/// }
+/// ```
+///
+/// Nor a conflict key that isn't one of the columns:
+///
+/// ```compile_fail
+/// use migration_utility::etl::bulk::PgBulkInsert;
+///
+/// #[derive(PgBulkInsert)]
+/// #[bulk_insert(table = "product_groups", conflict_key = "id")]
+/// struct GroupRow {
+///     whmcs_id: i32,
+///     name: String,
+/// }
+/// ```
+///
+pub trait PgBulkInsert: Sized + Send {
+    /// Name of the table.
+    const TABLE: &'static str;
+    /// Unique column matching the existing rows.
+    const CONFLICT_KEY: &'static str;
+    /// Columns of the table, in the order of the fields.
+    const COLUMNS: &'static [BulkColumn];
+
+    /// Binds one array per column to the `UNNEST` query.
+    ///
+    fn bind_arrays<'q>(rows: Vec<Self>, query: BulkQuery<'q>) -> BulkQuery<'q>;
+
+    /// Encodes the fields of the row for `COPY`.
+    ///
+    fn encode_row(&self, row: &mut CopyRow<'_>) -> sqlx::Result<()>;
+}
+
+/// Performs a bulk `INSERT ... ON CONFLICT` operation using PostgreSQL's
+/// `UNNEST` function.
+///
+/// Conflicts on unique constraints are handled by doing nothing, or by
+/// updating the row matching the conflict key, except for its `insert_only`
+/// columns. Updated rows are deduplicated
+/// by the key first, as a single statement can't update a row twice. Chunks
+/// of at least `copy::copy_threshold()` rows are streamed with `COPY`
+/// instead, rather than built into parameter arrays.
+///
+/// # Arguments
+///
+/// * `connection`: Connection of the in-progress transaction.
+/// * `on_conflict`: Handling of the existing rows.
+/// * `rows`: Rows to insert.
+///
+/// # Returns
+///
+/// On success, the number of affected rows.
+///
+pub async fn bulk_insert<T: PgBulkInsert>(
+    connection: &mut PgConnection,
+    on_conflict: OnConflict,
+    rows: impl IntoIterator<Item = T>,
+) -> sqlx::Result<u64> {
+    let rows = rows.into_iter().collect::<Vec<_>>();
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    if rows.len() >= copy::copy_threshold() {
+        return copy::copy_insert(connection, on_conflict, rows).await;
+    }
+
+    let query = unnest_query::<T>(on_conflict);
+    Ok(T::bind_arrays(rows, sqlx::query(&query))
+        .execute(connection)
+        .await?
+        .rows_affected())
+}
+
+/// Builds the `INSERT` of a table from one array per column.
+///
+fn unnest_query<T: PgBulkInsert>(on_conflict: OnConflict) -> String {
+    let columns = column_list(T::COLUMNS);
+    let arrays = T::COLUMNS
+        .iter()
+        .enumerate()
+        .map(|(index, column)| format!("${}::{}[]", index + 1, column.sql_type))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (table, key) = (T::TABLE, T::CONFLICT_KEY);
+
+    match on_conflict {
+        OnConflict::Skip => format!(
+            "INSERT INTO {table} ({columns})\nSELECT * FROM UNNEST({arrays})\nON CONFLICT DO NOTHING"
+        ),
+        OnConflict::Update => format!(
+            "INSERT INTO {table} ({columns})\nSELECT DISTINCT ON ({key}) * FROM UNNEST({arrays}) \
+             AS source ({columns})\n{}",
+            conflict_update::<T>()
+        ),
+    }
+}
+
+/// Joins the names of the columns.
+///
+pub(crate) fn column_list(columns: &[BulkColumn]) -> String {
+    columns
+        .iter()
+        .map(|column| column.name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Builds the `ON CONFLICT` clause updating the existing rows, in all columns
+/// except the key and the `insert_only` ones. Rows without such columns are
+/// kept as they are.
+///
+pub(crate) fn conflict_update<T: PgBulkInsert>() -> String {
+    let updates = T::COLUMNS
+        .iter()
+        .filter(|column| column.updated && column.name != T::CONFLICT_KEY)
+        .map(|column| format!("{0} = EXCLUDED.{0}", column.name))
+        .collect::<Vec<_>>();
+
+    match updates.is_empty() {
+        true => "ON CONFLICT DO NOTHING".to_owned(),
+        false => format!(
+            "ON CONFLICT ({}) DO UPDATE SET {}",
+            T::CONFLICT_KEY,
+            updates.join(", ")
+        ),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::etl::types;
+
+    #[test]
+    fn derive_should_follow_the_field_types_and_names() {
+        // Act
+        let columns = <types::IpAddressRow as PgBulkInsert>::COLUMNS;
+
+        // Assert
+        assert_eq!(types::IpAddressRow::TABLE, "ip_addresses");
+        assert_eq!(
+            columns
+                .iter()
+                .map(|column| (column.name, column.sql_type))
+                .collect::<Vec<_>>(),
+            [
+                ("ip_address", "text"),
+                ("network_id", "uuid"),
+                ("server_id", "uuid"),
+                ("whmcs_id", "int4"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unnest_query_should_update_all_columns_but_the_key() {
+        // Act
+        let skip = unnest_query::<types::ProductGroup>(OnConflict::Skip);
+        let update = unnest_query::<types::ProductGroup>(OnConflict::Update);
+
+        // Assert
+        assert_eq!(
+            skip,
+            "INSERT INTO product_groups (whmcs_id, name)\n\
+             SELECT * FROM UNNEST($1::int4[], $2::text[])\n\
+             ON CONFLICT DO NOTHING"
+        );
+        assert_eq!(
+            update,
+            "INSERT INTO product_groups (whmcs_id, name)\n\
+             SELECT DISTINCT ON (whmcs_id) * FROM UNNEST($1::int4[], $2::text[]) \
+             AS source (whmcs_id, name)\n\
+             ON CONFLICT (whmcs_id) DO UPDATE SET name = EXCLUDED.name"
+        );
+    }
+
+    #[test]
+    fn conflict_update_should_skip_insert_only_columns() {
+        // Act
+        let services = conflict_update::<types::ServiceRow>();
+        let users = conflict_update::<types::Client>();
+
+        // Assert
+        assert_eq!(
+            services,
+            "ON CONFLICT (whmcs_id) DO UPDATE SET status = EXCLUDED.status"
+        );
+        assert_eq!(users, "ON CONFLICT DO NOTHING");
+    }
+}
@@ -0,0 +1,225 @@
+//! This module loads large chunks with `COPY FROM STDIN` in the binary format,
+//! instead of the parameter arrays of the `UNNEST` inserts.
+//!
+//! The rows are encoded one by one and streamed into a temporary table typed
+//! like their columns, then moved into the target table by the same
+//! `INSERT ... ON CONFLICT` as the `UNNEST` path, so both paths treat the
+//! migrated rows alike. The loaders switch to this path on their own for
+//! chunks of at least `copy_threshold()` rows.
+
+use crate::etl::bulk::{self, PgBulkInsert};
+use crate::etl::loaders::OnConflict;
+use sqlx::encode::IsNull;
+use sqlx::postgres::PgArgumentBuffer;
+use sqlx::{Encode, PgConnection, Postgres};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Rows of a chunk from which the loaders use `COPY` by default.
+pub const DEFAULT_COPY_THRESHOLD: usize = 10_000;
+
+/// Encoded bytes sent to the database at once.
+const SEND_SIZE: usize = 64 * 1024;
+
+/// Header of the binary `COPY` format, followed by the flags and the length
+/// of the header extension.
+const SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
+static COPY_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_COPY_THRESHOLD);
+
+/// Sets the rows of a chunk from which the loaders of the process use `COPY`.
+///
+/// # Arguments
+///
+/// * `rows`: Smallest chunk loaded with `COPY`, `usize::MAX` to never use it.
+///
+pub fn set_copy_threshold(rows: usize) {
+    COPY_THRESHOLD.store(rows.max(1), Ordering::Relaxed);
+}
+
+/// Returns the rows of a chunk from which the loaders use `COPY`.
+///
+pub fn copy_threshold() -> usize {
+    COPY_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Row being encoded in the binary `COPY` format.
+///
+pub struct CopyRow<'a> {
+    buffer: &'a mut Vec<u8>,
+}
+
+impl CopyRow<'_> {
+    /// Encodes the next field of the row, `NULL` for a `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: Value of the field, encoded like a query argument.
+    ///
+    pub fn push<'q, T: Encode<'q, Postgres>>(&mut self, value: &T) -> sqlx::Result<()> {
+        let mut field = PgArgumentBuffer::default();
+        match value
+            .encode_by_ref(&mut field)
+            .map_err(sqlx::Error::Encode)?
+        {
+            IsNull::Yes => self.buffer.extend_from_slice(&(-1i32).to_be_bytes()),
+            IsNull::No => {
+                let length = i32::try_from(field.len())
+                    .map_err(|error| sqlx::Error::Encode(Box::new(error)))?;
+                self.buffer.extend_from_slice(&length.to_be_bytes());
+                self.buffer.extend_from_slice(&field);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Streams the rows into a temporary table with `COPY`, then inserts them with
+/// the same conflict handling as `bulk::bulk_insert`.
+///
+/// # Arguments
+///
+/// * `connection`: Connection of the in-progress transaction.
+/// * `on_conflict`: Handling of the existing rows.
+/// * `rows`: Rows to insert.
+///
+/// # Returns
+///
+/// On success, the number of affected rows.
+///
+pub async fn copy_insert<T: PgBulkInsert>(
+    connection: &mut PgConnection,
+    on_conflict: OnConflict,
+    rows: Vec<T>,
+) -> sqlx::Result<u64> {
+    let (table, key) = (T::TABLE, T::CONFLICT_KEY);
+    let staging = format!("copy_{table}");
+    let definitions = T::COLUMNS
+        .iter()
+        .map(|column| format!("{} {}", column.name, column.sql_type))
+        .collect::<Vec<_>>()
+        .join(", ");
+    sqlx::query(&format!(
+        "CREATE TEMPORARY TABLE {staging} ({definitions}) ON COMMIT DROP"
+    ))
+    .execute(&mut *connection)
+    .await?;
+
+    let mut copy = connection
+        .copy_in_raw(&format!("COPY {staging} FROM STDIN WITH (FORMAT binary)"))
+        .await?;
+    let mut buffer = Vec::with_capacity(SEND_SIZE * 2);
+    buffer.extend_from_slice(SIGNATURE);
+    buffer.extend_from_slice(&0i32.to_be_bytes());
+    buffer.extend_from_slice(&0i32.to_be_bytes());
+    for item in &rows {
+        buffer.extend_from_slice(&(T::COLUMNS.len() as i16).to_be_bytes());
+        if let Err(error) = item.encode_row(&mut CopyRow {
+            buffer: &mut buffer,
+        }) {
+            copy.abort(error.to_string()).await?;
+            return Err(error);
+        }
+        if buffer.len() >= SEND_SIZE {
+            copy.send(buffer.as_slice()).await?;
+            buffer.clear();
+        }
+    }
+    buffer.extend_from_slice(&(-1i16).to_be_bytes());
+    copy.send(buffer).await?;
+    copy.finish().await?;
+    tracing::trace!("Copying all rows completed.");
+
+    let columns = bulk::column_list(T::COLUMNS);
+    let query = match on_conflict {
+        OnConflict::Skip => format!(
+            "INSERT INTO {table} ({columns})\nSELECT {columns} FROM {staging}\nON CONFLICT DO NOTHING"
+        ),
+        OnConflict::Update => format!(
+            "INSERT INTO {table} ({columns})\n\
+             SELECT DISTINCT ON ({key}) {columns} FROM {staging}\n{}",
+            bulk::conflict_update::<T>()
+        ),
+    };
+    let affected = sqlx::query(&query)
+        .execute(&mut *connection)
+        .await?
+        .rows_affected();
+
+    // Every chunk of the transaction copies into a new temporary table.
+    sqlx::query(&format!("DROP TABLE {staging}"))
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(affected)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::etl::types;
+    use sqlx::PgPool;
+
+    #[test]
+    fn push_should_encode_fields_like_the_binary_format() {
+        // Arrange
+        let mut buffer = Vec::new();
+        let mut row = CopyRow {
+            buffer: &mut buffer,
+        };
+
+        // Act
+        row.push(&7i32).unwrap();
+        row.push(&"vm").unwrap();
+        row.push(&None::<i64>).unwrap();
+
+        // Assert
+        assert_eq!(
+            buffer,
+            [
+                &4i32.to_be_bytes()[..],
+                &7i32.to_be_bytes(),
+                &2i32.to_be_bytes(),
+                b"vm",
+                &(-1i32).to_be_bytes(),
+            ]
+            .concat()
+        );
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn copy_insert_should_handle_conflicts_like_unnest(pool: PgPool) {
+        // Arrange
+        let groups = |name: &str| {
+            (1..=3)
+                .map(|id| types::ProductGroup {
+                    id,
+                    name: format!("{name} {id}"),
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut tx = pool.begin().await.unwrap();
+
+        // Act
+        let inserted = copy_insert(&mut tx, OnConflict::Skip, groups("VPS"))
+            .await
+            .unwrap();
+        let kept = copy_insert(&mut tx, OnConflict::Skip, groups("Kept"))
+            .await
+            .unwrap();
+        let updated = copy_insert(&mut tx, OnConflict::Update, groups("Dedicated"))
+            .await
+            .unwrap();
+        let names =
+            sqlx::query_scalar::<_, String>("SELECT name FROM product_groups ORDER BY whmcs_id")
+                .fetch_all(&mut *tx)
+                .await
+                .unwrap();
+
+        // Assert
+        assert_eq!((inserted, kept, updated), (3, 0, 3));
+        assert_eq!(names, ["Dedicated 1", "Dedicated 2", "Dedicated 3"]);
+    }
+}
@@ -8,96 +8,37 @@
 //! efficient `INSERT` operations within the context of a single database
 //! transaction.
 
-use crate::etl::types;
+use crate::etl::bulk;
+use crate::etl::types::{self, DashboardTable, SkippedRow};
 use dashboard_common::prelude::Result;
 use sqlx::PgTransaction;
 use std::collections::HashMap;
 use uuid::Uuid;
 
-/// Performs a bulk `INSERT ... ON CONFLICT DO NOTHING` operation using
-/// PostgreSQL's `UNNEST` function.
+/// Defines what happens to the rows that were already migrated.
 ///
-/// Efficiently inserts multiple items from an iterator into a specified
-/// database table. Conflicts on unique constraints are handled by doing
-/// nothing.
-///
-/// # Arguments
-///
-/// * `$iterator`: Iterator over the items to be inserted.
-/// * `$db_table_name`: Name of the target database table.
-/// * `$executor`: An expression that evaluates to a `&mut sqlx::Executor`.
-///
-/// * `$index`: Index for the `UNNEST` placeholder.
-/// * `$item_field`: The field name for structs or index for tuples.
-/// * `$db_field`: Database column name where this data will be inserted.
-/// * `$sql_type`: PostgreSQL SQL type for the array.
-///
-/// # Returns
-///
-/// Expands to an expression of type `Result<u64>`, representing the number of
-/// rows affected by the `INSERT` operation.
-///
-macro_rules! unnest_insert {
-    (
-        $iterator:expr =>
-        $db_table_name:ident =>
-        $executor:expr,
-        [
-            (
-                $first_index:literal,
-                $first_item_field:tt,
-                $first_db_field:ident,
-                $first_sql_type:ident
-            ),
-            $((
-                $index:literal,
-                $item_field:tt,
-                $db_field:ident,
-                $sql_type:ident
-            )),* $(,)?
-        ]
-    ) => {{
-        let items = $iterator.collect::<Vec<_>>();
-        let count = items.len();
-
-        // Collect all fields.
-        let mut $first_db_field = Vec::with_capacity(count);
-        $(let mut $db_field = Vec::with_capacity(count);)*
-        for item in items {
-            $first_db_field.push(item.$first_item_field);
-            $($db_field.push(item.$item_field);)*
-        }
-        tracing::trace!("Collecting all fields completed.");
-
-        // Insert collected items.
-        let mut builder = sqlx::QueryBuilder::<sqlx::Postgres>::default();
-        let query = builder
-            .push("INSERT INTO ")
-            .push(stringify!($db_table_name))
-            .push(" (")
-            .push(stringify!($first_db_field))
-        $(
-            .push(", ")
-            .push(stringify!($db_field))
-        )*
-            .push(")\nSELECT * FROM UNNEST(")
-            .push(format!("${}::{}[]", $first_index, stringify!($first_sql_type)))
-        $(
-            .push(", ")
-            .push(format!("${}::{}[]", $index, stringify!($sql_type)))
-        )*
-            .push(")\nON CONFLICT DO NOTHING")
-            .build();
-
-        query
-            .bind(&$first_db_field)
-        $(
-            .bind(&$db_field)
-        )*
-            .execute($executor.as_mut())
-            .await
-            .map(|result| result.rows_affected())
-    }};
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// The existing rows are kept as they are.
+    Skip,
+    /// The existing rows are updated with the WHMCS data, for delta runs
+    /// picking up changes since the bulk run.
+    Update,
+}
+
+/// Defines what happens to the clients sharing the email of an earlier one.
+/// Emails are compared regardless of case, as the Dashboard does on login.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DuplicateEmails {
+    /// The later clients are skipped and reported, and so are their services.
+    Skip,
+    /// The later clients are migrated with their WHMCS id added to the email.
+    /// A client whose suffixed email is taken as well is skipped.
+    Suffix,
+    /// The later clients are merged into the account of the earlier one, which
+    /// their services are migrated to.
+    Merge,
 }
 
 /// Helper function to bulk insert users into the target database.
@@ -105,29 +46,153 @@ macro_rules! unnest_insert {
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `clients`: Vector of `whmcs::Client` structs to be inserted.
+/// * `duplicates`: Handling of the clients sharing the email of an earlier
+///   one, migrated or in the same chunk.
+/// * `skipped`: Collects the clients left out for a duplicated email.
 ///
 /// # Returns
 ///
 /// On success, the number of affected rows.
 ///
-pub async fn insert_users(tx: &mut PgTransaction<'_>, clients: Vec<types::Client>) -> Result<u64> {
-    Ok(unnest_insert!(
-        clients.into_iter() => users => tx,
-        [
-            (1, id, whmcs_id, int4),
-            (2, firstname, first_name, text),
-            (3, lastname, last_name, text),
-            (4, email, email, text),
-            (5, address1, address, text),
-            (6, city, city, text),
-            (7, state, state, text),
-            (8, postcode, post_code, text),
-            (9, country, country, text),
-            (10, phonenumber, phone_number, text),
-            (11, password, password, text),
-        ]
-    )?)
+pub async fn insert_users(
+    tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
+    clients: Vec<types::Client>,
+    duplicates: DuplicateEmails,
+    skipped: &mut Vec<SkippedRow>,
+) -> Result<u64> {
+    // WHMCS ids of the users owning the lowercased emails, `None` for
+    // Dashboard users. The suffixed emails are looked up too, so a suffix
+    // can't take the email of another user.
+    let mut emails = clients
+        .iter()
+        .map(|client| client.email.to_lowercase())
+        .collect::<Vec<_>>();
+    if duplicates == DuplicateEmails::Suffix {
+        emails.extend(
+            clients
+                .iter()
+                .map(|client| suffixed_email(&client.email, client.id).to_lowercase()),
+        );
+    }
+    let mut owners = sqlx::query!(
+        r#"SELECT LOWER(email) AS "email!", whmcs_id FROM users WHERE LOWER(email) = ANY($1)"#,
+        &emails
+    )
+    .fetch_all(tx.as_mut())
+    .await?
+    .into_iter()
+    .map(|user| (user.email, user.whmcs_id))
+    .collect::<HashMap<_, _>>();
+    let describe = |owner: Option<i32>| {
+        owner.map_or("a Dashboard user".to_owned(), |id| format!("client {id}"))
+    };
+
+    let mut users = Vec::with_capacity(clients.len());
+    let mut aliases = Vec::new();
+    for mut client in clients {
+        let email = client.email.to_lowercase();
+        let owner = match owners.get(&email) {
+            Some(owner) if *owner != Some(client.id) => Some(*owner),
+            Some(_) => None,
+            None => {
+                owners.insert(email, Some(client.id));
+                None
+            }
+        };
+        if let Some(owner) = owner {
+            let owner = describe(owner);
+            tracing::warn!(client_id = ?client.id, ?duplicates, "Client email is used by {owner}.");
+            match duplicates {
+                DuplicateEmails::Skip => {
+                    let reason = format!("Email {} is used by {owner}", client.email);
+                    skipped.push(SkippedRow::new(DashboardTable::Users, client.id, reason));
+                    continue;
+                }
+                DuplicateEmails::Suffix => {
+                    let suffixed = suffixed_email(&client.email, client.id);
+                    match owners.get(&suffixed.to_lowercase()) {
+                        Some(other) if *other != Some(client.id) => {
+                            let reason = format!(
+                                "Email {} is used by {owner} and {suffixed} by {}",
+                                client.email,
+                                describe(*other)
+                            );
+                            skipped.push(SkippedRow::new(DashboardTable::Users, client.id, reason));
+                            continue;
+                        }
+                        _ => {
+                            owners.insert(suffixed.to_lowercase(), Some(client.id));
+                            client.email = suffixed;
+                        }
+                    }
+                }
+                DuplicateEmails::Merge => {
+                    aliases.push((client.id, client.email));
+                    continue;
+                }
+            }
+        }
+        users.push(client);
+    }
+    let emails = users
+        .iter()
+        .map(|client| client.email.clone())
+        .collect::<Vec<_>>();
+
+    let affected = bulk::bulk_insert(tx.as_mut(), conflict, users).await?;
+
+    // Every user owns a personal organization with their services.
+    sqlx::query!(
+        r#"
+WITH organization AS (
+    INSERT INTO organizations (name, personal_user_id)
+    SELECT 'Personal', u.id
+    FROM users AS u
+    WHERE u.email = ANY($1)
+    ON CONFLICT (personal_user_id) DO NOTHING
+    RETURNING id, personal_user_id
+)
+INSERT INTO organization_members (organization_id, user_id, role)
+SELECT id, personal_user_id, 'Owner'
+FROM organization
+        "#,
+        &emails
+    )
+    .execute(tx.as_mut())
+    .await?;
+
+    // Merged clients are resolved to the user owning their email.
+    if !aliases.is_empty() {
+        let (whmcs_ids, emails) = aliases.into_iter().unzip::<_, _, Vec<_>, Vec<_>>();
+        sqlx::query!(
+            r#"
+INSERT INTO user_aliases (whmcs_id, user_id)
+SELECT alias.whmcs_id, u.id
+FROM UNNEST($1::INT[], $2::TEXT[]) AS alias (whmcs_id, email)
+JOIN users AS u ON LOWER(u.email) = LOWER(alias.email)
+ON CONFLICT (whmcs_id) DO UPDATE SET user_id = EXCLUDED.user_id
+            "#,
+            &whmcs_ids,
+            &emails
+        )
+        .execute(tx.as_mut())
+        .await?;
+    }
+
+    Ok(affected)
+}
+
+/// Adds the WHMCS id of a client to the local part of its email, keeping the
+/// address deliverable on servers supporting subaddressing.
+///
+fn suffixed_email(email: &str, whmcs_id: i32) -> String {
+    match email.rsplit_once('@') {
+        Some((local, domain)) => format!("{local}+whmcs{whmcs_id}@{domain}"),
+        None => format!("{email}+whmcs{whmcs_id}"),
+    }
 }
 
 /// Helper function to bulk insert product groups into the target database.
@@ -135,6 +200,7 @@ pub async fn insert_users(tx: &mut PgTransaction<'_>, clients: Vec<types::Client
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `groups`: Vector of `whmcs::ProductGroup` structs to be inserted.
 ///
 /// # Returns
@@ -143,12 +209,10 @@ pub async fn insert_users(tx: &mut PgTransaction<'_>, clients: Vec<types::Client
 ///
 pub async fn insert_product_groups(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     groups: Vec<types::ProductGroup>,
 ) -> Result<u64> {
-    Ok(unnest_insert!(
-        groups.into_iter() => product_groups => tx,
-        [(1, name, name, text), (2, id, whmcs_id, int4)]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, groups).await?)
 }
 
 /// Helper function to bulk insert products into the target database.
@@ -156,9 +220,11 @@ pub async fn insert_product_groups(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `products`: Vector of `whmcs::Product` structs to be inserted.
 /// * `group_id_map`: Relationship between the WHMCS id and the Dashboard id for
 ///   product groups.
+/// * `skipped`: Collects the rows left out for a missing reference.
 ///
 /// # Returns
 ///
@@ -166,29 +232,30 @@ pub async fn insert_product_groups(
 ///
 pub async fn insert_products(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     products: Vec<types::Product>,
     group_id_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
 ) -> Result<u64> {
     #[rustfmt::skip]
     let fields_iter = products
         .into_iter()
         .filter_map(|product| match group_id_map.get(&product.gid) {
-            Some(group_uuid) => Some((*group_uuid, product.name, product.id)),
+            Some(group_uuid) => Some(types::ProductRow {
+                group_id: *group_uuid,
+                name: product.name,
+                whmcs_id: product.id,
+            }),
             _ => {
                 tracing::warn!(product_id = ?product.id, group_id = ?product.gid,
                     "Skipping product with non-migrated product group." );
+                skipped.push(SkippedRow::new(DashboardTable::Products, product.id,
+                    format!("Product group {} was not migrated", product.gid)));
                 None
             }
         });
 
-    Ok(unnest_insert!(
-        fields_iter => products => tx,
-        [
-            (1, 0, group_id, uuid),
-            (2, 1, name, text),
-            (3, 2, whmcs_id, int4)
-        ]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, fields_iter).await?)
 }
 
 /// Helper function to bulk insert custom fields into the target database.
@@ -196,9 +263,11 @@ pub async fn insert_products(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `fields`: Vector of `whmcs::CustomField` structs to be inserted.
 /// * `product_id_map`:  Relationship between the WHMCS id and the Dashboard id
 ///   for products.
+/// * `skipped`: Collects the rows left out for a missing reference.
 ///
 /// # Returns
 ///
@@ -206,29 +275,30 @@ pub async fn insert_products(
 ///
 pub async fn insert_custom_fields(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     fields: Vec<types::CustomField>,
     product_id_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
 ) -> Result<u64> {
     #[rustfmt::skip]
     let fields_iter = fields
         .into_iter()
         .filter_map(|field| match product_id_map.get(&field.relid) {
-            Some(product_uuid) => Some((*product_uuid, field.fieldname, field.id)),
+            Some(product_uuid) => Some(types::CustomFieldRow {
+                product_id: *product_uuid,
+                name: field.fieldname,
+                whmcs_id: field.id,
+            }),
             _ => {
                 tracing::warn!(custom_field_id = ?field.id, product_id = ?field.relid,
                     "Skipping custom field with non-migrated product." );
+                skipped.push(SkippedRow::new(DashboardTable::CustomFields, field.id,
+                    format!("Product {} was not migrated", field.relid)));
                 None
             }
         });
 
-    Ok(unnest_insert!(
-        fields_iter => custom_fields => tx,
-        [
-            (1, 0, product_id, uuid),
-            (2, 1, name, text),
-            (3, 2, whmcs_id, int4)
-        ]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, fields_iter).await?)
 }
 
 /// Helper function to bulk insert config options into the target database.
@@ -236,6 +306,7 @@ pub async fn insert_custom_fields(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `options`: Vector of `whmcs::ConfigOption` structs to be inserted.
 ///
 /// # Returns
@@ -244,12 +315,10 @@ pub async fn insert_custom_fields(
 ///
 pub async fn insert_config_options(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     options: Vec<types::ConfigOption>,
 ) -> Result<u64> {
-    Ok(unnest_insert!(
-        options.into_iter() => config_options => tx,
-        [(1, optionname, name, text), (2, id, whmcs_id, int4)]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, options).await?)
 }
 
 /// Helper function to bulk insert servers into the target database.
@@ -257,6 +326,7 @@ pub async fn insert_config_options(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `vm_records`: Vector of `whmcs::VmRecord` structs to be inserted.
 ///
 /// # Returns
@@ -265,30 +335,21 @@ pub async fn insert_config_options(
 ///
 pub async fn insert_servers(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     vm_records: Vec<types::VmRecord>,
 ) -> Result<u64> {
-    let servers = vm_records
-        .into_iter()
-        .map(types::Server::from)
-        .collect::<Vec<types::Server>>();
-
-    Ok(unnest_insert!(
-        servers.into_iter() => servers => tx,
-        [
-            (1, vmid, vm_id, int8),
-            (2, node, node_name, text),
-            (3, hostname, host_name, text),
-            (4, status, status, text),
-            (5, id, whmcs_id, int4),
-        ]
-    )?)
+    let servers = vm_records.into_iter().map(types::Server::from);
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, servers).await?)
 }
 
-/// Helper function to bulk insert networks into the target database.
+/// Helper function to bulk insert networks into the target database. The
+/// datacenters named by the network titles are created first.
 ///
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `networks`: Vector of `whmcs::Network` structs to be inserted.
 ///
 /// # Returns
@@ -297,17 +358,16 @@ pub async fn insert_servers(
 ///
 pub async fn insert_networks(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     networks: Vec<types::Network>,
 ) -> Result<u64> {
-    Ok(unnest_insert!(
-        networks.into_iter() => networks => tx,
-        [
-            (1, title, datacenter_name, text),
-            (2, gateway, gateway, text),
-            (3, mask, subnet_mask, text),
-            (4, id, whmcs_id, int4),
-        ]
-    )?)
+    let datacenters = networks.iter().map(|network| types::DatacenterRow {
+        code: network.title.clone(),
+        display_name: network.title.clone(),
+    });
+    bulk::bulk_insert(tx.as_mut(), conflict, datacenters).await?;
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, networks).await?)
 }
 
 /// Helper function to bulk insert ip addresses into the target database.
@@ -315,9 +375,11 @@ pub async fn insert_networks(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `address`: Vector of `whmcs::IpAddress` structs to be inserted.
 /// * `server_map`: WHMCS ID to Dashboard UUID relationship for servers.
 /// * `network_map`: WHMCS ID to Dashboard UUID relationship for networks.
+/// * `skipped`: Collects the rows left out for a missing reference.
 ///
 /// # Returns
 ///
@@ -325,9 +387,11 @@ pub async fn insert_networks(
 ///
 pub async fn insert_ip_addresses(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     address: Vec<types::IpAddress>,
     server_map: &HashMap<i32, Uuid>,
     network_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
 ) -> Result<u64> {
     let addresses_iter = address
         .into_iter()
@@ -336,25 +400,26 @@ pub async fn insert_ip_addresses(
             _ => {
                 tracing::warn!(ip_address_id = ?field.id, network_id = ?field.pool_id,
                     "Skipping ip address with non-migrated network." );
+                skipped.push(SkippedRow::new(
+                    DashboardTable::IpAddresses,
+                    field.id,
+                    format!("Network {} was not migrated", field.pool_id),
+                ));
                 None
             }
         })
-        .map(|(ip_address, network_uuid, server_id, id)| {
-            let server_uuid = server_id
-                .map(|unsigned_id| unsigned_id as i32)
-                .and_then(|id| server_map.get(&id).copied());
-            (ip_address, network_uuid, server_uuid, id)
-        });
-
-    Ok(unnest_insert!(
-        addresses_iter => ip_addresses => tx,
-        [
-            (1, 0, ip_address, text),
-            (2, 1, network_id, uuid),
-            (3, 2, server_id, uuid),
-            (4, 3, whmcs_id, int4),
-        ]
-    )?)
+        .map(
+            |(ip_address, network_uuid, server_id, id)| types::IpAddressRow {
+                ip_address,
+                network_id: network_uuid,
+                server_id: server_id
+                    .map(|unsigned_id| unsigned_id as i32)
+                    .and_then(|id| server_map.get(&id).copied()),
+                whmcs_id: id,
+            },
+        );
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, addresses_iter).await?)
 }
 
 /// Helper function to bulk insert templates into the target database.
@@ -362,6 +427,7 @@ pub async fn insert_ip_addresses(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `temp_fields`: Vector of `whmcs::TemplateFields` structs to be inserted.
 ///
 /// # Returns
@@ -370,22 +436,14 @@ pub async fn insert_ip_addresses(
 ///
 pub async fn insert_templates(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     temp_fields: Vec<types::TemplateField>,
 ) -> Result<u64> {
     let templates = temp_fields
         .into_iter()
-        .flat_map(types::TemplateField::extract)
-        .collect::<Vec<_>>();
+        .flat_map(types::TemplateField::extract);
 
-    Ok(unnest_insert!(
-        templates.into_iter() => templates => tx,
-        [
-            (1, os_name, os_name, text),
-            (2, template_vmid, template_vmid, int4),
-            (3, template_node, template_node, text),
-            (4, virtual_type, virtual_type, text),
-        ]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, templates).await?)
 }
 
 /// Helper function to bulk insert services into the target database.
@@ -393,11 +451,13 @@ pub async fn insert_templates(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `services`: Vector of `whmcs::Service` structs to be inserted.
 /// * `user_map`: WHMCS ID to Dashboard UUID relationship for users.
 /// * `server_map`: WHMCS ID to Dashboard UUID relationship for servers.
 /// * `product_map`: WHMCS ID to Dashboard UUID relationship for products.
 /// * `template_map`: WHMCS ID to Dashboard UUID relationship for templates.
+/// * `skipped`: Collects the rows left out for a missing reference.
 ///
 /// # Returns
 ///
@@ -405,39 +465,71 @@ pub async fn insert_templates(
 ///
 pub async fn insert_services(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     services: Vec<types::Service>,
     user_map: &HashMap<i32, Uuid>,
     server_map: &HashMap<i32, Uuid>,
     product_map: &HashMap<i32, Uuid>,
     template_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
 ) -> Result<u64> {
+    // Services belong to the personal organization of their owner.
+    let user_ids = services
+        .iter()
+        .filter_map(|service| user_map.get(&service.userid).copied())
+        .collect::<Vec<_>>();
+    let organizations = sqlx::query!(
+        r#"
+SELECT personal_user_id AS "user_id!", id
+FROM organizations
+WHERE personal_user_id = ANY($1)
+        "#,
+        &user_ids
+    )
+    .fetch_all(tx.as_mut())
+    .await?
+    .into_iter()
+    .map(|organization| (organization.user_id, organization.id))
+    .collect::<HashMap<_, _>>();
+
     let iter = services.into_iter().filter_map(|service| {
-        let user_uuid = *user_map.get(&service.userid)?;
-        let product_uuid = *product_map.get(&service.packageid)?;
-        let server_uuid = *server_map.get(&service.id)?;
-        let template_uuid = *template_map.get(&service.packageid)?;
-
-        Some((
-            service.domainstatus,
-            user_uuid,
-            server_uuid,
-            product_uuid,
-            template_uuid,
+        let references = (
+            user_map.get(&service.userid),
+            product_map.get(&service.packageid),
+            server_map.get(&service.id),
+            template_map.get(&service.packageid),
+        );
+        let reason = match references {
+            (Some(user_uuid), Some(product_uuid), Some(server_uuid), Some(template_uuid)) => {
+                match organizations.get(user_uuid) {
+                    Some(organization_uuid) => {
+                        return Some(types::ServiceRow {
+                            status: service.domainstatus,
+                            user_id: *user_uuid,
+                            organization_id: *organization_uuid,
+                            server_id: *server_uuid,
+                            product_id: *product_uuid,
+                            template_id: *template_uuid,
+                            whmcs_id: service.id,
+                        });
+                    }
+                    None => format!("User {} has no personal organization", service.userid),
+                }
+            }
+            (None, ..) => format!("User {} was not migrated", service.userid),
+            (_, None, ..) => format!("Product {} was not migrated", service.packageid),
+            (_, _, None, _) => format!("Server of service {} was not migrated", service.id),
+            _ => format!("Product {} has no migrated template", service.packageid),
+        };
+        skipped.push(SkippedRow::new(
+            DashboardTable::Services,
             service.id,
-        ))
+            reason,
+        ));
+        None
     });
 
-    Ok(unnest_insert!(
-        iter => services => tx,
-        [
-            (1, 0, status, text),
-            (2, 1, user_id, uuid),
-            (3, 2, server_id, uuid),
-            (4, 3, product_id, uuid),
-            (5, 4, template_id, uuid),
-            (6, 5, whmcs_id, int4),
-        ]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
 }
 
 /// Helper function to bulk insert services into the target database.
@@ -445,9 +537,11 @@ pub async fn insert_services(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `values`: Vector of `whmcs::CustomValue` structs to be inserted.
 /// * `service_map`: WHMCS ID to Dashboard UUID relationship for services.
 /// * `custom_map`: WHMCS ID to Dashboard UUID relationship for custom fields.
+/// * `skipped`: Collects the rows left out for a missing reference.
 ///
 /// # Returns
 ///
@@ -455,26 +549,37 @@ pub async fn insert_services(
 ///
 pub async fn insert_custom_values(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     values: Vec<types::CustomValue>,
     service_map: &HashMap<i32, Uuid>,
     custom_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
 ) -> Result<u64> {
     let iter = values.into_iter().filter_map(|value| {
-        let service_uuid = *service_map.get(&value.relid)?;
-        let config_uuid = *custom_map.get(&value.fieldid)?;
-        let whmcs_id = value.id as i32;
-        Some((service_uuid, config_uuid, value.value, whmcs_id))
+        let reason = match (
+            service_map.get(&value.relid),
+            custom_map.get(&value.fieldid),
+        ) {
+            (Some(service_uuid), Some(config_uuid)) => {
+                return Some(types::CustomValueRow {
+                    service_id: *service_uuid,
+                    custom_field_id: *config_uuid,
+                    value: value.value,
+                    whmcs_id: value.id as i32,
+                });
+            }
+            (None, _) => format!("Service {} was not migrated", value.relid),
+            _ => format!("Custom field {} was not migrated", value.fieldid),
+        };
+        skipped.push(SkippedRow::new(
+            DashboardTable::CustomValues,
+            value.id,
+            reason,
+        ));
+        None
     });
 
-    Ok(unnest_insert!(
-        iter => custom_values => tx,
-        [
-            (1, 0, service_id, uuid),
-            (2, 1, custom_field_id, uuid),
-            (3, 2, value, text),
-            (4, 3, whmcs_id, int4),
-        ]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
 }
 
 /// Helper function to bulk insert services into the target database.
@@ -482,9 +587,11 @@ pub async fn insert_custom_values(
 /// # Arguments
 ///
 /// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
 /// * `values`: Vector of `whmcs::ConfigValue` structs to be inserted.
 /// * `service_map`: WHMCS ID to Dashboard UUID relationship for services.
 /// * `config_map`: WHMCS ID to Dashboard UUID relationship for config options.
+/// * `skipped`: Collects the rows left out for a missing reference.
 ///
 /// # Returns
 ///
@@ -492,30 +599,310 @@ pub async fn insert_custom_values(
 ///
 pub async fn insert_config_values(
     tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
     values: Vec<types::ConfigValue>,
     service_map: &HashMap<i32, Uuid>,
     config_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
 ) -> Result<u64> {
     let iter = values.into_iter().filter_map(|value| {
-        let service_uuid = *service_map.get(&value.relid)?;
-        let config_uuid = *config_map.get(&value.configid)?;
-        let name = value
-            .optionname
-            .chars()
-            .take_while(|char| char.is_numeric())
-            .collect::<String>();
-        Some((service_uuid, config_uuid, name, value.id))
+        let reason = match (
+            service_map.get(&value.relid),
+            config_map.get(&value.configid),
+        ) {
+            (Some(service_uuid), Some(config_uuid)) => {
+                let name = value
+                    .optionname
+                    .chars()
+                    .take_while(|char| char.is_numeric())
+                    .collect::<String>();
+                return Some(types::ConfigValueRow {
+                    service_id: *service_uuid,
+                    config_id: *config_uuid,
+                    value: name,
+                    whmcs_id: value.id,
+                });
+            }
+            (None, _) => format!("Service {} was not migrated", value.relid),
+            _ => format!("Config option {} was not migrated", value.configid),
+        };
+        skipped.push(SkippedRow::new(
+            DashboardTable::ConfigValues,
+            value.id,
+            reason,
+        ));
+        None
+    });
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
+}
+
+/// Helper function to bulk insert invoices into the target database.
+///
+/// # Arguments
+///
+/// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
+/// * `invoices`: Vector of `whmcs::Invoice` structs to be inserted.
+/// * `user_map`: WHMCS ID to Dashboard UUID relationship for users.
+/// * `skipped`: Collects the rows left out for a missing reference.
+///
+/// # Returns
+///
+/// On success, the number of affected rows.
+///
+pub async fn insert_invoices(
+    tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
+    invoices: Vec<types::Invoice>,
+    user_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
+) -> Result<u64> {
+    let iter = invoices.into_iter().filter_map(|invoice| {
+        let reason = match user_map.get(&invoice.userid) {
+            Some(_) if invoice.amount_cents < 0 => "Invoice total is negative".to_owned(),
+            Some(user_uuid) => {
+                return Some(types::InvoiceRow {
+                    user_id: *user_uuid,
+                    description: invoice.description(),
+                    amount_cents: invoice.amount_cents,
+                    credit_cents: invoice.credit_cents(),
+                    currency: invoice.currency.clone(),
+                    status: invoice.dashboard_status().to_owned(),
+                    created_at: invoice.created_at.and_utc(),
+                    paid_at: invoice.paid_at.map(|paid_at| paid_at.and_utc()),
+                    whmcs_id: invoice.id,
+                });
+            }
+            None => format!("User {} was not migrated", invoice.userid),
+        };
+        skipped.push(SkippedRow::new(
+            DashboardTable::Invoices,
+            invoice.id,
+            reason,
+        ));
+        None
+    });
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
+}
+
+/// Helper function to bulk insert invoice lines into the target database.
+/// Lines billing a service are linked to it if it was migrated.
+///
+/// # Arguments
+///
+/// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
+/// * `items`: Vector of `whmcs::InvoiceItem` structs to be inserted.
+/// * `invoice_map`: WHMCS ID to Dashboard UUID relationship for invoices.
+/// * `service_map`: WHMCS ID to Dashboard UUID relationship for services.
+/// * `skipped`: Collects the rows left out for a missing reference.
+///
+/// # Returns
+///
+/// On success, the number of affected rows.
+///
+pub async fn insert_invoice_items(
+    tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
+    items: Vec<types::InvoiceItem>,
+    invoice_map: &HashMap<i32, Uuid>,
+    service_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
+) -> Result<u64> {
+    let iter = items.into_iter().filter_map(|item| {
+        let Some(invoice_uuid) = invoice_map.get(&item.invoiceid) else {
+            let reason = format!("Invoice {} was not migrated", item.invoiceid);
+            skipped.push(SkippedRow::new(
+                DashboardTable::InvoiceItems,
+                item.id,
+                reason,
+            ));
+            return None;
+        };
+        let service_uuid = match item.item_type.as_str() {
+            "Hosting" => service_map.get(&item.relid).copied(),
+            _ => None,
+        };
+        Some(types::InvoiceItemRow {
+            invoice_id: *invoice_uuid,
+            service_id: service_uuid,
+            description: item.description,
+            amount_cents: item.amount_cents,
+            whmcs_id: item.id,
+        })
+    });
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
+}
+
+/// Helper function to bulk insert the payments of invoices into the target
+/// database, as succeeded payments of their WHMCS gateway. A payment whose
+/// gateway reference is already recorded for another payment is skipped, as
+/// the reference is unique.
+///
+/// # Arguments
+///
+/// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
+/// * `transactions`: Vector of `whmcs::Transaction` structs to be inserted.
+/// * `invoice_map`: WHMCS ID to Dashboard UUID relationship for invoices.
+/// * `skipped`: Collects the rows left out for a missing reference.
+///
+/// # Returns
+///
+/// On success, the number of affected rows.
+///
+pub async fn insert_payments(
+    tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
+    transactions: Vec<types::Transaction>,
+    invoice_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
+) -> Result<u64> {
+    // WHMCS ids of the payments owning the references, `None` for Dashboard
+    // payments.
+    let (providers, references) = transactions
+        .iter()
+        .map(|transaction| (transaction.gateway.clone(), transaction.reference.clone()))
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+    let mut owners = sqlx::query!(
+        r#"
+SELECT p.provider, p.provider_ref, p.whmcs_id
+FROM payments AS p
+JOIN UNNEST($1::TEXT[], $2::TEXT[]) AS source (provider, provider_ref)
+	ON source.provider = p.provider AND source.provider_ref = p.provider_ref
+        "#,
+        &providers,
+        &references
+    )
+    .fetch_all(tx.as_mut())
+    .await?
+    .into_iter()
+    .map(|payment| ((payment.provider, payment.provider_ref), payment.whmcs_id))
+    .collect::<HashMap<_, _>>();
+
+    let iter = transactions.into_iter().filter_map(|transaction| {
+        let key = (transaction.gateway.clone(), transaction.reference.clone());
+        let reason = match (invoice_map.get(&transaction.invoiceid), owners.get(&key)) {
+            (None, _) => format!("Invoice {} was not migrated", transaction.invoiceid),
+            (Some(_), Some(owner)) if *owner != Some(transaction.id) => format!(
+                "Reference {} of {} is already recorded",
+                transaction.reference, transaction.gateway
+            ),
+            (Some(invoice_uuid), _) => {
+                owners.insert(key, Some(transaction.id));
+                return Some(types::PaymentRow {
+                    invoice_id: *invoice_uuid,
+                    provider: transaction.gateway,
+                    provider_ref: transaction.reference,
+                    amount_cents: transaction.amount_cents,
+                    status: "Succeeded".to_owned(),
+                    created_at: transaction.date.and_utc(),
+                    whmcs_id: transaction.id,
+                });
+            }
+        };
+        skipped.push(SkippedRow::new(
+            DashboardTable::Payments,
+            transaction.id,
+            reason,
+        ));
+        None
+    });
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
+}
+
+/// Helper function to bulk insert support tickets into the target database.
+///
+/// # Arguments
+///
+/// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
+/// * `tickets`: Vector of `whmcs::Ticket` structs to be inserted.
+/// * `user_map`: WHMCS ID to Dashboard UUID relationship for users.
+/// * `skipped`: Collects the rows left out for a missing reference.
+///
+/// # Returns
+///
+/// On success, the number of affected rows.
+///
+pub async fn insert_tickets(
+    tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
+    tickets: Vec<types::Ticket>,
+    user_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
+) -> Result<u64> {
+    let iter = tickets.into_iter().filter_map(|ticket| {
+        let Some(user_uuid) = user_map.get(&ticket.userid) else {
+            let reason = format!("User {} was not migrated", ticket.userid);
+            skipped.push(SkippedRow::new(DashboardTable::Tickets, ticket.id, reason));
+            return None;
+        };
+        let created_at = ticket.date.and_utc();
+        Some(types::TicketRow {
+            user_id: *user_uuid,
+            reference: ticket.tid,
+            subject: ticket.title,
+            message: ticket.message,
+            status: ticket.status,
+            priority: ticket.urgency,
+            created_at,
+            updated_at: ticket
+                .lastreply
+                .map_or(created_at, |lastreply| lastreply.and_utc()),
+            whmcs_id: ticket.id,
+        })
     });
 
-    Ok(unnest_insert!(
-        iter => config_values => tx,
-        [
-            (1, 0, service_id, uuid),
-            (2, 1, config_id, uuid),
-            (3, 2, value, text),
-            (4, 3, whmcs_id, int4),
-        ]
-    )?)
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
+}
+
+/// Helper function to bulk insert ticket replies into the target database.
+///
+/// # Arguments
+///
+/// * `tx`: In-progress transaction for target database.
+/// * `conflict`: Handling of the rows that were already migrated.
+/// * `replies`: Vector of `whmcs::TicketReply` structs to be inserted.
+/// * `ticket_map`: WHMCS ID to Dashboard UUID relationship for tickets.
+/// * `skipped`: Collects the rows left out for a missing reference.
+///
+/// # Returns
+///
+/// On success, the number of affected rows.
+///
+pub async fn insert_ticket_replies(
+    tx: &mut PgTransaction<'_>,
+    conflict: OnConflict,
+    replies: Vec<types::TicketReply>,
+    ticket_map: &HashMap<i32, Uuid>,
+    skipped: &mut Vec<SkippedRow>,
+) -> Result<u64> {
+    let iter = replies.into_iter().filter_map(|reply| {
+        let Some(ticket_uuid) = ticket_map.get(&reply.tid) else {
+            let reason = format!("Ticket {} was not migrated", reply.tid);
+            skipped.push(SkippedRow::new(
+                DashboardTable::TicketReplies,
+                reply.id,
+                reason,
+            ));
+            return None;
+        };
+        Some(types::TicketReplyRow {
+            ticket_id: *ticket_uuid,
+            author: reply.author(),
+            from_staff: reply.from_staff(),
+            message: reply.message,
+            created_at: reply.date.and_utc(),
+            whmcs_id: reply.id,
+        })
+    });
+
+    Ok(bulk::bulk_insert(tx.as_mut(), conflict, iter).await?)
 }
 
 // -----------------------------------------------------------------------------
@@ -545,16 +932,177 @@ mod tests {
         let mut tx = pool.begin().await.unwrap();
 
         // Act
-        let affected_rows = insert_users(&mut tx, clients).await.unwrap();
+        let affected_rows = insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            clients,
+            DuplicateEmails::Skip,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
 
         // Assert
         let user = sqlx::query!("SELECT email FROM users WHERE whmcs_id = 1")
             .fetch_one(tx.as_mut())
             .await
             .unwrap();
+        let owners = sqlx::query!(
+            r#"
+SELECT m.role
+FROM organization_members AS m
+JOIN organizations AS o ON o.id = m.organization_id AND o.personal_user_id = m.user_id
+JOIN users AS u ON u.id = m.user_id
+WHERE u.whmcs_id = 1
+            "#
+        )
+        .fetch_all(tx.as_mut())
+        .await
+        .unwrap();
 
         assert_eq!(affected_rows, 1);
         assert_eq!(user.email, "john.doe@example.com");
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].role, "Owner");
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn merge_should_alias_clients_sharing_an_email(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user_map = helpers::populate_users(&mut tx).await;
+        let clients = vec![
+            helpers::client(3, "john.doe@example.com"),
+            helpers::client(4, "new@example.com"),
+            helpers::client(5, "new@example.com"),
+        ];
+
+        // Act
+        let affected_rows = insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            clients,
+            DuplicateEmails::Merge,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let aliases = sqlx::query!(
+            r#"
+SELECT a.whmcs_id, a.user_id, u.whmcs_id AS owner
+FROM user_aliases AS a
+JOIN users AS u ON u.id = a.user_id
+ORDER BY a.whmcs_id
+            "#
+        )
+        .fetch_all(tx.as_mut())
+        .await
+        .unwrap();
+
+        assert_eq!(affected_rows, 1);
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases[0].whmcs_id, 3);
+        assert_eq!(aliases[0].user_id, user_map[&1]);
+        assert_eq!(aliases[1].whmcs_id, 5);
+        assert_eq!(aliases[1].owner, Some(4));
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn suffix_and_skip_should_handle_clients_sharing_an_email(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        helpers::populate_users(&mut tx).await;
+        let mut skipped = Vec::new();
+
+        // Act
+        let suffixed = insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            vec![helpers::client(3, "john.doe@example.com")],
+            DuplicateEmails::Suffix,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+        let kept = insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            vec![helpers::client(4, "jane.doe@example.com")],
+            DuplicateEmails::Skip,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let user = sqlx::query!("SELECT email FROM users WHERE whmcs_id = 3")
+            .fetch_one(tx.as_mut())
+            .await
+            .unwrap();
+
+        assert_eq!((suffixed, kept), (1, 0));
+        assert_eq!(user.email, "john.doe+whmcs3@example.com");
+        assert_eq!(
+            skipped,
+            vec![SkippedRow::new(
+                DashboardTable::Users,
+                4,
+                "Email jane.doe@example.com is used by client 2"
+            )]
+        );
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn duplicates_should_be_found_regardless_of_case(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        helpers::populate_users(&mut tx).await;
+        insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            vec![helpers::client(3, "Jane.Doe+whmcs4@Example.com")],
+            DuplicateEmails::Skip,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
+        let mut skipped = Vec::new();
+
+        // Act
+        let affected_rows = insert_users(
+            &mut tx,
+            OnConflict::Skip,
+            vec![
+                helpers::client(4, "JANE.DOE@example.com"),
+                helpers::client(5, "John.Doe@Example.com"),
+            ],
+            DuplicateEmails::Suffix,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let users = sqlx::query!("SELECT whmcs_id, email FROM users WHERE whmcs_id > 3")
+            .fetch_all(tx.as_mut())
+            .await
+            .unwrap();
+
+        assert_eq!(affected_rows, 1);
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].whmcs_id, Some(5));
+        assert_eq!(users[0].email, "John.Doe+whmcs5@Example.com");
+        assert_eq!(
+            skipped,
+            vec![SkippedRow::new(
+                DashboardTable::Users,
+                4,
+                "Email JANE.DOE@example.com is used by client 2 and \
+                 JANE.DOE+whmcs4@example.com by client 3"
+            )]
+        );
     }
 
     #[sqlx::test(migrations = "../../migrations")]
@@ -567,7 +1115,9 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_product_groups(&mut tx, groups).await.unwrap();
+        let affected_rows = insert_product_groups(&mut tx, OnConflict::Skip, groups)
+            .await
+            .unwrap();
 
         // Assert
         let inserted_groups = sqlx::query!("SELECT whmcs_id, name FROM product_groups")
@@ -585,6 +1135,41 @@ mod tests {
         assert_eq!(inserted_groups[1].name, "Group2");
     }
 
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn update_on_conflict_should_refresh_migrated_rows(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let groups = vec![ProductGroup::new(1, "Group1")];
+        insert_product_groups(&mut tx, OnConflict::Skip, groups)
+            .await
+            .unwrap();
+        let changed = vec![
+            ProductGroup::new(1, "Renamed"),
+            ProductGroup::new(1, "Renamed"),
+            ProductGroup::new(2, "Group2"),
+        ];
+
+        // Act
+        let skipped = insert_product_groups(&mut tx, OnConflict::Skip, changed.clone())
+            .await
+            .unwrap();
+        let updated = insert_product_groups(&mut tx, OnConflict::Update, changed)
+            .await
+            .unwrap();
+
+        // Assert
+        let groups = sqlx::query!("SELECT whmcs_id, name FROM product_groups ORDER BY whmcs_id")
+            .fetch_all(tx.as_mut())
+            .await
+            .unwrap();
+
+        assert_eq!(skipped, 1);
+        assert_eq!(updated, 2);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "Renamed");
+        assert_eq!(groups[1].name, "Group2");
+    }
+
     #[sqlx::test(migrations = "../../migrations")]
     async fn insert_products_works(pool: PgPool) {
         // Arrange
@@ -596,9 +1181,15 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_products(&mut tx, products, &group_map)
-            .await
-            .unwrap();
+        let affected_rows = insert_products(
+            &mut tx,
+            OnConflict::Skip,
+            products,
+            &group_map,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
 
         // Assert
         let all_products = sqlx::query!("SELECT name, group_id FROM products")
@@ -623,11 +1214,18 @@ mod tests {
             types::CustomField::new(1, "Field1", 1),
             types::CustomField::new(2, "Field2", 999),
         ];
+        let mut skipped = Vec::new();
 
         // Act
-        let affected_rows = insert_custom_fields(&mut tx, fields, &product_map)
-            .await
-            .unwrap();
+        let affected_rows = insert_custom_fields(
+            &mut tx,
+            OnConflict::Skip,
+            fields,
+            &product_map,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
 
         // Assert
         let all_fields = sqlx::query!("SELECT name, product_id FROM custom_fields")
@@ -640,6 +1238,14 @@ mod tests {
 
         assert_eq!(all_fields[0].name, "Field1");
         assert_eq!(all_fields[0].product_id, product_map[&1]);
+        assert_eq!(
+            skipped,
+            vec![SkippedRow::new(
+                DashboardTable::CustomFields,
+                2,
+                "Product 999 was not migrated"
+            )]
+        );
     }
 
     #[sqlx::test(migrations = "../../migrations")]
@@ -652,11 +1258,15 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_config_options(&mut tx, options).await.unwrap();
+        let affected_rows = insert_config_options(&mut tx, OnConflict::Skip, options)
+            .await
+            .unwrap();
 
         // Assert
         let inserted_options =
-            sqlx::query!("SELECT whmcs_id as id, name FROM config_options ORDER BY whmcs_id")
+            sqlx::query!(
+                "SELECT whmcs_id as id, name FROM config_options WHERE whmcs_id IS NOT NULL ORDER BY whmcs_id"
+            )
                 .fetch_all(tx.as_mut())
                 .await
                 .unwrap();
@@ -681,7 +1291,9 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_servers(&mut tx, vm_records).await.unwrap();
+        let affected_rows = insert_servers(&mut tx, OnConflict::Skip, vm_records)
+            .await
+            .unwrap();
 
         // Assert
         let servers = sqlx::query!(
@@ -717,7 +1329,9 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_networks(&mut tx, networks).await.unwrap();
+        let affected_rows = insert_networks(&mut tx, OnConflict::Skip, networks)
+            .await
+            .unwrap();
 
         // Assert
         let inserted_networks = sqlx::query!(
@@ -754,9 +1368,16 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_ip_addresses(&mut tx, addresses, &server_map, &network_map)
-            .await
-            .unwrap();
+        let affected_rows = insert_ip_addresses(
+            &mut tx,
+            OnConflict::Skip,
+            addresses,
+            &server_map,
+            &network_map,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
 
         // Assert
         let inserted_ips = sqlx::query!(
@@ -789,7 +1410,9 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_templates(&mut tx, template_fields).await.unwrap();
+        let affected_rows = insert_templates(&mut tx, OnConflict::Skip, template_fields)
+            .await
+            .unwrap();
 
         // Assert
         let templates = sqlx::query!(
@@ -831,18 +1454,25 @@ mod tests {
         // Act
         let affected_rows = insert_services(
             &mut tx,
+            OnConflict::Skip,
             services,
             &user_map,
             &server_map,
             &product_map,
             &template_map,
+            &mut Vec::new(),
         )
         .await
         .unwrap();
 
         // Assert
         let inserted_services = sqlx::query!(
-            "SELECT user_id, server_id, product_id, template_id FROM services ORDER BY whmcs_id"
+            r#"
+SELECT s.user_id, s.server_id, s.product_id, s.template_id, o.personal_user_id
+FROM services AS s
+JOIN organizations AS o ON o.id = s.organization_id
+ORDER BY s.whmcs_id
+            "#
         )
         .fetch_all(tx.as_mut())
         .await
@@ -852,6 +1482,7 @@ mod tests {
         assert_eq!(inserted_services.len(), 2);
 
         assert_eq!(inserted_services[0].user_id, user_map[&1]);
+        assert_eq!(inserted_services[0].personal_user_id, Some(user_map[&1]));
         assert_eq!(inserted_services[0].server_id, server_map[&1]);
         assert_eq!(inserted_services[0].product_id, product_map[&1]);
         assert_eq!(inserted_services[0].template_id, template_map[&1]);
@@ -862,6 +1493,50 @@ mod tests {
         assert_eq!(inserted_services[1].template_id, template_map[&2]);
     }
 
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn update_should_keep_columns_edited_in_dashboard(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user_map = helpers::populate_users(&mut tx).await;
+        let server_map = helpers::populate_servers(&mut tx).await;
+        let groups_map = helpers::populate_product_groups(&mut tx).await;
+        let product_map = helpers::populate_products(&mut tx, &groups_map).await;
+        let template_map = helpers::populate_templates(&mut tx, &product_map).await;
+        helpers::populate_services(&mut tx, &user_map, &server_map, &product_map, &template_map)
+            .await;
+        sqlx::query!(
+            "UPDATE services SET user_id = $1 WHERE whmcs_id = 1",
+            user_map[&2]
+        )
+        .execute(tx.as_mut())
+        .await
+        .unwrap();
+
+        // Act
+        let affected_rows = insert_services(
+            &mut tx,
+            OnConflict::Update,
+            vec![types::Service::new(1, "Suspended", 1, 1)],
+            &user_map,
+            &server_map,
+            &product_map,
+            &template_map,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let service = sqlx::query!("SELECT status, user_id FROM services WHERE whmcs_id = 1")
+            .fetch_one(tx.as_mut())
+            .await
+            .unwrap();
+
+        assert_eq!(affected_rows, 1);
+        assert_eq!(service.status, "Suspended");
+        assert_eq!(service.user_id, user_map[&2]);
+    }
+
     #[sqlx::test(migrations = "../../migrations")]
     async fn insert_custom_values_works(pool: PgPool) {
         // Arrange
@@ -887,9 +1562,16 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_custom_values(&mut tx, values, &service_map, &custom_map)
-            .await
-            .unwrap();
+        let affected_rows = insert_custom_values(
+            &mut tx,
+            OnConflict::Skip,
+            values,
+            &service_map,
+            &custom_map,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
 
         // Assert
         let inserted_values = sqlx::query!(
@@ -936,9 +1618,16 @@ mod tests {
         ];
 
         // Act
-        let affected_rows = insert_config_values(&mut tx, values, &service_map, &config_map)
-            .await
-            .unwrap();
+        let affected_rows = insert_config_values(
+            &mut tx,
+            OnConflict::Skip,
+            values,
+            &service_map,
+            &config_map,
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap();
 
         // Assert
         let inserted_values =
@@ -965,6 +1654,238 @@ mod tests {
         assert_eq!(cpu_val.service_id, service_map[&2]);
     }
 
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn insert_billing_history_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user_map = helpers::populate_users(&mut tx).await;
+        let invoices = vec![
+            types::Invoice::new(10, 1, "Paid", 1250),
+            types::Invoice::new(11, 2, "Refunded", 500),
+            types::Invoice::new(12, 999, "Unpaid", 700),
+        ];
+        let items = vec![
+            types::InvoiceItem::new(20, 10, "Hosting", 1, 1500),
+            types::InvoiceItem::new(21, 10, "PromoHosting", 1, -250),
+        ];
+        let transactions = vec![
+            types::Transaction::new(30, 10, "stripe", "ch_1", 1250),
+            types::Transaction::new(31, 12, "paypal", "whmcs-31", 700),
+        ];
+        let mut skipped = Vec::new();
+
+        // Act
+        let invoice_rows =
+            insert_invoices(&mut tx, OnConflict::Skip, invoices, &user_map, &mut skipped)
+                .await
+                .unwrap();
+        let invoice_map = sqlx::query!(r#"SELECT whmcs_id AS "whmcs_id!", id FROM invoices"#)
+            .fetch_all(tx.as_mut())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|invoice| (invoice.whmcs_id, invoice.id))
+            .collect::<HashMap<_, _>>();
+        let item_rows = insert_invoice_items(
+            &mut tx,
+            OnConflict::Skip,
+            items,
+            &invoice_map,
+            &HashMap::new(),
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+        let payment_rows = insert_payments(
+            &mut tx,
+            OnConflict::Skip,
+            transactions,
+            &invoice_map,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let paid = sqlx::query!(
+            r#"
+SELECT i.status, i.amount_cents, p.provider, p.provider_ref
+FROM invoices AS i
+JOIN payments AS p ON p.invoice_id = i.id
+WHERE i.whmcs_id = 10
+            "#
+        )
+        .fetch_one(tx.as_mut())
+        .await
+        .unwrap();
+
+        assert_eq!((invoice_rows, item_rows, payment_rows), (2, 2, 1));
+        assert_eq!(paid.status, "Paid");
+        assert_eq!(paid.amount_cents, 1250);
+        assert_eq!(
+            (paid.provider.as_str(), paid.provider_ref.as_str()),
+            ("stripe", "ch_1")
+        );
+        assert_eq!(
+            skipped,
+            vec![
+                SkippedRow::new(DashboardTable::Invoices, 12, "User 999 was not migrated"),
+                SkippedRow::new(DashboardTable::Payments, 31, "Invoice 12 was not migrated"),
+            ]
+        );
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn insert_payments_should_skip_recorded_references(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user_map = helpers::populate_users(&mut tx).await;
+        let mut partial = types::Invoice::new(13, 1, "Unpaid", 1000);
+        partial.paid_cents = 400;
+        let mut skipped = Vec::new();
+        insert_invoices(
+            &mut tx,
+            OnConflict::Skip,
+            vec![partial],
+            &user_map,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+        let invoice_map = sqlx::query!(r#"SELECT whmcs_id AS "whmcs_id!", id FROM invoices"#)
+            .fetch_all(tx.as_mut())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|invoice| (invoice.whmcs_id, invoice.id))
+            .collect::<HashMap<_, _>>();
+        let first = types::Transaction::new(30, 13, "stripe", "ch_1", 400);
+        insert_payments(
+            &mut tx,
+            OnConflict::Update,
+            vec![first.clone()],
+            &invoice_map,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+
+        // Act
+        let payment_rows = insert_payments(
+            &mut tx,
+            OnConflict::Update,
+            vec![
+                first,
+                types::Transaction::new(32, 13, "stripe", "ch_1", 400),
+            ],
+            &invoice_map,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let invoice = sqlx::query!(
+            "SELECT status, amount_cents, credit_cents FROM invoices WHERE whmcs_id = 13"
+        )
+        .fetch_one(tx.as_mut())
+        .await
+        .unwrap();
+
+        assert_eq!(payment_rows, 1);
+        assert_eq!(
+            (
+                invoice.status.as_str(),
+                invoice.amount_cents,
+                invoice.credit_cents
+            ),
+            ("Unpaid", 1000, 400)
+        );
+        assert_eq!(
+            skipped,
+            vec![SkippedRow::new(
+                DashboardTable::Payments,
+                32,
+                "Reference ch_1 of stripe is already recorded"
+            )]
+        );
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn insert_tickets_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user_map = helpers::populate_users(&mut tx).await;
+        let tickets = vec![
+            types::Ticket::new(1, "ABC-123456", 2, "Server is down"),
+            types::Ticket::new(2, "DEF-654321", 999, "Refund"),
+        ];
+        let replies = vec![
+            types::TicketReply::new(10, 1, "", "It doesn't boot."),
+            types::TicketReply::new(11, 1, "Support Team", "Restarted it."),
+            types::TicketReply::new(12, 2, "", "Any news?"),
+        ];
+        let mut skipped = Vec::new();
+
+        // Act
+        let ticket_rows =
+            insert_tickets(&mut tx, OnConflict::Skip, tickets, &user_map, &mut skipped)
+                .await
+                .unwrap();
+        let ticket_map = sqlx::query!(r#"SELECT whmcs_id AS "whmcs_id!", id FROM tickets"#)
+            .fetch_all(tx.as_mut())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|ticket| (ticket.whmcs_id, ticket.id))
+            .collect::<HashMap<_, _>>();
+        let reply_rows = insert_ticket_replies(
+            &mut tx,
+            OnConflict::Skip,
+            replies,
+            &ticket_map,
+            &mut skipped,
+        )
+        .await
+        .unwrap();
+
+        // Assert
+        let thread = sqlx::query!(
+            r#"
+SELECT t.user_id, t.reference, r.author, r.from_staff
+FROM tickets AS t
+JOIN ticket_replies AS r ON r.ticket_id = t.id
+ORDER BY r.whmcs_id
+            "#
+        )
+        .fetch_all(tx.as_mut())
+        .await
+        .unwrap();
+
+        assert_eq!((ticket_rows, reply_rows), (1, 2));
+        assert_eq!(thread[0].user_id, user_map[&2]);
+        assert_eq!(thread[0].reference, "ABC-123456");
+        assert_eq!(
+            (thread[0].author.as_str(), thread[0].from_staff),
+            ("Client", false)
+        );
+        assert_eq!(
+            (thread[1].author.as_str(), thread[1].from_staff),
+            ("Support Team", true)
+        );
+        assert_eq!(
+            skipped,
+            vec![
+                SkippedRow::new(DashboardTable::Tickets, 2, "User 999 was not migrated"),
+                SkippedRow::new(
+                    DashboardTable::TicketReplies,
+                    12,
+                    "Ticket 2 was not migrated"
+                ),
+            ]
+        );
+    }
+
     mod helpers {
         use super::*;
         use crate::etl::types;
@@ -972,6 +1893,22 @@ mod tests {
         use std::collections::HashMap;
         use uuid::Uuid;
 
+        pub fn client(id: i32, email: &str) -> types::Client {
+            types::Client {
+                id,
+                firstname: "John".to_owned(),
+                lastname: "Doe".to_owned(),
+                email: email.to_owned(),
+                address1: "123 Main St".to_owned(),
+                city: "Anytown".to_owned(),
+                state: "CA".to_owned(),
+                postcode: "12345".to_owned(),
+                country: "US".to_owned(),
+                phonenumber: "555-1234".to_owned(),
+                password: "password123".to_owned(),
+            }
+        }
+
         pub async fn populate_users(tx: &mut PgTransaction<'_>) -> HashMap<i32, Uuid> {
             let clients = vec![
                 types::Client {
@@ -1002,7 +1939,15 @@ mod tests {
                 },
             ];
 
-            insert_users(tx, clients).await.unwrap();
+            insert_users(
+                tx,
+                OnConflict::Skip,
+                clients,
+                DuplicateEmails::Skip,
+                &mut Vec::new(),
+            )
+            .await
+            .unwrap();
 
             sqlx::query!("SELECT whmcs_id, id FROM users")
                 .fetch_all(tx.as_mut())
@@ -1018,7 +1963,9 @@ mod tests {
                 ProductGroup::new(1, "Group1"),
                 ProductGroup::new(2, "Group2"),
             ];
-            insert_product_groups(tx, groups).await.unwrap();
+            insert_product_groups(tx, OnConflict::Skip, groups)
+                .await
+                .unwrap();
 
             sqlx::query!("SELECT id, whmcs_id FROM product_groups")
                 .fetch_all(tx.as_mut())
@@ -1037,7 +1984,9 @@ mod tests {
                 types::Product::new(1, 1, "Product1"),
                 types::Product::new(2, 2, "Product2"),
             ];
-            insert_products(tx, products, &group_map).await.unwrap();
+            insert_products(tx, OnConflict::Skip, products, group_map, &mut Vec::new())
+                .await
+                .unwrap();
 
             sqlx::query!("SELECT id, whmcs_id FROM products")
                 .fetch_all(tx.as_mut())
@@ -1056,7 +2005,9 @@ mod tests {
                 types::CustomField::new(10, "CustomField1", 1),
                 types::CustomField::new(11, "CustomField2", 2),
             ];
-            insert_custom_fields(tx, fields, product_map).await.unwrap();
+            insert_custom_fields(tx, OnConflict::Skip, fields, product_map, &mut Vec::new())
+                .await
+                .unwrap();
 
             sqlx::query!("SELECT id, whmcs_id FROM custom_fields")
                 .fetch_all(tx.as_mut())
@@ -1072,9 +2023,11 @@ mod tests {
                 types::ConfigOption::new(1, "RAM"),
                 types::ConfigOption::new(2, "CPU"),
             ];
-            insert_config_options(tx, options).await.unwrap();
+            insert_config_options(tx, OnConflict::Skip, options)
+                .await
+                .unwrap();
 
-            sqlx::query!("SELECT id, whmcs_id FROM config_options")
+            sqlx::query!("SELECT id, whmcs_id FROM config_options WHERE whmcs_id IS NOT NULL")
                 .fetch_all(tx.as_mut())
                 .await
                 .unwrap()
@@ -1088,7 +2041,9 @@ mod tests {
                 types::VmRecord::new(1, 101, Some("pve1"), "server1.test.com", "Active"),
                 types::VmRecord::new(2, 102, None, "server2.test.com", "Active"),
             ];
-            insert_servers(tx, vm_records).await.unwrap();
+            insert_servers(tx, OnConflict::Skip, vm_records)
+                .await
+                .unwrap();
 
             sqlx::query!("SELECT id, whmcs_id FROM servers")
                 .fetch_all(tx.as_mut())
@@ -1104,7 +2059,9 @@ mod tests {
                 types::Network::new(1, "Pool1", "192.168.1.1", "255.255.255.0"),
                 types::Network::new(2, "Pool2", "10.0.0.1", "255.0.0.0"),
             ];
-            insert_networks(tx, networks).await.unwrap();
+            insert_networks(tx, OnConflict::Skip, networks)
+                .await
+                .unwrap();
 
             sqlx::query!("SELECT id, whmcs_id FROM networks")
                 .fetch_all(tx.as_mut())
@@ -1123,7 +2080,9 @@ mod tests {
                 1,
                 "Ubuntu 22.04|9000,CentOS 9|9002, Debian 11|9001",
             )];
-            insert_templates(tx, template_fields).await.unwrap();
+            insert_templates(tx, OnConflict::Skip, template_fields)
+                .await
+                .unwrap();
 
             sqlx::query!("SELECT id FROM templates ORDER BY template_vmid")
                 .fetch_all(tx.as_mut())
@@ -1148,11 +2107,13 @@ mod tests {
             ];
             insert_services(
                 tx,
+                OnConflict::Skip,
                 services,
                 user_map,
                 server_map,
                 product_map,
                 template_map,
+                &mut Vec::new(),
             )
             .await
             .unwrap();
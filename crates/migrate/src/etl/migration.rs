@@ -2,107 +2,464 @@
 //! pipeline with overall orchestration of the entire ETL migration process.
 //!
 //! The central `Migration` struct holds the application's state, including the
-//! connection pools for both the source and target databases. The `run` method
-//! ensures that all migration steps are executed in the correct order within a
-//! single atomic transaction.
+//! connection pools for both the source and target databases. The `migrate`
+//! method executes the migration steps in the correct order, committing the
+//! rows as chosen by [`Commit`]:
+//!
+//! - `Commit::Atomic`, the default of `run`, migrates every table in a single
+//!   transaction. A failed run leaves nothing behind, so it is simply run
+//!   again from the start.
+//! - `Commit::EachTable` (`--commit-each-table`) commits every table on its
+//!   own. A failed run keeps the tables before the failed one, and is resumed
+//!   with `resume --from` the failed table, which starts over.
+//! - `Commit::EachChunk` (`--commit-each-chunk`) commits every chunk on its
+//!   own. A failed run also keeps the committed chunks of the failed table, and
+//!   is resumed from that table, where the rows migrated already are skipped
+//!   as conflicts, or updated with `--since`.
+//!
+//! A dry run uses `Commit::Never`, rolling the single transaction back.
 //!
 //! The generic `migrate_table` function implements the "Extract" logic,
-//! streaming data in chunks from the source MySQL database before passing it to
-//! the appropriate loader function from the [`loaders`] module.
+//! streaming records in chunks from the [`source`] of the migration before
+//! passing them to the appropriate loader function from the [`loaders`]
+//! module.
+//!
+//! [`source`]: crate::etl::source
 
-use crate::cli::Cli;
-use crate::etl::loaders;
-use crate::etl::types::{self, DashboardTable};
-use dashboard_common::prelude::Result;
+use crate::cli::Databases;
+use crate::etl::anonymize::Anonymizer;
+use crate::etl::loaders::{self, DuplicateEmails, OnConflict};
+use crate::etl::preflight;
+use crate::etl::progress::Progress;
+use crate::etl::rules::Transformer;
+use crate::etl::source::{Record, Source, SourceRecord, WhmcsSource};
+use crate::etl::types::{self, DashboardTable, SkippedRow};
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::{Error, Result};
 use futures::StreamExt;
-use futures::stream::TryStreamExt;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, SecretString};
 use sqlx::mysql::MySqlPoolOptions;
 use sqlx::postgres::PgPoolOptions;
-use sqlx::{MySqlPool, PgPool, PgTransaction, Row};
+use sqlx::{Connection, PgConnection, PgPool, PgTransaction, Row};
 use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
 /// Holds the context and shared state for the entire migration process.
 ///
 #[derive(Debug, Clone)]
-pub struct Migration {
-    pub source_pool: MySqlPool,
+pub struct Migration<Src = WhmcsSource> {
+    pub source: Src,
     pub target_pool: PgPool,
-    dry_run: bool,
     chunk_size: usize,
+    since: Option<DateTime<Utc>>,
+    commit: Commit,
     statistic: types::Statistic,
+    skipped: Vec<SkippedRow>,
+    max_errors: usize,
+    decode_errors: usize,
+    rules: Arc<Transformer>,
+    duplicates: DuplicateEmails,
+    anonymizer: Arc<Anonymizer>,
+    progress: Progress,
+    buffered_chunks: usize,
+}
+
+/// Chunks extracted ahead of the one being loaded by default.
+pub const DEFAULT_BUFFERED_CHUNKS: usize = 2;
+
+/// Tables in the order they are migrated, each one after the tables it refers
+/// to.
+///
+pub const STEPS: [DashboardTable; 17] = [
+    DashboardTable::Users,
+    DashboardTable::ProductGroups,
+    DashboardTable::Products,
+    DashboardTable::CustomFields,
+    DashboardTable::ConfigOptions,
+    DashboardTable::Servers,
+    DashboardTable::Networks,
+    DashboardTable::IpAddresses,
+    DashboardTable::Templates,
+    DashboardTable::Services,
+    DashboardTable::CustomValues,
+    DashboardTable::ConfigValues,
+    DashboardTable::Invoices,
+    DashboardTable::InvoiceItems,
+    DashboardTable::Payments,
+    DashboardTable::Tickets,
+    DashboardTable::TicketReplies,
+];
+
+/// Defines when the migrated rows are committed.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Commit {
+    /// All tables are committed together at the end.
+    Atomic,
+    /// Every table is committed on its own, so a failed run can be resumed.
+    EachTable,
+    /// Every chunk is committed on its own, keeping the transactions short on
+    /// large tables. A failed run is resumed from the table it stopped at.
+    EachChunk,
+    /// Nothing is committed, the transaction is rolled back at the end.
+    Never,
 }
 
-impl Migration {
-    /// Creates new `Migration` instance.
+impl Migration<WhmcsSource> {
+    /// Creates new `Migration` instance reading from WHMCS.
     ///
     /// # Arguments
     ///
-    /// * `cli`: Parsed command-line arguments.
+    /// * `databases`: URLs of the source and target databases.
+    /// * `chunk_size`: Number of records to process per batch.
     ///
-    pub async fn new(cli: &Cli) -> Result<Self> {
+    pub async fn new(databases: &Databases, chunk_size: usize) -> Result<Self> {
         let source_pool = MySqlPoolOptions::new()
-            .connect(cli.source_url.expose_secret())
+            .connect(databases.required_source_url()?.expose_secret())
             .await?;
+        let migration = Self::with_source(
+            WhmcsSource::new(source_pool),
+            &databases.target_url,
+            chunk_size,
+        )
+        .await?;
+        tracing::info!("Database pools created.");
+
+        Ok(migration)
+    }
+}
+
+impl<Src: Source> Migration<Src> {
+    /// Creates new `Migration` instance reading from the given source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: Source of the migrated records.
+    /// * `target_url`: URL of the target database.
+    /// * `chunk_size`: Number of records to process per batch.
+    ///
+    pub async fn with_source(
+        source: Src,
+        target_url: &SecretString,
+        chunk_size: usize,
+    ) -> Result<Self> {
         let target_pool = PgPoolOptions::new()
-            .connect(cli.target_url.expose_secret())
+            .connect(target_url.expose_secret())
             .await?;
-        tracing::info!("Database pools created.");
 
         Ok(Self {
-            source_pool,
+            source,
             target_pool,
-            dry_run: cli.dry_run,
-            chunk_size: cli.chunk_size,
+            chunk_size,
+            since: None,
+            commit: Commit::Atomic,
             statistic: HashMap::new(),
+            skipped: Vec::new(),
+            max_errors: 0,
+            decode_errors: 0,
+            rules: Arc::default(),
+            duplicates: DuplicateEmails::Skip,
+            anonymizer: Arc::default(),
+            progress: Progress::default(),
+            buffered_chunks: DEFAULT_BUFFERED_CHUNKS,
         })
     }
 
-    /// Runs the complete, ordered migration process.
+    /// Switches to a delta run, which reads only the users, servers and
+    /// services changed in WHMCS since the given time, and updates the rows
+    /// that were already migrated instead of keeping them.
+    ///
+    /// # Arguments
+    ///
+    /// * `since`: Start of the changes to migrate, `None` for a bulk run.
+    ///
+    pub fn with_since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.since = since;
+        self
+    }
+
+    /// Tolerates WHMCS rows that can't be decoded, which are skipped and
+    /// reported instead of failing the migration.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_errors`: Number of rows the run may skip before it fails.
+    ///
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Cleans the users, servers and services with the given rules before
+    /// they are loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules`: Transformer applying the rules.
+    ///
+    pub fn with_rules(mut self, rules: Transformer) -> Self {
+        self.rules = Arc::new(rules);
+        self
+    }
+
+    /// Replaces the personal data of the users and ticket replies with
+    /// pseudonyms before they are loaded, after the cleaning rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `anonymizer`: Anonymizer deriving the pseudonyms.
+    ///
+    pub fn with_anonymizer(mut self, anonymizer: Anonymizer) -> Self {
+        self.anonymizer = Arc::new(anonymizer);
+        self
+    }
+
+    /// Reports the progress of every table and of the whole run.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress`: Progress in the mode to report it.
+    ///
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets the number of chunks extracted ahead of the one being loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunks`: Chunks waiting to be loaded at most, at least one.
+    ///
+    pub fn with_buffered_chunks(mut self, chunks: usize) -> Self {
+        self.buffered_chunks = chunks.max(1);
+        self
+    }
+
+    /// Sets the handling of the clients sharing the email of an earlier one.
+    ///
+    /// # Arguments
+    ///
+    /// * `duplicates`: Strategy for the later clients.
+    ///
+    pub fn with_duplicate_emails(mut self, duplicates: DuplicateEmails) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Takes the rows skipped so far for a missing reference, leaving none
+    /// behind for the next run.
+    ///
+    pub fn take_skipped(&mut self) -> Vec<SkippedRow> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    /// Returns the handling of the rows that were already migrated, which are
+    /// updated by delta runs only.
+    ///
+    fn on_conflict(&self) -> OnConflict {
+        match self.since {
+            Some(_) => OnConflict::Update,
+            None => OnConflict::Skip,
+        }
+    }
+
+    /// Runs the complete, ordered migration process in a single transaction.
     ///
     pub async fn run(&mut self) -> Result<types::Statistic> {
-        let mut transaction = self.target_pool.begin().await?;
-        self.migrate_users(&mut transaction).await?;
-        self.migrate_product_groups(&mut transaction).await?;
-        self.migrate_products(&mut transaction).await?;
-        self.migrate_custom_fields(&mut transaction).await?;
-        self.migrate_config_options(&mut transaction).await?;
-        self.migrate_servers(&mut transaction).await?;
-        self.migrate_networks(&mut transaction).await?;
-        self.migrate_ip_addresses(&mut transaction).await?;
-        self.migrate_templates(&mut transaction).await?;
-        self.migrate_services(&mut transaction).await?;
-        self.migrate_custom_values(&mut transaction).await?;
-        self.migrate_config_values(&mut transaction).await?;
-
-        match self.dry_run {
-            true => transaction.rollback().await?,
-            false => transaction.commit().await?,
+        self.migrate(DashboardTable::Users, Commit::Atomic).await
+    }
+
+    /// Runs the complete migration process and rolls it back.
+    ///
+    /// # Returns
+    ///
+    /// Statistics of the rows that would be inserted.
+    ///
+    pub async fn dry_run(&mut self) -> Result<types::Statistic> {
+        self.migrate(DashboardTable::Users, Commit::Never).await
+    }
+
+    /// Checks that the target database has every Dashboard migration applied
+    /// and that the source has every table and column the migration reads.
+    ///
+    /// # Returns
+    ///
+    /// An error listing every difference, if any.
+    ///
+    pub async fn preflight(&self) -> Result<()> {
+        let mut diff = self.source.check_schema().await?;
+        diff.missing_migrations = preflight::missing_migrations(&self.target_pool).await?;
+        diff.into_result()?;
+        tracing::info!("Database schemas checked.");
+
+        Ok(())
+    }
+
+    /// Runs the ordered migration process from the given table on.
+    ///
+    /// The earlier tables are skipped, the rows they refer to are looked up in
+    /// the target database as usual.
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: First table to migrate.
+    /// * `commit`: When the migrated rows are committed.
+    ///
+    /// # Returns
+    ///
+    /// Statistics of the inserted rows. When a run committing per table or
+    /// per chunk fails, the committed rows and the table to resume from are
+    /// logged before the error is returned.
+    ///
+    pub async fn migrate(
+        &mut self,
+        from: DashboardTable,
+        commit: Commit,
+    ) -> Result<types::Statistic> {
+        let steps = STEPS
+            .into_iter()
+            .skip_while(|table| *table != from)
+            .collect::<Vec<_>>();
+        self.commit = commit;
+        self.decode_errors = 0;
+        self.rules.reset();
+        self.start_progress(&steps).await?;
+
+        match commit {
+            Commit::EachTable | Commit::EachChunk => {
+                // Every table runs on the same connection, so the run never
+                // holds two connections and can't wait on itself with a pool
+                // of one. Committing per chunk, the chunks begin and commit
+                // their own transactions on it.
+                let mut connection = self.target_pool.acquire().await?;
+                for table in steps {
+                    let result = async {
+                        if commit == Commit::EachChunk {
+                            return self.migrate_step(table, &mut connection).await;
+                        }
+                        let mut transaction = connection.begin().await?;
+                        self.migrate_step(table, &mut transaction).await?;
+                        Ok::<_, Error>(transaction.commit().await?)
+                    }
+                    .await;
+
+                    if let Err(error) = result {
+                        let mut committed = std::mem::take(&mut self.statistic);
+                        if commit == Commit::EachTable {
+                            committed.remove(&table);
+                        }
+                        tracing::error!(
+                            resume_from = %table, ?committed, %error,
+                            "Migration stopped, the committed rows are kept."
+                        );
+                        return Err(error);
+                    }
+                    tracing::info!(%table, "Table committed.");
+                }
+            }
+            Commit::Atomic | Commit::Never => {
+                let mut transaction = self.target_pool.begin().await?;
+                for table in steps {
+                    self.migrate_step(table, &mut transaction).await?;
+                }
+                match commit {
+                    Commit::Never => transaction.rollback().await?,
+                    _ => transaction.commit().await?,
+                }
+            }
         }
-        tracing::info!(dry_run = %self.dry_run, statistic = ?self.statistic, "Migration completed.");
+        self.progress.finish();
+        tracing::info!(
+            ?from, ?commit, statistic = ?self.statistic, decode_errors = self.decode_errors,
+            rules = ?self.rules.statistic(),
+            "Migration completed."
+        );
 
         Ok(std::mem::take(&mut self.statistic))
     }
 
+    /// Counts the source rows of the tables to migrate up front, when the
+    /// progress is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps`: Tables to migrate, in order.
+    ///
+    async fn start_progress(&mut self, steps: &[DashboardTable]) -> Result<()> {
+        let mut totals = Vec::with_capacity(steps.len());
+        if self.progress.enabled() {
+            for &table in steps {
+                totals.push((table, self.source.count(table, self.since).await?));
+            }
+        }
+        self.progress.start(totals);
+
+        Ok(())
+    }
+
+    /// Migrates a single table.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: Table to migrate.
+    /// * `connection`: Connection to the target database.
+    ///
+    async fn migrate_step(
+        &mut self,
+        table: DashboardTable,
+        connection: &mut PgConnection,
+    ) -> Result<()> {
+        match table {
+            DashboardTable::Users => self.migrate_users(connection).await,
+            DashboardTable::ProductGroups => self.migrate_product_groups(connection).await,
+            DashboardTable::Products => self.migrate_products(connection).await,
+            DashboardTable::CustomFields => self.migrate_custom_fields(connection).await,
+            DashboardTable::ConfigOptions => self.migrate_config_options(connection).await,
+            DashboardTable::Servers => self.migrate_servers(connection).await,
+            DashboardTable::Networks => self.migrate_networks(connection).await,
+            DashboardTable::IpAddresses => self.migrate_ip_addresses(connection).await,
+            DashboardTable::Templates => self.migrate_templates(connection).await,
+            DashboardTable::Services => self.migrate_services(connection).await,
+            DashboardTable::CustomValues => self.migrate_custom_values(connection).await,
+            DashboardTable::ConfigValues => self.migrate_config_values(connection).await,
+            DashboardTable::Invoices => self.migrate_invoices(connection).await,
+            DashboardTable::InvoiceItems => self.migrate_invoice_items(connection).await,
+            DashboardTable::Payments => self.migrate_payments(connection).await,
+            DashboardTable::Tickets => self.migrate_tickets(connection).await,
+            DashboardTable::TicketReplies => self.migrate_ticket_replies(connection).await,
+        }
+    }
+
     /// Migrates active users from the WHMCS `tblclients` to the `users` table.
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_users(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_users(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let rules = self.rules.clone();
+        let anonymizer = self.anonymizer.clone();
+        let duplicates = self.duplicates;
         self.migrate_table(
-            include_str!("sql/get_active_clients.sql"),
             DashboardTable::Users,
-            tx,
+            connection,
             (),
-            |tx, chunk, _| Box::pin(loaders::insert_users(tx, chunk)),
+            |tx, chunk, _, skipped| {
+                let clients = rules.clean_clients(chunk);
+                let clients = anonymizer.anonymize_clients(clients);
+                Box::pin(loaders::insert_users(
+                    tx, conflict, clients, duplicates, skipped,
+                ))
+            },
         )
         .await
     }
@@ -112,19 +469,19 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_product_groups(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_product_groups(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         self.migrate_table(
-            include_str!("sql/get_product_groups.sql"),
             DashboardTable::ProductGroups,
-            tx,
+            connection,
             (),
-            |tx, chunk, _| Box::pin(loaders::insert_product_groups(tx, chunk)),
+            |tx, chunk, _, _| Box::pin(loaders::insert_product_groups(tx, conflict, chunk)),
         )
         .await
     }
@@ -134,23 +491,25 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_products(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_products(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         let groups_map = self
-            .get_existing_ids(tx, DashboardTable::ProductGroups, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::ProductGroups, "whmcs_id")
             .await?;
 
         self.migrate_table(
-            include_str!("sql/get_products.sql"),
             DashboardTable::Products,
-            tx,
+            connection,
             groups_map,
-            |tx, chunk, map| Box::pin(loaders::insert_products(tx, chunk, map)),
+            |tx, chunk, map, skipped| {
+                Box::pin(loaders::insert_products(tx, conflict, chunk, map, skipped))
+            },
         )
         .await
     }
@@ -160,23 +519,27 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_custom_fields(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_custom_fields(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         let products_map = self
-            .get_existing_ids(tx, DashboardTable::Products, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::Products, "whmcs_id")
             .await?;
 
         self.migrate_table(
-            include_str!("sql/get_custom_fields.sql"),
             DashboardTable::CustomFields,
-            tx,
+            connection,
             products_map,
-            |tx, chunk, prod_map| Box::pin(loaders::insert_custom_fields(tx, chunk, prod_map)),
+            |tx, chunk, prod_map, skipped| {
+                Box::pin(loaders::insert_custom_fields(
+                    tx, conflict, chunk, prod_map, skipped,
+                ))
+            },
         )
         .await
     }
@@ -186,19 +549,19 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_config_options(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_config_options(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         self.migrate_table(
-            include_str!("sql/get_config_options.sql"),
             DashboardTable::ConfigOptions,
-            tx,
+            connection,
             (),
-            |tx, chunk, _| Box::pin(loaders::insert_config_options(tx, chunk)),
+            |tx, chunk, _, _| Box::pin(loaders::insert_config_options(tx, conflict, chunk)),
         )
         .await
     }
@@ -208,19 +571,24 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_servers(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_servers(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let rules = self.rules.clone();
+        let anonymizer = self.anonymizer.clone();
         self.migrate_table(
-            include_str!("sql/get_servers.sql"),
             DashboardTable::Servers,
-            tx,
+            connection,
             (),
-            |tx, chunk, _| Box::pin(loaders::insert_servers(tx, chunk)),
+            |tx, chunk, _, _| {
+                let servers = anonymizer.anonymize_servers(rules.clean_servers(chunk));
+                Box::pin(loaders::insert_servers(tx, conflict, servers))
+            },
         )
         .await
     }
@@ -230,19 +598,19 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_networks(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_networks(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         self.migrate_table(
-            include_str!("sql/get_networks.sql"),
             DashboardTable::Networks,
-            tx,
+            connection,
             (),
-            |tx, chunk, _| Box::pin(loaders::insert_networks(tx, chunk)),
+            |tx, chunk, _, _| Box::pin(loaders::insert_networks(tx, conflict, chunk)),
         )
         .await
     }
@@ -252,27 +620,29 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_ip_addresses(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_ip_addresses(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         let servers_map = self
-            .get_existing_ids(tx, DashboardTable::Servers, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::Servers, "whmcs_id")
             .await?;
         let networks_map = self
-            .get_existing_ids(tx, DashboardTable::Networks, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::Networks, "whmcs_id")
             .await?;
 
         self.migrate_table(
-            include_str!("sql/get_ip_addresses.sql"),
             DashboardTable::IpAddresses,
-            tx,
+            connection,
             (servers_map, networks_map),
-            |tx, chunk, (serv_map, net_map)| {
-                Box::pin(loaders::insert_ip_addresses(tx, chunk, serv_map, net_map))
+            |tx, chunk, (serv_map, net_map), skipped| {
+                Box::pin(loaders::insert_ip_addresses(
+                    tx, conflict, chunk, serv_map, net_map, skipped,
+                ))
             },
         )
         .await
@@ -283,19 +653,19 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_templates(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_templates(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         self.migrate_table(
-            include_str!("sql/get_templates.sql"),
             DashboardTable::Templates,
-            tx,
+            connection,
             (),
-            |tx, chunk, _| Box::pin(loaders::insert_templates(tx, chunk)),
+            |tx, chunk, _, _| Box::pin(loaders::insert_templates(tx, conflict, chunk)),
         )
         .await
     }
@@ -304,32 +674,35 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_services(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
-        let user_map = self
-            .get_existing_ids(tx, DashboardTable::Users, "whmcs_id")
+    async fn migrate_services(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let mut user_map = self
+            .get_existing_ids(connection, DashboardTable::Users, "whmcs_id")
             .await?;
+        user_map.extend(self.get_user_aliases(connection).await?);
         let serv_map = self
-            .get_existing_ids(tx, DashboardTable::Servers, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::Servers, "whmcs_id")
             .await?;
         let prod_map = self
-            .get_existing_ids(tx, DashboardTable::Products, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::Products, "whmcs_id")
             .await?;
-        let temp_map = self.get_template_ids(tx).await?;
+        let temp_map = self.get_template_ids(connection).await?;
+        let rules = self.rules.clone();
 
         self.migrate_table(
-            include_str!("sql/get_services.sql"),
             DashboardTable::Services,
-            tx,
+            connection,
             (user_map, serv_map, prod_map, temp_map),
-            |tx, chunk, (user_map, serv_map, prod_map, temp_map)| {
+            |tx, chunk, (user_map, serv_map, prod_map, temp_map), skipped| {
+                let services = rules.clean_services(chunk);
                 Box::pin(loaders::insert_services(
-                    tx, chunk, user_map, serv_map, prod_map, temp_map,
+                    tx, conflict, services, user_map, serv_map, prod_map, temp_map, skipped,
                 ))
             },
         )
@@ -341,27 +714,29 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_custom_values(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_custom_values(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         let service_map = self
-            .get_existing_ids(tx, DashboardTable::Services, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::Services, "whmcs_id")
             .await?;
         let custom_map = self
-            .get_existing_ids(tx, DashboardTable::CustomFields, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::CustomFields, "whmcs_id")
             .await?;
 
         self.migrate_table(
-            include_str!("sql/get_custom_values.sql"),
             DashboardTable::CustomValues,
-            tx,
+            connection,
             (service_map, custom_map),
-            |tx, chunk, (serv_map, cust_map)| {
-                Box::pin(loaders::insert_custom_values(tx, chunk, serv_map, cust_map))
+            |tx, chunk, (serv_map, cust_map), skipped| {
+                Box::pin(loaders::insert_custom_values(
+                    tx, conflict, chunk, serv_map, cust_map, skipped,
+                ))
             },
         )
         .await
@@ -372,27 +747,187 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// Empty `Ok(())` on success.
     ///
-    async fn migrate_config_values(&mut self, tx: &mut PgTransaction<'_>) -> Result<()> {
+    async fn migrate_config_values(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
         let service_map = self
-            .get_existing_ids(tx, DashboardTable::Services, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::Services, "whmcs_id")
             .await?;
         let config_map = self
-            .get_existing_ids(tx, DashboardTable::ConfigOptions, "whmcs_id")
+            .get_existing_ids(connection, DashboardTable::ConfigOptions, "whmcs_id")
             .await?;
 
         self.migrate_table(
-            include_str!("sql/get_config_values.sql"),
             DashboardTable::ConfigValues,
-            tx,
+            connection,
             (service_map, config_map),
-            |tx, chunk, (serv_map, conf_map)| {
-                Box::pin(loaders::insert_config_values(tx, chunk, serv_map, conf_map))
+            |tx, chunk, (serv_map, conf_map), skipped| {
+                Box::pin(loaders::insert_config_values(
+                    tx, conflict, chunk, serv_map, conf_map, skipped,
+                ))
+            },
+        )
+        .await
+    }
+
+    /// Migrates invoices from the WHMCS `tblinvoices` to the `invoices` table.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: Connection to the target database.
+    ///
+    /// # Returns
+    ///
+    /// Empty `Ok(())` on success.
+    ///
+    async fn migrate_invoices(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let mut user_map = self
+            .get_existing_ids(connection, DashboardTable::Users, "whmcs_id")
+            .await?;
+        user_map.extend(self.get_user_aliases(connection).await?);
+
+        self.migrate_table(
+            DashboardTable::Invoices,
+            connection,
+            user_map,
+            |tx, chunk, user_map, skipped| {
+                Box::pin(loaders::insert_invoices(
+                    tx, conflict, chunk, user_map, skipped,
+                ))
+            },
+        )
+        .await
+    }
+
+    /// Migrates invoice lines from the WHMCS `tblinvoiceitems` to the
+    /// `invoice_items` table.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: Connection to the target database.
+    ///
+    /// # Returns
+    ///
+    /// Empty `Ok(())` on success.
+    ///
+    async fn migrate_invoice_items(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let invoice_map = self
+            .get_existing_ids(connection, DashboardTable::Invoices, "whmcs_id")
+            .await?;
+        let service_map = self
+            .get_existing_ids(connection, DashboardTable::Services, "whmcs_id")
+            .await?;
+
+        self.migrate_table(
+            DashboardTable::InvoiceItems,
+            connection,
+            (invoice_map, service_map),
+            |tx, chunk, (inv_map, serv_map), skipped| {
+                Box::pin(loaders::insert_invoice_items(
+                    tx, conflict, chunk, inv_map, serv_map, skipped,
+                ))
+            },
+        )
+        .await
+    }
+
+    /// Migrates incoming transactions from the WHMCS `tblaccounts` to the
+    /// `payments` table.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: Connection to the target database.
+    ///
+    /// # Returns
+    ///
+    /// Empty `Ok(())` on success.
+    ///
+    async fn migrate_payments(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let invoice_map = self
+            .get_existing_ids(connection, DashboardTable::Invoices, "whmcs_id")
+            .await?;
+
+        self.migrate_table(
+            DashboardTable::Payments,
+            connection,
+            invoice_map,
+            |tx, chunk, inv_map, skipped| {
+                Box::pin(loaders::insert_payments(
+                    tx, conflict, chunk, inv_map, skipped,
+                ))
+            },
+        )
+        .await
+    }
+
+    /// Migrates support tickets from the WHMCS `tbltickets` to the `tickets`
+    /// table.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: Connection to the target database.
+    ///
+    /// # Returns
+    ///
+    /// Empty `Ok(())` on success.
+    ///
+    async fn migrate_tickets(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let mut user_map = self
+            .get_existing_ids(connection, DashboardTable::Users, "whmcs_id")
+            .await?;
+        user_map.extend(self.get_user_aliases(connection).await?);
+
+        let anonymizer = self.anonymizer.clone();
+        self.migrate_table(
+            DashboardTable::Tickets,
+            connection,
+            user_map,
+            |tx, chunk, user_map, skipped| {
+                let tickets = anonymizer.anonymize_tickets(chunk);
+                Box::pin(loaders::insert_tickets(
+                    tx, conflict, tickets, user_map, skipped,
+                ))
+            },
+        )
+        .await
+    }
+
+    /// Migrates ticket replies from the WHMCS `tblticketreplies` to the
+    /// `ticket_replies` table.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: Connection to the target database.
+    ///
+    /// # Returns
+    ///
+    /// Empty `Ok(())` on success.
+    ///
+    async fn migrate_ticket_replies(&mut self, connection: &mut PgConnection) -> Result<()> {
+        let conflict = self.on_conflict();
+        let ticket_map = self
+            .get_existing_ids(connection, DashboardTable::Tickets, "whmcs_id")
+            .await?;
+
+        let anonymizer = self.anonymizer.clone();
+        self.migrate_table(
+            DashboardTable::TicketReplies,
+            connection,
+            ticket_map,
+            |tx, chunk, tick_map, skipped| {
+                let replies = anonymizer.anonymize_replies(chunk);
+                Box::pin(loaders::insert_ticket_replies(
+                    tx, conflict, replies, tick_map, skipped,
+                ))
             },
         )
         .await
@@ -405,7 +940,7 @@ impl Migration {
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     /// * `table`: Table to select WHMCS ids from.
     /// * `key_name`: Name of the WHMCS id field.
     ///
@@ -415,7 +950,7 @@ impl Migration {
     ///
     async fn get_existing_ids(
         &self,
-        tx: &mut PgTransaction<'_>,
+        connection: &mut PgConnection,
         table: DashboardTable,
         key_name: &str,
     ) -> Result<HashMap<i32, Uuid>> {
@@ -430,7 +965,7 @@ impl Migration {
             .push(" IS NOT NULL")
             .build();
         Ok(query
-            .fetch_all(tx.as_mut())
+            .fetch_all(&mut *connection)
             .await?
             .into_iter()
             .filter_map(|row| {
@@ -441,31 +976,55 @@ impl Migration {
             .collect::<HashMap<i32, Uuid>>())
     }
 
+    /// Retrieves a map of merged WHMCS client IDs to the Dashboard users they
+    /// were merged into.
+    ///
+    /// # Arguments
+    ///
+    /// * `connection`: Connection to the target database.
+    ///
+    async fn get_user_aliases(&self, connection: &mut PgConnection) -> Result<HashMap<i32, Uuid>> {
+        Ok(sqlx::query!("SELECT whmcs_id, user_id FROM user_aliases")
+            .fetch_all(&mut *connection)
+            .await?
+            .into_iter()
+            .map(|alias| (alias.whmcs_id, alias.user_id))
+            .collect())
+    }
+
     /// Retrieves a map of WHMCS product IDs to Dashboard template UUIDs.
     ///
     /// # Arguments
     ///
-    /// * `tx`: In-progress transaction for target database.
+    /// * `connection`: Connection to the target database.
     ///
     /// # Returns
     ///
     /// `HashMap` of relationship between the WHMCS product ID and the Dashboard
     /// template UUID.
     ///
-    async fn get_template_ids(&self, tx: &mut PgTransaction<'_>) -> Result<HashMap<i32, Uuid>> {
-        // Template field info from source WHMCS.
-        let query = include_str!("sql/get_templates.sql");
-        let relid_to_vmid = sqlx::query_as::<_, types::TemplateField>(query)
-            .fetch_all(&self.source_pool)
-            .await?
-            .into_iter()
-            .map(|temp_field| (temp_field.relid, temp_field.extract()))
-            .flat_map(|(relid, vec)| vec.into_iter().map(move |val| (relid, val.template_vmid)))
-            .collect::<HashMap<_, _>>();
+    async fn get_template_ids(&self, connection: &mut PgConnection) -> Result<HashMap<i32, Uuid>> {
+        // Template field info from the source, the undecodable fields were
+        // already reported with the templates.
+        let mut relid_to_vmid = HashMap::new();
+        let mut fields = self
+            .source
+            .records::<types::TemplateField>(DashboardTable::Templates, None);
+        while let Some(record) = fields.next().await {
+            if let Record::Decoded(temp_field) = record? {
+                let relid = temp_field.relid;
+                relid_to_vmid.extend(
+                    temp_field
+                        .extract()
+                        .into_iter()
+                        .map(|val| (relid, val.template_vmid)),
+                );
+            }
+        }
 
         // Proxmox template_vmid to Dashboard template UUID relationship.
         let vmid_to_temp_id = self
-            .get_existing_ids(tx, DashboardTable::Templates, "template_vmid")
+            .get_existing_ids(connection, DashboardTable::Templates, "template_vmid")
             .await?;
 
         // Combine to get the final map: WHMCS product_id to Dashboard template_id
@@ -494,23 +1053,33 @@ impl Migration {
         }
     }
 
-    /// A generic helper function to stream data from the source database,
-    /// process it in chunks, and insert it into the target database.
+    /// A generic helper function to stream records from the source, process
+    /// them in chunks, and insert them into the target database.
+    ///
+    /// The chunks are extracted by a task of their own while the previous ones
+    /// are loaded, and wait in a bounded channel, so at most `buffered_chunks`
+    /// chunks are held besides the one being extracted and the one being
+    /// loaded. The extraction pauses while the channel is full.
+    ///
+    /// Every chunk is loaded in a transaction of its own begun on the
+    /// connection. Committing per chunk, the connection is outside any
+    /// transaction and the chunk is committed right away, otherwise it is a
+    /// savepoint released into the transaction of the table or the run.
     ///
     /// # Types
     ///
     /// * `C`: Context data structure type, passed to the insertion function.
     /// * `F`: Insertion closure function type.
-    /// * `S`: Source data structure type, deserialized from the WHMCS database.
+    /// * `S`: Source data structure type, decoded from the source records.
     ///
     /// # Arguments
     ///
-    /// * `query`: SQL query string for fetching data from the source database.
-    /// * `table`: `DashboardTable` enum variant, for logging and statistics.
-    /// * `tx`: In-progress transaction for target database.
+    /// * `table`: `DashboardTable` enum variant, selecting the source records,
+    ///   for logging and statistics.
+    /// * `connection`: Connection to the target database.
     /// * `context`: Context data needed by `insert_fn`.
     /// * `insert_fn`: Closure that handles the transformation and insertion of
-    ///   a single chunk of data.
+    ///   a single chunk of data, collecting the rows it skips.
     ///
     /// # Returns
     ///
@@ -518,36 +1087,172 @@ impl Migration {
     ///
     async fn migrate_table<C, F, S>(
         &mut self,
-        query: &str,
         table: DashboardTable,
-        tx: &mut PgTransaction<'_>,
+        connection: &mut PgConnection,
         context: C,
         mut insert_fn: F,
     ) -> Result<()>
     where
         C: Send,
-        S: for<'r> sqlx::FromRow<'r, sqlx::mysql::MySqlRow> + Unpin + Send,
+        S: SourceRecord,
         F: for<'a> FnMut(
             &'a mut PgTransaction<'_>,
             Vec<S>,
             &'a C,
+            &'a mut Vec<SkippedRow>,
         ) -> Pin<Box<dyn Future<Output = Result<u64>> + Send + 'a>>,
     {
-        // Set up a stream to fetch source data in paginated chunks.
-        let mut chunks = sqlx::query_as::<_, S>(query)
-            .fetch(&self.source_pool)
-            .try_chunks(self.chunk_size);
-        let mut total_affected = 0;
+        // Set up a task to fetch source records in chunks, which stops once
+        // the receiver is dropped.
+        let records = self.source.records::<S>(table, self.since);
+        let chunk_size = self.chunk_size;
+        let (sender, mut chunks) = mpsc::channel(self.buffered_chunks);
+        let extractor = tokio::spawn(async move {
+            let mut records = records.chunks(chunk_size);
+            while let Some(chunk) = records.next().await {
+                if sender.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+        });
+        self.progress.start_table(table);
+
+        while let Some(records) = chunks.recv().await {
+            self.progress.advance(records.len() as u64);
+            // Records are decoded one by one, so a malformed record is skipped
+            // without losing the rest of its chunk.
+            let mut chunk = Vec::with_capacity(records.len());
+            for record in records {
+                match record? {
+                    Record::Decoded(source) => chunk.push(source),
+                    Record::Undecodable { source_id, error } => {
+                        self.skip_undecodable(table, source_id, error)?
+                    }
+                }
+            }
+            if chunk.is_empty() {
+                continue;
+            }
 
-        while let Some(Ok(chunk)) = chunks.next().await {
             tracing::trace!(size = %chunk.len(), %table, "Insert a chunk of WHMCS data.",);
-            total_affected += insert_fn(tx, chunk, &context).await?;
+            let mut transaction = connection.begin().await?;
+            let affected = insert_fn(&mut transaction, chunk, &context, &mut self.skipped).await?;
+            transaction.commit().await?;
+            if self.commit == Commit::EachChunk {
+                tracing::trace!(%table, "Chunk committed.");
+            }
+            self.collect_statistics(affected, table);
         }
 
+        // A panicking extractor closes the channel just like a finished one,
+        // so the table only counts as complete once the task returned.
         drop(chunks);
+        extractor
+            .await
+            .map_err(|error| Error::Any(format!("Extraction of {table} failed: {error}")))?;
+        self.progress.finish_table();
         tracing::debug!(?table, "Migration completed.");
-        self.collect_statistics(total_affected, table);
 
         Ok(())
     }
+
+    /// Records a source row that couldn't be decoded as skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: Table the row was meant for.
+    /// * `source_id`: ID of the source row.
+    /// * `error`: Decoding error.
+    ///
+    /// # Returns
+    ///
+    /// Error once more rows than allowed by `max_errors` were skipped.
+    ///
+    fn skip_undecodable(
+        &mut self,
+        table: DashboardTable,
+        source_id: i64,
+        error: String,
+    ) -> Result<()> {
+        tracing::warn!(%table, source_id, %error, "Skipping source row that can't be decoded.");
+        self.skipped.push(SkippedRow::new(
+            table,
+            source_id,
+            format!("Row can't be decoded: {error}"),
+        ));
+
+        self.decode_errors += 1;
+        match self.decode_errors > self.max_errors {
+            true => Err(Error::Any(format!(
+                "More than {} source rows can't be decoded",
+                self.max_errors
+            ))),
+            false => Ok(()),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the query reading the rows of a table from WHMCS.
+///
+pub(crate) fn source_query(table: DashboardTable) -> &'static str {
+    match table {
+        DashboardTable::Users => include_str!("sql/get_active_clients.sql"),
+        DashboardTable::ProductGroups => include_str!("sql/get_product_groups.sql"),
+        DashboardTable::Products => include_str!("sql/get_products.sql"),
+        DashboardTable::CustomFields => include_str!("sql/get_custom_fields.sql"),
+        DashboardTable::ConfigOptions => include_str!("sql/get_config_options.sql"),
+        DashboardTable::Servers => include_str!("sql/get_servers.sql"),
+        DashboardTable::Networks => include_str!("sql/get_networks.sql"),
+        DashboardTable::IpAddresses => include_str!("sql/get_ip_addresses.sql"),
+        DashboardTable::Templates => include_str!("sql/get_templates.sql"),
+        DashboardTable::Services => include_str!("sql/get_services.sql"),
+        DashboardTable::CustomValues => include_str!("sql/get_custom_values.sql"),
+        DashboardTable::ConfigValues => include_str!("sql/get_config_values.sql"),
+        DashboardTable::Invoices => include_str!("sql/get_invoices.sql"),
+        DashboardTable::InvoiceItems => include_str!("sql/get_invoice_items.sql"),
+        DashboardTable::Payments => include_str!("sql/get_transactions.sql"),
+        DashboardTable::Tickets => include_str!("sql/get_tickets.sql"),
+        DashboardTable::TicketReplies => include_str!("sql/get_ticket_replies.sql"),
+    }
+}
+
+/// Returns the column of a table holding the key of its WHMCS row. Templates
+/// are matched by their VMID, as WHMCS keeps no ID for them.
+///
+pub(crate) fn target_key(table: DashboardTable) -> &'static str {
+    match table {
+        DashboardTable::Templates => "template_vmid",
+        _ => "whmcs_id",
+    }
+}
+
+/// Counts the rows of each table that were migrated from WHMCS.
+///
+/// # Arguments
+///
+/// * `target_pool`: Pool of the target database.
+///
+/// # Returns
+///
+/// Number of migrated rows per table, in the order of the steps.
+///
+pub async fn status(target_pool: &PgPool) -> Result<Vec<(DashboardTable, u64)>> {
+    let mut status = Vec::with_capacity(STEPS.len());
+    for table in STEPS {
+        let mut builder = sqlx::QueryBuilder::<sqlx::Postgres>::default();
+        let count = builder
+            .push("SELECT COUNT(*) FROM ")
+            .push(table)
+            .push(" WHERE ")
+            .push(target_key(table))
+            .push(" IS NOT NULL")
+            .build_query_scalar::<i64>()
+            .fetch_one(target_pool)
+            .await?;
+        status.push((table, count as u64));
+    }
+
+    Ok(status)
 }
@@ -1,3 +1,13 @@
-﻿pub mod loaders;
+﻿pub mod anonymize;
+pub mod bulk;
+pub mod copy;
+pub mod loaders;
 pub mod migration;
+pub mod plan;
+pub mod preflight;
+pub mod progress;
+pub mod reconcile;
+pub mod rules;
+pub mod source;
+pub mod throttle;
 pub mod types;
@@ -0,0 +1,409 @@
+//! This module estimates a migration from the source alone, without touching
+//! the target database.
+//!
+//! Every table is read in the order of the steps, and each record is checked
+//! for the references the loaders need, against the records planned so far.
+//! Records whose reference can't be mapped are listed with the reason the
+//! loaders would skip them for. Rows already in the target database aren't
+//! known, so the estimate is the one of a first run.
+
+use crate::etl::migration::STEPS;
+use crate::etl::source::{Record, Source, SourceRecord};
+use crate::etl::types::{self, DashboardTable, SkippedRow};
+use dashboard_common::prelude::Result;
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Estimated outcome of a migration.
+///
+/// # Fields
+///
+/// * `tables`: Estimate of every table, in the order of the steps.
+/// * `unmapped`: Records that would be skipped, with the reason.
+///
+#[derive(Debug, Default, Serialize)]
+pub struct Plan {
+    pub tables: Vec<TablePlan>,
+    pub unmapped: Vec<SkippedRow>,
+}
+
+/// Estimate of a single table.
+///
+/// # Fields
+///
+/// * `table`: Planned table.
+/// * `source_rows`: Records read from the source.
+/// * `undecodable`: Records that can't be decoded.
+/// * `unmapped`: Records with a reference that can't be mapped.
+/// * `to_insert`: Distinct rows that would be inserted.
+///
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TablePlan {
+    pub table: DashboardTable,
+    pub source_rows: u64,
+    pub undecodable: u64,
+    pub unmapped: u64,
+    pub to_insert: u64,
+}
+
+/// Keys of the records planned so far, per table.
+///
+#[derive(Debug, Default)]
+struct Keys(HashMap<DashboardTable, HashSet<i64>>);
+
+impl Keys {
+    fn has(&self, table: DashboardTable, key: impl Into<i64>) -> bool {
+        self.0
+            .get(&table)
+            .is_some_and(|keys| keys.contains(&key.into()))
+    }
+}
+
+/// Outcome of the check of a record: its key, or its source ID with the
+/// reason it would be skipped for.
+///
+type Check = std::result::Result<i64, (i64, String)>;
+
+/// Reads every table from the source and estimates the rows the migration
+/// would insert and skip.
+///
+/// # Arguments
+///
+/// * `source`: Source of the migrated records.
+///
+pub async fn plan<Src: Source>(source: &Src) -> Result<Plan> {
+    let mut planner = Planner {
+        source,
+        keys: Keys::default(),
+        plan: Plan::default(),
+    };
+    for table in STEPS {
+        planner.plan_table(table).await?;
+    }
+
+    Ok(planner.plan)
+}
+
+struct Planner<'a, Src> {
+    source: &'a Src,
+    keys: Keys,
+    plan: Plan,
+}
+
+impl<Src: Source> Planner<'_, Src> {
+    /// Checks the records of a table with the references its loader needs.
+    ///
+    async fn plan_table(&mut self, table: DashboardTable) -> Result<()> {
+        use DashboardTable as T;
+
+        match table {
+            T::Users => {
+                self.scan(table, |c: &types::Client, _| Ok(c.id.into()))
+                    .await
+            }
+            T::ProductGroups => {
+                self.scan(table, |g: &types::ProductGroup, _| Ok(g.id.into()))
+                    .await
+            }
+            T::Products => {
+                self.scan(table, |p: &types::Product, keys| {
+                    match keys.has(T::ProductGroups, p.gid) {
+                        true => Ok(p.id.into()),
+                        false => Err((
+                            p.id.into(),
+                            format!("Product group {} was not migrated", p.gid),
+                        )),
+                    }
+                })
+                .await
+            }
+            T::CustomFields => {
+                self.scan(table, |f: &types::CustomField, keys| {
+                    match keys.has(T::Products, f.relid) {
+                        true => Ok(f.id.into()),
+                        false => {
+                            Err((f.id.into(), format!("Product {} was not migrated", f.relid)))
+                        }
+                    }
+                })
+                .await
+            }
+            T::ConfigOptions => {
+                self.scan(table, |o: &types::ConfigOption, _| Ok(o.id.into()))
+                    .await
+            }
+            T::Servers => {
+                self.scan(table, |v: &types::VmRecord, _| Ok(v.id.into()))
+                    .await
+            }
+            T::Networks => {
+                self.scan(table, |n: &types::Network, _| Ok(n.id.into()))
+                    .await
+            }
+            T::IpAddresses => {
+                self.scan(table, |a: &types::IpAddress, keys| {
+                    match keys.has(T::Networks, a.pool_id) {
+                        true => Ok(a.id.into()),
+                        false => Err((
+                            a.id.into(),
+                            format!("Network {} was not migrated", a.pool_id),
+                        )),
+                    }
+                })
+                .await
+            }
+            T::Templates => self.scan_templates().await,
+            T::Services => {
+                self.scan(table, |s: &types::Service, keys| {
+                    let reason = match () {
+                        _ if !keys.has(T::Users, s.userid) => {
+                            format!("User {} was not migrated", s.userid)
+                        }
+                        _ if !keys.has(T::Products, s.packageid) => {
+                            format!("Product {} was not migrated", s.packageid)
+                        }
+                        _ if !keys.has(T::Servers, s.id) => {
+                            format!("Server of service {} was not migrated", s.id)
+                        }
+                        _ if !keys.has(T::Templates, s.packageid) => {
+                            format!("Product {} has no migrated template", s.packageid)
+                        }
+                        _ => return Ok(s.id.into()),
+                    };
+                    Err((s.id.into(), reason))
+                })
+                .await
+            }
+            T::CustomValues => {
+                self.scan(table, |v: &types::CustomValue, keys| {
+                    let reason = match () {
+                        _ if !keys.has(T::Services, v.relid) => {
+                            format!("Service {} was not migrated", v.relid)
+                        }
+                        _ if !keys.has(T::CustomFields, v.fieldid) => {
+                            format!("Custom field {} was not migrated", v.fieldid)
+                        }
+                        _ => return Ok(v.id.into()),
+                    };
+                    Err((v.id.into(), reason))
+                })
+                .await
+            }
+            T::ConfigValues => {
+                self.scan(table, |v: &types::ConfigValue, keys| {
+                    let reason = match () {
+                        _ if !keys.has(T::Services, v.relid) => {
+                            format!("Service {} was not migrated", v.relid)
+                        }
+                        _ if !keys.has(T::ConfigOptions, v.configid) => {
+                            format!("Config option {} was not migrated", v.configid)
+                        }
+                        _ => return Ok(v.id.into()),
+                    };
+                    Err((v.id.into(), reason))
+                })
+                .await
+            }
+            T::Invoices => {
+                self.scan(table, |i: &types::Invoice, keys| {
+                    match keys.has(T::Users, i.userid) {
+                        true => Ok(i.id.into()),
+                        false => Err((i.id.into(), format!("User {} was not migrated", i.userid))),
+                    }
+                })
+                .await
+            }
+            T::InvoiceItems => {
+                self.scan(table, |i: &types::InvoiceItem, keys| {
+                    match keys.has(T::Invoices, i.invoiceid) {
+                        true => Ok(i.id.into()),
+                        false => Err((
+                            i.id.into(),
+                            format!("Invoice {} was not migrated", i.invoiceid),
+                        )),
+                    }
+                })
+                .await
+            }
+            T::Payments => {
+                self.scan(table, |t: &types::Transaction, keys| {
+                    match keys.has(T::Invoices, t.invoiceid) {
+                        true => Ok(t.id.into()),
+                        false => Err((
+                            t.id.into(),
+                            format!("Invoice {} was not migrated", t.invoiceid),
+                        )),
+                    }
+                })
+                .await
+            }
+            T::Tickets => {
+                self.scan(table, |t: &types::Ticket, keys| {
+                    match keys.has(T::Users, t.userid) {
+                        true => Ok(t.id.into()),
+                        false => Err((t.id.into(), format!("User {} was not migrated", t.userid))),
+                    }
+                })
+                .await
+            }
+            T::TicketReplies => {
+                self.scan(table, |r: &types::TicketReply, keys| {
+                    match keys.has(T::Tickets, r.tid) {
+                        true => Ok(r.id.into()),
+                        false => Err((r.id.into(), format!("Ticket {} was not migrated", r.tid))),
+                    }
+                })
+                .await
+            }
+        }
+    }
+
+    /// Reads the records of a table and checks each of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: Table to read.
+    /// * `check`: Returns the key of a record, or the reason it would be
+    ///   skipped for.
+    ///
+    async fn scan<S, F>(&mut self, table: DashboardTable, check: F) -> Result<()>
+    where
+        S: SourceRecord,
+        F: Fn(&S, &Keys) -> Check,
+    {
+        let mut keys = HashSet::new();
+        let mut entry = TablePlan::new(table);
+        let mut records = self.source.records::<S>(table, None);
+        while let Some(record) = records.next().await {
+            entry.source_rows += 1;
+            match record? {
+                Record::Decoded(record) => match check(&record, &self.keys) {
+                    Ok(key) => {
+                        keys.insert(key);
+                    }
+                    Err((source_id, reason)) => {
+                        entry.unmapped += 1;
+                        let row = SkippedRow::new(table, source_id, reason);
+                        self.plan.unmapped.push(row);
+                    }
+                },
+                Record::Undecodable { source_id, error } => {
+                    entry.undecodable += 1;
+                    let reason = format!("Row can't be decoded: {error}");
+                    self.plan
+                        .unmapped
+                        .push(SkippedRow::new(table, source_id, reason));
+                }
+            }
+        }
+
+        entry.to_insert = keys.len() as u64;
+        self.keys.0.insert(table, keys);
+        self.plan.tables.push(entry);
+
+        Ok(())
+    }
+
+    /// Reads the template fields of the products. The templates are keyed by
+    /// their VMID, and the products offering one are kept for the services.
+    ///
+    async fn scan_templates(&mut self) -> Result<()> {
+        let table = DashboardTable::Templates;
+        let mut products = HashSet::new();
+        let mut vmids = HashSet::new();
+        let mut entry = TablePlan::new(table);
+        let mut records = self.source.records::<types::TemplateField>(table, None);
+        while let Some(record) = records.next().await {
+            entry.source_rows += 1;
+            match record? {
+                Record::Decoded(field) => {
+                    let relid = field.relid;
+                    let templates = field.extract();
+                    if !templates.is_empty() {
+                        products.insert(relid.into());
+                    }
+                    vmids.extend(templates.into_iter().map(|t| t.template_vmid));
+                }
+                Record::Undecodable { source_id, error } => {
+                    entry.undecodable += 1;
+                    let reason = format!("Row can't be decoded: {error}");
+                    self.plan
+                        .unmapped
+                        .push(SkippedRow::new(table, source_id, reason));
+                }
+            }
+        }
+
+        entry.to_insert = vmids.len() as u64;
+        self.keys.0.insert(table, products);
+        self.plan.tables.push(entry);
+
+        Ok(())
+    }
+}
+
+impl TablePlan {
+    fn new(table: DashboardTable) -> Self {
+        Self {
+            table,
+            source_rows: 0,
+            undecodable: 0,
+            unmapped: 0,
+            to_insert: 0,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::etl::source::DirectorySource;
+
+    #[tokio::test]
+    async fn plan_should_list_unmapped_references() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("product_groups.csv"), "id,name\n1,VPS\n").unwrap();
+        std::fs::write(
+            dir.path().join("products.csv"),
+            "id,gid,name\n10,1,Small\n11,1,Small\n12,2,Orphan\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("custom_fields.json"),
+            r#"[{"id": 5, "fieldname": "OS", "relid": 12}, {"id": 6, "relid": 10}]"#,
+        )
+        .unwrap();
+
+        // Act
+        let plan = plan(&DirectorySource::new(dir.path())).await.unwrap();
+
+        // Assert
+        let products = &plan.tables[2];
+        assert_eq!(
+            (products.table, products.source_rows, products.to_insert),
+            (DashboardTable::Products, 3, 2)
+        );
+        let fields = &plan.tables[3];
+        assert_eq!((fields.unmapped, fields.undecodable), (1, 1));
+        assert_eq!(
+            plan.unmapped[..2],
+            [
+                SkippedRow::new(
+                    DashboardTable::Products,
+                    12,
+                    "Product group 2 was not migrated"
+                ),
+                SkippedRow::new(
+                    DashboardTable::CustomFields,
+                    5,
+                    "Product 12 was not migrated"
+                ),
+            ]
+        );
+        assert!(plan.tables[4..].iter().all(|table| table.source_rows == 0));
+    }
+}
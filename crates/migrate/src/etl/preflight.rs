@@ -0,0 +1,352 @@
+//! This module checks both databases before anything is migrated.
+//!
+//! The target database must have every Dashboard migration applied, and the
+//! source database every table and column the extract queries read. All the
+//! differences are reported at once, instead of the first missing column
+//! failing the run halfway through with a decoding error.
+
+use dashboard_common::prelude::{Error, Result};
+use futures::FutureExt;
+use futures::future::BoxFuture;
+use sqlx::migrate::Migrator;
+use sqlx::{MySqlPool, PgPool};
+use std::collections::HashSet;
+
+/// Migrations of the Dashboard schema the loaders are written for.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+/// Tables of a source database with the columns the extract queries read.
+pub type SourceSchema = &'static [(&'static str, &'static [&'static str])];
+
+/// Tables and columns of WHMCS and its Proxmox module read by the migration.
+pub const WHMCS_SCHEMA: SourceSchema = &[
+    (
+        "tblclients",
+        &[
+            "id",
+            "firstname",
+            "lastname",
+            "email",
+            "address1",
+            "city",
+            "state",
+            "postcode",
+            "country",
+            "phonenumber",
+            "password",
+            "currency",
+            "created_at",
+            "updated_at",
+        ],
+    ),
+    (
+        "tblhosting",
+        &[
+            "id",
+            "userid",
+            "packageid",
+            "domain",
+            "domainstatus",
+            "updated_at",
+        ],
+    ),
+    ("tblproductgroups", &["id", "name"]),
+    ("tblproducts", &["id", "gid", "name", "hidden"]),
+    (
+        "tblcustomfields",
+        &["id", "type", "relid", "fieldname", "fieldoptions"],
+    ),
+    (
+        "tblcustomfieldsvalues",
+        &["id", "fieldid", "relid", "value"],
+    ),
+    ("tblproductconfigoptions", &["id", "optionname"]),
+    ("tblproductconfigoptionssub", &["id", "optionname"]),
+    (
+        "tblhostingconfigoptions",
+        &["id", "relid", "configid", "optionid"],
+    ),
+    ("tblservers", &["id", "name"]),
+    ("tblcurrencies", &["id", "code"]),
+    (
+        "tblinvoices",
+        &[
+            "id",
+            "userid",
+            "invoicenum",
+            "status",
+            "total",
+            "credit",
+            "date",
+            "datepaid",
+        ],
+    ),
+    (
+        "tblinvoiceitems",
+        &["id", "invoiceid", "type", "relid", "description", "amount"],
+    ),
+    (
+        "tblaccounts",
+        &[
+            "id",
+            "invoiceid",
+            "gateway",
+            "transid",
+            "amountin",
+            "amountout",
+            "date",
+        ],
+    ),
+    (
+        "tbltickets",
+        &[
+            "id",
+            "tid",
+            "userid",
+            "title",
+            "message",
+            "status",
+            "urgency",
+            "date",
+            "lastreply",
+        ],
+    ),
+    (
+        "tblticketreplies",
+        &["id", "tid", "name", "admin", "message", "date"],
+    ),
+    (
+        "mod_pvewhmcs_vms",
+        &["id", "vmid", "user_id", "node_id", "ipaddress"],
+    ),
+    ("mod_pvewhmcs_ip_pools", &["id", "title", "gateway"]),
+    (
+        "mod_pvewhmcs_ip_addresses",
+        &["id", "pool_id", "ipaddress", "mask"],
+    ),
+];
+
+/// Tables and columns of Virtualizor read by the migration.
+pub const VIRTUALIZOR_SCHEMA: SourceSchema = &[
+    (
+        "users",
+        &["uid", "type", "fname", "lname", "email", "password"],
+    ),
+    (
+        "vps",
+        &[
+            "vpsid",
+            "vps_name",
+            "uid",
+            "serid",
+            "plid",
+            "hostname",
+            "suspended",
+        ],
+    ),
+    ("servers", &["serid", "server_name"]),
+    ("plans", &["plid", "plan_name"]),
+    ("ippool", &["ippid", "ippool_name", "gateway", "netmask"]),
+    ("ips", &["ipid", "ippid", "ip", "vpsid"]),
+];
+
+/// Differences between the databases and the schemas the migration expects.
+///
+/// # Fields
+///
+/// * `missing_migrations`: Dashboard migrations not applied to the target.
+/// * `missing_tables`: Source tables that don't exist.
+/// * `missing_columns`: Columns missing from existing source tables, as
+///   `table.column`.
+///
+#[derive(Debug, Default, PartialEq)]
+pub struct SchemaDiff {
+    pub missing_migrations: Vec<String>,
+    pub missing_tables: Vec<String>,
+    pub missing_columns: Vec<String>,
+}
+
+impl SchemaDiff {
+    /// Checks whether both databases match the expected schemas.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.missing_migrations.is_empty()
+            && self.missing_tables.is_empty()
+            && self.missing_columns.is_empty()
+    }
+
+    /// Fails with the whole diff unless it is empty.
+    ///
+    pub fn into_result(self) -> Result<()> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Any(format!(
+                "Database schemas don't match the migration:\n{self}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = [
+            ("Target migration not applied", &self.missing_migrations),
+            ("Source table missing", &self.missing_tables),
+            ("Source column missing", &self.missing_columns),
+        ];
+        for (label, items) in lines {
+            for item in items {
+                writeln!(f, "  {label}: {item}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists the Dashboard migrations that weren't applied to the target database,
+/// or failed.
+///
+/// # Arguments
+///
+/// * `pool`: Pool of the target database.
+///
+/// # Returns
+///
+/// On success, the version and description of every missing migration.
+///
+pub async fn missing_migrations(pool: &PgPool) -> Result<Vec<String>> {
+    let migrated =
+        sqlx::query_scalar::<_, Option<String>>("SELECT to_regclass('_sqlx_migrations')::TEXT")
+            .fetch_one(pool)
+            .await?
+            .is_some();
+    let applied = match migrated {
+        true => sqlx::query_scalar::<_, i64>("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>(),
+        false => HashSet::new(),
+    };
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.contains(&migration.version))
+        .map(|migration| format!("{} {}", migration.version, migration.description))
+        .collect())
+}
+
+/// Compares the tables of a MySQL source database with the given schema.
+///
+/// # Arguments
+///
+/// * `pool`: Pool of the source database.
+/// * `schema`: Tables and columns the extract queries read.
+///
+/// # Returns
+///
+/// On success, the missing source tables and columns.
+///
+pub fn check_source(
+    pool: &MySqlPool,
+    schema: SourceSchema,
+) -> BoxFuture<'static, Result<SchemaDiff>> {
+    let pool = pool.clone();
+    async move {
+        // Names are cast, as MySQL 8 returns them as binary strings.
+        let columns = sqlx::query_as::<_, (String, String)>(
+            "SELECT CAST(TABLE_NAME AS CHAR), CAST(COLUMN_NAME AS CHAR)
+             FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = DATABASE()",
+        )
+        .fetch_all(&pool)
+        .await?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+        Ok(diff_source(schema, &columns))
+    }
+    .boxed()
+}
+
+/// Lists the tables and columns of the schema missing from the source.
+///
+fn diff_source(schema: SourceSchema, columns: &HashSet<(String, String)>) -> SchemaDiff {
+    let tables = columns
+        .iter()
+        .map(|(table, _)| table.as_str())
+        .collect::<HashSet<_>>();
+    let mut diff = SchemaDiff::default();
+    for (table, expected) in schema {
+        if !tables.contains(table) {
+            diff.missing_tables.push(table.to_string());
+            continue;
+        }
+        diff.missing_columns.extend(
+            expected
+                .iter()
+                .filter(|column| !columns.contains(&(table.to_string(), column.to_string())))
+                .map(|column| format!("{table}.{column}")),
+        );
+    }
+
+    diff
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_source_should_list_missing_tables_and_columns() {
+        // Arrange
+        let schema: SourceSchema = &[
+            ("tblclients", &["id", "email"]),
+            ("tblhosting", &["id", "domainstatus", "updated_at"]),
+            ("tbltickets", &["id"]),
+        ];
+        let columns = [
+            ("tblclients", "id"),
+            ("tblclients", "email"),
+            ("tblhosting", "id"),
+            ("tblhosting", "domainstatus"),
+        ]
+        .into_iter()
+        .map(|(table, column)| (table.to_owned(), column.to_owned()))
+        .collect();
+
+        // Act
+        let diff = diff_source(schema, &columns);
+
+        // Assert
+        assert_eq!(diff.missing_tables, ["tbltickets"]);
+        assert_eq!(diff.missing_columns, ["tblhosting.updated_at"]);
+        assert_eq!(
+            diff.to_string(),
+            "  Source table missing: tbltickets\n  Source column missing: tblhosting.updated_at\n"
+        );
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn missing_migrations_should_list_the_unapplied_ones(pool: PgPool) {
+        // Arrange
+        let latest = MIGRATOR.iter().map(|migration| migration.version).max();
+        let complete = missing_migrations(&pool).await.unwrap();
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = $1")
+            .bind(latest)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        // Act
+        let missing = missing_migrations(&pool).await.unwrap();
+
+        // Assert
+        assert!(complete.is_empty());
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].starts_with(&latest.unwrap().to_string()));
+    }
+}
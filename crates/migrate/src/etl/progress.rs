@@ -0,0 +1,307 @@
+//! This module reports the progress of a migration while it runs.
+//!
+//! The source rows of every table are counted up front, then every chunk
+//! advances the bar of its table and the overall one, with the throughput and
+//! the estimated time left. CI pipelines can switch to JSON lines on stderr,
+//! or turn the progress off.
+
+use crate::etl::types::DashboardTable;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Represents the ways the progress is reported.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// No progress, only the logs.
+    #[default]
+    Quiet,
+    /// Live progress bars on stderr.
+    Bars,
+    /// A JSON line on stderr for every chunk.
+    Json,
+}
+
+/// Tracks the rows read per table and overall.
+///
+/// # Fields
+///
+/// * `mode`: How the progress is reported.
+/// * `totals`: Source rows of every table, unknown when the source can't count
+///   them up front.
+/// * `overall`: Rows read and start of the run.
+/// * `table`: Table being migrated, its rows read and its start.
+/// * `bars`: Progress bars, in the `Bars` mode.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    mode: ProgressMode,
+    totals: HashMap<DashboardTable, Option<u64>>,
+    overall: Counter,
+    table: Option<(DashboardTable, Counter)>,
+    bars: Option<Bars>,
+}
+
+#[derive(Debug, Clone)]
+struct Counter {
+    rows: u64,
+    started: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct Bars {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    table: Option<ProgressBar>,
+}
+
+/// Progress reported as a JSON line.
+///
+#[derive(Debug, PartialEq, Serialize)]
+struct ProgressLine {
+    table: DashboardTable,
+    rows: u64,
+    total: Option<u64>,
+    rows_per_sec: f64,
+    eta_secs: Option<u64>,
+    overall_rows: u64,
+    overall_total: Option<u64>,
+    overall_eta_secs: Option<u64>,
+}
+
+impl Default for Counter {
+    fn default() -> Self {
+        Self {
+            rows: 0,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Counter {
+    /// Returns the rows read per second so far.
+    ///
+    fn rate(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        match elapsed > 0.0 {
+            true => self.rows as f64 / elapsed,
+            false => 0.0,
+        }
+    }
+
+    /// Estimates the time left to read the rest of the rows at the current
+    /// rate.
+    ///
+    fn eta(&self, total: Option<u64>) -> Option<Duration> {
+        let rate = self.rate();
+        let left = total?.saturating_sub(self.rows);
+        (rate > 0.0).then(|| Duration::from_secs_f64(left as f64 / rate))
+    }
+}
+
+impl Progress {
+    /// Creates new `Progress` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode`: How the progress is reported.
+    ///
+    pub fn new(mode: ProgressMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Checks whether the progress is reported, so the rows are worth
+    /// counting up front.
+    ///
+    pub fn enabled(&self) -> bool {
+        self.mode != ProgressMode::Quiet
+    }
+
+    /// Starts the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `totals`: Source rows of every table to migrate, in order.
+    ///
+    pub fn start(&mut self, totals: Vec<(DashboardTable, Option<u64>)>) {
+        self.totals = totals.into_iter().collect();
+        self.overall = Counter::default();
+        self.table = None;
+        if self.mode == ProgressMode::Bars {
+            let multi = MultiProgress::new();
+            let overall = multi.add(new_bar(self.overall_total(), "overall"));
+            self.bars = Some(Bars {
+                multi,
+                overall,
+                table: None,
+            });
+        }
+    }
+
+    /// Starts a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: Table being migrated.
+    ///
+    pub fn start_table(&mut self, table: DashboardTable) {
+        self.table = Some((table, Counter::default()));
+        if let Some(bars) = &mut self.bars {
+            let total = self.totals.get(&table).copied().flatten();
+            let bar = bars
+                .multi
+                .insert_before(&bars.overall, new_bar(total, &table.to_string()));
+            bars.table = Some(bar);
+        }
+    }
+
+    /// Counts the rows of a chunk read from the source.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows`: Rows of the chunk, decoded or not.
+    ///
+    pub fn advance(&mut self, rows: u64) {
+        let Some((table, counter)) = &mut self.table else {
+            return;
+        };
+        counter.rows += rows;
+        self.overall.rows += rows;
+
+        match self.mode {
+            ProgressMode::Quiet => {}
+            ProgressMode::Bars => {
+                if let Some(bars) = &self.bars {
+                    bars.overall.inc(rows);
+                    if let Some(bar) = &bars.table {
+                        bar.inc(rows);
+                    }
+                }
+            }
+            ProgressMode::Json => {
+                let table = *table;
+                let line = self.line(table);
+                if let Ok(line) = serde_json::to_string(&line) {
+                    eprintln!("{line}");
+                }
+            }
+        }
+    }
+
+    /// Finishes the current table.
+    ///
+    pub fn finish_table(&mut self) {
+        if let Some(bar) = self.bars.as_mut().and_then(|bars| bars.table.take()) {
+            bar.finish();
+        }
+        self.table = None;
+    }
+
+    /// Finishes the run.
+    ///
+    pub fn finish(&mut self) {
+        self.finish_table();
+        if let Some(bars) = self.bars.take() {
+            bars.overall.finish();
+        }
+    }
+
+    /// Returns the rows of the whole run, unknown if any table is.
+    ///
+    fn overall_total(&self) -> Option<u64> {
+        self.totals.values().copied().sum()
+    }
+
+    fn line(&self, table: DashboardTable) -> ProgressLine {
+        let total = self.totals.get(&table).copied().flatten();
+        let overall_total = self.overall_total();
+        let counter = self.table.as_ref().map(|(_, counter)| counter);
+
+        ProgressLine {
+            table,
+            rows: counter.map_or(0, |counter| counter.rows),
+            total,
+            rows_per_sec: self.overall.rate().round(),
+            eta_secs: counter
+                .and_then(|counter| counter.eta(total))
+                .map(|eta| eta.as_secs()),
+            overall_rows: self.overall.rows,
+            overall_total,
+            overall_eta_secs: self.overall.eta(overall_total).map(|eta| eta.as_secs()),
+        }
+    }
+}
+
+/// Creates a bar of known length, or a spinner when the total is unknown.
+///
+fn new_bar(total: Option<u64>, prefix: &str) -> ProgressBar {
+    let (bar, template) = match total {
+        Some(total) => (
+            ProgressBar::new(total),
+            "{prefix:>16} [{bar:40}] {pos}/{len} {per_sec} ETA {eta}",
+        ),
+        None => (
+            ProgressBar::new_spinner(),
+            "{prefix:>16} {spinner} {pos} {per_sec}",
+        ),
+    };
+    if let Ok(style) = ProgressStyle::with_template(template) {
+        bar.set_style(style.progress_chars("=> "));
+    }
+    bar.set_prefix(prefix.to_owned());
+
+    bar
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_should_count_rows_per_table_and_overall() {
+        // Arrange
+        let mut progress = Progress::new(ProgressMode::Quiet);
+        progress.start(vec![
+            (DashboardTable::Users, Some(10)),
+            (DashboardTable::Servers, Some(5)),
+        ]);
+
+        // Act
+        progress.start_table(DashboardTable::Users);
+        progress.advance(4);
+        progress.advance(6);
+        progress.finish_table();
+        progress.start_table(DashboardTable::Servers);
+        progress.advance(2);
+        let line = progress.line(DashboardTable::Servers);
+
+        // Assert
+        assert_eq!(line.rows, 2);
+        assert_eq!(line.total, Some(5));
+        assert_eq!(line.overall_rows, 12);
+        assert_eq!(line.overall_total, Some(15));
+    }
+
+    #[test]
+    fn overall_total_should_be_unknown_with_any_unknown_table() {
+        // Arrange
+        let mut progress = Progress::new(ProgressMode::Quiet);
+
+        // Act
+        progress.start(vec![
+            (DashboardTable::Users, Some(10)),
+            (DashboardTable::Servers, None),
+        ]);
+
+        // Assert
+        assert_eq!(progress.overall_total(), None);
+        assert!(!progress.enabled());
+    }
+}
@@ -0,0 +1,395 @@
+//! This module validates a finished migration against its WHMCS source.
+//!
+//! Every table is compared by the WHMCS keys of its rows: their count and the
+//! keys missing on either side. The billing tables are also compared by the
+//! sum of their amounts, which catches rows migrated with a wrong value. The
+//! integrity checks then look for the relationships lost on the way, such as
+//! services skipped for a missing reference or users left without services.
+
+use crate::etl::migration::{STEPS, source_query, target_key};
+use crate::etl::types::{self, DashboardTable};
+use dashboard_common::prelude::Result;
+use serde::Serialize;
+use sqlx::{MySqlPool, PgPool};
+use std::collections::BTreeSet;
+
+/// Maximum number of keys listed per discrepancy.
+const SAMPLE_SIZE: usize = 20;
+
+/// Outcome of the comparison between the source and the target database.
+///
+/// # Fields
+///
+/// * `passed`: Whether no discrepancy was found.
+/// * `tables`: Comparison of every migrated table.
+/// * `integrity`: Outcome of every integrity check.
+///
+#[derive(Debug, Serialize)]
+pub struct Reconciliation {
+    pub passed: bool,
+    pub tables: Vec<TableCheck>,
+    pub integrity: Vec<IntegrityCheck>,
+}
+
+/// Comparison of a table by the WHMCS keys of its rows.
+///
+/// # Fields
+///
+/// * `table`: Name of the target table.
+/// * `source_rows`: Number of distinct keys read from WHMCS.
+/// * `target_rows`: Number of migrated rows.
+/// * `source_amount_cents`: Sum of the amounts read from WHMCS, for the
+///   tables holding money.
+/// * `target_amount_cents`: Sum of the amounts of the migrated rows, for the
+///   tables holding money.
+/// * `missing_keys`: First keys of WHMCS rows that were not migrated.
+/// * `unexpected_keys`: First keys of migrated rows no longer in WHMCS.
+///
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TableCheck {
+    pub table: String,
+    pub source_rows: usize,
+    pub target_rows: usize,
+    pub source_amount_cents: Option<i64>,
+    pub target_amount_cents: Option<i64>,
+    pub missing_keys: Vec<i64>,
+    pub unexpected_keys: Vec<i64>,
+}
+
+/// Outcome of a single integrity check.
+///
+/// # Fields
+///
+/// * `name`: Short name of the check.
+/// * `violations`: Number of rows violating it.
+/// * `keys`: First WHMCS keys of the violating rows.
+///
+#[derive(Debug, PartialEq, Serialize)]
+pub struct IntegrityCheck {
+    pub name: &'static str,
+    pub violations: usize,
+    pub keys: Vec<i64>,
+}
+
+impl TableCheck {
+    /// Compares the keys of a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: Compared table.
+    /// * `source`: Keys read from WHMCS.
+    /// * `target`: Keys of the migrated rows.
+    ///
+    pub fn new(table: DashboardTable, source: &BTreeSet<i64>, target: &BTreeSet<i64>) -> Self {
+        Self {
+            table: table.to_string(),
+            source_rows: source.len(),
+            target_rows: target.len(),
+            source_amount_cents: None,
+            target_amount_cents: None,
+            missing_keys: source
+                .difference(target)
+                .take(SAMPLE_SIZE)
+                .copied()
+                .collect(),
+            unexpected_keys: target
+                .difference(source)
+                .take(SAMPLE_SIZE)
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Adds the sums of the amounts of both sides to the comparison.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: Sum of the amounts read from WHMCS.
+    /// * `target`: Sum of the amounts of the migrated rows.
+    ///
+    pub fn with_amounts(mut self, source: i64, target: i64) -> Self {
+        self.source_amount_cents = Some(source);
+        self.target_amount_cents = Some(target);
+        self
+    }
+
+    /// Checks whether both sides hold the same keys and amounts.
+    ///
+    pub fn passed(&self) -> bool {
+        self.source_rows == self.target_rows
+            && self.source_amount_cents == self.target_amount_cents
+            && self.missing_keys.is_empty()
+            && self.unexpected_keys.is_empty()
+    }
+}
+
+impl IntegrityCheck {
+    /// Creates a new check from the keys of the violating rows.
+    ///
+    pub fn new(name: &'static str, keys: impl IntoIterator<Item = i64>) -> Self {
+        let keys = keys.into_iter().collect::<Vec<_>>();
+
+        Self {
+            name,
+            violations: keys.len(),
+            keys: keys.into_iter().take(SAMPLE_SIZE).collect(),
+        }
+    }
+}
+
+/// Compares the migrated tables with their WHMCS source and checks the
+/// relationships between the migrated rows.
+///
+/// # Arguments
+///
+/// * `source_pool`: Pool of the source database.
+/// * `target_pool`: Pool of the target database.
+///
+pub async fn reconcile(source_pool: &MySqlPool, target_pool: &PgPool) -> Result<Reconciliation> {
+    let mut tables = Vec::with_capacity(STEPS.len());
+    let mut orphaned_services = Vec::new();
+    for table in STEPS {
+        let source = source_keys(source_pool, table).await?;
+        let target = target_keys(target_pool, table).await?;
+        let mut check = TableCheck::new(table, &source, &target);
+        if let Some(column) = amount_column(table) {
+            check = check.with_amounts(
+                source_amount(source_pool, table, column).await?,
+                target_amount(target_pool, table, column).await?,
+            );
+        }
+        tables.push(check);
+
+        // Services are skipped when their user, server, product or template
+        // wasn't migrated.
+        if table == DashboardTable::Services {
+            orphaned_services = source.difference(&target).copied().collect();
+        }
+    }
+
+    let query = source_query(DashboardTable::IpAddresses)
+        .trim()
+        .trim_end_matches(';');
+    let assigned_ips = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT CAST(id AS SIGNED) FROM ({query}) AS source WHERE server_id IS NOT NULL"
+    ))
+    .fetch_all(source_pool)
+    .await?;
+    let unassigned_ips = sqlx::query_scalar!(
+        r#"
+SELECT whmcs_id::BIGINT AS "id!"
+FROM ip_addresses
+WHERE whmcs_id IS NOT NULL
+  AND server_id IS NULL
+        "#
+    )
+    .fetch_all(target_pool)
+    .await?
+    .into_iter()
+    .collect::<BTreeSet<_>>();
+
+    let users_without_services = sqlx::query_scalar!(
+        r#"
+SELECT u.whmcs_id::BIGINT AS "id!"
+FROM users AS u
+WHERE u.whmcs_id IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM services AS s WHERE s.user_id = u.id)
+ORDER BY u.whmcs_id
+        "#
+    )
+    .fetch_all(target_pool)
+    .await?;
+    let servers_without_services = sqlx::query_scalar!(
+        r#"
+SELECT v.whmcs_id::BIGINT AS "id!"
+FROM servers AS v
+WHERE v.whmcs_id IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM services AS s WHERE s.server_id = v.id)
+ORDER BY v.whmcs_id
+        "#
+    )
+    .fetch_all(target_pool)
+    .await?;
+
+    let integrity = vec![
+        IntegrityCheck::new("orphaned_services", orphaned_services),
+        IntegrityCheck::new(
+            "ips_missing_server",
+            assigned_ips
+                .into_iter()
+                .filter(|id| unassigned_ips.contains(id)),
+        ),
+        IntegrityCheck::new("users_without_services", users_without_services),
+        IntegrityCheck::new("servers_without_services", servers_without_services),
+    ];
+    let passed = tables.iter().all(TableCheck::passed)
+        && integrity.iter().all(|check| check.violations == 0);
+
+    Ok(Reconciliation {
+        passed,
+        tables,
+        integrity,
+    })
+}
+
+/// Reads the distinct WHMCS keys of the rows migrated into a table.
+///
+async fn source_keys(source_pool: &MySqlPool, table: DashboardTable) -> Result<BTreeSet<i64>> {
+    let query = source_query(table);
+    if table == DashboardTable::Templates {
+        return Ok(sqlx::query_as::<_, types::TemplateField>(query)
+            .fetch_all(source_pool)
+            .await?
+            .into_iter()
+            .flat_map(types::TemplateField::extract)
+            .map(|template| template.template_vmid as i64)
+            .collect());
+    }
+
+    let query = query.trim().trim_end_matches(';');
+    Ok(sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT DISTINCT CAST(id AS SIGNED) FROM ({query}) AS source"
+    ))
+    .fetch_all(source_pool)
+    .await?
+    .into_iter()
+    .collect())
+}
+
+/// Reads the WHMCS keys of the migrated rows of a table.
+///
+async fn target_keys(target_pool: &PgPool, table: DashboardTable) -> Result<BTreeSet<i64>> {
+    // Clients merged into another account count as migrated users.
+    let mut keys = BTreeSet::new();
+    if table == DashboardTable::Users {
+        keys = sqlx::query_scalar!(r#"SELECT whmcs_id::BIGINT AS "id!" FROM user_aliases"#)
+            .fetch_all(target_pool)
+            .await?
+            .into_iter()
+            .collect();
+    }
+
+    let key_name = target_key(table);
+    let mut builder = sqlx::QueryBuilder::<sqlx::Postgres>::default();
+    keys.extend(
+        builder
+            .push("SELECT ")
+            .push(key_name)
+            .push("::BIGINT FROM ")
+            .push(table)
+            .push(" WHERE ")
+            .push(key_name)
+            .push(" IS NOT NULL")
+            .build_query_scalar::<i64>()
+            .fetch_all(target_pool)
+            .await?,
+    );
+
+    Ok(keys)
+}
+
+/// Returns the column holding the amount of a table's rows, `None` for the
+/// tables holding no money.
+///
+fn amount_column(table: DashboardTable) -> Option<&'static str> {
+    match table {
+        DashboardTable::Invoices | DashboardTable::InvoiceItems | DashboardTable::Payments => {
+            Some("amount_cents")
+        }
+        _ => None,
+    }
+}
+
+/// Sums the amounts of the WHMCS rows migrated into a table.
+///
+async fn source_amount(
+    source_pool: &MySqlPool,
+    table: DashboardTable,
+    column: &str,
+) -> Result<i64> {
+    let query = source_query(table).trim().trim_end_matches(';');
+    Ok(sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT CAST(COALESCE(SUM({column}), 0) AS SIGNED) FROM ({query}) AS source"
+    ))
+    .fetch_one(source_pool)
+    .await?)
+}
+
+/// Sums the amounts of the migrated rows of a table.
+///
+async fn target_amount(target_pool: &PgPool, table: DashboardTable, column: &str) -> Result<i64> {
+    let key_name = target_key(table);
+    let mut builder = sqlx::QueryBuilder::<sqlx::Postgres>::default();
+    Ok(builder
+        .push("SELECT COALESCE(SUM(")
+        .push(column)
+        .push("), 0)::BIGINT FROM ")
+        .push(table)
+        .push(" WHERE ")
+        .push(key_name)
+        .push(" IS NOT NULL")
+        .build_query_scalar::<i64>()
+        .fetch_one(target_pool)
+        .await?)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_check_should_list_missing_and_unexpected_keys() {
+        // Arrange
+        let source = BTreeSet::from([1, 2, 3, 4]);
+        let target = BTreeSet::from([1, 2, 5]);
+
+        // Act
+        let check = TableCheck::new(DashboardTable::Servers, &source, &target);
+
+        // Assert
+        assert_eq!(
+            check,
+            TableCheck {
+                table: "servers".to_owned(),
+                source_rows: 4,
+                target_rows: 3,
+                source_amount_cents: None,
+                target_amount_cents: None,
+                missing_keys: vec![3, 4],
+                unexpected_keys: vec![5],
+            }
+        );
+        assert!(!check.passed());
+        assert!(TableCheck::new(DashboardTable::Servers, &source, &source).passed());
+    }
+
+    #[test]
+    fn table_check_should_fail_on_different_amounts() {
+        // Arrange
+        let keys = BTreeSet::from([1, 2, 3]);
+
+        // Act
+        let check =
+            TableCheck::new(DashboardTable::Invoices, &keys, &keys).with_amounts(1500, 1499);
+
+        // Assert
+        assert!(!check.passed());
+        assert!(
+            TableCheck::new(DashboardTable::Invoices, &keys, &keys)
+                .with_amounts(1500, 1500)
+                .passed()
+        );
+    }
+
+    #[test]
+    fn integrity_check_should_count_all_violations() {
+        // Act
+        let check = IntegrityCheck::new("users_without_services", 1..=50);
+
+        // Assert
+        assert_eq!(check.violations, 50);
+        assert_eq!(check.keys.len(), SAMPLE_SIZE);
+    }
+}
@@ -0,0 +1,391 @@
+//! This module holds the cleaning rules applied to the WHMCS rows right before
+//! they are loaded.
+//!
+//! The rules are read from a TOML file and are all disabled by default, so a
+//! run without a rules file loads the rows as they are. Every rule counts the
+//! rows it changed. Clients sharing an email are left to the
+//! `--duplicate-emails` policy of the user loader.
+
+use crate::etl::types;
+use dashboard_common::prelude::{Error, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+/// Cleaning rules, as configured in the rules file.
+///
+/// # Fields
+///
+/// * `country_codes`: Uppercases two-letter country codes and replaces the
+///   names listed in `countries` with their codes.
+/// * `countries`: Code of each country name found in WHMCS, matched
+///   case-insensitively.
+/// * `phone_numbers`: Keeps only the digits of phone numbers and their
+///   leading `+`.
+/// * `lowercase_emails`: Trims and lowercases emails.
+/// * `dedup_emails`: Deprecated and ignored, the clients sharing an email are
+///   handled by `--duplicate-emails` regardless of case.
+/// * `service_statuses`: Dashboard status of each WHMCS service status.
+/// * `server_statuses`: Dashboard status of each WHMCS server status.
+/// * `hostnames`: Lowercases hostnames and replaces the characters they can't
+///   contain with `-`.
+///
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    pub country_codes: bool,
+    pub countries: HashMap<String, String>,
+    pub phone_numbers: bool,
+    pub lowercase_emails: bool,
+    pub dedup_emails: bool,
+    pub service_statuses: HashMap<String, String>,
+    pub server_statuses: HashMap<String, String>,
+    pub hostnames: bool,
+}
+
+/// Represents the rules counted in the statistics.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    CountryCode,
+    PhoneNumber,
+    LowercaseEmail,
+    ServiceStatus,
+    ServerStatus,
+    Hostname,
+}
+
+/// Represents the count of the rows changed per rule.
+///
+pub type RuleStatistic = BTreeMap<Rule, u64>;
+
+/// Applies the rules to the chunks of a run, shared by the loading closures.
+///
+/// # Fields
+///
+/// * `rules`: Configured rules.
+/// * `state`: Rows changed per rule so far in the run.
+///
+#[derive(Debug, Default)]
+pub struct Transformer {
+    rules: Rules,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    statistic: RuleStatistic,
+}
+
+impl Transformer {
+    /// Creates new `Transformer` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules`: Rules to apply.
+    ///
+    pub fn new(mut rules: Rules) -> Self {
+        if rules.dedup_emails {
+            tracing::warn!(
+                "The dedup_emails rule is ignored, clients sharing an email are handled by \
+                 --duplicate-emails."
+            );
+        }
+        rules.countries = rules
+            .countries
+            .into_iter()
+            .map(|(name, code)| (name.trim().to_lowercase(), code.trim().to_uppercase()))
+            .collect();
+
+        Self {
+            rules,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Reads the rules from a TOML file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: Path of the rules file.
+    ///
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let rules = toml::from_str::<Rules>(&content).map_err(|error| {
+            Error::Any(format!("Invalid rules file {}: {error}", path.display()))
+        })?;
+
+        Ok(Self::new(rules))
+    }
+
+    /// Clears the statistics before a new run.
+    ///
+    pub fn reset(&self) {
+        *self.state() = State::default();
+    }
+
+    /// Returns the count of the rows changed per rule so far.
+    ///
+    pub fn statistic(&self) -> RuleStatistic {
+        self.state().statistic.clone()
+    }
+
+    /// Cleans the contact details of a chunk of clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `clients`: Chunk of clients read from WHMCS.
+    ///
+    /// # Returns
+    ///
+    /// Clients to insert.
+    ///
+    pub fn clean_clients(&self, clients: Vec<types::Client>) -> Vec<types::Client> {
+        let mut state = self.state();
+        clients
+            .into_iter()
+            .map(|mut client| {
+                if self.rules.country_codes {
+                    let code = self.country_code(&client.country);
+                    state.update(Rule::CountryCode, &mut client.country, code);
+                }
+                if self.rules.phone_numbers {
+                    let phone_number = format_phone_number(&client.phonenumber);
+                    state.update(Rule::PhoneNumber, &mut client.phonenumber, phone_number);
+                }
+                if self.rules.lowercase_emails {
+                    let email = client.email.trim().to_lowercase();
+                    state.update(Rule::LowercaseEmail, &mut client.email, email);
+                }
+
+                client
+            })
+            .collect()
+    }
+
+    /// Cleans the hostnames and maps the statuses of a chunk of servers.
+    ///
+    /// # Arguments
+    ///
+    /// * `records`: Chunk of servers read from WHMCS.
+    ///
+    pub fn clean_servers(&self, records: Vec<types::VmRecord>) -> Vec<types::VmRecord> {
+        let mut state = self.state();
+        records
+            .into_iter()
+            .map(|mut record| {
+                if self.rules.hostnames {
+                    let hostname = sanitize_hostname(&record.hostname, record.vmid);
+                    state.update(Rule::Hostname, &mut record.hostname, hostname);
+                }
+                if let Some(status) = self.rules.server_statuses.get(&record.status) {
+                    state.update(Rule::ServerStatus, &mut record.status, status.clone());
+                }
+
+                record
+            })
+            .collect()
+    }
+
+    /// Maps the statuses of a chunk of services.
+    ///
+    /// # Arguments
+    ///
+    /// * `services`: Chunk of services read from WHMCS.
+    ///
+    pub fn clean_services(&self, services: Vec<types::Service>) -> Vec<types::Service> {
+        let mut state = self.state();
+        services
+            .into_iter()
+            .map(|mut service| {
+                if let Some(status) = self.rules.service_statuses.get(&service.domainstatus) {
+                    let status = status.clone();
+                    state.update(Rule::ServiceStatus, &mut service.domainstatus, status);
+                }
+
+                service
+            })
+            .collect()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the code of a country, or the trimmed country if it's unknown.
+    ///
+    fn country_code(&self, country: &str) -> String {
+        let country = country.trim();
+        match self.rules.countries.get(&country.to_lowercase()) {
+            Some(code) => code.clone(),
+            None if country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic()) => {
+                country.to_uppercase()
+            }
+            None => country.to_owned(),
+        }
+    }
+}
+
+impl State {
+    /// Replaces the value and counts the rule if it changed anything.
+    ///
+    fn update(&mut self, rule: Rule, value: &mut String, cleaned: String) {
+        if *value != cleaned {
+            *value = cleaned;
+            self.count(rule);
+        }
+    }
+
+    fn count(&mut self, rule: Rule) {
+        *self.statistic.entry(rule).or_default() += 1;
+    }
+}
+
+/// Keeps only the digits of a phone number and its leading `+`.
+///
+fn format_phone_number(phone_number: &str) -> String {
+    let digits = phone_number
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect::<String>();
+    match phone_number.trim_start().starts_with('+') && !digits.is_empty() {
+        true => format!("+{digits}"),
+        false => digits,
+    }
+}
+
+/// Lowercases a hostname and replaces the characters it can't contain with
+/// `-`. Hostnames left empty are named after the VMID.
+///
+fn sanitize_hostname(hostname: &str, vmid: u32) -> String {
+    let hostname = hostname
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '.' => c,
+            _ => '-',
+        })
+        .collect::<String>();
+    match hostname.trim_matches(['-', '.']) {
+        "" => format!("vm-{vmid}"),
+        hostname => hostname.to_owned(),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(id: i32, email: &str, country: &str, phone_number: &str) -> types::Client {
+        types::Client {
+            id,
+            firstname: "John".to_owned(),
+            lastname: "Doe".to_owned(),
+            email: email.to_owned(),
+            address1: "Main Street 1".to_owned(),
+            city: "Berlin".to_owned(),
+            state: "Berlin".to_owned(),
+            postcode: "10115".to_owned(),
+            country: country.to_owned(),
+            phonenumber: phone_number.to_owned(),
+            password: "hash".to_owned(),
+        }
+    }
+
+    #[test]
+    fn rules_should_parse_from_toml() {
+        // Arrange
+        let content = r#"
+country_codes = true
+dedup_emails = true
+
+[countries]
+Germany = "DE"
+
+[service_statuses]
+Fraud = "Terminated"
+"#;
+
+        // Act
+        let rules = toml::from_str::<Rules>(content).unwrap();
+
+        // Assert
+        assert!(rules.country_codes && rules.dedup_emails);
+        assert!(!rules.phone_numbers && !rules.hostnames);
+        assert_eq!(rules.countries["Germany"], "DE");
+        assert_eq!(rules.service_statuses["Fraud"], "Terminated");
+    }
+
+    #[test]
+    fn clean_clients_should_normalize_contact_details() {
+        // Arrange
+        let transformer = Transformer::new(Rules {
+            country_codes: true,
+            countries: HashMap::from([("Germany".to_owned(), "de".to_owned())]),
+            phone_numbers: true,
+            lowercase_emails: true,
+            dedup_emails: true,
+            ..Rules::default()
+        });
+        let clients = vec![
+            client(1, "John@Example.com", " germany", "+49 (30) 123-45"),
+            client(2, "john@example.com", "fr", "030 12345"),
+            client(3, "jane@example.com", "PL", "+48.123"),
+        ];
+
+        // Act
+        let clients = transformer.clean_clients(clients);
+
+        // Assert
+        let cleaned = clients
+            .iter()
+            .map(|client| (client.id, client.email.as_str(), client.country.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cleaned,
+            vec![
+                (1, "john@example.com", "DE"),
+                (2, "john@example.com", "FR"),
+                (3, "jane@example.com", "PL")
+            ]
+        );
+        assert_eq!(clients[0].phonenumber, "+493012345");
+        assert_eq!(clients[1].phonenumber, "03012345");
+        assert_eq!(clients[2].phonenumber, "+48123");
+        assert_eq!(
+            transformer.statistic(),
+            RuleStatistic::from([
+                (Rule::CountryCode, 2),
+                (Rule::PhoneNumber, 3),
+                (Rule::LowercaseEmail, 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn clean_servers_should_sanitize_hostnames() {
+        // Arrange
+        let transformer = Transformer::new(Rules {
+            hostnames: true,
+            server_statuses: HashMap::from([("Stopped".to_owned(), "offline".to_owned())]),
+            ..Rules::default()
+        });
+        let records = vec![
+            types::VmRecord::new(1, 100, None, "Web_Server 01.", "Stopped"),
+            types::VmRecord::new(2, 101, None, "__", "Running"),
+        ];
+
+        // Act
+        let records = transformer.clean_servers(records);
+
+        // Assert
+        assert_eq!(records[0].hostname, "web-server-01");
+        assert_eq!(records[0].status, "offline");
+        assert_eq!(records[1].hostname, "vm-101");
+        assert_eq!(records[1].status, "Running");
+    }
+}
@@ -0,0 +1,713 @@
+//! This module holds the sources the "Extract" phase reads the records from.
+//!
+//! A `Source` streams the typed records of every logical entity, one Dashboard
+//! table at a time, so the loaders don't depend on where the records come
+//! from. `WhmcsSource` queries the WHMCS MySQL database, `VirtualizorSource`
+//! the Virtualizor one, and `DirectorySource` reads CSV or JSON exports, for
+//! hosts running neither or importing only some of the tables.
+
+use crate::etl::migration::source_query;
+use crate::etl::preflight::{self, SchemaDiff};
+use crate::etl::throttle::{self, Throttle};
+use crate::etl::types::DashboardTable;
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::{Error, Result};
+use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream};
+use futures::{FutureExt, SinkExt, StreamExt};
+use serde::Deserializer;
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+use sqlx::mysql::MySqlRow;
+use sqlx::{MySqlPool, Row};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Number of records a source reads ahead of the loaders.
+const BUFFER_SIZE: usize = 1024;
+
+/// Represents the kinds of source databases.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SourceKind {
+    /// WHMCS with the Proxmox module.
+    Whmcs,
+    /// Virtualizor, migrating its users, VMs, plans and IP pools only.
+    Virtualizor,
+}
+
+/// Represents a record read from a source.
+///
+#[derive(Debug, PartialEq)]
+pub enum Record<S> {
+    /// The decoded record.
+    Decoded(S),
+    /// A record that can't be decoded, with its source ID, or zero when it has
+    /// none, and the decoding error.
+    Undecodable { source_id: i64, error: String },
+}
+
+/// Types of the records every source can read.
+///
+pub trait SourceRecord:
+    for<'r> sqlx::FromRow<'r, MySqlRow> + DeserializeOwned + Unpin + Send + 'static
+{
+}
+
+impl<S> SourceRecord for S where
+    S: for<'r> sqlx::FromRow<'r, MySqlRow> + DeserializeOwned + Unpin + Send + 'static
+{
+}
+
+/// An abstract interface for the origin of the migrated records.
+///
+pub trait Source: Send + Sync {
+    /// Streams the records migrated into a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: Dashboard table the records are migrated into.
+    /// * `since`: Start of the changes to read for a delta run. Sources that
+    ///   don't track changes return all the records.
+    ///
+    /// # Returns
+    ///
+    /// Stream of records, ending with an error if the source fails.
+    ///
+    fn records<S: SourceRecord>(
+        &self,
+        table: DashboardTable,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxStream<'static, Result<Record<S>>>;
+
+    /// Counts the records migrated into a table, to report the progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: Dashboard table the records are migrated into.
+    /// * `since`: Start of the changes to read for a delta run.
+    ///
+    /// # Returns
+    ///
+    /// Number of records, `None` if the source can't count them without
+    /// reading them.
+    ///
+    fn count(
+        &self,
+        table: DashboardTable,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'static, Result<Option<u64>>>;
+
+    /// Compares the source with the tables and columns the migration reads.
+    /// Sources without a schema to check report no difference.
+    ///
+    /// # Returns
+    ///
+    /// The missing tables and columns, empty if the source matches.
+    ///
+    fn check_schema(&self) -> BoxFuture<'static, Result<SchemaDiff>> {
+        futures::future::ready(Ok(SchemaDiff::default())).boxed()
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Reads the records from the WHMCS MySQL database.
+///
+#[derive(Debug, Clone)]
+pub struct WhmcsSource {
+    pub pool: MySqlPool,
+    pub throttle: Throttle,
+}
+
+impl WhmcsSource {
+    /// Creates new `WhmcsSource` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool`: Pool of the WHMCS database.
+    ///
+    pub fn new(pool: MySqlPool) -> Self {
+        Self {
+            pool,
+            throttle: Throttle::default(),
+        }
+    }
+
+    /// Limits the rate of the reads.
+    ///
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+}
+
+impl Source for WhmcsSource {
+    fn records<S: SourceRecord>(
+        &self,
+        table: DashboardTable,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxStream<'static, Result<Record<S>>> {
+        // Delta runs read only the rows changed since the given time.
+        let since = since.filter(|_| tracks_changes(table));
+        let query = match since {
+            Some(_) => {
+                let query = delta_query(table).trim().trim_end_matches(';');
+                format!("SELECT * FROM ({query}) AS source WHERE updated_at >= ?")
+            }
+            None => source_query(table).to_owned(),
+        };
+
+        fetch_rows(&self.pool, &self.throttle, query, since)
+    }
+
+    fn count(
+        &self,
+        table: DashboardTable,
+        since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'static, Result<Option<u64>>> {
+        let since = since.filter(|_| tracks_changes(table));
+        let query = match since {
+            Some(_) => format!(
+                "SELECT COUNT(*) FROM ({}) AS source WHERE updated_at >= ?",
+                delta_query(table).trim().trim_end_matches(';')
+            ),
+            None => format!(
+                "SELECT COUNT(*) FROM ({}) AS source",
+                source_query(table).trim().trim_end_matches(';')
+            ),
+        };
+
+        count_rows(&self.pool, query, since)
+    }
+
+    fn check_schema(&self) -> BoxFuture<'static, Result<SchemaDiff>> {
+        preflight::check_source(&self.pool, preflight::WHMCS_SCHEMA)
+    }
+}
+
+/// Counts the rows of a MySQL `COUNT(*)` query.
+///
+fn count_rows(
+    pool: &MySqlPool,
+    query: String,
+    since: Option<DateTime<Utc>>,
+) -> BoxFuture<'static, Result<Option<u64>>> {
+    let pool = pool.clone();
+    async move {
+        let mut count = sqlx::query_scalar::<_, i64>(&query);
+        if let Some(since) = since {
+            count = count.bind(since);
+        }
+
+        Ok(Some(count.fetch_one(&pool).await? as u64))
+    }
+    .boxed()
+}
+
+/// Streams the rows of a MySQL query.
+///
+/// The rows are fetched by a task owning the query, which stops once the
+/// stream is dropped or fails. Every batch of rows waits for the throttle,
+/// and the time spent waiting for the source is reported back to it, except
+/// for the first batch, which includes the execution of the query.
+///
+fn fetch_rows<S: SourceRecord>(
+    pool: &MySqlPool,
+    throttle: &Throttle,
+    query: String,
+    since: Option<DateTime<Utc>>,
+) -> BoxStream<'static, Result<Record<S>>> {
+    let pool = pool.clone();
+    let throttle = throttle.clone();
+    let (mut sender, receiver) = mpsc::channel(BUFFER_SIZE);
+    tokio::spawn(async move {
+        let mut source = sqlx::query(&query);
+        if let Some(since) = since {
+            source = source.bind(since);
+        }
+        let mut rows = source.fetch(&pool);
+        let (mut batch, mut latency, mut first) = (0, Duration::ZERO, true);
+        loop {
+            if batch == 0 {
+                throttle.acquire(throttle::BATCH_SIZE).await;
+            }
+            let started = Instant::now();
+            let Some(row) = rows.next().await else {
+                break;
+            };
+            latency += started.elapsed();
+            batch += 1;
+            if batch == throttle::BATCH_SIZE {
+                if !first {
+                    throttle.observe(latency);
+                }
+                (batch, latency, first) = (0, Duration::ZERO, false);
+            }
+
+            let record = row.map(|row| decode_row(&row)).map_err(Error::from);
+            let failed = record.is_err();
+            if sender.send(record).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    receiver.boxed()
+}
+
+/// Decodes a WHMCS row, keeping its ID when it can't be decoded.
+///
+fn decode_row<S: SourceRecord>(row: &MySqlRow) -> Record<S> {
+    match S::from_row(row) {
+        Ok(record) => Record::Decoded(record),
+        Err(error) => {
+            // Templates are read from custom fields, keyed by their product.
+            let source_id = row
+                .try_get::<i64, _>("id")
+                .or_else(|_| row.try_get::<u32, _>("id").map(i64::from))
+                .or_else(|_| row.try_get::<i64, _>("relid"))
+                .unwrap_or_default();
+            Record::Undecodable {
+                source_id,
+                error: error.to_string(),
+            }
+        }
+    }
+}
+
+/// Checks whether the source rows of a table carry the `updated_at` time of
+/// their last change in WHMCS.
+///
+fn tracks_changes(table: DashboardTable) -> bool {
+    matches!(
+        table,
+        DashboardTable::Users | DashboardTable::Servers | DashboardTable::Services
+    )
+}
+
+/// Returns the query reading the rows of a table for a delta run. Services
+/// are read whatever their status, so the ones suspended or cancelled since
+/// the previous run are updated too.
+///
+fn delta_query(table: DashboardTable) -> &'static str {
+    match table {
+        DashboardTable::Services => include_str!("sql/get_changed_services.sql"),
+        _ => source_query(table),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Reads the records from the Virtualizor MySQL database.
+///
+/// Virtualizor users, VMs, plans, IP pools and IPs are migrated into the
+/// users, servers, products, networks and IP addresses, keyed by their
+/// Virtualizor IDs, and every VM becomes a service of its owner. Virtualizor
+/// installs from OS images rather than template VMs, so the services are
+/// linked to the template given by its VMID, and left out without one. The
+/// other tables are left empty, and delta runs read the tables in full.
+///
+#[derive(Debug, Clone)]
+pub struct VirtualizorSource {
+    pub pool: MySqlPool,
+    pub throttle: Throttle,
+    pub template_vmid: Option<i32>,
+}
+
+impl VirtualizorSource {
+    /// Creates new `VirtualizorSource` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool`: Pool of the Virtualizor database.
+    ///
+    pub fn new(pool: MySqlPool) -> Self {
+        Self {
+            pool,
+            throttle: Throttle::default(),
+            template_vmid: None,
+        }
+    }
+
+    /// Limits the rate of the reads.
+    ///
+    pub fn with_throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Links the services to the template with the given Proxmox VMID.
+    ///
+    pub fn with_template(mut self, template_vmid: Option<i32>) -> Self {
+        self.template_vmid = template_vmid;
+        self
+    }
+
+    /// Returns the query reading the rows of a table from Virtualizor, if it
+    /// holds any.
+    ///
+    fn query(&self, table: DashboardTable) -> Option<String> {
+        let query = match table {
+            DashboardTable::Users => include_str!("sql/virtualizor/get_users.sql"),
+            DashboardTable::ProductGroups => include_str!("sql/virtualizor/get_plan_group.sql"),
+            DashboardTable::Products => include_str!("sql/virtualizor/get_plans.sql"),
+            DashboardTable::Servers => include_str!("sql/virtualizor/get_vps.sql"),
+            DashboardTable::Networks => include_str!("sql/virtualizor/get_ip_pools.sql"),
+            DashboardTable::IpAddresses => include_str!("sql/virtualizor/get_ips.sql"),
+            DashboardTable::Templates => {
+                let template_vmid = self.template_vmid?;
+                return Some(
+                    include_str!("sql/virtualizor/get_plan_templates.sql")
+                        .replace("{template_vmid}", &template_vmid.to_string()),
+                );
+            }
+            DashboardTable::Services => include_str!("sql/virtualizor/get_services.sql"),
+            _ => return None,
+        };
+
+        Some(query.to_owned())
+    }
+}
+
+impl Source for VirtualizorSource {
+    fn records<S: SourceRecord>(
+        &self,
+        table: DashboardTable,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxStream<'static, Result<Record<S>>> {
+        match self.query(table) {
+            Some(query) => fetch_rows(&self.pool, &self.throttle, query, None),
+            None => stream::empty().boxed(),
+        }
+    }
+
+    fn count(
+        &self,
+        table: DashboardTable,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'static, Result<Option<u64>>> {
+        match self.query(table) {
+            Some(query) => {
+                let query = query.trim().trim_end_matches(';');
+                count_rows(
+                    &self.pool,
+                    format!("SELECT COUNT(*) FROM ({query}) AS source"),
+                    None,
+                )
+            }
+            None => futures::future::ready(Ok(Some(0))).boxed(),
+        }
+    }
+
+    fn check_schema(&self) -> BoxFuture<'static, Result<SchemaDiff>> {
+        preflight::check_source(&self.pool, preflight::VIRTUALIZOR_SCHEMA)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Reads the records from a directory of exports, one file per table named
+/// after it, such as `users.csv` or `users.json`.
+///
+/// CSV files start with a header naming the WHMCS columns, and JSON files hold
+/// an array of objects with the same keys. Tables without a file are left
+/// empty, and delta runs read the files in full.
+///
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    pub dir: PathBuf,
+}
+
+impl DirectorySource {
+    /// Creates new `DirectorySource` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir`: Directory holding the exports.
+    ///
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Source for DirectorySource {
+    fn records<S: SourceRecord>(
+        &self,
+        table: DashboardTable,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxStream<'static, Result<Record<S>>> {
+        let dir = self.dir.clone();
+        let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+        tokio::task::spawn_blocking(move || read_export::<S>(&dir, table, sender));
+
+        receiver.boxed()
+    }
+
+    fn count(
+        &self,
+        _table: DashboardTable,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'static, Result<Option<u64>>> {
+        // The exports are only parsed once, while they are migrated.
+        futures::future::ready(Ok(None)).boxed()
+    }
+}
+
+/// Streams the export of a table, preferring the CSV file over the JSON one.
+///
+/// The file is read on a blocking thread, one record at a time, and every
+/// record waits for room in the channel, so a large export is never held in
+/// memory. Reading stops once the stream is dropped or the file fails, the
+/// failure being the last item of the stream.
+///
+fn read_export<S: SourceRecord>(
+    dir: &Path,
+    table: DashboardTable,
+    mut sender: mpsc::Sender<Result<Record<S>>>,
+) {
+    let mut send = |record| futures::executor::block_on(sender.send(record)).is_ok();
+    let csv_path = dir.join(format!("{table}.csv"));
+    let json_path = dir.join(format!("{table}.json"));
+    let result = if csv_path.exists() {
+        read_csv(&csv_path, &mut send)
+    } else if json_path.exists() {
+        read_json(&json_path, &mut send)
+    } else {
+        tracing::info!(%table, dir = %dir.display(), "No export of the table, nothing to read.");
+        Ok(())
+    };
+
+    if let Err(error) = result {
+        send(Err(error));
+    }
+}
+
+/// Reads the rows of a CSV export, passing every record to `send` until it
+/// returns `false`.
+///
+fn read_csv<S: SourceRecord>(
+    path: &Path,
+    send: &mut impl FnMut(Result<Record<S>>) -> bool,
+) -> Result<()> {
+    let mut reader = csv::Reader::from_path(path).map_err(std::io::Error::from)?;
+    let headers = reader.headers().map_err(std::io::Error::from)?.clone();
+    let id_index = headers.iter().position(|header| header == "id");
+
+    for row in reader.records() {
+        let row = row.map_err(std::io::Error::from)?;
+        let record = match row.deserialize::<S>(Some(&headers)) {
+            Ok(record) => Record::Decoded(record),
+            Err(error) => Record::Undecodable {
+                source_id: id_index
+                    .and_then(|index| row.get(index))
+                    .and_then(|id| id.trim().parse().ok())
+                    .unwrap_or_default(),
+                error: error.to_string(),
+            },
+        };
+        if !send(Ok(record)) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the objects of a JSON export, passing every record to `send` until
+/// it returns `false`.
+///
+fn read_json<S: SourceRecord>(
+    path: &Path,
+    send: &mut impl FnMut(Result<Record<S>>) -> bool,
+) -> Result<()> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut deserializer = serde_json::Deserializer::from_reader(file);
+    let mut stopped = false;
+    let result = deserializer
+        .deserialize_seq(ExportVisitor(|value: serde_json::Value| {
+            let source_id = value
+                .get("id")
+                .and_then(serde_json::Value::as_i64)
+                .unwrap_or_default();
+            let record = match serde_json::from_value::<S>(value) {
+                Ok(record) => Record::Decoded(record),
+                Err(error) => Record::Undecodable {
+                    source_id,
+                    error: error.to_string(),
+                },
+            };
+            stopped = !send(Ok(record));
+            !stopped
+        }))
+        .and_then(|_| deserializer.end());
+
+    match result {
+        // The rest of the file is left unread once the stream is dropped.
+        Err(_) if stopped => Ok(()),
+        result => result
+            .map_err(|error| Error::Any(format!("Invalid export {}: {error}", path.display()))),
+    }
+}
+
+/// Visits the array of a JSON export one object at a time, passing every
+/// object to the callback until it returns `false`.
+///
+struct ExportVisitor<F>(F);
+
+impl<'de, F> Visitor<'de> for ExportVisitor<F>
+where
+    F: FnMut(serde_json::Value) -> bool,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of records")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> core::result::Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            if !(self.0)(value) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::etl::types;
+    use futures::TryStreamExt;
+
+    #[tokio::test]
+    async fn directory_source_should_read_csv_exports() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("product_groups.csv"),
+            "id,name\n1,VPS\nx,Broken\n3,\"Dedicated, legacy\"\n",
+        )
+        .unwrap();
+        let source = DirectorySource::new(dir.path());
+
+        // Act
+        let records = source
+            .records::<types::ProductGroup>(DashboardTable::ProductGroups, None)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(records.len(), 3);
+        assert!(
+            matches!(&records[0], Record::Decoded(group) if group.id == 1 && group.name == "VPS")
+        );
+        assert!(matches!(
+            &records[1],
+            Record::Undecodable { source_id: 0, .. }
+        ));
+        assert!(matches!(&records[2], Record::Decoded(group) if group.name == "Dedicated, legacy"));
+    }
+
+    #[tokio::test]
+    async fn directory_source_should_read_json_exports() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("product_groups.json"),
+            r#"[{"id": 1, "name": "VPS"}, {"id": 2, "name": null}]"#,
+        )
+        .unwrap();
+        let source = DirectorySource::new(dir.path());
+
+        // Act
+        let records = source
+            .records::<types::ProductGroup>(DashboardTable::ProductGroups, None)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        let missing = source
+            .records::<types::Client>(DashboardTable::Users, None)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(records.len(), 2);
+        assert!(matches!(&records[0], Record::Decoded(group) if group.name == "VPS"));
+        assert!(matches!(
+            &records[1],
+            Record::Undecodable { source_id: 2, .. }
+        ));
+        assert!(missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn directory_source_should_stream_records_before_broken_export() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("product_groups.json"),
+            r#"[{"id": 1, "name": "VPS"}, {"id": 2, "name": "#,
+        )
+        .unwrap();
+        let source = DirectorySource::new(dir.path());
+
+        // Act
+        let records = source
+            .records::<types::ProductGroup>(DashboardTable::ProductGroups, None)
+            .collect::<Vec<_>>()
+            .await;
+
+        // Assert
+        assert_eq!(records.len(), 2);
+        assert!(matches!(&records[0], Ok(Record::Decoded(group)) if group.name == "VPS"));
+        assert!(
+            matches!(&records[1], Err(Error::Any(message)) if message.starts_with("Invalid export"))
+        );
+    }
+
+    #[tokio::test]
+    async fn virtualizor_should_read_templates_only_when_given() {
+        // Arrange
+        let pool = MySqlPool::connect_lazy("mysql://localhost/virtualizor").unwrap();
+        let source = VirtualizorSource::new(pool);
+        let tables = |source: &VirtualizorSource| {
+            crate::etl::migration::STEPS
+                .into_iter()
+                .filter(|table| source.query(*table).is_some())
+                .collect::<Vec<_>>()
+        };
+
+        // Act
+        let without_template = tables(&source);
+        let source = source.with_template(Some(9000));
+        let with_template = tables(&source);
+
+        // Assert
+        assert_eq!(
+            without_template,
+            vec![
+                DashboardTable::Users,
+                DashboardTable::ProductGroups,
+                DashboardTable::Products,
+                DashboardTable::Servers,
+                DashboardTable::Networks,
+                DashboardTable::IpAddresses,
+                DashboardTable::Services,
+            ]
+        );
+        assert!(with_template.contains(&DashboardTable::Templates));
+        assert!(
+            source
+                .query(DashboardTable::Templates)
+                .unwrap()
+                .contains("'Virtualizor|', 9000")
+        );
+    }
+}
@@ -10,7 +10,7 @@ SELECT c.id,
        c.phonenumber,
        c.password,
        c.created_at,
-       c.updated_at
+       GREATEST(COALESCE(c.updated_at, h.updated_at), COALESCE(h.updated_at, c.updated_at)) AS updated_at
 FROM tblclients AS c
          JOIN tblhosting AS h ON c.id = h.userid
 WHERE h.domainstatus = 'Active'
@@ -0,0 +1,7 @@
+SELECT id,
+       domainstatus,
+       userid,
+       packageid,
+       updated_at
+FROM tblhosting
+;
\ No newline at end of file
@@ -0,0 +1,11 @@
+SELECT it.id,
+       it.invoiceid,
+       it.type                                 AS item_type,
+       it.relid,
+       it.description,
+       CAST(ROUND(it.amount * 100) AS SIGNED) AS amount_cents
+FROM tblinvoiceitems AS it
+         JOIN tblinvoices AS i ON i.id = it.invoiceid
+WHERE i.status <> 'Draft'
+  AND i.userid IN (SELECT userid FROM tblhosting WHERE domainstatus = 'Active')
+;
\ No newline at end of file
@@ -0,0 +1,18 @@
+SELECT i.id,
+       i.userid,
+       i.invoicenum,
+       i.status,
+       CAST(ROUND(i.total * 100) AS SIGNED)                      AS amount_cents,
+       CAST(ROUND((i.credit + COALESCE((SELECT SUM(a.amountin - a.amountout)
+                                        FROM tblaccounts AS a
+                                        WHERE a.invoiceid = i.id), 0)) * 100)
+           AS SIGNED)                                            AS paid_cents,
+       cur.code                                                  AS currency,
+       CAST(i.date AS DATETIME)                                  AS created_at,
+       CASE WHEN i.datepaid > '1970-01-01' THEN i.datepaid END AS paid_at
+FROM tblinvoices AS i
+         JOIN tblclients AS c ON c.id = i.userid
+         JOIN tblcurrencies AS cur ON cur.id = c.currency
+WHERE i.status <> 'Draft'
+  AND i.userid IN (SELECT userid FROM tblhosting WHERE domainstatus = 'Active')
+;
\ No newline at end of file
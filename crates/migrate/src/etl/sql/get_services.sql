@@ -1,7 +1,8 @@
 SELECT id,
        domainstatus,
        userid,
-       packageid
+       packageid,
+       updated_at
 FROM tblhosting
 WHERE domainstatus = 'Active'
 ;
\ No newline at end of file
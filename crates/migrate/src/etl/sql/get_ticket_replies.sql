@@ -0,0 +1,10 @@
+SELECT r.id,
+       r.tid,
+       r.name,
+       r.admin,
+       r.message,
+       r.date
+FROM tblticketreplies AS r
+         JOIN tbltickets AS t ON t.id = r.tid
+WHERE t.userid IN (SELECT userid FROM tblhosting WHERE domainstatus = 'Active')
+;
\ No newline at end of file
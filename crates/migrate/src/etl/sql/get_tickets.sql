@@ -0,0 +1,12 @@
+SELECT t.id,
+       t.tid,
+       t.userid,
+       t.title,
+       t.message,
+       t.status,
+       t.urgency,
+       t.date,
+       CASE WHEN t.lastreply > '1970-01-01' THEN t.lastreply END AS lastreply
+FROM tbltickets AS t
+WHERE t.userid IN (SELECT userid FROM tblhosting WHERE domainstatus = 'Active')
+;
\ No newline at end of file
@@ -0,0 +1,20 @@
+SELECT a.id,
+       a.invoiceid,
+       a.gateway,
+       CASE
+           WHEN a.transid <> ''
+               AND (SELECT COUNT(*)
+                    FROM tblaccounts AS t
+                    WHERE t.gateway = a.gateway
+                      AND t.transid = a.transid) = 1
+               THEN a.transid
+           ELSE CONCAT('whmcs-', a.id)
+           END                                 AS reference,
+       CAST(ROUND(a.amountin * 100) AS SIGNED) AS amount_cents,
+       a.date
+FROM tblaccounts AS a
+         JOIN tblinvoices AS i ON i.id = a.invoiceid
+WHERE a.amountin > 0
+  AND i.status <> 'Draft'
+  AND i.userid IN (SELECT userid FROM tblhosting WHERE domainstatus = 'Active')
+;
\ No newline at end of file
@@ -0,0 +1,6 @@
+SELECT p.ippid       AS id,
+       p.ippool_name AS title,
+       p.gateway,
+       p.netmask     AS mask
+FROM ippool AS p
+;
\ No newline at end of file
@@ -0,0 +1,6 @@
+SELECT i.ipid             AS id,
+       i.ippid            AS pool_id,
+       i.ip               AS ipaddress,
+       NULLIF(i.vpsid, 0) AS server_id
+FROM ips AS i
+;
\ No newline at end of file
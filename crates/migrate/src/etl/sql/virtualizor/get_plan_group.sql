@@ -0,0 +1,3 @@
+SELECT 0             AS id,
+       'Virtualizor' AS name
+;
\ No newline at end of file
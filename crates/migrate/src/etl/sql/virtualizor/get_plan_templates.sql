@@ -0,0 +1,4 @@
+SELECT p.plid                                     AS relid,
+       CONCAT('Virtualizor|', {template_vmid})    AS fieldoptions
+FROM plans AS p
+;
\ No newline at end of file
@@ -0,0 +1,5 @@
+SELECT p.plid      AS id,
+       0           AS gid,
+       p.plan_name AS name
+FROM plans AS p
+;
\ No newline at end of file
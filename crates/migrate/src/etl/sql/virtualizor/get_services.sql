@@ -0,0 +1,6 @@
+SELECT v.vpsid                                    AS id,
+       IF(v.suspended = 0, 'Active', 'Suspended') AS domainstatus,
+       v.uid                                      AS userid,
+       v.plid                                     AS packageid
+FROM vps AS v
+;
\ No newline at end of file
@@ -0,0 +1,15 @@
+SELECT u.uid                 AS id,
+       COALESCE(u.fname, '') AS firstname,
+       COALESCE(u.lname, '') AS lastname,
+       u.email,
+       ''                    AS address1,
+       ''                    AS city,
+       ''                    AS state,
+       ''                    AS postcode,
+       ''                    AS country,
+       ''                    AS phonenumber,
+       u.password
+FROM users AS u
+WHERE u.type = 0
+  AND EXISTS (SELECT 1 FROM vps AS v WHERE v.uid = u.uid)
+;
\ No newline at end of file
@@ -0,0 +1,8 @@
+SELECT v.vpsid                                    AS id,
+       CAST(SUBSTRING(v.vps_name, 2) AS SIGNED)   AS vmid,
+       s.server_name                              AS node,
+       v.hostname,
+       IF(v.suspended = 0, 'Active', 'Suspended') AS status
+FROM vps AS v
+         LEFT JOIN servers AS s ON v.serid = s.serid
+;
\ No newline at end of file
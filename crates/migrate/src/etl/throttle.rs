@@ -0,0 +1,173 @@
+//! This module limits the load the migration puts on the source database, so
+//! it can run against a live WHMCS without starving it.
+//!
+//! Rows are read at most at the given rate, and the time spent waiting for
+//! the source is compared with its usual latency. When the source slows down,
+//! the reads back off for a while that doubles as long as it stays slow, and
+//! halves again once it recovers.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Number of rows read between two checks of the throttle.
+pub const BATCH_SIZE: u64 = 128;
+
+/// Ratio of the usual latency above which the source is considered loaded.
+const SLOW_FACTOR: f64 = 3.0;
+
+/// First pause once the source is loaded.
+const MIN_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Longest pause between two batches.
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Shortest usual latency, so batches read from memory don't make any wait
+/// look slow.
+const MIN_BASELINE: Duration = Duration::from_millis(1);
+
+/// Shares the read budget between every query of a source.
+///
+/// # Fields
+///
+/// * `max_rate`: Rows read per second at most, `None` for no limit.
+/// * `adaptive`: Whether the reads back off when the source slows down.
+/// * `state`: Pacing and latency of the reads so far.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Throttle {
+    max_rate: Option<u32>,
+    adaptive: bool,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_read: Option<Instant>,
+    baseline: Option<Duration>,
+    backoff: Duration,
+}
+
+impl Throttle {
+    /// Creates new `Throttle` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_rate`: Rows read per second at most, `None` for no limit.
+    /// * `adaptive`: Whether the reads back off when the source slows down.
+    ///
+    pub fn new(max_rate: Option<u32>, adaptive: bool) -> Self {
+        Self {
+            max_rate: max_rate.filter(|rate| *rate > 0),
+            adaptive,
+            state: Arc::default(),
+        }
+    }
+
+    /// Waits until a batch of rows may be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `rows`: Rows of the batch.
+    ///
+    pub async fn acquire(&self, rows: u64) {
+        let wait = self.reserve(rows, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Records the time spent waiting for a batch of rows, adjusting the
+    /// backoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `latency`: Time spent waiting for the source.
+    ///
+    pub fn observe(&self, latency: Duration) {
+        if !self.adaptive {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let baseline = *state.baseline.get_or_insert(latency);
+
+        if latency.as_secs_f64() > baseline.max(MIN_BASELINE).as_secs_f64() * SLOW_FACTOR {
+            let backoff = (state.backoff * 2).clamp(MIN_BACKOFF, MAX_BACKOFF);
+            if backoff != state.backoff {
+                tracing::warn!(
+                    ?latency,
+                    ?baseline,
+                    ?backoff,
+                    "Source slowed down, backing off."
+                );
+            }
+            state.backoff = backoff;
+        } else {
+            // The usual latency follows the source slowly, so a loaded source
+            // doesn't become the norm.
+            state.baseline = Some(baseline.mul_f64(0.9) + latency.mul_f64(0.1));
+            state.backoff = match state.backoff / 2 {
+                backoff if backoff < MIN_BACKOFF => Duration::ZERO,
+                backoff => backoff,
+            };
+        }
+    }
+
+    /// Returns the time to wait before the batch is read, and reserves its
+    /// share of the rate.
+    ///
+    fn reserve(&self, rows: u64, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(rate) = self.max_rate else {
+            return state.backoff;
+        };
+
+        let start = state.next_read.map_or(now, |next| next.max(now));
+        state.next_read = Some(start + Duration::from_secs_f64(rows as f64 / rate as f64));
+
+        (start - now) + state.backoff
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve_should_spread_batches_over_the_rate() {
+        // Arrange
+        let throttle = Throttle::new(Some(100), false);
+        let now = Instant::now();
+
+        // Act
+        let first = throttle.reserve(50, now);
+        let second = throttle.reserve(50, now);
+        let third = throttle.reserve(50, now + Duration::from_secs(2));
+
+        // Assert
+        assert_eq!(first, Duration::ZERO);
+        assert_eq!(second, Duration::from_millis(500));
+        assert_eq!(third, Duration::ZERO);
+    }
+
+    #[test]
+    fn observe_should_back_off_while_the_source_is_slow() {
+        // Arrange
+        let throttle = Throttle::new(None, true);
+        let now = Instant::now();
+        throttle.observe(Duration::from_millis(10));
+
+        // Act
+        throttle.observe(Duration::from_millis(100));
+        throttle.observe(Duration::from_millis(100));
+        let slow = throttle.reserve(BATCH_SIZE, now);
+        throttle.observe(Duration::from_millis(10));
+        throttle.observe(Duration::from_millis(10));
+        let recovered = throttle.reserve(BATCH_SIZE, now);
+
+        // Assert
+        assert_eq!(slow, MIN_BACKOFF * 2);
+        assert_eq!(recovered, Duration::ZERO);
+    }
+}
@@ -5,10 +5,15 @@
 //! the associated `impl` blocks contain the core transformation logic.
 //! This includes parsing complex fields, converting types, and reshaping the
 //! data to fit the target schema before it is passed to the "Load" phase.
+//! The rows of the Dashboard tables derive `PgBulkInsert`, naming the columns
+//! their fields are inserted into.
+
+use crate::etl::bulk::PgBulkInsert;
 
 /// Represents tables in the target database, used for logging and statistics.
 ///
-#[derive(Clone, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, clap::ValueEnum)]
+#[value(rename_all = "snake_case")]
 pub enum DashboardTable {
     Users,
     ProductGroups,
@@ -22,6 +27,11 @@ pub enum DashboardTable {
     Templates,
     CustomValues,
     ConfigValues,
+    Invoices,
+    InvoiceItems,
+    Payments,
+    Tickets,
+    TicketReplies,
 }
 
 impl std::fmt::Display for DashboardTable {
@@ -39,6 +49,11 @@ impl std::fmt::Display for DashboardTable {
             DashboardTable::Templates => "templates",
             DashboardTable::CustomValues => "custom_values",
             DashboardTable::ConfigValues => "config_values",
+            DashboardTable::Invoices => "invoices",
+            DashboardTable::InvoiceItems => "invoice_items",
+            DashboardTable::Payments => "payments",
+            DashboardTable::Tickets => "tickets",
+            DashboardTable::TicketReplies => "ticket_replies",
         })
     }
 }
@@ -49,31 +64,83 @@ impl std::fmt::Debug for DashboardTable {
     }
 }
 
+impl serde::Serialize for DashboardTable {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 /// Represents the count of all changed fields per table.
 ///
 pub type Statistic = std::collections::HashMap<DashboardTable, u64>;
 
+/// Represents a WHMCS row left out of the migration.
+///
+/// # Fields
+///
+/// * `table`: Target table the row was meant for.
+/// * `source_id`: WHMCS id of the row.
+/// * `reason`: Why the row was skipped.
+///
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SkippedRow {
+    pub table: DashboardTable,
+    pub source_id: i64,
+    pub reason: String,
+}
+
+impl SkippedRow {
+    pub fn new(
+        table: DashboardTable,
+        source_id: impl Into<i64>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self {
+            table,
+            source_id: source_id.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
 /// Represents all necessary fields from the client row in the `MySQL` database.
+/// Users and admins edit the migrated users in the Dashboard, so delta runs
+/// don't update them.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow, PgBulkInsert)]
+#[bulk_insert(table = "users", conflict_key = "whmcs_id")]
 pub struct Client {
+    #[bulk_insert(column = "whmcs_id")]
     pub id: i32,
+    #[bulk_insert(column = "first_name", insert_only)]
     pub firstname: String,
+    #[bulk_insert(column = "last_name", insert_only)]
     pub lastname: String,
+    #[bulk_insert(insert_only)]
     pub email: String,
+    #[bulk_insert(column = "address", insert_only)]
     pub address1: String,
+    #[bulk_insert(insert_only)]
     pub city: String,
+    #[bulk_insert(insert_only)]
     pub state: String,
+    #[bulk_insert(column = "post_code", insert_only)]
     pub postcode: String,
+    #[bulk_insert(insert_only)]
     pub country: String,
+    #[bulk_insert(column = "phone_number", insert_only)]
     pub phonenumber: String,
+    #[bulk_insert(insert_only)]
     pub password: String,
 }
+
 /// Represents all necessary fields from the product group row in the `MySQL`
 /// database.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow, PgBulkInsert)]
+#[bulk_insert(table = "product_groups", conflict_key = "whmcs_id")]
 pub struct ProductGroup {
+    #[bulk_insert(column = "whmcs_id")]
     pub id: i32,
     pub name: String,
 }
@@ -90,7 +157,7 @@ impl ProductGroup {
 /// Represents all necessary fields from the product row in the `MySQL`
 /// database.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct Product {
     pub id: i32,
     pub gid: i32,
@@ -110,7 +177,7 @@ impl Product {
 /// Represents all necessary fields from the custom field row in the `MySQL`
 /// database.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct CustomField {
     pub id: i32,
     pub fieldname: String,
@@ -130,9 +197,12 @@ impl CustomField {
 /// Represents all necessary fields from the configurable option row in the
 /// `MySQL` database.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow, PgBulkInsert)]
+#[bulk_insert(table = "config_options", conflict_key = "whmcs_id")]
 pub struct ConfigOption {
+    #[bulk_insert(column = "whmcs_id")]
     pub id: i32,
+    #[bulk_insert(column = "name")]
     pub optionname: String,
 }
 
@@ -148,7 +218,7 @@ impl ConfigOption {
 /// Intermediate structure used before converting to the Dashboard's `Server`
 /// type.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct VmRecord {
     pub id: u32,
     pub vmid: u32,
@@ -170,14 +240,21 @@ impl VmRecord {
 }
 
 /// Represents a server entity in the Dashboard application, ready for insertion
-/// into the PostgreSQL `servers` table.
+/// into the PostgreSQL `servers` table. Delta runs follow the VM to another
+/// node, but the Dashboard manages the status of a migrated server.
 ///
-#[derive(Debug, sqlx::FromRow)]
+#[derive(Debug, sqlx::FromRow, PgBulkInsert)]
+#[bulk_insert(table = "servers", conflict_key = "whmcs_id")]
 pub struct Server {
+    #[bulk_insert(column = "whmcs_id")]
     pub id: i32,
+    #[bulk_insert(column = "vm_id")]
     pub vmid: i32,
+    #[bulk_insert(column = "node_name")]
     pub node: String,
+    #[bulk_insert(column = "host_name")]
     pub hostname: String,
+    #[bulk_insert(insert_only)]
     pub status: String,
 }
 
@@ -196,11 +273,15 @@ impl From<VmRecord> for Server {
 /// Represents all necessary fields from the network row in the `MySQL`
 /// database.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow, PgBulkInsert)]
+#[bulk_insert(table = "networks", conflict_key = "whmcs_id")]
 pub struct Network {
+    #[bulk_insert(column = "whmcs_id")]
     pub id: i32,
+    #[bulk_insert(column = "datacenter_name")]
     pub title: String,
     pub gateway: String,
+    #[bulk_insert(column = "subnet_mask")]
     pub mask: String,
 }
 
@@ -218,7 +299,7 @@ impl Network {
 /// Represents all necessary fields from the ip address row in the `MySQL`
 /// database.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct IpAddress {
     pub id: i32,
     pub pool_id: i32,
@@ -240,7 +321,7 @@ impl IpAddress {
 /// Represents a custom field record from WHMCS's `tblcustomfields` table
 /// that defines a template.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct TemplateField {
     pub relid: i32,
     pub fieldoptions: String,
@@ -257,7 +338,8 @@ impl TemplateField {
 
 /// Represents a template record for the Dashboard's `templates` table.
 ///
-#[derive(Debug)]
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "templates", conflict_key = "template_vmid")]
 pub struct Template {
     pub os_name: String,
     pub template_vmid: i32,
@@ -287,7 +369,7 @@ impl TemplateField {
 
 /// Represents a service record fetched from WHMCS's `tblhosting` table.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct Service {
     pub id: i32,
     pub domainstatus: String,
@@ -309,7 +391,7 @@ impl Service {
 /// Represents a configurable option value record from WHMCS's
 /// `tblhostingconfigoptions` table.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct ConfigValue {
     pub id: i32,
     pub relid: i32,
@@ -331,7 +413,7 @@ impl ConfigValue {
 /// Represents a custom field value record from WHMCS's `tblcustomfieldsvalues`
 /// table.
 ///
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
 pub struct CustomValue {
     pub id: u32,
     pub fieldid: i32,
@@ -350,6 +432,355 @@ impl CustomValue {
     }
 }
 
+/// Represents an invoice record from WHMCS's `tblinvoices` table, with the
+/// currency of its client, its total and the amount already paid in cents.
+///
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
+pub struct Invoice {
+    pub id: i32,
+    pub userid: i32,
+    pub invoicenum: String,
+    pub status: String,
+    pub amount_cents: i64,
+    #[serde(default)]
+    pub paid_cents: i64,
+    pub currency: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub paid_at: Option<chrono::NaiveDateTime>,
+}
+
+impl Invoice {
+    pub fn new(id: i32, userid: i32, status: &str, amount_cents: i64) -> Self {
+        Self {
+            id,
+            userid,
+            invoicenum: String::new(),
+            status: status.to_owned(),
+            amount_cents,
+            paid_cents: 0,
+            currency: "USD".to_owned(),
+            created_at: chrono::NaiveDateTime::default(),
+            paid_at: None,
+        }
+    }
+
+    /// Returns the description shown in the billing history, using the
+    /// custom invoice number if WHMCS assigned one.
+    ///
+    pub fn description(&self) -> String {
+        match self.invoicenum.is_empty() {
+            true => format!("WHMCS invoice #{}", self.id),
+            false => format!("WHMCS invoice #{}", self.invoicenum),
+        }
+    }
+
+    /// Maps the WHMCS status to the Dashboard invoice status. Refunded
+    /// invoices are cancelled, and the ones awaiting payment are unpaid.
+    ///
+    pub fn dashboard_status(&self) -> &'static str {
+        match self.status.as_str() {
+            "Paid" => "Paid",
+            "Cancelled" | "Refunded" => "Cancelled",
+            _ => "Unpaid",
+        }
+    }
+
+    /// Returns the part of an unpaid invoice already paid in WHMCS, carried
+    /// over as the credit of the invoice, so only the balance is charged.
+    ///
+    pub fn credit_cents(&self) -> i64 {
+        match self.dashboard_status() {
+            "Unpaid" => self.paid_cents.clamp(0, self.amount_cents),
+            _ => 0,
+        }
+    }
+}
+
+/// Represents an invoice line from WHMCS's `tblinvoiceitems` table, with its
+/// amount in cents.
+///
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
+pub struct InvoiceItem {
+    pub id: i32,
+    pub invoiceid: i32,
+    pub item_type: String,
+    pub relid: i32,
+    pub description: String,
+    pub amount_cents: i64,
+}
+
+impl InvoiceItem {
+    pub fn new(id: i32, invoiceid: i32, item_type: &str, relid: i32, amount_cents: i64) -> Self {
+        Self {
+            id,
+            invoiceid,
+            item_type: item_type.to_owned(),
+            relid,
+            description: format!("Item {id}"),
+            amount_cents,
+        }
+    }
+}
+
+/// Represents an incoming payment from WHMCS's `tblaccounts` table. The
+/// reference is the gateway transaction ID, unless it's empty or shared by
+/// several transactions.
+///
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
+pub struct Transaction {
+    pub id: i32,
+    pub invoiceid: i32,
+    pub gateway: String,
+    pub reference: String,
+    pub amount_cents: i64,
+    pub date: chrono::NaiveDateTime,
+}
+
+impl Transaction {
+    pub fn new(id: i32, invoiceid: i32, gateway: &str, reference: &str, amount_cents: i64) -> Self {
+        Self {
+            id,
+            invoiceid,
+            gateway: gateway.to_owned(),
+            reference: reference.to_owned(),
+            amount_cents,
+            date: chrono::NaiveDateTime::default(),
+        }
+    }
+}
+
+/// Represents a support ticket from WHMCS's `tbltickets` table.
+///
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
+pub struct Ticket {
+    pub id: i32,
+    pub tid: String,
+    pub userid: i32,
+    pub title: String,
+    pub message: String,
+    pub status: String,
+    pub urgency: String,
+    pub date: chrono::NaiveDateTime,
+    pub lastreply: Option<chrono::NaiveDateTime>,
+}
+
+impl Ticket {
+    pub fn new(id: i32, tid: &str, userid: i32, title: &str) -> Self {
+        Self {
+            id,
+            tid: tid.to_owned(),
+            userid,
+            title: title.to_owned(),
+            message: String::new(),
+            status: "Open".to_owned(),
+            urgency: "Medium".to_owned(),
+            date: chrono::NaiveDateTime::default(),
+            lastreply: None,
+        }
+    }
+}
+
+/// Represents a ticket reply from WHMCS's `tblticketreplies` table, where
+/// `tid` is the ID of the ticket and `admin` names the staff member who
+/// replied.
+///
+#[derive(Debug, Clone, serde::Deserialize, sqlx::FromRow)]
+pub struct TicketReply {
+    pub id: i32,
+    pub tid: i32,
+    pub name: String,
+    pub admin: String,
+    pub message: String,
+    pub date: chrono::NaiveDateTime,
+}
+
+impl TicketReply {
+    pub fn new(id: i32, tid: i32, admin: &str, message: &str) -> Self {
+        Self {
+            id,
+            tid,
+            name: String::new(),
+            admin: admin.to_owned(),
+            message: message.to_owned(),
+            date: chrono::NaiveDateTime::default(),
+        }
+    }
+
+    /// Checks whether the reply was written by a staff member.
+    ///
+    pub fn from_staff(&self) -> bool {
+        !self.admin.is_empty()
+    }
+
+    /// Returns the name shown as the author of the reply. Clients replying
+    /// from the client area leave their name empty.
+    ///
+    pub fn author(&self) -> String {
+        match (self.from_staff(), self.name.is_empty()) {
+            (true, _) => self.admin.clone(),
+            (false, true) => "Client".to_owned(),
+            (false, false) => self.name.clone(),
+        }
+    }
+}
+
+/// Represents a datacenter for the Dashboard's `datacenters` table, named by
+/// the title of a WHMCS network.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "datacenters", conflict_key = "code")]
+pub struct DatacenterRow {
+    pub code: String,
+    pub display_name: String,
+}
+
+/// Represents a product for the Dashboard's `products` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "products", conflict_key = "whmcs_id")]
+pub struct ProductRow {
+    pub group_id: uuid::Uuid,
+    pub name: String,
+    pub whmcs_id: i32,
+}
+
+/// Represents a custom field for the Dashboard's `custom_fields` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "custom_fields", conflict_key = "whmcs_id")]
+pub struct CustomFieldRow {
+    pub product_id: uuid::Uuid,
+    pub name: String,
+    pub whmcs_id: i32,
+}
+
+/// Represents an ip address for the Dashboard's `ip_addresses` table, not
+/// assigned to a server if its server wasn't migrated.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "ip_addresses", conflict_key = "whmcs_id")]
+pub struct IpAddressRow {
+    pub ip_address: String,
+    pub network_id: uuid::Uuid,
+    pub server_id: Option<uuid::Uuid>,
+    pub whmcs_id: i32,
+}
+
+/// Represents a service for the Dashboard's `services` table. Delta runs
+/// update only the status billed in WHMCS, as transfers, organizations and
+/// upgrades change the rest in the Dashboard.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "services", conflict_key = "whmcs_id")]
+pub struct ServiceRow {
+    pub status: String,
+    #[bulk_insert(insert_only)]
+    pub user_id: uuid::Uuid,
+    #[bulk_insert(insert_only)]
+    pub organization_id: uuid::Uuid,
+    #[bulk_insert(insert_only)]
+    pub server_id: uuid::Uuid,
+    #[bulk_insert(insert_only)]
+    pub product_id: uuid::Uuid,
+    #[bulk_insert(insert_only)]
+    pub template_id: uuid::Uuid,
+    pub whmcs_id: i32,
+}
+
+/// Represents a custom field value for the Dashboard's `custom_values` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "custom_values", conflict_key = "whmcs_id")]
+pub struct CustomValueRow {
+    pub service_id: uuid::Uuid,
+    pub custom_field_id: uuid::Uuid,
+    pub value: String,
+    pub whmcs_id: i32,
+}
+
+/// Represents a configurable option value for the Dashboard's `config_values`
+/// table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "config_values", conflict_key = "whmcs_id")]
+pub struct ConfigValueRow {
+    pub service_id: uuid::Uuid,
+    pub config_id: uuid::Uuid,
+    pub value: String,
+    pub whmcs_id: i32,
+}
+
+/// Represents an invoice for the Dashboard's `invoices` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "invoices", conflict_key = "whmcs_id")]
+pub struct InvoiceRow {
+    pub user_id: uuid::Uuid,
+    pub description: String,
+    pub amount_cents: i64,
+    pub credit_cents: i64,
+    pub currency: String,
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub paid_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub whmcs_id: i32,
+}
+
+/// Represents an invoice line for the Dashboard's `invoice_items` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "invoice_items", conflict_key = "whmcs_id")]
+pub struct InvoiceItemRow {
+    pub invoice_id: uuid::Uuid,
+    pub service_id: Option<uuid::Uuid>,
+    pub description: String,
+    pub amount_cents: i64,
+    pub whmcs_id: i32,
+}
+
+/// Represents a payment for the Dashboard's `payments` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "payments", conflict_key = "whmcs_id")]
+pub struct PaymentRow {
+    pub invoice_id: uuid::Uuid,
+    pub provider: String,
+    pub provider_ref: String,
+    pub amount_cents: i64,
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub whmcs_id: i32,
+}
+
+/// Represents a support ticket for the Dashboard's `tickets` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "tickets", conflict_key = "whmcs_id")]
+pub struct TicketRow {
+    pub user_id: uuid::Uuid,
+    pub reference: String,
+    pub subject: String,
+    pub message: String,
+    pub status: String,
+    pub priority: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub whmcs_id: i32,
+}
+
+/// Represents a ticket reply for the Dashboard's `ticket_replies` table.
+///
+#[derive(Debug, PgBulkInsert)]
+#[bulk_insert(table = "ticket_replies", conflict_key = "whmcs_id")]
+pub struct TicketReplyRow {
+    pub ticket_id: uuid::Uuid,
+    pub author: String,
+    pub from_staff: bool,
+    pub message: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub whmcs_id: i32,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,2 +1,7 @@
-﻿pub mod cli;
+﻿// The derived `PgBulkInsert` implementations name this crate by its path.
+extern crate self as migration_utility;
+
+pub mod bundle;
+pub mod cli;
+pub mod commands;
 pub mod etl;
@@ -2,7 +2,7 @@
 use dashboard_common::prelude::Result;
 use dashboard_common::telemetry;
 use migration_utility::cli::Cli;
-use migration_utility::etl::migration::Migration;
+use migration_utility::commands;
 use tracing::Level;
 
 /// The main entry point for the migration utility.
@@ -21,5 +21,5 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     tracing::info!(?cli, "Cli arguments parsed.");
 
-    Migration::new(&cli).await?.run().await.map(|_| ())
+    commands::execute(cli.command).await
 }
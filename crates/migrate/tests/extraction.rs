@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::Result;
+use dashboard_testing::database;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{FutureExt, StreamExt};
+use migration_utility::etl::migration::Migration;
+use migration_utility::etl::source::{DirectorySource, Record, Source, SourceRecord};
+use migration_utility::etl::types::DashboardTable;
+use sqlx::PgPool;
+use std::path::Path;
+
+/// Source whose extraction panics before it yields a record.
+///
+struct PanickingSource;
+
+impl Source for PanickingSource {
+    fn records<S: SourceRecord>(
+        &self,
+        table: DashboardTable,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxStream<'static, Result<Record<S>>> {
+        futures::stream::poll_fn(move |_| panic!("Source of {table} is broken")).boxed()
+    }
+
+    fn count(
+        &self,
+        _table: DashboardTable,
+        _since: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'static, Result<Option<u64>>> {
+        futures::future::ready(Ok(None)).boxed()
+    }
+}
+
+/// Writes an export of product groups with an undecodable row between two
+/// valid ones, and a second undecodable row when `broken` is 2.
+///
+fn write_product_groups(dir: &Path, broken: usize) {
+    let mut export = "id,name\n1,VPS\nx,Broken\n3,Dedicated\n".to_owned();
+    if broken > 1 {
+        export.push_str("y,Broken again\n");
+    }
+    std::fs::write(dir.join("product_groups.csv"), export).unwrap();
+}
+
+#[sqlx::test]
+async fn panicking_extraction_should_fail_migration(pool: PgPool) {
+    // Arrange
+    dotenv::dotenv().ok();
+    let target_url = std::env::var("TARGET_URL").unwrap().into();
+    let mut migration = Migration::with_source(PanickingSource, &target_url, 1024)
+        .await
+        .unwrap();
+    migration.target_pool = pool.clone();
+    database::migrate(&migration.target_pool).await;
+
+    // Act
+    let result = migration.run().await;
+
+    // Assert
+    let users = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM users"#)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(result.unwrap_err().to_string().contains("panicked"));
+    assert_eq!(users, 0);
+}
+
+#[sqlx::test]
+async fn undecodable_rows_should_be_skipped_within_max_errors(pool: PgPool) {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    write_product_groups(dir.path(), 1);
+    let mut migration = setup_migration(DirectorySource::new(dir.path()), pool.clone())
+        .await
+        .with_max_errors(1);
+
+    // Act
+    let statistic = migration.run().await.unwrap();
+
+    // Assert
+    let groups = sqlx::query_scalar!("SELECT whmcs_id FROM product_groups ORDER BY whmcs_id")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    let skipped = migration.take_skipped();
+    assert_eq!(statistic.get(&DashboardTable::ProductGroups), Some(&2));
+    assert_eq!(groups, vec![Some(1), Some(3)]);
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0].table, DashboardTable::ProductGroups);
+    assert!(skipped[0].reason.starts_with("Row can't be decoded"));
+}
+
+#[sqlx::test]
+async fn undecodable_rows_should_fail_migration_beyond_max_errors(pool: PgPool) {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    write_product_groups(dir.path(), 2);
+    let mut migration = setup_migration(DirectorySource::new(dir.path()), pool.clone())
+        .await
+        .with_max_errors(1);
+
+    // Act
+    let result = migration.run().await;
+
+    // Assert
+    let groups = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM product_groups"#)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("More than 1 source rows can't be decoded")
+    );
+    assert_eq!(groups, 0);
+}
+
+#[sqlx::test]
+async fn undecodable_row_should_fail_migration_by_default(pool: PgPool) {
+    // Arrange
+    let dir = tempfile::tempdir().unwrap();
+    write_product_groups(dir.path(), 1);
+    let mut migration = setup_migration(DirectorySource::new(dir.path()), pool).await;
+
+    // Act
+    let result = migration.run().await;
+
+    // Assert
+    assert!(
+        result
+            .unwrap_err()
+            .to_string()
+            .contains("More than 0 source rows can't be decoded")
+    );
+}
+
+async fn setup_migration<S: Source>(source: S, pool: PgPool) -> Migration<S> {
+    // Create new migration object.
+    dotenv::dotenv().ok();
+    let target_url = std::env::var("TARGET_URL").unwrap().into();
+    let mut migration = Migration::with_source(source, &target_url, 1024)
+        .await
+        .unwrap();
+
+    // Change target pool to the test one.
+    migration.target_pool = pool;
+    database::migrate(&migration.target_pool).await;
+
+    migration
+}
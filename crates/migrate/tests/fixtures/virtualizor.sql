@@ -0,0 +1,71 @@
+CREATE TABLE users
+(
+    uid      INT PRIMARY KEY,
+    type     INT          NOT NULL,
+    fname    VARCHAR(255),
+    lname    VARCHAR(255),
+    email    VARCHAR(255) NOT NULL,
+    password VARCHAR(255) NOT NULL
+);
+
+CREATE TABLE servers
+(
+    serid       INT PRIMARY KEY,
+    server_name VARCHAR(255) NOT NULL
+);
+
+CREATE TABLE plans
+(
+    plid      INT PRIMARY KEY,
+    plan_name VARCHAR(255) NOT NULL
+);
+
+CREATE TABLE vps
+(
+    vpsid     INT PRIMARY KEY,
+    vps_name  VARCHAR(255) NOT NULL,
+    uid       INT          NOT NULL,
+    serid     INT          NOT NULL,
+    plid      INT          NOT NULL,
+    hostname  VARCHAR(255) NOT NULL,
+    suspended INT          NOT NULL
+);
+
+CREATE TABLE ippool
+(
+    ippid       INT PRIMARY KEY,
+    ippool_name VARCHAR(255) NOT NULL,
+    gateway     VARCHAR(255) NOT NULL,
+    netmask     VARCHAR(255) NOT NULL
+);
+
+CREATE TABLE ips
+(
+    ipid  INT PRIMARY KEY,
+    ippid INT          NOT NULL,
+    ip    VARCHAR(255) NOT NULL,
+    vpsid INT UNSIGNED NOT NULL
+);
+
+INSERT INTO users (uid, type, fname, lname, email, password)
+VALUES (1, 0, 'Ada', 'Lovelace', 'ada@example.com', 'hash'),
+       (2, 0, 'Alan', 'Turing', 'alan@example.com', 'hash'),
+       (3, 1, 'Root', NULL, 'root@example.com', 'hash');
+
+INSERT INTO servers (serid, server_name)
+VALUES (1, 'pve1');
+
+INSERT INTO plans (plid, plan_name)
+VALUES (1, 'KVM 2G'),
+       (2, 'KVM 4G');
+
+INSERT INTO vps (vpsid, vps_name, uid, serid, plid, hostname, suspended)
+VALUES (7, 'v1007', 1, 1, 1, 'ada.example.com', 0),
+       (8, 'v1012', 2, 1, 2, 'alan.example.com', 1);
+
+INSERT INTO ippool (ippid, ippool_name, gateway, netmask)
+VALUES (1, 'Amsterdam', '10.0.0.1', '255.255.255.0');
+
+INSERT INTO ips (ipid, ippid, ip, vpsid)
+VALUES (1, 1, '10.0.0.7', 7),
+       (2, 1, '10.0.0.8', 0);
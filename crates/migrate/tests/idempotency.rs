@@ -1,6 +1,11 @@
-﻿use migration_utility::cli::Cli;
-use migration_utility::etl::migration::Migration;
+﻿use dashboard_testing::database;
+use migration_utility::cli::Databases;
+use migration_utility::etl::migration::{self, Commit, Migration};
+use migration_utility::etl::reconcile;
+use migration_utility::etl::types::DashboardTable;
 use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use std::time::Duration;
 
 #[sqlx::test]
 async fn idempotency_should_be_preserved(pool: PgPool) {
@@ -16,26 +21,152 @@ async fn idempotency_should_be_preserved(pool: PgPool) {
     assert!(second_stat.is_empty());
 }
 
+#[sqlx::test]
+async fn status_should_count_migrated_rows(pool: PgPool) {
+    // Arrange
+    let mut migration = setup_migration(pool).await;
+    let inserted = migration.run().await.unwrap();
+
+    // Act
+    let status = migration::status(&migration.target_pool).await.unwrap();
+
+    // Assert
+    for (table, rows) in status {
+        assert_eq!(inserted.get(&table).copied().unwrap_or_default(), rows);
+    }
+}
+
+#[sqlx::test]
+async fn chunk_commits_should_insert_same_rows(pool: PgPool) {
+    // Arrange
+    let mut migration = setup_migration(pool).await;
+
+    // Act
+    let chunked = migration
+        .migrate(DashboardTable::Users, Commit::EachChunk)
+        .await
+        .unwrap();
+    let status = migration::status(&migration.target_pool).await.unwrap();
+    let second = migration.run().await.unwrap();
+
+    // Assert
+    for (table, rows) in status {
+        assert_eq!(chunked.get(&table).copied().unwrap_or_default(), rows);
+    }
+    assert!(second.is_empty());
+}
+
+#[sqlx::test]
+async fn chunk_commits_should_need_single_connection(pool: PgPool) {
+    // Arrange
+    let mut migration = setup_migration(pool.clone()).await;
+    migration.target_pool = PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_secs(5))
+        .connect_with(pool.connect_options().as_ref().clone())
+        .await
+        .unwrap();
+
+    // Act
+    let chunked = migration
+        .migrate(DashboardTable::Users, Commit::EachChunk)
+        .await;
+
+    // Assert
+    assert!(!chunked.unwrap().is_empty());
+}
+
+#[sqlx::test]
+async fn reconciliation_should_count_migrated_rows(pool: PgPool) {
+    // Arrange
+    let mut migration = setup_migration(pool).await;
+    migration.run().await.unwrap();
+
+    // Act
+    let reconciliation = reconcile::reconcile(&migration.source.pool, &migration.target_pool)
+        .await
+        .unwrap();
+
+    // Assert
+    let status = migration::status(&migration.target_pool).await.unwrap();
+    for (check, (table, rows)) in reconciliation.tables.iter().zip(status) {
+        assert_eq!(check.table, table.to_string());
+        assert_eq!(check.target_rows as u64, rows);
+    }
+}
+
+#[sqlx::test]
+async fn reconciliation_should_catch_changed_amounts(pool: PgPool) {
+    // Arrange
+    let mut migration = setup_migration(pool.clone()).await;
+    migration.run().await.unwrap();
+    let before = reconcile::reconcile(&migration.source.pool, &migration.target_pool)
+        .await
+        .unwrap();
+    sqlx::query!(
+        r#"
+UPDATE invoices
+SET amount_cents = amount_cents + 100
+WHERE id = (SELECT id FROM invoices WHERE whmcs_id IS NOT NULL ORDER BY whmcs_id LIMIT 1)
+		"#
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Act
+    let after = reconcile::reconcile(&migration.source.pool, &migration.target_pool)
+        .await
+        .unwrap();
+
+    // Assert
+    let invoices = |reconciliation: &reconcile::Reconciliation| {
+        reconciliation
+            .tables
+            .iter()
+            .find(|check| check.table == DashboardTable::Invoices.to_string())
+            .unwrap()
+    };
+    let (before, after) = (invoices(&before), invoices(&after));
+    assert!(before.target_amount_cents.is_some());
+    assert_eq!(after.source_amount_cents, before.source_amount_cents);
+    assert_eq!(
+        after.target_amount_cents,
+        before.target_amount_cents.map(|amount| amount + 100)
+    );
+    assert!(!after.passed());
+}
+
+#[sqlx::test]
+async fn resume_should_skip_earlier_tables(pool: PgPool) {
+    // Arrange
+    let mut migration = setup_migration(pool).await;
+
+    // Act
+    let statistic = migration
+        .migrate(DashboardTable::Templates, Commit::EachTable)
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(!statistic.contains_key(&DashboardTable::Users));
+    assert!(!statistic.contains_key(&DashboardTable::Servers));
+}
+
 async fn setup_migration(pool: PgPool) -> Migration {
     // Create new migration object.
     dotenv::dotenv().ok();
     let mut migration = {
-        Migration::new(&Cli {
-            dry_run: false,
-            chunk_size: 1024,
-            source_url: std::env::var("SOURCE_URL").unwrap().into(),
+        let databases = Databases {
+            source_url: Some(std::env::var("SOURCE_URL").unwrap().into()),
             target_url: std::env::var("TARGET_URL").unwrap().into(),
-        })
-        .await
-        .unwrap()
+        };
+        Migration::new(&databases, 1024).await.unwrap()
     };
 
     // Change target pool to the test one.
     migration.target_pool = pool;
-    sqlx::migrate!("../../migrations")
-        .run(&migration.target_pool)
-        .await
-        .unwrap();
+    database::migrate(&migration.target_pool).await;
 
     migration
 }
@@ -0,0 +1,111 @@
+use dashboard_testing::database;
+use migration_utility::etl::migration::Migration;
+use migration_utility::etl::source::VirtualizorSource;
+use migration_utility::etl::types::DashboardTable;
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
+use sqlx::{MySqlPool, PgPool};
+use std::str::FromStr;
+
+#[sqlx::test]
+async fn virtualizor_vms_should_become_services_of_their_owners(pool: PgPool) {
+    // Arrange
+    let source = VirtualizorSource::new(virtualizor_database("virtualizor_services").await)
+        .with_template(Some(9000));
+    let mut migration = setup_migration(source, pool.clone()).await;
+
+    // Act
+    let statistic = migration.run().await.unwrap();
+
+    // Assert
+    let servers = sqlx::query!(
+        r#"
+SELECT s.whmcs_id, s.vm_id, s.node_name, s.status, sv.status AS service_status, u.email, p.name
+FROM servers AS s
+JOIN services AS sv ON sv.server_id = s.id
+JOIN users AS u ON u.id = sv.user_id
+JOIN products AS p ON p.id = sv.product_id
+JOIN templates AS t ON t.id = sv.template_id
+WHERE t.template_vmid = 9000
+ORDER BY s.whmcs_id
+		"#
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(statistic.get(&DashboardTable::Users), Some(&2));
+    assert_eq!(statistic.get(&DashboardTable::Services), Some(&2));
+    assert_eq!(servers.len(), 2);
+    assert_eq!(servers[0].whmcs_id, Some(7));
+    assert_eq!(servers[0].vm_id, Some(1007));
+    assert_eq!(servers[0].node_name.as_deref(), Some("pve1"));
+    assert_eq!(servers[0].service_status, "Active");
+    assert_eq!(servers[0].email, "ada@example.com");
+    assert_eq!(servers[0].name, "KVM 2G");
+    assert_eq!(servers[1].vm_id, Some(1012));
+    assert_eq!(servers[1].service_status, "Suspended");
+    assert_eq!(servers[1].email, "alan@example.com");
+}
+
+#[sqlx::test]
+async fn virtualizor_without_template_should_skip_services(pool: PgPool) {
+    // Arrange
+    let source = VirtualizorSource::new(virtualizor_database("virtualizor_no_template").await);
+    let mut migration = setup_migration(source, pool.clone()).await;
+
+    // Act
+    let statistic = migration.run().await.unwrap();
+
+    // Assert
+    assert_eq!(statistic.get(&DashboardTable::Servers), Some(&2));
+    assert_eq!(statistic.get(&DashboardTable::Services), None);
+    assert!(
+        migration
+            .take_skipped()
+            .iter()
+            .any(|row| row.table == DashboardTable::Services)
+    );
+}
+
+/// Creates a Virtualizor database next to the WHMCS one and fills it with the
+/// fixture.
+///
+async fn virtualizor_database(name: &str) -> MySqlPool {
+    dotenv::dotenv().ok();
+    let options = MySqlConnectOptions::from_str(&std::env::var("SOURCE_URL").unwrap()).unwrap();
+    let admin = MySqlPoolOptions::new()
+        .max_connections(1)
+        .connect_with(options.clone())
+        .await
+        .unwrap();
+    sqlx::raw_sql(&format!(
+        "DROP DATABASE IF EXISTS {name}; CREATE DATABASE {name};"
+    ))
+    .execute(&admin)
+    .await
+    .unwrap();
+
+    let pool = MySqlPoolOptions::new()
+        .connect_with(options.database(name))
+        .await
+        .unwrap();
+    sqlx::raw_sql(include_str!("fixtures/virtualizor.sql"))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    pool
+}
+
+async fn setup_migration(source: VirtualizorSource, pool: PgPool) -> Migration<VirtualizorSource> {
+    // Create new migration object.
+    let target_url = std::env::var("TARGET_URL").unwrap().into();
+    let mut migration = Migration::with_source(source, &target_url, 1024)
+        .await
+        .unwrap();
+
+    // Change target pool to the test one.
+    migration.target_pool = pool;
+    database::migrate(&migration.target_pool).await;
+
+    migration
+}
@@ -0,0 +1,31 @@
+use secrecy::SecretString;
+
+#[derive(Debug, clap::Parser)]
+#[command(
+    name = "Seed",
+    version = "0.1.0",
+    about = "Fills a fresh database with a demo dataset for development"
+)]
+pub struct Cli {
+    #[arg(
+        short,
+        long,
+        default_value_t = 5,
+        help = "Sets the number of customers registered next to the administrator"
+    )]
+    pub customers: usize,
+    #[arg(
+        short,
+        long,
+        default_value_t = 3,
+        help = "Sets the number of servers ordered by every customer"
+    )]
+    pub servers: usize,
+    #[arg(
+        short,
+        long,
+        help = "PostgreSQL URL of the database to seed",
+        env = "DATABASE_URL"
+    )]
+    pub database_url: SecretString,
+}
@@ -0,0 +1,227 @@
+use dashboard_common::prelude::{Error, Result};
+use dashboard_server::model::queries;
+use dashboard_server::model::types::{ApiServer, ServerStatus};
+use dashboard_testing::{CatalogBuilder, ServerBuilder, TestApp, TestUser, UserBuilder};
+use dashboard_testing::{database, requests};
+use sqlx::PgPool;
+use std::time::Duration;
+
+/// Email address of the seeded administrator.
+pub const ADMIN_EMAIL: &str = "admin@example.com";
+
+/// Password of every seeded user.
+pub const PASSWORD: &str = "demo_password_123";
+
+/// Names of the seeded customers, numbered once they run out.
+const CUSTOMERS: &[(&str, &str)] = &[
+    ("Alice", "Johnson"),
+    ("Bob", "Smith"),
+    ("Carol", "Williams"),
+    ("Dave", "Brown"),
+    ("Erin", "Davis"),
+    ("Frank", "Miller"),
+    ("Grace", "Wilson"),
+    ("Heidi", "Moore"),
+];
+
+/// Sizes of the ordered servers, as CPU cores and RAM in GB.
+const SIZES: &[(i32, i32)] = &[(1, 1), (2, 4), (4, 8)];
+
+/// Statuses the servers are left in, in turn.
+const STATUSES: &[ServerStatus] = &[
+    ServerStatus::Running,
+    ServerStatus::Stopped,
+    ServerStatus::Running,
+    ServerStatus::Failed,
+];
+
+/// Checks over the setups of the ordered servers before giving up.
+const SETUP_CHECKS: usize = 60;
+
+/// Demo dataset written into the database.
+///
+/// # Fields
+///
+/// * `customers`: Number of registered customers, next to the administrator.
+/// * `servers`: Ordered servers with the status they were left in.
+///
+pub struct Dataset {
+    pub customers: usize,
+    pub servers: Vec<ApiServer>,
+}
+
+/// Fails unless the database has neither users nor products yet, as the demo
+/// catalog and users can only be inserted once.
+///
+pub async fn ensure_fresh(pool: &PgPool) -> Result<()> {
+    let seeded = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM users) OR EXISTS (SELECT 1 FROM products)",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    match seeded {
+        true => Err(Error::Any(
+            "Database already has users or products, seed a fresh one".to_owned(),
+        )),
+        false => Ok(()),
+    }
+}
+
+/// Inserts the catalog, registers the administrator and the customers, and
+/// orders the servers of every customer through the API, with Proxmox, payments
+/// and mail mocked. The servers are then left in different statuses, and the
+/// failed ones with a failed provisioning step.
+///
+/// # Arguments
+///
+/// * `pool`: Pool of the migrated database.
+/// * `customers`: Number of customers to register.
+/// * `servers`: Number of servers ordered by every customer.
+///
+pub async fn seed(pool: &PgPool, customers: usize, servers: usize) -> Result<Dataset> {
+    let ordered = customers * servers;
+    let product_id = (0..ordered)
+        .fold(CatalogBuilder::new(), |catalog, server| {
+            catalog.ip_address(&format!("10.0.{}.{}", server / 250, server % 250 + 1))
+        })
+        .build(pool)
+        .await;
+    let app = TestApp::new(pool.clone()).await;
+
+    UserBuilder::new()
+        .name("Ada", "Admin")
+        .email(ADMIN_EMAIL)
+        .password(PASSWORD)
+        .admin()
+        .register(&app, pool)
+        .await;
+
+    let endpoint = format!("{}/servers", &app.url);
+    let mut users = Vec::<TestUser>::with_capacity(customers);
+    for customer in 0..customers {
+        let (first_name, last_name) = CUSTOMERS[customer % CUSTOMERS.len()];
+        let handle = match customer / CUSTOMERS.len() {
+            0 => first_name.to_lowercase(),
+            round => format!("{}{round}", first_name.to_lowercase()),
+        };
+        let user = UserBuilder::new()
+            .name(first_name, last_name)
+            .email(&format!("{handle}@example.com"))
+            .password(PASSWORD)
+            .register(&app, pool)
+            .await;
+
+        for server in 0..servers {
+            let (cpu_cores, ram_gb) = SIZES[server % SIZES.len()];
+            let host_name = format!("{handle}-{}.example.com", server + 1);
+            let payload = ServerBuilder::new(product_id)
+                .host_name(&host_name)
+                .cpu_cores(cpu_cores)
+                .ram_gb(ram_gb)
+                .payload();
+            let response = requests::post_response(&app, &endpoint, &user.token, &payload).await;
+            if !response.status().is_success() {
+                return Err(Error::Any(format!(
+                    "Ordering {host_name} failed with {}",
+                    response.status()
+                )));
+            }
+        }
+        users.push(user);
+    }
+
+    let mut servers = wait_for_setups(pool, &users).await?;
+    for (index, server) in servers.iter_mut().enumerate() {
+        server.status = STATUSES[index % STATUSES.len()];
+        queries::update_server_status(pool, server.server_id, server.status).await?;
+        if server.status == ServerStatus::Failed {
+            database::fail_provisioning_step(pool, server.server_id, "ConfigureVm").await;
+        }
+    }
+
+    Ok(Dataset { customers, servers })
+}
+
+/// Waits until no server of the customers is still being set up.
+///
+/// # Returns
+///
+/// On success, the servers of all customers.
+///
+async fn wait_for_setups(pool: &PgPool, users: &[TestUser]) -> Result<Vec<ApiServer>> {
+    for _ in 0..SETUP_CHECKS {
+        let mut servers = Vec::new();
+        for user in users {
+            servers.extend(queries::get_servers_for_user(pool, user.user_id).await?);
+        }
+        if servers
+            .iter()
+            .all(|server| server.status != ServerStatus::SettingUp)
+        {
+            return Ok(servers);
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    Err(Error::Any("Setting up the servers timed out".to_owned()))
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn seed_should_leave_servers_in_different_statuses(pool: PgPool) {
+        // Act
+        let dataset = seed(&pool, 2, 2).await.unwrap();
+
+        // Assert
+        let admin = queries::get_user_by_email(&pool, ADMIN_EMAIL)
+            .await
+            .unwrap();
+        let customer = queries::get_user_by_email(&pool, "bob@example.com")
+            .await
+            .unwrap();
+        let statuses = dataset
+            .servers
+            .iter()
+            .map(|server| server.status)
+            .collect::<Vec<_>>();
+        assert_eq!(dataset.customers, 2);
+        assert_eq!(
+            statuses,
+            [
+                ServerStatus::Running,
+                ServerStatus::Stopped,
+                ServerStatus::Running,
+                ServerStatus::Failed
+            ]
+        );
+        assert!(queries::is_admin(&pool, admin.id).await.unwrap());
+        assert!(!queries::is_admin(&pool, customer.id).await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn empty_database_should_be_fresh(pool: PgPool) {
+        // Act
+        let result = ensure_fresh(&pool).await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn database_with_catalog_should_not_be_seeded(pool: PgPool) {
+        // Arrange
+        CatalogBuilder::new().build(&pool).await;
+
+        // Act
+        let result = ensure_fresh(&pool).await;
+
+        // Assert
+        assert!(matches!(result, Err(Error::Any(_))));
+    }
+}
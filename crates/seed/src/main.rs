@@ -0,0 +1,44 @@
+//! Seeds a fresh development database with a demo dataset: a catalog with its
+//! datacenter and network, an administrator, a few customers, and their
+//! servers left in different statuses. The fixtures of `dashboard_testing`
+//! write it, through the API of a server with Proxmox, payments and mail
+//! mocked.
+
+mod cli;
+mod dataset;
+
+use clap::Parser;
+use cli::Cli;
+use dashboard_common::prelude::Result;
+use dashboard_testing::database;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+/// The main entry point for the seed command.
+///
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    let cli = Cli::parse();
+
+    let pool = PgPool::connect(cli.database_url.expose_secret()).await?;
+    database::migrate(&pool).await;
+    dataset::ensure_fresh(&pool).await?;
+
+    let dataset = dataset::seed(&pool, cli.customers, cli.servers).await?;
+    println!(
+        "Seeded {} customers with {} servers.",
+        dataset.customers,
+        dataset.servers.len()
+    );
+    for server in &dataset.servers {
+        println!("  {:<16} {}", server.ip_address, server.status);
+    }
+    println!(
+        "Log in as {} or any customer with the password {}.",
+        dataset::ADMIN_EMAIL,
+        dataset::PASSWORD
+    );
+
+    Ok(())
+}
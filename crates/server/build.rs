@@ -0,0 +1,8 @@
+/// Generates the gRPC server and client of the internal API, which needs
+/// `protoc` on the path.
+///
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::compile_protos("proto/internal.proto")?;
+
+    Ok(())
+}
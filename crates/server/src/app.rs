@@ -1,13 +1,23 @@
-﻿use crate::model;
+﻿use crate::config::{HttpEnv, TlsEnv, tls};
+use crate::model;
 use crate::state::AppState;
 use crate::web::middleware as mw;
-use crate::web::routes::{catalog, login, server};
+use crate::web::routes::{
+    admin, announcement, billing, catalog, event, login, metrics, notification, organization,
+    products, server, transfer, user, webhook,
+};
 use crate::web::{self};
-use axum::serve::Serve;
+use axum::extract::DefaultBodyLimit;
 use axum::{Router, middleware};
-use dashboard_common::prelude::Result;
+use axum_server::Handle;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
+use dashboard_common::prelude::{ApiError, Result};
+use hyper_util::rt::TokioTimer;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower_http::compression::CompressionLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use utoipa::OpenApi;
 use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
 use utoipa_swagger_ui::SwaggerUi;
@@ -15,7 +25,21 @@ use utoipa_swagger_ui::SwaggerUi;
 /// Represents the core web application.
 ///
 pub struct App {
-    server: Serve<TcpListener, Router, Router>,
+    server: Server,
+    router: Router,
+    address: SocketAddr,
+}
+
+/// Server of the application, speaking plain HTTP or, with a certificate
+/// configured, HTTPS.
+///
+enum Server {
+    Http(axum_server::Server),
+    Https {
+        server: axum_server::Server<RustlsAcceptor>,
+        tls: RustlsConfig,
+        settings: TlsEnv,
+    },
 }
 
 impl App {
@@ -23,7 +47,9 @@ impl App {
     ///
     /// This configures the entire Axum router, including routes, state, and
     /// middleware. Binds a `TcpListener` to the provided address and
-    /// determines the final URL of the application.
+    /// determines the final URL of the application. With the certificate and
+    /// the key configured, the application is served over HTTPS. The limits
+    /// and timeouts of the `http` settings apply to every request.
     ///
     /// # Arguments
     ///
@@ -33,17 +59,72 @@ impl App {
     ///
     pub async fn build(app_state: AppState, address: SocketAddr) -> Result<Self> {
         let listener = TcpListener::bind(&address).await?;
-        let router = Router::new()
-            .merge(login::routes())
-            .merge(server::routes(app_state.clone()))
-            .merge(catalog::routes(app_state.clone()))
+        let address = listener.local_addr()?;
+        let settings = app_state.config.tls.clone();
+        let tls = tls::load(&settings).await?;
+        let http = &app_state.config.http;
+        let body_limit = |group: &str| DefaultBodyLimit::max(http.body_limit(group));
+        let mut router = Router::new()
+            .merge(login::routes().layer(body_limit("login")))
+            .merge(server::routes(app_state.clone()).layer(body_limit("server")))
+            .merge(catalog::routes(app_state.clone()).layer(body_limit("catalog")))
+            .merge(billing::routes(app_state.clone()).layer(body_limit("billing")))
+            .merge(admin::routes(app_state.clone()).layer(body_limit("admin")))
+            .merge(products::routes(app_state.clone()).layer(body_limit("products")))
+            .merge(webhook::routes(app_state.clone()).layer(body_limit("webhook")))
+            .merge(transfer::routes(app_state.clone()).layer(body_limit("transfer")))
+            .merge(organization::routes(app_state.clone()).layer(body_limit("organization")))
+            .merge(announcement::routes(app_state.clone()).layer(body_limit("announcement")))
+            .merge(notification::routes(app_state.clone()).layer(body_limit("notification")))
+            .merge(event::routes(app_state.clone()).layer(body_limit("event")))
+            .merge(user::routes(app_state.clone()).layer(body_limit("user")))
+            .merge(metrics::routes())
             .merge(SwaggerUi::new("/openapi").url("/api-docs/openapi.json", ApiDoc::openapi()))
-            .with_state(app_state.clone())
+            .with_state(app_state.clone());
+        if http.etag {
+            router = router.layer(middleware::from_fn(mw::conditional_get));
+        }
+        // Waiting for a free slot counts towards the timeout of the request.
+        if let Some(limit) = http.concurrency_limit() {
+            router = router.layer(GlobalConcurrencyLimitLayer::new(limit));
+        }
+        if let Some(timeout) = http.request_timeout() {
+            router = router.layer(middleware::from_fn_with_state(timeout, mw::request_timeout));
+        }
+        let router = router
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                mw::resolve_client_ip,
+            ))
+            .layer(middleware::from_fn(mw::complete_error))
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
             .layer(middleware::map_response(mw::log_mapper))
-            .layer(mw::allow_cors(&app_state.config.cors));
+            .layer(
+                CompressionLayer::new()
+                    .gzip(http.compression)
+                    .br(http.compression),
+            )
+            .layer(mw::allow_cors(&app_state.config.cors))
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                mw::secure_headers,
+            ));
+
+        let listener = listener.into_std()?;
+        let server = match tls {
+            Some(tls) => Server::Https {
+                server: tune(axum_server::from_tcp_rustls(listener, tls.clone()), http),
+                tls,
+                settings,
+            },
+            None => Server::Http(tune(axum_server::from_tcp(listener), http)),
+        };
 
         Ok(Self {
-            server: axum::serve(listener, router),
+            server,
+            router,
+            address,
         })
     }
 
@@ -57,30 +138,76 @@ impl App {
     /// Empty `Ok(())` on success.
     ///
     pub async fn run(self) -> Result<()> {
-        // Future that completes when the server should begin graceful shutdown.
         // When the shutdown signal is received, the server stops accepting new
         // connections and waits for active requests to complete before shutting
         // down.
-        let shutdown_signal = async {
-            tokio::signal::ctrl_c()
-                .await
-                .expect("Failed to listen for Ctrl+C");
-            tracing::info!("Shutting signal received.");
-        };
+        let handle = Handle::new();
+        let shutdown = handle.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            shutdown.graceful_shutdown(None);
+        });
 
-        self.server
-            .with_graceful_shutdown(shutdown_signal)
-            .await
-            .map_err(Into::into)
+        let service = self
+            .router
+            .into_make_service_with_connect_info::<SocketAddr>();
+        match self.server {
+            Server::Http(server) => server.handle(handle).serve(service).await?,
+            Server::Https {
+                server,
+                tls,
+                settings,
+            } => {
+                if settings.reload_sec > 0 {
+                    tokio::spawn(tls::watch(tls, settings));
+                    tracing::info!(target: "server", "TLS certificate reload started.");
+                }
+                server.handle(handle).serve(service).await?
+            }
+        }
+
+        Ok(())
     }
 
     /// Returns the public URL of the application.
     ///
     pub fn get_url(&self) -> Result<String> {
-        Ok(format!("http://{}", self.server.local_addr()?))
+        let scheme = match self.server {
+            Server::Http(_) => "http",
+            Server::Https { .. } => "https",
+        };
+
+        Ok(format!("{scheme}://{}", self.address))
     }
 }
 
+/// Future that completes when the servers should begin graceful shutdown.
+///
+pub async fn shutdown_signal() {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to listen for Ctrl+C");
+    tracing::info!("Shutting signal received.");
+}
+
+/// Applies the connection settings to the server. The headers of a request
+/// must arrive within the timeout, so slow clients can't hold the connections.
+///
+fn tune<A>(mut server: axum_server::Server<A>, http: &HttpEnv) -> axum_server::Server<A> {
+    let builder = server.http_builder();
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .keep_alive(http.keep_alive)
+        .header_read_timeout(http.header_read_timeout());
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(http.http2_keep_alive());
+
+    server
+}
+
 /// API documentation for the application.
 ///
 /// This struct defines the OpenAPI specification for the entire application,
@@ -92,29 +219,292 @@ impl App {
     tags(
         (name = "Login", description = "User authentication endpoints"),
         (name = "Server", description = "Server management endpoints"),
-        (name = "Catalog", description = "Frontend helper endpoints")
+        (name = "Catalog", description = "Frontend helper endpoints"),
+        (name = "Billing", description = "Invoice, payment, usage and credit endpoints"),
+        (name = "Admin", description = "Administrator endpoints"),
+        (name = "Webhook", description = "Outbound webhook endpoints"),
+        (name = "Event", description = "Live domain event endpoints"),
+        (name = "User", description = "User profile endpoints"),
+        (name = "Monitoring", description = "Monitoring endpoints")
     ),
     paths(
         login::login,
         login::register,
+        login::verify_email,
+        login::oidc_login,
+        login::oidc_callback,
+        login::saml_metadata,
+        login::saml_login,
+        login::saml_acs,
         server::get_user,
+        server::get_quota,
         server::list_servers,
         server::create_server,
         server::get_server,
         server::delete_server,
         server::server_action,
+        server::bulk_action,
+        server::add_ip,
+        server::remove_ip,
+        server::get_firewall,
+        server::set_firewall,
+        server::add_firewall_rule,
+        server::delete_firewall_rule,
+        server::list_backups,
+        server::restore_backup,
+        server::get_backup_schedule,
+        server::set_backup_schedule,
+        server::delete_backup_schedule,
+        server::get_provisioning,
+        server::retry_provisioning,
+        server::list_tags,
+        server::set_tags,
+        server::remove_tag,
+        server::set_notes,
+        server::set_external_id,
+        server::get_server_by_external_id,
+        server::get_timeline,
+        server::reset_password,
+        server::list_disks,
+        server::add_disk,
+        server::resize_disk,
+        server::get_iso,
+        server::attach_iso,
+        server::detach_iso,
+        server::set_boot_order,
+        server::get_traffic,
         catalog::list_products,
+        catalog::list_apps,
+        catalog::list_isos,
         catalog::list_cpu_options,
         catalog::list_ram_options,
         catalog::list_os_options,
         catalog::list_datacenter_options,
+        catalog::list_product_datacenters,
+        billing::list_invoices,
+        billing::get_invoice,
+        billing::checkout_invoice,
+        billing::apply_credit,
+        billing::list_usage,
+        billing::get_credits,
+        billing::redeem_promo_code,
+        billing::payment_webhook,
+        admin::issue_credit,
+        admin::list_promo_codes,
+        admin::create_promo_code,
+        admin::set_account_quota,
+        admin::set_product_quota,
+        admin::create_network,
+        admin::set_ip_range,
+        admin::get_ip_utilization,
+        admin::list_nodes,
+        admin::list_storages,
+        admin::get_runtime,
+        admin::set_runtime,
+        admin::invalidate_catalog,
+        admin::search,
+        admin::transfer_server,
+        admin::list_announcements,
+        admin::create_announcement,
+        admin::update_announcement,
+        admin::delete_announcement,
+        admin::get_audit_log,
+        admin::replay_events,
+        products::list_product_groups,
+        products::create_product_group,
+        products::update_product_group,
+        products::delete_product_group,
+        products::list_products,
+        products::get_product,
+        products::create_product,
+        products::update_product,
+        products::delete_product,
+        products::list_custom_fields,
+        products::create_custom_field,
+        products::update_custom_field,
+        products::delete_custom_field,
+        products::list_config_options,
+        products::create_config_option,
+        products::update_config_option,
+        products::delete_config_option,
+        products::list_templates,
+        products::create_template,
+        products::update_template,
+        products::delete_template,
+        products::create_iso,
+        products::delete_iso,
+        products::list_product_datacenters,
+        products::set_product_datacenters,
+        products::list_datacenters,
+        products::create_datacenter,
+        products::update_datacenter,
+        products::delete_datacenter,
+        webhook::list_webhooks,
+        webhook::create_webhook,
+        webhook::delete_webhook,
+        webhook::list_deliveries,
+        transfer::request_transfer,
+        transfer::list_transfers,
+        transfer::accept_transfer,
+        transfer::decline_transfer,
+        organization::list_organizations,
+        organization::create_organization,
+        organization::list_members,
+        organization::set_member,
+        organization::remove_member,
+        organization::list_servers,
+        organization::add_server,
+        organization::get_saml,
+        organization::set_saml,
+        organization::delete_saml,
+        announcement::list_announcements,
+        notification::list_notifications,
+        notification::mark_read,
+        notification::mark_all_read,
+        notification::get_preferences,
+        notification::set_preference,
+        event::stream_events,
+        user::update_user,
+        user::request_email_change,
+        user::confirm_email_change,
+        user::resend_verification,
+        user::export_user,
+        user::delete_account,
+        user::list_allowed_networks,
+        user::add_allowed_network,
+        user::delete_allowed_network,
+        user::list_api_tokens,
+        user::create_api_token,
+        user::delete_api_token,
+        metrics::get_metrics,
+        metrics::get_health,
     ),
     components(schemas(
+        ApiError,
         model::types::NewUser,
         model::types::LoginPayload,
         model::types::ServerStatus,
+        model::types::ApiServerTag,
+        model::types::TimelineEventKind,
+        model::types::ApiTimelineEvent,
+        model::types::ApiServerDetail,
+        model::types::ApiGuestInfo,
+        model::types::ApiPasswordReset,
+        model::types::ApiDisk,
+        model::types::PasswordResetMethod,
+        model::types::ApiAuditEntry,
+        model::types::AuditAction,
         model::types::ApiUser,
+        model::types::ApiInvoice,
+        model::types::InvoiceStatus,
+        model::types::ApiUsage,
+        model::types::ApiCredit,
+        model::types::ApiCreditBalance,
+        model::types::ApiPromoCode,
+        model::types::NewPromoCode,
+        model::types::QuotaLimits,
+        model::types::ApiQuotaItem,
+        model::types::ApiQuota,
+        model::types::ApiQuotas,
+        model::types::ApiNetwork,
+        model::types::ApiIpRange,
+        model::types::ApiIpUtilization,
+        model::types::ApiNode,
+        model::types::ApiHealth,
+        model::types::ApiStorage,
+        model::types::ApiSearchUser,
+        model::types::ApiSearchServer,
+        model::types::ApiSearchService,
+        model::types::ApiSearchResults,
+        model::types::ApiProduct,
+        model::types::ApiProductGroup,
+        model::types::ApiConfigOption,
+        model::types::ApiCustomField,
+        model::types::ApiTemplate,
+        model::types::TemplateKind,
+        model::types::ApiApp,
+        model::types::ApiIso,
+        model::types::ApiServerIso,
+        model::types::ApiServerTraffic,
+        model::types::ApiTrafficMonth,
+        model::types::ApiDatacenter,
+        model::types::ApiAvailableDatacenter,
+        model::types::FirewallDirection,
+        model::types::FirewallAction,
+        model::types::FirewallProtocol,
+        model::types::FirewallSettings,
+        model::types::ApiFirewallRule,
+        model::types::ApiFirewall,
+        model::types::BackupMode,
+        model::types::ApiBackupSchedule,
+        model::types::ApiBackup,
+        model::types::ProvisioningStep,
+        model::types::ProvisioningStepStatus,
+        model::types::ApiProvisioningStep,
+        model::types::ApiActionResult,
+        model::types::ApiUserExport,
+        model::types::WebhookEvent,
+        model::types::WebhookDeliveryStatus,
+        model::types::ApiWebhook,
+        model::types::ApiAllowedNetwork,
+        model::types::ApiToken,
+        model::types::ApiWebhookAttempt,
+        model::types::ApiWebhookDelivery,
+        model::types::TransferStatus,
+        model::types::ApiServerTransfer,
+        model::types::OrganizationRole,
+        model::types::ApiOrganization,
+        model::types::ApiOrganizationMember,
+        model::types::ApiSamlSettings,
+        model::types::AnnouncementKind,
+        model::types::ApiAnnouncement,
+        model::types::NotificationEvent,
+        model::types::ApiNotification,
+        model::types::ApiNotificationPreference,
+        model::types::ApiCompletedTransfer,
+        model::types::DomainEventKind,
+        model::types::DomainEvent,
+        model::types::ApiEventReplay,
+        crate::payments::types::CheckoutSession,
+        crate::config::RuntimeEnv,
+        crate::config::FeatureFlags,
         web::types::ServerActionPayload,
+        web::types::BulkActionPayload,
+        web::types::RedeemPromoPayload,
+        web::types::IssueCreditPayload,
+        web::types::ReplayEventsPayload,
+        web::types::NewNetworkPayload,
+        web::types::IpRangePayload,
+        web::types::NamePayload,
+        web::types::ProductPayload,
+        web::types::CustomFieldPayload,
+        web::types::TemplatePayload,
+        web::types::DatacenterPayload,
+        web::types::ProductDatacentersPayload,
+        web::types::FirewallRulePayload,
+        web::types::ServerTagsPayload,
+        web::types::ServerNotesPayload,
+        web::types::ServerExternalIdPayload,
+        web::types::DiskPayload,
+        web::types::AttachIsoPayload,
+        web::types::BootOrderPayload,
+        web::types::IsoPayload,
+        web::types::BackupSchedulePayload,
+        web::types::WebhookPayload,
+        web::types::AllowedNetworkPayload,
+        web::types::ApiTokenPayload,
+        web::types::SsoAuthorization,
+        web::types::TransferPayload,
+        web::types::AdminTransferPayload,
+        web::types::MemberPayload,
+        web::types::SamlSettingsPayload,
+        web::types::SamlResponseForm,
+        web::types::OrganizationServerPayload,
+        web::types::AnnouncementPayload,
+        web::types::NotificationPreferencePayload,
+        web::types::UpdateUserPayload,
+        web::types::EmailChangePayload,
+        web::types::ConfirmEmailPayload,
         web::types::TokenResponse,
         web::types::UserResponse,
     )),
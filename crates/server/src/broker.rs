@@ -0,0 +1,39 @@
+//! Live subscribers of the domain events on this replica.
+
+use crate::model::types::DomainEvent;
+use tokio::sync::broadcast;
+
+/// Broker passing the domain events to the SSE connections of this replica.
+/// Every replica reads the committed events from the log itself, so a
+/// connection receives the events of all replicas. Clones share the channel.
+///
+/// A subscriber too slow to keep up with `capacity` events loses the oldest
+/// ones, and should reconnect with the last event it received.
+///
+#[derive(Clone)]
+pub struct Broker {
+    sender: broadcast::Sender<DomainEvent>,
+}
+
+impl Broker {
+    /// Creates a broker buffering `capacity` events for every subscriber.
+    ///
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+
+        Self { sender }
+    }
+
+    /// Passes an event to every current subscriber.
+    ///
+    pub fn publish(&self, event: DomainEvent) {
+        // Nobody listening on this replica is not an error.
+        _ = self.sender.send(event);
+    }
+
+    /// Subscribes to the events published from now on.
+    ///
+    pub fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        self.sender.subscribe()
+    }
+}
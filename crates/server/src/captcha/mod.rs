@@ -0,0 +1,56 @@
+pub mod siteverify;
+
+// -----------------------------------------------------------------------------
+
+use crate::captcha::siteverify::SiteVerifyClient;
+use crate::config::{CaptchaBackend, CaptchaEnv};
+use async_trait::async_trait;
+use dashboard_common::prelude::Result;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// An abstract interface for verifying the CAPTCHA solved by a client.
+///
+/// Defines a contract for a provider, like hCaptcha or Cloudflare Turnstile,
+/// that checks the token its widget issued to the client who solved the
+/// CAPTCHA.
+///
+#[async_trait]
+pub trait CaptchaProvider {
+    /// Short name of the provider (e.g., "hcaptcha").
+    ///
+    fn name(&self) -> &'static str;
+
+    /// Verify the token of a solved CAPTCHA.
+    ///
+    /// # Arguments
+    ///
+    /// * `token`: Token the widget issued to the client.
+    /// * `remote_ip`: Address of the client, if known.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the provider accepted the token.
+    ///
+    async fn verify(&self, token: &str, remote_ip: Option<IpAddr>) -> Result<bool>;
+}
+
+/// Creates the configured CAPTCHA provider.
+///
+/// # Arguments
+///
+/// * `settings`: Settings of the CAPTCHA.
+///
+/// # Returns
+///
+/// CAPTCHA provider, `None` if the CAPTCHA is disabled.
+///
+pub fn provider(settings: &CaptchaEnv) -> Result<Option<Arc<dyn CaptchaProvider + Send + Sync>>> {
+    let client = match settings.provider {
+        CaptchaBackend::Disabled => return Ok(None),
+        CaptchaBackend::Hcaptcha => SiteVerifyClient::hcaptcha(settings)?,
+        CaptchaBackend::Turnstile => SiteVerifyClient::turnstile(settings)?,
+    };
+
+    Ok(Some(Arc::new(client)))
+}
@@ -0,0 +1,158 @@
+use crate::captcha::CaptchaProvider;
+use crate::config::CaptchaEnv;
+use async_trait::async_trait;
+use dashboard_common::prelude::{Error, Result};
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Verification endpoint of hCaptcha.
+const HCAPTCHA_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+/// Verification endpoint of Cloudflare Turnstile.
+const TURNSTILE_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+/// Concrete implementation of the `CaptchaProvider` trait for hCaptcha and
+/// Cloudflare Turnstile, which share the `siteverify` protocol.
+///
+/// The secret, the token and the address of the client are posted as a form,
+/// and the JSON response tells whether the token is valid.
+///
+pub struct SiteVerifyClient {
+    client: Client,
+    name: &'static str,
+    url: String,
+    secret: SecretString,
+}
+
+impl SiteVerifyClient {
+    /// Creates a client verifying hCaptcha tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings`: Settings of the CAPTCHA.
+    ///
+    pub fn hcaptcha(settings: &CaptchaEnv) -> Result<Self> {
+        Self::new("hcaptcha", HCAPTCHA_URL, settings)
+    }
+
+    /// Creates a client verifying Cloudflare Turnstile tokens.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings`: Settings of the CAPTCHA.
+    ///
+    pub fn turnstile(settings: &CaptchaEnv) -> Result<Self> {
+        Self::new("turnstile", TURNSTILE_URL, settings)
+    }
+
+    fn new(name: &'static str, default_url: &str, settings: &CaptchaEnv) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_sec))
+            .build()?;
+
+        Ok(Self {
+            client,
+            name,
+            url: settings
+                .verify_url
+                .clone()
+                .unwrap_or_else(|| default_url.to_owned()),
+            secret: settings.secret.clone(),
+        })
+    }
+}
+
+/// Response of the `siteverify` endpoint.
+///
+#[derive(Debug, Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+#[async_trait]
+impl CaptchaProvider for SiteVerifyClient {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn verify(&self, token: &str, remote_ip: Option<IpAddr>) -> Result<bool> {
+        let mut params = vec![
+            ("secret", self.secret.expose_secret().to_owned()),
+            ("response", token.to_owned()),
+        ];
+        if let Some(remote_ip) = remote_ip {
+            params.push(("remoteip", remote_ip.to_string()));
+        }
+
+        let response = self.client.post(&self.url).form(&params).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::Any(format!(
+                "CAPTCHA verification by {} failed: status {status}",
+                self.name
+            )));
+        }
+        let result = response.json::<SiteVerifyResponse>().await?;
+        if !result.success {
+            tracing::info!(target: "captcha", provider = self.name, errors = ?result.error_codes, "CAPTCHA token rejected");
+        }
+
+        Ok(result.success)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CaptchaBackend;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn verify_should_post_token_with_secret_and_address() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/siteverify"))
+            .and(body_string_contains("secret=top-secret"))
+            .and(body_string_contains("response=solved"))
+            .and(body_string_contains("remoteip=203.0.113.5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/siteverify"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({ "success": false, "error-codes": ["invalid-input-response"] }),
+            ))
+            .mount(&mock_server)
+            .await;
+        let settings = CaptchaEnv {
+            provider: CaptchaBackend::Turnstile,
+            secret: "top-secret".into(),
+            verify_url: Some(format!("{}/siteverify", mock_server.uri())),
+            ..CaptchaEnv::default()
+        };
+        let client = SiteVerifyClient::turnstile(&settings).unwrap();
+
+        // Act
+        let solved = client
+            .verify("solved", Some("203.0.113.5".parse().unwrap()))
+            .await
+            .unwrap();
+        let forged = client.verify("forged", None).await.unwrap();
+
+        // Assert
+        assert_eq!(client.name(), "turnstile");
+        assert!(solved);
+        assert!(!forged);
+    }
+}
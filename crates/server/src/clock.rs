@@ -0,0 +1,117 @@
+//! Source of the current time for the services, replaceable in the tests.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Tells the time and waits, so the code depending on the passing of time can
+/// be tested without actually waiting.
+///
+#[async_trait]
+pub trait Clock {
+    /// Returns the current time.
+    ///
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Waits for the given duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration`: Time to wait.
+    ///
+    async fn sleep(&self, duration: Duration);
+
+    /// Returns the time passed since the given instant, zero if it is in the
+    /// future.
+    ///
+    /// # Arguments
+    ///
+    /// * `since`: Instant read from the same clock.
+    ///
+    fn elapsed(&self, since: DateTime<Utc>) -> Duration {
+        (self.now() - since).to_std().unwrap_or_default()
+    }
+}
+
+/// Clock of the system, waiting with the Tokio timer.
+///
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// Clock that stands still until it is advanced.
+///
+/// Waiting advances the clock by the duration right away, so a loop running
+/// into a timeout finishes instantly, with the time it would have taken.
+///
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    /// Creates a new clock showing the given time.
+    ///
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Moves the clock forward.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration`: Time to skip.
+    ///
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now += duration;
+    }
+}
+
+#[async_trait]
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+        tokio::task::yield_now().await;
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn manual_clock_should_advance_on_sleep() {
+        // Arrange
+        let start = Utc::now();
+        let clock = ManualClock::new(start);
+
+        // Act
+        clock.sleep(Duration::from_secs(90)).await;
+        clock.advance(Duration::from_secs(30));
+
+        // Assert
+        assert_eq!(clock.elapsed(start), Duration::from_secs(120));
+        assert_eq!(
+            clock.elapsed(start + Duration::from_secs(600)),
+            Duration::ZERO
+        );
+    }
+}
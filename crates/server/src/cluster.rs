@@ -0,0 +1,195 @@
+//! State shared between the replicas through Redis.
+
+use crate::config::RedisEnv;
+use crate::model::types::ServerStatus;
+use chrono::Utc;
+use dashboard_common::prelude::Result;
+use redis::AsyncCommands;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+/// Change of a server's status, published to every replica.
+///
+/// # Fields
+///
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `status`: New status of the server.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusEvent {
+    pub user_id: Uuid,
+    pub server_id: Uuid,
+    pub status: ServerStatus,
+}
+
+/// Connection to the Redis instance shared by the replicas, used for the
+/// session revocation list and the server status events. The connection is
+/// opened on first use, so a Redis outage doesn't hold up the startup, and
+/// every command gives up after `redis.timeout_ms`. Clones share the
+/// connection, which reconnects on failure.
+///
+#[derive(Clone)]
+pub struct Cluster {
+    client: redis::Client,
+    config: ConnectionManagerConfig,
+    connection: Arc<OnceCell<ConnectionManager>>,
+    prefix: String,
+}
+
+impl Cluster {
+    /// Prepares the connection to Redis without opening it.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings`: All settings required to connect to Redis.
+    ///
+    /// # Returns
+    ///
+    /// Cluster connecting on first use, `None` if no Redis is configured.
+    ///
+    pub fn connect_lazy(settings: &RedisEnv) -> Result<Option<Self>> {
+        let Some(url) = &settings.url else {
+            return Ok(None);
+        };
+        let timeout = Duration::from_millis(settings.timeout_ms);
+        let config = ConnectionManagerConfig::new()
+            .set_number_of_retries(1)
+            .set_connection_timeout(timeout)
+            .set_response_timeout(timeout);
+
+        Ok(Some(Self {
+            client: redis::Client::open(url.as_str())?,
+            config,
+            connection: Arc::new(OnceCell::new()),
+            prefix: settings.prefix.clone(),
+        }))
+    }
+
+    /// Revokes every session of the user issued until now. The revocation is
+    /// kept as long as the revoked tokens may stay valid.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id`: ID of the user.
+    /// * `token_duration`: Lifetime of the tokens.
+    ///
+    pub async fn revoke_sessions(&self, user_id: Uuid, token_duration: Duration) -> Result<()> {
+        let key = self.key(&format!("revoked:{user_id}"));
+        self.connection()
+            .await?
+            .set_ex::<_, _, ()>(key, Utc::now().timestamp(), token_duration.as_secs().max(1))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Checks whether a session of the user was revoked.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id`: ID of the user.
+    /// * `issued_at`: Time the token of the session was issued, as a Unix
+    ///   timestamp.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the session was revoked after the token was issued.
+    ///
+    pub async fn is_revoked(&self, user_id: Uuid, issued_at: i64) -> Result<bool> {
+        let key = self.key(&format!("revoked:{user_id}"));
+        let revoked_at = self.connection().await?.get::<_, Option<i64>>(key).await?;
+
+        Ok(revoked_at.is_some_and(|revoked_at| issued_at <= revoked_at))
+    }
+
+    /// Publishes a change of a server's status to every replica.
+    ///
+    pub async fn publish_status(&self, event: &StatusEvent) -> Result<()> {
+        let payload = serde_json::json!(event).to_string();
+        self.connection()
+            .await?
+            .publish::<_, _, ()>(self.key("status"), payload)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the shared connection, opening it on first use. A failed
+    /// attempt is repeated by the next command.
+    ///
+    async fn connection(&self) -> Result<ConnectionManager> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| {
+                ConnectionManager::new_with_config(self.client.clone(), self.config.clone())
+            })
+            .await?;
+
+        Ok(connection.clone())
+    }
+
+    /// Returns the key in the namespace of the application.
+    ///
+    fn key(&self, name: &str) -> String {
+        format!("{}:{name}", self.prefix)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Settings of a Redis that nothing listens on.
+    ///
+    fn unreachable() -> RedisEnv {
+        RedisEnv {
+            url: Some("redis://127.0.0.1:1".to_owned()),
+            timeout_ms: 200,
+            ..RedisEnv::default()
+        }
+    }
+
+    #[test]
+    fn cluster_should_be_disabled_without_url() {
+        // Act
+        let cluster = Cluster::connect_lazy(&RedisEnv::default()).unwrap();
+
+        // Assert
+        assert!(cluster.is_none());
+    }
+
+    #[tokio::test]
+    async fn unreachable_redis_should_fail_commands_in_time() {
+        // Arrange
+        let cluster = Cluster::connect_lazy(&unreachable()).unwrap().unwrap();
+
+        // Act
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            cluster.is_revoked(Uuid::new_v4(), 0),
+        )
+        .await;
+
+        // Assert
+        assert!(result.unwrap().is_err());
+    }
+
+    #[test]
+    fn keys_should_be_prefixed() {
+        // Arrange
+        let cluster = Cluster::connect_lazy(&unreachable()).unwrap().unwrap();
+
+        // Act
+        let key = cluster.key("status");
+
+        // Assert
+        assert_eq!(key, "dashboard:status");
+    }
+}
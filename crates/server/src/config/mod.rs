@@ -0,0 +1,1592 @@
+pub mod runtime;
+pub mod secrets;
+pub mod tls;
+
+// -----------------------------------------------------------------------------
+
+use crate::config::secrets::Secret;
+use axum::http::header::{
+    CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+};
+use axum::http::{HeaderName, HeaderValue, Method};
+use dashboard_common::prelude::Result;
+use dashboard_common::telemetry::LogSettings;
+use ipnet::IpNet;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::ConnectOptions;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::log::LevelFilter;
+use utoipa::ToSchema;
+
+/// Represents the application's configuration.
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    pub application: SocketAddr,
+    pub database: Database,
+    pub token: TokenEnv,
+    pub proxmox: ProxmoxEnv,
+    pub cors: Cors,
+    #[serde(default)]
+    pub payments: PaymentsEnv,
+    #[serde(default)]
+    pub placement: PlacementEnv,
+    #[serde(default)]
+    pub account: AccountEnv,
+    #[serde(default)]
+    pub provisioning: ProvisioningEnv,
+    #[serde(default)]
+    pub action: ActionEnv,
+    #[serde(default)]
+    pub scheduler: SchedulerEnv,
+    #[serde(default)]
+    pub usage: UsageEnv,
+    #[serde(default)]
+    pub quota: QuotaEnv,
+    #[serde(default)]
+    pub backup: BackupEnv,
+    #[serde(default)]
+    pub webhook: WebhookEnv,
+    #[serde(default)]
+    pub events: EventsEnv,
+    #[serde(default)]
+    pub smoke: SmokeEnv,
+    #[serde(default)]
+    pub secrets: SecretsEnv,
+    #[serde(default)]
+    pub runtime: RuntimeEnv,
+    #[serde(default)]
+    pub reload: ReloadEnv,
+    #[serde(default)]
+    pub log: LogSettings,
+    #[serde(default)]
+    pub cache: CacheEnv,
+    #[serde(default)]
+    pub redis: RedisEnv,
+    #[serde(default)]
+    pub proxy: ProxyEnv,
+    #[serde(default)]
+    pub captcha: CaptchaEnv,
+    #[serde(default)]
+    pub mail: MailEnv,
+    #[serde(default)]
+    pub oidc: OidcEnv,
+    #[serde(default)]
+    pub saml: SamlEnv,
+    #[serde(default)]
+    pub security: SecurityEnv,
+    #[serde(default)]
+    pub tls: TlsEnv,
+    #[serde(default)]
+    pub http: HttpEnv,
+    #[serde(default)]
+    pub grpc: GrpcEnv,
+}
+
+impl Config {
+    /// Loads the configuration from environment variables.
+    ///
+    /// Runs before the logger is initialized, as the logs are configured here,
+    /// so the caller logs the loaded configuration.
+    ///
+    pub fn from_env() -> Result<Self> {
+        dotenv::dotenv()?;
+
+        let config_dir = std::path::PathBuf::from(std::env::var("APP_CONFIG_PATH")?);
+        let env_filename = Environment::from(&*std::env::var("APP_ENVIRONMENT")?).as_filename();
+
+        let config = config::Config::builder()
+            .add_source(config::File::from(config_dir.join("base.yaml")))
+            .add_source(config::File::from(config_dir.join(env_filename)))
+            .add_source(config::Environment::with_prefix("APP").separator("__"))
+            .build()?
+            .try_deserialize::<Config>()?
+            .with_aliases();
+
+        Ok(config)
+    }
+
+    /// Returns the database connection options.
+    ///
+    pub fn get_database_connect_options(&self) -> PgConnectOptions {
+        self.database.get_connect_options()
+    }
+
+    /// Returns the socket address for the application server to bind to.
+    ///
+    pub fn get_address(&self) -> SocketAddr {
+        self.application
+    }
+
+    /// Returns the warnings about the settings that are set but no longer
+    /// used, for the caller to log once the logger is ready.
+    ///
+    pub fn deprecations(&self) -> Vec<&'static str> {
+        let mut warnings = Vec::new();
+        if self.usage.interval_sec.is_some() {
+            warnings.push("usage.interval_sec is ignored, set scheduler.usage_metering instead");
+        }
+        if self.backup.interval_sec.is_some() {
+            warnings.push("backup.interval_sec is ignored, set scheduler.backups instead");
+        }
+        if self.scheduler.tick_sec.is_some() {
+            warnings
+                .push("scheduler.tick_sec is deprecated, set runtime.scheduler_tick_sec instead");
+        }
+
+        warnings
+    }
+
+    /// Applies the settings that were moved but are still set under their
+    /// old name.
+    ///
+    fn with_aliases(mut self) -> Self {
+        if let Some(tick_sec) = self.scheduler.tick_sec {
+            self.runtime.scheduler_tick_sec = tick_sec;
+        }
+
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            application: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080),
+            database: Database::default(),
+            token: TokenEnv::default(),
+            proxmox: ProxmoxEnv::default(),
+            cors: Cors::default(),
+            payments: PaymentsEnv::default(),
+            placement: PlacementEnv::default(),
+            account: AccountEnv::default(),
+            provisioning: ProvisioningEnv::default(),
+            action: ActionEnv::default(),
+            scheduler: SchedulerEnv::default(),
+            usage: UsageEnv::default(),
+            quota: QuotaEnv::default(),
+            backup: BackupEnv::default(),
+            webhook: WebhookEnv::default(),
+            events: EventsEnv::default(),
+            smoke: SmokeEnv::default(),
+            secrets: SecretsEnv::default(),
+            runtime: RuntimeEnv::default(),
+            reload: ReloadEnv::default(),
+            log: LogSettings::default(),
+            cache: CacheEnv::default(),
+            redis: RedisEnv::default(),
+            proxy: ProxyEnv::default(),
+            captcha: CaptchaEnv::default(),
+            mail: MailEnv::default(),
+            oidc: OidcEnv::default(),
+            saml: SamlEnv::default(),
+            security: SecurityEnv::default(),
+            tls: TlsEnv::default(),
+            http: HttpEnv::default(),
+            grpc: GrpcEnv::default(),
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// All settings required to connect to the database.
+///
+/// With `migrate_on_start`, the server applies the pending migrations before
+/// it starts serving, so a deploy needs no separate migration step.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Database {
+    host: String,
+    port: u16,
+    username: String,
+    password: Secret,
+    database_name: String,
+    #[serde(default)]
+    pub migrate_on_start: bool,
+    #[serde(default)]
+    pub pool: PoolEnv,
+    pub replica: Option<ReplicaEnv>,
+}
+
+impl Database {
+    /// Constructs a `PgConnectOptions` instance for connecting to the database.
+    ///
+    /// Statements running longer than the slow query threshold are logged as
+    /// warnings, and cancelled by the server after the statement timeout.
+    ///
+    pub fn get_connect_options(&self) -> PgConnectOptions {
+        let options = PgConnectOptions::new()
+            .host(&self.host)
+            .port(self.port)
+            .username(&self.username)
+            .password(self.password.get().expose_secret())
+            .database(&self.database_name)
+            .log_slow_statements(
+                LevelFilter::Warn,
+                Duration::from_millis(self.pool.slow_query_ms),
+            );
+
+        match self.pool.statement_timeout_ms {
+            Some(timeout) => options.options([("statement_timeout", timeout)]),
+            None => options,
+        }
+    }
+
+    /// Constructs a `PgConnectOptions` instance for connecting to the read-only
+    /// replica, with the credentials of the primary database.
+    ///
+    /// # Returns
+    ///
+    /// Connect options, `None` if no replica is configured.
+    ///
+    pub fn get_replica_connect_options(&self) -> Option<PgConnectOptions> {
+        let replica = self.replica.as_ref()?;
+
+        Some(
+            self.get_connect_options()
+                .host(&replica.host)
+                .port(replica.port),
+        )
+    }
+
+    /// Constructs the `PgPoolOptions` sizing the connection pool.
+    ///
+    pub fn get_pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.pool.max_connections)
+            .min_connections(self.pool.min_connections)
+            .acquire_timeout(Duration::from_secs(self.pool.acquire_timeout_sec))
+            .idle_timeout(self.pool.idle_timeout_sec.map(Duration::from_secs))
+    }
+}
+
+/// Settings of the database connection pool.
+///
+/// The pool keeps at least `min_connections` and opens up to
+/// `max_connections`, a request waiting longer than `acquire_timeout_sec` for
+/// a connection fails. Connections idle for `idle_timeout_sec` are closed,
+/// `None` keeps them open. Statements are cancelled after
+/// `statement_timeout_ms`, `None` lets them run, and those slower than
+/// `slow_query_ms` are logged.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PoolEnv {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_sec: u64,
+    pub idle_timeout_sec: Option<u64>,
+    pub statement_timeout_ms: Option<u64>,
+    pub slow_query_ms: u64,
+}
+
+/// Location of the read-only replica of the database.
+///
+/// The replica shares the credentials, database name and pool settings of the
+/// primary. Its connection is checked every `check_sec`, and reads go to the
+/// primary while the check fails.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReplicaEnv {
+    pub host: String,
+    pub port: u16,
+    pub check_sec: u64,
+}
+
+impl Default for ReplicaEnv {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_owned(),
+            port: 5432,
+            check_sec: 10,
+        }
+    }
+}
+
+impl Default for PoolEnv {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_sec: 30,
+            idle_timeout_sec: Some(600),
+            statement_timeout_ms: Some(30_000),
+            slow_query_ms: 1000,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// All settings required to work with JWT.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TokenEnv {
+    pub secret: Secret,
+    pub duration_sec: u64,
+}
+
+/// All settings required to work with Proxmox.
+///
+/// At most `max_concurrent_requests` requests are sent to the cluster at once,
+/// the others wait in a queue. Zero disables the limit.
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxmoxEnv {
+    pub url: String,
+    pub auth_header: Secret,
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for ProxmoxEnv {
+    fn default() -> Self {
+        Self {
+            url: String::default(),
+            auth_header: Secret::default(),
+            max_concurrent_requests: default_max_concurrent_requests(),
+        }
+    }
+}
+
+fn default_max_concurrent_requests() -> usize {
+    8
+}
+
+/// All settings required to work with the Stripe payment provider.
+///
+/// Additional IP addresses are invoiced with `additional_ip_cents` when they
+/// are ordered, `0` makes them free. Recorded uptime is invoiced monthly with
+/// `usage_hour_cents` per hour, `0` disables usage invoices.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PaymentsEnv {
+    pub api_url: String,
+    pub secret_key: SecretString,
+    pub webhook_secret: SecretString,
+    pub webhook_tolerance_sec: u64,
+    pub currency: String,
+    pub success_url: String,
+    pub cancel_url: String,
+    pub additional_ip_cents: i64,
+    pub usage_hour_cents: i64,
+}
+
+impl Default for PaymentsEnv {
+    fn default() -> Self {
+        Self {
+            api_url: "https://api.stripe.com/v1".to_owned(),
+            secret_key: SecretString::default(),
+            webhook_secret: SecretString::default(),
+            webhook_tolerance_sec: 300,
+            currency: "usd".to_owned(),
+            success_url: "http://localhost:5173/billing/success".to_owned(),
+            cancel_url: "http://localhost:5173/billing/cancel".to_owned(),
+            additional_ip_cents: 0,
+            usage_hour_cents: 0,
+        }
+    }
+}
+
+/// Cluster-wide overcommit policy used when placing servers on nodes.
+///
+/// Ratios are multiplied by the physical node capacity, so `4.0` allows four
+/// vCPUs to be allocated per physical core. Nodes may override them in the
+/// `nodes` table.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PlacementEnv {
+    pub cpu_overcommit: f64,
+    pub ram_overcommit: f64,
+}
+
+impl Default for PlacementEnv {
+    fn default() -> Self {
+        Self {
+            cpu_overcommit: 4.0,
+            ram_overcommit: 1.0,
+        }
+    }
+}
+
+/// Settings of the user accounts.
+///
+/// A token confirming a change of the email address expires after
+/// `email_token_ttl_min` minutes, a token verifying the email address of a new
+/// account after `verification_token_ttl_min` minutes.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AccountEnv {
+    pub email_token_ttl_min: i64,
+    pub verification_token_ttl_min: i64,
+}
+
+impl Default for AccountEnv {
+    fn default() -> Self {
+        Self {
+            email_token_ttl_min: 60,
+            verification_token_ttl_min: 24 * 60,
+        }
+    }
+}
+
+/// Settings of the server provisioning.
+///
+/// A failed provisioning step may be retried until it has been attempted
+/// `max_attempts` times. After the last failed attempt, or a failure a retry
+/// can't fix, the provisioning is rolled back: the half-created VM is deleted,
+/// the reserved IP addresses are released and the server records are removed.
+/// A step still running after `step_timeout_min`, as its job crashed, is
+/// marked as failed by the provisioning sweep job, so it can be retried. It
+/// must exceed the longest provisioning step. A provisioning without progress
+/// for `stale_after_min`, as the failed server was never retried, is rolled
+/// back by the same job.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProvisioningEnv {
+    pub max_attempts: i32,
+    pub step_timeout_min: i64,
+    pub stale_after_min: i64,
+}
+
+impl Default for ProvisioningEnv {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            step_timeout_min: 60,
+            stale_after_min: 120,
+        }
+    }
+}
+
+/// Settings of the server actions.
+///
+/// A bulk action accepts up to `bulk_max_servers` servers and runs the action
+/// on at most `bulk_parallelism` of them at once. A server left in the
+/// transient status of an action for `stuck_after_min`, longer than a backup
+/// restore may take, gets the status of its VM back from the status sync.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ActionEnv {
+    pub bulk_max_servers: usize,
+    pub bulk_parallelism: usize,
+    pub stuck_after_min: i64,
+}
+
+impl Default for ActionEnv {
+    fn default() -> Self {
+        Self {
+            bulk_max_servers: 100,
+            bulk_parallelism: 4,
+            stuck_after_min: 120,
+        }
+    }
+}
+
+/// Settings of the scheduler, which runs the periodic jobs.
+///
+/// Jobs are scheduled with five-field cron expressions in UTC, a missing
+/// expression disables the job, as do `usage.enabled` and `backup.enabled`
+/// for their jobs. Due jobs are checked every
+/// `runtime.scheduler_tick_sec` by the leader replica, so a job may start up to
+/// that late. The former `tick_sec` is still read in its place, with a
+/// warning. A claimed job is not
+/// run again for `lease_sec`, even by a new leader, unless the previous run
+/// finishes earlier. Every sample of the usage metering job accounts the time
+/// until its next run, so its schedule also defines the billing granularity.
+/// The traffic accounting job reads a day of RRD data, so it must run at least
+/// daily not to lose traffic. The notifications job notifies the audience of
+/// the newly published maintenance windows and sends the queued emails.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SchedulerEnv {
+    pub enabled: bool,
+    pub tick_sec: Option<u64>,
+    pub lease_sec: i64,
+    pub status_sync: Option<String>,
+    pub usage_metering: Option<String>,
+    pub traffic_accounting: Option<String>,
+    pub backups: Option<String>,
+    pub invoices: Option<String>,
+    pub webhooks: Option<String>,
+    pub notifications: Option<String>,
+    pub deprovisioning: Option<String>,
+    pub provisioning_sweep: Option<String>,
+}
+
+impl Default for SchedulerEnv {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tick_sec: None,
+            lease_sec: 3600,
+            status_sync: Some("*/5 * * * *".to_owned()),
+            usage_metering: Some("0 * * * *".to_owned()),
+            traffic_accounting: Some("15 * * * *".to_owned()),
+            backups: Some("* * * * *".to_owned()),
+            invoices: Some("0 1 1 * *".to_owned()),
+            webhooks: Some("* * * * *".to_owned()),
+            notifications: Some("* * * * *".to_owned()),
+            deprovisioning: Some("*/10 * * * *".to_owned()),
+            provisioning_sweep: Some("*/15 * * * *".to_owned()),
+        }
+    }
+}
+
+/// Settings of the usage metering, run by the scheduler.
+///
+/// With `enabled` off the metering job doesn't run, like without its schedule.
+/// Its schedule replaced `interval_sec`, which is only read to warn about it.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UsageEnv {
+    pub enabled: bool,
+    pub interval_sec: Option<u64>,
+}
+
+impl Default for UsageEnv {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_sec: None,
+        }
+    }
+}
+
+/// Default account quota, applied to accounts without their own row in the
+/// `quotas` table. A missing limit leaves the resource unlimited.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuotaEnv {
+    pub max_servers: Option<i32>,
+    pub max_cpu_cores: Option<i32>,
+    pub max_ram_gb: Option<i32>,
+    pub max_ips: Option<i32>,
+}
+
+impl Default for QuotaEnv {
+    fn default() -> Self {
+        Self {
+            max_servers: Some(10),
+            max_cpu_cores: Some(32),
+            max_ram_gb: Some(64),
+            max_ips: Some(10),
+        }
+    }
+}
+
+/// Settings of scheduled backups, which are triggered by the scheduler.
+///
+/// With `enabled` off no scheduled backup is triggered, like without the
+/// schedule of the backups job, which replaced `interval_sec`. Schedules
+/// running more often than `min_interval_sec` are rejected. Restoring a backup
+/// fails after `restore_timeout_sec`.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BackupEnv {
+    pub enabled: bool,
+    pub interval_sec: Option<u64>,
+    pub storage: String,
+    pub min_interval_sec: i64,
+    pub restore_timeout_sec: u64,
+}
+
+impl Default for BackupEnv {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_sec: None,
+            storage: "local".to_owned(),
+            min_interval_sec: 3600,
+            restore_timeout_sec: 3600,
+        }
+    }
+}
+
+/// Settings of the webhook worker, which delivers events to the registered
+/// webhooks when run by the scheduler.
+///
+/// Receivers must respond within `timeout_sec`. A failed delivery is retried
+/// after `retry_base_sec`, the delay growing four times with every attempt,
+/// until `max_attempts` is reached. At most `batch_size` events are delivered
+/// per run.
+///
+/// Receivers must resolve to public addresses, unless `allow_private_targets`
+/// is set for a dashboard whose receivers run in the same private network.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebhookEnv {
+    pub timeout_sec: u64,
+    pub retry_base_sec: i64,
+    pub max_attempts: i32,
+    pub batch_size: i64,
+    pub allow_private_targets: bool,
+}
+
+impl Default for WebhookEnv {
+    fn default() -> Self {
+        Self {
+            timeout_sec: 10,
+            retry_base_sec: 60,
+            max_attempts: 6,
+            batch_size: 100,
+            allow_private_targets: false,
+        }
+    }
+}
+
+/// Settings of the domain event consumers, like the webhooks and the
+/// notifications, run by the elected leader among the replicas.
+///
+/// The consumers are woken once events are committed, and check the log every
+/// `poll_ms` in case a wake-up was missed. At most `batch_size` events are
+/// handled per transaction. A gap in the log stops the consumers for up to
+/// `gap_wait_ms`, in case its event is still being committed. An event failing
+/// `max_attempts` times is given up. Every SSE connection buffers
+/// `broker_capacity` events before it loses the oldest ones.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EventsEnv {
+    pub poll_ms: u64,
+    pub batch_size: i64,
+    pub gap_wait_ms: i64,
+    pub max_attempts: i32,
+    pub broker_capacity: usize,
+}
+
+impl Default for EventsEnv {
+    fn default() -> Self {
+        Self {
+            poll_ms: 5000,
+            batch_size: 100,
+            gap_wait_ms: 10000,
+            max_attempts: 5,
+            broker_capacity: 256,
+        }
+    }
+}
+
+/// Settings of the end-to-end smoke test, run with the `--smoke-test` flag.
+///
+/// The test VM is cloned from `template_vmid` on the designated `node` and
+/// destroyed afterwards, so the node should be dedicated to testing. The
+/// connectivity check is skipped when `address` is not set.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SmokeEnv {
+    pub node: String,
+    pub template_vmid: i32,
+    pub ip_config: String,
+    pub address: Option<SocketAddr>,
+    pub cpu_cores: i32,
+    pub ram_gb: i32,
+    pub timeout_sec: u64,
+}
+
+impl Default for SmokeEnv {
+    fn default() -> Self {
+        Self {
+            node: "pve".to_owned(),
+            template_vmid: 9000,
+            ip_config: "ip=dhcp".to_owned(),
+            address: None,
+            cpu_cores: 1,
+            ram_gb: 1,
+            timeout_sec: 300,
+        }
+    }
+}
+
+/// Settings of the secrets provider, which reads the database password, the
+/// JWT secret and the Proxmox authorization header instead of the plain
+/// configuration values.
+///
+/// With the `file` provider, the secrets are the paths of files holding them,
+/// like Docker or Kubernetes secrets. With the `vault` provider, they are the
+/// keys of the KV v2 secret at `vault.path`. A missing secret keeps its plain
+/// value, the `env` provider keeps all of them. The secrets are read again
+/// every `reload_sec`, `0` disables the reload.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SecretsEnv {
+    pub provider: SecretBackend,
+    pub reload_sec: u64,
+    pub database_password: Option<String>,
+    pub token_secret: Option<String>,
+    pub proxmox_auth_header: Option<String>,
+    pub vault: VaultEnv,
+}
+
+impl Default for SecretsEnv {
+    fn default() -> Self {
+        Self {
+            provider: SecretBackend::Env,
+            reload_sec: 60,
+            database_password: None,
+            token_secret: None,
+            proxmox_auth_header: None,
+            vault: VaultEnv::default(),
+        }
+    }
+}
+
+/// Source of the secrets.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretBackend {
+    #[default]
+    Env,
+    File,
+    Vault,
+}
+
+/// All settings required to read secrets from HashiCorp Vault. The token is
+/// read from `token_file` on every reload when set, so it may rotate as well.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct VaultEnv {
+    pub url: String,
+    pub token: SecretString,
+    pub token_file: Option<PathBuf>,
+    pub mount: String,
+    pub path: String,
+}
+
+impl Default for VaultEnv {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:8200".to_owned(),
+            token: SecretString::default(),
+            token_file: None,
+            mount: "secret".to_owned(),
+            path: "dashboard".to_owned(),
+        }
+    }
+}
+
+/// Settings that may be changed at runtime, without a restart. The configured
+/// values are the initial ones, replaced by the reload file or by an
+/// administrator.
+///
+/// `log_level` takes filter directives in the `RUST_LOG` format, `None` keeps
+/// the filter set at startup. Features disabled by the flags are rejected
+/// until enabled again, the running operations are not affected.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct RuntimeEnv {
+    pub log_level: Option<String>,
+    pub scheduler_tick_sec: u64,
+    pub features: FeatureFlags,
+}
+
+impl Default for RuntimeEnv {
+    fn default() -> Self {
+        Self {
+            log_level: None,
+            scheduler_tick_sec: 30,
+            features: FeatureFlags::default(),
+        }
+    }
+}
+
+/// Features that may be switched off at runtime, like during a maintenance.
+///
+/// # Fields
+///
+/// * `registration`: New users may register.
+/// * `server_orders`: Users may order new servers.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct FeatureFlags {
+    pub registration: bool,
+    pub server_orders: bool,
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        Self {
+            registration: true,
+            server_orders: true,
+        }
+    }
+}
+
+/// Settings of the runtime settings reload.
+///
+/// The YAML `file` holding the runtime settings is checked for changes every
+/// `interval_sec`, no file disables the reload.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReloadEnv {
+    pub file: Option<PathBuf>,
+    pub interval_sec: u64,
+}
+
+impl Default for ReloadEnv {
+    fn default() -> Self {
+        Self {
+            file: None,
+            interval_sec: 10,
+        }
+    }
+}
+
+/// Settings of the in-process caches.
+///
+/// Entries of the product catalog stay valid for `catalog_ttl_sec`, the usage
+/// of the Proxmox nodes for `nodes_ttl_sec`, the power status of a VM for
+/// `vm_status_ttl_sec`, what the guest agent of a VM reported for
+/// `guest_info_ttl_sec` and the allowed networks of a user for
+/// `allowlist_ttl_sec`. Zero disables the cache.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CacheEnv {
+    pub catalog_ttl_sec: u64,
+    pub nodes_ttl_sec: u64,
+    pub vm_status_ttl_sec: u64,
+    pub guest_info_ttl_sec: u64,
+    pub allowlist_ttl_sec: u64,
+}
+
+impl Default for CacheEnv {
+    fn default() -> Self {
+        Self {
+            catalog_ttl_sec: 300,
+            nodes_ttl_sec: 15,
+            vm_status_ttl_sec: 2,
+            guest_info_ttl_sec: 30,
+            allowlist_ttl_sec: 30,
+        }
+    }
+}
+
+/// Settings of the Redis instance shared by the replicas.
+///
+/// The keys of the application start with `prefix`, so several deployments
+/// may share the instance. No `url` disables the shared state, every replica
+/// then keeps to itself. Connecting and every command give up after
+/// `timeout_ms`, so an unavailable Redis doesn't hold up the requests.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RedisEnv {
+    pub url: Option<String>,
+    pub prefix: String,
+    pub timeout_ms: u64,
+}
+
+impl Default for RedisEnv {
+    fn default() -> Self {
+        Self {
+            url: None,
+            prefix: "dashboard".to_owned(),
+            timeout_ms: 500,
+        }
+    }
+}
+
+/// Reverse proxies in front of the application.
+///
+/// `trusted` is a comma-separated list of IPv4 or IPv6 CIDR blocks, parsed
+/// once when the configuration is loaded. The client address is read from the
+/// `X-Forwarded-For` header only when the connection comes from one of them,
+/// otherwise it is the address of the peer. Invalid blocks are ignored.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ProxyEnv {
+    #[serde(deserialize_with = "deserialize_networks")]
+    pub trusted: Vec<IpNet>,
+}
+
+impl Default for ProxyEnv {
+    fn default() -> Self {
+        Self {
+            trusted: parse_networks("127.0.0.0/8"),
+        }
+    }
+}
+
+/// Parses a comma-separated list of CIDR blocks, skipping the invalid ones and
+/// the ones with host bits set.
+///
+/// # Arguments
+///
+/// * `networks`: Comma-separated CIDR blocks.
+///
+/// # Returns
+///
+/// `Vec<IpNet>` containing the valid networks.
+///
+fn parse_networks(networks: &str) -> Vec<IpNet> {
+    networks
+        .split(',')
+        .filter_map(|cidr| cidr.trim().parse::<IpNet>().ok())
+        .filter(|network| network.trunc() == *network)
+        .collect()
+}
+
+fn deserialize_networks<'de, D>(deserializer: D) -> core::result::Result<Vec<IpNet>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer).map(|networks| parse_networks(&networks))
+}
+
+/// Settings of the CAPTCHA guarding the logins and registrations.
+///
+/// A login requires a solved CAPTCHA once `login_failures` logins failed for
+/// the email address or from the client address within `window_sec`, a
+/// registration once `registrations` accounts were registered from the client
+/// address. The tokens are verified with the `secret` at `verify_url`, the
+/// endpoint of the provider by default, which must respond within
+/// `timeout_sec`. The `disabled` provider never requires a CAPTCHA.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CaptchaEnv {
+    pub provider: CaptchaBackend,
+    pub secret: SecretString,
+    pub verify_url: Option<String>,
+    pub timeout_sec: u64,
+    pub window_sec: i64,
+    pub login_failures: i64,
+    pub registrations: i64,
+}
+
+impl Default for CaptchaEnv {
+    fn default() -> Self {
+        Self {
+            provider: CaptchaBackend::Disabled,
+            secret: SecretString::default(),
+            verify_url: None,
+            timeout_sec: 10,
+            window_sec: 900,
+            login_failures: 3,
+            registrations: 3,
+        }
+    }
+}
+
+/// Provider of the CAPTCHA.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptchaBackend {
+    #[default]
+    Disabled,
+    Hcaptcha,
+    Turnstile,
+}
+
+/// Settings of the transport delivering the emails to the users.
+///
+/// The `http` transport posts every email as JSON to `api_url` with the
+/// `api_key` as a bearer token, from the `from` address, and the API must
+/// respond within `timeout_sec`. The `log` transport only logs the recipient
+/// and the subject, never the body holding the tokens, and is meant for
+/// development.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MailEnv {
+    pub transport: MailTransport,
+    pub api_url: String,
+    pub api_key: SecretString,
+    pub from: String,
+    pub timeout_sec: u64,
+}
+
+impl Default for MailEnv {
+    fn default() -> Self {
+        Self {
+            transport: MailTransport::Log,
+            api_url: "https://api.resend.com/emails".to_owned(),
+            api_key: SecretString::default(),
+            from: String::new(),
+            timeout_sec: 10,
+        }
+    }
+}
+
+/// Transport of the emails.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailTransport {
+    #[default]
+    Log,
+    Http,
+}
+
+/// Settings of the login with OpenID Connect providers.
+///
+/// Every provider is registered under `providers` by the name the login is
+/// started with, like `google`. The providers redirect the user back to
+/// `redirect_url`, the address of `/auth/oidc/callback`, which must happen
+/// within `state_ttl_sec`. The callback then redirects the user to
+/// `frontend_url` with the token. The providers must respond within
+/// `timeout_sec`.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OidcEnv {
+    pub redirect_url: String,
+    pub frontend_url: String,
+    pub state_ttl_sec: i64,
+    pub timeout_sec: u64,
+    pub providers: HashMap<String, OidcProviderEnv>,
+}
+
+impl Default for OidcEnv {
+    fn default() -> Self {
+        Self {
+            redirect_url: String::new(),
+            frontend_url: String::new(),
+            state_ttl_sec: 600,
+            timeout_sec: 10,
+            providers: HashMap::new(),
+        }
+    }
+}
+
+/// Client registered at an OpenID Connect provider.
+///
+/// The endpoints are discovered at the `issuer`, which must match the issuer
+/// of the ID tokens. Issuers not sending the `email_verified` claim, like
+/// Microsoft Entra ID, only link accounts with `trust_email`, which treats
+/// their email addresses as verified.
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderEnv {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: SecretString,
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: String,
+    #[serde(default)]
+    pub trust_email: bool,
+}
+
+fn default_oidc_scopes() -> String {
+    "openid email profile".to_owned()
+}
+
+/// Settings of the SAML single sign-on of the organizations.
+///
+/// The dashboard is the service provider of every organization, identified by
+/// its metadata URL under `base_url`, the public URL of the API. After the
+/// assertion is consumed, the user is redirected to `redirect_url`, a page of
+/// the frontend, with the token in the fragment. A login must complete within
+/// `request_ttl_sec`.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SamlEnv {
+    pub base_url: String,
+    pub redirect_url: String,
+    pub request_ttl_sec: i64,
+}
+
+impl Default for SamlEnv {
+    fn default() -> Self {
+        Self {
+            base_url: "http://127.0.0.1:8080".to_owned(),
+            redirect_url: String::new(),
+            request_ttl_sec: 600,
+        }
+    }
+}
+
+/// Security headers added to every response.
+///
+/// Browsers are told to use HTTPS only for `hsts_max_age_sec`, 0 to leave the
+/// `Strict-Transport-Security` header out, covering the subdomains with
+/// `hsts_include_subdomains`. `nosniff` stops them from guessing the content
+/// type, `referrer_policy` limits the referrer sent along, and
+/// `frame_ancestors` is the CSP directive restricting the pages allowed to
+/// frame the responses. Empty values leave their header out. Without
+/// `enabled`, responses are left as they are.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SecurityEnv {
+    pub enabled: bool,
+    pub hsts_max_age_sec: u64,
+    pub hsts_include_subdomains: bool,
+    pub nosniff: bool,
+    pub referrer_policy: String,
+    pub frame_ancestors: String,
+}
+
+impl SecurityEnv {
+    /// Builds the security headers from the settings. Values that aren't valid
+    /// in a header are ignored.
+    ///
+    /// # Returns
+    ///
+    /// `Vec` of the headers with their values.
+    ///
+    pub fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        let hsts = (self.hsts_max_age_sec > 0).then(|| match self.hsts_include_subdomains {
+            true => format!("max-age={}; includeSubDomains", self.hsts_max_age_sec),
+            false => format!("max-age={}", self.hsts_max_age_sec),
+        });
+        let nosniff = self.nosniff.then(|| "nosniff".to_owned());
+        let referrer_policy = Some(self.referrer_policy.trim().to_owned());
+        let frame_ancestors = Some(self.frame_ancestors.trim())
+            .filter(|sources| !sources.is_empty())
+            .map(|sources| format!("frame-ancestors {sources}"));
+
+        [
+            (STRICT_TRANSPORT_SECURITY, hsts),
+            (X_CONTENT_TYPE_OPTIONS, nosniff),
+            (REFERRER_POLICY, referrer_policy),
+            (CONTENT_SECURITY_POLICY, frame_ancestors),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = value.filter(|value| !value.is_empty())?;
+            Some((name, HeaderValue::from_str(&value).ok()?))
+        })
+        .collect()
+    }
+}
+
+impl Default for SecurityEnv {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hsts_max_age_sec: 31_536_000,
+            hsts_include_subdomains: true,
+            nosniff: true,
+            referrer_policy: "no-referrer".to_owned(),
+            frame_ancestors: "'none'".to_owned(),
+        }
+    }
+}
+
+/// Settings of the HTTPS served by the application itself.
+///
+/// With both the PEM `cert_file`, holding the certificate chain, and the PEM
+/// `key_file` set, the server speaks HTTPS only; without them, plain HTTP for
+/// a reverse proxy to terminate TLS. The files are checked for changes every
+/// `reload_sec`, so a renewed certificate is served without a restart, 0
+/// disables the reload.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TlsEnv {
+    pub cert_file: Option<PathBuf>,
+    pub key_file: Option<PathBuf>,
+    pub reload_sec: u64,
+}
+
+impl Default for TlsEnv {
+    fn default() -> Self {
+        Self {
+            cert_file: None,
+            key_file: None,
+            reload_sec: 60,
+        }
+    }
+}
+
+/// Limits and timeouts of the HTTP server, protecting it against slow clients
+/// and oversized payloads.
+///
+/// Request bodies are limited to `body_limit_bytes`, overridden for a route
+/// group, named after its module like `admin` or `webhook`, in `body_limits`.
+/// A request must be answered within `request_timeout_sec`, and its headers
+/// must arrive within `header_read_timeout_sec`, so slowloris clients can't
+/// hold the connections. Idle HTTP/1 connections are kept open with
+/// `keep_alive`, HTTP/2 ones are pinged every `http2_keep_alive_sec`. At most
+/// `max_concurrent_requests` are handled at once, the others wait. Zero
+/// disables a timeout or the concurrency limit.
+///
+/// With `compression`, responses are compressed with gzip or Brotli if the
+/// client accepts it. With `etag`, successful `GET` responses are tagged, so
+/// polling clients get `304 Not Modified` for unchanged data.
+///
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpEnv {
+    pub body_limit_bytes: usize,
+    pub body_limits: HashMap<String, usize>,
+    pub request_timeout_sec: u64,
+    pub header_read_timeout_sec: u64,
+    pub keep_alive: bool,
+    pub http2_keep_alive_sec: u64,
+    pub max_concurrent_requests: usize,
+    pub compression: bool,
+    pub etag: bool,
+}
+
+impl HttpEnv {
+    /// Returns the body limit of a route group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group`: Name of the route group.
+    ///
+    /// # Returns
+    ///
+    /// Maximum size of a request body in bytes.
+    ///
+    pub fn body_limit(&self, group: &str) -> usize {
+        self.body_limits
+            .get(group)
+            .copied()
+            .unwrap_or(self.body_limit_bytes)
+    }
+
+    /// Returns the time a request must be answered within, `None` if unlimited.
+    ///
+    pub fn request_timeout(&self) -> Option<Duration> {
+        Self::duration(self.request_timeout_sec)
+    }
+
+    /// Returns the time the headers of a request must arrive within, `None` if
+    /// unlimited.
+    ///
+    pub fn header_read_timeout(&self) -> Option<Duration> {
+        Self::duration(self.header_read_timeout_sec)
+    }
+
+    /// Returns the interval of the HTTP/2 pings, `None` if disabled.
+    ///
+    pub fn http2_keep_alive(&self) -> Option<Duration> {
+        Self::duration(self.http2_keep_alive_sec)
+    }
+
+    /// Returns the number of requests handled at once, `None` if unlimited.
+    ///
+    pub fn concurrency_limit(&self) -> Option<usize> {
+        Some(self.max_concurrent_requests).filter(|limit| *limit > 0)
+    }
+
+    fn duration(seconds: u64) -> Option<Duration> {
+        (seconds > 0).then(|| Duration::from_secs(seconds))
+    }
+}
+
+impl Default for HttpEnv {
+    fn default() -> Self {
+        Self {
+            body_limit_bytes: 2 * 1024 * 1024,
+            body_limits: HashMap::new(),
+            request_timeout_sec: 60,
+            header_read_timeout_sec: 10,
+            keep_alive: true,
+            http2_keep_alive_sec: 20,
+            max_concurrent_requests: 1024,
+            compression: true,
+            etag: true,
+        }
+    }
+}
+
+/// Settings of the internal gRPC API, for automation and other services.
+///
+/// The API is served on `address`, next to the REST API, no address disables
+/// it. Every call must carry the shared `token` as `Bearer` authorization
+/// metadata, without a token the API isn't started.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GrpcEnv {
+    pub address: Option<SocketAddr>,
+    pub token: SecretString,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Represents the different environments the application can run in.
+///
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    Local,
+    Production,
+}
+
+impl Environment {
+    /// Returns the filename for the environment-specific configuration file.
+    ///
+    pub fn as_filename(&self) -> String {
+        serde_json::to_string(self)
+            .expect("Environment should always serialize successfully")
+            .trim_matches('"')
+            .to_owned()
+            + ".yaml"
+    }
+}
+
+impl From<&str> for Environment {
+    fn from(value: &str) -> Self {
+        match serde_json::from_str::<Self>(value) {
+            Ok(environment) => environment,
+            Err(error) => {
+                tracing::warn!(target: "config", value, ?error, "Incorrect environment format. Use either `local` or `production`.");
+                Self::Local
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Configuration for Cross-Origin Resource Sharing (CORS).
+///
+/// `origin` is a comma-separated list of allowed origins, or `*` for any
+/// origin. Browsers send credentials (cookies, authorization headers) only
+/// when `credentials` is enabled, which is ignored for any origin. Preflight
+/// responses are cached by browsers for `max_age_sec`, if set.
+///
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Cors {
+    pub origin: String,
+    pub methods: String,
+    pub headers: String,
+    #[serde(default)]
+    pub expose_headers: String,
+    #[serde(default)]
+    pub credentials: bool,
+    #[serde(default)]
+    pub max_age_sec: Option<u64>,
+}
+
+impl Cors {
+    /// Checks whether any origin is allowed.
+    ///
+    pub fn any_origin(&self) -> bool {
+        self.origin.trim() == "*"
+    }
+
+    /// Parses the comma-separated origins string into a vector of
+    /// `axum::http::HeaderValue`. Each origin is trimmed and parsed. Invalid
+    /// origins are ignored.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<HeaderValue>` containing the allowed origins.
+    ///
+    pub fn allow_origins(&self) -> Vec<HeaderValue> {
+        self.origin
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .filter_map(|origin| origin.parse().ok())
+            .collect()
+    }
+
+    /// Parses the comma-separated methods string into a vector of
+    /// `axum::http::Method`. Each method string is trimmed and parsed. Invalid
+    /// method strings are ignored.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<Method>` containing the allowed HTTP methods.
+    ///
+    pub fn allow_methods(&self) -> Vec<Method> {
+        self.methods
+            .split(',')
+            .filter_map(|method| method.trim().parse().ok())
+            .collect()
+    }
+
+    /// Parses the comma-separated headers string into a vector of
+    /// `axum::http::HeaderName`. Each header string is trimmed and parsed.
+    /// Invalid header strings are ignored.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<HeaderName>` containing the allowed HTTP headers.
+    ///
+    pub fn allow_headers(&self) -> Vec<HeaderName> {
+        Self::parse_headers(&self.headers)
+    }
+
+    /// Parses the comma-separated headers string that scripts of the allowed
+    /// origins may read from the responses.
+    ///
+    /// # Returns
+    ///
+    /// `Vec<HeaderName>` containing the exposed HTTP headers.
+    ///
+    pub fn expose_headers(&self) -> Vec<HeaderName> {
+        Self::parse_headers(&self.expose_headers)
+    }
+
+    /// Returns whether credentials are allowed. Never for any origin, which
+    /// browsers reject together with credentials.
+    ///
+    pub fn allow_credentials(&self) -> bool {
+        self.credentials && !self.any_origin()
+    }
+
+    /// Returns how long browsers may cache the preflight responses.
+    ///
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age_sec.map(Duration::from_secs)
+    }
+
+    fn parse_headers(headers: &str) -> Vec<HeaderName> {
+        headers
+            .split(',')
+            .filter_map(|header| header.trim().parse().ok())
+            .collect()
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scheduler_tick_should_be_read_under_its_old_name() {
+        // Arrange
+        let config = Config {
+            scheduler: SchedulerEnv {
+                tick_sec: Some(5),
+                ..SchedulerEnv::default()
+            },
+            ..Config::default()
+        };
+
+        // Act
+        let config = config.with_aliases();
+
+        // Assert
+        assert_eq!(config.runtime.scheduler_tick_sec, 5);
+        assert!(
+            config
+                .deprecations()
+                .iter()
+                .any(|warning| warning.starts_with("scheduler.tick_sec"))
+        );
+    }
+
+    #[test]
+    fn cors_should_parse_origin_list() {
+        // Arrange
+        let cors = Cors {
+            origin: "https://a.example.com, https://b.example.com,".to_owned(),
+            credentials: true,
+            ..Cors::default()
+        };
+
+        // Act
+        let origins = cors.allow_origins();
+
+        // Assert
+        assert_eq!(origins, ["https://a.example.com", "https://b.example.com"]);
+        assert!(cors.allow_credentials());
+    }
+
+    #[test]
+    fn cors_should_not_allow_credentials_for_any_origin() {
+        // Arrange
+        let cors = Cors {
+            origin: "*".to_owned(),
+            credentials: true,
+            ..Cors::default()
+        };
+
+        // Act
+        let credentials = cors.allow_credentials();
+
+        // Assert
+        assert!(cors.any_origin());
+        assert!(!credentials);
+    }
+
+    #[test]
+    fn proxy_should_skip_invalid_trusted_networks() {
+        // Arrange
+        let networks = "10.0.0.0/8, 10.0.0.1/24,, 192.168.1.10/32, fd00::/8, 2001:db8::1/64";
+
+        // Act
+        let trusted = parse_networks(networks)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(trusted, ["10.0.0.0/8", "192.168.1.10/32", "fd00::/8"]);
+    }
+
+    #[test]
+    fn http_should_override_body_limit_per_group() {
+        // Arrange
+        let http = HttpEnv {
+            body_limits: HashMap::from([("admin".to_owned(), 64)]),
+            request_timeout_sec: 0,
+            ..HttpEnv::default()
+        };
+
+        // Act
+        let admin = http.body_limit("admin");
+        let server = http.body_limit("server");
+
+        // Assert
+        assert_eq!(admin, 64);
+        assert_eq!(server, 2 * 1024 * 1024);
+        assert_eq!(http.request_timeout(), None);
+        assert_eq!(http.concurrency_limit(), Some(1024));
+    }
+
+    #[test]
+    fn security_should_leave_out_disabled_headers() {
+        // Arrange
+        let security = SecurityEnv {
+            hsts_max_age_sec: 0,
+            referrer_policy: " ".to_owned(),
+            frame_ancestors: "'self' https://admin.example.com".to_owned(),
+            ..SecurityEnv::default()
+        };
+
+        // Act
+        let headers = security.headers();
+
+        // Assert
+        assert_eq!(
+            headers,
+            [
+                (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                (
+                    CONTENT_SECURITY_POLICY,
+                    HeaderValue::from_static("frame-ancestors 'self' https://admin.example.com")
+                ),
+            ]
+        );
+    }
+}
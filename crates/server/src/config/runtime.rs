@@ -0,0 +1,139 @@
+use crate::config::RuntimeEnv;
+use crate::state::AppState;
+use arc_swap::ArcSwap;
+use dashboard_common::prelude::{Error, Result};
+use dashboard_common::telemetry;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::time::MissedTickBehavior;
+
+/// Validates the runtime settings and replaces the current ones. The new log
+/// filter is applied immediately, the other settings on their next use.
+///
+/// # Arguments
+///
+/// * `runtime`: Current runtime settings, shared by the application state.
+/// * `settings`: New runtime settings.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the settings are replaced.
+///
+pub fn apply(runtime: &ArcSwap<RuntimeEnv>, settings: RuntimeEnv) -> Result<()> {
+    if settings.scheduler_tick_sec == 0 {
+        return Err(Error::Validation(
+            "Scheduler tick must be at least one second".to_owned(),
+        ));
+    }
+    if let Some(directives) = &settings.log_level {
+        telemetry::reload_filter(directives)?;
+    }
+
+    runtime.store(Arc::new(settings));
+    Ok(())
+}
+
+/// Public entry point for the runtime settings reload background task.
+///
+/// Checks the configured reload file for changes every configured interval,
+/// never returning, and applies the settings of a changed file. An invalid
+/// file keeps the current settings. Returns immediately if no reload file is
+/// configured.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+///
+pub async fn watch(app_state: AppState) {
+    let Some(file) = app_state.config.reload.file.clone() else {
+        return;
+    };
+
+    let period = Duration::from_secs(app_state.config.reload.interval_sec.max(1));
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut last_modified = None;
+
+    loop {
+        interval.tick().await;
+        let modified = match modified_at(&file).await {
+            Ok(modified) => modified,
+            Err(error) => {
+                tracing::error!(target: "config", ?error, ?file, "Failed to check runtime settings!");
+                continue;
+            }
+        };
+        if last_modified == Some(modified) {
+            continue;
+        }
+        last_modified = Some(modified);
+
+        match load(&file).and_then(|settings| apply(&app_state.runtime, settings)) {
+            Ok(()) => {
+                tracing::info!(target: "config", runtime = ?app_state.runtime.load(), "Runtime settings reloaded")
+            }
+            Err(error) => {
+                tracing::error!(target: "config", ?error, ?file, "Failed to reload runtime settings!")
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the time the file was last modified.
+///
+async fn modified_at(file: &Path) -> Result<SystemTime> {
+    Ok(tokio::fs::metadata(file).await?.modified()?)
+}
+
+/// Reads the runtime settings from a YAML file, missing settings take their
+/// default values.
+///
+fn load(file: &Path) -> Result<RuntimeEnv> {
+    let settings = config::Config::builder()
+        .add_source(config::File::from(file))
+        .build()?
+        .try_deserialize::<RuntimeEnv>()?;
+
+    Ok(settings)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_settings_should_be_loaded_and_validated() {
+        // Arrange
+        let file = std::env::temp_dir().join(format!("runtime-{}.yaml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &file,
+            "scheduler_tick_sec: 5\nfeatures:\n  server_orders: false\n",
+        )
+        .unwrap();
+        let runtime = ArcSwap::from_pointee(RuntimeEnv::default());
+
+        // Act
+        let loaded = load(&file).and_then(|settings| apply(&runtime, settings));
+        let invalid = apply(
+            &runtime,
+            RuntimeEnv {
+                scheduler_tick_sec: 0,
+                ..RuntimeEnv::default()
+            },
+        );
+        std::fs::remove_file(&file).unwrap();
+
+        // Assert
+        assert!(loaded.is_ok());
+        assert!(matches!(invalid, Err(Error::Validation(_))));
+        let settings = runtime.load();
+        assert_eq!(settings.scheduler_tick_sec, 5);
+        assert!(!settings.features.server_orders);
+        assert!(settings.features.registration);
+    }
+}
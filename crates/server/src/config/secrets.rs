@@ -0,0 +1,345 @@
+use crate::config::{Config, SecretBackend, SecretsEnv, VaultEnv};
+use crate::state::AppState;
+use async_trait::async_trait;
+use dashboard_common::prelude::{Error, Result};
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// Secret setting that may be replaced at runtime when the secret rotates.
+///
+/// Clones share the value, so every clone of the configuration sees the
+/// rotated secret.
+///
+#[derive(Clone, Default)]
+pub struct Secret(Arc<RwLock<SecretString>>);
+
+impl Secret {
+    /// Returns the current value of the secret.
+    ///
+    pub fn get(&self) -> SecretString {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Replaces the value of the secret.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the value has changed.
+    ///
+    pub fn set(&self, value: SecretString) -> bool {
+        let mut current = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        if current.expose_secret() == value.expose_secret() {
+            return false;
+        }
+        *current = value;
+
+        true
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(Arc::new(RwLock::new(value.into())))
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        SecretString::deserialize(deserializer).map(|value| Self(Arc::new(RwLock::new(value))))
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// An abstract interface for reading secrets from an external store.
+///
+#[async_trait]
+pub trait SecretProvider {
+    /// Reads the current value of a secret.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: Name of the secret in the store.
+    ///
+    /// # Returns
+    ///
+    /// Value of the secret.
+    ///
+    async fn read(&self, name: &str) -> Result<SecretString>;
+}
+
+/// Provider reading every secret from its own file, as mounted by Docker or
+/// Kubernetes. The name of a secret is the path of its file, the trailing line
+/// break is ignored.
+///
+#[derive(Debug, Clone, Default)]
+pub struct FileSecrets;
+
+#[async_trait]
+impl SecretProvider for FileSecrets {
+    async fn read(&self, name: &str) -> Result<SecretString> {
+        read_file(Path::new(name)).await
+    }
+}
+
+/// Provider reading the secrets from a single KV v2 secret of HashiCorp Vault.
+/// The name of a secret is its key in the KV secret.
+///
+pub struct VaultSecrets {
+    client: Client,
+    settings: VaultEnv,
+}
+
+impl VaultSecrets {
+    /// Creates a new instance of the Vault provider.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings`: All settings required to read secrets from Vault.
+    ///
+    pub fn new(settings: VaultEnv) -> Self {
+        Self {
+            client: Client::new(),
+            settings,
+        }
+    }
+
+    /// Returns the Vault token, preferring the token file.
+    ///
+    async fn token(&self) -> Result<SecretString> {
+        match &self.settings.token_file {
+            Some(path) => read_file(path).await,
+            None => Ok(self.settings.token.clone()),
+        }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for VaultSecrets {
+    async fn read(&self, name: &str) -> Result<SecretString> {
+        let url = format!(
+            "{}/v1/{}/data/{}",
+            self.settings.url.trim_end_matches('/'),
+            self.settings.mount,
+            self.settings.path,
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("X-Vault-Token", self.token().await?.expose_secret())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::Secret(format!(
+                "Vault responded with status {} to reading {}",
+                response.status(),
+                self.settings.path,
+            )));
+        }
+
+        let body = response.json::<serde_json::Value>().await?;
+        body["data"]["data"][name]
+            .as_str()
+            .map(SecretString::from)
+            .ok_or_else(|| {
+                Error::Secret(format!(
+                    "Vault secret {} has no key {name}",
+                    self.settings.path
+                ))
+            })
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Creates the configured secrets provider.
+///
+/// # Arguments
+///
+/// * `settings`: Settings of the secrets provider.
+///
+/// # Returns
+///
+/// Secrets provider, `None` if the secrets are taken from the configuration.
+///
+pub fn provider(settings: &SecretsEnv) -> Option<Arc<dyn SecretProvider + Send + Sync>> {
+    match settings.provider {
+        SecretBackend::Env => None,
+        SecretBackend::File => Some(Arc::new(FileSecrets)),
+        SecretBackend::Vault => Some(Arc::new(VaultSecrets::new(settings.vault.clone()))),
+    }
+}
+
+/// Reads the secrets from the provider into the configuration. Secrets without
+/// a name in the settings keep their configured value.
+///
+/// # Arguments
+///
+/// * `provider`: Secrets provider.
+/// * `config`: Application's configuration, whose secrets are replaced.
+///
+/// # Returns
+///
+/// Names of the secrets whose value has changed.
+///
+pub async fn refresh(
+    provider: &(dyn SecretProvider + Send + Sync),
+    config: &Config,
+) -> Result<Vec<&'static str>> {
+    let settings = &config.secrets;
+    let secrets = [
+        (
+            "database_password",
+            &settings.database_password,
+            &config.database.password,
+        ),
+        ("token_secret", &settings.token_secret, &config.token.secret),
+        (
+            "proxmox_auth_header",
+            &settings.proxmox_auth_header,
+            &config.proxmox.auth_header,
+        ),
+    ];
+
+    let mut changed = Vec::new();
+    for (key, name, secret) in secrets {
+        let Some(name) = name else {
+            continue;
+        };
+        if secret.set(provider.read(name).await?) {
+            changed.push(key);
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Public entry point for the secrets reload background task.
+///
+/// Reads the secrets again every configured interval, never returning. A
+/// rotated database password is applied to the new connections of the pool,
+/// the other secrets are read on every use. A failed reload keeps the previous
+/// values.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `provider`: Secrets provider.
+///
+pub async fn watch(app_state: AppState, provider: Arc<dyn SecretProvider + Send + Sync>) {
+    let period = Duration::from_secs(app_state.config.secrets.reload_sec);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        let changed = match refresh(provider.as_ref(), &app_state.config).await {
+            Ok(changed) => changed,
+            Err(error) => {
+                tracing::error!(target: "config", ?error, "Failed to reload secrets!");
+                continue;
+            }
+        };
+
+        if changed.contains(&"database_password") {
+            app_state
+                .pool
+                .set_connect_options(app_state.config.get_database_connect_options());
+            if let Some(replica) = &app_state.replica {
+                replica.set_connect_options(&app_state.config);
+            }
+        }
+        if !changed.is_empty() {
+            tracing::info!(target: "config", ?changed, "Secrets rotated");
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Reads a secret from a file, without the trailing line break.
+///
+async fn read_file(path: &Path) -> Result<SecretString> {
+    let value = tokio::fs::read_to_string(path).await?;
+
+    Ok(value.trim_end_matches(['\r', '\n']).into())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn file_secrets_should_rotate_shared_config() {
+        // Arrange
+        let file = std::env::temp_dir().join(format!("secret-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&file, "first\n").unwrap();
+        let mut config = Config::default();
+        config.secrets.token_secret = Some(file.to_string_lossy().into_owned());
+        let shared = config.clone();
+
+        // Act
+        let loaded = refresh(&FileSecrets, &config).await.unwrap();
+        let unchanged = refresh(&FileSecrets, &config).await.unwrap();
+        std::fs::write(&file, "second").unwrap();
+        let rotated = refresh(&FileSecrets, &config).await.unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        // Assert
+        assert_eq!(loaded, ["token_secret"]);
+        assert!(unchanged.is_empty());
+        assert_eq!(rotated, ["token_secret"]);
+        assert_eq!(shared.token.secret.get().expose_secret(), "second");
+        assert_eq!(shared.proxmox.auth_header.get().expose_secret(), "");
+    }
+
+    #[tokio::test]
+    async fn vault_secrets_should_read_kv_key() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let response = json!({ "data": { "data": { "db": "vault-password" } } });
+        Mock::given(method("GET"))
+            .and(path("/v1/secret/data/dashboard"))
+            .and(header("X-Vault-Token", "root"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response))
+            .mount(&mock_server)
+            .await;
+        let provider = VaultSecrets::new(VaultEnv {
+            url: mock_server.uri(),
+            token: "root".into(),
+            ..VaultEnv::default()
+        });
+
+        // Act
+        let secret = provider.read("db").await;
+        let missing = provider.read("jwt").await;
+
+        // Assert
+        assert_eq!(secret.unwrap().expose_secret(), "vault-password");
+        assert!(matches!(missing, Err(Error::Secret(_))));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::config::TlsEnv;
+use axum_server::tls_rustls::RustlsConfig;
+use dashboard_common::prelude::{Error, Result};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tokio::time::MissedTickBehavior;
+
+/// Loads the certificate and the key the server speaks HTTPS with.
+///
+/// # Arguments
+///
+/// * `settings`: TLS settings of the application.
+///
+/// # Returns
+///
+/// TLS configuration of the server, `None` if HTTPS isn't configured.
+///
+/// # Errors
+///
+/// `Error::Validation` if only one of the files is configured,
+/// `Error::InputOutput` if the files can't be read or don't match.
+///
+pub async fn load(settings: &TlsEnv) -> Result<Option<RustlsConfig>> {
+    let (cert_file, key_file) = match (&settings.cert_file, &settings.key_file) {
+        (Some(cert_file), Some(key_file)) => (cert_file, key_file),
+        (None, None) => return Ok(None),
+        _ => {
+            return Err(Error::Validation(
+                "TLS needs both the certificate and the key file".to_owned(),
+            ));
+        }
+    };
+
+    // The HTTP client and the database driver use ring as well, so it is the
+    // only provider compiled in. Fails if already installed, which is fine.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let config = RustlsConfig::from_pem_file(cert_file, key_file).await?;
+
+    Ok(Some(config))
+}
+
+/// Public entry point for the certificate reload background task.
+///
+/// Checks the certificate and the key for changes every configured interval,
+/// never returning, and serves the changed pair to the new connections. A
+/// pair that can't be loaded, like a certificate renewed before its key, keeps
+/// the current one and is tried again on the next check. Returns immediately
+/// if HTTPS or the reload isn't configured.
+///
+/// # Arguments
+///
+/// * `config`: TLS configuration of the running server.
+/// * `settings`: TLS settings of the application.
+///
+pub async fn watch(config: RustlsConfig, settings: TlsEnv) {
+    let (Some(cert_file), Some(key_file)) = (settings.cert_file, settings.key_file) else {
+        return;
+    };
+    if settings.reload_sec == 0 {
+        return;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(settings.reload_sec));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut last_modified = modified_at(&cert_file, &key_file).await.ok();
+
+    loop {
+        interval.tick().await;
+        let modified = match modified_at(&cert_file, &key_file).await {
+            Ok(modified) => modified,
+            Err(error) => {
+                tracing::error!(target: "config", ?error, ?cert_file, "Failed to check TLS certificate!");
+                continue;
+            }
+        };
+        if last_modified == Some(modified) {
+            continue;
+        }
+
+        match config.reload_from_pem_file(&cert_file, &key_file).await {
+            Ok(()) => {
+                last_modified = Some(modified);
+                tracing::info!(target: "config", ?cert_file, "TLS certificate reloaded");
+            }
+            Err(error) => {
+                tracing::error!(target: "config", ?error, ?cert_file, "Failed to reload TLS certificate!")
+            }
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the times the certificate and the key were last modified.
+///
+async fn modified_at(cert_file: &Path, key_file: &Path) -> Result<(SystemTime, SystemTime)> {
+    let cert = tokio::fs::metadata(cert_file).await?.modified()?;
+    let key = tokio::fs::metadata(key_file).await?.modified()?;
+
+    Ok((cert, key))
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn load_should_require_both_files() {
+        // Arrange
+        let settings = |cert_file: Option<&str>, key_file: Option<&str>| TlsEnv {
+            cert_file: cert_file.map(Into::into),
+            key_file: key_file.map(Into::into),
+            ..TlsEnv::default()
+        };
+
+        // Act
+        let plain = load(&settings(None, None)).await;
+        let partial = load(&settings(Some("cert.pem"), None)).await;
+        let missing = load(&settings(Some("missing.pem"), Some("missing.key"))).await;
+
+        // Assert
+        assert!(matches!(plain, Ok(None)));
+        assert!(matches!(partial, Err(Error::Validation(_))));
+        assert!(matches!(missing, Err(Error::InputOutput(_))));
+    }
+}
@@ -0,0 +1,285 @@
+//! Internal gRPC API for service-to-service calls, sharing the service layer
+//! with the REST handlers.
+
+/// Messages, server and client generated from `proto/internal.proto`.
+pub mod proto {
+    tonic::include_proto!("dashboard.internal.v1");
+}
+
+use crate::model::queries;
+use crate::model::types::{ApiServer, ServerStatus};
+use crate::services::action;
+use crate::state::AppState;
+use crate::web::types::ServerAction;
+use axum::http::StatusCode;
+use dashboard_common::prelude::Error;
+use proto::servers_server::{Servers, ServersServer};
+use proto::{
+    Action, GetServerRequest, ListServersRequest, ListServersResponse, RunActionRequest, Server,
+    Status as ProtoStatus,
+};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Code, Request, Response, Status};
+use uuid::Uuid;
+
+/// Metadata carrying the token of a call.
+const AUTHORIZATION: &str = "authorization";
+
+type GrpcResult<T> = Result<Response<T>, Status>;
+
+/// gRPC service of the servers. Servers are found by their ID alone and act
+/// on behalf of their owner, as the callers are trusted services.
+///
+#[derive(Clone)]
+pub struct ServersService {
+    app_state: AppState,
+}
+
+#[tonic::async_trait]
+impl Servers for ServersService {
+    #[tracing::instrument(level = "trace", target = "grpc", skip(self))]
+    async fn get_server(&self, request: Request<GetServerRequest>) -> GrpcResult<Server> {
+        let server_id = parse_id(&request.get_ref().server_id)?;
+        let server = self.find(server_id).await?;
+
+        Ok(Response::new(server))
+    }
+
+    #[tracing::instrument(level = "trace", target = "grpc", skip(self))]
+    async fn list_servers(
+        &self,
+        request: Request<ListServersRequest>,
+    ) -> GrpcResult<ListServersResponse> {
+        let user_id = parse_id(&request.get_ref().user_id)?;
+        let servers = queries::get_servers_for_user(&self.app_state.pool, user_id)
+            .await
+            .map_err(to_status)?
+            .into_iter()
+            .map(|server| to_proto(server, user_id))
+            .collect();
+
+        Ok(Response::new(ListServersResponse { servers }))
+    }
+
+    #[tracing::instrument(level = "trace", target = "grpc", skip(self))]
+    async fn run_action(&self, request: Request<RunActionRequest>) -> GrpcResult<Server> {
+        let server_id = parse_id(&request.get_ref().server_id)?;
+        let action = match request.get_ref().action() {
+            Action::Start => ServerAction::Start,
+            Action::Stop => ServerAction::Stop,
+            Action::Reboot => ServerAction::Reboot,
+            Action::Shutdown => ServerAction::Shutdown,
+            Action::Unspecified => return Err(Status::invalid_argument("Action is missing")),
+        };
+        let app_state = &self.app_state;
+        let user_id = queries::get_server_owner(&app_state.pool, server_id)
+            .await
+            .map_err(to_status)?;
+
+        let old_status = action::begin(app_state, user_id, server_id, action)
+            .await
+            .map_err(to_status)?;
+        action::run(app_state.clone(), user_id, server_id, action, old_status)
+            .await
+            .map_err(to_status)?;
+        let server = self.find(server_id).await?;
+
+        Ok(Response::new(server))
+    }
+}
+
+impl ServersService {
+    async fn find(&self, server_id: Uuid) -> Result<Server, Status> {
+        let pool = &self.app_state.pool;
+        let user_id = queries::get_server_owner(pool, server_id)
+            .await
+            .map_err(to_status)?;
+        let server = queries::get_server_by_id(pool, user_id, server_id)
+            .await
+            .map_err(to_status)?;
+
+        Ok(to_proto(server, user_id))
+    }
+}
+
+/// Public entry point for the gRPC server background task. Serves the internal
+/// API on the listener until it fails or the shutdown signal completes, every
+/// call authorized with the configured token. On shutdown, the calls in
+/// progress are completed first. Returns immediately if no token is
+/// configured, so the API is never open.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `listener`: Listener bound to the address of the API.
+/// * `shutdown`: Future completing when the server should shut down.
+///
+pub async fn serve<F>(app_state: AppState, listener: TcpListener, shutdown: F)
+where
+    F: Future<Output = ()> + Send,
+{
+    let token = app_state.config.grpc.token.clone();
+    if token.expose_secret().is_empty() {
+        tracing::error!(target: "grpc", "gRPC API needs a token, not started!");
+        return;
+    }
+    let address = listener.local_addr().ok();
+    let incoming = match TcpIncoming::from_listener(listener, true, None) {
+        Ok(incoming) => incoming,
+        Err(error) => {
+            tracing::error!(target: "grpc", ?error, "Failed to listen for gRPC calls!");
+            return;
+        }
+    };
+
+    let service = ServersServer::with_interceptor(ServersService { app_state }, move |request| {
+        authorize(&token, request)
+    });
+    tracing::info!(target: "grpc", ?address, "gRPC API started.");
+    if let Err(error) = tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_incoming_shutdown(incoming, shutdown)
+        .await
+    {
+        tracing::error!(target: "grpc", ?error, "gRPC server failed!");
+    }
+    tracing::info!(target: "grpc", "gRPC API stopped.");
+}
+
+/// Maps an error of the service layer to the gRPC status with the same
+/// meaning, based on the HTTP status and message the REST API responds with.
+/// Internal errors are logged and their details kept from the caller.
+///
+/// # Arguments
+///
+/// * `error`: Error of the service layer.
+///
+/// # Returns
+///
+/// gRPC status of the error.
+///
+pub fn to_status(error: Error) -> Status {
+    let (status, error) = error.into_api_error();
+    let code = match status {
+        StatusCode::BAD_REQUEST => Code::InvalidArgument,
+        StatusCode::UNAUTHORIZED => Code::Unauthenticated,
+        StatusCode::FORBIDDEN => Code::PermissionDenied,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        StatusCode::CONFLICT => Code::FailedPrecondition,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        _ => Code::Internal,
+    };
+
+    Status::new(code, error.message)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Accepts the calls carrying the token. Digests of the tokens are compared,
+/// so the time of the comparison doesn't reveal the token.
+///
+fn authorize(token: &SecretString, request: Request<()>) -> Result<Request<()>, Status> {
+    let provided = request
+        .metadata()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(provided)
+            if Sha256::digest(provided.as_bytes())
+                == Sha256::digest(token.expose_secret().as_bytes()) =>
+        {
+            Ok(request)
+        }
+        _ => Err(Status::unauthenticated("Token is missing or invalid")),
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, Status> {
+    id.parse()
+        .map_err(|_| Status::invalid_argument(format!("Invalid ID {id}")))
+}
+
+fn to_proto(server: ApiServer, user_id: Uuid) -> Server {
+    let status = match server.status {
+        ServerStatus::Running => ProtoStatus::Running,
+        ServerStatus::Stopped => ProtoStatus::Stopped,
+        ServerStatus::Failed => ProtoStatus::Failed,
+        ServerStatus::SettingUp => ProtoStatus::SettingUp,
+        ServerStatus::Deleting => ProtoStatus::Deleting,
+        ServerStatus::Starting => ProtoStatus::Starting,
+        ServerStatus::Stopping => ProtoStatus::Stopping,
+        ServerStatus::Rebooting => ProtoStatus::Rebooting,
+        ServerStatus::ShuttingDown => ProtoStatus::ShuttingDown,
+        ServerStatus::Restoring => ProtoStatus::Restoring,
+        ServerStatus::Resizing => ProtoStatus::Resizing,
+        ServerStatus::Configuring => ProtoStatus::Configuring,
+    };
+
+    Server {
+        server_id: server.server_id.to_string(),
+        user_id: user_id.to_string(),
+        status: status.into(),
+        node_name: server.node_name,
+        vm_id: server.vm_id,
+        ip_address: server.ip_address,
+        additional_ips: server.additional_ips,
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorize_should_require_configured_token() {
+        // Arrange
+        let token = SecretString::from("internal-token");
+        let request = |authorization: Option<&str>| {
+            let mut request = Request::new(());
+            if let Some(authorization) = authorization {
+                request
+                    .metadata_mut()
+                    .insert(AUTHORIZATION, authorization.parse().unwrap());
+            }
+            request
+        };
+
+        // Act
+        let valid = authorize(&token, request(Some("Bearer internal-token")));
+        let invalid = authorize(&token, request(Some("Bearer other-token")));
+        let missing = authorize(&token, request(None));
+
+        // Assert
+        assert!(valid.is_ok());
+        assert_eq!(invalid.unwrap_err().code(), tonic::Code::Unauthenticated);
+        assert_eq!(missing.unwrap_err().code(), tonic::Code::Unauthenticated);
+    }
+
+    #[test]
+    fn to_status_should_follow_http_error_mapping() {
+        // Act
+        let not_found = to_status(Error::Database(sqlx::Error::RowNotFound));
+        let conflict = to_status(Error::Conflict("Server is busy".to_owned()));
+        let quota = to_status(Error::Quota("Server limit reached".to_owned()));
+        let timeout = to_status(Error::Timeout(3.0));
+        let internal = to_status(Error::Any("connection reset".to_owned()));
+
+        // Assert
+        assert_eq!(not_found.code(), Code::NotFound);
+        assert_eq!(not_found.message(), "Resource not found!");
+        assert_eq!(conflict.code(), Code::FailedPrecondition);
+        assert_eq!(conflict.message(), "Server is busy");
+        assert_eq!(quota.code(), Code::PermissionDenied);
+        assert_eq!(timeout.code(), Code::DeadlineExceeded);
+        assert_eq!(internal.code(), Code::Internal);
+        assert_eq!(internal.message(), "Internal server error!");
+    }
+}
@@ -0,0 +1,188 @@
+//! Translations of the user-facing messages: the error responses, in the
+//! language of the `Accept-Language` header, and the notifications, in the
+//! language preferred by the user.
+//!
+//! The message catalogs are bundled from `locales/*.json`. A message missing
+//! from the catalog of a language falls back to English, and to its key if it
+//! is missing there too.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Message catalogs of every language, each mapping the message keys to the
+/// message templates.
+static CATALOGS: LazyLock<HashMap<Locale, HashMap<String, String>>> = LazyLock::new(|| {
+    [
+        (Locale::En, include_str!("../locales/en.json")),
+        (Locale::De, include_str!("../locales/de.json")),
+        (Locale::Fr, include_str!("../locales/fr.json")),
+    ]
+    .into_iter()
+    .map(|(locale, catalog)| {
+        let messages = serde_json::from_str(catalog).expect("Bundled message catalog is invalid");
+        (locale, messages)
+    })
+    .collect()
+});
+
+/// Language of the user-facing messages.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+}
+
+impl Locale {
+    /// Every language with a message catalog.
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::De, Locale::Fr];
+
+    /// Returns the ISO 639-1 code of the language.
+    ///
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+        }
+    }
+
+    /// Parses a language tag, such as `de` or `de-AT`, by its primary language
+    /// subtag, so every regional variant falls back to the language.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: Language tag.
+    ///
+    /// # Returns
+    ///
+    /// Language of the tag, `None` if it has no message catalog.
+    ///
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let language = tag.trim().split(['-', '_']).next()?.to_lowercase();
+
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.code() == language)
+    }
+
+    /// Picks the language the client prefers most out of an `Accept-Language`
+    /// header. Languages are ranked by their quality, in the order of the
+    /// header for equal ones. The unsupported ones, and `*`, are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `header`: Value of the header, such as `de-CH, fr;q=0.8, *;q=0.1`.
+    ///
+    /// # Returns
+    ///
+    /// Preferred supported language, `None` if the header names none.
+    ///
+    pub fn from_accept_language(header: &str) -> Option<Locale> {
+        let mut ranked = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let locale = Locale::parse(parts.next()?)?;
+                let quality = match parts.find_map(|param| param.trim().strip_prefix("q=")) {
+                    Some(quality) => quality.trim().parse::<f32>().ok()?,
+                    None => 1.0,
+                };
+                Some((locale, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        ranked.first().map(|(locale, _)| *locale)
+    }
+
+    /// Resolves the language preferred by a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `preference`: Language tag stored for the user, if any.
+    ///
+    /// # Returns
+    ///
+    /// Preferred language, the default one without a supported preference.
+    ///
+    pub fn of_user(preference: Option<&str>) -> Locale {
+        preference.and_then(Locale::parse).unwrap_or_default()
+    }
+
+    /// Translates a message into the language.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: Key of the message in the catalogs.
+    /// * `args`: Values of the `{name}` placeholders of the message.
+    ///
+    /// # Returns
+    ///
+    /// Translated message, in English if the language lacks it, or the key if
+    /// no catalog has it.
+    ///
+    pub fn translate(self, key: &str, args: &[(&str, &str)]) -> String {
+        let template = [self, Locale::default()]
+            .iter()
+            .find_map(|locale| CATALOGS.get(locale)?.get(key))
+            .map_or(key, String::as_str);
+
+        args.iter()
+            .fold(template.to_owned(), |message, (name, value)| {
+                message.replace(&format!("{{{name}}}"), value)
+            })
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_should_translate_every_english_message() {
+        // Arrange
+        let english = &CATALOGS[&Locale::En];
+
+        // Act & Assert
+        for locale in Locale::ALL {
+            let catalog = &CATALOGS[&locale];
+            assert_eq!(catalog.len(), english.len(), "{}", locale.code());
+            assert!(english.keys().all(|key| catalog.contains_key(key)));
+        }
+    }
+
+    #[test]
+    fn accept_language_should_pick_best_supported_language() {
+        // Act & Assert
+        assert_eq!(Locale::from_accept_language("de-CH"), Some(Locale::De));
+        assert_eq!(
+            Locale::from_accept_language("it, fr;q=0.5, de;q=0.8"),
+            Some(Locale::De)
+        );
+        assert_eq!(
+            Locale::from_accept_language("fr;q=0.9, en;q=0.9"),
+            Some(Locale::Fr)
+        );
+        assert_eq!(Locale::from_accept_language("de;q=0, *"), None);
+        assert_eq!(Locale::from_accept_language(""), None);
+    }
+
+    #[test]
+    fn translate_should_fall_back_to_english_then_key() {
+        // Act
+        let german = Locale::De.translate("error.proxmox_error", &[("operation", "Start")]);
+        let user = Locale::of_user(Some("xx"));
+        let missing = Locale::Fr.translate("missing.key", &[]);
+
+        // Assert
+        assert_eq!(german, "Proxmox-Anfrage Start ist fehlgeschlagen!");
+        assert_eq!(user, Locale::En);
+        assert_eq!(missing, "missing.key");
+    }
+}
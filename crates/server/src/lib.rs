@@ -1,7 +1,16 @@
 pub mod app;
+pub mod broker;
+pub mod captcha;
+pub mod clock;
+pub mod cluster;
 pub mod config;
+pub mod grpc;
+pub mod i18n;
+pub mod mail;
 pub mod model;
+pub mod payments;
 pub mod proxmox;
+pub mod scheduler;
 pub mod services;
 pub mod state;
 pub mod web;
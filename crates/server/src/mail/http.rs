@@ -0,0 +1,145 @@
+use crate::config::MailEnv;
+use crate::mail::Mailer;
+use crate::mail::types::Email;
+use async_trait::async_trait;
+use dashboard_common::prelude::{Error, Result};
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+use std::time::Duration;
+
+/// Concrete implementation of the `Mailer` trait for HTTP email APIs, like
+/// Resend, which take a JSON email authorized with a bearer token.
+///
+/// The email is posted with the sender, the recipient, the subject and the
+/// plain text body, and any `2xx` response means the API accepted it.
+///
+pub struct HttpMailer {
+    client: Client,
+    url: String,
+    api_key: SecretString,
+    from: String,
+}
+
+impl HttpMailer {
+    /// Creates new `HttpMailer` instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings`: Settings of the mail transport.
+    ///
+    pub fn new(settings: &MailEnv) -> Result<Self> {
+        if settings.from.trim().is_empty() {
+            return Err(Error::Any(
+                "Mail sender address is required for the HTTP transport".to_owned(),
+            ));
+        }
+        let client = Client::builder()
+            .timeout(Duration::from_secs(settings.timeout_sec))
+            .build()?;
+
+        Ok(Self {
+            client,
+            url: settings.api_url.clone(),
+            api_key: settings.api_key.clone(),
+            from: settings.from.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for HttpMailer {
+    async fn send(&self, email: Email) -> Result<()> {
+        let body = serde_json::json!({
+            "from": self.from,
+            "to": [email.to],
+            "subject": email.subject,
+            "text": email.body,
+        });
+        let response = self
+            .client
+            .post(&self.url)
+            .bearer_auth(self.api_key.expose_secret())
+            .json(&body)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::Any(format!("Email not accepted: status {status}")));
+        }
+        tracing::info!(target: "mail", subject = %email.subject, "Email sent");
+
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MailTransport;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn settings(server: &MockServer) -> MailEnv {
+        MailEnv {
+            transport: MailTransport::Http,
+            api_url: format!("{}/emails", server.uri()),
+            api_key: SecretString::from("re_test"),
+            from: "Dashboard <noreply@example.com>".to_owned(),
+            timeout_sec: 5,
+        }
+    }
+
+    fn email() -> Email {
+        Email {
+            to: "john@example.com".to_owned(),
+            subject: "Verify your email address".to_owned(),
+            body: "Use this token: 123".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn send_should_post_email_with_api_key() {
+        // Arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/emails"))
+            .and(header("authorization", "Bearer re_test"))
+            .and(body_json(json!({
+                "from": "Dashboard <noreply@example.com>",
+                "to": ["john@example.com"],
+                "subject": "Verify your email address",
+                "text": "Use this token: 123",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "1" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let mailer = HttpMailer::new(&settings(&server)).unwrap();
+
+        // Act
+        let result = mailer.send(email()).await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_should_fail_when_email_is_not_accepted() {
+        // Arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(422))
+            .mount(&server)
+            .await;
+        let mailer = HttpMailer::new(&settings(&server)).unwrap();
+
+        // Act
+        let result = mailer.send(email()).await;
+
+        // Assert
+        assert!(matches!(result, Err(Error::Any(_))));
+    }
+}
@@ -0,0 +1,20 @@
+use crate::mail::Mailer;
+use crate::mail::types::Email;
+use async_trait::async_trait;
+use dashboard_common::prelude::Result;
+
+/// Mailer that writes the emails to the log instead of delivering them, for
+/// environments without a mail transport. Only the recipient and the subject
+/// are logged, as the body holds the tokens.
+///
+#[derive(Debug, Clone, Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<()> {
+        tracing::info!(target: "mail", to = %email.to, subject = %email.subject, "Email logged");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+pub mod http;
+pub mod log;
+pub mod types;
+
+// -----------------------------------------------------------------------------
+
+use crate::config::{MailEnv, MailTransport};
+use crate::mail::http::HttpMailer;
+use crate::mail::log::LogMailer;
+use crate::mail::types::Email;
+use async_trait::async_trait;
+use dashboard_common::prelude::Result;
+use std::sync::Arc;
+
+/// An abstract interface for sending emails to the users.
+///
+/// Defines a contract for a transport that delivers transactional emails, like
+/// confirmation links, to a single recipient.
+///
+#[async_trait]
+pub trait Mailer {
+    /// Send the email.
+    ///
+    /// # Arguments
+    ///
+    /// * `email`: Recipient, subject and plain text body of the email.
+    ///
+    /// # Returns
+    ///
+    /// An empty `Result` once the transport accepted the email.
+    ///
+    async fn send(&self, email: Email) -> Result<()>;
+}
+
+/// Creates the mailer of the configured transport.
+///
+/// # Arguments
+///
+/// * `settings`: Settings of the mail transport.
+///
+/// # Returns
+///
+/// Mailer delivering the emails, or only logging them with the `log`
+/// transport.
+///
+pub fn mailer(settings: &MailEnv) -> Result<Arc<dyn Mailer + Send + Sync>> {
+    Ok(match settings.transport {
+        MailTransport::Log => {
+            tracing::warn!(target: "mail", "Emails are only logged, not delivered.");
+            Arc::new(LogMailer)
+        }
+        MailTransport::Http => Arc::new(HttpMailer::new(settings)?),
+    })
+}
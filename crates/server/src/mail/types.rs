@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+/// Plain text email to a single recipient.
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
@@ -1,35 +1,166 @@
-use dashboard_common::prelude::Result;
+use arc_swap::ArcSwap;
+use dashboard_common::prelude::{Error, Result};
 use dashboard_common::telemetry;
-use dashboard_server::app::App;
-use dashboard_server::config::Config;
+use dashboard_server::app::{self, App};
+use dashboard_server::broker::Broker;
+use dashboard_server::captcha;
+use dashboard_server::clock::SystemClock;
+use dashboard_server::cluster::Cluster;
+use dashboard_server::config::{Config, RuntimeEnv, runtime, secrets};
+use dashboard_server::grpc;
+use dashboard_server::mail;
+use dashboard_server::model::cache::{Catalog, TtlCache};
 use dashboard_server::model::queries;
+use dashboard_server::model::replica::{self, Replica};
+use dashboard_server::payments::stripe::StripeClient;
+use dashboard_server::proxmox::Proxmox;
+use dashboard_server::proxmox::cached::CachedProxmox;
 use dashboard_server::proxmox::client::ProxmoxClient;
+use dashboard_server::proxmox::queue::RequestQueue;
+use dashboard_server::scheduler;
+use dashboard_server::services::{event, smoke};
 use dashboard_server::state::AppState;
 use std::sync::Arc;
-use tracing::Level;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// Command line flag that runs the end-to-end smoke test instead of the server.
+const SMOKE_TEST_FLAG: &str = "--smoke-test";
 
 /// The main entry point for the server application.
 ///
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging.
-    let subscriber = telemetry::get_subscriber(Level::TRACE, std::io::stdout);
+    let config = Config::from_env()?;
+
+    // Initialize logging, the file writer flushes the logs once dropped.
+    let (subscriber, _log_guard) = telemetry::compose_subscriber(&config.log)?;
     telemetry::init_subscriber(subscriber)?;
     tracing::info!(target: "server", "Start!");
     tracing::info!(target: "server", "Logger ready.");
+    tracing::info!(target: "config", ?config, "Configuration loaded.");
+    for warning in config.deprecations() {
+        tracing::warn!(target: "config", warning, "Deprecated setting.");
+    }
 
-    let config = Config::from_env()?;
+    let secret_provider = secrets::provider(&config.secrets);
+    if let Some(provider) = &secret_provider {
+        secrets::refresh(provider.as_ref(), &config).await?;
+        tracing::info!(target: "server", provider = ?config.secrets.provider, "Secrets loaded.");
+    }
+    let runtime = Arc::new(ArcSwap::from_pointee(RuntimeEnv::default()));
+    runtime::apply(&runtime, config.runtime.clone())?;
     let address = config.get_address();
-    let app_state = AppState {
-        pool: queries::connect_to_db(&config).await?,
-        proxmox: Arc::new(ProxmoxClient::new(
+    let proxmox_queue = Arc::new(RequestQueue::new(config.proxmox.max_concurrent_requests));
+    let proxmox = Arc::new(
+        ProxmoxClient::new(
             config.proxmox.url.clone(),
             config.proxmox.auth_header.clone(),
-        )?),
+        )?
+        .with_queue(proxmox_queue.clone()),
+    );
+
+    if std::env::args().any(|arg| arg == SMOKE_TEST_FLAG) {
+        return run_smoke_test(proxmox, &config).await;
+    }
+
+    let pool = queries::connect_to_db(&config).await?;
+    if config.database.migrate_on_start {
+        queries::run_migrations(&config.get_database_connect_options()).await?;
+        tracing::info!(target: "server", "Database migrations applied.");
+    }
+
+    let app_state = AppState {
+        pool,
+        replica: Replica::connect_lazy(&config),
+        proxmox: Arc::new(CachedProxmox::new(
+            proxmox,
+            Duration::from_secs(config.cache.vm_status_ttl_sec),
+        )),
+        proxmox_queue,
+        payments: Arc::new(StripeClient::new(config.payments.clone())),
+        mailer: mail::mailer(&config.mail)?,
+        runtime,
+        catalog: Arc::new(Catalog::new(Duration::from_secs(
+            config.cache.catalog_ttl_sec,
+        ))),
+        nodes: Arc::new(TtlCache::new(Duration::from_secs(
+            config.cache.nodes_ttl_sec,
+        ))),
+        guests: Arc::new(TtlCache::new(Duration::from_secs(
+            config.cache.guest_info_ttl_sec,
+        ))),
+        allowlists: Arc::new(TtlCache::new(Duration::from_secs(
+            config.cache.allowlist_ttl_sec,
+        ))),
+        cluster: Cluster::connect_lazy(&config.redis)?,
+        broker: Broker::new(config.events.broker_capacity),
+        captcha: captcha::provider(&config.captcha)?,
+        clock: Arc::new(SystemClock),
         config,
     };
+
+    if let Some(provider) = secret_provider.filter(|_| app_state.config.secrets.reload_sec > 0) {
+        tokio::spawn(secrets::watch(app_state.clone(), provider));
+        tracing::info!(target: "server", "Secrets reload started.");
+    }
+
+    if let Some(replica) = &app_state.replica {
+        if !replica.check().await {
+            tracing::warn!(target: "server", "Replica unavailable, reading from primary.");
+        }
+        tokio::spawn(replica::watch(app_state.clone()));
+        tracing::info!(target: "server", "Replica health check started.");
+    }
+
+    if app_state.config.reload.file.is_some() {
+        tokio::spawn(runtime::watch(app_state.clone()));
+        tracing::info!(target: "server", "Runtime settings reload started.");
+    }
+
+    tokio::spawn(event::watch(app_state.clone()));
+    tracing::info!(target: "server", "Domain event relay started.");
+
+    if app_state.config.scheduler.enabled {
+        tokio::spawn(scheduler::run(app_state.clone()));
+        tracing::info!(target: "server", "Scheduler started.");
+    }
+
+    let grpc = match app_state.config.grpc.address {
+        Some(address) => {
+            let listener = TcpListener::bind(address).await?;
+            let shutdown = app::shutdown_signal();
+            Some(tokio::spawn(grpc::serve(
+                app_state.clone(),
+                listener,
+                shutdown,
+            )))
+        }
+        None => None,
+    };
+
     let app = App::build(app_state, address).await?;
     tracing::info!(target: "server", "Listening on '{}'\n", app.get_url()?);
 
-    app.run().await
+    app.run().await?;
+    if let Some(grpc) = grpc {
+        grpc.await
+            .map_err(|error| Error::Any(format!("gRPC server failed: {error}")))?;
+    }
+
+    Ok(())
+}
+
+/// Runs the end-to-end smoke test against the configured cluster and reports
+/// the step timings. Doesn't touch the database.
+///
+async fn run_smoke_test(proxmox: Arc<dyn Proxmox + Send + Sync>, config: &Config) -> Result<()> {
+    tracing::info!(target: "server", node = %config.smoke.node, "Smoke test started.");
+    let report = smoke::run(&proxmox, &config.smoke).await;
+    tracing::info!(target: "server", "Smoke test report:\n{report}");
+
+    match report.passed() {
+        true => Ok(()),
+        false => Err(Error::Any("Smoke test failed".to_owned())),
+    }
 }
@@ -0,0 +1,252 @@
+use crate::model::queries;
+use crate::model::types::{
+    ApiApp, ApiAvailableDatacenter, ApiConfigValue, ApiCustomValue, ApiProduct, TemplateKind,
+};
+use crate::web::types::{RequiredConfigOption, RequiredCustomField};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::{PgPool, PgTransaction};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// In-process cache whose entries expire after a fixed time to live.
+///
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    /// Creates an empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl`: Time an entry stays valid, zero disables the cache.
+    ///
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Returns the cached value of the key, `None` if it is missing or expired.
+    ///
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries
+            .get(key)
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Caches the value of the key.
+    ///
+    pub fn insert(&self, key: K, value: V) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.insert(key, (Instant::now(), value));
+    }
+
+    /// Removes the cached value of the key.
+    ///
+    pub fn remove(&self, key: &K) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key);
+    }
+
+    /// Removes every cached value.
+    ///
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Returns the cached value of the key, loading and caching it when it is
+    /// missing or expired. Failed loads are not cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: Key of the value.
+    /// * `load`: Future loading the value.
+    ///
+    pub async fn get_or_load<F>(&self, key: K, load: F) -> Result<V>
+    where
+        F: Future<Output = Result<V>>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+        let value = load.await?;
+        self.insert(key, value.clone());
+
+        Ok(value)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Cache of the product catalog, read on every provisioning request but
+/// rarely changed.
+///
+/// Admin endpoints modifying the catalog call `invalidate`, so the changes are
+/// visible immediately on this replica, and after the time to live on the
+/// others.
+///
+pub struct Catalog {
+    products: TtlCache<(), Vec<ApiProduct>>,
+    apps: TtlCache<(), Vec<ApiApp>>,
+    config_values: TtlCache<RequiredConfigOption, Vec<ApiConfigValue>>,
+    custom_values: TtlCache<RequiredCustomField, Vec<ApiCustomValue>>,
+    template_ids: TtlCache<(String, TemplateKind), Uuid>,
+    datacenters: TtlCache<Uuid, Vec<ApiAvailableDatacenter>>,
+}
+
+impl Catalog {
+    /// Creates an empty catalog cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl`: Time a cached entry stays valid, zero disables the cache.
+    ///
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            products: TtlCache::new(ttl),
+            apps: TtlCache::new(ttl),
+            config_values: TtlCache::new(ttl),
+            custom_values: TtlCache::new(ttl),
+            template_ids: TtlCache::new(ttl),
+            datacenters: TtlCache::new(ttl),
+        }
+    }
+
+    /// Returns all available products.
+    ///
+    pub async fn products(&self, pool: &PgPool) -> Result<Vec<ApiProduct>> {
+        self.products
+            .get_or_load((), queries::get_products(pool))
+            .await
+    }
+
+    /// Returns the applications offered in the marketplace.
+    ///
+    pub async fn apps(&self, pool: &PgPool) -> Result<Vec<ApiApp>> {
+        self.apps.get_or_load((), queries::get_apps(pool)).await
+    }
+
+    /// Returns all available values of a configurable option.
+    ///
+    pub async fn config_values(
+        &self,
+        pool: &PgPool,
+        option: RequiredConfigOption,
+    ) -> Result<Vec<ApiConfigValue>> {
+        self.config_values
+            .get_or_load(option, queries::get_config_option_value(pool, option))
+            .await
+    }
+
+    /// Returns all available values of a custom field.
+    ///
+    pub async fn custom_values(
+        &self,
+        pool: &PgPool,
+        field: RequiredCustomField,
+    ) -> Result<Vec<ApiCustomValue>> {
+        self.custom_values
+            .get_or_load(field, queries::get_custom_field_value(pool, field))
+            .await
+    }
+
+    /// Returns the ID of the template of the OS or the application.
+    ///
+    pub async fn template_id(
+        &self,
+        transaction: &mut PgTransaction<'_>,
+        os_name: &str,
+        kind: TemplateKind,
+    ) -> Result<Uuid> {
+        self.template_ids
+            .get_or_load(
+                (os_name.to_owned(), kind),
+                queries::find_template_id(transaction, os_name, kind),
+            )
+            .await
+    }
+
+    /// Returns the datacenters the product can be ordered in. Only the
+    /// products of the catalog are cached, so unknown IDs can't fill the
+    /// cache.
+    ///
+    pub async fn datacenters(
+        &self,
+        pool: &PgPool,
+        product_id: Uuid,
+    ) -> Result<Vec<ApiAvailableDatacenter>> {
+        if !self
+            .products(pool)
+            .await?
+            .iter()
+            .any(|product| product.id == product_id)
+        {
+            return Err(Error::NotFound(format!("Product {product_id}")));
+        }
+        self.datacenters
+            .get_or_load(
+                product_id,
+                queries::get_available_datacenters(pool, product_id),
+            )
+            .await
+    }
+
+    /// Removes every cached entry, so the next reads see the changed catalog.
+    ///
+    pub fn invalidate(&self) {
+        self.products.clear();
+        self.apps.clear();
+        self.config_values.clear();
+        self.custom_values.clear();
+        self.template_ids.clear();
+        self.datacenters.clear();
+        tracing::info!(target: "database", "Catalog cache invalidated");
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cached_value_should_be_loaded_once() {
+        // Arrange
+        let cache = TtlCache::new(Duration::from_secs(60));
+        let disabled = TtlCache::new(Duration::ZERO);
+
+        // Act
+        let first = cache.get_or_load("key", async { Ok(1) }).await.unwrap();
+        let cached = cache.get_or_load("key", async { Ok(2) }).await.unwrap();
+        cache.clear();
+        let reloaded = cache.get_or_load("key", async { Ok(3) }).await.unwrap();
+        disabled.insert("key", 4);
+
+        // Assert
+        assert_eq!(first, 1);
+        assert_eq!(cached, 1);
+        assert_eq!(reloaded, 3);
+        assert_eq!(disabled.get(&"key"), None);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod cache;
 pub mod queries;
+pub mod replica;
 pub mod types;
@@ -2,11 +2,18 @@ use crate::config::Config;
 use crate::model::types::*;
 use crate::proxmox::types::VmRef;
 use crate::web::auth::password::hash;
-use crate::web::types::{NewServerPayload, RequiredConfigOption, RequiredCustomField};
+use crate::web::types::{
+    AnnouncementPayload, CustomFieldPayload, DatacenterPayload, FirewallRulePayload, IsoPayload,
+    NewServerPayload, ProductPayload, RequiredConfigOption, RequiredCustomField,
+    SamlSettingsPayload, TemplatePayload, UpdateUserPayload,
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use dashboard_common::prelude::{Error, Result};
+use ipnet::IpNet;
 use secrecy::ExposeSecret;
-use sqlx::postgres::PgPoolOptions;
-use sqlx::{Executor, PgPool, PgTransaction, Postgres};
+use sqlx::migrate::Migrator;
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{Connection, Executor, PgConnection, PgPool, PgTransaction, Postgres};
 use uuid::Uuid;
 
 /// Creates and returns a connection pool to the database.
@@ -22,11 +29,91 @@ use uuid::Uuid;
 #[tracing::instrument(level = "trace", target = "database")]
 pub async fn connect_to_db(config: &Config) -> Result<PgPool> {
     let connect_options = config.get_database_connect_options();
-    let pool = PgPoolOptions::new().connect_with(connect_options).await?;
+    let pool = config
+        .database
+        .get_pool_options()
+        .connect_with(connect_options)
+        .await?;
 
     Ok(pool)
 }
 
+/// Migrations of the workspace, embedded in the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+/// Applies the pending migrations to the database.
+///
+/// The migrator holds a Postgres advisory lock while it runs, so replicas
+/// starting together apply every migration once: the others wait for the lock
+/// and then find nothing pending. Migrations run on a connection of their own,
+/// without the statement timeout of the pool.
+///
+/// # Arguments
+///
+/// * `options`: Options of the database connection.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the database is up to date.
+///
+pub async fn run_migrations(options: &PgConnectOptions) -> Result<()> {
+    let options = options.clone().options([("statement_timeout", 0)]);
+    let mut connection = PgConnection::connect_with(&options).await?;
+    MIGRATOR
+        .run(&mut connection)
+        .await
+        .map_err(sqlx::Error::from)?;
+    connection.close().await?;
+
+    Ok(())
+}
+
+/// Returns the latest migration applied to the database.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// Version of the migration, `None` if none was applied.
+///
+pub async fn schema_version<'e, E>(executor: E) -> Result<Option<i64>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    // The table is created by the migrator, so the query can't be checked
+    // against the migrations at compile time.
+    let version = sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT MAX(version) FROM _sqlx_migrations WHERE success",
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(version)
+}
+
+/// Runs a trivial query to check the connection to the database.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the database responds.
+///
+pub async fn ping<'e, E>(executor: E) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(r#"SELECT 1 AS "one!""#)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(())
+}
+
 /// Updates a user's password hash in the database.
 ///
 /// # Arguments
@@ -54,7 +141,8 @@ WHERE id = $1
     Ok(())
 }
 
-/// Inserts a new user into the database.
+/// Inserts a new user into the database, together with the personal
+/// organization owning the user's services.
 ///
 /// # Arguments
 ///
@@ -63,10 +151,12 @@ WHERE id = $1
 ///
 /// # Returns
 ///
-/// `ApiUser` struct representing the newly created user.
+/// `ApiUser` struct representing the newly created user, `Error::Conflict` if
+/// the email address is already registered.
 ///
 pub async fn add_new_user(pool: &PgPool, new_user: NewUser) -> Result<ApiUser> {
-    Ok(sqlx::query_as!(
+    let mut transaction = pool.begin().await?;
+    let user = sqlx::query_as!(
         DbUser,
         r#"
 INSERT INTO users (
@@ -79,8 +169,9 @@ INSERT INTO users (
     post_code,
     country,
     phone_number,
-    password)
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    password,
+    email_verified_at)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NULL)
 RETURNING
     id,
     first_name,
@@ -93,6 +184,8 @@ RETURNING
     country,
     phone_number,
     password,
+    email_verified_at,
+    locale,
     created_at,
     updated_at
 		"#,
@@ -107,9 +200,18 @@ RETURNING
         new_user.phone_number,
         hash(&new_user.plain_password.expose_secret())?
     )
-    .fetch_one(pool)
-    .await?
-    .into())
+    .fetch_one(transaction.as_mut())
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Conflict("Email address is already registered".to_owned())
+        }
+        _ => error.into(),
+    })?;
+    create_personal_organization(transaction.as_mut(), user.id).await?;
+    transaction.commit().await?;
+
+    Ok(user.into())
 }
 
 /// Retrieves a user from the database by their ID.
@@ -139,6 +241,8 @@ SELECT
     country,
     phone_number,
     password,
+    email_verified_at,
+    locale,
     created_at,
     updated_at
 FROM users
@@ -178,10 +282,12 @@ SELECT
     country,
     phone_number,
     password,
+    email_verified_at,
+    locale,
     created_at,
     updated_at
 FROM users
-WHERE email = $1
+WHERE LOWER(email) = LOWER($1)
         "#,
         email
     )
@@ -189,697 +295,9647 @@ WHERE email = $1
     .await?)
 }
 
-/// Retrieves all servers associated with a specific user.
+/// Updates the profile of a user, leaving the missing fields unchanged.
 ///
 /// # Arguments
 ///
-/// * `pool`: Reference to the `PgPool`.
-/// * `user_id`: UUID of the user whose servers are to be retrieved.
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `profile`: New values of the profile fields.
 ///
 /// # Returns
 ///
-/// `Vec<ApiServer>` containing the list of servers for the user.
+/// `ApiUser` struct for the updated user.
 ///
-pub async fn get_servers_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<ApiServer>> {
-    let rows = sqlx::query!(
+pub async fn update_user_profile<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    profile: &UpdateUserPayload,
+) -> Result<ApiUser>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        DbUser,
         r#"
-SELECT
-	svc.id AS "service_id",
-	srv.id AS "server_id",
-	srv.vm_id,
-	srv.node_name,
-	ip.ip_address,
-	srv.status
-FROM services AS svc
-JOIN servers AS srv ON srv.id = svc.server_id
-INNER JOIN ip_addresses as ip ON ip.server_id = srv.id
-WHERE svc.user_id = $1
+UPDATE users
+SET first_name   = COALESCE($2, first_name),
+    last_name    = COALESCE($3, last_name),
+    address      = COALESCE($4, address),
+    city         = COALESCE($5, city),
+    state        = COALESCE($6, state),
+    post_code    = COALESCE($7, post_code),
+    country      = COALESCE($8, country),
+    phone_number = COALESCE($9, phone_number),
+    locale       = COALESCE($10, locale),
+    updated_at   = now()
+WHERE id = $1
+RETURNING
+    id,
+    first_name,
+    last_name,
+    email,
+    address,
+    city,
+    state,
+    post_code,
+    country,
+    phone_number,
+    password,
+    email_verified_at,
+    locale,
+    created_at,
+    updated_at
 		"#,
-        user_id
+        user_id,
+        profile.first_name,
+        profile.last_name,
+        profile.address,
+        profile.city,
+        profile.state,
+        profile.post_code,
+        profile.country,
+        profile.phone_number,
+        profile.locale,
     )
-    .fetch_all(pool)
-    .await?;
-
-    Ok(rows
-        .into_iter()
-        .map(|row| ApiServer {
-            service_id: row.service_id,
-            server_id: row.server_id,
-            vm_id: row.vm_id,
-            node_name: row.node_name,
-            ip_address: row.ip_address,
-            status: row.status.as_str().into(),
-        })
-        .collect::<Vec<_>>())
+    .fetch_one(executor)
+    .await?
+    .into())
 }
 
-/// Creates a new server record in the `servers` table.
+/// Checks whether the email address belongs to any user.
 ///
 /// # Arguments
 ///
-/// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `host_name`: Name of the new host.
+/// * `executor`: Database executor (pool or transaction).
+/// * `email`: Email address to check.
 ///
 /// # Returns
 ///
-/// UUID of the newly created server record.
+/// `true` if a user with the email exists.
 ///
-pub async fn create_server_record(
-    transaction: &mut PgTransaction<'_>,
-    host_name: &str,
-) -> Result<Uuid> {
+pub async fn user_email_exists<'e, E>(executor: E, email: &str) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     let record = sqlx::query!(
         r#"
-INSERT INTO servers (host_name, status)
-VALUES ($1, $2)
-RETURNING id
-        "#,
-        host_name,
-        ServerStatus::SettingUp.to_string(),
+SELECT EXISTS (SELECT 1 FROM users WHERE LOWER(email) = LOWER($1)) AS "exists!"
+		"#,
+        email
     )
-    .fetch_one(&mut **transaction)
+    .fetch_one(executor)
     .await?;
 
-    Ok(record.id)
+    Ok(record.exists)
 }
 
-/// Finds an available IP address and assigns it to a server.
+/// Stores a pending change of a user's email address, replacing the previous
+/// one.
 ///
 /// # Arguments
 ///
-/// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `server_id`: UUID of the server to assign the IP to.
-/// * `datacenter`: Datacenter location name.
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `new_email`: Requested email address.
+/// * `token_hash`: Hash of the confirmation token.
+/// * `expires_at`: Time the confirmation token expires.
+///
+pub async fn create_email_change<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    new_email: &str,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO email_changes (user_id, new_email, token_hash, expires_at)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (user_id) DO UPDATE
+    SET new_email  = excluded.new_email,
+        token_hash = excluded.token_hash,
+        expires_at = excluded.expires_at,
+        created_at = now()
+		"#,
+        user_id,
+        new_email,
+        token_hash,
+        expires_at,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes the pending change of a user's email address if the token matches
+/// and hasn't expired yet.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `token_hash`: Hash of the confirmation token.
+/// * `now`: Current time.
 ///
 /// # Returns
 ///
-/// `IpConfig` struct with the reserved IP address details.
+/// Requested email address, `None` if there is no such pending change.
 ///
-pub async fn reserve_ip_for_server(
-    transaction: &mut PgTransaction<'_>,
-    server_id: Uuid,
-    datacenter: &str,
-) -> Result<IpConfig> {
-    // Find available IP address.
-    let network_details = sqlx::query!(
+pub async fn take_email_change<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    token_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<String>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
         r#"
-SELECT
-	ip.id AS "ip_id",
-	ip.ip_address,
-	n.gateway,
-	n.subnet_mask
-FROM ip_addresses AS ip
-JOIN networks AS n ON ip.network_id = n.id
-WHERE ip.server_id IS NULL AND n.datacenter_name = $1
-LIMIT 1
-FOR UPDATE SKIP LOCKED
+DELETE FROM email_changes
+WHERE user_id = $1 AND token_hash = $2 AND expires_at > $3
+RETURNING new_email
 		"#,
-        datacenter,
+        user_id,
+        token_hash,
+        now,
     )
-    .fetch_one(&mut **transaction)
+    .fetch_optional(executor)
     .await?;
 
-    // Reserve IP address.
+    Ok(record.map(|record| record.new_email))
+}
+
+/// Stores a pending verification of a user's email address, replacing the
+/// previous one.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `token_hash`: Hash of the verification token.
+/// * `expires_at`: Time the verification token expires.
+///
+pub async fn create_email_verification<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    token_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     sqlx::query!(
         r#"
-UPDATE ip_addresses SET server_id = $1
-WHERE id = $2
+INSERT INTO email_verifications (user_id, token_hash, expires_at)
+VALUES ($1, $2, $3)
+ON CONFLICT (user_id) DO UPDATE
+    SET token_hash = excluded.token_hash,
+        expires_at = excluded.expires_at,
+        created_at = now()
 		"#,
-        server_id,
-        network_details.ip_id,
+        user_id,
+        token_hash,
+        expires_at,
     )
-    .execute(&mut **transaction)
+    .execute(executor)
     .await?;
 
-    Ok(IpConfig {
-        ip_address: network_details.ip_address,
-        gateway: network_details.gateway,
-        subnet_mask: network_details.subnet_mask,
-    })
+    Ok(())
 }
 
-/// Creates a service record to link a user, server, and product.
+/// Marks the email address of a user as verified if the token matches a
+/// pending verification that hasn't expired yet, removing the verification.
 ///
 /// # Arguments
 ///
-/// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `user_id`: UUID of the user.
-/// * `server_id`: UUID of the server.
-/// * `template_id`: UUID of the template.
-/// * `payload`: `NewServerPayload` containing the product ID.
+/// * `executor`: Database executor (pool or transaction).
+/// * `token_hash`: Hash of the verification token.
+/// * `now`: Current time.
 ///
 /// # Returns
 ///
-/// UUID of the newly created service record.
+/// UUID of the verified user, `None` if there is no such pending verification.
 ///
-pub async fn create_service_record(
-    transaction: &mut PgTransaction<'_>,
-    user_id: Uuid,
-    server_id: Uuid,
-    template_id: Uuid,
-    payload: &NewServerPayload,
-) -> Result<Uuid> {
+pub async fn verify_user_email<'e, E>(
+    executor: E,
+    token_hash: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<Uuid>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
     let record = sqlx::query!(
         r#"
-INSERT INTO services (status, user_id, server_id, product_id, template_id)
-VALUES ($1, $2, $3, $4, $5)
-RETURNING id
-        "#,
-        ServiceStatus::Pending.to_string(),
-        user_id,
-        server_id,
-        payload.product_id,
-        template_id,
+WITH verification AS (
+	DELETE FROM email_verifications
+	WHERE token_hash = $1 AND expires_at > $2
+	RETURNING user_id
+)
+UPDATE users
+SET email_verified_at = $2
+FROM verification
+WHERE users.id = verification.user_id
+RETURNING users.id
+		"#,
+        token_hash,
+        now,
     )
-    .fetch_one(&mut **transaction)
+    .fetch_optional(executor)
     .await?;
 
-    Ok(record.id)
+    Ok(record.map(|record| record.id))
 }
 
-/// Saves configurable option values (like CPU, RAM) for a service.
+/// Checks whether a user has verified the email address.
 ///
 /// # Arguments
 ///
-/// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `service_id`: UUID of the service.
-/// * `payload`: `NewServerPayload` containing the configuration values.
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
 ///
 /// # Returns
 ///
-/// Empty `Ok(())` on success.
+/// `true` if the email address is verified.
 ///
-pub async fn save_config_values(
-    transaction: &mut PgTransaction<'_>,
-    service_id: Uuid,
-    payload: &NewServerPayload,
-) -> Result<()> {
-    let config_options = sqlx::query!(
+pub async fn is_email_verified<'e, E>(executor: E, user_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
         r#"
-SELECT id, name FROM config_options
-WHERE name IN ('cpu_cores', 'ram_gb')
-        "#
+SELECT email_verified_at IS NOT NULL AS "verified!"
+FROM users
+WHERE id = $1
+		"#,
+        user_id
     )
-    .fetch_all(&mut **transaction)
+    .fetch_optional(executor)
     .await?;
 
-    for option in config_options {
-        let value = match option.name.as_str() {
-            "cpu_cores" => payload.cpu_cores.unwrap_or(2).to_string(),
-            "ram_gb" => payload.ram_gb.unwrap_or(2).to_string(),
-            _ => continue,
-        };
-
-        sqlx::query!(
-            r#"
-INSERT INTO config_values (service_id, config_id, value)
-VALUES ($1, $2, $3)
-            "#,
-            service_id,
-            option.id,
-            value
-        )
-        .execute(&mut **transaction)
-        .await?;
-    }
-
-    Ok(())
+    Ok(record.is_some_and(|record| record.verified))
 }
 
-/// Saves custom field values (like OS, Datacenter) for a service.
+/// Anonymizes a user that deleted the account, removing the personal data that
+/// doesn't have to be retained: pending email changes and verifications, the
+/// webhooks, the notifications and their preferences, the support tickets
+/// migrated from WHMCS, the linked OpenID Connect accounts, and the memberships
+/// of other organizations than the personal one. Pending transfers of the user's
+/// servers, or offered to the user, are cancelled.
 ///
 /// # Arguments
 ///
 /// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `service_id`: UUID of the service.
-/// * `payload`: `NewServerPayload` containing the custom values.
+/// * `user_id`: UUID of the user.
+/// * `now`: Time of the deletion.
 ///
 /// # Returns
 ///
-/// Empty `Ok(())` on success.
+/// `false` if the account doesn't exist or is already deleted.
 ///
-pub async fn save_custom_values(
+pub async fn anonymize_user(
     transaction: &mut PgTransaction<'_>,
-    service_id: Uuid,
-    payload: &NewServerPayload,
-) -> Result<()> {
-    let custom_fields = sqlx::query!(
+    user_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let result = sqlx::query!(
         r#"
-SELECT id, name FROM custom_fields
-WHERE name IN ('os', 'datacenter')
-        "#
+UPDATE users
+SET first_name        = 'Deleted',
+    last_name         = 'User',
+    email             = 'deleted-' || id || '@deleted.invalid',
+    address           = '',
+    city              = '',
+    state             = '',
+    post_code         = '',
+    country           = '',
+    phone_number      = '',
+    password          = '',
+    email_verified_at = NULL,
+    locale            = NULL,
+    deleted_at        = $2,
+    updated_at        = $2
+WHERE id = $1 AND deleted_at IS NULL
+		"#,
+        user_id,
+        now,
     )
-    .fetch_all(&mut **transaction)
+    .execute(transaction.as_mut())
     .await?;
-
-    for field in custom_fields {
-        let value = match field.name.as_str() {
-            "os" => &payload.os,
-            "datacenter" => &payload.datacenter,
-            _ => continue,
-        };
-
-        sqlx::query!(
-            r#"
-INSERT INTO custom_values (service_id, custom_field_id, value)
-VALUES ($1, $2, $3)
-            "#,
-            service_id,
-            field.id,
-            value
-        )
-        .execute(&mut **transaction)
-        .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
     }
 
-    Ok(())
+    sqlx::query!(
+        r#"
+WITH
+	changes AS (DELETE FROM email_changes WHERE user_id = $1),
+	verifications AS (DELETE FROM email_verifications WHERE user_id = $1),
+	tickets AS (DELETE FROM tickets WHERE user_id = $1),
+	inbox AS (DELETE FROM notifications WHERE user_id = $1),
+	preferences AS (DELETE FROM notification_preferences WHERE user_id = $1),
+	identities AS (DELETE FROM user_identities WHERE user_id = $1),
+	transfers AS (
+		UPDATE server_transfers SET status = 'Cancelled', completed_at = $2
+		WHERE status = 'Pending' AND $1 IN (from_user_id, to_user_id)
+	),
+	memberships AS (
+		DELETE FROM organization_members
+		WHERE user_id = $1
+		  AND organization_id NOT IN (SELECT id FROM organizations WHERE personal_user_id = $1)
+	)
+DELETE FROM webhooks
+WHERE user_id = $1
+		"#,
+        user_id,
+        now,
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(true)
 }
 
-/// Updates a server record with its initial Proxmox VM details.
+/// Retrieves the servers of deleted accounts that are in a stable status, so
+/// their deprovisioning can start.
 ///
 /// # Arguments
 ///
-/// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `server_id`: UUID of the server to update.
-/// * `new_vm`: `VmRef` struct containing the VM ID and node name.
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of a single deleted user, `None` for all of them.
 ///
 /// # Returns
 ///
-/// Empty `Ok(())` on success.
+/// Pairs of the user and the server IDs.
 ///
-pub async fn update_initial_server(
-    transaction: &mut PgTransaction<'_>,
-    server_id: Uuid,
-    new_vm: VmRef,
-) -> Result<()> {
-    sqlx::query!(
+pub async fn get_deprovisioning_servers<'e, E>(
+    executor: E,
+    user_id: Option<Uuid>,
+) -> Result<Vec<(Uuid, Uuid)>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let rows = sqlx::query!(
         r#"
-UPDATE servers SET vm_id = $2, node_name = $3
-WHERE id = $1
+SELECT svc.user_id, srv.id AS "server_id"
+FROM services AS svc
+JOIN servers AS srv ON srv.id = svc.server_id
+JOIN users AS usr ON usr.id = svc.user_id
+WHERE usr.deleted_at IS NOT NULL
+	AND ($1::UUID IS NULL OR usr.id = $1)
+	AND srv.status IN ('Running', 'Stopped', 'Failed')
 		"#,
-        server_id,
-        new_vm.id,
-        new_vm.node,
+        user_id as Option<Uuid>,
     )
-    .execute(&mut **transaction)
+    .fetch_all(executor)
     .await?;
-    Ok(())
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.user_id, row.server_id))
+        .collect())
 }
 
-/// Finds the ID of a template by OS name.
+/// Updates the email address of a user. The address counts as verified, as
+/// the user has confirmed it.
 ///
 /// # Arguments
 ///
-/// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `os_name`: Name of the OS to find the template for.
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `email`: New email address.
 ///
 /// # Returns
 ///
-/// UUID of the found template.
+/// `ApiUser` struct for the updated user.
 ///
-pub async fn find_template_id(transaction: &mut PgTransaction<'_>, os_name: &str) -> Result<Uuid> {
-    let record = sqlx::query!(
+pub async fn update_user_email<'e, E>(executor: E, user_id: Uuid, email: &str) -> Result<ApiUser>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        DbUser,
         r#"
-SELECT * FROM templates
-WHERE os_name = $1
+UPDATE users
+SET email             = $2,
+    email_verified_at = now(),
+    updated_at        = now()
+WHERE id = $1
+RETURNING
+    id,
+    first_name,
+    last_name,
+    email,
+    address,
+    city,
+    state,
+    post_code,
+    country,
+    phone_number,
+    password,
+    email_verified_at,
+    locale,
+    created_at,
+    updated_at
 		"#,
-        os_name,
+        user_id,
+        email,
     )
-    .fetch_one(&mut **transaction)
-    .await?;
-
-    Ok(record.id)
+    .fetch_one(executor)
+    .await?
+    .into())
 }
 
-/// Finds the Proxmox template details for a given service.
+/// Retrieves all servers associated with a specific user.
 ///
 /// # Arguments
 ///
-/// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `service_id`: UUID of the service.
+/// * `pool`: Reference to the `PgPool`.
+/// * `user_id`: UUID of the user whose servers are to be retrieved.
 ///
 /// # Returns
 ///
-/// `VmRef` struct containing the template's node and VMID.
+/// `Vec<ApiServer>` containing the list of servers for the user.
 ///
-pub async fn find_template(transaction: &mut PgTransaction<'_>, service_id: Uuid) -> Result<VmRef> {
-    let record = sqlx::query!(
+pub async fn get_servers_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<ApiServer>> {
+    let rows = sqlx::query!(
         r#"
 SELECT
-	t.template_node,
-	t.template_vmid
-FROM services AS s
-JOIN templates AS t ON t.id = s.template_id
-WHERE s.id = $1
+	svc.id AS "service_id",
+	srv.id AS "server_id",
+	srv.vm_id,
+	srv.node_name,
+	ip.ip_address,
+	srv.status,
+	srv.net_rate_mbps,
+	ARRAY(
+		SELECT extra.ip_address FROM ip_addresses AS extra
+		WHERE extra.server_id = srv.id AND extra.nic_index > 0
+		ORDER BY extra.nic_index
+	) AS "additional_ips!",
+	ARRAY(
+		SELECT tag.tag FROM server_tags AS tag
+		WHERE tag.server_id = srv.id
+		ORDER BY tag.tag
+	) AS "tags!",
+	srv.notes,
+	svc.external_id
+FROM services AS svc
+JOIN servers AS srv ON srv.id = svc.server_id
+INNER JOIN ip_addresses as ip ON ip.server_id = srv.id AND ip.nic_index = 0
+WHERE svc.user_id = $1
 		"#,
-        service_id
+        user_id
     )
-    .fetch_one(&mut **transaction)
+    .fetch_all(pool)
     .await?;
 
-    let vm = VmRef::new(&record.template_node, record.template_vmid);
-    Ok(vm)
+    Ok(rows
+        .into_iter()
+        .map(|row| ApiServer {
+            service_id: row.service_id,
+            server_id: row.server_id,
+            vm_id: row.vm_id,
+            node_name: row.node_name,
+            ip_address: row.ip_address,
+            status: row.status.as_str().into(),
+            net_rate_mbps: row.net_rate_mbps,
+            additional_ips: row.additional_ips,
+            tags: row.tags,
+            notes: row.notes,
+            external_id: row.external_id,
+        })
+        .collect::<Vec<_>>())
 }
 
-/// Updates the status of a service.
+/// Creates a new server record in the `servers` table.
 ///
 /// # Arguments
 ///
-/// * `executor`: Database executor (pool or transaction).
-/// * `service_id`: UUID of the service to update.
-/// * `status`: New `ServiceStatus`.
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `host_name`: Normalized name of the new host.
 ///
 /// # Returns
 ///
-/// Empty `Ok(())` on success.
+/// UUID of the newly created server record, `Error::Conflict` if another
+/// server already has the host name.
 ///
-pub async fn update_service_status<'e, E>(
-    executor: E,
-    service_id: Uuid,
-    status: ServiceStatus,
-) -> Result<()>
-where
-    E: Executor<'e, Database = Postgres>,
-{
-    sqlx::query!(
+pub async fn create_server_record(
+    transaction: &mut PgTransaction<'_>,
+    host_name: &str,
+) -> Result<Uuid> {
+    let record = sqlx::query!(
         r#"
-UPDATE services SET status = $2
-WHERE id = $1
-		"#,
-        service_id,
-        status.to_string(),
+INSERT INTO servers (host_name, status)
+VALUES ($1, $2)
+RETURNING id
+        "#,
+        host_name,
+        ServerStatus::SettingUp.to_string(),
     )
-    .execute(executor)
-    .await?;
-    Ok(())
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.constraint() == Some("servers_host_name_key") => {
+            Error::Conflict(format!("Host name {host_name} is already in use"))
+        }
+        _ => error.into(),
+    })?;
+
+    Ok(record.id)
 }
 
-/// Updates the status of a server.
+/// Checks whether the host name belongs to any server, ignoring the case.
 ///
 /// # Arguments
 ///
 /// * `executor`: Database executor (pool or transaction).
-/// * `server_id`: UUID of the server to update.
-/// * `status`: New `ServerStatus`.
+/// * `host_name`: Host name to check.
 ///
 /// # Returns
 ///
-/// Empty `Ok(())` on success.
+/// `true` if a server with the host name exists.
 ///
-pub async fn update_server_status<'e, E>(
-    executor: E,
-    server_id: Uuid,
-    status: ServerStatus,
-) -> Result<()>
+pub async fn host_name_exists<'e, E>(executor: E, host_name: &str) -> Result<bool>
 where
     E: Executor<'e, Database = Postgres>,
 {
-    sqlx::query!(
+    let record = sqlx::query!(
         r#"
-UPDATE servers SET status = $2
-WHERE id = $1
+SELECT EXISTS (SELECT 1 FROM servers WHERE lower(host_name) = lower($1)) AS "exists!"
 		"#,
-        server_id,
-        status.to_string(),
+        host_name
     )
-    .execute(executor)
+    .fetch_one(executor)
     .await?;
-    Ok(())
+
+    Ok(record.exists)
 }
 
-/// Retrieves a single server by its ID.
+/// Finds an available IP address and assigns it to a server.
 ///
 /// # Arguments
 ///
-/// * `executor`: Database executor (pool or transaction).
-/// * `user_id`: UUID of the user who owns the server.
-/// * `server_id`: UUID of the server to retrieve.
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server to assign the IP to.
+/// * `datacenter`: Datacenter location name.
 ///
 /// # Returns
 ///
-/// `ApiServer` struct for the found server.
+/// `IpConfig` struct with the reserved IP address details.
 ///
-pub async fn get_server_by_id<'e, E>(
-    executor: E,
-    user_id: Uuid,
+pub async fn reserve_ip_for_server(
+    transaction: &mut PgTransaction<'_>,
     server_id: Uuid,
-) -> Result<ApiServer>
-where
-    E: Executor<'e, Database = Postgres>,
-{
-    let server = sqlx::query_as!(
-        ApiServer,
+    datacenter: &str,
+) -> Result<IpConfig> {
+    // Find available IP address.
+    let network_details = sqlx::query!(
         r#"
 SELECT
-	svc.id AS "service_id",
-	srv.id AS "server_id",
-	srv.vm_id,
-	srv.node_name,
+	ip.id AS "ip_id",
 	ip.ip_address,
-	srv.status
-FROM services AS svc
-JOIN servers AS srv ON srv.id = svc.server_id
-INNER JOIN ip_addresses AS ip ON ip.server_id = srv.id
-WHERE svc.user_id = $1 AND srv.id = $2
+	n.gateway,
+	n.subnet_mask
+FROM ip_addresses AS ip
+JOIN networks AS n ON ip.network_id = n.id
+WHERE ip.server_id IS NULL AND NOT ip.is_reserved AND n.datacenter_name = $1
+LIMIT 1
+FOR UPDATE SKIP LOCKED
+		"#,
+        datacenter,
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    // Reserve IP address.
+    sqlx::query!(
+        r#"
+UPDATE ip_addresses SET server_id = $1
+WHERE id = $2
 		"#,
-        user_id,
         server_id,
+        network_details.ip_id,
     )
-    .fetch_one(executor)
+    .execute(&mut **transaction)
     .await?;
 
-    Ok(server)
+    Ok(IpConfig {
+        ip_address: network_details.ip_address,
+        gateway: network_details.gateway,
+        subnet_mask: network_details.subnet_mask,
+    })
 }
 
-/// Deletes a server record and releases its associated IP address.
+/// Creates a service record to link a user, server, and product.
 ///
 /// # Arguments
 ///
 /// * `transaction`: Mutable reference to a `PgTransaction`.
-/// * `server_id`: UUID of the server to delete.
+/// * `user_id`: UUID of the user.
+/// * `server_id`: UUID of the server.
+/// * `template_id`: UUID of the template.
+/// * `payload`: `NewServerPayload` containing the product ID.
 ///
 /// # Returns
 ///
-/// Empty `Ok(())` on success.
+/// UUID of the newly created service record.
 ///
-pub async fn delete_server_record(
+pub async fn create_service_record(
     transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
     server_id: Uuid,
-) -> Result<()> {
-    // Clear IP address.
-    sqlx::query!(
+    template_id: Uuid,
+    payload: &NewServerPayload,
+) -> Result<Uuid> {
+    let record = sqlx::query!(
         r#"
-UPDATE ip_addresses SET server_id = NULL
-WHERE server_id = $1
+INSERT INTO services (status, user_id, organization_id, server_id, product_id, template_id, external_id)
+SELECT $1, $2, org.id, $3, $4, $5, $6
+FROM organizations AS org
+WHERE org.personal_user_id = $2
+RETURNING id
         "#,
+        ServiceStatus::Pending.to_string(),
+        user_id,
         server_id,
+        payload.product_id,
+        template_id,
+        payload.external_id,
     )
-    .execute(&mut **transaction)
-    .await?;
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|error| external_id_taken(error, payload.external_id.as_deref()))?;
 
-    // Delete the server.
-    sqlx::query!(
+    Ok(record.id)
+}
+
+/// Saves configurable option values (like CPU, RAM) for a service.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `service_id`: UUID of the service.
+/// * `payload`: `NewServerPayload` containing the configuration values.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn save_config_values(
+    transaction: &mut PgTransaction<'_>,
+    service_id: Uuid,
+    payload: &NewServerPayload,
+) -> Result<()> {
+    let config_options = sqlx::query!(
         r#"
-DELETE FROM servers
-WHERE id = $1
-		"#,
-        server_id,
+SELECT id, name FROM config_options
+WHERE name IN ('cpu_cores', 'ram_gb')
+        "#
     )
-    .execute(&mut **transaction)
+    .fetch_all(&mut **transaction)
     .await?;
 
+    for option in config_options {
+        let value = match option.name.as_str() {
+            "cpu_cores" => payload.cpu_cores.unwrap_or(2).to_string(),
+            "ram_gb" => payload.ram_gb.unwrap_or(2).to_string(),
+            _ => continue,
+        };
+
+        sqlx::query!(
+            r#"
+INSERT INTO config_values (service_id, config_id, value)
+VALUES ($1, $2, $3)
+            "#,
+            service_id,
+            option.id,
+            value
+        )
+        .execute(&mut **transaction)
+        .await?;
+    }
+
     Ok(())
 }
 
-/// Retrieves the Proxmox `VmRef` for a server owned by a user.
+/// Saves custom field values (like OS, Datacenter) for a service.
 ///
 /// # Arguments
 ///
-/// * `executor`: Database executor (pool or transaction).
-/// * `user_id`: UUID of the user who owns the server.
-/// * `server_id`: UUID of the server.
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `service_id`: UUID of the service.
+/// * `payload`: `NewServerPayload` containing the custom values.
 ///
 /// # Returns
 ///
-/// `VmRef` struct for the server.
+/// Empty `Ok(())` on success.
 ///
-pub async fn get_server_proxmox_ref<'e, E>(
-    executor: E,
-    user_id: Uuid,
-    server_id: Uuid,
-) -> Result<VmRef>
-where
-    E: Executor<'e, Database = Postgres>,
-{
-    let record = sqlx::query!(
+pub async fn save_custom_values(
+    transaction: &mut PgTransaction<'_>,
+    service_id: Uuid,
+    payload: &NewServerPayload,
+) -> Result<()> {
+    let custom_fields = sqlx::query!(
         r#"
-SELECT
-	srv.vm_id,
-	srv.node_name
-FROM servers AS srv
-JOIN services AS svc ON svc.server_id = srv.id
-WHERE svc.user_id = $1 AND srv.id = $2
-		"#,
-        user_id,
-        server_id,
+SELECT id, name FROM custom_fields
+WHERE name IN ('os', 'datacenter')
+        "#
     )
-    .fetch_one(executor)
+    .fetch_all(&mut **transaction)
     .await?;
 
-    match (record.node_name, record.vm_id) {
-        (Some(node_name), Some(vm_id)) => Ok(VmRef::new(&node_name, vm_id)),
-        _ => Err(Error::NotReady(format!("Server: {}", server_id))),
+    for field in custom_fields {
+        let value = match field.name.as_str() {
+            "os" => &payload.os,
+            "datacenter" => &payload.datacenter,
+            _ => continue,
+        };
+
+        sqlx::query!(
+            r#"
+INSERT INTO custom_values (service_id, custom_field_id, value)
+VALUES ($1, $2, $3)
+            "#,
+            service_id,
+            field.id,
+            value
+        )
+        .execute(&mut **transaction)
+        .await?;
     }
+
+    Ok(())
 }
 
-/// Retrieves all available products.
+/// Updates a server record with its initial Proxmox VM details.
 ///
 /// # Arguments
 ///
-/// * `pool`: Reference to the `PgPool`.
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server to update.
+/// * `new_vm`: `VmRef` struct containing the VM ID and node name.
 ///
 /// # Returns
 ///
-/// `Vec<ApiProduct>` containing the list of products.
+/// Empty `Ok(())` on success.
 ///
-pub async fn get_products(pool: &PgPool) -> Result<Vec<ApiProduct>> {
-    Ok(sqlx::query_as!(
-        ApiProduct,
+pub async fn update_initial_server(
+    transaction: &mut PgTransaction<'_>,
+    server_id: Uuid,
+    new_vm: VmRef,
+) -> Result<()> {
+    sqlx::query!(
         r#"
-SELECT id, name
-FROM products
-        "#
+UPDATE servers SET vm_id = $2, node_name = $3
+WHERE id = $1
+		"#,
+        server_id,
+        new_vm.id,
+        new_vm.node,
     )
-    .fetch_all(pool)
-    .await?)
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
 }
 
-/// Retrieves all available values for a specific configurable option.
+/// Retrieves the network rate limit of a product.
 ///
 /// # Arguments
 ///
-/// * `pool`: Reference to the `PgPool`.
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `product_id`: UUID of the product.
 ///
 /// # Returns
 ///
-/// `Vec<ApiConfigValue>` containing the list of configurable option values.
+/// Rate limit in Mbit/s, `None` if the product is unlimited.
 ///
-pub async fn get_config_option_value(
-    pool: &PgPool,
-    option: RequiredConfigOption,
-) -> Result<Vec<ApiConfigValue>> {
-    Ok(sqlx::query_as!(
-        ApiConfigValue,
+pub async fn get_product_net_rate(
+    transaction: &mut PgTransaction<'_>,
+    product_id: Uuid,
+) -> Result<Option<i32>> {
+    let record = sqlx::query!(
         r#"
-SELECT DISTINCT v.value
-FROM config_values v
-JOIN config_options o ON v.config_id = o.id
-WHERE o.name = $1
-ORDER BY value
-        "#,
-        option.to_string(),
+SELECT net_rate_mbps FROM products
+WHERE id = $1
+		"#,
+        product_id
     )
-    .fetch_all(pool)
-    .await?)
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    Ok(record.net_rate_mbps)
 }
 
-/// Retrieves all available values for a specific custom field.
+/// Saves the network rate limit applied to a server's VM.
 ///
 /// # Arguments
 ///
-/// * `pool`: Reference to the `PgPool`.
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server to update.
+/// * `net_rate_mbps`: Applied rate limit in Mbit/s, `None` for unlimited.
 ///
 /// # Returns
 ///
-/// `Vec<ApiCustomValue>` containing the list of custom field values.
+/// Empty `Ok(())` on success.
 ///
-pub async fn get_custom_field_value(
-    pool: &PgPool,
-    field: RequiredCustomField,
-) -> Result<Vec<ApiCustomValue>> {
-    Ok(sqlx::query_as!(
-        ApiCustomValue,
-        r#"
-SELECT DISTINCT split_part(v.value, '|', 1) as "value"
-FROM custom_values v
-JOIN custom_fields f ON v.custom_field_id = f.id
-WHERE f.name = $1
-ORDER BY value
+pub async fn update_server_net_rate(
+    transaction: &mut PgTransaction<'_>,
+    server_id: Uuid,
+    net_rate_mbps: Option<i32>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE servers SET net_rate_mbps = $2
+WHERE id = $1
+		"#,
+        server_id,
+        net_rate_mbps,
+    )
+    .execute(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+/// Finds the ID of a template by OS or application name.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `os_name`: Name of the OS or application to find the template for.
+/// * `kind`: Whether an OS or an application template is looked for.
+///
+/// # Returns
+///
+/// UUID of the found template.
+///
+pub async fn find_template_id(
+    transaction: &mut PgTransaction<'_>,
+    os_name: &str,
+    kind: TemplateKind,
+) -> Result<Uuid> {
+    let record = sqlx::query!(
+        r#"
+SELECT * FROM templates
+WHERE os_name = $1 AND kind = $2
+		"#,
+        os_name,
+        kind.to_string(),
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    Ok(record.id)
+}
+
+/// Finds the Proxmox template details for a given service.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `service_id`: UUID of the service.
+///
+/// # Returns
+///
+/// `VmRef` struct containing the template's node and VMID.
+///
+pub async fn find_template(transaction: &mut PgTransaction<'_>, service_id: Uuid) -> Result<VmRef> {
+    let record = sqlx::query!(
+        r#"
+SELECT
+	t.template_node,
+	t.template_vmid
+FROM services AS s
+JOIN templates AS t ON t.id = s.template_id
+WHERE s.id = $1
+		"#,
+        service_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    let vm = VmRef::new(&record.template_node, record.template_vmid);
+    Ok(vm)
+}
+
+/// Updates the status of a service.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `service_id`: UUID of the service to update.
+/// * `status`: New `ServiceStatus`.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn update_service_status<'e, E>(
+    executor: E,
+    service_id: Uuid,
+    status: ServiceStatus,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE services SET status = $2
+WHERE id = $1
+		"#,
+        service_id,
+        status.to_string(),
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Updates the status of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server to update.
+/// * `status`: New `ServerStatus`.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn update_server_status<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    status: ServerStatus,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE servers SET status = $2
+WHERE id = $1
+		"#,
+        server_id,
+        status.to_string(),
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Retrieves a single server by its ID, if the user is a member of its
+/// organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server to retrieve.
+///
+/// # Returns
+///
+/// `ApiServer` struct for the found server.
+///
+pub async fn get_server_by_id<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ApiServer>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let server = sqlx::query_as!(
+        ApiServer,
+        r#"
+SELECT
+	svc.id AS "service_id",
+	srv.id AS "server_id",
+	srv.vm_id,
+	srv.node_name,
+	ip.ip_address,
+	srv.status,
+	srv.net_rate_mbps,
+	ARRAY(
+		SELECT extra.ip_address FROM ip_addresses AS extra
+		WHERE extra.server_id = srv.id AND extra.nic_index > 0
+		ORDER BY extra.nic_index
+	) AS "additional_ips!",
+	ARRAY(
+		SELECT tag.tag FROM server_tags AS tag
+		WHERE tag.server_id = srv.id
+		ORDER BY tag.tag
+	) AS "tags!",
+	srv.notes,
+	svc.external_id
+FROM services AS svc
+JOIN servers AS srv ON srv.id = svc.server_id
+INNER JOIN ip_addresses AS ip ON ip.server_id = srv.id AND ip.nic_index = 0
+WHERE srv.id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1)
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(server)
+}
+
+/// Deletes a server record and releases its associated IP addresses.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server to delete.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn delete_server_record(
+    transaction: &mut PgTransaction<'_>,
+    server_id: Uuid,
+) -> Result<()> {
+    // Clear IP addresses.
+    sqlx::query!(
+        r#"
+UPDATE ip_addresses SET server_id = NULL, nic_index = 0
+WHERE server_id = $1
         "#,
-        field.to_string(),
+        server_id,
     )
-    .fetch_all(pool)
-    .await?)
+    .execute(&mut **transaction)
+    .await?;
+
+    // Delete the server.
+    sqlx::query!(
+        r#"
+DELETE FROM servers
+WHERE id = $1
+		"#,
+        server_id,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the Proxmox `VmRef` for a server managed by a user, an owner or
+/// admin of its organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `VmRef` struct for the server.
+///
+pub async fn get_server_proxmox_ref<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<VmRef>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT
+	srv.vm_id,
+	srv.node_name
+FROM servers AS srv
+JOIN services AS svc ON svc.server_id = srv.id
+WHERE srv.id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1 AND mem.role IN ('Owner', 'Admin'))
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    match (record.node_name, record.vm_id) {
+        (Some(node_name), Some(vm_id)) => Ok(VmRef::new(&node_name, vm_id)),
+        _ => Err(Error::NotReady(format!("Server: {}", server_id))),
+    }
 }
 
-// -----------------------------------------------------------------------------
+/// Retrieves all available products.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+///
+/// # Returns
+///
+/// `Vec<ApiProduct>` containing the list of products.
+///
+pub async fn get_products(pool: &PgPool) -> Result<Vec<ApiProduct>> {
+    Ok(sqlx::query_as!(
+        ApiProduct,
+        r#"
+SELECT id, group_id, name, net_rate_mbps, storage, max_disks, max_disk_gb, traffic_quota_gb
+FROM products
+        "#
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Retrieves all available values for a specific configurable option.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+///
+/// # Returns
+///
+/// `Vec<ApiConfigValue>` containing the list of configurable option values.
+///
+pub async fn get_config_option_value(
+    pool: &PgPool,
+    option: RequiredConfigOption,
+) -> Result<Vec<ApiConfigValue>> {
+    Ok(sqlx::query_as!(
+        ApiConfigValue,
+        r#"
+SELECT DISTINCT v.value
+FROM config_values v
+JOIN config_options o ON v.config_id = o.id
+WHERE o.name = $1
+ORDER BY value
+        "#,
+        option.to_string(),
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Retrieves all available values for a specific custom field.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+///
+/// # Returns
+///
+/// `Vec<ApiCustomValue>` containing the list of custom field values.
+///
+pub async fn get_custom_field_value(
+    pool: &PgPool,
+    field: RequiredCustomField,
+) -> Result<Vec<ApiCustomValue>> {
+    Ok(sqlx::query_as!(
+        ApiCustomValue,
+        r#"
+SELECT DISTINCT split_part(v.value, '|', 1) as "value"
+FROM custom_values v
+JOIN custom_fields f ON v.custom_field_id = f.id
+WHERE f.name = $1
+ORDER BY value
+        "#,
+        field.to_string(),
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Retrieves all product groups.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiProductGroup>` sorted by name.
+///
+pub async fn get_product_groups<'e, E>(executor: E) -> Result<Vec<ApiProductGroup>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiProductGroup,
+        r#"
+SELECT id, name
+FROM product_groups
+ORDER BY name
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Creates a product group.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `name`: Name of the group.
+///
+/// # Returns
+///
+/// Created `ApiProductGroup`.
+///
+pub async fn create_product_group<'e, E>(executor: E, name: &str) -> Result<ApiProductGroup>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiProductGroup,
+        r#"
+INSERT INTO product_groups (name)
+VALUES ($1)
+RETURNING id, name
+        "#,
+        name
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Renames a product group.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `group_id`: UUID of the group.
+/// * `name`: New name of the group.
+///
+/// # Returns
+///
+/// Updated `ApiProductGroup`.
+///
+pub async fn update_product_group<'e, E>(
+    executor: E,
+    group_id: Uuid,
+    name: &str,
+) -> Result<ApiProductGroup>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiProductGroup,
+        r#"
+UPDATE product_groups SET name = $2
+WHERE id = $1
+RETURNING id, name
+        "#,
+        group_id,
+        name
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Deletes a product group without products.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `group_id`: UUID of the group.
+///
+/// # Returns
+///
+/// `true` if the group was deleted, `false` if it doesn't exist.
+///
+pub async fn delete_product_group<'e, E>(executor: E, group_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM product_groups
+WHERE id = $1
+        "#,
+        group_id
+    )
+    .execute(executor)
+    .await
+    .map_err(|error| in_use(error, "Product group"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves a single product.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+///
+/// # Returns
+///
+/// Found `ApiProduct`.
+///
+pub async fn get_product<'e, E>(executor: E, product_id: Uuid) -> Result<ApiProduct>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiProduct,
+        r#"
+SELECT id, group_id, name, net_rate_mbps, storage, max_disks, max_disk_gb, traffic_quota_gb
+FROM products
+WHERE id = $1
+        "#,
+        product_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Creates a product.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `payload`: Group, name, network rate limit, storage, disk limits and
+///   traffic quota of the product.
+///
+/// # Returns
+///
+/// Created `ApiProduct`.
+///
+pub async fn create_product<'e, E>(executor: E, payload: &ProductPayload) -> Result<ApiProduct>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiProduct,
+        r#"
+INSERT INTO products (group_id, name, net_rate_mbps, storage, max_disks, max_disk_gb, traffic_quota_gb)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING id, group_id, name, net_rate_mbps, storage, max_disks, max_disk_gb, traffic_quota_gb
+        "#,
+        payload.group_id,
+        payload.name,
+        payload.net_rate_mbps,
+        payload.storage,
+        payload.max_disks,
+        payload.max_disk_gb,
+        payload.traffic_quota_gb,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| missing_group(error, payload.group_id))
+}
+
+/// Updates a product. Servers keep the network rate limit applied to their VM.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+/// * `payload`: New group, name, network rate limit, storage, disk limits
+///   and traffic quota of the product.
+///
+/// # Returns
+///
+/// Updated `ApiProduct`.
+///
+pub async fn update_product<'e, E>(
+    executor: E,
+    product_id: Uuid,
+    payload: &ProductPayload,
+) -> Result<ApiProduct>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiProduct,
+        r#"
+UPDATE products
+SET
+	group_id = $2, name = $3, net_rate_mbps = $4, storage = $5,
+	max_disks = $6, max_disk_gb = $7, traffic_quota_gb = $8
+WHERE id = $1
+RETURNING id, group_id, name, net_rate_mbps, storage, max_disks, max_disk_gb, traffic_quota_gb
+        "#,
+        product_id,
+        payload.group_id,
+        payload.name,
+        payload.net_rate_mbps,
+        payload.storage,
+        payload.max_disks,
+        payload.max_disk_gb,
+        payload.traffic_quota_gb,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| missing_group(error, payload.group_id))
+}
+
+/// Deletes a product that was never ordered, together with its custom fields.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `product_id`: UUID of the product.
+///
+/// # Returns
+///
+/// `true` if the product was deleted, `false` if it doesn't exist.
+///
+pub async fn delete_product(transaction: &mut PgTransaction<'_>, product_id: Uuid) -> Result<bool> {
+    sqlx::query!(
+        r#"
+DELETE FROM custom_fields
+WHERE product_id = $1
+        "#,
+        product_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|error| in_use(error, "Product"))?;
+
+    let result = sqlx::query!(
+        r#"
+DELETE FROM products
+WHERE id = $1
+        "#,
+        product_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|error| in_use(error, "Product"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves all configurable options.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiConfigOption>` sorted by name.
+///
+pub async fn get_config_options<'e, E>(executor: E) -> Result<Vec<ApiConfigOption>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiConfigOption,
+        r#"
+SELECT id, name
+FROM config_options
+ORDER BY name
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Creates a configurable option.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `name`: Name of the option.
+///
+/// # Returns
+///
+/// Created `ApiConfigOption`.
+///
+pub async fn create_config_option<'e, E>(executor: E, name: &str) -> Result<ApiConfigOption>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiConfigOption,
+        r#"
+INSERT INTO config_options (name)
+VALUES ($1)
+RETURNING id, name
+        "#,
+        name
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Renames a configurable option.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `option_id`: UUID of the option.
+/// * `name`: New name of the option.
+///
+/// # Returns
+///
+/// Updated `ApiConfigOption`.
+///
+pub async fn update_config_option<'e, E>(
+    executor: E,
+    option_id: Uuid,
+    name: &str,
+) -> Result<ApiConfigOption>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiConfigOption,
+        r#"
+UPDATE config_options SET name = $2
+WHERE id = $1
+RETURNING id, name
+        "#,
+        option_id,
+        name
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Deletes a configurable option without values.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `option_id`: UUID of the option.
+///
+/// # Returns
+///
+/// `true` if the option was deleted, `false` if it doesn't exist.
+///
+pub async fn delete_config_option<'e, E>(executor: E, option_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM config_options
+WHERE id = $1
+        "#,
+        option_id
+    )
+    .execute(executor)
+    .await
+    .map_err(|error| in_use(error, "Configurable option"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the custom fields of a product.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+///
+/// # Returns
+///
+/// `Vec<ApiCustomField>` sorted by name.
+///
+pub async fn get_custom_fields<'e, E>(executor: E, product_id: Uuid) -> Result<Vec<ApiCustomField>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiCustomField,
+        r#"
+SELECT id, product_id, name, options
+FROM custom_fields
+WHERE product_id = $1
+ORDER BY name
+        "#,
+        product_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Creates a custom field of a product.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+/// * `payload`: Name and offered values of the field.
+///
+/// # Returns
+///
+/// Created `ApiCustomField`.
+///
+pub async fn create_custom_field<'e, E>(
+    executor: E,
+    product_id: Uuid,
+    payload: &CustomFieldPayload,
+) -> Result<ApiCustomField>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiCustomField,
+        r#"
+INSERT INTO custom_fields (product_id, name, options)
+VALUES ($1, $2, $3)
+RETURNING id, product_id, name, options
+        "#,
+        product_id,
+        payload.name,
+        &payload.options,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Updates a custom field.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `field_id`: UUID of the field.
+/// * `payload`: New name and offered values of the field.
+///
+/// # Returns
+///
+/// Updated `ApiCustomField`.
+///
+pub async fn update_custom_field<'e, E>(
+    executor: E,
+    field_id: Uuid,
+    payload: &CustomFieldPayload,
+) -> Result<ApiCustomField>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiCustomField,
+        r#"
+UPDATE custom_fields SET name = $2, options = $3
+WHERE id = $1
+RETURNING id, product_id, name, options
+        "#,
+        field_id,
+        payload.name,
+        &payload.options,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Deletes a custom field without values.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `field_id`: UUID of the field.
+///
+/// # Returns
+///
+/// `true` if the field was deleted, `false` if it doesn't exist.
+///
+pub async fn delete_custom_field<'e, E>(executor: E, field_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM custom_fields
+WHERE id = $1
+        "#,
+        field_id
+    )
+    .execute(executor)
+    .await
+    .map_err(|error| in_use(error, "Custom field"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves all OS and application templates.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiTemplate>` sorted by OS name.
+///
+pub async fn get_templates<'e, E>(executor: E) -> Result<Vec<ApiTemplate>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiTemplate,
+        r#"
+SELECT id, os_name, template_vmid, template_node, virtual_type, datacenter_code, storage,
+	kind, description, cloud_init_snippet, post_install_script
+FROM templates
+ORDER BY os_name
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves the applications offered in the marketplace.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiApp>` sorted by name.
+///
+pub async fn get_apps<'e, E>(executor: E) -> Result<Vec<ApiApp>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiApp,
+        r#"
+SELECT id, os_name AS name, description
+FROM templates
+WHERE kind = 'Application'
+ORDER BY os_name
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Registers an OS or application template.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `payload`: OS name, VMID, node, storage and application of the template.
+///
+/// # Returns
+///
+/// Created `ApiTemplate`, `Error::Conflict` if the OS name or the VMID is
+/// already registered.
+///
+pub async fn create_template<'e, E>(executor: E, payload: &TemplatePayload) -> Result<ApiTemplate>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiTemplate,
+        r#"
+INSERT INTO templates (
+	os_name, template_vmid, template_node, virtual_type, datacenter_code, storage,
+	kind, description, cloud_init_snippet, post_install_script
+)
+VALUES ($1, $2, $3, 'qemu', $4, $5, $6, $7, $8, $9)
+RETURNING id, os_name, template_vmid, template_node, virtual_type, datacenter_code, storage,
+	kind, description, cloud_init_snippet, post_install_script
+        "#,
+        payload.os_name,
+        payload.template_vmid,
+        payload.template_node,
+        payload.datacenter_code,
+        payload.storage,
+        payload.kind.to_string(),
+        payload.description,
+        payload.cloud_init_snippet,
+        payload.post_install_script,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| template_error(error, payload))
+}
+
+/// Updates an OS or application template. A changed OS name is renamed in the values of the
+/// OS template fields too.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `template_id`: UUID of the template.
+/// * `payload`: New OS name, VMID, node, storage and application of the
+///   template.
+///
+/// # Returns
+///
+/// Updated `ApiTemplate`, `Error::Conflict` if the OS name or the VMID is
+/// registered with another template.
+///
+pub async fn update_template(
+    transaction: &mut PgTransaction<'_>,
+    template_id: Uuid,
+    payload: &TemplatePayload,
+) -> Result<ApiTemplate> {
+    let old_name = sqlx::query!(
+        r#"
+SELECT os_name FROM templates
+WHERE id = $1
+FOR UPDATE
+        "#,
+        template_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .os_name;
+
+    let template = sqlx::query_as!(
+        ApiTemplate,
+        r#"
+UPDATE templates
+SET os_name = $2, template_vmid = $3, template_node = $4, datacenter_code = $5, storage = $6,
+	kind = $7, description = $8, cloud_init_snippet = $9, post_install_script = $10
+WHERE id = $1
+RETURNING id, os_name, template_vmid, template_node, virtual_type, datacenter_code, storage,
+	kind, description, cloud_init_snippet, post_install_script
+        "#,
+        template_id,
+        payload.os_name,
+        payload.template_vmid,
+        payload.template_node,
+        payload.datacenter_code,
+        payload.storage,
+        payload.kind.to_string(),
+        payload.description,
+        payload.cloud_init_snippet,
+        payload.post_install_script,
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|error| template_error(error, payload))?;
+
+    sqlx::query!(
+        r#"
+UPDATE custom_fields SET options = array_replace(options, $1, $2)
+WHERE lower(name) = lower($3) AND $1 = ANY (options)
+        "#,
+        old_name,
+        template.os_name,
+        RequiredCustomField::OsTemplate.to_string(),
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(template)
+}
+
+/// Deletes an OS template no service was created from, removing its OS name
+/// from the values of the OS template fields.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `template_id`: UUID of the template.
+///
+/// # Returns
+///
+/// `true` if the template was deleted, `false` if it doesn't exist.
+///
+pub async fn delete_template(
+    transaction: &mut PgTransaction<'_>,
+    template_id: Uuid,
+) -> Result<bool> {
+    let record = sqlx::query!(
+        r#"
+DELETE FROM templates
+WHERE id = $1
+RETURNING os_name
+        "#,
+        template_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(|error| in_use(error, "Template"))?;
+    let Some(record) = record else {
+        return Ok(false);
+    };
+
+    sqlx::query!(
+        r#"
+UPDATE custom_fields SET options = array_remove(options, $1)
+WHERE lower(name) = lower($2) AND $1 = ANY (options)
+        "#,
+        record.os_name,
+        RequiredCustomField::OsTemplate.to_string(),
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(true)
+}
+
+/// Returns the names that don't belong to any template.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `os_names`: OS names of the templates.
+///
+/// # Returns
+///
+/// Unknown OS names.
+///
+pub async fn get_missing_templates<'e, E>(executor: E, os_names: &[String]) -> Result<Vec<String>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT name AS "name!"
+FROM unnest($1::TEXT[]) AS name
+WHERE NOT EXISTS (SELECT 1 FROM templates WHERE os_name = name)
+        "#,
+        os_names
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records.into_iter().map(|record| record.name).collect())
+}
+
+/// Returns the names that don't belong to any datacenter.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `datacenter_names`: Names of the datacenters.
+///
+/// # Returns
+///
+/// Unknown datacenter names.
+///
+pub async fn get_missing_datacenters<'e, E>(
+    executor: E,
+    datacenter_names: &[String],
+) -> Result<Vec<String>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT name AS "name!"
+FROM unnest($1::TEXT[]) AS name
+WHERE NOT EXISTS (SELECT 1 FROM datacenters WHERE code = name)
+        "#,
+        datacenter_names
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records.into_iter().map(|record| record.name).collect())
+}
+
+/// Retrieves all datacenters.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiDatacenter>` sorted by code.
+///
+pub async fn get_datacenters<'e, E>(executor: E) -> Result<Vec<ApiDatacenter>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiDatacenter,
+        r#"
+SELECT code, display_name, cluster_url, is_active, is_full
+FROM datacenters
+ORDER BY code
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Creates a datacenter.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `payload`: Code, name, cluster endpoint and capacity flags.
+///
+/// # Returns
+///
+/// Created `ApiDatacenter`, `Error::Conflict` if the code is already in use.
+///
+pub async fn create_datacenter<'e, E>(
+    executor: E,
+    payload: &DatacenterPayload,
+) -> Result<ApiDatacenter>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiDatacenter,
+        r#"
+INSERT INTO datacenters (code, display_name, cluster_url, is_active, is_full)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING code, display_name, cluster_url, is_active, is_full
+        "#,
+        payload.code,
+        payload.display_name,
+        payload.cluster_url,
+        payload.is_active,
+        payload.is_full,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| datacenter_taken(error, &payload.code))
+}
+
+/// Updates a datacenter. A changed code is changed in the networks, templates
+/// and products of the datacenter too.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `code`: Current code of the datacenter.
+/// * `payload`: New code, name, cluster endpoint and capacity flags.
+///
+/// # Returns
+///
+/// Updated `ApiDatacenter`, `Error::Conflict` if the new code is already in
+/// use.
+///
+pub async fn update_datacenter<'e, E>(
+    executor: E,
+    code: &str,
+    payload: &DatacenterPayload,
+) -> Result<ApiDatacenter>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiDatacenter,
+        r#"
+UPDATE datacenters
+SET code = $2, display_name = $3, cluster_url = $4, is_active = $5, is_full = $6
+WHERE code = $1
+RETURNING code, display_name, cluster_url, is_active, is_full
+        "#,
+        code,
+        payload.code,
+        payload.display_name,
+        payload.cluster_url,
+        payload.is_active,
+        payload.is_full,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| datacenter_taken(error, &payload.code))
+}
+
+/// Deletes a datacenter without networks and templates.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `code`: Code of the datacenter.
+///
+/// # Returns
+///
+/// `true` if the datacenter was deleted, `false` if it doesn't exist.
+///
+pub async fn delete_datacenter<'e, E>(executor: E, code: &str) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM datacenters
+WHERE code = $1
+        "#,
+        code
+    )
+    .execute(executor)
+    .await
+    .map_err(|error| in_use(error, "Datacenter"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the datacenters a product is offered in.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+///
+/// # Returns
+///
+/// `Vec<ApiDatacenter>` sorted by code, including inactive and full ones.
+///
+pub async fn get_product_datacenters<'e, E>(
+    executor: E,
+    product_id: Uuid,
+) -> Result<Vec<ApiDatacenter>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiDatacenter,
+        r#"
+SELECT d.code, d.display_name, d.cluster_url, d.is_active, d.is_full
+FROM product_datacenters pd
+         JOIN datacenters d ON d.code = pd.datacenter_code
+WHERE pd.product_id = $1
+ORDER BY d.code
+        "#,
+        product_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves the datacenters a product can be ordered in: the active ones it
+/// is offered in, with capacity left.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+///
+/// # Returns
+///
+/// `Vec<ApiAvailableDatacenter>` sorted by code.
+///
+pub async fn get_available_datacenters<'e, E>(
+    executor: E,
+    product_id: Uuid,
+) -> Result<Vec<ApiAvailableDatacenter>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiAvailableDatacenter,
+        r#"
+SELECT d.code, d.display_name
+FROM product_datacenters pd
+         JOIN datacenters d ON d.code = pd.datacenter_code
+WHERE pd.product_id = $1 AND d.is_active AND NOT d.is_full
+ORDER BY d.code
+        "#,
+        product_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Replaces the datacenters a product is offered in.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `product_id`: UUID of the product.
+/// * `codes`: Codes of the datacenters.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success, `Error::Validation` naming an unknown datacenter.
+///
+pub async fn set_product_datacenters(
+    transaction: &mut PgTransaction<'_>,
+    product_id: Uuid,
+    codes: &[String],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+DELETE FROM product_datacenters
+WHERE product_id = $1
+        "#,
+        product_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"
+INSERT INTO product_datacenters (product_id, datacenter_code)
+SELECT $1, code
+FROM unnest($2::TEXT[]) AS code
+        "#,
+        product_id,
+        codes
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+            Error::Validation(format!("Unknown datacenters in {}", codes.join(", ")))
+        }
+        _ => error.into(),
+    })?;
+
+    Ok(())
+}
+
+/// Reports a datacenter code used by another datacenter as a conflict.
+///
+fn datacenter_taken(error: sqlx::Error, code: &str) -> Error {
+    match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Conflict(format!("Datacenter code {code} is already in use"))
+        }
+        _ => error.into(),
+    }
+}
+
+/// Reports a deleted catalog record that is still referenced as a conflict.
+///
+fn in_use(error: sqlx::Error, record: &str) -> Error {
+    match &error {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+            Error::Conflict(format!("{record} is in use"))
+        }
+        _ => error.into(),
+    }
+}
+
+/// Reports an OS name or a VMID registered with another template as a
+/// conflict, and an unknown datacenter as a validation error.
+///
+fn template_error(error: sqlx::Error, payload: &TemplatePayload) -> Error {
+    match &error {
+        sqlx::Error::Database(db) if db.constraint() == Some("templates_os_name_key") => {
+            Error::Conflict(format!("OS name {} is already in use", payload.os_name))
+        }
+        sqlx::Error::Database(db) if db.constraint() == Some("templates_template_vmid_key") => {
+            Error::Conflict(format!(
+                "Template VMID {} is already registered",
+                payload.template_vmid
+            ))
+        }
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => Error::Validation(format!(
+            "Datacenter {} not found",
+            payload.datacenter_code.as_deref().unwrap_or_default()
+        )),
+        _ => error.into(),
+    }
+}
+
+/// Reports a product referencing an unknown group as a validation error.
+///
+fn missing_group(error: sqlx::Error, group_id: Uuid) -> Error {
+    match &error {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+            Error::Validation(format!("Product group {group_id} not found"))
+        }
+        _ => error.into(),
+    }
+}
+
+/// Creates a new invoice for a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `invoice`: `NewInvoice` struct with the invoice details.
+///
+/// # Returns
+///
+/// UUID of the newly created invoice.
+///
+pub async fn create_invoice<'e, E>(executor: E, invoice: NewInvoice) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+INSERT INTO invoices (user_id, service_id, description, amount_cents, currency, status)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING id
+        "#,
+        invoice.user_id,
+        invoice.service_id,
+        invoice.description,
+        invoice.amount_cents,
+        invoice.currency,
+        InvoiceStatus::Unpaid.to_string(),
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.id)
+}
+
+/// Retrieves all invoices of a specific user, newest first.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+/// * `user_id`: UUID of the user whose invoices are to be retrieved.
+///
+/// # Returns
+///
+/// `Vec<ApiInvoice>` containing the list of invoices for the user.
+///
+pub async fn get_invoices_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<ApiInvoice>> {
+    Ok(sqlx::query_as!(
+        ApiInvoice,
+        r#"
+SELECT id, service_id, description, amount_cents, credit_cents, currency, status, created_at, paid_at
+FROM invoices
+WHERE user_id = $1
+ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Retrieves a single invoice owned by a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the invoice.
+/// * `invoice_id`: UUID of the invoice to retrieve.
+///
+/// # Returns
+///
+/// `ApiInvoice` struct for the found invoice.
+///
+pub async fn get_invoice_by_id<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    invoice_id: Uuid,
+) -> Result<ApiInvoice>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiInvoice,
+        r#"
+SELECT id, service_id, description, amount_cents, credit_cents, currency, status, created_at, paid_at
+FROM invoices
+WHERE user_id = $1 AND id = $2
+        "#,
+        user_id,
+        invoice_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Records a checkout attempt started with the payment provider.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `invoice_id`: UUID of the invoice being paid.
+/// * `provider`: Name of the payment provider.
+/// * `provider_ref`: Provider-side ID of the checkout session.
+/// * `amount_cents`: Amount to be charged.
+///
+/// # Returns
+///
+/// UUID of the newly created payment record.
+///
+pub async fn create_payment_record<'e, E>(
+    executor: E,
+    invoice_id: Uuid,
+    provider: &str,
+    provider_ref: &str,
+    amount_cents: i64,
+) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+INSERT INTO payments (invoice_id, provider, provider_ref, amount_cents, status)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING id
+        "#,
+        invoice_id,
+        provider,
+        provider_ref,
+        amount_cents,
+        PaymentStatus::Pending.to_string(),
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.id)
+}
+
+/// Marks a payment provider event as processed.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `provider`: Name of the payment provider.
+/// * `event_id`: Provider-side unique ID of the event.
+/// * `event_type`: Raw event type.
+///
+/// # Returns
+///
+/// `true` if the event is new, `false` if it was already processed.
+///
+pub async fn record_payment_event(
+    transaction: &mut PgTransaction<'_>,
+    provider: &str,
+    event_id: &str,
+    event_type: &str,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+INSERT INTO payment_events (provider, event_id, event_type)
+VALUES ($1, $2, $3)
+ON CONFLICT DO NOTHING
+        "#,
+        provider,
+        event_id,
+        event_type,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Settles an unpaid invoice and marks the matching payment as succeeded.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `invoice_id`: UUID of the invoice to settle.
+/// * `provider`: Name of the payment provider.
+/// * `provider_ref`: Provider-side ID of the checkout session.
+///
+/// # Returns
+///
+/// Owner and linked service of the settled invoice, `None` when the invoice
+/// was not unpaid anymore.
+///
+pub async fn settle_invoice(
+    transaction: &mut PgTransaction<'_>,
+    invoice_id: Uuid,
+    provider: &str,
+    provider_ref: &str,
+) -> Result<Option<SettledInvoice>> {
+    sqlx::query!(
+        r#"
+UPDATE payments SET status = $3
+WHERE provider = $1 AND provider_ref = $2
+        "#,
+        provider,
+        provider_ref,
+        PaymentStatus::Succeeded.to_string(),
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(sqlx::query_as!(
+        SettledInvoice,
+        r#"
+UPDATE invoices SET status = $2, paid_at = CURRENT_TIMESTAMP
+WHERE id = $1 AND status = $3
+RETURNING user_id, service_id
+        "#,
+        invoice_id,
+        InvoiceStatus::Paid.to_string(),
+        InvoiceStatus::Unpaid.to_string(),
+    )
+    .fetch_optional(&mut **transaction)
+    .await?)
+}
+
+/// Sets the status of the payment made in a checkout session.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `provider`: Name of the payment provider.
+/// * `provider_ref`: Provider-side ID of the checkout session.
+/// * `status`: New status of the payment.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_payment_status<'e, E>(
+    executor: E,
+    provider: &str,
+    provider_ref: &str,
+    status: PaymentStatus,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE payments SET status = $3
+WHERE provider = $1 AND provider_ref = $2
+        "#,
+        provider,
+        provider_ref,
+        status.to_string(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the owner of an invoice.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `invoice_id`: UUID of the invoice.
+///
+/// # Returns
+///
+/// UUID of the user who owns the invoice.
+///
+pub async fn get_invoice_owner<'e, E>(executor: E, invoice_id: Uuid) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT user_id FROM invoices
+WHERE id = $1
+        "#,
+        invoice_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Activates a service that was suspended for non-payment.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `service_id`: UUID of the service to activate.
+///
+/// # Returns
+///
+/// `true` if the service was suspended and is active now.
+///
+pub async fn activate_suspended_service<'e, E>(executor: E, service_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE services SET status = $2
+WHERE id = $1 AND status = $3
+        "#,
+        service_id,
+        ServiceStatus::Active.to_string(),
+        ServiceStatus::Suspended.to_string(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Checks whether a user exists and has not deleted the account.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user to check.
+///
+/// # Returns
+///
+/// `true` if the user exists and is not deleted.
+///
+pub async fn is_active_user<'e, E>(executor: E, user_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let active = sqlx::query_scalar!(
+        r#"
+SELECT EXISTS(SELECT 1 FROM users WHERE id = $1 AND deleted_at IS NULL) AS "active!"
+		"#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(active)
+}
+
+/// Checks whether a user is an administrator.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user to check.
+///
+/// # Returns
+///
+/// `true` if the user exists and is an administrator.
+///
+pub async fn is_admin<'e, E>(executor: E, user_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT is_admin FROM users
+WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record.is_some_and(|record| record.is_admin))
+}
+
+/// Locks the user row until the end of the transaction, serializing all
+/// operations that check and change the user's credit balance or quota usage.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `user_id`: UUID of the user to lock.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn lock_user(transaction: &mut PgTransaction<'_>, user_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+SELECT id FROM users
+WHERE id = $1
+FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Calculates the current credit balance of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// Balance in cents, `0` if the user has no credit entries.
+///
+pub async fn get_credit_balance<'e, E>(executor: E, user_id: Uuid) -> Result<i64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT COALESCE(SUM(amount_cents), 0)::BIGINT AS "balance!"
+FROM credits
+WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.balance)
+}
+
+/// Retrieves the credit ledger of a user, newest first.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// `Vec<ApiCredit>` containing all credit entries of the user.
+///
+pub async fn get_credits_for_user(pool: &PgPool, user_id: Uuid) -> Result<Vec<ApiCredit>> {
+    Ok(sqlx::query_as!(
+        ApiCredit,
+        r#"
+SELECT id, amount_cents, reason, invoice_id, created_at
+FROM credits
+WHERE user_id = $1
+ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Adds a new entry to the credit ledger.
+///
+/// A promo code can be redeemed only once per user, a repeated redemption is
+/// ignored.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `credit`: `NewCredit` struct with the entry details.
+///
+/// # Returns
+///
+/// UUID of the new entry, `None` if the promo code was already redeemed by the
+/// user.
+///
+pub async fn create_credit<'e, E>(executor: E, credit: NewCredit) -> Result<Option<Uuid>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+INSERT INTO credits (user_id, amount_cents, reason, promo_code_id, invoice_id, issued_by)
+VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (user_id, promo_code_id) DO NOTHING
+RETURNING id
+        "#,
+        credit.user_id,
+        credit.amount_cents,
+        credit.reason,
+        credit.promo_code_id,
+        credit.invoice_id,
+        credit.issued_by,
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record.map(|record| record.id))
+}
+
+/// Creates a new promo code.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `promo_code`: `NewPromoCode` struct with the code details.
+///
+/// # Returns
+///
+/// `ApiPromoCode` struct for the created code.
+///
+pub async fn create_promo_code<'e, E>(executor: E, promo_code: NewPromoCode) -> Result<ApiPromoCode>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiPromoCode,
+        r#"
+INSERT INTO promo_codes (code, amount_cents, max_redemptions, expires_at)
+VALUES ($1, $2, $3, $4)
+RETURNING id, code, amount_cents, max_redemptions, redemptions, expires_at, created_at
+        "#,
+        promo_code.code,
+        promo_code.amount_cents,
+        promo_code.max_redemptions,
+        promo_code.expires_at,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Retrieves all promo codes, newest first.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+///
+/// # Returns
+///
+/// `Vec<ApiPromoCode>` containing all promo codes.
+///
+pub async fn get_promo_codes(pool: &PgPool) -> Result<Vec<ApiPromoCode>> {
+    Ok(sqlx::query_as!(
+        ApiPromoCode,
+        r#"
+SELECT id, code, amount_cents, max_redemptions, redemptions, expires_at, created_at
+FROM promo_codes
+ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Finds a promo code and locks it until the end of the transaction.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `code`: Normalized promo code.
+///
+/// # Returns
+///
+/// `Some(ApiPromoCode)` if the code exists, `None` otherwise.
+///
+pub async fn find_promo_code_for_update(
+    transaction: &mut PgTransaction<'_>,
+    code: &str,
+) -> Result<Option<ApiPromoCode>> {
+    Ok(sqlx::query_as!(
+        ApiPromoCode,
+        r#"
+SELECT id, code, amount_cents, max_redemptions, redemptions, expires_at, created_at
+FROM promo_codes
+WHERE code = $1
+FOR UPDATE
+        "#,
+        code
+    )
+    .fetch_optional(&mut **transaction)
+    .await?)
+}
+
+/// Counts one more redemption of a promo code.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `promo_code_id`: UUID of the redeemed promo code.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn increment_promo_redemptions(
+    transaction: &mut PgTransaction<'_>,
+    promo_code_id: Uuid,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE promo_codes SET redemptions = redemptions + 1
+WHERE id = $1
+        "#,
+        promo_code_id
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Covers part of an unpaid invoice with credit. The invoice is marked as paid
+/// once credit covers its whole amount.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `invoice_id`: UUID of the invoice.
+/// * `credit_cents`: Amount of credit to apply.
+///
+/// # Returns
+///
+/// `ApiInvoice` struct for the updated invoice.
+///
+pub async fn apply_credit_to_invoice(
+    transaction: &mut PgTransaction<'_>,
+    invoice_id: Uuid,
+    credit_cents: i64,
+) -> Result<ApiInvoice> {
+    Ok(sqlx::query_as!(
+        ApiInvoice,
+        r#"
+UPDATE invoices
+SET credit_cents = credit_cents + $2,
+    status       = CASE WHEN credit_cents + $2 >= amount_cents THEN $3 ELSE status END,
+    paid_at      = CASE WHEN credit_cents + $2 >= amount_cents THEN CURRENT_TIMESTAMP END
+WHERE id = $1 AND status = $4
+RETURNING id, service_id, description, amount_cents, credit_cents, currency, status, created_at, paid_at
+        "#,
+        invoice_id,
+        credit_cents,
+        InvoiceStatus::Paid.to_string(),
+        InvoiceStatus::Unpaid.to_string(),
+    )
+    .fetch_one(&mut **transaction)
+    .await?)
+}
+
+/// Retrieves the capacity of a Proxmox node along with the CPU cores and RAM
+/// already allocated to the servers placed on it.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `node_name`: Name of the Proxmox node.
+///
+/// # Returns
+///
+/// `Some(NodeCapacity)` if the node is registered, `None` otherwise.
+///
+pub async fn get_node_capacity<'e, E>(executor: E, node_name: &str) -> Result<Option<NodeCapacity>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        NodeCapacity,
+        r#"
+SELECT n.name,
+       n.cpu_cores,
+       n.ram_gb,
+       n.cpu_overcommit,
+       n.ram_overcommit,
+       COALESCE(SUM(v.value::INTEGER) FILTER (WHERE o.name = 'cpu_cores'), 0)::BIGINT AS "allocated_cpu_cores!",
+       COALESCE(SUM(v.value::INTEGER) FILTER (WHERE o.name = 'ram_gb'), 0)::BIGINT    AS "allocated_ram_gb!"
+FROM nodes n
+         LEFT JOIN servers s ON s.node_name = n.name
+         LEFT JOIN services sv ON sv.server_id = s.id
+         LEFT JOIN config_values v ON v.service_id = sv.id
+         LEFT JOIN config_options o ON o.id = v.config_id
+WHERE n.name = $1
+GROUP BY n.name
+        "#,
+        node_name
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Counts the servers placed on each Proxmox node.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<NodeVmCount>` with every node that hosts at least one server.
+///
+pub async fn get_node_vm_counts<'e, E>(executor: E) -> Result<Vec<NodeVmCount>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        NodeVmCount,
+        r#"
+SELECT srv.node_name                                   AS "node_name!",
+       COUNT(*)                                        AS "vms!",
+       COUNT(*) FILTER (WHERE srv.status = $1)         AS "running_vms!"
+FROM servers AS srv
+WHERE srv.node_name IS NOT NULL
+GROUP BY srv.node_name
+        "#,
+        ServerStatus::Running.to_string(),
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves all servers that are provisioned on Proxmox, along with their
+/// owners, for usage metering.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+///
+/// # Returns
+///
+/// `Vec<MeteredServer>` containing every server with a known VM.
+///
+pub async fn get_metered_servers(pool: &PgPool) -> Result<Vec<MeteredServer>> {
+    Ok(sqlx::query_as!(
+        MeteredServer,
+        r#"
+SELECT
+	svc.user_id,
+	svc.id AS "service_id",
+	srv.id AS "server_id",
+	srv.vm_id AS "vm_id!",
+	srv.node_name AS "node_name!"
+FROM services AS svc
+JOIN servers AS srv ON srv.id = svc.server_id
+WHERE srv.vm_id IS NOT NULL AND srv.node_name IS NOT NULL
+        "#
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Saves a single usage sample of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `record`: `NewUsageRecord` struct with the sampled usage.
+///
+/// # Returns
+///
+/// UUID of the newly created usage record.
+///
+pub async fn create_usage_record<'e, E>(executor: E, record: NewUsageRecord) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+INSERT INTO usage_records (user_id, service_id, server_id, uptime_hours, cpu_usage, ram_mb)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING id
+        "#,
+        record.user_id,
+        record.service_id,
+        record.server_id,
+        record.uptime_hours,
+        record.cpu_usage,
+        record.ram_mb,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.id)
+}
+
+/// Retrieves the usage of a user's services aggregated over a billing period.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+/// * `user_id`: UUID of the user whose usage is to be retrieved.
+/// * `from`: Start of the period, inclusive.
+/// * `to`: End of the period, exclusive.
+///
+/// # Returns
+///
+/// `Vec<ApiUsage>` with one entry per service.
+///
+pub async fn get_usage_for_user(
+    pool: &PgPool,
+    user_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<ApiUsage>> {
+    Ok(sqlx::query_as!(
+        ApiUsage,
+        r#"
+SELECT
+	service_id,
+	server_id,
+	COUNT(*) AS "samples!",
+	SUM(uptime_hours) AS "uptime_hours!",
+	AVG(cpu_usage) AS avg_cpu_usage,
+	AVG(ram_mb)::DOUBLE PRECISION AS avg_ram_mb
+FROM usage_records
+WHERE user_id = $1 AND sampled_at >= $2 AND sampled_at < $3
+GROUP BY service_id, server_id
+ORDER BY MIN(sampled_at)
+        "#,
+        user_id,
+        from,
+        to
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Retrieves the quota of a user's account.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// `Some(QuotaLimits)` if the account has its own quota, `None` otherwise.
+///
+pub async fn get_account_quota<'e, E>(executor: E, user_id: Uuid) -> Result<Option<QuotaLimits>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        QuotaLimits,
+        r#"
+SELECT max_servers, max_cpu_cores, max_ram_gb, max_ips
+FROM quotas
+WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Retrieves the quota of a product.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+///
+/// # Returns
+///
+/// `Some(ProductQuota)` if the product is limited, `None` otherwise.
+///
+pub async fn get_product_quota<'e, E>(executor: E, product_id: Uuid) -> Result<Option<ProductQuota>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT q.product_id AS "product_id!", p.name, q.max_servers, q.max_cpu_cores, q.max_ram_gb, q.max_ips
+FROM quotas q
+         JOIN products p ON p.id = q.product_id
+WHERE q.product_id = $1
+        "#,
+        product_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record.map(|record| ProductQuota {
+        product_id: record.product_id,
+        product_name: record.name,
+        limits: QuotaLimits {
+            max_servers: record.max_servers,
+            max_cpu_cores: record.max_cpu_cores,
+            max_ram_gb: record.max_ram_gb,
+            max_ips: record.max_ips,
+        },
+    }))
+}
+
+/// Retrieves the quotas of all limited products.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ProductQuota>` sorted by product name.
+///
+pub async fn get_product_quotas<'e, E>(executor: E) -> Result<Vec<ProductQuota>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT q.product_id AS "product_id!", p.name, q.max_servers, q.max_cpu_cores, q.max_ram_gb, q.max_ips
+FROM quotas q
+         JOIN products p ON p.id = q.product_id
+ORDER BY p.name
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| ProductQuota {
+            product_id: record.product_id,
+            product_name: record.name,
+            limits: QuotaLimits {
+                max_servers: record.max_servers,
+                max_cpu_cores: record.max_cpu_cores,
+                max_ram_gb: record.max_ram_gb,
+                max_ips: record.max_ips,
+            },
+        })
+        .collect())
+}
+
+/// Calculates the resources held by the servers of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `product_id`: Optional UUID of a product to count only its servers.
+///
+/// # Returns
+///
+/// `QuotaUsage` with the number of servers and IPs, and the sum of vCPUs and
+/// RAM allocated to them.
+///
+pub async fn get_quota_usage<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    product_id: Option<Uuid>,
+) -> Result<QuotaUsage>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        QuotaUsage,
+        r#"
+SELECT COUNT(DISTINCT sv.id)                                                          AS "servers!",
+       COALESCE(SUM(v.value::INTEGER) FILTER (WHERE o.name = 'cpu_cores'), 0)::BIGINT AS "cpu_cores!",
+       COALESCE(SUM(v.value::INTEGER) FILTER (WHERE o.name = 'ram_gb'), 0)::BIGINT    AS "ram_gb!",
+       (SELECT COUNT(*)
+        FROM services isv
+                 JOIN ip_addresses ip ON ip.server_id = isv.server_id
+        WHERE isv.user_id = $1
+          AND ($2::UUID IS NULL OR isv.product_id = $2))                              AS "ips!"
+FROM services sv
+         LEFT JOIN config_values v ON v.service_id = sv.id
+         LEFT JOIN config_options o ON o.id = v.config_id
+WHERE sv.user_id = $1
+  AND ($2::UUID IS NULL OR sv.product_id = $2)
+        "#,
+        user_id,
+        product_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Creates or replaces the quota of a user's account.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `limits`: New limits of the account.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_account_quota<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    limits: &QuotaLimits,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO quotas (user_id, max_servers, max_cpu_cores, max_ram_gb, max_ips)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (user_id) DO UPDATE
+    SET max_servers   = EXCLUDED.max_servers,
+        max_cpu_cores = EXCLUDED.max_cpu_cores,
+        max_ram_gb    = EXCLUDED.max_ram_gb,
+        max_ips       = EXCLUDED.max_ips
+        "#,
+        user_id,
+        limits.max_servers,
+        limits.max_cpu_cores,
+        limits.max_ram_gb,
+        limits.max_ips,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Creates or replaces the quota of a product.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: UUID of the product.
+/// * `limits`: New limits of the product.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_product_quota<'e, E>(
+    executor: E,
+    product_id: Uuid,
+    limits: &QuotaLimits,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO quotas (product_id, max_servers, max_cpu_cores, max_ram_gb, max_ips)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (product_id) DO UPDATE
+    SET max_servers   = EXCLUDED.max_servers,
+        max_cpu_cores = EXCLUDED.max_cpu_cores,
+        max_ram_gb    = EXCLUDED.max_ram_gb,
+        max_ips       = EXCLUDED.max_ips
+        "#,
+        product_id,
+        limits.max_servers,
+        limits.max_cpu_cores,
+        limits.max_ram_gb,
+        limits.max_ips,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Creates a new network from a CIDR block.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `datacenter_name`: Datacenter location name.
+/// * `cidr`: Block of the network.
+/// * `gateway`: Gateway address of the network.
+///
+/// # Returns
+///
+/// UUID of the new network.
+///
+pub async fn create_network(
+    transaction: &mut PgTransaction<'_>,
+    datacenter_name: &str,
+    cidr: &Ipv4Cidr,
+    gateway: &str,
+) -> Result<Uuid> {
+    let record = sqlx::query!(
+        r#"
+INSERT INTO networks (datacenter_name, cidr, gateway, subnet_mask)
+VALUES ($1, $2, $3, $4)
+RETURNING id
+        "#,
+        datacenter_name,
+        cidr.to_string(),
+        gateway,
+        cidr.subnet_mask().to_string(),
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+            Error::Validation(format!("Datacenter {datacenter_name} not found"))
+        }
+        _ => error.into(),
+    })?;
+
+    Ok(record.id)
+}
+
+/// Adds addresses to a network, skipping the ones it already contains.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `network_id`: UUID of the network.
+/// * `addresses`: IP addresses to add.
+///
+/// # Returns
+///
+/// Number of added addresses.
+///
+pub async fn create_ip_addresses(
+    transaction: &mut PgTransaction<'_>,
+    network_id: Uuid,
+    addresses: &[String],
+) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+INSERT INTO ip_addresses (ip_address, network_id)
+SELECT address, $1
+FROM UNNEST($2::TEXT[]) AS address
+ON CONFLICT (network_id, ip_address) DO NOTHING
+        "#,
+        network_id,
+        addresses,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Retrieves a network together with the number of its addresses.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `network_id`: UUID of the network.
+///
+/// # Returns
+///
+/// `Some(ApiNetwork)` if the network exists, `None` otherwise.
+///
+pub async fn get_network<'e, E>(executor: E, network_id: Uuid) -> Result<Option<ApiNetwork>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiNetwork,
+        r#"
+SELECT n.id, n.datacenter_name, n.cidr, n.gateway, n.subnet_mask, COUNT(ip.id) AS "addresses!"
+FROM networks n
+         LEFT JOIN ip_addresses ip ON ip.network_id = n.id
+WHERE n.id = $1
+GROUP BY n.id
+        "#,
+        network_id
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Marks a range of a network's addresses as reserved, or returns them to the
+/// pool. Reserved addresses are never allocated to new servers.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `network_id`: UUID of the network.
+/// * `start`: First address of the range.
+/// * `end`: Last address of the range, inclusive.
+/// * `reserved`: `true` to exclude the range, `false` to release it.
+///
+/// # Returns
+///
+/// Number of updated addresses.
+///
+pub async fn set_ip_range_reserved<'e, E>(
+    executor: E,
+    network_id: Uuid,
+    start: &str,
+    end: &str,
+    reserved: bool,
+) -> Result<u64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE ip_addresses SET is_reserved = $4
+WHERE network_id = $1
+  AND ip_address::INET BETWEEN $2::INET AND $3::INET
+        "#,
+        network_id,
+        start,
+        end,
+        reserved,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Calculates the utilization of the IP pool of every datacenter.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiIpUtilization>` sorted by datacenter name.
+///
+pub async fn get_ip_utilization<'e, E>(executor: E) -> Result<Vec<ApiIpUtilization>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiIpUtilization,
+        r#"
+SELECT n.datacenter_name,
+       COUNT(DISTINCT n.id)                                                      AS "networks!",
+       COUNT(ip.id)                                                              AS "total!",
+       COUNT(ip.id) FILTER (WHERE ip.server_id IS NOT NULL)                      AS "allocated!",
+       COUNT(ip.id) FILTER (WHERE ip.server_id IS NULL AND ip.is_reserved)       AS "reserved!",
+       COUNT(ip.id) FILTER (WHERE ip.server_id IS NULL AND NOT ip.is_reserved)   AS "available!"
+FROM networks n
+         LEFT JOIN ip_addresses ip ON ip.network_id = n.id
+GROUP BY n.datacenter_name
+ORDER BY n.datacenter_name
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Searches users by ID, name and email, servers by ID, host name, IP address
+/// and VMID, and services by ID, product name and host name. The IDs, the
+/// VMID and the IP address are compared exactly, the texts by the word
+/// similarity of the trigram indexes. Deleted users and their services are
+/// left out.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `term`: Searched text, matched against the texts and used for the
+///   ranking.
+/// * `id`: Searched ID, if the text is a UUID.
+/// * `vm_id`: Searched VMID, if the text is a number.
+/// * `ip_address`: Searched IP address, if the text is one.
+/// * `limit`: Maximum number of results of every kind.
+///
+/// # Returns
+///
+/// `Vec<SearchHit>` ordered by kind, the best matches first.
+///
+pub async fn search<'e, E>(
+    executor: E,
+    term: &str,
+    id: Option<Uuid>,
+    vm_id: Option<i32>,
+    ip_address: Option<&str>,
+    limit: i64,
+) -> Result<Vec<SearchHit>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        SearchHit,
+        r#"
+WITH hits AS (SELECT 'user'                                                   AS kind,
+                     u.id,
+                     u.first_name || ' ' || u.last_name                       AS name,
+                     u.email,
+                     NULL::TEXT                                               AS status,
+                     NULL::TEXT                                               AS node_name,
+                     NULL::INTEGER                                            AS vm_id,
+                     NULL::TEXT[]                                             AS ip_addresses,
+                     NULL::TEXT                                               AS host_name,
+                     NULL::UUID                                               AS user_id,
+                     NULL::UUID                                               AS server_id,
+                     CASE
+                         WHEN u.id = $2 THEN 1
+                         ELSE greatest(word_similarity($1, u.first_name || ' ' || u.last_name),
+                                       word_similarity($1, u.email))
+                         END                                                  AS score
+              FROM users u
+              WHERE u.deleted_at IS NULL
+                AND (u.id = $2
+                  OR $1 <% (u.first_name || ' ' || u.last_name)
+                  OR $1 <% u.email)
+              UNION ALL
+              SELECT 'server',
+                     s.id,
+                     s.host_name,
+                     NULL,
+                     s.status,
+                     s.node_name,
+                     s.vm_id,
+                     ARRAY(SELECT ip.ip_address
+                           FROM ip_addresses ip
+                           WHERE ip.server_id = s.id
+                           ORDER BY ip.nic_index),
+                     NULL,
+                     NULL,
+                     NULL,
+                     CASE
+                         WHEN s.id = $2
+                             OR s.vm_id = $3
+                             OR EXISTS (SELECT 1
+                                        FROM ip_addresses ip
+                                        WHERE ip.server_id = s.id
+                                          AND ip.ip_address = $4) THEN 1
+                         ELSE word_similarity($1, s.host_name)
+                         END
+              FROM servers s
+              WHERE s.id = $2
+                 OR s.vm_id = $3
+                 OR $1 <% s.host_name
+                 OR s.id IN (SELECT ip.server_id
+                             FROM ip_addresses ip
+                             WHERE ip.ip_address = $4
+                                OR $1 <% ip.ip_address)
+              UNION ALL
+              SELECT 'service',
+                     sv.id,
+                     p.name,
+                     u.email,
+                     sv.status,
+                     NULL,
+                     NULL,
+                     NULL,
+                     s.host_name,
+                     sv.user_id,
+                     sv.server_id,
+                     CASE
+                         WHEN sv.id = $2 THEN 1
+                         ELSE greatest(word_similarity($1, p.name), word_similarity($1, s.host_name))
+                         END
+              FROM services sv
+                       JOIN products p ON p.id = sv.product_id
+                       JOIN servers s ON s.id = sv.server_id
+                       JOIN users u ON u.id = sv.user_id
+              WHERE u.deleted_at IS NULL
+                AND (sv.id = $2
+                  OR $1 <% p.name
+                  OR $1 <% s.host_name))
+SELECT kind AS "kind!",
+       id   AS "id!",
+       name AS "name!",
+       email,
+       status,
+       node_name,
+       vm_id,
+       ip_addresses,
+       host_name,
+       user_id,
+       server_id
+FROM (SELECT *, row_number() OVER (PARTITION BY kind ORDER BY score DESC, name) AS position
+      FROM hits) AS ranked
+WHERE position <= $5
+ORDER BY kind, position
+        "#,
+        term,
+        id,
+        vm_id,
+        ip_address,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves the product of a server's service.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// UUID of the product.
+///
+pub async fn get_server_product_id<'e, E>(executor: E, server_id: Uuid) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT product_id FROM services
+WHERE server_id = $1
+        "#,
+        server_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.product_id)
+}
+
+/// Finds an available IP address in the datacenter of a server's primary
+/// address and assigns it to the server's first free network device.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server to assign the IP to.
+///
+/// # Returns
+///
+/// Index of the network device together with the reserved IP address details.
+///
+pub async fn reserve_additional_ip(
+    transaction: &mut PgTransaction<'_>,
+    server_id: Uuid,
+) -> Result<(i32, IpConfig)> {
+    // Find the first network device without an address.
+    let nic_index = sqlx::query!(
+        r#"
+SELECT MIN(idx) AS "nic_index"
+FROM generate_series(1, 31) AS idx
+WHERE idx NOT IN (SELECT nic_index FROM ip_addresses WHERE server_id = $1)
+        "#,
+        server_id,
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .nic_index
+    .ok_or_else(|| Error::Validation("All network devices are in use".to_owned()))?;
+
+    // Find available IP address.
+    let network_details = sqlx::query!(
+        r#"
+SELECT
+	ip.id AS "ip_id",
+	ip.ip_address,
+	n.gateway,
+	n.subnet_mask
+FROM ip_addresses AS ip
+JOIN networks AS n ON ip.network_id = n.id
+WHERE ip.server_id IS NULL AND NOT ip.is_reserved AND n.datacenter_name = (
+	SELECT pn.datacenter_name
+	FROM ip_addresses AS pip
+	JOIN networks AS pn ON pip.network_id = pn.id
+	WHERE pip.server_id = $1 AND pip.nic_index = 0
+)
+LIMIT 1
+FOR UPDATE OF ip SKIP LOCKED
+		"#,
+        server_id,
+    )
+    .fetch_optional(&mut **transaction)
+    .await?
+    .ok_or_else(|| Error::Capacity("No free IP address in the datacenter".to_owned()))?;
+
+    // Reserve IP address.
+    sqlx::query!(
+        r#"
+UPDATE ip_addresses SET server_id = $1, nic_index = $2
+WHERE id = $3
+		"#,
+        server_id,
+        nic_index,
+        network_details.ip_id,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok((
+        nic_index,
+        IpConfig {
+            ip_address: network_details.ip_address,
+            gateway: network_details.gateway,
+            subnet_mask: network_details.subnet_mask,
+        },
+    ))
+}
+
+/// Releases an additional IP address of a server back to the pool.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server.
+/// * `ip_address`: Additional IP address to release.
+///
+/// # Returns
+///
+/// Index of the network device the address was attached to, `None` if the
+/// server has no such additional address.
+///
+pub async fn release_additional_ip(
+    transaction: &mut PgTransaction<'_>,
+    server_id: Uuid,
+    ip_address: &str,
+) -> Result<Option<i32>> {
+    let record = sqlx::query!(
+        r#"
+WITH released AS (
+	SELECT id, nic_index FROM ip_addresses
+	WHERE server_id = $1 AND ip_address = $2 AND nic_index > 0
+	FOR UPDATE
+)
+UPDATE ip_addresses AS ip SET server_id = NULL, nic_index = 0
+FROM released
+WHERE ip.id = released.id
+RETURNING released.nic_index
+		"#,
+        server_id,
+        ip_address,
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+
+    Ok(record.map(|record| record.nic_index))
+}
+
+/// Retrieves the firewall settings of a server of an organization of the
+/// user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `FirewallSettings` of the server.
+///
+pub async fn get_firewall_settings<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<FirewallSettings>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        FirewallSettings,
+        r#"
+SELECT srv.firewall_enabled AS enabled, srv.firewall_policy_in AS policy_in
+FROM servers AS srv
+JOIN services AS svc ON svc.server_id = srv.id
+WHERE srv.id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1)
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Saves the firewall settings of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `settings`: Whether the firewall is enabled and its incoming policy.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_firewall_settings<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    settings: &FirewallSettings,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE servers SET firewall_enabled = $2, firewall_policy_in = $3
+WHERE id = $1
+		"#,
+        server_id,
+        settings.enabled,
+        settings.policy_in.to_string(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the firewall rules of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `Vec<ApiFirewallRule>` sorted by creation time.
+///
+pub async fn get_firewall_rules<'e, E>(executor: E, server_id: Uuid) -> Result<Vec<ApiFirewallRule>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiFirewallRule,
+        r#"
+SELECT id, direction, action, protocol, port, source, created_at
+FROM firewall_rules
+WHERE server_id = $1
+ORDER BY created_at
+        "#,
+        server_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Creates a firewall rule for a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `rule`: Direction, action, protocol, port and optional source of the rule.
+///
+/// # Returns
+///
+/// Created `ApiFirewallRule`.
+///
+pub async fn create_firewall_rule<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    rule: &FirewallRulePayload,
+) -> Result<ApiFirewallRule>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiFirewallRule,
+        r#"
+INSERT INTO firewall_rules (server_id, direction, action, protocol, port, source)
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING id, direction, action, protocol, port, source, created_at
+        "#,
+        server_id,
+        rule.direction.to_string(),
+        rule.action.to_string(),
+        rule.protocol.to_string(),
+        rule.port,
+        rule.source,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Deletes a firewall rule of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `rule_id`: UUID of the rule.
+///
+/// # Returns
+///
+/// `true` if the rule was deleted, `false` if the server has no such rule.
+///
+pub async fn delete_firewall_rule<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    rule_id: Uuid,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM firewall_rules
+WHERE server_id = $1 AND id = $2
+        "#,
+        server_id,
+        rule_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the backup schedule of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `Some(ApiBackupSchedule)` if the server is scheduled for backups, `None`
+/// otherwise.
+///
+pub async fn get_backup_schedule<'e, E>(
+    executor: E,
+    server_id: Uuid,
+) -> Result<Option<ApiBackupSchedule>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiBackupSchedule,
+        r#"
+SELECT server_id, cron, mode, next_run_at, last_run_at
+FROM backup_schedules
+WHERE server_id = $1
+        "#,
+        server_id
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Creates or replaces the backup schedule of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `cron`: Schedule of the backups.
+/// * `mode`: Mode of the backups.
+/// * `next_run_at`: Time of the first backup.
+///
+/// # Returns
+///
+/// Saved `ApiBackupSchedule`.
+///
+pub async fn set_backup_schedule<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    cron: &CronSchedule,
+    mode: BackupMode,
+    next_run_at: DateTime<Utc>,
+) -> Result<ApiBackupSchedule>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiBackupSchedule,
+        r#"
+INSERT INTO backup_schedules (server_id, cron, mode, next_run_at)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (server_id) DO UPDATE
+SET cron = EXCLUDED.cron, mode = EXCLUDED.mode, next_run_at = EXCLUDED.next_run_at
+RETURNING server_id, cron, mode, next_run_at, last_run_at
+        "#,
+        server_id,
+        cron.to_string(),
+        mode.to_string(),
+        next_run_at,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Deletes the backup schedule of a server. Existing backups are kept.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `true` if the schedule was deleted, `false` if the server had none.
+///
+pub async fn delete_backup_schedule<'e, E>(executor: E, server_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM backup_schedules
+WHERE server_id = $1
+        "#,
+        server_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves and locks the backup schedules that are due, with the VM of the
+/// server if it is provisioned. Schedules locked by another transaction are
+/// skipped, so concurrent workers don't trigger the same backup twice.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// `Vec<DueBackup>` sorted by the scheduled time.
+///
+pub async fn get_due_backups(
+    transaction: &mut PgTransaction<'_>,
+    now: DateTime<Utc>,
+) -> Result<Vec<DueBackup>> {
+    Ok(sqlx::query_as!(
+        DueBackup,
+        r#"
+SELECT bs.server_id, srv.vm_id, srv.node_name, bs.cron, bs.mode
+FROM backup_schedules AS bs
+JOIN servers AS srv ON srv.id = bs.server_id
+WHERE bs.next_run_at <= $1
+ORDER BY bs.next_run_at
+FOR UPDATE OF bs SKIP LOCKED
+		"#,
+        now
+    )
+    .fetch_all(&mut **transaction)
+    .await?)
+}
+
+/// Moves a due schedule to its next run, recording the backup if one was
+/// triggered.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server.
+/// * `last_run_at`: Time the backup was triggered, `None` if it wasn't.
+/// * `next_run_at`: Time of the next backup.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn update_backup_run(
+    transaction: &mut PgTransaction<'_>,
+    server_id: Uuid,
+    last_run_at: Option<DateTime<Utc>>,
+    next_run_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+UPDATE backup_schedules SET last_run_at = COALESCE($2, last_run_at), next_run_at = $3
+WHERE server_id = $1
+		"#,
+        server_id,
+        last_run_at,
+        next_run_at,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Registers a periodic job of the scheduler. The next run of an already
+/// registered job is only recalculated when its cron expression changes, so
+/// restarting the replicas doesn't postpone the job.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `name`: Unique name of the job.
+/// * `cron`: Schedule of the job.
+/// * `next_run_at`: First run of the job according to its schedule.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn register_scheduled_job<'e, E>(
+    executor: E,
+    name: &str,
+    cron: &CronSchedule,
+    next_run_at: DateTime<Utc>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO scheduled_jobs (name, cron, next_run_at)
+VALUES ($1, $2, $3)
+ON CONFLICT (name) DO UPDATE SET cron = EXCLUDED.cron, next_run_at = EXCLUDED.next_run_at
+WHERE scheduled_jobs.cron <> EXCLUDED.cron
+		"#,
+        name,
+        cron.to_string(),
+        next_run_at,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Claims a due job that is not running on any replica. The job is moved to
+/// its next run and leased until `locked_until`, after which another replica
+/// may claim it again if the lease was not released.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `name`: Name of the job.
+/// * `now`: Current time.
+/// * `next_run_at`: Next run of the job after this one.
+/// * `locked_until`: End of the lease.
+///
+/// # Returns
+///
+/// Scheduled time of the claimed run, `None` if the job is not due or is
+/// claimed by another replica.
+///
+pub async fn claim_scheduled_job<'e, E>(
+    executor: E,
+    name: &str,
+    now: DateTime<Utc>,
+    next_run_at: DateTime<Utc>,
+    locked_until: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+WITH due AS (
+	SELECT name, next_run_at
+	FROM scheduled_jobs
+	WHERE name = $1
+	  AND next_run_at <= $2
+	  AND (locked_until IS NULL OR locked_until < $2)
+	FOR UPDATE SKIP LOCKED
+)
+UPDATE scheduled_jobs AS job
+SET next_run_at = $3, locked_until = $4, last_started_at = $2
+FROM due
+WHERE job.name = due.name
+RETURNING due.next_run_at AS "scheduled_at"
+		"#,
+        name,
+        now,
+        next_run_at,
+        locked_until,
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record.map(|record| record.scheduled_at))
+}
+
+/// Releases the lease of a finished job and records the outcome of the run.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `name`: Name of the job.
+/// * `finished_at`: Time the run finished.
+/// * `error`: Error of a failed run, `None` for a successful one.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn finish_scheduled_job<'e, E>(
+    executor: E,
+    name: &str,
+    finished_at: DateTime<Utc>,
+    error: Option<&str>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE scheduled_jobs SET locked_until = NULL, last_finished_at = $2, last_error = $3
+WHERE name = $1
+		"#,
+        name,
+        finished_at,
+        error,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves provisioned servers that are either running or stopped. Servers
+/// in a transient status are handled by their background tasks.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<SyncedServer>` with the stored status of each server.
+///
+pub async fn get_synced_servers<'e, E>(executor: E) -> Result<Vec<SyncedServer>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        SyncedServer,
+        r#"
+SELECT srv.id AS "server_id", srv.vm_id AS "vm_id!", srv.node_name AS "node_name!", srv.status
+FROM servers AS srv
+WHERE srv.vm_id IS NOT NULL
+  AND srv.node_name IS NOT NULL
+  AND srv.status IN ($1, $2)
+		"#,
+        ServerStatus::Running.to_string(),
+        ServerStatus::Stopped.to_string(),
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves provisioned servers left in the transient status of an action,
+/// e.g. by a replica that crashed while running it, since before the cutoff.
+/// Provisionings and deletions are not included, they have their own
+/// recovery.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `before`: Time the servers entered their status before.
+///
+/// # Returns
+///
+/// `Vec<SyncedServer>` with the stored status of each server.
+///
+pub async fn get_stuck_servers<'e, E>(
+    executor: E,
+    before: DateTime<Utc>,
+) -> Result<Vec<SyncedServer>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let statuses = [
+        ServerStatus::Starting,
+        ServerStatus::Stopping,
+        ServerStatus::Rebooting,
+        ServerStatus::ShuttingDown,
+        ServerStatus::Restoring,
+        ServerStatus::Resizing,
+        ServerStatus::Configuring,
+    ]
+    .map(|status| status.to_string());
+
+    Ok(sqlx::query_as!(
+        SyncedServer,
+        r#"
+SELECT srv.id AS "server_id", srv.vm_id AS "vm_id!", srv.node_name AS "node_name!", srv.status
+FROM servers AS srv
+WHERE srv.vm_id IS NOT NULL
+  AND srv.node_name IS NOT NULL
+  AND srv.status = ANY($1)
+  AND (SELECT MAX(ssc.changed_at)
+	   FROM server_status_changes AS ssc
+	   WHERE ssc.server_id = srv.id) < $2
+		"#,
+        &statuses[..],
+        before,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Replaces the status of a server, unless it changed since it was read.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `expected`: Status the server had when it was read.
+/// * `status`: New status of the server.
+///
+/// # Returns
+///
+/// `true` if the status was replaced.
+///
+pub async fn replace_server_status<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    expected: ServerStatus,
+    status: ServerStatus,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE servers SET status = $3
+WHERE id = $1 AND status = $2
+		"#,
+        server_id,
+        expected.to_string(),
+        status.to_string(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Creates an unpaid invoice for the recorded usage of every service within a
+/// billing period. Services already invoiced for the period are skipped, so
+/// the invoices are created only once.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `from`: Start of the billing period, inclusive.
+/// * `to`: End of the billing period, exclusive.
+/// * `hour_cents`: Price of one hour of uptime in cents.
+/// * `currency`: Currency of the invoices.
+/// * `description`: Description of the invoices, followed by the invoiced
+///   hours.
+///
+/// # Returns
+///
+/// IDs of the owners of the created invoices, once per invoice.
+///
+pub async fn create_usage_invoices<'e, E>(
+    executor: E,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    hour_cents: i64,
+    currency: &str,
+    description: &str,
+) -> Result<Vec<Uuid>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+INSERT INTO invoices (user_id, service_id, description, amount_cents, currency, status, period_start)
+SELECT
+	ur.user_id,
+	ur.service_id,
+	$5 || ': ' || ROUND(SUM(ur.uptime_hours)::NUMERIC, 2) || ' h',
+	CEIL(SUM(ur.uptime_hours) * $3::BIGINT)::BIGINT,
+	$4,
+	$6,
+	$1
+FROM usage_records AS ur
+WHERE ur.sampled_at >= $1 AND ur.sampled_at < $2 AND ur.service_id IS NOT NULL
+GROUP BY ur.user_id, ur.service_id
+HAVING SUM(ur.uptime_hours) > 0
+ON CONFLICT (service_id, period_start) WHERE period_start IS NOT NULL DO NOTHING
+RETURNING user_id
+		"#,
+        from,
+        to,
+        hour_cents,
+        currency,
+        description,
+        InvoiceStatus::Unpaid.to_string(),
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Tries to take a session-level advisory lock without waiting. The lock is
+/// held until it is released or the connection is closed.
+///
+/// # Arguments
+///
+/// * `executor`: Database connection that will hold the lock.
+/// * `key`: Key of the lock.
+///
+/// # Returns
+///
+/// `true` if the lock was taken, `false` if another session holds it.
+///
+pub async fn try_advisory_lock<'e, E>(executor: E, key: i64) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT pg_try_advisory_lock($1) AS "locked!"
+		"#,
+        key
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.locked)
+}
+
+/// Releases a session-level advisory lock.
+///
+/// # Arguments
+///
+/// * `executor`: Database connection that holds the lock.
+/// * `key`: Key of the lock.
+///
+/// # Returns
+///
+/// `true` if the lock was held by the session and is released.
+///
+pub async fn advisory_unlock<'e, E>(executor: E, key: i64) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT pg_advisory_unlock($1) AS "unlocked!"
+		"#,
+        key
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.unlocked)
+}
+
+/// Registers a webhook of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `url`: Address that receives the events.
+/// * `secret`: Secret used to sign the deliveries.
+/// * `events`: Events the webhook is subscribed to.
+///
+/// # Returns
+///
+/// Created webhook, including its secret.
+///
+pub async fn create_webhook<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    url: &str,
+    secret: &str,
+    events: &[WebhookEvent],
+) -> Result<ApiWebhook>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+INSERT INTO webhooks (user_id, url, secret, events)
+VALUES ($1, $2, $3, $4)
+RETURNING id, created_at
+		"#,
+        user_id,
+        url,
+        secret,
+        &events.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(ApiWebhook {
+        id: record.id,
+        url: url.to_owned(),
+        events: events.to_vec(),
+        secret: Some(secret.to_owned()),
+        created_at: record.created_at,
+    })
+}
+
+/// Retrieves all webhooks of a user, oldest first, without their secrets.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// `Vec<ApiWebhook>` of the user.
+///
+pub async fn get_webhooks<'e, E>(executor: E, user_id: Uuid) -> Result<Vec<ApiWebhook>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT id, url, events, created_at
+FROM webhooks
+WHERE user_id = $1
+ORDER BY created_at, id
+		"#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| ApiWebhook {
+            id: record.id,
+            url: record.url,
+            events: record
+                .events
+                .iter()
+                .filter_map(|event| event.parse().ok())
+                .collect(),
+            secret: None,
+            created_at: record.created_at,
+        })
+        .collect())
+}
+
+/// Deletes a webhook of a user together with its deliveries.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the webhook.
+/// * `webhook_id`: UUID of the webhook.
+///
+/// # Returns
+///
+/// `true` if the webhook existed and was deleted.
+///
+pub async fn delete_webhook<'e, E>(executor: E, user_id: Uuid, webhook_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM webhooks
+WHERE id = $1 AND user_id = $2
+		"#,
+        webhook_id,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Queues an event for every webhook of the user subscribed to it.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user the event belongs to.
+/// * `event`: Lifecycle event.
+/// * `payload`: Data of the event.
+///
+/// # Returns
+///
+/// Number of queued deliveries.
+///
+pub async fn create_webhook_deliveries<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    event: WebhookEvent,
+    payload: &serde_json::Value,
+) -> Result<u64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+INSERT INTO webhook_deliveries (webhook_id, event, payload)
+SELECT id, $2, $3
+FROM webhooks
+WHERE user_id = $1 AND $2 = ANY (events)
+		"#,
+        user_id,
+        event.to_string(),
+        payload,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Retrieves the latest deliveries of a user's webhook, newest first.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the webhook.
+/// * `webhook_id`: UUID of the webhook.
+/// * `limit`: Maximal number of deliveries.
+///
+/// # Returns
+///
+/// `Vec<ApiWebhookDelivery>` without their attempts.
+///
+pub async fn get_webhook_deliveries<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    webhook_id: Uuid,
+    limit: i64,
+) -> Result<Vec<ApiWebhookDelivery>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT wd.id, wd.event, wd.payload, wd.status, wd.next_attempt_at, wd.created_at, wd.delivered_at
+FROM webhook_deliveries AS wd
+JOIN webhooks AS wh ON wh.id = wd.webhook_id
+WHERE wd.webhook_id = $1 AND wh.user_id = $2
+ORDER BY wd.created_at DESC, wd.id
+LIMIT $3
+		"#,
+        webhook_id,
+        user_id,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| ApiWebhookDelivery {
+            id: record.id,
+            event: record.event,
+            payload: record.payload,
+            status: record.status.into(),
+            next_attempt_at: record.next_attempt_at,
+            created_at: record.created_at,
+            delivered_at: record.delivered_at,
+            attempts: Vec::new(),
+        })
+        .collect())
+}
+
+/// Retrieves the attempts of webhook deliveries, oldest first.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `delivery_ids`: UUIDs of the deliveries.
+///
+/// # Returns
+///
+/// `Vec<ApiWebhookAttempt>` of all given deliveries.
+///
+pub async fn get_webhook_attempts<'e, E>(
+    executor: E,
+    delivery_ids: &[Uuid],
+) -> Result<Vec<ApiWebhookAttempt>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiWebhookAttempt,
+        r#"
+SELECT delivery_id, response_status, error, duration_ms, attempted_at
+FROM webhook_attempts
+WHERE delivery_id = ANY ($1)
+ORDER BY attempted_at, id
+		"#,
+        delivery_ids
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Claims the pending deliveries whose next attempt is due, by moving their
+/// next attempt to the end of the lease. Until then, concurrent workers don't
+/// send the same event, and a worker that stopped sending leaves the
+/// deliveries to the next one.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `now`: Current time.
+/// * `lease_until`: End of the lease of the claimed deliveries.
+/// * `limit`: Maximal number of deliveries.
+///
+/// # Returns
+///
+/// `Vec<DueDelivery>` of the claimed deliveries.
+///
+pub async fn claim_due_webhook_deliveries<'e, E>(
+    executor: E,
+    now: DateTime<Utc>,
+    lease_until: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<DueDelivery>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        DueDelivery,
+        r#"
+WITH due AS (
+	SELECT id
+	FROM webhook_deliveries
+	WHERE status = $1 AND next_attempt_at <= $2
+	ORDER BY next_attempt_at
+	LIMIT $4
+	FOR UPDATE SKIP LOCKED
+)
+UPDATE webhook_deliveries AS wd
+SET next_attempt_at = $3
+FROM due, webhooks AS wh
+WHERE wd.id = due.id AND wh.id = wd.webhook_id
+RETURNING wd.id, wh.url, wh.secret, wd.event, wd.payload, wd.attempts, wd.created_at
+		"#,
+        WebhookDeliveryStatus::Pending.to_string(),
+        now,
+        lease_until,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Records a delivery attempt and moves the delivery to its new status.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `delivery_id`: UUID of the delivery.
+/// * `attempt`: Outcome of the attempt.
+/// * `status`: Status of the delivery after the attempt.
+/// * `next_attempt_at`: Time of the next attempt of a pending delivery.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn record_webhook_attempt(
+    transaction: &mut PgTransaction<'_>,
+    delivery_id: Uuid,
+    attempt: &WebhookAttempt,
+    status: WebhookDeliveryStatus,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+INSERT INTO webhook_attempts (delivery_id, response_status, error, duration_ms)
+VALUES ($1, $2, $3, $4)
+		"#,
+        delivery_id,
+        attempt.response_status,
+        attempt.error,
+        attempt.duration_ms,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"
+UPDATE webhook_deliveries
+SET attempts        = attempts + 1,
+    status          = $2,
+    next_attempt_at = $3,
+    delivered_at    = CASE WHEN $2 = $4 THEN NOW() END
+WHERE id = $1
+		"#,
+        delivery_id,
+        status.to_string(),
+        next_attempt_at,
+        WebhookDeliveryStatus::Delivered.to_string(),
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Creates the pending steps of a new server provisioning.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the provisioned server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn create_provisioning_steps<'e, E>(executor: E, server_id: Uuid) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO provisioning_steps (server_id, step, position)
+SELECT $1, s.step, s.position::INTEGER
+FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS s(step, position)
+		"#,
+        server_id,
+        &ProvisioningStep::ALL
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the provisioning steps of a server of an organization of the
+/// user, in execution order.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `Vec<ApiProvisioningStep>`, empty if the server is not found.
+///
+pub async fn get_provisioning_steps<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<Vec<ApiProvisioningStep>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT ps.step, ps.status, ps.attempts, ps.error, ps.started_at, ps.finished_at
+FROM provisioning_steps AS ps
+JOIN services AS s ON s.server_id = ps.server_id
+WHERE ps.server_id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = s.organization_id AND mem.user_id = $1)
+ORDER BY ps.position
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|record| {
+            Ok(ApiProvisioningStep {
+                step: record.step.parse()?,
+                status: record.status.parse()?,
+                attempts: record.attempts,
+                error: record.error,
+                started_at: record.started_at,
+                finished_at: record.finished_at,
+            })
+        })
+        .collect()
+}
+
+/// Retrieves the time the current owner of a server got it.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// Completion time of the latest transfer of the server, `None` if it was
+/// never transferred.
+///
+pub async fn get_server_owned_since<'e, E>(
+    executor: E,
+    server_id: Uuid,
+) -> Result<Option<DateTime<Utc>>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT MAX(st.completed_at)
+FROM server_transfers AS st
+JOIN services AS svc ON svc.id = st.service_id
+WHERE svc.server_id = $1 AND st.status = 'Completed'
+		"#,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Retrieves the latest events of a server recorded in the database: the
+/// started provisioning steps, the status changes, the disk changes and the
+/// completed transfers.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `since`: Time of the earliest event, `None` for all of them.
+/// * `limit`: Maximum number of events.
+///
+/// # Returns
+///
+/// `Vec<ApiTimelineEvent>` of the events, the latest first.
+///
+pub async fn get_server_timeline<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    since: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<ApiTimelineEvent>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiTimelineEvent,
+        r#"
+SELECT
+	event.kind AS "kind!",
+	event.occurred_at AS "occurred_at!",
+	event.name AS "name!",
+	event.status,
+	event.detail
+FROM (
+	SELECT
+		'Provisioning' AS kind,
+		COALESCE(ps.finished_at, ps.started_at) AS occurred_at,
+		ps.step AS name,
+		ps.status,
+		ps.error AS detail
+	FROM provisioning_steps AS ps
+	WHERE ps.server_id = $1 AND ps.started_at IS NOT NULL
+	UNION ALL
+	SELECT
+		CASE
+			WHEN sc.to_status IN ('Starting', 'Stopping', 'Rebooting', 'ShuttingDown') THEN 'Power'
+			WHEN sc.to_status = 'Restoring' THEN 'Restore'
+			ELSE 'Status'
+		END,
+		sc.changed_at,
+		sc.to_status,
+		NULL,
+		sc.from_status
+	FROM server_status_changes AS sc
+	WHERE sc.server_id = $1
+	UNION ALL
+	SELECT
+		'Resize',
+		al.created_at,
+		al.action,
+		NULL,
+		format('%s, %s GB', al.details ->> 'device', al.details ->> 'size_gb')
+	FROM audit_log AS al
+	WHERE al.server_id = $1 AND al.action IN ('DiskAdded', 'DiskResized')
+	UNION ALL
+	SELECT 'Transfer', st.completed_at, st.status, NULL, NULL
+	FROM server_transfers AS st
+	JOIN services AS svc ON svc.id = st.service_id
+	WHERE svc.server_id = $1 AND st.status = 'Completed'
+) AS event
+WHERE $2::TIMESTAMPTZ IS NULL OR event.occurred_at >= $2
+ORDER BY event.occurred_at DESC
+LIMIT $3
+		"#,
+        server_id,
+        since,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves the server being provisioned together with its template, its
+/// resources and its primary address.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `ProvisioningTarget` of the server.
+///
+pub async fn get_provisioning_target<'e, E>(
+    executor: E,
+    server_id: Uuid,
+) -> Result<ProvisioningTarget>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ProvisioningTarget,
+        r#"
+SELECT
+	srv.id AS server_id,
+	svc.user_id,
+	svc.id AS service_id,
+	srv.host_name,
+	srv.vm_id,
+	srv.node_name,
+	t.template_node,
+	t.template_vmid,
+	COALESCE(p.storage, t.storage) AS storage,
+	(
+		SELECT v.value::INTEGER
+		FROM config_values AS v
+		JOIN config_options AS o ON o.id = v.config_id
+		WHERE v.service_id = svc.id AND o.name = 'cpu_cores'
+	) AS cpu_cores,
+	(
+		SELECT v.value::INTEGER
+		FROM config_values AS v
+		JOIN config_options AS o ON o.id = v.config_id
+		WHERE v.service_id = svc.id AND o.name = 'ram_gb'
+	) AS ram_gb,
+	p.net_rate_mbps,
+	ip.ip_address,
+	n.gateway,
+	n.subnet_mask,
+	t.cloud_init_snippet,
+	t.post_install_script
+FROM servers AS srv
+JOIN services AS svc ON svc.server_id = srv.id
+JOIN templates AS t ON t.id = svc.template_id
+JOIN products AS p ON p.id = svc.product_id
+JOIN ip_addresses AS ip ON ip.server_id = srv.id AND ip.nic_index = 0
+JOIN networks AS n ON n.id = ip.network_id
+WHERE srv.id = $1
+		"#,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Retrieves the Proxmox task started by a provisioning step.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `step`: Provisioning step.
+///
+/// # Returns
+///
+/// UPID of the task, `None` if the step didn't start any.
+///
+pub async fn get_provisioning_task<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    step: ProvisioningStep,
+) -> Result<Option<String>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT task_id FROM provisioning_steps
+WHERE server_id = $1 AND step = $2
+		"#,
+        server_id,
+        step.to_string(),
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record.and_then(|record| record.task_id))
+}
+
+/// Saves the Proxmox task started by a provisioning step, so a retry of the
+/// step can follow it instead of starting a new one.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `step`: Provisioning step.
+/// * `task_id`: UPID of the task.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_provisioning_task<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    step: ProvisioningStep,
+    task_id: &str,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE provisioning_steps SET task_id = $3
+WHERE server_id = $1 AND step = $2
+		"#,
+        server_id,
+        step.to_string(),
+        task_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Marks a provisioning step as running and counts the attempt.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `step`: Provisioning step.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn start_provisioning_step<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    step: ProvisioningStep,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE provisioning_steps
+SET status      = $3,
+	attempts    = attempts + 1,
+	error       = NULL,
+	started_at  = NOW(),
+	finished_at = NULL
+WHERE server_id = $1 AND step = $2
+		"#,
+        server_id,
+        step.to_string(),
+        ProvisioningStepStatus::Running.to_string(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Records the outcome of a provisioning step.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `step`: Provisioning step.
+/// * `status`: Status of the step after the attempt.
+/// * `error`: Reason of a failure, `None` on success.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn finish_provisioning_step<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    step: ProvisioningStep,
+    status: ProvisioningStepStatus,
+    error: Option<&str>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE provisioning_steps
+SET status      = $3,
+	error       = $4,
+	finished_at = NOW()
+WHERE server_id = $1 AND step = $2
+		"#,
+        server_id,
+        step.to_string(),
+        status.to_string(),
+        error,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Marks the provisioning steps still running since before the cutoff as
+/// failed, as their job was interrupted.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `before`: Time the steps were started before.
+/// * `error`: Reason of the failure.
+///
+/// # Returns
+///
+/// Owner, server and step of every failed step.
+///
+pub async fn fail_interrupted_provisioning_steps<'e, E>(
+    executor: E,
+    before: DateTime<Utc>,
+    error: &str,
+) -> Result<Vec<(Uuid, Uuid, ProvisioningStep)>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let rows = sqlx::query!(
+        r#"
+UPDATE provisioning_steps AS ps
+SET status      = $3,
+	error       = $4,
+	finished_at = NOW()
+FROM services AS svc
+WHERE svc.server_id = ps.server_id AND ps.status = $2 AND ps.started_at < $1
+RETURNING svc.user_id, ps.server_id, ps.step
+		"#,
+        before,
+        ProvisioningStepStatus::Running.to_string(),
+        ProvisioningStepStatus::Failed.to_string(),
+        error,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| Ok((row.user_id, row.server_id, row.step.parse()?)))
+        .collect()
+}
+
+/// Moves the failed provisioning step of a server managed by a user back to
+/// pending.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `true` if a failed step was found.
+///
+pub async fn reset_failed_provisioning_step<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE provisioning_steps AS ps
+SET status = $3
+FROM services AS s
+WHERE s.server_id = ps.server_id AND ps.server_id = $2 AND ps.status = $4
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = s.organization_id AND mem.user_id = $1 AND mem.role IN ('Owner', 'Admin'))
+		"#,
+        user_id,
+        server_id,
+        ProvisioningStepStatus::Pending.to_string(),
+        ProvisioningStepStatus::Failed.to_string(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Detaches the Proxmox VM from a server record after the VM was deleted.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn clear_server_vm<'e, E>(executor: E, server_id: Uuid) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE servers SET vm_id = NULL, node_name = NULL
+WHERE id = $1
+		"#,
+        server_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the servers whose provisioning stalled: still setting up or
+/// failed, with an unfinished step, and without any progress since `before`.
+/// Their provisioning job crashed or was abandoned.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `before`: Time of the last progress of a stalled provisioning.
+///
+/// # Returns
+///
+/// Pairs of the user and the server IDs.
+///
+pub async fn get_stale_provisionings<'e, E>(
+    executor: E,
+    before: DateTime<Utc>,
+) -> Result<Vec<(Uuid, Uuid)>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let rows = sqlx::query!(
+        r#"
+SELECT svc.user_id, srv.id AS "server_id"
+FROM servers AS srv
+JOIN services AS svc ON svc.server_id = srv.id
+WHERE srv.status IN ('SettingUp', 'Failed')
+	AND EXISTS (SELECT 1
+				FROM provisioning_steps AS ps
+				WHERE ps.server_id = srv.id AND ps.status <> 'Completed')
+	AND GREATEST((SELECT MAX(GREATEST(ps.started_at, ps.finished_at))
+				  FROM provisioning_steps AS ps
+				  WHERE ps.server_id = srv.id),
+				 (SELECT MAX(ssc.changed_at)
+				  FROM server_status_changes AS ssc
+				  WHERE ssc.server_id = srv.id)) < $1
+		"#,
+        before,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.user_id, row.server_id))
+        .collect())
+}
+
+/// Locks the record of a server managed by a user until the end of the
+/// transaction and retrieves its status.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// Current `ServerStatus`, `None` if the server is not found.
+///
+pub async fn lock_server_status(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<Option<ServerStatus>> {
+    let record = sqlx::query!(
+        r#"
+SELECT srv.status
+FROM servers AS srv
+JOIN services AS svc ON svc.server_id = srv.id
+WHERE srv.id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1 AND mem.role IN ('Owner', 'Admin'))
+FOR UPDATE OF srv
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_optional(&mut **transaction)
+    .await?;
+
+    Ok(record.map(|record| ServerStatus::from(record.status)))
+}
+
+/// Retrieves the owner of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// UUID of the user who owns the server, `Error::NotFound` if there is no such
+/// server.
+///
+pub async fn get_server_owner<'e, E>(executor: E, server_id: Uuid) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT user_id FROM services
+WHERE server_id = $1
+		"#,
+        server_id,
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("Server {server_id}")))?;
+
+    Ok(record.user_id)
+}
+
+/// Calculates the resources held by a single server, counted like the
+/// `get_quota_usage` of its owner.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `QuotaUsage` of the server.
+///
+pub async fn get_server_quota_usage<'e, E>(executor: E, server_id: Uuid) -> Result<QuotaUsage>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        QuotaUsage,
+        r#"
+SELECT COUNT(DISTINCT sv.id)                                                          AS "servers!",
+       COALESCE(SUM(v.value::INTEGER) FILTER (WHERE o.name = 'cpu_cores'), 0)::BIGINT AS "cpu_cores!",
+       COALESCE(SUM(v.value::INTEGER) FILTER (WHERE o.name = 'ram_gb'), 0)::BIGINT    AS "ram_gb!",
+       (SELECT COUNT(*) FROM ip_addresses WHERE server_id = $1)                       AS "ips!"
+FROM services sv
+         LEFT JOIN config_values v ON v.service_id = sv.id
+         LEFT JOIN config_options o ON o.id = v.config_id
+WHERE sv.server_id = $1
+        "#,
+        server_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Inserts a pending server transfer.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `transfer`: Service, owner and recipient of the transfer.
+///
+/// # Returns
+///
+/// UUID of the transfer, `Error::Conflict` if the service already has a
+/// pending transfer.
+///
+pub async fn create_server_transfer<'e, E>(executor: E, transfer: NewServerTransfer) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+INSERT INTO server_transfers (service_id, from_user_id, to_user_id, initiated_by, regenerate_credentials)
+VALUES ($1, $2, $3, $4, $5)
+RETURNING id
+		"#,
+        transfer.service_id,
+        transfer.from_user_id,
+        transfer.to_user_id,
+        transfer.initiated_by,
+        transfer.regenerate_credentials,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Conflict("Server already has a pending transfer".to_owned())
+        }
+        _ => error.into(),
+    })?;
+
+    Ok(record.id)
+}
+
+/// Retrieves a server transfer.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `transfer_id`: UUID of the transfer.
+///
+/// # Returns
+///
+/// `ApiServerTransfer` on success.
+///
+pub async fn get_server_transfer<'e, E>(executor: E, transfer_id: Uuid) -> Result<ApiServerTransfer>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiServerTransfer,
+        r#"
+SELECT
+	st.id,
+	srv.id AS "server_id",
+	srv.host_name,
+	st.from_user_id,
+	st.to_user_id,
+	st.initiated_by,
+	st.status,
+	st.regenerate_credentials,
+	st.created_at,
+	st.completed_at
+FROM server_transfers AS st
+JOIN services AS svc ON svc.id = st.service_id
+JOIN servers AS srv ON srv.id = svc.server_id
+WHERE st.id = $1
+		"#,
+        transfer_id,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Retrieves the pending transfers of a user, of the own servers and of the
+/// servers offered to the user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the owner or the recipient.
+///
+/// # Returns
+///
+/// Pending transfers, the oldest first.
+///
+pub async fn get_pending_transfers<'e, E>(
+    executor: E,
+    user_id: Uuid,
+) -> Result<Vec<ApiServerTransfer>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiServerTransfer,
+        r#"
+SELECT
+	st.id,
+	srv.id AS "server_id",
+	srv.host_name,
+	st.from_user_id,
+	st.to_user_id,
+	st.initiated_by,
+	st.status,
+	st.regenerate_credentials,
+	st.created_at,
+	st.completed_at
+FROM server_transfers AS st
+JOIN services AS svc ON svc.id = st.service_id
+JOIN servers AS srv ON srv.id = svc.server_id
+WHERE st.status = 'Pending' AND $1 IN (st.from_user_id, st.to_user_id)
+ORDER BY st.created_at, st.id
+		"#,
+        user_id,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Locks a pending transfer offered to the recipient until the end of the
+/// transaction.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `transfer_id`: UUID of the transfer.
+/// * `to_user_id`: UUID of the recipient.
+///
+/// # Returns
+///
+/// The transfer, `None` if no such transfer is pending for the recipient.
+///
+pub async fn lock_pending_transfer(
+    transaction: &mut PgTransaction<'_>,
+    transfer_id: Uuid,
+    to_user_id: Uuid,
+) -> Result<Option<ApiServerTransfer>> {
+    Ok(sqlx::query_as!(
+        ApiServerTransfer,
+        r#"
+SELECT
+	st.id,
+	srv.id AS "server_id",
+	srv.host_name,
+	st.from_user_id,
+	st.to_user_id,
+	st.initiated_by,
+	st.status,
+	st.regenerate_credentials,
+	st.created_at,
+	st.completed_at
+FROM server_transfers AS st
+JOIN services AS svc ON svc.id = st.service_id
+JOIN servers AS srv ON srv.id = svc.server_id
+WHERE st.id = $1 AND st.to_user_id = $2 AND st.status = 'Pending'
+FOR UPDATE OF st
+		"#,
+        transfer_id,
+        to_user_id,
+    )
+    .fetch_optional(&mut **transaction)
+    .await?)
+}
+
+/// Moves a service to the recipient of its transfer, into the recipient's
+/// personal organization, and completes the transfer.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `transfer`: Pending transfer of the service.
+/// * `now`: Completion time.
+///
+/// # Returns
+///
+/// `true` if the service still belonged to the owner of the transfer and was
+/// moved, `false` otherwise.
+///
+pub async fn complete_server_transfer(
+    transaction: &mut PgTransaction<'_>,
+    transfer: &ApiServerTransfer,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+UPDATE services AS svc
+SET user_id = $3, organization_id = org.id, external_id = NULL
+FROM organizations AS org
+WHERE svc.server_id = $1 AND svc.user_id = $2 AND org.personal_user_id = $3
+		"#,
+        transfer.server_id,
+        transfer.from_user_id,
+        transfer.to_user_id,
+    )
+    .execute(&mut **transaction)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        r#"
+UPDATE server_transfers SET status = 'Completed', completed_at = $2
+WHERE id = $1
+		"#,
+        transfer.id,
+        now,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(true)
+}
+
+/// Closes a pending transfer without moving the service: declined when the
+/// recipient closes it, cancelled when the owner does.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `transfer_id`: UUID of the transfer.
+/// * `user_id`: UUID of the owner or the recipient.
+/// * `now`: Closing time.
+///
+/// # Returns
+///
+/// `true` if a pending transfer of the user was closed, `false` otherwise.
+///
+pub async fn close_server_transfer<'e, E>(
+    executor: E,
+    transfer_id: Uuid,
+    user_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE server_transfers
+SET status       = CASE WHEN to_user_id = $2 THEN 'Declined' ELSE 'Cancelled' END,
+    completed_at = $3
+WHERE id = $1 AND status = 'Pending' AND $2 IN (from_user_id, to_user_id)
+		"#,
+        transfer_id,
+        user_id,
+        now,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Cancels the pending transfer of a server, if there is one.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `now`: Cancellation time.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn cancel_server_transfers<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE server_transfers SET status = 'Cancelled', completed_at = $2
+WHERE status = 'Pending'
+  AND service_id IN (SELECT id FROM services WHERE server_id = $1)
+		"#,
+        server_id,
+        now,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Creates an organization with the user as its owner.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `name`: Name of the organization.
+/// * `user_id`: UUID of the owner.
+///
+/// # Returns
+///
+/// UUID of the organization.
+///
+pub async fn create_organization(
+    transaction: &mut PgTransaction<'_>,
+    name: &str,
+    user_id: Uuid,
+) -> Result<Uuid> {
+    let organization_id = sqlx::query!(
+        r#"
+INSERT INTO organizations (name)
+VALUES ($1)
+RETURNING id
+		"#,
+        name,
+    )
+    .fetch_one(&mut **transaction)
+    .await?
+    .id;
+    set_organization_member(
+        &mut **transaction,
+        organization_id,
+        user_id,
+        OrganizationRole::Owner,
+    )
+    .await?;
+
+    Ok(organization_id)
+}
+
+/// Creates the personal organization of a new user, with the user as its
+/// only owner.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn create_personal_organization<'e, E>(executor: E, user_id: Uuid) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+WITH organization AS (
+	INSERT INTO organizations (name, personal_user_id)
+	VALUES ('Personal', $1)
+	RETURNING id
+)
+INSERT INTO organization_members (organization_id, user_id, role)
+SELECT id, $1, 'Owner'
+FROM organization
+		"#,
+        user_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Hands the services a user is accountable for in shared organizations over
+/// to another owner of each organization, the one who joined first. Services
+/// of an organization without another owner are left to the user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user leaving.
+/// * `organization_id`: UUID of the organization the user leaves, `None` for
+///   all of them.
+///
+/// # Returns
+///
+/// Number of services handed over.
+///
+pub async fn hand_over_organization_services<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    organization_id: Option<Uuid>,
+) -> Result<u64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE services AS svc
+SET user_id = (
+	SELECT mem.user_id
+	FROM organization_members AS mem
+	WHERE mem.organization_id = svc.organization_id AND mem.role = 'Owner' AND mem.user_id <> $1
+	ORDER BY mem.created_at, mem.user_id
+	LIMIT 1
+)
+WHERE svc.user_id = $1
+	AND ($2::UUID IS NULL OR svc.organization_id = $2)
+	AND EXISTS (
+		SELECT 1 FROM organization_members AS mem
+		WHERE mem.organization_id = svc.organization_id AND mem.role = 'Owner' AND mem.user_id <> $1
+	)
+		"#,
+        user_id,
+        organization_id as Option<Uuid>,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Retrieves the organizations of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// Organizations with the role of the user, the personal one first.
+///
+pub async fn get_organizations<'e, E>(executor: E, user_id: Uuid) -> Result<Vec<ApiOrganization>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiOrganization,
+        r#"
+SELECT
+	org.id,
+	org.name,
+	org.personal_user_id IS NOT NULL AS "personal!",
+	mem.role,
+	org.created_at
+FROM organizations AS org
+JOIN organization_members AS mem ON mem.organization_id = org.id
+WHERE mem.user_id = $1
+ORDER BY org.personal_user_id IS NULL, org.created_at, org.id
+		"#,
+        user_id,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves an organization of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+/// * `user_id`: UUID of the member.
+///
+/// # Returns
+///
+/// The organization with the role of the user, `None` if the user isn't a
+/// member.
+///
+pub async fn get_organization<'e, E>(
+    executor: E,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<ApiOrganization>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiOrganization,
+        r#"
+SELECT
+	org.id,
+	org.name,
+	org.personal_user_id IS NOT NULL AS "personal!",
+	mem.role,
+	org.created_at
+FROM organizations AS org
+JOIN organization_members AS mem ON mem.organization_id = org.id
+WHERE org.id = $1 AND mem.user_id = $2
+		"#,
+        organization_id,
+        user_id,
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Locks an organization of a user until the end of the transaction,
+/// serializing the changes of its members.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `organization_id`: UUID of the organization.
+/// * `user_id`: UUID of the member.
+///
+/// # Returns
+///
+/// The organization with the role of the user, `None` if the user isn't a
+/// member.
+///
+pub async fn lock_organization(
+    transaction: &mut PgTransaction<'_>,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<ApiOrganization>> {
+    Ok(sqlx::query_as!(
+        ApiOrganization,
+        r#"
+SELECT
+	org.id,
+	org.name,
+	org.personal_user_id IS NOT NULL AS "personal!",
+	mem.role,
+	org.created_at
+FROM organizations AS org
+JOIN organization_members AS mem ON mem.organization_id = org.id
+WHERE org.id = $1 AND mem.user_id = $2
+FOR UPDATE OF org
+		"#,
+        organization_id,
+        user_id,
+    )
+    .fetch_optional(&mut **transaction)
+    .await?)
+}
+
+/// Retrieves the members of an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+///
+/// # Returns
+///
+/// Members with their roles, in the order they joined.
+///
+pub async fn get_organization_members<'e, E>(
+    executor: E,
+    organization_id: Uuid,
+) -> Result<Vec<ApiOrganizationMember>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiOrganizationMember,
+        r#"
+SELECT
+	usr.id AS "user_id",
+	usr.email,
+	usr.first_name,
+	usr.last_name,
+	mem.role,
+	mem.created_at
+FROM organization_members AS mem
+JOIN users AS usr ON usr.id = mem.user_id
+WHERE mem.organization_id = $1
+ORDER BY mem.created_at, usr.id
+		"#,
+        organization_id,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Adds a member to an organization, or changes the role of a member.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+/// * `user_id`: UUID of the member.
+/// * `role`: Role of the member.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_organization_member<'e, E>(
+    executor: E,
+    organization_id: Uuid,
+    user_id: Uuid,
+    role: OrganizationRole,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO organization_members (organization_id, user_id, role)
+VALUES ($1, $2, $3)
+ON CONFLICT (organization_id, user_id) DO UPDATE SET role = EXCLUDED.role
+		"#,
+        organization_id,
+        user_id,
+        role.to_string(),
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a member from an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+/// * `user_id`: UUID of the member.
+///
+/// # Returns
+///
+/// `true` if the user was a member, `false` otherwise.
+///
+pub async fn remove_organization_member<'e, E>(
+    executor: E,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM organization_members
+WHERE organization_id = $1 AND user_id = $2
+		"#,
+        organization_id,
+        user_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Counts the owners of an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+///
+/// # Returns
+///
+/// Number of members with the `Owner` role.
+///
+pub async fn count_organization_owners<'e, E>(executor: E, organization_id: Uuid) -> Result<i64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT COUNT(*) AS "owners!"
+FROM organization_members
+WHERE organization_id = $1 AND role = 'Owner'
+		"#,
+        organization_id,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.owners)
+}
+
+/// Retrieves the servers owned by an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+///
+/// # Returns
+///
+/// `Vec<ApiServer>` of the servers of the organization.
+///
+pub async fn get_organization_servers<'e, E>(
+    executor: E,
+    organization_id: Uuid,
+) -> Result<Vec<ApiServer>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let rows = sqlx::query!(
+        r#"
+SELECT
+	svc.id AS "service_id",
+	srv.id AS "server_id",
+	srv.vm_id,
+	srv.node_name,
+	ip.ip_address,
+	srv.status,
+	srv.net_rate_mbps,
+	ARRAY(
+		SELECT extra.ip_address FROM ip_addresses AS extra
+		WHERE extra.server_id = srv.id AND extra.nic_index > 0
+		ORDER BY extra.nic_index
+	) AS "additional_ips!",
+	ARRAY(
+		SELECT tag.tag FROM server_tags AS tag
+		WHERE tag.server_id = srv.id
+		ORDER BY tag.tag
+	) AS "tags!",
+	srv.notes,
+	svc.external_id
+FROM services AS svc
+JOIN servers AS srv ON srv.id = svc.server_id
+INNER JOIN ip_addresses as ip ON ip.server_id = srv.id AND ip.nic_index = 0
+WHERE svc.organization_id = $1
+		"#,
+        organization_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ApiServer {
+            service_id: row.service_id,
+            server_id: row.server_id,
+            vm_id: row.vm_id,
+            node_name: row.node_name,
+            ip_address: row.ip_address,
+            status: row.status.as_str().into(),
+            net_rate_mbps: row.net_rate_mbps,
+            additional_ips: row.additional_ips,
+            tags: row.tags,
+            notes: row.notes,
+            external_id: row.external_id,
+        })
+        .collect::<Vec<_>>())
+}
+
+/// Moves a server of a user to an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `user_id`: UUID of the user accountable for the server.
+/// * `organization_id`: UUID of the new owning organization.
+///
+/// # Returns
+///
+/// `true` if the user's server was moved, `false` otherwise.
+///
+pub async fn set_server_organization<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE services SET organization_id = $3
+WHERE server_id = $1 AND user_id = $2
+		"#,
+        server_id,
+        user_id,
+        organization_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Creates an announcement.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `payload`: Kind, text, audience and schedule of the announcement.
+///
+/// # Returns
+///
+/// Created `ApiAnnouncement`, `Error::Validation` if the datacenter doesn't
+/// exist.
+///
+pub async fn create_announcement<'e, E>(
+    executor: E,
+    payload: &AnnouncementPayload,
+) -> Result<ApiAnnouncement>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiAnnouncement,
+        r#"
+INSERT INTO announcements (kind, title, message, datacenter_code, publish_at, expires_at)
+VALUES ($1, $2, $3, $4, COALESCE($5, NOW()), $6)
+RETURNING id, kind, title, message, datacenter_code, publish_at, expires_at, created_at, updated_at
+        "#,
+        payload.kind.to_string(),
+        payload.title,
+        payload.message,
+        payload.datacenter_code,
+        payload.publish_at,
+        payload.expires_at,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| missing_datacenter(error, payload))
+}
+
+/// Updates an announcement.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `announcement_id`: ID of the announcement.
+/// * `payload`: New kind, text, audience and schedule of the announcement.
+///
+/// # Returns
+///
+/// Updated `ApiAnnouncement`, `Error::Validation` if the datacenter doesn't
+/// exist.
+///
+pub async fn update_announcement<'e, E>(
+    executor: E,
+    announcement_id: Uuid,
+    payload: &AnnouncementPayload,
+) -> Result<ApiAnnouncement>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiAnnouncement,
+        r#"
+UPDATE announcements
+SET kind = $2, title = $3, message = $4, datacenter_code = $5,
+    publish_at = COALESCE($6, NOW()), expires_at = $7, updated_at = NOW()
+WHERE id = $1
+RETURNING id, kind, title, message, datacenter_code, publish_at, expires_at, created_at, updated_at
+        "#,
+        announcement_id,
+        payload.kind.to_string(),
+        payload.title,
+        payload.message,
+        payload.datacenter_code,
+        payload.publish_at,
+        payload.expires_at,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| missing_datacenter(error, payload))
+}
+
+/// Deletes an announcement.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `announcement_id`: ID of the announcement.
+///
+/// # Returns
+///
+/// `true` if the announcement existed, `false` otherwise.
+///
+pub async fn delete_announcement<'e, E>(executor: E, announcement_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM announcements
+WHERE id = $1
+        "#,
+        announcement_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves all announcements, including the scheduled and the expired ones,
+/// the latest published first.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiAnnouncement>` containing all announcements.
+///
+pub async fn get_announcements<'e, E>(executor: E) -> Result<Vec<ApiAnnouncement>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiAnnouncement,
+        r#"
+SELECT id, kind, title, message, datacenter_code, publish_at, expires_at, created_at, updated_at
+FROM announcements
+ORDER BY publish_at DESC, id
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves the announcements shown to a user at the given moment: the
+/// published, unexpired ones for all users, and for the datacenters the
+/// user's servers, or those of the user's organizations, are placed in.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `now`: Current moment.
+///
+/// # Returns
+///
+/// `Vec<ApiAnnouncement>` of the active announcements, the latest published
+/// first.
+///
+pub async fn get_active_announcements<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<Vec<ApiAnnouncement>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiAnnouncement,
+        r#"
+SELECT
+	ann.id,
+	ann.kind,
+	ann.title,
+	ann.message,
+	ann.datacenter_code,
+	ann.publish_at,
+	ann.expires_at,
+	ann.created_at,
+	ann.updated_at
+FROM announcements AS ann
+WHERE ann.publish_at <= $2
+  AND (ann.expires_at IS NULL OR $2 < ann.expires_at)
+  AND (ann.datacenter_code IS NULL OR ann.datacenter_code IN (
+	SELECT net.datacenter_name
+	FROM services AS svc
+	JOIN ip_addresses AS ip ON ip.server_id = svc.server_id
+	JOIN networks AS net ON net.id = ip.network_id
+	WHERE svc.user_id = $1
+	   OR svc.organization_id IN (SELECT organization_id FROM organization_members WHERE user_id = $1)
+  ))
+ORDER BY ann.publish_at DESC, ann.id
+		"#,
+        user_id,
+        now,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Claims the published maintenance windows whose audience isn't notified yet,
+/// so that every window notifies it once, even with several replicas.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `now`: Current moment.
+///
+/// # Returns
+///
+/// `Vec<ApiAnnouncement>` of the claimed maintenance windows.
+///
+pub async fn claim_published_maintenances<'e, E>(
+    executor: E,
+    now: DateTime<Utc>,
+) -> Result<Vec<ApiAnnouncement>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiAnnouncement,
+        r#"
+UPDATE announcements
+SET notified_at = $2
+WHERE kind = $1
+  AND notified_at IS NULL
+  AND publish_at <= $2
+  AND (expires_at IS NULL OR $2 < expires_at)
+RETURNING id, kind, title, message, datacenter_code, publish_at, expires_at, created_at, updated_at
+		"#,
+        AnnouncementKind::Maintenance.to_string(),
+        now,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Releases a claimed maintenance window whose audience couldn't be notified,
+/// so that the next run tries again.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `announcement_id`: ID of the announcement.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn release_maintenance<'e, E>(executor: E, announcement_id: Uuid) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE announcements
+SET notified_at = NULL
+WHERE id = $1
+		"#,
+        announcement_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Reports an announcement for an unknown datacenter as a validation error.
+///
+fn missing_datacenter(error: sqlx::Error, payload: &AnnouncementPayload) -> Error {
+    match &error {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => Error::Validation(format!(
+            "Datacenter {} not found",
+            payload.datacenter_code.as_deref().unwrap_or_default()
+        )),
+        _ => error.into(),
+    }
+}
+
+/// Replaces the tags of a server.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `server_id`: UUID of the server.
+/// * `tags`: New tags of the server, without duplicates.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_server_tags(
+    transaction: &mut PgTransaction<'_>,
+    server_id: Uuid,
+    tags: &[String],
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+DELETE FROM server_tags
+WHERE server_id = $1 AND tag <> ALL($2)
+		"#,
+        server_id,
+        tags,
+    )
+    .execute(&mut **transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+INSERT INTO server_tags (server_id, tag)
+SELECT $1, tag FROM UNNEST($2::TEXT[]) AS tag
+ON CONFLICT DO NOTHING
+		"#,
+        server_id,
+        tags,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a tag from a server managed by a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the owner.
+/// * `server_id`: UUID of the server.
+/// * `tag`: Removed tag.
+///
+/// # Returns
+///
+/// `true` if the server had the tag, `false` otherwise.
+///
+pub async fn remove_server_tag<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+    tag: &str,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM server_tags AS tag
+USING services AS svc
+WHERE svc.server_id = tag.server_id AND tag.server_id = $2 AND tag.tag = $3
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1 AND mem.role IN ('Owner', 'Admin'))
+		"#,
+        user_id,
+        server_id,
+        tag,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Sets the notes of a server managed by a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the owner.
+/// * `server_id`: UUID of the server.
+/// * `notes`: New notes, `None` to clear them.
+///
+/// # Returns
+///
+/// `true` if the user's server was updated, `false` otherwise.
+///
+pub async fn set_server_notes<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+    notes: Option<&str>,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE servers AS srv
+SET notes = $3
+FROM services AS svc
+WHERE svc.server_id = srv.id AND srv.id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1 AND mem.role IN ('Owner', 'Admin'))
+		"#,
+        user_id,
+        server_id,
+        notes,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the tags used on the servers of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the owner.
+///
+/// # Returns
+///
+/// `Vec<ApiServerTag>` of the tags with the number of servers having them,
+/// sorted by tag.
+///
+pub async fn get_server_tags<'e, E>(executor: E, user_id: Uuid) -> Result<Vec<ApiServerTag>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiServerTag,
+        r#"
+SELECT tag.tag, COUNT(*) AS "servers!"
+FROM server_tags AS tag
+JOIN services AS svc ON svc.server_id = tag.server_id
+WHERE svc.user_id = $1
+GROUP BY tag.tag
+ORDER BY tag.tag
+		"#,
+        user_id,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Finds a server of a user by its external ID.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the owner.
+/// * `external_id`: External ID of the server.
+///
+/// # Returns
+///
+/// UUID of the server, `None` if the user has no server with the ID.
+///
+pub async fn find_server_by_external_id<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    external_id: &str,
+) -> Result<Option<Uuid>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT server_id FROM services
+WHERE user_id = $1 AND external_id = $2
+		"#,
+        user_id,
+        external_id,
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Sets the external ID of a server of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the owner.
+/// * `server_id`: UUID of the server.
+/// * `external_id`: New external ID, `None` to clear it.
+///
+/// # Returns
+///
+/// `true` if the user's server was updated, `false` otherwise.
+///
+pub async fn set_server_external_id<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+    external_id: Option<&str>,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE services SET external_id = $3
+WHERE user_id = $1 AND server_id = $2
+		"#,
+        user_id,
+        server_id,
+        external_id,
+    )
+    .execute(executor)
+    .await
+    .map_err(|error| external_id_taken(error, external_id))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Reports an external ID already used by another server of the user as a
+/// conflict.
+///
+fn external_id_taken(error: sqlx::Error, external_id: Option<&str>) -> Error {
+    match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => Error::Conflict(format!(
+            "External ID {} is already in use",
+            external_id.unwrap_or_default()
+        )),
+        _ => error.into(),
+    }
+}
+
+/// Adds the notifications to the notification centers of their users.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `event`: Event the users are notified about.
+/// * `notifications`: Notification of every user.
+///
+/// # Returns
+///
+/// Number of created notifications.
+///
+pub async fn create_notifications<'e, E>(
+    executor: E,
+    event: NotificationEvent,
+    notifications: &[NewNotification],
+) -> Result<u64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let user_ids = notifications
+        .iter()
+        .map(|notification| notification.user_id)
+        .collect::<Vec<_>>();
+    let titles = notifications
+        .iter()
+        .map(|notification| notification.title.clone())
+        .collect::<Vec<_>>();
+    let messages = notifications
+        .iter()
+        .map(|notification| notification.message.clone())
+        .collect::<Vec<_>>();
+    let emailed = notifications
+        .iter()
+        .map(|notification| notification.emailed)
+        .collect::<Vec<_>>();
+    let result = sqlx::query!(
+        r#"
+INSERT INTO notifications (user_id, event, title, message, email_pending)
+SELECT notification.user_id, $1, notification.title, notification.message, notification.emailed
+FROM UNNEST($2::UUID[], $3::TEXT[], $4::TEXT[], $5::BOOL[])
+	AS notification(user_id, title, message, emailed)
+		"#,
+        event.to_string(),
+        &user_ids,
+        &titles,
+        &messages,
+        &emailed,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Claims the queued notification emails that are due, leasing them so that
+/// no other run sends them meanwhile. The emails of deleted accounts are left
+/// out.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `now`: Current moment.
+/// * `lease_until`: Time until the claimed emails are leased.
+/// * `limit`: Maximum number of claimed emails.
+///
+/// # Returns
+///
+/// `Vec<DueNotificationEmail>` of the claimed emails.
+///
+pub async fn claim_due_notification_emails<'e, E>(
+    executor: E,
+    now: DateTime<Utc>,
+    lease_until: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<DueNotificationEmail>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        DueNotificationEmail,
+        r#"
+WITH due AS (
+	SELECT ntf.id
+	FROM notifications AS ntf
+	JOIN users AS usr ON usr.id = ntf.user_id
+	WHERE ntf.email_pending AND ntf.email_attempt_at <= $1 AND usr.deleted_at IS NULL
+	ORDER BY ntf.email_attempt_at
+	LIMIT $3
+	FOR UPDATE OF ntf SKIP LOCKED
+)
+UPDATE notifications AS ntf
+SET email_attempt_at = $2
+FROM due, users AS usr
+WHERE ntf.id = due.id AND usr.id = ntf.user_id
+RETURNING ntf.id, usr.email, ntf.title, ntf.message, ntf.email_attempts AS attempts
+		"#,
+        now,
+        lease_until,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Records an attempt to send the email of a notification.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `notification_id`: ID of the notification.
+/// * `emailed_at`: Time the email was sent, `None` if the attempt failed.
+/// * `retry_at`: Time of the next attempt, `None` if the email was sent or
+///   ran out of attempts.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn record_notification_email<'e, E>(
+    executor: E,
+    notification_id: Uuid,
+    emailed_at: Option<DateTime<Utc>>,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE notifications
+SET email_attempts   = email_attempts + 1,
+	emailed_at       = $2,
+	email_pending    = $3::TIMESTAMPTZ IS NOT NULL,
+	email_attempt_at = COALESCE($3, email_attempt_at)
+WHERE id = $1
+		"#,
+        notification_id,
+        emailed_at,
+        retry_at,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the users to notify about an event, with their language and
+/// whether they get the event by email. Deleted accounts are left out.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_ids`: IDs of the notified users.
+/// * `event`: Event the users are notified about.
+///
+/// # Returns
+///
+/// Users to notify.
+///
+pub async fn get_notification_recipients<'e, E>(
+    executor: E,
+    user_ids: &[Uuid],
+    event: NotificationEvent,
+) -> Result<Vec<NotificationRecipient>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        NotificationRecipient,
+        r#"
+SELECT
+	usr.id AS user_id,
+	usr.email,
+	usr.locale,
+	COALESCE(pref.email, TRUE) AS "emailed!"
+FROM users AS usr
+LEFT JOIN notification_preferences AS pref ON pref.user_id = usr.id AND pref.event = $2
+WHERE usr.id = ANY($1) AND usr.deleted_at IS NULL
+		"#,
+        user_ids,
+        event.to_string(),
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves the latest notifications of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user.
+/// * `unread`: Whether to retrieve only the unread notifications.
+/// * `limit`: Maximum number of notifications.
+///
+/// # Returns
+///
+/// Notifications of the user, the latest first.
+///
+pub async fn get_notifications<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    unread: bool,
+    limit: i64,
+) -> Result<Vec<ApiNotification>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiNotification,
+        r#"
+SELECT id, event, title, message, read_at, created_at
+FROM notifications
+WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+ORDER BY created_at DESC, id
+LIMIT $3
+		"#,
+        user_id,
+        unread,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Marks a notification of a user as read, keeping the time it was first read.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user.
+/// * `notification_id`: ID of the notification.
+///
+/// # Returns
+///
+/// `true` if the user has the notification, `false` otherwise.
+///
+pub async fn mark_notification_read<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    notification_id: Uuid,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE notifications
+SET read_at = COALESCE(read_at, NOW())
+WHERE id = $2 AND user_id = $1
+		"#,
+        user_id,
+        notification_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Marks every unread notification of a user as read.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Number of notifications marked as read.
+///
+pub async fn mark_notifications_read<'e, E>(executor: E, user_id: Uuid) -> Result<u64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE notifications
+SET read_at = NOW()
+WHERE user_id = $1 AND read_at IS NULL
+		"#,
+        user_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Retrieves the notification preferences a user changed.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Stored preferences, events without one are emailed.
+///
+pub async fn get_notification_preferences<'e, E>(
+    executor: E,
+    user_id: Uuid,
+) -> Result<Vec<ApiNotificationPreference>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiNotificationPreference,
+        r#"
+SELECT event, email
+FROM notification_preferences
+WHERE user_id = $1
+		"#,
+        user_id,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Sets whether a user gets an event by email.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user.
+/// * `event`: Event the user is notified about.
+/// * `email`: Whether the event is emailed.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_notification_preference<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    event: NotificationEvent,
+    email: bool,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO notification_preferences (user_id, event, email)
+VALUES ($1, $2, $3)
+ON CONFLICT (user_id, event) DO UPDATE SET email = EXCLUDED.email, updated_at = NOW()
+		"#,
+        user_id,
+        event.to_string(),
+        email,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the users an announcement is meant for: every account, or the
+/// users with a server in the datacenter, their own or one of their
+/// organizations'. Deleted accounts are left out.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `datacenter_code`: Datacenter of the announcement, `None` for all users.
+///
+/// # Returns
+///
+/// IDs of the users.
+///
+pub async fn get_announcement_audience<'e, E>(
+    executor: E,
+    datacenter_code: Option<&str>,
+) -> Result<Vec<Uuid>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT usr.id
+FROM users AS usr
+WHERE usr.deleted_at IS NULL
+  AND ($1::TEXT IS NULL OR EXISTS (
+	SELECT 1
+	FROM services AS svc
+	JOIN ip_addresses AS ip ON ip.server_id = svc.server_id
+	JOIN networks AS net ON net.id = ip.network_id
+	WHERE net.datacenter_name = $1
+	  AND (svc.user_id = usr.id
+	   OR svc.organization_id IN (SELECT organization_id FROM organization_members WHERE user_id = usr.id))
+  ))
+		"#,
+        datacenter_code,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Records an action of a user in the audit log.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who performed the action.
+/// * `action`: Performed action.
+/// * `server_id`: UUID of the server the action was performed on, if any.
+/// * `details`: Action specific details.
+///
+/// # Returns
+///
+/// UUID of the audit log entry.
+///
+pub async fn create_audit_entry<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    action: AuditAction,
+    server_id: Option<Uuid>,
+    details: &serde_json::Value,
+) -> Result<Uuid>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let id = sqlx::query_scalar!(
+        r#"
+INSERT INTO audit_log (user_id, action, server_id, details)
+VALUES ($1, $2, $3, $4)
+RETURNING id
+		"#,
+        user_id,
+        action.to_string(),
+        server_id,
+        details,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(id)
+}
+
+/// Retrieves the latest entries of the audit log, newest first.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server to list the entries of, `None` for every
+///   entry.
+/// * `limit`: Maximal number of entries.
+///
+/// # Returns
+///
+/// `Vec<ApiAuditEntry>` of the entries.
+///
+pub async fn get_audit_log<'e, E>(
+    executor: E,
+    server_id: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<ApiAuditEntry>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT id, user_id, action, server_id, details, created_at
+FROM audit_log
+WHERE $1::UUID IS NULL OR server_id = $1
+ORDER BY created_at DESC, id
+LIMIT $2
+		"#,
+        server_id,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|record| {
+            Ok(ApiAuditEntry {
+                id: record.id,
+                user_id: record.user_id,
+                action: record.action.parse()?,
+                server_id: record.server_id,
+                details: record.details,
+                created_at: record.created_at,
+            })
+        })
+        .collect()
+}
+
+/// Retrieves every entry of the audit log recorded for the actions of a user,
+/// oldest first.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// `Vec<ApiAuditEntry>` of the entries.
+///
+pub async fn get_audit_log_for_user<'e, E>(executor: E, user_id: Uuid) -> Result<Vec<ApiAuditEntry>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+SELECT id, user_id, action, server_id, details, created_at
+FROM audit_log
+WHERE user_id = $1
+ORDER BY created_at, id
+		"#,
+        user_id,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    records
+        .into_iter()
+        .map(|record| {
+            Ok(ApiAuditEntry {
+                id: record.id,
+                user_id: record.user_id,
+                action: record.action.parse()?,
+                server_id: record.server_id,
+                details: record.details,
+                created_at: record.created_at,
+            })
+        })
+        .collect()
+}
+
+/// Retrieves the disk limits of the product of a server of an organization of
+/// the user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `DiskLimits` of the server.
+///
+pub async fn get_disk_limits<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<DiskLimits>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        DiskLimits,
+        r#"
+SELECT
+	svc.id AS service_id,
+	p.max_disks,
+	p.max_disk_gb,
+	COALESCE(p.storage, t.storage) AS storage
+FROM servers AS srv
+JOIN services AS svc ON svc.server_id = srv.id
+JOIN products AS p ON p.id = svc.product_id
+JOIN templates AS t ON t.id = svc.template_id
+WHERE srv.id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1)
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Sets the value of a configurable option of a service, replacing the
+/// current one. Nothing is saved if the option doesn't exist.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `service_id`: UUID of the service.
+/// * `option`: Name of the configurable option, like `disk_gb`.
+/// * `value`: New value.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_config_value<'e, E>(
+    executor: E,
+    service_id: Uuid,
+    option: &str,
+    value: &str,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+WITH updated AS (
+	UPDATE config_values AS v SET value = $3
+	FROM config_options AS o
+	WHERE o.id = v.config_id AND v.service_id = $1 AND o.name = $2
+	RETURNING v.id
+)
+INSERT INTO config_values (service_id, config_id, value)
+SELECT $1, o.id, $3
+FROM config_options AS o
+WHERE o.name = $2 AND NOT EXISTS (SELECT 1 FROM updated)
+		"#,
+        service_id,
+        option,
+        value,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves all registered ISOs.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// `Vec<ApiIso>` sorted by name.
+///
+pub async fn get_isos<'e, E>(executor: E) -> Result<Vec<ApiIso>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiIso,
+        r#"
+SELECT id, name, volid, node_name, size_bytes, created_at
+FROM isos
+ORDER BY name
+		"#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves a registered ISO.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `iso_id`: UUID of the ISO.
+///
+/// # Returns
+///
+/// `ApiIso`, `Error::NotFound` if it isn't registered.
+///
+pub async fn get_iso<'e, E>(executor: E, iso_id: Uuid) -> Result<ApiIso>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiIso,
+        r#"
+SELECT id, name, volid, node_name, size_bytes, created_at
+FROM isos
+WHERE id = $1
+		"#,
+        iso_id,
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("ISO {iso_id}")))
+}
+
+/// Registers an ISO.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `payload`: Name and volume of the ISO.
+/// * `node_name`: Node whose local storage holds the ISO, `None` for a shared
+///   storage.
+/// * `size_bytes`: Size of the ISO reported by Proxmox.
+///
+/// # Returns
+///
+/// Registered `ApiIso`, `Error::Conflict` if the name or the volume is
+/// already registered.
+///
+pub async fn create_iso<'e, E>(
+    executor: E,
+    payload: &IsoPayload,
+    node_name: Option<&str>,
+    size_bytes: i64,
+) -> Result<ApiIso>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiIso,
+        r#"
+INSERT INTO isos (name, volid, node_name, size_bytes)
+VALUES ($1, $2, $3, $4)
+RETURNING id, name, volid, node_name, size_bytes, created_at
+		"#,
+        payload.name,
+        payload.volid,
+        node_name,
+        size_bytes,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Conflict(format!("ISO {} is already registered", payload.volid))
+        }
+        _ => error.into(),
+    })
+}
+
+/// Deletes an ISO no server has inserted.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `iso_id`: UUID of the ISO.
+///
+/// # Returns
+///
+/// `true` if the ISO was deleted, `Error::Conflict` if a server has it
+/// inserted.
+///
+pub async fn delete_iso<'e, E>(executor: E, iso_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM isos
+WHERE id = $1
+		"#,
+        iso_id,
+    )
+    .execute(executor)
+    .await
+    .map_err(|error| in_use(error, "ISO"))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the CD-ROM drive of a server managed by a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// `ApiServerIso` with the inserted ISO, if any.
+///
+pub async fn get_server_iso<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ApiServerIso>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT
+	srv.boot_from_iso,
+	srv.iso_device,
+	i.id AS "iso_id?",
+	i.name AS "name?",
+	i.volid AS "volid?",
+	i.node_name,
+	i.size_bytes AS "size_bytes?",
+	i.created_at AS "created_at?"
+FROM servers AS srv
+JOIN services AS svc ON svc.server_id = srv.id
+LEFT JOIN isos AS i ON i.id = srv.iso_id
+WHERE srv.id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1 AND mem.role IN ('Owner', 'Admin'))
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?;
+
+    let iso = match (record.iso_id, record.name, record.volid) {
+        (Some(id), Some(name), Some(volid)) => Some(ApiIso {
+            id,
+            name,
+            volid,
+            node_name: record.node_name,
+            size_bytes: record.size_bytes.unwrap_or_default(),
+            created_at: record.created_at.unwrap_or_default(),
+        }),
+        _ => None,
+    };
+
+    Ok(ApiServerIso {
+        device: iso.as_ref().and(record.iso_device),
+        iso,
+        boot_from_iso: record.boot_from_iso,
+    })
+}
+
+/// Saves the CD-ROM drive of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `iso_id`: UUID of the inserted ISO, `None` if the drive is empty.
+/// * `device`: Device of the drive the ISO is inserted into.
+/// * `boot_from_iso`: Whether the server boots from the ISO.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn set_server_iso<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    iso_id: Option<Uuid>,
+    device: Option<&str>,
+    boot_from_iso: bool,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+UPDATE servers SET iso_id = $2, iso_device = $3, boot_from_iso = $4
+WHERE id = $1
+		"#,
+        server_id,
+        iso_id,
+        device,
+        boot_from_iso,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves all servers that are provisioned on Proxmox, along with their
+/// owners and traffic quotas, for traffic accounting.
+///
+/// # Arguments
+///
+/// * `pool`: Reference to the `PgPool`.
+///
+/// # Returns
+///
+/// `Vec<TrafficServer>` containing every server with a known VM.
+///
+pub async fn get_traffic_servers(pool: &PgPool) -> Result<Vec<TrafficServer>> {
+    Ok(sqlx::query_as!(
+        TrafficServer,
+        r#"
+SELECT
+	svc.user_id,
+	srv.id AS "server_id",
+	srv.host_name,
+	srv.vm_id AS "vm_id!",
+	srv.node_name AS "node_name!",
+	p.traffic_quota_gb,
+	(
+		SELECT MAX(t.sampled_until)
+		FROM server_traffic AS t
+		WHERE t.server_id = srv.id
+	) AS sampled_until
+FROM services AS svc
+JOIN servers AS srv ON srv.id = svc.server_id
+JOIN products AS p ON p.id = svc.product_id
+WHERE srv.vm_id IS NOT NULL AND srv.node_name IS NOT NULL
+		"#
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Adds network traffic to the monthly traffic of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `traffic`: Traffic collected within a month.
+///
+/// # Returns
+///
+/// Traffic of the server in the whole month so far.
+///
+pub async fn add_server_traffic<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    traffic: &ApiTrafficMonth,
+) -> Result<ApiTrafficMonth>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiTrafficMonth,
+        r#"
+INSERT INTO server_traffic (server_id, month, rx_bytes, tx_bytes, sampled_until)
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (server_id, month) DO UPDATE
+SET
+	rx_bytes = server_traffic.rx_bytes + EXCLUDED.rx_bytes,
+	tx_bytes = server_traffic.tx_bytes + EXCLUDED.tx_bytes,
+	sampled_until = GREATEST(server_traffic.sampled_until, EXCLUDED.sampled_until)
+RETURNING month, rx_bytes, tx_bytes, sampled_until
+		"#,
+        server_id,
+        traffic.month,
+        traffic.rx_bytes,
+        traffic.tx_bytes,
+        traffic.sampled_until,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Marks the owner of a server as alerted about its traffic quota of a month.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `month`: First day of the month.
+///
+/// # Returns
+///
+/// `true` if the owner wasn't alerted about the month yet.
+///
+pub async fn mark_traffic_alerted<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    month: NaiveDate,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+UPDATE server_traffic SET quota_alerted_at = NOW()
+WHERE server_id = $1 AND month = $2 AND quota_alerted_at IS NULL
+		"#,
+        server_id,
+        month,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the monthly traffic quota of the product of a server of an
+/// organization of the user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the server.
+/// * `server_id`: UUID of the server.
+///
+/// # Returns
+///
+/// Quota in GB, `None` for unlimited.
+///
+pub async fn get_traffic_quota<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<Option<i32>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT p.traffic_quota_gb
+FROM services AS svc
+JOIN products AS p ON p.id = svc.product_id
+WHERE svc.server_id = $2
+	AND EXISTS (SELECT 1 FROM organization_members AS mem WHERE mem.organization_id = svc.organization_id AND mem.user_id = $1)
+		"#,
+        user_id,
+        server_id,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Retrieves the latest monthly traffic of a server.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `server_id`: UUID of the server.
+/// * `limit`: Maximum number of months.
+///
+/// # Returns
+///
+/// Traffic of the server by month, the latest first.
+///
+pub async fn get_server_traffic<'e, E>(
+    executor: E,
+    server_id: Uuid,
+    limit: i64,
+) -> Result<Vec<ApiTrafficMonth>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiTrafficMonth,
+        r#"
+SELECT month, rx_bytes, tx_bytes, sampled_until
+FROM server_traffic
+WHERE server_id = $1
+ORDER BY month DESC
+LIMIT $2
+		"#,
+        server_id,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Retrieves the networks a user may access the account from, oldest first.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// `Vec<ApiAllowedNetwork>` of the user, empty if the account is unrestricted.
+///
+pub async fn get_allowed_networks<'e, E>(
+    executor: E,
+    user_id: Uuid,
+) -> Result<Vec<ApiAllowedNetwork>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiAllowedNetwork,
+        r#"
+SELECT id, cidr, description, created_at
+FROM user_allowed_networks
+WHERE user_id = $1
+ORDER BY created_at, id
+		"#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Allows a user to access the account from a network.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `cidr`: CIDR block of the network.
+/// * `description`: Optional note on the network.
+///
+/// # Returns
+///
+/// Created `ApiAllowedNetwork`, `Error::Conflict` if the network is already
+/// allowed.
+///
+pub async fn add_allowed_network<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    cidr: &IpNet,
+    description: Option<&str>,
+) -> Result<ApiAllowedNetwork>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ApiAllowedNetwork,
+        r#"
+INSERT INTO user_allowed_networks (user_id, cidr, description)
+VALUES ($1, $2, $3)
+RETURNING id, cidr, description, created_at
+		"#,
+        user_id,
+        cidr.to_string(),
+        description,
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Conflict(format!("Network {cidr} is already allowed"))
+        }
+        _ => error.into(),
+    })
+}
+
+/// Removes a network a user allowed.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `network_id`: UUID of the allowed network.
+///
+/// # Returns
+///
+/// `true` if the network existed and was removed.
+///
+pub async fn delete_allowed_network<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    network_id: Uuid,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM user_allowed_networks
+WHERE id = $1 AND user_id = $2
+		"#,
+        network_id,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the API tokens of a user, oldest first, without the tokens
+/// themselves.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// `Vec<ApiToken>` of the user.
+///
+pub async fn get_api_tokens<'e, E>(executor: E, user_id: Uuid) -> Result<Vec<ApiToken>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiToken,
+        r#"
+SELECT id, name, NULL::TEXT AS secret, created_at, last_used_at
+FROM api_tokens
+WHERE user_id = $1
+ORDER BY created_at, id
+		"#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Creates an API token of a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `name`: Name of the token.
+/// * `token_hash`: Hash of the token, the only form in which it is stored.
+///
+/// # Returns
+///
+/// Created `ApiToken`, without the token itself.
+///
+pub async fn create_api_token<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    name: &str,
+    token_hash: &str,
+) -> Result<ApiToken>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiToken,
+        r#"
+INSERT INTO api_tokens (user_id, name, token_hash)
+VALUES ($1, $2, $3)
+RETURNING id, name, NULL::TEXT AS secret, created_at, last_used_at
+		"#,
+        user_id,
+        name,
+        token_hash,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Deletes an API token of a user, so it can't be used any more.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user who owns the token.
+/// * `token_id`: UUID of the token.
+///
+/// # Returns
+///
+/// `true` if the token existed and was deleted.
+///
+pub async fn delete_api_token<'e, E>(executor: E, user_id: Uuid, token_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM api_tokens
+WHERE id = $1 AND user_id = $2
+		"#,
+        token_id,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Finds the owner of an API token and records that the token was used.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `token_hash`: Hash of the token.
+/// * `used_at`: Time of the request authorized with the token.
+///
+/// # Returns
+///
+/// UUID of the user who owns the token, `None` if the token is unknown.
+///
+pub async fn use_api_token<'e, E>(
+    executor: E,
+    token_hash: &str,
+    used_at: DateTime<Utc>,
+) -> Result<Option<Uuid>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+UPDATE api_tokens
+SET last_used_at = $2
+WHERE token_hash = $1
+RETURNING user_id
+		"#,
+        token_hash,
+        used_at
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Records an authentication attempt, pruning the attempts that left the
+/// counting window.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `kind`: Kind of the attempt.
+/// * `email`: Email address of the attempt, if any.
+/// * `ip`: Address of the client, if known.
+/// * `since`: Start of the counting window.
+///
+pub async fn add_auth_attempt<'e, E>(
+    executor: E,
+    kind: AuthAttemptKind,
+    email: Option<&str>,
+    ip: Option<&str>,
+    since: DateTime<Utc>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+WITH pruned AS (
+	DELETE FROM auth_attempts
+	WHERE created_at < $4
+)
+INSERT INTO auth_attempts (kind, email, ip)
+VALUES ($1, $2, $3)
+		"#,
+        kind.to_string(),
+        email,
+        ip,
+        since,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Counts the authentication attempts of a kind with the email address or
+/// from the client address since the start of the counting window.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `kind`: Kind of the attempts.
+/// * `email`: Email address of the attempts, `None` to count by address only.
+/// * `ip`: Address of the client, `None` to count by email address only.
+/// * `since`: Start of the counting window.
+///
+/// # Returns
+///
+/// Number of matching attempts.
+///
+pub async fn count_auth_attempts<'e, E>(
+    executor: E,
+    kind: AuthAttemptKind,
+    email: Option<&str>,
+    ip: Option<&str>,
+    since: DateTime<Utc>,
+) -> Result<i64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT COUNT(*) AS "count!"
+FROM auth_attempts
+WHERE kind = $1
+	AND created_at >= $4
+	AND (email = $2 OR ip = $3)
+		"#,
+        kind.to_string(),
+        email,
+        ip,
+        since,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Stores a login started with an OpenID Connect provider, pruning the expired
+/// ones.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `state`: Random state the provider passes back to the callback.
+/// * `provider`: Name of the provider.
+/// * `nonce`: Random nonce the ID token must contain.
+/// * `since`: Start time of the logins that haven't expired yet.
+///
+pub async fn add_oidc_login<'e, E>(
+    executor: E,
+    state: &str,
+    provider: &str,
+    nonce: &str,
+    since: DateTime<Utc>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+WITH pruned AS (
+	DELETE FROM oidc_logins
+	WHERE created_at < $4
+)
+INSERT INTO oidc_logins (state, provider, nonce)
+VALUES ($1, $2, $3)
+		"#,
+        state,
+        provider,
+        nonce,
+        since,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a started OpenID Connect login by its state, so the state can be
+/// used only once.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `state`: State passed back to the callback.
+/// * `since`: Start time of the logins that haven't expired yet.
+///
+/// # Returns
+///
+/// The login, `None` if the state is unknown or expired.
+///
+pub async fn take_oidc_login<'e, E>(
+    executor: E,
+    state: &str,
+    since: DateTime<Utc>,
+) -> Result<Option<OidcLogin>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        OidcLogin,
+        r#"
+DELETE FROM oidc_logins
+WHERE state = $1
+RETURNING provider, nonce, created_at
+		"#,
+        state,
+    )
+    .fetch_optional(executor)
+    .await?
+    .filter(|login| login.created_at >= since))
+}
+
+/// Retrieves the user an OpenID Connect account is linked to.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `provider`: Name of the provider.
+/// * `subject`: ID of the account at the provider.
+///
+/// # Returns
+///
+/// ID of the user, `None` if the account isn't linked to an active user.
+///
+pub async fn get_identity_user<'e, E>(
+    executor: E,
+    provider: &str,
+    subject: &str,
+) -> Result<Option<Uuid>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT usr.id
+FROM user_identities AS idt
+JOIN users AS usr ON usr.id = idt.user_id
+WHERE idt.provider = $1
+	AND idt.subject = $2
+	AND usr.deleted_at IS NULL
+		"#,
+        provider,
+        subject,
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Retrieves the active user with an email address, ignoring the case.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `email`: Email address of the user.
+///
+/// # Returns
+///
+/// ID of the user and whether the email address is verified, `None` if no
+/// user has the address.
+///
+pub async fn get_user_verification_by_email<'e, E>(
+    executor: E,
+    email: &str,
+) -> Result<Option<(Uuid, bool)>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+SELECT id, email_verified_at IS NOT NULL AS "verified!"
+FROM users
+WHERE LOWER(email) = LOWER($1)
+	AND deleted_at IS NULL
+		"#,
+        email,
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record.map(|record| (record.id, record.verified)))
+}
+
+/// Links an OpenID Connect account to a user.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+/// * `provider`: Name of the provider.
+/// * `subject`: ID of the account at the provider.
+/// * `email`: Email address of the account at the provider.
+///
+/// # Errors
+///
+/// `Error::Conflict` if the account is already linked.
+///
+pub async fn add_user_identity<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    provider: &str,
+    subject: &str,
+    email: &str,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+INSERT INTO user_identities (user_id, provider, subject, email)
+VALUES ($1, $2, $3, $4)
+		"#,
+        user_id,
+        provider,
+        subject,
+        email,
+    )
+    .execute(executor)
+    .await
+    .map_err(|error| match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            Error::Conflict(format!("The {provider} account is already linked"))
+        }
+        _ => error.into(),
+    })?;
+
+    Ok(())
+}
+
+/// Retrieves the SAML single sign-on of an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+///
+/// # Returns
+///
+/// The settings, `None` if the organization has no single sign-on.
+///
+pub async fn get_saml_settings<'e, E>(
+    executor: E,
+    organization_id: Uuid,
+) -> Result<Option<ApiSamlSettings>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiSamlSettings,
+        r#"
+SELECT
+	organization_id,
+	idp_metadata,
+	email_attribute,
+	role_attribute,
+	admin_values,
+	enforced,
+	updated_at
+FROM organization_saml
+WHERE organization_id = $1
+		"#,
+        organization_id,
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Sets up or changes the SAML single sign-on of an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+/// * `payload`: Settings of the single sign-on.
+///
+/// # Returns
+///
+/// The stored settings.
+///
+pub async fn set_saml_settings<'e, E>(
+    executor: E,
+    organization_id: Uuid,
+    payload: &SamlSettingsPayload,
+) -> Result<ApiSamlSettings>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_as!(
+        ApiSamlSettings,
+        r#"
+INSERT INTO organization_saml
+	(organization_id, idp_metadata, email_attribute, role_attribute, admin_values, enforced)
+VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (organization_id) DO UPDATE
+SET idp_metadata    = EXCLUDED.idp_metadata,
+    email_attribute = EXCLUDED.email_attribute,
+    role_attribute  = EXCLUDED.role_attribute,
+    admin_values    = EXCLUDED.admin_values,
+    enforced        = EXCLUDED.enforced,
+    updated_at      = NOW()
+RETURNING
+	organization_id,
+	idp_metadata,
+	email_attribute,
+	role_attribute,
+	admin_values,
+	enforced,
+	updated_at
+		"#,
+        organization_id,
+        payload.idp_metadata,
+        payload.email_attribute,
+        payload.role_attribute,
+        &payload.admin_values,
+        payload.enforced,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Removes the SAML single sign-on of an organization.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `organization_id`: UUID of the organization.
+///
+/// # Returns
+///
+/// `false` if the organization had no single sign-on.
+///
+pub async fn delete_saml_settings<'e, E>(executor: E, organization_id: Uuid) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM organization_saml
+WHERE organization_id = $1
+		"#,
+        organization_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Stores an authentication request sent to the identity provider of an
+/// organization, pruning the expired ones.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `request_id`: ID of the authentication request.
+/// * `organization_id`: UUID of the organization.
+/// * `since`: Send time of the requests that haven't expired yet.
+///
+pub async fn add_saml_request<'e, E>(
+    executor: E,
+    request_id: &str,
+    organization_id: Uuid,
+    since: DateTime<Utc>,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+WITH pruned AS (
+	DELETE FROM saml_requests
+	WHERE created_at < $3
+)
+INSERT INTO saml_requests (id, organization_id)
+VALUES ($1, $2)
+		"#,
+        request_id,
+        organization_id,
+        since,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes an authentication request of an organization, so it can be
+/// responded to only once.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `request_id`: ID of the authentication request.
+/// * `organization_id`: UUID of the organization.
+/// * `since`: Send time of the requests that haven't expired yet.
+///
+/// # Returns
+///
+/// `false` if the request is unknown or expired.
+///
+pub async fn take_saml_request<'e, E>(
+    executor: E,
+    request_id: &str,
+    organization_id: Uuid,
+    since: DateTime<Utc>,
+) -> Result<bool>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+DELETE FROM saml_requests
+WHERE id = $1
+	AND organization_id = $2
+	AND created_at >= $3
+		"#,
+        request_id,
+        organization_id,
+        since,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves an organization that requires the user to log in with single
+/// sign-on. Owners are exempt, so they can't be locked out by a broken
+/// identity provider.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user.
+///
+/// # Returns
+///
+/// Name of the organization, `None` if the user may log in otherwise.
+///
+pub async fn get_enforced_sso<'e, E>(executor: E, user_id: Uuid) -> Result<Option<String>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT org.name
+FROM organization_saml AS sso
+JOIN organizations AS org ON org.id = sso.organization_id
+JOIN organization_members AS mem ON mem.organization_id = sso.organization_id
+WHERE sso.enforced
+	AND mem.user_id = $1
+	AND mem.role <> $2
+ORDER BY org.name
+LIMIT 1
+		"#,
+        user_id,
+        OrganizationRole::Owner.to_string(),
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Appends an event to the log of the domain. Should be called within the
+/// transaction making the change, so the event is recorded exactly when the
+/// change is committed.
+///
+/// The transactions aren't serialized, so an event may be committed after the
+/// ones following it in the log. The consumers wait for such gaps to close, see
+/// `get_settled_domain_events_after`.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: UUID of the user the changed resource belongs to.
+/// * `kind`: Kind of the change.
+/// * `data`: Data of the event.
+///
+/// # Returns
+///
+/// Position of the event in the log.
+///
+pub async fn record_domain_event<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    kind: DomainEventKind,
+    data: &serde_json::Value,
+) -> Result<i64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+INSERT INTO domain_events (kind, user_id, data)
+VALUES ($1, $2, $3)
+RETURNING id
+		"#,
+        kind.to_string(),
+        user_id,
+        data,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Locks the cursor of a consumer of the domain events, created at the start
+/// of the log if missing, so a single replica handles the events at once.
+///
+/// # Arguments
+///
+/// * `transaction`: Mutable reference to a `PgTransaction`.
+/// * `consumer`: Name of the consumer.
+///
+/// # Returns
+///
+/// Last event handled by the consumer, `None` if another replica holds the
+/// cursor.
+///
+pub async fn lock_event_cursor(
+    transaction: &mut PgTransaction<'_>,
+    consumer: &str,
+) -> Result<Option<i64>> {
+    sqlx::query!(
+        r#"
+INSERT INTO domain_event_cursors (consumer)
+VALUES ($1)
+ON CONFLICT DO NOTHING
+		"#,
+        consumer,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT last_event_id FROM domain_event_cursors
+WHERE consumer = $1
+FOR UPDATE SKIP LOCKED
+		"#,
+        consumer,
+    )
+    .fetch_optional(&mut **transaction)
+    .await?)
+}
+
+/// Moves the cursor of a consumer of the domain events, clearing the failed
+/// attempts of the events it has handled since.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `consumer`: Name of the consumer.
+/// * `last_event_id`: Last event handled by the consumer.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn advance_event_cursor<'e, E>(
+    executor: E,
+    consumer: &str,
+    last_event_id: i64,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+WITH cleared AS (
+	DELETE FROM domain_event_failures
+	WHERE consumer = $1 AND event_id <= $2 AND dead_at IS NULL
+)
+UPDATE domain_event_cursors SET last_event_id = $2, updated_at = NOW()
+WHERE consumer = $1
+		"#,
+        consumer,
+        last_event_id,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the domain events following a position of the log.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `after_id`: Position the events follow.
+/// * `until_id`: Last position of the events, up to the end of the log if
+///   `None`.
+/// * `user_id`: Optional user the events belong to, the events of every user
+///   if `None`.
+/// * `limit`: Maximum number of events.
+///
+/// # Returns
+///
+/// `Vec<DomainEvent>` in the order of the log.
+///
+pub async fn get_domain_events_after<'e, E>(
+    executor: E,
+    after_id: i64,
+    until_id: Option<i64>,
+    user_id: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<DomainEvent>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+SELECT id, kind, user_id, data, created_at
+FROM domain_events
+WHERE id > $1
+	AND ($2::BIGINT IS NULL OR id <= $2)
+	AND ($3::UUID IS NULL OR user_id = $3)
+ORDER BY id
+LIMIT $4
+		"#,
+        after_id,
+        until_id,
+        user_id,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Ok(DomainEvent {
+            id: row.id,
+            kind: row.kind.parse()?,
+            user_id: row.user_id,
+            data: row.data,
+            created_at: row.created_at,
+        })
+    })
+    .collect()
+}
+
+/// Retrieves the domain events following a position of the log, up to the
+/// first gap that may still be filled.
+///
+/// The IDs are taken before the recording transactions commit, so a missing ID
+/// is either an event yet to be committed or one rolled back. A gap is only
+/// passed once the event following it is older than `gap_wait_ms`, so a
+/// consumer doesn't move its cursor past an event committed late.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `after_id`: Position the events follow.
+/// * `gap_wait_ms`: Time a gap may stay open before it is passed.
+/// * `limit`: Maximum number of events.
+///
+/// # Returns
+///
+/// `Vec<DomainEvent>` in the order of the log.
+///
+pub async fn get_settled_domain_events_after<'e, E>(
+    executor: E,
+    after_id: i64,
+    gap_wait_ms: i64,
+    limit: i64,
+) -> Result<Vec<DomainEvent>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+WITH events AS (
+	SELECT id, kind, user_id, data, created_at,
+		   id - LAG(id, 1, $1) OVER (ORDER BY id) > 1
+			   AND created_at > clock_timestamp() - $2::BIGINT * INTERVAL '1 millisecond' AS open_gap
+	FROM domain_events
+	WHERE id > $1
+	ORDER BY id
+	LIMIT $3
+)
+SELECT id AS "id!", kind AS "kind!", user_id AS "user_id!", data AS "data!",
+	   created_at AS "created_at!"
+FROM events
+WHERE NOT EXISTS (SELECT 1 FROM events AS gap WHERE gap.open_gap AND gap.id <= events.id)
+ORDER BY id
+		"#,
+        after_id,
+        gap_wait_ms,
+        limit,
+    )
+    .fetch_all(executor)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Ok(DomainEvent {
+            id: row.id,
+            kind: row.kind.parse()?,
+            user_id: row.user_id,
+            data: row.data,
+            created_at: row.created_at,
+        })
+    })
+    .collect()
+}
+
+/// Records a failed attempt of a consumer to handle a domain event. Once the
+/// event has failed `max_attempts` times, the consumer gives up on it.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `consumer`: Name of the consumer.
+/// * `event_id`: ID of the failed event.
+/// * `error`: Error of the attempt.
+/// * `max_attempts`: Number of attempts before the event is given up.
+///
+/// # Returns
+///
+/// Number of the failed attempts so far.
+///
+pub async fn record_domain_event_failure<'e, E>(
+    executor: E,
+    consumer: &str,
+    event_id: i64,
+    error: &str,
+    max_attempts: i32,
+) -> Result<i32>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+INSERT INTO domain_event_failures (consumer, event_id, error, dead_at)
+VALUES ($1, $2, $3, CASE WHEN $4 <= 1 THEN NOW() END)
+ON CONFLICT (consumer, event_id) DO UPDATE
+SET attempts   = domain_event_failures.attempts + 1,
+	error      = EXCLUDED.error,
+	dead_at    = CASE WHEN domain_event_failures.attempts + 1 >= $4 THEN NOW() END,
+	updated_at = NOW()
+RETURNING attempts
+		"#,
+        consumer,
+        event_id,
+        error,
+        max_attempts,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Returns the position of the last domain event, 0 for an empty log.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+///
+/// # Returns
+///
+/// ID of the last event.
+///
+pub async fn get_last_domain_event_id<'e, E>(executor: E) -> Result<i64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query_scalar!(
+        r#"
+SELECT COALESCE(MAX(id), 0) AS "id!" FROM domain_events
+		"#,
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Marks the domain events every consumer has handled as published. Until
+/// each consumer has a cursor, nothing is marked.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `consumers`: Names of all the consumers of the log.
+///
+/// # Returns
+///
+/// Number of the newly published events.
+///
+pub async fn mark_domain_events_published<'e, E>(executor: E, consumers: &[String]) -> Result<u64>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    Ok(sqlx::query!(
+        r#"
+UPDATE domain_events SET published_at = NOW()
+WHERE published_at IS NULL
+	AND id <= (SELECT CASE
+						  WHEN COUNT(*) = CARDINALITY($1::TEXT[]) THEN MIN(last_event_id)
+						  ELSE 0
+					  END
+			   FROM domain_event_cursors
+			   WHERE consumer = ANY ($1))
+		"#,
+        consumers,
+    )
+    .execute(executor)
+    .await?
+    .rows_affected())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::model::types::Server;
+    use crate::web::types::NewServerPayload;
+    use secrecy::ExposeSecret;
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn update_password_should_works(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let new_password = "new_secure_password";
+        let new_hash = bcrypt::hash(new_password, 10).unwrap();
+        // Act
+        update_password_hash(&pool, &user.id, &new_hash)
+            .await
+            .unwrap();
+        // Assert
+        let new_user = get_user_by_email(&pool, &user.email).await.unwrap();
+        assert!(bcrypt::verify(new_password, new_user.password.expose_secret()).is_ok());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn add_new_user_should_works(pool: PgPool) {
+        // Arrange
+        let test_user = payload::test_user();
+        // Act
+        let new_user = add_new_user(&pool, test_user.clone()).await.unwrap();
+        // Assert
+        assert_eq!(new_user.email, test_user.email);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn get_user_by_id_should_works(pool: PgPool) {
+        // Arrange
+        let test_user = payload::test_user();
+        let new_user = add_new_user(&pool, test_user.clone()).await.unwrap();
+        // Act
+        let found_user = get_user_by_id(&pool, new_user.id).await.unwrap();
+        // Assert
+        assert_eq!(found_user.id, new_user.id);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn get_user_by_email_should_works(pool: PgPool) {
+        // Arrange
+        let test_user = payload::test_user();
+        let new_user = add_new_user(&pool, test_user.clone()).await.unwrap();
+        // Act
+        let found_user = get_user_by_email(&pool, &new_user.email).await.unwrap();
+        // Assert
+        assert_eq!(found_user.email, new_user.email);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn audit_log_should_reject_unknown_actions(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let details = serde_json::json!({});
+        create_audit_entry(
+            &pool,
+            user.id,
+            AuditAction::ServerTransferred,
+            None,
+            &details,
+        )
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO audit_log (user_id, action, details) VALUES ($1, 'ServerRenamed', '{}')",
+            user.id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Act
+        let result = get_audit_log_for_user(&pool, user.id).await;
+
+        // Assert
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn email_change_should_be_taken_only_before_expiry(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::minutes(10);
+        create_email_change(&pool, user.id, "new@example.com", "hash", expires_at)
+            .await
+            .unwrap();
+
+        // Act
+        let wrong_token = take_email_change(&pool, user.id, "other", now)
+            .await
+            .unwrap();
+        let expired = take_email_change(&pool, user.id, "hash", expires_at)
+            .await
+            .unwrap();
+        let taken = take_email_change(&pool, user.id, "hash", now)
+            .await
+            .unwrap();
+        let taken_again = take_email_change(&pool, user.id, "hash", now)
+            .await
+            .unwrap();
+
+        // Assert
+        assert!(wrong_token.is_none());
+        assert!(expired.is_none());
+        assert_eq!(taken.as_deref(), Some("new@example.com"));
+        assert!(taken_again.is_none());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn email_should_be_verified_only_before_expiry(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::minutes(10);
+        create_email_verification(&pool, user.id, "hash", expires_at)
+            .await
+            .unwrap();
+        let verified_before = is_email_verified(&pool, user.id).await.unwrap();
+
+        // Act
+        let expired = verify_user_email(&pool, "hash", expires_at).await.unwrap();
+        let verified = verify_user_email(&pool, "hash", now).await.unwrap();
+
+        // Assert
+        assert!(!verified_before);
+        assert!(expired.is_none());
+        assert_eq!(verified, Some(user.id));
+        assert!(is_email_verified(&pool, user.id).await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn get_servers_for_user_should_works(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        let network_id = helpers::test_network_id(&mut tx).await;
+        helpers::test_ip_id(&mut tx, Some(server_id), network_id).await;
+        tx.commit().await.unwrap();
+
+        // Act
+        let servers = get_servers_for_user(&pool, user.id).await.unwrap();
+
+        // Assert
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].server_id, server_id);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn create_server_record_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let payload = payload::test_server(None);
+
+        // Act
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+
+        // Assert
+        let server = helpers::test_get_server(&mut tx, server_id).await.unwrap();
+        assert_eq!(server.id, server_id);
+        assert_eq!(server.host_name, payload.host_name);
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn reserve_ip_for_server_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let network_id = helpers::test_network_id(&mut tx).await;
+        let ip_id = helpers::test_ip_id(&mut tx, None, network_id).await;
+
+        // Act
+        let ip_config = reserve_ip_for_server(&mut tx, server_id, &payload.datacenter)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(ip_config.ip_address, "10.0.0.101");
+        let ip_server_id = helpers::test_get_server_id_from_ip(&mut tx, ip_id).await;
+        assert_eq!(ip_server_id, Some(server_id));
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn create_service_record_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+
+        // Act
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        let service = sqlx::query!("SELECT * FROM services WHERE id = $1", service_id)
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(service.user_id, user.id);
+        assert_eq!(service.server_id, server_id);
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn save_config_values_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        let cpu_option_id = helpers::test_config_option(&mut tx, "cpu_cores").await;
+        let ram_option_id = helpers::test_config_option(&mut tx, "ram_gb").await;
+
+        // Act
+        save_config_values(&mut tx, service_id, &payload)
+            .await
+            .unwrap();
+
+        // Assert
+        let cpu_value = helpers::test_config_value(&mut tx, service_id, cpu_option_id).await;
+        assert_eq!(cpu_value, payload.cpu_cores.unwrap().to_string());
+        let ram_value = helpers::test_config_value(&mut tx, service_id, ram_option_id).await;
+        assert_eq!(ram_value, payload.ram_gb.unwrap().to_string());
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn set_config_value_should_replace_value(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        let disk_option_id =
+            sqlx::query_scalar!("SELECT id FROM config_options WHERE name = 'disk_gb'")
+                .fetch_one(tx.as_mut())
+                .await
+                .unwrap();
+
+        // Act
+        set_config_value(tx.as_mut(), service_id, "disk_gb", "20")
+            .await
+            .unwrap();
+        set_config_value(tx.as_mut(), service_id, "disk_gb", "30")
+            .await
+            .unwrap();
+        set_config_value(tx.as_mut(), service_id, "missing", "1")
+            .await
+            .unwrap();
+
+        // Assert
+        let disk_value = helpers::test_config_value(&mut tx, service_id, disk_option_id).await;
+        assert_eq!(disk_value, "30");
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn save_custom_values_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+
+        let os_field_id = helpers::test_custom_option(&mut tx, "os", product_id).await;
+        let dc_field_id = helpers::test_custom_option(&mut tx, "datacenter", product_id).await;
+
+        // Act
+        save_custom_values(&mut tx, service_id, &payload)
+            .await
+            .unwrap();
+
+        // Assert
+        let os_value = helpers::test_custom_value(&mut tx, service_id, os_field_id).await;
+        assert_eq!(os_value, payload.os);
+        let dc_value = helpers::test_custom_value(&mut tx, service_id, dc_field_id).await;
+        assert_eq!(dc_value, payload.datacenter);
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn update_initial_server_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let payload = payload::test_server(None);
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let vm_ref = VmRef::new("test-node", 123);
+
+        // Act
+        update_initial_server(&mut tx, server_id, vm_ref.clone())
+            .await
+            .unwrap();
+
+        // Assert
+        let server = helpers::test_get_server(&mut tx, server_id).await.unwrap();
+        assert_eq!(server.vm_id, Some(vm_ref.id));
+        assert_eq!(server.node_name, Some(vm_ref.node));
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn find_template_id_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let expected_id = helpers::test_template_id(&mut tx).await;
+
+        // Act
+        let found_id = find_template_id(&mut tx, "os", TemplateKind::Os)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(found_id, expected_id);
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn find_template_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+
+        // Act
+        let vm_ref = find_template(&mut tx, service_id).await.unwrap();
+
+        // Assert
+        assert_eq!(vm_ref.node, "node");
+        assert_eq!(vm_ref.id, 1);
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn update_service_status_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        let new_status = ServiceStatus::Active;
+
+        // Act
+        update_service_status(&mut *tx, service_id, new_status.clone())
+            .await
+            .unwrap();
+
+        // Assert
+        let service = sqlx::query!("SELECT status FROM services WHERE id = $1", service_id)
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        assert_eq!(service.status, new_status.to_string());
+        tx.commit().await.unwrap();
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::model::types::Server;
-    use crate::web::types::NewServerPayload;
-    use secrecy::ExposeSecret;
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn update_server_status_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let payload = payload::test_server(None);
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let new_status = ServerStatus::Stopped;
+
+        // Act
+        update_server_status(&mut *tx, server_id, new_status)
+            .await
+            .unwrap();
+
+        // Assert
+        let server = sqlx::query!("SELECT status FROM servers WHERE id = $1", server_id)
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        assert_eq!(server.status, new_status.to_string());
+        tx.commit().await.unwrap();
+    }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn update_password_should_works(pool: PgPool) {
+    async fn get_server_by_id_should_works(pool: PgPool) {
         // Arrange
         let user = add_new_user(&pool, payload::test_user()).await.unwrap();
-        let new_password = "new_secure_password";
-        let new_hash = bcrypt::hash(&new_password, 10).unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        let network_id = helpers::test_network_id(&mut tx).await;
+        helpers::test_ip_id(&mut tx, Some(server_id), network_id).await;
+        tx.commit().await.unwrap();
+
         // Act
-        update_password_hash(&pool, &user.id, &new_hash)
+        let server = get_server_by_id(&pool, user.id, server_id).await.unwrap();
+
+        // Assert
+        assert_eq!(server.server_id, server_id);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn delete_server_record_should_works(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let payload = payload::test_server(None);
+        let server_id = create_server_record(&mut tx, &payload.host_name)
             .await
             .unwrap();
+        let network_id = helpers::test_network_id(&mut tx).await;
+        let ip_id = helpers::test_ip_id(&mut tx, Some(server_id), network_id).await;
+
+        // Act
+        delete_server_record(&mut tx, server_id).await.unwrap();
+
         // Assert
-        let new_user = get_user_by_email(&pool, &user.email).await.unwrap();
-        assert!(bcrypt::verify(&new_password, &new_user.password.expose_secret()).is_ok());
+        let server = helpers::test_get_server(&mut tx, server_id).await;
+        assert!(server.is_none());
+        let ip_server_id = helpers::test_get_server_id_from_ip(&mut tx, ip_id).await;
+        assert!(ip_server_id.is_none());
+        tx.commit().await.unwrap();
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn add_new_user_should_works(pool: PgPool) {
+    async fn get_server_proxmox_ref_should_works(pool: PgPool) {
         // Arrange
-        let test_user = payload::test_user();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+
+        let vm_ref = VmRef::new("test-node", 42);
+        update_initial_server(&mut tx, server_id, vm_ref.clone())
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
         // Act
-        let new_user = add_new_user(&pool, test_user.clone()).await.unwrap();
+        let found_ref = get_server_proxmox_ref(&pool, user.id, server_id)
+            .await
+            .unwrap();
+
         // Assert
-        assert_eq!(new_user.email, test_user.email);
+        assert_eq!(found_ref.id, vm_ref.id);
+        assert_eq!(found_ref.node, vm_ref.node);
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn get_user_by_id_should_works(pool: PgPool) {
+    async fn settle_invoice_should_works(pool: PgPool) {
         // Arrange
-        let test_user = payload::test_user();
-        let new_user = add_new_user(&pool, test_user.clone()).await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let invoice_id = create_invoice(&pool, payload::test_invoice(user.id))
+            .await
+            .unwrap();
+        create_payment_record(&pool, invoice_id, "stripe", "cs_1", 1500)
+            .await
+            .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+
         // Act
-        let found_user = get_user_by_id(&pool, new_user.id).await.unwrap();
+        settle_invoice(&mut tx, invoice_id, "stripe", "cs_1")
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
         // Assert
-        assert_eq!(found_user.id, new_user.id);
+        let invoice = get_invoice_by_id(&pool, user.id, invoice_id).await.unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::Paid);
+        assert!(invoice.paid_at.is_some());
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn get_user_by_email_should_works(pool: PgPool) {
+    async fn record_payment_event_should_be_idempotent(pool: PgPool) {
         // Arrange
-        let test_user = payload::test_user();
-        let new_user = add_new_user(&pool, test_user.clone()).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+
         // Act
-        let found_user = get_user_by_email(&pool, &new_user.email).await.unwrap();
+        let first = record_payment_event(&mut tx, "stripe", "evt_1", "checkout.session.completed")
+            .await
+            .unwrap();
+        let second = record_payment_event(&mut tx, "stripe", "evt_1", "checkout.session.completed")
+            .await
+            .unwrap();
+
         // Assert
-        assert_eq!(found_user.email, new_user.email);
+        assert!(first);
+        assert!(!second);
+        tx.commit().await.unwrap();
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn get_servers_for_user_should_works(pool: PgPool) {
+    async fn create_credit_should_ignore_repeated_promo_redemption(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let promo_code = create_promo_code(&pool, payload::test_promo_code())
+            .await
+            .unwrap();
+        let credit = NewCredit {
+            user_id: user.id,
+            amount_cents: promo_code.amount_cents,
+            reason: "Promo".to_owned(),
+            promo_code_id: Some(promo_code.id),
+            invoice_id: None,
+            issued_by: None,
+        };
+
+        // Act
+        let first = create_credit(&pool, credit.clone()).await.unwrap();
+        let second = create_credit(&pool, credit).await.unwrap();
+
+        // Assert
+        assert!(first.is_some());
+        assert!(second.is_none());
+        let balance = get_credit_balance(&pool, user.id).await.unwrap();
+        assert_eq!(balance, promo_code.amount_cents);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn apply_credit_to_invoice_should_settle_covered_invoice(pool: PgPool) {
         // Arrange
         let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let invoice_id = create_invoice(&pool, payload::test_invoice(user.id))
+            .await
+            .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+
+        // Act
+        let partial = apply_credit_to_invoice(&mut tx, invoice_id, 1000)
+            .await
+            .unwrap();
+        let covered = apply_credit_to_invoice(&mut tx, invoice_id, 500)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        // Assert
+        assert_eq!(partial.status, InvoiceStatus::Unpaid);
+        assert_eq!(partial.amount_due(), 500);
+        assert!(partial.paid_at.is_none());
+        assert_eq!(covered.status, InvoiceStatus::Paid);
+        assert_eq!(covered.amount_due(), 0);
+        assert!(covered.paid_at.is_some());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn get_node_capacity_should_sum_allocations(pool: PgPool) {
+        // Arrange
         let mut tx = pool.begin().await.unwrap();
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
         let product_id = helpers::test_product(&mut tx).await;
         let payload = payload::test_server(Some(product_id));
         let server_id = create_server_record(&mut tx, &payload.host_name)
             .await
             .unwrap();
         let template_id = helpers::test_template_id(&mut tx).await;
-        create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        helpers::test_config_option(&mut tx, "cpu_cores").await;
+        helpers::test_config_option(&mut tx, "ram_gb").await;
+        save_config_values(&mut tx, service_id, &payload)
+            .await
+            .unwrap();
+        update_initial_server(&mut tx, server_id, VmRef::new("pve-1", 100))
+            .await
+            .unwrap();
+        helpers::test_node(&mut tx, "pve-1").await;
+
+        // Act
+        let capacity = get_node_capacity(tx.as_mut(), "pve-1").await.unwrap();
+        let missing = get_node_capacity(tx.as_mut(), "pve-2").await.unwrap();
+
+        // Assert
+        let capacity = capacity.unwrap();
+        assert_eq!(
+            capacity.allocated_cpu_cores,
+            payload.cpu_cores.unwrap() as i64
+        );
+        assert_eq!(capacity.allocated_ram_gb, payload.ram_gb.unwrap() as i64);
+        assert_eq!(capacity.cpu_overcommit, Some(4.0));
+        assert_eq!(capacity.ram_overcommit, None);
+        assert!(missing.is_none());
+        tx.commit().await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn get_quota_usage_should_sum_resources(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
+        let server_id = create_server_record(&mut tx, &payload.host_name)
+            .await
+            .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        helpers::test_config_option(&mut tx, "cpu_cores").await;
+        helpers::test_config_option(&mut tx, "ram_gb").await;
+        save_config_values(&mut tx, service_id, &payload)
             .await
             .unwrap();
         let network_id = helpers::test_network_id(&mut tx).await;
@@ -887,208 +9943,399 @@ mod tests {
         tx.commit().await.unwrap();
 
         // Act
-        let servers = get_servers_for_user(&pool, user.id).await.unwrap();
+        let account = get_quota_usage(&pool, user.id, None).await.unwrap();
+        let product = get_quota_usage(&pool, user.id, Some(product_id))
+            .await
+            .unwrap();
+        let other = get_quota_usage(&pool, user.id, Some(Uuid::new_v4()))
+            .await
+            .unwrap();
 
         // Assert
-        assert_eq!(servers.len(), 1);
-        assert_eq!(servers[0].server_id, server_id);
+        let expected = QuotaUsage {
+            servers: 1,
+            cpu_cores: payload.cpu_cores.unwrap() as i64,
+            ram_gb: payload.ram_gb.unwrap() as i64,
+            ips: 1,
+        };
+        assert_eq!(account, expected);
+        assert_eq!(product, expected);
+        assert_eq!(other, QuotaUsage::default());
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn create_server_record_should_works(pool: PgPool) {
+    async fn set_account_quota_should_replace_limits(pool: PgPool) {
         // Arrange
-        let mut tx = pool.begin().await.unwrap();
-        let payload = payload::test_server(None);
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let first = QuotaLimits {
+            max_servers: Some(1),
+            ..QuotaLimits::default()
+        };
+        let second = QuotaLimits {
+            max_ips: Some(2),
+            ..QuotaLimits::default()
+        };
 
         // Act
+        set_account_quota(&pool, user.id, &first).await.unwrap();
+        set_account_quota(&pool, user.id, &second).await.unwrap();
+
+        // Assert
+        let limits = get_account_quota(&pool, user.id).await.unwrap();
+        assert_eq!(limits, Some(second));
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn get_usage_for_user_should_aggregate_samples(pool: PgPool) {
+        // Arrange
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let product_id = helpers::test_product(&mut tx).await;
+        let payload = payload::test_server(Some(product_id));
         let server_id = create_server_record(&mut tx, &payload.host_name)
             .await
             .unwrap();
+        let template_id = helpers::test_template_id(&mut tx).await;
+        create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+            .await
+            .unwrap();
+        update_initial_server(&mut tx, server_id, VmRef::new("pve", 100))
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        let server = get_metered_servers(&pool).await.unwrap().remove(0);
+        for (uptime_hours, cpu_usage) in [(1.0, Some(0.2)), (0.5, Some(0.4)), (0.0, None)] {
+            create_usage_record(
+                &pool,
+                NewUsageRecord {
+                    user_id: server.user_id,
+                    service_id: server.service_id,
+                    server_id: server.server_id,
+                    uptime_hours,
+                    cpu_usage,
+                    ram_mb: cpu_usage.map(|_| 1024),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        // Act
+        let now = Utc::now();
+        let usage = get_usage_for_user(&pool, user.id, now - chrono::Duration::hours(1), now)
+            .await
+            .unwrap();
 
         // Assert
-        let server = helpers::test_get_server(&mut tx, server_id).await.unwrap();
-        assert_eq!(server.id, server_id);
-        assert_eq!(server.host_name, payload.host_name);
+        assert_eq!(server.server_id, server_id);
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].samples, 3);
+        assert_eq!(usage[0].uptime_hours, 1.5);
+        assert!((usage[0].avg_cpu_usage.unwrap() - 0.3).abs() < 1e-9);
+        assert_eq!(usage[0].avg_ram_mb, Some(1024.0));
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn create_ip_addresses_should_skip_existing(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let cidr = "10.0.1.0/29".parse::<Ipv4Cidr>().unwrap();
+        helpers::test_datacenter(&mut tx).await;
+        let network_id = create_network(&mut tx, "dc-1", &cidr, "10.0.1.1")
+            .await
+            .unwrap();
+        let addresses = cidr.hosts().map(|ip| ip.to_string()).collect::<Vec<_>>();
+
+        // Act
+        let first = create_ip_addresses(&mut tx, network_id, &addresses)
+            .await
+            .unwrap();
+        let second = create_ip_addresses(&mut tx, network_id, &addresses)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(first, 6);
+        assert_eq!(second, 0);
+        let network = get_network(tx.as_mut(), network_id).await.unwrap().unwrap();
+        assert_eq!(network.cidr.as_deref(), Some("10.0.1.0/29"));
+        assert_eq!(network.subnet_mask, "255.255.255.248");
+        assert_eq!(network.addresses, 6);
         tx.commit().await.unwrap();
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn reserve_ip_for_server_should_works(pool: PgPool) {
+    async fn reserved_ip_should_not_be_allocated(pool: PgPool) {
         // Arrange
         let mut tx = pool.begin().await.unwrap();
-        let product_id = helpers::test_product(&mut tx).await;
-        let payload = payload::test_server(Some(product_id));
+        let payload = payload::test_server(None);
         let server_id = create_server_record(&mut tx, &payload.host_name)
             .await
             .unwrap();
         let network_id = helpers::test_network_id(&mut tx).await;
-        let ip_id = helpers::test_ip_id(&mut tx, None, network_id).await;
+        helpers::test_ip_id(&mut tx, None, network_id).await;
 
         // Act
-        let ip_config = reserve_ip_for_server(&mut tx, server_id, &payload.datacenter)
-            .await
-            .unwrap();
+        let updated =
+            set_ip_range_reserved(tx.as_mut(), network_id, "10.0.0.100", "10.0.0.110", true)
+                .await
+                .unwrap();
+        let result = reserve_ip_for_server(&mut tx, server_id, &payload.datacenter).await;
 
         // Assert
-        assert_eq!(ip_config.ip_address, "10.0.0.101");
-        let ip_server_id = helpers::test_get_server_id_from_ip(&mut tx, ip_id).await;
-        assert_eq!(ip_server_id, Some(server_id));
-        tx.commit().await.unwrap();
+        assert_eq!(updated, 1);
+        assert!(result.is_err());
+        let utilization = get_ip_utilization(tx.as_mut()).await.unwrap();
+        assert_eq!(
+            utilization,
+            [ApiIpUtilization {
+                datacenter_name: "dc-1".to_owned(),
+                networks: 1,
+                total: 1,
+                allocated: 0,
+                reserved: 1,
+                available: 0,
+            }]
+        );
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn create_service_record_should_works(pool: PgPool) {
+    async fn additional_ip_should_be_reserved_and_released(pool: PgPool) {
         // Arrange
         let mut tx = pool.begin().await.unwrap();
-        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
-        let product_id = helpers::test_product(&mut tx).await;
-        let payload = payload::test_server(Some(product_id));
+        let payload = payload::test_server(None);
         let server_id = create_server_record(&mut tx, &payload.host_name)
             .await
             .unwrap();
-        let template_id = helpers::test_template_id(&mut tx).await;
+        let network_id = helpers::test_network_id(&mut tx).await;
+        helpers::test_ip_id(&mut tx, Some(server_id), network_id).await;
+        create_ip_addresses(&mut tx, network_id, &["10.0.0.102".to_owned()])
+            .await
+            .unwrap();
 
         // Act
-        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+        let (nic_index, ip_config) = reserve_additional_ip(&mut tx, server_id).await.unwrap();
+        let exhausted = reserve_additional_ip(&mut tx, server_id).await;
+        let released = release_additional_ip(&mut tx, server_id, "10.0.0.102")
             .await
             .unwrap();
-        let service = sqlx::query!("SELECT * FROM services WHERE id = $1", service_id)
-            .fetch_one(&mut *tx)
+        let primary = release_additional_ip(&mut tx, server_id, "10.0.0.101")
             .await
             .unwrap();
 
         // Assert
-        assert_eq!(service.user_id, user.id);
-        assert_eq!(service.server_id, server_id);
+        assert_eq!(nic_index, 1);
+        assert_eq!(ip_config.ip_address, "10.0.0.102");
+        assert!(matches!(exhausted, Err(Error::Capacity(_))));
+        assert_eq!(released, Some(1));
+        assert_eq!(primary, None);
         tx.commit().await.unwrap();
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn save_config_values_should_works(pool: PgPool) {
+    async fn firewall_rule_should_be_created_and_deleted(pool: PgPool) {
         // Arrange
         let mut tx = pool.begin().await.unwrap();
-        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
-        let product_id = helpers::test_product(&mut tx).await;
-        let payload = payload::test_server(Some(product_id));
-        let server_id = create_server_record(&mut tx, &payload.host_name)
+        let server_id = create_server_record(&mut tx, "test-server").await.unwrap();
+        let rule = FirewallRulePayload {
+            direction: FirewallDirection::In,
+            action: FirewallAction::Accept,
+            protocol: FirewallProtocol::Tcp,
+            port: 22,
+            source: Some("10.0.0.0/24".to_owned()),
+        };
+
+        // Act
+        let created = create_firewall_rule(tx.as_mut(), server_id, &rule)
             .await
             .unwrap();
-        let template_id = helpers::test_template_id(&mut tx).await;
-        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+        let rules = get_firewall_rules(tx.as_mut(), server_id).await.unwrap();
+        let deleted = delete_firewall_rule(tx.as_mut(), server_id, created.id)
             .await
             .unwrap();
-        let cpu_option_id = helpers::test_config_option(&mut tx, "cpu_cores").await;
-        let ram_option_id = helpers::test_config_option(&mut tx, "ram_gb").await;
-
-        // Act
-        save_config_values(&mut tx, service_id, &payload)
+        let deleted_again = delete_firewall_rule(tx.as_mut(), server_id, created.id)
             .await
             .unwrap();
 
         // Assert
-        let cpu_value = helpers::test_config_value(&mut tx, service_id, cpu_option_id).await;
-        assert_eq!(cpu_value, payload.cpu_cores.unwrap().to_string());
-        let ram_value = helpers::test_config_value(&mut tx, service_id, ram_option_id).await;
-        assert_eq!(ram_value, payload.ram_gb.unwrap().to_string());
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].id, created.id);
+        assert_eq!(rules[0].action, FirewallAction::Accept);
+        assert_eq!(rules[0].source.as_deref(), Some("10.0.0.0/24"));
+        assert!(deleted);
+        assert!(!deleted_again);
         tx.commit().await.unwrap();
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn save_custom_values_should_works(pool: PgPool) {
+    async fn due_backups_should_move_to_next_run(pool: PgPool) {
         // Arrange
         let mut tx = pool.begin().await.unwrap();
-        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
-        let product_id = helpers::test_product(&mut tx).await;
-        let payload = payload::test_server(Some(product_id));
-        let server_id = create_server_record(&mut tx, &payload.host_name)
-            .await
-            .unwrap();
-        let template_id = helpers::test_template_id(&mut tx).await;
-        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+        let provisioned_id = create_server_record(&mut tx, "provisioned").await.unwrap();
+        update_initial_server(&mut tx, provisioned_id, VmRef::new("pve", 100))
             .await
             .unwrap();
-
-        let os_field_id = helpers::test_custom_option(&mut tx, "os", product_id).await;
-        let dc_field_id = helpers::test_custom_option(&mut tx, "datacenter", product_id).await;
+        let pending_id = create_server_record(&mut tx, "pending").await.unwrap();
+        let cron = "30 2 * * *".parse::<CronSchedule>().unwrap();
+        let now = Utc::now();
+        for server_id in [provisioned_id, pending_id] {
+            set_backup_schedule(tx.as_mut(), server_id, &cron, BackupMode::Stop, now)
+                .await
+                .unwrap();
+        }
 
         // Act
-        save_custom_values(&mut tx, service_id, &payload)
+        let due = get_due_backups(&mut tx, now).await.unwrap();
+        let next_run_at = now + chrono::Duration::days(1);
+        update_backup_run(&mut tx, provisioned_id, Some(now), next_run_at)
+            .await
+            .unwrap();
+        update_backup_run(&mut tx, pending_id, None, next_run_at)
             .await
             .unwrap();
+        let due_after_run = get_due_backups(&mut tx, now).await.unwrap();
 
         // Assert
-        let os_value = helpers::test_custom_value(&mut tx, service_id, os_field_id).await;
-        assert_eq!(os_value, payload.os);
-        let dc_value = helpers::test_custom_value(&mut tx, service_id, dc_field_id).await;
-        assert_eq!(dc_value, payload.datacenter);
+        assert_eq!(due.len(), 2);
+        let provisioned = due
+            .iter()
+            .find(|backup| backup.server_id == provisioned_id)
+            .unwrap();
+        assert_eq!(provisioned.vm_id, Some(100));
+        assert_eq!(provisioned.mode, BackupMode::Stop);
+        let pending = due
+            .iter()
+            .find(|backup| backup.server_id == pending_id)
+            .unwrap();
+        assert_eq!(pending.vm_id, None);
+        assert!(due_after_run.is_empty());
+        let schedule = get_backup_schedule(tx.as_mut(), provisioned_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(schedule.cron, "30 2 * * *");
+        assert!(schedule.last_run_at.is_some());
+        let pending_schedule = get_backup_schedule(tx.as_mut(), pending_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(pending_schedule.last_run_at.is_none());
         tx.commit().await.unwrap();
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn update_initial_server_should_works(pool: PgPool) {
+    async fn scheduled_job_should_be_claimed_once(pool: PgPool) {
         // Arrange
-        let mut tx = pool.begin().await.unwrap();
-        let payload = payload::test_server(None);
-        let server_id = create_server_record(&mut tx, &payload.host_name)
+        let cron = "0 * * * *".parse::<CronSchedule>().unwrap();
+        let now = Utc::now();
+        let next_run_at = now + chrono::Duration::hours(1);
+        let locked_until = now + chrono::Duration::minutes(10);
+        register_scheduled_job(&pool, "test", &cron, now)
             .await
             .unwrap();
-        let vm_ref = VmRef::new("test-node", 123);
 
         // Act
-        update_initial_server(&mut tx, server_id, vm_ref.clone())
+        let first = claim_scheduled_job(&pool, "test", now, next_run_at, locked_until)
+            .await
+            .unwrap();
+        let second = claim_scheduled_job(&pool, "test", now, next_run_at, locked_until)
+            .await
+            .unwrap();
+        finish_scheduled_job(&pool, "test", now, None)
+            .await
+            .unwrap();
+        let after_finish = claim_scheduled_job(&pool, "test", now, next_run_at, locked_until)
+            .await
+            .unwrap();
+        let overdue = claim_scheduled_job(&pool, "test", next_run_at, next_run_at, next_run_at)
             .await
             .unwrap();
 
         // Assert
-        let server = helpers::test_get_server(&mut tx, server_id).await.unwrap();
-        assert_eq!(server.vm_id, Some(vm_ref.id));
-        assert_eq!(server.node_name, Some(vm_ref.node));
-        tx.commit().await.unwrap();
+        assert!(first.is_some());
+        assert_eq!(second, None);
+        assert_eq!(after_finish, None);
+        assert_eq!(
+            overdue.map(|time| time.timestamp()),
+            Some(next_run_at.timestamp())
+        );
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn find_template_id_should_works(pool: PgPool) {
+    async fn settled_domain_events_should_stop_at_open_gap(pool: PgPool) {
         // Arrange
-        let mut tx = pool.begin().await.unwrap();
-        let expected_id = helpers::test_template_id(&mut tx).await;
+        let data = serde_json::json!({});
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let id =
+                record_domain_event(&pool, Uuid::new_v4(), DomainEventKind::ServerCreated, &data)
+                    .await
+                    .unwrap();
+            ids.push(id);
+        }
+        // The middle event is still being committed, or was rolled back.
+        sqlx::query!("DELETE FROM domain_events WHERE id = $1", ids[1])
+            .execute(&pool)
+            .await
+            .unwrap();
 
         // Act
-        let found_id = find_template_id(&mut tx, "os").await.unwrap();
+        let open = get_settled_domain_events_after(&pool, 0, 60_000, 10)
+            .await
+            .unwrap();
+        sqlx::query!("UPDATE domain_events SET created_at = created_at - INTERVAL '2 minutes'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let settled = get_settled_domain_events_after(&pool, 0, 60_000, 10)
+            .await
+            .unwrap();
 
         // Assert
-        assert_eq!(found_id, expected_id);
-        tx.commit().await.unwrap();
+        assert_eq!(
+            open.iter().map(|event| event.id).collect::<Vec<_>>(),
+            [ids[0]]
+        );
+        assert_eq!(
+            settled.iter().map(|event| event.id).collect::<Vec<_>>(),
+            [ids[0], ids[2]]
+        );
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn find_template_should_works(pool: PgPool) {
+    async fn domain_event_failure_should_count_attempts(pool: PgPool) {
         // Arrange
-        let mut tx = pool.begin().await.unwrap();
-        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
-        let product_id = helpers::test_product(&mut tx).await;
-        let payload = payload::test_server(Some(product_id));
-        let server_id = create_server_record(&mut tx, &payload.host_name)
-            .await
-            .unwrap();
-        let template_id = helpers::test_template_id(&mut tx).await;
-        let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
+        let data = serde_json::json!({});
+        let id = record_domain_event(&pool, Uuid::new_v4(), DomainEventKind::ServerCreated, &data)
             .await
             .unwrap();
+        let mut attempts = Vec::new();
 
         // Act
-        let vm_ref = find_template(&mut tx, service_id).await.unwrap();
+        for _ in 0..2 {
+            let attempt = record_domain_event_failure(&pool, "test", id, "failed", 2)
+                .await
+                .unwrap();
+            attempts.push(attempt);
+        }
 
         // Assert
-        assert_eq!(vm_ref.node, "node");
-        assert_eq!(vm_ref.id, 1);
-        tx.commit().await.unwrap();
+        assert_eq!(attempts, [1, 2]);
+        let failure = sqlx::query!(
+            "SELECT dead_at FROM domain_event_failures WHERE consumer = 'test' AND event_id = $1",
+            id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert!(failure.dead_at.is_some());
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn update_service_status_should_works(pool: PgPool) {
+    async fn create_usage_invoices_should_invoice_period_once(pool: PgPool) {
         // Arrange
-        let mut tx = pool.begin().await.unwrap();
         let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
         let product_id = helpers::test_product(&mut tx).await;
         let payload = payload::test_server(Some(product_id));
         let server_id = create_server_record(&mut tx, &payload.host_name)
@@ -1098,48 +10345,112 @@ mod tests {
         let service_id = create_service_record(&mut tx, user.id, server_id, template_id, &payload)
             .await
             .unwrap();
-        let new_status = ServiceStatus::Active;
+        tx.commit().await.unwrap();
+        for uptime_hours in [1.0, 0.5] {
+            create_usage_record(
+                &pool,
+                NewUsageRecord {
+                    user_id: user.id,
+                    service_id,
+                    server_id,
+                    uptime_hours,
+                    cpu_usage: None,
+                    ram_mb: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let now = Utc::now();
+        let from = now - chrono::Duration::hours(1);
+        let to = now + chrono::Duration::hours(1);
 
         // Act
-        update_service_status(&mut *tx, service_id, new_status.clone())
+        let created = create_usage_invoices(&pool, from, to, 10, "usd", "Usage")
             .await
             .unwrap();
-
-        // Assert
-        let service = sqlx::query!("SELECT status FROM services WHERE id = $1", service_id)
-            .fetch_one(&mut *tx)
+        let created_again = create_usage_invoices(&pool, from, to, 10, "usd", "Usage")
             .await
             .unwrap();
-        assert_eq!(service.status, new_status.to_string());
-        tx.commit().await.unwrap();
+
+        // Assert
+        assert_eq!(created, [user.id]);
+        assert!(created_again.is_empty());
+        let invoices = get_invoices_for_user(&pool, user.id).await.unwrap();
+        assert_eq!(invoices.len(), 1);
+        assert_eq!(invoices[0].amount_cents, 15);
+        assert_eq!(invoices[0].description, "Usage: 1.50 h");
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn update_server_status_should_works(pool: PgPool) {
+    async fn webhook_delivery_should_be_queued_for_subscribed_events(pool: PgPool) {
         // Arrange
-        let mut tx = pool.begin().await.unwrap();
-        let payload = payload::test_server(None);
-        let server_id = create_server_record(&mut tx, &payload.host_name)
-            .await
-            .unwrap();
-        let new_status = ServerStatus::Stopped;
+        let user = add_new_user(&pool, payload::test_user()).await.unwrap();
+        let webhook = create_webhook(
+            &pool,
+            user.id,
+            "https://example.com/hooks",
+            "secret",
+            &[WebhookEvent::ServerCreated],
+        )
+        .await
+        .unwrap();
+        let data = serde_json::json!({"server_id": Uuid::new_v4()});
+        let attempt = WebhookAttempt {
+            response_status: Some(200),
+            error: None,
+            duration_ms: 15,
+        };
+        let now = Utc::now();
+        let lease_until = now + chrono::Duration::minutes(1);
 
         // Act
-        update_server_status(&mut *tx, server_id, new_status.clone())
+        let subscribed =
+            create_webhook_deliveries(&pool, user.id, WebhookEvent::ServerCreated, &data)
+                .await
+                .unwrap();
+        let unsubscribed =
+            create_webhook_deliveries(&pool, user.id, WebhookEvent::InvoicePaid, &data)
+                .await
+                .unwrap();
+        let due = claim_due_webhook_deliveries(&pool, now, lease_until, 10)
+            .await
+            .unwrap();
+        let leased = claim_due_webhook_deliveries(&pool, now, lease_until, 10)
+            .await
+            .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        record_webhook_attempt(
+            &mut tx,
+            due[0].id,
+            &attempt,
+            WebhookDeliveryStatus::Delivered,
+            now,
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+        let due_again = claim_due_webhook_deliveries(&pool, lease_until, lease_until, 10)
             .await
             .unwrap();
 
         // Assert
-        let server = sqlx::query!("SELECT status FROM servers WHERE id = $1", server_id)
-            .fetch_one(&mut *tx)
+        assert_eq!(subscribed, 1);
+        assert_eq!(unsubscribed, 0);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].url, webhook.url);
+        assert_eq!(due[0].payload, data);
+        assert!(leased.is_empty());
+        assert!(due_again.is_empty());
+        let deliveries = get_webhook_deliveries(&pool, user.id, webhook.id, 10)
             .await
             .unwrap();
-        assert_eq!(server.status, new_status.to_string());
-        tx.commit().await.unwrap();
+        assert_eq!(deliveries[0].status, WebhookDeliveryStatus::Delivered);
+        assert!(deliveries[0].delivered_at.is_some());
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn get_server_by_id_should_works(pool: PgPool) {
+    async fn provisioning_should_resume_from_failed_step(pool: PgPool) {
         // Arrange
         let user = add_new_user(&pool, payload::test_user()).await.unwrap();
         let mut tx = pool.begin().await.unwrap();
@@ -1152,41 +10463,69 @@ mod tests {
         create_service_record(&mut tx, user.id, server_id, template_id, &payload)
             .await
             .unwrap();
-        let network_id = helpers::test_network_id(&mut tx).await;
-        helpers::test_ip_id(&mut tx, Some(server_id), network_id).await;
+        create_provisioning_steps(tx.as_mut(), server_id)
+            .await
+            .unwrap();
         tx.commit().await.unwrap();
+        let clone = ProvisioningStep::CloneVm;
+        let configure = ProvisioningStep::ConfigureVm;
 
         // Act
-        let server = get_server_by_id(&pool, user.id, server_id).await.unwrap();
-
-        // Assert
-        assert_eq!(server.server_id, server_id);
-    }
-
-    #[sqlx::test(migrations = "../../migrations")]
-    async fn delete_server_record_should_works(pool: PgPool) {
-        // Arrange
-        let mut tx = pool.begin().await.unwrap();
-        let payload = payload::test_server(None);
-        let server_id = create_server_record(&mut tx, &payload.host_name)
+        start_provisioning_step(&pool, server_id, clone)
+            .await
+            .unwrap();
+        set_provisioning_task(&pool, server_id, clone, "UPID:node:1")
+            .await
+            .unwrap();
+        finish_provisioning_step(
+            &pool,
+            server_id,
+            clone,
+            ProvisioningStepStatus::Completed,
+            None,
+        )
+        .await
+        .unwrap();
+        start_provisioning_step(&pool, server_id, configure)
+            .await
+            .unwrap();
+        finish_provisioning_step(
+            &pool,
+            server_id,
+            configure,
+            ProvisioningStepStatus::Failed,
+            Some("timeout"),
+        )
+        .await
+        .unwrap();
+        let reset = reset_failed_provisioning_step(&pool, user.id, server_id)
+            .await
+            .unwrap();
+        let reset_again = reset_failed_provisioning_step(&pool, user.id, server_id)
             .await
             .unwrap();
-        let network_id = helpers::test_network_id(&mut tx).await;
-        let ip_id = helpers::test_ip_id(&mut tx, Some(server_id), network_id).await;
-
-        // Act
-        delete_server_record(&mut tx, server_id).await.unwrap();
 
         // Assert
-        let server = helpers::test_get_server(&mut tx, server_id).await;
-        assert!(server.is_none());
-        let ip_server_id = helpers::test_get_server_id_from_ip(&mut tx, ip_id).await;
-        assert!(ip_server_id.is_none());
-        tx.commit().await.unwrap();
+        assert!(reset);
+        assert!(!reset_again);
+        let steps = get_provisioning_steps(&pool, user.id, server_id)
+            .await
+            .unwrap();
+        let names = steps.iter().map(|step| step.step).collect::<Vec<_>>();
+        assert_eq!(names, ProvisioningStep::ALL);
+        assert_eq!(steps[0].status, ProvisioningStepStatus::Completed);
+        assert_eq!(steps[1].status, ProvisioningStepStatus::Pending);
+        assert_eq!(steps[1].attempts, 1);
+        assert_eq!(steps[1].error.as_deref(), Some("timeout"));
+        assert_eq!(steps[2].attempts, 0);
+        let task = get_provisioning_task(&pool, server_id, clone)
+            .await
+            .unwrap();
+        assert_eq!(task.as_deref(), Some("UPID:node:1"));
     }
 
     #[sqlx::test(migrations = "../../migrations")]
-    async fn get_server_proxmox_ref_should_works(pool: PgPool) {
+    async fn stale_provisionings_should_skip_progressing_ones(pool: PgPool) {
         // Arrange
         let user = add_new_user(&pool, payload::test_user()).await.unwrap();
         let mut tx = pool.begin().await.unwrap();
@@ -1199,21 +10538,77 @@ mod tests {
         create_service_record(&mut tx, user.id, server_id, template_id, &payload)
             .await
             .unwrap();
-
-        let vm_ref = VmRef::new("test-node", 42);
-        update_initial_server(&mut tx, server_id, vm_ref.clone())
+        create_provisioning_steps(tx.as_mut(), server_id)
             .await
             .unwrap();
         tx.commit().await.unwrap();
+        start_provisioning_step(&pool, server_id, ProvisioningStep::CloneVm)
+            .await
+            .unwrap();
+        let now = Utc::now();
 
         // Act
-        let found_ref = get_server_proxmox_ref(&pool, user.id, server_id)
+        let progressing = get_stale_provisionings(&pool, now - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        let stalled = get_stale_provisionings(&pool, now + chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        update_server_status(&pool, server_id, ServerStatus::Running)
+            .await
+            .unwrap();
+        let provisioned = get_stale_provisionings(&pool, now + chrono::Duration::hours(1))
             .await
             .unwrap();
 
         // Assert
-        assert_eq!(found_ref.id, vm_ref.id);
-        assert_eq!(found_ref.node, vm_ref.node);
+        assert!(progressing.is_empty());
+        assert_eq!(stalled, [(user.id, server_id)]);
+        assert!(provisioned.is_empty());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn server_traffic_should_accumulate_and_alert_once(pool: PgPool) {
+        // Arrange
+        let mut tx = pool.begin().await.unwrap();
+        let server_id = create_server_record(&mut tx, "traffic").await.unwrap();
+        let now = Utc::now();
+        let traffic = |rx_bytes, sampled_until| ApiTrafficMonth {
+            month: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            rx_bytes,
+            tx_bytes: 100,
+            sampled_until,
+        };
+
+        // Act
+        add_server_traffic(tx.as_mut(), server_id, &traffic(1_000, now))
+            .await
+            .unwrap();
+        let total = add_server_traffic(
+            tx.as_mut(),
+            server_id,
+            &traffic(500, now - chrono::Duration::hours(1)),
+        )
+        .await
+        .unwrap();
+        let alerted = mark_traffic_alerted(tx.as_mut(), server_id, total.month)
+            .await
+            .unwrap();
+        let alerted_again = mark_traffic_alerted(tx.as_mut(), server_id, total.month)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(total.rx_bytes, 1_500);
+        assert_eq!(total.tx_bytes, 200);
+        assert_eq!(total.sampled_until.timestamp(), now.timestamp());
+        assert!(alerted);
+        assert!(!alerted_again);
+        let months = get_server_traffic(tx.as_mut(), server_id, 12)
+            .await
+            .unwrap();
+        assert_eq!(months, [total]);
+        tx.commit().await.unwrap();
     }
 
     // -------------------------------------------------------------------------
@@ -1233,6 +10628,7 @@ mod tests {
                 country: "USA".to_owned(),
                 phone_number: "555-1234".to_owned(),
                 plain_password: "secure_password_123".into(),
+                captcha_token: None,
             }
         }
 
@@ -1245,6 +10641,28 @@ mod tests {
                 cpu_cores: Some(4),
                 ram_gb: Some(8),
                 ip_config: None,
+                app: None,
+                external_id: None,
+                tags: Vec::new(),
+            }
+        }
+
+        pub fn test_invoice(user_id: Uuid) -> NewInvoice {
+            NewInvoice {
+                user_id,
+                service_id: None,
+                description: "Test invoice".to_owned(),
+                amount_cents: 1500,
+                currency: "usd".to_owned(),
+            }
+        }
+
+        pub fn test_promo_code() -> NewPromoCode {
+            NewPromoCode {
+                code: "WELCOME10".to_owned(),
+                amount_cents: 1000,
+                max_redemptions: Some(10),
+                expires_at: None,
             }
         }
     }
@@ -1292,7 +10710,20 @@ RETURNING id"#
             .id
         }
 
+        pub async fn test_datacenter(transaction: &mut PgTransaction<'_>) {
+            sqlx::query!(
+                r#"
+INSERT INTO datacenters (code, display_name)
+VALUES ('dc-1', 'Datacenter 1')
+ON CONFLICT DO NOTHING"#
+            )
+            .execute(transaction.as_mut())
+            .await
+            .unwrap();
+        }
+
         pub async fn test_network_id(transaction: &mut PgTransaction<'_>) -> Uuid {
+            test_datacenter(transaction).await;
             sqlx::query!(
                 r#"
 INSERT INTO networks (datacenter_name, gateway, subnet_mask)
@@ -1324,6 +10755,18 @@ RETURNING id"#,
             .id
         }
 
+        pub async fn test_node(transaction: &mut PgTransaction<'_>, name: &str) {
+            sqlx::query!(
+                r#"
+INSERT INTO nodes (name, cpu_cores, ram_gb, cpu_overcommit)
+VALUES ($1, 16, 64, 4.0)"#,
+                name
+            )
+            .execute(transaction.as_mut())
+            .await
+            .unwrap();
+        }
+
         pub async fn test_get_server(
             transaction: &mut PgTransaction<'_>,
             server_id: Uuid,
@@ -0,0 +1,139 @@
+use crate::config::Config;
+use crate::model::queries;
+use crate::state::AppState;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// Connection pool of the read-only replica of the database.
+///
+/// The replica is used only while its last health check succeeded, so reads
+/// fall back to the primary once the replica becomes unavailable. Clones share
+/// the health of the replica.
+///
+#[derive(Debug, Clone)]
+pub struct Replica {
+    pool: PgPool,
+    available: Arc<AtomicBool>,
+}
+
+impl Replica {
+    /// Creates the replica pool without connecting to it. The replica is
+    /// considered unavailable until its first health check.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: Application's configuration.
+    ///
+    /// # Returns
+    ///
+    /// Replica pool, `None` if no replica is configured.
+    ///
+    pub fn connect_lazy(config: &Config) -> Option<Self> {
+        let connect_options = config.database.get_replica_connect_options()?;
+        let pool = config
+            .database
+            .get_pool_options()
+            .connect_lazy_with(connect_options);
+
+        Some(Self {
+            pool,
+            available: Arc::default(),
+        })
+    }
+
+    /// Returns the replica pool, `None` if the replica is unavailable.
+    ///
+    pub fn pool(&self) -> Option<&PgPool> {
+        self.available.load(Ordering::Relaxed).then_some(&self.pool)
+    }
+
+    /// Checks the connection to the replica and records its availability.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the replica is available.
+    ///
+    pub async fn check(&self) -> bool {
+        let result = queries::ping(&self.pool).await;
+        let available = result.is_ok();
+        let previous = self.available.swap(available, Ordering::Relaxed);
+
+        match result {
+            Ok(()) if !previous => tracing::info!(target: "database", "Replica available"),
+            Err(error) if previous => {
+                tracing::warn!(target: "database", ?error, "Replica unavailable, reading from primary")
+            }
+            Err(error) => tracing::debug!(target: "database", ?error, "Replica still unavailable"),
+            Ok(()) => {}
+        }
+
+        available
+    }
+
+    /// Replaces the connect options of the replica, used by the new
+    /// connections.
+    ///
+    pub fn set_connect_options(&self, config: &Config) {
+        if let Some(connect_options) = config.database.get_replica_connect_options() {
+            self.pool.set_connect_options(connect_options);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Public entry point for the replica health check background task.
+///
+/// Checks the replica every configured interval, never returning. Returns
+/// immediately if no replica is configured.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+///
+pub async fn watch(app_state: AppState) {
+    let (Some(replica), Some(settings)) = (&app_state.replica, &app_state.config.database.replica)
+    else {
+        return;
+    };
+
+    let mut interval = tokio::time::interval(Duration::from_secs(settings.check_sec.max(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        replica.check().await;
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ReplicaEnv;
+
+    #[tokio::test]
+    async fn unavailable_replica_should_not_be_used() {
+        // Arrange
+        let mut config = Config::default();
+        config.database.pool.acquire_timeout_sec = 1;
+        config.database.replica = Some(ReplicaEnv {
+            host: "127.0.0.1".to_owned(),
+            port: 1,
+            ..ReplicaEnv::default()
+        });
+        let replica = Replica::connect_lazy(&config).unwrap();
+
+        // Act
+        let available = replica.check().await;
+
+        // Assert
+        assert!(!available);
+        assert!(replica.pool().is_none());
+        assert!(Replica::connect_lazy(&Config::default()).is_none());
+    }
+}
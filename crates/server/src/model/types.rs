@@ -1,12 +1,16 @@
-use chrono::{DateTime, Utc};
-use dashboard_common::prelude::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+pub use dashboard_common::api::{
+    ApiGuestInfo, ApiProvisioningStep, ApiServer, ApiServerDetail, ApiToken, ProvisioningStep,
+    ProvisioningStepStatus, ServerStatus,
+};
+use dashboard_common::prelude::{Error, Result};
 use derive_more::Display;
 use secrecy::SecretString;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::net::Ipv4Addr;
+use std::str::FromStr;
 use utoipa::ToSchema;
-use utoipa::schema;
 use uuid::Uuid;
 
 /// Represents a user row in the database, including the password hash.
@@ -24,12 +28,21 @@ pub struct DbUser {
     pub country: String,
     pub phone_number: String,
     pub password: SecretString,
-    pub created_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub locale: Option<String>,
+    pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
 /// Represents a user that is safe to expose to the public API.
 ///
+/// # Fields
+///
+/// * `email_verified_at`: Time the email address was confirmed, `None` until
+///   then.
+/// * `locale`: Language preferred for the notifications, `None` for the
+///   default one.
+///
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ApiUser {
     pub id: Uuid,
@@ -42,6 +55,28 @@ pub struct ApiUser {
     pub post_code: String,
     pub country: String,
     pub phone_number: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub locale: Option<String>,
+}
+
+/// Bundle of all personal data of a user, exported on the user's request.
+///
+/// # Fields
+///
+/// * `exported_at`: Time the bundle was assembled.
+/// * `servers`: Servers of the user together with their service IDs.
+/// * `webhooks`: Webhooks of the user, without their secrets.
+/// * `audit_log`: Audit log entries of the user's actions, oldest first.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiUserExport {
+    pub exported_at: DateTime<Utc>,
+    pub profile: ApiUser,
+    pub servers: Vec<ApiServer>,
+    pub invoices: Vec<ApiInvoice>,
+    pub credits: Vec<ApiCredit>,
+    pub webhooks: Vec<ApiWebhook>,
+    pub audit_log: Vec<ApiAuditEntry>,
 }
 
 /// Payload for creating a new user, contains the plaintext password
@@ -60,6 +95,9 @@ pub struct NewUser {
     #[serde(rename = "password")]
     #[schema(value_type = String)]
     pub plain_password: SecretString,
+    /// Token of the solved CAPTCHA, required after suspicious activity.
+    #[serde(default)]
+    pub captcha_token: Option<String>,
 }
 
 impl From<DbUser> for ApiUser {
@@ -75,6 +113,8 @@ impl From<DbUser> for ApiUser {
             post_code: user.post_code,
             country: user.country,
             phone_number: user.phone_number,
+            email_verified_at: user.email_verified_at,
+            locale: user.locale,
         }
     }
 }
@@ -86,6 +126,9 @@ pub struct LoginPayload {
     pub email: String,
     #[schema(value_type = String)]
     pub password: SecretString,
+    /// Token of the solved CAPTCHA, required after suspicious activity.
+    #[serde(default)]
+    pub captcha_token: Option<String>,
 }
 
 // -----------------------------------------------------------------------------
@@ -96,6 +139,7 @@ pub struct LoginPayload {
 pub enum ServiceStatus {
     Pending,
     Active,
+    Suspended,
     Failed,
 }
 
@@ -104,6 +148,7 @@ impl From<&str> for ServiceStatus {
         match value.to_lowercase().as_str() {
             "pending" => ServiceStatus::Pending,
             "active" => ServiceStatus::Active,
+            "suspended" => ServiceStatus::Suspended,
             _ => ServiceStatus::Failed,
         }
     }
@@ -115,67 +160,127 @@ impl From<String> for ServiceStatus {
     }
 }
 
-/// Represents the status from the `servers` table.
+/// Represents a row from the `servers` table.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Server {
+    pub id: Uuid,
+    pub vm_id: Option<i32>,
+    pub node_name: Option<String>,
+    pub host_name: String,
+}
+
+/// Represents a tag used on the servers of a user.
+///
+/// # Fields
+///
+/// * `servers`: Number of the user's servers with the tag.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiServerTag {
+    pub tag: String,
+    pub servers: i64,
+}
+
+/// How a new password was applied to a server.
 ///
 #[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
-pub enum ServerStatus {
-    // Stable statuses.
-    Running,
-    Stopped,
-    Failed,
-    // Lifecycle.
-    SettingUp,
-    Deleting,
-    // Progress statuses.
-    Starting,
-    Stopping,
-    Rebooting,
-    ShuttingDown,
+pub enum PasswordResetMethod {
+    /// Set inside the running guest by its QEMU guest agent, effective at once.
+    GuestAgent,
+    /// Set in the cloud-init configuration, effective on the next boot.
+    CloudInit,
 }
 
-impl From<&str> for ServerStatus {
-    fn from(value: &str) -> Self {
-        match value.to_lowercase().as_str() {
-            "running" => ServerStatus::Running,
-            "stopped" => ServerStatus::Stopped,
-            "setting_up" => ServerStatus::SettingUp,
-            "deleting" => ServerStatus::Deleting,
-            "starting" => ServerStatus::Starting,
-            "stopping" => ServerStatus::Stopping,
-            "rebooting" => ServerStatus::Rebooting,
-            "shutting_down" => ServerStatus::ShuttingDown,
-            _ => ServerStatus::Failed,
-        }
-    }
+/// Represents a reset password of a server.
+///
+/// # Fields
+///
+/// * `password`: New password, only returned here.
+/// * `username`: User whose password was set, `None` for the default user of
+///   the cloud-init configuration.
+/// * `method`: How the password was applied.
+/// * `reboot_required`: Whether the running server must be rebooted before
+///   the password works.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiPasswordReset {
+    pub password: String,
+    pub username: Option<String>,
+    pub method: PasswordResetMethod,
+    pub reboot_required: bool,
 }
 
-impl From<String> for ServerStatus {
-    fn from(value: String) -> Self {
-        Self::from(value.as_str())
-    }
+/// Represents a virtual disk of a server.
+///
+/// # Fields
+///
+/// * `device`: Bus and index of the disk, like `scsi0` for the boot disk.
+/// * `storage`: Proxmox storage the disk is on.
+/// * `size_gb`: Size of the disk in GB.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiDisk {
+    pub device: String,
+    pub storage: String,
+    pub size_gb: i32,
 }
 
-/// Represents a row from the `servers` table.
+/// Disk limits of the product of a server and the storage new disks go to.
 ///
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Server {
+/// # Fields
+///
+/// * `service_id`: ID of the service of the server.
+/// * `max_disks`: Number of disks allowed besides the boot disk.
+/// * `max_disk_gb`: Size a single disk may grow to in GB, `None` for
+///   unlimited.
+/// * `storage`: Storage of the product or the template, `None` to use the one
+///   of the boot disk.
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct DiskLimits {
+    pub service_id: Uuid,
+    pub max_disks: i32,
+    pub max_disk_gb: Option<i32>,
+    pub storage: Option<String>,
+}
+
+/// Represents an ISO image users can insert into the CD-ROM drive of their
+/// servers.
+///
+/// # Fields
+///
+/// * `name`: Name of the ISO shown to the users.
+/// * `volid`: Proxmox volume of the ISO, like `local:iso/debian-12.iso`.
+/// * `node_name`: Node whose local storage holds the ISO, `None` if the
+///   storage is shared by the nodes.
+/// * `size_bytes`: Size of the ISO in bytes.
+/// * `created_at`: When the ISO was registered.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiIso {
     pub id: Uuid,
-    pub vm_id: Option<i32>,
+    pub name: String,
+    pub volid: String,
     pub node_name: Option<String>,
-    pub host_name: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
 }
 
-/// Combined struct for the public API response.
+/// Represents the CD-ROM drive of a server.
 ///
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
-pub struct ApiServer {
-    pub service_id: Uuid,
-    pub server_id: Uuid,
-    pub vm_id: Option<i32>,
-    pub node_name: Option<String>,
-    pub ip_address: String,
-    pub status: ServerStatus,
+/// # Fields
+///
+/// * `iso`: ISO inserted into the drive, `None` if it is empty.
+/// * `device`: Device of the drive the ISO is inserted into, like `ide0`.
+/// * `boot_from_iso`: Whether the server boots from the ISO before its disks.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiServerIso {
+    pub iso: Option<ApiIso>,
+    pub device: Option<String>,
+    pub boot_from_iso: bool,
 }
 
 /// Configuration for an IP address.
@@ -195,38 +300,1920 @@ impl IpConfig {
     /// A formatted string, e.g., "ip=192.168.1.100/24,gw=192.168.1.1".
     ///
     pub fn form(self) -> Result<String> {
-        // Convert IPv4 mask into CIDR, for example:
-        // 255.255.255.0 -> 11111111.11111111.11111111.00000000 -> /24
-        let mask = self.subnet_mask.parse::<Ipv4Addr>()?;
-        let mask_u32 = u32::from(mask);
-        let cidr_prefix = mask_u32.leading_ones();
+        let cidr_prefix = self.prefix()?;
 
         Ok(format!(
             "ip={}/{},gw={}",
             self.ip_address, cidr_prefix, self.gateway
         ))
     }
+
+    /// Formats the IP configuration of an additional network device. Only the
+    /// primary device gets the gateway.
+    ///
+    /// # Returns
+    ///
+    /// A formatted string, e.g., "ip=192.168.1.101/24".
+    ///
+    pub fn form_additional(self) -> Result<String> {
+        Ok(format!("ip={}/{}", self.ip_address, self.prefix()?))
+    }
+
+    /// Returns the length of the network prefix of the subnet mask.
+    ///
+    fn prefix(&self) -> Result<u32> {
+        // Convert IPv4 mask into CIDR, for example:
+        // 255.255.255.0 -> 11111111.11111111.11111111.00000000 -> /24
+        let mask = self.subnet_mask.parse::<Ipv4Addr>()?;
+        Ok(u32::from(mask).leading_ones())
+    }
+}
+
+/// IPv4 block in CIDR notation, e.g. `10.0.0.0/24`.
+///
+/// # Fields
+///
+/// * `network`: Network address of the block.
+/// * `prefix`: Length of the network prefix in bits.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ipv4Cidr {
+    pub network: Ipv4Addr,
+    pub prefix: u32,
+}
+
+impl Ipv4Cidr {
+    /// Returns the subnet mask of the block, e.g. `255.255.255.0` for `/24`.
+    ///
+    pub fn subnet_mask(&self) -> Ipv4Addr {
+        Ipv4Addr::from(u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0))
+    }
+
+    /// Returns the usable host addresses of the block, without the network and
+    /// broadcast addresses.
+    ///
+    pub fn hosts(&self) -> impl Iterator<Item = Ipv4Addr> {
+        let network = u32::from(self.network);
+        let broadcast = network | !u32::from(self.subnet_mask());
+        (network + 1..broadcast).map(Ipv4Addr::from)
+    }
+
+    /// Checks whether the address belongs to the block.
+    ///
+    pub fn contains(&self, address: Ipv4Addr) -> bool {
+        u32::from(address) & u32::from(self.subnet_mask()) == u32::from(self.network)
+    }
+}
+
+impl FromStr for Ipv4Cidr {
+    type Err = Error;
+
+    fn from_str(cidr: &str) -> Result<Self> {
+        let invalid = || Error::Validation(format!("Invalid CIDR block {cidr}"));
+        let (network, prefix) = cidr.trim().split_once('/').ok_or_else(invalid)?;
+        let network = network.parse::<Ipv4Addr>().map_err(|_| invalid())?;
+        let prefix = prefix.parse::<u32>().map_err(|_| invalid())?;
+        if prefix > 32 {
+            return Err(invalid());
+        }
+
+        let cidr = Self { network, prefix };
+        if u32::from(network) & u32::from(cidr.subnet_mask()) != u32::from(network) {
+            return Err(Error::Validation(format!(
+                "{network} is not the network address of /{prefix}"
+            )));
+        }
+
+        Ok(cidr)
+    }
+}
+
+impl std::fmt::Display for Ipv4Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
 }
 
 /// Represents a product that is safe to expose to the public API.
 ///
-#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ApiProduct {
     pub id: Uuid,
+    pub group_id: Uuid,
     pub name: String,
+    pub net_rate_mbps: Option<i32>,
+    pub storage: Option<String>,
+    pub max_disks: i32,
+    pub max_disk_gb: Option<i32>,
+    pub traffic_quota_gb: Option<i32>,
 }
 
 /// Represents a configurable option value that is safe to expose to the public
 /// API.
 ///
-#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ApiConfigValue {
     pub value: String,
 }
 
 /// Represents a custom field value that is safe to expose to the public API.
 ///
-#[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ApiCustomValue {
     pub value: Option<String>,
 }
+
+/// Represents a group of products.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiProductGroup {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// Represents a configurable option, like the CPU cores or the RAM.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiConfigOption {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// Represents a custom field of a product.
+///
+/// # Fields
+///
+/// * `options`: Values offered with the product.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiCustomField {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub name: String,
+    pub options: Vec<String>,
+}
+
+/// Kind of a template.
+///
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Display, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateKind {
+    /// Plain operating system, offered as a value of the OS template field.
+    #[default]
+    Os,
+    /// Application installed on top of an operating system, like a Docker
+    /// host, offered in the marketplace.
+    Application,
+}
+
+impl From<&str> for TemplateKind {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "application" => TemplateKind::Application,
+            _ => TemplateKind::Os,
+        }
+    }
+}
+
+impl From<String> for TemplateKind {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents a template, the Proxmox VM cloned for every new server.
+///
+/// # Fields
+///
+/// * `os_name`: Name of the OS, offered as a value of the OS template field,
+///   or of the application.
+/// * `template_vmid`: ID of the template VM.
+/// * `template_node`: Node the template VM is on.
+/// * `virtual_type`: Virtualization type, `qemu`.
+/// * `datacenter_code`: Datacenter whose cluster holds the template, `None`
+///   if not limited to one.
+/// * `storage`: Storage the disks of new servers are cloned to, `None` for
+///   the storage of the template.
+/// * `kind`: Whether the template is a plain OS or an application.
+/// * `description`: Description shown in the marketplace.
+/// * `cloud_init_snippet`: Cloud-init snippet volume passed to new servers as
+///   vendor data, like `local:snippets/docker.yaml`.
+/// * `post_install_script`: Shell script run through the guest agent once a
+///   new server boots.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiTemplate {
+    pub id: Uuid,
+    pub os_name: String,
+    pub template_vmid: i32,
+    pub template_node: String,
+    pub virtual_type: String,
+    pub datacenter_code: Option<String>,
+    pub storage: Option<String>,
+    pub kind: TemplateKind,
+    pub description: Option<String>,
+    pub cloud_init_snippet: Option<String>,
+    pub post_install_script: Option<String>,
+}
+
+/// Represents an application offered in the marketplace.
+///
+/// # Fields
+///
+/// * `id`: ID of the application template.
+/// * `name`: Name of the application, picked in the `app` field of a new
+///   server.
+/// * `description`: What the application provides.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiApp {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Represents a datacenter servers can be ordered in.
+///
+/// # Fields
+///
+/// * `code`: Unique code, picked by customers in the datacenter location
+///   field and named by the networks of the datacenter.
+/// * `display_name`: Human-readable name.
+/// * `cluster_url`: Endpoint of the datacenter's Proxmox cluster, if known.
+/// * `is_active`: Whether the datacenter is offered at all.
+/// * `is_full`: Whether the datacenter is out of capacity for new servers.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiDatacenter {
+    pub code: String,
+    pub display_name: String,
+    pub cluster_url: Option<String>,
+    pub is_active: bool,
+    pub is_full: bool,
+}
+
+/// Represents a datacenter a product can be ordered in, as shown to
+/// customers.
+///
+/// # Fields
+///
+/// * `code`: Unique code, sent in the datacenter location field.
+/// * `display_name`: Human-readable name.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiAvailableDatacenter {
+    pub code: String,
+    pub display_name: String,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Represents the status from the `invoices` table.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Unpaid,
+    Paid,
+    Cancelled,
+}
+
+impl From<&str> for InvoiceStatus {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "paid" => InvoiceStatus::Paid,
+            "cancelled" => InvoiceStatus::Cancelled,
+            _ => InvoiceStatus::Unpaid,
+        }
+    }
+}
+
+impl From<String> for InvoiceStatus {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents the status from the `payments` table.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize)]
+pub enum PaymentStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// Represents an invoice that is safe to expose to the public API.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiInvoice {
+    pub id: Uuid,
+    pub service_id: Option<Uuid>,
+    pub description: String,
+    pub amount_cents: i64,
+    pub credit_cents: i64,
+    pub currency: String,
+    pub status: InvoiceStatus,
+    pub created_at: Option<DateTime<Utc>>,
+    pub paid_at: Option<DateTime<Utc>>,
+}
+
+impl ApiInvoice {
+    /// Returns the amount that is not covered by credit yet.
+    ///
+    pub fn amount_due(&self) -> i64 {
+        (self.amount_cents - self.credit_cents).max(0)
+    }
+}
+
+/// Payload for creating a new invoice.
+///
+#[derive(Debug, Clone)]
+pub struct NewInvoice {
+    pub user_id: Uuid,
+    pub service_id: Option<Uuid>,
+    pub description: String,
+    pub amount_cents: i64,
+    pub currency: String,
+}
+
+/// Invoice settled by a payment, with its owner and linked service.
+///
+#[derive(Debug, Clone)]
+pub struct SettledInvoice {
+    pub user_id: Uuid,
+    pub service_id: Option<Uuid>,
+}
+
+/// Represents a credit ledger entry that is safe to expose to the public API.
+///
+/// Issued credit is positive, credit applied to an invoice is negative.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiCredit {
+    pub id: Uuid,
+    pub amount_cents: i64,
+    pub reason: String,
+    pub invoice_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Credit balance of a user together with the ledger it is computed from.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiCreditBalance {
+    pub balance_cents: i64,
+    pub entries: Vec<ApiCredit>,
+}
+
+/// Payload for creating a new credit ledger entry.
+///
+#[derive(Debug, Clone)]
+pub struct NewCredit {
+    pub user_id: Uuid,
+    pub amount_cents: i64,
+    pub reason: String,
+    pub promo_code_id: Option<Uuid>,
+    pub invoice_id: Option<Uuid>,
+    pub issued_by: Option<Uuid>,
+}
+
+/// Represents a promo code that is safe to expose to the admin API.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiPromoCode {
+    pub id: Uuid,
+    pub code: String,
+    pub amount_cents: i64,
+    pub max_redemptions: Option<i32>,
+    pub redemptions: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiPromoCode {
+    /// Checks whether the code can still be redeemed at the given moment.
+    ///
+    pub fn is_redeemable(&self, now: DateTime<Utc>) -> bool {
+        let not_expired = self.expires_at.is_none_or(|expires_at| now < expires_at);
+        let not_exhausted = self
+            .max_redemptions
+            .is_none_or(|max_redemptions| self.redemptions < max_redemptions);
+
+        not_expired && not_exhausted
+    }
+}
+
+/// Payload for creating a new promo code.
+///
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NewPromoCode {
+    pub code: String,
+    pub amount_cents: i64,
+    pub max_redemptions: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Physical capacity of a Proxmox node together with its overcommit policy
+/// and the resources already allocated to servers placed on it.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeCapacity {
+    pub name: String,
+    pub cpu_cores: i32,
+    pub ram_gb: i32,
+    pub cpu_overcommit: Option<f64>,
+    pub ram_overcommit: Option<f64>,
+    pub allocated_cpu_cores: i64,
+    pub allocated_ram_gb: i64,
+}
+
+/// Number of servers placed on a Proxmox node.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeVmCount {
+    pub node_name: String,
+    pub vms: i64,
+    pub running_vms: i64,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Server that is provisioned on Proxmox and should be metered by the usage
+/// collector.
+///
+#[derive(Debug, Clone)]
+pub struct MeteredServer {
+    pub user_id: Uuid,
+    pub service_id: Uuid,
+    pub server_id: Uuid,
+    pub vm_id: i32,
+    pub node_name: String,
+}
+
+/// Payload for creating a new usage record.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewUsageRecord {
+    pub user_id: Uuid,
+    pub service_id: Uuid,
+    pub server_id: Uuid,
+    pub uptime_hours: f64,
+    pub cpu_usage: Option<f64>,
+    pub ram_mb: Option<i64>,
+}
+
+/// Usage of a single service aggregated over a billing period, safe to expose
+/// to the public API.
+///
+/// Service and server IDs are empty for servers that were deleted since.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiUsage {
+    pub service_id: Option<Uuid>,
+    pub server_id: Option<Uuid>,
+    pub samples: i64,
+    pub uptime_hours: f64,
+    pub avg_cpu_usage: Option<f64>,
+    pub avg_ram_mb: Option<f64>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Server that is provisioned on Proxmox and whose network traffic should be
+/// accounted by the traffic collector.
+///
+/// # Fields
+///
+/// * `traffic_quota_gb`: Monthly traffic quota of the product in GB, `None`
+///   for unlimited.
+/// * `sampled_until`: Time of the last accounted RRD sample, `None` if the
+///   traffic of the server was never collected.
+///
+#[derive(Debug, Clone)]
+pub struct TrafficServer {
+    pub user_id: Uuid,
+    pub server_id: Uuid,
+    pub host_name: String,
+    pub vm_id: i32,
+    pub node_name: String,
+    pub traffic_quota_gb: Option<i32>,
+    pub sampled_until: Option<DateTime<Utc>>,
+}
+
+/// Network traffic of a server within a calendar month, in bytes.
+///
+/// # Fields
+///
+/// * `month`: First day of the month.
+/// * `rx_bytes`: Traffic received by the server.
+/// * `tx_bytes`: Traffic sent by the server.
+/// * `sampled_until`: Time of the last RRD sample counted in, when collected.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiTrafficMonth {
+    pub month: NaiveDate,
+    pub rx_bytes: i64,
+    pub tx_bytes: i64,
+    pub sampled_until: DateTime<Utc>,
+}
+
+impl ApiTrafficMonth {
+    /// Returns the traffic received and sent by the server in bytes.
+    ///
+    pub fn total_bytes(&self) -> i64 {
+        self.rx_bytes + self.tx_bytes
+    }
+}
+
+/// Network traffic of a server by month, the latest first, and the monthly
+/// quota of its product in GB, `None` for unlimited. Both directions count
+/// against the quota.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiServerTraffic {
+    pub quota_gb: Option<i32>,
+    pub months: Vec<ApiTrafficMonth>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Resource limits of a quota. A missing limit leaves the resource unlimited.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QuotaLimits {
+    pub max_servers: Option<i32>,
+    pub max_cpu_cores: Option<i32>,
+    pub max_ram_gb: Option<i32>,
+    pub max_ips: Option<i32>,
+}
+
+/// Quota of a product, limiting the servers of that product within every
+/// account separately.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductQuota {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub limits: QuotaLimits,
+}
+
+/// Resources held by the servers of a user.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuotaUsage {
+    pub servers: i64,
+    pub cpu_cores: i64,
+    pub ram_gb: i64,
+    pub ips: i64,
+}
+
+/// Usage of a single resource against its quota limit, safe to expose to the
+/// public API.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiQuotaItem {
+    pub used: i64,
+    pub limit: Option<i32>,
+}
+
+/// Usage against limits of a whole account, or of one product within it.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiQuota {
+    pub product_id: Option<Uuid>,
+    pub product_name: Option<String>,
+    pub servers: ApiQuotaItem,
+    pub cpu_cores: ApiQuotaItem,
+    pub ram_gb: ApiQuotaItem,
+    pub ips: ApiQuotaItem,
+}
+
+impl ApiQuota {
+    /// Creates the account-level quota view.
+    ///
+    pub fn new(limits: &QuotaLimits, usage: &QuotaUsage) -> Self {
+        let item = |used, limit| ApiQuotaItem { used, limit };
+        Self {
+            product_id: None,
+            product_name: None,
+            servers: item(usage.servers, limits.max_servers),
+            cpu_cores: item(usage.cpu_cores, limits.max_cpu_cores),
+            ram_gb: item(usage.ram_gb, limits.max_ram_gb),
+            ips: item(usage.ips, limits.max_ips),
+        }
+    }
+
+    /// Creates the quota view of a single product.
+    ///
+    pub fn for_product(quota: &ProductQuota, usage: &QuotaUsage) -> Self {
+        Self {
+            product_id: Some(quota.product_id),
+            product_name: Some(quota.product_name.clone()),
+            ..Self::new(&quota.limits, usage)
+        }
+    }
+}
+
+/// Account quota together with the quotas of all limited products.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiQuotas {
+    pub account: ApiQuota,
+    pub products: Vec<ApiQuota>,
+}
+
+/// Represents an IP network that is safe to expose to the public API.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiNetwork {
+    pub id: Uuid,
+    pub datacenter_name: String,
+    pub cidr: Option<String>,
+    pub gateway: String,
+    pub subnet_mask: String,
+    pub addresses: i64,
+}
+
+/// Range of a network's addresses updated by an administrator.
+///
+/// # Fields
+///
+/// * `reserved`: Whether the range is excluded from allocation.
+/// * `updated`: Number of addresses of the network within the range.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiIpRange {
+    pub network_id: Uuid,
+    pub start: String,
+    pub end: String,
+    pub reserved: bool,
+    pub updated: u64,
+}
+
+/// Utilization of the IP pool of a single datacenter.
+///
+/// # Fields
+///
+/// * `total`: All addresses of the datacenter's networks.
+/// * `allocated`: Addresses assigned to servers.
+/// * `reserved`: Free addresses excluded from allocation.
+/// * `available`: Free addresses that can be allocated to new servers.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiIpUtilization {
+    pub datacenter_name: String,
+    pub networks: i64,
+    pub total: i64,
+    pub allocated: i64,
+    pub reserved: i64,
+    pub available: i64,
+}
+
+/// Storage of a Proxmox node.
+///
+/// # Fields
+///
+/// * `name`: Storage identifier, e.g. `local-lvm`.
+/// * `kind`: Storage type, e.g. `lvmthin` or `zfspool`.
+/// * `content`: Content types the storage accepts.
+/// * `active`: Whether the storage is available on the node.
+/// * `holds_images`: Whether servers can be cloned to the storage.
+/// * `used`, `total`, `available`: Space in bytes.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiStorage {
+    pub name: String,
+    pub kind: String,
+    pub content: Vec<String>,
+    pub active: bool,
+    pub holds_images: bool,
+    pub used: u64,
+    pub total: u64,
+    pub available: u64,
+}
+
+/// Resource usage of a Proxmox node, for capacity planning.
+///
+/// # Fields
+///
+/// * `name`: Name of the node.
+/// * `online`: Whether the node is a live member of the cluster.
+/// * `uptime`: Uptime in seconds.
+/// * `cpu_usage`: Current CPU usage as a fraction of all cores.
+/// * `cpu_cores`: Number of logical cores.
+/// * `memory_used`, `memory_total`: Memory in bytes.
+/// * `storage_used`, `storage_total`: Root filesystem space in bytes.
+/// * `vms`: Servers placed on the node.
+/// * `running_vms`: Servers placed on the node that are running.
+///
+/// The usage is missing for offline nodes and for nodes whose status could
+/// not be read.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiNode {
+    pub name: String,
+    pub online: bool,
+    pub uptime: Option<u64>,
+    pub cpu_usage: Option<f64>,
+    pub cpu_cores: Option<u32>,
+    pub memory_used: Option<u64>,
+    pub memory_total: Option<u64>,
+    pub storage_used: Option<u64>,
+    pub storage_total: Option<u64>,
+    pub vms: i64,
+    pub running_vms: i64,
+}
+
+/// Represents the health of the application for the orchestrator.
+///
+/// # Fields
+///
+/// * `schema_version`: Latest migration applied to the database, `None` if
+///   none was.
+/// * `latest_version`: Latest migration embedded in the server.
+/// * `up_to_date`: Whether the database has every embedded migration. A
+///   database migrated by a newer replica is up to date too.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiHealth {
+    pub schema_version: Option<i64>,
+    pub latest_version: i64,
+    pub up_to_date: bool,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Single match of the admin search, with the columns of every kind of result.
+/// Columns that don't apply to the kind are `None`.
+///
+/// # Fields
+///
+/// * `kind`: Kind of the matched record, `user`, `server` or `service`.
+/// * `name`: Full name of the user, host name of the server, or product name of
+///   the service.
+/// * `email`: Email address of the user or of the owner of the service.
+/// * `host_name`: Host name of the server of the service.
+///
+#[derive(Debug, Clone, FromRow)]
+pub struct SearchHit {
+    pub kind: String,
+    pub id: Uuid,
+    pub name: String,
+    pub email: Option<String>,
+    pub status: Option<String>,
+    pub node_name: Option<String>,
+    pub vm_id: Option<i32>,
+    pub ip_addresses: Option<Vec<String>>,
+    pub host_name: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub server_id: Option<Uuid>,
+}
+
+/// User found by the admin search.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiSearchUser {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
+}
+
+/// Server found by the admin search.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiSearchServer {
+    pub id: Uuid,
+    pub host_name: String,
+    pub status: ServerStatus,
+    pub node_name: Option<String>,
+    pub vm_id: Option<i32>,
+    pub ip_addresses: Vec<String>,
+}
+
+/// Service found by the admin search.
+///
+/// # Fields
+///
+/// * `product_name`: Name of the ordered product.
+/// * `email`: Email address of the owner.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiSearchService {
+    pub id: Uuid,
+    pub status: String,
+    pub product_name: String,
+    pub user_id: Uuid,
+    pub email: String,
+    pub server_id: Uuid,
+    pub host_name: String,
+}
+
+/// Results of the admin search grouped by kind, the best matches first.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiSearchResults {
+    pub users: Vec<ApiSearchUser>,
+    pub servers: Vec<ApiSearchServer>,
+    pub services: Vec<ApiSearchService>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Direction of the traffic a firewall rule applies to.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallDirection {
+    In,
+    Out,
+}
+
+impl From<&str> for FirewallDirection {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "out" => FirewallDirection::Out,
+            _ => FirewallDirection::In,
+        }
+    }
+}
+
+impl From<String> for FirewallDirection {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Action taken on the traffic matched by a firewall rule or policy.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallAction {
+    Accept,
+    Drop,
+    Reject,
+}
+
+impl From<&str> for FirewallAction {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "accept" => FirewallAction::Accept,
+            "reject" => FirewallAction::Reject,
+            _ => FirewallAction::Drop,
+        }
+    }
+}
+
+impl From<String> for FirewallAction {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Transport protocol matched by a firewall rule.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallProtocol {
+    Tcp,
+    Udp,
+}
+
+impl From<&str> for FirewallProtocol {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "udp" => FirewallProtocol::Udp,
+            _ => FirewallProtocol::Tcp,
+        }
+    }
+}
+
+impl From<String> for FirewallProtocol {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// VM-level firewall settings of a server.
+///
+/// # Fields
+///
+/// * `enabled`: Whether the firewall filters the server's traffic.
+/// * `policy_in`: Action for incoming traffic not matched by any rule.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FirewallSettings {
+    pub enabled: bool,
+    pub policy_in: FirewallAction,
+}
+
+/// Represents a firewall rule that is safe to expose to the public API.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiFirewallRule {
+    pub id: Uuid,
+    pub direction: FirewallDirection,
+    pub action: FirewallAction,
+    pub protocol: FirewallProtocol,
+    pub port: i32,
+    pub source: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Firewall settings of a server together with its rules.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiFirewall {
+    pub enabled: bool,
+    pub policy_in: FirewallAction,
+    pub rules: Vec<ApiFirewallRule>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Schedule in the five-field cron format: minute, hour, day of month, month
+/// and day of week, e.g. `30 2 * * 1-5`. Every field accepts `*`, single
+/// values, ranges, lists and steps. Times are in UTC.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Returns the first matching minute after the given time, `None` if the
+    /// schedule doesn't match within the next five years, e.g. `0 0 30 2 *`.
+    ///
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = time + Duration::days(5 * 366);
+        let mut next = time.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        while next <= limit {
+            if !self.matches_day(next) {
+                next = (next.date_naive() + Duration::days(1))
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+            } else if !has_bit(self.hours, next.hour()) {
+                next = next.with_minute(0)? + Duration::hours(1);
+            } else if !has_bit(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+
+        None
+    }
+
+    /// Checks the month and the day. As in cron, a day matches either field
+    /// when both the day of month and the day of week are restricted.
+    ///
+    fn matches_day(&self, time: DateTime<Utc>) -> bool {
+        let day = has_bit(self.days, time.day());
+        let weekday = has_bit(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match self.any_day || self.any_weekday {
+            true => day && weekday,
+            false => day || weekday,
+        };
+
+        has_bit(self.months, time.month()) && day_matches
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let invalid = || Error::Validation(format!("Invalid cron expression {expression}"));
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid());
+        };
+
+        let mut weekdays = parse_cron_field(weekday, 0, 7).ok_or_else(invalid)?;
+        // Both 0 and 7 stand for Sunday.
+        if has_bit(weekdays, 7) {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            expression: fields.join(" "),
+            minutes: parse_cron_field(minute, 0, 59).ok_or_else(invalid)?,
+            hours: parse_cron_field(hour, 0, 23).ok_or_else(invalid)?,
+            days: parse_cron_field(day, 1, 31).ok_or_else(invalid)?,
+            months: parse_cron_field(month, 1, 12).ok_or_else(invalid)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl std::fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+/// Parses a single cron field into a bit mask of the matching values.
+///
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Option<u64> {
+    let mut mask = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|step| *step > 0)?),
+            None => (part, 1),
+        };
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+            None if part.contains('/') => (range.parse().ok()?, max),
+            None => (range.parse().ok()?, range.parse().ok()?),
+        };
+        if start < min || end > max || start > end {
+            return None;
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Some(mask)
+}
+
+/// Checks whether the value is set in the bit mask.
+///
+fn has_bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Mode of a backup, i.e. how the VM is treated while it is backed up.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupMode {
+    /// Live backup of the running VM.
+    Snapshot,
+    /// VM is suspended for the duration of the backup.
+    Suspend,
+    /// VM is stopped for the duration of the backup.
+    Stop,
+}
+
+impl From<&str> for BackupMode {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "suspend" => BackupMode::Suspend,
+            "stop" => BackupMode::Stop,
+            _ => BackupMode::Snapshot,
+        }
+    }
+}
+
+impl From<String> for BackupMode {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents a backup schedule of a server that is safe to expose to the
+/// public API.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiBackupSchedule {
+    pub server_id: Uuid,
+    pub cron: String,
+    pub mode: BackupMode,
+    pub next_run_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+}
+
+/// Backup schedule whose next run is due, together with the VM to back up,
+/// `None` while the server is not provisioned.
+///
+#[derive(Debug, Clone)]
+pub struct DueBackup {
+    pub server_id: Uuid,
+    pub vm_id: Option<i32>,
+    pub node_name: Option<String>,
+    pub cron: String,
+    pub mode: BackupMode,
+}
+
+/// Represents a backup archive of a server that is safe to expose to the
+/// public API.
+///
+/// # Fields
+///
+/// * `volid`: Proxmox volume ID of the archive, used to restore it.
+/// * `size_bytes`: Size of the archive.
+/// * `notes`: Notes attached to the archive on Proxmox, if any.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiBackup {
+    pub volid: String,
+    pub format: String,
+    pub size_bytes: i64,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Provisioned server in a stable state, whose status is kept in sync with
+/// Proxmox by the scheduler.
+///
+#[derive(Debug, Clone)]
+pub struct SyncedServer {
+    pub server_id: Uuid,
+    pub vm_id: i32,
+    pub node_name: String,
+    pub status: ServerStatus,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Kind of an event in the activity timeline of a server.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    /// Provisioning step started or finished.
+    Provisioning,
+    /// Power action started.
+    Power,
+    /// Backup restore started.
+    Restore,
+    /// Disk added or grown.
+    Resize,
+    /// Any other status change, such as an action finishing.
+    Status,
+    /// Backup archive created.
+    Backup,
+    /// Server moved to another account.
+    Transfer,
+}
+
+impl From<&str> for TimelineEventKind {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "provisioning" => TimelineEventKind::Provisioning,
+            "power" => TimelineEventKind::Power,
+            "restore" => TimelineEventKind::Restore,
+            "resize" => TimelineEventKind::Resize,
+            "backup" => TimelineEventKind::Backup,
+            "transfer" => TimelineEventKind::Transfer,
+            _ => TimelineEventKind::Status,
+        }
+    }
+}
+
+impl From<String> for TimelineEventKind {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents an event in the activity timeline of a server.
+///
+/// # Fields
+///
+/// * `name`: Provisioning step, new status of the server, disk change, volume
+///   ID of the backup archive, or status of the transfer.
+/// * `status`: Status of the provisioning step.
+/// * `detail`: Failure of the provisioning step, previous status of the
+///   server, device and size of the changed disk, or notes of the backup
+///   archive.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiTimelineEvent {
+    pub kind: TimelineEventKind,
+    pub occurred_at: DateTime<Utc>,
+    pub name: String,
+    pub status: Option<String>,
+    pub detail: Option<String>,
+}
+
+/// Represents the outcome of the start of a bulk action on a single server.
+///
+/// # Fields
+///
+/// * `status`: Transient status of the server while the action runs, `None`
+///   if the action couldn't start.
+/// * `error`: Reason the action couldn't start.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiActionResult {
+    pub server_id: Uuid,
+    pub status: Option<ServerStatus>,
+    pub error: Option<String>,
+}
+
+/// Server being provisioned, with everything its remaining steps need.
+///
+/// # Fields
+///
+/// * `vm_id`, `node_name`: Cloned VM, `None` before the clone step.
+/// * `template_node`, `template_vmid`: Template the VM is cloned from.
+/// * `storage`: Storage the VM is cloned to, of the product or else of the
+///   template, `None` for the storage of the template VM.
+/// * `ip_address`, `gateway`, `subnet_mask`: Reserved primary address.
+/// * `cloud_init_snippet`, `post_install_script`: Installation of the
+///   application of the template, if any.
+///
+#[derive(Debug, Clone)]
+pub struct ProvisioningTarget {
+    pub server_id: Uuid,
+    pub user_id: Uuid,
+    pub service_id: Uuid,
+    pub host_name: String,
+    pub vm_id: Option<i32>,
+    pub node_name: Option<String>,
+    pub template_node: String,
+    pub template_vmid: i32,
+    pub storage: Option<String>,
+    pub cpu_cores: Option<i32>,
+    pub ram_gb: Option<i32>,
+    pub net_rate_mbps: Option<i32>,
+    pub ip_address: String,
+    pub gateway: String,
+    pub subnet_mask: String,
+    pub cloud_init_snippet: Option<String>,
+    pub post_install_script: Option<String>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Lifecycle event delivered to the webhooks subscribed to it.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+pub enum WebhookEvent {
+    /// Server was provisioned.
+    #[display("server.created")]
+    #[serde(rename = "server.created")]
+    ServerCreated,
+    /// Server provisioning failed.
+    #[display("server.failed")]
+    #[serde(rename = "server.failed")]
+    ServerFailed,
+    /// Server was deleted.
+    #[display("server.deleted")]
+    #[serde(rename = "server.deleted")]
+    ServerDeleted,
+    /// Invoice was paid, by a payment or by credit.
+    #[display("invoice.paid")]
+    #[serde(rename = "invoice.paid")]
+    InvoicePaid,
+    /// Server used up the monthly traffic quota of its product.
+    #[display("traffic.quota_exceeded")]
+    #[serde(rename = "traffic.quota_exceeded")]
+    TrafficQuotaExceeded,
+}
+
+impl FromStr for WebhookEvent {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "server.created" => Ok(WebhookEvent::ServerCreated),
+            "server.failed" => Ok(WebhookEvent::ServerFailed),
+            "server.deleted" => Ok(WebhookEvent::ServerDeleted),
+            "invoice.paid" => Ok(WebhookEvent::InvoicePaid),
+            "traffic.quota_exceeded" => Ok(WebhookEvent::TrafficQuotaExceeded),
+            _ => Err(Error::Validation(format!("Unknown webhook event {value}"))),
+        }
+    }
+}
+
+/// Delivery status of a webhook event.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    /// Event waits for its first or next attempt.
+    Pending,
+    /// Receiver accepted the event.
+    Delivered,
+    /// All attempts failed, the event is not retried anymore.
+    Failed,
+}
+
+impl From<&str> for WebhookDeliveryStatus {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "delivered" => WebhookDeliveryStatus::Delivered,
+            "failed" => WebhookDeliveryStatus::Failed,
+            _ => WebhookDeliveryStatus::Pending,
+        }
+    }
+}
+
+impl From<String> for WebhookDeliveryStatus {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents a webhook that is safe to expose to the public API.
+///
+/// # Fields
+///
+/// * `secret`: Signing secret, only returned when the webhook is created.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiWebhook {
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Represents a network a user may access the account from.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiAllowedNetwork {
+    pub id: Uuid,
+    pub cidr: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Kind of an authentication attempt counted towards the CAPTCHA thresholds.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display)]
+pub enum AuthAttemptKind {
+    /// Login with an unknown email address or a wrong password.
+    FailedLogin,
+    /// Registration of a new account.
+    Registration,
+}
+
+/// SAML single sign-on of an organization.
+///
+/// # Fields
+///
+/// * `organization_id`: ID of the organization.
+/// * `idp_metadata`: Metadata XML of the identity provider.
+/// * `email_attribute`: Attribute with the email address of the user, `None`
+///   for the NameID.
+/// * `role_attribute`: Attribute with the roles or groups of the user, `None`
+///   to leave the roles to the owners and admins.
+/// * `admin_values`: Values of the role attribute making the member an admin.
+/// * `enforced`: Whether the members must log in with single sign-on.
+/// * `updated_at`: Time of the last change.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiSamlSettings {
+    pub organization_id: Uuid,
+    pub idp_metadata: String,
+    pub email_attribute: Option<String>,
+    pub role_attribute: Option<String>,
+    pub admin_values: Vec<String>,
+    pub enforced: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Login started with an OpenID Connect provider, awaiting the callback.
+///
+/// # Fields
+///
+/// * `provider`: Name of the provider.
+/// * `nonce`: Random nonce the ID token must contain.
+/// * `created_at`: Start time of the login.
+///
+#[derive(Debug, Clone)]
+pub struct OidcLogin {
+    pub provider: String,
+    pub nonce: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Represents a single delivery attempt of a webhook event.
+///
+/// # Fields
+///
+/// * `response_status`: HTTP status returned by the receiver, if it responded.
+/// * `error`: Reason of a failed attempt.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiWebhookAttempt {
+    pub delivery_id: Uuid,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub attempted_at: DateTime<Utc>,
+}
+
+/// Represents a webhook event delivery together with its attempts, safe to
+/// expose to the public API.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiWebhookDelivery {
+    pub id: Uuid,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub attempts: Vec<ApiWebhookAttempt>,
+}
+
+/// Webhook event delivery whose next attempt is due, together with the
+/// receiver.
+///
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of a single attempt to deliver a webhook event.
+///
+/// # Fields
+///
+/// * `response_status`: HTTP status returned by the receiver, if it responded.
+/// * `error`: Reason of a failed attempt, `None` if the event was delivered.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookAttempt {
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+}
+
+/// Status of a server transfer.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    /// Transfer waits for the recipient.
+    Pending,
+    /// Server belongs to the recipient.
+    Completed,
+    /// Recipient declined the transfer.
+    Declined,
+    /// Owner withdrew the transfer, or it was replaced.
+    Cancelled,
+}
+
+impl From<&str> for TransferStatus {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "completed" => TransferStatus::Completed,
+            "declined" => TransferStatus::Declined,
+            "cancelled" => TransferStatus::Cancelled,
+            _ => TransferStatus::Pending,
+        }
+    }
+}
+
+impl From<String> for TransferStatus {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents the transfer of a server to another account.
+///
+/// # Fields
+///
+/// * `from_user_id`: Owner of the server before the transfer.
+/// * `to_user_id`: Recipient of the server.
+/// * `initiated_by`: Owner or administrator who started the transfer.
+/// * `regenerate_credentials`: Whether the recipient gets a new password, and
+///   the SSH keys of the owner are removed.
+/// * `completed_at`: Time the transfer was completed, declined or cancelled.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiServerTransfer {
+    pub id: Uuid,
+    pub server_id: Uuid,
+    pub host_name: String,
+    pub from_user_id: Uuid,
+    pub to_user_id: Uuid,
+    pub initiated_by: Uuid,
+    pub status: TransferStatus,
+    pub regenerate_credentials: bool,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Represents a completed server transfer.
+///
+/// # Fields
+///
+/// * `password`: New cloud-init password of the server, only returned here
+///   when the credentials were regenerated.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiCompletedTransfer {
+    pub transfer: ApiServerTransfer,
+    pub password: Option<String>,
+}
+
+/// Payload for creating a new server transfer.
+///
+#[derive(Debug, Clone)]
+pub struct NewServerTransfer {
+    pub service_id: Uuid,
+    pub from_user_id: Uuid,
+    pub to_user_id: Uuid,
+    pub initiated_by: Uuid,
+    pub regenerate_credentials: bool,
+}
+
+/// Role of a user in an organization.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationRole {
+    /// Manages the members, including the other owners.
+    Owner,
+    /// Manages the members and the servers of the organization.
+    Admin,
+    /// Sees the servers of the organization.
+    Member,
+}
+
+impl OrganizationRole {
+    /// Checks whether the role manages the members and the servers.
+    ///
+    pub fn can_manage(&self) -> bool {
+        matches!(self, OrganizationRole::Owner | OrganizationRole::Admin)
+    }
+}
+
+impl From<&str> for OrganizationRole {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "owner" => OrganizationRole::Owner,
+            "admin" => OrganizationRole::Admin,
+            _ => OrganizationRole::Member,
+        }
+    }
+}
+
+impl From<String> for OrganizationRole {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents an organization of the user, with the role of the user in it.
+///
+/// # Fields
+///
+/// * `personal`: Whether this is the personal organization of the user, which
+///   owns the servers the user orders.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiOrganization {
+    pub id: Uuid,
+    pub name: String,
+    pub personal: bool,
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Represents a member of an organization.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiOrganizationMember {
+    pub user_id: Uuid,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub role: OrganizationRole,
+    pub created_at: DateTime<Utc>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Kind of an announcement, styling the banner.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncementKind {
+    /// General information.
+    Info,
+    /// Planned maintenance window.
+    Maintenance,
+    /// Ongoing incident.
+    Incident,
+}
+
+impl From<&str> for AnnouncementKind {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "maintenance" => AnnouncementKind::Maintenance,
+            "incident" => AnnouncementKind::Incident,
+            _ => AnnouncementKind::Info,
+        }
+    }
+}
+
+impl From<String> for AnnouncementKind {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents an announcement of the administrators.
+///
+/// # Fields
+///
+/// * `datacenter_code`: Datacenter whose users see the announcement, `None`
+///   for all users.
+/// * `publish_at`: Moment the announcement is shown from.
+/// * `expires_at`: Moment the announcement is hidden at, `None` to show it
+///   until it is deleted.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiAnnouncement {
+    pub id: Uuid,
+    pub kind: AnnouncementKind,
+    pub title: String,
+    pub message: String,
+    pub datacenter_code: Option<String>,
+    pub publish_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Event the users are notified about. Every notification is shown in the
+/// notification center, and emailed unless the user opted out of the event.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// Server was provisioned.
+    ServerReady,
+    /// Usage invoice was issued.
+    InvoiceDue,
+    /// Maintenance window was announced.
+    Maintenance,
+    /// Server used up the monthly traffic quota of its product.
+    TrafficQuota,
+}
+
+impl NotificationEvent {
+    /// Every event the users are notified about.
+    pub const ALL: [NotificationEvent; 4] = [
+        NotificationEvent::ServerReady,
+        NotificationEvent::InvoiceDue,
+        NotificationEvent::Maintenance,
+        NotificationEvent::TrafficQuota,
+    ];
+}
+
+impl From<&str> for NotificationEvent {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "serverready" => NotificationEvent::ServerReady,
+            "invoicedue" => NotificationEvent::InvoiceDue,
+            "trafficquota" => NotificationEvent::TrafficQuota,
+            _ => NotificationEvent::Maintenance,
+        }
+    }
+}
+
+impl From<String> for NotificationEvent {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Represents a notification in the notification center of a user.
+///
+/// # Fields
+///
+/// * `read_at`: Moment the user marked the notification as read, `None` while
+///   it is unread.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiNotification {
+    pub id: Uuid,
+    pub event: NotificationEvent,
+    pub title: String,
+    pub message: String,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// User notified about an event.
+///
+/// # Fields
+///
+/// * `locale`: Language preferred by the user, `None` for the default one.
+/// * `emailed`: Whether the user gets the event by email too.
+///
+#[derive(Debug, Clone)]
+pub struct NotificationRecipient {
+    pub user_id: Uuid,
+    pub email: String,
+    pub locale: Option<String>,
+    pub emailed: bool,
+}
+
+/// Notification of a user, in the language of the user.
+///
+/// # Fields
+///
+/// * `emailed`: Whether the notification is queued to be emailed too.
+///
+#[derive(Debug, Clone)]
+pub struct NewNotification {
+    pub user_id: Uuid,
+    pub title: String,
+    pub message: String,
+    pub emailed: bool,
+}
+
+/// Notification whose queued email is due, together with the address of its
+/// user.
+///
+/// # Fields
+///
+/// * `attempts`: Number of the failed attempts so far.
+///
+#[derive(Debug, Clone)]
+pub struct DueNotificationEmail {
+    pub id: Uuid,
+    pub email: String,
+    pub title: String,
+    pub message: String,
+    pub attempts: i32,
+}
+
+/// Represents whether a user gets an event by email, besides the notification
+/// center.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiNotificationPreference {
+    pub event: NotificationEvent,
+    pub email: bool,
+}
+
+/// Sensitive action recorded in the audit log.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// Password of a server was reset.
+    PasswordReset,
+    /// Server was moved to another account.
+    ServerTransferred,
+    /// Disk was attached to a server.
+    DiskAdded,
+    /// Disk of a server was grown.
+    DiskResized,
+}
+
+impl FromStr for AuditAction {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "PasswordReset" => Ok(AuditAction::PasswordReset),
+            "ServerTransferred" => Ok(AuditAction::ServerTransferred),
+            "DiskAdded" => Ok(AuditAction::DiskAdded),
+            "DiskResized" => Ok(AuditAction::DiskResized),
+            _ => Err(Error::Validation(format!("Unknown audit action {value}"))),
+        }
+    }
+}
+
+/// Represents an entry of the audit log.
+///
+/// # Fields
+///
+/// * `user_id`: User who performed the action, `None` once deleted.
+/// * `server_id`: Server the action was performed on, if any, `None` once
+///   deleted.
+/// * `details`: Action specific details, such as how a password was reset.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiAuditEntry {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub server_id: Option<Uuid>,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+// -----------------------------------------------------------------------------
+
+/// Kind of a change recorded in the `domain_events` log.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Display, Serialize, Deserialize, ToSchema)]
+pub enum DomainEventKind {
+    /// Server was provisioned.
+    #[display("server.created")]
+    #[serde(rename = "server.created")]
+    ServerCreated,
+    /// Server provisioning failed.
+    #[display("server.failed")]
+    #[serde(rename = "server.failed")]
+    ServerFailed,
+    /// Server was deleted.
+    #[display("server.deleted")]
+    #[serde(rename = "server.deleted")]
+    ServerDeleted,
+    /// Status of a server changed, after an action or the provisioning.
+    #[display("server.status_changed")]
+    #[serde(rename = "server.status_changed")]
+    ServerStatusChanged,
+    /// Invoice was paid, by a payment or by credit.
+    #[display("invoice.paid")]
+    #[serde(rename = "invoice.paid")]
+    InvoicePaid,
+    /// Server used up the monthly traffic quota of its product.
+    #[display("traffic.quota_exceeded")]
+    #[serde(rename = "traffic.quota_exceeded")]
+    TrafficQuotaExceeded,
+}
+
+impl DomainEventKind {
+    /// Returns the webhook event the change is delivered as, `None` if it
+    /// isn't delivered to the webhooks.
+    ///
+    pub fn webhook_event(self) -> Option<WebhookEvent> {
+        match self {
+            DomainEventKind::ServerCreated => Some(WebhookEvent::ServerCreated),
+            DomainEventKind::ServerFailed => Some(WebhookEvent::ServerFailed),
+            DomainEventKind::ServerDeleted => Some(WebhookEvent::ServerDeleted),
+            DomainEventKind::InvoicePaid => Some(WebhookEvent::InvoicePaid),
+            DomainEventKind::TrafficQuotaExceeded => Some(WebhookEvent::TrafficQuotaExceeded),
+            DomainEventKind::ServerStatusChanged => None,
+        }
+    }
+}
+
+impl FromStr for DomainEventKind {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "server.created" => Ok(DomainEventKind::ServerCreated),
+            "server.failed" => Ok(DomainEventKind::ServerFailed),
+            "server.deleted" => Ok(DomainEventKind::ServerDeleted),
+            "server.status_changed" => Ok(DomainEventKind::ServerStatusChanged),
+            "invoice.paid" => Ok(DomainEventKind::InvoicePaid),
+            "traffic.quota_exceeded" => Ok(DomainEventKind::TrafficQuotaExceeded),
+            _ => Err(Error::Validation(format!("Unknown domain event {value}"))),
+        }
+    }
+}
+
+/// Represents a row from the append-only `domain_events` table.
+///
+/// # Fields
+///
+/// * `id`: Position of the event in the log, increasing with every event.
+/// * `user_id`: User the changed resource belongs to.
+/// * `data`: Data of the event, such as the ID of the changed server.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DomainEvent {
+    pub id: i64,
+    pub kind: DomainEventKind,
+    pub user_id: Uuid,
+    pub data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Represents a replayed range of the domain events.
+///
+/// # Fields
+///
+/// * `replayed`: Number of the events in the range, handled again.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiEventReplay {
+    pub from_id: i64,
+    pub to_id: i64,
+    pub replayed: usize,
+}
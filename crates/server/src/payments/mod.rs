@@ -0,0 +1,46 @@
+pub mod stripe;
+pub mod types;
+
+// -----------------------------------------------------------------------------
+
+use crate::payments::types::*;
+use async_trait::async_trait;
+use dashboard_common::prelude::Result;
+
+/// An abstract interface for interacting with a payment provider.
+///
+/// Defines a contract for a client that can start a hosted checkout for an
+/// invoice and turn the provider's webhook calls into verified payment events.
+///
+#[async_trait]
+pub trait PaymentProvider {
+    /// Short name of the provider, stored alongside payments and processed
+    /// events (e.g., "stripe").
+    ///
+    fn name(&self) -> &'static str;
+
+    /// Create a hosted checkout session for the invoice.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: Invoice details to charge the customer for.
+    ///
+    /// # Returns
+    ///
+    /// Checkout session with the URL the customer should be redirected to.
+    ///
+    async fn create_checkout(&self, request: CheckoutRequest) -> Result<CheckoutSession>;
+
+    /// Verify the webhook signature and parse the event payload.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload`: Raw request body, exactly as it was received.
+    /// * `signature`: Signature header sent by the provider.
+    ///
+    /// # Returns
+    ///
+    /// Verified `PaymentEvent`.
+    ///
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> Result<PaymentEvent>;
+}
@@ -0,0 +1,460 @@
+use crate::config::PaymentsEnv;
+use crate::payments::PaymentProvider;
+use crate::payments::types::*;
+use async_trait::async_trait;
+use chrono::Utc;
+use dashboard_common::prelude::{Error, Result};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// Concrete implementation of the `PaymentProvider` trait for Stripe.
+///
+/// Creates Stripe Checkout sessions through the REST API and verifies webhook
+/// calls using the `Stripe-Signature` header scheme (HMAC-SHA256 over the
+/// timestamp and the raw payload).
+///
+pub struct StripeClient {
+    client: Client,
+    settings: PaymentsEnv,
+}
+
+impl StripeClient {
+    /// Creates a new instance of the Stripe client.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings`: All settings required to work with Stripe.
+    ///
+    pub fn new(settings: PaymentsEnv) -> Self {
+        Self {
+            client: Client::new(),
+            settings,
+        }
+    }
+
+    /// Verifies the `Stripe-Signature` header against the raw payload.
+    ///
+    /// The header has the form `t=<timestamp>,v1=<signature>[,v1=...]`. The
+    /// signed message is `<timestamp>.<payload>`, and the timestamp must be
+    /// within the configured tolerance to prevent replay attacks.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload`: Raw request body.
+    /// * `header`: Value of the `Stripe-Signature` header.
+    /// * `now`: Current UNIX timestamp in seconds.
+    ///
+    /// # Returns
+    ///
+    /// Empty `Ok(())` if at least one signature matches.
+    ///
+    fn verify_signature(&self, payload: &[u8], header: &str, now: i64) -> Result<()> {
+        let invalid = || Error::Validation("Invalid webhook signature".to_owned());
+
+        let mut timestamp = None;
+        let mut signatures = Vec::new();
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                Some(("v1", value)) => signatures.push(value),
+                _ => {}
+            }
+        }
+
+        let timestamp = timestamp.ok_or_else(invalid)?;
+        if now.abs_diff(timestamp) > self.settings.webhook_tolerance_sec {
+            return Err(invalid());
+        }
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(self.settings.webhook_secret.expose_secret().as_bytes())
+                .map_err(|_| invalid())?;
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(payload);
+
+        let is_valid = signatures.into_iter().any(|signature| {
+            hex::decode(signature).is_ok_and(|bytes| mac.clone().verify_slice(&bytes).is_ok())
+        });
+        match is_valid {
+            true => Ok(()),
+            false => Err(invalid()),
+        }
+    }
+}
+
+/// Parses a raw Stripe event payload without verifying its signature.
+///
+/// # Arguments
+///
+/// * `payload`: Raw request body.
+///
+/// # Returns
+///
+/// Parsed `PaymentEvent`.
+///
+pub fn parse_event(payload: &[u8]) -> Result<PaymentEvent> {
+    let event = serde_json::from_slice::<StripeEvent>(payload)
+        .map_err(|error| Error::Validation(format!("Malformed webhook payload: {error}")))?;
+
+    let kind = match event.event_type.as_str() {
+        "checkout.session.completed" => {
+            let (session, invoice_id) = parse_session(event.data.object)?;
+            match session.payment_status.as_str() {
+                "paid" => PaymentEventKind::CheckoutCompleted {
+                    session_id: session.id,
+                    invoice_id,
+                    amount_cents: session.amount_total.unwrap_or_default(),
+                },
+                // Delayed payment methods complete the checkout before the
+                // money arrives, the outcome follows as an async payment event.
+                _ => PaymentEventKind::Ignored,
+            }
+        }
+        "checkout.session.async_payment_succeeded" => {
+            let (session, invoice_id) = parse_session(event.data.object)?;
+            PaymentEventKind::CheckoutCompleted {
+                session_id: session.id,
+                invoice_id,
+                amount_cents: session.amount_total.unwrap_or_default(),
+            }
+        }
+        "checkout.session.async_payment_failed" => {
+            let (session, invoice_id) = parse_session(event.data.object)?;
+            PaymentEventKind::CheckoutFailed {
+                session_id: session.id,
+                invoice_id,
+            }
+        }
+        _ => PaymentEventKind::Ignored,
+    };
+
+    Ok(PaymentEvent {
+        id: event.id,
+        event_type: event.event_type,
+        kind,
+    })
+}
+
+/// Parses the checkout session of an event, with the invoice it pays.
+///
+fn parse_session(object: serde_json::Value) -> Result<(StripeCheckoutSession, Uuid)> {
+    let session = serde_json::from_value::<StripeCheckoutSession>(object)
+        .map_err(|error| Error::Validation(format!("Malformed checkout session: {error}")))?;
+    let invoice_id = session
+        .client_reference_id
+        .as_deref()
+        .and_then(|reference| Uuid::parse_str(reference).ok())
+        .ok_or_else(|| Error::Validation("Checkout session without invoice".to_owned()))?;
+
+    Ok((session, invoice_id))
+}
+
+#[async_trait]
+impl PaymentProvider for StripeClient {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    async fn create_checkout(&self, request: CheckoutRequest) -> Result<CheckoutSession> {
+        let url = format!("{}/checkout/sessions", self.settings.api_url);
+        let invoice_id = request.invoice_id.to_string();
+        let params = [
+            ("mode", "payment".to_owned()),
+            ("success_url", self.settings.success_url.clone()),
+            ("cancel_url", self.settings.cancel_url.clone()),
+            ("client_reference_id", invoice_id.clone()),
+            ("metadata[invoice_id]", invoice_id),
+            ("line_items[0][quantity]", "1".to_owned()),
+            ("line_items[0][price_data][currency]", request.currency),
+            (
+                "line_items[0][price_data][unit_amount]",
+                request.amount_cents.to_string(),
+            ),
+            (
+                "line_items[0][price_data][product_data][name]",
+                request.description,
+            ),
+        ];
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(self.settings.secret_key.expose_secret())
+            .form(&params)
+            .send()
+            .await?;
+
+        match response.status() {
+            status if status.is_success() => {
+                let session = response.json::<StripeCheckoutSession>().await?;
+                Ok(CheckoutSession {
+                    id: session.id,
+                    url: session.url.unwrap_or_default(),
+                })
+            }
+            status => {
+                let text = response.text().await?;
+                Err(Error::Payment(status, text))
+            }
+        }
+    }
+
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> Result<PaymentEvent> {
+        self.verify_signature(payload, signature, Utc::now().timestamp())?;
+        parse_event(payload)
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Raw Stripe event envelope.
+///
+#[derive(Deserialize)]
+struct StripeEvent {
+    id: String,
+    #[serde(rename = "type")]
+    event_type: String,
+    data: StripeEventData,
+}
+
+/// Object the Stripe event is about.
+///
+#[derive(Deserialize)]
+struct StripeEventData {
+    object: serde_json::Value,
+}
+
+/// Subset of the Stripe Checkout Session object used by the application.
+///
+#[derive(Deserialize)]
+struct StripeCheckoutSession {
+    id: String,
+    url: Option<String>,
+    client_reference_id: Option<String>,
+    #[serde(default)]
+    payment_status: String,
+    amount_total: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use reqwest::Method;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const SECRET_KEY: &str = "sk_test_123";
+    const WEBHOOK_SECRET: &str = "whsec_test_123";
+
+    fn settings(api_url: &str) -> PaymentsEnv {
+        PaymentsEnv {
+            api_url: api_url.to_owned(),
+            secret_key: SECRET_KEY.into(),
+            webhook_secret: WEBHOOK_SECRET.into(),
+            ..PaymentsEnv::default()
+        }
+    }
+
+    fn sign(payload: &[u8], timestamp: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(WEBHOOK_SECRET.as_bytes()).unwrap();
+        mac.update(format!("{timestamp}.").as_bytes());
+        mac.update(payload);
+        let signature = hex::encode(mac.finalize().into_bytes());
+        format!("t={timestamp},v1={signature}")
+    }
+
+    fn completed_event(invoice_id: Uuid) -> Vec<u8> {
+        json!({
+            "id": "evt_1",
+            "type": "checkout.session.completed",
+            "data": {"object": {
+                "id": "cs_test_1",
+                "client_reference_id": invoice_id.to_string(),
+                "payment_status": "paid",
+                "amount_total": 1500
+            }}
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[tokio::test]
+    async fn create_checkout_success() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let client = StripeClient::new(settings(&mock_server.uri()));
+        let invoice_id = Uuid::new_v4();
+        let response_json = json!({"id": "cs_test_1", "url": "https://checkout.stripe.com/c/1"});
+        Mock::given(method(Method::POST))
+            .and(path("/checkout/sessions"))
+            .and(header("authorization", format!("Bearer {SECRET_KEY}")))
+            .and(body_string_contains(format!(
+                "client_reference_id={invoice_id}"
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .create_checkout(CheckoutRequest {
+                invoice_id,
+                description: "VPS".to_owned(),
+                amount_cents: 1500,
+                currency: "usd".to_owned(),
+            })
+            .await;
+
+        // Assert
+        let session = result.unwrap();
+        assert_eq!(session.id, "cs_test_1");
+        assert_eq!(session.url, "https://checkout.stripe.com/c/1");
+    }
+
+    #[tokio::test]
+    async fn create_checkout_failure() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let client = StripeClient::new(settings(&mock_server.uri()));
+        Mock::given(method(Method::POST))
+            .and(path("/checkout/sessions"))
+            .respond_with(ResponseTemplate::new(402).set_body_string("Card declined"))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .create_checkout(CheckoutRequest {
+                invoice_id: Uuid::new_v4(),
+                description: "VPS".to_owned(),
+                amount_cents: 1500,
+                currency: "usd".to_owned(),
+            })
+            .await;
+
+        // Assert
+        match result.unwrap_err() {
+            Error::Payment(status, text) => {
+                assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+                assert_eq!(text, "Card declined");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn verify_webhook_accepts_valid_signature() {
+        // Arrange
+        let client = StripeClient::new(settings(""));
+        let invoice_id = Uuid::new_v4();
+        let payload = completed_event(invoice_id);
+        let signature = sign(&payload, Utc::now().timestamp());
+
+        // Act
+        let event = client.verify_webhook(&payload, &signature).unwrap();
+
+        // Assert
+        assert_eq!(event.id, "evt_1");
+        assert_eq!(
+            event.kind,
+            PaymentEventKind::CheckoutCompleted {
+                session_id: "cs_test_1".to_owned(),
+                invoice_id,
+                amount_cents: 1500,
+            }
+        );
+    }
+
+    #[test]
+    fn verify_webhook_rejects_tampered_payload() {
+        // Arrange
+        let client = StripeClient::new(settings(""));
+        let payload = completed_event(Uuid::new_v4());
+        let signature = sign(&payload, Utc::now().timestamp());
+        let tampered = completed_event(Uuid::new_v4());
+
+        // Act
+        let result = client.verify_webhook(&tampered, &signature);
+
+        // Assert
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn verify_webhook_rejects_stale_timestamp() {
+        // Arrange
+        let client = StripeClient::new(settings(""));
+        let payload = completed_event(Uuid::new_v4());
+        let signature = sign(&payload, Utc::now().timestamp() - 3600);
+
+        // Act
+        let result = client.verify_webhook(&payload, &signature);
+
+        // Assert
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn parse_event_maps_async_payment_outcomes() {
+        // Arrange
+        let invoice_id = Uuid::new_v4();
+        let event = |event_type: &str| {
+            json!({
+                "id": "evt_3",
+                "type": event_type,
+                "data": {"object": {
+                    "id": "cs_test_1",
+                    "client_reference_id": invoice_id.to_string(),
+                    "payment_status": "unpaid",
+                    "amount_total": 1500
+                }}
+            })
+            .to_string()
+        };
+
+        // Act
+        let completed = parse_event(event("checkout.session.completed").as_bytes()).unwrap();
+        let succeeded =
+            parse_event(event("checkout.session.async_payment_succeeded").as_bytes()).unwrap();
+        let failed =
+            parse_event(event("checkout.session.async_payment_failed").as_bytes()).unwrap();
+
+        // Assert
+        assert_eq!(completed.kind, PaymentEventKind::Ignored);
+        assert_eq!(
+            succeeded.kind,
+            PaymentEventKind::CheckoutCompleted {
+                session_id: "cs_test_1".to_owned(),
+                invoice_id,
+                amount_cents: 1500,
+            }
+        );
+        assert_eq!(
+            failed.kind,
+            PaymentEventKind::CheckoutFailed {
+                session_id: "cs_test_1".to_owned(),
+                invoice_id,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_event_ignores_unknown_types() {
+        // Arrange
+        let payload = json!({"id": "evt_2", "type": "customer.created", "data": {"object": {}}});
+
+        // Act
+        let event = parse_event(payload.to_string().as_bytes()).unwrap();
+
+        // Assert
+        assert_eq!(event.event_type, "customer.created");
+        assert_eq!(event.kind, PaymentEventKind::Ignored);
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Invoice details required to start a checkout.
+///
+/// # Fields
+///
+/// * `invoice_id`: ID of the invoice being paid.
+/// * `description`: Human-readable line item shown on the checkout page.
+/// * `amount_cents`: Amount to charge in the smallest currency unit.
+/// * `currency`: Three-letter ISO currency code (e.g., "usd").
+///
+#[derive(Debug, Clone)]
+pub struct CheckoutRequest {
+    pub invoice_id: Uuid,
+    pub description: String,
+    pub amount_cents: i64,
+    pub currency: String,
+}
+
+/// Hosted checkout session created by the payment provider.
+///
+/// # Fields
+///
+/// * `id`: Provider-side ID of the session.
+/// * `url`: URL of the hosted payment page.
+///
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CheckoutSession {
+    pub id: String,
+    pub url: String,
+}
+
+/// Verified event received from the payment provider.
+///
+/// # Fields
+///
+/// * `id`: Provider-side unique ID of the event, used for idempotency.
+/// * `event_type`: Raw event type (e.g., "checkout.session.completed").
+/// * `kind`: Parsed event data the application acts on.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentEvent {
+    pub id: String,
+    pub event_type: String,
+    pub kind: PaymentEventKind,
+}
+
+/// Payment events the application reacts to.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaymentEventKind {
+    /// Customer completed the checkout and the payment was captured, right
+    /// away or later by a delayed payment method.
+    CheckoutCompleted {
+        session_id: String,
+        invoice_id: Uuid,
+        amount_cents: i64,
+    },
+    /// Delayed payment of a completed checkout failed.
+    CheckoutFailed {
+        session_id: String,
+        invoice_id: Uuid,
+    },
+    /// Any other event, acknowledged but not processed.
+    Ignored,
+}
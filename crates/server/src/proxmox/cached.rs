@@ -0,0 +1,275 @@
+use crate::model::cache::TtlCache;
+use crate::model::types::BackupMode;
+use crate::proxmox::Proxmox;
+use crate::proxmox::types::*;
+use async_trait::async_trait;
+use dashboard_common::prelude::Result;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decorator of a `Proxmox` client that caches the power status of the VMs
+/// for a short time.
+///
+/// List endpoints, status pollers and the reconciliation read the status of
+/// the same VMs in bursts, so those reads are served from the cache instead of
+/// each reaching Proxmox. Operations changing the power status of a VM drop
+/// its cached status, every other call goes straight to the inner client.
+///
+pub struct CachedProxmox {
+    inner: Arc<dyn Proxmox + Send + Sync>,
+    statuses: TtlCache<VmRef, Status>,
+}
+
+impl CachedProxmox {
+    /// Wraps the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner`: Client performing the calls.
+    /// * `ttl`: Time a cached status stays valid, zero disables the cache.
+    ///
+    pub fn new(inner: Arc<dyn Proxmox + Send + Sync>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            statuses: TtlCache::new(ttl),
+        }
+    }
+
+    /// Drops the cached status of the VM, passing the result of the operation
+    /// changing it through.
+    ///
+    fn forget<T>(&self, vm: &VmRef, result: Result<T>) -> Result<T> {
+        self.statuses.remove(vm);
+        result
+    }
+}
+
+#[async_trait]
+impl Proxmox for CachedProxmox {
+    async fn start(&self, vm: VmRef) -> Result<UniqueProcessId> {
+        self.forget(&vm, self.inner.start(vm.clone()).await)
+    }
+
+    async fn shutdown(&self, vm: VmRef) -> Result<UniqueProcessId> {
+        self.forget(&vm, self.inner.shutdown(vm.clone()).await)
+    }
+
+    async fn stop(&self, vm: VmRef) -> Result<UniqueProcessId> {
+        self.forget(&vm, self.inner.stop(vm.clone()).await)
+    }
+
+    async fn reboot(&self, vm: VmRef) -> Result<UniqueProcessId> {
+        self.forget(&vm, self.inner.reboot(vm.clone()).await)
+    }
+
+    async fn create(&self, vm: VmRef, storage: Option<&str>) -> Result<(i32, UniqueProcessId)> {
+        self.inner.create(vm, storage).await
+    }
+
+    async fn delete(&self, vm: VmRef) -> Result<UniqueProcessId> {
+        self.forget(&vm, self.inner.delete(vm.clone()).await)
+    }
+
+    async fn vm_config(&self, vm: VmRef, config: VmConfig) -> Result<UniqueProcessId> {
+        self.inner.vm_config(vm, config).await
+    }
+
+    async fn vm_current_config(&self, vm: VmRef) -> Result<VmCurrentConfig> {
+        self.inner.vm_current_config(vm).await
+    }
+
+    async fn vm_drives(&self, vm: VmRef) -> Result<Vec<VmDrive>> {
+        self.inner.vm_drives(vm).await
+    }
+
+    async fn resize_disk(&self, vm: VmRef, disk: &str, size_gb: i32) -> Result<UniqueProcessId> {
+        self.inner.resize_disk(vm, disk, size_gb).await
+    }
+
+    async fn vm_exists(&self, vm: VmRef) -> Result<bool> {
+        self.inner.vm_exists(vm).await
+    }
+
+    async fn vm_status(&self, vm: VmRef) -> Result<Status> {
+        self.statuses
+            .get_or_load(vm.clone(), self.inner.vm_status(vm))
+            .await
+    }
+
+    async fn vm_usage(&self, vm: VmRef) -> Result<VmUsage> {
+        self.inner.vm_usage(vm).await
+    }
+
+    async fn vm_rrd_data(&self, vm: VmRef) -> Result<Vec<RrdSample>> {
+        self.inner.vm_rrd_data(vm).await
+    }
+
+    async fn firewall_options(&self, vm: VmRef, options: FirewallOptions) -> Result<()> {
+        self.inner.firewall_options(vm, options).await
+    }
+
+    async fn firewall_rules(&self, vm: VmRef) -> Result<Vec<FirewallRuleInfo>> {
+        self.inner.firewall_rules(vm).await
+    }
+
+    async fn create_firewall_rule(&self, vm: VmRef, rule: FirewallRule) -> Result<()> {
+        self.inner.create_firewall_rule(vm, rule).await
+    }
+
+    async fn delete_firewall_rule(&self, vm: VmRef, pos: i32) -> Result<()> {
+        self.inner.delete_firewall_rule(vm, pos).await
+    }
+
+    async fn backup(
+        &self,
+        vm: VmRef,
+        storage: &str,
+        mode: BackupMode,
+        notes: &str,
+    ) -> Result<UniqueProcessId> {
+        self.inner.backup(vm, storage, mode, notes).await
+    }
+
+    async fn backups(&self, vm: VmRef, storage: &str) -> Result<Vec<BackupArchive>> {
+        self.inner.backups(vm, storage).await
+    }
+
+    async fn restore(&self, vm: VmRef, archive: &str) -> Result<UniqueProcessId> {
+        self.forget(&vm, self.inner.restore(vm.clone(), archive).await)
+    }
+
+    async fn agent_ping(&self, vm: VmRef) -> Result<()> {
+        self.inner.agent_ping(vm).await
+    }
+
+    async fn agent_os_info(&self, vm: VmRef) -> Result<GuestOsInfo> {
+        self.inner.agent_os_info(vm).await
+    }
+
+    async fn agent_network_interfaces(&self, vm: VmRef) -> Result<Vec<GuestNetworkInterface>> {
+        self.inner.agent_network_interfaces(vm).await
+    }
+
+    async fn agent_set_user_password(
+        &self,
+        vm: VmRef,
+        username: &str,
+        password: &str,
+    ) -> Result<()> {
+        self.inner
+            .agent_set_user_password(vm, username, password)
+            .await
+    }
+
+    async fn agent_exec(&self, vm: VmRef, command: &str, input: &str) -> Result<i64> {
+        self.inner.agent_exec(vm, command, input).await
+    }
+
+    async fn agent_exec_status(&self, vm: VmRef, pid: i64) -> Result<GuestExecStatus> {
+        self.inner.agent_exec_status(vm, pid).await
+    }
+
+    async fn list_isos(&self, node: &str, storage: &str) -> Result<Vec<IsoImage>> {
+        self.inner.list_isos(node, storage).await
+    }
+
+    async fn set_cdrom(
+        &self,
+        vm: VmRef,
+        device: &str,
+        iso: Option<&str>,
+    ) -> Result<UniqueProcessId> {
+        self.inner.set_cdrom(vm, device, iso).await
+    }
+
+    async fn set_boot_order(&self, vm: VmRef, devices: &[String]) -> Result<UniqueProcessId> {
+        self.inner.set_boot_order(vm, devices).await
+    }
+
+    async fn list_nodes(&self) -> Result<Vec<NodeListItem>> {
+        self.inner.list_nodes().await
+    }
+
+    async fn node_status(&self, node: &str) -> Result<NodeStatus> {
+        self.inner.node_status(node).await
+    }
+
+    async fn list_storages(&self, node: &str) -> Result<Vec<StorageInfo>> {
+        self.inner.list_storages(node).await
+    }
+
+    async fn task_status(&self, task: &TaskRef) -> Result<TaskStatus> {
+        self.inner.task_status(task).await
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxmox::client::ProxmoxClient;
+    use reqwest::Method;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn setup(ttl: Duration) -> (MockServer, CachedProxmox) {
+        let mock_server = MockServer::start().await;
+        let client = ProxmoxClient::new(mock_server.uri(), "PVEAPIToken=test".into()).unwrap();
+
+        (mock_server, CachedProxmox::new(Arc::new(client), ttl))
+    }
+
+    #[tokio::test]
+    async fn burst_of_status_reads_should_reach_proxmox_once() {
+        // Arrange
+        let (mock_server, client) = setup(Duration::from_secs(60)).await;
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/status/current"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"data": {"status": "running"}})),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        for _ in 0..5 {
+            let status = client.vm_status(VmRef::new("pve", 100)).await;
+
+            // Assert
+            assert_eq!(status.unwrap(), Status::Running);
+        }
+    }
+
+    #[tokio::test]
+    async fn power_operation_should_drop_cached_status() {
+        // Arrange
+        let (mock_server, client) = setup(Duration::from_secs(60)).await;
+        let vm = VmRef::new("pve", 100);
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/status/current"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"data": {"status": "stopped"}})),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/status/start"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"data": "UPID:pve:12345678:90ABCDEF:12345678:qmstart:100:id@realm:"}),
+            ))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        client.vm_status(vm.clone()).await.unwrap();
+        client.start(vm.clone()).await.unwrap();
+        let status = client.vm_status(vm).await;
+
+        // Assert
+        assert_eq!(status.unwrap(), Status::Stopped);
+    }
+}
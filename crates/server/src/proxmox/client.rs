@@ -1,12 +1,18 @@
+use crate::config::secrets::Secret;
+use crate::model::types::BackupMode;
 use crate::proxmox::Proxmox;
+use crate::proxmox::queue::RequestQueue;
 use crate::proxmox::types::*;
 use async_trait::async_trait;
 use dashboard_common::prelude::{Error, ProxmoxError, Result};
-use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use reqwest::header::{AUTHORIZATION, HeaderValue};
 use reqwest::{Client, Method};
-use secrecy::{ExposeSecret, SecretString};
+use secrecy::ExposeSecret;
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use tokio::sync::OnceCell;
 
 /// Concrete implementation of the `Proxmox` trait using `reqwest` crate.
@@ -18,7 +24,8 @@ use tokio::sync::OnceCell;
 pub struct ProxmoxClient {
     client: OnceCell<Client>,
     url: String,
-    auth_header: SecretString,
+    auth_header: Secret,
+    queue: Arc<RequestQueue>,
 }
 
 impl ProxmoxClient {
@@ -27,33 +34,39 @@ impl ProxmoxClient {
     /// # Arguments
     ///
     /// * `url`: URL of the Proxmox API.
-    /// * `auth_header`: The full, pre-formatted authorization header string.
+    /// * `auth_header`: The full, pre-formatted authorization header string,
+    ///   read on every request, so it may rotate.
     ///
-    pub fn new(url: String, auth_header: SecretString) -> Result<Self> {
+    pub fn new(url: String, auth_header: Secret) -> Result<Self> {
         Ok(Self {
             client: OnceCell::new(),
             url,
             auth_header,
+            queue: Arc::new(RequestQueue::new(0)),
         })
     }
 
+    /// Sends the requests through the queue, bounding how many of them reach
+    /// the cluster at once. Without a queue the requests are not bounded.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue`: Queue shared with the metrics.
+    ///
+    pub fn with_queue(mut self, queue: Arc<RequestQueue>) -> Self {
+        self.queue = queue;
+        self
+    }
+
     /// Lazily initializes and returns a reference to the `reqwest::Client`.
     ///
     /// If the client has not been initialized yet, it will be built on the
-    /// first call with default headers (including Authorization). Subsequent
-    /// calls will return the existing client.
+    /// first call. Subsequent calls will return the existing client.
     ///
     async fn get_client(&self) -> Result<&Client> {
         self.client
             .get_or_try_init(|| async {
-                let mut auth_header = HeaderValue::from_str(self.auth_header.expose_secret())?;
-                auth_header.set_sensitive(true);
-
-                let mut headers = HeaderMap::new();
-                headers.insert(AUTHORIZATION, auth_header);
-
                 Client::builder()
-                    .default_headers(headers)
                     .danger_accept_invalid_certs(true)
                     .danger_accept_invalid_hostnames(true)
                     .use_rustls_tls()
@@ -99,9 +112,13 @@ impl ProxmoxClient {
     {
         let client = self.get_client().await?;
         let url = format!("{}{}", self.url, path);
+        let mut auth_header = HeaderValue::from_str(self.auth_header.get().expose_secret())?;
+        auth_header.set_sensitive(true);
 
+        let _permit = self.queue.acquire().await;
         let response = client
             .request(method, &url)
+            .header(AUTHORIZATION, auth_header)
             .form(&body.unwrap_or_default())
             .send()
             .await?;
@@ -142,7 +159,11 @@ impl Proxmox for ProxmoxClient {
             .await
     }
 
-    async fn create(&self, template_vm: VmRef) -> Result<(i32, UniqueProcessId)> {
+    async fn create(
+        &self,
+        template_vm: VmRef,
+        storage: Option<&str>,
+    ) -> Result<(i32, UniqueProcessId)> {
         // Get next free VMID.
         let new_id_str: String = self
             .make_request(
@@ -156,7 +177,7 @@ impl Proxmox for ProxmoxClient {
 
         // Create a copy of virtual machine/template.
         let path = format!("/nodes/{}/qemu/{}/clone", template_vm.node, template_vm.id);
-        let params = HashMap::from([("newid", new_id)]);
+        let params = CloneParams::new(new_id, storage);
         let upid: UniqueProcessId = self
             .make_request(Method::POST, &path, Some(params), ProxmoxError::Create)
             .await?;
@@ -176,6 +197,38 @@ impl Proxmox for ProxmoxClient {
             .await
     }
 
+    async fn vm_current_config(&self, vm: VmRef) -> Result<VmCurrentConfig> {
+        let path = format!("/nodes/{}/qemu/{}/config", vm.node, vm.id);
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Status)
+            .await
+    }
+
+    async fn vm_drives(&self, vm: VmRef) -> Result<Vec<VmDrive>> {
+        let path = format!("/nodes/{}/qemu/{}/config", vm.node, vm.id);
+        let config: BTreeMap<String, Value> = self
+            .make_request(Method::GET, &path, None::<()>, ProxmoxError::Status)
+            .await?;
+        Ok(config
+            .iter()
+            .filter_map(|(key, value)| VmDrive::parse(key, value.as_str()?))
+            .collect())
+    }
+
+    async fn resize_disk(&self, vm: VmRef, disk: &str, size_gb: i32) -> Result<UniqueProcessId> {
+        let path = format!("/nodes/{}/qemu/{}/resize", vm.node, vm.id);
+        let params = ResizeParams::new(disk, size_gb);
+        self.make_request(Method::PUT, &path, Some(params), ProxmoxError::Disk)
+            .await
+    }
+
+    async fn vm_exists(&self, vm: VmRef) -> Result<bool> {
+        let path = format!("/nodes/{}/qemu", vm.node);
+        let vms: Vec<VmListItem> = self
+            .make_request(Method::GET, &path, None::<()>, ProxmoxError::Status)
+            .await?;
+        Ok(vms.iter().any(|item| item.vmid == vm.id))
+    }
+
     async fn vm_status(&self, vm: VmRef) -> Result<Status> {
         let path = format!("/nodes/{}/qemu/{}/status/current", vm.node, vm.id);
         let payload: StatusPayload = self
@@ -184,6 +237,175 @@ impl Proxmox for ProxmoxClient {
         Ok(payload.status)
     }
 
+    async fn vm_usage(&self, vm: VmRef) -> Result<VmUsage> {
+        let path = format!("/nodes/{}/qemu/{}/status/current", vm.node, vm.id);
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Status)
+            .await
+    }
+
+    async fn vm_rrd_data(&self, vm: VmRef) -> Result<Vec<RrdSample>> {
+        let path = format!(
+            "/nodes/{}/qemu/{}/rrddata?timeframe=day&cf=AVERAGE",
+            vm.node, vm.id
+        );
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Traffic)
+            .await
+    }
+
+    async fn firewall_options(&self, vm: VmRef, options: FirewallOptions) -> Result<()> {
+        let path = format!("/nodes/{}/qemu/{}/firewall/options", vm.node, vm.id);
+        self.make_request(Method::PUT, &path, Some(options), ProxmoxError::Firewall)
+            .await
+    }
+
+    async fn firewall_rules(&self, vm: VmRef) -> Result<Vec<FirewallRuleInfo>> {
+        let path = format!("/nodes/{}/qemu/{}/firewall/rules", vm.node, vm.id);
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Firewall)
+            .await
+    }
+
+    async fn create_firewall_rule(&self, vm: VmRef, rule: FirewallRule) -> Result<()> {
+        let path = format!("/nodes/{}/qemu/{}/firewall/rules", vm.node, vm.id);
+        self.make_request(Method::POST, &path, Some(rule), ProxmoxError::Firewall)
+            .await
+    }
+
+    async fn delete_firewall_rule(&self, vm: VmRef, pos: i32) -> Result<()> {
+        let path = format!("/nodes/{}/qemu/{}/firewall/rules/{}", vm.node, vm.id, pos);
+        self.make_request(Method::DELETE, &path, None::<()>, ProxmoxError::Firewall)
+            .await
+    }
+
+    async fn backup(
+        &self,
+        vm: VmRef,
+        storage: &str,
+        mode: BackupMode,
+        notes: &str,
+    ) -> Result<UniqueProcessId> {
+        let path = format!("/nodes/{}/vzdump", vm.node);
+        let params = BackupParams::new(&vm, storage, mode, notes);
+        self.make_request(Method::POST, &path, Some(params), ProxmoxError::Backup)
+            .await
+    }
+
+    async fn backups(&self, vm: VmRef, storage: &str) -> Result<Vec<BackupArchive>> {
+        let path = format!(
+            "/nodes/{}/storage/{}/content?content=backup&vmid={}",
+            vm.node, storage, vm.id
+        );
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Backup)
+            .await
+    }
+
+    async fn restore(&self, vm: VmRef, archive: &str) -> Result<UniqueProcessId> {
+        let path = format!("/nodes/{}/qemu", vm.node);
+        let params = RestoreParams::new(&vm, archive);
+        self.make_request(Method::POST, &path, Some(params), ProxmoxError::Restore)
+            .await
+    }
+
+    async fn agent_ping(&self, vm: VmRef) -> Result<()> {
+        let path = format!("/nodes/{}/qemu/{}/agent/ping", vm.node, vm.id);
+        let _: IgnoredAny = self
+            .make_request(Method::POST, &path, None::<()>, ProxmoxError::Agent)
+            .await?;
+        Ok(())
+    }
+
+    async fn agent_os_info(&self, vm: VmRef) -> Result<GuestOsInfo> {
+        let path = format!("/nodes/{}/qemu/{}/agent/get-osinfo", vm.node, vm.id);
+        let data: AgentResponse<GuestOsInfo> = self
+            .make_request(Method::GET, &path, None::<()>, ProxmoxError::Agent)
+            .await?;
+        Ok(data.result)
+    }
+
+    async fn agent_network_interfaces(&self, vm: VmRef) -> Result<Vec<GuestNetworkInterface>> {
+        let path = format!(
+            "/nodes/{}/qemu/{}/agent/network-get-interfaces",
+            vm.node, vm.id
+        );
+        let data: AgentResponse<Vec<GuestNetworkInterface>> = self
+            .make_request(Method::GET, &path, None::<()>, ProxmoxError::Agent)
+            .await?;
+        Ok(data.result)
+    }
+
+    async fn agent_set_user_password(
+        &self,
+        vm: VmRef,
+        username: &str,
+        password: &str,
+    ) -> Result<()> {
+        let path = format!("/nodes/{}/qemu/{}/agent/set-user-password", vm.node, vm.id);
+        let params = UserPasswordParams::new(username, password);
+        let _: IgnoredAny = self
+            .make_request(Method::POST, &path, Some(params), ProxmoxError::Agent)
+            .await?;
+        Ok(())
+    }
+
+    async fn agent_exec(&self, vm: VmRef, command: &str, input: &str) -> Result<i64> {
+        let path = format!("/nodes/{}/qemu/{}/agent/exec", vm.node, vm.id);
+        let params = ExecParams::new(command, input);
+        let data: GuestExec = self
+            .make_request(Method::POST, &path, Some(params), ProxmoxError::Agent)
+            .await?;
+        Ok(data.pid)
+    }
+
+    async fn agent_exec_status(&self, vm: VmRef, pid: i64) -> Result<GuestExecStatus> {
+        let path = format!(
+            "/nodes/{}/qemu/{}/agent/exec-status?pid={}",
+            vm.node, vm.id, pid
+        );
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Agent)
+            .await
+    }
+
+    async fn list_isos(&self, node: &str, storage: &str) -> Result<Vec<IsoImage>> {
+        let path = format!("/nodes/{}/storage/{}/content?content=iso", node, storage);
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Iso)
+            .await
+    }
+
+    async fn set_cdrom(
+        &self,
+        vm: VmRef,
+        device: &str,
+        iso: Option<&str>,
+    ) -> Result<UniqueProcessId> {
+        let path = format!("/nodes/{}/qemu/{}/config", vm.node, vm.id);
+        let config = VmConfig::builder().cdrom(device, iso).build();
+        self.make_request(Method::POST, &path, Some(config), ProxmoxError::Iso)
+            .await
+    }
+
+    async fn set_boot_order(&self, vm: VmRef, devices: &[String]) -> Result<UniqueProcessId> {
+        let path = format!("/nodes/{}/qemu/{}/config", vm.node, vm.id);
+        let config = VmConfig::builder().boot_order(devices).build();
+        self.make_request(Method::POST, &path, Some(config), ProxmoxError::Iso)
+            .await
+    }
+
+    async fn list_nodes(&self) -> Result<Vec<NodeListItem>> {
+        self.make_request(Method::GET, "/nodes", None::<()>, ProxmoxError::Status)
+            .await
+    }
+
+    async fn node_status(&self, node: &str) -> Result<NodeStatus> {
+        let path = format!("/nodes/{}/status", node);
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Status)
+            .await
+    }
+
+    async fn list_storages(&self, node: &str) -> Result<Vec<StorageInfo>> {
+        let path = format!("/nodes/{}/storage", node);
+        self.make_request(Method::GET, &path, None::<()>, ProxmoxError::Status)
+            .await
+    }
+
     async fn task_status(&self, task: &TaskRef) -> Result<TaskStatus> {
         let path = format!("/nodes/{}/tasks/{}/status", task.node, task.upid.encoded());
         let data: TaskResponse = self
@@ -204,7 +426,9 @@ mod tests {
     use crate::proxmox::types::{TaskRef, VmRef};
     use axum::http::StatusCode;
     use serde_json::json;
-    use wiremock::matchers::{header, method, path};
+    use wiremock::matchers::{
+        body_string, body_string_contains, header, method, path, query_param,
+    };
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     const FAKE_UPID: &str = "UPID:pve:12345678:90ABCDEF:12345678:type:100:id@realm:";
@@ -421,7 +645,7 @@ mod tests {
             .await;
 
         // Act
-        let result = client.create(VmRef::new("pve", 100)).await;
+        let result = client.create(VmRef::new("pve", 100), None).await;
 
         // Assert
         assert!(result.is_ok());
@@ -429,6 +653,38 @@ mod tests {
         assert_eq!(upid.into_inner(), FAKE_UPID);
     }
 
+    #[tokio::test]
+    async fn clone_vm_to_storage_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_vmid_json = json!({"data": "101"});
+        let response_upid_json = json!({"data": FAKE_UPID});
+        Mock::given(method(Method::GET))
+            .and(path("/cluster/nextid"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_vmid_json))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/clone"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string_contains("full=1"))
+            .and(body_string_contains("storage=local-zfs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_upid_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .create(VmRef::new("pve", 100), Some("local-zfs"))
+            .await;
+
+        // Assert
+        let (vmid, upid) = result.unwrap();
+        assert_eq!(vmid, 101);
+        assert_eq!(upid.into_inner(), FAKE_UPID);
+    }
+
     #[tokio::test]
     async fn clone_vm_failure_second() {
         // Arrange
@@ -448,7 +704,7 @@ mod tests {
             .await;
 
         // Act
-        let result = client.create(VmRef::new("pve", 100)).await;
+        let result = client.create(VmRef::new("pve", 100), None).await;
 
         // Assert
         assert!(result.is_err());
@@ -473,7 +729,7 @@ mod tests {
             .await;
 
         // Act
-        let result = client.create(VmRef::new("pve", 100)).await;
+        let result = client.create(VmRef::new("pve", 100), None).await;
 
         // Assert
         assert!(result.is_err());
@@ -578,86 +834,187 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn task_status_pending() {
+    async fn vm_current_config_success() {
         // Arrange
         let (mock_server, client) = setup().await;
-        let upid = UniqueProcessId::from(FAKE_UPID);
-        let response_json = json!({"data": {"status": "running"}});
+        let response_json = json!({"data": {
+            "cores": 2,
+            "net0": "virtio=BC:24:11:2A:3B:4C,bridge=vmbr0"
+        }});
         Mock::given(method(Method::GET))
-            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(path("/nodes/pve/qemu/100/config"))
             .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
             .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
             .mount(&mock_server)
             .await;
 
         // Act
-        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+        let result = client.vm_current_config(VmRef::new("pve", 100)).await;
 
         // Assert
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), TaskStatus::Pending);
+        assert_eq!(
+            result.unwrap(),
+            VmCurrentConfig {
+                net0: Some("virtio=BC:24:11:2A:3B:4C,bridge=vmbr0".to_owned()),
+                template: None,
+            }
+        );
     }
 
     #[tokio::test]
-    async fn task_status_completed() {
+    async fn vm_exists_success() {
         // Arrange
         let (mock_server, client) = setup().await;
-        let upid = UniqueProcessId::from(FAKE_UPID);
-        let response_json = json!({"data": {"status": "stopped", "exitstatus": "OK"}});
+        let response_json = json!({"data": [
+            {"vmid": 100, "status": "running"},
+            {"vmid": 9000, "status": "stopped", "template": 1}
+        ]});
         Mock::given(method(Method::GET))
-            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(path("/nodes/pve/qemu"))
             .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
             .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
             .mount(&mock_server)
             .await;
 
         // Act
-        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+        let existing = client.vm_exists(VmRef::new("pve", 9000)).await;
+        let missing = client.vm_exists(VmRef::new("pve", 9001)).await;
+
+        // Assert
+        assert!(existing.unwrap());
+        assert!(!missing.unwrap());
+    }
+
+    #[tokio::test]
+    async fn vm_config_should_send_form_fields() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": FAKE_UPID});
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/config"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string(
+                "balloon=1024&ciuser=admin&cores=2&description=Web+server\
+                 &ipconfig0=ip%3D10.0.0.2%2F24%2Cgw%3D10.0.0.1&memory=2048\
+                 &net0=virtio%2Cbridge%3Dvmbr0&onboot=1&sockets=1\
+                 &sshkeys=ssh%252Ded25519%2520AAAA%2520user%2540host",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+        let config = VmConfig::builder()
+            .cores(2)
+            .sockets(1)
+            .memory(2048)
+            .balloon(1024)
+            .net(0, "virtio,bridge=vmbr0")
+            .ipconfig(0, "ip=10.0.0.2/24,gw=10.0.0.1")
+            .ciuser("admin")
+            .sshkeys(&["ssh-ed25519 AAAA user@host".to_owned()])
+            .onboot(true)
+            .description("Web server")
+            .build();
+
+        // Act
+        let result = client.vm_config(VmRef::new("pve", 100), config).await;
+
+        // Assert
+        assert_eq!(result.unwrap().into_inner(), FAKE_UPID);
+    }
+
+    #[tokio::test]
+    async fn vm_config_should_send_deleted_options() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": FAKE_UPID});
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/config"))
+            .and(body_string("delete=net1%2Cipconfig1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .vm_config(VmRef::new("pve", 100), VmConfig::remove_device(1))
+            .await;
 
         // Assert
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), TaskStatus::Completed);
     }
 
     #[tokio::test]
-    async fn task_status_failed() {
+    async fn vm_current_config_failure() {
         // Arrange
         let (mock_server, client) = setup().await;
-        let upid = UniqueProcessId::from(FAKE_UPID);
-        let response_json =
-            json!({"data": {"status": "stopped", "exitstatus": "ERROR: command failed"}});
         Mock::given(method(Method::GET))
-            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(path("/nodes/pve/qemu/100/config"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(500).set_body_string("Internal Server Error"))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.vm_current_config(VmRef::new("pve", 100)).await;
+
+        // Assert
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::Proxmox(ProxmoxError::Status, status, text) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(text, "Internal Server Error");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn vm_usage_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": {
+            "status": "running",
+            "uptime": 7200,
+            "cpu": 0.25,
+            "mem": 1073741824u64
+        }});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/status/current"))
             .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
             .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
             .mount(&mock_server)
             .await;
 
         // Act
-        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+        let result = client.vm_usage(VmRef::new("pve", 100)).await;
 
         // Assert
         assert!(result.is_ok());
         assert_eq!(
             result.unwrap(),
-            TaskStatus::Failed("ERROR: command failed".to_owned())
+            VmUsage {
+                status: Status::Running,
+                uptime: 7200,
+                cpu: 0.25,
+                mem: 1073741824,
+            }
         );
     }
 
     #[tokio::test]
-    async fn task_status_failure() {
+    async fn vm_usage_failure() {
         // Arrange
         let (mock_server, client) = setup().await;
-        let upid = UniqueProcessId::from(FAKE_UPID);
         Mock::given(method(Method::GET))
-            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(path("/nodes/pve/qemu/100/status/current"))
             .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
             .respond_with(ResponseTemplate::new(500).set_body_string("Internal Server Error"))
             .mount(&mock_server)
             .await;
 
         // Act
-        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+        let result = client.vm_usage(VmRef::new("pve", 100)).await;
 
         // Assert
         assert!(result.is_err());
@@ -669,4 +1026,787 @@ mod tests {
             error => panic!("unexpected error: {}", error),
         }
     }
+
+    #[tokio::test]
+    async fn list_nodes_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": [
+            {"node": "pve", "status": "online", "cpu": 0.1, "maxcpu": 16},
+            {"node": "pve-2", "status": "offline"}
+        ]});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.list_nodes().await.unwrap();
+
+        // Assert
+        assert_eq!(result.len(), 2);
+        assert!(result[0].is_online());
+        assert!(!result[1].is_online());
+    }
+
+    #[tokio::test]
+    async fn node_status_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": {
+            "uptime": 86400,
+            "cpu": 0.125,
+            "cpuinfo": {"cpus": 16, "model": "AMD EPYC"},
+            "memory": {"used": 8589934592u64, "total": 68719476736u64, "free": 60129542144u64},
+            "rootfs": {"used": 10737418240u64, "total": 107374182400u64}
+        }});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/status"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.node_status("pve").await;
+
+        // Assert
+        assert_eq!(
+            result.unwrap(),
+            NodeStatus {
+                uptime: 86400,
+                cpu: 0.125,
+                cpuinfo: NodeCpuInfo { cpus: 16 },
+                memory: NodeUsage {
+                    used: 8589934592,
+                    total: 68719476736,
+                },
+                rootfs: NodeUsage {
+                    used: 10737418240,
+                    total: 107374182400,
+                },
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn list_storages_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": [
+            {"storage": "local", "type": "dir", "content": "iso,vztmpl,backup", "active": 1},
+            {"storage": "local-zfs", "type": "zfspool", "content": "images,rootdir", "active": 1,
+             "used": 1073741824u64, "total": 107374182400u64, "avail": 106300440576u64}
+        ]});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/storage"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.list_storages("pve").await.unwrap();
+
+        // Assert
+        assert_eq!(result.len(), 2);
+        assert!(!result[0].holds_images());
+        assert!(result[1].holds_images());
+        assert_eq!(result[1].kind, "zfspool");
+        assert_eq!(result[1].avail, 106300440576);
+    }
+
+    #[tokio::test]
+    async fn task_status_pending() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let upid = UniqueProcessId::from(FAKE_UPID);
+        let response_json = json!({"data": {"status": "running"}});
+        Mock::given(method(Method::GET))
+            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn task_status_completed() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let upid = UniqueProcessId::from(FAKE_UPID);
+        let response_json = json!({"data": {"status": "stopped", "exitstatus": "OK"}});
+        Mock::given(method(Method::GET))
+            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn task_status_failed() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let upid = UniqueProcessId::from(FAKE_UPID);
+        let response_json =
+            json!({"data": {"status": "stopped", "exitstatus": "ERROR: command failed"}});
+        Mock::given(method(Method::GET))
+            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            TaskStatus::Failed("ERROR: command failed".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn task_status_failure() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let upid = UniqueProcessId::from(FAKE_UPID);
+        Mock::given(method(Method::GET))
+            .and(path(format!("/nodes/pve/tasks/{}/status", upid.encoded())))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(500).set_body_string("Internal Server Error"))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.task_status(&TaskRef::new("pve", &upid)).await;
+
+        // Assert
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::Proxmox(ProxmoxError::Status, status, text) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(text, "Internal Server Error");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn firewall_options_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::PUT))
+            .and(path("/nodes/pve/qemu/100/firewall/options"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string_contains("enable=1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": null})))
+            .mount(&mock_server)
+            .await;
+        let options = FirewallOptions {
+            enable: 1,
+            policy_in: "DROP".to_owned(),
+        };
+
+        // Act
+        let result = client
+            .firewall_options(VmRef::new("pve", 100), options)
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn firewall_rules_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": [
+            {"pos": 0, "type": "in", "action": "ACCEPT", "comment": "rule-1"},
+            {"pos": 1, "type": "in", "action": "DROP"}
+        ]});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/firewall/rules"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.firewall_rules(VmRef::new("pve", 100)).await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            [
+                FirewallRuleInfo {
+                    pos: 0,
+                    comment: Some("rule-1".to_owned()),
+                },
+                FirewallRuleInfo {
+                    pos: 1,
+                    comment: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn create_firewall_rule_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/firewall/rules"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string_contains("dport=22"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": null})))
+            .mount(&mock_server)
+            .await;
+        let rule = FirewallRule {
+            direction: "in".to_owned(),
+            action: "ACCEPT".to_owned(),
+            enable: 1,
+            proto: "tcp".to_owned(),
+            dport: "22".to_owned(),
+            source: None,
+            comment: "rule-1".to_owned(),
+        };
+
+        // Act
+        let result = client
+            .create_firewall_rule(VmRef::new("pve", 100), rule)
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn create_firewall_rule_failure() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/firewall/rules"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(500).set_body_string("Internal Server Error"))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .create_firewall_rule(VmRef::new("pve", 100), FirewallRule::default())
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::Proxmox(ProxmoxError::Firewall, status, text) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(text, "Internal Server Error");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_firewall_rule_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::DELETE))
+            .and(path("/nodes/pve/qemu/100/firewall/rules/3"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": null})))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.delete_firewall_rule(VmRef::new("pve", 100), 3).await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn backup_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json =
+            json!({"data": "UPID:pve:00001234:00005678:6543210F:vzdump:100:root@pam:"});
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/vzdump"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string_contains("mode=snapshot"))
+            .and(body_string_contains("storage=backups"))
+            .and(body_string_contains("notes-template=server"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .backup(
+                VmRef::new("pve", 100),
+                "backups",
+                BackupMode::Snapshot,
+                "server",
+            )
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            UniqueProcessId::from("UPID:pve:00001234:00005678:6543210F:vzdump:100:root@pam:")
+        );
+    }
+
+    #[tokio::test]
+    async fn backup_failure() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/vzdump"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(500).set_body_string("Internal Server Error"))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .backup(
+                VmRef::new("pve", 100),
+                "backups",
+                BackupMode::Stop,
+                "server",
+            )
+            .await;
+
+        // Assert
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::Proxmox(ProxmoxError::Backup, status, text) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(text, "Internal Server Error");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn backups_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": [{
+            "volid": "backups:backup/vzdump-qemu-100-2026_10_16-02_30_00.vma.zst",
+            "content": "backup",
+            "ctime": 1792117800,
+            "size": 1073741824,
+            "format": "vma.zst",
+            "vmid": 100
+        }]});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/storage/backups/content"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.backups(VmRef::new("pve", 100), "backups").await;
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            [BackupArchive {
+                volid: "backups:backup/vzdump-qemu-100-2026_10_16-02_30_00.vma.zst".to_owned(),
+                ctime: 1792117800,
+                size: 1073741824,
+                format: "vma.zst".to_owned(),
+                notes: None,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn restore_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json =
+            json!({"data": "UPID:pve:00001234:00005678:6543210F:qmrestore:100:root@pam:"});
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string_contains("vmid=100"))
+            .and(body_string_contains("force=1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .restore(
+                VmRef::new("pve", 100),
+                "backups:backup/vzdump-qemu-100-2026_10_16-02_30_00.vma.zst",
+            )
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn restore_failure() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(500).set_body_string("Internal Server Error"))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.restore(VmRef::new("pve", 100), "missing").await;
+
+        // Assert
+        assert!(result.is_err());
+        match result.unwrap_err() {
+            Error::Proxmox(ProxmoxError::Restore, status, text) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(text, "Internal Server Error");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn agent_ping_failure() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/agent/ping"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(
+                ResponseTemplate::new(500).set_body_string("QEMU guest agent is not running"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.agent_ping(VmRef::new("pve", 100)).await;
+
+        // Assert
+        match result.unwrap_err() {
+            Error::Proxmox(ProxmoxError::Agent, status, text) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(text, "QEMU guest agent is not running");
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[tokio::test]
+    async fn agent_os_info_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": {"result": {
+            "id": "ubuntu",
+            "name": "Ubuntu",
+            "pretty-name": "Ubuntu 24.04.1 LTS",
+            "version": "24.04.1 LTS (Noble Numbat)",
+            "version-id": "24.04",
+            "kernel-release": "6.8.0-45-generic",
+            "machine": "x86_64"
+        }}});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/agent/get-osinfo"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.agent_os_info(VmRef::new("pve", 100)).await.unwrap();
+
+        // Assert
+        assert_eq!(result.pretty_name.as_deref(), Some("Ubuntu 24.04.1 LTS"));
+        assert_eq!(result.kernel_release.as_deref(), Some("6.8.0-45-generic"));
+    }
+
+    #[tokio::test]
+    async fn agent_network_interfaces_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": {"result": [
+            {"name": "lo", "hardware-address": "00:00:00:00:00:00", "ip-addresses": [
+                {"ip-address": "127.0.0.1", "ip-address-type": "ipv4", "prefix": 8}
+            ]},
+            {"name": "eth0", "hardware-address": "bc:24:11:2a:3b:4c", "ip-addresses": [
+                {"ip-address": "203.0.113.10", "ip-address-type": "ipv4", "prefix": 24}
+            ], "statistics": {"rx-bytes": 1024, "tx-bytes": 2048}}
+        ]}});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/agent/network-get-interfaces"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .agent_network_interfaces(VmRef::new("pve", 100))
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[1].name, "eth0");
+        assert_eq!(result[1].ip_addresses[0].ip_address, "203.0.113.10");
+    }
+
+    #[tokio::test]
+    async fn agent_set_user_password_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/agent/set-user-password"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string("username=root&password=s3cret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {"result": {}}})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .agent_set_user_password(VmRef::new("pve", 100), "root", "s3cret")
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn agent_exec_should_pass_script_as_input() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/agent/exec"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string("command=%2Fbin%2Fsh&input-data=echo+ok"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": {"pid": 42}})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .agent_exec(VmRef::new("pve", 100), "/bin/sh", "echo ok")
+            .await;
+
+        // Assert
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn agent_exec_status_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": {
+            "exited": 1,
+            "exitcode": 2,
+            "err-data": "docker: not found"
+        }});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/agent/exec-status"))
+            .and(query_param("pid", "42"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.agent_exec_status(VmRef::new("pve", 100), 42).await;
+
+        // Assert
+        let status = result.unwrap();
+        assert!(status.exited);
+        assert_eq!(status.exitcode, Some(2));
+        assert_eq!(status.err_data.as_deref(), Some("docker: not found"));
+        assert_eq!(status.out_data, None);
+    }
+
+    #[tokio::test]
+    async fn vm_rrd_data_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": [
+            {"time": 1792152000, "netin": 1024.5, "netout": 2048.0, "cpu": 0.1},
+            {"time": 1792153800}
+        ]});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/rrddata"))
+            .and(query_param("timeframe", "day"))
+            .and(query_param("cf", "AVERAGE"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.vm_rrd_data(VmRef::new("pve", 100)).await;
+
+        // Assert
+        assert_eq!(
+            result.unwrap(),
+            [
+                RrdSample {
+                    time: 1792152000,
+                    netin: Some(1024.5),
+                    netout: Some(2048.0),
+                },
+                RrdSample {
+                    time: 1792153800,
+                    netin: None,
+                    netout: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn list_isos_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": [{
+            "volid": "local:iso/debian-12.iso",
+            "content": "iso",
+            "format": "iso",
+            "size": 658505728
+        }]});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/storage/local/content"))
+            .and(query_param("content", "iso"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.list_isos("pve", "local").await;
+
+        // Assert
+        let isos = result.unwrap();
+        assert_eq!(isos.len(), 1);
+        assert_eq!(isos[0].volid, "local:iso/debian-12.iso");
+        assert_eq!(isos[0].size, 658505728);
+    }
+
+    #[tokio::test]
+    async fn set_cdrom_and_boot_order_should_send_config() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/config"))
+            .and(body_string(
+                "ide3=local%3Aiso%2Fdebian-12.iso%2Cmedia%3Dcdrom",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": FAKE_UPID})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method(Method::POST))
+            .and(path("/nodes/pve/qemu/100/config"))
+            .and(body_string("boot=order%3Dide3%3Bscsi0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": FAKE_UPID})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let vm = VmRef::new("pve", 100);
+        let inserted = client
+            .set_cdrom(vm.clone(), "ide3", Some("local:iso/debian-12.iso"))
+            .await;
+        let boot = client
+            .set_boot_order(vm, &["ide3".to_owned(), "scsi0".to_owned()])
+            .await;
+
+        // Assert
+        assert!(inserted.is_ok());
+        assert!(boot.is_ok());
+    }
+
+    #[tokio::test]
+    async fn vm_drives_success() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        let response_json = json!({"data": {
+            "cores": 2,
+            "ide2": "local-lvm:vm-100-cloudinit,media=cdrom,size=4M",
+            "net0": "virtio=BC:24:11:2A:3B:4C,bridge=vmbr0",
+            "scsi0": "local-lvm:vm-100-disk-0,iothread=1,size=32G",
+            "scsi1": "local-lvm:vm-100-disk-1,size=10G"
+        }});
+        Mock::given(method(Method::GET))
+            .and(path("/nodes/pve/qemu/100/config"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response_json))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client.vm_drives(VmRef::new("pve", 100)).await;
+
+        // Assert
+        let drives = result.unwrap();
+        let devices = drives.iter().map(|drive| drive.device.as_str());
+        assert_eq!(devices.collect::<Vec<_>>(), ["ide2", "scsi0", "scsi1"]);
+        assert_eq!(drives.iter().filter(|drive| drive.is_disk()).count(), 2);
+    }
+
+    #[tokio::test]
+    async fn resize_disk_should_send_size() {
+        // Arrange
+        let (mock_server, client) = setup().await;
+        Mock::given(method(Method::PUT))
+            .and(path("/nodes/pve/qemu/100/resize"))
+            .and(header(AUTHORIZATION.as_str(), AUTH_TOKEN))
+            .and(body_string("disk=scsi0&size=40G"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"data": FAKE_UPID})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let result = client
+            .resize_disk(VmRef::new("pve", 100), "scsi0", 40)
+            .await;
+
+        // Assert
+        assert!(result.is_ok());
+    }
 }
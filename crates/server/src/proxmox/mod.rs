@@ -1,8 +1,11 @@
+pub mod cached;
 pub mod client;
+pub mod queue;
 pub mod types;
 
 // -----------------------------------------------------------------------------
 
+use crate::model::types::BackupMode;
 use crate::proxmox::types::*;
 use async_trait::async_trait;
 use dashboard_common::prelude::Result;
@@ -88,6 +91,8 @@ pub trait Proxmox {
     /// # Arguments
     ///
     /// * `vm`: template virtual machine on the Proxmox cluster to clone.
+    /// * `storage`: Storage for the disks of the clone, `None` for the storage
+    ///   of the template.
     ///
     /// # Proxmox API
     ///
@@ -96,7 +101,7 @@ pub trait Proxmox {
     /// [`HTTP: GET /api2/json/cluster/nextid`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/cluster/nextid)\
     /// [`HTTP: POST /api2/json/nodes/{node}/qemu/{vmid}/clone`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/clone)
     ///
-    async fn create(&self, vm: VmRef) -> Result<(i32, UniqueProcessId)>;
+    async fn create(&self, vm: VmRef, storage: Option<&str>) -> Result<(i32, UniqueProcessId)>;
 
     /// Destroy the VM and all used/owned volumes. Removes any VM specific
     /// permissions and firewall rules
@@ -128,6 +133,64 @@ pub trait Proxmox {
     ///
     async fn vm_config(&self, vm: VmRef, config: VmConfig) -> Result<UniqueProcessId>;
 
+    /// Get current virtual machine configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/config`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/config)
+    ///
+    async fn vm_current_config(&self, vm: VmRef) -> Result<VmCurrentConfig>;
+
+    /// Get drives of a virtual machine, out of its current configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/config`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/config)
+    ///
+    async fn vm_drives(&self, vm: VmRef) -> Result<Vec<VmDrive>>;
+
+    /// Grow a disk of a virtual machine. Disks can't shrink.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `disk`: Device of the disk, like `scsi0`.
+    /// * `size_gb`: New size of the disk in GB.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`PUT /api2/json/nodes/{node}/qemu/{vmid}/resize`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/resize)
+    ///
+    async fn resize_disk(&self, vm: VmRef, disk: &str, size_gb: i32) -> Result<UniqueProcessId>;
+
+    /// Check whether a virtual machine exists on the node.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu)
+    ///
+    async fn vm_exists(&self, vm: VmRef) -> Result<bool>;
+
     /// Get virtual machine status.
     ///
     /// # Arguments
@@ -142,6 +205,351 @@ pub trait Proxmox {
     ///
     async fn vm_status(&self, vm: VmRef) -> Result<Status>;
 
+    /// Get virtual machine runtime metrics (uptime, CPU and memory usage).
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/status/current`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/status/current)
+    ///
+    async fn vm_usage(&self, vm: VmRef) -> Result<VmUsage>;
+
+    /// Get the network RRD data of a virtual machine over the last day, in
+    /// 30-minute steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/rrddata`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/rrddata)
+    ///
+    async fn vm_rrd_data(&self, vm: VmRef) -> Result<Vec<RrdSample>>;
+
+    /// Set virtual machine firewall options.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `options`: Firewall options to apply.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`PUT /api2/json/nodes/{node}/qemu/{vmid}/firewall/options`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/firewall/options)
+    ///
+    async fn firewall_options(&self, vm: VmRef, options: FirewallOptions) -> Result<()>;
+
+    /// List virtual machine firewall rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/firewall/rules`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/firewall/rules)
+    ///
+    async fn firewall_rules(&self, vm: VmRef) -> Result<Vec<FirewallRuleInfo>>;
+
+    /// Create a virtual machine firewall rule.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `rule`: Rule to create.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/qemu/{vmid}/firewall/rules`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/firewall/rules)
+    ///
+    async fn create_firewall_rule(&self, vm: VmRef, rule: FirewallRule) -> Result<()>;
+
+    /// Delete a virtual machine firewall rule.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `pos`: Position of the rule.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`DELETE /api2/json/nodes/{node}/qemu/{vmid}/firewall/rules/{pos}`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/firewall/rules/{pos})
+    ///
+    async fn delete_firewall_rule(&self, vm: VmRef, pos: i32) -> Result<()>;
+
+    /// Back up virtual machine.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `storage`: Storage the backup archive is written to.
+    /// * `mode`: How the VM is treated while it is backed up.
+    /// * `notes`: Notes attached to the backup archive.
+    ///
+    /// # Returns
+    ///
+    /// * `UniqueProcessId` (UPID) of the backup task.
+    ///   This task can be monitored using the `task_status` method.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/vzdump`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/vzdump)
+    ///
+    async fn backup(
+        &self,
+        vm: VmRef,
+        storage: &str,
+        mode: BackupMode,
+        notes: &str,
+    ) -> Result<UniqueProcessId>;
+
+    /// List backup archives of virtual machine.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `storage`: Storage the backup archives are kept on.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/storage/{storage}/content`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/storage/{storage}/content)
+    ///
+    async fn backups(&self, vm: VmRef, storage: &str) -> Result<Vec<BackupArchive>>;
+
+    /// Restore virtual machine from a backup archive, overwriting its disks
+    /// and configuration. The VM must be stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `archive`: Volume ID of the backup archive.
+    ///
+    /// # Returns
+    ///
+    /// * `UniqueProcessId` (UPID) of the restore task.
+    ///   This task can be monitored using the `task_status` method.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/qemu`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu)
+    ///
+    async fn restore(&self, vm: VmRef, archive: &str) -> Result<UniqueProcessId>;
+
+    /// Ping the QEMU guest agent, to check whether it is running inside the
+    /// virtual machine.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/qemu/{vmid}/agent/ping`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/agent/ping)
+    ///
+    async fn agent_ping(&self, vm: VmRef) -> Result<()>;
+
+    /// Get the operating system of the virtual machine, as reported by the
+    /// QEMU guest agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/agent/get-osinfo`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/agent/get-osinfo)
+    ///
+    async fn agent_os_info(&self, vm: VmRef) -> Result<GuestOsInfo>;
+
+    /// Get the network interfaces of the virtual machine and their addresses,
+    /// as reported by the QEMU guest agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/agent/network-get-interfaces`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/agent/network-get-interfaces)
+    ///
+    async fn agent_network_interfaces(&self, vm: VmRef) -> Result<Vec<GuestNetworkInterface>>;
+
+    /// Set the password of a user inside the virtual machine through the QEMU
+    /// guest agent. Unlike the cloud-init password, it applies immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `username`: User whose password is set.
+    /// * `password`: New plaintext password.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/qemu/{vmid}/agent/set-user-password`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/agent/set-user-password)
+    ///
+    async fn agent_set_user_password(
+        &self,
+        vm: VmRef,
+        username: &str,
+        password: &str,
+    ) -> Result<()>;
+
+    /// Run a program inside the virtual machine through the QEMU guest
+    /// agent, without waiting for it to finish.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `command`: Program to run, like `/bin/sh`.
+    /// * `input`: Data written to the standard input of the program.
+    ///
+    /// # Returns
+    ///
+    /// ID of the started process inside the guest.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/qemu/{vmid}/agent/exec`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/agent/exec)
+    ///
+    async fn agent_exec(&self, vm: VmRef, command: &str, input: &str) -> Result<i64>;
+
+    /// Get the status of a program started through the QEMU guest agent.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `pid`: ID of the process inside the guest.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/qemu/{vmid}/agent/exec-status`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/agent/exec-status)
+    ///
+    async fn agent_exec_status(&self, vm: VmRef, pid: i64) -> Result<GuestExecStatus>;
+
+    /// List the ISO images on a storage of a node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node`: Name of the node.
+    /// * `storage`: Storage holding the images.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/storage/{storage}/content`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/storage/{storage}/content)
+    ///
+    async fn list_isos(&self, node: &str, storage: &str) -> Result<Vec<IsoImage>>;
+
+    /// Insert an ISO image into a CD-ROM drive of the virtual machine, or eject
+    /// the inserted one.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `device`: CD-ROM drive, like `ide3`.
+    /// * `iso`: Volume of the ISO image, `None` to eject.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/qemu/{vmid}/config`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/config)
+    ///
+    async fn set_cdrom(
+        &self,
+        vm: VmRef,
+        device: &str,
+        iso: Option<&str>,
+    ) -> Result<UniqueProcessId>;
+
+    /// Set the devices the virtual machine boots from, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `vm`: target virtual machine on the Proxmox cluster.
+    /// * `devices`: Devices, like `ide3` and `scsi0`.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`POST /api2/json/nodes/{node}/qemu/{vmid}/config`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/qemu/{vmid}/config)
+    ///
+    async fn set_boot_order(&self, vm: VmRef, devices: &[String]) -> Result<UniqueProcessId>;
+
+    /// List nodes of the cluster.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes)
+    ///
+    async fn list_nodes(&self) -> Result<Vec<NodeListItem>>;
+
+    /// Get node resource usage (CPU, memory and root filesystem).
+    ///
+    /// # Arguments
+    ///
+    /// * `node`: Name of the node.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/status`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/status)
+    ///
+    async fn node_status(&self, node: &str) -> Result<NodeStatus>;
+
+    /// List storages of the node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node`: Name of the node.
+    ///
+    /// # Proxmox API
+    ///
+    /// This method corresponds to the following Proxmox API endpoint:
+    ///
+    /// [`GET /api2/json/nodes/{node}/storage`](https://pve.proxmox.com/pve-docs/api-viewer/index.html#/nodes/{node}/storage)
+    ///
+    async fn list_storages(&self, node: &str) -> Result<Vec<StorageInfo>>;
+
     /// Read task status.
     ///
     /// # Arguments
@@ -0,0 +1,163 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Queue bounding the requests sent to a Proxmox cluster at once.
+///
+/// Bulk operations may issue many requests together, which small Proxmox hosts
+/// don't cope with. Requests over the limit wait for a free slot in FIFO order,
+/// and the time they waited is recorded for the metrics.
+///
+pub struct RequestQueue {
+    limit: usize,
+    permits: Semaphore,
+    waiting: AtomicUsize,
+    requests: AtomicU64,
+    waited_us: AtomicU64,
+}
+
+impl RequestQueue {
+    /// Creates a new queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit`: Maximum number of concurrent requests, zero means unbounded.
+    ///
+    pub fn new(limit: usize) -> Self {
+        let limit = match limit {
+            0 => Semaphore::MAX_PERMITS,
+            limit => limit,
+        };
+
+        Self {
+            limit,
+            permits: Semaphore::new(limit),
+            waiting: AtomicUsize::new(0),
+            requests: AtomicU64::new(0),
+            waited_us: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits for a free slot, which is held until the returned permit is
+    /// dropped. A caller giving up while waiting stops being counted.
+    ///
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        let start = Instant::now();
+        let waiting = Waiting::new(&self.waiting);
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("Request queue semaphore is never closed");
+        drop(waiting);
+
+        let waited = start.elapsed().as_micros() as u64;
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.waited_us.fetch_add(waited, Ordering::Relaxed);
+        permit
+    }
+
+    /// Returns the number of requests waiting for a slot.
+    ///
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests being sent.
+    ///
+    pub fn in_flight(&self) -> usize {
+        self.limit - self.permits.available_permits()
+    }
+
+    /// Returns the number of requests that went through the queue.
+    ///
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total time the requests spent waiting for a slot.
+    ///
+    pub fn waited(&self) -> Duration {
+        Duration::from_micros(self.waited_us.load(Ordering::Relaxed))
+    }
+}
+
+/// Counts a request as waiting until it is dropped, including when the
+/// waiting future is cancelled.
+///
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn new(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Self(waiting)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    #[tokio::test]
+    async fn request_over_limit_should_wait_for_free_slot() {
+        // Arrange
+        let queue = RequestQueue::new(1);
+        let permit = queue.acquire().await;
+        let mut waiter = Box::pin(queue.acquire());
+
+        // Act
+        let blocked = (&mut waiter).now_or_never().is_none();
+        let waiting = queue.waiting();
+        drop(permit);
+        drop(waiter.await);
+
+        // Assert
+        assert!(blocked);
+        assert_eq!(waiting, 1);
+        assert_eq!(queue.waiting(), 0);
+        assert_eq!(queue.in_flight(), 0);
+        assert_eq!(queue.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn cancelled_request_should_stop_waiting() {
+        // Arrange
+        let queue = RequestQueue::new(1);
+        let _permit = queue.acquire().await;
+        let mut waiter = Box::pin(queue.acquire());
+        let _ = (&mut waiter).now_or_never();
+
+        // Act
+        drop(waiter);
+
+        // Assert
+        assert_eq!(queue.waiting(), 0);
+        assert_eq!(queue.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn zero_limit_should_not_bound_requests() {
+        // Arrange
+        let queue = RequestQueue::new(0);
+
+        // Act
+        let permits = [
+            queue.acquire().await,
+            queue.acquire().await,
+            queue.acquire().await,
+        ];
+
+        // Assert
+        assert_eq!(queue.in_flight(), permits.len());
+        assert!(queue.waited() < Duration::from_millis(20));
+    }
+}
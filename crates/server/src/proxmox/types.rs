@@ -1,7 +1,13 @@
-﻿use crate::web::types::NewServerPayload;
+﻿use crate::model::types::{
+    ApiBackup, ApiFirewallRule, ApiStorage, BackupMode, FirewallSettings, ServerStatus,
+};
+use crate::web::types::NewServerPayload;
+use chrono::{DateTime, Utc};
 use dashboard_common::prelude::{Error, Result};
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
 
 /// Generic wrapper for all successful Proxmox API responses.
 ///
@@ -46,6 +52,66 @@ impl UniqueProcessId {
     pub fn into_inner(self) -> String {
         self.0
     }
+
+    /// Parses the fields encoded in the UPID,
+    /// `UPID:{node}:{pid}:{pstart}:{starttime}:{type}:{id}:{user}:` with the
+    /// numbers in hex.
+    ///
+    pub fn parse(&self) -> Result<UpidInfo> {
+        let invalid = || Error::Any(format!("Invalid UPID: {}", self.0));
+        let parts = self.0.split(':').collect::<Vec<_>>();
+        let [
+            "UPID",
+            node,
+            pid,
+            pstart,
+            start_time,
+            task_type,
+            id,
+            owner,
+            "",
+        ] = parts[..]
+        else {
+            return Err(invalid());
+        };
+        if node.is_empty() || task_type.is_empty() || owner.is_empty() {
+            return Err(invalid());
+        }
+        let start_time = i64::from_str_radix(start_time, 16).map_err(|_| invalid())?;
+
+        Ok(UpidInfo {
+            node: node.to_owned(),
+            pid: u32::from_str_radix(pid, 16).map_err(|_| invalid())?,
+            pstart: u64::from_str_radix(pstart, 16).map_err(|_| invalid())?,
+            start_time: DateTime::from_timestamp(start_time, 0).ok_or_else(invalid)?,
+            task_type: task_type.to_owned(),
+            id: id.to_owned(),
+            owner: owner.to_owned(),
+        })
+    }
+}
+
+/// Fields encoded in a UPID.
+///
+/// # Fields
+///
+/// * `node`: Node the task runs on.
+/// * `pid`: ID of the worker process.
+/// * `pstart`: Start time of the worker process, in clock ticks since boot.
+/// * `start_time`: Start time of the task.
+/// * `task_type`: Type of the task, e.g. `qmclone` or `vzdump`.
+/// * `id`: ID of the object of the task, usually the VMID, may be empty.
+/// * `owner`: User who started the task, e.g. `root@pam`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpidInfo {
+    pub node: String,
+    pub pid: u32,
+    pub pstart: u64,
+    pub start_time: DateTime<Utc>,
+    pub task_type: String,
+    pub id: String,
+    pub owner: String,
 }
 
 impl From<&str> for UniqueProcessId {
@@ -67,15 +133,134 @@ pub struct StatusPayload {
     pub status: Status,
 }
 
-/// Power status of a virtual machine.
+/// Specific response structure for the endpoint listing the VMs of a node.
+///
+/// # Fields
+///
+/// * `vmid`: ID of a virtual machine.
+///
+#[derive(Deserialize)]
+pub struct VmListItem {
+    pub vmid: i32,
+}
+
+/// Specific response structure for the endpoint listing the cluster nodes.
+///
+/// # Fields
+///
+/// * `node`: Name of a node.
+/// * `status`: Membership status of a node, `"online"` or `"offline"`.
+///
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct NodeListItem {
+    pub node: String,
+    pub status: String,
+}
+
+impl NodeListItem {
+    pub fn is_online(&self) -> bool {
+        self.status == "online"
+    }
+}
+
+/// Resource usage of a node.
+///
+/// # Fields
+///
+/// * `uptime`: Uptime in seconds.
+/// * `cpu`: Current CPU usage as a fraction of all cores.
+/// * `cpuinfo`: Physical CPU of a node.
+/// * `memory`: Used and total memory in bytes.
+/// * `rootfs`: Used and total root filesystem space in bytes.
+///
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct NodeStatus {
+    #[serde(default)]
+    pub uptime: u64,
+    #[serde(default)]
+    pub cpu: f64,
+    pub cpuinfo: NodeCpuInfo,
+    pub memory: NodeUsage,
+    pub rootfs: NodeUsage,
+}
+
+/// Physical CPU of a node.
+///
+/// # Fields
+///
+/// * `cpus`: Number of logical cores.
 ///
 #[derive(Debug, PartialEq, Deserialize)]
+pub struct NodeCpuInfo {
+    pub cpus: u32,
+}
+
+/// Used and total amount of a node resource in bytes.
+///
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct NodeUsage {
+    pub used: u64,
+    pub total: u64,
+}
+
+/// Power status of a virtual machine.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Status {
     Stopped,
     Running,
 }
 
+impl From<Status> for ServerStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Stopped => ServerStatus::Stopped,
+            Status::Running => ServerStatus::Running,
+        }
+    }
+}
+
+/// Runtime metrics of a virtual machine, reported by the same endpoint as its
+/// power status.
+///
+/// # Fields
+///
+/// * `status`: Current power status of a virtual machine.
+/// * `uptime`: Uptime in seconds, `0` for stopped machines.
+/// * `cpu`: Current CPU usage as a fraction of the allocated vCPUs.
+/// * `mem`: Currently used memory in bytes.
+///
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct VmUsage {
+    pub status: Status,
+    #[serde(default)]
+    pub uptime: u64,
+    #[serde(default)]
+    pub cpu: f64,
+    #[serde(default)]
+    pub mem: u64,
+}
+
+/// Sample of the RRD data of a virtual machine, averaged over the step of the
+/// timeframe ending at `time`. Values are missing for the steps the machine
+/// was stopped or not sampled.
+///
+/// # Fields
+///
+/// * `time`: End of the step as a Unix timestamp.
+/// * `netin`: Traffic received by the machine in bytes per second.
+/// * `netout`: Traffic sent by the machine in bytes per second.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RrdSample {
+    pub time: i64,
+    #[serde(default)]
+    pub netin: Option<f64>,
+    #[serde(default)]
+    pub netout: Option<f64>,
+}
+
 /// High-level status of a long-running asynchronous task in Proxmox.
 ///
 #[derive(Debug, PartialEq)]
@@ -100,6 +285,125 @@ pub struct TaskResponse {
     pub exit_status: Option<String>,
 }
 
+/// Wrapper of the responses of the QEMU guest agent endpoints, which nest the
+/// output of the agent command in a `result` field.
+///
+#[derive(Deserialize)]
+pub struct AgentResponse<T> {
+    pub result: T,
+}
+
+/// Operating system of a virtual machine, as reported by the guest agent.
+/// Every field is optional, as the agents of different systems fill in
+/// different ones.
+///
+/// # Fields
+///
+/// * `id`: Short identifier of the system, e.g. `ubuntu` or `mswindows`.
+/// * `name`: Name of the system, e.g. `Ubuntu`.
+/// * `pretty_name`: Full name with the version, e.g. `Ubuntu 24.04.1 LTS`.
+/// * `version`: Version of the system.
+/// * `kernel_release`: Release of the running kernel.
+/// * `machine`: Architecture, e.g. `x86_64`.
+///
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GuestOsInfo {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub pretty_name: Option<String>,
+    pub version: Option<String>,
+    pub kernel_release: Option<String>,
+    pub machine: Option<String>,
+}
+
+/// Network interface of a virtual machine, as reported by the guest agent.
+///
+/// # Fields
+///
+/// * `name`: Name of the interface inside the guest, e.g. `eth0`.
+/// * `hardware_address`: MAC address, missing for some virtual interfaces.
+/// * `ip_addresses`: Addresses assigned to the interface.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GuestNetworkInterface {
+    pub name: String,
+    pub hardware_address: Option<String>,
+    #[serde(default)]
+    pub ip_addresses: Vec<GuestIpAddress>,
+}
+
+impl GuestNetworkInterface {
+    /// Returns the addresses of the interface reachable from outside the
+    /// guest, skipping the loopback, link-local and unparsable ones.
+    ///
+    pub fn routable_addresses(&self) -> impl Iterator<Item = IpAddr> {
+        self.ip_addresses
+            .iter()
+            .filter_map(|address| address.ip_address.parse::<IpAddr>().ok())
+            .filter(|address| match address {
+                IpAddr::V4(v4) => !v4.is_loopback() && !v4.is_link_local(),
+                IpAddr::V6(v6) => !v6.is_loopback() && !v6.is_unicast_link_local(),
+            })
+    }
+}
+
+/// Process started by the guest agent, as reported once it is polled.
+///
+/// # Fields
+///
+/// * `exited`: Whether the process has finished.
+/// * `exitcode`: Exit code of the finished process.
+/// * `out_data`: Captured standard output.
+/// * `err_data`: Captured standard error.
+///
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GuestExecStatus {
+    #[serde(deserialize_with = "deserialize_flag")]
+    pub exited: bool,
+    pub exitcode: Option<i32>,
+    pub out_data: Option<String>,
+    pub err_data: Option<String>,
+}
+
+/// Reads a flag Proxmox reports either as a boolean or as `0` and `1`.
+///
+fn deserialize_flag<'de, D>(deserializer: D) -> std::result::Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(i64),
+    }
+
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(flag) => flag,
+        Flag::Int(flag) => flag != 0,
+    })
+}
+
+/// Address of a guest network interface.
+///
+/// # Fields
+///
+/// * `ip_address`: The address, without the prefix.
+/// * `ip_address_type`: `ipv4` or `ipv6`.
+/// * `prefix`: Length of the network prefix.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GuestIpAddress {
+    pub ip_address: String,
+    pub ip_address_type: String,
+    #[serde(default)]
+    pub prefix: u8,
+}
+
 // -----------------------------------------------------------------------------
 
 /// Reference to a specific virtual machine on a Proxmox cluster.
@@ -109,7 +413,7 @@ pub struct TaskResponse {
 /// * `node`: Name of the Proxmox node where the VM is located (e.g., "pve").
 /// * `id`: Unique integer ID of the virtual machine (VMID).
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct VmRef {
     pub node: String,
     pub id: i32,
@@ -132,11 +436,13 @@ impl VmRef {
 ///
 /// * `node`: Name of the Proxmox node where the task is running.
 /// * `upid`: Unique Process ID (UPID) of the task.
+/// * `info`: Fields parsed from the UPID, `None` if it is malformed.
 ///
 #[derive(Debug, Clone)]
 pub struct TaskRef {
     pub node: String,
     pub upid: UniqueProcessId,
+    pub info: Option<UpidInfo>,
 }
 
 impl TaskRef {
@@ -146,37 +452,912 @@ impl TaskRef {
         Self {
             node: node.to_owned(),
             upid: upid.clone(),
+            info: upid.parse().ok(),
+        }
+    }
+
+    /// Returns how long before `now` the task started, `None` if the start
+    /// time is unknown.
+    ///
+    pub fn age(&self, now: DateTime<Utc>) -> Option<std::time::Duration> {
+        let info = self.info.as_ref()?;
+        (now - info.start_time).to_std().ok()
+    }
+}
+
+/// Configuration change of a virtual machine, sent to Proxmox as form fields.
+///
+/// Built with `VmConfig::builder()`, so every option is encoded the way
+/// Proxmox expects. Options that are not set are left unchanged on the VM.
+///
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct VmConfig(BTreeMap<String, String>);
+
+impl VmConfig {
+    /// Starts an empty configuration change.
+    ///
+    pub fn builder() -> VmConfigBuilder {
+        VmConfigBuilder::default()
+    }
+
+    /// Returns the encoded value of an option, if it is set.
+    ///
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Creates a configuration adding a network device together with its IP
+    /// configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `index`: Index of the new device, `net{index}` and `ipconfig{index}`.
+    /// * `net`: Specification of the device.
+    /// * `ip_config`: IP configuration of the device.
+    ///
+    pub fn add_device(index: i32, net: String, ip_config: String) -> Self {
+        Self::builder()
+            .net(index, net)
+            .ipconfig(index, ip_config)
+            .build()
+    }
+
+    /// Creates a configuration removing a network device together with its IP
+    /// configuration.
+    ///
+    pub fn remove_device(index: i32) -> Self {
+        let (net, ip_config) = (format!("net{index}"), format!("ipconfig{index}"));
+        Self::builder().delete(&[&net, &ip_config]).build()
+    }
+
+    /// Returns the specification of a new device on the same bridge and with
+    /// the same options as the given one. The MAC address is dropped, so
+    /// Proxmox generates a new one.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: Existing device specification (e.g., `virtio=...,bridge=vmbr0`).
+    ///
+    pub fn net_like(net: &str) -> String {
+        net.split(',')
+            .enumerate()
+            .map(|(index, option)| match option.split_once('=') {
+                Some((model, _)) if index == 0 => model,
+                _ => option,
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Returns the network device specification with its rate limit replaced.
+    ///
+    /// Products define the limit in Mbit/s, while Proxmox expects MB/s. The
+    /// rest of the specification, including the MAC address, is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `net`: Current device specification (e.g. `virtio=...,bridge=vmbr0`).
+    /// * `rate_mbps`: New rate limit in Mbit/s.
+    ///
+    pub fn net_with_rate(net: &str, rate_mbps: i32) -> String {
+        Self::net_with_option(net, "rate", &(rate_mbps as f64 / 8.0).to_string())
+    }
+
+    /// Returns the network device specification with the firewall switched on
+    /// or off. Proxmox filters traffic of a device only when it is switched on.
+    ///
+    pub fn net_with_firewall(net: &str, enabled: bool) -> String {
+        Self::net_with_option(net, "firewall", if enabled { "1" } else { "0" })
+    }
+
+    /// Replaces a single option of the network device specification.
+    ///
+    fn net_with_option(net: &str, key: &str, value: &str) -> String {
+        let option = format!("{key}={value}");
+        net.split(',')
+            .filter(|current| current.split_once('=').map(|(k, _)| k) != Some(key))
+            .chain(std::iter::once(option.as_str()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Builder of a `VmConfig`, one method per supported option.
+///
+#[derive(Debug, Default)]
+pub struct VmConfigBuilder {
+    fields: BTreeMap<String, String>,
+}
+
+impl VmConfigBuilder {
+    /// Number of cores per socket.
+    ///
+    pub fn cores(self, cores: i32) -> Self {
+        self.set("cores", cores)
+    }
+
+    /// Number of CPU sockets.
+    ///
+    pub fn sockets(self, sockets: i32) -> Self {
+        self.set("sockets", sockets)
+    }
+
+    /// Amount of memory in MiB.
+    ///
+    pub fn memory(self, memory_mb: i32) -> Self {
+        self.set("memory", memory_mb)
+    }
+
+    /// Minimum amount of memory in MiB the balloon device may shrink the VM
+    /// to, `0` disables the device.
+    ///
+    pub fn balloon(self, balloon_mb: i32) -> Self {
+        self.set("balloon", balloon_mb)
+    }
+
+    /// Specification of the network device `net{index}`.
+    ///
+    pub fn net(self, index: i32, net: impl Into<String>) -> Self {
+        self.set(&format!("net{index}"), net.into())
+    }
+
+    /// Cloud-init IP configuration of the network device `net{index}`.
+    ///
+    pub fn ipconfig(self, index: i32, ip_config: impl Into<String>) -> Self {
+        self.set(&format!("ipconfig{index}"), ip_config.into())
+    }
+
+    /// Cloud-init user, instead of the default user of the image.
+    ///
+    pub fn ciuser(self, user: impl Into<String>) -> Self {
+        self.set("ciuser", user.into())
+    }
+
+    /// Cloud-init password of the user, applied on the next boot.
+    ///
+    pub fn cipassword(self, password: impl Into<String>) -> Self {
+        self.set("cipassword", password.into())
+    }
+
+    /// Cloud-init public SSH keys, one per line. Proxmox expects them URL
+    /// encoded on top of the form encoding.
+    ///
+    pub fn sshkeys(self, keys: &[String]) -> Self {
+        let keys = utf8_percent_encode(&keys.join("\n"), NON_ALPHANUMERIC).to_string();
+        self.set("sshkeys", keys)
+    }
+
+    /// Whether the VM starts together with the node.
+    ///
+    pub fn onboot(self, onboot: bool) -> Self {
+        self.set("onboot", u8::from(onboot))
+    }
+
+    /// Description of the VM, shown in the Proxmox UI.
+    ///
+    pub fn description(self, description: impl Into<String>) -> Self {
+        self.set("description", description.into())
+    }
+
+    /// Cloud-init snippets replacing the generated ones, like
+    /// `vendor=local:snippets/docker.yaml`.
+    ///
+    pub fn cicustom(self, snippets: impl Into<String>) -> Self {
+        self.set("cicustom", snippets.into())
+    }
+
+    /// CD-ROM drive `device`, like `ide3`, with the ISO volume inserted, or
+    /// empty for `None`.
+    ///
+    pub fn cdrom(self, device: &str, iso: Option<&str>) -> Self {
+        self.set(device, format!("{},media=cdrom", iso.unwrap_or("none")))
+    }
+
+    /// Devices the VM tries to boot from, in order.
+    ///
+    pub fn boot_order(self, devices: &[String]) -> Self {
+        self.set("boot", format!("order={}", devices.join(";")))
+    }
+
+    /// New disk `device`, like `scsi1`, allocated on the storage.
+    ///
+    pub fn disk(self, device: &str, storage: &str, size_gb: i32) -> Self {
+        self.set(device, format!("{storage}:{size_gb}"))
+    }
+
+    /// Options to remove from the VM configuration.
+    ///
+    pub fn delete(self, keys: &[&str]) -> Self {
+        self.set("delete", keys.join(","))
+    }
+
+    /// Finishes the configuration change.
+    ///
+    pub fn build(self) -> VmConfig {
+        VmConfig(self.fields)
+    }
+
+    fn set(mut self, key: &str, value: impl ToString) -> Self {
+        self.fields.insert(key.to_owned(), value.to_string());
+        self
+    }
+}
+
+impl TryFrom<NewServerPayload> for VmConfig {
+    type Error = Error;
+    fn try_from(payload: NewServerPayload) -> Result<Self> {
+        let mut builder = VmConfig::builder();
+        if let Some(cores) = payload.cpu_cores {
+            builder = builder.cores(cores);
+        }
+        if let Some(ram_gb) = payload.ram_gb {
+            builder = builder.memory(ram_gb * 1024);
+        }
+        if let Some(ip_config) = payload.ip_config {
+            builder = builder.ipconfig(0, ip_config);
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Current configuration of a virtual machine, limited to the options the
+/// dashboard changes.
+///
+/// # Fields
+///
+/// * `net0`: Specification of the first network device, if the VM has one.
+/// * `template`: `1` if the VM is a template.
+///
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct VmCurrentConfig {
+    #[serde(default)]
+    pub net0: Option<String>,
+    #[serde(default)]
+    pub template: Option<u8>,
+}
+
+impl VmCurrentConfig {
+    /// Checks whether the VM is a template, which can only be cloned.
+    ///
+    pub fn is_template(&self) -> bool {
+        self.template == Some(1)
+    }
+}
+
+/// Drive of a virtual machine, parsed from its configuration, like
+/// `scsi0: local-lvm:vm-100-disk-0,iothread=1,size=32G`.
+///
+/// # Fields
+///
+/// * `device`: Bus and index of the drive, like `scsi0`.
+/// * `storage`: Storage of the volume, `None` for an empty CD-ROM drive.
+/// * `size_gb`: Size of the volume in GB, rounded up, if Proxmox reports it.
+/// * `cdrom`: Whether the drive is a CD-ROM or a cloud-init drive.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmDrive {
+    pub device: String,
+    pub storage: Option<String>,
+    pub size_gb: Option<i32>,
+    pub cdrom: bool,
+}
+
+impl VmDrive {
+    /// Buses Proxmox attaches drives to.
+    pub const BUSES: [&str; 4] = ["scsi", "virtio", "sata", "ide"];
+
+    /// Parses a drive out of an option of the VM configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: Name of the option, like `scsi0`.
+    /// * `value`: Value of the option.
+    ///
+    /// # Returns
+    ///
+    /// Parsed drive, `None` if the option isn't a drive.
+    ///
+    pub fn parse(key: &str, value: &str) -> Option<VmDrive> {
+        let index = Self::BUSES.iter().find_map(|bus| key.strip_prefix(bus))?;
+        if index.is_empty() || !index.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut options = value.split(',');
+        let storage = options
+            .next()?
+            .split_once(':')
+            .map(|(storage, _)| storage.to_owned());
+        let mut drive = VmDrive {
+            device: key.to_owned(),
+            storage,
+            size_gb: None,
+            cdrom: false,
+        };
+        for option in options {
+            match option.split_once('=') {
+                Some(("media", "cdrom")) => drive.cdrom = true,
+                Some(("size", size)) => drive.size_gb = parse_size_gb(size),
+                _ => {}
+            }
+        }
+
+        Some(drive)
+    }
+
+    /// Checks whether the drive is a virtual disk, which can be resized.
+    ///
+    pub fn is_disk(&self) -> bool {
+        !self.cdrom && self.storage.is_some() && self.size_gb.is_some()
+    }
+
+    /// Checks whether Proxmox attaches and grows the drive while the VM is
+    /// running. Only the SCSI and VirtIO buses support hot-plugging.
+    ///
+    pub fn is_hotpluggable(&self) -> bool {
+        self.device.starts_with("scsi") || self.device.starts_with("virtio")
+    }
+}
+
+/// Converts a Proxmox volume size, like `32G` or `512M`, to GB, rounded up. A
+/// size without a unit is in bytes.
+///
+fn parse_size_gb(size: &str) -> Option<i32> {
+    let (number, unit) = size.split_at(
+        size.find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(size.len()),
+    );
+    let number = number.parse::<f64>().ok()?;
+    let divisor = match unit {
+        "" => 1024.0 * 1024.0 * 1024.0,
+        "K" => 1024.0 * 1024.0,
+        "M" => 1024.0,
+        "G" => 1.0,
+        "T" => 1.0 / 1024.0,
+        _ => return None,
+    };
+
+    Some((number / divisor).ceil() as i32)
+}
+
+/// VM-level firewall options.
+///
+/// # Fields
+///
+/// * `enable`: `1` to enable the firewall, `0` to disable it.
+/// * `policy_in`: Action for incoming traffic not matched by any rule.
+///
+#[derive(Debug, Default, Serialize)]
+pub struct FirewallOptions {
+    pub enable: u8,
+    pub policy_in: String,
+}
+
+impl From<&FirewallSettings> for FirewallOptions {
+    fn from(settings: &FirewallSettings) -> Self {
+        Self {
+            enable: settings.enabled as u8,
+            policy_in: settings.policy_in.to_string().to_uppercase(),
         }
     }
 }
 
+/// Firewall rule in the format expected by Proxmox.
+///
+/// # Fields
+///
+/// * `direction`: `in` or `out`.
+/// * `action`: `ACCEPT`, `DROP` or `REJECT`.
+/// * `comment`: ID of the rule in the dashboard, used to find it again.
+///
 #[derive(Debug, Default, Serialize)]
-pub struct VmConfig {
+pub struct FirewallRule {
+    #[serde(rename = "type")]
+    pub direction: String,
+    pub action: String,
+    pub enable: u8,
+    pub proto: String,
+    pub dport: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ipconfig0: Option<String>,
+    pub source: Option<String>,
+    pub comment: String,
+}
+
+impl From<&ApiFirewallRule> for FirewallRule {
+    fn from(rule: &ApiFirewallRule) -> Self {
+        Self {
+            direction: rule.direction.to_string().to_lowercase(),
+            action: rule.action.to_string().to_uppercase(),
+            enable: 1,
+            proto: rule.protocol.to_string().to_lowercase(),
+            dport: rule.port.to_string(),
+            source: rule.source.clone(),
+            comment: rule.id.to_string(),
+        }
+    }
+}
+
+/// Firewall rule as listed by Proxmox, limited to the fields the dashboard
+/// uses.
+///
+/// # Fields
+///
+/// * `pos`: Position of the rule, which identifies it in Proxmox.
+/// * `comment`: Comment of the rule, if any.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FirewallRuleInfo {
+    pub pos: i32,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Parameters of a vzdump backup task.
+///
+/// # Fields
+///
+/// * `vmid`: ID of the virtual machine to back up.
+/// * `storage`: Storage the archive is written to.
+/// * `mode`: `snapshot`, `suspend` or `stop`.
+/// * `compress`: Compression of the archive.
+/// * `notes_template`: Notes attached to the archive.
+///
+#[derive(Debug, Default, Serialize)]
+pub struct BackupParams {
+    pub vmid: i32,
+    pub storage: String,
+    pub mode: String,
+    pub compress: String,
+    #[serde(rename = "notes-template")]
+    pub notes_template: String,
+}
+
+impl BackupParams {
+    /// Creates parameters for a zstd-compressed backup of the VM.
+    ///
+    pub fn new(vm: &VmRef, storage: &str, mode: BackupMode, notes: &str) -> Self {
+        Self {
+            vmid: vm.id,
+            storage: storage.to_owned(),
+            mode: mode.to_string().to_lowercase(),
+            compress: "zstd".to_owned(),
+            notes_template: notes.to_owned(),
+        }
+    }
+}
+
+/// Parameters of a clone task, which copies a template into a new VM.
+///
+/// # Fields
+///
+/// * `newid`: ID of the new virtual machine.
+/// * `full`: `1` for a full copy of the disks, required to move them to
+///   another storage.
+/// * `storage`: Storage of the copied disks, `None` for the storage of the
+///   template.
+///
+#[derive(Debug, Default, Serialize)]
+pub struct CloneParams {
+    pub newid: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub cores: Option<i32>,
+    pub full: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub memory: Option<i32>,
+    pub storage: Option<String>,
 }
 
-impl VmConfig {
-    pub fn new(ip_config: String, cpu_cores: Option<i32>, memory_gb: Option<i32>) -> Self {
+impl CloneParams {
+    /// Creates parameters for cloning into the new VM, fully copying the disks
+    /// if they go to another storage.
+    ///
+    pub fn new(newid: i32, storage: Option<&str>) -> Self {
         Self {
-            cores: cpu_cores,
-            memory: memory_gb.map(|ram| ram * 1024),
-            ipconfig0: Some(ip_config),
+            newid,
+            full: storage.map(|_| 1),
+            storage: storage.map(str::to_owned),
         }
     }
 }
 
-impl TryFrom<NewServerPayload> for VmConfig {
-    type Error = Error;
-    fn try_from(payload: NewServerPayload) -> Result<Self> {
-        Ok(Self {
-            cores: payload.cpu_cores,
-            memory: payload.ram_gb.map(|ram| ram * 1024),
-            ipconfig0: payload.ip_config,
-        })
+/// Parameters of a restore task, which overwrites an existing VM with the
+/// content of a backup archive.
+///
+/// # Fields
+///
+/// * `vmid`: ID of the virtual machine to overwrite.
+/// * `archive`: Volume ID of the backup archive.
+/// * `force`: `1` to allow overwriting the existing VM.
+///
+#[derive(Debug, Default, Serialize)]
+pub struct RestoreParams {
+    pub vmid: i32,
+    pub archive: String,
+    pub force: u8,
+}
+
+impl RestoreParams {
+    /// Creates parameters for overwriting the VM with the archive.
+    ///
+    pub fn new(vm: &VmRef, archive: &str) -> Self {
+        Self {
+            vmid: vm.id,
+            archive: archive.to_owned(),
+            force: 1,
+        }
+    }
+}
+
+/// Parameters of the guest agent command setting the password of a user.
+///
+/// # Fields
+///
+/// * `username`: User whose password is set.
+/// * `password`: New plaintext password.
+///
+#[derive(Default, Serialize)]
+pub struct UserPasswordParams {
+    pub username: String,
+    pub password: String,
+}
+
+impl UserPasswordParams {
+    /// Creates parameters for setting the plaintext password of the user.
+    ///
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            username: username.to_owned(),
+            password: password.to_owned(),
+        }
+    }
+}
+
+/// Parameters of the guest agent command running a program. The script is
+/// passed to the program on its standard input, so no argument list has to
+/// be encoded.
+///
+/// # Fields
+///
+/// * `command`: Program to run, like `/bin/sh`.
+/// * `input_data`: Data written to the standard input of the program.
+///
+#[derive(Default, Serialize)]
+pub struct ExecParams {
+    pub command: String,
+    #[serde(rename = "input-data")]
+    pub input_data: String,
+}
+
+impl ExecParams {
+    /// Creates parameters for running the program with the input.
+    ///
+    pub fn new(command: &str, input: &str) -> Self {
+        Self {
+            command: command.to_owned(),
+            input_data: input.to_owned(),
+        }
+    }
+}
+
+/// Process started by the guest agent.
+///
+/// # Fields
+///
+/// * `pid`: ID of the process inside the guest.
+///
+#[derive(Debug, Deserialize)]
+pub struct GuestExec {
+    pub pid: i64,
+}
+
+/// Parameters of growing a disk of a virtual machine.
+///
+/// # Fields
+///
+/// * `disk`: Device of the disk, like `scsi0`.
+/// * `size`: New size of the disk, like `40G`.
+///
+#[derive(Default, Serialize)]
+pub struct ResizeParams {
+    pub disk: String,
+    pub size: String,
+}
+
+impl ResizeParams {
+    /// Creates parameters for growing the disk to the size in GB.
+    ///
+    pub fn new(disk: &str, size_gb: i32) -> Self {
+        Self {
+            disk: disk.to_owned(),
+            size: format!("{size_gb}G"),
+        }
+    }
+}
+
+/// Backup archive as listed in the content of a Proxmox storage.
+///
+/// # Fields
+///
+/// * `volid`: Volume ID of the archive, e.g.
+///   `local:backup/vzdump-qemu-100-2026_10_16-02_30_00.vma.zst`.
+/// * `ctime`: Creation time as a Unix timestamp.
+/// * `size`: Size of the archive in bytes.
+/// * `format`: Format of the archive, e.g. `vma.zst`.
+/// * `notes`: Notes attached to the archive, if any.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BackupArchive {
+    pub volid: String,
+    pub ctime: i64,
+    pub size: i64,
+    pub format: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl From<BackupArchive> for ApiBackup {
+    fn from(archive: BackupArchive) -> Self {
+        Self {
+            volid: archive.volid,
+            format: archive.format,
+            size_bytes: archive.size,
+            notes: archive.notes,
+            created_at: DateTime::from_timestamp(archive.ctime, 0).unwrap_or_default(),
+        }
+    }
+}
+
+/// ISO image as listed in the content of a Proxmox storage.
+///
+/// # Fields
+///
+/// * `volid`: Volume ID of the image, e.g. `local:iso/debian-12.iso`.
+/// * `size`: Size of the image in bytes.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IsoImage {
+    pub volid: String,
+    #[serde(default)]
+    pub size: i64,
+}
+
+/// Storage as listed for a node.
+///
+/// # Fields
+///
+/// * `storage`: Storage identifier, e.g. `local-lvm`.
+/// * `kind`: Storage type, e.g. `lvmthin` or `zfspool`.
+/// * `content`: Comma separated content types the storage accepts.
+/// * `active`: `1` if the storage is available on the node.
+/// * `shared`: `1` if every node of the cluster reaches the same storage.
+/// * `used`, `total`, `avail`: Space in bytes, `0` for inactive storages.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct StorageInfo {
+    pub storage: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub active: u8,
+    #[serde(default)]
+    pub shared: u8,
+    #[serde(default)]
+    pub used: u64,
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default)]
+    pub avail: u64,
+}
+
+impl StorageInfo {
+    /// Returns whether the storage is active and accepts VM disks.
+    ///
+    pub fn holds_images(&self) -> bool {
+        self.active == 1 && self.content.split(',').any(|content| content == "images")
+    }
+}
+
+impl From<StorageInfo> for ApiStorage {
+    fn from(storage: StorageInfo) -> Self {
+        Self {
+            holds_images: storage.holds_images(),
+            name: storage.storage,
+            kind: storage.kind,
+            content: storage
+                .content
+                .split(',')
+                .filter(|content| !content.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            active: storage.active == 1,
+            used: storage.used,
+            total: storage.total,
+            available: storage.avail,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn net_with_rate_should_replace_existing_rate() {
+        // Arrange
+        let net = "virtio=BC:24:11:2A:3B:4C,bridge=vmbr0,rate=5";
+
+        // Act
+        let net = VmConfig::net_with_rate(net, 100);
+
+        // Assert
+        assert_eq!(net, "virtio=BC:24:11:2A:3B:4C,bridge=vmbr0,rate=12.5");
+    }
+
+    #[test]
+    fn net_with_firewall_should_replace_existing_flag() {
+        // Act
+        let net =
+            VmConfig::net_with_firewall("virtio=BC:24:11:2A:3B:4C,firewall=0,bridge=vmbr0", true);
+
+        // Assert
+        assert_eq!(net, "virtio=BC:24:11:2A:3B:4C,bridge=vmbr0,firewall=1");
+    }
+
+    #[test]
+    fn net_like_should_drop_mac_address() {
+        // Act
+        let net = VmConfig::net_like("virtio=BC:24:11:2A:3B:4C,bridge=vmbr0,rate=12.5");
+
+        // Assert
+        assert_eq!(net, "virtio,bridge=vmbr0,rate=12.5");
+    }
+
+    #[test]
+    fn net_with_rate_should_append_missing_rate() {
+        // Act
+        let net = VmConfig::net_with_rate("virtio=BC:24:11:2A:3B:4C,bridge=vmbr0", 80);
+
+        // Assert
+        assert_eq!(net, "virtio=BC:24:11:2A:3B:4C,bridge=vmbr0,rate=10");
+    }
+
+    #[test]
+    fn drives_should_be_parsed_from_config() {
+        // Act
+        let disk = VmDrive::parse("scsi0", "local-lvm:vm-100-disk-0,iothread=1,size=32G");
+        let small = VmDrive::parse("virtio1", "local-zfs:vm-100-disk-1,size=1536M");
+        let cloud_init = VmDrive::parse("ide2", "local-lvm:vm-100-cloudinit,media=cdrom,size=4M");
+        let empty = VmDrive::parse("ide0", "none,media=cdrom");
+        let network = VmDrive::parse("net0", "virtio=BC:24:11:2A:3B:4C,bridge=vmbr0");
+        let state = VmDrive::parse("scsihw", "virtio-scsi-single");
+
+        // Assert
+        let disk = disk.unwrap();
+        assert_eq!(disk.storage.as_deref(), Some("local-lvm"));
+        assert_eq!(disk.size_gb, Some(32));
+        assert!(disk.is_disk() && disk.is_hotpluggable());
+        assert_eq!(small.unwrap().size_gb, Some(2));
+        assert!(!cloud_init.unwrap().is_disk());
+        assert_eq!(empty.unwrap().storage, None);
+        assert_eq!(network, None);
+        assert_eq!(state, None);
+    }
+
+    #[test]
+    fn upid_should_be_parsed_into_fields() {
+        // Arrange
+        let upid =
+            UniqueProcessId::from("UPID:pve:0001A2B3:0C4D5E6F:6710B3A0:qmclone:9000:root@pam:");
+
+        // Act
+        let info = upid.parse().unwrap();
+
+        // Assert
+        assert_eq!(info.node, "pve");
+        assert_eq!(info.pid, 0x1A2B3);
+        assert_eq!(info.pstart, 0xC4D5E6F);
+        assert_eq!(info.start_time.timestamp(), 0x6710B3A0);
+        assert_eq!(info.task_type, "qmclone");
+        assert_eq!(info.id, "9000");
+        assert_eq!(info.owner, "root@pam");
+    }
+
+    #[test]
+    fn malformed_upid_should_be_rejected() {
+        // Arrange
+        let upids = [
+            "mock_process_id",
+            "UPID:pve:1",
+            "UPID:pve:XYZ:0C4D5E6F:6710B3A0:qmclone:9000:root@pam:",
+            "UPID:pve:0001A2B3:0C4D5E6F:6710B3A0:qmclone:9000:root@pam",
+        ];
+
+        // Act
+        let results = upids.map(|upid| UniqueProcessId::from(upid).parse());
+
+        // Assert
+        assert!(results.iter().all(Result::is_err));
+    }
+
+    #[test]
+    fn task_age_should_follow_upid_start_time() {
+        // Arrange
+        let started = Utc::now().timestamp() - 600;
+        let upid = UniqueProcessId::from(
+            format!("UPID:pve:0001A2B3:0C4D5E6F:{started:08X}:vzdump:100:root@pam:").as_str(),
+        );
+
+        // Act
+        let parsed = TaskRef::new("pve", &upid);
+        let opaque = TaskRef::new("pve", &"mock_process_id".into());
+
+        // Assert
+        let age = parsed.age(Utc::now()).unwrap().as_secs();
+        assert!((600..610).contains(&age));
+        assert!(opaque.age(Utc::now()).is_none());
+    }
+
+    #[test]
+    fn builder_should_encode_typed_options() {
+        // Arrange
+        let keys = [
+            "ssh-ed25519 AAAA user@host".to_owned(),
+            "ssh-rsa BBBB".to_owned(),
+        ];
+
+        // Act
+        let config = VmConfig::builder()
+            .cores(2)
+            .onboot(false)
+            .sshkeys(&keys)
+            .build();
+
+        // Assert
+        assert_eq!(config.get("cores"), Some("2"));
+        assert_eq!(config.get("onboot"), Some("0"));
+        assert_eq!(
+            config.get("sshkeys"),
+            Some("ssh%2Ded25519%20AAAA%20user%40host%0Assh%2Drsa%20BBBB")
+        );
+        assert_eq!(config.get("memory"), None);
+    }
+
+    #[test]
+    fn remove_device_should_delete_net_and_ipconfig() {
+        // Act
+        let config = VmConfig::remove_device(1);
+
+        // Assert
+        assert_eq!(config.get("delete"), Some("net1,ipconfig1"));
+        assert_eq!(config.get("net1"), None);
+    }
+
+    #[test]
+    fn routable_addresses_should_skip_loopback_and_link_local() {
+        // Arrange
+        let interface: GuestNetworkInterface = serde_json::from_value(serde_json::json!({
+            "name": "eth0",
+            "hardware-address": "bc:24:11:2a:3b:4c",
+            "ip-addresses": [
+                {"ip-address": "203.0.113.10", "ip-address-type": "ipv4", "prefix": 24},
+                {"ip-address": "127.0.0.1", "ip-address-type": "ipv4", "prefix": 8},
+                {"ip-address": "fe80::be24:11ff:fe2a:3b4c", "ip-address-type": "ipv6", "prefix": 64},
+                {"ip-address": "2001:db8::10", "ip-address-type": "ipv6", "prefix": 64}
+            ]
+        }))
+        .unwrap();
+
+        // Act
+        let addresses = interface
+            .routable_addresses()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(addresses, ["203.0.113.10", "2001:db8::10"]);
     }
 }
@@ -0,0 +1,354 @@
+use crate::config::Config;
+use crate::model::queries;
+use crate::model::types::CronSchedule;
+use crate::services::leader::Leader;
+use crate::services::{
+    announcement, backup, billing, notification, setup, status, traffic, usage, user, webhook,
+};
+use crate::state::AppState;
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::{Error, Result};
+use std::time::Duration;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// Periodic job run by the scheduler.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Job {
+    StatusSync,
+    UsageMetering,
+    TrafficAccounting,
+    Backups,
+    Invoices,
+    Webhooks,
+    Notifications,
+    Deprovisioning,
+    ProvisioningSweep,
+}
+
+impl Job {
+    /// Every job known to the scheduler.
+    pub const ALL: [Job; 9] = [
+        Job::StatusSync,
+        Job::UsageMetering,
+        Job::TrafficAccounting,
+        Job::Backups,
+        Job::Invoices,
+        Job::Webhooks,
+        Job::Notifications,
+        Job::Deprovisioning,
+        Job::ProvisioningSweep,
+    ];
+
+    /// Returns the unique name of the job, used as its key in the database.
+    ///
+    pub fn name(self) -> &'static str {
+        match self {
+            Job::StatusSync => "status_sync",
+            Job::UsageMetering => "usage_metering",
+            Job::TrafficAccounting => "traffic_accounting",
+            Job::Backups => "backups",
+            Job::Invoices => "invoices",
+            Job::Webhooks => "webhooks",
+            Job::Notifications => "notifications",
+            Job::Deprovisioning => "deprovisioning",
+            Job::ProvisioningSweep => "provisioning_sweep",
+        }
+    }
+
+    /// Returns the configured cron expression of the job, `None` if the job is
+    /// disabled.
+    ///
+    pub fn cron(self, config: &Config) -> Option<&str> {
+        let settings = &config.scheduler;
+        match self {
+            Job::UsageMetering if !config.usage.enabled => None,
+            Job::Backups if !config.backup.enabled => None,
+            Job::StatusSync => settings.status_sync.as_deref(),
+            Job::UsageMetering => settings.usage_metering.as_deref(),
+            Job::TrafficAccounting => settings.traffic_accounting.as_deref(),
+            Job::Backups => settings.backups.as_deref(),
+            Job::Invoices => settings.invoices.as_deref(),
+            Job::Webhooks => settings.webhooks.as_deref(),
+            Job::Notifications => settings.notifications.as_deref(),
+            Job::Deprovisioning => settings.deprovisioning.as_deref(),
+            Job::ProvisioningSweep => settings.provisioning_sweep.as_deref(),
+        }
+    }
+
+    /// Runs the job once.
+    ///
+    /// # Returns
+    ///
+    /// Number of items processed by the job.
+    ///
+    async fn run(self, app_state: &AppState, run: &JobRun) -> Result<u64> {
+        let count = match self {
+            Job::StatusSync => status::sync(app_state, app_state.clock.now()).await? as u64,
+            Job::UsageMetering => usage::collect(app_state, run.period).await? as u64,
+            Job::TrafficAccounting => traffic::collect(app_state).await? as u64,
+            Job::Backups => backup::trigger_due(app_state, app_state.clock.now()).await? as u64,
+            Job::Invoices => billing::invoice_usage(app_state, run.scheduled_at).await?,
+            Job::Webhooks => webhook::deliver_due(app_state, app_state.clock.now()).await? as u64,
+            Job::Notifications => {
+                let now = app_state.clock.now();
+                announcement::notify_published(app_state, now).await?;
+                notification::send_due(app_state, now).await? as u64
+            }
+            Job::Deprovisioning => user::deprovision(app_state, None).await? as u64,
+            Job::ProvisioningSweep => {
+                setup::sweep_stale(app_state, app_state.clock.now()).await? as u64
+            }
+        };
+
+        Ok(count)
+    }
+}
+
+/// Single run of a job claimed by this replica.
+///
+/// # Fields
+///
+/// * `scheduled_at`: Time the run was scheduled for, it may have started later.
+/// * `period`: Time between the scheduled run and the following one.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRun {
+    pub scheduled_at: DateTime<Utc>,
+    pub period: Duration,
+}
+
+/// Name of the leader role of the scheduler.
+const LEADER_ROLE: &str = "scheduler";
+
+/// Public entry point for the scheduler background task.
+///
+/// Registers the enabled jobs and checks them once per tick of the runtime
+/// settings, never returning. A changed tick applies from the next check. Only
+/// the elected leader among the replicas checks the jobs, the others stand by
+/// to take over. Every due job that the leader manages to claim
+/// is run in its own task, so a long job doesn't delay the others.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+///
+pub async fn run(app_state: AppState) {
+    let jobs = match register(&app_state, app_state.clock.now()).await {
+        Ok(jobs) => jobs,
+        Err(error) => {
+            tracing::error!(target: "scheduler", ?error, "Failed to register jobs!");
+            return;
+        }
+    };
+
+    let mut tick = app_state.runtime.load().scheduler_tick_sec;
+    let mut interval = ticker(tick);
+    let mut leader = Leader::new(LEADER_ROLE);
+
+    loop {
+        interval.tick().await;
+        let current_tick = app_state.runtime.load().scheduler_tick_sec;
+        if current_tick != tick {
+            tick = current_tick;
+            interval = ticker(tick);
+            tracing::info!(target: "scheduler", tick, "Tick changed");
+        }
+
+        match leader.elect(&app_state.pool).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(error) => {
+                tracing::error!(target: "scheduler", ?error, "Failed to elect leader!");
+                continue;
+            }
+        }
+
+        for (job, cron) in &jobs {
+            match claim(&app_state, *job, cron, app_state.clock.now()).await {
+                Ok(Some(run)) => {
+                    tokio::spawn(execute(app_state.clone(), *job, run));
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    tracing::error!(target: "scheduler", job = job.name(), ?error, "Failed to claim job!")
+                }
+            }
+        }
+    }
+}
+
+/// Parses the schedules of the enabled jobs and registers them in the
+/// database.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Enabled jobs with their schedules.
+///
+#[tracing::instrument(level = "trace", target = "scheduler", skip(app_state))]
+pub async fn register(
+    app_state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<Vec<(Job, CronSchedule)>> {
+    let mut jobs = Vec::new();
+
+    for job in Job::ALL {
+        let Some(cron) = job.cron(&app_state.config) else {
+            tracing::info!(target: "scheduler", job = job.name(), "Job disabled");
+            continue;
+        };
+        let cron = cron.parse::<CronSchedule>()?;
+        let next_run_at = next_run(job, &cron, now)?;
+
+        queries::register_scheduled_job(&app_state.pool, job.name(), &cron, next_run_at).await?;
+        tracing::info!(target: "scheduler", job = job.name(), %cron, "Job registered");
+        jobs.push((job, cron));
+    }
+
+    Ok(jobs)
+}
+
+/// Claims the job if it is due and not running on another replica.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `job`: Job to claim.
+/// * `cron`: Schedule of the job.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Claimed run, `None` if there is nothing to run.
+///
+#[tracing::instrument(level = "trace", target = "scheduler", skip(app_state))]
+pub async fn claim(
+    app_state: &AppState,
+    job: Job,
+    cron: &CronSchedule,
+    now: DateTime<Utc>,
+) -> Result<Option<JobRun>> {
+    let next_run_at = next_run(job, cron, now)?;
+    let locked_until = now + chrono::Duration::seconds(app_state.config.scheduler.lease_sec);
+
+    let scheduled_at =
+        queries::claim_scheduled_job(&app_state.pool, job.name(), now, next_run_at, locked_until)
+            .await?;
+
+    Ok(scheduled_at.map(|scheduled_at| JobRun {
+        scheduled_at,
+        period: period(cron, scheduled_at),
+    }))
+}
+
+// -----------------------------------------------------------------------------
+
+/// Runs a claimed job and releases it, recording the outcome.
+///
+async fn execute(app_state: AppState, job: Job, run: JobRun) {
+    let error = match job.run(&app_state, &run).await {
+        Ok(count) => {
+            tracing::info!(target: "scheduler", job = job.name(), count, "Job finished");
+            None
+        }
+        Err(error) => {
+            tracing::error!(target: "scheduler", job = job.name(), ?error, "Job failed!");
+            Some(error.to_string())
+        }
+    };
+
+    if let Err(error) = queries::finish_scheduled_job(
+        &app_state.pool,
+        job.name(),
+        app_state.clock.now(),
+        error.as_deref(),
+    )
+    .await
+    {
+        tracing::error!(target: "scheduler", job = job.name(), ?error, "Failed to release job!");
+    }
+}
+
+/// Creates the interval between the checks of the due jobs, the first tick
+/// completes immediately.
+///
+fn ticker(tick_sec: u64) -> Interval {
+    let mut interval = tokio::time::interval(Duration::from_secs(tick_sec.max(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    interval
+}
+
+/// Calculates the next run of a job, reporting a schedule that never runs
+/// again as a validation error.
+///
+fn next_run(job: Job, cron: &CronSchedule, time: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    cron.next_after(time).ok_or_else(|| {
+        Error::Validation(format!("Schedule {cron} of job {} never runs", job.name()))
+    })
+}
+
+/// Returns the time between a scheduled run and the following one, regardless
+/// of how late the run actually starts.
+///
+fn period(cron: &CronSchedule, scheduled_at: DateTime<Utc>) -> Duration {
+    cron.next_after(scheduled_at)
+        .and_then(|next| (next - scheduled_at).to_std().ok())
+        .unwrap_or_default()
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn default_schedules_should_be_valid() {
+        // Arrange
+        let config = Config::default();
+
+        // Act
+        let schedules = Job::ALL.map(|job| job.cron(&config).map(str::parse::<CronSchedule>));
+
+        // Assert
+        assert!(schedules.iter().all(|cron| matches!(cron, Some(Ok(_)))));
+    }
+
+    #[test]
+    fn legacy_switches_should_disable_jobs() {
+        // Arrange
+        let mut config = Config::default();
+        config.usage.enabled = false;
+        config.backup.enabled = false;
+
+        // Act
+        let usage_metering = Job::UsageMetering.cron(&config);
+        let backups = Job::Backups.cron(&config);
+        let invoices = Job::Invoices.cron(&config);
+
+        // Assert
+        assert!(usage_metering.is_none());
+        assert!(backups.is_none());
+        assert!(invoices.is_some());
+    }
+
+    #[test]
+    fn period_should_span_until_next_run() {
+        // Arrange
+        let cron = "0 * * * *".parse::<CronSchedule>().unwrap();
+        let scheduled_at = Utc.with_ymd_and_hms(2026, 10, 16, 10, 0, 0).unwrap();
+
+        // Act
+        let hourly = period(&cron, scheduled_at);
+
+        // Assert
+        assert_eq!(hourly, Duration::from_secs(3600));
+    }
+}
@@ -1,16 +1,69 @@
+use crate::clock::Clock;
 use crate::model::queries;
-use crate::model::types::ServerStatus;
+use crate::model::types::{ApiActionResult, ServerStatus};
 use crate::proxmox::Proxmox;
 use crate::proxmox::types::TaskRef;
-use crate::services;
+use crate::services::{self, Polling, event};
 use crate::state::AppState;
-use crate::web::types::ServerAction;
-use dashboard_common::prelude::Result;
+use crate::web::types::{BulkActionPayload, ServerAction};
+use dashboard_common::prelude::{Error, Result};
 use sqlx::PgTransaction;
+use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
-/// Public entry point for a server action background task.
+/// Returns the transient status of a server while the action runs, and the
+/// final status once it is done.
+///
+pub fn statuses(action: ServerAction) -> (ServerStatus, ServerStatus) {
+    match action {
+        ServerAction::Start => (ServerStatus::Starting, ServerStatus::Running),
+        ServerAction::Stop => (ServerStatus::Stopping, ServerStatus::Stopped),
+        ServerAction::Shutdown => (ServerStatus::ShuttingDown, ServerStatus::Stopped),
+        ServerAction::Reboot => (ServerStatus::Rebooting, ServerStatus::Running),
+    }
+}
+
+/// Returns the actions allowed on a server in the given status. Servers in a
+/// transient status or failed ones accept no actions.
+///
+pub fn allowed_actions(status: ServerStatus) -> &'static [ServerAction] {
+    match status {
+        ServerStatus::Running => &[
+            ServerAction::Stop,
+            ServerAction::Shutdown,
+            ServerAction::Reboot,
+        ],
+        ServerStatus::Stopped => &[ServerAction::Start],
+        _ => &[],
+    }
+}
+
+/// Checks the transition table, so the action is allowed on a server in the
+/// given status.
+///
+/// # Returns
+///
+/// An empty `Result` on success, `Error::Transition` with the allowed actions
+/// otherwise.
+///
+pub fn ensure_allowed(status: ServerStatus, action: ServerAction) -> Result<()> {
+    let allowed = allowed_actions(status);
+    if allowed.contains(&action) {
+        return Ok(());
+    }
+
+    Err(Error::Transition {
+        status,
+        action,
+        allowed: allowed.to_vec(),
+    })
+}
+
+/// Validates the action against the current status of the server and moves
+/// the server to the transient status of the action.
 ///
 /// # Arguments
 ///
@@ -19,33 +72,67 @@ use uuid::Uuid;
 /// * `server_id`: ID of the target server.
 /// * `action`: Specific action to perform.
 ///
-pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid, action: ServerAction) {
-    // Find desired statuses and update the server to the transient one
-    // immediately.
-    let (transient_status, final_status) = match action {
-        ServerAction::Start => (ServerStatus::Starting, ServerStatus::Running),
-        ServerAction::Stop => (ServerStatus::Stopping, ServerStatus::Stopped),
-        ServerAction::Shutdown => (ServerStatus::ShuttingDown, ServerStatus::Stopped),
-        ServerAction::Reboot => (ServerStatus::Rebooting, ServerStatus::Running),
-    };
-    let Ok(old_status) =
-        services::set_transient_status(&app_state.pool, user_id, server_id, transient_status).await
-    else {
-        tracing::error!(target: "service", status = ?transient_status, "Can't update server status to transient state");
-        return;
-    };
+/// # Returns
+///
+/// Status of the server before the action, to pass to `run`.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn begin(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    action: ServerAction,
+) -> Result<ServerStatus> {
+    let (transient_status, _) = statuses(action);
+    services::transition_status(
+        &app_state.pool,
+        user_id,
+        server_id,
+        transient_status,
+        |status| ensure_allowed(status, action),
+    )
+    .await
+}
+
+/// Public entry point for a server action background task. The server must
+/// already be in the transient status of the action, set with `begin`.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user performing the action.
+/// * `server_id`: ID of the target server.
+/// * `action`: Specific action to perform.
+/// * `old_status`: Status of the server before the action, restored on failure.
+///
+/// # Returns
+///
+/// Final status of the server once the action is done.
+///
+pub async fn run(
+    app_state: AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    action: ServerAction,
+    old_status: ServerStatus,
+) -> Result<ServerStatus> {
+    let (_, final_status) = statuses(action);
 
     // Create a transaction for a chain of all sequential queries.
-    let Ok(mut transaction) = app_state.pool.begin().await else {
-        tracing::error!(target: "service", "Failed to begin transaction!");
-        services::set_transient_status(&app_state.pool, user_id, server_id, old_status)
-            .await
-            .ok();
-        return;
+    let mut transaction = match app_state.pool.begin().await {
+        Ok(transaction) => transaction,
+        Err(error) => {
+            tracing::error!(target: "service", "Failed to begin transaction!");
+            queries::update_server_status(&app_state.pool, server_id, old_status)
+                .await
+                .ok();
+            return Err(error.into());
+        }
     };
 
     let result = start_action(
         &app_state.proxmox,
+        &app_state.clock,
         &mut transaction,
         user_id,
         server_id,
@@ -59,10 +146,131 @@ pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid, action: Se
     // Return the old status if something went wrong.
     if result.is_err() {
         tracing::error!(target: "service", status = ?old_status, "Action failed, reverting status");
-        services::set_transient_status(&app_state.pool, user_id, server_id, old_status)
+        queries::update_server_status(&app_state.pool, server_id, old_status)
             .await
             .ok();
+        if let Err(error) =
+            event::record_status(&app_state.pool, user_id, server_id, old_status).await
+        {
+            tracing::error!(target: "service", ?error, "Failed to record domain event!");
+        }
     }
+
+    result.map(|_| final_status)
+}
+
+/// Starts the same action on several servers of a user. Every server is moved
+/// to the transient status of the action at once, and a background job then
+/// runs the actions on at most `bulk_parallelism` servers at a time. A failure
+/// on one server doesn't affect the others.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user performing the action.
+/// * `payload`: Servers and the action to perform on them.
+///
+/// # Returns
+///
+/// Outcome of the start of the action for every distinct server, in the
+/// requested order, and the handle of the background job.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn start_bulk(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: BulkActionPayload,
+) -> Result<(Vec<ApiActionResult>, JoinHandle<()>)> {
+    let settings = &app_state.config.action;
+    let mut seen = HashSet::new();
+    let server_ids = payload
+        .server_ids
+        .into_iter()
+        .filter(|server_id| seen.insert(*server_id))
+        .collect::<Vec<_>>();
+    if server_ids.is_empty() {
+        return Err(Error::Validation("No servers given".to_owned()));
+    }
+    if server_ids.len() > settings.bulk_max_servers {
+        return Err(Error::Validation(format!(
+            "Too many servers, at most {} are allowed",
+            settings.bulk_max_servers
+        )));
+    }
+
+    let (transient_status, _) = statuses(payload.action);
+    let mut results = Vec::with_capacity(server_ids.len());
+    let mut started = Vec::with_capacity(server_ids.len());
+    for server_id in server_ids {
+        match begin(app_state, user_id, server_id, payload.action).await {
+            Ok(old_status) => {
+                started.push((server_id, old_status));
+                results.push(ApiActionResult {
+                    server_id,
+                    status: Some(transient_status),
+                    error: None,
+                });
+            }
+            Err(error) => {
+                tracing::warn!(target: "service", %server_id, ?error, "Bulk action rejected on server");
+                results.push(ApiActionResult {
+                    server_id,
+                    status: None,
+                    error: Some(error.into_status_message().1),
+                });
+            }
+        }
+    }
+    let job = tokio::spawn(run_bulk(
+        app_state.clone(),
+        user_id,
+        payload.action,
+        started,
+    ));
+
+    Ok((results, job))
+}
+
+/// Background job of a bulk action, running the action on the servers already
+/// moved to its transient status.
+///
+async fn run_bulk(
+    app_state: AppState,
+    user_id: Uuid,
+    action: ServerAction,
+    servers: Vec<(Uuid, ServerStatus)>,
+) {
+    let semaphore = Arc::new(Semaphore::new(
+        app_state.config.action.bulk_parallelism.max(1),
+    ));
+    let handles = servers
+        .into_iter()
+        .map(|(server_id, old_status)| {
+            let app_state = app_state.clone();
+            let semaphore = semaphore.clone();
+            let handle = tokio::spawn(async move {
+                let _permit = semaphore
+                    .acquire_owned()
+                    .await
+                    .map_err(|error| Error::Any(error.to_string()))?;
+                run(app_state, user_id, server_id, action, old_status).await
+            });
+            (server_id, handle)
+        })
+        .collect::<Vec<_>>();
+
+    let count = handles.len();
+    let mut failed = 0;
+    for (server_id, handle) in handles {
+        let result = handle
+            .await
+            .unwrap_or_else(|error| Err(Error::Any(error.to_string())));
+        if let Err(error) = result {
+            failed += 1;
+            tracing::warn!(target: "service", %server_id, ?error, "Bulk action failed on server");
+        }
+    }
+    tracing::info!(target: "service", count, failed, "Bulk action finished");
 }
 
 /// Core logic for a server action, executed within a database transaction.
@@ -70,6 +278,7 @@ pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid, action: Se
 /// # Arguments
 ///
 /// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `clock`: Clock waiting for the Proxmox task.
 /// * `transaction`: Active database transaction.
 /// * `user_id`: ID of the user performing the action.
 /// * `server_id`: ID of the target server.
@@ -79,9 +288,14 @@ pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid, action: Se
 ///
 /// An empty `Result` on success.
 ///
-#[tracing::instrument(level = "trace", target = "service", skip(proxmox_client, transaction))]
+#[tracing::instrument(
+    level = "trace",
+    target = "service",
+    skip(proxmox_client, clock, transaction)
+)]
 async fn start_action(
     proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
     transaction: &mut PgTransaction<'_>,
     user_id: Uuid,
     server_id: Uuid,
@@ -103,10 +317,41 @@ async fn start_action(
     tracing::debug!(target: "service", ?upid, "Proxmox action task started, waiting for completion");
 
     let task = TaskRef::new(&node, &upid);
-    services::wait_until_finish(proxmox_client, task, 1, None).await?;
+    services::wait_until_finish(proxmox_client, clock, task, Polling::POWER).await?;
     tracing::info!(target: "service", "Proxmox task finished successfully");
 
     queries::update_server_status(transaction.as_mut(), server_id, final_status).await?;
+    event::record_status(transaction.as_mut(), user_id, server_id, final_status).await?;
 
     Ok(())
 }
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_table_should_reject_invalid_actions() {
+        // Act
+        let reboot_running = ensure_allowed(ServerStatus::Running, ServerAction::Reboot);
+        let reboot_stopped = ensure_allowed(ServerStatus::Stopped, ServerAction::Reboot);
+        let start_deleting = ensure_allowed(ServerStatus::Deleting, ServerAction::Start);
+
+        // Assert
+        assert!(reboot_running.is_ok());
+        assert!(matches!(
+            reboot_stopped,
+            Err(Error::Transition {
+                status: ServerStatus::Stopped,
+                action: ServerAction::Reboot,
+                ref allowed,
+            }) if allowed == &[ServerAction::Start]
+        ));
+        assert!(matches!(
+            start_deleting,
+            Err(Error::Transition { ref allowed, .. }) if allowed.is_empty()
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::model::queries;
+use crate::model::types::{ApiGuestInfo, ApiServerDetail, ServerStatus};
+use crate::proxmox::types::VmRef;
+use crate::state::AppState;
+use dashboard_common::prelude::{Error, Result};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Time the guest agent is given to report, so a hanging agent doesn't hold
+/// up the request.
+const AGENT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Returns a server of the user, completed with the system and the addresses
+/// reported by its QEMU guest agent. The agent is only asked while the server
+/// is running, and a missing, unresponsive or slow agent leaves the guest
+/// details out instead of failing the request. A report is cached for
+/// `cache.guest_info_ttl_sec`.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Server with the guest details, if the agent reported them.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_server_detail(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ApiServerDetail> {
+    let server = queries::get_server_by_id(&app_state.pool, user_id, server_id).await?;
+    let guest = match (server.status, &server.node_name, server.vm_id) {
+        (ServerStatus::Running, Some(node), Some(vm_id)) => {
+            let vm = VmRef::new(node, vm_id);
+            let guest = app_state
+                .guests
+                .get_or_load(vm.clone(), get_guest_info(app_state, vm));
+            match guest.await {
+                Ok(guest) => Some(guest),
+                Err(error) => {
+                    tracing::debug!(target: "service", %server_id, ?error, "Guest agent not available");
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    Ok(ApiServerDetail { server, guest })
+}
+
+// -----------------------------------------------------------------------------
+
+/// Asks the guest agent of the VM for its system and network interfaces, both
+/// at once and within `AGENT_TIMEOUT`.
+///
+async fn get_guest_info(app_state: &AppState, vm: VmRef) -> Result<ApiGuestInfo> {
+    let requests = async {
+        tokio::try_join!(
+            app_state.proxmox.agent_os_info(vm.clone()),
+            app_state.proxmox.agent_network_interfaces(vm.clone()),
+        )
+    };
+    let (os_info, interfaces) = tokio::time::timeout(AGENT_TIMEOUT, requests)
+        .await
+        .map_err(|_| Error::Timeout(AGENT_TIMEOUT.as_secs_f32()))??;
+
+    Ok(ApiGuestInfo {
+        os_name: os_info.pretty_name.or(os_info.name),
+        kernel_release: os_info.kernel_release,
+        ip_addresses: interfaces
+            .iter()
+            .flat_map(|interface| interface.routable_addresses())
+            .map(|address| address.to_string())
+            .collect(),
+    })
+}
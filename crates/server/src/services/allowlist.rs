@@ -0,0 +1,259 @@
+use crate::model::queries;
+use crate::model::types::ApiAllowedNetwork;
+use crate::state::AppState;
+use crate::web::types::AllowedNetworkPayload;
+use dashboard_common::prelude::{Error, Result};
+use ipnet::IpNet;
+use sqlx::{Executor, PgTransaction, Postgres};
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Returns the networks the user may access the account from.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Allowed networks, oldest first.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn list_networks(app_state: &AppState, user_id: Uuid) -> Result<Vec<ApiAllowedNetwork>> {
+    queries::get_allowed_networks(&app_state.pool, user_id).await
+}
+
+/// Allows the user to access the account from an IPv4 or IPv6 network. The
+/// first network restricts the account, so it is rejected unless it contains
+/// the address of the client, which would be locked out otherwise.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `client_ip`: Address of the client adding the network.
+/// * `payload`: CIDR block of the network and its description.
+///
+/// # Returns
+///
+/// Allowed network.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn add_network(
+    app_state: &AppState,
+    user_id: Uuid,
+    client_ip: Option<IpAddr>,
+    payload: AllowedNetworkPayload,
+) -> Result<ApiAllowedNetwork> {
+    let cidr = parse_network(&payload.cidr)?;
+    let description = payload
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+
+    let mut transaction = app_state.pool.begin().await?;
+    let network =
+        queries::add_allowed_network(transaction.as_mut(), user_id, &cidr, description).await?;
+    ensure_not_locked_out(&mut transaction, user_id, client_ip).await?;
+    transaction.commit().await?;
+    app_state.allowlists.remove(&user_id);
+    tracing::info!(target: "service", network_id = %network.id, %cidr, "Network allowed");
+
+    Ok(network)
+}
+
+/// Removes a network the user allowed. Rejected if the remaining networks
+/// don't contain the address of the client, unless none remain.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `client_ip`: Address of the client removing the network.
+/// * `network_id`: ID of the allowed network.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn remove_network(
+    app_state: &AppState,
+    user_id: Uuid,
+    client_ip: Option<IpAddr>,
+    network_id: Uuid,
+) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+    if !queries::delete_allowed_network(transaction.as_mut(), user_id, network_id).await? {
+        return Err(Error::Validation(format!(
+            "Allowed network {network_id} not found"
+        )));
+    }
+    ensure_not_locked_out(&mut transaction, user_id, client_ip).await?;
+    transaction.commit().await?;
+    app_state.allowlists.remove(&user_id);
+    tracing::info!(target: "service", %network_id, "Allowed network removed");
+
+    Ok(())
+}
+
+/// Checks whether the user may access the account from the address of the
+/// client. Accounts without allowed networks are unrestricted.
+///
+/// The networks are cached for `cache.allowlist_ttl_sec`. Changes made on this
+/// replica apply at once, the ones made on another replica once the cached
+/// networks expire.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `client_ip`: Address of the client, `None` if unknown.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if access is allowed, `Error::Forbidden` otherwise.
+///
+pub async fn ensure_allowed(
+    app_state: &AppState,
+    user_id: Uuid,
+    client_ip: Option<IpAddr>,
+) -> Result<()> {
+    let networks = app_state
+        .allowlists
+        .get_or_load(user_id, load_networks(&app_state.pool, user_id))
+        .await?;
+    if is_allowed(&networks, client_ip) {
+        return Ok(());
+    }
+    tracing::warn!(target: "service", %user_id, ?client_ip, "Access from not allowed address");
+
+    Err(Error::Forbidden(match client_ip {
+        Some(ip) => format!("Access from {ip} is not allowed for this account"),
+        None => "Access from an unknown address is not allowed for this account".to_owned(),
+    }))
+}
+
+/// Checks whether the address belongs to one of the networks. Any address,
+/// even an unknown one, is allowed without networks. IPv4-mapped IPv6
+/// addresses are matched as IPv4.
+///
+pub fn is_allowed(networks: &[IpNet], client_ip: Option<IpAddr>) -> bool {
+    if networks.is_empty() {
+        return true;
+    }
+    let Some(ip) = client_ip.map(|ip| ip.to_canonical()) else {
+        return false;
+    };
+
+    networks.iter().any(|network| network.contains(&ip))
+}
+
+/// Parses a CIDR block, which must be the network address of the block.
+///
+/// # Arguments
+///
+/// * `cidr`: IPv4 or IPv6 CIDR block, like `203.0.113.0/24`.
+///
+/// # Returns
+///
+/// Parsed network, `Error::Validation` if the block is invalid.
+///
+pub fn parse_network(cidr: &str) -> Result<IpNet> {
+    let network = cidr
+        .trim()
+        .parse::<IpNet>()
+        .map_err(|_| Error::Validation(format!("Invalid CIDR block {cidr}")))?;
+    if network.trunc() != network {
+        return Err(Error::Validation(format!(
+            "{} is not the network address of /{}",
+            network.addr(),
+            network.prefix_len()
+        )));
+    }
+
+    Ok(network)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Rejects a change of the allowed networks that would lock the client out of
+/// the account.
+///
+async fn ensure_not_locked_out(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    client_ip: Option<IpAddr>,
+) -> Result<()> {
+    let networks = load_networks(transaction.as_mut(), user_id).await?;
+    if is_allowed(&networks, client_ip) {
+        return Ok(());
+    }
+
+    Err(Error::Validation(match client_ip {
+        Some(ip) => format!("The change would lock out the current address {ip}"),
+        None => "The change would lock out the current address".to_owned(),
+    }))
+}
+
+/// Reads the allowed networks of the user and parses their CIDR blocks.
+///
+async fn load_networks<'e, E>(executor: E, user_id: Uuid) -> Result<Vec<IpNet>>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let networks = queries::get_allowed_networks(executor, user_id).await?;
+
+    Ok(networks
+        .iter()
+        .filter_map(|network| parse_network(&network.cidr).ok())
+        .collect())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(cidr: &str) -> IpNet {
+        parse_network(cidr).unwrap()
+    }
+
+    #[test]
+    fn is_allowed_should_match_address_against_networks() {
+        // Arrange
+        let networks = [
+            network("203.0.113.0/24"),
+            network("198.51.100.7/32"),
+            network("2001:db8:1::/48"),
+        ];
+        let ip = |ip: &str| Some(ip.parse::<IpAddr>().unwrap());
+
+        // Act & Assert
+        assert!(is_allowed(&[], None));
+        assert!(is_allowed(&networks, ip("203.0.113.200")));
+        assert!(is_allowed(&networks, ip("::ffff:198.51.100.7")));
+        assert!(!is_allowed(&networks, ip("198.51.100.8")));
+        assert!(is_allowed(&networks, ip("2001:db8:1::7")));
+        assert!(!is_allowed(&networks, ip("2001:db8:2::7")));
+        assert!(!is_allowed(&networks, None));
+    }
+
+    #[test]
+    fn parse_network_should_require_network_address() {
+        // Act
+        let ipv4 = parse_network(" 203.0.113.0/24 ");
+        let ipv6 = parse_network("2001:db8::/32");
+        let host_bits = parse_network("203.0.113.7/24");
+        let invalid = parse_network("203.0.113.0");
+
+        // Assert
+        assert_eq!(ipv4.unwrap().to_string(), "203.0.113.0/24");
+        assert_eq!(ipv6.unwrap().to_string(), "2001:db8::/32");
+        assert!(matches!(host_bits, Err(Error::Validation(_))));
+        assert!(matches!(invalid, Err(Error::Validation(_))));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::model::queries;
+use crate::model::types::{AnnouncementKind, ApiAnnouncement, NotificationEvent};
+use crate::services::notification;
+use crate::state::AppState;
+use crate::web::types::AnnouncementPayload;
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::{Error, Result};
+use uuid::Uuid;
+
+/// Maximum length of an announcement title.
+const MAX_TITLE_LEN: usize = 200;
+
+/// Creates an announcement. A maintenance window is also sent as a
+/// notification to the users the announcement is meant for, once it is
+/// published.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Kind, text, audience and schedule of the announcement.
+///
+/// # Returns
+///
+/// Created announcement.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_announcement(
+    app_state: &AppState,
+    payload: AnnouncementPayload,
+) -> Result<ApiAnnouncement> {
+    let now = app_state.clock.now();
+    let payload = validate(payload, now)?;
+    let announcement = queries::create_announcement(&app_state.pool, &payload).await?;
+    tracing::info!(target: "service", announcement_id = %announcement.id, "Announcement created");
+
+    if announcement.kind == AnnouncementKind::Maintenance
+        && let Err(error) = notify_published(app_state, now).await
+    {
+        tracing::error!(target: "service", ?error, "Failed to notify about the maintenance!");
+    }
+
+    Ok(announcement)
+}
+
+/// Replaces the kind, text, audience and schedule of an announcement.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `announcement_id`: ID of the announcement.
+/// * `payload`: New kind, text, audience and schedule of the announcement.
+///
+/// # Returns
+///
+/// Updated announcement.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_announcement(
+    app_state: &AppState,
+    announcement_id: Uuid,
+    payload: AnnouncementPayload,
+) -> Result<ApiAnnouncement> {
+    let now = app_state.clock.now();
+    let payload = validate(payload, now)?;
+    let announcement =
+        queries::update_announcement(&app_state.pool, announcement_id, &payload).await?;
+    tracing::info!(target: "service", %announcement_id, "Announcement updated");
+
+    if announcement.kind == AnnouncementKind::Maintenance
+        && let Err(error) = notify_published(app_state, now).await
+    {
+        tracing::error!(target: "service", ?error, "Failed to notify about the maintenance!");
+    }
+
+    Ok(announcement)
+}
+
+/// Deletes an announcement.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `announcement_id`: ID of the announcement.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success, `Error::NotFound` if the announcement doesn't
+/// exist.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_announcement(app_state: &AppState, announcement_id: Uuid) -> Result<()> {
+    if !queries::delete_announcement(&app_state.pool, announcement_id).await? {
+        return Err(Error::NotFound(format!("Announcement {announcement_id}")));
+    }
+    tracing::info!(target: "service", %announcement_id, "Announcement deleted");
+
+    Ok(())
+}
+
+/// Notifies the audience of every maintenance window published by now whose
+/// audience isn't notified yet. Called when a window is created or updated,
+/// and by the scheduler for the windows scheduled to be published later. A
+/// window whose audience couldn't be notified is tried again on the next call.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Number of maintenance windows whose audience was notified.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn notify_published(app_state: &AppState, now: DateTime<Utc>) -> Result<usize> {
+    let mut count = 0;
+    for announcement in queries::claim_published_maintenances(&app_state.pool, now).await? {
+        match notify_maintenance(app_state, &announcement).await {
+            Ok(()) => count += 1,
+            Err(error) => {
+                tracing::error!(target: "service", announcement_id = %announcement.id, ?error, "Failed to notify about the maintenance!");
+                queries::release_maintenance(&app_state.pool, announcement.id).await?;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Notifies the audience of a published maintenance window.
+///
+async fn notify_maintenance(app_state: &AppState, announcement: &ApiAnnouncement) -> Result<()> {
+    let datacenter_code = announcement.datacenter_code.as_deref();
+    let user_ids = queries::get_announcement_audience(&app_state.pool, datacenter_code).await?;
+
+    notification::notify(app_state, &user_ids, NotificationEvent::Maintenance, |_| {
+        (announcement.title.clone(), announcement.message.clone())
+    })
+    .await
+}
+
+/// Trims the text of an announcement and publishes it now unless it is
+/// scheduled, checking that it expires after it is published.
+///
+fn validate(mut payload: AnnouncementPayload, now: DateTime<Utc>) -> Result<AnnouncementPayload> {
+    payload.title = payload.title.trim().to_owned();
+    payload.message = payload.message.trim().to_owned();
+    if payload.title.is_empty() || payload.title.chars().count() > MAX_TITLE_LEN {
+        return Err(Error::Validation(format!(
+            "Field title must be between 1 and {MAX_TITLE_LEN} characters long"
+        )));
+    }
+    if payload.message.is_empty() {
+        return Err(Error::Validation("Field message is empty".to_owned()));
+    }
+
+    let publish_at = *payload.publish_at.get_or_insert(now);
+    if payload
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= publish_at)
+    {
+        return Err(Error::Validation(
+            "Announcement must expire after it is published".to_owned(),
+        ));
+    }
+
+    Ok(payload)
+}
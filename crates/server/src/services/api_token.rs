@@ -0,0 +1,117 @@
+use crate::model::queries;
+use crate::model::types::ApiToken;
+use crate::services::user::{hash_token, new_token};
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::types::ApiTokenPayload;
+use dashboard_common::prelude::{AuthError, Error, Result};
+use uuid::Uuid;
+
+/// Prefix of the API tokens, telling them apart from the JWTs of the sessions.
+pub const PREFIX: &str = "dash_";
+
+/// Longest name of a token, in characters.
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Returns the API tokens of the user, without the tokens themselves.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// API tokens, oldest first.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn list_tokens(app_state: &AppState, user_id: Uuid) -> Result<Vec<ApiToken>> {
+    queries::get_api_tokens(&app_state.pool, user_id).await
+}
+
+/// Creates an API token for the user. Only its hash is stored, so the token
+/// itself can't be shown again.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `payload`: Name of the token.
+///
+/// # Returns
+///
+/// Created token, the only response that contains the token itself.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_token(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: ApiTokenPayload,
+) -> Result<ApiToken> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(Error::Validation(format!(
+            "Token name must have 1 to {MAX_NAME_LENGTH} characters"
+        )));
+    }
+
+    let (secret, _) = new_token();
+    let secret = format!("{PREFIX}{secret}");
+    let token =
+        queries::create_api_token(&app_state.pool, user_id, name, &hash_token(&secret)).await?;
+    tracing::info!(target: "service", token_id = %token.id, "API token created");
+
+    Ok(ApiToken {
+        secret: Some(secret),
+        ..token
+    })
+}
+
+/// Revokes an API token of the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `token_id`: ID of the token.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn revoke_token(app_state: &AppState, user_id: Uuid, token_id: Uuid) -> Result<()> {
+    if !queries::delete_api_token(&app_state.pool, user_id, token_id).await? {
+        return Err(Error::NotFound(format!("API token {token_id}")));
+    }
+    tracing::info!(target: "service", %token_id, "API token revoked");
+
+    Ok(())
+}
+
+/// Authenticates a request with an API token. The claims are those of a
+/// session issued for this request only.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `token`: API token from the `Authorization` header.
+///
+/// # Returns
+///
+/// Claims of the owner of the token, `AuthError::Token` if it is unknown or
+/// was revoked.
+///
+pub async fn authenticate(app_state: &AppState, token: &str) -> Result<Claims> {
+    let now = app_state.clock.now();
+    let user_id = queries::use_api_token(&app_state.pool, &hash_token(token), now)
+        .await?
+        .ok_or(Error::Auth(AuthError::Token))?;
+    let timestamp = now.timestamp() as usize;
+
+    Ok(Claims {
+        exp: timestamp,
+        iat: timestamp,
+        user_id,
+    })
+}
@@ -0,0 +1,476 @@
+use crate::clock::Clock;
+use crate::model::queries;
+use crate::model::types::{
+    ApiBackup, ApiBackupSchedule, BackupMode, CronSchedule, DueBackup, ServerStatus,
+};
+use crate::proxmox::Proxmox;
+use crate::proxmox::types::{Status, TaskRef, VmRef};
+use crate::services::{self, Polling};
+use crate::state::AppState;
+use crate::web::types::BackupSchedulePayload;
+use chrono::{DateTime, Utc};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::PgTransaction;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Number of upcoming runs checked against the minimal interval of a schedule.
+const CHECKED_RUNS: usize = 48;
+
+/// Triggers a backup of every server whose schedule is due and moves every
+/// due schedule to its next run, also the ones of servers not provisioned yet.
+/// A schedule that never runs again is deleted.
+///
+/// A failed backup doesn't stop the others and is not retried before the next
+/// scheduled run. The archives are tagged with the server, as Proxmox reuses
+/// the IDs of deleted VMs.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Number of triggered backups.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn trigger_due(app_state: &AppState, now: DateTime<Utc>) -> Result<usize> {
+    let mut transaction = app_state.pool.begin().await?;
+    let mut count = 0;
+
+    for backup in queries::get_due_backups(&mut transaction, now).await? {
+        let Some(next_run_at) = next_run(&backup, now) else {
+            tracing::error!(target: "service", server_id = %backup.server_id, cron = %backup.cron, "Backup schedule never runs again, deleting it");
+            queries::delete_backup_schedule(transaction.as_mut(), backup.server_id).await?;
+            continue;
+        };
+
+        let last_run_at = match (backup.vm_id, &backup.node_name) {
+            (Some(vm_id), Some(node_name)) => {
+                let vm = VmRef::new(node_name, vm_id);
+                let storage = &app_state.config.backup.storage;
+                let notes = notes(backup.server_id);
+                match app_state
+                    .proxmox
+                    .backup(vm, storage, backup.mode, &notes)
+                    .await
+                {
+                    Ok(upid) => {
+                        tracing::info!(target: "service", server_id = %backup.server_id, ?upid, "Backup started");
+                        count += 1;
+                    }
+                    Err(error) => {
+                        tracing::warn!(target: "service", server_id = %backup.server_id, ?error, "Can't start backup");
+                    }
+                }
+                Some(now)
+            }
+            _ => None,
+        };
+
+        queries::update_backup_run(&mut transaction, backup.server_id, last_run_at, next_run_at)
+            .await?;
+    }
+
+    transaction.commit().await?;
+    Ok(count)
+}
+
+/// Returns the backup schedule of a server.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Backup schedule of the server, `None` if it has none.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_schedule(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<Option<ApiBackupSchedule>> {
+    queries::get_server_by_id(&app_state.pool, user_id, server_id).await?;
+    queries::get_backup_schedule(&app_state.pool, server_id).await
+}
+
+/// Creates or replaces the backup schedule of a server.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `payload`: Cron expression and mode of the backups.
+///
+/// # Returns
+///
+/// Saved backup schedule with the time of the first backup.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_schedule(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: BackupSchedulePayload,
+) -> Result<ApiBackupSchedule> {
+    let cron = payload.cron.parse::<CronSchedule>()?;
+    let next_run_at = validate_schedule(
+        &cron,
+        app_state.config.backup.min_interval_sec,
+        app_state.clock.now(),
+    )?;
+    let mode = payload.mode.unwrap_or(BackupMode::Snapshot);
+
+    queries::get_server_by_id(&app_state.pool, user_id, server_id).await?;
+    let schedule =
+        queries::set_backup_schedule(&app_state.pool, server_id, &cron, mode, next_run_at).await?;
+    tracing::info!(target: "service", %server_id, %cron, %next_run_at, "Backup schedule saved");
+
+    Ok(schedule)
+}
+
+/// Deletes the backup schedule of a server. Existing backups are kept.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_schedule(app_state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    queries::get_server_by_id(&app_state.pool, user_id, server_id).await?;
+    if !queries::delete_backup_schedule(&app_state.pool, server_id).await? {
+        return Err(Error::Validation(format!(
+            "Server {server_id} has no backup schedule"
+        )));
+    }
+    tracing::info!(target: "service", %server_id, "Backup schedule deleted");
+
+    Ok(())
+}
+
+/// Lists the backup archives of a server that can be restored, newest first.
+/// Only the archives tagged with the server are listed, not the ones of a
+/// deleted VM that had the same ID.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Backup archives of the server on the configured storage.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn list_backups(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<Vec<ApiBackup>> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let tag = notes(server_id);
+    let mut backups = app_state
+        .proxmox
+        .backups(vm, &app_state.config.backup.storage)
+        .await?
+        .into_iter()
+        .filter(|archive| archive.notes.as_deref().map(str::trim) == Some(tag.as_str()))
+        .map(ApiBackup::from)
+        .collect::<Vec<_>>();
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+    Ok(backups)
+}
+
+/// Checks that a backup archive belongs to a server, so users can only restore
+/// the archives of their own servers.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `volid`: Volume ID of the backup archive.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the archive is a backup of the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn ensure_backup(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    volid: &str,
+) -> Result<()> {
+    let backups = list_backups(app_state, user_id, server_id).await?;
+    match backups.iter().any(|backup| backup.volid == volid) {
+        true => Ok(()),
+        false => Err(Error::Validation(format!(
+            "Backup {volid} not found for server {server_id}"
+        ))),
+    }
+}
+
+/// Public entry point for the backup restore background task.
+///
+/// Stops the VM, overwrites it with the backup archive and starts it again.
+/// The server stays in the `Restoring` status meanwhile, which must already be
+/// set with `services::set_transient_status`.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server to restore.
+/// * `volid`: Volume ID of the backup archive, checked by `ensure_backup`.
+/// * `old_status`: Status of the server before the restore.
+///
+pub async fn restore(
+    app_state: AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    volid: String,
+    old_status: ServerStatus,
+) {
+    // Create a transaction for a chain of all sequential queries.
+    let Ok(mut transaction) = app_state.pool.begin().await else {
+        tracing::error!(target: "service", "Failed to begin transaction!");
+        queries::update_server_status(&app_state.pool, server_id, old_status)
+            .await
+            .ok();
+        return;
+    };
+
+    let result = restore_backup(
+        &app_state.proxmox,
+        &app_state.clock,
+        &mut transaction,
+        user_id,
+        server_id,
+        &volid,
+        app_state.config.backup.restore_timeout_sec,
+    )
+    .await;
+
+    services::finalize_transaction(&result, transaction).await;
+
+    // The VM may be left stopped, so take its actual status if something went
+    // wrong.
+    if result.is_err() {
+        let status = actual_status(&app_state, user_id, server_id)
+            .await
+            .unwrap_or(old_status);
+        tracing::error!(target: "service", ?status, "Restore failed, reverting status");
+        queries::update_server_status(&app_state.pool, server_id, status)
+            .await
+            .ok();
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Core logic for a backup restore, executed within a database transaction.
+///
+/// # Arguments
+///
+/// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `clock`: Clock waiting for the Proxmox tasks.
+/// * `transaction`: Active database transaction.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server to restore.
+/// * `volid`: Volume ID of the backup archive.
+/// * `timeout`: Time in seconds the restore task may take.
+///
+/// # Returns
+///
+/// An empty `Result` on success.
+///
+#[tracing::instrument(
+    level = "trace",
+    target = "service",
+    skip(proxmox_client, clock, transaction)
+)]
+async fn restore_backup(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    server_id: Uuid,
+    volid: &str,
+    timeout: u64,
+) -> Result<()> {
+    let vm = queries::get_server_proxmox_ref(transaction.as_mut(), user_id, server_id).await?;
+
+    // Proxmox restores only stopped VMs.
+    if proxmox_client.vm_status(vm.clone()).await? == Status::Running {
+        let upid = proxmox_client.stop(vm.clone()).await?;
+        let task = TaskRef::new(&vm.node, &upid);
+        services::wait_until_finish(proxmox_client, clock, task, Polling::POWER).await?;
+        tracing::info!(target: "service", "VM stopped for restore");
+    }
+
+    let upid = proxmox_client.restore(vm.clone(), volid).await?;
+    tracing::debug!(target: "service", ?upid, "Proxmox restore task started, waiting for completion");
+    let task = TaskRef::new(&vm.node, &upid);
+    let polling = Polling::RESTORE.with_timeout(timeout);
+    services::wait_until_finish(proxmox_client, clock, task, polling).await?;
+    tracing::info!(target: "service", "Backup restored");
+
+    let upid = proxmox_client.start(vm.clone()).await?;
+    let task = TaskRef::new(&vm.node, &upid);
+    services::wait_until_finish(proxmox_client, clock, task, Polling::POWER).await?;
+    tracing::info!(target: "service", "VM started after restore");
+
+    queries::update_server_status(transaction.as_mut(), server_id, ServerStatus::Running).await?;
+
+    Ok(())
+}
+
+/// Reads the power status of a server's VM from Proxmox.
+///
+async fn actual_status(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Option<ServerStatus> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id)
+        .await
+        .ok()?;
+    let status = app_state.proxmox.vm_status(vm).await.ok()?;
+    Some(status.into())
+}
+
+/// Returns the notes the backup archives of a server are tagged with.
+///
+pub fn notes(server_id: Uuid) -> String {
+    format!("Dashboard server {server_id}")
+}
+
+/// Calculates the next run of a due schedule.
+///
+fn next_run(backup: &DueBackup, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    backup.cron.parse::<CronSchedule>().ok()?.next_after(now)
+}
+
+/// Checks that the schedule runs at all and not more often than the minimal
+/// interval allows.
+///
+/// # Returns
+///
+/// Time of the first run.
+///
+fn validate_schedule(
+    cron: &CronSchedule,
+    min_interval_sec: i64,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>> {
+    let first = cron
+        .next_after(now)
+        .ok_or_else(|| Error::Validation(format!("Backup schedule {cron} never runs")))?;
+
+    let mut previous = first;
+    for _ in 0..CHECKED_RUNS {
+        let Some(next) = cron.next_after(previous) else {
+            break;
+        };
+        if (next - previous).num_seconds() < min_interval_sec {
+            return Err(Error::Validation(format!(
+                "Backup schedule {cron} runs more often than every {min_interval_sec} seconds"
+            )));
+        }
+        previous = next;
+    }
+
+    Ok(first)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn cron_should_find_next_run() {
+        // Arrange
+        let daily = "30 2 * * *".parse::<CronSchedule>().unwrap();
+        let every_15_min = "*/15 * * * *".parse::<CronSchedule>().unwrap();
+        // 2026-10-16 is a Friday.
+        let weekdays = "0 22 * * 1-5".parse::<CronSchedule>().unwrap();
+        let sunday = "0 3 * * 7".parse::<CronSchedule>().unwrap();
+
+        // Assert
+        assert_eq!(daily.next_after(at(16, 1, 0)), Some(at(16, 2, 30)));
+        assert_eq!(daily.next_after(at(16, 2, 30)), Some(at(17, 2, 30)));
+        assert_eq!(every_15_min.next_after(at(16, 9, 7)), Some(at(16, 9, 15)));
+        assert_eq!(weekdays.next_after(at(16, 23, 0)), Some(at(19, 22, 0)));
+        assert_eq!(sunday.next_after(at(16, 0, 0)), Some(at(18, 3, 0)));
+    }
+
+    #[test]
+    fn cron_should_match_either_day_when_both_are_restricted() {
+        // Arrange
+        let cron = "0 0 20 * 6".parse::<CronSchedule>().unwrap();
+
+        // Assert
+        assert_eq!(cron.next_after(at(16, 12, 0)), Some(at(17, 0, 0)));
+        assert_eq!(cron.next_after(at(18, 12, 0)), Some(at(20, 0, 0)));
+    }
+
+    #[test]
+    fn cron_should_reject_invalid_expression() {
+        // Assert
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "0 0 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(matches!(
+                expression.parse::<CronSchedule>(),
+                Err(Error::Validation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn validate_schedule_should_reject_frequent_and_impossible_schedules() {
+        // Arrange
+        let hourly = "0 * * * *".parse::<CronSchedule>().unwrap();
+        let frequent = "*/30 * * * *".parse::<CronSchedule>().unwrap();
+        let never = "0 0 30 2 *".parse::<CronSchedule>().unwrap();
+        let now = Utc::now();
+
+        // Assert
+        assert!(validate_schedule(&hourly, 3600, now).is_ok());
+        assert!(matches!(
+            validate_schedule(&frequent, 3600, now),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            validate_schedule(&never, 3600, now),
+            Err(Error::Validation(_))
+        ));
+    }
+}
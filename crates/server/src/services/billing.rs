@@ -0,0 +1,318 @@
+use crate::i18n::Locale;
+use crate::model::queries;
+use crate::model::types::{
+    DomainEventKind, InvoiceStatus, NewCredit, NotificationEvent, PaymentStatus,
+};
+use crate::payments::types::{CheckoutRequest, CheckoutSession, PaymentEvent, PaymentEventKind};
+use crate::services::{event, notification};
+use crate::state::AppState;
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::PgTransaction;
+use uuid::Uuid;
+
+/// Starts a hosted checkout for an unpaid invoice. Only the part of the amount
+/// that is not covered by credit is charged.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the invoice.
+/// * `invoice_id`: ID of the invoice to pay.
+///
+/// # Returns
+///
+/// Checkout session with the URL the customer should be redirected to.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn start_checkout(
+    app_state: &AppState,
+    user_id: Uuid,
+    invoice_id: Uuid,
+) -> Result<CheckoutSession> {
+    let invoice = queries::get_invoice_by_id(&app_state.pool, user_id, invoice_id).await?;
+    if invoice.status != InvoiceStatus::Unpaid {
+        return Err(Error::Validation(format!(
+            "Invoice {} is already {}",
+            invoice.id, invoice.status
+        )));
+    }
+
+    let amount_cents = invoice.amount_due();
+    let session = app_state
+        .payments
+        .create_checkout(CheckoutRequest {
+            invoice_id: invoice.id,
+            description: invoice.description,
+            amount_cents,
+            currency: invoice.currency,
+        })
+        .await?;
+    tracing::info!(target: "service", session_id = %session.id, "Checkout session created");
+
+    queries::create_payment_record(
+        &app_state.pool,
+        invoice.id,
+        app_state.payments.name(),
+        &session.id,
+        amount_cents,
+    )
+    .await?;
+
+    Ok(session)
+}
+
+/// Processes a verified payment provider event exactly once.
+///
+/// The event ID is recorded in the same transaction as its side effects, so a
+/// redelivered event is acknowledged without settling the invoice twice. A
+/// payment only settles the invoice if it covers the amount due, see
+/// `settle_payment`.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `event`: Verified event received from the payment provider.
+///
+/// # Returns
+///
+/// An empty `Result` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn process_event(app_state: &AppState, event: PaymentEvent) -> Result<()> {
+    let provider = app_state.payments.name();
+    let mut transaction = app_state.pool.begin().await?;
+
+    let is_new =
+        queries::record_payment_event(&mut transaction, provider, &event.id, &event.event_type)
+            .await?;
+    if !is_new {
+        tracing::info!(target: "service", event_id = %event.id, "Payment event already processed");
+        return Ok(());
+    }
+
+    match event.kind {
+        PaymentEventKind::CheckoutCompleted {
+            session_id,
+            invoice_id,
+            amount_cents,
+        } => {
+            settle_payment(
+                &mut transaction,
+                provider,
+                &session_id,
+                invoice_id,
+                amount_cents,
+            )
+            .await?;
+        }
+        PaymentEventKind::CheckoutFailed {
+            session_id,
+            invoice_id,
+        } => {
+            queries::set_payment_status(
+                transaction.as_mut(),
+                provider,
+                &session_id,
+                PaymentStatus::Failed,
+            )
+            .await?;
+            tracing::warn!(target: "service", %invoice_id, %session_id, "Delayed payment failed");
+        }
+        PaymentEventKind::Ignored => {
+            tracing::debug!(target: "service", event_type = %event.event_type, "Payment event ignored");
+        }
+    }
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Settles the invoice paid in a checkout session. The invoice is only settled
+/// when the payment covers the amount due: a short payment leaves it unpaid,
+/// and whatever the invoice doesn't need, like a payment for an invoice that
+/// credit settled meanwhile, is kept as credit of the user.
+///
+async fn settle_payment(
+    transaction: &mut PgTransaction<'_>,
+    provider: &str,
+    session_id: &str,
+    invoice_id: Uuid,
+    amount_cents: i64,
+) -> Result<()> {
+    // Serializes with the credit applied to the invoice.
+    let user_id = queries::get_invoice_owner(transaction.as_mut(), invoice_id).await?;
+    queries::lock_user(transaction, user_id).await?;
+    let invoice = queries::get_invoice_by_id(transaction.as_mut(), user_id, invoice_id).await?;
+    let amount_due = match invoice.status {
+        InvoiceStatus::Unpaid => invoice.amount_due(),
+        _ => 0,
+    };
+
+    if amount_cents < amount_due {
+        queries::set_payment_status(
+            transaction.as_mut(),
+            provider,
+            session_id,
+            PaymentStatus::Succeeded,
+        )
+        .await?;
+        tracing::warn!(target: "service", %invoice_id, amount_cents, amount_due, "Payment short of the amount due, kept as credit");
+        let reason = format!("Partial payment of invoice {invoice_id}");
+        return keep_as_credit(transaction, user_id, invoice_id, amount_cents, reason).await;
+    }
+
+    let settled = queries::settle_invoice(transaction, invoice_id, provider, session_id).await?;
+    if amount_cents > amount_due {
+        tracing::warn!(target: "service", %invoice_id, amount_cents, amount_due, "Payment exceeds the amount due, kept as credit");
+        let reason = format!("Overpayment of invoice {invoice_id}");
+        keep_as_credit(
+            transaction,
+            user_id,
+            invoice_id,
+            amount_cents - amount_due,
+            reason,
+        )
+        .await?;
+    }
+    let Some(settled) = settled else {
+        return Ok(());
+    };
+    tracing::info!(target: "service", %invoice_id, amount_cents, "Invoice settled");
+
+    let data = serde_json::json!({
+        "invoice_id": invoice_id,
+        "amount_cents": amount_cents,
+    });
+    event::record(
+        transaction.as_mut(),
+        settled.user_id,
+        DomainEventKind::InvoicePaid,
+        data,
+    )
+    .await?;
+
+    if let Some(service_id) = settled.service_id
+        && queries::activate_suspended_service(transaction.as_mut(), service_id).await?
+    {
+        tracing::info!(target: "service", %service_id, "Service activated after payment");
+    }
+
+    Ok(())
+}
+
+/// Adds a paid amount the invoice doesn't need to the credit of the user.
+///
+async fn keep_as_credit(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    invoice_id: Uuid,
+    amount_cents: i64,
+    reason: String,
+) -> Result<()> {
+    if amount_cents <= 0 {
+        return Ok(());
+    }
+    let credit = NewCredit {
+        user_id,
+        amount_cents,
+        reason,
+        promo_code_id: None,
+        invoice_id: Some(invoice_id),
+        issued_by: None,
+    };
+    queries::create_credit(transaction.as_mut(), credit).await?;
+
+    Ok(())
+}
+
+/// Invoices the usage recorded in the calendar month before the given time.
+///
+/// Every service with recorded uptime gets a single invoice per month, so
+/// running the invoicing again for the same month doesn't create duplicates.
+/// Nothing is invoiced while usage is free. The invoiced users are notified
+/// once that their invoices are due.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `time`: Time within the month after the invoiced one.
+///
+/// # Returns
+///
+/// Number of created invoices.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn invoice_usage(app_state: &AppState, time: DateTime<Utc>) -> Result<u64> {
+    let payments = &app_state.config.payments;
+    if payments.usage_hour_cents <= 0 {
+        return Ok(0);
+    }
+
+    let to = month_start(time)?;
+    let from = month_start(to - Duration::days(1))?;
+    let month = from.format("%Y-%m").to_string();
+    let description = format!("Usage {month}");
+    let mut user_ids = queries::create_usage_invoices(
+        &app_state.pool,
+        from,
+        to,
+        payments.usage_hour_cents,
+        &payments.currency,
+        &description,
+    )
+    .await?;
+    let count = user_ids.len() as u64;
+    tracing::info!(target: "service", %from, %to, count, "Usage invoiced");
+    // Users with several services get several invoices, but one notification.
+    user_ids.sort_unstable();
+    user_ids.dedup();
+
+    let render = |locale: Locale| {
+        (
+            locale.translate("notification.invoice_due.title", &[]),
+            locale.translate(
+                "notification.invoice_due.message",
+                &[("month", month.as_str())],
+            ),
+        )
+    };
+    if let Err(error) =
+        notification::notify(app_state, &user_ids, NotificationEvent::InvoiceDue, render).await
+    {
+        tracing::error!(target: "service", ?error, "Failed to notify about the due invoices!");
+    }
+
+    Ok(count)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the start of the calendar month of the given time.
+///
+fn month_start(time: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    Utc.with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0)
+        .single()
+        .ok_or_else(|| Error::Any("Invalid billing period start".to_owned()))
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn month_start_should_cross_year_boundary() {
+        // Arrange
+        let time = Utc.with_ymd_and_hms(2026, 1, 1, 1, 0, 0).unwrap();
+
+        // Act
+        let to = month_start(time).unwrap();
+        let from = month_start(to - Duration::days(1)).unwrap();
+
+        // Assert
+        assert_eq!(to, Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(from, Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap());
+    }
+}
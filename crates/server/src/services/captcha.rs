@@ -0,0 +1,201 @@
+use crate::captcha::CaptchaProvider;
+use crate::model::queries;
+use crate::model::types::AuthAttemptKind;
+use crate::state::AppState;
+use chrono::{DateTime, Duration, Utc};
+use dashboard_common::prelude::{AuthError, Error, Result};
+use std::net::IpAddr;
+
+/// Requires a solved CAPTCHA for a login once too many logins failed with the
+/// email address or from the client address. Runs before the password is
+/// checked, so guessing passwords is slowed down by the CAPTCHA.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `email`: Email address of the login.
+/// * `client_ip`: Address of the client, if known.
+/// * `token`: Token of the solved CAPTCHA, if sent.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if no CAPTCHA is required or it was solved,
+/// `AuthError::Captcha` otherwise.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, token))]
+pub async fn check_login(
+    app_state: &AppState,
+    email: &str,
+    client_ip: Option<IpAddr>,
+    token: Option<&str>,
+) -> Result<()> {
+    let Some(provider) = &app_state.captcha else {
+        return Ok(());
+    };
+    let settings = &app_state.config.captcha;
+    let failures = queries::count_auth_attempts(
+        &app_state.pool,
+        AuthAttemptKind::FailedLogin,
+        Some(&normalize(email)),
+        client_ip.map(|ip| ip.to_string()).as_deref(),
+        window_start(app_state),
+    )
+    .await?;
+    if failures < settings.login_failures {
+        return Ok(());
+    }
+
+    verify(provider.as_ref(), token, client_ip).await
+}
+
+/// Requires a solved CAPTCHA for a registration once too many accounts were
+/// registered from the client address.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `client_ip`: Address of the client, if known.
+/// * `token`: Token of the solved CAPTCHA, if sent.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if no CAPTCHA is required or it was solved,
+/// `AuthError::Captcha` otherwise.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, token))]
+pub async fn check_registration(
+    app_state: &AppState,
+    client_ip: Option<IpAddr>,
+    token: Option<&str>,
+) -> Result<()> {
+    let Some(provider) = &app_state.captcha else {
+        return Ok(());
+    };
+    let registrations = queries::count_auth_attempts(
+        &app_state.pool,
+        AuthAttemptKind::Registration,
+        None,
+        client_ip.map(|ip| ip.to_string()).as_deref(),
+        window_start(app_state),
+    )
+    .await?;
+    if registrations < app_state.config.captcha.registrations {
+        return Ok(());
+    }
+
+    verify(provider.as_ref(), token, client_ip).await
+}
+
+/// Records an attempt counted towards the CAPTCHA thresholds. Nothing is
+/// recorded without a CAPTCHA provider, and a failed record is only logged,
+/// as it must not fail the request.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `kind`: Kind of the attempt.
+/// * `email`: Email address of the attempt, if any.
+/// * `client_ip`: Address of the client, if known.
+///
+pub async fn record_attempt(
+    app_state: &AppState,
+    kind: AuthAttemptKind,
+    email: Option<&str>,
+    client_ip: Option<IpAddr>,
+) {
+    if app_state.captcha.is_none() {
+        return;
+    }
+    let email = email.map(normalize);
+    let ip = client_ip.map(|ip| ip.to_string());
+
+    if let Err(error) = queries::add_auth_attempt(
+        &app_state.pool,
+        kind,
+        email.as_deref(),
+        ip.as_deref(),
+        window_start(app_state),
+    )
+    .await
+    {
+        tracing::error!(target: "service", ?error, %kind, "Failed to record authentication attempt!");
+    }
+}
+
+/// Verifies the token of a solved CAPTCHA with the provider.
+///
+/// # Arguments
+///
+/// * `provider`: CAPTCHA provider.
+/// * `token`: Token of the solved CAPTCHA, if sent.
+/// * `client_ip`: Address of the client, if known.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the provider accepted the token, `AuthError::Captcha` if
+/// the token is missing or rejected.
+///
+pub async fn verify(
+    provider: &(dyn CaptchaProvider + Send + Sync),
+    token: Option<&str>,
+    client_ip: Option<IpAddr>,
+) -> Result<()> {
+    let Some(token) = token.filter(|token| !token.is_empty()) else {
+        return Err(Error::Auth(AuthError::Captcha));
+    };
+    if !provider.verify(token, client_ip).await? {
+        tracing::warn!(target: "service", provider = provider.name(), ?client_ip, "CAPTCHA not solved");
+        return Err(Error::Auth(AuthError::Captcha));
+    }
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the start of the window the attempts are counted in.
+///
+fn window_start(app_state: &AppState) -> DateTime<Utc> {
+    app_state.clock.now() - Duration::seconds(app_state.config.captcha.window_sec)
+}
+
+/// Normalizes the email address, so variants of its case count together.
+///
+fn normalize(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct Provider;
+
+    #[async_trait]
+    impl CaptchaProvider for Provider {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+        async fn verify(&self, token: &str, _remote_ip: Option<IpAddr>) -> Result<bool> {
+            Ok(token == "solved")
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_should_require_accepted_token() {
+        // Act
+        let solved = verify(&Provider, Some("solved"), None).await;
+        let forged = verify(&Provider, Some("forged"), None).await;
+        let empty = verify(&Provider, Some(""), None).await;
+        let missing = verify(&Provider, None, None).await;
+
+        // Assert
+        assert!(solved.is_ok());
+        for result in [forged, empty, missing] {
+            assert!(matches!(result, Err(Error::Auth(AuthError::Captcha))));
+        }
+    }
+}
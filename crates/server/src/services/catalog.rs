@@ -0,0 +1,910 @@
+use crate::model::queries;
+use crate::model::types::{
+    ApiConfigOption, ApiCustomField, ApiDatacenter, ApiIso, ApiProduct, ApiProductGroup,
+    ApiTemplate,
+};
+use crate::proxmox::Proxmox;
+use crate::proxmox::types::{NodeListItem, VmRef};
+use crate::state::AppState;
+use crate::web::types::{
+    CustomFieldPayload, DatacenterPayload, IsoPayload, NamePayload, ProductDatacentersPayload,
+    ProductPayload, RequiredCustomField, TemplatePayload,
+};
+use dashboard_common::prelude::{Error, Result};
+use reqwest::Url;
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+/// Longest name of a catalog record.
+const MAX_NAME_LEN: usize = 100;
+
+/// Creates a product group.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Name of the group.
+///
+/// # Returns
+///
+/// Created group.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_group(app_state: &AppState, payload: NamePayload) -> Result<ApiProductGroup> {
+    let name = validate_name("name", &payload.name)?;
+    let group = queries::create_product_group(&app_state.pool, &name).await?;
+    app_state.catalog.invalidate();
+
+    Ok(group)
+}
+
+/// Renames a product group.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `group_id`: ID of the group.
+/// * `payload`: New name of the group.
+///
+/// # Returns
+///
+/// Updated group.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_group(
+    app_state: &AppState,
+    group_id: Uuid,
+    payload: NamePayload,
+) -> Result<ApiProductGroup> {
+    let name = validate_name("name", &payload.name)?;
+    let group = queries::update_product_group(&app_state.pool, group_id, &name).await?;
+    app_state.catalog.invalidate();
+
+    Ok(group)
+}
+
+/// Deletes a product group, which must not hold any product.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `group_id`: ID of the group.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the group is deleted.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_group(app_state: &AppState, group_id: Uuid) -> Result<()> {
+    if !queries::delete_product_group(&app_state.pool, group_id).await? {
+        return Err(Error::NotFound(format!("Product group {group_id}")));
+    }
+    app_state.catalog.invalidate();
+
+    Ok(())
+}
+
+/// Creates a product in an existing group.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Group, name and network rate limit of the product.
+///
+/// # Returns
+///
+/// Created product.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_product(app_state: &AppState, payload: ProductPayload) -> Result<ApiProduct> {
+    let payload = validate_product(payload)?;
+    if let Some(storage) = &payload.storage {
+        validate_product_storage(app_state, storage).await?;
+    }
+    let product = queries::create_product(&app_state.pool, &payload).await?;
+    app_state.catalog.invalidate();
+
+    Ok(product)
+}
+
+/// Updates a product.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `product_id`: ID of the product.
+/// * `payload`: New group, name and network rate limit of the product.
+///
+/// # Returns
+///
+/// Updated product.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_product(
+    app_state: &AppState,
+    product_id: Uuid,
+    payload: ProductPayload,
+) -> Result<ApiProduct> {
+    let payload = validate_product(payload)?;
+    if let Some(storage) = &payload.storage {
+        validate_product_storage(app_state, storage).await?;
+    }
+    let product = queries::update_product(&app_state.pool, product_id, &payload).await?;
+    app_state.catalog.invalidate();
+
+    Ok(product)
+}
+
+/// Deletes a product that was never ordered, together with its custom fields.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `product_id`: ID of the product.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the product is deleted.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_product(app_state: &AppState, product_id: Uuid) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+    if !queries::delete_product(&mut transaction, product_id).await? {
+        return Err(Error::NotFound(format!("Product {product_id}")));
+    }
+    transaction.commit().await?;
+    app_state.catalog.invalidate();
+
+    Ok(())
+}
+
+/// Creates a configurable option.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Name of the option.
+///
+/// # Returns
+///
+/// Created option.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_config_option(
+    app_state: &AppState,
+    payload: NamePayload,
+) -> Result<ApiConfigOption> {
+    let name = validate_name("name", &payload.name)?;
+    let option = queries::create_config_option(&app_state.pool, &name).await?;
+    app_state.catalog.invalidate();
+
+    Ok(option)
+}
+
+/// Renames a configurable option.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `option_id`: ID of the option.
+/// * `payload`: New name of the option.
+///
+/// # Returns
+///
+/// Updated option.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_config_option(
+    app_state: &AppState,
+    option_id: Uuid,
+    payload: NamePayload,
+) -> Result<ApiConfigOption> {
+    let name = validate_name("name", &payload.name)?;
+    let option = queries::update_config_option(&app_state.pool, option_id, &name).await?;
+    app_state.catalog.invalidate();
+
+    Ok(option)
+}
+
+/// Deletes a configurable option that no service has a value of.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `option_id`: ID of the option.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the option is deleted.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_config_option(app_state: &AppState, option_id: Uuid) -> Result<()> {
+    if !queries::delete_config_option(&app_state.pool, option_id).await? {
+        return Err(Error::NotFound(format!("Configurable option {option_id}")));
+    }
+    app_state.catalog.invalidate();
+
+    Ok(())
+}
+
+/// Creates a custom field of an existing product.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `product_id`: ID of the product.
+/// * `payload`: Name and offered values of the field.
+///
+/// # Returns
+///
+/// Created field.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_custom_field(
+    app_state: &AppState,
+    product_id: Uuid,
+    payload: CustomFieldPayload,
+) -> Result<ApiCustomField> {
+    queries::get_product(&app_state.pool, product_id).await?;
+    let payload = validate_custom_field(&app_state.pool, payload).await?;
+    let field = queries::create_custom_field(&app_state.pool, product_id, &payload).await?;
+    app_state.catalog.invalidate();
+
+    Ok(field)
+}
+
+/// Updates a custom field.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `field_id`: ID of the field.
+/// * `payload`: New name and offered values of the field.
+///
+/// # Returns
+///
+/// Updated field.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_custom_field(
+    app_state: &AppState,
+    field_id: Uuid,
+    payload: CustomFieldPayload,
+) -> Result<ApiCustomField> {
+    let payload = validate_custom_field(&app_state.pool, payload).await?;
+    let field = queries::update_custom_field(&app_state.pool, field_id, &payload).await?;
+    app_state.catalog.invalidate();
+
+    Ok(field)
+}
+
+/// Deletes a custom field that no service has a value of.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `field_id`: ID of the field.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the field is deleted.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_custom_field(app_state: &AppState, field_id: Uuid) -> Result<()> {
+    if !queries::delete_custom_field(&app_state.pool, field_id).await? {
+        return Err(Error::NotFound(format!("Custom field {field_id}")));
+    }
+    app_state.catalog.invalidate();
+
+    Ok(())
+}
+
+/// Registers an OS template, once Proxmox confirms the template VM.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: OS name, VMID and node of the template.
+///
+/// # Returns
+///
+/// Created template.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_template(
+    app_state: &AppState,
+    payload: TemplatePayload,
+) -> Result<ApiTemplate> {
+    let payload = validate_template(app_state.proxmox.as_ref(), payload).await?;
+    let template = queries::create_template(&app_state.pool, &payload).await?;
+    app_state.catalog.invalidate();
+
+    Ok(template)
+}
+
+/// Updates an OS template, once Proxmox confirms the template VM.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `template_id`: ID of the template.
+/// * `payload`: New OS name, VMID and node of the template.
+///
+/// # Returns
+///
+/// Updated template.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_template(
+    app_state: &AppState,
+    template_id: Uuid,
+    payload: TemplatePayload,
+) -> Result<ApiTemplate> {
+    let payload = validate_template(app_state.proxmox.as_ref(), payload).await?;
+    let mut transaction = app_state.pool.begin().await?;
+    let template = queries::update_template(&mut transaction, template_id, &payload).await?;
+    transaction.commit().await?;
+    app_state.catalog.invalidate();
+
+    Ok(template)
+}
+
+/// Deletes an OS template no service was created from.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `template_id`: ID of the template.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the template is deleted.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_template(app_state: &AppState, template_id: Uuid) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+    if !queries::delete_template(&mut transaction, template_id).await? {
+        return Err(Error::NotFound(format!("Template {template_id}")));
+    }
+    transaction.commit().await?;
+    app_state.catalog.invalidate();
+
+    Ok(())
+}
+
+/// Registers an ISO, once Proxmox lists it on the storage of the node. An ISO
+/// on a storage local to the node is kept for the servers on that node.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Name, volume and node of the ISO.
+///
+/// # Returns
+///
+/// Registered ISO.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_iso(app_state: &AppState, payload: IsoPayload) -> Result<ApiIso> {
+    let (payload, node_name, size_bytes) =
+        validate_iso(app_state.proxmox.as_ref(), payload).await?;
+
+    queries::create_iso(&app_state.pool, &payload, node_name.as_deref(), size_bytes).await
+}
+
+/// Deletes an ISO no server has inserted.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `iso_id`: ID of the ISO.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the ISO is deleted.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_iso(app_state: &AppState, iso_id: Uuid) -> Result<()> {
+    if !queries::delete_iso(&app_state.pool, iso_id).await? {
+        return Err(Error::NotFound(format!("ISO {iso_id}")));
+    }
+
+    Ok(())
+}
+
+/// Creates a datacenter.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Code, name, cluster endpoint and capacity flags.
+///
+/// # Returns
+///
+/// Created datacenter.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_datacenter(
+    app_state: &AppState,
+    payload: DatacenterPayload,
+) -> Result<ApiDatacenter> {
+    let payload = validate_datacenter(payload)?;
+    let datacenter = queries::create_datacenter(&app_state.pool, &payload).await?;
+    app_state.catalog.invalidate();
+
+    Ok(datacenter)
+}
+
+/// Updates a datacenter. A changed code is changed in the networks, templates
+/// and products of the datacenter too, but not in the values of the
+/// datacenter location fields, which name the datacenter until edited.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `code`: Current code of the datacenter.
+/// * `payload`: New code, name, cluster endpoint and capacity flags.
+///
+/// # Returns
+///
+/// Updated datacenter.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_datacenter(
+    app_state: &AppState,
+    code: &str,
+    payload: DatacenterPayload,
+) -> Result<ApiDatacenter> {
+    let payload = validate_datacenter(payload)?;
+    let datacenter = queries::update_datacenter(&app_state.pool, code, &payload).await?;
+    app_state.catalog.invalidate();
+
+    Ok(datacenter)
+}
+
+/// Deletes a datacenter without networks and templates.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `code`: Code of the datacenter.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the datacenter is deleted.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_datacenter(app_state: &AppState, code: &str) -> Result<()> {
+    if !queries::delete_datacenter(&app_state.pool, code).await? {
+        return Err(Error::NotFound(format!("Datacenter {code}")));
+    }
+    app_state.catalog.invalidate();
+
+    Ok(())
+}
+
+/// Replaces the datacenters an existing product is offered in.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `product_id`: ID of the product.
+/// * `payload`: Codes of the datacenters.
+///
+/// # Returns
+///
+/// Datacenters the product is offered in.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_product_datacenters(
+    app_state: &AppState,
+    product_id: Uuid,
+    payload: ProductDatacentersPayload,
+) -> Result<Vec<ApiDatacenter>> {
+    let mut transaction = app_state.pool.begin().await?;
+    queries::get_product(transaction.as_mut(), product_id).await?;
+    queries::set_product_datacenters(&mut transaction, product_id, &payload.datacenter_codes)
+        .await?;
+    let datacenters = queries::get_product_datacenters(transaction.as_mut(), product_id).await?;
+    transaction.commit().await?;
+    app_state.catalog.invalidate();
+
+    Ok(datacenters)
+}
+
+/// Ensures that the product can be ordered in the datacenter.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `product_id`: ID of the ordered product.
+/// * `code`: Code of the picked datacenter.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the datacenter is available, `Error::Validation` if the
+/// product is not offered there, `Error::Capacity` if the datacenter is full.
+///
+pub async fn ensure_datacenter_available<'e, E>(
+    executor: E,
+    product_id: Uuid,
+    code: &str,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let datacenter = queries::get_product_datacenters(executor, product_id)
+        .await?
+        .into_iter()
+        .find(|datacenter| datacenter.code == code && datacenter.is_active);
+
+    match datacenter {
+        Some(datacenter) if datacenter.is_full => Err(Error::Capacity(format!(
+            "Datacenter {code} takes no new servers"
+        ))),
+        Some(_) => Ok(()),
+        None => Err(Error::Validation(format!(
+            "Product {product_id} is not available in datacenter {code}"
+        ))),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Trims a name, rejecting an empty or too long one.
+///
+fn validate_name(field: &str, value: &str) -> Result<String> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().count() > MAX_NAME_LEN {
+        return Err(Error::Validation(format!(
+            "Field {field} must be between 1 and {MAX_NAME_LEN} characters long"
+        )));
+    }
+
+    Ok(value.to_owned())
+}
+
+/// Validates the name, the network rate limit, the storage, the disk limits and
+/// the traffic quota of a product.
+///
+fn validate_product(payload: ProductPayload) -> Result<ProductPayload> {
+    if payload.net_rate_mbps.is_some_and(|rate| rate <= 0) {
+        return Err(Error::Validation(
+            "Network rate limit must be positive".to_owned(),
+        ));
+    }
+    if payload.max_disks < 0 {
+        return Err(Error::Validation(
+            "Number of disks must not be negative".to_owned(),
+        ));
+    }
+    if payload.max_disk_gb.is_some_and(|size| size <= 0) {
+        return Err(Error::Validation(
+            "Disk size limit must be positive".to_owned(),
+        ));
+    }
+    if payload.traffic_quota_gb.is_some_and(|quota| quota <= 0) {
+        return Err(Error::Validation(
+            "Traffic quota must be positive".to_owned(),
+        ));
+    }
+
+    Ok(ProductPayload {
+        name: validate_name("name", &payload.name)?,
+        storage: optional_name(payload.storage),
+        ..payload
+    })
+}
+
+/// Checks that the storage of a product holds VM disks on every node the
+/// servers are cloned on: the nodes of the templates, or every online node
+/// of the cluster while there are no templates yet.
+///
+async fn validate_product_storage(app_state: &AppState, storage: &str) -> Result<()> {
+    let mut nodes = queries::get_templates(&app_state.pool)
+        .await?
+        .into_iter()
+        .map(|template| template.template_node)
+        .collect::<Vec<_>>();
+    if nodes.is_empty() {
+        nodes = app_state
+            .proxmox
+            .list_nodes()
+            .await?
+            .into_iter()
+            .filter(NodeListItem::is_online)
+            .map(|item| item.node)
+            .collect();
+    }
+    nodes.sort();
+    nodes.dedup();
+
+    for node in nodes {
+        let storages = app_state.proxmox.list_storages(&node).await?;
+        if !storages
+            .iter()
+            .any(|info| info.storage == storage && info.holds_images())
+        {
+            return Err(Error::Validation(format!(
+                "Storage {storage} on node {node} cannot hold VM disks"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates the name and the offered values of a custom field. The values of
+/// the OS template field must name existing templates, those of the
+/// datacenter location field existing datacenters.
+///
+async fn validate_custom_field<'e, E>(
+    executor: E,
+    payload: CustomFieldPayload,
+) -> Result<CustomFieldPayload>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let name = validate_name("name", &payload.name)?;
+    let mut options = Vec::with_capacity(payload.options.len());
+    for option in &payload.options {
+        let option = validate_name("options", option)?;
+        if !options.contains(&option) {
+            options.push(option);
+        }
+    }
+
+    let missing = match referenced_field(&name) {
+        Some(RequiredCustomField::OsTemplate) => {
+            queries::get_missing_templates(executor, &options).await?
+        }
+        Some(RequiredCustomField::Datacenter) => {
+            queries::get_missing_datacenters(executor, &options).await?
+        }
+        None => Vec::new(),
+    };
+    if !missing.is_empty() {
+        return Err(Error::Validation(format!(
+            "Field {name} references unknown values: {}",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(CustomFieldPayload { name, options })
+}
+
+/// Validates the OS name and checks that the VM exists on the node and is a
+/// template, so new servers can be cloned from it. The cloud-init snippet of
+/// an application must be a volume of a snippets storage.
+///
+async fn validate_template(
+    proxmox: &(dyn Proxmox + Send + Sync),
+    payload: TemplatePayload,
+) -> Result<TemplatePayload> {
+    let os_name = validate_name("os_name", &payload.os_name)?;
+    let template_node = validate_name("template_node", &payload.template_node)?;
+    if payload.template_vmid <= 0 {
+        return Err(Error::Validation(
+            "Template VMID must be positive".to_owned(),
+        ));
+    }
+
+    let vm = VmRef::new(&template_node, payload.template_vmid);
+    if !proxmox.vm_exists(vm.clone()).await? {
+        return Err(Error::Validation(format!(
+            "VM {} not found on node {}",
+            vm.id, vm.node
+        )));
+    }
+    if !proxmox.vm_current_config(vm.clone()).await?.is_template() {
+        return Err(Error::Validation(format!(
+            "VM {} on node {} is not a template",
+            vm.id, vm.node
+        )));
+    }
+
+    let storage = optional_name(payload.storage);
+    if let Some(storage) = &storage {
+        let storages = proxmox.list_storages(&vm.node).await?;
+        if !storages
+            .iter()
+            .any(|info| &info.storage == storage && info.holds_images())
+        {
+            return Err(Error::Validation(format!(
+                "Storage {storage} on node {} cannot hold VM disks",
+                vm.node
+            )));
+        }
+    }
+
+    let cloud_init_snippet = optional_name(payload.cloud_init_snippet);
+    if let Some(snippet) = cloud_init_snippet
+        .as_deref()
+        .filter(|snippet| !snippet.contains(":snippets/"))
+    {
+        return Err(Error::Validation(format!(
+            "Cloud-init snippet {snippet} is not a snippets volume"
+        )));
+    }
+
+    Ok(TemplatePayload {
+        os_name,
+        template_vmid: payload.template_vmid,
+        template_node,
+        datacenter_code: optional_name(payload.datacenter_code),
+        storage,
+        kind: payload.kind,
+        description: optional_name(payload.description),
+        cloud_init_snippet,
+        post_install_script: payload
+            .post_install_script
+            .filter(|script| !script.trim().is_empty()),
+    })
+}
+
+/// Validates the name of an ISO and checks that its volume is an ISO image on
+/// the storage of the node.
+///
+/// # Returns
+///
+/// Trimmed payload, the node the ISO is limited to unless its storage is
+/// shared, and the size of the ISO in bytes.
+///
+async fn validate_iso(
+    proxmox: &(dyn Proxmox + Send + Sync),
+    payload: IsoPayload,
+) -> Result<(IsoPayload, Option<String>, i64)> {
+    let name = validate_name("name", &payload.name)?;
+    let node = validate_name("node", &payload.node)?;
+    let volid = payload.volid.trim().to_owned();
+    let Some((storage, _)) = volid
+        .split_once(':')
+        .filter(|(storage, path)| !storage.is_empty() && path.starts_with("iso/"))
+    else {
+        return Err(Error::Validation(format!(
+            "Volume {volid} is not an ISO image"
+        )));
+    };
+
+    let image = proxmox
+        .list_isos(&node, storage)
+        .await?
+        .into_iter()
+        .find(|image| image.volid == volid)
+        .ok_or_else(|| Error::Validation(format!("ISO {volid} not found on node {node}")))?;
+    let shared = proxmox
+        .list_storages(&node)
+        .await?
+        .into_iter()
+        .find(|info| info.storage == storage)
+        .ok_or_else(|| Error::Validation(format!("Storage {storage} not found on node {node}")))?
+        .shared
+        == 1;
+
+    let node_name = (!shared).then(|| node.clone());
+    Ok((IsoPayload { name, volid, node }, node_name, image.size))
+}
+
+/// Trims an optional name, treating a blank one as missing.
+///
+fn optional_name(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Validates the code, the name and the cluster endpoint of a datacenter.
+///
+fn validate_datacenter(payload: DatacenterPayload) -> Result<DatacenterPayload> {
+    let cluster_url = payload
+        .cluster_url
+        .map(|url| url.trim().to_owned())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &cluster_url {
+        let valid = Url::parse(url)
+            .is_ok_and(|parsed| parsed.scheme() == "https" && parsed.host().is_some());
+        if !valid {
+            return Err(Error::Validation(format!("Invalid cluster URL {url}")));
+        }
+    }
+
+    Ok(DatacenterPayload {
+        code: validate_name("code", &payload.code)?,
+        display_name: validate_name("display_name", &payload.display_name)?,
+        cluster_url,
+        ..payload
+    })
+}
+
+/// Returns the required custom field whose values reference other records,
+/// matching the name case-insensitively.
+///
+fn referenced_field(name: &str) -> Option<RequiredCustomField> {
+    [
+        RequiredCustomField::OsTemplate,
+        RequiredCustomField::Datacenter,
+    ]
+    .into_iter()
+    .find(|field| field.to_string().eq_ignore_ascii_case(name))
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn product_should_be_validated() {
+        // Arrange
+        let payload = |name: &str, net_rate_mbps| ProductPayload {
+            group_id: Uuid::new_v4(),
+            name: name.to_owned(),
+            net_rate_mbps,
+            storage: Some(" local-zfs ".to_owned()),
+            max_disks: 0,
+            max_disk_gb: None,
+            traffic_quota_gb: None,
+        };
+
+        // Act
+        let valid = validate_product(payload("  VPS S  ", Some(100)));
+        let unnamed = validate_product(payload(" ", None));
+        let zero_rate = validate_product(payload("VPS S", Some(0)));
+        let zero_disk = validate_product(ProductPayload {
+            max_disk_gb: Some(0),
+            ..payload("VPS S", None)
+        });
+        let zero_traffic = validate_product(ProductPayload {
+            traffic_quota_gb: Some(0),
+            ..payload("VPS S", None)
+        });
+
+        // Assert
+        let valid = valid.unwrap();
+        assert_eq!(valid.name, "VPS S");
+        assert_eq!(valid.storage.as_deref(), Some("local-zfs"));
+        assert!(matches!(unnamed, Err(Error::Validation(_))));
+        assert!(matches!(zero_rate, Err(Error::Validation(_))));
+        assert!(matches!(zero_disk, Err(Error::Validation(_))));
+        assert!(matches!(zero_traffic, Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn datacenter_should_be_validated() {
+        // Arrange
+        let payload = |code: &str, cluster_url: Option<&str>| DatacenterPayload {
+            code: code.to_owned(),
+            display_name: "Amsterdam".to_owned(),
+            cluster_url: cluster_url.map(str::to_owned),
+            is_active: true,
+            is_full: false,
+        };
+
+        // Act
+        let valid = validate_datacenter(payload(" ams-1 ", Some("https://pve.ams-1.example.com")));
+        let blank_url = validate_datacenter(payload("ams-1", Some(" ")));
+        let plain_http = validate_datacenter(payload("ams-1", Some("http://pve.example.com")));
+        let uncoded = validate_datacenter(payload("", None));
+
+        // Assert
+        assert_eq!(valid.unwrap().code, "ams-1");
+        assert_eq!(blank_url.unwrap().cluster_url, None);
+        assert!(matches!(plain_http, Err(Error::Validation(_))));
+        assert!(matches!(uncoded, Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn referenced_fields_should_match_case_insensitively() {
+        // Assert
+        assert_eq!(
+            referenced_field("os template"),
+            Some(RequiredCustomField::OsTemplate)
+        );
+        assert_eq!(
+            referenced_field("Datacenter Location"),
+            Some(RequiredCustomField::Datacenter)
+        );
+        assert_eq!(referenced_field("Hostname"), None);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::model::queries;
+use crate::model::types::{
+    ApiCreditBalance, ApiInvoice, ApiPromoCode, DomainEventKind, InvoiceStatus, NewCredit,
+    NewPromoCode,
+};
+use crate::services::event;
+use crate::state::AppState;
+use dashboard_common::prelude::{Error, Result};
+use uuid::Uuid;
+
+/// Returns the credit balance of a user together with its ledger.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Current balance and all ledger entries of the user.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_balance(app_state: &AppState, user_id: Uuid) -> Result<ApiCreditBalance> {
+    let balance_cents = queries::get_credit_balance(&app_state.pool, user_id).await?;
+    let entries = queries::get_credits_for_user(&app_state.pool, user_id).await?;
+
+    Ok(ApiCreditBalance {
+        balance_cents,
+        entries,
+    })
+}
+
+/// Redeems a promo code, adding its amount to the user's credit balance.
+///
+/// The code row is locked for the whole transaction, so concurrent redemptions
+/// can't exceed its redemption limit.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user redeeming the code.
+/// * `code`: Promo code as entered by the user.
+///
+/// # Returns
+///
+/// Credit balance of the user after the redemption.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn redeem_promo_code(
+    app_state: &AppState,
+    user_id: Uuid,
+    code: &str,
+) -> Result<ApiCreditBalance> {
+    let code = normalize_code(code)?;
+    let mut transaction = app_state.pool.begin().await?;
+
+    let promo_code = queries::find_promo_code_for_update(&mut transaction, &code)
+        .await?
+        .filter(|promo_code| promo_code.is_redeemable(app_state.clock.now()))
+        .ok_or_else(|| Error::Validation(format!("Promo code {code} is not valid")))?;
+
+    let credit = NewCredit {
+        user_id,
+        amount_cents: promo_code.amount_cents,
+        reason: format!("Promo code {code}"),
+        promo_code_id: Some(promo_code.id),
+        invoice_id: None,
+        issued_by: None,
+    };
+    if queries::create_credit(transaction.as_mut(), credit)
+        .await?
+        .is_none()
+    {
+        return Err(Error::Validation(format!(
+            "Promo code {code} is already redeemed"
+        )));
+    }
+    queries::increment_promo_redemptions(&mut transaction, promo_code.id).await?;
+
+    transaction.commit().await?;
+    tracing::info!(target: "service", %code, amount_cents = promo_code.amount_cents, "Promo code redeemed");
+
+    get_balance(app_state, user_id).await
+}
+
+/// Covers an unpaid invoice with the user's credit balance, as much as the
+/// balance allows. Once the invoice is fully covered, it is settled and the
+/// suspended service is activated.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the invoice.
+/// * `invoice_id`: ID of the invoice to pay.
+///
+/// # Returns
+///
+/// Updated invoice.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn apply_to_invoice(
+    app_state: &AppState,
+    user_id: Uuid,
+    invoice_id: Uuid,
+) -> Result<ApiInvoice> {
+    let mut transaction = app_state.pool.begin().await?;
+
+    queries::lock_user(&mut transaction, user_id).await?;
+    let invoice = queries::get_invoice_by_id(transaction.as_mut(), user_id, invoice_id).await?;
+    if invoice.status != InvoiceStatus::Unpaid {
+        return Err(Error::Validation(format!(
+            "Invoice {} is already {}",
+            invoice.id, invoice.status
+        )));
+    }
+
+    let balance_cents = queries::get_credit_balance(transaction.as_mut(), user_id).await?;
+    let applied_cents = applicable_credit(balance_cents, invoice.amount_due());
+    if applied_cents == 0 {
+        return Err(Error::Validation("No credit available".to_owned()));
+    }
+
+    let credit = NewCredit {
+        user_id,
+        amount_cents: -applied_cents,
+        reason: format!("Applied to invoice {invoice_id}"),
+        promo_code_id: None,
+        invoice_id: Some(invoice_id),
+        issued_by: None,
+    };
+    queries::create_credit(transaction.as_mut(), credit).await?;
+    let invoice =
+        queries::apply_credit_to_invoice(&mut transaction, invoice_id, applied_cents).await?;
+    tracing::info!(target: "service", %invoice_id, applied_cents, "Credit applied to invoice");
+
+    if invoice.status == InvoiceStatus::Paid {
+        let data = serde_json::json!({
+            "invoice_id": invoice_id,
+            "amount_cents": invoice.amount_cents,
+        });
+        event::record(
+            transaction.as_mut(),
+            user_id,
+            DomainEventKind::InvoicePaid,
+            data,
+        )
+        .await?;
+
+        if let Some(service_id) = invoice.service_id
+            && queries::activate_suspended_service(transaction.as_mut(), service_id).await?
+        {
+            tracing::info!(target: "service", %service_id, "Service activated after payment");
+        }
+    }
+
+    transaction.commit().await?;
+    Ok(invoice)
+}
+
+/// Issues credit to a user on behalf of an administrator.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `admin_id`: ID of the administrator issuing the credit.
+/// * `user_id`: ID of the user receiving the credit.
+/// * `amount_cents`: Amount of credit, must be positive.
+/// * `reason`: Reason shown in the user's credit ledger.
+///
+/// # Returns
+///
+/// Credit balance of the user after the credit is issued.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn issue_credit(
+    app_state: &AppState,
+    admin_id: Uuid,
+    user_id: Uuid,
+    amount_cents: i64,
+    reason: String,
+) -> Result<ApiCreditBalance> {
+    if amount_cents <= 0 {
+        return Err(Error::Validation(
+            "Credit amount must be positive".to_owned(),
+        ));
+    }
+
+    queries::get_user_by_id(&app_state.pool, user_id).await?;
+    let credit = NewCredit {
+        user_id,
+        amount_cents,
+        reason,
+        promo_code_id: None,
+        invoice_id: None,
+        issued_by: Some(admin_id),
+    };
+    queries::create_credit(&app_state.pool, credit).await?;
+    tracing::info!(target: "service", %user_id, amount_cents, "Credit issued");
+
+    get_balance(app_state, user_id).await
+}
+
+/// Creates a new promo code.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `promo_code`: Details of the new code.
+///
+/// # Returns
+///
+/// Created promo code.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_promo_code(
+    app_state: &AppState,
+    mut promo_code: NewPromoCode,
+) -> Result<ApiPromoCode> {
+    promo_code.code = normalize_code(&promo_code.code)?;
+    if promo_code.amount_cents <= 0 {
+        return Err(Error::Validation(
+            "Promo code amount must be positive".to_owned(),
+        ));
+    }
+    if promo_code.max_redemptions.is_some_and(|max| max <= 0) {
+        return Err(Error::Validation(
+            "Promo code redemption limit must be positive".to_owned(),
+        ));
+    }
+
+    queries::create_promo_code(&app_state.pool, promo_code).await
+}
+
+// -----------------------------------------------------------------------------
+
+/// Normalizes a promo code, so codes are matched case-insensitively.
+///
+fn normalize_code(code: &str) -> Result<String> {
+    let code = code.trim().to_uppercase();
+    if code.is_empty() {
+        return Err(Error::Validation("Promo code is empty".to_owned()));
+    }
+
+    Ok(code)
+}
+
+/// Calculates how much of the credit balance can be applied to the amount due.
+///
+fn applicable_credit(balance_cents: i64, due_cents: i64) -> i64 {
+    balance_cents.min(due_cents).max(0)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn normalize_code_should_ignore_case_and_whitespace() {
+        // Arrange
+        let code = "  welcome10 ";
+
+        // Act
+        let normalized = normalize_code(code).unwrap();
+
+        // Assert
+        assert_eq!(normalized, "WELCOME10");
+    }
+
+    #[test]
+    fn normalize_code_should_reject_empty_code() {
+        // Act
+        let result = normalize_code("   ");
+
+        // Assert
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[test]
+    fn applicable_credit_should_not_exceed_balance_or_amount_due() {
+        // Assert
+        assert_eq!(applicable_credit(500, 1500), 500);
+        assert_eq!(applicable_credit(2000, 1500), 1500);
+        assert_eq!(applicable_credit(-100, 1500), 0);
+        assert_eq!(applicable_credit(500, 0), 0);
+    }
+
+    #[test]
+    fn promo_code_should_not_be_redeemable_when_expired_or_exhausted() {
+        // Arrange
+        let now = Utc::now();
+        let promo_code = ApiPromoCode {
+            id: Uuid::new_v4(),
+            code: "WELCOME10".to_owned(),
+            amount_cents: 1000,
+            max_redemptions: Some(2),
+            redemptions: 1,
+            expires_at: Some(now + chrono::Duration::days(1)),
+            created_at: now,
+        };
+        let expired = ApiPromoCode {
+            expires_at: Some(now - chrono::Duration::days(1)),
+            ..promo_code.clone()
+        };
+        let exhausted = ApiPromoCode {
+            redemptions: 2,
+            ..promo_code.clone()
+        };
+
+        // Assert
+        assert!(promo_code.is_redeemable(now));
+        assert!(!expired.is_redeemable(now));
+        assert!(!exhausted.is_redeemable(now));
+    }
+}
@@ -1,45 +1,51 @@
-﻿use crate::model::queries;
-use crate::model::types::ServerStatus;
+﻿use crate::clock::Clock;
+use crate::model::queries;
+use crate::model::types::{DomainEventKind, ServerStatus};
 use crate::proxmox::Proxmox;
 use crate::proxmox::types::TaskRef;
 use crate::services;
-use crate::services::wait_until_finish;
+use crate::services::{Polling, wait_until_finish};
 use crate::state::AppState;
-use dashboard_common::prelude::Result;
+use dashboard_common::prelude::{Error, Result};
 use sqlx::PgTransaction;
 use std::sync::Arc;
 use uuid::Uuid;
 
-/// Public entry point for the server deletion background task.
+/// Public entry point for the server deletion background task. The server
+/// must already be in the `Deleting` status, set with
+/// `services::set_transient_status`.
 ///
 /// # Arguments
 ///
 /// * `app_state`: Shared application state.
 /// * `user_id`: ID of the user who owns the server.
 /// * `server_id`: ID of the server to delete.
+/// * `old_status`: Status of the server before the deletion, restored on
+///   failure.
 ///
-pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid) {
-    // Immediately update the status to `Deleting.
-    let Ok(old_status) =
-        services::set_transient_status(&app_state.pool, user_id, server_id, ServerStatus::Deleting)
-            .await
-    else {
-        tracing::error!(target: "service", "Can't update server status to 'Deleting' state");
-        return;
-    };
-
+pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid, old_status: ServerStatus) {
     // Create a transaction for a chain of all sequential queries.
     let Ok(mut transaction) = app_state.pool.begin().await else {
         tracing::error!(target: "service", "Failed to begin transaction!");
+        queries::update_server_status(&app_state.pool, server_id, old_status)
+            .await
+            .ok();
         return;
     };
 
-    let result = delete_server(&app_state.proxmox, &mut transaction, user_id, server_id).await;
+    let result = delete_server(
+        &app_state.proxmox,
+        &app_state.clock,
+        &mut transaction,
+        user_id,
+        server_id,
+    )
+    .await;
     services::finalize_transaction(&result, transaction).await;
 
     // Return the old status if something went wrong.
     if result.is_err() {
-        services::set_transient_status(&app_state.pool, user_id, server_id, old_status)
+        queries::update_server_status(&app_state.pool, server_id, old_status)
             .await
             .ok();
     }
@@ -50,6 +56,7 @@ pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid) {
 /// # Arguments
 ///
 /// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `clock`: Clock waiting for the Proxmox task.
 /// * `transaction`: Active database transaction.
 /// * `user_id`: ID of the user who owns the server.
 /// * `server_id`: ID of the server to delete.
@@ -58,26 +65,48 @@ pub async fn run(app_state: AppState, user_id: Uuid, server_id: Uuid) {
 ///
 /// An empty `Result` on success.
 ///
-#[tracing::instrument(level = "trace", target = "service", skip(proxmox_client, transaction))]
+#[tracing::instrument(
+    level = "trace",
+    target = "service",
+    skip(proxmox_client, clock, transaction)
+)]
 async fn delete_server(
     proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
     transaction: &mut PgTransaction<'_>,
     user_id: Uuid,
     server_id: Uuid,
 ) -> Result<()> {
-    let vm = queries::get_server_proxmox_ref(&mut **transaction, user_id, server_id).await?;
-    tracing::debug!(target: "service", ?vm, "Found server on Proxmox");
+    // A server whose provisioning failed before cloning has no VM to delete.
+    match queries::get_server_proxmox_ref(&mut **transaction, user_id, server_id).await {
+        Ok(vm) => {
+            tracing::debug!(target: "service", ?vm, "Found server on Proxmox");
 
-    // Delete Proxmox VM and wait until process finish.
-    let upid = proxmox_client.delete(vm.clone()).await?;
-    tracing::debug!(target: "service", upid = ?upid, "Proxmox delete task started");
+            // Delete Proxmox VM and wait until process finish.
+            let upid = proxmox_client.delete(vm.clone()).await?;
+            tracing::debug!(target: "service", upid = ?upid, "Proxmox delete task started");
 
-    let task = TaskRef::new(&vm.node, &upid);
-    wait_until_finish(proxmox_client, task, 1, None).await?;
-    tracing::info!(target: "service", "Proxmox VM deletion finished successfully");
+            let task = TaskRef::new(&vm.node, &upid);
+            wait_until_finish(proxmox_client, clock, task, Polling::DELETE).await?;
+            tracing::info!(target: "service", "Proxmox VM deletion finished successfully");
+        }
+        Err(Error::NotReady(_)) => {
+            tracing::info!(target: "service", "Server was never cloned, skipping Proxmox")
+        }
+        Err(error) => return Err(error),
+    }
 
     // Then delete server record from the database.
     queries::delete_server_record(transaction, server_id).await?;
 
+    let data = serde_json::json!({ "server_id": server_id });
+    services::event::record(
+        transaction.as_mut(),
+        user_id,
+        DomainEventKind::ServerDeleted,
+        data,
+    )
+    .await?;
+
     Ok(())
 }
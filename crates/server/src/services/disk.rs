@@ -0,0 +1,395 @@
+use crate::model::queries;
+use crate::model::types::{ApiDisk, AuditAction, DiskLimits, ServerStatus};
+use crate::proxmox::types::{TaskRef, UniqueProcessId, VmConfig, VmDrive, VmRef};
+use crate::services::{self, Polling};
+use crate::state::AppState;
+use dashboard_common::prelude::{Error, Result};
+use serde_json::json;
+use sqlx::PgTransaction;
+use uuid::Uuid;
+
+/// Configurable option holding the total size of the disks of a server in GB.
+const DISK_OPTION: &str = "disk_gb";
+/// Highest index of a SCSI disk Proxmox supports.
+const MAX_SCSI_INDEX: i32 = 30;
+
+/// Returns the virtual disks of a server of the user, the CD-ROM and the
+/// cloud-init drives left out.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Disks of the server, by device.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn list_disks(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<Vec<ApiDisk>> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let drives = app_state.proxmox.vm_drives(vm).await?;
+
+    Ok(to_disks(&drives))
+}
+
+/// Attaches a new disk to a server of the user, as the next free SCSI device,
+/// so it is hot-plugged into a running server. The disk is allocated on the
+/// storage of the product, falling back to the one of the template and then
+/// to the one of the first disk.
+///
+/// The server is reserved in the `Resizing` status while Proxmox attaches the
+/// disk, and the disk is detached again if the change can't be finalized.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `size_gb`: Size of the new disk in GB.
+///
+/// # Returns
+///
+/// Disks of the server with the new one, `Error::Quota` if the product doesn't
+/// allow another disk or one this large, `Error::Conflict` if the server is
+/// neither running nor stopped.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn add_disk(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    size_gb: i32,
+) -> Result<Vec<ApiDisk>> {
+    let (status, limits) = reserve(app_state, user_id, server_id, size_gb).await?;
+    let mut attached = None;
+    let result = match attach(
+        app_state,
+        user_id,
+        server_id,
+        &limits,
+        size_gb,
+        &mut attached,
+    )
+    .await
+    {
+        Ok(total_gb) => {
+            let device = attached.as_ref().map_or("", |(_, device)| device.as_str());
+            let change = (AuditAction::DiskAdded, device, size_gb);
+            finalize(
+                app_state,
+                user_id,
+                server_id,
+                status,
+                limits.service_id,
+                total_gb,
+                change,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    };
+    if let Err(error) = result {
+        compensate(app_state, server_id, status, attached).await;
+        return Err(error);
+    }
+    tracing::info!(target: "service", %server_id, size_gb, "Disk attached");
+
+    list_disks(app_state, user_id, server_id).await
+}
+
+/// Grows a disk of a server of the user. Disks never shrink. SCSI and VirtIO
+/// disks grow while the server is running, the others only while it is
+/// stopped. The file system inside the guest must be grown separately.
+///
+/// The server is reserved in the `Resizing` status while Proxmox grows the
+/// disk.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `device`: Device of the disk, like `scsi0`.
+/// * `size_gb`: New size of the disk in GB.
+///
+/// # Returns
+///
+/// Disks of the server with the grown one, `Error::NotFound` if the server has
+/// no such disk, `Error::Validation` if the size is not larger than the
+/// current one, `Error::Quota` if the product doesn't allow a disk this large,
+/// `Error::Conflict` if the server must be stopped first.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn resize_disk(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    device: &str,
+    size_gb: i32,
+) -> Result<Vec<ApiDisk>> {
+    let (status, limits) = reserve(app_state, user_id, server_id, size_gb).await?;
+    let result = match grow(app_state, user_id, server_id, status, device, size_gb).await {
+        Ok(total_gb) => {
+            let change = (AuditAction::DiskResized, device, size_gb);
+            finalize(
+                app_state,
+                user_id,
+                server_id,
+                status,
+                limits.service_id,
+                total_gb,
+                change,
+            )
+            .await
+        }
+        Err(error) => Err(error),
+    };
+    if let Err(error) = result {
+        compensate(app_state, server_id, status, None).await;
+        return Err(error);
+    }
+    tracing::info!(target: "service", %server_id, device, size_gb, "Disk resized");
+
+    list_disks(app_state, user_id, server_id).await
+}
+
+// -----------------------------------------------------------------------------
+
+/// Reserves a server of the user for a disk change. Checks the size against
+/// the product and moves the server to the `Resizing` status, so no other
+/// operation starts until the change is finalized. The transaction ends right
+/// away, so the server isn't locked while Proxmox works.
+///
+/// # Returns
+///
+/// Status of the server before the change and the disk limits of its product.
+///
+async fn reserve(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    size_gb: i32,
+) -> Result<(ServerStatus, DiskLimits)> {
+    let mut transaction = app_state.pool.begin().await?;
+    let status = lock_stable_server(&mut transaction, user_id, server_id).await?;
+    let limits = queries::get_disk_limits(transaction.as_mut(), user_id, server_id).await?;
+    validate_size(&limits, size_gb)?;
+    queries::update_server_status(transaction.as_mut(), server_id, ServerStatus::Resizing).await?;
+    transaction.commit().await?;
+
+    Ok((status, limits))
+}
+
+/// Attaches the new disk in Proxmox, noting the device once it is requested,
+/// so it can be detached again.
+///
+/// # Returns
+///
+/// Total size of the disks of the server in GB.
+///
+async fn attach(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    limits: &DiskLimits,
+    size_gb: i32,
+    attached: &mut Option<(VmRef, String)>,
+) -> Result<i32> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let drives = app_state.proxmox.vm_drives(vm.clone()).await?;
+    let disks = to_disks(&drives);
+    if disks.len().saturating_sub(1) >= limits.max_disks as usize {
+        return Err(Error::Quota(format!(
+            "Product allows {} additional disks",
+            limits.max_disks
+        )));
+    }
+    let storage = limits
+        .storage
+        .as_deref()
+        .or(disks.first().map(|disk| disk.storage.as_str()))
+        .ok_or_else(|| Error::Validation(format!("Server {server_id} has no disk")))?;
+    let device = (1..=MAX_SCSI_INDEX)
+        .map(|index| format!("scsi{index}"))
+        .find(|device| drives.iter().all(|drive| &drive.device != device))
+        .ok_or_else(|| Error::Quota(format!("Server {server_id} has no free SCSI device")))?;
+
+    let vm_config = VmConfig::builder().disk(&device, storage, size_gb).build();
+    *attached = Some((vm.clone(), device));
+    let upid = app_state.proxmox.vm_config(vm.clone(), vm_config).await?;
+    wait_for_task(app_state, &vm, upid).await?;
+
+    Ok(disks.iter().map(|disk| disk.size_gb).sum::<i32>() + size_gb)
+}
+
+/// Grows the disk in Proxmox.
+///
+/// # Returns
+///
+/// Total size of the disks of the server in GB.
+///
+async fn grow(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    status: ServerStatus,
+    device: &str,
+    size_gb: i32,
+) -> Result<i32> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let drives = app_state.proxmox.vm_drives(vm.clone()).await?;
+    let drive = drives
+        .iter()
+        .find(|drive| drive.device == device && drive.is_disk())
+        .ok_or_else(|| Error::NotFound(format!("Disk {device} of server {server_id}")))?;
+    let current_gb = drive.size_gb.unwrap_or_default();
+    if size_gb <= current_gb {
+        return Err(Error::Validation(format!(
+            "Disk {device} can only grow beyond {current_gb} GB"
+        )));
+    }
+    if status == ServerStatus::Running && !drive.is_hotpluggable() {
+        return Err(Error::Conflict(format!(
+            "Disk {device} can only be resized while server {server_id} is stopped"
+        )));
+    }
+
+    let upid = app_state
+        .proxmox
+        .resize_disk(vm.clone(), device, size_gb)
+        .await?;
+    wait_for_task(app_state, &vm, upid).await?;
+
+    Ok(to_disks(&drives)
+        .iter()
+        .map(|disk| disk.size_gb)
+        .sum::<i32>()
+        + size_gb
+        - current_gb)
+}
+
+/// Finalizes a disk change, saving the total size of the disks, recording the
+/// change, given by its action, device and size, in the audit log and moving
+/// the server back to its status from before the change.
+///
+async fn finalize(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    status: ServerStatus,
+    service_id: Uuid,
+    total_gb: i32,
+    (action, device, size_gb): (AuditAction, &str, i32),
+) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+    queries::set_config_value(
+        transaction.as_mut(),
+        service_id,
+        DISK_OPTION,
+        &total_gb.to_string(),
+    )
+    .await?;
+    queries::create_audit_entry(
+        transaction.as_mut(),
+        user_id,
+        action,
+        Some(server_id),
+        &json!({ "device": device, "size_gb": size_gb }),
+    )
+    .await?;
+    queries::update_server_status(transaction.as_mut(), server_id, status).await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Undoes a failed disk change as far as possible. An attached disk is
+/// detached, left as an unused volume of the VM, while a grown disk can't
+/// shrink again. The server is moved back to its status from before.
+///
+async fn compensate(
+    app_state: &AppState,
+    server_id: Uuid,
+    status: ServerStatus,
+    attached: Option<(VmRef, String)>,
+) {
+    if let Some((vm, device)) = attached {
+        let vm_config = VmConfig::builder().delete(&[device.as_str()]).build();
+        if let Err(error) = app_state.proxmox.vm_config(vm, vm_config).await {
+            tracing::error!(target: "service", %server_id, device, ?error, "Failed to detach disk!");
+        }
+    }
+    if let Err(error) = queries::update_server_status(&app_state.pool, server_id, status).await {
+        tracing::error!(target: "service", %server_id, ?error, "Failed to restore server status!");
+    }
+}
+
+/// Locks a server of the user until the end of the transaction and checks
+/// that it is running or stopped.
+///
+/// # Returns
+///
+/// Current status of the server.
+///
+async fn lock_stable_server(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ServerStatus> {
+    let status = queries::lock_server_status(transaction, user_id, server_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Server {server_id}")))?;
+
+    match status {
+        ServerStatus::Running | ServerStatus::Stopped => Ok(status),
+        _ => Err(Error::Conflict(match status.operation() {
+            Some(operation) => {
+                format!("Server {server_id} is busy, operation '{operation}' is in progress")
+            }
+            None => format!("Server {server_id} must be running or stopped"),
+        })),
+    }
+}
+
+/// Checks a disk size against the limit of the product.
+///
+fn validate_size(limits: &DiskLimits, size_gb: i32) -> Result<()> {
+    if size_gb <= 0 {
+        return Err(Error::Validation("Disk size must be positive".to_owned()));
+    }
+    match limits.max_disk_gb {
+        Some(max_disk_gb) if size_gb > max_disk_gb => Err(Error::Quota(format!(
+            "Product allows disks up to {max_disk_gb} GB"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Keeps the virtual disks out of the drives of a VM.
+///
+fn to_disks(drives: &[VmDrive]) -> Vec<ApiDisk> {
+    drives
+        .iter()
+        .filter(|drive| !drive.cdrom)
+        .filter_map(|drive| {
+            Some(ApiDisk {
+                device: drive.device.clone(),
+                storage: drive.storage.clone()?,
+                size_gb: drive.size_gb?,
+            })
+        })
+        .collect()
+}
+
+/// Waits until the disk task of the VM finishes.
+///
+async fn wait_for_task(app_state: &AppState, vm: &VmRef, upid: UniqueProcessId) -> Result<()> {
+    let task = TaskRef::new(&vm.node, &upid);
+    services::wait_until_finish(&app_state.proxmox, &app_state.clock, task, Polling::CONFIG).await
+}
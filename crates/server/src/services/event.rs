@@ -0,0 +1,603 @@
+use crate::cluster::StatusEvent;
+use crate::i18n::Locale;
+use crate::model::queries;
+use crate::model::types::{
+    ApiEventReplay, DomainEvent, DomainEventKind, NotificationEvent, ServerStatus,
+};
+use crate::services::leader::Leader;
+use crate::services::{notification, webhook};
+use crate::state::AppState;
+use crate::web::types::ReplayEventsPayload;
+use chrono::NaiveDate;
+use dashboard_common::prelude::{Error, Result};
+use futures_util::{Stream, StreamExt, stream};
+use sqlx::postgres::PgListener;
+use sqlx::{Connection, Executor, PgTransaction, Postgres};
+use std::str::FromStr;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Channel the database notifies once domain events are committed.
+const CHANNEL: &str = "domain_events";
+
+/// Name of the leader role of the consumers.
+const LEADER_ROLE: &str = "events";
+
+/// Consumer of the domain events, reading the log from its own cursor. An
+/// event is published once every consumer has handled it.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Consumer {
+    /// Queues the deliveries of the webhooks subscribed to the event.
+    Webhooks,
+    /// Notifies the owner in the notification center and by email.
+    Notifications,
+    /// Publishes the status changes to every replica, for the WebSocket
+    /// subscribers.
+    Status,
+}
+
+impl Consumer {
+    /// Every consumer of the domain events.
+    pub const ALL: [Consumer; 3] = [Consumer::Webhooks, Consumer::Notifications, Consumer::Status];
+
+    /// Returns the unique name of the consumer, used as the key of its cursor.
+    ///
+    pub fn name(self) -> &'static str {
+        match self {
+            Consumer::Webhooks => "webhooks",
+            Consumer::Notifications => "notifications",
+            Consumer::Status => "status",
+        }
+    }
+
+    /// Returns the names of every consumer.
+    ///
+    fn names() -> Vec<String> {
+        Consumer::ALL
+            .map(|consumer| consumer.name().to_owned())
+            .to_vec()
+    }
+
+    /// Handles a single event. Webhook deliveries are queued within the
+    /// transaction that moves the cursor, so they are queued exactly once. The
+    /// other consumers act outside of it and may repeat an event if the
+    /// transaction fails afterwards.
+    ///
+    async fn handle(
+        self,
+        app_state: &AppState,
+        transaction: &mut PgTransaction<'_>,
+        event: &DomainEvent,
+    ) -> Result<()> {
+        match self {
+            Consumer::Webhooks => match event.kind.webhook_event() {
+                Some(webhook_event) => {
+                    webhook::publish(
+                        transaction.as_mut(),
+                        event.user_id,
+                        webhook_event,
+                        event.data.clone(),
+                    )
+                    .await
+                }
+                None => Ok(()),
+            },
+            Consumer::Notifications => notify(app_state, event).await,
+            Consumer::Status => match (&app_state.cluster, event.kind) {
+                (Some(cluster), DomainEventKind::ServerStatusChanged) => {
+                    let status = serde_json::from_value::<StatusEvent>(event.data.clone())
+                        .map_err(|error| Error::Any(format!("Invalid status event: {error}")))?;
+                    cluster.publish_status(&status).await
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl FromStr for Consumer {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Consumer::ALL
+            .into_iter()
+            .find(|consumer| consumer.name() == value)
+            .ok_or_else(|| Error::Validation(format!("Unknown event consumer {value}")))
+    }
+}
+
+/// Records a change of the domain for the consumers. Should be called within
+/// the transaction making the change, so the event is recorded exactly when
+/// the change is committed, even if the process dies right after.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user the changed resource belongs to.
+/// * `kind`: Kind of the change.
+/// * `data`: Data of the event, delivered as the `data` of the webhooks.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn record<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    kind: DomainEventKind,
+    data: serde_json::Value,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let event_id = queries::record_domain_event(executor, user_id, kind, &data).await?;
+    tracing::debug!(target: "service", event_id, %kind, "Domain event recorded");
+
+    Ok(())
+}
+
+/// Records a change of a server's status.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `status`: New status of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn record_status<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    server_id: Uuid,
+    status: ServerStatus,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let data = serde_json::json!(StatusEvent {
+        user_id,
+        server_id,
+        status,
+    });
+
+    record(executor, user_id, DomainEventKind::ServerStatusChanged, data).await
+}
+
+/// Handles the events following the cursor of the consumer, one batch at most,
+/// and moves the cursor past the handled ones. A failed event stops the batch,
+/// so it is handled again on the next run, until it has failed
+/// `events.max_attempts` times and is given up. Every event is handled in its
+/// own savepoint, so a failed one leaves nothing behind.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `consumer`: Consumer of the events.
+///
+/// # Returns
+///
+/// Number of handled events, 0 if another transaction holds the cursor, like
+/// the consumers of a replica that just lost the leadership.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn consume(app_state: &AppState, consumer: Consumer) -> Result<usize> {
+    let mut transaction = app_state.pool.begin().await?;
+    let Some(cursor) = queries::lock_event_cursor(&mut transaction, consumer.name()).await? else {
+        return Ok(0);
+    };
+
+    let settings = &app_state.config.events;
+    let events = queries::get_settled_domain_events_after(
+        transaction.as_mut(),
+        cursor,
+        settings.gap_wait_ms,
+        settings.batch_size,
+    )
+    .await?;
+    let mut last_event_id = cursor;
+    let mut count = 0;
+    for event in &events {
+        let mut savepoint = transaction.begin().await?;
+        match consumer.handle(app_state, &mut savepoint, event).await {
+            Ok(()) => savepoint.commit().await?,
+            Err(error) => {
+                savepoint.rollback().await?;
+                let attempts = queries::record_domain_event_failure(
+                    transaction.as_mut(),
+                    consumer.name(),
+                    event.id,
+                    &error.to_string(),
+                    settings.max_attempts,
+                )
+                .await?;
+                if attempts < settings.max_attempts {
+                    tracing::error!(target: "service", consumer = consumer.name(), event_id = event.id, attempts, ?error, "Failed to handle domain event!");
+                    break;
+                }
+                tracing::error!(target: "service", consumer = consumer.name(), event_id = event.id, attempts, ?error, "Domain event given up!");
+            }
+        }
+        last_event_id = event.id;
+        count += 1;
+    }
+
+    if last_event_id != cursor {
+        queries::advance_event_cursor(transaction.as_mut(), consumer.name(), last_event_id)
+            .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(count)
+}
+
+/// Streams the events of a user to a live subscriber, like an SSE connection.
+/// A subscriber resuming after an event first gets the events it missed.
+///
+/// The stream ends after a full batch of missed events, or once the subscriber
+/// falls behind the broker, so it resumes from the last event it received.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the subscribed user.
+/// * `last_event_id`: Last event the subscriber received, if resuming.
+///
+/// # Returns
+///
+/// Events of the user in the order of the log.
+///
+pub async fn subscribe(
+    app_state: &AppState,
+    user_id: Uuid,
+    last_event_id: Option<i64>,
+) -> Result<impl Stream<Item = DomainEvent> + Send + 'static> {
+    // Subscribing first, so no event falls between the missed and the live ones.
+    let receiver = app_state.broker.subscribe();
+    let batch_size = app_state.config.events.batch_size;
+    let missed = match last_event_id {
+        Some(after_id) => {
+            queries::get_domain_events_after(
+                &app_state.pool,
+                after_id,
+                None,
+                Some(user_id),
+                batch_size,
+            )
+            .await?
+        }
+        None => Vec::new(),
+    };
+    let caught_up = (missed.len() as i64) < batch_size;
+    let position = missed
+        .last()
+        .map(|event| event.id)
+        .or(last_event_id)
+        .unwrap_or_default();
+
+    let live = stream::unfold(
+        (receiver, position),
+        move |(mut receiver, position)| async move {
+            if !caught_up {
+                return None;
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.user_id == user_id && event.id > position => {
+                        let position = event.id;
+                        return Some((event, (receiver, position)));
+                    }
+                    Ok(_) => continue,
+                    // Lagged behind or closed, the subscriber resumes.
+                    Err(_) => return None,
+                }
+            }
+        },
+    );
+
+    Ok(stream::iter(missed).chain(live))
+}
+
+/// Handles the events of a range of the log again, for the consumers that
+/// lost or mishandled them, like webhooks delivered to a broken endpoint. The
+/// cursors of the consumers don't move. The events are handled in batches,
+/// and a failed event stops the replay, while the batches before it stay
+/// handled.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Range of the events and the consumers handling them again.
+///
+/// # Returns
+///
+/// Replayed range with the number of its events.
+///
+/// # Errors
+///
+/// `Error::Validation` if the range is empty or starts before the log, or a
+/// consumer is unknown.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn replay(app_state: &AppState, payload: ReplayEventsPayload) -> Result<ApiEventReplay> {
+    let ReplayEventsPayload {
+        from_id,
+        to_id,
+        consumers,
+    } = payload;
+    if from_id < 1 || from_id > to_id {
+        return Err(Error::Validation(format!(
+            "Invalid event range {from_id}..{to_id}"
+        )));
+    }
+    let consumers = match consumers.is_empty() {
+        true => Consumer::ALL.to_vec(),
+        false => consumers
+            .iter()
+            .map(|name| name.parse())
+            .collect::<Result<Vec<Consumer>>>()?,
+    };
+
+    let batch_size = app_state.config.events.batch_size;
+    let mut after_id = from_id - 1;
+    let mut count = 0;
+    loop {
+        let mut transaction = app_state.pool.begin().await?;
+        let events = queries::get_domain_events_after(
+            transaction.as_mut(),
+            after_id,
+            Some(to_id),
+            None,
+            batch_size,
+        )
+        .await?;
+        let Some(last) = events.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        for event in &events {
+            for consumer in &consumers {
+                consumer.handle(app_state, &mut transaction, event).await?;
+            }
+        }
+        transaction.commit().await?;
+        count += events.len();
+        if (events.len() as i64) < batch_size {
+            break;
+        }
+    }
+    tracing::info!(target: "service", from_id, to_id, count, "Domain events replayed");
+
+    Ok(ApiEventReplay {
+        from_id,
+        to_id,
+        replayed: count,
+    })
+}
+
+/// Public entry point for the domain event background task, the relay of the
+/// log.
+///
+/// Runs every consumer until it has caught up with the log and marks the
+/// events all of them handled as published, then passes the new events to the
+/// live subscribers of this replica. Then waits until new events are
+/// committed, never returning. The log is also checked every configured
+/// interval, in case the database connection listening for the new events is
+/// lost.
+///
+/// Only the elected leader among the replicas runs the consumers, the others
+/// stand by to take over. The cursor locks are not meant to spread the work,
+/// they only keep a replay or a leader change from handling an event twice.
+/// Every replica passes the events to its own live subscribers.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+///
+pub async fn watch(app_state: AppState) {
+    let poll = Duration::from_millis(app_state.config.events.poll_ms);
+    let mut listener = match listen(&app_state).await {
+        Ok(listener) => Some(listener),
+        Err(error) => {
+            tracing::warn!(target: "service", ?error, "Domain events are polled only");
+            None
+        }
+    };
+    let mut leader = Leader::new(LEADER_ROLE);
+    // Live subscribers only get the events committed after the start.
+    let mut position = None;
+
+    loop {
+        match leader.elect(&app_state.pool).await {
+            Ok(true) => relay(&app_state).await,
+            Ok(false) => {}
+            Err(error) => {
+                tracing::error!(target: "service", ?error, "Failed to elect leader!")
+            }
+        }
+
+        let forwarded = match position {
+            Some(after_id) => forward(&app_state, after_id).await,
+            None => queries::get_last_domain_event_id(&app_state.pool).await,
+        };
+        match forwarded {
+            Ok(last_id) => position = Some(last_id),
+            Err(error) => {
+                tracing::error!(target: "service", ?error, "Failed to forward domain events!")
+            }
+        }
+
+        match &mut listener {
+            Some(listener) => {
+                // Timing out is the periodic check, a failed wait is retried
+                // by the next one.
+                let _ = tokio::time::timeout(poll, listener.recv()).await;
+            }
+            None => tokio::time::sleep(poll).await,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Runs every consumer until it has caught up with the log, and marks the
+/// events all of them handled as published.
+///
+async fn relay(app_state: &AppState) {
+    for consumer in Consumer::ALL {
+        loop {
+            match consume(app_state, consumer).await {
+                Ok(count) if count as i64 >= app_state.config.events.batch_size => continue,
+                Ok(_) => break,
+                Err(error) => {
+                    tracing::error!(target: "service", consumer = consumer.name(), ?error, "Failed to consume domain events!");
+                    break;
+                }
+            }
+        }
+    }
+
+    match queries::mark_domain_events_published(&app_state.pool, &Consumer::names()).await {
+        Ok(0) => {}
+        Ok(count) => tracing::debug!(target: "service", count, "Domain events published"),
+        Err(error) => {
+            tracing::error!(target: "service", ?error, "Failed to mark domain events published!")
+        }
+    }
+}
+
+async fn listen(app_state: &AppState) -> Result<PgListener> {
+    let mut listener = PgListener::connect_with(&app_state.pool).await?;
+    listener.listen(CHANNEL).await?;
+
+    Ok(listener)
+}
+
+/// Passes the events following the position to the live subscribers of this
+/// replica, returning the new position. Like the consumers, stops at a gap that
+/// may still be filled.
+///
+async fn forward(app_state: &AppState, mut after_id: i64) -> Result<i64> {
+    let settings = &app_state.config.events;
+    loop {
+        let events = queries::get_settled_domain_events_after(
+            &app_state.pool,
+            after_id,
+            settings.gap_wait_ms,
+            settings.batch_size,
+        )
+        .await?;
+        let full = events.len() as i64 >= settings.batch_size;
+        for event in events {
+            after_id = event.id;
+            app_state.broker.publish(event);
+        }
+        if !full {
+            return Ok(after_id);
+        }
+    }
+}
+
+/// Notifies the owner about the events the users are notified about, like a
+/// ready server.
+///
+async fn notify(app_state: &AppState, event: &DomainEvent) -> Result<()> {
+    let text = |key: &str| event.data[key].as_str().unwrap_or_default().to_owned();
+    match event.kind {
+        DomainEventKind::ServerCreated => {
+            let host_name = text("host_name");
+            let render = |locale: Locale| {
+                (
+                    locale.translate("notification.server_ready.title", &[]),
+                    locale.translate(
+                        "notification.server_ready.message",
+                        &[("host_name", host_name.as_str())],
+                    ),
+                )
+            };
+            notification::notify(
+                app_state,
+                &[event.user_id],
+                NotificationEvent::ServerReady,
+                render,
+            )
+            .await
+        }
+        DomainEventKind::TrafficQuotaExceeded => {
+            let host_name = text("host_name");
+            let quota_gb = event.data["quota_gb"].to_string();
+            let month = text("month")
+                .parse::<NaiveDate>()
+                .map(|month| month.format("%Y-%m").to_string())
+                .unwrap_or_default();
+            let render = |locale: Locale| {
+                (
+                    locale.translate("notification.traffic_quota.title", &[]),
+                    locale.translate(
+                        "notification.traffic_quota.message",
+                        &[
+                            ("host_name", host_name.as_str()),
+                            ("quota_gb", quota_gb.as_str()),
+                            ("month", month.as_str()),
+                        ],
+                    ),
+                )
+            };
+            notification::notify(
+                app_state,
+                &[event.user_id],
+                NotificationEvent::TrafficQuota,
+                render,
+            )
+            .await
+        }
+        _ => Ok(()),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::types::WebhookEvent;
+
+    #[test]
+    fn consumers_should_have_unique_parsable_names() {
+        // Arrange
+        let mut names = Consumer::ALL.map(Consumer::name).to_vec();
+
+        // Act
+        names.sort_unstable();
+        names.dedup();
+
+        // Assert
+        assert_eq!(names.len(), Consumer::ALL.len());
+        for consumer in Consumer::ALL {
+            assert_eq!(consumer.name().parse::<Consumer>().unwrap(), consumer);
+        }
+        assert!("email".parse::<Consumer>().is_err());
+    }
+
+    #[test]
+    fn domain_event_kind_should_round_trip_and_map_to_webhooks() {
+        // Act & Assert
+        for kind in [
+            DomainEventKind::ServerCreated,
+            DomainEventKind::ServerStatusChanged,
+            DomainEventKind::TrafficQuotaExceeded,
+        ] {
+            assert_eq!(kind.to_string().parse::<DomainEventKind>().unwrap(), kind);
+        }
+        assert_eq!(
+            DomainEventKind::InvoicePaid.webhook_event(),
+            Some(WebhookEvent::InvoicePaid)
+        );
+        assert_eq!(DomainEventKind::ServerStatusChanged.webhook_event(), None);
+    }
+}
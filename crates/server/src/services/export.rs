@@ -0,0 +1,35 @@
+use crate::model::queries;
+use crate::model::types::ApiUserExport;
+use crate::state::AppState;
+use dashboard_common::prelude::Result;
+use uuid::Uuid;
+
+/// Assembles all personal data of a user into a single bundle: the profile,
+/// the servers with their services, the invoices, the credits, the webhooks
+/// and the audit log entries of the user's actions.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Bundle with the personal data of the user.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn export_user(app_state: &AppState, user_id: Uuid) -> Result<ApiUserExport> {
+    let pool = &app_state.pool;
+    let export = ApiUserExport {
+        exported_at: app_state.clock.now(),
+        profile: queries::get_user_by_id(pool, user_id).await?,
+        servers: queries::get_servers_for_user(pool, user_id).await?,
+        invoices: queries::get_invoices_for_user(pool, user_id).await?,
+        credits: queries::get_credits_for_user(pool, user_id).await?,
+        webhooks: queries::get_webhooks(pool, user_id).await?,
+        audit_log: queries::get_audit_log_for_user(pool, user_id).await?,
+    };
+    tracing::info!(target: "service", servers = export.servers.len(), invoices = export.invoices.len(), "User data exported");
+
+    Ok(export)
+}
@@ -0,0 +1,286 @@
+use crate::model::queries;
+use crate::model::types::{ApiFirewall, ApiFirewallRule, FirewallSettings, Ipv4Cidr};
+use crate::proxmox::types::{FirewallOptions, FirewallRule, TaskRef, VmConfig};
+use crate::services::{self, Polling};
+use crate::state::AppState;
+use crate::web::types::FirewallRulePayload;
+use dashboard_common::prelude::{Error, Result};
+use std::net::Ipv4Addr;
+use uuid::Uuid;
+
+/// Returns the firewall settings of a server together with its rules.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Firewall settings and rules of the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_firewall(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ApiFirewall> {
+    let settings = queries::get_firewall_settings(&app_state.pool, user_id, server_id).await?;
+    let rules = queries::get_firewall_rules(&app_state.pool, server_id).await?;
+
+    Ok(ApiFirewall {
+        enabled: settings.enabled,
+        policy_in: settings.policy_in,
+        rules,
+    })
+}
+
+/// Enables or disables the firewall of a server and sets its policy for
+/// incoming traffic.
+///
+/// Proxmox filters only the traffic of network devices with the firewall
+/// flag, so the flag is added to the primary device when the firewall is
+/// enabled for the first time. The server is reserved in the `Configuring`
+/// status meanwhile.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `settings`: New firewall settings.
+///
+/// # Returns
+///
+/// Firewall settings and rules of the server, `Error::Conflict` if an
+/// operation is in progress on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_settings(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    settings: FirewallSettings,
+) -> Result<ApiFirewall> {
+    let status = services::reserve_server(&app_state.pool, user_id, server_id).await?;
+    let result = apply_settings(app_state, user_id, server_id, &settings).await;
+    services::release_server(&app_state.pool, server_id, status).await;
+    result?;
+    tracing::info!(target: "service", %server_id, enabled = settings.enabled, policy_in = %settings.policy_in, "Firewall settings saved");
+
+    get_firewall(app_state, user_id, server_id).await
+}
+
+/// Adds a firewall rule to a server.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `payload`: Direction, action, protocol, port and optional source of the
+///   rule.
+///
+/// # Returns
+///
+/// Created rule, `Error::Conflict` if an operation is in progress on the
+/// server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn add_rule(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    mut payload: FirewallRulePayload,
+) -> Result<ApiFirewallRule> {
+    validate_rule(&mut payload)?;
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+
+    let mut transaction = app_state.pool.begin().await?;
+    services::ensure_idle(&mut transaction, user_id, server_id).await?;
+    let rule = queries::create_firewall_rule(transaction.as_mut(), server_id, &payload).await?;
+    app_state
+        .proxmox
+        .create_firewall_rule(vm, FirewallRule::from(&rule))
+        .await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %server_id, rule_id = %rule.id, "Firewall rule added");
+
+    Ok(rule)
+}
+
+/// Deletes a firewall rule of a server.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `rule_id`: ID of the rule.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success, `Error::Conflict` if an operation is in progress
+/// on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_rule(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    rule_id: Uuid,
+) -> Result<()> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+
+    let mut transaction = app_state.pool.begin().await?;
+    services::ensure_idle(&mut transaction, user_id, server_id).await?;
+    if !queries::delete_firewall_rule(transaction.as_mut(), server_id, rule_id).await? {
+        return Err(Error::Validation(format!(
+            "Firewall rule {rule_id} not found"
+        )));
+    }
+
+    // Proxmox addresses rules by position, the rule ID is kept in the comment.
+    let comment = rule_id.to_string();
+    let position = app_state
+        .proxmox
+        .firewall_rules(vm.clone())
+        .await?
+        .into_iter()
+        .find(|info| info.comment.as_deref() == Some(comment.as_str()))
+        .map(|info| info.pos);
+    match position {
+        Some(pos) => app_state.proxmox.delete_firewall_rule(vm, pos).await?,
+        None => {
+            tracing::warn!(target: "service", %server_id, %rule_id, "Firewall rule not found on Proxmox")
+        }
+    }
+
+    transaction.commit().await?;
+    tracing::info!(target: "service", %server_id, %rule_id, "Firewall rule deleted");
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+
+/// Enables or disables the firewall of the reserved server.
+///
+async fn apply_settings(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    settings: &FirewallSettings,
+) -> Result<()> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+
+    if settings.enabled
+        && let Some(net0) = app_state.proxmox.vm_current_config(vm.clone()).await?.net0
+    {
+        let with_firewall = VmConfig::net_with_firewall(&net0, true);
+        if with_firewall != net0 {
+            let vm_config = VmConfig::builder().net(0, with_firewall).build();
+            let upid = app_state.proxmox.vm_config(vm.clone(), vm_config).await?;
+            let task = TaskRef::new(&vm.node, &upid);
+            services::wait_until_finish(
+                &app_state.proxmox,
+                &app_state.clock,
+                task,
+                Polling::CONFIG,
+            )
+            .await?;
+            tracing::info!(target: "service", %server_id, "Firewall flag added to network device");
+        }
+    }
+
+    app_state
+        .proxmox
+        .firewall_options(vm, FirewallOptions::from(settings))
+        .await?;
+    queries::set_firewall_settings(&app_state.pool, server_id, settings).await
+}
+
+/// Checks the port and the source of a rule, normalizing the source.
+///
+fn validate_rule(payload: &mut FirewallRulePayload) -> Result<()> {
+    if !(1..=65535).contains(&payload.port) {
+        return Err(Error::Validation(format!(
+            "Invalid port {}, must be between 1 and 65535",
+            payload.port
+        )));
+    }
+
+    if let Some(source) = payload.source.take() {
+        let source = source.trim();
+        let valid = source.parse::<Ipv4Addr>().is_ok() || source.parse::<Ipv4Cidr>().is_ok();
+        if !valid {
+            return Err(Error::Validation(format!(
+                "Invalid source {source}, must be an IPv4 address or CIDR block"
+            )));
+        }
+        payload.source = Some(source.to_owned());
+    }
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::types::{FirewallAction, FirewallDirection, FirewallProtocol};
+
+    fn rule(port: i32, source: Option<&str>) -> FirewallRulePayload {
+        FirewallRulePayload {
+            direction: FirewallDirection::In,
+            action: FirewallAction::Accept,
+            protocol: FirewallProtocol::Tcp,
+            port,
+            source: source.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn validate_rule_should_accept_address_and_cidr_sources() {
+        // Arrange
+        let mut address = rule(22, Some(" 10.0.0.5 "));
+        let mut cidr = rule(443, Some("10.0.0.0/24"));
+        let mut any = rule(65535, None);
+
+        // Act
+        let results = [
+            validate_rule(&mut address),
+            validate_rule(&mut cidr),
+            validate_rule(&mut any),
+        ];
+
+        // Assert
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(address.source.as_deref(), Some("10.0.0.5"));
+        assert_eq!(cidr.source.as_deref(), Some("10.0.0.0/24"));
+        assert_eq!(any.source, None);
+    }
+
+    #[test]
+    fn validate_rule_should_reject_invalid_port_and_source() {
+        // Assert
+        assert!(matches!(
+            validate_rule(&mut rule(0, None)),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            validate_rule(&mut rule(65536, None)),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            validate_rule(&mut rule(22, Some("example.com"))),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            validate_rule(&mut rule(22, Some("10.0.0.1/24"))),
+            Err(Error::Validation(_))
+        ));
+    }
+}
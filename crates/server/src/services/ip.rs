@@ -0,0 +1,216 @@
+use crate::clock::Clock;
+use crate::model::queries;
+use crate::model::types::{ApiServer, IpConfig, NewInvoice};
+use crate::proxmox::Proxmox;
+use crate::proxmox::types::{TaskRef, VmConfig, VmRef};
+use crate::services::{self, Polling};
+use crate::state::AppState;
+use dashboard_common::prelude::{Error, Result};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Allocates an additional IP address from the datacenter pool of a server and
+/// attaches it to the VM as a new network device on the primary bridge.
+///
+/// The address is saved before Proxmox attaches it and invoiced, when
+/// additional IPs have a price, once it is attached. A failed attachment or
+/// invoice is undone: the device is removed and the address goes back to the
+/// pool. The server is reserved in the `Configuring` status meanwhile.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Server with its updated list of additional addresses, `Error::Conflict` if
+/// an operation is in progress on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn add_ip(app_state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<ApiServer> {
+    let status = services::reserve_server(&app_state.pool, user_id, server_id).await?;
+    let result = attach_ip(app_state, user_id, server_id).await;
+    services::release_server(&app_state.pool, server_id, status).await;
+    result?;
+
+    queries::get_server_by_id(&app_state.pool, user_id, server_id).await
+}
+
+/// Detaches an additional IP address from a server and returns it to the
+/// datacenter pool. The server is reserved in the `Configuring` status
+/// meanwhile.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `ip_address`: Additional address to release.
+///
+/// # Returns
+///
+/// Server with its updated list of additional addresses, `Error::Conflict` if
+/// an operation is in progress on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn remove_ip(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    ip_address: &str,
+) -> Result<ApiServer> {
+    let status = services::reserve_server(&app_state.pool, user_id, server_id).await?;
+    let result = detach_ip(app_state, user_id, server_id, ip_address).await;
+    services::release_server(&app_state.pool, server_id, status).await;
+    result?;
+
+    queries::get_server_by_id(&app_state.pool, user_id, server_id).await
+}
+
+// -----------------------------------------------------------------------------
+
+/// Allocates an additional IP address to the reserved server.
+///
+async fn attach_ip(app_state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+
+    // Check quotas, the user stays locked until the address is saved, not
+    // while Proxmox attaches it.
+    queries::lock_user(&mut transaction, user_id).await?;
+    let server = queries::get_server_by_id(transaction.as_mut(), user_id, server_id).await?;
+    services::quota::ensure_ip_quota(
+        transaction.as_mut(),
+        &app_state.config.quota,
+        user_id,
+        server_id,
+    )
+    .await?;
+    let vm = queries::get_server_proxmox_ref(transaction.as_mut(), user_id, server_id).await?;
+
+    let (nic_index, ip_config) =
+        queries::reserve_additional_ip(&mut transaction, server_id).await?;
+    transaction.commit().await?;
+    let ip_address = ip_config.ip_address.clone();
+    tracing::info!(target: "service", %ip_address, nic_index, "Additional IP reserved");
+
+    let result = async {
+        add_device(app_state, vm.clone(), nic_index, ip_config).await?;
+        tracing::info!(target: "service", %ip_address, nic_index, "Network device added");
+
+        let amount_cents = app_state.config.payments.additional_ip_cents;
+        if amount_cents > 0 {
+            let invoice = NewInvoice {
+                user_id,
+                service_id: Some(server.service_id),
+                description: format!("Additional IP {ip_address}"),
+                amount_cents,
+                currency: app_state.config.payments.currency.clone(),
+            };
+            let invoice_id = queries::create_invoice(&app_state.pool, invoice).await?;
+            tracing::info!(target: "service", %invoice_id, "Additional IP invoiced");
+        }
+
+        Ok::<_, Error>(())
+    }
+    .await;
+
+    if result.is_err() {
+        undo_attach(app_state, vm, server_id, &ip_address, nic_index).await;
+    }
+    result
+}
+
+/// Attaches a new network device like the primary one, carrying the address.
+///
+async fn add_device(
+    app_state: &AppState,
+    vm: VmRef,
+    nic_index: i32,
+    ip_config: IpConfig,
+) -> Result<()> {
+    let net0 = app_state
+        .proxmox
+        .vm_current_config(vm.clone())
+        .await?
+        .net0
+        .ok_or_else(|| Error::Validation(format!("VM {} has no network device", vm.id)))?;
+    let vm_config = VmConfig::add_device(
+        nic_index,
+        VmConfig::net_like(&net0),
+        ip_config.form_additional()?,
+    );
+
+    apply_config(&app_state.proxmox, &app_state.clock, vm, vm_config).await
+}
+
+/// Undoes a failed attachment: removes the network device, in case Proxmox
+/// added it, and returns the address to the pool. A failure is only logged.
+///
+async fn undo_attach(
+    app_state: &AppState,
+    vm: VmRef,
+    server_id: Uuid,
+    ip_address: &str,
+    nic_index: i32,
+) {
+    let vm_config = VmConfig::remove_device(nic_index);
+    if let Err(error) = apply_config(&app_state.proxmox, &app_state.clock, vm, vm_config).await {
+        tracing::warn!(target: "service", %ip_address, nic_index, ?error, "Can't remove network device");
+    }
+
+    let result = async {
+        let mut transaction = app_state.pool.begin().await?;
+        queries::release_additional_ip(&mut transaction, server_id, ip_address).await?;
+        transaction.commit().await?;
+        Ok::<_, Error>(())
+    }
+    .await;
+    match result {
+        Ok(()) => tracing::info!(target: "service", %ip_address, "Additional IP released"),
+        Err(error) => {
+            tracing::error!(target: "service", %ip_address, ?error, "Failed to release additional IP!")
+        }
+    }
+}
+
+/// Releases an additional IP address of the reserved server.
+///
+async fn detach_ip(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    ip_address: &str,
+) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+
+    let vm = queries::get_server_proxmox_ref(transaction.as_mut(), user_id, server_id).await?;
+    let nic_index = queries::release_additional_ip(&mut transaction, server_id, ip_address)
+        .await?
+        .ok_or_else(|| {
+            Error::Validation(format!(
+                "{ip_address} is not an additional IP of server {server_id}"
+            ))
+        })?;
+
+    let vm_config = VmConfig::remove_device(nic_index);
+    apply_config(&app_state.proxmox, &app_state.clock, vm, vm_config).await?;
+    tracing::info!(target: "service", %ip_address, nic_index, "Network device removed");
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Applies the configuration to the VM and waits until the task finishes.
+///
+async fn apply_config(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    vm: VmRef,
+    vm_config: VmConfig,
+) -> Result<()> {
+    let upid = proxmox_client.vm_config(vm.clone(), vm_config).await?;
+    let task = TaskRef::new(&vm.node, &upid);
+    services::wait_until_finish(proxmox_client, clock, task, Polling::CONFIG).await
+}
@@ -0,0 +1,255 @@
+use crate::model::queries;
+use crate::model::types::ApiServerIso;
+use crate::proxmox::types::{TaskRef, UniqueProcessId, VmRef};
+use crate::services::{self, Polling};
+use crate::state::AppState;
+use dashboard_common::prelude::{Error, Result};
+use uuid::Uuid;
+
+/// Devices the CD-ROM drive of an ISO may take, in order of preference. The
+/// cloud-init drive usually sits on `ide2`, so it is skipped as taken.
+const ISO_DEVICES: [&str; 10] = [
+    "ide0", "ide1", "ide2", "ide3", "sata0", "sata1", "sata2", "sata3", "sata4", "sata5",
+];
+
+/// Inserts a registered ISO into the CD-ROM drive of a server of the user,
+/// replacing the inserted one. The first free IDE or SATA device takes the
+/// drive if the server has none yet. A server booting from its ISO keeps
+/// doing so.
+///
+/// The server is reserved in the `Configuring` status while Proxmox changes
+/// the drive.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `iso_id`: ID of the ISO.
+///
+/// # Returns
+///
+/// CD-ROM drive of the server, `Error::NotFound` if the ISO isn't registered,
+/// `Error::Validation` if the ISO is on a storage local to another node,
+/// `Error::Conflict` if an operation is in progress on the server or it has no
+/// free device for the drive.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn attach_iso(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    iso_id: Uuid,
+) -> Result<ApiServerIso> {
+    let status = services::reserve_server(&app_state.pool, user_id, server_id).await?;
+    let result = insert(app_state, user_id, server_id, iso_id).await;
+    services::release_server(&app_state.pool, server_id, status).await;
+    let drive = result?;
+    tracing::info!(target: "service", %server_id, %iso_id, "ISO inserted");
+
+    Ok(drive)
+}
+
+/// Ejects the ISO from the CD-ROM drive of a server of the user, switching
+/// the server back to boot from its disks.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success, `Error::Conflict` if an operation is in
+/// progress on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn detach_iso(app_state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    let status = services::reserve_server(&app_state.pool, user_id, server_id).await?;
+    let result = eject(app_state, user_id, server_id).await;
+    services::release_server(&app_state.pool, server_id, status).await;
+    result?;
+    tracing::info!(target: "service", %server_id, "ISO ejected");
+
+    Ok(())
+}
+
+/// Switches a server of the user to boot from its ISO before its disks, or
+/// from its disks only. The change applies on the next boot.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `boot_from_iso`: Whether the server boots from its ISO.
+///
+/// # Returns
+///
+/// CD-ROM drive of the server, `Error::Validation` if booting from an ISO is
+/// asked while none is inserted, `Error::Conflict` if an operation is in
+/// progress on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_boot_from_iso(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    boot_from_iso: bool,
+) -> Result<ApiServerIso> {
+    let status = services::reserve_server(&app_state.pool, user_id, server_id).await?;
+    let result = set_boot(app_state, user_id, server_id, boot_from_iso).await;
+    services::release_server(&app_state.pool, server_id, status).await;
+    let drive = result?;
+    tracing::info!(target: "service", %server_id, boot_from_iso, "Boot order set");
+
+    Ok(drive)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Inserts the ISO into the drive of the reserved server.
+///
+async fn insert(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    iso_id: Uuid,
+) -> Result<ApiServerIso> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let iso = queries::get_iso(&app_state.pool, iso_id).await?;
+    if let Some(node) = iso.node_name.as_deref().filter(|node| *node != vm.node) {
+        return Err(Error::Validation(format!(
+            "ISO {} is only available on node {node}",
+            iso.name
+        )));
+    }
+    let current = queries::get_server_iso(&app_state.pool, user_id, server_id).await?;
+    let device = match current.device {
+        Some(device) => device,
+        None => free_device(app_state, &vm, server_id).await?,
+    };
+
+    let upid = app_state
+        .proxmox
+        .set_cdrom(vm.clone(), &device, Some(&iso.volid))
+        .await?;
+    wait_for_task(app_state, &vm, upid).await?;
+    queries::set_server_iso(
+        &app_state.pool,
+        server_id,
+        Some(iso_id),
+        Some(&device),
+        current.boot_from_iso,
+    )
+    .await?;
+
+    Ok(ApiServerIso {
+        iso: Some(iso),
+        device: Some(device),
+        boot_from_iso: current.boot_from_iso,
+    })
+}
+
+/// Ejects the ISO from the drive of the reserved server.
+///
+async fn eject(app_state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let current = queries::get_server_iso(&app_state.pool, user_id, server_id).await?;
+
+    if let Some(device) = &current.device {
+        if current.boot_from_iso {
+            set_boot_order(app_state, &vm, None).await?;
+        }
+        let upid = app_state
+            .proxmox
+            .set_cdrom(vm.clone(), device, None)
+            .await?;
+        wait_for_task(app_state, &vm, upid).await?;
+    }
+
+    queries::set_server_iso(&app_state.pool, server_id, None, None, false).await
+}
+
+/// Sets the boot order of the reserved server.
+///
+async fn set_boot(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    boot_from_iso: bool,
+) -> Result<ApiServerIso> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let current = queries::get_server_iso(&app_state.pool, user_id, server_id).await?;
+    let iso_id = current.iso.as_ref().map(|iso| iso.id);
+    if boot_from_iso && current.device.is_none() {
+        return Err(Error::Validation(format!(
+            "Server {server_id} has no ISO to boot from"
+        )));
+    }
+
+    let boot_device = current.device.as_deref().filter(|_| boot_from_iso);
+    set_boot_order(app_state, &vm, boot_device).await?;
+    queries::set_server_iso(
+        &app_state.pool,
+        server_id,
+        iso_id,
+        current.device.as_deref(),
+        boot_from_iso,
+    )
+    .await?;
+
+    Ok(ApiServerIso {
+        boot_from_iso,
+        ..current
+    })
+}
+
+/// Finds the first device of `ISO_DEVICES` the VM doesn't use, or uses for an
+/// empty CD-ROM drive.
+///
+async fn free_device(app_state: &AppState, vm: &VmRef, server_id: Uuid) -> Result<String> {
+    let drives = app_state.proxmox.vm_drives(vm.clone()).await?;
+
+    ISO_DEVICES
+        .iter()
+        .find(|device| {
+            drives
+                .iter()
+                .filter(|drive| drive.device == **device)
+                .all(|drive| drive.cdrom && drive.storage.is_none())
+        })
+        .map(|device| (*device).to_owned())
+        .ok_or_else(|| Error::Conflict(format!("Server {server_id} has no free CD-ROM device")))
+}
+
+/// Sets the boot order of the VM: the CD-ROM drive first if given, then its
+/// disks in the order of their devices.
+///
+async fn set_boot_order(app_state: &AppState, vm: &VmRef, iso_device: Option<&str>) -> Result<()> {
+    let drives = app_state.proxmox.vm_drives(vm.clone()).await?;
+    let devices = iso_device
+        .map(str::to_owned)
+        .into_iter()
+        .chain(
+            drives
+                .into_iter()
+                .filter(|drive| drive.is_disk())
+                .map(|drive| drive.device),
+        )
+        .collect::<Vec<_>>();
+
+    let upid = app_state
+        .proxmox
+        .set_boot_order(vm.clone(), &devices)
+        .await?;
+    wait_for_task(app_state, vm, upid).await
+}
+
+/// Waits until the configuration task of the VM finishes.
+///
+async fn wait_for_task(app_state: &AppState, vm: &VmRef, upid: UniqueProcessId) -> Result<()> {
+    let task = TaskRef::new(&vm.node, &upid);
+    services::wait_until_finish(&app_state.proxmox, &app_state.clock, task, Polling::CONFIG).await
+}
@@ -0,0 +1,132 @@
+use crate::model::queries;
+use dashboard_common::prelude::Result;
+use sqlx::{Connection, PgConnection, PgPool};
+
+/// Leadership of a role shared by all replicas, such as running the periodic
+/// jobs or the domain event consumers, backed by a session-level Postgres
+/// advisory lock.
+///
+/// The leader keeps the lock on a connection detached from the pool. Postgres
+/// releases the lock as soon as that connection is closed, so when the leader
+/// stops or loses the database, another replica takes over on its next
+/// election.
+///
+pub struct Leader {
+    role: &'static str,
+    key: i64,
+    connection: Option<PgConnection>,
+}
+
+impl Leader {
+    /// Creates a follower for the role. Every replica must use the same role
+    /// name for the same work.
+    ///
+    pub fn new(role: &'static str) -> Self {
+        Self {
+            role,
+            key: lock_key(role),
+            connection: None,
+        }
+    }
+
+    /// Returns whether this replica was the leader at the last election.
+    ///
+    pub fn is_leader(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// Keeps the leadership if this replica still holds the lock, or tries to
+    /// take it over otherwise. Should be called before every unit of work.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool`: Database connection pool.
+    ///
+    /// # Returns
+    ///
+    /// `true` if this replica is the leader.
+    ///
+    pub async fn elect(&mut self, pool: &PgPool) -> Result<bool> {
+        if let Some(connection) = self.connection.as_mut() {
+            if connection.ping().await.is_ok() {
+                return Ok(true);
+            }
+            self.connection = None;
+            tracing::warn!(target: "service", role = self.role, "Leadership lost");
+        }
+
+        let mut connection = pool.acquire().await?;
+        if !queries::try_advisory_lock(&mut *connection, self.key).await? {
+            return Ok(false);
+        }
+
+        self.connection = Some(connection.detach());
+        tracing::info!(target: "service", role = self.role, "Leadership acquired");
+        Ok(true)
+    }
+
+    /// Gives up the leadership, letting another replica take over.
+    ///
+    /// # Returns
+    ///
+    /// Empty `Ok(())` on success.
+    ///
+    pub async fn resign(&mut self) -> Result<()> {
+        if let Some(mut connection) = self.connection.take() {
+            queries::advisory_unlock(&mut connection, self.key).await?;
+            connection.close().await?;
+            tracing::info!(target: "service", role = self.role, "Leadership resigned");
+        }
+
+        Ok(())
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Derives the advisory lock key from the role name with 64-bit FNV-1a, which
+/// is stable across builds, unlike the standard library hasher.
+///
+fn lock_key(role: &str) -> i64 {
+    let hash = role.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+
+    hash as i64
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_key_should_be_stable() {
+        // Assert
+        assert_eq!(lock_key(""), 0xcbf2_9ce4_8422_2325_u64 as i64);
+        assert_eq!(lock_key("a"), 0xaf63_dc4c_8601_ec8c_u64 as i64);
+        assert_ne!(lock_key("scheduler"), lock_key("schedules"));
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn only_one_replica_should_lead(pool: PgPool) {
+        // Arrange
+        let mut first = Leader::new("test");
+        let mut second = Leader::new("test");
+
+        // Act
+        let first_elected = first.elect(&pool).await.unwrap();
+        let second_elected = second.elect(&pool).await.unwrap();
+        let first_reelected = first.elect(&pool).await.unwrap();
+        first.resign().await.unwrap();
+        let second_took_over = second.elect(&pool).await.unwrap();
+
+        // Assert
+        assert!(first_elected);
+        assert!(!second_elected);
+        assert!(first_reelected);
+        assert!(!first.is_leader());
+        assert!(second_took_over);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::clock::Clock;
+use crate::model::types::ProvisioningTarget;
+use crate::proxmox::Proxmox;
+use crate::proxmox::types::{GuestExecStatus, Status, TaskRef, VmRef};
+use crate::services::{self, Polling};
+use crate::state::AppState;
+use dashboard_common::prelude::{Error, Result};
+use std::sync::Arc;
+
+/// Shell the post-install scripts are piped into.
+const SCRIPT_SHELL: &str = "/bin/sh";
+/// Lock in the guest held while a post-install script runs.
+const SCRIPT_LOCK: &str = "/var/lock/dashboard-app.lock";
+/// Marker left in the guest once a post-install script succeeded.
+const SCRIPT_MARKER: &str = "/var/lib/dashboard-app.installed";
+
+/// Checks that an application is offered in the marketplace.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `app`: Name of the application.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if it is offered, `Error::Validation` otherwise.
+///
+pub async fn ensure_app_offered(app_state: &AppState, app: &str) -> Result<()> {
+    let apps = app_state.catalog.apps(app_state.reader()).await?;
+    if !apps.iter().any(|offered| offered.name == app) {
+        return Err(Error::Validation(format!(
+            "Application {app} is not offered"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Runs the post-install script of the application template of a new server
+/// through the QEMU guest agent. The server is started first, if it isn't
+/// running yet, and the script runs once its agent responds. A retried step
+/// waits for a script still running from an earlier attempt and doesn't run a
+/// script that already succeeded again.
+///
+/// # Arguments
+///
+/// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `clock`: Clock waiting for the server and the script.
+/// * `target`: Provisioning details of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the script succeeded, or right away if the template has
+/// no script. `Error::Any` with the output of the script if it failed or was
+/// killed.
+///
+pub async fn install_app(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    target: &ProvisioningTarget,
+) -> Result<()> {
+    let Some(script) = &target.post_install_script else {
+        return Ok(());
+    };
+    let server_id = target.server_id;
+    let (Some(node), Some(vm_id)) = (&target.node_name, target.vm_id) else {
+        return Err(Error::NotReady(format!("Server: {server_id}")));
+    };
+    let vm = VmRef::new(node, vm_id);
+
+    if proxmox_client.vm_status(vm.clone()).await? != Status::Running {
+        let upid = proxmox_client.start(vm.clone()).await?;
+        let task = TaskRef::new(node, &upid);
+        services::wait_until_finish(proxmox_client, clock, task, Polling::POWER).await?;
+        tracing::info!(target: "service", %server_id, "Server started for the application");
+    }
+
+    wait_for_agent(proxmox_client, clock, vm.clone()).await?;
+    let pid = proxmox_client
+        .agent_exec(vm.clone(), SCRIPT_SHELL, &guarded_script(script))
+        .await?;
+    tracing::info!(target: "service", %server_id, pid, "Post-install script started");
+
+    let start = clock.now();
+    let mut interval = Polling::SCRIPT.initial;
+    loop {
+        let status = proxmox_client.agent_exec_status(vm.clone(), pid).await?;
+        if status.exited {
+            script_result(status)?;
+            tracing::info!(target: "service", %server_id, "Post-install script finished");
+            return Ok(());
+        }
+
+        let elapsed = clock.elapsed(start);
+        if elapsed > Polling::SCRIPT.timeout {
+            return Err(Error::Timeout(elapsed.as_secs_f32()));
+        }
+        clock.sleep(Polling::jittered(interval)).await;
+        interval = Polling::SCRIPT.next_interval(interval);
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Wraps the post-install script so that only one run of it goes at a time,
+/// under a lock in the guest, and a run after a successful one exits right
+/// away.
+///
+fn guarded_script(script: &str) -> String {
+    format!(
+        "exec 9>{SCRIPT_LOCK}\n\
+         flock 9 || exit 1\n\
+         [ -e {SCRIPT_MARKER} ] && exit 0\n\
+         (\n{script}\n)\n\
+         status=$?\n\
+         [ \"$status\" -eq 0 ] && touch {SCRIPT_MARKER}\n\
+         exit \"$status\"\n"
+    )
+}
+
+/// Turns the status of the exited post-install script into its result. A
+/// script without an exit code was killed by a signal and has failed.
+///
+fn script_result(status: GuestExecStatus) -> Result<()> {
+    match status.exitcode {
+        Some(0) => Ok(()),
+        Some(code) => Err(Error::Any(format!(
+            "Post-install script exited with code {code}: {}",
+            status.err_data.unwrap_or_default().trim()
+        ))),
+        None => Err(Error::Any(format!(
+            "Post-install script was killed: {}",
+            status.err_data.unwrap_or_default().trim()
+        ))),
+    }
+}
+
+/// Pings the guest agent of the booting VM until it responds.
+///
+async fn wait_for_agent(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    vm: VmRef,
+) -> Result<()> {
+    let start = clock.now();
+    let mut interval = Polling::AGENT.initial;
+
+    loop {
+        let Err(error) = proxmox_client.agent_ping(vm.clone()).await else {
+            return Ok(());
+        };
+        let elapsed = clock.elapsed(start);
+        if elapsed > Polling::AGENT.timeout {
+            tracing::warn!(target: "service", ?vm, ?error, "Guest agent never responded");
+            return Err(Error::Timeout(elapsed.as_secs_f32()));
+        }
+        clock.sleep(Polling::jittered(interval)).await;
+        interval = Polling::AGENT.next_interval(interval);
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exited(exitcode: Option<i32>) -> GuestExecStatus {
+        GuestExecStatus {
+            exited: true,
+            exitcode,
+            out_data: None,
+            err_data: Some("Terminated".to_owned()),
+        }
+    }
+
+    #[test]
+    fn script_should_succeed_with_zero_exit_code_only() {
+        // Act
+        let succeeded = script_result(exited(Some(0)));
+        let failed = script_result(exited(Some(2)));
+        let killed = script_result(exited(None));
+
+        // Assert
+        assert!(succeeded.is_ok());
+        assert!(failed.is_err());
+        assert!(killed.is_err());
+    }
+
+    #[test]
+    fn guarded_script_should_lock_and_skip_installed_app() {
+        // Act
+        let guarded = guarded_script("apt-get install -y docker.io");
+
+        // Assert
+        let lock = guarded.find("flock 9").unwrap();
+        let marker = guarded
+            .find(&format!("[ -e {SCRIPT_MARKER} ] && exit 0"))
+            .unwrap();
+        let script = guarded.find("apt-get install -y docker.io").unwrap();
+        assert!(lock < marker && marker < script);
+        assert!(guarded.contains(&format!("touch {SCRIPT_MARKER}")));
+    }
+}
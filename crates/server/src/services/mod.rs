@@ -1,28 +1,163 @@
-﻿use crate::model::queries;
+﻿use crate::clock::Clock;
+use crate::model::queries;
 use crate::model::types::ServerStatus;
 use crate::proxmox::Proxmox;
 use crate::proxmox::types::{TaskRef, TaskStatus};
 use dashboard_common::prelude::{Error, Result};
+use rand::Rng;
+use rand::distr::Alphanumeric;
 use sqlx::{PgPool, PgTransaction};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use uuid::Uuid;
 
 pub mod action;
+pub mod agent;
+pub mod allowlist;
+pub mod announcement;
+pub mod api_token;
+pub mod backup;
+pub mod billing;
+pub mod captcha;
+pub mod catalog;
+pub mod credit;
 pub mod deletion;
+pub mod disk;
+pub mod export;
+pub mod firewall;
+pub mod ip;
+pub mod iso;
+pub mod leader;
+pub mod marketplace;
+pub mod network;
+pub mod node;
+pub mod notification;
+pub mod oidc;
+pub mod organization;
+pub mod password;
+pub mod placement;
+pub mod quota;
+pub mod saml;
+pub mod search;
 pub mod setup;
+pub mod smoke;
+pub mod status;
+pub mod tag;
+pub mod timeline;
+pub mod traffic;
+pub mod transfer;
+pub mod usage;
+pub mod user;
+pub mod webhook;
 
 // -----------------------------------------------------------------------------
 
+/// Age after which a task that is still running is considered stuck, unless
+/// the caller waits longer.
+const STALE_TASK_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// Length of a generated password.
+const PASSWORD_LEN: usize = 20;
+
+/// How often and how long to poll a Proxmox task.
+///
+/// Polling starts at the `initial` interval and grows up to `max_interval`,
+/// each sleep shifted by a random jitter, so tasks started together don't
+/// poll the node in lockstep. Short operations get a tight timeout, cloning
+/// and restoring get minutes.
+///
+/// # Fields
+///
+/// * `initial`: Interval before the second poll.
+/// * `max_interval`: Cap of the growing interval.
+/// * `timeout`: Total time before returning a timeout error.
+/// * `max_attempts`: Polls before returning a timeout error, `None` to be
+///   bounded by the timeout only.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Polling {
+    pub initial: Duration,
+    pub max_interval: Duration,
+    pub timeout: Duration,
+    pub max_attempts: Option<u32>,
+}
+
+impl Polling {
+    /// Starting, stopping, shutting down and rebooting a VM.
+    pub const POWER: Self = Self::new(500, 5_000, 180, Some(60));
+    /// Applying a configuration change to a VM.
+    pub const CONFIG: Self = Self::new(500, 5_000, 60, Some(30));
+    /// Cloning a VM from a template, copying its disks.
+    pub const CLONE: Self = Self::new(2_000, 15_000, 900, Some(100));
+    /// Deleting a VM together with its disks.
+    pub const DELETE: Self = Self::new(1_000, 10_000, 300, Some(50));
+    /// Restoring a backup, bounded by the configured timeout.
+    pub const RESTORE: Self = Self::new(5_000, 30_000, 3_600, None);
+    /// Waiting for the guest agent of a booting VM.
+    pub const AGENT: Self = Self::new(2_000, 10_000, 300, None);
+    /// Running a post-install script through the guest agent.
+    pub const SCRIPT: Self = Self::new(2_000, 15_000, 1_800, None);
+
+    const fn new(
+        initial_ms: u64,
+        max_interval_ms: u64,
+        timeout_sec: u64,
+        max_attempts: Option<u32>,
+    ) -> Self {
+        Self {
+            initial: Duration::from_millis(initial_ms),
+            max_interval: Duration::from_millis(max_interval_ms),
+            timeout: Duration::from_secs(timeout_sec),
+            max_attempts,
+        }
+    }
+
+    /// Returns the same polling with another timeout.
+    ///
+    pub fn with_timeout(self, timeout_sec: u64) -> Self {
+        Self {
+            timeout: Duration::from_secs(timeout_sec),
+            ..self
+        }
+    }
+
+    /// Returns the interval following the given one, half as long again up to
+    /// the cap.
+    ///
+    pub fn next_interval(&self, interval: Duration) -> Duration {
+        interval.mul_f64(1.5).min(self.max_interval)
+    }
+
+    /// Shifts the interval randomly by up to a fifth either way.
+    ///
+    pub fn jittered(interval: Duration) -> Duration {
+        interval.mul_f64(rand::rng().random_range(0.8..=1.2))
+    }
+}
+
+/// Progress of a task that is still running, reported after every poll.
+///
+/// # Fields
+///
+/// * `attempt`: Number of the poll, starting at 1.
+/// * `elapsed`: Time since waiting started.
+/// * `next_poll`: Time until the next poll.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct TaskProgress {
+    pub attempt: u32,
+    pub elapsed: Duration,
+    pub next_poll: Duration,
+}
+
 /// Polls a Proxmox task until it is complete, with a timeout.
 ///
 /// # Arguments
 ///
 /// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `clock`: Clock measuring the timeout and waiting between the polls.
 /// * `task`: Proxmox task to monitor.
-/// * `wait_secs`: Interval in seconds between polling attempts.
-/// * `timeout`: Optional total time in seconds before returning a timeout
-///   error.
+/// * `polling`: Intervals and limits of the operation.
 ///
 /// # Returns
 ///
@@ -30,21 +165,65 @@ pub mod setup;
 ///
 pub async fn wait_until_finish(
     proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
     task: TaskRef,
-    wait_secs: u64,
-    timeout: Option<u64>,
+    polling: Polling,
 ) -> Result<()> {
-    let start = Instant::now();
-    let timeout = Duration::from_secs(timeout.unwrap_or(30));
+    wait_with_progress(proxmox_client, clock, task, polling, |_| {}).await
+}
+
+/// Polls a Proxmox task like `wait_until_finish`, reporting the progress
+/// while the task is running.
+///
+/// A task that is still running long after the start time in its UPID is
+/// stuck, so waiting on it stops right away instead of running into the
+/// timeout. This matters when following a task started by an earlier attempt.
+///
+/// # Arguments
+///
+/// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `clock`: Clock measuring the timeout and waiting between the polls.
+/// * `task`: Proxmox task to monitor.
+/// * `polling`: Intervals and limits of the operation.
+/// * `on_progress`: Called after every poll that found the task running.
+///
+pub async fn wait_with_progress<F>(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    task: TaskRef,
+    polling: Polling,
+    mut on_progress: F,
+) -> Result<()>
+where
+    F: FnMut(&TaskProgress),
+{
+    let start = clock.now();
+    let stale_age = polling.timeout.max(STALE_TASK_AGE);
+    let mut interval = polling.initial;
+    let mut attempt = 0;
 
     loop {
-        let elapsed = start.elapsed();
-        if elapsed > timeout {
+        let elapsed = clock.elapsed(start);
+        if elapsed > polling.timeout || polling.max_attempts.is_some_and(|max| attempt >= max) {
             return Err(Error::Timeout(elapsed.as_secs_f32()));
         }
+        attempt += 1;
 
         match proxmox_client.task_status(&task).await? {
-            TaskStatus::Pending => tokio::time::sleep(Duration::from_secs(wait_secs)).await,
+            TaskStatus::Pending => {
+                if let Some(age) = task.age(clock.now()).filter(|age| *age > stale_age) {
+                    tracing::warn!(target: "service", upid = ?task.upid, ?age, "Proxmox task is stale");
+                    return Err(Error::Timeout(age.as_secs_f32()));
+                }
+                let next_poll = Polling::jittered(interval);
+                on_progress(&TaskProgress {
+                    attempt,
+                    elapsed,
+                    next_poll,
+                });
+                clock.sleep(next_poll).await;
+                interval = polling.next_interval(interval);
+            }
             TaskStatus::Completed => break,
             TaskStatus::Failed(error) => return Err(Error::Any(error)),
         }
@@ -56,6 +235,11 @@ pub async fn wait_until_finish(
 /// Sets a server's status to a transient state and commits the change
 /// immediately.
 ///
+/// Guards the server against conflicting operations: the server record stays
+/// locked while its status is checked, so of concurrent operations on the same
+/// server only the first one gets through, until its status is set back to a
+/// stable one.
+///
 /// # Arguments
 ///
 /// * `pool`: Database connection pool.
@@ -65,7 +249,8 @@ pub async fn wait_until_finish(
 ///
 /// # Returns
 ///
-/// The old server status on success.
+/// The old server status on success, `Error::Conflict` naming the operation in
+/// flight if the server is busy.
 ///
 pub async fn set_transient_status(
     pool: &PgPool,
@@ -73,11 +258,38 @@ pub async fn set_transient_status(
     server_id: Uuid,
     new_status: ServerStatus,
 ) -> Result<ServerStatus> {
+    transition_status(pool, user_id, server_id, new_status, |_| Ok(())).await
+}
+
+/// Sets a server's status to a transient state like `set_transient_status`,
+/// only if the validation accepts the current status of the locked server.
+///
+/// # Arguments
+///
+/// * `pool`: Database connection pool.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the target server.
+/// * `new_status`: New transient status to set.
+/// * `validate`: Check of the current stable status.
+///
+/// # Returns
+///
+/// The old server status on success, the validation error otherwise.
+///
+pub async fn transition_status<F>(
+    pool: &PgPool,
+    user_id: Uuid,
+    server_id: Uuid,
+    new_status: ServerStatus,
+    validate: F,
+) -> Result<ServerStatus>
+where
+    F: FnOnce(ServerStatus) -> Result<()>,
+{
     let mut transaction = pool.begin().await?;
 
-    let old_status = queries::get_server_by_id(transaction.as_mut(), user_id, server_id)
-        .await?
-        .status;
+    let old_status = ensure_idle(&mut transaction, user_id, server_id).await?;
+    validate(old_status)?;
     queries::update_server_status(transaction.as_mut(), server_id, new_status).await?;
 
     transaction.commit().await?;
@@ -86,6 +298,73 @@ pub async fn set_transient_status(
     Ok(old_status)
 }
 
+/// Checks within a transaction that no operation is in progress on a server of
+/// the user. The server record stays locked until the end of the transaction,
+/// so no operation starts before it ends.
+///
+/// # Arguments
+///
+/// * `transaction`: Transaction of the caller.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// The current server status on success, `Error::Conflict` naming the
+/// operation in flight if the server is busy.
+///
+pub async fn ensure_idle(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ServerStatus> {
+    let status = queries::lock_server_status(transaction, user_id, server_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Server {server_id}")))?;
+    if let Some(operation) = status.operation() {
+        return Err(Error::Conflict(format!(
+            "Server {server_id} is busy, operation '{operation}' is in progress"
+        )));
+    }
+
+    Ok(status)
+}
+
+/// Reserves a server of the user in the `Configuring` status, so no other
+/// operation starts while its configuration changes on Proxmox. The server
+/// gets its status back with `release_server`.
+///
+/// # Arguments
+///
+/// * `pool`: Database connection pool.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Status of the server before the change, `Error::Conflict` if the server is
+/// busy.
+///
+pub async fn reserve_server(pool: &PgPool, user_id: Uuid, server_id: Uuid) -> Result<ServerStatus> {
+    set_transient_status(pool, user_id, server_id, ServerStatus::Configuring).await
+}
+
+/// Moves a server reserved with `reserve_server` back to its status from
+/// before the change. A failure is only logged, the status sync recovers the
+/// server later.
+///
+/// # Arguments
+///
+/// * `pool`: Database connection pool.
+/// * `server_id`: ID of the server.
+/// * `status`: Status of the server before the change.
+///
+pub async fn release_server(pool: &PgPool, server_id: Uuid, status: ServerStatus) {
+    if let Err(error) = queries::update_server_status(pool, server_id, status).await {
+        tracing::error!(target: "service", %server_id, ?error, "Failed to restore server status!");
+    }
+}
+
 /// Finalizes a database transaction by committing on success or rolling back on
 /// error.
 ///
@@ -113,3 +392,57 @@ pub async fn finalize_transaction(service_result: &Result<()>, transaction: PgTr
         },
     }
 }
+
+/// Generates a random alphanumeric password, like the passwords set for the
+/// administrator of a server.
+///
+pub fn new_password() -> String {
+    rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(PASSWORD_LEN)
+        .map(char::from)
+        .collect()
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polling_interval_should_grow_up_to_cap() {
+        // Arrange
+        let polling = Polling::CONFIG;
+
+        // Act
+        let intervals = std::iter::successors(Some(polling.initial), |interval| {
+            Some(polling.next_interval(*interval))
+        })
+        .take(8)
+        .collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(intervals[1], Duration::from_millis(750));
+        assert!(intervals.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(intervals[7], polling.max_interval);
+    }
+
+    #[test]
+    fn jitter_should_stay_within_a_fifth() {
+        // Arrange
+        let interval = Duration::from_secs(10);
+
+        // Act
+        let jittered = (0..100)
+            .map(|_| Polling::jittered(interval))
+            .collect::<Vec<_>>();
+
+        // Assert
+        assert!(
+            jittered.iter().all(|delay| {
+                (Duration::from_secs(8)..=Duration::from_secs(12)).contains(delay)
+            })
+        );
+    }
+}
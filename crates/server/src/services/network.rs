@@ -0,0 +1,189 @@
+use crate::model::queries;
+use crate::model::types::{ApiIpRange, ApiNetwork, Ipv4Cidr};
+use crate::web::types::{IpRangePayload, NewNetworkPayload};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::PgPool;
+use std::net::Ipv4Addr;
+use uuid::Uuid;
+
+/// Shortest prefix accepted for a new network, so a single request can't
+/// generate more than 65534 addresses.
+const MIN_PREFIX: u32 = 16;
+
+/// Longest prefix accepted for a new network, leaving at least one address
+/// besides the gateway.
+const MAX_PREFIX: u32 = 29;
+
+/// Creates a network from a CIDR block and fills its pool with every usable
+/// address of the block except the gateway.
+///
+/// # Arguments
+///
+/// * `pool`: Database connection pool.
+/// * `payload`: Datacenter, block and optional gateway of the network. Without
+///   a gateway, the first usable address of the block is used.
+///
+/// # Returns
+///
+/// Created network with the number of generated addresses.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(pool))]
+pub async fn create_network(pool: &PgPool, payload: NewNetworkPayload) -> Result<ApiNetwork> {
+    let cidr = payload.cidr.parse::<Ipv4Cidr>()?;
+    if !(MIN_PREFIX..=MAX_PREFIX).contains(&cidr.prefix) {
+        return Err(Error::Validation(format!(
+            "Network prefix must be between /{MIN_PREFIX} and /{MAX_PREFIX}"
+        )));
+    }
+    let gateway = match payload.gateway {
+        Some(gateway) => parse_address(&gateway)?,
+        None => cidr
+            .hosts()
+            .next()
+            .ok_or_else(|| Error::Validation(format!("Network {cidr} has no usable addresses")))?,
+    };
+    if !cidr.contains(gateway) {
+        return Err(Error::Validation(format!(
+            "Gateway {gateway} is outside of {cidr}"
+        )));
+    }
+
+    let addresses = cidr
+        .hosts()
+        .filter(|address| *address != gateway)
+        .map(|address| address.to_string())
+        .collect::<Vec<_>>();
+
+    let mut transaction = pool.begin().await?;
+    let network_id = queries::create_network(
+        &mut transaction,
+        &payload.datacenter_name,
+        &cidr,
+        &gateway.to_string(),
+    )
+    .await?;
+    let count = queries::create_ip_addresses(&mut transaction, network_id, &addresses).await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %network_id, %cidr, count, "Network created");
+
+    get_network(pool, network_id).await
+}
+
+/// Excludes a range of a network's addresses from allocation, or returns it to
+/// the pool. Addresses already assigned to servers keep their servers.
+///
+/// # Arguments
+///
+/// * `pool`: Database connection pool.
+/// * `network_id`: ID of the network.
+/// * `payload`: First and last address of the range, and whether to reserve it.
+///
+/// # Returns
+///
+/// Updated range with the number of its addresses.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(pool))]
+pub async fn set_range_reserved(
+    pool: &PgPool,
+    network_id: Uuid,
+    payload: IpRangePayload,
+) -> Result<ApiIpRange> {
+    let start = parse_address(&payload.start)?;
+    let end = parse_address(&payload.end)?;
+    if start > end {
+        return Err(Error::Validation(format!(
+            "Range start {start} is after its end {end}"
+        )));
+    }
+
+    get_network(pool, network_id).await?;
+    let (start, end) = (start.to_string(), end.to_string());
+    let updated =
+        queries::set_ip_range_reserved(pool, network_id, &start, &end, payload.reserved).await?;
+    tracing::info!(target: "service", %network_id, %start, %end, updated, reserved = payload.reserved, "IP range updated");
+
+    Ok(ApiIpRange {
+        network_id,
+        start,
+        end,
+        reserved: payload.reserved,
+        updated,
+    })
+}
+
+// -----------------------------------------------------------------------------
+
+/// Retrieves a network, reporting a missing one as a validation error.
+///
+async fn get_network(pool: &PgPool, network_id: Uuid) -> Result<ApiNetwork> {
+    queries::get_network(pool, network_id)
+        .await?
+        .ok_or_else(|| Error::Validation(format!("Network {network_id} not found")))
+}
+
+/// Parses an IPv4 address, reporting an invalid one as a validation error.
+///
+fn parse_address(address: &str) -> Result<Ipv4Addr> {
+    address
+        .trim()
+        .parse()
+        .map_err(|_| Error::Validation(format!("Invalid IP address {address}")))
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cidr_should_list_usable_hosts() {
+        // Arrange
+        let cidr = "192.168.10.0/30".parse::<Ipv4Cidr>().unwrap();
+
+        // Act
+        let hosts = cidr.hosts().collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(cidr.subnet_mask(), Ipv4Addr::new(255, 255, 255, 252));
+        assert_eq!(
+            hosts,
+            [
+                Ipv4Addr::new(192, 168, 10, 1),
+                Ipv4Addr::new(192, 168, 10, 2)
+            ]
+        );
+        assert!(cidr.contains(Ipv4Addr::new(192, 168, 10, 3)));
+        assert!(!cidr.contains(Ipv4Addr::new(192, 168, 10, 4)));
+    }
+
+    #[test]
+    fn cidr_should_reject_host_bits_and_invalid_prefix() {
+        // Assert
+        assert!(matches!(
+            "10.0.0.1/24".parse::<Ipv4Cidr>(),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            "10.0.0.0/33".parse::<Ipv4Cidr>(),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            "10.0.0.0".parse::<Ipv4Cidr>(),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn parse_address_should_reject_invalid_address() {
+        // Assert
+        assert_eq!(
+            parse_address(" 10.0.0.5 ").unwrap(),
+            Ipv4Addr::new(10, 0, 0, 5)
+        );
+        assert!(matches!(
+            parse_address("10.0.0.256"),
+            Err(Error::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::model::queries;
+use crate::model::types::{ApiNode, NodeVmCount};
+use crate::proxmox::Proxmox;
+use crate::proxmox::types::{NodeListItem, NodeStatus};
+use crate::state::AppState;
+use dashboard_common::prelude::Result;
+use futures_util::future::join_all;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Returns the resource usage of every node of the cluster, together with the
+/// number of servers placed on it.
+///
+/// The result is cached for `cache.nodes_ttl_sec`, so refreshing the admin UI
+/// doesn't query every node again.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+///
+/// # Returns
+///
+/// `Vec<ApiNode>` sorted by node name.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn list_nodes(app_state: &AppState) -> Result<Vec<ApiNode>> {
+    app_state.nodes.get_or_load((), load_nodes(app_state)).await
+}
+
+/// Reads the nodes from Proxmox, querying the status of the online ones
+/// concurrently.
+///
+async fn load_nodes(app_state: &AppState) -> Result<Vec<ApiNode>> {
+    let items = app_state.proxmox.list_nodes().await?;
+    let mut counts = queries::get_node_vm_counts(app_state.reader())
+        .await?
+        .into_iter()
+        .map(|count| (count.node_name.clone(), count))
+        .collect::<HashMap<_, _>>();
+    let statuses = join_all(
+        items
+            .iter()
+            .map(|item| read_status(&app_state.proxmox, item)),
+    )
+    .await;
+
+    let mut nodes = items
+        .into_iter()
+        .zip(statuses)
+        .map(|(item, status)| {
+            let count = counts.remove(&item.node);
+            to_api_node(item, status, count)
+        })
+        .collect::<Vec<_>>();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(nodes)
+}
+
+/// Reads the status of an online node. A node that fails to answer is still
+/// listed, only without its usage.
+///
+async fn read_status(
+    proxmox: &Arc<dyn Proxmox + Send + Sync>,
+    item: &NodeListItem,
+) -> Option<NodeStatus> {
+    if !item.is_online() {
+        return None;
+    }
+    proxmox
+        .node_status(&item.node)
+        .await
+        .inspect_err(|error| {
+            tracing::warn!(target: "service", node = %item.node, ?error, "Failed to read node status");
+        })
+        .ok()
+}
+
+/// Combines the Proxmox node with its status and server counts.
+///
+/// # Arguments
+///
+/// * `item`: Node as listed by Proxmox.
+/// * `status`: Resource usage of the node, if known.
+/// * `count`: Servers placed on the node, `None` if there are none.
+///
+pub fn to_api_node(
+    item: NodeListItem,
+    status: Option<NodeStatus>,
+    count: Option<NodeVmCount>,
+) -> ApiNode {
+    let online = item.is_online();
+    let (vms, running_vms) = count.map_or((0, 0), |c| (c.vms, c.running_vms));
+
+    ApiNode {
+        name: item.node,
+        online,
+        uptime: status.as_ref().map(|s| s.uptime),
+        cpu_usage: status.as_ref().map(|s| s.cpu),
+        cpu_cores: status.as_ref().map(|s| s.cpuinfo.cpus),
+        memory_used: status.as_ref().map(|s| s.memory.used),
+        memory_total: status.as_ref().map(|s| s.memory.total),
+        storage_used: status.as_ref().map(|s| s.rootfs.used),
+        storage_total: status.as_ref().map(|s| s.rootfs.total),
+        vms,
+        running_vms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxmox::types::{NodeCpuInfo, NodeUsage};
+
+    #[test]
+    fn offline_node_should_have_no_usage() {
+        // Arrange
+        let item = NodeListItem {
+            node: "pve-2".to_owned(),
+            status: "offline".to_owned(),
+        };
+        let count = NodeVmCount {
+            node_name: "pve-2".to_owned(),
+            vms: 3,
+            running_vms: 0,
+        };
+
+        // Act
+        let node = to_api_node(item, None, Some(count));
+
+        // Assert
+        assert!(!node.online);
+        assert_eq!(node.cpu_cores, None);
+        assert_eq!(node.vms, 3);
+    }
+
+    #[test]
+    fn online_node_should_report_usage() {
+        // Arrange
+        let item = NodeListItem {
+            node: "pve".to_owned(),
+            status: "online".to_owned(),
+        };
+        let status = NodeStatus {
+            uptime: 60,
+            cpu: 0.5,
+            cpuinfo: NodeCpuInfo { cpus: 8 },
+            memory: NodeUsage { used: 1, total: 4 },
+            rootfs: NodeUsage { used: 2, total: 8 },
+        };
+
+        // Act
+        let node = to_api_node(item, Some(status), None);
+
+        // Assert
+        assert!(node.online);
+        assert_eq!(node.cpu_cores, Some(8));
+        assert_eq!(node.memory_total, Some(4));
+        assert_eq!(node.storage_used, Some(2));
+        assert_eq!(node.vms, 0);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::i18n::Locale;
+use crate::mail::types::Email;
+use crate::model::queries;
+use crate::model::types::{ApiNotificationPreference, NewNotification, NotificationEvent};
+use crate::state::AppState;
+use crate::web::types::NotificationPreferencePayload;
+use chrono::{DateTime, Duration, Utc};
+use dashboard_common::prelude::{Error, Result};
+use uuid::Uuid;
+
+/// Maximum number of emails sent by a run of the scheduler.
+const EMAIL_BATCH_SIZE: i64 = 100;
+
+/// Number of attempts to send an email before giving up on it.
+const EMAIL_MAX_ATTEMPTS: i32 = 5;
+
+/// Delay before the first retry of a failed email, in seconds, growing with
+/// every attempt.
+const EMAIL_RETRY_SEC: i64 = 300;
+
+/// Time the claimed emails are leased for, in seconds, so that a run dying
+/// halfway doesn't hold them up for long.
+const EMAIL_LEASE_SEC: i64 = 600;
+
+/// Adds a notification to the notification center of every user, in the
+/// language the user prefers, and queues its email for the users who didn't
+/// opt out of the event. The emails are sent by the scheduler, see
+/// [`send_due`].
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_ids`: IDs of the notified users.
+/// * `event`: Event the users are notified about.
+/// * `render`: Renders the title and the text of the notification in a
+///   language, the title being the subject of the email.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the notifications are recorded.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, user_ids, render))]
+pub async fn notify<F>(
+    app_state: &AppState,
+    user_ids: &[Uuid],
+    event: NotificationEvent,
+    render: F,
+) -> Result<()>
+where
+    F: Fn(Locale) -> (String, String),
+{
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let recipients = queries::get_notification_recipients(&app_state.pool, user_ids, event).await?;
+    let notifications = recipients
+        .iter()
+        .map(|recipient| {
+            let (title, message) = render(Locale::of_user(recipient.locale.as_deref()));
+            NewNotification {
+                user_id: recipient.user_id,
+                title,
+                message,
+                emailed: recipient.emailed,
+            }
+        })
+        .collect::<Vec<_>>();
+    let count = queries::create_notifications(&app_state.pool, event, &notifications).await?;
+    tracing::info!(target: "service", %event, count, "Users notified");
+
+    Ok(())
+}
+
+/// Sends the queued notification emails that are due and schedules the failed
+/// ones for a retry, until they run out of attempts. The emails are claimed
+/// first and sent outside of any transaction, the notification staying in the
+/// notification center whatever happens to its email.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Number of sent emails.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn send_due(app_state: &AppState, now: DateTime<Utc>) -> Result<usize> {
+    let due = queries::claim_due_notification_emails(
+        &app_state.pool,
+        now,
+        now + Duration::seconds(EMAIL_LEASE_SEC),
+        EMAIL_BATCH_SIZE,
+    )
+    .await?;
+    let mut count = 0;
+
+    for email in due {
+        let notification_id = email.id;
+        let attempts = email.attempts + 1;
+        let sent = app_state
+            .mailer
+            .send(Email {
+                to: email.email,
+                subject: email.title,
+                body: email.message,
+            })
+            .await;
+        let (emailed_at, retry_at) = match sent {
+            Ok(()) => {
+                count += 1;
+                (Some(now), None)
+            }
+            Err(error) if attempts >= EMAIL_MAX_ATTEMPTS => {
+                tracing::error!(target: "service", %notification_id, attempts, ?error, "Notification email given up!");
+                (None, None)
+            }
+            Err(error) => {
+                tracing::warn!(target: "service", %notification_id, attempts, ?error, "Notification email not sent");
+                (None, Some(now + retry_delay(attempts)))
+            }
+        };
+        queries::record_notification_email(&app_state.pool, notification_id, emailed_at, retry_at)
+            .await?;
+    }
+
+    Ok(count)
+}
+
+/// Marks a notification of the user as read.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `notification_id`: ID of the notification.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success, `Error::NotFound` if the user doesn't have the
+/// notification.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn mark_read(app_state: &AppState, user_id: Uuid, notification_id: Uuid) -> Result<()> {
+    if !queries::mark_notification_read(&app_state.pool, user_id, notification_id).await? {
+        return Err(Error::NotFound(format!("Notification {notification_id}")));
+    }
+
+    Ok(())
+}
+
+/// Returns whether the user gets every event by email.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Preference of every event, emailed unless the user opted out.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_preferences(
+    app_state: &AppState,
+    user_id: Uuid,
+) -> Result<Vec<ApiNotificationPreference>> {
+    let stored = queries::get_notification_preferences(&app_state.pool, user_id).await?;
+
+    Ok(NotificationEvent::ALL
+        .into_iter()
+        .map(|event| ApiNotificationPreference {
+            event,
+            email: stored
+                .iter()
+                .find(|preference| preference.event == event)
+                .is_none_or(|preference| preference.email),
+        })
+        .collect())
+}
+
+/// Sets whether the user gets an event by email. The event stays in the
+/// notification center either way.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `payload`: Event and whether it is emailed.
+///
+/// # Returns
+///
+/// Updated preferences of every event.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_preference(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: NotificationPreferencePayload,
+) -> Result<Vec<ApiNotificationPreference>> {
+    queries::set_notification_preference(&app_state.pool, user_id, payload.event, payload.email)
+        .await?;
+    tracing::info!(target: "service", event = %payload.event, email = payload.email, "Notification preference set");
+
+    get_preferences(app_state, user_id).await
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the delay before the next attempt of an email, growing linearly
+/// with every failed attempt.
+///
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::seconds(EMAIL_RETRY_SEC.saturating_mul(attempts.max(1) as i64))
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_should_grow_with_attempts() {
+        // Assert
+        assert_eq!(retry_delay(1), Duration::minutes(5));
+        assert_eq!(retry_delay(4), Duration::minutes(20));
+    }
+}
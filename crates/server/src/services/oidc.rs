@@ -0,0 +1,608 @@
+use crate::config::{OidcEnv, OidcProviderEnv};
+use crate::model::queries;
+use crate::services::{allowlist, saml};
+use crate::state::AppState;
+use crate::web::types::{OidcCallbackQuery, SsoAuthorization};
+use axum::http::HeaderMap;
+use axum::http::header::COOKIE;
+use chrono::{DateTime, Duration, Utc};
+use dashboard_common::prelude::{AuthError, Error, Result};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use rand::Rng;
+use reqwest::{Client, Url};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::fmt::Debug;
+use std::net::IpAddr;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Path of the discovery document, relative to the issuer.
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+/// Cookie binding a started login to the browser that started it.
+pub const STATE_COOKIE: &str = "oidc_state";
+
+/// Path the state cookie is sent to.
+const STATE_COOKIE_PATH: &str = "/auth/oidc";
+
+/// Endpoints of a provider, published in its discovery document.
+///
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+/// Response of the token endpoint, of which only the ID token is used.
+///
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// Claims of a verified ID token identifying the account at the provider.
+///
+/// # Fields
+///
+/// * `sub`: ID of the account at the provider.
+/// * `nonce`: Nonce of the started login.
+/// * `email`: Email address of the account, if shared.
+/// * `email_verified`: Whether the provider verified the email address.
+///
+#[derive(Debug, Deserialize)]
+pub struct IdClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+}
+
+/// Starts a login with an OpenID Connect provider. The state and the nonce of
+/// the login are stored until the provider redirects the user back, and the
+/// state is also set in a signed cookie, so only the browser that started the
+/// login can complete it.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `name`: Name of the provider.
+///
+/// # Returns
+///
+/// Authorization page of the provider to redirect the user to, and the
+/// `Set-Cookie` header with the state of the login.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn authorize(app_state: &AppState, name: &str) -> Result<(SsoAuthorization, String)> {
+    let settings = &app_state.config.oidc;
+    let provider = find_provider(settings, name)?;
+    let discovery = discover(&client(settings)?, provider).await?;
+
+    let now = app_state.clock.now();
+    let state = random_token();
+    let nonce = random_token();
+    queries::add_oidc_login(
+        &app_state.pool,
+        &state,
+        name,
+        &nonce,
+        state_start(settings, now),
+    )
+    .await?;
+    let url = authorization_url(
+        &discovery.authorization_endpoint,
+        provider,
+        &settings.redirect_url,
+        &state,
+        &nonce,
+    )?;
+    let expires_at = now + Duration::seconds(settings.state_ttl_sec);
+    let secret = app_state.config.token.secret.get();
+    let cookie = format!(
+        "{STATE_COOKIE}={}; Max-Age={}; Path={STATE_COOKIE_PATH}; HttpOnly; Secure; SameSite=Lax",
+        sign_state(secret.expose_secret(), &state, expires_at),
+        settings.state_ttl_sec
+    );
+
+    Ok((SsoAuthorization { url }, cookie))
+}
+
+/// Returns the `Set-Cookie` header removing the state cookie of a completed
+/// login.
+///
+pub fn clear_state_cookie() -> String {
+    format!("{STATE_COOKIE}=; Max-Age=0; Path={STATE_COOKIE_PATH}; HttpOnly; Secure; SameSite=Lax")
+}
+
+/// Completes a login the provider redirected the user back from. The code is
+/// exchanged for the ID token, which must be signed by the provider for this
+/// client and contain the nonce of the login.
+///
+/// The login must come from the browser that started it, with the state cookie
+/// of the login. The account at the provider logs into the user it is linked
+/// to. An account
+/// that isn't linked yet is linked to the user with the same email address, if
+/// both the provider and the dashboard verified the address, so nobody can
+/// take over an account by registering its address elsewhere. No new users are
+/// registered, as they lack the billing details.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `query`: Code and state passed back by the provider.
+/// * `headers`: Headers of the request, with the state cookie.
+/// * `client_ip`: Address of the client, if known.
+///
+/// # Returns
+///
+/// ID of the logged in user.
+///
+/// # Errors
+///
+/// `Error::Validation` if the login is unknown or expired,
+/// `AuthError::Login` if the state cookie is missing or doesn't match, or the
+/// code or the ID token is invalid,
+/// `Error::Forbidden` if the account can't be linked to a user, the user must
+/// log in with single sign-on, or the address of the client is not allowed.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, query, headers))]
+pub async fn callback(
+    app_state: &AppState,
+    query: OidcCallbackQuery,
+    headers: &HeaderMap,
+    client_ip: Option<IpAddr>,
+) -> Result<Uuid> {
+    let settings = &app_state.config.oidc;
+    let now = app_state.clock.now();
+    let secret = app_state.config.token.secret.get();
+    let cookie = state_cookie(headers).ok_or_else(|| rejected("State cookie missing"))?;
+    verify_state(secret.expose_secret(), &cookie, &query.state, now)?;
+    let login = queries::take_oidc_login(&app_state.pool, &query.state, state_start(settings, now))
+        .await?
+        .ok_or_else(|| Error::Validation("Login is expired, start it again".to_owned()))?;
+    let provider = find_provider(settings, &login.provider)?;
+    let client = client(settings)?;
+    let discovery = discover(&client, provider).await?;
+
+    let id_token = exchange_code(
+        &client,
+        &discovery,
+        provider,
+        &settings.redirect_url,
+        &query.code,
+    )
+    .await?;
+    let keys = client
+        .get(&discovery.jwks_uri)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<JwkSet>()
+        .await?;
+    let claims = verify_id_token(
+        &id_token,
+        &keys,
+        &discovery.issuer,
+        &provider.client_id,
+        &login.nonce,
+    )?;
+
+    let user_id = link(app_state, &login.provider, provider, &claims).await?;
+    saml::ensure_not_enforced(app_state, user_id).await?;
+    allowlist::ensure_allowed(app_state, user_id, client_ip).await?;
+
+    Ok(user_id)
+}
+
+/// Builds the URL of the authorization page of the provider.
+///
+/// # Arguments
+///
+/// * `endpoint`: Authorization endpoint of the provider.
+/// * `provider`: Client registered at the provider.
+/// * `redirect_url`: Page the provider redirects the user back to.
+/// * `state`: State of the login.
+/// * `nonce`: Nonce the ID token must contain.
+///
+/// # Returns
+///
+/// URL requesting an authorization code for the scopes of the client.
+///
+pub fn authorization_url(
+    endpoint: &str,
+    provider: &OidcProviderEnv,
+    redirect_url: &str,
+    state: &str,
+    nonce: &str,
+) -> Result<String> {
+    let url = Url::parse_with_params(
+        endpoint,
+        [
+            ("response_type", "code"),
+            ("client_id", provider.client_id.as_str()),
+            ("redirect_uri", redirect_url),
+            ("scope", provider.scopes.as_str()),
+            ("state", state),
+            ("nonce", nonce),
+        ],
+    )
+    .map_err(|error| Error::Any(format!("Invalid authorization endpoint: {error}")))?;
+
+    Ok(url.into())
+}
+
+/// Verifies the signature and the claims of an ID token with the published
+/// keys of the provider. The token must be signed with an algorithm of its
+/// key, the one the key declares or else one of its type, so a token can't
+/// choose how it is verified.
+///
+/// # Arguments
+///
+/// * `token`: ID token returned by the token endpoint.
+/// * `keys`: Published keys of the provider.
+/// * `issuer`: Issuer the token must come from.
+/// * `client_id`: Client the token must be issued to.
+/// * `nonce`: Nonce of the login the token must contain.
+///
+/// # Returns
+///
+/// Claims of the token, `AuthError::Login` if it is invalid.
+///
+pub fn verify_id_token(
+    token: &str,
+    keys: &JwkSet,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+) -> Result<IdClaims> {
+    let header = decode_header(token).map_err(rejected)?;
+    let jwk = match &header.kid {
+        Some(kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    }
+    .ok_or_else(|| rejected("Unknown signing key"))?;
+    let algorithms = key_algorithms(jwk);
+    if !algorithms.contains(&header.alg) {
+        return Err(rejected(format!(
+            "Algorithm {:?} not allowed by the key",
+            header.alg
+        )));
+    }
+    let key = DecodingKey::from_jwk(jwk).map_err(rejected)?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.algorithms = algorithms;
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[client_id]);
+    let claims = decode::<IdClaims>(token, &key, &validation)
+        .map_err(rejected)?
+        .claims;
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(rejected("Nonce mismatch"));
+    }
+
+    Ok(claims)
+}
+
+/// Signs the state of a login for its cookie.
+///
+/// # Arguments
+///
+/// * `secret`: Secret the cookie is signed with.
+/// * `state`: State of the login.
+/// * `expires_at`: Time the login expires.
+///
+/// # Returns
+///
+/// Value of the cookie, `{state}.{expiration}.{signature}` with the Unix
+/// timestamp of the expiration and the hex-encoded HMAC-SHA256 of the rest.
+///
+pub fn sign_state(secret: &str, state: &str, expires_at: DateTime<Utc>) -> String {
+    let payload = format!("{state}.{}", expires_at.timestamp());
+    let signature = hex::encode(state_mac(secret, &payload).finalize().into_bytes());
+
+    format!("{payload}.{signature}")
+}
+
+/// Checks that the state cookie was signed for the state of the login and
+/// hasn't expired.
+///
+/// # Arguments
+///
+/// * `secret`: Secret the cookie is signed with.
+/// * `cookie`: Value of the state cookie.
+/// * `state`: State passed back by the provider.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the cookie belongs to the login, `AuthError::Login`
+/// otherwise.
+///
+pub fn verify_state(secret: &str, cookie: &str, state: &str, now: DateTime<Utc>) -> Result<()> {
+    let (payload, signature) = cookie
+        .rsplit_once('.')
+        .ok_or_else(|| rejected("Malformed state cookie"))?;
+    let signature = hex::decode(signature).map_err(rejected)?;
+    state_mac(secret, payload)
+        .verify_slice(&signature)
+        .map_err(rejected)?;
+    let (signed_state, expires_at) = payload
+        .rsplit_once('.')
+        .ok_or_else(|| rejected("Malformed state cookie"))?;
+    if signed_state != state {
+        return Err(rejected("State cookie of another login"));
+    }
+    if expires_at.parse::<i64>().map_err(rejected)? < now.timestamp() {
+        return Err(rejected("State cookie expired"));
+    }
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+
+/// Returns the algorithms a published key verifies. A key declaring its
+/// algorithm verifies only that one, any other one the algorithms of its type.
+/// Symmetric keys verify nothing, as their secret would be public.
+///
+fn key_algorithms(jwk: &Jwk) -> Vec<Algorithm> {
+    if let Some(declared) = jwk.common.key_algorithm {
+        return Algorithm::from_str(&declared.to_string())
+            .into_iter()
+            .filter(|algorithm| {
+                !matches!(
+                    algorithm,
+                    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+                )
+            })
+            .collect();
+    }
+
+    match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => vec![
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P256 => vec![Algorithm::ES256],
+            EllipticCurve::P384 => vec![Algorithm::ES384],
+            EllipticCurve::P521 | EllipticCurve::Ed25519 => Vec::new(),
+        },
+        AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+        AlgorithmParameters::OctetKey(_) => Vec::new(),
+    }
+}
+
+/// Finds the user the account at the provider logs into, linking the account
+/// by its verified email address on the first login.
+///
+async fn link(
+    app_state: &AppState,
+    name: &str,
+    provider: &OidcProviderEnv,
+    claims: &IdClaims,
+) -> Result<Uuid> {
+    if let Some(user_id) = queries::get_identity_user(&app_state.pool, name, &claims.sub).await? {
+        return Ok(user_id);
+    }
+
+    let email = claims
+        .email
+        .as_deref()
+        .filter(|_| claims.email_verified || provider.trust_email)
+        .ok_or_else(|| {
+            Error::Forbidden(format!("The {name} account has no verified email address"))
+        })?;
+    let (user_id, verified) = queries::get_user_verification_by_email(&app_state.pool, email)
+        .await?
+        .ok_or_else(|| Error::Forbidden(format!("No account is registered with {email}")))?;
+    if !verified {
+        return Err(Error::Forbidden(format!(
+            "Verify {email} before logging in with {name}"
+        )));
+    }
+    queries::add_user_identity(&app_state.pool, user_id, name, &claims.sub, email).await?;
+    tracing::info!(target: "service", %user_id, provider = name, "OpenID Connect account linked");
+
+    Ok(user_id)
+}
+
+/// Exchanges the authorization code for the ID token, authenticating the client
+/// with its secret.
+///
+async fn exchange_code(
+    client: &Client,
+    discovery: &Discovery,
+    provider: &OidcProviderEnv,
+    redirect_url: &str,
+    code: &str,
+) -> Result<String> {
+    let response = client
+        .post(&discovery.token_endpoint)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_url),
+            ("client_id", provider.client_id.as_str()),
+            ("client_secret", provider.client_secret.expose_secret()),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(rejected(response.status()));
+    }
+
+    Ok(response.json::<TokenResponse>().await?.id_token)
+}
+
+/// Fetches the discovery document of the provider, which must belong to its
+/// configured issuer.
+///
+async fn discover(client: &Client, provider: &OidcProviderEnv) -> Result<Discovery> {
+    let issuer = provider.issuer.trim_end_matches('/');
+    let discovery = client
+        .get(format!("{issuer}{DISCOVERY_PATH}"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Discovery>()
+        .await?;
+    if discovery.issuer.trim_end_matches('/') != issuer {
+        return Err(Error::Any(format!(
+            "Issuer {} doesn't match {issuer}",
+            discovery.issuer
+        )));
+    }
+
+    Ok(discovery)
+}
+
+fn find_provider<'a>(settings: &'a OidcEnv, name: &str) -> Result<&'a OidcProviderEnv> {
+    settings
+        .providers
+        .get(name)
+        .ok_or_else(|| Error::NotFound(format!("OpenID Connect provider {name}")))
+}
+
+fn client(settings: &OidcEnv) -> Result<Client> {
+    Ok(Client::builder()
+        .timeout(std::time::Duration::from_secs(settings.timeout_sec))
+        .build()?)
+}
+
+/// Returns the start time of the logins that haven't expired yet.
+///
+fn state_start(settings: &OidcEnv, now: DateTime<Utc>) -> DateTime<Utc> {
+    now - Duration::seconds(settings.state_ttl_sec)
+}
+
+/// Returns the value of the state cookie sent with the request.
+///
+fn state_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == STATE_COOKIE)
+        .map(|(_, value)| value.to_owned())
+}
+
+fn state_mac(secret: &str, payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC should accept keys of any length");
+    mac.update(payload.as_bytes());
+
+    mac
+}
+
+fn random_token() -> String {
+    hex::encode(rand::rng().random::<[u8; 32]>())
+}
+
+/// Logs why the login is rejected, as the client only learns that it failed.
+///
+fn rejected(reason: impl Debug) -> Error {
+    tracing::warn!(target: "service", ?reason, "OpenID Connect login rejected");
+    Error::Auth(AuthError::Login)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use secrecy::SecretString;
+    use serde_json::json;
+
+    #[test]
+    fn authorization_url_should_request_code_with_state_and_nonce() {
+        // Arrange
+        let provider = OidcProviderEnv {
+            issuer: "https://accounts.example.com".to_owned(),
+            client_id: "dashboard".to_owned(),
+            client_secret: SecretString::from("secret"),
+            scopes: "openid email".to_owned(),
+            trust_email: false,
+        };
+
+        // Act
+        let url = authorization_url(
+            "https://accounts.example.com/authorize?prompt=login",
+            &provider,
+            "https://dashboard.example.com/oidc",
+            "state",
+            "nonce",
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            url,
+            "https://accounts.example.com/authorize?prompt=login&response_type=code\
+             &client_id=dashboard&redirect_uri=https%3A%2F%2Fdashboard.example.com%2Foidc\
+             &scope=openid+email&state=state&nonce=nonce"
+        );
+    }
+
+    #[test]
+    fn id_token_should_be_signed_with_algorithm_of_key() {
+        // Arrange
+        let keys = serde_json::from_value::<JwkSet>(json!({ "keys": [{
+            "kty": "RSA",
+            "kid": "rsa",
+            "alg": "RS256",
+            "n": "sXchDaQebHnPiGvyDOAT4saGEUetSyo9MKLOoWFsueri23bOdgWp4Dy1WlUzewbgBHod5pcM9H95GQRV3JDXboIRROSBigeC5yjU1hGzHHyXss8UDprecbAYxknTcQkhslANGRUZmdTOQ5qTRsLAt6BTYuyvVRdhS8exSZEy_c4gs_7svlJJQ4H9_NxsiIoLwAEk7-Q3UXERGYw_75IDrGA84-lA_-Ct4eTlXHBIY2EaV7t7LjJaynVJCpkv4LKjTTAumiGUIuQhrNhZLuF_RJLqHpM2kgWFLU7-VTdL1VbC2tejvcI2BlMkEpk1BzBZI0KQB0GaDWFLN-aEAw3vRw",
+            "e": "AQAB",
+        }]}))
+        .unwrap();
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("rsa".to_owned());
+        let claims = json!({ "sub": "alice", "nonce": "nonce", "exp": u32::MAX });
+        let token = encode(&header, &claims, &EncodingKey::from_secret(b"public")).unwrap();
+
+        // Act
+        let result = verify_id_token(&token, &keys, "issuer", "dashboard", "nonce");
+
+        // Assert
+        assert!(matches!(result, Err(Error::Auth(AuthError::Login))));
+        assert_eq!(key_algorithms(&keys.keys[0]), [Algorithm::RS256]);
+    }
+
+    #[test]
+    fn state_cookie_should_belong_to_login_until_expired() {
+        // Arrange
+        let now = Utc::now();
+        let cookie = sign_state("secret", "state", now + Duration::minutes(10));
+        let tampered = cookie.replacen("state", "other", 1);
+
+        // Act
+        let valid = verify_state("secret", &cookie, "state", now);
+        let other_login = verify_state("secret", &cookie, "other", now);
+        let forged = verify_state("secret", &tampered, "other", now);
+        let other_secret = verify_state("rotated", &cookie, "state", now);
+        let expired = verify_state("secret", &cookie, "state", now + Duration::minutes(11));
+
+        // Assert
+        assert!(valid.is_ok());
+        assert!(other_login.is_err());
+        assert!(forged.is_err());
+        assert!(other_secret.is_err());
+        assert!(expired.is_err());
+    }
+}
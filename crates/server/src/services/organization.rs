@@ -0,0 +1,300 @@
+use crate::model::queries;
+use crate::model::types::{ApiOrganization, ApiOrganizationMember, ApiServer, OrganizationRole};
+use crate::state::AppState;
+use crate::web::types::{MemberPayload, NamePayload, OrganizationServerPayload};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::PgTransaction;
+use uuid::Uuid;
+
+/// Maximum length of an organization name.
+const MAX_NAME_LEN: usize = 100;
+
+/// Creates an organization owned by the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the owner.
+/// * `payload`: Name of the organization.
+///
+/// # Returns
+///
+/// Created organization.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_organization(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: NamePayload,
+) -> Result<ApiOrganization> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(Error::Validation(format!(
+            "Field name must be between 1 and {MAX_NAME_LEN} characters long"
+        )));
+    }
+
+    let mut transaction = app_state.pool.begin().await?;
+    let organization_id = queries::create_organization(&mut transaction, name, user_id).await?;
+    let organization = queries::get_organization(transaction.as_mut(), organization_id, user_id)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %organization_id, "Organization created");
+
+    Ok(organization)
+}
+
+/// Retrieves the members of an organization of the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of a member.
+/// * `organization_id`: ID of the organization.
+///
+/// # Returns
+///
+/// Members of the organization.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_members(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<Vec<ApiOrganizationMember>> {
+    queries::get_organization(&app_state.pool, organization_id, user_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Organization {organization_id}")))?;
+
+    queries::get_organization_members(&app_state.pool, organization_id).await
+}
+
+/// Adds a member to an organization, or changes the role of a member. Only
+/// owners appoint or demote owners, and the last owner can't be demoted.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the member managing the organization.
+/// * `organization_id`: ID of the organization.
+/// * `payload`: Email address and role of the member.
+///
+/// # Returns
+///
+/// Members of the organization.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_member(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+    payload: MemberPayload,
+) -> Result<Vec<ApiOrganizationMember>> {
+    let member = queries::get_user_by_email(&app_state.pool, payload.email.trim()).await?;
+
+    let mut transaction = app_state.pool.begin().await?;
+    let organization = lock_managed(&mut transaction, user_id, organization_id).await?;
+    ensure_shared(&organization)?;
+    let current = queries::get_organization(transaction.as_mut(), organization_id, member.id)
+        .await?
+        .map(|organization| organization.role);
+    let appoints_owner =
+        payload.role == OrganizationRole::Owner || current == Some(OrganizationRole::Owner);
+    if appoints_owner && organization.role != OrganizationRole::Owner {
+        return Err(Error::Forbidden(
+            "Only owners can change the owners of an organization".to_owned(),
+        ));
+    }
+    if current == Some(OrganizationRole::Owner) && payload.role != OrganizationRole::Owner {
+        ensure_other_owner(&mut transaction, organization_id).await?;
+    }
+
+    queries::set_organization_member(
+        transaction.as_mut(),
+        organization_id,
+        member.id,
+        payload.role,
+    )
+    .await?;
+    let members = queries::get_organization_members(transaction.as_mut(), organization_id).await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %organization_id, member_id = %member.id, role = %payload.role, "Member set");
+
+    Ok(members)
+}
+
+/// Removes a member from an organization. Members may leave on their own,
+/// otherwise the organization must be managed by the user. The last owner
+/// can't be removed. The servers the member is accountable for stay in the
+/// organization and are handed over to another owner.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the member removing.
+/// * `organization_id`: ID of the organization.
+/// * `member_id`: ID of the removed member.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn remove_member(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+    member_id: Uuid,
+) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+    let organization = match member_id == user_id {
+        true => lock_member(&mut transaction, user_id, organization_id).await?,
+        false => lock_managed(&mut transaction, user_id, organization_id).await?,
+    };
+    ensure_shared(&organization)?;
+    let member = queries::get_organization(transaction.as_mut(), organization_id, member_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Member {member_id}")))?;
+    if member.role == OrganizationRole::Owner {
+        if organization.role != OrganizationRole::Owner {
+            return Err(Error::Forbidden(
+                "Only owners can change the owners of an organization".to_owned(),
+            ));
+        }
+        ensure_other_owner(&mut transaction, organization_id).await?;
+    }
+
+    queries::hand_over_organization_services(
+        transaction.as_mut(),
+        member_id,
+        Some(organization_id),
+    )
+    .await?;
+    queries::remove_organization_member(transaction.as_mut(), organization_id, member_id).await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %organization_id, %member_id, "Member removed");
+
+    Ok(())
+}
+
+/// Retrieves the servers of an organization of the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of a member.
+/// * `organization_id`: ID of the organization.
+///
+/// # Returns
+///
+/// Servers of the organization.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_servers(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<Vec<ApiServer>> {
+    queries::get_organization(&app_state.pool, organization_id, user_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Organization {organization_id}")))?;
+
+    queries::get_organization_servers(&app_state.pool, organization_id).await
+}
+
+/// Moves a server of the user to an organization the user manages, or back to
+/// the personal one. The user stays accountable for the server.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `organization_id`: ID of the organization.
+/// * `payload`: ID of the server.
+///
+/// # Returns
+///
+/// Servers of the organization.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn add_server(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+    payload: OrganizationServerPayload,
+) -> Result<Vec<ApiServer>> {
+    let server_id = payload.server_id;
+    let mut transaction = app_state.pool.begin().await?;
+    lock_managed(&mut transaction, user_id, organization_id).await?;
+    if !queries::set_server_organization(transaction.as_mut(), server_id, user_id, organization_id)
+        .await?
+    {
+        return Err(Error::NotFound(format!("Server {server_id}")));
+    }
+
+    let servers = queries::get_organization_servers(transaction.as_mut(), organization_id).await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %organization_id, %server_id, "Server moved to organization");
+
+    Ok(servers)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Locks an organization of the user.
+///
+async fn lock_member(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<ApiOrganization> {
+    queries::lock_organization(transaction, organization_id, user_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Organization {organization_id}")))
+}
+
+/// Locks an organization the user manages.
+///
+async fn lock_managed(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<ApiOrganization> {
+    let organization = lock_member(transaction, user_id, organization_id).await?;
+    if !organization.role.can_manage() {
+        return Err(Error::Forbidden(format!(
+            "Organization {organization_id} is managed by its owners and admins"
+        )));
+    }
+
+    Ok(organization)
+}
+
+/// Checks that the members of an organization can change, which they can't
+/// for a personal one.
+///
+fn ensure_shared(organization: &ApiOrganization) -> Result<()> {
+    if organization.personal {
+        return Err(Error::Validation(
+            "Personal organizations have a single member".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that an organization keeps an owner when one of them leaves.
+///
+async fn ensure_other_owner(
+    transaction: &mut PgTransaction<'_>,
+    organization_id: Uuid,
+) -> Result<()> {
+    if queries::count_organization_owners(&mut **transaction, organization_id).await? < 2 {
+        return Err(Error::Conflict(
+            "Organization must keep at least one owner".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
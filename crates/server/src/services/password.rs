@@ -0,0 +1,133 @@
+use crate::model::queries;
+use crate::model::types::{ApiPasswordReset, AuditAction, PasswordResetMethod, ServerStatus};
+use crate::proxmox::types::{TaskRef, VmConfig, VmRef};
+use crate::services::{self, Polling};
+use crate::state::AppState;
+use dashboard_common::prelude::Result;
+use serde_json::json;
+use uuid::Uuid;
+
+/// Administrator of the Linux guests.
+const ROOT_USER: &str = "root";
+/// Administrator of the Windows guests.
+const WINDOWS_ADMIN_USER: &str = "Administrator";
+/// Identifier the guest agent reports for Windows systems.
+const WINDOWS_OS_ID: &str = "mswindows";
+
+/// Sets a new random password of the administrator of a server of the user
+/// and records the reset in the audit log.
+///
+/// A running server with a responding guest agent gets the password of its
+/// `root` or `Administrator` user set at once. Otherwise the password goes to
+/// the cloud-init configuration of the server, which applies it to the default
+/// user on the next boot. The server is reserved in the `Configuring` status
+/// meanwhile.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// New password and how it was applied, `Error::Conflict` if an operation is
+/// in progress on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn reset_password(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ApiPasswordReset> {
+    let status = services::reserve_server(&app_state.pool, user_id, server_id).await?;
+    let result = reset(app_state, user_id, server_id, status).await;
+    services::release_server(&app_state.pool, server_id, status).await;
+
+    result
+}
+
+// -----------------------------------------------------------------------------
+
+/// Sets a new password of the administrator of the reserved server, which had
+/// the given status before.
+///
+async fn reset(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    status: ServerStatus,
+) -> Result<ApiPasswordReset> {
+    let vm = queries::get_server_proxmox_ref(&app_state.pool, user_id, server_id).await?;
+    let running = status == ServerStatus::Running;
+    let agent_user = match running {
+        true => get_agent_user(app_state, vm.clone()).await,
+        false => None,
+    };
+
+    let password = services::new_password();
+    let method = match agent_user {
+        Some(username) => {
+            app_state
+                .proxmox
+                .agent_set_user_password(vm, username, &password)
+                .await?;
+            PasswordResetMethod::GuestAgent
+        }
+        None => {
+            set_cloud_init_password(app_state, vm, &password).await?;
+            PasswordResetMethod::CloudInit
+        }
+    };
+
+    queries::create_audit_entry(
+        &app_state.pool,
+        user_id,
+        AuditAction::PasswordReset,
+        Some(server_id),
+        &json!({ "method": method, "username": agent_user }),
+    )
+    .await?;
+    tracing::info!(target: "service", %server_id, %method, "Password reset");
+
+    Ok(ApiPasswordReset {
+        password,
+        username: agent_user.map(str::to_owned),
+        method,
+        reboot_required: running && method == PasswordResetMethod::CloudInit,
+    })
+}
+
+/// Checks that the guest agent of the VM responds and picks the administrator
+/// of its system.
+///
+/// # Returns
+///
+/// Name of the administrator, `None` if the agent doesn't respond.
+///
+async fn get_agent_user(app_state: &AppState, vm: VmRef) -> Option<&'static str> {
+    if let Err(error) = app_state.proxmox.agent_ping(vm.clone()).await {
+        tracing::info!(target: "service", ?vm, ?error, "Guest agent not responding, falling back to cloud-init");
+        return None;
+    }
+
+    match app_state.proxmox.agent_os_info(vm).await {
+        Ok(os_info) if os_info.id.as_deref() == Some(WINDOWS_OS_ID) => Some(WINDOWS_ADMIN_USER),
+        Ok(_) => Some(ROOT_USER),
+        Err(error) => {
+            tracing::warn!(target: "service", ?error, "Guest system unknown, assuming Linux");
+            Some(ROOT_USER)
+        }
+    }
+}
+
+/// Sets the cloud-init password of the VM and waits until the configuration
+/// is applied.
+///
+async fn set_cloud_init_password(app_state: &AppState, vm: VmRef, password: &str) -> Result<()> {
+    let vm_config = VmConfig::builder().cipassword(password).build();
+    let upid = app_state.proxmox.vm_config(vm.clone(), vm_config).await?;
+    let task = TaskRef::new(&vm.node, &upid);
+
+    services::wait_until_finish(&app_state.proxmox, &app_state.clock, task, Polling::CONFIG).await
+}
@@ -0,0 +1,129 @@
+use crate::config::PlacementEnv;
+use crate::model::queries;
+use crate::model::types::NodeCapacity;
+use dashboard_common::prelude::{Error, Result};
+use sqlx::{Executor, Postgres};
+
+/// Checks that the node can host a server with the requested resources under
+/// its overcommit policy.
+///
+/// Nodes that are not registered in the `nodes` table are not capacity
+/// managed, and placement on them is always allowed.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `policy`: Cluster-wide default overcommit ratios.
+/// * `node_name`: Name of the Proxmox node to place the server on.
+/// * `cpu_cores`: Number of vCPUs requested for the new server.
+/// * `ram_gb`: Amount of RAM in GB requested for the new server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the node has enough capacity.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(executor, policy))]
+pub async fn ensure_capacity<'e, E>(
+    executor: E,
+    policy: &PlacementEnv,
+    node_name: &str,
+    cpu_cores: i32,
+    ram_gb: i32,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let Some(node) = queries::get_node_capacity(executor, node_name).await? else {
+        tracing::warn!(target: "service", node_name, "Node is not registered, skipping capacity check");
+        return Ok(());
+    };
+
+    match has_capacity(&node, policy, cpu_cores, ram_gb) {
+        true => Ok(()),
+        false => Err(Error::Capacity(format!(
+            "Node {} cannot fit {} vCPU and {} GB RAM",
+            node.name, cpu_cores, ram_gb
+        ))),
+    }
+}
+
+/// Decides whether the node can fit the requested resources, comparing the
+/// allocations against the physical capacity multiplied by the overcommit
+/// ratios instead of raw totals.
+///
+/// # Arguments
+///
+/// * `node`: Node capacity and current allocations.
+/// * `policy`: Default ratios for nodes without their own overrides.
+/// * `cpu_cores`: Number of vCPUs requested.
+/// * `ram_gb`: Amount of RAM in GB requested.
+///
+/// # Returns
+///
+/// `true` if both CPU and RAM fit.
+///
+pub fn has_capacity(
+    node: &NodeCapacity,
+    policy: &PlacementEnv,
+    cpu_cores: i32,
+    ram_gb: i32,
+) -> bool {
+    let cpu_ratio = node.cpu_overcommit.unwrap_or(policy.cpu_overcommit);
+    let ram_ratio = node.ram_overcommit.unwrap_or(policy.ram_overcommit);
+
+    let cpu_limit = (node.cpu_cores as f64 * cpu_ratio).floor() as i64;
+    let ram_limit = (node.ram_gb as f64 * ram_ratio).floor() as i64;
+
+    node.allocated_cpu_cores + cpu_cores as i64 <= cpu_limit
+        && node.allocated_ram_gb + ram_gb as i64 <= ram_limit
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(cpu_overcommit: Option<f64>, ram_overcommit: Option<f64>) -> NodeCapacity {
+        NodeCapacity {
+            name: "pve".to_owned(),
+            cpu_cores: 8,
+            ram_gb: 32,
+            cpu_overcommit,
+            ram_overcommit,
+            allocated_cpu_cores: 28,
+            allocated_ram_gb: 24,
+        }
+    }
+
+    #[test]
+    fn has_capacity_applies_default_ratios() {
+        // Arrange
+        let policy = PlacementEnv::default();
+        let node = node(None, None);
+
+        // Act & Assert
+        assert!(has_capacity(&node, &policy, 4, 8));
+        assert!(!has_capacity(&node, &policy, 5, 8));
+        assert!(!has_capacity(&node, &policy, 4, 9));
+    }
+
+    #[test]
+    fn has_capacity_prefers_node_overrides() {
+        // Arrange
+        let policy = PlacementEnv::default();
+        let node = node(Some(2.0), Some(1.5));
+
+        // Act & Assert
+        assert!(!has_capacity(&node, &policy, 1, 1));
+        assert!(has_capacity(
+            &NodeCapacity {
+                allocated_cpu_cores: 12,
+                ..node.clone()
+            },
+            &policy,
+            4,
+            24
+        ));
+    }
+}
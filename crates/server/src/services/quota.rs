@@ -0,0 +1,309 @@
+use crate::config::QuotaEnv;
+use crate::model::queries;
+use crate::model::types::{ApiQuota, ApiQuotas, QuotaLimits, QuotaUsage};
+use crate::web::types::NewServerPayload;
+use dashboard_common::prelude::{Error, Result};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Returns the usage against limits of a user's account and of every limited
+/// product.
+///
+/// # Arguments
+///
+/// * `connection`: Database connection.
+/// * `defaults`: Account quota used when the account has no quota of its own.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Account quota together with the product quotas.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(connection, defaults))]
+pub async fn get_quotas(
+    connection: &mut PgConnection,
+    defaults: &QuotaEnv,
+    user_id: Uuid,
+) -> Result<ApiQuotas> {
+    let limits = account_limits(connection, defaults, user_id).await?;
+    let usage = queries::get_quota_usage(&mut *connection, user_id, None).await?;
+    let account = ApiQuota::new(&limits, &usage);
+
+    let mut products = Vec::new();
+    for quota in queries::get_product_quotas(&mut *connection).await? {
+        let usage =
+            queries::get_quota_usage(&mut *connection, user_id, Some(quota.product_id)).await?;
+        products.push(ApiQuota::for_product(&quota, &usage));
+    }
+
+    Ok(ApiQuotas { account, products })
+}
+
+/// Checks that a new server fits into both the account quota and the quota of
+/// its product.
+///
+/// # Arguments
+///
+/// * `connection`: Database connection.
+/// * `defaults`: Account quota used when the account has no quota of its own.
+/// * `user_id`: ID of the user who requests the server.
+/// * `payload`: Specifications for the new server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the server fits, `Error::Quota` naming the exceeded limit
+/// otherwise.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(connection, defaults))]
+pub async fn ensure_quota(
+    connection: &mut PgConnection,
+    defaults: &QuotaEnv,
+    user_id: Uuid,
+    payload: &NewServerPayload,
+) -> Result<()> {
+    let requested = QuotaUsage {
+        servers: 1,
+        cpu_cores: payload.cpu_cores.unwrap_or(2) as i64,
+        ram_gb: payload.ram_gb.unwrap_or(2) as i64,
+        ips: 1,
+    };
+
+    ensure_resources(
+        connection,
+        defaults,
+        user_id,
+        payload.product_id,
+        &requested,
+    )
+    .await
+}
+
+/// Checks that one more IP address for an existing server fits into both the
+/// account quota and the quota of the server's product.
+///
+/// # Arguments
+///
+/// * `connection`: Database connection.
+/// * `defaults`: Account quota used when the account has no quota of its own.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the address fits, `Error::Quota` otherwise.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(connection, defaults))]
+pub async fn ensure_ip_quota(
+    connection: &mut PgConnection,
+    defaults: &QuotaEnv,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<()> {
+    let requested = QuotaUsage {
+        ips: 1,
+        ..QuotaUsage::default()
+    };
+    let product_id = queries::get_server_product_id(&mut *connection, server_id).await?;
+
+    ensure_resources(connection, defaults, user_id, product_id, &requested).await
+}
+
+/// Checks that a server transferred from another account fits into both the
+/// account quota of the recipient and the quota of the server's product.
+///
+/// # Arguments
+///
+/// * `connection`: Database connection.
+/// * `defaults`: Account quota used when the account has no quota of its own.
+/// * `user_id`: ID of the user who receives the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the server fits, `Error::Quota` otherwise.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(connection, defaults))]
+pub async fn ensure_transfer_quota(
+    connection: &mut PgConnection,
+    defaults: &QuotaEnv,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<()> {
+    let requested = queries::get_server_quota_usage(&mut *connection, server_id).await?;
+    let product_id = queries::get_server_product_id(&mut *connection, server_id).await?;
+
+    ensure_resources(connection, defaults, user_id, product_id, &requested).await
+}
+
+/// Compares the current usage increased by the requested resources against
+/// the limits. Resources that are not requested are not checked.
+///
+/// # Arguments
+///
+/// * `scope`: Name of the quota used in the error message.
+/// * `limits`: Quota limits.
+/// * `usage`: Resources already in use.
+/// * `requested`: Resources requested on top of the usage.
+///
+/// # Returns
+///
+/// `Error::Quota` for the first exceeded limit.
+///
+pub fn check(
+    scope: &str,
+    limits: &QuotaLimits,
+    usage: &QuotaUsage,
+    requested: &QuotaUsage,
+) -> Result<()> {
+    let resources = [
+        (
+            "servers",
+            limits.max_servers,
+            usage.servers,
+            requested.servers,
+        ),
+        (
+            "vCPU",
+            limits.max_cpu_cores,
+            usage.cpu_cores,
+            requested.cpu_cores,
+        ),
+        ("GB RAM", limits.max_ram_gb, usage.ram_gb, requested.ram_gb),
+        ("IPs", limits.max_ips, usage.ips, requested.ips),
+    ];
+
+    for (name, limit, used, requested) in resources {
+        if requested > 0
+            && let Some(limit) = limit
+            && used + requested > limit as i64
+        {
+            return Err(Error::Quota(format!(
+                "{scope} quota exceeded: {requested} {name} requested, {used} of {limit} in use"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+
+/// Checks the requested resources against the account quota and the quota of
+/// the product.
+///
+async fn ensure_resources(
+    connection: &mut PgConnection,
+    defaults: &QuotaEnv,
+    user_id: Uuid,
+    product_id: Uuid,
+    requested: &QuotaUsage,
+) -> Result<()> {
+    let limits = account_limits(connection, defaults, user_id).await?;
+    let usage = queries::get_quota_usage(&mut *connection, user_id, None).await?;
+    check("Account", &limits, &usage, requested)?;
+
+    if let Some(quota) = queries::get_product_quota(&mut *connection, product_id).await? {
+        let usage =
+            queries::get_quota_usage(&mut *connection, user_id, Some(quota.product_id)).await?;
+        check(
+            &format!("Product {}", quota.product_name),
+            &quota.limits,
+            &usage,
+            requested,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the quota of the account, falling back to the configured defaults.
+///
+async fn account_limits(
+    connection: &mut PgConnection,
+    defaults: &QuotaEnv,
+    user_id: Uuid,
+) -> Result<QuotaLimits> {
+    let limits = queries::get_account_quota(&mut *connection, user_id)
+        .await?
+        .unwrap_or_else(|| QuotaLimits {
+            max_servers: defaults.max_servers,
+            max_cpu_cores: defaults.max_cpu_cores,
+            max_ram_gb: defaults.max_ram_gb,
+            max_ips: defaults.max_ips,
+        });
+
+    Ok(limits)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> QuotaLimits {
+        QuotaLimits {
+            max_servers: Some(3),
+            max_cpu_cores: Some(8),
+            max_ram_gb: None,
+            max_ips: Some(3),
+        }
+    }
+
+    fn usage(servers: i64, cpu_cores: i64, ram_gb: i64) -> QuotaUsage {
+        QuotaUsage {
+            servers,
+            cpu_cores,
+            ram_gb,
+            ips: servers,
+        }
+    }
+
+    #[test]
+    fn check_allows_usage_up_to_limits() {
+        // Act
+        let result = check("Account", &limits(), &usage(2, 4, 100), &usage(1, 4, 100));
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn check_reports_exceeded_limit() {
+        // Act
+        let result = check("Account", &limits(), &usage(1, 6, 0), &usage(1, 4, 4));
+
+        // Assert
+        let Err(Error::Quota(message)) = result else {
+            panic!("Quota error expected");
+        };
+        assert_eq!(
+            message,
+            "Account quota exceeded: 4 vCPU requested, 6 of 8 in use"
+        );
+    }
+
+    #[test]
+    fn check_ignores_resources_not_requested() {
+        // Arrange
+        let requested = QuotaUsage {
+            ips: 1,
+            ..QuotaUsage::default()
+        };
+
+        // Act
+        let result = check("Account", &limits(), &usage(2, 10, 0), &requested);
+
+        // Assert
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn check_rejects_servers_over_limit() {
+        // Act
+        let result = check("Product VPS", &limits(), &usage(3, 0, 0), &usage(1, 1, 1));
+
+        // Assert
+        assert!(matches!(result, Err(Error::Quota(_))));
+    }
+}
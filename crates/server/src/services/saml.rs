@@ -0,0 +1,587 @@
+use crate::config::SamlEnv;
+use crate::model::queries;
+use crate::model::types::{ApiOrganization, ApiSamlSettings, OrganizationRole};
+use crate::services::allowlist;
+use crate::state::AppState;
+use crate::web::types::{SamlResponseForm, SamlSettingsPayload, SsoAuthorization};
+use chrono::{DateTime, Duration, Utc};
+use dashboard_common::prelude::{AuthError, Error, Result};
+use samael::metadata::{EntityDescriptor, HTTP_REDIRECT_BINDING};
+use samael::schema::Assertion;
+use samael::service_provider::{ServiceProvider, ServiceProviderBuilder};
+use sqlx::PgPool;
+use std::fmt::Debug;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// NameID format of email addresses, requested from the identity providers.
+const EMAIL_NAME_ID_FORMAT: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+
+/// User identified by a verified SAML assertion.
+///
+/// # Fields
+///
+/// * `email`: Email address of the user.
+/// * `role`: Role mapped from the role attribute, `None` without one.
+///
+#[derive(Debug)]
+struct SamlIdentity {
+    email: String,
+    role: Option<OrganizationRole>,
+}
+
+/// Returns the SAML single sign-on of an organization the user owns.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of an owner.
+/// * `organization_id`: ID of the organization.
+///
+/// # Returns
+///
+/// Settings of the single sign-on.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_settings(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<ApiSamlSettings> {
+    ensure_owner(app_state, user_id, organization_id).await?;
+
+    find_settings(app_state, organization_id).await
+}
+
+/// Sets up or changes the SAML single sign-on of an organization the user
+/// owns. The metadata of the identity provider must offer the redirect
+/// binding, which the logins are started with.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of an owner.
+/// * `organization_id`: ID of the organization.
+/// * `payload`: Settings of the single sign-on.
+///
+/// # Returns
+///
+/// Stored settings of the single sign-on.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, payload))]
+pub async fn set_settings(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+    payload: SamlSettingsPayload,
+) -> Result<ApiSamlSettings> {
+    let organization = ensure_owner(app_state, user_id, organization_id).await?;
+    if organization.personal {
+        return Err(Error::Validation(
+            "Personal organizations have no single sign-on".to_owned(),
+        ));
+    }
+    let sso_url = service_provider(
+        &app_state.config.saml,
+        organization_id,
+        &payload.idp_metadata,
+    )?
+    .sso_binding_location(HTTP_REDIRECT_BINDING);
+    if sso_url.is_none() {
+        return Err(Error::Validation(
+            "Identity provider must support the HTTP-Redirect binding".to_owned(),
+        ));
+    }
+
+    let settings = queries::set_saml_settings(&app_state.pool, organization_id, &payload).await?;
+    tracing::info!(target: "service", %organization_id, enforced = settings.enforced, "Single sign-on set");
+
+    Ok(settings)
+}
+
+/// Removes the SAML single sign-on of an organization the user owns.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of an owner.
+/// * `organization_id`: ID of the organization.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_settings(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<()> {
+    ensure_owner(app_state, user_id, organization_id).await?;
+    if !queries::delete_saml_settings(&app_state.pool, organization_id).await? {
+        return Err(not_configured(organization_id));
+    }
+    tracing::info!(target: "service", %organization_id, "Single sign-on removed");
+
+    Ok(())
+}
+
+/// Builds the metadata of the service provider of an organization, which the
+/// identity provider is set up with.
+///
+/// # Arguments
+///
+/// * `settings`: Settings of the SAML single sign-on.
+/// * `organization_id`: ID of the organization.
+///
+/// # Returns
+///
+/// Metadata XML requesting signed assertions with the email address.
+///
+pub fn metadata(settings: &SamlEnv, organization_id: Uuid) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{entity_id}">
+  <md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <md:NameIDFormat>{EMAIL_NAME_ID_FORMAT}</md:NameIDFormat>
+    <md:AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="{acs_url}" index="0" isDefault="true"/>
+  </md:SPSSODescriptor>
+</md:EntityDescriptor>
+"#,
+        entity_id = escape_xml(&entity_id(settings, organization_id)),
+        acs_url = escape_xml(&acs_url(settings, organization_id)),
+    )
+}
+
+/// Starts a login with the identity provider of an organization. The ID of the
+/// authentication request is stored until the provider responds to it.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `organization_id`: ID of the organization.
+///
+/// # Returns
+///
+/// Page of the identity provider to redirect the user to.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn authorize(app_state: &AppState, organization_id: Uuid) -> Result<SsoAuthorization> {
+    let settings = &app_state.config.saml;
+    let sso = find_settings(app_state, organization_id).await?;
+    let (request_id, url) = authentication_request(settings, &sso)?;
+    queries::add_saml_request(
+        &app_state.pool,
+        &request_id,
+        organization_id,
+        request_start(settings, app_state.clock.now()),
+    )
+    .await?;
+
+    Ok(SsoAuthorization { url })
+}
+
+/// Consumes the assertion the identity provider of an organization posted in
+/// response to a login. The response must be signed by the provider, and
+/// respond to an authentication request of the organization that hasn't been
+/// consumed yet.
+///
+/// The assertion identifies the user by the email address, which the
+/// dashboard must have verified. Only members of the organization log in this
+/// way: the owners choose the identity provider, which could assert the email
+/// address of any other user. Members get the role mapped from the role
+/// attribute, owners keep their role.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `organization_id`: ID of the organization.
+/// * `form`: SAML response and the ID of the request it responds to.
+/// * `client_ip`: Address of the client, if known.
+///
+/// # Returns
+///
+/// ID of the logged in user.
+///
+/// # Errors
+///
+/// `Error::Validation` if the request is unknown or expired,
+/// `AuthError::Login` if the response is invalid,
+/// `Error::Forbidden` if the user is unknown, not a member of the
+/// organization or the address of the client is not allowed.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, form))]
+pub async fn consume(
+    app_state: &AppState,
+    organization_id: Uuid,
+    form: SamlResponseForm,
+    client_ip: Option<IpAddr>,
+) -> Result<Uuid> {
+    let settings = &app_state.config.saml;
+    let sso = find_settings(app_state, organization_id).await?;
+    let since = request_start(settings, app_state.clock.now());
+    if !queries::take_saml_request(&app_state.pool, &form.relay_state, organization_id, since)
+        .await?
+    {
+        return Err(Error::Validation(
+            "Login is expired, start it again".to_owned(),
+        ));
+    }
+    let identity = verify_response(settings, &sso, &form)?;
+
+    let email = identity.email.as_str();
+    let (user_id, verified) = queries::get_user_verification_by_email(&app_state.pool, email)
+        .await?
+        .ok_or_else(|| Error::Forbidden(format!("No account is registered with {email}")))?;
+    if !verified {
+        return Err(Error::Forbidden(format!(
+            "Verify {email} before logging in with single sign-on"
+        )));
+    }
+    update_role(&app_state.pool, organization_id, user_id, identity.role).await?;
+    allowlist::ensure_allowed(app_state, user_id, client_ip).await?;
+
+    Ok(user_id)
+}
+
+/// Rejects the other logins of a member of an organization enforcing single
+/// sign-on. Owners are exempt, so a broken identity provider can't lock the
+/// organization out.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user logging in.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the user may log in otherwise, `Error::Forbidden` if not.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn ensure_not_enforced(app_state: &AppState, user_id: Uuid) -> Result<()> {
+    if let Some(name) = queries::get_enforced_sso(&app_state.pool, user_id).await? {
+        return Err(Error::Forbidden(format!(
+            "Members of {name} must log in with single sign-on"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Maps the values of the role attribute to the role in the organization.
+///
+/// # Arguments
+///
+/// * `values`: Values of the role attribute, like the groups of the user.
+/// * `admin_values`: Values making the member an admin.
+///
+/// # Returns
+///
+/// `OrganizationRole::Admin` if any value is an admin one, `Member` otherwise.
+///
+pub fn role_of(values: &[&str], admin_values: &[String]) -> OrganizationRole {
+    match values
+        .iter()
+        .any(|value| admin_values.iter().any(|admin| admin == value))
+    {
+        true => OrganizationRole::Admin,
+        false => OrganizationRole::Member,
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Changes the role of a member of the organization to the mapped one, and
+/// rejects the users who are not members.
+///
+async fn update_role(
+    pool: &PgPool,
+    organization_id: Uuid,
+    user_id: Uuid,
+    role: Option<OrganizationRole>,
+) -> Result<()> {
+    let current = queries::get_organization(pool, organization_id, user_id)
+        .await?
+        .map(|organization| organization.role)
+        .ok_or_else(|| {
+            Error::Forbidden(format!(
+                "Only members of organization {organization_id} log in with its single sign-on"
+            ))
+        })?;
+    let role = match (current, role) {
+        (OrganizationRole::Owner, _) | (_, None) => return Ok(()),
+        (_, Some(role)) => role,
+    };
+    if current == role {
+        return Ok(());
+    }
+
+    queries::set_organization_member(pool, organization_id, user_id, role).await?;
+    tracing::info!(target: "service", %organization_id, member_id = %user_id, %role, "Member set by single sign-on");
+
+    Ok(())
+}
+
+/// Creates the authentication request of a login, sent with the redirect
+/// binding. The ID of the request is passed as the relay state, so the
+/// response can be matched to it.
+///
+/// # Returns
+///
+/// ID of the request and the URL it is sent with.
+///
+fn authentication_request(settings: &SamlEnv, sso: &ApiSamlSettings) -> Result<(String, String)> {
+    let provider = service_provider(settings, sso.organization_id, &sso.idp_metadata)?;
+    let sso_url = provider
+        .sso_binding_location(HTTP_REDIRECT_BINDING)
+        .ok_or_else(|| Error::Any("Identity provider has no redirect binding".to_owned()))?;
+    let request = provider
+        .make_authentication_request(&sso_url)
+        .map_err(|error| Error::Any(format!("Can't create the SAML request: {error}")))?;
+    let url = request
+        .redirect(&request.id)
+        .map_err(|error| Error::Any(format!("Can't encode the SAML request: {error}")))?
+        .ok_or_else(|| Error::Any("SAML request has no destination".to_owned()))?;
+
+    Ok((request.id, url.to_string()))
+}
+
+/// Verifies the signature, the audience, the validity and the request of the
+/// SAML response, and reads the user from its assertion.
+///
+fn verify_response(
+    settings: &SamlEnv,
+    sso: &ApiSamlSettings,
+    form: &SamlResponseForm,
+) -> Result<SamlIdentity> {
+    let provider = service_provider(settings, sso.organization_id, &sso.idp_metadata)?;
+    let assertion = provider
+        .parse_base64_response(&form.saml_response, Some(&[form.relay_state.as_str()]))
+        .map_err(rejected)?;
+
+    let email = match &sso.email_attribute {
+        Some(name) => attribute_values(&assertion, name)
+            .first()
+            .map(|email| email.to_string()),
+        None => assertion
+            .subject
+            .as_ref()
+            .and_then(|subject| subject.name_id.as_ref())
+            .map(|name_id| name_id.value.clone()),
+    }
+    .ok_or_else(|| Error::Forbidden("SAML assertion has no email address".to_owned()))?;
+    let role = sso
+        .role_attribute
+        .as_deref()
+        .map(|name| role_of(&attribute_values(&assertion, name), &sso.admin_values));
+
+    Ok(SamlIdentity { email, role })
+}
+
+/// Returns the values of an attribute of the assertion, matched by its name or
+/// friendly name.
+///
+fn attribute_values<'a>(assertion: &'a Assertion, name: &str) -> Vec<&'a str> {
+    assertion
+        .attribute_statements
+        .iter()
+        .flatten()
+        .flat_map(|statement| &statement.attributes)
+        .filter(|attribute| {
+            attribute.name.as_deref() == Some(name)
+                || attribute.friendly_name.as_deref() == Some(name)
+        })
+        .flat_map(|attribute| &attribute.values)
+        .filter_map(|value| value.value.as_deref())
+        .collect()
+}
+
+/// Creates the service provider of an organization, trusting the keys in the
+/// metadata of its identity provider.
+///
+fn service_provider(
+    settings: &SamlEnv,
+    organization_id: Uuid,
+    idp_metadata: &str,
+) -> Result<ServiceProvider> {
+    let idp_metadata = idp_metadata.parse::<EntityDescriptor>().map_err(|error| {
+        Error::Validation(format!(
+            "Invalid metadata of the identity provider: {error}"
+        ))
+    })?;
+
+    ServiceProviderBuilder::default()
+        .entity_id(entity_id(settings, organization_id))
+        .acs_url(acs_url(settings, organization_id))
+        .idp_metadata(idp_metadata)
+        .allow_idp_initiated(false)
+        .build()
+        .map_err(|error| Error::Any(format!("Invalid SAML service provider: {error}")))
+}
+
+async fn ensure_owner(
+    app_state: &AppState,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<ApiOrganization> {
+    let organization = queries::get_organization(&app_state.pool, organization_id, user_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Organization {organization_id}")))?;
+    if organization.role != OrganizationRole::Owner {
+        return Err(Error::Forbidden(format!(
+            "Single sign-on of organization {organization_id} is managed by its owners"
+        )));
+    }
+
+    Ok(organization)
+}
+
+async fn find_settings(app_state: &AppState, organization_id: Uuid) -> Result<ApiSamlSettings> {
+    queries::get_saml_settings(&app_state.pool, organization_id)
+        .await?
+        .ok_or_else(|| not_configured(organization_id))
+}
+
+fn not_configured(organization_id: Uuid) -> Error {
+    Error::NotFound(format!("Single sign-on of organization {organization_id}"))
+}
+
+/// Returns the entity ID of the service provider of an organization, the URL of
+/// its metadata.
+///
+fn entity_id(settings: &SamlEnv, organization_id: Uuid) -> String {
+    let base_url = settings.base_url.trim_end_matches('/');
+    format!("{base_url}/saml/{organization_id}/metadata")
+}
+
+fn acs_url(settings: &SamlEnv, organization_id: Uuid) -> String {
+    let base_url = settings.base_url.trim_end_matches('/');
+    format!("{base_url}/saml/{organization_id}/acs")
+}
+
+/// Returns the send time of the requests that haven't expired yet.
+///
+fn request_start(settings: &SamlEnv, now: DateTime<Utc>) -> DateTime<Utc> {
+    now - Duration::seconds(settings.request_ttl_sec)
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Logs why the response is rejected, as the client only learns that it
+/// failed.
+///
+fn rejected(reason: impl Debug) -> Error {
+    tracing::warn!(target: "service", ?reason, "SAML response rejected");
+    Error::Auth(AuthError::Login)
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::queries::tests::payload;
+    use crate::model::types::NewUser;
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn only_members_should_log_in(pool: PgPool) {
+        // Arrange
+        let owner = queries::add_new_user(&pool, payload::test_user())
+            .await
+            .unwrap();
+        let member = queries::add_new_user(
+            &pool,
+            NewUser {
+                email: "member@example.com".to_owned(),
+                ..payload::test_user()
+            },
+        )
+        .await
+        .unwrap();
+        let outsider = queries::add_new_user(
+            &pool,
+            NewUser {
+                email: "outsider@example.com".to_owned(),
+                ..payload::test_user()
+            },
+        )
+        .await
+        .unwrap();
+        let mut transaction = pool.begin().await.unwrap();
+        let organization_id = queries::create_organization(&mut transaction, "Acme", owner.id)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+        queries::set_organization_member(
+            &pool,
+            organization_id,
+            member.id,
+            OrganizationRole::Member,
+        )
+        .await
+        .unwrap();
+        let admin = Some(OrganizationRole::Admin);
+
+        // Act
+        let rejected = update_role(&pool, organization_id, outsider.id, admin).await;
+        let promoted = update_role(&pool, organization_id, member.id, admin).await;
+        let owner_kept = update_role(&pool, organization_id, owner.id, admin).await;
+
+        // Assert
+        assert!(matches!(rejected, Err(Error::Forbidden(_))));
+        assert!(promoted.is_ok() && owner_kept.is_ok());
+        let role = |user_id| queries::get_organization(&pool, organization_id, user_id);
+        assert!(role(outsider.id).await.unwrap().is_none());
+        assert_eq!(
+            role(member.id).await.unwrap().unwrap().role,
+            OrganizationRole::Admin
+        );
+        assert_eq!(
+            role(owner.id).await.unwrap().unwrap().role,
+            OrganizationRole::Owner
+        );
+    }
+
+    #[test]
+    fn role_of_should_map_admin_values() {
+        // Arrange
+        let admin_values = ["dashboard-admins".to_owned()];
+
+        // Act
+        let admin = role_of(&["staff", "dashboard-admins"], &admin_values);
+        let member = role_of(&["staff"], &admin_values);
+        let without_values = role_of(&["dashboard-admins"], &[]);
+
+        // Assert
+        assert_eq!(admin, OrganizationRole::Admin);
+        assert_eq!(member, OrganizationRole::Member);
+        assert_eq!(without_values, OrganizationRole::Member);
+    }
+
+    #[test]
+    fn metadata_should_point_to_organization_endpoints() {
+        // Arrange
+        let settings = SamlEnv {
+            base_url: "https://api.example.com/".to_owned(),
+            ..SamlEnv::default()
+        };
+        let organization_id = Uuid::nil();
+
+        // Act
+        let metadata = metadata(&settings, organization_id);
+
+        // Assert
+        assert!(metadata.contains(&format!(
+            r#"entityID="https://api.example.com/saml/{organization_id}/metadata""#
+        )));
+        assert!(metadata.contains(&format!(
+            r#"Location="https://api.example.com/saml/{organization_id}/acs""#
+        )));
+    }
+}
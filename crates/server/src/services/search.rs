@@ -0,0 +1,77 @@
+use crate::model::queries;
+use crate::model::types::{ApiSearchResults, ApiSearchServer, ApiSearchService, ApiSearchUser};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::PgPool;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Shortest searched text, shorter ones would match most of the records.
+const MIN_TERM_LEN: usize = 2;
+
+/// Maximum number of results of every kind.
+const RESULTS_PER_KIND: i64 = 10;
+
+/// Searches users, servers and services at once for the admin's global search.
+///
+/// # Arguments
+///
+/// * `pool`: Database connection pool.
+/// * `query`: Searched text.
+///
+/// # Returns
+///
+/// Results grouped by kind, the best matches first.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(pool))]
+pub async fn search(pool: &PgPool, query: &str) -> Result<ApiSearchResults> {
+    let term = query.trim();
+    if term.chars().count() < MIN_TERM_LEN {
+        return Err(Error::Validation(format!(
+            "Search query must be at least {MIN_TERM_LEN} characters long"
+        )));
+    }
+
+    let id = term.parse::<Uuid>().ok();
+    let vm_id = term.parse::<i32>().ok();
+    let ip_address = term.parse::<IpAddr>().ok().map(|ip| ip.to_string());
+    let hits = queries::search(
+        pool,
+        term,
+        id,
+        vm_id,
+        ip_address.as_deref(),
+        RESULTS_PER_KIND,
+    )
+    .await?;
+
+    let mut results = ApiSearchResults::default();
+    for hit in hits {
+        match hit.kind.as_str() {
+            "user" => results.users.push(ApiSearchUser {
+                id: hit.id,
+                name: hit.name,
+                email: hit.email.unwrap_or_default(),
+            }),
+            "server" => results.servers.push(ApiSearchServer {
+                id: hit.id,
+                host_name: hit.name,
+                status: hit.status.unwrap_or_default().into(),
+                node_name: hit.node_name,
+                vm_id: hit.vm_id,
+                ip_addresses: hit.ip_addresses.unwrap_or_default(),
+            }),
+            "service" => results.services.push(ApiSearchService {
+                id: hit.id,
+                status: hit.status.unwrap_or_default(),
+                product_name: hit.name,
+                user_id: hit.user_id.unwrap_or_default(),
+                email: hit.email.unwrap_or_default(),
+                server_id: hit.server_id.unwrap_or_default(),
+                host_name: hit.host_name.unwrap_or_default(),
+            }),
+            kind => tracing::warn!(target: "service", kind, "Unknown search result skipped"),
+        }
+    }
+
+    Ok(results)
+}
@@ -1,17 +1,27 @@
+use crate::clock::Clock;
+use crate::config::{PlacementEnv, QuotaEnv};
+use crate::model::cache::Catalog;
 use crate::model::queries;
-use crate::model::types::{ServerStatus, ServiceStatus};
+use crate::model::types::{
+    DomainEventKind, IpConfig, ProvisioningStep, ProvisioningStepStatus, ProvisioningTarget,
+    ServerStatus, ServiceStatus, TemplateKind,
+};
 use crate::proxmox::Proxmox;
 use crate::proxmox::types::{TaskRef, VmConfig, VmRef};
-use crate::services;
+use crate::services::{self, Polling, event, marketplace};
 use crate::state::AppState;
 use crate::web::types::NewServerPayload;
-use dashboard_common::prelude::Result;
-use sqlx::PgTransaction;
+use chrono::{DateTime, Duration, Utc};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::{Executor, PgPool, PgTransaction, Postgres};
 use std::sync::Arc;
 use uuid::Uuid;
 
 /// Public entry point for the new server setup background task.
 ///
+/// Reserves the database records of the server in a single transaction, then
+/// provisions it on Proxmox step by step.
+///
 /// # Arguments
 ///
 /// * `app_state`: Shared application state.
@@ -25,38 +35,258 @@ pub async fn run(app_state: AppState, user_id: Uuid, payload: NewServerPayload)
         return;
     };
 
-    let result = create_server(&app_state.proxmox, &mut transaction, user_id, &payload).await;
+    let result = reserve_server(
+        &app_state.config.placement,
+        &app_state.config.quota,
+        &app_state.catalog,
+        &mut transaction,
+        user_id,
+        &payload,
+    )
+    .await;
+
+    let server_id = match result {
+        Ok(server_id) => server_id,
+        Err(error) => {
+            services::finalize_transaction(&Err(error), transaction).await;
+            let data = serde_json::json!({
+                "host_name": payload.host_name,
+                "product_id": payload.product_id,
+            });
+            publish_failure(&app_state.pool, user_id, data).await;
+            return;
+        }
+    };
+
+    if let Err(error) = transaction.commit().await {
+        tracing::error!(target: "service", ?error, "Failed to commit transaction!");
+        return;
+    }
 
-    services::finalize_transaction(&result, transaction).await;
+    provision(app_state, user_id, server_id).await;
 }
 
-/// Creates all initial database records for a new server within a transaction.
-/// and orchestrates the Proxmox-side setup for a new VM, including cloning and
-/// configuration.
+/// Public entry point for the provisioning background task. Runs every step
+/// that is not completed yet, stopping at the first failure. The owner is
+/// notified once the server is ready.
+///
+/// A failed step is marked as such and the server as failed, the completed
+/// steps are kept, so a retry resumes from the failed step. Once the step has
+/// failed the configured number of times, or with an error a retry can't fix,
+/// the provisioning is rolled back.
 ///
 /// # Arguments
 ///
-/// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the provisioned server.
+///
+pub async fn provision(app_state: AppState, user_id: Uuid, server_id: Uuid) {
+    let Err((step, error)) = run_steps(&app_state, user_id, server_id).await else {
+        tracing::info!(target: "service", %server_id, "Proxmox VM setup finished successfully");
+        return;
+    };
+    tracing::error!(target: "service", %server_id, ?step, ?error, "Provisioning failed!");
+
+    fail(&app_state, user_id, server_id, step, &error).await;
+}
+
+/// Moves the failed provisioning step of a server back to pending, so the
+/// provisioning can be resumed from it.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the provisioning can be resumed, `Error::Conflict` if an
+/// operation is in progress on the server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn retry(app_state: &AppState, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    let mut transaction = app_state.pool.begin().await?;
+
+    services::ensure_idle(&mut transaction, user_id, server_id).await?;
+
+    if !queries::reset_failed_provisioning_step(transaction.as_mut(), user_id, server_id).await? {
+        return Err(Error::Validation(format!(
+            "Server {server_id} has no failed provisioning step"
+        )));
+    }
+    queries::update_server_status(transaction.as_mut(), server_id, ServerStatus::SettingUp).await?;
+
+    transaction.commit().await?;
+    tracing::info!(target: "service", %server_id, "Provisioning reset for retry");
+    Ok(())
+}
+
+/// Recovers the provisionings whose job crashed and rolls back the ones never
+/// retried.
+///
+/// A step still running after `provisioning.step_timeout_min` was interrupted
+/// with its job, so it is marked as failed like a step that failed by itself:
+/// the provisioning can be retried from it, or is rolled back if the step
+/// used all its attempts. A provisioning that made no progress for
+/// `provisioning.stale_after_min` is rolled back. A rollback that fails
+/// doesn't stop the others and is tried again on the next run.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Number of recovered and rolled back provisionings.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn sweep_stale(app_state: &AppState, now: DateTime<Utc>) -> Result<usize> {
+    let step_timeout = Duration::minutes(app_state.config.provisioning.step_timeout_min);
+    let error = Error::Any("Provisioning step interrupted".to_owned());
+    let interrupted = queries::fail_interrupted_provisioning_steps(
+        &app_state.pool,
+        now - step_timeout,
+        &error.to_string(),
+    )
+    .await?;
+
+    let mut count = 0;
+    for (user_id, server_id, step) in interrupted {
+        tracing::warn!(target: "service", %server_id, ?step, "Provisioning step interrupted");
+        fail(app_state, user_id, server_id, Some(step), &error).await;
+        count += 1;
+    }
+
+    let stale_after = Duration::minutes(app_state.config.provisioning.stale_after_min);
+    let stale = queries::get_stale_provisionings(&app_state.pool, now - stale_after).await?;
+    for (user_id, server_id) in stale {
+        tracing::warn!(target: "service", %server_id, "Provisioning stalled, rolling it back");
+        let result = compensate(
+            &app_state.proxmox,
+            &app_state.clock,
+            &app_state.pool,
+            server_id,
+        )
+        .await;
+        if let Err(error) = result {
+            tracing::error!(target: "service", %server_id, ?error, "Failed to roll back provisioning!");
+            continue;
+        }
+
+        let data = serde_json::json!({
+            "server_id": server_id,
+            "step": None::<ProvisioningStep>,
+            "error": "Provisioning stalled",
+            "rolled_back": true,
+        });
+        publish_failure(&app_state.pool, user_id, data).await;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Normalizes a host name to lowercase without surrounding whitespace and the
+/// trailing dot, then validates it as a DNS name: at most 253 characters of
+/// dot-separated labels, each of 1 to 63 ASCII letters, digits and hyphens,
+/// neither starting nor ending with a hyphen.
+///
+/// # Arguments
+///
+/// * `host_name`: Host name requested by the user.
+///
+/// # Returns
+///
+/// Normalized host name.
+///
+pub fn normalize_host_name(host_name: &str) -> Result<String> {
+    let host_name = host_name.trim().trim_end_matches('.').to_ascii_lowercase();
+
+    let valid_label = |label: &str| {
+        (1..=63).contains(&label.len())
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-')
+    };
+    if host_name.len() > 253 || !host_name.split('.').all(valid_label) {
+        return Err(Error::Validation(format!(
+            "Host name {host_name} is not a valid DNS name"
+        )));
+    }
+
+    Ok(host_name)
+}
+
+/// Ensures that no other server has the host name, ignoring the case.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `host_name`: Normalized host name.
+///
+/// # Returns
+///
+/// Empty `Ok(())` if the host name is available, `Error::Conflict` otherwise.
+///
+pub async fn ensure_host_name_available<'e, E>(executor: E, host_name: &str) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    match queries::host_name_exists(executor, host_name).await? {
+        true => Err(Error::Conflict(format!(
+            "Host name {host_name} is already in use"
+        ))),
+        false => Ok(()),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Creates all initial database records for a new server within a transaction,
+/// together with its pending provisioning steps.
+///
+/// # Arguments
+///
+/// * `placement`: Overcommit policy used to check the node capacity.
+/// * `quota`: Default account quota.
+/// * `catalog`: Cache of the product catalog.
 /// * `transaction`: Active database transaction.
 /// * `user_id`: ID of the user who owns the server.
 /// * `payload`: Specifications for the new server.
 ///
 /// # Returns
 ///
-/// An empty `Result` on success.
+/// ID of the reserved server.
 ///
-async fn create_server(
-    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+async fn reserve_server(
+    placement: &PlacementEnv,
+    quota: &QuotaEnv,
+    catalog: &Catalog,
     transaction: &mut PgTransaction<'_>,
     user_id: Uuid,
     payload: &NewServerPayload,
-) -> Result<()> {
+) -> Result<Uuid> {
+    // Check quotas, the user stays locked until the server records are saved.
+    queries::lock_user(transaction, user_id).await?;
+    services::user::ensure_verified(transaction.as_mut(), user_id).await?;
+    services::quota::ensure_quota(transaction.as_mut(), quota, user_id, payload).await?;
+    tracing::info!(target: "service", "Quota confirmed");
+
     // Create initial server.
 
     let server_id = queries::create_server_record(transaction, &payload.host_name).await?;
     tracing::info!(target: "service", %server_id, "Initial server record created");
 
-    let template_id = queries::find_template_id(transaction, &payload.os).await?;
+    let template_id = match &payload.app {
+        Some(app) => catalog.template_id(transaction, app, TemplateKind::Application),
+        None => catalog.template_id(transaction, &payload.os, TemplateKind::Os),
+    }
+    .await?;
     let service_id =
         queries::create_service_record(transaction, user_id, server_id, template_id, payload)
             .await?;
@@ -66,39 +296,454 @@ async fn create_server(
     queries::save_custom_values(transaction, service_id, payload).await?;
     tracing::info!(target: "service", "Custom field and configurable option records created");
 
-    let ip_config =
-        queries::reserve_ip_for_server(transaction, server_id, &payload.datacenter).await?;
-    let vm_config = VmConfig::new(ip_config.form()?, payload.cpu_cores, payload.ram_gb);
-    tracing::info!(target: "service", %server_id, %service_id, "IP and VM config created");
+    if !payload.tags.is_empty() {
+        queries::set_server_tags(transaction, server_id, &payload.tags).await?;
+    }
 
-    // Setup service.
+    services::catalog::ensure_datacenter_available(
+        transaction.as_mut(),
+        payload.product_id,
+        &payload.datacenter,
+    )
+    .await?;
+    queries::reserve_ip_for_server(transaction, server_id, &payload.datacenter).await?;
+    tracing::info!(target: "service", %server_id, %service_id, "IP reserved");
 
     let template_vm: VmRef = queries::find_template(transaction, service_id).await?;
     tracing::info!(target: "service", template_vmid = %template_vm.id, "Found VM template");
 
+    services::placement::ensure_capacity(
+        transaction.as_mut(),
+        placement,
+        &template_vm.node,
+        payload.cpu_cores.unwrap_or(2),
+        payload.ram_gb.unwrap_or(2),
+    )
+    .await?;
+    tracing::info!(target: "service", node = %template_vm.node, "Node capacity confirmed");
+
+    queries::create_provisioning_steps(transaction.as_mut(), server_id).await?;
+
+    Ok(server_id)
+}
+
+/// Runs the steps that are not completed yet in order, persisting the progress
+/// of each one.
+///
+/// # Returns
+///
+/// The failed step together with the error on failure.
+///
+async fn run_steps(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> std::result::Result<(), (Option<ProvisioningStep>, Error)> {
+    let pool = &app_state.pool;
+    let steps = queries::get_provisioning_steps(pool, user_id, server_id)
+        .await
+        .map_err(|error| (None, error))?;
+
+    for step in steps
+        .into_iter()
+        .filter(|step| step.status != ProvisioningStepStatus::Completed)
+        .map(|step| step.step)
+    {
+        let result = run_step(app_state, server_id, step).await;
+        let (status, error) = match &result {
+            Ok(_) => (ProvisioningStepStatus::Completed, None),
+            Err(error) => (ProvisioningStepStatus::Failed, Some(error.to_string())),
+        };
+        queries::finish_provisioning_step(pool, server_id, step, status, error.as_deref())
+            .await
+            .and(result)
+            .map_err(|error| (Some(step), error))?;
+        tracing::info!(target: "service", %server_id, ?step, "Provisioning step completed");
+    }
+
+    Ok(())
+}
+
+/// Starts a single provisioning step and runs it.
+///
+async fn run_step(app_state: &AppState, server_id: Uuid, step: ProvisioningStep) -> Result<()> {
+    let pool = &app_state.pool;
+    queries::start_provisioning_step(pool, server_id, step).await?;
+    let target = queries::get_provisioning_target(pool, server_id).await?;
+
+    match step {
+        ProvisioningStep::CloneVm => {
+            clone_vm(&app_state.proxmox, &app_state.clock, pool, &target).await
+        }
+        ProvisioningStep::ConfigureVm => {
+            configure_vm(&app_state.proxmox, &app_state.clock, pool, &target).await
+        }
+        ProvisioningStep::InstallApp => {
+            marketplace::install_app(&app_state.proxmox, &app_state.clock, &target).await
+        }
+        ProvisioningStep::Activate => activate(pool, &target).await,
+    }
+}
+
+/// Clones the VM from the template and saves it to the server. A retry follows
+/// the clone task of the previous attempt, and clones again only if that task
+/// didn't succeed, deleting the half-created VM first.
+///
+async fn clone_vm(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    pool: &PgPool,
+    target: &ProvisioningTarget,
+) -> Result<()> {
+    let server_id = target.server_id;
+    let step = ProvisioningStep::CloneVm;
+
+    let previous_task = queries::get_provisioning_task(pool, server_id, step).await?;
+    if let (Some(upid), Some(node)) = (previous_task, &target.node_name) {
+        let clone_task = TaskRef::new(node, &upid.as_str().into());
+        match services::wait_until_finish(proxmox_client, clock, clone_task, Polling::CLONE).await {
+            Ok(_) => {
+                tracing::info!(target: "service", vm_id = ?target.vm_id, "Proxmox VM already cloned");
+                return Ok(());
+            }
+            Err(error) => {
+                tracing::warn!(target: "service", ?error, "Previous Proxmox clone failed, cloning again")
+            }
+        }
+
+        if let Some(vm_id) = target.vm_id {
+            delete_vm(proxmox_client, clock, VmRef::new(node, vm_id)).await?;
+            queries::clear_server_vm(pool, server_id).await?;
+        }
+    }
+
     // Clone new Proxmox server.
-    let (new_vmid, clone_upid) = proxmox_client.create(template_vm.clone()).await?;
+    let template_vm = VmRef::new(&target.template_node, target.template_vmid);
+    let (new_vmid, clone_upid) = proxmox_client
+        .create(template_vm.clone(), target.storage.as_deref())
+        .await?;
     tracing::info!(target: "service", upid = ?clone_upid, "Proxmox clone task started");
-    let clone_task = TaskRef::new(&template_vm.node, &clone_upid);
-    services::wait_until_finish(proxmox_client, clone_task, 1, None).await?;
-    tracing::info!(target: "service", %new_vmid, "Proxmox VM cloned");
 
-    // Save vmid to the database.
+    // Save vmid and the task to the database.
+    let mut transaction = pool.begin().await?;
     let new_vm = VmRef::new(&template_vm.node, new_vmid);
-    queries::update_initial_server(transaction, server_id, new_vm.clone()).await?;
+    queries::update_initial_server(&mut transaction, server_id, new_vm).await?;
+    queries::set_provisioning_task(
+        transaction.as_mut(),
+        server_id,
+        step,
+        &clone_upid.clone().into_inner(),
+    )
+    .await?;
+    transaction.commit().await?;
     tracing::info!(target: "service", "Server record updated");
 
-    // Setup configuration (IP, CPU, RAM).
+    let clone_task = TaskRef::new(&template_vm.node, &clone_upid);
+    services::wait_with_progress(proxmox_client, clock, clone_task, Polling::CLONE, |progress| {
+        tracing::debug!(target: "service", attempt = progress.attempt, elapsed = ?progress.elapsed, "Proxmox clone in progress");
+    })
+    .await?;
+    tracing::info!(target: "service", %new_vmid, "Proxmox VM cloned");
+
+    Ok(())
+}
+
+/// Applies the IP, CPU, RAM, network rate and the cloud-init snippet of the
+/// application to the cloned VM.
+///
+async fn configure_vm(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    pool: &PgPool,
+    target: &ProvisioningTarget,
+) -> Result<()> {
+    let server_id = target.server_id;
+    let (Some(node), Some(vm_id)) = (&target.node_name, target.vm_id) else {
+        return Err(Error::NotReady(format!("Server: {server_id}")));
+    };
+    let new_vm = VmRef::new(node, vm_id);
+
+    let ip_config = IpConfig {
+        ip_address: target.ip_address.clone(),
+        gateway: target.gateway.clone(),
+        subnet_mask: target.subnet_mask.clone(),
+    };
+    let mut builder = VmConfig::builder().ipconfig(0, ip_config.form()?);
+    if let Some(cores) = target.cpu_cores {
+        builder = builder.cores(cores);
+    }
+    if let Some(ram_gb) = target.ram_gb {
+        builder = builder.memory(ram_gb * 1024);
+    }
+    if let Some(snippet) = &target.cloud_init_snippet {
+        builder = builder.cicustom(format!("vendor={snippet}"));
+    }
+
+    // Limit network bandwidth, keeping the cloned device (MAC, bridge) as is.
+    if let Some(rate) = target.net_rate_mbps {
+        let current = proxmox_client.vm_current_config(new_vm.clone()).await?;
+        match current.net0 {
+            Some(net0) => builder = builder.net(0, VmConfig::net_with_rate(&net0, rate)),
+            None => {
+                tracing::warn!(target: "service", %vm_id, "VM has no network device to limit")
+            }
+        }
+    }
+
+    // Setup configuration (IP, CPU, RAM, network rate, cloud-init snippet).
+    let vm_config = builder.build();
+    let applied_rate = target
+        .net_rate_mbps
+        .filter(|_| vm_config.get("net0").is_some());
     let config_upid = proxmox_client.vm_config(new_vm, vm_config).await?;
     tracing::info!(%server_id, upid = ?config_upid, "Proxmox config task started");
+    queries::set_provisioning_task(
+        pool,
+        server_id,
+        ProvisioningStep::ConfigureVm,
+        &config_upid.clone().into_inner(),
+    )
+    .await?;
+
+    let config_task = TaskRef::new(node, &config_upid);
+    services::wait_until_finish(proxmox_client, clock, config_task, Polling::CONFIG).await?;
+    tracing::info!(%server_id, %vm_id, "VM configuration applied");
+
+    let mut transaction = pool.begin().await?;
+    queries::update_server_net_rate(&mut transaction, server_id, applied_rate).await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Marks the server and its service as ready and announces the new server. A
+/// server that got its application installed was started for it and is left
+/// running.
+///
+async fn activate(pool: &PgPool, target: &ProvisioningTarget) -> Result<()> {
+    let mut transaction = pool.begin().await?;
+
+    let server_id = target.server_id;
+    let status = match target.post_install_script {
+        Some(_) => ServerStatus::Running,
+        None => ServerStatus::Stopped,
+    };
+    queries::update_server_status(transaction.as_mut(), server_id, status).await?;
+    queries::update_service_status(
+        transaction.as_mut(),
+        target.service_id,
+        ServiceStatus::Active,
+    )
+    .await?;
+
+    let data = serde_json::json!({
+        "server_id": server_id,
+        "service_id": target.service_id,
+        "host_name": target.host_name,
+    });
+    event::record(
+        transaction.as_mut(),
+        target.user_id,
+        DomainEventKind::ServerCreated,
+        data,
+    )
+    .await?;
+    event::record_status(transaction.as_mut(), target.user_id, server_id, status).await?;
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Rolls the provisioning back once it failed for good: its failed step has
+/// used all the allowed attempts, or the error can't be fixed by a retry.
+///
+/// # Returns
+///
+/// `true` if the provisioning was rolled back.
+///
+async fn roll_back_if_terminal(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    error: &Error,
+) -> Result<bool> {
+    let max_attempts = app_state.config.provisioning.max_attempts;
+    let steps = queries::get_provisioning_steps(&app_state.pool, user_id, server_id).await?;
+    let exhausted = steps
+        .iter()
+        .any(|step| step.status == ProvisioningStepStatus::Failed && step.attempts >= max_attempts);
+    if !exhausted && !is_permanent(error) {
+        return Ok(false);
+    }
 
-    let config_task = TaskRef::new(&template_vm.node, &config_upid);
-    services::wait_until_finish(proxmox_client, config_task, 1, None).await?;
-    tracing::info!(%server_id, %new_vmid, "VM configuration applied");
+    compensate(
+        &app_state.proxmox,
+        &app_state.clock,
+        &app_state.pool,
+        server_id,
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Undoes a provisioning that failed for good. Deletes the half-created VM, so
+/// its VMID is not left consumed on Proxmox, then releases the reserved IP
+/// addresses together with the server records.
+///
+/// # Arguments
+///
+/// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `clock`: Clock waiting for the Proxmox task.
+/// * `pool`: Database connection pool.
+/// * `server_id`: ID of the server to roll back.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(proxmox_client, clock, pool))]
+async fn compensate(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    pool: &PgPool,
+    server_id: Uuid,
+) -> Result<()> {
+    let target = queries::get_provisioning_target(pool, server_id).await?;
+
+    // The VM is forgotten right after its deletion, so a repeated rollback
+    // doesn't try to delete it again.
+    if let (Some(node), Some(vm_id)) = (&target.node_name, target.vm_id) {
+        delete_vm(proxmox_client, clock, VmRef::new(node, vm_id)).await?;
+        queries::clear_server_vm(pool, server_id).await?;
+    }
+
+    let mut transaction = pool.begin().await?;
+    queries::delete_server_record(&mut transaction, server_id).await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %server_id, "Provisioning rolled back");
+
+    Ok(())
+}
+
+/// Tells whether a provisioning error would fail every retry the same way, as
+/// it comes from the order or the catalog rather than from Proxmox.
+///
+fn is_permanent(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Validation(_)
+            | Error::NotFound(_)
+            | Error::NotSupported(_)
+            | Error::Capacity(_)
+            | Error::Quota(_)
+            | Error::Forbidden(_)
+    )
+}
+
+/// Deletes a half-created VM and waits until Proxmox finishes.
+///
+async fn delete_vm(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    clock: &Arc<dyn Clock + Send + Sync>,
+    vm: VmRef,
+) -> Result<()> {
+    let upid = proxmox_client.delete(vm.clone()).await?;
+    tracing::debug!(target: "service", upid = ?upid, "Proxmox delete task started");
+
+    let task = TaskRef::new(&vm.node, &upid);
+    services::wait_until_finish(proxmox_client, clock, task, Polling::DELETE).await?;
+    tracing::info!(target: "service", vm_id = %vm.id, "Half-created Proxmox VM deleted");
+
+    Ok(())
+}
+
+/// Handles a provisioning that failed at the step: marks the server as failed,
+/// rolls the provisioning back if it failed for good and announces the
+/// failure.
+///
+async fn fail(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    step: Option<ProvisioningStep>,
+    error: &Error,
+) {
+    if let Err(error) = mark_failed(&app_state.pool, user_id, server_id).await {
+        tracing::error!(target: "service", ?error, "Failed to update server status!");
+    }
+
+    let rolled_back = roll_back_if_terminal(app_state, user_id, server_id, error)
+        .await
+        .unwrap_or_else(|error| {
+            tracing::error!(target: "service", ?error, "Failed to roll back provisioning!");
+            false
+        });
+
+    let data = serde_json::json!({
+        "server_id": server_id,
+        "step": step,
+        "error": error.to_string(),
+        "rolled_back": rolled_back,
+    });
+    publish_failure(&app_state.pool, user_id, data).await;
+}
 
-    queries::update_server_status(transaction.as_mut(), server_id, ServerStatus::Stopped).await?;
-    queries::update_service_status(transaction.as_mut(), service_id, ServiceStatus::Active).await?;
-    tracing::info!(target: "service", "Proxmox VM setup finished successfully");
+/// Marks the server as failed together with the status change event.
+///
+async fn mark_failed(pool: &PgPool, user_id: Uuid, server_id: Uuid) -> Result<()> {
+    let mut transaction = pool.begin().await?;
+    queries::update_server_status(transaction.as_mut(), server_id, ServerStatus::Failed).await?;
+    event::record_status(
+        transaction.as_mut(),
+        user_id,
+        server_id,
+        ServerStatus::Failed,
+    )
+    .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
+
+/// Announces a failed server setup, only logging if the event can't be
+/// recorded.
+///
+async fn publish_failure(pool: &PgPool, user_id: Uuid, data: serde_json::Value) {
+    if let Err(error) = event::record(pool, user_id, DomainEventKind::ServerFailed, data).await {
+        tracing::error!(target: "service", ?error, "Failed to record domain event!");
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_names_should_be_normalized() {
+        // Arrange
+        let long_label = "a".repeat(64);
+
+        // Act
+        let normalized = normalize_host_name("  Web-01.Example.COM. ");
+        let invalid = [
+            "",
+            "web..example.com",
+            "-web.example.com",
+            "web-.example.com",
+            "web_01.example.com",
+            "wéb.example.com",
+            long_label.as_str(),
+        ]
+        .map(normalize_host_name);
+
+        // Assert
+        assert_eq!(normalized.unwrap(), "web-01.example.com");
+        assert!(
+            invalid
+                .iter()
+                .all(|result| matches!(result, Err(Error::Validation(_))))
+        );
+    }
+}
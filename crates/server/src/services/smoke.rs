@@ -0,0 +1,475 @@
+use crate::clock::{Clock, SystemClock};
+use crate::config::SmokeEnv;
+use crate::proxmox::Proxmox;
+use crate::proxmox::types::{Status, TaskRef, UniqueProcessId, VmConfig, VmRef};
+use crate::services::{self, Polling};
+use dashboard_common::prelude::{Error, Result};
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+/// Interval between status and connectivity polling attempts.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Runs the end-to-end smoke test against a real Proxmox cluster.
+///
+/// Provisions a tiny VM on the designated test node the same way the setup
+/// service does, boots it, verifies its status and connectivity, then tears it
+/// down. Teardown runs even when an earlier step fails, so a failed run doesn't
+/// leave VMs behind.
+///
+/// # Arguments
+///
+/// * `proxmox_client`: Client for interacting with the Proxmox API.
+/// * `smoke`: Smoke test settings.
+///
+/// # Returns
+///
+/// Report with the outcome and timing of every executed step.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(proxmox_client))]
+pub async fn run(proxmox_client: &Arc<dyn Proxmox + Send + Sync>, smoke: &SmokeEnv) -> SmokeReport {
+    let mut report = SmokeReport::default();
+    let timeout = smoke.timeout_sec;
+
+    // Provision.
+    let template = VmRef::new(&smoke.node, smoke.template_vmid);
+    let Some(vm) = report
+        .step("clone", clone(proxmox_client, template, timeout))
+        .await
+    else {
+        return report;
+    };
+    let vm_config = VmConfig::builder()
+        .ipconfig(0, smoke.ip_config.clone())
+        .cores(smoke.cpu_cores)
+        .memory(smoke.ram_gb * 1024)
+        .build();
+    let started = report
+        .step(
+            "configure",
+            run_task(
+                proxmox_client,
+                &vm,
+                timeout,
+                proxmox_client.vm_config(vm.clone(), vm_config),
+            ),
+        )
+        .await
+        .is_some()
+        && report
+            .step(
+                "start",
+                run_task(
+                    proxmox_client,
+                    &vm,
+                    timeout,
+                    proxmox_client.start(vm.clone()),
+                ),
+            )
+            .await
+            .is_some();
+
+    // Verify.
+    if started {
+        let running = report
+            .step("status", wait_for_running(proxmox_client, &vm, timeout))
+            .await
+            .is_some();
+        if running && let Some(address) = smoke.address {
+            report
+                .step("connectivity", wait_for_connection(address, timeout))
+                .await;
+        }
+        report
+            .step(
+                "stop",
+                run_task(
+                    proxmox_client,
+                    &vm,
+                    timeout,
+                    proxmox_client.stop(vm.clone()),
+                ),
+            )
+            .await;
+    }
+
+    // Tear down.
+    report
+        .step(
+            "delete",
+            run_task(
+                proxmox_client,
+                &vm,
+                timeout,
+                proxmox_client.delete(vm.clone()),
+            ),
+        )
+        .await;
+
+    report
+}
+
+// -----------------------------------------------------------------------------
+
+/// Outcome and timings of a smoke test run.
+///
+#[derive(Debug, Default)]
+pub struct SmokeReport {
+    pub steps: Vec<SmokeStep>,
+}
+
+/// Outcome of a single smoke test step.
+///
+/// # Fields
+///
+/// * `name`: Short name of the step.
+/// * `elapsed`: Time the step took.
+/// * `error`: Error message, `None` if the step succeeded.
+///
+#[derive(Debug)]
+pub struct SmokeStep {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+impl SmokeReport {
+    /// Checks whether all executed steps succeeded.
+    ///
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.error.is_none())
+    }
+
+    /// Returns the total time of all executed steps.
+    ///
+    pub fn total(&self) -> Duration {
+        self.steps.iter().map(|step| step.elapsed).sum()
+    }
+
+    /// Executes a single step, recording its outcome and timing.
+    ///
+    async fn step<T>(
+        &mut self,
+        name: &'static str,
+        step: impl Future<Output = Result<T>>,
+    ) -> Option<T> {
+        let start = Instant::now();
+        let result = step.await;
+        let elapsed = start.elapsed();
+
+        match &result {
+            Ok(_) => {
+                tracing::info!(target: "service", step = name, ?elapsed, "Smoke test step passed")
+            }
+            Err(error) => {
+                tracing::error!(target: "service", step = name, ?elapsed, ?error, "Smoke test step failed!")
+            }
+        }
+        self.steps.push(SmokeStep {
+            name,
+            elapsed,
+            error: result.as_ref().err().map(ToString::to_string),
+        });
+
+        result.ok()
+    }
+}
+
+impl Display for SmokeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            let outcome = step.error.as_deref().unwrap_or("ok");
+            writeln!(
+                f,
+                "{:<14}{:>9.2}s  {}",
+                step.name,
+                step.elapsed.as_secs_f32(),
+                outcome
+            )?;
+        }
+        let outcome = if self.passed() { "PASSED" } else { "FAILED" };
+        write!(
+            f,
+            "{:<14}{:>9.2}s  {}",
+            "total",
+            self.total().as_secs_f32(),
+            outcome
+        )
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Clones the template and waits until the clone task finishes.
+///
+async fn clone(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    template: VmRef,
+    timeout: u64,
+) -> Result<VmRef> {
+    let (vm_id, upid) = proxmox_client.create(template.clone(), None).await?;
+    let task = TaskRef::new(&template.node, &upid);
+    let clock: Arc<dyn Clock + Send + Sync> = Arc::new(SystemClock);
+    let polling = Polling::CLONE.with_timeout(timeout);
+    services::wait_until_finish(proxmox_client, &clock, task, polling).await?;
+
+    Ok(VmRef::new(&template.node, vm_id))
+}
+
+/// Waits until the started Proxmox task finishes.
+///
+async fn run_task(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    vm: &VmRef,
+    timeout: u64,
+    task: impl Future<Output = Result<UniqueProcessId>>,
+) -> Result<()> {
+    let upid = task.await?;
+    let task = TaskRef::new(&vm.node, &upid);
+    let clock: Arc<dyn Clock + Send + Sync> = Arc::new(SystemClock);
+    let polling = Polling::POWER.with_timeout(timeout);
+    services::wait_until_finish(proxmox_client, &clock, task, polling).await
+}
+
+/// Polls the VM status until it is reported as running.
+///
+async fn wait_for_running(
+    proxmox_client: &Arc<dyn Proxmox + Send + Sync>,
+    vm: &VmRef,
+    timeout: u64,
+) -> Result<()> {
+    let start = Instant::now();
+    while proxmox_client.vm_status(vm.clone()).await? != Status::Running {
+        if start.elapsed() > Duration::from_secs(timeout) {
+            return Err(Error::Timeout(start.elapsed().as_secs_f32()));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    Ok(())
+}
+
+/// Tries to open a TCP connection to the booted VM until it succeeds.
+///
+async fn wait_for_connection(address: SocketAddr, timeout: u64) -> Result<()> {
+    let start = Instant::now();
+    loop {
+        match tokio::time::timeout(POLL_INTERVAL, TcpStream::connect(address)).await {
+            Ok(Ok(_)) => return Ok(()),
+            _ if start.elapsed() > Duration::from_secs(timeout) => {
+                return Err(Error::Timeout(start.elapsed().as_secs_f32()));
+            }
+            _ => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::types::BackupMode;
+    use crate::proxmox::types::{
+        BackupArchive, FirewallOptions, FirewallRule, FirewallRuleInfo, GuestExecStatus,
+        GuestNetworkInterface, GuestOsInfo, IsoImage, NodeListItem, NodeStatus, RrdSample,
+        StorageInfo, TaskStatus, VmCurrentConfig, VmDrive, VmUsage,
+    };
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Proxmox client that records called methods and fails to start VMs.
+    ///
+    #[derive(Default)]
+    struct FailingStart {
+        calls: Mutex<Vec<&'static str>>,
+    }
+
+    impl FailingStart {
+        fn call(&self, name: &'static str) -> Result<UniqueProcessId> {
+            self.calls.lock().unwrap().push(name);
+            Ok("UPID:pve:1".into())
+        }
+    }
+
+    #[async_trait]
+    impl Proxmox for FailingStart {
+        async fn start(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+            self.calls.lock().unwrap().push("start");
+            Err(Error::Any("no space left".to_owned()))
+        }
+        async fn shutdown(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+            self.call("shutdown")
+        }
+        async fn stop(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+            self.call("stop")
+        }
+        async fn reboot(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+            self.call("reboot")
+        }
+        async fn create(
+            &self,
+            _vm: VmRef,
+            _storage: Option<&str>,
+        ) -> Result<(i32, UniqueProcessId)> {
+            Ok((101, self.call("create")?))
+        }
+        async fn delete(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+            self.call("delete")
+        }
+        async fn vm_config(&self, _vm: VmRef, _config: VmConfig) -> Result<UniqueProcessId> {
+            self.call("vm_config")
+        }
+        async fn vm_current_config(&self, _vm: VmRef) -> Result<VmCurrentConfig> {
+            Ok(VmCurrentConfig::default())
+        }
+        async fn vm_drives(&self, _vm: VmRef) -> Result<Vec<VmDrive>> {
+            Err(Error::NotSupported("vm_drives".to_owned()))
+        }
+        async fn resize_disk(
+            &self,
+            _vm: VmRef,
+            _disk: &str,
+            _size_gb: i32,
+        ) -> Result<UniqueProcessId> {
+            Err(Error::NotSupported("resize_disk".to_owned()))
+        }
+        async fn vm_exists(&self, _vm: VmRef) -> Result<bool> {
+            Ok(true)
+        }
+        async fn firewall_options(&self, _vm: VmRef, _options: FirewallOptions) -> Result<()> {
+            Err(Error::NotSupported("firewall_options".to_owned()))
+        }
+        async fn firewall_rules(&self, _vm: VmRef) -> Result<Vec<FirewallRuleInfo>> {
+            Err(Error::NotSupported("firewall_rules".to_owned()))
+        }
+        async fn create_firewall_rule(&self, _vm: VmRef, _rule: FirewallRule) -> Result<()> {
+            Err(Error::NotSupported("create_firewall_rule".to_owned()))
+        }
+        async fn delete_firewall_rule(&self, _vm: VmRef, _pos: i32) -> Result<()> {
+            Err(Error::NotSupported("delete_firewall_rule".to_owned()))
+        }
+        async fn vm_status(&self, _vm: VmRef) -> Result<Status> {
+            Ok(Status::Stopped)
+        }
+        async fn vm_usage(&self, _vm: VmRef) -> Result<VmUsage> {
+            Err(Error::NotSupported("vm_usage".to_owned()))
+        }
+        async fn vm_rrd_data(&self, _vm: VmRef) -> Result<Vec<RrdSample>> {
+            Err(Error::NotSupported("vm_rrd_data".to_owned()))
+        }
+        async fn backup(
+            &self,
+            _vm: VmRef,
+            _storage: &str,
+            _mode: BackupMode,
+            _notes: &str,
+        ) -> Result<UniqueProcessId> {
+            self.call("backup")
+        }
+        async fn backups(&self, _vm: VmRef, _storage: &str) -> Result<Vec<BackupArchive>> {
+            Err(Error::NotSupported("backups".to_owned()))
+        }
+        async fn restore(&self, _vm: VmRef, _archive: &str) -> Result<UniqueProcessId> {
+            self.call("restore")
+        }
+        async fn agent_ping(&self, _vm: VmRef) -> Result<()> {
+            Err(Error::NotSupported("agent_ping".to_owned()))
+        }
+        async fn agent_os_info(&self, _vm: VmRef) -> Result<GuestOsInfo> {
+            Err(Error::NotSupported("agent_os_info".to_owned()))
+        }
+        async fn agent_network_interfaces(&self, _vm: VmRef) -> Result<Vec<GuestNetworkInterface>> {
+            Err(Error::NotSupported("agent_network_interfaces".to_owned()))
+        }
+        async fn agent_set_user_password(
+            &self,
+            _vm: VmRef,
+            _username: &str,
+            _password: &str,
+        ) -> Result<()> {
+            Err(Error::NotSupported("agent_set_user_password".to_owned()))
+        }
+        async fn agent_exec(&self, _vm: VmRef, _command: &str, _input: &str) -> Result<i64> {
+            Err(Error::NotSupported("agent_exec".to_owned()))
+        }
+        async fn agent_exec_status(&self, _vm: VmRef, _pid: i64) -> Result<GuestExecStatus> {
+            Err(Error::NotSupported("agent_exec_status".to_owned()))
+        }
+        async fn list_isos(&self, _node: &str, _storage: &str) -> Result<Vec<IsoImage>> {
+            Err(Error::NotSupported("list_isos".to_owned()))
+        }
+        async fn set_cdrom(
+            &self,
+            _vm: VmRef,
+            _device: &str,
+            _iso: Option<&str>,
+        ) -> Result<UniqueProcessId> {
+            Err(Error::NotSupported("set_cdrom".to_owned()))
+        }
+        async fn set_boot_order(&self, _vm: VmRef, _devices: &[String]) -> Result<UniqueProcessId> {
+            Err(Error::NotSupported("set_boot_order".to_owned()))
+        }
+        async fn list_nodes(&self) -> Result<Vec<NodeListItem>> {
+            Err(Error::NotSupported("list_nodes".to_owned()))
+        }
+        async fn node_status(&self, _node: &str) -> Result<NodeStatus> {
+            Err(Error::NotSupported("node_status".to_owned()))
+        }
+        async fn list_storages(&self, _node: &str) -> Result<Vec<StorageInfo>> {
+            Err(Error::NotSupported("list_storages".to_owned()))
+        }
+        async fn task_status(&self, _task: &TaskRef) -> Result<TaskStatus> {
+            Ok(TaskStatus::Completed)
+        }
+    }
+
+    #[tokio::test]
+    async fn run_should_tear_down_vm_after_failed_step() {
+        // Arrange
+        let proxmox = Arc::new(FailingStart::default());
+        let client: Arc<dyn Proxmox + Send + Sync> = proxmox.clone();
+
+        // Act
+        let report = run(&client, &SmokeEnv::default()).await;
+
+        // Assert
+        assert!(!report.passed());
+        let steps: Vec<_> = report.steps.iter().map(|step| step.name).collect();
+        assert_eq!(steps, ["clone", "configure", "start", "delete"]);
+        assert_eq!(
+            *proxmox.calls.lock().unwrap(),
+            ["create", "vm_config", "start", "delete"]
+        );
+    }
+
+    #[test]
+    fn report_should_sum_step_timings() {
+        // Arrange
+        let report = SmokeReport {
+            steps: vec![
+                SmokeStep {
+                    name: "clone",
+                    elapsed: Duration::from_millis(1500),
+                    error: None,
+                },
+                SmokeStep {
+                    name: "delete",
+                    elapsed: Duration::from_millis(500),
+                    error: None,
+                },
+            ],
+        };
+
+        // Act
+        let printed = report.to_string();
+
+        // Assert
+        assert!(report.passed());
+        assert_eq!(report.total(), Duration::from_secs(2));
+        assert!(printed.ends_with("2.00s  PASSED"));
+    }
+}
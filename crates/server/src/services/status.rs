@@ -0,0 +1,57 @@
+use crate::model::queries;
+use crate::model::types::ServerStatus;
+use crate::proxmox::types::VmRef;
+use crate::state::AppState;
+use chrono::{DateTime, Duration, Utc};
+use dashboard_common::prelude::Result;
+
+/// Updates the stored status of every running or stopped server that was
+/// started or stopped outside of the dashboard, e.g. from the Proxmox UI or
+/// from inside the VM.
+///
+/// A server left in the transient status of an action for
+/// `action.stuck_after_min`, because the replica running the action crashed,
+/// gets the status of its VM too, so it accepts operations again.
+///
+/// Servers that can't be reached on Proxmox are skipped, and a server whose
+/// status changed since it was read keeps the new status.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Number of updated servers.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn sync(app_state: &AppState, now: DateTime<Utc>) -> Result<usize> {
+    let stuck_after = Duration::minutes(app_state.config.action.stuck_after_min);
+    let mut servers = queries::get_synced_servers(&app_state.pool).await?;
+    servers.extend(queries::get_stuck_servers(&app_state.pool, now - stuck_after).await?);
+    let mut count = 0;
+
+    for server in servers {
+        let vm = VmRef::new(&server.node_name, server.vm_id);
+        let status = match app_state.proxmox.vm_status(vm).await {
+            Ok(status) => ServerStatus::from(status),
+            Err(error) => {
+                tracing::warn!(target: "service", server_id = %server.server_id, ?error, "Can't read VM status");
+                continue;
+            }
+        };
+        if status == server.status {
+            continue;
+        }
+
+        if queries::replace_server_status(&app_state.pool, server.server_id, server.status, status)
+            .await?
+        {
+            tracing::info!(target: "service", server_id = %server.server_id, from = %server.status, to = %status, "Server status synced");
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
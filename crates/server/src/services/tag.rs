@@ -0,0 +1,322 @@
+use crate::model::queries;
+use crate::model::types::ApiServer;
+use crate::state::AppState;
+use crate::web::types::{ServerExternalIdPayload, ServerNotesPayload, ServerTagsPayload};
+use dashboard_common::prelude::{Error, Result};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Maximum length of a tag.
+const MAX_TAG_LEN: usize = 64;
+/// Maximum number of tags on a server.
+const MAX_TAGS: usize = 32;
+/// Maximum length of the notes of a server.
+const MAX_NOTES_LEN: usize = 10_000;
+/// Maximum length of the external ID of a server.
+const MAX_EXTERNAL_ID_LEN: usize = 255;
+
+/// Replaces the tags of a server of the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the owner.
+/// * `server_id`: ID of the server.
+/// * `payload`: New tags, such as `env:prod`.
+///
+/// # Returns
+///
+/// Updated server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_tags(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: ServerTagsPayload,
+) -> Result<ApiServer> {
+    let tags = normalize_tags(&payload.tags)?;
+
+    let mut transaction = app_state.pool.begin().await?;
+    queries::get_server_by_id(transaction.as_mut(), user_id, server_id).await?;
+    queries::set_server_tags(&mut transaction, server_id, &tags).await?;
+    let server = queries::get_server_by_id(transaction.as_mut(), user_id, server_id).await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %server_id, count = tags.len(), "Server tags set");
+
+    Ok(server)
+}
+
+/// Removes a tag from a server of the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the owner.
+/// * `server_id`: ID of the server.
+/// * `tag`: Removed tag.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success, `Error::NotFound` if the server doesn't have
+/// the tag.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn remove_tag(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    tag: &str,
+) -> Result<()> {
+    if !queries::remove_server_tag(&app_state.pool, user_id, server_id, tag.trim()).await? {
+        return Err(Error::NotFound(format!("Tag {tag} of server {server_id}")));
+    }
+    tracing::info!(target: "service", %server_id, tag, "Server tag removed");
+
+    Ok(())
+}
+
+/// Sets the notes of a server of the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the owner.
+/// * `server_id`: ID of the server.
+/// * `payload`: New notes, blank to clear them.
+///
+/// # Returns
+///
+/// Updated server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, payload))]
+pub async fn set_notes(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: ServerNotesPayload,
+) -> Result<ApiServer> {
+    let notes = payload
+        .notes
+        .as_deref()
+        .map(str::trim)
+        .filter(|notes| !notes.is_empty());
+    if notes.is_some_and(|notes| notes.chars().count() > MAX_NOTES_LEN) {
+        return Err(Error::Validation(format!(
+            "Notes must be at most {MAX_NOTES_LEN} characters long"
+        )));
+    }
+
+    if !queries::set_server_notes(&app_state.pool, user_id, server_id, notes).await? {
+        return Err(Error::NotFound(format!("Server {server_id}")));
+    }
+    tracing::info!(target: "service", %server_id, "Server notes set");
+
+    queries::get_server_by_id(&app_state.pool, user_id, server_id).await
+}
+
+/// Sets the external ID of a server of the user, adopting a server that was
+/// created elsewhere into tooling such as Terraform.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the owner.
+/// * `server_id`: ID of the server.
+/// * `payload`: New external ID, blank to clear it.
+///
+/// # Returns
+///
+/// Updated server, `Error::Conflict` if another server of the user has the
+/// ID.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn set_external_id(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: ServerExternalIdPayload,
+) -> Result<ApiServer> {
+    let external_id = payload
+        .external_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|external_id| !external_id.is_empty())
+        .map(normalize_external_id)
+        .transpose()?;
+
+    let updated = queries::set_server_external_id(
+        &app_state.pool,
+        user_id,
+        server_id,
+        external_id.as_deref(),
+    )
+    .await?;
+    if !updated {
+        return Err(Error::NotFound(format!("Server {server_id}")));
+    }
+    tracing::info!(target: "service", %server_id, ?external_id, "Server external ID set");
+
+    queries::get_server_by_id(&app_state.pool, user_id, server_id).await
+}
+
+/// Finds a server of the user by its external ID.
+///
+/// # Arguments
+///
+/// * `connection`: Database connection.
+/// * `user_id`: ID of the owner.
+/// * `external_id`: External ID of the server.
+///
+/// # Returns
+///
+/// Found server, `None` if the user has no server with the ID.
+///
+pub async fn find_by_external_id(
+    connection: &mut PgConnection,
+    user_id: Uuid,
+    external_id: &str,
+) -> Result<Option<ApiServer>> {
+    let Some(server_id) =
+        queries::find_server_by_external_id(&mut *connection, user_id, external_id).await?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        queries::get_server_by_id(&mut *connection, user_id, server_id).await?,
+    ))
+}
+
+/// Trims, checks, sorts and deduplicates the tags of a server.
+///
+/// # Arguments
+///
+/// * `tags`: Tags as entered by the user.
+///
+/// # Returns
+///
+/// Tags to store, `Error::Validation` if one is invalid or there are too
+/// many.
+///
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>> {
+    let mut tags = tags
+        .iter()
+        .map(|tag| normalize_tag(tag))
+        .collect::<Result<Vec<_>>>()?;
+    tags.sort_unstable();
+    tags.dedup();
+    if tags.len() > MAX_TAGS {
+        return Err(Error::Validation(format!(
+            "A server has at most {MAX_TAGS} tags"
+        )));
+    }
+
+    Ok(tags)
+}
+
+/// Trims an external ID and checks that it is short and free of control
+/// characters. Anything else is allowed, like the quoted keys of Terraform
+/// resource addresses.
+///
+/// # Arguments
+///
+/// * `external_id`: External ID as sent by the user's tooling.
+///
+/// # Returns
+///
+/// External ID to store, `Error::Validation` if it is invalid.
+///
+pub fn normalize_external_id(external_id: &str) -> Result<String> {
+    let external_id = external_id.trim();
+    let valid = !external_id.is_empty()
+        && external_id.chars().count() <= MAX_EXTERNAL_ID_LEN
+        && !external_id.chars().any(char::is_control);
+    if !valid {
+        return Err(Error::Validation(format!(
+            "External ID must be 1 to {MAX_EXTERNAL_ID_LEN} characters without control characters"
+        )));
+    }
+
+    Ok(external_id.to_owned())
+}
+
+/// Parses the comma-separated tags filtering the server list.
+///
+/// # Arguments
+///
+/// * `tags`: Comma-separated tags, `None` for no filter.
+///
+/// # Returns
+///
+/// Tags a listed server must all have.
+///
+pub fn parse_filter(tags: Option<&str>) -> Vec<String> {
+    tags.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+// -----------------------------------------------------------------------------
+
+/// Trims a tag and checks that it is short and made of letters, digits and
+/// `:`, `=`, `-`, `_`, `.` or `/`, so it reads well in the filter and in URLs.
+///
+fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim();
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_TAG_LEN
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ':' | '=' | '-' | '_' | '.' | '/'));
+    if !valid {
+        return Err(Error::Validation(format!(
+            "Tag '{tag}' must be 1 to {MAX_TAG_LEN} letters, digits or ':=-_./' characters"
+        )));
+    }
+
+    Ok(tag.to_owned())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_tag_should_accept_labels_only() {
+        // Act & Assert
+        assert_eq!(normalize_tag(" env:prod ").unwrap(), "env:prod");
+        assert_eq!(normalize_tag("team/web_1.0").unwrap(), "team/web_1.0");
+        assert!(normalize_tag("").is_err());
+        assert!(normalize_tag("two words").is_err());
+        assert!(normalize_tag("a,b").is_err());
+        assert!(normalize_tag(&"x".repeat(MAX_TAG_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn normalize_external_id_should_reject_blank_and_control_characters() {
+        // Act & Assert
+        assert_eq!(
+            normalize_external_id(" module.web.server[\"eu 1\"] ").unwrap(),
+            "module.web.server[\"eu 1\"]"
+        );
+        assert!(normalize_external_id(" ").is_err());
+        assert!(normalize_external_id("web\n1").is_err());
+        assert!(normalize_external_id(&"x".repeat(MAX_EXTERNAL_ID_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn parse_filter_should_skip_blank_tags() {
+        // Act & Assert
+        assert_eq!(parse_filter(None), Vec::<String>::new());
+        assert_eq!(
+            parse_filter(Some("env:prod, ,project:x")),
+            ["env:prod", "project:x"]
+        );
+    }
+}
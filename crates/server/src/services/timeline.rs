@@ -0,0 +1,64 @@
+use crate::model::queries;
+use crate::model::types::{ApiTimelineEvent, TimelineEventKind};
+use crate::services::backup;
+use crate::state::AppState;
+use dashboard_common::prelude::Result;
+use uuid::Uuid;
+
+/// Maximum number of events in a timeline.
+const TIMELINE_LEN: usize = 200;
+
+/// Builds the activity timeline of a server of the user from its provisioning
+/// steps, status changes, disk changes, completed transfers and backup
+/// archives. A storage failing to list the archives leaves them out instead of
+/// failing the whole timeline. A transferred server's timeline starts with its
+/// latest transfer, so the history of the previous owner stays private.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Latest events of the server, in chronological order.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_timeline(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<Vec<ApiTimelineEvent>> {
+    let server = queries::get_server_by_id(app_state.reader(), user_id, server_id).await?;
+    let since = queries::get_server_owned_since(app_state.reader(), server_id).await?;
+    let mut events =
+        queries::get_server_timeline(app_state.reader(), server_id, since, TIMELINE_LEN as i64)
+            .await?;
+
+    if server.vm_id.is_some() {
+        match backup::list_backups(app_state, user_id, server_id).await {
+            Ok(backups) => events.extend(
+                backups
+                    .into_iter()
+                    .filter(|backup| since.is_none_or(|since| backup.created_at >= since))
+                    .map(|backup| ApiTimelineEvent {
+                        kind: TimelineEventKind::Backup,
+                        occurred_at: backup.created_at,
+                        name: backup.volid,
+                        status: None,
+                        detail: backup.notes,
+                    }),
+            ),
+            Err(error) => {
+                tracing::warn!(target: "service", %server_id, ?error, "Backups left out of the timeline")
+            }
+        }
+    }
+
+    events.sort_by_key(|event| event.occurred_at);
+    let excess = events.len().saturating_sub(TIMELINE_LEN);
+    events.drain(..excess);
+
+    Ok(events)
+}
@@ -0,0 +1,266 @@
+use crate::model::queries;
+use crate::model::types::{ApiServerTraffic, ApiTrafficMonth, DomainEventKind, TrafficServer};
+use crate::proxmox::types::{RrdSample, VmRef};
+use crate::services::event;
+use crate::state::AppState;
+use chrono::{DateTime, Datelike, Utc};
+use dashboard_common::prelude::Result;
+use uuid::Uuid;
+
+/// Step of the RRD samples of the day timeframe in seconds.
+const RRD_STEP_SEC: i64 = 1800;
+/// Bytes in a GB of the traffic quotas.
+const BYTES_PER_GB: i64 = 1024 * 1024 * 1024;
+/// Number of months of traffic listed for a server.
+const TRAFFIC_MONTHS: i64 = 12;
+
+/// Returns the monthly network traffic of a server of the user.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+///
+/// # Returns
+///
+/// Traffic of the last 12 months with traffic and the monthly quota of the
+/// server.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_traffic(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ApiServerTraffic> {
+    let quota_gb = queries::get_traffic_quota(app_state.reader(), user_id, server_id).await?;
+    let months = queries::get_server_traffic(app_state.reader(), server_id, TRAFFIC_MONTHS).await?;
+
+    Ok(ApiServerTraffic { quota_gb, months })
+}
+
+/// Adds the network traffic of every provisioned server since its previous
+/// collection to its monthly traffic, out of the RRD data of Proxmox. The
+/// owner of a server that used up its monthly quota is alerted once a month,
+/// by a notification and a webhook event.
+///
+/// Servers that can't be reached on Proxmox are skipped, so one broken node
+/// doesn't stop the accounting of the whole cluster. RRD data only reaches a
+/// day back, so the traffic of a longer outage is lost.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+///
+/// # Returns
+///
+/// Number of updated monthly records.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn collect(app_state: &AppState) -> Result<usize> {
+    let servers = queries::get_traffic_servers(&app_state.pool).await?;
+    let mut count = 0;
+
+    for server in servers {
+        let vm = VmRef::new(&server.node_name, server.vm_id);
+        let samples = match app_state.proxmox.vm_rrd_data(vm).await {
+            Ok(samples) => samples,
+            Err(error) => {
+                tracing::warn!(target: "service", server_id = %server.server_id, ?error, "Can't read VM traffic");
+                continue;
+            }
+        };
+
+        for traffic in to_monthly(&samples, server.sampled_until) {
+            let total =
+                queries::add_server_traffic(&app_state.pool, server.server_id, &traffic).await?;
+            count += 1;
+
+            if let Some(quota_gb) = exceeded_quota(server.traffic_quota_gb, &total) {
+                alert_quota(app_state, &server, &total, quota_gb).await?;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Sums up the RRD samples taken after the previous collection by the
+/// calendar month their step started in. Samples without values are skipped.
+///
+/// # Arguments
+///
+/// * `samples`: RRD samples of the VM.
+/// * `since`: Time of the last accounted sample, `None` to account them all.
+///
+/// # Returns
+///
+/// Traffic of every month with new samples, in the order of the samples.
+///
+pub fn to_monthly(samples: &[RrdSample], since: Option<DateTime<Utc>>) -> Vec<ApiTrafficMonth> {
+    let mut months: Vec<ApiTrafficMonth> = Vec::new();
+
+    for sample in samples {
+        let (Some(netin), Some(netout)) = (sample.netin, sample.netout) else {
+            continue;
+        };
+        let (Some(time), Some(started)) = (
+            DateTime::from_timestamp(sample.time, 0),
+            DateTime::from_timestamp(sample.time - RRD_STEP_SEC, 0),
+        ) else {
+            continue;
+        };
+        if since.is_some_and(|since| time <= since) {
+            continue;
+        }
+        let Some(month) = started.date_naive().with_day(1) else {
+            continue;
+        };
+        let rx_bytes = (netin * RRD_STEP_SEC as f64).round() as i64;
+        let tx_bytes = (netout * RRD_STEP_SEC as f64).round() as i64;
+
+        match months.iter_mut().find(|traffic| traffic.month == month) {
+            Some(traffic) => {
+                traffic.rx_bytes += rx_bytes;
+                traffic.tx_bytes += tx_bytes;
+                traffic.sampled_until = traffic.sampled_until.max(time);
+            }
+            None => months.push(ApiTrafficMonth {
+                month,
+                rx_bytes,
+                tx_bytes,
+                sampled_until: time,
+            }),
+        }
+    }
+
+    months
+}
+
+/// Checks the traffic of a month against the quota of the server.
+///
+/// # Returns
+///
+/// Quota in GB if the traffic reached it, `None` otherwise.
+///
+pub fn exceeded_quota(quota_gb: Option<i32>, traffic: &ApiTrafficMonth) -> Option<i32> {
+    quota_gb.filter(|quota_gb| traffic.total_bytes() >= i64::from(*quota_gb) * BYTES_PER_GB)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Alerts the owner of a server about its used up quota, unless already done
+/// this month. The domain event is recorded along with the mark.
+///
+async fn alert_quota(
+    app_state: &AppState,
+    server: &TrafficServer,
+    traffic: &ApiTrafficMonth,
+    quota_gb: i32,
+) -> Result<()> {
+    let server_id = server.server_id;
+    let mut transaction = app_state.pool.begin().await?;
+    if !queries::mark_traffic_alerted(transaction.as_mut(), server_id, traffic.month).await? {
+        return Ok(());
+    }
+    let data = serde_json::json!({
+        "server_id": server_id,
+        "host_name": server.host_name,
+        "month": traffic.month,
+        "rx_bytes": traffic.rx_bytes,
+        "tx_bytes": traffic.tx_bytes,
+        "quota_gb": quota_gb,
+    });
+    event::record(
+        transaction.as_mut(),
+        server.user_id,
+        DomainEventKind::TrafficQuotaExceeded,
+        data,
+    )
+    .await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", %server_id, quota_gb, "Traffic quota exceeded");
+
+    Ok(())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn sample(time: DateTime<Utc>, netin: Option<f64>) -> RrdSample {
+        RrdSample {
+            time: time.timestamp(),
+            netin,
+            netout: netin.map(|netin| netin * 2.0),
+        }
+    }
+
+    #[test]
+    fn to_monthly_should_split_new_samples_by_month() {
+        // Arrange
+        let time = |day, hour, minute| {
+            Utc.with_ymd_and_hms(2026, 10, day, hour, minute, 0)
+                .unwrap()
+        };
+        let samples = [
+            sample(time(30, 23, 0), Some(1.0)),
+            sample(time(31, 23, 30), Some(1.0)),
+            sample(
+                Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap(),
+                Some(2.0),
+            ),
+            sample(
+                Utc.with_ymd_and_hms(2026, 11, 1, 0, 30, 0).unwrap(),
+                Some(4.0),
+            ),
+            sample(Utc.with_ymd_and_hms(2026, 11, 1, 1, 0, 0).unwrap(), None),
+        ];
+
+        // Act
+        let months = to_monthly(&samples, Some(time(30, 23, 0)));
+
+        // Assert
+        assert_eq!(
+            months,
+            [
+                ApiTrafficMonth {
+                    month: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+                    rx_bytes: 5400,
+                    tx_bytes: 10800,
+                    sampled_until: Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap(),
+                },
+                ApiTrafficMonth {
+                    month: NaiveDate::from_ymd_opt(2026, 11, 1).unwrap(),
+                    rx_bytes: 7200,
+                    tx_bytes: 14400,
+                    sampled_until: Utc.with_ymd_and_hms(2026, 11, 1, 0, 30, 0).unwrap(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn exceeded_quota_should_count_both_directions() {
+        // Arrange
+        let traffic = |rx_bytes, tx_bytes| ApiTrafficMonth {
+            month: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            rx_bytes,
+            tx_bytes,
+            sampled_until: Utc::now(),
+        };
+
+        // Act
+        let below = exceeded_quota(Some(1), &traffic(BYTES_PER_GB / 2, BYTES_PER_GB / 4));
+        let reached = exceeded_quota(Some(1), &traffic(BYTES_PER_GB / 2, BYTES_PER_GB / 2));
+        let unlimited = exceeded_quota(None, &traffic(BYTES_PER_GB, BYTES_PER_GB));
+
+        // Assert
+        assert_eq!(below, None);
+        assert_eq!(reached, Some(1));
+        assert_eq!(unlimited, None);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::mail::types::Email;
+use crate::model::queries;
+use crate::model::types::{
+    ApiCompletedTransfer, ApiServer, ApiServerTransfer, AuditAction, NewServerTransfer,
+    PasswordResetMethod, ServerStatus,
+};
+use crate::proxmox::types::{TaskRef, VmConfig};
+use crate::services::{self, Polling, quota};
+use crate::state::AppState;
+use crate::web::types::{AdminTransferPayload, TransferPayload};
+use dashboard_common::prelude::{Error, Result};
+use serde_json::json;
+use sqlx::PgTransaction;
+use uuid::Uuid;
+
+/// Offers a server to another account. The server is moved only once the
+/// recipient accepts the transfer. The answer doesn't tell whether the email
+/// address belongs to an account: an unknown address gets no transfer, but is
+/// answered like a known one.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the server.
+/// * `server_id`: ID of the server.
+/// * `payload`: Email address of the recipient.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the transfer is offered, `Error::Conflict` if the server
+/// is busy or already has a pending transfer.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn request_transfer(
+    app_state: &AppState,
+    user_id: Uuid,
+    server_id: Uuid,
+    payload: TransferPayload,
+) -> Result<()> {
+    let recipient = match queries::get_user_by_email(&app_state.pool, payload.email.trim()).await {
+        Ok(recipient) => Some(recipient),
+        Err(Error::Database(sqlx::Error::RowNotFound)) => None,
+        Err(error) => return Err(error),
+    };
+    if recipient
+        .as_ref()
+        .is_some_and(|recipient| recipient.id == user_id)
+    {
+        return Err(Error::Validation(
+            "Server already belongs to the recipient".to_owned(),
+        ));
+    }
+
+    let mut transaction = app_state.pool.begin().await?;
+    let server = lock_stable_server(&mut transaction, user_id, server_id).await?;
+    let Some(recipient) = recipient else {
+        tracing::info!(target: "service", %server_id, "Server transfer to unknown recipient ignored");
+        return Ok(());
+    };
+    let transfer = NewServerTransfer {
+        service_id: server.service_id,
+        from_user_id: user_id,
+        to_user_id: recipient.id,
+        initiated_by: user_id,
+        regenerate_credentials: payload.regenerate_credentials,
+    };
+    let transfer_id = queries::create_server_transfer(transaction.as_mut(), transfer).await?;
+    let transfer = queries::get_server_transfer(transaction.as_mut(), transfer_id).await?;
+    transaction.commit().await?;
+
+    app_state
+        .mailer
+        .send(Email {
+            to: recipient.email,
+            subject: "A server was offered to you".to_owned(),
+            body: format!(
+                "Server {} is being transferred to your account.\n\
+                 Accept or decline transfer {transfer_id} in the dashboard.",
+                transfer.host_name
+            ),
+        })
+        .await?;
+    tracing::info!(target: "service", %transfer_id, "Server transfer requested");
+
+    Ok(())
+}
+
+/// Accepts a transfer offered to the user, moving the server to the account
+/// if it fits into its quota.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the recipient.
+/// * `transfer_id`: ID of the transfer.
+///
+/// # Returns
+///
+/// Completed transfer, with the new password of the server if the credentials
+/// were regenerated.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn accept_transfer(
+    app_state: &AppState,
+    user_id: Uuid,
+    transfer_id: Uuid,
+) -> Result<ApiCompletedTransfer> {
+    let mut transaction = app_state.pool.begin().await?;
+
+    let transfer = queries::lock_pending_transfer(&mut transaction, transfer_id, user_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Transfer {transfer_id}")))?;
+    let server =
+        lock_stable_server(&mut transaction, transfer.from_user_id, transfer.server_id).await?;
+    ensure_quota(app_state, &mut transaction, &transfer).await?;
+
+    let completion = Completion {
+        actor_id: user_id,
+        status: server.status,
+        enforce_quota: true,
+    };
+    complete(app_state, transaction, transfer, completion).await
+}
+
+/// Declines a transfer offered to the user, or cancels a transfer of the
+/// user's server.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the recipient or of the owner.
+/// * `transfer_id`: ID of the transfer.
+///
+/// # Returns
+///
+/// Declined or cancelled transfer.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn close_transfer(
+    app_state: &AppState,
+    user_id: Uuid,
+    transfer_id: Uuid,
+) -> Result<ApiServerTransfer> {
+    if !queries::close_server_transfer(&app_state.pool, transfer_id, user_id, app_state.clock.now())
+        .await?
+    {
+        return Err(Error::NotFound(format!("Transfer {transfer_id}")));
+    }
+    tracing::info!(target: "service", %transfer_id, "Server transfer closed");
+
+    queries::get_server_transfer(&app_state.pool, transfer_id).await
+}
+
+/// Moves a server to another account on behalf of an administrator, at once
+/// and regardless of the quota of the recipient. A pending transfer of the
+/// server is cancelled.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `admin_id`: ID of the administrator.
+/// * `server_id`: ID of the server.
+/// * `payload`: Recipient of the server.
+///
+/// # Returns
+///
+/// Completed transfer, with the new password of the server if the credentials
+/// were regenerated.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn transfer_server(
+    app_state: &AppState,
+    admin_id: Uuid,
+    server_id: Uuid,
+    payload: AdminTransferPayload,
+) -> Result<ApiCompletedTransfer> {
+    let owner_id = queries::get_server_owner(&app_state.pool, server_id).await?;
+    if owner_id == payload.user_id {
+        return Err(Error::Validation(
+            "Server already belongs to the recipient".to_owned(),
+        ));
+    }
+    queries::get_user_by_id(&app_state.pool, payload.user_id).await?;
+
+    let mut transaction = app_state.pool.begin().await?;
+    let server = lock_stable_server(&mut transaction, owner_id, server_id).await?;
+    queries::cancel_server_transfers(transaction.as_mut(), server_id, app_state.clock.now())
+        .await?;
+    let transfer = NewServerTransfer {
+        service_id: server.service_id,
+        from_user_id: owner_id,
+        to_user_id: payload.user_id,
+        initiated_by: admin_id,
+        regenerate_credentials: payload.regenerate_credentials,
+    };
+    let transfer_id = queries::create_server_transfer(transaction.as_mut(), transfer).await?;
+    let transfer = queries::get_server_transfer(transaction.as_mut(), transfer_id).await?;
+
+    let completion = Completion {
+        actor_id: admin_id,
+        status: server.status,
+        enforce_quota: false,
+    };
+    let result = complete(app_state, transaction, transfer, completion).await;
+    // A transfer left pending by a failed regeneration of the credentials
+    // isn't offered to the recipient.
+    if result.is_err()
+        && let Err(error) = queries::close_server_transfer(
+            &app_state.pool,
+            transfer_id,
+            owner_id,
+            app_state.clock.now(),
+        )
+        .await
+    {
+        tracing::error!(target: "service", %transfer_id, ?error, "Failed to cancel transfer!");
+    }
+
+    result
+}
+
+// -----------------------------------------------------------------------------
+
+/// Locks the server of the owner and checks that no operation is in progress
+/// on it.
+///
+async fn lock_stable_server(
+    transaction: &mut PgTransaction<'_>,
+    user_id: Uuid,
+    server_id: Uuid,
+) -> Result<ApiServer> {
+    let status = queries::lock_server_status(transaction, user_id, server_id)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Server {server_id}")))?;
+    if let Some(operation) = status.operation() {
+        return Err(Error::Conflict(format!(
+            "Server {server_id} is busy, operation '{operation}' is in progress"
+        )));
+    }
+
+    queries::get_server_by_id(&mut **transaction, user_id, server_id).await
+}
+
+/// Completion of a transfer.
+///
+/// # Fields
+///
+/// * `actor_id`: User completing the transfer, recorded in the audit log.
+/// * `status`: Status of the server before the transfer.
+/// * `enforce_quota`: Whether the server must fit into the quota of the
+///   recipient.
+///
+struct Completion {
+    actor_id: Uuid,
+    status: ServerStatus,
+    enforce_quota: bool,
+}
+
+/// Locks the recipient of the transfer and checks that the server fits into
+/// their quota.
+///
+async fn ensure_quota(
+    app_state: &AppState,
+    transaction: &mut PgTransaction<'_>,
+    transfer: &ApiServerTransfer,
+) -> Result<()> {
+    queries::lock_user(transaction, transfer.to_user_id).await?;
+    quota::ensure_transfer_quota(
+        transaction.as_mut(),
+        &app_state.config.quota,
+        transfer.to_user_id,
+        transfer.server_id,
+    )
+    .await
+}
+
+/// Moves the server of a pending transfer to the recipient and commits the
+/// transaction, regenerating the credentials if requested.
+///
+/// No transaction is held while Proxmox applies new credentials: the server is
+/// reserved in the `Configuring` status and the transaction committed first,
+/// and the server is moved in a new transaction afterwards, checking the quota
+/// of the recipient again, once the transfer is still pending. A failed
+/// regeneration leaves the transfer pending and the server in its status from
+/// before.
+///
+async fn complete(
+    app_state: &AppState,
+    mut transaction: PgTransaction<'_>,
+    transfer: ApiServerTransfer,
+    completion: Completion,
+) -> Result<ApiCompletedTransfer> {
+    if !transfer.regenerate_credentials {
+        return finish(app_state, transaction, &transfer, &completion, None).await;
+    }
+
+    let server_id = transfer.server_id;
+    queries::update_server_status(transaction.as_mut(), server_id, ServerStatus::Configuring)
+        .await?;
+    transaction.commit().await?;
+
+    let result = async {
+        let password = regenerate_credentials(app_state, &transfer).await?;
+        let mut transaction = app_state.pool.begin().await?;
+        queries::lock_pending_transfer(&mut transaction, transfer.id, transfer.to_user_id)
+            .await?
+            .ok_or_else(|| Error::Conflict(format!("Transfer {} was closed", transfer.id)))?;
+        if completion.enforce_quota {
+            ensure_quota(app_state, &mut transaction, &transfer).await?;
+        }
+        queries::update_server_status(transaction.as_mut(), server_id, completion.status).await?;
+        finish(
+            app_state,
+            transaction,
+            &transfer,
+            &completion,
+            Some(password),
+        )
+        .await
+    }
+    .await;
+    if result.is_err()
+        && let Err(error) =
+            queries::update_server_status(&app_state.pool, server_id, completion.status).await
+    {
+        tracing::error!(target: "service", %server_id, ?error, "Failed to restore server status!");
+    }
+
+    result
+}
+
+/// Sets a new cloud-init password for the server of the transfer and removes
+/// the SSH keys of the owner.
+///
+/// # Returns
+///
+/// New password of the server.
+///
+async fn regenerate_credentials(
+    app_state: &AppState,
+    transfer: &ApiServerTransfer,
+) -> Result<String> {
+    let vm =
+        queries::get_server_proxmox_ref(&app_state.pool, transfer.from_user_id, transfer.server_id)
+            .await?;
+    let password = services::new_password();
+    let vm_config = VmConfig::builder()
+        .cipassword(password.clone())
+        .delete(&["sshkeys"])
+        .build();
+    let upid = app_state.proxmox.vm_config(vm.clone(), vm_config).await?;
+    let task = TaskRef::new(&vm.node, &upid);
+    services::wait_until_finish(&app_state.proxmox, &app_state.clock, task, Polling::CONFIG)
+        .await?;
+    tracing::info!(target: "service", server_id = %transfer.server_id, "Credentials regenerated");
+
+    Ok(password)
+}
+
+/// Moves the server to the recipient, records the transfer and the new
+/// credentials in the audit log, and commits the transaction.
+///
+async fn finish(
+    app_state: &AppState,
+    mut transaction: PgTransaction<'_>,
+    transfer: &ApiServerTransfer,
+    completion: &Completion,
+    password: Option<String>,
+) -> Result<ApiCompletedTransfer> {
+    let server_id = transfer.server_id;
+    if !queries::complete_server_transfer(&mut transaction, transfer, app_state.clock.now()).await?
+    {
+        return Err(Error::Conflict(format!(
+            "Server {server_id} changed its owner"
+        )));
+    }
+
+    queries::create_audit_entry(
+        transaction.as_mut(),
+        completion.actor_id,
+        AuditAction::ServerTransferred,
+        Some(server_id),
+        &json!({
+            "transfer_id": transfer.id,
+            "from_user_id": transfer.from_user_id,
+            "to_user_id": transfer.to_user_id,
+        }),
+    )
+    .await?;
+    if password.is_some() {
+        queries::create_audit_entry(
+            transaction.as_mut(),
+            completion.actor_id,
+            AuditAction::PasswordReset,
+            Some(server_id),
+            &json!({ "method": PasswordResetMethod::CloudInit, "transfer_id": transfer.id }),
+        )
+        .await?;
+    }
+
+    let transfer = queries::get_server_transfer(transaction.as_mut(), transfer.id).await?;
+    transaction.commit().await?;
+    tracing::info!(target: "service", transfer_id = %transfer.id, "Server transferred");
+
+    Ok(ApiCompletedTransfer { transfer, password })
+}
@@ -0,0 +1,183 @@
+use crate::model::queries;
+use crate::model::types::{MeteredServer, NewUsageRecord};
+use crate::proxmox::types::{Status, VmRef, VmUsage};
+use crate::state::AppState;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use dashboard_common::prelude::{Error, Result};
+use std::time::Duration;
+
+/// Takes a single usage sample of every provisioned server.
+///
+/// Servers that can't be reached on Proxmox are skipped, so one broken node
+/// doesn't stop metering of the whole cluster.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `period`: Time covered by this sample.
+///
+/// # Returns
+///
+/// Number of saved usage records.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn collect(app_state: &AppState, period: Duration) -> Result<usize> {
+    let servers = queries::get_metered_servers(&app_state.pool).await?;
+    let mut count = 0;
+
+    for server in servers {
+        let vm = VmRef::new(&server.node_name, server.vm_id);
+        let usage = match app_state.proxmox.vm_usage(vm).await {
+            Ok(usage) => usage,
+            Err(error) => {
+                tracing::warn!(target: "service", server_id = %server.server_id, ?error, "Can't read VM usage");
+                continue;
+            }
+        };
+
+        queries::create_usage_record(&app_state.pool, to_record(&server, &usage, period)).await?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Converts raw VM metrics into a usage record for the sampled period.
+///
+/// Running servers are accounted for the whole period, or for their uptime
+/// if they were started in the middle of it. CPU and RAM are only recorded
+/// for running servers.
+///
+/// # Arguments
+///
+/// * `server`: Sampled server.
+/// * `usage`: Metrics reported by Proxmox.
+/// * `period`: Time covered by this sample.
+///
+/// # Returns
+///
+/// `NewUsageRecord` ready to be saved.
+///
+pub fn to_record(server: &MeteredServer, usage: &VmUsage, period: Duration) -> NewUsageRecord {
+    let running = usage.status == Status::Running;
+    let uptime_hours = match running {
+        true => period.min(Duration::from_secs(usage.uptime)).as_secs_f64() / 3600.0,
+        false => 0.0,
+    };
+
+    NewUsageRecord {
+        user_id: server.user_id,
+        service_id: server.service_id,
+        server_id: server.server_id,
+        uptime_hours,
+        cpu_usage: running.then_some(usage.cpu),
+        ram_mb: running.then_some((usage.mem / (1024 * 1024)) as i64),
+    }
+}
+
+/// Resolves the billing period for usage reports, defaulting to the current
+/// calendar month.
+///
+/// # Arguments
+///
+/// * `from`: Optional start of the period.
+/// * `to`: Optional end of the period.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// `(from, to)` tuple of the resolved period.
+///
+pub fn billing_period(
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let from = match from {
+        Some(from) => from,
+        None => Utc
+            .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .single()
+            .ok_or_else(|| Error::Any("Invalid billing period start".to_owned()))?,
+    };
+    let to = to.unwrap_or(now);
+
+    match from < to {
+        true => Ok((from, to)),
+        false => Err(Error::Validation(
+            "Billing period start must be before its end".to_owned(),
+        )),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn server() -> MeteredServer {
+        MeteredServer {
+            user_id: Uuid::new_v4(),
+            service_id: Uuid::new_v4(),
+            server_id: Uuid::new_v4(),
+            vm_id: 100,
+            node_name: "pve".to_owned(),
+        }
+    }
+
+    #[test]
+    fn to_record_accounts_running_server() {
+        // Arrange
+        let usage = VmUsage {
+            status: Status::Running,
+            uptime: 1800,
+            cpu: 0.5,
+            mem: 2 * 1024 * 1024 * 1024,
+        };
+
+        // Act
+        let short = to_record(&server(), &usage, Duration::from_secs(3600));
+        let long = to_record(&server(), &usage, Duration::from_secs(900));
+
+        // Assert
+        assert_eq!(short.uptime_hours, 0.5);
+        assert_eq!(long.uptime_hours, 0.25);
+        assert_eq!(short.cpu_usage, Some(0.5));
+        assert_eq!(short.ram_mb, Some(2048));
+    }
+
+    #[test]
+    fn to_record_skips_stopped_server() {
+        // Arrange
+        let usage = VmUsage {
+            status: Status::Stopped,
+            uptime: 0,
+            cpu: 0.0,
+            mem: 0,
+        };
+
+        // Act
+        let record = to_record(&server(), &usage, Duration::from_secs(3600));
+
+        // Assert
+        assert_eq!(record.uptime_hours, 0.0);
+        assert_eq!(record.cpu_usage, None);
+        assert_eq!(record.ram_mb, None);
+    }
+
+    #[test]
+    fn billing_period_defaults_to_current_month() {
+        // Arrange
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+
+        // Act
+        let (from, to) = billing_period(None, None, now).unwrap();
+
+        // Assert
+        assert_eq!(from, Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap());
+        assert_eq!(to, now);
+        assert!(billing_period(Some(to), Some(from), now).is_err());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::i18n::Locale;
+use crate::mail::types::Email;
+use crate::model::queries;
+use crate::model::types::{ApiUser, ServerStatus};
+use crate::services::{self, deletion};
+use crate::state::AppState;
+use crate::web::types::{ConfirmEmailPayload, EmailChangePayload, UpdateUserPayload};
+use chrono::Duration;
+use dashboard_common::prelude::{Error, Result};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+/// Maximal length of a profile field.
+const MAX_FIELD_LEN: usize = 255;
+
+/// Updates the profile of a user, leaving the missing fields unchanged.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `payload`: New values of the profile fields.
+///
+/// # Returns
+///
+/// Updated profile of the user.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn update_profile(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: UpdateUserPayload,
+) -> Result<ApiUser> {
+    let profile = UpdateUserPayload {
+        first_name: validate_field("first_name", payload.first_name)?,
+        last_name: validate_field("last_name", payload.last_name)?,
+        address: validate_field("address", payload.address)?,
+        city: validate_field("city", payload.city)?,
+        state: validate_field("state", payload.state)?,
+        post_code: validate_field("post_code", payload.post_code)?,
+        country: validate_field("country", payload.country)?,
+        phone_number: validate_phone(payload.phone_number)?,
+        locale: validate_locale(payload.locale)?,
+    };
+
+    let user = queries::update_user_profile(&app_state.pool, user_id, &profile).await?;
+    tracing::info!(target: "service", "User profile updated");
+
+    Ok(user)
+}
+
+/// Starts a change of the user's email address. The address is changed only
+/// once the user confirms it with the token sent to the new address.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `payload`: Requested email address.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the confirmation email is sent.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn request_email_change(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: EmailChangePayload,
+) -> Result<()> {
+    let email = validate_email(&payload.email)?;
+    let user = queries::get_user_by_id(&app_state.pool, user_id).await?;
+    if user.email == email {
+        return Err(Error::Validation("Email address is already set".to_owned()));
+    }
+    if queries::user_email_exists(&app_state.pool, &email).await? {
+        return Err(email_in_use());
+    }
+
+    let (token, token_hash) = new_token();
+    let ttl = app_state.config.account.email_token_ttl_min;
+    let expires_at = app_state.clock.now() + Duration::minutes(ttl);
+    queries::create_email_change(&app_state.pool, user_id, &email, &token_hash, expires_at).await?;
+
+    app_state
+        .mailer
+        .send(Email {
+            to: email,
+            subject: "Confirm your new email address".to_owned(),
+            body: format!(
+                "Use this token to confirm your new email address: {token}\n\
+                 The token expires in {ttl} minutes."
+            ),
+        })
+        .await?;
+    tracing::info!(target: "service", "Email change requested");
+
+    Ok(())
+}
+
+/// Applies the pending change of the user's email address.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `payload`: Token sent to the new address.
+///
+/// # Returns
+///
+/// Updated profile of the user.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state, payload))]
+pub async fn confirm_email_change(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: ConfirmEmailPayload,
+) -> Result<ApiUser> {
+    let mut transaction = app_state.pool.begin().await?;
+
+    let email = queries::take_email_change(
+        transaction.as_mut(),
+        user_id,
+        &hash_token(payload.token.trim()),
+        app_state.clock.now(),
+    )
+    .await?
+    .ok_or_else(|| Error::Validation("Invalid or expired confirmation token".to_owned()))?;
+    if queries::user_email_exists(transaction.as_mut(), &email).await? {
+        return Err(email_in_use());
+    }
+    let user = queries::update_user_email(transaction.as_mut(), user_id, &email).await?;
+
+    transaction.commit().await?;
+    tracing::info!(target: "service", "Email address changed");
+
+    Ok(user)
+}
+
+/// Sends a token verifying the email address of a new account, replacing the
+/// previously sent one.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `email`: Email address to verify.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the verification email is sent.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn send_verification(app_state: &AppState, user_id: Uuid, email: &str) -> Result<()> {
+    let (token, token_hash) = new_token();
+    let ttl = app_state.config.account.verification_token_ttl_min;
+    let expires_at = app_state.clock.now() + Duration::minutes(ttl);
+    queries::create_email_verification(&app_state.pool, user_id, &token_hash, expires_at).await?;
+
+    app_state
+        .mailer
+        .send(Email {
+            to: email.to_owned(),
+            subject: "Verify your email address".to_owned(),
+            body: format!(
+                "Use this token to verify your email address: {token}\n\
+                 The token expires in {ttl} minutes."
+            ),
+        })
+        .await?;
+    tracing::info!(target: "service", "Verification email sent");
+
+    Ok(())
+}
+
+/// Sends a new verification token to a user whose email address isn't
+/// verified yet, e.g. once the previous token expired.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Empty `Ok(())` once the verification email is sent.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn resend_verification(app_state: &AppState, user_id: Uuid) -> Result<()> {
+    let user = queries::get_user_by_id(&app_state.pool, user_id).await?;
+    if user.email_verified_at.is_some() {
+        return Err(Error::Validation(
+            "Email address is already verified".to_owned(),
+        ));
+    }
+
+    send_verification(app_state, user_id, &user.email).await
+}
+
+/// Verifies the email address of the account the token was sent for.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `payload`: Token sent to the email address.
+///
+/// # Returns
+///
+/// ID of the verified user.
+///
+#[tracing::instrument(level = "trace", target = "service", skip_all)]
+pub async fn verify_email(app_state: &AppState, payload: ConfirmEmailPayload) -> Result<Uuid> {
+    let token_hash = hash_token(payload.token.trim());
+    let user_id = queries::verify_user_email(&app_state.pool, &token_hash, app_state.clock.now())
+        .await?
+        .ok_or_else(|| Error::Validation("Invalid or expired verification token".to_owned()))?;
+    tracing::info!(target: "service", %user_id, "Email address verified");
+
+    Ok(user_id)
+}
+
+/// Ensures the user has verified the email address, which is required to order
+/// servers.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// An empty `Result` on success, `Error::Forbidden` otherwise.
+///
+pub async fn ensure_verified<'e, E>(executor: E, user_id: Uuid) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    if !queries::is_email_verified(executor, user_id).await? {
+        return Err(Error::Forbidden(
+            "Email address must be verified before ordering servers".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Deletes the account of a user. The user is anonymized right away, keeping
+/// only the records that must be retained, like invoices, and the servers are
+/// deprovisioned in the background. Servers of shared organizations are
+/// handed over to another owner instead.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+///
+/// # Returns
+///
+/// Number of servers whose deprovisioning started right away, the others are
+/// deprovisioned by the scheduler once their operations finish.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_account(app_state: &AppState, user_id: Uuid) -> Result<usize> {
+    let mut transaction = app_state.pool.begin().await?;
+    queries::hand_over_organization_services(transaction.as_mut(), user_id, None).await?;
+    if !queries::anonymize_user(&mut transaction, user_id, app_state.clock.now()).await? {
+        return Err(Error::Validation(format!("User {user_id} not found")));
+    }
+    transaction.commit().await?;
+    tracing::info!(target: "service", "User anonymized");
+
+    if let Some(cluster) = &app_state.cluster {
+        let token_duration = std::time::Duration::from_secs(app_state.config.token.duration_sec);
+        match cluster.revoke_sessions(user_id, token_duration).await {
+            Ok(()) => tracing::info!(target: "service", "Sessions revoked"),
+            // The tokens of the deleted user are rejected anyway.
+            Err(error) => tracing::warn!(target: "service", ?error, "Failed to revoke sessions"),
+        }
+    }
+
+    deprovision(app_state, Some(user_id)).await
+}
+
+/// Starts the deletion of every stable server of the deleted accounts. Busy
+/// servers are left for the next run.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of a single deleted user, `None` for all of them.
+///
+/// # Returns
+///
+/// Number of servers whose deletion started.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn deprovision(app_state: &AppState, user_id: Option<Uuid>) -> Result<usize> {
+    let mut count = 0;
+    for (user_id, server_id) in
+        queries::get_deprovisioning_servers(&app_state.pool, user_id).await?
+    {
+        let result = services::set_transient_status(
+            &app_state.pool,
+            user_id,
+            server_id,
+            ServerStatus::Deleting,
+        )
+        .await;
+        match result {
+            Ok(old_status) => {
+                tokio::spawn(deletion::run(
+                    app_state.clone(),
+                    user_id,
+                    server_id,
+                    old_status,
+                ));
+                count += 1;
+            }
+            Err(error) => {
+                tracing::warn!(target: "service", %server_id, ?error, "Failed to start deprovisioning");
+            }
+        }
+    }
+    tracing::info!(target: "service", count, "Deprovisioning started");
+
+    Ok(count)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Trims a profile field, rejecting empty and too long values.
+///
+fn validate_field(name: &str, value: Option<String>) -> Result<Option<String>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let value = value.trim();
+    if value.is_empty() || value.len() > MAX_FIELD_LEN {
+        return Err(Error::Validation(format!(
+            "Field '{name}' must be between 1 and {MAX_FIELD_LEN} characters"
+        )));
+    }
+
+    Ok(Some(value.to_owned()))
+}
+
+/// Trims a phone number, accepting digits with an optional leading `+` and
+/// the usual separators.
+///
+fn validate_phone(value: Option<String>) -> Result<Option<String>> {
+    let Some(value) = validate_field("phone_number", value)? else {
+        return Ok(None);
+    };
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    let allowed = value
+        .trim_start_matches('+')
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '-' | '.' | '(' | ')'));
+    if !allowed || !(7..=15).contains(&digits) {
+        return Err(Error::Validation(format!("Invalid phone number '{value}'")));
+    }
+
+    Ok(Some(value))
+}
+
+/// Normalizes a preferred language tag to the supported language, e.g. `de-AT`
+/// to `de`.
+///
+fn validate_locale(value: Option<String>) -> Result<Option<String>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let Some(locale) = Locale::parse(&value) else {
+        let supported = Locale::ALL.map(Locale::code).join(", ");
+        return Err(Error::Validation(format!(
+            "Unsupported locale '{value}', supported: {supported}"
+        )));
+    };
+
+    Ok(Some(locale.code().to_owned()))
+}
+
+/// Normalizes an email address for storing and comparing it, as the addresses
+/// are compared case-insensitively.
+///
+/// # Arguments
+///
+/// * `email`: Email address as entered by the user.
+///
+/// # Returns
+///
+/// Trimmed and lowercased address.
+///
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Normalizes an email address and checks its shape.
+///
+fn validate_email(value: &str) -> Result<String> {
+    let email = normalize_email(value);
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+                && !email.chars().any(char::is_whitespace)
+                && email.len() <= MAX_FIELD_LEN
+        }
+        None => false,
+    };
+    if !valid {
+        return Err(Error::Validation(format!(
+            "Invalid email address '{email}'"
+        )));
+    }
+
+    Ok(email)
+}
+
+/// Generates a random token, like the confirmation token of an email address.
+///
+/// # Returns
+///
+/// The token to send to the user and its hash to store.
+///
+pub fn new_token() -> (String, String) {
+    let token = hex::encode(rand::rng().random::<[u8; 32]>());
+    let token_hash = hash_token(&token);
+
+    (token, token_hash)
+}
+
+/// Returns the hex encoded SHA-256 hash of a token, the only form in which the
+/// token is stored.
+///
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Error of an email address that already belongs to a user.
+///
+fn email_in_use() -> Error {
+    Error::Conflict("Email address is already in use".to_owned())
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_profile_values_should_be_rejected() {
+        // Arrange
+        let phone = |value: &str| validate_phone(Some(value.to_owned()));
+
+        // Act
+        let valid_phone = phone(" +1 (555) 123-4567 ");
+        let short_phone = phone("12-34");
+        let letter_phone = phone("+1 555 CALL NOW");
+        let blank_name = validate_field("first_name", Some("  ".to_owned()));
+        let valid_email = validate_email(" User@Example.COM ");
+        let invalid_emails =
+            ["user", "@example.com", "user@example", "us er@example.com"].map(validate_email);
+
+        // Assert
+        assert_eq!(valid_phone.unwrap().as_deref(), Some("+1 (555) 123-4567"));
+        assert!(short_phone.is_err());
+        assert!(letter_phone.is_err());
+        assert!(blank_name.is_err());
+        assert_eq!(valid_email.unwrap(), "user@example.com");
+        assert!(invalid_emails.iter().all(Result::is_err));
+    }
+}
@@ -0,0 +1,548 @@
+use crate::config::WebhookEnv;
+use crate::model::queries;
+use crate::model::types::{
+    ApiWebhook, ApiWebhookDelivery, DueDelivery, WebhookAttempt, WebhookDeliveryStatus,
+    WebhookEvent,
+};
+use crate::state::AppState;
+use crate::web::types::WebhookPayload;
+use chrono::{DateTime, Duration, Utc};
+use dashboard_common::prelude::{Error, Result};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use sha2::Sha256;
+use sqlx::{Executor, Postgres};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Header with the signature of a delivery, in the `t={timestamp},v1={hex}`
+/// format. The signature is an HMAC-SHA256 of `{timestamp}.{body}`.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Header with the name of the delivered event.
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+/// Header with the ID of the delivery, the same for all its attempts.
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// Number of latest deliveries returned for a webhook.
+const DELIVERIES_LIMIT: i64 = 50;
+
+/// Longest delay between two attempts of a delivery, in seconds.
+const MAX_RETRY_DELAY_SEC: i64 = 24 * 3600;
+
+/// Registers a webhook for the user and generates its signing secret.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user.
+/// * `payload`: Address of the receiver and the subscribed events.
+///
+/// # Returns
+///
+/// Created webhook, the only response that contains its secret.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn create_webhook(
+    app_state: &AppState,
+    user_id: Uuid,
+    payload: WebhookPayload,
+) -> Result<ApiWebhook> {
+    let url = validate_url(&payload.url)?;
+    resolve(&url, app_state.config.webhook.allow_private_targets).await?;
+    let mut events = Vec::new();
+    for event in payload.events {
+        if !events.contains(&event) {
+            events.push(event);
+        }
+    }
+    if events.is_empty() {
+        return Err(Error::Validation(
+            "Webhook must subscribe to at least one event".to_owned(),
+        ));
+    }
+
+    let secret = format!("whsec_{}", hex::encode(rand::rng().random::<[u8; 24]>()));
+    let webhook =
+        queries::create_webhook(&app_state.pool, user_id, url.as_str(), &secret, &events).await?;
+    tracing::info!(target: "service", webhook_id = %webhook.id, "Webhook created");
+
+    Ok(webhook)
+}
+
+/// Deletes a webhook of the user together with its deliveries.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the webhook.
+/// * `webhook_id`: ID of the webhook.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn delete_webhook(app_state: &AppState, user_id: Uuid, webhook_id: Uuid) -> Result<()> {
+    if !queries::delete_webhook(&app_state.pool, user_id, webhook_id).await? {
+        return Err(not_found(webhook_id));
+    }
+    tracing::info!(target: "service", %webhook_id, "Webhook deleted");
+
+    Ok(())
+}
+
+/// Returns the latest deliveries of a webhook with all their attempts.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `user_id`: ID of the user who owns the webhook.
+/// * `webhook_id`: ID of the webhook.
+///
+/// # Returns
+///
+/// Deliveries of the webhook, newest first.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn get_deliveries(
+    app_state: &AppState,
+    user_id: Uuid,
+    webhook_id: Uuid,
+) -> Result<Vec<ApiWebhookDelivery>> {
+    let webhooks = queries::get_webhooks(&app_state.pool, user_id).await?;
+    if !webhooks.iter().any(|webhook| webhook.id == webhook_id) {
+        return Err(not_found(webhook_id));
+    }
+
+    let mut deliveries =
+        queries::get_webhook_deliveries(&app_state.pool, user_id, webhook_id, DELIVERIES_LIMIT)
+            .await?;
+    let delivery_ids = deliveries
+        .iter()
+        .map(|delivery| delivery.id)
+        .collect::<Vec<_>>();
+    for attempt in queries::get_webhook_attempts(&app_state.pool, &delivery_ids).await? {
+        if let Some(delivery) = deliveries
+            .iter_mut()
+            .find(|delivery| delivery.id == attempt.delivery_id)
+        {
+            delivery.attempts.push(attempt);
+        }
+    }
+
+    Ok(deliveries)
+}
+
+/// Queues an event for every webhook of the user subscribed to it. Called by
+/// the webhook consumer of the domain events, within the transaction that
+/// moves its cursor, so the event is queued exactly once.
+///
+/// # Arguments
+///
+/// * `executor`: Database executor (pool or transaction).
+/// * `user_id`: ID of the user the event belongs to.
+/// * `event`: Lifecycle event.
+/// * `data`: Data of the event, sent in the `data` field of the body.
+///
+/// # Returns
+///
+/// Empty `Ok(())` on success.
+///
+pub async fn publish<'e, E>(
+    executor: E,
+    user_id: Uuid,
+    event: WebhookEvent,
+    data: serde_json::Value,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let count = queries::create_webhook_deliveries(executor, user_id, event, &data).await?;
+    if count > 0 {
+        tracing::info!(target: "service", %event, count, "Webhook event queued");
+    }
+
+    Ok(())
+}
+
+/// Attempts every pending delivery that is due and schedules the failed ones
+/// for a retry, until they run out of attempts. The deliveries are claimed
+/// first and sent outside of any transaction, each attempt being recorded on
+/// its own.
+///
+/// # Arguments
+///
+/// * `app_state`: Shared application state.
+/// * `now`: Current time.
+///
+/// # Returns
+///
+/// Number of delivered events.
+///
+#[tracing::instrument(level = "trace", target = "service", skip(app_state))]
+pub async fn deliver_due(app_state: &AppState, now: DateTime<Utc>) -> Result<usize> {
+    let settings = &app_state.config.webhook;
+    let lease_sec = (settings.timeout_sec as i64 + 1).saturating_mul(settings.batch_size);
+    let mut count = 0;
+
+    let deliveries = queries::claim_due_webhook_deliveries(
+        &app_state.pool,
+        now,
+        now + Duration::seconds(lease_sec),
+        settings.batch_size,
+    )
+    .await?;
+    for delivery in deliveries {
+        let attempt = match client(settings, &delivery.url).await {
+            Ok(client) => send(&client, &delivery, app_state.clock.now()).await,
+            Err(error) => WebhookAttempt {
+                response_status: None,
+                error: Some(error.to_string()),
+                duration_ms: 0,
+            },
+        };
+        let attempts = delivery.attempts + 1;
+        let (status, next_attempt_at) = match attempt.error {
+            None => (WebhookDeliveryStatus::Delivered, now),
+            Some(_) if attempts >= settings.max_attempts => (WebhookDeliveryStatus::Failed, now),
+            Some(_) => (
+                WebhookDeliveryStatus::Pending,
+                now + retry_delay(settings.retry_base_sec, attempts),
+            ),
+        };
+
+        match status {
+            WebhookDeliveryStatus::Delivered => count += 1,
+            _ => {
+                tracing::warn!(target: "service", delivery_id = %delivery.id, attempts, %status, error = ?attempt.error, "Webhook delivery failed")
+            }
+        }
+        let mut transaction = app_state.pool.begin().await?;
+        queries::record_webhook_attempt(
+            &mut transaction,
+            delivery.id,
+            &attempt,
+            status,
+            next_attempt_at,
+        )
+        .await?;
+        transaction.commit().await?;
+    }
+
+    Ok(count)
+}
+
+/// Signs the body of a delivery.
+///
+/// # Arguments
+///
+/// * `secret`: Signing secret of the webhook.
+/// * `timestamp`: Unix timestamp of the attempt.
+/// * `body`: Raw body of the request.
+///
+/// # Returns
+///
+/// Hex-encoded HMAC-SHA256 of `{timestamp}.{body}`.
+///
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC should accept keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// -----------------------------------------------------------------------------
+
+/// Sends a single signed attempt of the delivery. Only a `2xx` response counts
+/// as delivered.
+///
+async fn send(client: &Client, delivery: &DueDelivery, now: DateTime<Utc>) -> WebhookAttempt {
+    let body = serde_json::json!({
+        "id": delivery.id,
+        "event": delivery.event,
+        "created_at": delivery.created_at,
+        "data": delivery.payload,
+    })
+    .to_string();
+    let timestamp = now.timestamp();
+    let signature = sign(&delivery.secret, timestamp, &body);
+
+    let started = Instant::now();
+    let result = client
+        .post(&delivery.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, format!("t={timestamp},v1={signature}"))
+        .header(EVENT_HEADER, &delivery.event)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .body(body)
+        .send()
+        .await;
+    let duration_ms = i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX);
+
+    match result {
+        Ok(response) => {
+            let status = response.status();
+            WebhookAttempt {
+                response_status: Some(status.as_u16() as i32),
+                error: (!status.is_success())
+                    .then(|| format!("Receiver responded with status {status}")),
+                duration_ms,
+            }
+        }
+        Err(error) => WebhookAttempt {
+            response_status: None,
+            error: Some(error.to_string()),
+            duration_ms,
+        },
+    }
+}
+
+/// Returns the delay before the next attempt, growing four times with every
+/// failed attempt, up to a day.
+///
+fn retry_delay(base_sec: i64, attempts: i32) -> Duration {
+    let factor = 4_i64.saturating_pow(attempts.saturating_sub(1).max(0) as u32);
+    Duration::seconds(base_sec.saturating_mul(factor).min(MAX_RETRY_DELAY_SEC))
+}
+
+/// Checks that the address of a receiver is an absolute HTTP or HTTPS URL.
+///
+fn validate_url(url: &str) -> Result<Url> {
+    let invalid = || Error::Validation(format!("Invalid webhook URL {url}"));
+    let url = Url::parse(url.trim()).map_err(|_| invalid())?;
+
+    match matches!(url.scheme(), "http" | "https") && url.host().is_some() {
+        true => Ok(url),
+        false => Err(invalid()),
+    }
+}
+
+/// Resolves the host of a receiver and checks that all its addresses are
+/// public, so a webhook can't reach the internal network of the dashboard.
+///
+/// # Returns
+///
+/// Addresses of the receiver, none when private targets are allowed.
+///
+async fn resolve(url: &Url, allow_private: bool) -> Result<Vec<SocketAddr>> {
+    if allow_private {
+        return Ok(Vec::new());
+    }
+
+    let port = url.port_or_known_default().unwrap_or(80);
+    let host = url.host_str().unwrap_or_default();
+    let addresses = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map(|addresses| addresses.collect())
+            .unwrap_or_default(),
+    };
+    match !addresses.is_empty() && addresses.iter().all(|address| is_public(address.ip())) {
+        true => Ok(addresses),
+        false => Err(Error::Validation(format!(
+            "Webhook URL {url} must resolve to public addresses"
+        ))),
+    }
+}
+
+/// Builds the client of a single delivery, connecting only to the checked
+/// addresses of the receiver and not following redirects, which could lead
+/// to a private address.
+///
+async fn client(settings: &WebhookEnv, url: &str) -> Result<Client> {
+    let url = validate_url(url)?;
+    let addresses = resolve(&url, settings.allow_private_targets).await?;
+    let mut builder = Client::builder()
+        .timeout(std::time::Duration::from_secs(settings.timeout_sec))
+        .redirect(Policy::none());
+    if let Some(domain) = url.domain().filter(|_| !addresses.is_empty()) {
+        builder = builder.resolve_to_addrs(domain, &addresses);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Checks that an address is reachable over the internet, rejecting the
+/// private, loopback, link-local and other reserved ranges.
+///
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || a >= 240
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 198 && (18..20).contains(&b)))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let [a, b, ..] = ip.segments();
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        || (a == 0x2001 && b == 0x0db8))
+}
+
+/// Reports a missing webhook.
+///
+fn not_found(webhook_id: Uuid) -> Error {
+    Error::NotFound(format!("Webhook {webhook_id}"))
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn delivery(url: String) -> DueDelivery {
+        DueDelivery {
+            id: Uuid::new_v4(),
+            url,
+            secret: "whsec_test".to_owned(),
+            event: WebhookEvent::ServerCreated.to_string(),
+            payload: serde_json::json!({ "server_id": Uuid::new_v4() }),
+            attempts: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn sign_should_match_known_signature() {
+        // Act
+        let signature = sign("key", 1_700_000_000, r#"{"event":"server.created"}"#);
+
+        // Assert
+        assert_eq!(
+            signature,
+            "3a1c72b60e12af006deb581fecdb441f14e17df8cd5c5ce1d314ee86190ae86f"
+        );
+    }
+
+    #[test]
+    fn retry_delay_should_grow_up_to_a_day() {
+        // Assert
+        assert_eq!(retry_delay(60, 1), Duration::minutes(1));
+        assert_eq!(retry_delay(60, 2), Duration::minutes(4));
+        assert_eq!(retry_delay(60, 3), Duration::minutes(16));
+        assert_eq!(retry_delay(60, 40), Duration::days(1));
+    }
+
+    #[test]
+    fn validate_url_should_accept_only_http_urls() {
+        // Assert
+        assert!(validate_url("https://example.com/hooks").is_ok());
+        assert!(validate_url(" http://93.184.215.14:8080 ").is_ok());
+        assert!(matches!(
+            validate_url("ftp://example.com"),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            validate_url("example.com/hooks"),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolve_should_reject_private_addresses() {
+        // Arrange
+        let urls = [
+            "http://10.0.0.5:8080",
+            "http://127.0.0.1",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1",
+            "http://0.0.0.0",
+            "http://[::1]",
+            "http://[fd00::1]",
+            "http://[fe80::1]",
+            "http://[::ffff:10.0.0.5]",
+        ];
+
+        // Act
+        let mut results = Vec::new();
+        for url in urls {
+            results.push(resolve(&validate_url(url).unwrap(), false).await);
+        }
+        let public = resolve(&validate_url("http://93.184.215.14").unwrap(), false).await;
+        let allowed = resolve(&validate_url("http://10.0.0.5").unwrap(), true).await;
+
+        // Assert
+        assert!(
+            results
+                .iter()
+                .all(|result| matches!(result, Err(Error::Validation(_))))
+        );
+        assert_eq!(public.unwrap().len(), 1);
+        assert!(allowed.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_should_sign_request_and_report_status() {
+        // Arrange
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/"))
+            .and(header(EVENT_HEADER, "server.created"))
+            .and(header_exists(SIGNATURE_HEADER))
+            .and(header_exists(DELIVERY_HEADER))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Client::new();
+
+        // Act
+        let delivered = send(&client, &delivery(server.uri()), Utc::now()).await;
+        let failed = send(
+            &client,
+            &delivery(format!("{}/missing", server.uri())),
+            Utc::now(),
+        )
+        .await;
+
+        // Assert
+        assert_eq!(delivered.response_status, Some(204));
+        assert_eq!(delivered.error, None);
+        let request = &server.received_requests().await.unwrap()[0];
+        let signature = request.headers[SIGNATURE_HEADER].to_str().unwrap();
+        let (timestamp, signature) = signature
+            .strip_prefix("t=")
+            .and_then(|value| value.split_once(",v1="))
+            .unwrap();
+        let body = String::from_utf8(request.body.clone()).unwrap();
+        assert_eq!(
+            signature,
+            sign("whsec_test", timestamp.parse().unwrap(), &body)
+        );
+        assert!(failed.error.is_some());
+    }
+}
@@ -1,14 +1,68 @@
-use crate::config::Config;
+use crate::broker::Broker;
+use crate::captcha::CaptchaProvider;
+use crate::clock::Clock;
+use crate::cluster::Cluster;
+use crate::config::{Config, RuntimeEnv};
+use crate::mail::Mailer;
+use crate::model::cache::{Catalog, TtlCache};
+use crate::model::replica::Replica;
+use crate::model::types::{ApiGuestInfo, ApiNode};
+use crate::payments::PaymentProvider;
 use crate::proxmox::Proxmox;
+use crate::proxmox::queue::RequestQueue;
+use crate::proxmox::types::VmRef;
+use arc_swap::ArcSwap;
+use ipnet::IpNet;
 use sqlx::PgPool;
 use std::sync::Arc;
+use uuid::Uuid;
 
-/// Holds the application's shared state, like the database connection pool and
-/// the Proxmox client across Axum handlers.
+/// Holds the application's shared state, like the database connection pool, the
+/// Proxmox client, the payment provider and the mailer across Axum handlers.
+///
+/// The settings that may change at runtime are kept apart from the static
+/// configuration, so every clone of the state sees the replaced settings.
+///
+/// Writes and transactions use the primary `pool`, reads that tolerate the lag
+/// of the replica use the `reader` pool.
+///
+/// Logins and registrations require a solved CAPTCHA after suspicious activity
+/// only with a `captcha` provider.
+///
+/// Services waiting for Proxmox and the scheduler read the time from the
+/// `clock`, which the tests replace to skip the waits.
+///
+/// The domain events committed by any replica reach the live subscribers of
+/// this one through the `broker`.
 ///
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
+    pub replica: Option<Replica>,
     pub proxmox: Arc<dyn Proxmox + Send + Sync>,
+    pub proxmox_queue: Arc<RequestQueue>,
+    pub payments: Arc<dyn PaymentProvider + Send + Sync>,
+    pub mailer: Arc<dyn Mailer + Send + Sync>,
     pub config: Config,
+    pub runtime: Arc<ArcSwap<RuntimeEnv>>,
+    pub catalog: Arc<Catalog>,
+    pub nodes: Arc<TtlCache<(), Vec<ApiNode>>>,
+    pub guests: Arc<TtlCache<VmRef, ApiGuestInfo>>,
+    pub allowlists: Arc<TtlCache<Uuid, Vec<IpNet>>>,
+    pub cluster: Option<Cluster>,
+    pub broker: Broker,
+    pub captcha: Option<Arc<dyn CaptchaProvider + Send + Sync>>,
+    pub clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl AppState {
+    /// Returns the pool for the reads, the replica while it is available and
+    /// the primary otherwise.
+    ///
+    pub fn reader(&self) -> &PgPool {
+        self.replica
+            .as_ref()
+            .and_then(Replica::pool)
+            .unwrap_or(&self.pool)
+    }
 }
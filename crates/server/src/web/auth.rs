@@ -11,10 +11,67 @@ pub struct Claims {
 }
 
 pub mod password {
+    use argon2::{Config, Variant, Version};
     use dashboard_common::prelude::{AuthError, Error, Result};
+    use md5::{Digest, Md5};
     use rand::Rng;
 
-    /// Hashes a password using Argon2.
+    /// Alphabet of the phpass encoding.
+    const ITOA64: &[u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    /// Format of a stored password hash.
+    ///
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum HashFormat {
+        /// Argon2id, the format of the new hashes.
+        Argon2id,
+        /// Argon2i or Argon2d, from before Argon2id became the default.
+        Argon2,
+        /// Bcrypt in any of its `$2?$` variants, e.g. imported from WHMCS.
+        Bcrypt,
+        /// Portable phpass hash, `$P$` or `$H$`.
+        Phpass,
+        /// MD5 of old WHMCS versions, `md5(salt + password):salt` or unsalted.
+        Md5,
+        Unknown,
+    }
+
+    impl HashFormat {
+        /// Detects the format of a hash from its prefix.
+        ///
+        pub fn detect(hash: &str) -> Self {
+            let has_prefix = |prefixes: &[&str]| prefixes.iter().any(|p| hash.starts_with(p));
+            if has_prefix(&["$argon2id$"]) {
+                HashFormat::Argon2id
+            } else if has_prefix(&["$argon2i$", "$argon2d$"]) {
+                HashFormat::Argon2
+            } else if has_prefix(&["$2a$", "$2b$", "$2x$", "$2y$"]) {
+                HashFormat::Bcrypt
+            } else if has_prefix(&["$P$", "$H$"]) && hash.len() == 34 && hash.is_ascii() {
+                HashFormat::Phpass
+            } else if hash
+                .split(':')
+                .next()
+                .is_some_and(|md5| md5.len() == 32 && md5.bytes().all(|b| b.is_ascii_hexdigit()))
+            {
+                HashFormat::Md5
+            } else {
+                HashFormat::Unknown
+            }
+        }
+    }
+
+    /// Returns the Argon2id settings used for new hashes.
+    ///
+    fn config() -> Config<'static> {
+        Config {
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            ..Config::default()
+        }
+    }
+
+    /// Hashes a password using Argon2id.
     ///
     /// # Arguments
     ///
@@ -26,11 +83,10 @@ pub mod password {
     ///
     pub fn hash(password: &str) -> Result<String> {
         let salt = rand::rng().random::<[u8; 16]>();
-        argon2::hash_encoded(password.as_bytes(), &salt, &argon2::Config::default())
-            .map_err(Error::Hash)
+        argon2::hash_encoded(password.as_bytes(), &salt, &config()).map_err(Error::Hash)
     }
 
-    /// Verifies a password against a hash.
+    /// Verifies a password against a hash of any supported format.
     ///
     /// # Arguments
     ///
@@ -42,11 +98,174 @@ pub mod password {
     /// Empty `Result` if the password is valid.
     ///
     pub fn verify(hash: &str, password: &str) -> Result<()> {
-        match argon2::verify_encoded(hash, password.as_bytes())? {
+        let valid = match HashFormat::detect(hash) {
+            HashFormat::Argon2id | HashFormat::Argon2 => {
+                argon2::verify_encoded(hash, password.as_bytes())?
+            }
+            HashFormat::Bcrypt => bcrypt::verify(password, hash).unwrap_or(false),
+            HashFormat::Phpass => verify_phpass(hash, password),
+            HashFormat::Md5 => verify_md5(hash, password),
+            HashFormat::Unknown => false,
+        };
+
+        match valid {
             true => Ok(()),
             false => Err(Error::Auth(AuthError::Login)),
         }
     }
+
+    /// Checks whether a hash should be replaced with a new one, because it
+    /// isn't an Argon2id hash with the current settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash`: Hash to check.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the password should be hashed again.
+    ///
+    pub fn needs_rehash(hash: &str) -> bool {
+        let config = config();
+        let current = format!(
+            "$argon2id$v=19$m={},t={},p={}$",
+            config.mem_cost, config.time_cost, config.lanes
+        );
+
+        !hash.starts_with(&current)
+    }
+
+    /// Verifies a password against a portable phpass hash: the MD5 of the salt
+    /// and the password, rehashed `2^n` times together with the password.
+    ///
+    fn verify_phpass(hash: &str, password: &str) -> bool {
+        let bytes = hash.as_bytes();
+        let Some(log2) = ITOA64.iter().position(|&c| c == bytes[3]) else {
+            return false;
+        };
+        if !(7..=30).contains(&log2) {
+            return false;
+        }
+
+        let password = password.as_bytes();
+        let mut digest = Md5::new()
+            .chain_update(&bytes[4..12])
+            .chain_update(password)
+            .finalize();
+        for _ in 0..1u32 << log2 {
+            digest = Md5::new()
+                .chain_update(digest)
+                .chain_update(password)
+                .finalize();
+        }
+
+        let expected = format!("{}{}", &hash[..12], encode_phpass(&digest));
+        constant_eq(&expected, hash)
+    }
+
+    /// Encodes bytes with the phpass variant of base64.
+    ///
+    fn encode_phpass(input: &[u8]) -> String {
+        let mut output = String::new();
+        for chunk in input.chunks(3) {
+            let value = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |value, (i, &byte)| value | ((byte as u32) << (8 * i)));
+            for i in 0..=chunk.len() {
+                output.push(ITOA64[((value >> (6 * i)) & 0x3f) as usize] as char);
+            }
+        }
+
+        output
+    }
+
+    /// Verifies a password against an MD5 hash of old WHMCS versions, salted
+    /// as `md5(salt + password):salt` or unsalted.
+    ///
+    fn verify_md5(hash: &str, password: &str) -> bool {
+        let (md5, salt) = hash.split_once(':').unwrap_or((hash, ""));
+        let digest = Md5::new()
+            .chain_update(salt)
+            .chain_update(password)
+            .finalize();
+
+        constant_eq(&hex::encode(digest), &md5.to_ascii_lowercase())
+    }
+
+    /// Compares two strings in a time that doesn't depend on where they differ.
+    ///
+    fn constant_eq(a: &str, b: &str) -> bool {
+        a.len() == b.len()
+            && a.bytes()
+                .zip(b.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    // -------------------------------------------------------------------------
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn new_hash_should_be_current_argon2id() {
+            // Arrange
+            let password = "secure_password";
+
+            // Act
+            let hash = hash(password).unwrap();
+
+            // Assert
+            assert_eq!(HashFormat::detect(&hash), HashFormat::Argon2id);
+            assert!(!needs_rehash(&hash));
+            assert!(verify(&hash, password).is_ok());
+            assert!(verify(&hash, "wrong_password").is_err());
+        }
+
+        #[test]
+        fn bcrypt_hash_should_be_verified_and_rehashed() {
+            // Arrange
+            let password = "secure_password";
+            let hash = bcrypt::hash(password, 4)
+                .unwrap()
+                .replacen("$2b$", "$2y$", 1);
+
+            // Act
+            let format = HashFormat::detect(&hash);
+
+            // Assert
+            assert_eq!(format, HashFormat::Bcrypt);
+            assert!(needs_rehash(&hash));
+            assert!(verify(&hash, password).is_ok());
+            assert!(verify(&hash, "wrong_password").is_err());
+            assert!(verify("5f4dcc3b5aa765d61d8327deb882cf99", password).is_err());
+        }
+
+        #[test]
+        fn legacy_whmcs_hashes_should_be_verified() {
+            // Arrange
+            let phpass = "$P$9IQRaTwmfeRo7ud9Fh4E2PdI0S3r.L0";
+            let salted_md5 = "8eae1257b3dae442ea29a26d9abd58b6:xY9#!";
+            let plain_md5 = "5f4dcc3b5aa765d61d8327deb882cf99";
+
+            // Act
+            let formats = [phpass, salted_md5, plain_md5].map(HashFormat::detect);
+
+            // Assert
+            assert_eq!(
+                formats,
+                [HashFormat::Phpass, HashFormat::Md5, HashFormat::Md5]
+            );
+            assert!(verify(phpass, "test12345").is_ok());
+            assert!(verify(phpass, "test12346").is_err());
+            assert!(verify(salted_md5, "secure_password").is_ok());
+            assert!(verify(salted_md5, "wrong_password").is_err());
+            assert!(verify(plain_md5, "password").is_ok());
+            assert!(needs_rehash(phpass) && needs_rehash(salted_md5));
+        }
+    }
 }
 
 pub mod token {
@@ -80,7 +299,7 @@ pub mod token {
         let token = encode(
             &header,
             &claims,
-            &EncodingKey::from_secret(token_settings.secret.expose_secret().as_bytes()),
+            &EncodingKey::from_secret(token_settings.secret.get().expose_secret().as_bytes()),
         )
         .map_err(|_| Error::Auth(AuthError::Token))?;
 
@@ -100,7 +319,7 @@ pub mod token {
     ///
     pub fn validate(token: &str, token_settings: TokenEnv) -> Result<Claims> {
         let decoding_key =
-            DecodingKey::from_secret(token_settings.secret.expose_secret().as_bytes());
+            DecodingKey::from_secret(token_settings.secret.get().expose_secret().as_bytes());
         let validation = Validation::default();
 
         decode::<Claims>(token, &decoding_key, &validation)
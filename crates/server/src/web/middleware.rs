@@ -1,14 +1,57 @@
-use crate::config::Cors;
+use crate::config::{Cors, SecurityEnv};
+use crate::i18n::Locale;
+use crate::model::queries;
+use crate::services::{allowlist, api_token};
 use crate::state::AppState;
-use crate::web::auth::token;
+use crate::web::auth::{Claims, token};
+use axum::Json;
 use axum::body::Body;
-use axum::extract::State;
-use axum::http::Request;
-use axum::http::header::AUTHORIZATION;
+use axum::extract::{ConnectInfo, MatchedPath, State};
+use axum::http::header::{
+    ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE, ETAG,
+    IF_NONE_MATCH,
+};
+use axum::http::{HeaderMap, HeaderValue, Method, Request, StatusCode};
 use axum::middleware::Next;
-use axum::response::Response;
-use dashboard_common::prelude::{AuthError, Error, Result};
-use tower_http::cors::CorsLayer;
+use axum::response::{IntoResponse, Response};
+use dashboard_common::prelude::{ApiError, AuthError, Error, Result};
+use ipnet::IpNet;
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Header carrying the ID of the request and its response.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Header carrying the addresses of the client and the proxies it passed,
+/// the nearest proxy last.
+pub const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Routes exempt from the request timeout. Their handlers run several Proxmox
+/// steps in a row, and dropping them partway would leave a half-applied change
+/// behind, so only the polling limits of their steps bound them.
+const UNTIMED_ROUTES: [(Method, &str); 13] = [
+    (Method::POST, "/servers/{id}/ips"),
+    (Method::DELETE, "/servers/{id}/ips/{address}"),
+    (Method::PUT, "/servers/{id}/firewall"),
+    (Method::POST, "/servers/{id}/firewall/rules"),
+    (Method::DELETE, "/servers/{id}/firewall/rules/{rule_id}"),
+    (Method::POST, "/servers/{id}/password"),
+    (Method::POST, "/servers/{id}/disks"),
+    (Method::PUT, "/servers/{id}/disks/{device}/resize"),
+    (Method::PUT, "/servers/{id}/iso"),
+    (Method::DELETE, "/servers/{id}/iso"),
+    (Method::PUT, "/servers/{id}/iso/boot"),
+    (Method::POST, "/transfers/{id}/accept"),
+    (Method::POST, "/admin/servers/{id}/transfer"),
+];
+
+/// Address of the client of a request, stored in the request extensions by
+/// `resolve_client_ip`.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClientIp(pub IpAddr);
 
 /// A middleware to print a blank line after each response.
 ///
@@ -21,9 +64,226 @@ pub async fn log_mapper(res: Response) -> Response {
     res
 }
 
+/// Middleware to complete the error envelope of a failed response. Adds the ID
+/// of the request, so the client can refer to the request in the logs, and
+/// translates a generic message into the language of the `Accept-Language`
+/// header.
+///
+/// The ID itself is set by the `SetRequestIdLayer`, which must wrap this
+/// middleware.
+///
+/// # Arguments
+///
+/// * `request`: Body of the incoming request.
+/// * `next`: `Next` middleware in the chain.
+///
+/// # Returns
+///
+/// Response from the next middleware, with the completed error envelope.
+///
+pub async fn complete_error(request: Request<Body>, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let locale = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Locale::from_accept_language);
+    let response = next.run(request).await;
+
+    let (mut parts, body) = response.into_parts();
+    let error = parts.extensions.remove::<ApiError>();
+    let Some(mut error) = error.filter(|_| request_id.is_some() || locale.is_some()) else {
+        return Response::from_parts(parts, body);
+    };
+    error.request_id = request_id;
+    if let (Some(locale), Some(key)) = (locale, error.message_key) {
+        let mut args = message_args(error.details.as_ref());
+        args.push(("reason".to_owned(), error.message.clone()));
+        let args = args
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        error.message = locale.translate(key, &args);
+        parts
+            .headers
+            .insert(CONTENT_LANGUAGE, HeaderValue::from_static(locale.code()));
+    }
+
+    (parts, Json(error)).into_response()
+}
+
+/// Middleware to resolve the address of the client and store it in the request
+/// extensions as `ClientIp`. Behind a trusted proxy, the address is read from
+/// the `X-Forwarded-For` header.
+///
+/// Requires the server to provide the `ConnectInfo` of the connection, without
+/// it no address is stored.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+/// * `request`: Body of the incoming request.
+/// * `next`: `Next` middleware in the chain.
+///
+/// # Returns
+///
+/// Response from the next middleware.
+///
+pub async fn resolve_client_ip(
+    State(app_state): State<AppState>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip());
+    if let Some(peer) = peer {
+        let ip = client_ip(peer, request.headers(), &app_state.config.proxy.trusted);
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    next.run(request).await
+}
+
+/// Middleware to add the security headers configured in the `security`
+/// section to every response. Headers set by the handler are kept.
+///
+/// Must be the outermost layer, so the responses of the CORS preflights and of
+/// the rejected requests are covered too.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+/// * `request`: Body of the incoming request.
+/// * `next`: `Next` middleware in the chain.
+///
+/// # Returns
+///
+/// Response from the next middleware, with the security headers.
+///
+pub async fn secure_headers(
+    State(app_state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let settings = &app_state.config.security;
+    if settings.enabled {
+        apply_security(settings, response.headers_mut());
+    }
+
+    response
+}
+
+/// Middleware to answer the conditional requests of the clients polling the
+/// API. A successful `GET` response is tagged with a weak ETag of its body,
+/// and a request whose `If-None-Match` header lists the tag gets
+/// `304 Not Modified` without the body. Responses tagged by the handler and
+/// event streams, whose body never ends, are left as they are.
+///
+/// The body is hashed before it is compressed, so the compression layer must
+/// wrap this middleware, and the tag is the same for every encoding.
+///
+/// # Arguments
+///
+/// * `request`: Body of the incoming request.
+/// * `next`: `Next` middleware in the chain.
+///
+/// # Returns
+///
+/// Response from the next middleware, with its ETag.
+///
+pub async fn conditional_get(request: Request<Body>, next: Next) -> Result<Response> {
+    if request.method() != Method::GET {
+        return Ok(next.run(request).await);
+    }
+    let if_none_match = request.headers().get(IF_NONE_MATCH).cloned();
+    let response = next.run(request).await;
+    let streamed = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"text/event-stream"));
+    if response.status() != StatusCode::OK || response.headers().contains_key(ETAG) || streamed {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|error| Error::Any(format!("Failed to read response body: {error}")))?;
+    let etag = etag_of(&bytes);
+    let not_modified = if_none_match
+        .as_ref()
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag));
+    parts.headers.insert(ETAG, HeaderValue::from_str(&etag)?);
+    if not_modified {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(CONTENT_LENGTH);
+        return Ok(Response::from_parts(parts, Body::empty()));
+    }
+
+    Ok(Response::from_parts(parts, Body::from(bytes)))
+}
+
+/// Axum middleware failing requests not answered within the timeout with
+/// `504 Gateway Timeout`. The routes in `UNTIMED_ROUTES` run to completion.
+///
+/// # Arguments
+///
+/// * `State(timeout)` - Time a request must be answered within.
+/// * `request`: Body of the incoming request.
+/// * `next`: `Next` middleware in the chain.
+///
+/// # Returns
+///
+/// Response from the next middleware if it is in time.
+///
+pub async fn request_timeout(
+    State(timeout): State<Duration>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response> {
+    let untimed = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|path| is_untimed(request.method(), path.as_str()));
+    if untimed {
+        return Ok(next.run(request).await);
+    }
+
+    tokio::time::timeout(timeout, next.run(request))
+        .await
+        .map_err(|_| Error::Timeout(timeout.as_millis() as f32))
+}
+
+/// Checks whether a route is exempt from the request timeout.
+///
+/// # Arguments
+///
+/// * `method`: Method of the request.
+/// * `path`: Matched path of the route, like `/servers/{id}/disks`.
+///
+pub fn is_untimed(method: &Method, path: &str) -> bool {
+    UNTIMED_ROUTES
+        .iter()
+        .any(|(untimed_method, untimed_path)| untimed_method == method && *untimed_path == path)
+}
+
 /// Axum middleware to require authentication.
 /// Extracts the Bearer token from the `Authorization` header,
 /// validates it, and stores the resulting claims in the request extensions.
+/// The token is either the JWT of a session or an API token of the user, told
+/// apart by the prefix of the latter.
+/// Tokens of deleted users are rejected, as are tokens used from outside the
+/// networks the user allowed. With Redis configured, so are tokens of revoked
+/// sessions. An unavailable Redis fails open: sessions are only revoked when
+/// the account is deleted, which the database check covers on its own.
 ///
 /// # Arguments
 ///
@@ -47,18 +307,313 @@ pub async fn require_auth(
         .and_then(|slice| slice.strip_prefix("Bearer "))
         .ok_or(Error::Auth(AuthError::Token))?;
 
-    let claims = token::validate(token, app_state.config.token)?;
+    let claims = if token.starts_with(api_token::PREFIX) {
+        api_token::authenticate(&app_state, token).await?
+    } else {
+        let claims = token::validate(token, app_state.config.token)?;
+        if let Some(cluster) = &app_state.cluster {
+            match cluster.is_revoked(claims.user_id, claims.iat as i64).await {
+                Ok(true) => return Err(Error::Auth(AuthError::Token)),
+                Ok(false) => {}
+                Err(error) => {
+                    tracing::warn!(target: "handler", ?error, "Redis unavailable, session revocation not checked");
+                }
+            }
+        }
+        claims
+    };
+    if !queries::is_active_user(&app_state.pool, claims.user_id).await? {
+        tracing::warn!(target: "handler", user_id = %claims.user_id, "Token of deleted user");
+        return Err(Error::Auth(AuthError::Token));
+    }
+    let client_ip = request
+        .extensions()
+        .get::<ClientIp>()
+        .map(|ClientIp(ip)| *ip);
+    allowlist::ensure_allowed(&app_state, claims.user_id, client_ip).await?;
     request.extensions_mut().insert(claims);
 
     Ok(next.run(request).await)
 }
 
-/// Configures CORS to allow requests from the local frontend during
-/// development.
+/// Axum middleware to require administrator privileges.
+/// Must run after `require_auth`, which puts the claims into the request
+/// extensions. The privilege is read from the database on every request, so a
+/// revoked administrator loses access without waiting for the token to expire.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+/// * `request`: Body of the incoming request.
+/// * `next`: `Next` middleware in the chain.
+///
+/// # Returns
+///
+/// Response from the next middleware if the user is an administrator.
+///
+pub async fn require_admin(
+    State(app_state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response> {
+    let user_id = request
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.user_id)
+        .ok_or(Error::Auth(AuthError::Token))?;
+
+    if !queries::is_admin(&app_state.pool, user_id).await? {
+        tracing::warn!(target: "handler", %user_id, "Admin access denied");
+        return Err(Error::Auth(AuthError::Forbidden));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Configures CORS to allow requests from the frontends hosted on the
+/// configured origins.
 ///
 pub fn allow_cors(cors: &Cors) -> CorsLayer {
-    CorsLayer::new()
-        .allow_origin(cors.allow_origin())
+    let origin = match cors.any_origin() {
+        true => AllowOrigin::any(),
+        false => AllowOrigin::list(cors.allow_origins()),
+    };
+    let layer = CorsLayer::new()
+        .allow_origin(origin)
         .allow_methods(cors.allow_methods())
         .allow_headers(cors.allow_headers())
+        .expose_headers(cors.expose_headers())
+        .allow_credentials(cors.allow_credentials());
+
+    match cors.max_age() {
+        Some(max_age) => layer.max_age(max_age),
+        None => layer,
+    }
+}
+
+/// Resolves the address of the client from the address of the peer. When the
+/// peer is a trusted proxy, the `X-Forwarded-For` addresses are walked from
+/// the nearest one, skipping the trusted proxies, up to the first address that
+/// isn't trusted. An invalid entry stops the walk, as the addresses before it
+/// can't be trusted either.
+///
+/// IPv4-mapped IPv6 addresses are converted to IPv4.
+///
+/// # Arguments
+///
+/// * `peer`: Address of the peer of the connection.
+/// * `headers`: Headers of the request.
+/// * `trusted`: Networks of the trusted proxies.
+///
+/// # Returns
+///
+/// Address of the client.
+///
+pub fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|network| network.contains(&ip));
+    let mut client = peer.to_canonical();
+    if !is_trusted(client) {
+        return client;
+    }
+
+    let forwarded = headers
+        .get_all(FORWARDED_FOR_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<_>>();
+    for hop in forwarded.into_iter().rev() {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip.to_canonical();
+        if !is_trusted(client) {
+            break;
+        }
+    }
+
+    client
+}
+
+/// Adds the security headers missing from a response.
+///
+/// # Arguments
+///
+/// * `settings`: Security settings.
+/// * `headers`: Headers of the response.
+///
+pub fn apply_security(settings: &SecurityEnv, headers: &mut HeaderMap) {
+    for (name, value) in settings.headers() {
+        headers.entry(name).or_insert(value);
+    }
+}
+
+/// Computes the weak ETag of a response body.
+///
+/// # Arguments
+///
+/// * `body`: Body of the response.
+///
+/// # Returns
+///
+/// Quoted tag of the body, like `W/"0123abcd..."`.
+///
+pub fn etag_of(body: &[u8]) -> String {
+    format!("W/\"{}\"", hex::encode(&Sha256::digest(body)[..16]))
+}
+
+/// Checks whether the value of an `If-None-Match` header lists the tag. Tags
+/// are compared weakly, as the header of a `GET` request requires, and `*`
+/// matches any tag.
+///
+/// # Arguments
+///
+/// * `if_none_match`: Comma-separated tags of the header.
+/// * `etag`: Tag of the current response.
+///
+/// # Returns
+///
+/// `true` if the client already has the response.
+///
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| {
+        let tag = tag.trim();
+        tag.strip_prefix("W/").unwrap_or(tag).to_owned()
+    };
+    let etag = opaque(etag);
+
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// Reads the values of the placeholders of a translated error message from
+/// the details of the error, joining the lists with commas.
+///
+fn message_args(details: Option<&serde_json::Value>) -> Vec<(String, String)> {
+    let Some(serde_json::Value::Object(details)) = details else {
+        return Vec::new();
+    };
+
+    details
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => value.clone(),
+                serde_json::Value::Array(values) => values
+                    .iter()
+                    .map(|value| {
+                        value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), str::to_owned)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                value => value.to_string(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+// -----------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(peer: &str, forwarded: &[&str]) -> String {
+        let trusted =
+            ["127.0.0.0/8", "10.0.0.0/8", "fd00::/8"].map(|cidr| cidr.parse::<IpNet>().unwrap());
+        let mut headers = HeaderMap::new();
+        for value in forwarded {
+            headers.append(FORWARDED_FOR_HEADER, value.parse().unwrap());
+        }
+
+        client_ip(peer.parse().unwrap(), &headers, &trusted).to_string()
+    }
+
+    #[test]
+    fn multi_step_routes_should_be_untimed() {
+        // Act
+        let disk = is_untimed(&Method::POST, "/servers/{id}/disks");
+        let disk_list = is_untimed(&Method::GET, "/servers/{id}/disks");
+        let servers = is_untimed(&Method::GET, "/servers");
+
+        // Assert
+        assert!(disk);
+        assert!(!disk_list);
+        assert!(!servers);
+    }
+
+    #[test]
+    fn client_ip_should_ignore_header_of_untrusted_peer() {
+        // Act
+        let direct = resolve("203.0.113.7", &["198.51.100.1"]);
+        let mapped = resolve("::ffff:203.0.113.7", &[]);
+
+        // Assert
+        assert_eq!(direct, "203.0.113.7");
+        assert_eq!(mapped, "203.0.113.7");
+    }
+
+    #[test]
+    fn client_ip_should_skip_trusted_proxies() {
+        // Act
+        let single = resolve("127.0.0.1", &["198.51.100.1"]);
+        let chain = resolve("127.0.0.1", &["1.1.1.1, 198.51.100.1", "10.0.0.5"]);
+        let spoofed = resolve("127.0.0.1", &["1.1.1.1, bogus, 10.0.0.5"]);
+        let proxies_only = resolve("127.0.0.1", &["10.0.0.5"]);
+        let missing = resolve("127.0.0.1", &[]);
+        let ipv6 = resolve("fd00::1", &["2001:db8::7, fd00::2"]);
+
+        // Assert
+        assert_eq!(single, "198.51.100.1");
+        assert_eq!(chain, "198.51.100.1");
+        assert_eq!(spoofed, "10.0.0.5");
+        assert_eq!(proxies_only, "10.0.0.5");
+        assert_eq!(missing, "127.0.0.1");
+        assert_eq!(ipv6, "2001:db8::7");
+    }
+
+    #[test]
+    fn etag_matches_should_compare_weakly() {
+        // Arrange
+        let etag = etag_of(b"[]");
+
+        // Act
+        let weak = etag_matches(&etag, &etag);
+        let strong = etag_matches(&format!("\"other\", {}", &etag[2..]), &etag);
+        let any = etag_matches("*", &etag);
+        let changed = etag_matches(&etag_of(b"[1]"), &etag);
+
+        // Assert
+        assert!(etag.starts_with("W/\""));
+        assert!(weak);
+        assert!(strong);
+        assert!(any);
+        assert!(!changed);
+    }
+
+    #[test]
+    fn apply_security_should_keep_headers_of_handler() {
+        // Arrange
+        let settings = SecurityEnv::default();
+        let mut headers = HeaderMap::new();
+        headers.insert("www-authenticate", HeaderValue::from_static("Bearer"));
+        headers.insert("referrer-policy", HeaderValue::from_static("same-origin"));
+
+        // Act
+        apply_security(&settings, &mut headers);
+
+        // Assert
+        assert_eq!(headers["www-authenticate"], "Bearer");
+        assert_eq!(headers["referrer-policy"], "same-origin");
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(headers["content-security-policy"], "frame-ancestors 'none'");
+        assert_eq!(
+            headers["strict-transport-security"],
+            "max-age=31536000; includeSubDomains"
+        );
+    }
 }
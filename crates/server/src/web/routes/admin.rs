@@ -0,0 +1,874 @@
+//! Admin routes
+
+use crate::config::{RuntimeEnv, runtime};
+use crate::model::queries;
+use crate::model::types::{
+    ApiAnnouncement, ApiAuditEntry, ApiCompletedTransfer, ApiCreditBalance, ApiEventReplay,
+    ApiIpRange, ApiIpUtilization, ApiNetwork, ApiNode, ApiPromoCode, ApiSearchResults, ApiStorage,
+    NewPromoCode, QuotaLimits,
+};
+use crate::services::{
+    announcement, credit, event, network, node, search as search_service, transfer,
+};
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use crate::web::types::{
+    AdminTransferPayload, AnnouncementPayload, AuditLogQuery, IpRangePayload, IssueCreditPayload,
+    NewNetworkPayload, ReplayEventsPayload, Response, SearchQuery,
+};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, post, put};
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
+
+/// Number of latest audit log entries returned.
+const AUDIT_LOG_LIMIT: i64 = 100;
+
+/// Defines routes for the admin section. All routes require authentication and
+/// administrator privileges.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/admin/credits", post(issue_credit))
+        .route(
+            "/admin/promo-codes",
+            get(list_promo_codes).post(create_promo_code),
+        )
+        .route("/admin/quotas/users/{id}", put(set_account_quota))
+        .route("/admin/quotas/products/{id}", put(set_product_quota))
+        .route("/admin/networks", post(create_network))
+        .route("/admin/networks/{id}/ranges", post(set_ip_range))
+        .route("/admin/networks/utilization", get(get_ip_utilization))
+        .route("/admin/nodes", get(list_nodes))
+        .route("/admin/nodes/{node}/storages", get(list_storages))
+        .route("/admin/runtime", get(get_runtime).put(set_runtime))
+        .route("/admin/catalog/cache", delete(invalidate_catalog))
+        .route("/admin/search", get(search))
+        .route("/admin/servers/{id}/transfer", post(transfer_server))
+        .route(
+            "/admin/announcements",
+            get(list_announcements).post(create_announcement),
+        )
+        .route(
+            "/admin/announcements/{id}",
+            put(update_announcement).delete(delete_announcement),
+        )
+        .route("/admin/audit-log", get(get_audit_log))
+        .route("/admin/events/replay", post(replay_events))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            mw::require_admin,
+        ))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Issues credit to a user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   administrator's ID.
+/// * `Json(payload)`: Receiving user, amount and reason of the credit.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the user's updated credit balance.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/credits",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = IssueCreditPayload,
+    responses(
+        (status = 200, body = Response<ApiCreditBalance>, description = "Credit issued"),
+        (status = 400, body = ApiError, description = "Invalid credit amount"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "User not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn issue_credit(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<IssueCreditPayload>,
+) -> Result<Json<Response<ApiCreditBalance>>> {
+    let balance = credit::issue_credit(
+        &app_state,
+        claims.user_id,
+        payload.user_id,
+        payload.amount_cents,
+        payload.reason,
+    )
+    .await?;
+    tracing::info!(target: "handler", user_id = %payload.user_id, "Credit issued");
+
+    Ok(Json(Response::new(balance)))
+}
+
+/// Returns the list of all promo codes.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the list of promo codes.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/promo-codes",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiPromoCode>>, description = "Promo codes found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_promo_codes(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiPromoCode>>>> {
+    let promo_codes = queries::get_promo_codes(&app_state.pool).await?;
+    tracing::info!(target: "handler", count = promo_codes.len(), "Found promo codes");
+
+    Ok(Json(Response::new(promo_codes)))
+}
+
+/// Creates a new promo code.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Code, amount and redemption limits of the promo code.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created promo code.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/promo-codes",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = NewPromoCode,
+    responses(
+        (status = 200, body = Response<ApiPromoCode>, description = "Promo code created"),
+        (status = 400, body = ApiError, description = "Invalid promo code"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_promo_code(
+    State(app_state): State<AppState>,
+    Json(payload): Json<NewPromoCode>,
+) -> Result<Json<Response<ApiPromoCode>>> {
+    let promo_code = credit::create_promo_code(&app_state, payload).await?;
+    tracing::info!(target: "handler", code = %promo_code.code, "Promo code created");
+
+    Ok(Json(Response::new(promo_code)))
+}
+
+/// Sets the quota of a user's account, replacing the configured defaults for
+/// that account.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Path(user_id)`: ID of the user.
+/// * `Json(limits)`: New limits, missing ones are unlimited.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the saved limits.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/quotas/users/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "User ID")),
+    request_body = QuotaLimits,
+    responses(
+        (status = 200, body = Response<QuotaLimits>, description = "Quota saved"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn set_account_quota(
+    State(app_state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Json(limits): Json<QuotaLimits>,
+) -> Result<Json<Response<QuotaLimits>>> {
+    queries::set_account_quota(&app_state.pool, user_id, &limits).await?;
+    tracing::info!(target: "handler", %user_id, "Account quota saved");
+
+    Ok(Json(Response::new(limits)))
+}
+
+/// Sets the quota of a product, limiting its servers within every account.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Path(product_id)`: ID of the product.
+/// * `Json(limits)`: New limits, missing ones are unlimited.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the saved limits.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/quotas/products/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    request_body = QuotaLimits,
+    responses(
+        (status = 200, body = Response<QuotaLimits>, description = "Quota saved"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn set_product_quota(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Json(limits): Json<QuotaLimits>,
+) -> Result<Json<Response<QuotaLimits>>> {
+    queries::set_product_quota(&app_state.pool, product_id, &limits).await?;
+    tracing::info!(target: "handler", %product_id, "Product quota saved");
+
+    Ok(Json(Response::new(limits)))
+}
+
+/// Creates a network from a CIDR block, generating all its usable addresses.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Json(payload)`: Datacenter, CIDR block and optional gateway.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created network.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/networks",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = NewNetworkPayload,
+    responses(
+        (status = 200, body = Response<ApiNetwork>, description = "Network created"),
+        (status = 400, body = ApiError, description = "Invalid CIDR block or gateway"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_network(
+    State(app_state): State<AppState>,
+    Json(payload): Json<NewNetworkPayload>,
+) -> Result<Json<Response<ApiNetwork>>> {
+    let network = network::create_network(&app_state.pool, payload).await?;
+    tracing::info!(target: "handler", network_id = %network.id, addresses = network.addresses, "Network created");
+
+    Ok(Json(Response::new(network)))
+}
+
+/// Reserves a range of a network's addresses, excluding it from allocation,
+/// or returns it to the pool.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Path(network_id)`: ID of the network.
+/// * `Json(payload)`: First and last address of the range, and whether to
+///   reserve it.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated range.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/networks/{id}/ranges",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Network ID")),
+    request_body = IpRangePayload,
+    responses(
+        (status = 200, body = Response<ApiIpRange>, description = "Range updated"),
+        (status = 400, body = ApiError, description = "Invalid range or network"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn set_ip_range(
+    State(app_state): State<AppState>,
+    Path(network_id): Path<Uuid>,
+    Json(payload): Json<IpRangePayload>,
+) -> Result<Json<Response<ApiIpRange>>> {
+    let range = network::set_range_reserved(&app_state.pool, network_id, payload).await?;
+    tracing::info!(target: "handler", %network_id, updated = range.updated, "IP range updated");
+
+    Ok(Json(Response::new(range)))
+}
+
+/// Returns the utilization of the IP pool of every datacenter.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the utilization per datacenter.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/networks/utilization",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiIpUtilization>>, description = "Utilization found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn get_ip_utilization(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiIpUtilization>>>> {
+    let utilization = queries::get_ip_utilization(&app_state.pool).await?;
+    tracing::info!(target: "handler", count = utilization.len(), "Found IP utilization");
+
+    Ok(Json(Response::new(utilization)))
+}
+
+/// Returns the CPU, memory and storage usage of every Proxmox node, with the
+/// number of servers placed on it.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the nodes.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/nodes",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiNode>>, description = "Nodes found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_nodes(State(app_state): State<AppState>) -> Result<Json<Response<Vec<ApiNode>>>> {
+    let nodes = node::list_nodes(&app_state).await?;
+    tracing::info!(target: "handler", count = nodes.len(), "Found nodes");
+
+    Ok(Json(Response::new(nodes)))
+}
+
+/// Returns the storages of a Proxmox node, to pick the storage new servers are
+/// cloned to.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(node)`: Name of the node.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the storages.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/nodes/{node}/storages",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("node" = String, Path, description = "Name of the node")),
+    responses(
+        (status = 200, body = Response<Vec<ApiStorage>>, description = "Storages found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_storages(
+    State(app_state): State<AppState>,
+    Path(node): Path<String>,
+) -> Result<Json<Response<Vec<ApiStorage>>>> {
+    let storages = app_state
+        .proxmox
+        .list_storages(&node)
+        .await?
+        .into_iter()
+        .map(ApiStorage::from)
+        .collect::<Vec<_>>();
+    tracing::info!(target: "handler", count = storages.len(), "Found storages");
+
+    Ok(Json(Response::new(storages)))
+}
+
+/// Returns the current runtime settings.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the runtime settings.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/runtime",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<RuntimeEnv>, description = "Runtime settings found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn get_runtime(State(app_state): State<AppState>) -> Result<Json<Response<RuntimeEnv>>> {
+    let settings = RuntimeEnv::clone(&app_state.runtime.load());
+
+    Ok(Json(Response::new(settings)))
+}
+
+/// Replaces the runtime settings, without a restart. The settings of the other
+/// replicas are not changed, and the settings are replaced again when the
+/// reload file changes.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   administrator's ID.
+/// * `Json(payload)`: New runtime settings, missing ones take their defaults.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the new runtime settings.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/runtime",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = RuntimeEnv,
+    responses(
+        (status = 200, body = Response<RuntimeEnv>, description = "Runtime settings replaced"),
+        (status = 400, body = ApiError, description = "Invalid runtime settings"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn set_runtime(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<RuntimeEnv>,
+) -> Result<Json<Response<RuntimeEnv>>> {
+    runtime::apply(&app_state.runtime, payload.clone())?;
+    tracing::info!(target: "handler", runtime = ?payload, "Runtime settings replaced");
+
+    Ok(Json(Response::new(payload)))
+}
+
+/// Invalidates the cached product catalog of this replica, so changes made
+/// directly in the database are visible before the cache expires.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   administrator's ID.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/catalog/cache",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Catalog cache invalidated"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn invalidate_catalog(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> StatusCode {
+    app_state.catalog.invalidate();
+
+    StatusCode::NO_CONTENT
+}
+
+/// Searches users by name and email, servers by host name, IP address and
+/// VMID, and services by ID, product name and host name, for the global search
+/// box of the admin UI.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Query(query)`: Searched text.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the results grouped by kind.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/search",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(SearchQuery),
+    responses(
+        (status = 200, body = Response<ApiSearchResults>, description = "Search completed"),
+        (status = 400, body = ApiError, description = "Search query too short"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn search(
+    State(app_state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Response<ApiSearchResults>>> {
+    let results = search_service::search(app_state.reader(), &query.q).await?;
+    tracing::info!(
+        target: "handler",
+        users = results.users.len(),
+        servers = results.servers.len(),
+        services = results.services.len(),
+        "Search completed"
+    );
+
+    Ok(Json(Response::new(results)))
+}
+
+/// Moves a server to another account at once, regardless of the quota of the
+/// recipient. A pending transfer of the server is cancelled.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   administrator's ID.
+/// * `Path(server_id)`: ID of the server.
+/// * `Json(payload)`: Recipient of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the completed transfer, and the
+/// new password of the server if its credentials were regenerated.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/servers/{id}/transfer",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = AdminTransferPayload,
+    responses(
+        (status = 200, body = Response<ApiCompletedTransfer>, description = "Server transferred"),
+        (status = 400, body = ApiError, description = "Server already belongs to the recipient"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Server or recipient not found"),
+        (status = 409, body = ApiError, description = "Server busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn transfer_server(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<AdminTransferPayload>,
+) -> Result<Json<Response<ApiCompletedTransfer>>> {
+    let completed =
+        transfer::transfer_server(&app_state, claims.user_id, server_id, payload).await?;
+    tracing::info!(target: "handler", %server_id, "Server transferred");
+
+    Ok(Json(Response::new(completed)))
+}
+
+/// Returns all announcements, including the scheduled and the expired ones.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the announcements, the latest
+/// published first.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/announcements",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiAnnouncement>>, description = "Announcements found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_announcements(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiAnnouncement>>>> {
+    let announcements = queries::get_announcements(&app_state.pool).await?;
+    tracing::info!(target: "handler", count = announcements.len(), "Found announcements");
+
+    Ok(Json(Response::new(announcements)))
+}
+
+/// Creates an announcement for all users or for the users of a datacenter,
+/// published now or at the given time.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Kind, text, audience and schedule of the announcement.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created announcement.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/announcements",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = AnnouncementPayload,
+    responses(
+        (status = 200, body = Response<ApiAnnouncement>, description = "Announcement created"),
+        (status = 400, body = ApiError, description = "Invalid announcement or unknown datacenter"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_announcement(
+    State(app_state): State<AppState>,
+    Json(payload): Json<AnnouncementPayload>,
+) -> Result<Json<Response<ApiAnnouncement>>> {
+    let announcement = announcement::create_announcement(&app_state, payload).await?;
+    tracing::info!(target: "handler", announcement_id = %announcement.id, "Announcement created");
+
+    Ok(Json(Response::new(announcement)))
+}
+
+/// Replaces the kind, text, audience and schedule of an announcement.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(announcement_id)`: ID of the announcement.
+/// * `Json(payload)`: New kind, text, audience and schedule.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated announcement.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/announcements/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Announcement ID")),
+    request_body = AnnouncementPayload,
+    responses(
+        (status = 200, body = Response<ApiAnnouncement>, description = "Announcement updated"),
+        (status = 400, body = ApiError, description = "Invalid announcement or unknown datacenter"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Announcement not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn update_announcement(
+    State(app_state): State<AppState>,
+    Path(announcement_id): Path<Uuid>,
+    Json(payload): Json<AnnouncementPayload>,
+) -> Result<Json<Response<ApiAnnouncement>>> {
+    let announcement =
+        announcement::update_announcement(&app_state, announcement_id, payload).await?;
+    tracing::info!(target: "handler", %announcement_id, "Announcement updated");
+
+    Ok(Json(Response::new(announcement)))
+}
+
+/// Deletes an announcement.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(announcement_id)`: ID of the announcement.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/announcements/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Announcement ID")),
+    responses(
+        (status = 204, description = "Announcement deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Announcement not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_announcement(
+    State(app_state): State<AppState>,
+    Path(announcement_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    announcement::delete_announcement(&app_state, announcement_id).await?;
+    tracing::info!(target: "handler", %announcement_id, "Announcement deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the latest entries of the audit log, such as the password resets.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Query(query)`: Server to list the entries of, if any.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the entries, the latest first.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/audit-log",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(AuditLogQuery),
+    responses(
+        (status = 200, body = Response<Vec<ApiAuditEntry>>, description = "Audit log entries found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn get_audit_log(
+    State(app_state): State<AppState>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Response<Vec<ApiAuditEntry>>>> {
+    let entries = queries::get_audit_log(&app_state.pool, query.server_id, AUDIT_LOG_LIMIT).await?;
+    tracing::info!(target: "handler", count = entries.len(), "Found audit log entries");
+
+    Ok(Json(Response::new(entries)))
+}
+
+/// Hands a range of the domain events to the consumers again, to recover
+/// from lost webhook deliveries or notifications.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Range of the events and the consumers handling them.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the number of replayed events.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/events/replay",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = ReplayEventsPayload,
+    responses(
+        (status = 200, body = Response<ApiEventReplay>, description = "Events replayed"),
+        (status = 400, body = ApiError, description = "Invalid range or unknown consumer"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn replay_events(
+    State(app_state): State<AppState>,
+    Json(payload): Json<ReplayEventsPayload>,
+) -> Result<Json<Response<ApiEventReplay>>> {
+    let replay = event::replay(&app_state, payload).await?;
+    tracing::info!(target: "handler", replayed = replay.replayed, "Domain events replayed");
+
+    Ok(Json(Response::new(replay)))
+}
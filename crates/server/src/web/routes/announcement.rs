@@ -0,0 +1,67 @@
+//! Announcement routes
+
+use crate::model::queries;
+use crate::model::types::ApiAnnouncement;
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use crate::web::types::Response;
+use axum::extract::State;
+use axum::routing::get;
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+
+/// Defines routes for the announcements shown in the banner of the dashboard.
+/// All routes are protected and require authentication.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/announcements", get(list_announcements))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Returns the announcements currently shown to the authenticated user: the
+/// published, unexpired ones for all users, and for the datacenters of the
+/// user's servers.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the active announcements, the
+/// latest published first.
+///
+#[utoipa::path(
+    get,
+    path = "/announcements",
+    tags = ["Announcement"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiAnnouncement>>, description = "Announcements found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_announcements(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiAnnouncement>>>> {
+    let announcements =
+        queries::get_active_announcements(&app_state.pool, claims.user_id, app_state.clock.now())
+            .await?;
+    tracing::info!(target: "handler", count = announcements.len(), "Found announcements");
+
+    Ok(Json(Response::new(announcements)))
+}
@@ -0,0 +1,373 @@
+//! Billing routes
+
+use crate::model::queries;
+use crate::model::types::{ApiCreditBalance, ApiInvoice, ApiUsage};
+use crate::payments::types::CheckoutSession;
+use crate::services::{billing, credit, usage};
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use crate::web::types::{PeriodQuery, RedeemPromoPayload, Response};
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Error, Result};
+use uuid::Uuid;
+
+/// Header with the signature of the payment provider webhook call.
+const SIGNATURE_HEADER: &str = "Stripe-Signature";
+
+/// Defines routes for the billing section. Invoice routes are protected and
+/// require authentication, while the webhook route is public and verified by
+/// the payment provider signature instead.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/invoices", get(list_invoices))
+        .route("/invoices/{id}", get(get_invoice))
+        .route("/invoices/{id}/checkout", post(checkout_invoice))
+        .route("/invoices/{id}/credit", post(apply_credit))
+        .route("/usage", get(list_usage))
+        .route("/credits", get(get_credits))
+        .route("/promo-codes/redeem", post(redeem_promo_code))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+        .route("/payments/webhook", post(payment_webhook))
+}
+
+/// Returns the list of all invoices that belong to currently authenticated
+/// user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the list of user's invoices.
+///
+#[utoipa::path(
+    get,
+    path = "/invoices",
+    tags = ["Billing"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiInvoice>>, description = "Invoices found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_invoices(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiInvoice>>>> {
+    let invoices = queries::get_invoices_for_user(app_state.reader(), claims.user_id).await?;
+    tracing::info!(target: "handler", count = invoices.len(), "Found invoices");
+
+    Ok(Json(Response::new(invoices)))
+}
+
+/// Returns the invoice with the given ID, if it belongs to the currently
+/// authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(invoice_id)`: ID of the invoice.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the invoice.
+///
+#[utoipa::path(
+    get,
+    path = "/invoices/{id}",
+    tags = ["Billing"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Invoice ID")),
+    responses(
+        (status = 200, body = Response<ApiInvoice>, description = "Invoice found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Invoice not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_invoice(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(invoice_id): Path<Uuid>,
+) -> Result<Json<Response<ApiInvoice>>> {
+    let invoice =
+        queries::get_invoice_by_id(app_state.reader(), claims.user_id, invoice_id).await?;
+    tracing::info!(target: "handler", %invoice_id, "Found invoice");
+
+    Ok(Json(Response::new(invoice)))
+}
+
+/// Starts a hosted checkout for the unpaid invoice.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(invoice_id)`: ID of the invoice to pay.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the checkout session, whose URL
+/// the customer should be redirected to.
+///
+#[utoipa::path(
+    post,
+    path = "/invoices/{id}/checkout",
+    tags = ["Billing"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Invoice ID")),
+    responses(
+        (status = 200, body = Response<CheckoutSession>, description = "Checkout session created"),
+        (status = 400, body = ApiError, description = "Invoice is not payable"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Invoice not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn checkout_invoice(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(invoice_id): Path<Uuid>,
+) -> Result<Json<Response<CheckoutSession>>> {
+    let session = billing::start_checkout(&app_state, claims.user_id, invoice_id).await?;
+    tracing::info!(target: "handler", %invoice_id, "Checkout started");
+
+    Ok(Json(Response::new(session)))
+}
+
+/// Covers the invoice with the currently authenticated user's credit balance.
+/// The invoice is settled once credit covers its whole amount, otherwise the
+/// rest can be paid with a checkout.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(invoice_id)`: ID of the invoice to pay.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated invoice.
+///
+#[utoipa::path(
+    post,
+    path = "/invoices/{id}/credit",
+    tags = ["Billing"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Invoice ID")),
+    responses(
+        (status = 200, body = Response<ApiInvoice>, description = "Credit applied"),
+        (status = 400, body = ApiError, description = "Invoice is not payable or no credit available"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Invoice not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn apply_credit(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(invoice_id): Path<Uuid>,
+) -> Result<Json<Response<ApiInvoice>>> {
+    let invoice = credit::apply_to_invoice(&app_state, claims.user_id, invoice_id).await?;
+    tracing::info!(target: "handler", %invoice_id, status = %invoice.status, "Credit applied");
+
+    Ok(Json(Response::new(invoice)))
+}
+
+/// Returns the resource usage of the currently authenticated user's services,
+/// aggregated over a billing period.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Query(period)`: Optional bounds of the billing period, the current month
+///   by default.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the usage per service.
+///
+#[utoipa::path(
+    get,
+    path = "/usage",
+    tags = ["Billing"],
+    security(("bearer_auth" = [])),
+    params(PeriodQuery),
+    responses(
+        (status = 200, body = Response<Vec<ApiUsage>>, description = "Usage found"),
+        (status = 400, body = ApiError, description = "Invalid billing period"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_usage(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(period): Query<PeriodQuery>,
+) -> Result<Json<Response<Vec<ApiUsage>>>> {
+    let (from, to) = usage::billing_period(period.from, period.to, app_state.clock.now())?;
+    let usage = queries::get_usage_for_user(app_state.reader(), claims.user_id, from, to).await?;
+    tracing::info!(target: "handler", count = usage.len(), %from, %to, "Found usage");
+
+    Ok(Json(Response::new(usage)))
+}
+
+/// Returns the credit balance of the currently authenticated user together
+/// with the ledger it is computed from.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the credit balance.
+///
+#[utoipa::path(
+    get,
+    path = "/credits",
+    tags = ["Billing"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<ApiCreditBalance>, description = "Credit balance found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_credits(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<ApiCreditBalance>>> {
+    let balance = credit::get_balance(&app_state, claims.user_id).await?;
+    tracing::info!(target: "handler", balance_cents = balance.balance_cents, "Found credit balance");
+
+    Ok(Json(Response::new(balance)))
+}
+
+/// Redeems a promo code for the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: Promo code to redeem.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated credit balance.
+///
+#[utoipa::path(
+    post,
+    path = "/promo-codes/redeem",
+    tags = ["Billing"],
+    security(("bearer_auth" = [])),
+    request_body = RedeemPromoPayload,
+    responses(
+        (status = 200, body = Response<ApiCreditBalance>, description = "Promo code redeemed"),
+        (status = 400, body = ApiError, description = "Invalid or already redeemed promo code"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn redeem_promo_code(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<RedeemPromoPayload>,
+) -> Result<Json<Response<ApiCreditBalance>>> {
+    let balance = credit::redeem_promo_code(&app_state, claims.user_id, &payload.code).await?;
+    tracing::info!(target: "handler", balance_cents = balance.balance_cents, "Promo code redeemed");
+
+    Ok(Json(Response::new(balance)))
+}
+
+/// Receives payment provider webhook calls.
+///
+/// The raw body is verified against the signature header before any processing,
+/// and already processed events are acknowledged without side effects.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `headers`: Request headers, containing the provider signature.
+/// * `body`: Raw request body.
+///
+/// # Returns
+///
+/// `200 OK` once the event is processed.
+///
+#[utoipa::path(
+    post,
+    path = "/payments/webhook",
+    tags = ["Billing"],
+    request_body(content = String, description = "Raw payment provider event"),
+    responses(
+        (status = 200, description = "Event processed"),
+        (status = 400, body = ApiError, description = "Invalid signature or payload"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip_all)]
+async fn payment_webhook(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode> {
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| Error::Validation("Missing webhook signature".to_owned()))?;
+
+    let event = app_state.payments.verify_webhook(&body, signature)?;
+    tracing::info!(target: "handler", event_id = %event.id, event_type = %event.event_type, "Payment event received");
+    billing::process_event(&app_state, event).await?;
+
+    Ok(StatusCode::OK)
+}
@@ -1,12 +1,15 @@
 ﻿use crate::model::queries;
-use crate::model::types::{ApiConfigValue, ApiCustomValue, ApiProduct};
+use crate::model::types::{
+    ApiApp, ApiAvailableDatacenter, ApiConfigValue, ApiCustomValue, ApiIso, ApiProduct,
+};
 use crate::state::AppState;
 use crate::web::middleware as mw;
 use crate::web::types::{RequiredConfigOption, RequiredCustomField, Response};
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::routing::get;
 use axum::{Json, Router, middleware};
-use dashboard_common::prelude::Result;
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
 
 /// Defines routes for the catalog section. All routes are protected and require
 /// authentication.
@@ -18,6 +21,12 @@ use dashboard_common::prelude::Result;
 pub fn routes(app_state: AppState) -> Router<AppState> {
     Router::new()
         .route("/api/products", get(list_products))
+        .route(
+            "/api/products/{id}/datacenters",
+            get(list_product_datacenters),
+        )
+        .route("/api/apps", get(list_apps))
+        .route("/api/isos", get(list_isos))
         .route("/api/config/cpu", get(list_cpu_options))
         .route("/api/config/ram", get(list_ram_options))
         .route("/api/custom/os", get(list_os_options))
@@ -42,21 +51,108 @@ pub fn routes(app_state: AppState) -> Router<AppState> {
     security(("bearer_auth" = [])),
     responses(
         (status = 200, body = Response<Vec<ApiProduct>>, description = "Products found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "Products not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Products not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 #[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
 async fn list_products(
     State(app_state): State<AppState>,
 ) -> Result<Json<Response<Vec<ApiProduct>>>> {
-    let products = queries::get_products(&app_state.pool).await?;
+    let products = app_state.catalog.products(app_state.reader()).await?;
     tracing::info!(target: "handler", "Found {} products", products.len());
 
     Ok(Json(Response::new(products)))
 }
 
+/// Retrieves the datacenters a product can be ordered in: the active ones it is
+/// offered in, with capacity left.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+/// * `Path(product_id)` - ID of the product.
+///
+#[utoipa::path(
+    get,
+    path = "/api/products/{id}/datacenters",
+    tags = ["Catalog"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiAvailableDatacenter>>, description = "Datacenters found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Product not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_product_datacenters(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiAvailableDatacenter>>>> {
+    let datacenters = app_state
+        .catalog
+        .datacenters(app_state.reader(), product_id)
+        .await?;
+    tracing::info!(target: "handler", "Found {} datacenters", datacenters.len());
+
+    Ok(Json(Response::new(datacenters)))
+}
+
+/// Retrieves the applications of the marketplace, which can be installed on a
+/// new server by naming them in its `app` field.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+#[utoipa::path(
+    get,
+    path = "/api/apps",
+    tags = ["Catalog"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiApp>>, description = "Applications found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_apps(State(app_state): State<AppState>) -> Result<Json<Response<Vec<ApiApp>>>> {
+    let apps = app_state.catalog.apps(app_state.reader()).await?;
+    tracing::info!(target: "handler", count = apps.len(), "Found applications");
+
+    Ok(Json(Response::new(apps)))
+}
+
+/// Retrieves the ISOs users can insert into the CD-ROM drive of their
+/// servers.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+#[utoipa::path(
+    get,
+    path = "/api/isos",
+    tags = ["Catalog"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiIso>>, description = "ISOs found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_isos(State(app_state): State<AppState>) -> Result<Json<Response<Vec<ApiIso>>>> {
+    let isos = queries::get_isos(app_state.reader()).await?;
+    tracing::info!(target: "handler", count = isos.len(), "Found ISOs");
+
+    Ok(Json(Response::new(isos)))
+}
+
 /// Retrieves CPU options catalog.
 ///
 /// # Arguments
@@ -74,16 +170,18 @@ async fn list_products(
     security(("bearer_auth" = [])),
     responses(
         (status = 200, body = Response<Vec<ApiConfigValue>>, description = "CPU options found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "CPU options not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "CPU options not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 async fn list_cpu_options(
     State(app_state): State<AppState>,
 ) -> Result<Json<Response<Vec<ApiConfigValue>>>> {
-    let options =
-        queries::get_config_option_value(&app_state.pool, RequiredConfigOption::CPU).await?;
+    let options = app_state
+        .catalog
+        .config_values(app_state.reader(), RequiredConfigOption::CPU)
+        .await?;
     tracing::info!(target: "handler", count = options.len(), "Found CPU options");
 
     Ok(Json(Response::new(options)))
@@ -106,16 +204,18 @@ async fn list_cpu_options(
     security(("bearer_auth" = [])),
     responses(
         (status = 200, body = Response<Vec<ApiConfigValue>>, description = "RAM options found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "RAM options not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "RAM options not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 async fn list_ram_options(
     State(app_state): State<AppState>,
 ) -> Result<Json<Response<Vec<ApiConfigValue>>>> {
-    let options =
-        queries::get_config_option_value(&app_state.pool, RequiredConfigOption::RAM).await?;
+    let options = app_state
+        .catalog
+        .config_values(app_state.reader(), RequiredConfigOption::RAM)
+        .await?;
     tracing::info!(target: "handler", count = options.len(), "Found RAM options");
 
     Ok(Json(Response::new(options)))
@@ -138,16 +238,18 @@ async fn list_ram_options(
     security(("bearer_auth" = [])),
     responses(
         (status = 200, body = Response<Vec<ApiCustomValue>>, description = "OS Template custom values found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "OS Template custom values options not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "OS Template custom values options not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 async fn list_os_options(
     State(app_state): State<AppState>,
 ) -> Result<Json<Response<Vec<ApiCustomValue>>>> {
-    let options =
-        queries::get_custom_field_value(&app_state.pool, RequiredCustomField::OsTemplate).await?;
+    let options = app_state
+        .catalog
+        .custom_values(app_state.reader(), RequiredCustomField::OsTemplate)
+        .await?;
     tracing::info!(target: "handler", count = options.len(), "Found OS options");
 
     Ok(Json(Response::new(options)))
@@ -170,16 +272,18 @@ async fn list_os_options(
     security(("bearer_auth" = [])),
     responses(
         (status = 200, body = Response<Vec<ApiCustomValue>>, description = "Datacenter Location custom values found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "Datacenter Location custom values options not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Datacenter Location custom values options not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 async fn list_datacenter_options(
     State(app_state): State<AppState>,
 ) -> Result<Json<Response<Vec<ApiCustomValue>>>> {
-    let options =
-        queries::get_custom_field_value(&app_state.pool, RequiredCustomField::Datacenter).await?;
+    let options = app_state
+        .catalog
+        .custom_values(app_state.reader(), RequiredCustomField::Datacenter)
+        .await?;
     tracing::info!(target: "handler", count = options.len(), "Found Datacenter options");
 
     Ok(Json(Response::new(options)))
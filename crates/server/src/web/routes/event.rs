@@ -0,0 +1,84 @@
+//! Domain event stream routes
+
+use crate::model::types::DomainEvent;
+use crate::services::event;
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::{Extension, Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use futures_util::{Stream, StreamExt};
+
+/// Header of a reconnecting SSE client, carrying the last event it received.
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Defines routes for the live domain events of the user. All routes are
+/// protected and require authentication.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/me/events", get(stream_events))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Streams the domain events of the currently authenticated user as
+/// server-sent events, such as the status changes of the servers. Every
+/// event carries its ID, so a reconnecting client gets the events it missed.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `headers`: Request headers, containing the last received event if the
+///   client reconnects.
+///
+/// # Returns
+///
+/// On success, returns an open stream of the events, named by their kind
+/// with the event as JSON data.
+///
+#[utoipa::path(
+    get,
+    path = "/me/events",
+    tags = ["Event"],
+    security(("bearer_auth" = [])),
+    params(("Last-Event-ID" = Option<i64>, Header, description = "Last event received before reconnecting")),
+    responses(
+        (status = 200, content_type = "text/event-stream", body = DomainEvent, description = "Stream of the events"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, headers),
+	fields(id = %claims.user_id))]
+async fn stream_events(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok());
+    let events = event::subscribe(&app_state, claims.user_id, last_event_id).await?;
+    tracing::info!(target: "handler", ?last_event_id, "Event stream opened");
+
+    let stream = events.map(|domain_event| {
+        Event::default()
+            .id(domain_event.id.to_string())
+            .event(domain_event.kind.to_string())
+            .json_data(&domain_event)
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
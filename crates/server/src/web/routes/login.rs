@@ -1,15 +1,23 @@
 //! Public routes
 
 use crate::model::queries;
-use crate::model::types::{LoginPayload, NewUser};
+use crate::model::types::{AuthAttemptKind, LoginPayload, NewUser};
+use crate::services::{allowlist, captcha, oidc, saml, user};
 use crate::state::AppState;
 use crate::web::auth::{password, token};
-use crate::web::types::TokenResponse;
-use axum::extract::State;
-use axum::routing::post;
-use axum::{Json, Router};
-use dashboard_common::prelude::{AuthError, Error, Result};
+use crate::web::middleware::ClientIp;
+use crate::web::types::{
+    ConfirmEmailPayload, OidcCallbackQuery, OidcLoginQuery, Response, SamlResponseForm,
+    SsoAuthorization, TokenResponse,
+};
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Redirect};
+use axum::routing::{get, post};
+use axum::{Extension, Form, Json, Router};
+use dashboard_common::prelude::{ApiError, AuthError, Error, Result};
 use secrecy::ExposeSecret;
+use uuid::Uuid;
 
 /// Defines routes for the catalog section. All routes are public and don't
 /// require authentication.
@@ -18,23 +26,35 @@ pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/auth/verify", post(verify_email))
+        .route("/auth/oidc/login", get(oidc_login))
+        .route("/auth/oidc/callback", get(oidc_callback))
+        .route("/saml/{id}/metadata", get(saml_metadata))
+        .route("/saml/{id}/login", get(saml_login))
+        .route("/saml/{id}/acs", post(saml_acs))
 }
 
 /// Creates a new user account.
 ///
 /// On successful registration, it returns a `TokenResponse` containing a JWT
-/// for the newly created user.
+/// for the newly created user, and sends a token verifying the email address.
+/// Servers can be ordered only once the address is verified. After too many
+/// registrations from the address of the client, a solved CAPTCHA is required.
 ///
 /// # Arguments
 ///
 /// * `State(app_state)` - The shared application state, containing the database
 ///   pool.
+/// * `client_ip` - Address of the client.
 /// * `Json(new_user)` - Payload for creating a new user, contains the plaintext
 ///   password.
 ///
 /// # Errors
 ///
-/// Returns an `Error` if the database query fails  or if JWT creation fails.
+/// Returns an `Error` if the database query fails  or if JWT creation fails,
+/// `Error::Forbidden` if the registration is switched off,
+/// `Error::Conflict` if the email address is already registered,
+/// `AuthError::Captcha` if the required CAPTCHA is not solved.
 ///
 #[utoipa::path(
     post,
@@ -43,7 +63,10 @@ pub fn routes() -> Router<AppState> {
     tags = ["Login"],
     responses(
         (status = 200, body = TokenResponse, description = "User registration completed"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "CAPTCHA required"),
+        (status = 403, body = ApiError, description = "Registration closed"),
+        (status = 409, body = ApiError, description = "Email address already registered"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 #[tracing::instrument(level = "trace", target = "handler",
@@ -51,11 +74,34 @@ pub fn routes() -> Router<AppState> {
 	fields(email = %new_user.email))]
 async fn register(
     State(app_state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
     Json(new_user): Json<NewUser>,
 ) -> Result<Json<TokenResponse>> {
-    let user = queries::add_new_user(&app_state.pool, new_user).await?;
-    let token = token::create(user.id, app_state.config.token)?;
-    tracing::info!(target: "handler", user_id = %user.id, "Token generated successfully");
+    if !app_state.runtime.load().features.registration {
+        return Err(Error::Forbidden("Registration is closed".to_owned()));
+    }
+    let client_ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    let captcha_token = new_user.captcha_token.as_deref();
+    captcha::check_registration(&app_state, client_ip, captcha_token).await?;
+
+    let new_user = NewUser {
+        email: user::normalize_email(&new_user.email),
+        ..new_user
+    };
+    let new_user = queries::add_new_user(&app_state.pool, new_user).await?;
+    captcha::record_attempt(
+        &app_state,
+        AuthAttemptKind::Registration,
+        Some(&new_user.email),
+        client_ip,
+    )
+    .await;
+    if let Err(error) = user::send_verification(&app_state, new_user.id, &new_user.email).await {
+        // The user can request another verification email.
+        tracing::error!(target: "handler", ?error, "Failed to send verification email!");
+    }
+    let token = token::create(new_user.id, app_state.config.token)?;
+    tracing::info!(target: "handler", user_id = %new_user.id, "Token generated successfully");
 
     Ok(Json(TokenResponse::new(token.into())))
 }
@@ -63,12 +109,17 @@ async fn register(
 /// Authenticates a user and provides a JWT.
 ///
 /// Takes a user's email and password, verifies them against the database,
-/// and returns a `TokenResponse` with a new JWT on success.
+/// and returns a `TokenResponse` with a new JWT on success. Users who allowed
+/// only some networks can log in only from there, and members of organizations
+/// enforcing single sign-on only with it. After too many failed logins
+/// with the email address or from the address of the client, a solved CAPTCHA
+/// is required before the password is checked.
 ///
 /// # Arguments
 ///
 /// * `State(app_state)` - The shared application state, containing the database
 ///   pool.
+/// * `client_ip` - Address of the client.
 /// * `Json(payload)` - Payload for authentication an existing user.
 ///
 /// # Returns
@@ -78,7 +129,9 @@ async fn register(
 /// # Errors
 ///
 /// Returns an `Error` if the user is not found by email, if the password
-/// verification fails, or if JWT creation fails.
+/// verification fails, or if JWT creation fails, `Error::Forbidden` if the
+/// user must log in with single sign-on or the address of the client is not
+/// allowed, `AuthError::Captcha` if the required CAPTCHA is not solved.
 ///
 #[utoipa::path(
     post,
@@ -87,8 +140,9 @@ async fn register(
     tags = ["Login"],
     responses(
         (status = 200, body = TokenResponse, description = "User login completed"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized or CAPTCHA required"),
+        (status = 403, body = ApiError, description = "Single sign-on required or address not allowed"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 #[tracing::instrument(level = "trace", target = "handler",
@@ -96,24 +150,47 @@ async fn register(
 	fields(email = %payload.email))]
 async fn login(
     State(app_state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
     Json(payload): Json<LoginPayload>,
 ) -> Result<Json<TokenResponse>> {
-    let user = queries::get_user_by_email(&app_state.pool, &payload.email)
-        .await
-        .map_err(|_| Error::Auth(AuthError::Login))?;
+    let client_ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    let captcha_token = payload.captcha_token.as_deref();
+    let email = user::normalize_email(&payload.email);
+    captcha::check_login(&app_state, &email, client_ip, captcha_token).await?;
+
+    let credentials = async {
+        let user = queries::get_user_by_email(&app_state.pool, &email)
+            .await
+            .map_err(|_| Error::Auth(AuthError::Login))?;
+        password::verify(
+            user.password.expose_secret(),
+            payload.password.expose_secret(),
+        )?;
+        Ok::<_, Error>(user)
+    };
+    let user = match credentials.await {
+        Ok(user) => user,
+        Err(error) => {
+            let email = Some(email.as_str());
+            captcha::record_attempt(&app_state, AuthAttemptKind::FailedLogin, email, client_ip)
+                .await;
+            return Err(error);
+        }
+    };
 
     let (hash, pass) = (
         user.password.expose_secret(),
         payload.password.expose_secret(),
     );
-    if hash.starts_with("$2y$10$") {
-        // Rehash old WHMCS passwords immediately, without confirmation email.
-        bcrypt::verify(pass, hash).map_err(|_| Error::Auth(AuthError::Login))?;
+    saml::ensure_not_enforced(&app_state, user.id).await?;
+    allowlist::ensure_allowed(&app_state, user.id, client_ip).await?;
+    if password::needs_rehash(hash) {
+        // Rehash legacy WHMCS (bcrypt, phpass, MD5) and outdated Argon2
+        // passwords immediately, without confirmation email.
+        let format = password::HashFormat::detect(hash);
         let new_hash = password::hash(pass)?;
         queries::update_password_hash(&app_state.pool, &user.id, &new_hash).await?;
-        tracing::info!(target: "handler", user_id = %user.id, "Old password hash updated");
-    } else {
-        password::verify(hash, pass)?;
+        tracing::info!(target: "handler", user_id = %user.id, ?format, "Old password hash updated");
     }
 
     let token = token::create(user.id, app_state.config.token)?;
@@ -121,3 +198,268 @@ async fn login(
 
     Ok(Json(TokenResponse::new(token.into())))
 }
+
+/// Verifies the email address of a new account with the token sent there.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state, containing the database
+///   pool.
+/// * `Json(payload)` - Token sent to the email address.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    post,
+    path = "/auth/verify",
+    request_body = ConfirmEmailPayload,
+    tags = ["Login"],
+    responses(
+        (status = 204, description = "Email address verified"),
+        (status = 400, body = ApiError, description = "Invalid or expired token"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip_all)]
+async fn verify_email(
+    State(app_state): State<AppState>,
+    Json(payload): Json<ConfirmEmailPayload>,
+) -> Result<StatusCode> {
+    let user_id = user::verify_email(&app_state, payload).await?;
+    tracing::info!(target: "handler", %user_id, "Email address verified");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Starts a login with an OpenID Connect provider, like Google or Microsoft.
+///
+/// The frontend redirects the user to the returned authorization page. After
+/// the user signed in there, the provider redirects the user back to
+/// `/auth/oidc/callback`. The state of the login is set in a cookie, so the
+/// frontend must send the request with credentials.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state, containing the
+///   registered providers.
+/// * `Query(query)` - Name of the provider.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the authorization page and sets
+/// the state cookie.
+///
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/login",
+    params(OidcLoginQuery),
+    tags = ["Login"],
+    responses(
+        (status = 200, body = Response<SsoAuthorization>, description = "Login started"),
+        (status = 404, body = ApiError, description = "Provider not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn oidc_login(
+    State(app_state): State<AppState>,
+    Query(query): Query<OidcLoginQuery>,
+) -> Result<impl IntoResponse> {
+    let (authorization, cookie) = oidc::authorize(&app_state, &query.provider).await?;
+
+    Ok((
+        [(header::SET_COOKIE, cookie)],
+        Json(Response::new(authorization)),
+    ))
+}
+
+/// Completes a login with an OpenID Connect provider the user was redirected
+/// back from, and redirects the user to the frontend with a JWT in the
+/// fragment of the URL.
+///
+/// The login must come from the browser that started it, with its state
+/// cookie. The account at the provider logs into the user it was linked to. On the
+/// first login, it is linked to the user with the same email address, if both
+/// the provider and the dashboard verified it. Users who allowed only some
+/// networks can log in only from there.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state, containing the
+///   registered providers.
+/// * `client_ip` - Address of the client.
+/// * `headers` - Headers of the request, with the state cookie.
+/// * `Query(query)` - Code and state passed back by the provider.
+///
+/// # Returns
+///
+/// On success, returns `303 See Other` to the frontend and removes the state
+/// cookie.
+///
+/// # Errors
+///
+/// Returns `Error::Validation` if the login is unknown or expired,
+/// `AuthError::Login` if the state cookie doesn't belong to the login, the
+/// provider rejected the code or the ID token is invalid, `Error::Forbidden` if the account can't be linked to a user or the
+/// address of the client is not allowed.
+///
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/callback",
+    params(OidcCallbackQuery),
+    tags = ["Login"],
+    responses(
+        (status = 303, description = "User login completed"),
+        (status = 400, body = ApiError, description = "Login expired"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Account not linkable or address not allowed"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip_all)]
+async fn oidc_callback(
+    State(app_state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
+    headers: HeaderMap,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<impl IntoResponse> {
+    let client_ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    let user_id = oidc::callback(&app_state, query, &headers, client_ip).await?;
+
+    let token = token::create(user_id, app_state.config.token)?;
+    tracing::info!(target: "handler", %user_id, "Token generated successfully");
+
+    Ok((
+        [(header::SET_COOKIE, oidc::clear_state_cookie())],
+        Redirect::to(&format!(
+            "{}#token={token}",
+            app_state.config.oidc.frontend_url
+        )),
+    ))
+}
+
+/// Returns the SAML metadata of the service provider of an organization, which
+/// its identity provider is set up with.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+/// * `Path(organization_id)` - ID of the organization.
+///
+/// # Returns
+///
+/// Metadata XML of the service provider.
+///
+#[utoipa::path(
+    get,
+    path = "/saml/{id}/metadata",
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    tags = ["Login"],
+    responses(
+        (status = 200, body = String, content_type = "application/samlmetadata+xml", description = "Service provider metadata")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn saml_metadata(
+    State(app_state): State<AppState>,
+    Path(organization_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let metadata = saml::metadata(&app_state.config.saml, organization_id);
+
+    (
+        [(header::CONTENT_TYPE, "application/samlmetadata+xml")],
+        metadata,
+    )
+}
+
+/// Starts a login with the SAML identity provider of an organization.
+///
+/// The frontend redirects the user to the returned page. After the user signed
+/// in there, the identity provider posts the assertion to `/saml/{id}/acs`.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+/// * `Path(organization_id)` - ID of the organization.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the page of the identity provider.
+///
+#[utoipa::path(
+    get,
+    path = "/saml/{id}/login",
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    tags = ["Login"],
+    responses(
+        (status = 200, body = Response<SsoAuthorization>, description = "Login started"),
+        (status = 404, body = ApiError, description = "Single sign-on not set up"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn saml_login(
+    State(app_state): State<AppState>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<Json<Response<SsoAuthorization>>> {
+    let authorization = saml::authorize(&app_state, organization_id).await?;
+
+    Ok(Json(Response::new(authorization)))
+}
+
+/// Consumes the assertion the SAML identity provider of an organization posted
+/// after the user signed in, and redirects the user to the frontend with a JWT
+/// in the fragment of the URL.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+/// * `client_ip` - Address of the client.
+/// * `Path(organization_id)` - ID of the organization.
+/// * `Form(form)` - SAML response and the relay state.
+///
+/// # Returns
+///
+/// On success, returns `303 See Other` to the frontend.
+///
+/// # Errors
+///
+/// Returns `Error::Validation` if the login is unknown or expired,
+/// `AuthError::Login` if the response is invalid, `Error::Forbidden` if the
+/// user is unknown or the address of the client is not allowed.
+///
+#[utoipa::path(
+    post,
+    path = "/saml/{id}/acs",
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    request_body(content = SamlResponseForm, content_type = "application/x-www-form-urlencoded"),
+    tags = ["Login"],
+    responses(
+        (status = 303, description = "User login completed"),
+        (status = 400, body = ApiError, description = "Login expired"),
+        (status = 401, body = ApiError, description = "Invalid SAML response"),
+        (status = 403, body = ApiError, description = "Unknown user or address not allowed"),
+        (status = 404, body = ApiError, description = "Single sign-on not set up"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state, form))]
+async fn saml_acs(
+    State(app_state): State<AppState>,
+    client_ip: Option<Extension<ClientIp>>,
+    Path(organization_id): Path<Uuid>,
+    Form(form): Form<SamlResponseForm>,
+) -> Result<Redirect> {
+    let client_ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    let user_id = saml::consume(&app_state, organization_id, form, client_ip).await?;
+
+    let token = token::create(user_id, app_state.config.token)?;
+    tracing::info!(target: "handler", %user_id, "Token generated successfully");
+
+    Ok(Redirect::to(&format!(
+        "{}#token={token}",
+        app_state.config.saml.redirect_url
+    )))
+}
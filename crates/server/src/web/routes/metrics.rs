@@ -0,0 +1,143 @@
+//! Monitoring routes
+
+use crate::model::queries;
+use crate::model::types::ApiHealth;
+use crate::state::AppState;
+use crate::web::types::Response;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use dashboard_common::prelude::{ApiError, Result};
+use std::fmt::Write;
+
+/// Defines routes for the monitoring section. All routes are public and don't
+/// require authentication.
+///
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .route("/healthz", get(get_health))
+}
+
+/// Returns the gauges and counters of the application in the Prometheus text
+/// format.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// Gauges of the database connection pool: open, idle and in use connections,
+/// and the configured maximum. Gauges and counters of the Proxmox request
+/// queue: requests waiting and in flight, requests sent and time waited.
+///
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tags = ["Monitoring"],
+    responses(
+        (status = 200, body = String, content_type = "text/plain", description = "Prometheus metrics"),
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn get_metrics(State(app_state): State<AppState>) -> impl IntoResponse {
+    let pool = &app_state.pool;
+    let size = pool.size() as usize;
+    let idle = pool.num_idle();
+    let queue = &app_state.proxmox_queue;
+    let gauges = [
+        ("db_pool_connections", "Open database connections", size),
+        (
+            "db_pool_idle_connections",
+            "Idle database connections",
+            idle,
+        ),
+        (
+            "db_pool_active_connections",
+            "Database connections in use",
+            size.saturating_sub(idle),
+        ),
+        (
+            "db_pool_max_connections",
+            "Maximum database connections",
+            pool.options().get_max_connections() as usize,
+        ),
+        (
+            "proxmox_queue_waiting_requests",
+            "Proxmox requests waiting for a free slot",
+            queue.waiting(),
+        ),
+        (
+            "proxmox_in_flight_requests",
+            "Proxmox requests being sent",
+            queue.in_flight(),
+        ),
+    ];
+    let counters = [
+        (
+            "proxmox_requests_total",
+            "Requests sent to Proxmox",
+            queue.requests() as f64,
+        ),
+        (
+            "proxmox_queue_wait_seconds_total",
+            "Time Proxmox requests waited for a free slot",
+            queue.waited().as_secs_f64(),
+        ),
+    ];
+
+    let mut body = String::new();
+    for (name, help, value) in gauges {
+        let _ = writeln!(
+            body,
+            "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+        );
+    }
+    for (name, help, value) in counters {
+        let _ = writeln!(
+            body,
+            "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}"
+        );
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Checks the connection to the database and reports its schema version.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the latest migration applied to
+/// the database and the latest one embedded in the server.
+///
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tags = ["Monitoring"],
+    responses(
+        (status = 200, body = Response<ApiHealth>, description = "Database reachable"),
+        (status = 500, body = ApiError, description = "Database unreachable")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn get_health(State(app_state): State<AppState>) -> Result<Json<Response<ApiHealth>>> {
+    let schema_version = queries::schema_version(&app_state.pool).await?;
+    let latest_version = queries::MIGRATOR
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or_default();
+
+    Ok(Json(Response::new(ApiHealth {
+        schema_version,
+        latest_version,
+        up_to_date: schema_version >= Some(latest_version),
+    })))
+}
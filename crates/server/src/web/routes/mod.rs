@@ -1,3 +1,14 @@
+pub mod admin;
+pub mod announcement;
+pub mod billing;
 pub mod catalog;
+pub mod event;
 pub mod login;
+pub mod metrics;
+pub mod notification;
+pub mod organization;
+pub mod products;
 pub mod server;
+pub mod transfer;
+pub mod user;
+pub mod webhook;
@@ -0,0 +1,236 @@
+//! Notification center routes
+
+use crate::model::queries;
+use crate::model::types::{ApiNotification, ApiNotificationPreference};
+use crate::services::notification;
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use crate::web::types::{NotificationListQuery, NotificationPreferencePayload, Response};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
+
+/// Number of latest notifications returned to a user.
+const NOTIFICATIONS_LIMIT: i64 = 100;
+
+/// Defines routes for the notification center of the user. All routes are
+/// protected and require authentication.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/me/notifications", get(list_notifications))
+        .route("/me/notifications/read", post(mark_all_read))
+        .route("/me/notifications/{id}/read", post(mark_read))
+        .route(
+            "/me/notifications/preferences",
+            get(get_preferences).put(set_preference),
+        )
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Returns the latest notifications of the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Query(query)`: Whether to list only the unread notifications.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the notifications, the latest
+/// first.
+///
+#[utoipa::path(
+    get,
+    path = "/me/notifications",
+    tags = ["Notification"],
+    security(("bearer_auth" = [])),
+    params(NotificationListQuery),
+    responses(
+        (status = 200, body = Response<Vec<ApiNotification>>, description = "Notifications found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_notifications(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<NotificationListQuery>,
+) -> Result<Json<Response<Vec<ApiNotification>>>> {
+    let notifications = queries::get_notifications(
+        &app_state.pool,
+        claims.user_id,
+        query.unread,
+        NOTIFICATIONS_LIMIT,
+    )
+    .await?;
+    tracing::info!(target: "handler", count = notifications.len(), "Found notifications");
+
+    Ok(Json(Response::new(notifications)))
+}
+
+/// Marks a notification of the currently authenticated user as read.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(notification_id)`: ID of the notification.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    post,
+    path = "/me/notifications/{id}/read",
+    tags = ["Notification"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Notification ID")),
+    responses(
+        (status = 204, description = "Notification marked as read"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Notification not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn mark_read(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(notification_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    notification::mark_read(&app_state, claims.user_id, notification_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Marks every unread notification of the currently authenticated user as
+/// read.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    post,
+    path = "/me/notifications/read",
+    tags = ["Notification"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Notifications marked as read"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn mark_all_read(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode> {
+    let count = queries::mark_notifications_read(&app_state.pool, claims.user_id).await?;
+    tracing::info!(target: "handler", count, "Notifications marked as read");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns whether the currently authenticated user gets every event by
+/// email, besides the notification center.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the preference of every event.
+///
+#[utoipa::path(
+    get,
+    path = "/me/notifications/preferences",
+    tags = ["Notification"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiNotificationPreference>>, description = "Preferences found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_preferences(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiNotificationPreference>>>> {
+    let preferences = notification::get_preferences(&app_state, claims.user_id).await?;
+
+    Ok(Json(Response::new(preferences)))
+}
+
+/// Sets whether the currently authenticated user gets an event by email, or
+/// only in the notification center.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: Event and whether it is emailed.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated preference of every
+/// event.
+///
+#[utoipa::path(
+    put,
+    path = "/me/notifications/preferences",
+    tags = ["Notification"],
+    security(("bearer_auth" = [])),
+    request_body = NotificationPreferencePayload,
+    responses(
+        (status = 200, body = Response<Vec<ApiNotificationPreference>>, description = "Preference set"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn set_preference(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<NotificationPreferencePayload>,
+) -> Result<Json<Response<Vec<ApiNotificationPreference>>>> {
+    let preferences = notification::set_preference(&app_state, claims.user_id, payload).await?;
+
+    Ok(Json(Response::new(preferences)))
+}
@@ -0,0 +1,486 @@
+//! Organization routes
+
+use crate::model::queries;
+use crate::model::types::{ApiOrganization, ApiOrganizationMember, ApiSamlSettings, ApiServer};
+use crate::services::{organization, saml};
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use crate::web::types::{
+    MemberPayload, NamePayload, OrganizationServerPayload, Response, SamlSettingsPayload,
+};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get};
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
+
+/// Defines routes for the organizations of the user, their members and their
+/// servers. All routes are protected and require authentication.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/organizations",
+            get(list_organizations).post(create_organization),
+        )
+        .route(
+            "/organizations/{id}/members",
+            get(list_members).put(set_member),
+        )
+        .route(
+            "/organizations/{id}/members/{user_id}",
+            delete(remove_member),
+        )
+        .route(
+            "/organizations/{id}/servers",
+            get(list_servers).post(add_server),
+        )
+        .route(
+            "/organizations/{id}/saml",
+            get(get_saml).put(set_saml).delete(delete_saml),
+        )
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Returns the organizations of the currently authenticated user, the
+/// personal one first.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the organizations and the role of
+/// the user in each of them.
+///
+#[utoipa::path(
+    get,
+    path = "/organizations",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiOrganization>>, description = "Organizations found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_organizations(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiOrganization>>>> {
+    let organizations = queries::get_organizations(&app_state.pool, claims.user_id).await?;
+    tracing::info!(target: "handler", count = organizations.len(), "Found organizations");
+
+    Ok(Json(Response::new(organizations)))
+}
+
+/// Creates an organization owned by the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: Name of the organization.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created organization.
+///
+#[utoipa::path(
+    post,
+    path = "/organizations",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    request_body = NamePayload,
+    responses(
+        (status = 200, body = Response<ApiOrganization>, description = "Organization created"),
+        (status = 400, body = ApiError, description = "Invalid name"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, payload),
+	fields(id = %claims.user_id))]
+async fn create_organization(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<NamePayload>,
+) -> Result<Json<Response<ApiOrganization>>> {
+    let organization =
+        organization::create_organization(&app_state, claims.user_id, payload).await?;
+    tracing::info!(target: "handler", organization_id = %organization.id, "Organization created");
+
+    Ok(Json(Response::new(organization)))
+}
+
+/// Returns the members of an organization of the currently authenticated
+/// user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(organization_id)`: ID of the organization.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the members and their roles.
+///
+#[utoipa::path(
+    get,
+    path = "/organizations/{id}/members",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiOrganizationMember>>, description = "Members found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Organization not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_members(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiOrganizationMember>>>> {
+    let members = organization::get_members(&app_state, claims.user_id, organization_id).await?;
+    tracing::info!(target: "handler", count = members.len(), "Found members");
+
+    Ok(Json(Response::new(members)))
+}
+
+/// Adds a member to an organization managed by the currently authenticated
+/// user, or changes the role of a member.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(organization_id)`: ID of the organization.
+/// * `Json(payload)`: Email address and role of the member.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the members of the organization.
+///
+#[utoipa::path(
+    put,
+    path = "/organizations/{id}/members",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    request_body = MemberPayload,
+    responses(
+        (status = 200, body = Response<Vec<ApiOrganizationMember>>, description = "Member set"),
+        (status = 400, body = ApiError, description = "Personal organization"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Not managed by the user"),
+        (status = 404, body = ApiError, description = "Organization or user not found"),
+        (status = 409, body = ApiError, description = "Last owner of the organization"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, payload),
+	fields(id = %claims.user_id))]
+async fn set_member(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<MemberPayload>,
+) -> Result<Json<Response<Vec<ApiOrganizationMember>>>> {
+    let members =
+        organization::set_member(&app_state, claims.user_id, organization_id, payload).await?;
+    tracing::info!(target: "handler", %organization_id, "Member set");
+
+    Ok(Json(Response::new(members)))
+}
+
+/// Removes a member from an organization of the currently authenticated user.
+/// Members may remove themselves to leave the organization.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path((organization_id, member_id))`: IDs of the organization and of the
+///   member.
+///
+/// # Returns
+///
+/// On success, returns `204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/organizations/{id}/members/{user_id}",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Organization ID"),
+        ("user_id" = Uuid, Path, description = "Member ID")
+    ),
+    responses(
+        (status = 204, description = "Member removed"),
+        (status = 400, body = ApiError, description = "Personal organization"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Not managed by the user"),
+        (status = 404, body = ApiError, description = "Organization or member not found"),
+        (status = 409, body = ApiError, description = "Last owner of the organization"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn remove_member(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((organization_id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    organization::remove_member(&app_state, claims.user_id, organization_id, member_id).await?;
+    tracing::info!(target: "handler", %organization_id, %member_id, "Member removed");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the servers of an organization of the currently authenticated
+/// user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(organization_id)`: ID of the organization.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the servers of the organization.
+///
+#[utoipa::path(
+    get,
+    path = "/organizations/{id}/servers",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiServer>>, description = "Servers found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Organization not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_servers(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiServer>>>> {
+    let servers = organization::get_servers(&app_state, claims.user_id, organization_id).await?;
+    tracing::info!(target: "handler", count = servers.len(), "Found servers");
+
+    Ok(Json(Response::new(servers)))
+}
+
+/// Moves a server of the currently authenticated user to an organization the
+/// user manages.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(organization_id)`: ID of the organization.
+/// * `Json(payload)`: ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the servers of the organization.
+///
+#[utoipa::path(
+    post,
+    path = "/organizations/{id}/servers",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    request_body = OrganizationServerPayload,
+    responses(
+        (status = 200, body = Response<Vec<ApiServer>>, description = "Server moved"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Not managed by the user"),
+        (status = 404, body = ApiError, description = "Organization or server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, payload),
+	fields(id = %claims.user_id))]
+async fn add_server(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<OrganizationServerPayload>,
+) -> Result<Json<Response<Vec<ApiServer>>>> {
+    let servers =
+        organization::add_server(&app_state, claims.user_id, organization_id, payload).await?;
+    tracing::info!(target: "handler", %organization_id, "Server moved to organization");
+
+    Ok(Json(Response::new(servers)))
+}
+
+/// Returns the SAML single sign-on of an organization the currently
+/// authenticated user owns.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(organization_id)`: ID of the organization.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the settings of the single
+/// sign-on.
+///
+#[utoipa::path(
+    get,
+    path = "/organizations/{id}/saml",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    responses(
+        (status = 200, body = Response<ApiSamlSettings>, description = "Single sign-on found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Not owned by the user"),
+        (status = 404, body = ApiError, description = "Organization or single sign-on not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_saml(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<Json<Response<ApiSamlSettings>>> {
+    let settings = saml::get_settings(&app_state, claims.user_id, organization_id).await?;
+    tracing::info!(target: "handler", %organization_id, "Found single sign-on");
+
+    Ok(Json(Response::new(settings)))
+}
+
+/// Sets up or changes the SAML single sign-on of an organization the currently
+/// authenticated user owns.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(organization_id)`: ID of the organization.
+/// * `Json(payload)`: Metadata of the identity provider and the mapping of its
+///   attributes.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the settings of the single
+/// sign-on.
+///
+#[utoipa::path(
+    put,
+    path = "/organizations/{id}/saml",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    request_body = SamlSettingsPayload,
+    responses(
+        (status = 200, body = Response<ApiSamlSettings>, description = "Single sign-on set"),
+        (status = 400, body = ApiError, description = "Invalid metadata or personal organization"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Not owned by the user"),
+        (status = 404, body = ApiError, description = "Organization not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, payload),
+	fields(id = %claims.user_id))]
+async fn set_saml(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<SamlSettingsPayload>,
+) -> Result<Json<Response<ApiSamlSettings>>> {
+    let settings = saml::set_settings(&app_state, claims.user_id, organization_id, payload).await?;
+    tracing::info!(target: "handler", %organization_id, "Single sign-on set");
+
+    Ok(Json(Response::new(settings)))
+}
+
+/// Removes the SAML single sign-on of an organization the currently
+/// authenticated user owns.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(organization_id)`: ID of the organization.
+///
+/// # Returns
+///
+/// On success, returns `204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/organizations/{id}/saml",
+    tags = ["Organization"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Organization ID")),
+    responses(
+        (status = 204, description = "Single sign-on removed"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Not owned by the user"),
+        (status = 404, body = ApiError, description = "Organization or single sign-on not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn delete_saml(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    saml::delete_settings(&app_state, claims.user_id, organization_id).await?;
+    tracing::info!(target: "handler", %organization_id, "Single sign-on removed");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,1154 @@
+//! Admin routes of the product catalog
+
+use crate::model::queries;
+use crate::model::types::{
+    ApiConfigOption, ApiCustomField, ApiDatacenter, ApiIso, ApiProduct, ApiProductGroup,
+    ApiTemplate,
+};
+use crate::services::catalog;
+use crate::state::AppState;
+use crate::web::middleware as mw;
+use crate::web::types::{
+    CustomFieldPayload, DatacenterPayload, IsoPayload, NamePayload, ProductDatacentersPayload,
+    ProductPayload, Response, TemplatePayload,
+};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
+
+/// Defines routes managing the product catalog: product groups, products,
+/// their custom fields, the configurable options, the OS templates, the ISOs
+/// and the datacenters. All routes require authentication and administrator
+/// privileges. Every change invalidates the catalog cache of this replica.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/admin/product-groups",
+            get(list_product_groups).post(create_product_group),
+        )
+        .route(
+            "/admin/product-groups/{id}",
+            put(update_product_group).delete(delete_product_group),
+        )
+        .route("/admin/products", get(list_products).post(create_product))
+        .route(
+            "/admin/products/{id}",
+            get(get_product).put(update_product).delete(delete_product),
+        )
+        .route(
+            "/admin/products/{id}/datacenters",
+            get(list_product_datacenters).put(set_product_datacenters),
+        )
+        .route(
+            "/admin/products/{id}/custom-fields",
+            get(list_custom_fields).post(create_custom_field),
+        )
+        .route(
+            "/admin/custom-fields/{id}",
+            put(update_custom_field).delete(delete_custom_field),
+        )
+        .route(
+            "/admin/config-options",
+            get(list_config_options).post(create_config_option),
+        )
+        .route(
+            "/admin/config-options/{id}",
+            put(update_config_option).delete(delete_config_option),
+        )
+        .route(
+            "/admin/templates",
+            get(list_templates).post(create_template),
+        )
+        .route(
+            "/admin/templates/{id}",
+            put(update_template).delete(delete_template),
+        )
+        .route("/admin/isos", post(create_iso))
+        .route("/admin/isos/{id}", delete(delete_iso))
+        .route(
+            "/admin/datacenters",
+            get(list_datacenters).post(create_datacenter),
+        )
+        .route(
+            "/admin/datacenters/{code}",
+            put(update_datacenter).delete(delete_datacenter),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            mw::require_admin,
+        ))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Returns all product groups.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the groups sorted by name.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/product-groups",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiProductGroup>>, description = "Product groups found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_product_groups(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiProductGroup>>>> {
+    let groups = queries::get_product_groups(&app_state.pool).await?;
+
+    Ok(Json(Response::new(groups)))
+}
+
+/// Creates a product group.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Name of the group.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created group.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/product-groups",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = NamePayload,
+    responses(
+        (status = 200, body = Response<ApiProductGroup>, description = "Product group created"),
+        (status = 400, body = ApiError, description = "Invalid name"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_product_group(
+    State(app_state): State<AppState>,
+    Json(payload): Json<NamePayload>,
+) -> Result<Json<Response<ApiProductGroup>>> {
+    let group = catalog::create_group(&app_state, payload).await?;
+    tracing::info!(target: "handler", group_id = %group.id, "Product group created");
+
+    Ok(Json(Response::new(group)))
+}
+
+/// Renames a product group.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(group_id)`: ID of the group.
+/// * `Json(payload)`: New name of the group.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated group.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/product-groups/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product group ID")),
+    request_body = NamePayload,
+    responses(
+        (status = 200, body = Response<ApiProductGroup>, description = "Product group updated"),
+        (status = 400, body = ApiError, description = "Invalid name"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Product group not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn update_product_group(
+    State(app_state): State<AppState>,
+    Path(group_id): Path<Uuid>,
+    Json(payload): Json<NamePayload>,
+) -> Result<Json<Response<ApiProductGroup>>> {
+    let group = catalog::update_group(&app_state, group_id, payload).await?;
+    tracing::info!(target: "handler", %group_id, "Product group updated");
+
+    Ok(Json(Response::new(group)))
+}
+
+/// Deletes a product group without products.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(group_id)`: ID of the group.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/product-groups/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product group ID")),
+    responses(
+        (status = 204, description = "Product group deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Product group not found"),
+        (status = 409, body = ApiError, description = "Product group holds products"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_product_group(
+    State(app_state): State<AppState>,
+    Path(group_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    catalog::delete_group(&app_state, group_id).await?;
+    tracing::info!(target: "handler", %group_id, "Product group deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns all products.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the products.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/products",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiProduct>>, description = "Products found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_products(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiProduct>>>> {
+    let products = queries::get_products(&app_state.pool).await?;
+
+    Ok(Json(Response::new(products)))
+}
+
+/// Returns a single product.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(product_id)`: ID of the product.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the product.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/products/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    responses(
+        (status = 200, body = Response<ApiProduct>, description = "Product found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Product not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn get_product(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> Result<Json<Response<ApiProduct>>> {
+    let product = queries::get_product(&app_state.pool, product_id).await?;
+
+    Ok(Json(Response::new(product)))
+}
+
+/// Creates a product in an existing group.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Group, name and network rate limit of the product.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created product.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/products",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = ProductPayload,
+    responses(
+        (status = 200, body = Response<ApiProduct>, description = "Product created"),
+        (status = 400, body = ApiError, description = "Invalid product or unknown group"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_product(
+    State(app_state): State<AppState>,
+    Json(payload): Json<ProductPayload>,
+) -> Result<Json<Response<ApiProduct>>> {
+    let product = catalog::create_product(&app_state, payload).await?;
+    tracing::info!(target: "handler", product_id = %product.id, "Product created");
+
+    Ok(Json(Response::new(product)))
+}
+
+/// Updates a product. Servers keep the network rate limit applied to their VM.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(product_id)`: ID of the product.
+/// * `Json(payload)`: New group, name and network rate limit of the product.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated product.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/products/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    request_body = ProductPayload,
+    responses(
+        (status = 200, body = Response<ApiProduct>, description = "Product updated"),
+        (status = 400, body = ApiError, description = "Invalid product or unknown group"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Product not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn update_product(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<ProductPayload>,
+) -> Result<Json<Response<ApiProduct>>> {
+    let product = catalog::update_product(&app_state, product_id, payload).await?;
+    tracing::info!(target: "handler", %product_id, "Product updated");
+
+    Ok(Json(Response::new(product)))
+}
+
+/// Deletes a product that was never ordered, together with its custom fields.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(product_id)`: ID of the product.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/products/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    responses(
+        (status = 204, description = "Product deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Product not found"),
+        (status = 409, body = ApiError, description = "Product was ordered"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_product(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    catalog::delete_product(&app_state, product_id).await?;
+    tracing::info!(target: "handler", %product_id, "Product deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the custom fields of a product.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(product_id)`: ID of the product.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the fields sorted by name.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/products/{id}/custom-fields",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiCustomField>>, description = "Custom fields found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_custom_fields(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiCustomField>>>> {
+    let fields = queries::get_custom_fields(&app_state.pool, product_id).await?;
+
+    Ok(Json(Response::new(fields)))
+}
+
+/// Creates a custom field of a product. The values of the OS template field
+/// must name existing templates, those of the datacenter location field
+/// existing datacenters.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(product_id)`: ID of the product.
+/// * `Json(payload)`: Name and offered values of the field.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created field.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/products/{id}/custom-fields",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    request_body = CustomFieldPayload,
+    responses(
+        (status = 200, body = Response<ApiCustomField>, description = "Custom field created"),
+        (status = 400, body = ApiError, description = "Invalid field or unknown values"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Product not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_custom_field(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<CustomFieldPayload>,
+) -> Result<Json<Response<ApiCustomField>>> {
+    let field = catalog::create_custom_field(&app_state, product_id, payload).await?;
+    tracing::info!(target: "handler", field_id = %field.id, "Custom field created");
+
+    Ok(Json(Response::new(field)))
+}
+
+/// Updates a custom field, validating its values like on creation.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(field_id)`: ID of the field.
+/// * `Json(payload)`: New name and offered values of the field.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated field.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/custom-fields/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Custom field ID")),
+    request_body = CustomFieldPayload,
+    responses(
+        (status = 200, body = Response<ApiCustomField>, description = "Custom field updated"),
+        (status = 400, body = ApiError, description = "Invalid field or unknown values"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Custom field not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn update_custom_field(
+    State(app_state): State<AppState>,
+    Path(field_id): Path<Uuid>,
+    Json(payload): Json<CustomFieldPayload>,
+) -> Result<Json<Response<ApiCustomField>>> {
+    let field = catalog::update_custom_field(&app_state, field_id, payload).await?;
+    tracing::info!(target: "handler", %field_id, "Custom field updated");
+
+    Ok(Json(Response::new(field)))
+}
+
+/// Deletes a custom field that no service has a value of.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(field_id)`: ID of the field.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/custom-fields/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Custom field ID")),
+    responses(
+        (status = 204, description = "Custom field deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Custom field not found"),
+        (status = 409, body = ApiError, description = "Custom field has values"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_custom_field(
+    State(app_state): State<AppState>,
+    Path(field_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    catalog::delete_custom_field(&app_state, field_id).await?;
+    tracing::info!(target: "handler", %field_id, "Custom field deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns all configurable options.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the options sorted by name.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/config-options",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiConfigOption>>, description = "Configurable options found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_config_options(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiConfigOption>>>> {
+    let options = queries::get_config_options(&app_state.pool).await?;
+
+    Ok(Json(Response::new(options)))
+}
+
+/// Creates a configurable option.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Name of the option.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created option.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/config-options",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = NamePayload,
+    responses(
+        (status = 200, body = Response<ApiConfigOption>, description = "Configurable option created"),
+        (status = 400, body = ApiError, description = "Invalid name"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_config_option(
+    State(app_state): State<AppState>,
+    Json(payload): Json<NamePayload>,
+) -> Result<Json<Response<ApiConfigOption>>> {
+    let option = catalog::create_config_option(&app_state, payload).await?;
+    tracing::info!(target: "handler", option_id = %option.id, "Configurable option created");
+
+    Ok(Json(Response::new(option)))
+}
+
+/// Renames a configurable option.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(option_id)`: ID of the option.
+/// * `Json(payload)`: New name of the option.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated option.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/config-options/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Configurable option ID")),
+    request_body = NamePayload,
+    responses(
+        (status = 200, body = Response<ApiConfigOption>, description = "Configurable option updated"),
+        (status = 400, body = ApiError, description = "Invalid name"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Configurable option not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn update_config_option(
+    State(app_state): State<AppState>,
+    Path(option_id): Path<Uuid>,
+    Json(payload): Json<NamePayload>,
+) -> Result<Json<Response<ApiConfigOption>>> {
+    let option = catalog::update_config_option(&app_state, option_id, payload).await?;
+    tracing::info!(target: "handler", %option_id, "Configurable option updated");
+
+    Ok(Json(Response::new(option)))
+}
+
+/// Deletes a configurable option that no service has a value of.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(option_id)`: ID of the option.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/config-options/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Configurable option ID")),
+    responses(
+        (status = 204, description = "Configurable option deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Configurable option not found"),
+        (status = 409, body = ApiError, description = "Configurable option has values"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_config_option(
+    State(app_state): State<AppState>,
+    Path(option_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    catalog::delete_config_option(&app_state, option_id).await?;
+    tracing::info!(target: "handler", %option_id, "Configurable option deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns all OS templates.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the templates sorted by OS name.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/templates",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiTemplate>>, description = "Templates found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_templates(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiTemplate>>>> {
+    let templates = queries::get_templates(&app_state.pool).await?;
+
+    Ok(Json(Response::new(templates)))
+}
+
+/// Registers an OS template. The VM must exist on the node and be a template.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: OS name, VMID and node of the template.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the registered template.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/templates",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = TemplatePayload,
+    responses(
+        (status = 200, body = Response<ApiTemplate>, description = "Template registered"),
+        (status = 400, body = ApiError, description = "Invalid template or no template VM"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 409, body = ApiError, description = "OS name or VMID already registered"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_template(
+    State(app_state): State<AppState>,
+    Json(payload): Json<TemplatePayload>,
+) -> Result<Json<Response<ApiTemplate>>> {
+    let template = catalog::create_template(&app_state, payload).await?;
+    tracing::info!(target: "handler", template_id = %template.id, "Template registered");
+
+    Ok(Json(Response::new(template)))
+}
+
+/// Updates an OS template, validating the VM like on registration. A changed
+/// OS name is renamed in the values of the OS template fields too.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(template_id)`: ID of the template.
+/// * `Json(payload)`: New OS name, VMID and node of the template.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated template.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/templates/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Template ID")),
+    request_body = TemplatePayload,
+    responses(
+        (status = 200, body = Response<ApiTemplate>, description = "Template updated"),
+        (status = 400, body = ApiError, description = "Invalid template or no template VM"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Template not found"),
+        (status = 409, body = ApiError, description = "OS name or VMID already registered"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn update_template(
+    State(app_state): State<AppState>,
+    Path(template_id): Path<Uuid>,
+    Json(payload): Json<TemplatePayload>,
+) -> Result<Json<Response<ApiTemplate>>> {
+    let template = catalog::update_template(&app_state, template_id, payload).await?;
+    tracing::info!(target: "handler", %template_id, "Template updated");
+
+    Ok(Json(Response::new(template)))
+}
+
+/// Deletes an OS template no server was created from. Its OS name is removed
+/// from the values of the OS template fields.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(template_id)`: ID of the template.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/templates/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Template ID")),
+    responses(
+        (status = 204, description = "Template deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Template not found"),
+        (status = 409, body = ApiError, description = "Template is in use"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_template(
+    State(app_state): State<AppState>,
+    Path(template_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    catalog::delete_template(&app_state, template_id).await?;
+    tracing::info!(target: "handler", %template_id, "Template deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Registers an ISO. The volume must be an ISO image listed on the storage of
+/// the node.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Name, volume and node of the ISO.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the registered ISO.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/isos",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = IsoPayload,
+    responses(
+        (status = 200, body = Response<ApiIso>, description = "ISO registered"),
+        (status = 400, body = ApiError, description = "Invalid name or no ISO image"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 409, body = ApiError, description = "Name or volume already registered"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_iso(
+    State(app_state): State<AppState>,
+    Json(payload): Json<IsoPayload>,
+) -> Result<Json<Response<ApiIso>>> {
+    let iso = catalog::create_iso(&app_state, payload).await?;
+    tracing::info!(target: "handler", iso_id = %iso.id, "ISO registered");
+
+    Ok(Json(Response::new(iso)))
+}
+
+/// Deletes an ISO no server has inserted. The image stays on the storage.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(iso_id)`: ID of the ISO.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/isos/{id}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "ISO ID")),
+    responses(
+        (status = 204, description = "ISO deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "ISO not found"),
+        (status = 409, body = ApiError, description = "ISO is inserted into a server"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_iso(
+    State(app_state): State<AppState>,
+    Path(iso_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    catalog::delete_iso(&app_state, iso_id).await?;
+    tracing::info!(target: "handler", %iso_id, "ISO deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the datacenters a product is offered in, including the inactive and
+/// full ones.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(product_id)`: ID of the product.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the datacenters sorted by code.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/products/{id}/datacenters",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiDatacenter>>, description = "Datacenters found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_product_datacenters(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiDatacenter>>>> {
+    let datacenters = queries::get_product_datacenters(&app_state.pool, product_id).await?;
+
+    Ok(Json(Response::new(datacenters)))
+}
+
+/// Replaces the datacenters a product is offered in.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(product_id)`: ID of the product.
+/// * `Json(payload)`: Codes of the datacenters.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the datacenters sorted by code.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/products/{id}/datacenters",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Product ID")),
+    request_body = ProductDatacentersPayload,
+    responses(
+        (status = 200, body = Response<Vec<ApiDatacenter>>, description = "Datacenters set"),
+        (status = 400, body = ApiError, description = "Unknown datacenter"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Product not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn set_product_datacenters(
+    State(app_state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<ProductDatacentersPayload>,
+) -> Result<Json<Response<Vec<ApiDatacenter>>>> {
+    let datacenters = catalog::set_product_datacenters(&app_state, product_id, payload).await?;
+    tracing::info!(target: "handler", %product_id, "Product datacenters set");
+
+    Ok(Json(Response::new(datacenters)))
+}
+
+/// Returns all datacenters.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the datacenters sorted by code.
+///
+#[utoipa::path(
+    get,
+    path = "/admin/datacenters",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiDatacenter>>, description = "Datacenters found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn list_datacenters(
+    State(app_state): State<AppState>,
+) -> Result<Json<Response<Vec<ApiDatacenter>>>> {
+    let datacenters = queries::get_datacenters(&app_state.pool).await?;
+
+    Ok(Json(Response::new(datacenters)))
+}
+
+/// Creates a datacenter.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Json(payload)`: Code, name, cluster endpoint and capacity flags.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created datacenter.
+///
+#[utoipa::path(
+    post,
+    path = "/admin/datacenters",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    request_body = DatacenterPayload,
+    responses(
+        (status = 200, body = Response<ApiDatacenter>, description = "Datacenter created"),
+        (status = 400, body = ApiError, description = "Invalid datacenter"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 409, body = ApiError, description = "Code already in use"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn create_datacenter(
+    State(app_state): State<AppState>,
+    Json(payload): Json<DatacenterPayload>,
+) -> Result<Json<Response<ApiDatacenter>>> {
+    let datacenter = catalog::create_datacenter(&app_state, payload).await?;
+    tracing::info!(target: "handler", code = %datacenter.code, "Datacenter created");
+
+    Ok(Json(Response::new(datacenter)))
+}
+
+/// Updates a datacenter. A changed code is changed in the networks, templates
+/// and products of the datacenter too.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(code)`: Current code of the datacenter.
+/// * `Json(payload)`: New code, name, cluster endpoint and capacity flags.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated datacenter.
+///
+#[utoipa::path(
+    put,
+    path = "/admin/datacenters/{code}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("code" = String, Path, description = "Datacenter code")),
+    request_body = DatacenterPayload,
+    responses(
+        (status = 200, body = Response<ApiDatacenter>, description = "Datacenter updated"),
+        (status = 400, body = ApiError, description = "Invalid datacenter"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Datacenter not found"),
+        (status = 409, body = ApiError, description = "Code already in use"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn update_datacenter(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+    Json(payload): Json<DatacenterPayload>,
+) -> Result<Json<Response<ApiDatacenter>>> {
+    let datacenter = catalog::update_datacenter(&app_state, &code, payload).await?;
+    tracing::info!(target: "handler", %code, "Datacenter updated");
+
+    Ok(Json(Response::new(datacenter)))
+}
+
+/// Deletes a datacenter without networks and templates.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Path(code)`: Code of the datacenter.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/admin/datacenters/{code}",
+    tags = ["Admin"],
+    security(("bearer_auth" = [])),
+    params(("code" = String, Path, description = "Datacenter code")),
+    responses(
+        (status = 204, description = "Datacenter deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Forbidden"),
+        (status = 404, body = ApiError, description = "Datacenter not found"),
+        (status = 409, body = ApiError, description = "Datacenter is in use"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler", skip(app_state))]
+async fn delete_datacenter(
+    State(app_state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<StatusCode> {
+    catalog::delete_datacenter(&app_state, &code).await?;
+    tracing::info!(target: "handler", %code, "Datacenter deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,18 +1,26 @@
 //! Protected routes
 
 use crate::model::queries;
-use crate::model::types::ApiServer;
-use crate::services::{action, deletion, setup};
+use crate::model::types::{
+    ApiActionResult, ApiBackup, ApiBackupSchedule, ApiDisk, ApiFirewall, ApiFirewallRule,
+    ApiPasswordReset, ApiProvisioningStep, ApiQuotas, ApiServer, ApiServerDetail, ApiServerIso,
+    ApiServerTag, ApiServerTraffic, ApiTimelineEvent, FirewallSettings, ServerStatus,
+};
+use crate::services::{
+    self, action, agent, backup, catalog, deletion, disk, firewall, ip, iso, marketplace, password,
+    quota, setup, tag, timeline, traffic, user,
+};
 use crate::state::AppState;
 use crate::web::auth::Claims;
 use crate::web::middleware as mw;
 use crate::web::types::*;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
-use axum::routing::{get, post};
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post, put};
 use axum::{Extension, Json};
 use axum::{Router, middleware};
-use dashboard_common::prelude::Result;
+use dashboard_common::prelude::{ApiError, Error, Result};
 use uuid::Uuid;
 
 /// Defines routes for the server section. All routes are protected and require
@@ -25,9 +33,54 @@ use uuid::Uuid;
 pub fn routes(app_state: AppState) -> Router<AppState> {
     Router::new()
         .route("/user/me", get(get_user))
+        .route("/me/quota", get(get_quota))
         .route("/servers", get(list_servers).post(create_server))
+        .route("/servers/actions", post(bulk_action))
+        .route("/servers/tags", get(list_tags))
+        .route(
+            "/servers/external/{external_id}",
+            get(get_server_by_external_id),
+        )
         .route("/servers/{id}", get(get_server).delete(delete_server))
         .route("/servers/{id}/actions", post(server_action))
+        .route("/servers/{id}/ips", post(add_ip))
+        .route("/servers/{id}/ips/{address}", delete(remove_ip))
+        .route(
+            "/servers/{id}/firewall",
+            get(get_firewall).put(set_firewall),
+        )
+        .route("/servers/{id}/firewall/rules", post(add_firewall_rule))
+        .route(
+            "/servers/{id}/firewall/rules/{rule_id}",
+            delete(delete_firewall_rule),
+        )
+        .route("/servers/{id}/backups", get(list_backups))
+        .route(
+            "/servers/{id}/backups/{volid}/restore",
+            post(restore_backup),
+        )
+        .route(
+            "/servers/{id}/backups/schedule",
+            get(get_backup_schedule)
+                .put(set_backup_schedule)
+                .delete(delete_backup_schedule),
+        )
+        .route("/servers/{id}/tags", put(set_tags))
+        .route("/servers/{id}/tags/{tag}", delete(remove_tag))
+        .route("/servers/{id}/notes", put(set_notes))
+        .route("/servers/{id}/external-id", put(set_external_id))
+        .route("/servers/{id}/timeline", get(get_timeline))
+        .route("/servers/{id}/password", post(reset_password))
+        .route("/servers/{id}/disks", get(list_disks).post(add_disk))
+        .route("/servers/{id}/disks/{device}/resize", put(resize_disk))
+        .route(
+            "/servers/{id}/iso",
+            get(get_iso).put(attach_iso).delete(detach_iso),
+        )
+        .route("/servers/{id}/iso/boot", put(set_boot_order))
+        .route("/servers/{id}/traffic", get(get_traffic))
+        .route("/servers/{id}/provisioning", get(get_provisioning))
+        .route("/servers/{id}/provisioning/retry", post(retry_provisioning))
         .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
 }
 
@@ -55,9 +108,9 @@ pub fn routes(app_state: AppState) -> Router<AppState> {
     tags = ["Server"],
     responses(
         (status = 200, body = UserResponse, description = "User found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "User not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "User not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 #[tracing::instrument(level = "trace", target = "handler",
@@ -67,13 +120,54 @@ async fn get_user(
     State(app_state): State<AppState>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<UserResponse>> {
-    let user = queries::get_user_by_id(&app_state.pool, claims.user_id).await?;
+    let user = queries::get_user_by_id(app_state.reader(), claims.user_id).await?;
     tracing::info!(target: "handler", email = user.email, "Found user");
 
     Ok(Json(Response::new(user)))
 }
 
-/// Returns the list of all servers that belong to currently authenticated user.
+/// Returns the resource usage of the currently authenticated user against the
+/// quotas of their account and of every limited product.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state, containing the database
+///   pool.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the usage and limits.
+///
+#[utoipa::path(
+    get,
+    path = "/me/quota",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<ApiQuotas>, description = "Quota found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_quota(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<ApiQuotas>>> {
+    let mut connection = app_state.pool.acquire().await?;
+    let quotas =
+        quota::get_quotas(&mut connection, &app_state.config.quota, claims.user_id).await?;
+    tracing::info!(target: "handler", products = quotas.products.len(), "Found quota");
+
+    Ok(Json(Response::new(quotas)))
+}
+
+/// Returns the list of all servers that belong to currently authenticated user,
+/// optionally only those with all the given tags.
 ///
 /// This endpoint is protected, and the user is identified via the `user_id`
 /// claim from the JWT provided in the `Authorization` bearer token.
@@ -84,6 +178,7 @@ async fn get_user(
 ///   pool.
 /// * `Extension(claims)`: The claims extracted from the JWT, which include the
 ///   user's ID.
+/// * `Query(query)`: Comma-separated tags filtering the servers.
 ///
 /// # Returns
 ///
@@ -95,11 +190,12 @@ async fn get_user(
     path = "/servers",
     tags = ["Server"],
     security(("bearer_auth" = [])),
+    params(ServerListQuery),
     responses(
         (status = 200, body = Response<Vec<ApiServer>>, description = "Servers found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "User not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "User not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 #[tracing::instrument(level = "trace", target = "handler",
@@ -108,8 +204,11 @@ async fn get_user(
 async fn list_servers(
     State(app_state): State<AppState>,
     Extension(claims): Extension<Claims>,
+    Query(query): Query<ServerListQuery>,
 ) -> Result<Json<Response<Vec<ApiServer>>>> {
-    let servers = queries::get_servers_for_user(&app_state.pool, claims.user_id).await?;
+    let mut servers = queries::get_servers_for_user(app_state.reader(), claims.user_id).await?;
+    let tags = tag::parse_filter(query.tags.as_deref());
+    servers.retain(|server| tags.iter().all(|wanted| server.tags.contains(wanted)));
     tracing::info!(target: "handler", count = servers.len(), "Found servers");
 
     Ok(Json(Response::new(servers)))
@@ -131,7 +230,15 @@ async fn list_servers(
 ///
 /// # Returns
 ///
-/// This handler always returns an `HTTP 202 Accepted`
+/// `HTTP 202 Accepted` once the request fits into the user's quotas, the
+/// user's email address is verified, the host name is free and the product is
+/// offered in the picked datacenter, as is the application, if any. All of them are checked again by the
+/// setup service before cloning. Orders are rejected while switched off by the
+/// feature flags, or while the datacenter is full.
+///
+/// An order with the external ID of a server the user already has is not
+/// placed again, the server is returned with `HTTP 200 OK` instead, so
+/// infrastructure-as-code tools can re-apply their plans.
 ///
 #[utoipa::path(
     post,
@@ -139,19 +246,59 @@ async fn list_servers(
     tags = ["Server"],
     security(("bearer_auth" = [])),
     responses(
+        (status = 200, body = Response<ApiServer>, description = "Server with the external ID already ordered"),
         (status = 202, description = "Server creation accepted"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 400, body = ApiError, description = "Invalid host name, datacenter, application, tag or external ID"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Quota exceeded or email address not verified"),
+        (status = 409, body = ApiError, description = "Host name already in use or server orders paused"),
+        (status = 500, body = ApiError, description = "Internal server error"),
+        (status = 503, body = ApiError, description = "Datacenter full")
     )
 )]
 async fn create_server(
     State(app_state): State<AppState>,
     Extension(claims): Extension<Claims>,
-    Json(payload): Json<NewServerPayload>,
-) -> Result<StatusCode> {
+    Json(mut payload): Json<NewServerPayload>,
+) -> Result<axum::response::Response> {
+    payload.external_id = payload
+        .external_id
+        .as_deref()
+        .map(tag::normalize_external_id)
+        .transpose()?;
+    let mut connection = app_state.pool.acquire().await?;
+    if let Some(external_id) = &payload.external_id
+        && let Some(server) =
+            tag::find_by_external_id(&mut connection, claims.user_id, external_id).await?
+    {
+        tracing::info!(target: "handler", server_id = %server.server_id, "Server already ordered");
+        return Ok(Json(Response::new(server)).into_response());
+    }
+
+    if !app_state.runtime.load().features.server_orders {
+        return Err(Error::Conflict("Server orders are paused".to_owned()));
+    }
+    payload.host_name = setup::normalize_host_name(&payload.host_name)?;
+    payload.tags = tag::normalize_tags(&payload.tags)?;
+
+    user::ensure_verified(&mut *connection, claims.user_id).await?;
+    setup::ensure_host_name_available(&mut *connection, &payload.host_name).await?;
+    catalog::ensure_datacenter_available(&mut *connection, payload.product_id, &payload.datacenter)
+        .await?;
+    if let Some(app) = &payload.app {
+        marketplace::ensure_app_offered(&app_state, app).await?;
+    }
+    quota::ensure_quota(
+        &mut connection,
+        &app_state.config.quota,
+        claims.user_id,
+        &payload,
+    )
+    .await?;
+
     tokio::spawn(setup::run(app_state.clone(), claims.user_id, payload));
 
-    Ok(StatusCode::ACCEPTED)
+    Ok(StatusCode::ACCEPTED.into_response())
 }
 
 /// Retrieves and returns the details of a specific server.
@@ -167,8 +314,8 @@ async fn create_server(
 ///
 /// # Returns
 ///
-/// On success, returns a Json response with the user's server.
-/// [`ApiUser`].
+/// On success, returns a Json response with the user's server, and the system
+/// and addresses reported by its guest agent while it is running.
 ///
 #[utoipa::path(
     get,
@@ -177,19 +324,19 @@ async fn create_server(
     security(("bearer_auth" = [])),
     params(("id", Path, description = "Unique server ID")),
     responses(
-        (status = 200, body = Response<ApiServer>, description = "Server found"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 404, body = String, description = "Server not found"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 200, body = Response<ApiServerDetail>, description = "Server found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 async fn get_server(
     State(app_state): State<AppState>,
     Extension(claims): Extension<Claims>,
     Path(server_id): Path<Uuid>,
-) -> Result<Json<Response<ApiServer>>> {
-    let server = queries::get_server_by_id(&app_state.pool, claims.user_id, server_id).await?;
-    tracing::info!(target: "handler", server_id = ?server.server_id, "Server found");
+) -> Result<Json<Response<ApiServerDetail>>> {
+    let server = agent::get_server_detail(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", server_id = ?server.server.server_id, "Server found");
 
     Ok(Json(Response::new(server)))
 }
@@ -207,7 +354,9 @@ async fn get_server(
 ///
 /// # Returns
 ///
-/// This handler always returns an `HTTP 202 Accepted`
+/// `HTTP 202 Accepted` once the deletion started, `HTTP 404 Not Found` if the
+/// user has no such server, `HTTP 409 Conflict` if another operation is in
+/// progress on the server.
 ///
 #[utoipa::path(
     delete,
@@ -217,8 +366,10 @@ async fn get_server(
     params(("id", Path, description = "Unique server ID")),
     responses(
         (status = 202, description = "Server action accepted"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "Another operation in progress"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 async fn delete_server(
@@ -226,7 +377,19 @@ async fn delete_server(
     Extension(claims): Extension<Claims>,
     Path(server_id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    tokio::spawn(deletion::run(app_state.clone(), claims.user_id, server_id));
+    let old_status = services::set_transient_status(
+        &app_state.pool,
+        claims.user_id,
+        server_id,
+        ServerStatus::Deleting,
+    )
+    .await?;
+    tokio::spawn(deletion::run(
+        app_state.clone(),
+        claims.user_id,
+        server_id,
+        old_status,
+    ));
 
     Ok(StatusCode::ACCEPTED)
 }
@@ -245,7 +408,9 @@ async fn delete_server(
 ///
 /// # Returns
 ///
-/// This handler always returns an `HTTP 202 Accepted`
+/// `HTTP 202 Accepted` once the action started, `HTTP 404 Not Found` if the
+/// user has no such server, `HTTP 409 Conflict` if another operation is in
+/// progress on the server or the action isn't allowed in its current status.
 ///
 #[utoipa::path(
     post,
@@ -256,8 +421,10 @@ async fn delete_server(
     request_body = ServerActionPayload,
     responses(
         (status = 202, description = "Server deleted"),
-        (status = 401, body = String, description = "Unauthorized"),
-        (status = 500, body = String, description = "Internal server error")
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "Another operation in progress or action not allowed"),
+        (status = 500, body = ApiError, description = "Internal server error")
     )
 )]
 async fn server_action(
@@ -266,12 +433,1349 @@ async fn server_action(
     Path(server_id): Path<Uuid>,
     Json(payload): Json<ServerActionPayload>,
 ) -> Result<StatusCode> {
+    let old_status = action::begin(&app_state, claims.user_id, server_id, payload.action).await?;
     tokio::spawn(action::run(
         app_state.clone(),
         claims.user_id,
         server_id,
         payload.action,
+        old_status,
     ));
 
     Ok(StatusCode::ACCEPTED)
 }
+
+/// Makes the same action on several servers at once.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Json(payload)`: Servers and the action to perform on them.
+///
+/// # Returns
+///
+/// On success, returns a `202 Accepted` Json response with the outcome of the
+/// start of the action for every server, while the actions run in the
+/// background.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/actions",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    request_body = BulkActionPayload,
+    responses(
+        (status = 202, body = Response<Vec<ApiActionResult>>, description = "Actions started"),
+        (status = 400, body = ApiError, description = "No servers or too many servers"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn bulk_action(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<BulkActionPayload>,
+) -> Result<(StatusCode, Json<Response<Vec<ApiActionResult>>>)> {
+    let (results, _) = action::start_bulk(&app_state, claims.user_id, payload).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(Response::new(results))))
+}
+
+/// Orders an additional IP address for a server from the pool of its
+/// datacenter.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated server.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/ips",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<ApiServer>, description = "IP address added"),
+        (status = 400, body = ApiError, description = "No free network device"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Quota exceeded"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 503, body = ApiError, description = "No free IP address"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn add_ip(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<ApiServer>>> {
+    let server = ip::add_ip(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, "Additional IP added");
+
+    Ok(Json(Response::new(server)))
+}
+
+/// Releases an additional IP address of a server.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path((server_id, address))`: Unique ID of the server and the address to
+///   release.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated server.
+///
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/ips/{address}",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(
+        ("id", Path, description = "Unique server ID"),
+        ("address", Path, description = "Additional IP address")
+    ),
+    responses(
+        (status = 200, body = Response<ApiServer>, description = "IP address released"),
+        (status = 400, body = ApiError, description = "Not an additional IP of the server"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn remove_ip(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((server_id, address)): Path<(Uuid, String)>,
+) -> Result<Json<Response<ApiServer>>> {
+    let server = ip::remove_ip(&app_state, claims.user_id, server_id, &address).await?;
+    tracing::info!(target: "handler", %server_id, %address, "Additional IP released");
+
+    Ok(Json(Response::new(server)))
+}
+
+/// Returns the firewall settings and rules of a server.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the server's firewall.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/firewall",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<ApiFirewall>, description = "Firewall found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_firewall(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<ApiFirewall>>> {
+    let firewall = firewall::get_firewall(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, rules = firewall.rules.len(), "Found firewall");
+
+    Ok(Json(Response::new(firewall)))
+}
+
+/// Enables or disables the firewall of a server and sets its policy for
+/// incoming traffic.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+/// * `Json(settings)`: New firewall settings.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the server's firewall.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/firewall",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    request_body = FirewallSettings,
+    responses(
+        (status = 200, body = Response<ApiFirewall>, description = "Firewall settings saved"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn set_firewall(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(settings): Json<FirewallSettings>,
+) -> Result<Json<Response<ApiFirewall>>> {
+    let firewall = firewall::set_settings(&app_state, claims.user_id, server_id, settings).await?;
+    tracing::info!(target: "handler", %server_id, enabled = firewall.enabled, "Firewall settings saved");
+
+    Ok(Json(Response::new(firewall)))
+}
+
+/// Adds a firewall rule to a server.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+/// * `Json(payload)`: Direction, action, protocol, port and optional source of
+///   the rule.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created rule.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/firewall/rules",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    request_body = FirewallRulePayload,
+    responses(
+        (status = 200, body = Response<ApiFirewallRule>, description = "Firewall rule added"),
+        (status = 400, body = ApiError, description = "Invalid port or source"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn add_firewall_rule(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<FirewallRulePayload>,
+) -> Result<Json<Response<ApiFirewallRule>>> {
+    let rule = firewall::add_rule(&app_state, claims.user_id, server_id, payload).await?;
+    tracing::info!(target: "handler", %server_id, rule_id = %rule.id, "Firewall rule added");
+
+    Ok(Json(Response::new(rule)))
+}
+
+/// Deletes a firewall rule of a server.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path((server_id, rule_id))`: Unique ID of the server and of the rule.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/firewall/rules/{rule_id}",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(
+        ("id", Path, description = "Unique server ID"),
+        ("rule_id", Path, description = "Unique firewall rule ID")
+    ),
+    responses(
+        (status = 204, description = "Firewall rule deleted"),
+        (status = 400, body = ApiError, description = "Firewall rule not found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn delete_firewall_rule(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((server_id, rule_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    firewall::delete_rule(&app_state, claims.user_id, server_id, rule_id).await?;
+    tracing::info!(target: "handler", %server_id, %rule_id, "Firewall rule deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lists the backup archives of a server that can be restored, newest first.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the list of backups.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/backups",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiBackup>>, description = "Backups found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_backups(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiBackup>>>> {
+    let backups = backup::list_backups(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, count = backups.len(), "Found backups");
+
+    Ok(Json(Response::new(backups)))
+}
+
+/// Restores a server from one of its backups. The server is stopped, its disks
+/// and configuration are overwritten with the backup, and it is started again.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path((server_id, volid))`: Unique ID of the server and the URL-encoded
+///   volume ID of the backup.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 202 Accepted`, the server's status shows the
+/// progress.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/backups/{volid}/restore",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(
+        ("id", Path, description = "Unique server ID"),
+        ("volid", Path, description = "Volume ID of the backup")
+    ),
+    responses(
+        (status = 202, description = "Restore started"),
+        (status = 400, body = ApiError, description = "Backup not found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Another operation in progress"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn restore_backup(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((server_id, volid)): Path<(Uuid, String)>,
+) -> Result<StatusCode> {
+    backup::ensure_backup(&app_state, claims.user_id, server_id, &volid).await?;
+    let old_status = services::set_transient_status(
+        &app_state.pool,
+        claims.user_id,
+        server_id,
+        ServerStatus::Restoring,
+    )
+    .await?;
+    tokio::spawn(backup::restore(
+        app_state.clone(),
+        claims.user_id,
+        server_id,
+        volid.clone(),
+        old_status,
+    ));
+    tracing::info!(target: "handler", %server_id, %volid, "Restore started");
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Returns the backup schedule of a server.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the schedule, `null` if the server
+/// is not scheduled for backups.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/backups/schedule",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<Option<ApiBackupSchedule>>, description = "Backup schedule found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_backup_schedule(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<Option<ApiBackupSchedule>>>> {
+    let schedule = backup::get_schedule(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, scheduled = schedule.is_some(), "Found backup schedule");
+
+    Ok(Json(Response::new(schedule)))
+}
+
+/// Schedules backups of a server, replacing its previous schedule.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+/// * `Json(payload)`: Cron expression and mode of the backups.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the saved schedule.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/backups/schedule",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    request_body = BackupSchedulePayload,
+    responses(
+        (status = 200, body = Response<ApiBackupSchedule>, description = "Backup schedule saved"),
+        (status = 400, body = ApiError, description = "Invalid or too frequent schedule"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn set_backup_schedule(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<BackupSchedulePayload>,
+) -> Result<Json<Response<ApiBackupSchedule>>> {
+    let schedule = backup::set_schedule(&app_state, claims.user_id, server_id, payload).await?;
+    tracing::info!(target: "handler", %server_id, cron = %schedule.cron, "Backup schedule saved");
+
+    Ok(Json(Response::new(schedule)))
+}
+
+/// Stops scheduled backups of a server. Existing backups are kept.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/backups/schedule",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    responses(
+        (status = 204, description = "Backup schedule deleted"),
+        (status = 400, body = ApiError, description = "No backup schedule"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn delete_backup_schedule(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    backup::delete_schedule(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, "Backup schedule deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the provisioning steps of a server with their progress, in
+/// execution order.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the list of steps.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/provisioning",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiProvisioningStep>>, description = "Provisioning steps found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_provisioning(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiProvisioningStep>>>> {
+    let steps = queries::get_provisioning_steps(&app_state.pool, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, count = steps.len(), "Found provisioning steps");
+
+    Ok(Json(Response::new(steps)))
+}
+
+/// Resumes a failed server provisioning from the step that failed, keeping
+/// the steps that already completed.
+///
+/// This endpoint is protected, and the user is identified via the `user_id`
+/// claim from the JWT provided in the `Authorization` bearer token.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 202 Accepted`, the provisioning steps show the
+/// progress.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/provisioning/retry",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id", Path, description = "Unique server ID")),
+    responses(
+        (status = 202, description = "Provisioning resumed"),
+        (status = 400, body = ApiError, description = "No failed provisioning step"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn retry_provisioning(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    setup::retry(&app_state, claims.user_id, server_id).await?;
+    tokio::spawn(setup::provision(
+        app_state.clone(),
+        claims.user_id,
+        server_id,
+    ));
+    tracing::info!(target: "handler", %server_id, "Provisioning resumed");
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Returns the tags used on the servers of the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the tags and the number of servers
+/// having each of them.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/tags",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiServerTag>>, description = "Tags found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_tags(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiServerTag>>>> {
+    let tags = queries::get_server_tags(app_state.reader(), claims.user_id).await?;
+    tracing::info!(target: "handler", count = tags.len(), "Found tags");
+
+    Ok(Json(Response::new(tags)))
+}
+
+/// Replaces the tags of a server of the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(server_id)`: ID of the server.
+/// * `Json(payload)`: New tags of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated server.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/tags",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = ServerTagsPayload,
+    responses(
+        (status = 200, body = Response<ApiServer>, description = "Tags set"),
+        (status = 400, body = ApiError, description = "Invalid tag"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn set_tags(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<ServerTagsPayload>,
+) -> Result<Json<Response<ApiServer>>> {
+    let server = tag::set_tags(&app_state, claims.user_id, server_id, payload).await?;
+    tracing::info!(target: "handler", %server_id, "Tags set");
+
+    Ok(Json(Response::new(server)))
+}
+
+/// Removes a tag from a server of the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path((server_id, tag))`: ID of the server and the removed tag.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/tags/{tag}",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Unique server ID"),
+        ("tag" = String, Path, description = "Removed tag")
+    ),
+    responses(
+        (status = 204, description = "Tag removed"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server or tag not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn remove_tag(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((server_id, removed)): Path<(Uuid, String)>,
+) -> Result<StatusCode> {
+    tag::remove_tag(&app_state, claims.user_id, server_id, &removed).await?;
+    tracing::info!(target: "handler", %server_id, tag = %removed, "Tag removed");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Sets the notes of a server of the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(server_id)`: ID of the server.
+/// * `Json(payload)`: New notes, blank to clear them.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated server.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/notes",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = ServerNotesPayload,
+    responses(
+        (status = 200, body = Response<ApiServer>, description = "Notes set"),
+        (status = 400, body = ApiError, description = "Notes too long"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, payload),
+	fields(id = %claims.user_id))]
+async fn set_notes(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<ServerNotesPayload>,
+) -> Result<Json<Response<ApiServer>>> {
+    let server = tag::set_notes(&app_state, claims.user_id, server_id, payload).await?;
+    tracing::info!(target: "handler", %server_id, "Notes set");
+
+    Ok(Json(Response::new(server)))
+}
+
+/// Sets the external ID of a server of the currently authenticated user, so
+/// infrastructure-as-code tools can adopt a server they didn't order.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(server_id)`: ID of the server.
+/// * `Json(payload)`: New external ID, blank to clear it.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated server.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/external-id",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = ServerExternalIdPayload,
+    responses(
+        (status = 200, body = Response<ApiServer>, description = "External ID set"),
+        (status = 400, body = ApiError, description = "Invalid external ID"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "External ID used by another server"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn set_external_id(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<ServerExternalIdPayload>,
+) -> Result<Json<Response<ApiServer>>> {
+    let server = tag::set_external_id(&app_state, claims.user_id, server_id, payload).await?;
+    tracing::info!(target: "handler", %server_id, "External ID set");
+
+    Ok(Json(Response::new(server)))
+}
+
+/// Returns the server of the currently authenticated user with the external
+/// ID, like the lookup of an infrastructure-as-code tool refreshing its state.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(external_id)`: External ID of the server, URL-encoded.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the server.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/external/{external_id}",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("external_id" = String, Path, description = "External ID of the server")),
+    responses(
+        (status = 200, body = Response<ApiServer>, description = "Server found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "No server with the external ID"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_server_by_external_id(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(external_id): Path<String>,
+) -> Result<Json<Response<ApiServer>>> {
+    let mut connection = app_state.reader().acquire().await?;
+    let server = tag::find_by_external_id(&mut connection, claims.user_id, external_id.trim())
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Server with external ID {external_id}")))?;
+    tracing::info!(target: "handler", server_id = %server.server_id, "Found server");
+
+    Ok(Json(Response::new(server)))
+}
+
+/// Returns the activity timeline of a server of the currently authenticated
+/// user: its provisioning steps, power actions, status changes, restores,
+/// backups and transfers.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the latest events in
+/// chronological order.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/timeline",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiTimelineEvent>>, description = "Timeline found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_timeline(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiTimelineEvent>>>> {
+    let events = timeline::get_timeline(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, count = events.len(), "Found timeline events");
+
+    Ok(Json(Response::new(events)))
+}
+
+/// Resets the password of the administrator of a server of the currently
+/// authenticated user. The password is set through the guest agent of a
+/// running server, or through cloud-init otherwise, and is only returned in
+/// this response.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the new password and how it was
+/// applied, `HTTP 409 Conflict` if an operation is in progress on the server.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/password",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<ApiPasswordReset>, description = "Password reset"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn reset_password(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<ApiPasswordReset>>> {
+    let reset = password::reset_password(&app_state, claims.user_id, server_id).await?;
+
+    Ok(Json(Response::new(reset)))
+}
+
+/// Returns the virtual disks of a server of the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the disks of the server.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/disks",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiDisk>>, description = "Disks found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_disks(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiDisk>>>> {
+    let disks = disk::list_disks(&app_state, claims.user_id, server_id).await?;
+    tracing::info!(target: "handler", %server_id, count = disks.len(), "Found disks");
+
+    Ok(Json(Response::new(disks)))
+}
+
+/// Attaches a new virtual disk to a server of the currently authenticated
+/// user, within the disk limits of its product.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+/// * `Json(payload)`: Size of the new disk.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the disks of the server,
+/// `HTTP 409 Conflict` if the server is neither running nor stopped.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/disks",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = DiskPayload,
+    responses(
+        (status = 200, body = Response<Vec<ApiDisk>>, description = "Disk attached"),
+        (status = 400, body = ApiError, description = "Invalid size"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Disk limit of the product exceeded"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn add_disk(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<DiskPayload>,
+) -> Result<Json<Response<Vec<ApiDisk>>>> {
+    let disks = disk::add_disk(&app_state, claims.user_id, server_id, payload.size_gb).await?;
+
+    Ok(Json(Response::new(disks)))
+}
+
+/// Grows a virtual disk of a server of the currently authenticated user,
+/// within the disk limits of its product. Only SCSI and VirtIO disks grow
+/// while the server is running.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path((server_id, device))`: Unique ID of the server and device of the
+///   disk.
+/// * `Json(payload)`: New size of the disk.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the disks of the server,
+/// `HTTP 409 Conflict` if the server must be stopped first.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/disks/{device}/resize",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(
+        ("id" = Uuid, Path, description = "Unique server ID"),
+        ("device", Path, description = "Device of the disk, like scsi0")
+    ),
+    request_body = DiskPayload,
+    responses(
+        (status = 200, body = Response<Vec<ApiDisk>>, description = "Disk resized"),
+        (status = 400, body = ApiError, description = "Disk would not grow"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Disk limit of the product exceeded"),
+        (status = 404, body = ApiError, description = "Server or disk not found"),
+        (status = 409, body = ApiError, description = "Server is busy or must be stopped"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn resize_disk(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path((server_id, device)): Path<(Uuid, String)>,
+    Json(payload): Json<DiskPayload>,
+) -> Result<Json<Response<Vec<ApiDisk>>>> {
+    let disks = disk::resize_disk(
+        &app_state,
+        claims.user_id,
+        server_id,
+        &device,
+        payload.size_gb,
+    )
+    .await?;
+
+    Ok(Json(Response::new(disks)))
+}
+
+/// Returns the CD-ROM drive of a server of the currently authenticated user:
+/// the inserted ISO and whether the server boots from it.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the CD-ROM drive of the server.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/iso",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<ApiServerIso>, description = "CD-ROM drive found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_iso(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<ApiServerIso>>> {
+    let drive = queries::get_server_iso(app_state.reader(), claims.user_id, server_id).await?;
+
+    Ok(Json(Response::new(drive)))
+}
+
+/// Inserts a registered ISO into the CD-ROM drive of a server of the currently
+/// authenticated user, replacing the inserted one.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+/// * `Json(payload)`: ID of the ISO.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the CD-ROM drive of the server.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/iso",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = AttachIsoPayload,
+    responses(
+        (status = 200, body = Response<ApiServerIso>, description = "ISO inserted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server or ISO not found"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn attach_iso(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<AttachIsoPayload>,
+) -> Result<Json<Response<ApiServerIso>>> {
+    let drive = iso::attach_iso(&app_state, claims.user_id, server_id, payload.iso_id).await?;
+
+    Ok(Json(Response::new(drive)))
+}
+
+/// Ejects the ISO from the CD-ROM drive of a server of the currently
+/// authenticated user, which boots from its disks again.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/servers/{id}/iso",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    responses(
+        (status = 204, description = "ISO ejected"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn detach_iso(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    iso::detach_iso(&app_state, claims.user_id, server_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Switches a server of the currently authenticated user to boot from its ISO
+/// before its disks, or from its disks only. The change applies on the next
+/// boot.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+/// * `Json(payload)`: Whether the server boots from its ISO.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the CD-ROM drive of the server.
+///
+#[utoipa::path(
+    put,
+    path = "/servers/{id}/iso/boot",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = BootOrderPayload,
+    responses(
+        (status = 200, body = Response<ApiServerIso>, description = "Boot order set"),
+        (status = 400, body = ApiError, description = "No ISO inserted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "Server is busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn set_boot_order(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<BootOrderPayload>,
+) -> Result<Json<Response<ApiServerIso>>> {
+    let drive =
+        iso::set_boot_from_iso(&app_state, claims.user_id, server_id, payload.boot_from_iso)
+            .await?;
+
+    Ok(Json(Response::new(drive)))
+}
+
+/// Returns the monthly network traffic of a server of the currently
+/// authenticated user, along with its monthly quota.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: Shared application state.
+/// * `Extension(claims)`: Claims extracted from the JWT.
+/// * `Path(server_id)`: Unique ID of the server.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the traffic of the server.
+///
+#[utoipa::path(
+    get,
+    path = "/servers/{id}/traffic",
+    tags = ["Server"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    responses(
+        (status = 200, body = Response<ApiServerTraffic>, description = "Traffic found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn get_traffic(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+) -> Result<Json<Response<ApiServerTraffic>>> {
+    let traffic = traffic::get_traffic(&app_state, claims.user_id, server_id).await?;
+
+    Ok(Json(Response::new(traffic)))
+}
@@ -0,0 +1,203 @@
+//! Server transfer routes
+
+use crate::model::queries;
+use crate::model::types::{ApiCompletedTransfer, ApiServerTransfer};
+use crate::services::transfer;
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use crate::web::types::{Response, TransferPayload};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
+
+/// Defines routes for the server transfers between accounts. All routes are
+/// protected and require authentication.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/servers/{id}/transfer", post(request_transfer))
+        .route("/transfers", get(list_transfers))
+        .route("/transfers/{id}/accept", post(accept_transfer))
+        .route("/transfers/{id}/decline", post(decline_transfer))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Offers a server of the currently authenticated user to another account.
+/// The recipient is notified by email and the server is moved once they
+/// accept. An email address without an account is answered the same way, so
+/// the request doesn't reveal which addresses are registered.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(server_id)`: ID of the server.
+/// * `Json(payload)`: Email address of the recipient.
+///
+/// # Returns
+///
+/// On success, returns `202 Accepted`. The pending transfer is listed by
+/// `GET /transfers`.
+///
+#[utoipa::path(
+    post,
+    path = "/servers/{id}/transfer",
+    tags = ["Transfer"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Unique server ID")),
+    request_body = TransferPayload,
+    responses(
+        (status = 202, description = "Transfer offered, if the email address belongs to an account"),
+        (status = 400, body = ApiError, description = "Server already belongs to the recipient"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Server not found"),
+        (status = 409, body = ApiError, description = "Server busy or already being transferred"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, payload),
+	fields(id = %claims.user_id))]
+async fn request_transfer(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(server_id): Path<Uuid>,
+    Json(payload): Json<TransferPayload>,
+) -> Result<StatusCode> {
+    transfer::request_transfer(&app_state, claims.user_id, server_id, payload).await?;
+    tracing::info!(target: "handler", %server_id, "Transfer requested");
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Returns the pending transfers of the currently authenticated user, both of
+/// the own servers and of the servers offered to the user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the pending transfers.
+///
+#[utoipa::path(
+    get,
+    path = "/transfers",
+    tags = ["Transfer"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiServerTransfer>>, description = "Transfers found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_transfers(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiServerTransfer>>>> {
+    let transfers = queries::get_pending_transfers(&app_state.pool, claims.user_id).await?;
+    tracing::info!(target: "handler", count = transfers.len(), "Found transfers");
+
+    Ok(Json(Response::new(transfers)))
+}
+
+/// Accepts a transfer offered to the currently authenticated user, moving the
+/// server to the account.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(transfer_id)`: ID of the transfer.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the completed transfer, and the
+/// new password of the server if its credentials were regenerated.
+///
+#[utoipa::path(
+    post,
+    path = "/transfers/{id}/accept",
+    tags = ["Transfer"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Transfer ID")),
+    responses(
+        (status = 200, body = Response<ApiCompletedTransfer>, description = "Server transferred"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Quota exceeded"),
+        (status = 404, body = ApiError, description = "Transfer not found"),
+        (status = 409, body = ApiError, description = "Server busy"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn accept_transfer(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(transfer_id): Path<Uuid>,
+) -> Result<Json<Response<ApiCompletedTransfer>>> {
+    let completed = transfer::accept_transfer(&app_state, claims.user_id, transfer_id).await?;
+    tracing::info!(target: "handler", %transfer_id, "Transfer accepted");
+
+    Ok(Json(Response::new(completed)))
+}
+
+/// Declines a transfer offered to the currently authenticated user, or
+/// cancels a transfer of the user's server.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(transfer_id)`: ID of the transfer.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the closed transfer.
+///
+#[utoipa::path(
+    post,
+    path = "/transfers/{id}/decline",
+    tags = ["Transfer"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Transfer ID")),
+    responses(
+        (status = 200, body = Response<ApiServerTransfer>, description = "Transfer closed"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "Transfer not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn decline_transfer(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(transfer_id): Path<Uuid>,
+) -> Result<Json<Response<ApiServerTransfer>>> {
+    let transfer = transfer::close_transfer(&app_state, claims.user_id, transfer_id).await?;
+    tracing::info!(target: "handler", %transfer_id, status = %transfer.status, "Transfer closed");
+
+    Ok(Json(Response::new(transfer)))
+}
@@ -0,0 +1,532 @@
+//! User profile routes
+
+use crate::model::types::{ApiAllowedNetwork, ApiToken, ApiUserExport};
+use crate::services::{allowlist, api_token, export, user};
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware::{self as mw, ClientIp};
+use crate::web::types::{
+    AllowedNetworkPayload, ApiTokenPayload, ConfirmEmailPayload, EmailChangePayload, Response,
+    UpdateUserPayload, UserResponse,
+};
+use axum::extract::{Path, State};
+use axum::http::header::CONTENT_DISPOSITION;
+use axum::http::{HeaderName, StatusCode};
+use axum::routing::{delete, get, patch, post};
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
+
+/// Defines routes for the user profile section. All routes are protected and
+/// require authentication.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/me", patch(update_user).delete(delete_account))
+        .route("/me/export", get(export_user))
+        .route("/me/email", post(request_email_change))
+        .route("/me/email/confirm", post(confirm_email_change))
+        .route(
+            "/me/allowed-networks",
+            get(list_allowed_networks).post(add_allowed_network),
+        )
+        .route("/me/allowed-networks/{id}", delete(delete_allowed_network))
+        .route(
+            "/me/api-tokens",
+            get(list_api_tokens).post(create_api_token),
+        )
+        .route("/me/api-tokens/{id}", delete(delete_api_token))
+        .route("/auth/verify/resend", post(resend_verification))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Updates the profile of the currently authenticated user. Fields missing in
+/// the payload are left unchanged, the email address has its own flow.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: New values of the profile fields.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated profile.
+///
+#[utoipa::path(
+    patch,
+    path = "/me",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    request_body = UpdateUserPayload,
+    responses(
+        (status = 200, body = UserResponse, description = "Profile updated"),
+        (status = 400, body = ApiError, description = "Invalid field value"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn update_user(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<UpdateUserPayload>,
+) -> Result<Json<UserResponse>> {
+    let user = user::update_profile(&app_state, claims.user_id, payload).await?;
+
+    Ok(Json(UserResponse::new(user)))
+}
+
+/// Requests a change of the email address of the currently authenticated
+/// user. A confirmation token is sent to the new address.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: Requested email address.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 202 Accepted`.
+///
+#[utoipa::path(
+    post,
+    path = "/me/email",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    request_body = EmailChangePayload,
+    responses(
+        (status = 202, description = "Confirmation token sent"),
+        (status = 400, body = ApiError, description = "Invalid email address"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Email address already in use"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn request_email_change(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<EmailChangePayload>,
+) -> Result<StatusCode> {
+    user::request_email_change(&app_state, claims.user_id, payload).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Confirms the pending change of the email address of the currently
+/// authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: Token sent to the new address.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the updated profile.
+///
+#[utoipa::path(
+    post,
+    path = "/me/email/confirm",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    request_body = ConfirmEmailPayload,
+    responses(
+        (status = 200, body = UserResponse, description = "Email address changed"),
+        (status = 400, body = ApiError, description = "Invalid or expired token"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 409, body = ApiError, description = "Email address already in use"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims, payload),
+	fields(id = %claims.user_id))]
+async fn confirm_email_change(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ConfirmEmailPayload>,
+) -> Result<Json<UserResponse>> {
+    let user = user::confirm_email_change(&app_state, claims.user_id, payload).await?;
+
+    Ok(Json(UserResponse::new(user)))
+}
+
+/// Sends a new token verifying the email address of the currently
+/// authenticated user, replacing the previous one.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 202 Accepted`.
+///
+#[utoipa::path(
+    post,
+    path = "/auth/verify/resend",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Verification token sent"),
+        (status = 400, body = ApiError, description = "Email address already verified"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn resend_verification(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode> {
+    user::resend_verification(&app_state, claims.user_id).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Exports all personal data of the currently authenticated user as a JSON
+/// file.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns the bundle with the personal data as an attachment.
+///
+#[utoipa::path(
+    get,
+    path = "/me/export",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = ApiUserExport, description = "Personal data exported"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn export_user(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<([(HeaderName, String); 1], Json<ApiUserExport>)> {
+    let export = export::export_user(&app_state, claims.user_id).await?;
+    let disposition = format!(
+        "attachment; filename=\"export-{}.json\"",
+        export.exported_at.format("%Y%m%d%H%M%S")
+    );
+
+    Ok(([(CONTENT_DISPOSITION, disposition)], Json(export)))
+}
+
+/// Deletes the account of the currently authenticated user. The personal data
+/// is anonymized right away and the servers are deprovisioned in the
+/// background.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 202 Accepted`.
+///
+#[utoipa::path(
+    delete,
+    path = "/me",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Account deleted, servers deprovisioning"),
+        (status = 400, body = ApiError, description = "Account already deleted"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn delete_account(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode> {
+    let servers = user::delete_account(&app_state, claims.user_id).await?;
+    tracing::info!(target: "handler", servers, "Account deleted");
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Returns the networks the currently authenticated user allowed to access
+/// the account from.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the allowed networks, an empty
+/// list if the account is unrestricted.
+///
+#[utoipa::path(
+    get,
+    path = "/me/allowed-networks",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiAllowedNetwork>>, description = "Allowed networks found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Address not allowed"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_allowed_networks(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiAllowedNetwork>>>> {
+    let networks = allowlist::list_networks(&app_state, claims.user_id).await?;
+    tracing::info!(target: "handler", count = networks.len(), "Found allowed networks");
+
+    Ok(Json(Response::new(networks)))
+}
+
+/// Allows the currently authenticated user to access the account from a
+/// network. The first network restricts the account, so it must contain the
+/// address of the request.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `client_ip`: Address of the client.
+/// * `Json(payload)`: CIDR block of the network and its description.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the allowed network.
+///
+#[utoipa::path(
+    post,
+    path = "/me/allowed-networks",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    request_body = AllowedNetworkPayload,
+    responses(
+        (status = 200, body = Response<ApiAllowedNetwork>, description = "Network allowed"),
+        (status = 400, body = ApiError, description = "Invalid CIDR block or address locked out"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Address not allowed"),
+        (status = 409, body = ApiError, description = "Network already allowed"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn add_allowed_network(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    client_ip: Option<Extension<ClientIp>>,
+    Json(payload): Json<AllowedNetworkPayload>,
+) -> Result<Json<Response<ApiAllowedNetwork>>> {
+    let client_ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    let network = allowlist::add_network(&app_state, claims.user_id, client_ip, payload).await?;
+    tracing::info!(target: "handler", network_id = %network.id, "Network allowed");
+
+    Ok(Json(Response::new(network)))
+}
+
+/// Removes a network the currently authenticated user allowed. The remaining
+/// networks must contain the address of the request, unless none remain.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `client_ip`: Address of the client.
+/// * `Path(network_id)`: ID of the allowed network.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/me/allowed-networks/{id}",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Allowed network ID")),
+    responses(
+        (status = 204, description = "Network removed"),
+        (status = 400, body = ApiError, description = "Network not found or address locked out"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 403, body = ApiError, description = "Address not allowed"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn delete_allowed_network(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    client_ip: Option<Extension<ClientIp>>,
+    Path(network_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let client_ip = client_ip.map(|Extension(ClientIp(ip))| ip);
+    allowlist::remove_network(&app_state, claims.user_id, client_ip, network_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the API tokens of the currently authenticated user, without the
+/// tokens themselves.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the API tokens.
+///
+#[utoipa::path(
+    get,
+    path = "/me/api-tokens",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiToken>>, description = "API tokens found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_api_tokens(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiToken>>>> {
+    let tokens = api_token::list_tokens(&app_state, claims.user_id).await?;
+    tracing::info!(target: "handler", count = tokens.len(), "Found API tokens");
+
+    Ok(Json(Response::new(tokens)))
+}
+
+/// Creates an API token for the currently authenticated user, to authorize
+/// scripts and the command-line client with. The token is only returned in
+/// this response.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: Name of the token.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created token.
+///
+#[utoipa::path(
+    post,
+    path = "/me/api-tokens",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    request_body = ApiTokenPayload,
+    responses(
+        (status = 200, body = Response<ApiToken>, description = "API token created"),
+        (status = 400, body = ApiError, description = "Invalid name"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn create_api_token(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ApiTokenPayload>,
+) -> Result<Json<Response<ApiToken>>> {
+    let token = api_token::create_token(&app_state, claims.user_id, payload).await?;
+    tracing::info!(target: "handler", token_id = %token.id, "API token created");
+
+    Ok(Json(Response::new(token)))
+}
+
+/// Revokes an API token of the currently authenticated user.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(token_id)`: ID of the API token.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/me/api-tokens/{id}",
+    tags = ["User"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "API token ID")),
+    responses(
+        (status = 204, description = "API token revoked"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 404, body = ApiError, description = "API token not found"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn delete_api_token(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(token_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    api_token::revoke_token(&app_state, claims.user_id, token_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,190 @@
+//! Webhook routes
+
+use crate::model::queries;
+use crate::model::types::{ApiWebhook, ApiWebhookDelivery};
+use crate::services::webhook;
+use crate::state::AppState;
+use crate::web::auth::Claims;
+use crate::web::middleware as mw;
+use crate::web::types::{Response, WebhookPayload};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get};
+use axum::{Extension, Json};
+use axum::{Router, middleware};
+use dashboard_common::prelude::{ApiError, Result};
+use uuid::Uuid;
+
+/// Defines routes for the webhook section. All routes are protected and
+/// require authentication.
+///
+/// # Arguments
+///
+/// * `State(app_state)` - The shared application state.
+///
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/webhooks", get(list_webhooks).post(create_webhook))
+        .route("/webhooks/{id}", delete(delete_webhook))
+        .route("/webhooks/{id}/deliveries", get(list_deliveries))
+        .route_layer(middleware::from_fn_with_state(app_state, mw::require_auth))
+}
+
+/// Returns the webhooks of the currently authenticated user, without their
+/// secrets.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the list of webhooks.
+///
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tags = ["Webhook"],
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = Response<Vec<ApiWebhook>>, description = "Webhooks found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_webhooks(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Response<Vec<ApiWebhook>>>> {
+    let webhooks = queries::get_webhooks(&app_state.pool, claims.user_id).await?;
+    tracing::info!(target: "handler", count = webhooks.len(), "Found webhooks");
+
+    Ok(Json(Response::new(webhooks)))
+}
+
+/// Registers a webhook for the currently authenticated user. The signing
+/// secret is only returned in this response.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Json(payload)`: Address of the receiver and the subscribed events.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the created webhook.
+///
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tags = ["Webhook"],
+    security(("bearer_auth" = [])),
+    request_body = WebhookPayload,
+    responses(
+        (status = 200, body = Response<ApiWebhook>, description = "Webhook created"),
+        (status = 400, body = ApiError, description = "Invalid URL or no events"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn create_webhook(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<WebhookPayload>,
+) -> Result<Json<Response<ApiWebhook>>> {
+    let webhook = webhook::create_webhook(&app_state, claims.user_id, payload).await?;
+    tracing::info!(target: "handler", webhook_id = %webhook.id, "Webhook created");
+
+    Ok(Json(Response::new(webhook)))
+}
+
+/// Deletes a webhook of the currently authenticated user, together with its
+/// pending deliveries.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(webhook_id)`: ID of the webhook.
+///
+/// # Returns
+///
+/// On success, returns an `HTTP 204 No Content`.
+///
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    tags = ["Webhook"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Webhook ID")),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 404, body = ApiError, description = "Webhook not found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn delete_webhook(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    webhook::delete_webhook(&app_state, claims.user_id, webhook_id).await?;
+    tracing::info!(target: "handler", %webhook_id, "Webhook deleted");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the latest deliveries of a webhook with all their attempts.
+///
+/// # Arguments
+///
+/// * `State(app_state)`: The shared application state.
+/// * `Extension(claims)`: The claims extracted from the JWT, which include the
+///   user's ID.
+/// * `Path(webhook_id)`: ID of the webhook.
+///
+/// # Returns
+///
+/// On success, returns a Json response with the deliveries, newest first.
+///
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    tags = ["Webhook"],
+    security(("bearer_auth" = [])),
+    params(("id" = Uuid, Path, description = "Webhook ID")),
+    responses(
+        (status = 200, body = Response<Vec<ApiWebhookDelivery>>, description = "Deliveries found"),
+        (status = 404, body = ApiError, description = "Webhook not found"),
+        (status = 401, body = ApiError, description = "Unauthorized"),
+        (status = 500, body = ApiError, description = "Internal server error")
+    )
+)]
+#[tracing::instrument(level = "trace", target = "handler",
+	skip(app_state, claims),
+	fields(id = %claims.user_id))]
+async fn list_deliveries(
+    State(app_state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<Json<Response<Vec<ApiWebhookDelivery>>>> {
+    let deliveries = webhook::get_deliveries(&app_state, claims.user_id, webhook_id).await?;
+    tracing::info!(target: "handler", %webhook_id, count = deliveries.len(), "Found webhook deliveries");
+
+    Ok(Json(Response::new(deliveries)))
+}
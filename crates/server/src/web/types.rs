@@ -1,46 +1,19 @@
-﻿use crate::model::types::ApiUser;
+﻿use crate::model::types::{
+    AnnouncementKind, ApiUser, BackupMode, FirewallAction, FirewallDirection, FirewallProtocol,
+    NotificationEvent, OrganizationRole, TemplateKind, WebhookEvent,
+};
+use chrono::{DateTime, Utc};
+pub use dashboard_common::api::{Response, ServerAction, TokenPayload, TokenResponse};
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-/// API response with JWT inside.
-///
-pub type TokenResponse = Response<TokenPayload>;
-
 /// API response with user info inside.
 ///
 pub type UserResponse = Response<ApiUser>;
 
-/// Generic API response.
-///
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
-pub struct Response<T> {
-    pub result: T,
-}
-
-impl<T> Response<T> {
-    /// Creates a new instance of the API response.
-    ///
-    pub fn new(result: T) -> Self {
-        Self { result }
-    }
-}
-
-/// Payload for successful registration or login, containing `JWT`.
-///
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
-pub struct TokenPayload {
-    pub token: String,
-}
-
-impl From<String> for TokenPayload {
-    fn from(token: String) -> Self {
-        Self { token }
-    }
-}
-
 /// Payload for creating a new server.
 ///
 #[derive(Debug, Clone, Deserialize, ToSchema)]
@@ -52,6 +25,49 @@ pub struct NewServerPayload {
     pub os: String,
     pub datacenter: String,
     pub ip_config: Option<String>,
+    /// Application of the marketplace, whose template is cloned instead of the
+    /// one of the OS.
+    #[serde(default)]
+    pub app: Option<String>,
+    /// ID of the server in the tooling of the user, unique among their
+    /// servers. An order repeated with the same ID returns the ordered server.
+    #[serde(default)]
+    pub external_id: Option<String>,
+    /// Tags the server starts with.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Payload for updating the profile of a user. Missing fields are left
+/// unchanged.
+///
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct UpdateUserPayload {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub post_code: Option<String>,
+    pub country: Option<String>,
+    pub phone_number: Option<String>,
+    /// Preferred language, such as `de`.
+    pub locale: Option<String>,
+}
+
+/// Payload for requesting a change of the email address.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EmailChangePayload {
+    pub email: String,
+}
+
+/// Payload with the token confirming an email address, either a changed one or
+/// the one of a new account.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConfirmEmailPayload {
+    pub token: String,
 }
 
 /// Payload for performing an action on a server.
@@ -61,22 +77,494 @@ pub struct ServerActionPayload {
     pub action: ServerAction,
 }
 
-/// Represents the possible actions that can be performed on a server.
+/// Payload for performing the same action on several servers.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkActionPayload {
+    pub server_ids: Vec<Uuid>,
+    pub action: ServerAction,
+}
+
+/// Query parameters selecting a billing period. Missing bounds default to the
+/// start of the current month and the current moment.
+///
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PeriodQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Payload for creating or renaming a product group or a configurable option.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NamePayload {
+    pub name: String,
+}
+
+/// Payload for creating or updating a product.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProductPayload {
+    pub group_id: Uuid,
+    pub name: String,
+    /// Network rate limit in Mbit/s, `None` for unlimited.
+    pub net_rate_mbps: Option<i32>,
+    /// Storage the disks of new servers are cloned to, taking precedence over
+    /// the storage of the template.
+    #[serde(default)]
+    pub storage: Option<String>,
+    /// Number of disks the servers may attach besides the boot disk.
+    #[serde(default)]
+    pub max_disks: i32,
+    /// Size a single disk may grow to in GB, `None` for unlimited.
+    #[serde(default)]
+    pub max_disk_gb: Option<i32>,
+    /// Monthly network traffic of a server in GB, `None` for unlimited.
+    #[serde(default)]
+    pub traffic_quota_gb: Option<i32>,
+}
+
+/// Payload for creating or updating a custom field of a product.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CustomFieldPayload {
+    pub name: String,
+    /// Values offered with the product. The values of the OS template field
+    /// name existing templates, those of the datacenter location field
+    /// existing datacenters.
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// Payload for registering an ISO image.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IsoPayload {
+    pub name: String,
+    /// Proxmox volume of the ISO, like `local:iso/debian-12.iso`. An ISO on a
+    /// storage local to the node only goes into the servers on that node.
+    pub volid: String,
+    /// Node whose storage is checked for the ISO.
+    pub node: String,
+}
+
+/// Payload for registering or updating an OS or application template.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TemplatePayload {
+    pub os_name: String,
+    /// ID of the template VM, which must exist and be a template.
+    pub template_vmid: i32,
+    /// Node the template VM is on.
+    pub template_node: String,
+    /// Datacenter whose cluster holds the template, `None` if not limited to
+    /// one.
+    #[serde(default)]
+    pub datacenter_code: Option<String>,
+    /// Storage the disks of new servers are cloned to, which must accept VM
+    /// disks on the template node. `None` for the storage of the template.
+    #[serde(default)]
+    pub storage: Option<String>,
+    /// Whether the template is a plain OS or an application of the
+    /// marketplace.
+    #[serde(default)]
+    pub kind: TemplateKind,
+    /// Description shown in the marketplace.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Cloud-init snippet volume passed to new servers as vendor data, like
+    /// `local:snippets/docker.yaml`.
+    #[serde(default)]
+    pub cloud_init_snippet: Option<String>,
+    /// Shell script run through the guest agent once a new server boots.
+    #[serde(default)]
+    pub post_install_script: Option<String>,
+}
+
+/// Payload for creating or updating a datacenter.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DatacenterPayload {
+    pub code: String,
+    pub display_name: String,
+    /// Endpoint of the datacenter's Proxmox cluster.
+    #[serde(default)]
+    pub cluster_url: Option<String>,
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+    #[serde(default)]
+    pub is_full: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Payload for setting the datacenters a product is offered in.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProductDatacentersPayload {
+    pub datacenter_codes: Vec<String>,
+}
+
+/// Query parameters of the admin search.
+///
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Searched text, at least two characters.
+    pub q: String,
+}
+
+/// Payload for redeeming a promo code.
 ///
 #[derive(Debug, Deserialize, ToSchema)]
-#[serde(rename_all = "lowercase")]
-pub enum ServerAction {
-    Start,
-    Stop,
-    // Shutting VM down, and starting it again.
-    Reboot,
-    // This is similar to pressing the power button on a physical machine.
-    Shutdown,
+pub struct RedeemPromoPayload {
+    pub code: String,
+}
+
+/// Payload for issuing credit to a user.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IssueCreditPayload {
+    pub user_id: Uuid,
+    pub amount_cents: i64,
+    pub reason: String,
+}
+
+/// Query parameters filtering the server list.
+///
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ServerListQuery {
+    /// Comma-separated tags, all of which a listed server has.
+    pub tags: Option<String>,
+}
+
+/// Payload for replacing the tags of a server.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ServerTagsPayload {
+    pub tags: Vec<String>,
+}
+
+/// Payload for setting the external ID of a server, adopting it into the
+/// tooling of the user.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ServerExternalIdPayload {
+    /// External ID, `None` or blank to clear it.
+    #[serde(default)]
+    pub external_id: Option<String>,
+}
+
+/// Payload for setting the notes of a server.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ServerNotesPayload {
+    /// Free-text notes, `None` or blank to clear them.
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// Payload for attaching a disk to a server or growing one of its disks.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DiskPayload {
+    /// Size of the disk in GB.
+    pub size_gb: i32,
+}
+
+/// Payload for inserting an ISO into the CD-ROM drive of a server.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AttachIsoPayload {
+    /// ID of the registered ISO.
+    pub iso_id: Uuid,
+}
+
+/// Payload for switching a server to boot from its ISO or from its disks.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BootOrderPayload {
+    /// Whether the server boots from the inserted ISO before its disks.
+    pub boot_from_iso: bool,
+}
+
+/// Payload for transferring a server to another account.
+///
+/// # Fields
+///
+/// * `email`: Email address of the recipient's account.
+/// * `regenerate_credentials`: Whether to give the server a new password and
+///   remove its SSH keys once the recipient accepts.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransferPayload {
+    pub email: String,
+    #[serde(default)]
+    pub regenerate_credentials: bool,
+}
+
+/// Payload for transferring a server to another account by an administrator.
+///
+/// # Fields
+///
+/// * `user_id`: ID of the recipient.
+/// * `regenerate_credentials`: Whether to give the server a new password and
+///   remove its SSH keys.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminTransferPayload {
+    pub user_id: Uuid,
+    #[serde(default)]
+    pub regenerate_credentials: bool,
+}
+
+/// Payload for replaying a range of the domain events.
+///
+/// # Fields
+///
+/// * `from_id`: First event of the range.
+/// * `to_id`: Last event of the range.
+/// * `consumers`: Names of the consumers handling the events again, like
+///   `webhooks`, `notifications` or `status`, every consumer if empty.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReplayEventsPayload {
+    pub from_id: i64,
+    pub to_id: i64,
+    #[serde(default)]
+    pub consumers: Vec<String>,
+}
+
+/// Payload for adding a member to an organization, or changing the role of a
+/// member.
+///
+/// # Fields
+///
+/// * `email`: Email address of the member's account.
+/// * `role`: Role of the member in the organization.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MemberPayload {
+    pub email: String,
+    pub role: OrganizationRole,
+}
+
+/// Payload for creating or updating an announcement.
+///
+/// # Fields
+///
+/// * `datacenter_code`: Datacenter whose users see the announcement, `None`
+///   for all users.
+/// * `publish_at`: Moment the announcement is shown from, `None` for now.
+/// * `expires_at`: Moment the announcement is hidden at, `None` to show it
+///   until it is deleted.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AnnouncementPayload {
+    pub kind: AnnouncementKind,
+    pub title: String,
+    pub message: String,
+    #[serde(default)]
+    pub datacenter_code: Option<String>,
+    #[serde(default)]
+    pub publish_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters filtering the notification center.
+///
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotificationListQuery {
+    /// Lists only the unread notifications.
+    #[serde(default)]
+    pub unread: bool,
+}
+
+/// Query parameters filtering the audit log.
+///
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditLogQuery {
+    /// Lists only the entries of the server.
+    #[serde(default)]
+    pub server_id: Option<Uuid>,
+}
+
+/// Payload for choosing whether an event is emailed to the user, besides the
+/// notification center.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NotificationPreferencePayload {
+    pub event: NotificationEvent,
+    pub email: bool,
+}
+
+/// Payload for moving a server of the user to an organization.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OrganizationServerPayload {
+    pub server_id: Uuid,
+}
+
+/// Payload for creating a network from a CIDR block.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewNetworkPayload {
+    pub datacenter_name: String,
+    pub cidr: String,
+    pub gateway: Option<String>,
+}
+
+/// Payload for reserving a range of a network's addresses, or returning it to
+/// the pool.
+///
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IpRangePayload {
+    pub start: String,
+    pub end: String,
+    pub reserved: bool,
+}
+
+/// Payload for adding a firewall rule to a server. Without a source, the rule
+/// matches traffic from any address.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FirewallRulePayload {
+    pub direction: FirewallDirection,
+    pub action: FirewallAction,
+    pub protocol: FirewallProtocol,
+    pub port: i32,
+    pub source: Option<String>,
+}
+
+/// Payload for scheduling backups of a server.
+///
+/// # Fields
+///
+/// * `cron`: Five-field cron expression in UTC, e.g. `30 2 * * *`.
+/// * `mode`: Mode of the backups, `snapshot` by default.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BackupSchedulePayload {
+    pub cron: String,
+    pub mode: Option<BackupMode>,
+}
+
+/// Payload for registering a webhook.
+///
+/// # Fields
+///
+/// * `url`: HTTP or HTTPS address that receives the events.
+/// * `events`: Events the webhook is subscribed to.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WebhookPayload {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+/// Payload for allowing a network to access the account.
+///
+/// # Fields
+///
+/// * `cidr`: IPv4 or IPv6 CIDR block of the network, like `203.0.113.0/24`.
+/// * `description`: Optional note, like the name of the office.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AllowedNetworkPayload {
+    pub cidr: String,
+    pub description: Option<String>,
+}
+
+/// Payload for creating an API token.
+///
+/// # Fields
+///
+/// * `name`: Name telling the tokens apart, like the host of a script.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApiTokenPayload {
+    pub name: String,
+}
+
+/// Query parameters starting a login with an OpenID Connect provider.
+///
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OidcLoginQuery {
+    /// Name of the provider, like `google`.
+    pub provider: String,
+}
+
+/// Query parameters the OpenID Connect provider redirected the user back
+/// with.
+///
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OidcCallbackQuery {
+    /// Authorization code to exchange for the ID token.
+    pub code: String,
+    /// State of the started login.
+    pub state: String,
+}
+
+/// Payload for setting up the SAML single sign-on of an organization.
+///
+/// # Fields
+///
+/// * `idp_metadata`: Metadata XML of the identity provider.
+/// * `email_attribute`: Attribute with the email address of the user, the
+///   NameID if missing.
+/// * `role_attribute`: Attribute with the roles or groups of the user.
+/// * `admin_values`: Values of the role attribute making the member an admin.
+/// * `enforced`: Whether the members other than the owners must log in with
+///   single sign-on.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SamlSettingsPayload {
+    pub idp_metadata: String,
+    #[serde(default)]
+    pub email_attribute: Option<String>,
+    #[serde(default)]
+    pub role_attribute: Option<String>,
+    #[serde(default)]
+    pub admin_values: Vec<String>,
+    #[serde(default)]
+    pub enforced: bool,
+}
+
+/// Form the identity provider posts the SAML response with.
+///
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SamlResponseForm {
+    /// Base64 encoded SAML response.
+    #[serde(rename = "SAMLResponse")]
+    pub saml_response: String,
+    /// ID of the authentication request the response belongs to.
+    #[serde(rename = "RelayState", default)]
+    pub relay_state: String,
+}
+
+/// Login started with an OpenID Connect or a SAML identity provider.
+///
+/// # Fields
+///
+/// * `url`: Authorization page of the provider to redirect the user to.
+///
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SsoAuthorization {
+    pub url: String,
 }
 
 /// Represents all required configurable options.
 ///
-#[derive(Debug, Display)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
 pub enum RequiredConfigOption {
     CPU,
     RAM,
@@ -84,7 +572,7 @@ pub enum RequiredConfigOption {
 
 /// Represents all required custom fields.
 ///
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum RequiredCustomField {
     #[serde(rename = "OS Template")]
     OsTemplate,
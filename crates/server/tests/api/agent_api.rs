@@ -0,0 +1,226 @@
+use axum::http::StatusCode;
+use dashboard_server::model::types::{
+    ApiAuditEntry, ApiPasswordReset, ApiServerDetail, AuditAction, PasswordResetMethod,
+    ServerStatus,
+};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{
+    MockProxmoxClient, Outcome, TestApp, TestData, UserBuilder, database, requests,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Starts the server and waits until it is running.
+///
+async fn start_server(app: &TestApp, data: &TestData, server_id: Uuid) {
+    let endpoint = format!("{}/servers/{}/actions", &app.url, server_id);
+    requests::post_response(app, &endpoint, &data.token, &json!({ "action": "start" })).await;
+    database::wait_for_status(&app.state.pool, server_id, ServerStatus::Running).await;
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn get_server_should_show_guest_info_while_running(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let stopped = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServerDetail>>()
+        .await
+        .unwrap()
+        .result;
+    start_server(&app, &data, server.server_id).await;
+
+    // Act
+    let running = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServerDetail>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert!(stopped.guest.is_none());
+    assert_eq!(running.server.server_id, server.server_id);
+    let guest = running.guest.unwrap();
+    assert_eq!(guest.os_name.as_deref(), Some("Ubuntu 24.04.1 LTS"));
+    assert_eq!(guest.kernel_release.as_deref(), Some("6.8.0-45-generic"));
+    assert_eq!(guest.ip_addresses, ["203.0.113.10"]);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn get_server_should_leave_out_guest_info_without_agent(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    start_server(&app, &data, server.server_id).await;
+    proxmox.fail_times("agent_os_info", 1);
+
+    // Act
+    let endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let response = requests::get_response(&app, &endpoint, &data.token).await;
+    let response_status = response.status();
+    let detail = response
+        .json::<Response<ApiServerDetail>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(response_status, StatusCode::OK);
+    assert!(detail.guest.is_none());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn guest_info_should_be_requested_concurrently_and_cached(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    start_server(&app, &data, server.server_id).await;
+    // Each call takes two thirds of the timeout, only both at once fit in it.
+    let delay = Duration::from_secs(2);
+    proxmox.script("agent_os_info", [Outcome::Delay(delay)]);
+    proxmox.script("agent_network_interfaces", [Outcome::Delay(delay)]);
+    let endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+
+    // Act
+    let first = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServerDetail>>()
+        .await
+        .unwrap()
+        .result;
+    let second = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServerDetail>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert!(first.guest.is_some());
+    assert_eq!(second.guest, first.guest);
+    assert_eq!(proxmox.call_count("agent_os_info"), 1);
+    assert_eq!(proxmox.call_count("agent_network_interfaces"), 1);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn get_server_should_not_wait_for_slow_guest_agent(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    start_server(&app, &data, server.server_id).await;
+    let delay = Duration::from_secs(30);
+    proxmox.script("agent_network_interfaces", [Outcome::Delay(delay)]);
+    let endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let started = Instant::now();
+
+    // Act
+    let detail = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServerDetail>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert!(started.elapsed() < delay);
+    assert!(detail.guest.is_none());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn reset_password_should_use_guest_agent_and_be_audited(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    start_server(&app, &data, server.server_id).await;
+    let admin = UserBuilder::new()
+        .email("admin@example.com")
+        .admin()
+        .register(&app, &pool)
+        .await;
+
+    // Act
+    let endpoint = format!("{}/servers/{}/password", &app.url, server.server_id);
+    let response = requests::post_response(&app, &endpoint, &data.token, &json!({})).await;
+    let response_status = response.status();
+    let reset = response
+        .json::<Response<ApiPasswordReset>>()
+        .await
+        .unwrap()
+        .result;
+    let audit_endpoint = format!(
+        "{}/admin/audit-log?server_id={}",
+        &app.url, server.server_id
+    );
+    let entries = requests::get_response(&app, &audit_endpoint, &admin.token)
+        .await
+        .json::<Response<Vec<ApiAuditEntry>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(response_status, StatusCode::OK);
+    assert_eq!(reset.method, PasswordResetMethod::GuestAgent);
+    assert_eq!(reset.username.as_deref(), Some("root"));
+    assert!(!reset.reboot_required);
+    assert_eq!(
+        *proxmox.passwords.lock().unwrap(),
+        [("root".to_owned(), reset.password)]
+    );
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].action, AuditAction::PasswordReset);
+    assert_eq!(entries[0].user_id, Some(data.user_id));
+    assert_eq!(entries[0].details["method"], "guest_agent");
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn reset_password_without_agent_should_fall_back_to_cloud_init(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/password", &app.url, server.server_id);
+    let configs_before = proxmox.call_count("vm_config");
+
+    // Act
+    let stopped = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<ApiPasswordReset>>()
+        .await
+        .unwrap()
+        .result;
+    start_server(&app, &data, server.server_id).await;
+    proxmox.fail_times("agent_ping", 1);
+    let running = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<ApiPasswordReset>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(stopped.method, PasswordResetMethod::CloudInit);
+    assert!(!stopped.reboot_required);
+    assert_eq!(running.method, PasswordResetMethod::CloudInit);
+    assert!(running.reboot_required);
+    assert_eq!(running.username, None);
+    assert_ne!(stopped.password, running.password);
+    assert_eq!(proxmox.call_count("vm_config"), configs_before + 2);
+    assert_eq!(proxmox.call_count("agent_set_user_password"), 0);
+}
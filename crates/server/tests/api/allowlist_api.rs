@@ -0,0 +1,114 @@
+use axum::http::StatusCode;
+use dashboard_server::model::types::ApiAllowedNetwork;
+use dashboard_server::web::middleware::FORWARDED_FOR_HEADER;
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, payload};
+use serde_json::json;
+use sqlx::PgPool;
+
+const OFFICE: &str = "203.0.113.5";
+const ELSEWHERE: &str = "198.51.100.1";
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn allowed_networks_should_restrict_login_and_token_use(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me/allowed-networks", &app.url);
+    let login_endpoint = format!("{}/login", &app.url);
+    let add = |from: &str| {
+        app.client
+            .post(&endpoint)
+            .bearer_auth(&data.token)
+            .header(FORWARDED_FOR_HEADER, from)
+            .json(&json!({ "cidr": "203.0.113.0/24", "description": "Office" }))
+            .send()
+    };
+    let list = |from: &str| {
+        app.client
+            .get(&endpoint)
+            .bearer_auth(&data.token)
+            .header(FORWARDED_FOR_HEADER, from)
+            .send()
+    };
+    let login = |from: &str| {
+        app.client
+            .post(&login_endpoint)
+            .header(FORWARDED_FOR_HEADER, from)
+            .json(&payload::login_user())
+            .send()
+    };
+
+    // Act
+    let locked_out = add(ELSEWHERE).await.unwrap();
+    let network = add(OFFICE)
+        .await
+        .unwrap()
+        .json::<Response<ApiAllowedNetwork>>()
+        .await
+        .unwrap()
+        .result;
+    let duplicate = add(OFFICE).await.unwrap();
+    let denied = list(ELSEWHERE).await.unwrap();
+    let denied_login = login(ELSEWHERE).await.unwrap();
+    let allowed_login = login(OFFICE).await.unwrap();
+    let removed = app
+        .client
+        .delete(format!("{}/{}", &endpoint, network.id))
+        .bearer_auth(&data.token)
+        .header(FORWARDED_FOR_HEADER, OFFICE)
+        .send()
+        .await
+        .unwrap();
+    let unrestricted = list(ELSEWHERE)
+        .await
+        .unwrap()
+        .json::<Response<Vec<ApiAllowedNetwork>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(locked_out.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(network.cidr, "203.0.113.0/24");
+    assert_eq!(network.description.as_deref(), Some("Office"));
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+    assert_eq!(denied_login.status(), StatusCode::FORBIDDEN);
+    assert!(allowed_login.status().is_success());
+    assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+    assert!(unrestricted.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn allowed_ipv6_network_should_admit_its_addresses(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me/allowed-networks", &app.url);
+    let list = |from: &str| {
+        app.client
+            .get(&endpoint)
+            .bearer_auth(&data.token)
+            .header(FORWARDED_FOR_HEADER, from)
+            .send()
+    };
+    app.client
+        .post(&endpoint)
+        .bearer_auth(&data.token)
+        .header(FORWARDED_FOR_HEADER, "2001:db8:1::5")
+        .json(&json!({ "cidr": "2001:db8:1::/48" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Act
+    let allowed = list("2001:db8:1:2::7").await.unwrap();
+    let denied = list("2001:db8:2::7").await.unwrap();
+    let denied_ipv4 = list(OFFICE).await.unwrap();
+
+    // Assert
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+    assert_eq!(denied_ipv4.status(), StatusCode::FORBIDDEN);
+}
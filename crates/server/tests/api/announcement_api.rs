@@ -0,0 +1,130 @@
+use axum::http::StatusCode;
+use chrono::Duration;
+use dashboard_server::clock::Clock;
+use dashboard_server::model::types::{AnnouncementKind, ApiAnnouncement};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, UserBuilder, database, requests};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn announcements_should_target_active_ones_at_the_audience(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    data.create_server(&app, &pool).await;
+    database::add_datacenter(&pool, "fra-1").await;
+    let admin = UserBuilder::new()
+        .email("admin@example.com")
+        .admin()
+        .register(&app, &pool)
+        .await;
+    let admin_endpoint = format!("{}/admin/announcements", &app.url);
+    let now = app.clock.now();
+    let payloads = [
+        json!({"kind": "info", "title": "Everyone", "message": "New plans"}),
+        json!({"kind": "maintenance", "title": "Amsterdam", "message": "Network upgrade",
+            "datacenter_code": "Amsterdam", "expires_at": now + Duration::hours(2)}),
+        json!({"kind": "incident", "title": "Frankfurt", "message": "Storage outage",
+            "datacenter_code": "fra-1"}),
+        json!({"kind": "maintenance", "title": "Scheduled", "message": "Reboots",
+            "publish_at": now + Duration::days(1)}),
+        json!({"kind": "info", "title": "Expired", "message": "Done",
+            "publish_at": now - Duration::days(2), "expires_at": now - Duration::days(1)}),
+    ];
+    for payload in &payloads {
+        requests::post_response(&app, &admin_endpoint, &admin.token, payload).await;
+    }
+
+    // Act
+    let active = requests::get_response(&app, &format!("{}/announcements", &app.url), &data.token)
+        .await
+        .json::<Response<Vec<ApiAnnouncement>>>()
+        .await
+        .unwrap()
+        .result;
+    let all = requests::get_response(&app, &admin_endpoint, &admin.token)
+        .await
+        .json::<Response<Vec<ApiAnnouncement>>>()
+        .await
+        .unwrap()
+        .result;
+    let as_customer = requests::get_response(&app, &admin_endpoint, &data.token).await;
+
+    // Assert
+    let mut titles = active
+        .iter()
+        .map(|announcement| announcement.title.as_str())
+        .collect::<Vec<_>>();
+    titles.sort_unstable();
+    assert_eq!(titles, ["Amsterdam", "Everyone"]);
+    let amsterdam = active.iter().find(|a| a.title == "Amsterdam").unwrap();
+    assert_eq!(amsterdam.kind, AnnouncementKind::Maintenance);
+    assert_eq!(amsterdam.datacenter_code.as_deref(), Some("Amsterdam"));
+    assert_eq!(all.len(), payloads.len());
+    assert_eq!(as_customer.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn announcement_should_be_updated_and_deleted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let admin = UserBuilder::new()
+        .email("admin@example.com")
+        .admin()
+        .register(&app, &pool)
+        .await;
+    let admin_endpoint = format!("{}/admin/announcements", &app.url);
+    let created = requests::post_response(
+        &app,
+        &admin_endpoint,
+        &admin.token,
+        &json!({"kind": "incident", "title": "Outage", "message": "Investigating"}),
+    )
+    .await
+    .json::<Response<ApiAnnouncement>>()
+    .await
+    .unwrap()
+    .result;
+    let endpoint = format!("{admin_endpoint}/{}", created.id);
+
+    // Act
+    let unknown_datacenter = requests::put_response(
+        &app,
+        &endpoint,
+        &admin.token,
+        &json!({"kind": "incident", "title": "Outage", "message": "Investigating",
+            "datacenter_code": "nowhere"}),
+    )
+    .await;
+    let expires_early = requests::put_response(
+        &app,
+        &endpoint,
+        &admin.token,
+        &json!({"kind": "incident", "title": "Outage", "message": "Investigating",
+            "expires_at": app.clock.now() - Duration::hours(1)}),
+    )
+    .await;
+    let updated = requests::put_response(
+        &app,
+        &endpoint,
+        &admin.token,
+        &json!({"kind": "info", "title": "Resolved", "message": "Storage is back"}),
+    )
+    .await
+    .json::<Response<ApiAnnouncement>>()
+    .await
+    .unwrap()
+    .result;
+    let deleted = requests::delete_response(&app, &endpoint, &admin.token).await;
+    let deleted_again = requests::delete_response(&app, &endpoint, &admin.token).await;
+
+    // Assert
+    assert_eq!(unknown_datacenter.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(expires_early.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(updated.id, created.id);
+    assert_eq!(updated.kind, AnnouncementKind::Info);
+    assert_eq!(updated.title, "Resolved");
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert_eq!(deleted_again.status(), StatusCode::NOT_FOUND);
+}
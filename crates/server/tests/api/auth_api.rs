@@ -1,5 +1,12 @@
-﻿use crate::helpers::{TestApp, payload, requests};
-use dashboard_server::web::types::TokenResponse;
+﻿use axum::http::StatusCode;
+use dashboard_server::config::{Config, RedisEnv};
+use dashboard_server::model::queries;
+use dashboard_server::web::types::{TokenPayload, TokenResponse, UserResponse};
+use dashboard_testing::{
+    MockCaptchaProvider, TestApp, TestData, UserBuilder, database, payload, requests,
+};
+use secrecy::ExposeSecret;
+use serde_json::json;
 use sqlx::PgPool;
 
 #[sqlx::test(migrations = "../../migrations")]
@@ -36,3 +43,216 @@ async fn should_login(pool: PgPool) {
     let payload = response.json::<TokenResponse>().await.unwrap();
     assert!(!payload.result.token.is_empty());
 }
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn email_should_be_compared_case_insensitively(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let endpoint = format!("{}/register", &app.url);
+    let user = UserBuilder::new().email(" John.Doe@Example.com ");
+    let registered = requests::post_response(&app, &endpoint, "", &user.payload()).await;
+
+    // Act
+    let duplicate = UserBuilder::new().email("john.doe@example.COM").payload();
+    let duplicate = requests::post_response(&app, &endpoint, "", &duplicate).await;
+    let endpoint = format!("{}/login", &app.url);
+    let login = UserBuilder::new()
+        .email("JOHN.DOE@example.com")
+        .login_payload();
+    let login = requests::post_response(&app, &endpoint, "", &login).await;
+
+    // Assert
+    assert!(registered.status().is_success());
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    assert!(login.status().is_success());
+    let stored = queries::get_user_by_email(&pool, "john.doe@example.com")
+        .await
+        .unwrap();
+    assert_eq!(stored.email, "john.doe@example.com");
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn concurrent_registrations_should_conflict(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool).await;
+    let endpoint = format!("{}/register", &app.url);
+    let payload = payload::register_user();
+
+    // Act
+    let (first, second) = tokio::join!(
+        requests::post_response(&app, &endpoint, "", &payload),
+        requests::post_response(&app, &endpoint, "", &payload),
+    );
+
+    // Assert
+    let mut statuses = [first.status(), second.status()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn unverified_user_should_not_order_servers(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let endpoint = format!("{}/register", &app.url);
+    let token = requests::post_result::<TokenPayload>(&app, &endpoint, &payload::register_user())
+        .await
+        .token;
+    let first_token = app.last_email_token();
+    let product_id = database::populate_product(&pool).await;
+
+    // Act
+    let endpoint = format!("{}/servers", &app.url);
+    let order =
+        requests::post_response(&app, &endpoint, &token, &payload::new_server(product_id)).await;
+    let endpoint = format!("{}/auth/verify/resend", &app.url);
+    let resend = requests::post_response(&app, &endpoint, &token, &json!({})).await;
+    let endpoint = format!("{}/auth/verify", &app.url);
+    let replaced =
+        requests::post_response(&app, &endpoint, "", &json!({ "token": first_token })).await;
+    let verified = requests::post_response(
+        &app,
+        &endpoint,
+        "",
+        &json!({ "token": app.last_email_token() }),
+    )
+    .await;
+    let endpoint = format!("{}/user/me", &app.url);
+    let user = requests::get_response(&app, &endpoint, &token)
+        .await
+        .json::<UserResponse>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(order.status(), StatusCode::FORBIDDEN);
+    assert_eq!(resend.status(), StatusCode::ACCEPTED);
+    assert_eq!(app.mailer.sent.lock().unwrap().len(), 2);
+    assert_eq!(replaced.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(verified.status(), StatusCode::NO_CONTENT);
+    assert!(user.email_verified_at.is_some());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn bcrypt_password_should_be_rehashed_on_login(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let endpoint = format!("{}/register", &app.url);
+    let payload = payload::register_user();
+    requests::post_response(&app, &endpoint, "", &payload).await;
+    let email = payload["email"].as_str().unwrap();
+    let password = payload["password"].as_str().unwrap();
+    let user = queries::get_user_by_email(&pool, email).await.unwrap();
+    let whmcs_hash = bcrypt::hash(password, 4)
+        .unwrap()
+        .replacen("$2b$", "$2y$", 1);
+    queries::update_password_hash(&pool, &user.id, &whmcs_hash)
+        .await
+        .unwrap();
+
+    // Act
+    let endpoint = format!("{}/login", &app.url);
+    let wrong_payload = json!({ "email": email, "password": "wrong_password" });
+    let wrong = requests::post_response(&app, &endpoint, "", &wrong_payload).await;
+    let hash_after_wrong = queries::get_user_by_email(&pool, email)
+        .await
+        .unwrap()
+        .password;
+    let login = requests::post_response(&app, &endpoint, "", &payload::login_user()).await;
+    let hash_after_login = queries::get_user_by_email(&pool, email)
+        .await
+        .unwrap()
+        .password;
+
+    // Assert
+    assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(hash_after_wrong.expose_secret(), whmcs_hash);
+    assert!(login.status().is_success());
+    assert!(hash_after_login.expose_secret().starts_with("$argon2id$"));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn login_should_require_captcha_after_failed_logins(pool: PgPool) {
+    // Arrange
+    let app = TestApp::with_captcha(pool).await;
+    let endpoint = format!("{}/register", &app.url);
+    requests::post_response(&app, &endpoint, "", &payload::register_user()).await;
+    let endpoint = format!("{}/login", &app.url);
+    let mut payload = payload::login_user();
+    let wrong_payload = json!({ "email": payload["email"], "password": "wrong_password" });
+
+    // Act
+    let mut failures = Vec::new();
+    for _ in 0..3 {
+        let wrong = requests::post_response(&app, &endpoint, "", &wrong_payload).await;
+        failures.push(wrong.status());
+    }
+    let without_captcha = requests::post_response(&app, &endpoint, "", &payload).await;
+    payload["captcha_token"] = json!("forged");
+    let forged = requests::post_response(&app, &endpoint, "", &payload).await;
+    payload["captcha_token"] = json!(MockCaptchaProvider::SOLVED);
+    let solved = requests::post_response(&app, &endpoint, "", &payload).await;
+
+    // Assert
+    assert_eq!(failures, [StatusCode::UNAUTHORIZED; 3]);
+    assert_eq!(without_captcha.status(), StatusCode::UNAUTHORIZED);
+    let error = without_captcha.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(error["code"], "captcha_required");
+    assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+    assert!(solved.status().is_success());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn registration_should_require_captcha_after_registrations_from_address(pool: PgPool) {
+    // Arrange
+    let app = TestApp::with_captcha(pool).await;
+    let endpoint = format!("{}/register", &app.url);
+    let user = |index: usize| {
+        UserBuilder::new()
+            .email(&format!("user{index}@example.com"))
+            .payload()
+    };
+
+    // Act
+    let mut registrations = Vec::new();
+    for index in 0..3 {
+        let response = requests::post_response(&app, &endpoint, "", &user(index)).await;
+        registrations.push(response.status());
+    }
+    let without_captcha = requests::post_response(&app, &endpoint, "", &user(3)).await;
+    let mut payload = user(3);
+    payload["captcha_token"] = json!(MockCaptchaProvider::SOLVED);
+    let solved = requests::post_response(&app, &endpoint, "", &payload).await;
+
+    // Assert
+    assert_eq!(registrations, [StatusCode::OK; 3]);
+    assert_eq!(without_captcha.status(), StatusCode::UNAUTHORIZED);
+    assert!(solved.status().is_success());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn unavailable_redis_should_not_block_authenticated_requests(pool: PgPool) {
+    // Arrange
+    let config = Config {
+        redis: RedisEnv {
+            url: Some("redis://127.0.0.1:1".to_owned()),
+            timeout_ms: 200,
+            ..RedisEnv::default()
+        },
+        ..TestApp::config()
+    };
+    let app = TestApp::with_config(pool.clone(), config).await;
+    let data = TestData::new(&app, &pool).await;
+
+    // Act
+    let me = requests::get_response(&app, &format!("{}/user/me", &app.url), &data.token).await;
+    let endpoint = format!("{}/me", &app.url);
+    let deletion = requests::delete_response(&app, &endpoint, &data.token).await;
+    let after_deletion = requests::delete_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(me.status(), StatusCode::OK);
+    assert_eq!(deletion.status(), StatusCode::ACCEPTED);
+    assert_eq!(after_deletion.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,325 @@
+use dashboard_server::model::queries;
+use dashboard_server::model::types::{
+    ApiUsage, InvoiceStatus, NewInvoice, NewUsageRecord, PaymentStatus,
+};
+use dashboard_server::payments::types::CheckoutSession;
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, requests};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn invoice_should_be_settled_by_webhook(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let invoice_id = queries::create_invoice(
+        &pool,
+        NewInvoice {
+            user_id: data.user_id,
+            service_id: None,
+            description: "VPS monthly".to_owned(),
+            amount_cents: 1500,
+            currency: "usd".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let endpoint = format!("{}/invoices/{}/checkout", &app.url, invoice_id);
+    let session = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<CheckoutSession>>()
+        .await
+        .unwrap()
+        .result;
+    let event = json!({
+        "id": "evt_1",
+        "type": "checkout.session.completed",
+        "data": {"object": {
+            "id": session.id,
+            "client_reference_id": invoice_id.to_string(),
+            "payment_status": "paid",
+            "amount_total": 1500
+        }}
+    });
+
+    // Act
+    let endpoint = format!("{}/payments/webhook", &app.url);
+    let mut statuses = Vec::new();
+    for _ in 0..2 {
+        let response = app
+            .client
+            .post(&endpoint)
+            .header("Stripe-Signature", "t=0,v1=mock")
+            .body(event.to_string())
+            .send()
+            .await
+            .unwrap();
+        statuses.push(response.status());
+    }
+
+    // Assert
+    assert!(statuses.iter().all(|status| status.is_success()));
+    let invoice = queries::get_invoice_by_id(&pool, data.user_id, invoice_id)
+        .await
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Paid);
+    assert!(invoice.paid_at.is_some());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn invoice_should_be_settled_by_delayed_payment(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let invoice_id = queries::create_invoice(
+        &pool,
+        NewInvoice {
+            user_id: data.user_id,
+            service_id: None,
+            description: "VPS monthly".to_owned(),
+            amount_cents: 1500,
+            currency: "usd".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let endpoint = format!("{}/invoices/{}/checkout", &app.url, invoice_id);
+    let session = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<CheckoutSession>>()
+        .await
+        .unwrap()
+        .result;
+    let event = json!({
+        "id": "evt_1",
+        "type": "checkout.session.async_payment_succeeded",
+        "data": {"object": {
+            "id": session.id,
+            "client_reference_id": invoice_id.to_string(),
+            "payment_status": "paid",
+            "amount_total": 1500
+        }}
+    });
+
+    // Act
+    let endpoint = format!("{}/payments/webhook", &app.url);
+    let response = app
+        .client
+        .post(&endpoint)
+        .header("Stripe-Signature", "t=0,v1=mock")
+        .body(event.to_string())
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(response.status().is_success());
+    let invoice = queries::get_invoice_by_id(&pool, data.user_id, invoice_id)
+        .await
+        .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Paid);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn failed_delayed_payment_should_leave_invoice_unpaid(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let invoice_id = queries::create_invoice(
+        &pool,
+        NewInvoice {
+            user_id: data.user_id,
+            service_id: None,
+            description: "VPS monthly".to_owned(),
+            amount_cents: 1500,
+            currency: "usd".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let endpoint = format!("{}/invoices/{}/checkout", &app.url, invoice_id);
+    let session = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<CheckoutSession>>()
+        .await
+        .unwrap()
+        .result;
+    let event = json!({
+        "id": "evt_1",
+        "type": "checkout.session.async_payment_failed",
+        "data": {"object": {
+            "id": session.id,
+            "client_reference_id": invoice_id.to_string(),
+            "payment_status": "unpaid",
+            "amount_total": 1500
+        }}
+    });
+
+    // Act
+    let endpoint = format!("{}/payments/webhook", &app.url);
+    let response = app
+        .client
+        .post(&endpoint)
+        .header("Stripe-Signature", "t=0,v1=mock")
+        .body(event.to_string())
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(response.status().is_success());
+    let invoice = queries::get_invoice_by_id(&pool, data.user_id, invoice_id)
+        .await
+        .unwrap();
+    let payment_status: String =
+        sqlx::query_scalar("SELECT status FROM payments WHERE provider_ref = $1")
+            .bind(&session.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(invoice.status, InvoiceStatus::Unpaid);
+    assert_eq!(payment_status, PaymentStatus::Failed.to_string());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn mismatched_payment_should_be_kept_as_credit(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let invoice_id = queries::create_invoice(
+        &pool,
+        NewInvoice {
+            user_id: data.user_id,
+            service_id: None,
+            description: "VPS monthly".to_owned(),
+            amount_cents: 1500,
+            currency: "usd".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+    let endpoint = format!("{}/invoices/{}/checkout", &app.url, invoice_id);
+    let session = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<CheckoutSession>>()
+        .await
+        .unwrap()
+        .result;
+    let short = json!({
+        "id": "evt_1",
+        "type": "checkout.session.completed",
+        "data": {"object": {
+            "id": session.id,
+            "client_reference_id": invoice_id.to_string(),
+            "payment_status": "paid",
+            "amount_total": 500
+        }}
+    });
+    let over = json!({
+        "id": "evt_2",
+        "type": "checkout.session.completed",
+        "data": {"object": {
+            "id": session.id,
+            "client_reference_id": invoice_id.to_string(),
+            "payment_status": "paid",
+            "amount_total": 2000
+        }}
+    });
+    let endpoint = format!("{}/payments/webhook", &app.url);
+
+    // Act
+    let mut balances = Vec::new();
+    let mut statuses = Vec::new();
+    for event in [short, over] {
+        app.client
+            .post(&endpoint)
+            .header("Stripe-Signature", "t=0,v1=mock")
+            .body(event.to_string())
+            .send()
+            .await
+            .unwrap();
+        let invoice = queries::get_invoice_by_id(&pool, data.user_id, invoice_id)
+            .await
+            .unwrap();
+        statuses.push(invoice.status);
+        balances.push(
+            queries::get_credit_balance(&pool, data.user_id)
+                .await
+                .unwrap(),
+        );
+    }
+
+    // Assert
+    assert_eq!(statuses, [InvoiceStatus::Unpaid, InvoiceStatus::Paid]);
+    assert_eq!(balances, [500, 1000]);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn webhook_without_signature_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool).await;
+    let endpoint = format!("{}/payments/webhook", &app.url);
+
+    // Act
+    let response = app.client.post(&endpoint).body("{}").send().await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn usage_should_be_aggregated_for_current_period(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    for _ in 0..2 {
+        queries::create_usage_record(
+            &pool,
+            NewUsageRecord {
+                user_id: data.user_id,
+                service_id: server.service_id,
+                server_id: server.server_id,
+                uptime_hours: 1.0,
+                cpu_usage: Some(0.5),
+                ram_mb: Some(1024),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    // Act
+    let endpoint = format!("{}/usage", &app.url);
+    let response = requests::get_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert!(response.status().is_success());
+    let usage = response
+        .json::<Response<Vec<ApiUsage>>>()
+        .await
+        .unwrap()
+        .result;
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0].service_id, Some(server.service_id));
+    assert_eq!(usage[0].samples, 2);
+    assert_eq!(usage[0].uptime_hours, 2.0);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn usage_with_inverted_period_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+
+    // Act
+    let endpoint = format!(
+        "{}/usage?from=2026-02-01T00:00:00Z&to=2026-01-01T00:00:00Z",
+        &app.url
+    );
+    let response = requests::get_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
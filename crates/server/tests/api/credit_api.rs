@@ -0,0 +1,111 @@
+use dashboard_server::model::queries;
+use dashboard_server::model::types::{ApiCreditBalance, ApiInvoice, InvoiceStatus, NewInvoice};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, database, requests};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn promo_code_should_be_redeemed_only_once(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/promo-codes", &app.url);
+    let payload = json!({"code": "welcome10", "amount_cents": 1000, "max_redemptions": 5});
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+    assert!(response.status().is_success());
+
+    // Act
+    let endpoint = format!("{}/promo-codes/redeem", &app.url);
+    let payload = json!({"code": " Welcome10 "});
+    let first = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+    let second = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert!(first.status().is_success());
+    let balance = first
+        .json::<Response<ApiCreditBalance>>()
+        .await
+        .unwrap()
+        .result;
+    assert_eq!(balance.balance_cents, 1000);
+    assert_eq!(balance.entries.len(), 1);
+    assert_eq!(second.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn unknown_promo_code_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/promo-codes/redeem", &app.url);
+
+    // Act
+    let response =
+        requests::post_response(&app, &endpoint, &data.token, &json!({"code": "NOPE"})).await;
+
+    // Assert
+    assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn admin_endpoints_should_reject_regular_users(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/admin/credits", &app.url);
+    let payload = json!({"user_id": data.user_id, "amount_cents": 1000, "reason": "Gift"});
+
+    // Act
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    let balance = queries::get_credit_balance(&pool, data.user_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 0);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn issued_credit_should_settle_invoice(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/credits", &app.url);
+    let payload = json!({"user_id": data.user_id, "amount_cents": 2000, "reason": "Goodwill"});
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+    assert!(response.status().is_success());
+    let invoice_id = queries::create_invoice(
+        &pool,
+        NewInvoice {
+            user_id: data.user_id,
+            service_id: None,
+            description: "VPS monthly".to_owned(),
+            amount_cents: 1500,
+            currency: "usd".to_owned(),
+        },
+    )
+    .await
+    .unwrap();
+
+    // Act
+    let endpoint = format!("{}/invoices/{}/credit", &app.url, invoice_id);
+    let invoice = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<ApiInvoice>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(invoice.status, InvoiceStatus::Paid);
+    assert_eq!(invoice.credit_cents, 1500);
+    assert!(invoice.paid_at.is_some());
+    let balance = queries::get_credit_balance(&pool, data.user_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 500);
+}
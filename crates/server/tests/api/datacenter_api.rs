@@ -0,0 +1,119 @@
+use dashboard_server::model::types::{ApiAvailableDatacenter, ApiDatacenter};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, database, payload, requests};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn product_should_list_available_datacenters(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let datacenter = json!({
+        "code": "fra-1",
+        "display_name": "Frankfurt",
+        "cluster_url": "https://pve.fra-1.example.com:8006"
+    });
+    let available = format!("{}/api/products/{}/datacenters", &app.url, data.product_id);
+
+    // Act
+    let created = requests::post_response(
+        &app,
+        &format!("{}/admin/datacenters", &app.url),
+        &data.token,
+        &datacenter,
+    )
+    .await
+    .json::<Response<ApiDatacenter>>()
+    .await
+    .unwrap()
+    .result;
+    let offered = requests::put_response(
+        &app,
+        &format!(
+            "{}/admin/products/{}/datacenters",
+            &app.url, data.product_id
+        ),
+        &data.token,
+        &json!({ "datacenter_codes": ["Amsterdam", "fra-1"] }),
+    )
+    .await
+    .json::<Response<Vec<ApiDatacenter>>>()
+    .await
+    .unwrap()
+    .result;
+    let before = requests::get_response(&app, &available, &data.token)
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    requests::put_response(
+        &app,
+        &format!("{}/admin/datacenters/fra-1", &app.url),
+        &data.token,
+        &json!({ "code": "fra-1", "display_name": "Frankfurt", "is_full": true }),
+    )
+    .await;
+    let after = requests::get_response(&app, &available, &data.token)
+        .await
+        .json::<Response<Vec<ApiAvailableDatacenter>>>()
+        .await
+        .unwrap()
+        .result;
+    let unknown = requests::get_response(
+        &app,
+        &format!("{}/api/products/{}/datacenters", &app.url, Uuid::new_v4()),
+        &data.token,
+    )
+    .await;
+
+    // Assert
+    assert!(created.is_active);
+    assert!(!created.is_full);
+    assert_eq!(offered.len(), 2);
+    assert_eq!(
+        before["result"],
+        json!([
+            {"code": "Amsterdam", "display_name": offered[0].display_name},
+            {"code": "fra-1", "display_name": "Frankfurt"}
+        ])
+    );
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0].code, "Amsterdam");
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn order_outside_product_datacenters_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::add_datacenter(&pool, "fra-1").await;
+    let mut payload = payload::new_server(data.product_id);
+    payload["datacenter"] = json!("fra-1");
+
+    // Act
+    let endpoint = format!("{}/servers", &app.url);
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn datacenter_with_networks_should_not_be_deleted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/datacenters/Amsterdam", &app.url);
+
+    // Act
+    let response = requests::delete_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
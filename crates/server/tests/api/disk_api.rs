@@ -0,0 +1,107 @@
+use axum::http::StatusCode;
+use dashboard_server::model::types::{ApiDisk, ApiServer};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, database, requests};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn add_disk_should_attach_within_product_limits(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::set_product_disk_limits(&pool, data.product_id, 1, Some(50)).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/disks", &app.url, server.server_id);
+
+    // Act
+    let too_large =
+        requests::post_response(&app, &endpoint, &data.token, &json!({ "size_gb": 60 })).await;
+    let attached =
+        requests::post_response(&app, &endpoint, &data.token, &json!({ "size_gb": 10 })).await;
+    let attached_status = attached.status();
+    let disks = attached
+        .json::<Response<Vec<ApiDisk>>>()
+        .await
+        .unwrap()
+        .result;
+    let over_limit =
+        requests::post_response(&app, &endpoint, &data.token, &json!({ "size_gb": 10 })).await;
+    let disk_gb = database::get_config_value(&pool, server.server_id, "disk_gb").await;
+
+    // Assert
+    assert_eq!(too_large.status(), StatusCode::FORBIDDEN);
+    assert_eq!(attached_status, StatusCode::OK);
+    let devices = disks
+        .iter()
+        .map(|disk| (disk.device.as_str(), disk.size_gb));
+    assert_eq!(devices.collect::<Vec<_>>(), [("scsi0", 20), ("scsi1", 10)]);
+    assert_eq!(over_limit.status(), StatusCode::FORBIDDEN);
+    assert_eq!(disk_gb.as_deref(), Some("30"));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn resize_disk_should_only_grow(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::set_product_disk_limits(&pool, data.product_id, 0, Some(50)).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = |device: &str| {
+        format!(
+            "{}/servers/{}/disks/{}/resize",
+            &app.url, server.server_id, device
+        )
+    };
+
+    // Act
+    let shrunk = requests::put_response(
+        &app,
+        &endpoint("scsi0"),
+        &data.token,
+        &json!({ "size_gb": 10 }),
+    )
+    .await;
+    let too_large = requests::put_response(
+        &app,
+        &endpoint("scsi0"),
+        &data.token,
+        &json!({ "size_gb": 60 }),
+    )
+    .await;
+    let cdrom = requests::put_response(
+        &app,
+        &endpoint("ide2"),
+        &data.token,
+        &json!({ "size_gb": 40 }),
+    )
+    .await;
+    let grown = requests::put_response(
+        &app,
+        &endpoint("scsi0"),
+        &data.token,
+        &json!({ "size_gb": 40 }),
+    )
+    .await;
+
+    let disk_gb = database::get_config_value(&pool, server.server_id, "disk_gb").await;
+    let server_endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let status = requests::get_response(&app, &server_endpoint, &data.token)
+        .await
+        .json::<Response<ApiServer>>()
+        .await
+        .unwrap()
+        .result
+        .status;
+
+    // Assert
+    assert_eq!(shrunk.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(too_large.status(), StatusCode::FORBIDDEN);
+    assert_eq!(cdrom.status(), StatusCode::NOT_FOUND);
+    assert_eq!(grown.status(), StatusCode::OK);
+    let disks = grown.json::<Response<Vec<ApiDisk>>>().await.unwrap().result;
+    assert_eq!(disks[0].device, "scsi0");
+    assert_eq!(disks[0].size_gb, 40);
+    assert_eq!(disk_gb.as_deref(), Some("40"));
+    assert_eq!(status, server.status);
+}
@@ -0,0 +1,129 @@
+use axum::http::StatusCode;
+use dashboard_server::model::queries;
+use dashboard_server::model::types::{ApiEventReplay, ApiWebhook, ApiWebhookDelivery};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, database, requests};
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn replayed_events_should_queue_webhook_deliveries_again(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/webhooks", &app.url);
+    let payload = json!({"url": "https://example.com/hooks", "events": ["server.created"]});
+    let webhook = requests::post_response(&app, &endpoint, &data.token, &payload)
+        .await
+        .json::<Response<ApiWebhook>>()
+        .await
+        .unwrap()
+        .result;
+    data.create_server(&app, &pool).await;
+    let last_id = queries::get_last_domain_event_id(&pool).await.unwrap();
+    let unpublished: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM domain_events WHERE published_at IS NULL")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+    // Act
+    let replay = requests::post_response(
+        &app,
+        &format!("{}/admin/events/replay", &app.url),
+        &data.token,
+        &json!({"from_id": 1, "to_id": last_id, "consumers": ["webhooks"]}),
+    )
+    .await
+    .json::<Response<ApiEventReplay>>()
+    .await
+    .unwrap()
+    .result;
+    let deliveries = requests::get_response(
+        &app,
+        &format!("{endpoint}/{}/deliveries", webhook.id),
+        &data.token,
+    )
+    .await
+    .json::<Response<Vec<ApiWebhookDelivery>>>()
+    .await
+    .unwrap()
+    .result;
+
+    // Assert
+    assert_eq!(unpublished, 0);
+    assert!(replay.replayed >= 1);
+    assert_eq!(deliveries.len(), 2);
+    assert!(
+        deliveries
+            .iter()
+            .all(|delivery| delivery.event == "server.created")
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn invalid_replay_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/events/replay", &app.url);
+
+    // Act
+    let reversed = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({"from_id": 10, "to_id": 1}),
+    )
+    .await;
+    let unknown = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({"from_id": 1, "to_id": 10, "consumers": ["email"]}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(reversed.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn event_stream_should_resume_after_last_event_id(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+
+    // Act
+    let mut response = app
+        .client
+        .get(format!("{}/me/events", &app.url))
+        .bearer_auth(&data.token)
+        .header("Last-Event-ID", "0")
+        .send()
+        .await
+        .unwrap();
+    let mut body = String::new();
+    while !body.contains("event: server.status_changed") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), response.chunk())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        body.push_str(&String::from_utf8_lossy(&chunk));
+    }
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    assert!(body.contains("event: server.created"));
+    assert!(body.contains(&server.server_id.to_string()));
+}
@@ -0,0 +1,75 @@
+use dashboard_server::config::{Config, GrpcEnv};
+use dashboard_server::grpc::proto::servers_client::ServersClient;
+use dashboard_server::grpc::proto::{
+    Action, GetServerRequest, ListServersRequest, RunActionRequest, Status,
+};
+use dashboard_testing::{TestApp, TestData};
+use secrecy::SecretString;
+use sqlx::PgPool;
+use tonic::{Code, Request};
+use uuid::Uuid;
+
+const TOKEN: &str = "internal-token";
+
+fn authorized<T>(message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    request
+        .metadata_mut()
+        .insert("authorization", format!("Bearer {TOKEN}").parse().unwrap());
+    request
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn grpc_should_query_and_start_servers(pool: PgPool) {
+    // Arrange
+    let config = Config {
+        grpc: GrpcEnv {
+            address: Some("127.0.0.1:0".parse().unwrap()),
+            token: SecretString::from(TOKEN),
+        },
+        ..Config::default()
+    };
+    let app = TestApp::with_config(pool.clone(), config).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let server_id = server.server_id.to_string();
+    let mut client = ServersClient::connect(app.grpc_url.clone().unwrap())
+        .await
+        .unwrap();
+
+    // Act
+    let unauthorized = client
+        .get_server(GetServerRequest {
+            server_id: server_id.clone(),
+        })
+        .await;
+    let missing = client
+        .get_server(authorized(GetServerRequest {
+            server_id: Uuid::new_v4().to_string(),
+        }))
+        .await;
+    let started = client
+        .run_action(authorized(RunActionRequest {
+            server_id: server_id.clone(),
+            action: Action::Start.into(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+    let servers = client
+        .list_servers(authorized(ListServersRequest {
+            user_id: data.user_id.to_string(),
+        }))
+        .await
+        .unwrap()
+        .into_inner()
+        .servers;
+
+    // Assert
+    assert_eq!(unauthorized.unwrap_err().code(), Code::Unauthenticated);
+    assert_eq!(missing.unwrap_err().code(), Code::NotFound);
+    assert_eq!(started.status(), Status::Running);
+    assert_eq!(started.user_id, data.user_id.to_string());
+    assert_eq!(started.vm_id, server.vm_id);
+    assert_eq!(servers, [started]);
+}
@@ -0,0 +1,66 @@
+use axum::http::StatusCode;
+use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, ETAG, IF_NONE_MATCH};
+use dashboard_server::config::{Config, HttpEnv};
+use dashboard_testing::{TestApp, TestData};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn oversized_body_should_be_rejected_per_route_group(pool: PgPool) {
+    // Arrange
+    let config = Config {
+        http: HttpEnv {
+            body_limits: HashMap::from([("login".to_owned(), 256)]),
+            ..HttpEnv::default()
+        },
+        ..Config::default()
+    };
+    let app = TestApp::with_config(pool, config).await;
+    let login = |password: String| {
+        app.client
+            .post(format!("{}/login", &app.url))
+            .json(&json!({ "email": "john@example.com", "password": password }))
+            .send()
+    };
+
+    // Act
+    let small = login("password".to_owned()).await.unwrap();
+    let oversized = login("x".repeat(1024)).await.unwrap();
+
+    // Assert
+    assert_eq!(small.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(oversized.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn unchanged_catalog_should_not_be_sent_again(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let get = || {
+        app.client
+            .get(format!("{}/api/products", &app.url))
+            .bearer_auth(&data.token)
+    };
+
+    // Act
+    let first = get().send().await.unwrap();
+    let etag = first.headers()[ETAG].clone();
+    let cached = get().header(IF_NONE_MATCH, &etag).send().await.unwrap();
+    let stale = get()
+        .header(IF_NONE_MATCH, "W/\"stale\"")
+        .send()
+        .await
+        .unwrap();
+    let compressed = get().header(ACCEPT_ENCODING, "gzip").send().await.unwrap();
+
+    // Assert
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(cached.headers()[ETAG], etag);
+    assert!(cached.bytes().await.unwrap().is_empty());
+    assert_eq!(stale.status(), StatusCode::OK);
+    assert_eq!(compressed.headers()[CONTENT_ENCODING], "gzip");
+    assert_eq!(compressed.headers()[ETAG], etag);
+}
@@ -0,0 +1,106 @@
+use axum::http::StatusCode;
+use axum::http::header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE};
+use dashboard_server::clock::Clock;
+use dashboard_server::model::types::ApiNotification;
+use dashboard_server::services::notification;
+use dashboard_server::web::types::{Response, UserResponse};
+use dashboard_testing::{TestApp, TestData, requests};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn error_message_should_follow_accept_language(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool).await;
+    let endpoint = format!("{}/servers", &app.url);
+    let get = |language: &'static str| app.client.get(&endpoint).header(ACCEPT_LANGUAGE, language);
+
+    // Act
+    let german = get("it, de-CH;q=0.9, en;q=0.5").send().await.unwrap();
+    let unsupported = get("it").send().await.unwrap();
+
+    // Assert
+    assert_eq!(german.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(german.headers()[CONTENT_LANGUAGE], "de");
+    let error = german.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(error["code"], "unauthorized");
+    assert_eq!(
+        error["message"],
+        "Autorisierungstoken fehlt oder ist ungültig!"
+    );
+    assert!(unsupported.headers().get(CONTENT_LANGUAGE).is_none());
+    let error = unsupported.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(
+        error["message"],
+        "Authorization token is missing or invalid!"
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn specific_error_message_should_get_translated_lead_in(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me", &app.url);
+    let patch = |language: &'static str| {
+        app.client
+            .patch(&endpoint)
+            .bearer_auth(&data.token)
+            .header(ACCEPT_LANGUAGE, language)
+            .json(&json!({"locale": "xx"}))
+    };
+
+    // Act
+    let english = patch("en").send().await.unwrap();
+    let french = patch("fr").send().await.unwrap();
+
+    // Assert
+    assert_eq!(english.status(), StatusCode::BAD_REQUEST);
+    let reason = english.json::<serde_json::Value>().await.unwrap()["message"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    assert_eq!(french.headers()[CONTENT_LANGUAGE], "fr");
+    let error = french.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(error["code"], "validation_error");
+    assert_eq!(error["message"], format!("Requête invalide : {reason}"));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn notifications_should_use_preferred_locale(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me", &app.url);
+
+    // Act
+    let unsupported =
+        requests::patch_response(&app, &endpoint, &data.token, &json!({"locale": "xx"})).await;
+    let user = requests::patch_response(&app, &endpoint, &data.token, &json!({"locale": "de-AT"}))
+        .await
+        .json::<UserResponse>()
+        .await
+        .unwrap()
+        .result;
+    data.create_server(&app, &pool).await;
+    let notifications =
+        requests::get_response(&app, &format!("{endpoint}/notifications"), &data.token)
+            .await
+            .json::<Response<Vec<ApiNotification>>>()
+            .await
+            .unwrap()
+            .result;
+    notification::send_due(&app.state, app.clock.now())
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(unsupported.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(user.locale.as_deref(), Some("de"));
+    assert_eq!(notifications[0].title, "Ihr Server ist bereit");
+    let sent = app.mailer.sent.lock().unwrap().clone();
+    assert!(
+        sent.iter()
+            .any(|email| email.subject == "Ihr Server ist bereit")
+    );
+}
@@ -0,0 +1,201 @@
+use dashboard_server::model::types::{ApiIso, ApiServer, ApiServerIso};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{MockProxmoxClient, TestApp, TestData, database, requests};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn admin_should_register_iso_listed_on_storage(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/isos", &app.url);
+    let payload = |volid: &str| json!({ "name": "Debian 12", "volid": volid, "node": "pve" });
+
+    // Act
+    let not_iso = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &payload("local:backup/debian-12.iso"),
+    )
+    .await;
+    let missing = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &payload("local:iso/missing.iso"),
+    )
+    .await;
+    let iso = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &payload("local:iso/debian-12.iso"),
+    )
+    .await
+    .json::<Response<ApiIso>>()
+    .await
+    .unwrap()
+    .result;
+    let duplicate = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &payload("local:iso/debian-12.iso"),
+    )
+    .await;
+    let isos = requests::get_response(&app, &format!("{}/api/isos", &app.url), &data.token)
+        .await
+        .json::<Response<Vec<ApiIso>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(not_iso.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(missing.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(iso.size_bytes, 658505728);
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    assert_eq!(isos, [iso]);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn server_should_boot_from_attached_iso_until_detached(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let node = server.node_name.clone().unwrap();
+    let iso = requests::post_response(
+        &app,
+        &format!("{}/admin/isos", &app.url),
+        &data.token,
+        &json!({ "name": "Debian 12", "volid": "local:iso/debian-12.iso", "node": node }),
+    )
+    .await
+    .json::<Response<ApiIso>>()
+    .await
+    .unwrap()
+    .result;
+    let endpoint = format!("{}/servers/{}/iso", &app.url, server.server_id);
+    let boot_endpoint = format!("{}/boot", endpoint);
+
+    // Act
+    let boot_without_iso = requests::put_response(
+        &app,
+        &boot_endpoint,
+        &data.token,
+        &json!({ "boot_from_iso": true }),
+    )
+    .await;
+    let attached =
+        requests::put_response(&app, &endpoint, &data.token, &json!({ "iso_id": iso.id }))
+            .await
+            .json::<Response<ApiServerIso>>()
+            .await
+            .unwrap()
+            .result;
+    let booted = requests::put_response(
+        &app,
+        &boot_endpoint,
+        &data.token,
+        &json!({ "boot_from_iso": true }),
+    )
+    .await
+    .json::<Response<ApiServerIso>>()
+    .await
+    .unwrap()
+    .result;
+    let in_use = requests::delete_response(
+        &app,
+        &format!("{}/admin/isos/{}", &app.url, iso.id),
+        &data.token,
+    )
+    .await;
+    let detached = requests::delete_response(&app, &endpoint, &data.token).await;
+    let drive = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServerIso>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(boot_without_iso.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(attached.device.as_deref(), Some("ide0"));
+    assert!(booted.boot_from_iso);
+    assert_eq!(booted.iso.map(|iso| iso.name), Some("Debian 12".to_owned()));
+    assert_eq!(in_use.status(), StatusCode::CONFLICT);
+    assert_eq!(detached.status(), StatusCode::NO_CONTENT);
+    assert_eq!(drive.iso, None);
+    assert_eq!(drive.device, None);
+    assert!(!drive.boot_from_iso);
+    assert_eq!(
+        *proxmox.boot_orders.lock().unwrap(),
+        ["ide0;scsi0", "scsi0"]
+    );
+    assert_eq!(
+        *proxmox.cdroms.lock().unwrap(),
+        [Some("local:iso/debian-12.iso".to_owned()), None]
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn iso_on_local_storage_should_stay_on_its_node(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let other_node = match server.node_name.as_deref() {
+        Some("pve") => "pve-2",
+        _ => "pve",
+    };
+    let iso = requests::post_response(
+        &app,
+        &format!("{}/admin/isos", &app.url),
+        &data.token,
+        &json!({ "name": "Debian 12", "volid": "local:iso/debian-12.iso", "node": other_node }),
+    )
+    .await
+    .json::<Response<ApiIso>>()
+    .await
+    .unwrap()
+    .result;
+    let endpoint = format!("{}/servers/{}/iso", &app.url, server.server_id);
+
+    // Act
+    let response =
+        requests::put_response(&app, &endpoint, &data.token, &json!({ "iso_id": iso.id })).await;
+    let drive = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServerIso>>()
+        .await
+        .unwrap()
+        .result;
+    let status = requests::get_response(
+        &app,
+        &format!("{}/servers/{}", &app.url, server.server_id),
+        &data.token,
+    )
+    .await
+    .json::<Response<ApiServer>>()
+    .await
+    .unwrap()
+    .result
+    .status;
+
+    // Assert
+    assert_eq!(iso.node_name.as_deref(), Some(other_node));
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(drive.iso, None);
+    assert!(proxmox.cdroms.lock().unwrap().is_empty());
+    assert_eq!(status, server.status);
+}
@@ -1,4 +1,29 @@
-﻿mod auth_api;
-mod helpers;
+﻿mod agent_api;
+mod allowlist_api;
+mod announcement_api;
+mod auth_api;
+mod billing_api;
+mod credit_api;
+mod datacenter_api;
+mod disk_api;
+mod event_api;
+mod grpc_api;
+mod http_api;
+mod i18n_api;
+mod iso_api;
+mod marketplace_api;
+mod network_api;
+mod node_api;
+mod notification_api;
+mod oidc_api;
+mod organization_api;
+mod product_api;
+mod saml_api;
+mod search_api;
+mod security_api;
 mod server_api;
+mod tls_api;
+mod traffic_api;
+mod transfer_api;
 mod user_api;
+mod webhook_api;
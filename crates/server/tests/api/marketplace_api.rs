@@ -0,0 +1,99 @@
+use dashboard_server::model::types::{ApiApp, ApiTemplate, ServerStatus, TemplateKind};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{MockProxmoxClient, ServerBuilder, TestApp, TestData, database, requests};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn admin_should_offer_application_in_marketplace(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/templates", &app.url);
+    let payload = |snippet: &str| {
+        json!({
+            "os_name": "docker-host",
+            "template_vmid": 9001,
+            "template_node": "pve",
+            "kind": "application",
+            "description": "Ubuntu with Docker Engine",
+            "cloud_init_snippet": snippet,
+            "post_install_script": "systemctl enable --now docker"
+        })
+    };
+
+    // Act
+    let not_snippet = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &payload("local:iso/docker.iso"),
+    )
+    .await;
+    let template = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &payload("local:snippets/docker.yaml"),
+    )
+    .await
+    .json::<Response<ApiTemplate>>()
+    .await
+    .unwrap()
+    .result;
+    let apps = requests::get_response(&app, &format!("{}/api/apps", &app.url), &data.token)
+        .await
+        .json::<Response<Vec<ApiApp>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(not_snippet.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(template.kind, TemplateKind::Application);
+    assert_eq!(
+        template.cloud_init_snippet.as_deref(),
+        Some("local:snippets/docker.yaml")
+    );
+    assert_eq!(apps.len(), 1);
+    assert_eq!(apps[0].name, "docker-host");
+    assert_eq!(
+        apps[0].description.as_deref(),
+        Some("Ubuntu with Docker Engine")
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn server_with_app_should_run_post_install_script(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::add_app_template(&pool, "docker-host", "apt-get install -y docker.io").await;
+    let unknown = ServerBuilder::new(data.product_id).app("minecraft");
+
+    // Act
+    let rejected = requests::post_response(
+        &app,
+        &format!("{}/servers", &app.url),
+        &data.token,
+        &unknown.payload(),
+    )
+    .await;
+    let (response, server) = ServerBuilder::new(data.product_id)
+        .app("docker-host")
+        .create(&app, &pool, &data)
+        .await;
+
+    // Assert
+    assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(server.status, ServerStatus::Running);
+    let scripts = proxmox.scripts.lock().unwrap().clone();
+    assert_eq!(scripts.len(), 1);
+    assert!(scripts[0].contains("\napt-get install -y docker.io\n"));
+    assert_eq!(proxmox.call_count("agent_exec_status"), 1);
+}
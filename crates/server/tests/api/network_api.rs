@@ -0,0 +1,98 @@
+use dashboard_server::model::types::{ApiIpRange, ApiIpUtilization, ApiNetwork};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, database, requests};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn network_should_be_created_from_cidr(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    database::add_datacenter(&pool, "dc-2").await;
+    let endpoint = format!("{}/admin/networks", &app.url);
+    let payload = json!({"datacenter_name": "dc-2", "cidr": "10.20.0.0/29"});
+
+    // Act
+    let network = requests::post_response(&app, &endpoint, &data.token, &payload)
+        .await
+        .json::<Response<ApiNetwork>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(network.gateway, "10.20.0.1");
+    assert_eq!(network.subnet_mask, "255.255.255.248");
+    assert_eq!(network.addresses, 5);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn invalid_cidr_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/networks", &app.url);
+    let payload = json!({"datacenter_name": "dc-2", "cidr": "10.20.0.1/24"});
+
+    // Act
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn reserved_range_should_be_reported_in_utilization(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    database::add_datacenter(&pool, "dc-2").await;
+    let endpoint = format!("{}/admin/networks", &app.url);
+    let payload = json!({"datacenter_name": "dc-2", "cidr": "10.20.0.0/29"});
+    let network = requests::post_response(&app, &endpoint, &data.token, &payload)
+        .await
+        .json::<Response<ApiNetwork>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Act
+    let endpoint = format!("{}/admin/networks/{}/ranges", &app.url, network.id);
+    let payload = json!({"start": "10.20.0.5", "end": "10.20.0.6", "reserved": true});
+    let range = requests::post_response(&app, &endpoint, &data.token, &payload)
+        .await
+        .json::<Response<ApiIpRange>>()
+        .await
+        .unwrap()
+        .result;
+    let endpoint = format!("{}/admin/networks/utilization", &app.url);
+    let utilization = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiIpUtilization>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(range.updated, 2);
+    let datacenter = utilization
+        .into_iter()
+        .find(|item| item.datacenter_name == "dc-2")
+        .unwrap();
+    assert_eq!(
+        datacenter,
+        ApiIpUtilization {
+            datacenter_name: "dc-2".to_owned(),
+            networks: 1,
+            total: 5,
+            allocated: 0,
+            reserved: 2,
+            available: 3,
+        }
+    );
+}
@@ -0,0 +1,72 @@
+use dashboard_server::model::types::{ApiNode, ApiStorage};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, database, requests};
+use reqwest::StatusCode;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn admin_should_list_node_usage(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/admin/nodes", &app.url);
+
+    // Act
+    let nodes = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiNode>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0].name, "pve");
+    assert!(nodes[0].online);
+    assert_eq!(nodes[0].cpu_cores, Some(16));
+    assert_eq!(nodes[0].vms, 1);
+    assert_eq!(nodes[1].name, "pve-2");
+    assert!(!nodes[1].online);
+    assert_eq!(nodes[1].memory_total, None);
+    assert_eq!(nodes[1].vms, 0);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn node_usage_should_require_admin(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/admin/nodes", &app.url);
+
+    // Act
+    let response = requests::get_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn admin_should_list_node_storages(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/nodes/pve/storages", &app.url);
+
+    // Act
+    let storages = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiStorage>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(storages.len(), 2);
+    assert!(!storages[0].holds_images);
+    assert_eq!(storages[1].name, "local-zfs");
+    assert_eq!(storages[1].content, vec!["images", "rootdir"]);
+    assert!(storages[1].holds_images);
+}
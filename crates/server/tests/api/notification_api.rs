@@ -0,0 +1,232 @@
+use axum::http::StatusCode;
+use chrono::Duration;
+use dashboard_server::clock::Clock;
+use dashboard_server::model::types::{
+    ApiNotification, ApiNotificationPreference, NotificationEvent,
+};
+use dashboard_server::services::{announcement, notification};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, UserBuilder, requests};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn notifications_should_track_unread_and_respect_email_preferences(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me/notifications", &app.url);
+    requests::put_response(
+        &app,
+        &format!("{endpoint}/preferences"),
+        &data.token,
+        &json!({"event": "maintenance", "email": false}),
+    )
+    .await;
+    data.create_server(&app, &pool).await;
+    let admin = UserBuilder::new()
+        .email("admin@example.com")
+        .admin()
+        .register(&app, &pool)
+        .await;
+    requests::post_response(
+        &app,
+        &format!("{}/admin/announcements", &app.url),
+        &admin.token,
+        &json!({"kind": "maintenance", "title": "Network upgrade", "message": "Short outages",
+            "datacenter_code": "Amsterdam"}),
+    )
+    .await;
+
+    // Act
+    let all = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiNotification>>>()
+        .await
+        .unwrap()
+        .result;
+    let read = requests::post_response(
+        &app,
+        &format!("{endpoint}/{}/read", all[0].id),
+        &data.token,
+        &json!({}),
+    )
+    .await;
+    let unread = requests::get_response(&app, &format!("{endpoint}?unread=true"), &data.token)
+        .await
+        .json::<Response<Vec<ApiNotification>>>()
+        .await
+        .unwrap()
+        .result;
+    let unknown = requests::post_response(
+        &app,
+        &format!("{endpoint}/{}/read", Uuid::new_v4()),
+        &data.token,
+        &json!({}),
+    )
+    .await;
+    let read_all =
+        requests::post_response(&app, &format!("{endpoint}/read"), &data.token, &json!({})).await;
+    let unread_after =
+        requests::get_response(&app, &format!("{endpoint}?unread=true"), &data.token)
+            .await
+            .json::<Response<Vec<ApiNotification>>>()
+            .await
+            .unwrap()
+            .result;
+    let emailed_before_sending = app
+        .mailer
+        .sent
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|email| email.subject == "Your server is ready");
+    let sent = notification::send_due(&app.state, app.clock.now())
+        .await
+        .unwrap();
+    let sent_again = notification::send_due(&app.state, app.clock.now())
+        .await
+        .unwrap();
+
+    // Assert
+    let events = all
+        .iter()
+        .map(|notification| notification.event)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        [
+            NotificationEvent::Maintenance,
+            NotificationEvent::ServerReady
+        ]
+    );
+    assert!(
+        all.iter()
+            .all(|notification| notification.read_at.is_none())
+    );
+    assert_eq!(read.status(), StatusCode::NO_CONTENT);
+    assert_eq!(unread.len(), 1);
+    assert_eq!(unread[0].event, NotificationEvent::ServerReady);
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    assert_eq!(read_all.status(), StatusCode::NO_CONTENT);
+    assert!(unread_after.is_empty());
+    assert!(!emailed_before_sending);
+    assert_eq!(sent, 1);
+    assert_eq!(sent_again, 0);
+    let subjects = app
+        .mailer
+        .sent
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|email| email.subject.clone())
+        .collect::<Vec<_>>();
+    assert!(subjects.contains(&"Your server is ready".to_owned()));
+    assert!(!subjects.contains(&"Network upgrade".to_owned()));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn notification_preferences_should_default_to_email(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me/notifications/preferences", &app.url);
+
+    // Act
+    let defaults = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiNotificationPreference>>>()
+        .await
+        .unwrap()
+        .result;
+    let updated = requests::put_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({"event": "invoice_due", "email": false}),
+    )
+    .await
+    .json::<Response<Vec<ApiNotificationPreference>>>()
+    .await
+    .unwrap()
+    .result;
+    let unknown_event = requests::put_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({"event": "server_deleted", "email": false}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(defaults.len(), NotificationEvent::ALL.len());
+    assert!(defaults.iter().all(|preference| preference.email));
+    let emailed = updated
+        .iter()
+        .map(|preference| (preference.event, preference.email))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        emailed,
+        [
+            (NotificationEvent::ServerReady, true),
+            (NotificationEvent::InvoiceDue, false),
+            (NotificationEvent::Maintenance, true)
+        ]
+    );
+    assert!(unknown_event.status().is_client_error());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn scheduled_maintenance_should_notify_once_published(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me/notifications", &app.url);
+    let admin = UserBuilder::new()
+        .email("admin@example.com")
+        .admin()
+        .register(&app, &pool)
+        .await;
+    let publish_at = app.clock.now() + Duration::hours(1);
+    requests::post_response(
+        &app,
+        &format!("{}/admin/announcements", &app.url),
+        &admin.token,
+        &json!({"kind": "maintenance", "title": "Reboots", "message": "Kernel update",
+            "publish_at": publish_at}),
+    )
+    .await;
+
+    // Act
+    let before = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiNotification>>>()
+        .await
+        .unwrap()
+        .result;
+    let early = announcement::notify_published(&app.state, app.clock.now())
+        .await
+        .unwrap();
+    let published = announcement::notify_published(&app.state, publish_at)
+        .await
+        .unwrap();
+    let published_again = announcement::notify_published(&app.state, publish_at)
+        .await
+        .unwrap();
+    let after = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiNotification>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert!(before.is_empty());
+    assert_eq!(early, 0);
+    assert_eq!(published, 1);
+    assert_eq!(published_again, 0);
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0].event, NotificationEvent::Maintenance);
+    assert_eq!(after[0].title, "Reboots");
+}
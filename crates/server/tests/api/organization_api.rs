@@ -0,0 +1,222 @@
+use axum::http::StatusCode;
+use dashboard_server::model::types::{
+    ApiOrganization, ApiOrganizationMember, ApiServer, OrganizationRole,
+};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, UserBuilder, requests};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn organization_should_share_servers_with_members(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let member = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let organizations_endpoint = format!("{}/organizations", &app.url);
+    let organization = requests::post_response(
+        &app,
+        &organizations_endpoint,
+        &data.token,
+        &json!({"name": "Acme Hosting"}),
+    )
+    .await
+    .json::<Response<ApiOrganization>>()
+    .await
+    .unwrap()
+    .result;
+    let organization_endpoint = format!("{organizations_endpoint}/{}", organization.id);
+    let members = requests::put_response(
+        &app,
+        &format!("{organization_endpoint}/members"),
+        &data.token,
+        &json!({"email": "jane.doe@example.com", "role": "member"}),
+    )
+    .await
+    .json::<Response<Vec<ApiOrganizationMember>>>()
+    .await
+    .unwrap()
+    .result;
+    let as_member = requests::post_response(
+        &app,
+        &format!("{organization_endpoint}/servers"),
+        &member.token,
+        &json!({"server_id": server.server_id}),
+    )
+    .await;
+
+    // Act
+    let moved = requests::post_response(
+        &app,
+        &format!("{organization_endpoint}/servers"),
+        &data.token,
+        &json!({"server_id": server.server_id}),
+    )
+    .await;
+    let shared = requests::get_response(
+        &app,
+        &format!("{organization_endpoint}/servers"),
+        &member.token,
+    )
+    .await
+    .json::<Response<Vec<ApiServer>>>()
+    .await
+    .unwrap()
+    .result;
+    let listed = requests::get_response(&app, &organizations_endpoint, &member.token)
+        .await
+        .json::<Response<Vec<ApiOrganization>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(organization.role, OrganizationRole::Owner);
+    assert!(!organization.personal);
+    assert_eq!(members.len(), 2);
+    assert_eq!(members[1].user_id, member.user_id);
+    assert_eq!(members[1].role, OrganizationRole::Member);
+    assert_eq!(as_member.status(), StatusCode::FORBIDDEN);
+    assert_eq!(moved.status(), StatusCode::OK);
+    assert_eq!(shared.len(), 1);
+    assert_eq!(shared[0].server_id, server.server_id);
+    assert_eq!(listed.len(), 2);
+    assert!(listed[0].personal);
+    assert_eq!(listed[1].id, organization.id);
+    assert_eq!(listed[1].role, OrganizationRole::Member);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn organization_should_keep_its_last_owner(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let admin = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let organization = requests::post_response(
+        &app,
+        &format!("{}/organizations", &app.url),
+        &data.token,
+        &json!({"name": "Acme Hosting"}),
+    )
+    .await
+    .json::<Response<ApiOrganization>>()
+    .await
+    .unwrap()
+    .result;
+    let members_endpoint = format!("{}/organizations/{}/members", &app.url, organization.id);
+    requests::put_response(
+        &app,
+        &members_endpoint,
+        &data.token,
+        &json!({"email": "jane.doe@example.com", "role": "admin"}),
+    )
+    .await;
+
+    // Act
+    let owner_leaves = requests::delete_response(
+        &app,
+        &format!("{members_endpoint}/{}", data.user_id),
+        &data.token,
+    )
+    .await;
+    let owner_removed = requests::delete_response(
+        &app,
+        &format!("{members_endpoint}/{}", data.user_id),
+        &admin.token,
+    )
+    .await;
+    let admin_leaves = requests::delete_response(
+        &app,
+        &format!("{members_endpoint}/{}", admin.user_id),
+        &admin.token,
+    )
+    .await;
+    let members = requests::get_response(&app, &members_endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiOrganizationMember>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(owner_leaves.status(), StatusCode::CONFLICT);
+    assert_eq!(owner_removed.status(), StatusCode::FORBIDDEN);
+    assert_eq!(admin_leaves.status(), StatusCode::NO_CONTENT);
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].user_id, data.user_id);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn removed_member_should_hand_servers_over(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let owner = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let organization = requests::post_response(
+        &app,
+        &format!("{}/organizations", &app.url),
+        &data.token,
+        &json!({"name": "Acme Hosting"}),
+    )
+    .await
+    .json::<Response<ApiOrganization>>()
+    .await
+    .unwrap()
+    .result;
+    let organization_endpoint = format!("{}/organizations/{}", &app.url, organization.id);
+    requests::put_response(
+        &app,
+        &format!("{organization_endpoint}/members"),
+        &data.token,
+        &json!({"email": "jane.doe@example.com", "role": "owner"}),
+    )
+    .await;
+    requests::post_response(
+        &app,
+        &format!("{organization_endpoint}/servers"),
+        &data.token,
+        &json!({"server_id": server.server_id}),
+    )
+    .await;
+    let server_endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let as_member = requests::get_response(&app, &server_endpoint, &owner.token).await;
+
+    // Act
+    let removed = requests::delete_response(
+        &app,
+        &format!("{organization_endpoint}/members/{}", data.user_id),
+        &owner.token,
+    )
+    .await;
+    let as_removed = requests::get_response(&app, &server_endpoint, &data.token).await;
+    let as_owner = requests::get_response(&app, &server_endpoint, &owner.token).await;
+    let shared = requests::get_response(
+        &app,
+        &format!("{organization_endpoint}/servers"),
+        &owner.token,
+    )
+    .await
+    .json::<Response<Vec<ApiServer>>>()
+    .await
+    .unwrap()
+    .result;
+
+    // Assert
+    assert!(as_member.status().is_success());
+    assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+    assert_eq!(as_removed.status(), StatusCode::NOT_FOUND);
+    assert!(as_owner.status().is_success());
+    assert_eq!(shared.len(), 1);
+    assert_eq!(shared[0].server_id, server.server_id);
+}
@@ -0,0 +1,374 @@
+use dashboard_server::model::types::{ApiCustomField, ApiProduct, ApiProductGroup, ApiTemplate};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{MockProxmoxClient, TestApp, TestData, database, requests};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn admin_should_create_product_visible_in_catalog(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let catalog = format!("{}/api/products", &app.url);
+    let cached = requests::get_response(&app, &catalog, &data.token)
+        .await
+        .json::<Response<Vec<ApiProduct>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Act
+    let group = requests::post_response(
+        &app,
+        &format!("{}/admin/product-groups", &app.url),
+        &data.token,
+        &json!({ "name": "VPS" }),
+    )
+    .await
+    .json::<Response<ApiProductGroup>>()
+    .await
+    .unwrap()
+    .result;
+    let product = requests::post_response(
+        &app,
+        &format!("{}/admin/products", &app.url),
+        &data.token,
+        &json!({ "group_id": group.id, "name": " VPS S ", "net_rate_mbps": 100 }),
+    )
+    .await
+    .json::<Response<ApiProduct>>()
+    .await
+    .unwrap()
+    .result;
+    let field = requests::post_response(
+        &app,
+        &format!("{}/admin/products/{}/custom-fields", &app.url, product.id),
+        &data.token,
+        &json!({ "name": "OS Template", "options": ["ubuntu-22.04", "ubuntu-22.04"] }),
+    )
+    .await
+    .json::<Response<ApiCustomField>>()
+    .await
+    .unwrap()
+    .result;
+    let products = requests::get_response(&app, &catalog, &data.token)
+        .await
+        .json::<Response<Vec<ApiProduct>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(product.name, "VPS S");
+    assert_eq!(product.group_id, group.id);
+    assert_eq!(field.product_id, product.id);
+    assert_eq!(field.options, vec!["ubuntu-22.04".to_owned()]);
+    assert_eq!(products.len(), cached.len() + 1);
+    assert!(products.contains(&product));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn custom_field_with_unknown_template_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!(
+        "{}/admin/products/{}/custom-fields",
+        &app.url, data.product_id
+    );
+
+    // Act
+    let unknown_template = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({ "name": "OS Template", "options": ["ubuntu-22.04", "plan9"] }),
+    )
+    .await;
+    let unknown_datacenter = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({ "name": "Datacenter Location", "options": ["Atlantis"] }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(unknown_template.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(unknown_datacenter.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn ordered_product_should_not_be_deleted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/admin/products/{}", &app.url, data.product_id);
+
+    // Act
+    let response = requests::delete_response(&app, &endpoint, &data.token).await;
+    let product = requests::get_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(product.status(), StatusCode::OK);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn catalog_management_should_require_admin(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/admin/products/{}", &app.url, data.product_id);
+
+    // Act
+    let response = requests::delete_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn renamed_template_should_be_renamed_in_custom_fields(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let template = requests::post_response(
+        &app,
+        &format!("{}/admin/templates", &app.url),
+        &data.token,
+        &json!({ "os_name": "debian-12", "template_vmid": 9001, "template_node": "pve" }),
+    )
+    .await
+    .json::<Response<ApiTemplate>>()
+    .await
+    .unwrap()
+    .result;
+    let fields = format!(
+        "{}/admin/products/{}/custom-fields",
+        &app.url, data.product_id
+    );
+    requests::post_response(
+        &app,
+        &fields,
+        &data.token,
+        &json!({ "name": "OS Template", "options": ["ubuntu-22.04", "debian-12"] }),
+    )
+    .await;
+
+    // Act
+    let renamed = requests::put_response(
+        &app,
+        &format!("{}/admin/templates/{}", &app.url, template.id),
+        &data.token,
+        &json!({ "os_name": "debian-12.5", "template_vmid": 9001, "template_node": "pve" }),
+    )
+    .await
+    .json::<Response<ApiTemplate>>()
+    .await
+    .unwrap()
+    .result;
+    let field = requests::get_response(&app, &fields, &data.token)
+        .await
+        .json::<Response<Vec<ApiCustomField>>>()
+        .await
+        .unwrap()
+        .result
+        .into_iter()
+        .find(|field| field.name == "OS Template")
+        .unwrap();
+
+    // Assert
+    assert_eq!(template.virtual_type, "qemu");
+    assert_eq!(renamed.os_name, "debian-12.5");
+    assert_eq!(field.options, vec!["ubuntu-22.04", "debian-12.5"]);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn template_should_be_rejected_unless_proxmox_has_it(pool: PgPool) {
+    // Arrange
+    let missing = TestApp::with_proxmox(
+        pool.clone(),
+        Arc::new(MockProxmoxClient {
+            missing_vm: true,
+            ..MockProxmoxClient::default()
+        }),
+    )
+    .await;
+    let regular = TestApp::with_proxmox(
+        pool.clone(),
+        Arc::new(MockProxmoxClient {
+            not_template: true,
+            ..MockProxmoxClient::default()
+        }),
+    )
+    .await;
+    let data = TestData::new(&missing, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let payload = json!({ "os_name": "debian-12", "template_vmid": 9001, "template_node": "pve" });
+    let duplicate =
+        json!({ "os_name": "debian-12", "template_vmid": 9000, "template_node": "pve" });
+
+    // Act
+    let missing_vm = requests::post_response(
+        &missing,
+        &format!("{}/admin/templates", &missing.url),
+        &data.token,
+        &payload,
+    )
+    .await;
+    let not_template = requests::post_response(
+        &regular,
+        &format!("{}/admin/templates", &regular.url),
+        &data.token,
+        &payload,
+    )
+    .await;
+    let app = TestApp::new(pool.clone()).await;
+    let taken_vmid = requests::post_response(
+        &app,
+        &format!("{}/admin/templates", &app.url),
+        &data.token,
+        &duplicate,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(missing_vm.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(not_template.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(taken_vmid.status(), StatusCode::CONFLICT);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn template_in_use_should_not_be_deleted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    data.create_server(&app, &pool).await;
+    let template =
+        requests::get_response(&app, &format!("{}/admin/templates", &app.url), &data.token)
+            .await
+            .json::<Response<Vec<ApiTemplate>>>()
+            .await
+            .unwrap()
+            .result
+            .remove(0);
+    let endpoint = format!("{}/admin/templates/{}", &app.url, template.id);
+
+    // Act
+    let response = requests::delete_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn template_storage_should_hold_vm_disks(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/templates", &app.url);
+
+    // Act
+    let iso_storage = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({ "os_name": "debian-12", "template_vmid": 9001, "template_node": "pve", "storage": "local" }),
+    )
+    .await;
+    let template = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({ "os_name": "debian-12", "template_vmid": 9001, "template_node": "pve", "storage": "local-zfs" }),
+    )
+    .await
+    .json::<Response<ApiTemplate>>()
+    .await
+    .unwrap()
+    .result;
+
+    // Assert
+    assert_eq!(iso_storage.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(template.storage.as_deref(), Some("local-zfs"));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn product_storage_should_hold_vm_disks(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/products/{}", &app.url, data.product_id);
+    let product = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiProduct>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Act
+    let mut statuses = Vec::new();
+    for storage in ["local", "local-zsf", "local-zfs"] {
+        let response = requests::put_response(
+            &app,
+            &endpoint,
+            &data.token,
+            &json!({ "group_id": product.group_id, "name": product.name, "storage": storage }),
+        )
+        .await;
+        statuses.push(response.status());
+    }
+
+    // Assert
+    assert_eq!(
+        statuses,
+        vec![
+            StatusCode::BAD_REQUEST,
+            StatusCode::BAD_REQUEST,
+            StatusCode::OK
+        ]
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn server_should_be_cloned_to_product_storage(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/products/{}", &app.url, data.product_id);
+    let product = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiProduct>>()
+        .await
+        .unwrap()
+        .result;
+    requests::put_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({ "group_id": product.group_id, "name": product.name, "storage": "local-zfs" }),
+    )
+    .await;
+
+    // Act
+    data.create_server(&app, &pool).await;
+
+    // Assert
+    assert_eq!(
+        *proxmox.cloned_to.lock().unwrap(),
+        vec![Some("local-zfs".to_owned())]
+    );
+}
@@ -0,0 +1,228 @@
+use axum::http::StatusCode;
+use dashboard_server::model::types::{ApiOrganization, ApiSamlSettings};
+use dashboard_server::web::types::{Response, SsoAuthorization};
+use dashboard_testing::{TestApp, TestData, UserBuilder, payload, requests};
+use reqwest::Url;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+
+const IDP_CERTIFICATE: &str = "\
+MIIDFTCCAf2gAwIBAgIUSKbYKlEfRtWvyyv2TAOqOUSB5eswDQYJKoZIhvcNAQELBQAwGjEYMBYG\
+A1UEAwwPaWRwLmV4YW1wbGUuY29tMB4XDTI2MTAxNjExNTYzMFoXDTM2MTAxMzExNTYzMFowGjEY\
+MBYGA1UEAwwPaWRwLmV4YW1wbGUuY29tMIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA\
+t/Ar4QOoBFL6V6TTTDNiPejxGlfT0RgMomE1xiXJ1dEGr2P143N+wIOxfns1diydcYlBbIZ6JH7y\
+N7g/k7DuLRBRkkHaLoXmzXSjYHqoJi6ajsrgwPuHZEPrsLY3lxl6uTu9JQFUe2Ns02L6xcYNRXY9\
+3mUvdaecW2OHViTiGNKo8O6UeTqYeh37OZ623bDUGSrkmZDi9Ha3fi6pWkab+fP4s6BuRa6XV00X\
+/8EgWjbQyKzWMIlq6txuN82+Bg7mK7iD7ZI+ceAzmwRcNM3nNuwGpTnkU5scOh/BL6GiUtsuIreo\
+SfumsoDaKF3Dhaq4oiwZZQ5us4ktakFvKiJKuwIDAQABo1MwUTAdBgNVHQ4EFgQU+9RBziUHyOWo\
+iqWnWZYH+lUYk/wwHwYDVR0jBBgwFoAU+9RBziUHyOWoiqWnWZYH+lUYk/wwDwYDVR0TAQH/BAUw\
+AwEB/zANBgkqhkiG9w0BAQsFAAOCAQEAREM6ySmbqvjjHlKhonNM+oRlo7ZPKu0frxg8YJAw2Sus\
+q3MDKNDI7JskjyibFqXUDVvnhaLcIcps/SYqp6n+3O69cKbJznjKhY6ET68CSQNj+o27lAIc5dJ1\
+EdyV+rSYQgKOCyXGf38t44BYtq0MGkVcQPJ99EWnqK0NDXhZ9NHvOd0dru0hxVwJMQmChNKWoiN8\
+3Ktjm8Fdmnq+HJPVYi3Jho7GXn5CanQmQCSl6FKhMXE5VkCgWjvp/q1t8Y2R99wN8NfNpH7j7p6u\
+CvMd0kC8r+KF3EvhtYYh3SbLwMFoP3lMmqS6DzFw4sSIzb9B4lAvshIy+v+Z/bouAz33Pw==";
+
+fn idp_metadata() -> String {
+    format!(
+        r#"<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="https://idp.example.com">
+  <md:IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+    <md:KeyDescriptor use="signing">
+      <ds:KeyInfo xmlns:ds="http://www.w3.org/2000/09/xmldsig#">
+        <ds:X509Data>
+          <ds:X509Certificate>{IDP_CERTIFICATE}</ds:X509Certificate>
+        </ds:X509Data>
+      </ds:KeyInfo>
+    </md:KeyDescriptor>
+    <md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://idp.example.com/sso"/>
+  </md:IDPSSODescriptor>
+</md:EntityDescriptor>"#
+    )
+}
+
+/// Creates a shared organization of the test user.
+///
+async fn create_organization(app: &TestApp, data: &TestData) -> ApiOrganization {
+    requests::post_response(
+        app,
+        &format!("{}/organizations", &app.url),
+        &data.token,
+        &json!({ "name": "Acme Corp" }),
+    )
+    .await
+    .json::<Response<ApiOrganization>>()
+    .await
+    .unwrap()
+    .result
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn owner_should_set_up_saml_and_start_logins(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let organization = create_organization(&app, &data).await;
+    let personal =
+        requests::get_response(&app, &format!("{}/organizations", &app.url), &data.token)
+            .await
+            .json::<Response<Vec<ApiOrganization>>>()
+            .await
+            .unwrap()
+            .result
+            .remove(0);
+    let endpoint = |organization_id| format!("{}/organizations/{organization_id}/saml", &app.url);
+    let settings = |idp_metadata: String| {
+        json!({
+            "idp_metadata": idp_metadata,
+            "role_attribute": "groups",
+            "admin_values": ["dashboard-admins"],
+        })
+    };
+
+    // Act
+    let invalid = requests::put_response(
+        &app,
+        &endpoint(organization.id),
+        &data.token,
+        &settings("<md:EntityDescriptor".to_owned()),
+    )
+    .await;
+    let in_personal = requests::put_response(
+        &app,
+        &endpoint(personal.id),
+        &data.token,
+        &settings(idp_metadata()),
+    )
+    .await;
+    let saved = requests::put_response(
+        &app,
+        &endpoint(organization.id),
+        &data.token,
+        &settings(idp_metadata()),
+    )
+    .await
+    .json::<Response<ApiSamlSettings>>()
+    .await
+    .unwrap()
+    .result;
+    let metadata = app
+        .client
+        .get(format!("{}/saml/{}/metadata", &app.url, organization.id))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let authorization = app
+        .client
+        .get(format!("{}/saml/{}/login", &app.url, organization.id))
+        .send()
+        .await
+        .unwrap()
+        .json::<Response<SsoAuthorization>>()
+        .await
+        .unwrap()
+        .result;
+    let removed = requests::delete_response(&app, &endpoint(organization.id), &data.token).await;
+    let without_saml = app
+        .client
+        .get(format!("{}/saml/{}/login", &app.url, organization.id))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(in_personal.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(saved.admin_values, ["dashboard-admins"]);
+    assert!(!saved.enforced);
+    assert!(metadata.contains(&format!("/saml/{}/acs", organization.id)));
+    let url = Url::parse(&authorization.url).unwrap();
+    assert_eq!(url.host_str(), Some("idp.example.com"));
+    assert!(url.query_pairs().any(|(key, _)| key == "SAMLRequest"));
+    assert!(url.query_pairs().any(|(key, _)| key == "RelayState"));
+    assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+    assert_eq!(without_saml.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn enforced_saml_should_reject_other_logins_of_members(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let organization = create_organization(&app, &data).await;
+    let member = || UserBuilder::new().email("jane.doe@example.com");
+    member().register(&app, &pool).await;
+    let organization_endpoint = format!("{}/organizations/{}", &app.url, organization.id);
+    requests::put_response(
+        &app,
+        &format!("{organization_endpoint}/members"),
+        &data.token,
+        &json!({ "email": "jane.doe@example.com", "role": "member" }),
+    )
+    .await;
+    let enforced = requests::put_response(
+        &app,
+        &format!("{organization_endpoint}/saml"),
+        &data.token,
+        &json!({ "idp_metadata": idp_metadata(), "enforced": true }),
+    )
+    .await;
+    let login = |payload: Value| {
+        app.client
+            .post(format!("{}/login", &app.url))
+            .json(&payload)
+            .send()
+    };
+    let acs_endpoint = format!("{}/saml/{}/acs", &app.url, organization.id);
+    let relay_state = |url: &str| {
+        Url::parse(url)
+            .unwrap()
+            .query_pairs()
+            .find(|(key, _)| key == "RelayState")
+            .map(|(_, value)| value.into_owned())
+            .unwrap()
+    };
+
+    // Act
+    let member_login = login(member().login_payload()).await.unwrap();
+    let owner_login = login(payload::login_user()).await.unwrap();
+    let authorization = app
+        .client
+        .get(format!("{}/saml/{}/login", &app.url, organization.id))
+        .send()
+        .await
+        .unwrap()
+        .json::<Response<SsoAuthorization>>()
+        .await
+        .unwrap()
+        .result;
+    let request_id = relay_state(&authorization.url);
+    let unsigned = app
+        .client
+        .post(&acs_endpoint)
+        .form(&[
+            ("SAMLResponse", "PHNhbWxwOlJlc3BvbnNlLz4="),
+            ("RelayState", request_id.as_str()),
+        ])
+        .send()
+        .await
+        .unwrap();
+    let replayed = app
+        .client
+        .post(&acs_endpoint)
+        .form(&[
+            ("SAMLResponse", "PHNhbWxwOlJlc3BvbnNlLz4="),
+            ("RelayState", request_id.as_str()),
+        ])
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(enforced.status(), StatusCode::OK);
+    assert_eq!(member_login.status(), StatusCode::FORBIDDEN);
+    assert_eq!(owner_login.status(), StatusCode::OK);
+    assert_eq!(unsigned.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(replayed.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,120 @@
+use dashboard_server::model::types::ApiSearchResults;
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, UserBuilder, database, requests};
+use reqwest::StatusCode;
+use sqlx::PgPool;
+
+/// Searches as the admin.
+///
+async fn search(app: &TestApp, token: &str, term: &str) -> ApiSearchResults {
+    let endpoint = format!("{}/admin/search?q={term}", &app.url);
+    requests::get_response(app, &endpoint, token)
+        .await
+        .json::<Response<ApiSearchResults>>()
+        .await
+        .unwrap()
+        .result
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn search_should_group_matches_by_kind(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/admin/search?q=TEST-SERVER", &app.url);
+
+    // Act
+    let results = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiSearchResults>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert!(results.users.is_empty());
+    assert_eq!(results.servers.len(), 1);
+    assert_eq!(results.servers[0].id, server.server_id);
+    assert_eq!(results.services.len(), 1);
+    assert_eq!(results.services[0].id, server.service_id);
+    assert_eq!(results.services[0].user_id, data.user_id);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn search_should_find_users_by_email(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let endpoint = format!("{}/admin/search?q=doe.reqwest", &app.url);
+    let short = format!("{}/admin/search?q=a", &app.url);
+
+    // Act
+    let results = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiSearchResults>>()
+        .await
+        .unwrap()
+        .result;
+    let rejected = requests::get_response(&app, &short, &data.token).await;
+
+    // Assert
+    assert_eq!(results.users.len(), 1);
+    assert_eq!(results.users[0].id, data.user_id);
+    assert!(results.servers.is_empty());
+    assert_eq!(rejected.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn search_should_compare_ids_exactly(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+
+    // Act
+    let by_vm_id = search(&app, &data.token, &server.vm_id.unwrap().to_string()).await;
+    let by_ip_address = search(&app, &data.token, &server.ip_address).await;
+    let by_service_id = search(&app, &data.token, &server.service_id.to_string()).await;
+    let by_user_id = search(&app, &data.token, &data.user_id.to_string()).await;
+
+    // Assert
+    assert_eq!(by_vm_id.servers[0].id, server.server_id);
+    assert_eq!(by_ip_address.servers[0].id, server.server_id);
+    assert_eq!(by_service_id.services.len(), 1);
+    assert_eq!(by_service_id.services[0].id, server.service_id);
+    assert_eq!(by_user_id.users.len(), 1);
+    assert_eq!(by_user_id.users[0].id, data.user_id);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn search_should_skip_deleted_users(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let deleted = UserBuilder::new()
+        .email("deleted.reqwest@example.com")
+        .register(&app, &pool)
+        .await;
+    sqlx::query!(
+        r#"
+UPDATE users
+SET deleted_at = NOW()
+WHERE id = $1
+		"#,
+        deleted.user_id
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Act
+    let results = search(&app, &data.token, "deleted.reqwest").await;
+
+    // Assert
+    assert!(results.users.is_empty());
+}
@@ -0,0 +1,64 @@
+use axum::http::StatusCode;
+use dashboard_server::config::{Config, SecurityEnv};
+use dashboard_testing::TestApp;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn responses_should_carry_configured_security_headers(pool: PgPool) {
+    // Arrange
+    let config = Config {
+        security: SecurityEnv {
+            hsts_include_subdomains: false,
+            referrer_policy: "strict-origin".to_owned(),
+            ..SecurityEnv::default()
+        },
+        ..Config::default()
+    };
+    let app = TestApp::with_config(pool, config).await;
+
+    // Act
+    let response = app
+        .client
+        .get(format!("{}/servers", &app.url))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let headers = response.headers();
+    assert_eq!(headers["strict-transport-security"], "max-age=31536000");
+    assert_eq!(headers["x-content-type-options"], "nosniff");
+    assert_eq!(headers["referrer-policy"], "strict-origin");
+    assert_eq!(headers["content-security-policy"], "frame-ancestors 'none'");
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn disabled_security_should_leave_responses_unchanged(pool: PgPool) {
+    // Arrange
+    let config = Config {
+        security: SecurityEnv {
+            enabled: false,
+            ..SecurityEnv::default()
+        },
+        ..Config::default()
+    };
+    let app = TestApp::with_config(pool, config).await;
+
+    // Act
+    let response = app
+        .client
+        .get(format!("{}/servers", &app.url))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(
+        response
+            .headers()
+            .get("strict-transport-security")
+            .is_none()
+    );
+    assert!(response.headers().get("content-security-policy").is_none());
+}
@@ -1,10 +1,23 @@
-use crate::helpers::{TestApp, TestData, payload, requests};
 use axum::http::StatusCode;
+use dashboard_server::clock::Clock;
+use dashboard_server::config::RuntimeEnv;
 use dashboard_server::model::queries;
-use dashboard_server::model::types::{ApiServer, ServerStatus};
-use dashboard_server::web::types::{Response, TokenPayload};
+use dashboard_server::model::types::{
+    ApiActionResult, ApiBackup, ApiBackupSchedule, ApiFirewall, ApiFirewallRule, ApiHealth,
+    ApiProvisioningStep, ApiQuotas, ApiServer, ApiServerTag, ApiTimelineEvent, BackupMode,
+    FirewallAction, ProvisioningStepStatus, QuotaLimits, ServerStatus, TimelineEventKind,
+};
+use dashboard_server::services::{action, backup, setup, status};
+use dashboard_server::web::types::{BulkActionPayload, Response, ServerAction, TokenPayload};
+use dashboard_testing::{
+    MockProxmoxClient, ServerBuilder, TestApp, TestData, database, payload, requests,
+};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use serde_json::json;
 use sqlx::PgPool;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use uuid::Uuid;
 
 #[sqlx::test(migrations = "../../migrations")]
 async fn server_list_for_new_user_should_be_empty(pool: PgPool) {
@@ -66,6 +79,27 @@ async fn get_server_should_works(pool: PgPool) {
     assert_eq!(server.status, ServerStatus::Stopped);
 }
 
+#[sqlx::test(migrations = "../../migrations")]
+async fn get_server_should_show_net_rate(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::set_product_net_rate(&pool, data.product_id, 100).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+
+    // Act
+    let endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let server = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServer>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(server.net_rate_mbps, Some(100));
+}
+
 #[sqlx::test(migrations = "../../migrations")]
 async fn list_servers_should_works(pool: PgPool) {
     // Arrange
@@ -104,7 +138,7 @@ async fn server_action_should_works(pool: PgPool) {
     let endpoint = format!("{}/servers/{}/actions", &app.url, server_id);
     let response = requests::post_response(&app, &endpoint, &data.token, &action_payload).await;
 
-    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    database::wait_for_status(&pool, server_id, ServerStatus::Running).await;
     let status_after = queries::get_servers_for_user(&pool, data.user_id)
         .await
         .unwrap()
@@ -126,7 +160,6 @@ async fn delete_server_should_works(pool: PgPool) {
     let (_, server) = data.create_server(&app, &pool).await;
     let server_id = server.server_id;
 
-    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     let servers_before = queries::get_servers_for_user(&pool, data.user_id)
         .await
         .unwrap();
@@ -135,7 +168,7 @@ async fn delete_server_should_works(pool: PgPool) {
     let endpoint = format!("{}/servers/{}", &app.url, server_id);
     let response = requests::delete_response(&app, &endpoint, &data.token).await;
 
-    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    database::wait_for_deletion(&pool, server_id).await;
     let servers_after = queries::get_servers_for_user(&pool, data.user_id)
         .await
         .unwrap();
@@ -145,3 +178,1206 @@ async fn delete_server_should_works(pool: PgPool) {
     assert!(!servers_before.is_empty());
     assert!(servers_after.is_empty());
 }
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn create_server_over_quota_should_be_forbidden(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let limits = QuotaLimits {
+        max_cpu_cores: Some(1),
+        ..QuotaLimits::default()
+    };
+    queries::set_account_quota(&pool, data.user_id, &limits)
+        .await
+        .unwrap();
+
+    // Act
+    let endpoint = format!("{}/servers", &app.url);
+    let payload = payload::new_server(data.product_id);
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let message = response.text().await.unwrap();
+    assert!(message.contains("vCPU"));
+    let servers = queries::get_servers_for_user(&pool, data.user_id)
+        .await
+        .unwrap();
+    assert!(servers.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn quota_should_show_usage_against_limits(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let limits = QuotaLimits {
+        max_servers: Some(1),
+        ..QuotaLimits::default()
+    };
+    queries::set_product_quota(&pool, data.product_id, &limits)
+        .await
+        .unwrap();
+    data.create_server(&app, &pool).await;
+
+    // Act
+    let endpoint = format!("{}/me/quota", &app.url);
+    let quotas = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiQuotas>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(quotas.account.servers.used, 1);
+    assert_eq!(quotas.account.cpu_cores.used, 2);
+    assert_eq!(quotas.account.ips.used, 1);
+    assert_eq!(quotas.account.servers.limit, Some(10));
+    assert_eq!(quotas.products.len(), 1);
+    assert_eq!(quotas.products[0].product_id, Some(data.product_id));
+    assert_eq!(quotas.products[0].servers.used, 1);
+    assert_eq!(quotas.products[0].servers.limit, Some(1));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn additional_ip_should_be_added_and_released(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    database::add_ip_address(&pool, "192.168.0.101").await;
+    let endpoint = format!("{}/servers/{}/ips", &app.url, server.server_id);
+
+    // Act
+    let added = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<ApiServer>>()
+        .await
+        .unwrap()
+        .result;
+    let endpoint = format!("{}/192.168.0.101", endpoint);
+    let released = requests::delete_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiServer>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(added.ip_address, server.ip_address);
+    assert_eq!(added.additional_ips, ["192.168.0.101"]);
+    assert!(released.additional_ips.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn failed_additional_ip_should_return_to_pool(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    database::add_ip_address(&pool, "192.168.0.101").await;
+    proxmox.fail_times("vm_config", 1);
+    let endpoint = format!("{}/servers/{}/ips", &app.url, server.server_id);
+
+    // Act
+    let failed = requests::post_response(&app, &endpoint, &data.token, &json!({})).await;
+    let added = requests::post_response(&app, &endpoint, &data.token, &json!({}))
+        .await
+        .json::<Response<ApiServer>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(failed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(added.additional_ips, ["192.168.0.101"]);
+    assert_eq!(added.status, server.status);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn additional_ip_over_quota_should_be_forbidden(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    database::add_ip_address(&pool, "192.168.0.101").await;
+    let limits = QuotaLimits {
+        max_ips: Some(1),
+        ..QuotaLimits::default()
+    };
+    queries::set_account_quota(&pool, data.user_id, &limits)
+        .await
+        .unwrap();
+
+    // Act
+    let endpoint = format!("{}/servers/{}/ips", &app.url, server.server_id);
+    let response = requests::post_response(&app, &endpoint, &data.token, &json!({})).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert!(server.additional_ips.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn firewall_rule_should_be_added_and_deleted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/firewall", &app.url, server.server_id);
+    let settings = json!({"enabled": true, "policy_in": "drop"});
+    let rule = json!({
+        "direction": "in",
+        "action": "accept",
+        "protocol": "tcp",
+        "port": 22,
+        "source": "10.0.0.0/24"
+    });
+
+    // Act
+    let enabled = requests::put_response(&app, &endpoint, &data.token, &settings)
+        .await
+        .json::<Response<ApiFirewall>>()
+        .await
+        .unwrap()
+        .result;
+    let rules_endpoint = format!("{}/rules", endpoint);
+    let added = requests::post_response(&app, &rules_endpoint, &data.token, &rule)
+        .await
+        .json::<Response<ApiFirewallRule>>()
+        .await
+        .unwrap()
+        .result;
+    let listed = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<ApiFirewall>>()
+        .await
+        .unwrap()
+        .result;
+    let rule_endpoint = format!("{}/{}", rules_endpoint, added.id);
+    let deleted = requests::delete_response(&app, &rule_endpoint, &data.token).await;
+    let deleted_again = requests::delete_response(&app, &rule_endpoint, &data.token).await;
+
+    // Assert
+    assert!(enabled.enabled);
+    assert_eq!(enabled.policy_in, FirewallAction::Drop);
+    assert_eq!(added.port, 22);
+    assert_eq!(listed.rules.len(), 1);
+    assert_eq!(listed.rules[0].id, added.id);
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert_eq!(deleted_again.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn firewall_rule_with_invalid_port_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let rule = json!({
+        "direction": "in",
+        "action": "accept",
+        "protocol": "udp",
+        "port": 70000
+    });
+
+    // Act
+    let endpoint = format!("{}/servers/{}/firewall/rules", &app.url, server.server_id);
+    let response = requests::post_response(&app, &endpoint, &data.token, &rule).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let firewall = requests::get_response(
+        &app,
+        &format!("{}/servers/{}/firewall", &app.url, server.server_id),
+        &data.token,
+    )
+    .await
+    .json::<Response<ApiFirewall>>()
+    .await
+    .unwrap()
+    .result;
+    assert!(firewall.rules.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn backup_schedule_should_be_saved_and_deleted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/backups/schedule", &app.url, server.server_id);
+    let payload = json!({"cron": "30 2 * * *", "mode": "stop"});
+
+    // Act
+    let saved = requests::put_response(&app, &endpoint, &data.token, &payload)
+        .await
+        .json::<Response<ApiBackupSchedule>>()
+        .await
+        .unwrap()
+        .result;
+    let found = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Option<ApiBackupSchedule>>>()
+        .await
+        .unwrap()
+        .result;
+    let deleted = requests::delete_response(&app, &endpoint, &data.token).await;
+    let deleted_again = requests::delete_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(saved.cron, "30 2 * * *");
+    assert_eq!(saved.mode, BackupMode::Stop);
+    assert!(saved.last_run_at.is_none());
+    assert_eq!(found.unwrap().next_run_at, saved.next_run_at);
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert_eq!(deleted_again.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn frequent_backup_schedule_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/backups/schedule", &app.url, server.server_id);
+
+    // Act
+    let frequent = json!({"cron": "*/5 * * * *"});
+    let frequent = requests::put_response(&app, &endpoint, &data.token, &frequent).await;
+    let invalid = json!({"cron": "every night"});
+    let invalid = requests::put_response(&app, &endpoint, &data.token, &invalid).await;
+
+    // Assert
+    assert_eq!(frequent.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn backups_should_be_listed(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    proxmox
+        .backup_notes
+        .lock()
+        .unwrap()
+        .push(backup::notes(server.server_id));
+
+    // Act
+    let endpoint = format!("{}/servers/{}/backups", &app.url, server.server_id);
+    let backups = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiBackup>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0].volid.starts_with("local:backup/vzdump-qemu-"));
+    assert!(backups[0].volid.ends_with("-02_30_01.vma.zst"));
+    assert_eq!(backups[0].size_bytes, 1073741824);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn backup_should_be_restored(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    proxmox
+        .backup_notes
+        .lock()
+        .unwrap()
+        .push(backup::notes(server.server_id));
+    let endpoint = format!("{}/servers/{}/backups", &app.url, server.server_id);
+    let backups = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiBackup>>>()
+        .await
+        .unwrap()
+        .result;
+    let volid = utf8_percent_encode(&backups[0].volid, NON_ALPHANUMERIC);
+
+    // Act
+    let endpoint = format!("{}/{}/restore", endpoint, volid);
+    let response = requests::post_response(&app, &endpoint, &data.token, &json!({})).await;
+    database::wait_for_status(&pool, server.server_id, ServerStatus::Running).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Running);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn restore_of_foreign_backup_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let volid = utf8_percent_encode(
+        "local:backup/vzdump-qemu-999-2026_10_16-02_30_00.vma.zst",
+        NON_ALPHANUMERIC,
+    );
+
+    // Act
+    let endpoint = format!(
+        "{}/servers/{}/backups/{}/restore",
+        &app.url, server.server_id, volid
+    );
+    let response = requests::post_response(&app, &endpoint, &data.token, &json!({})).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn provisioning_steps_should_be_completed(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/provisioning", &app.url, server.server_id);
+
+    // Act
+    let steps = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiProvisioningStep>>>()
+        .await
+        .unwrap()
+        .result;
+    let endpoint = format!("{}/retry", endpoint);
+    let retry = requests::post_response(&app, &endpoint, &data.token, &json!({})).await;
+
+    // Assert
+    assert_eq!(steps.len(), 4);
+    assert!(
+        steps
+            .iter()
+            .all(|step| step.status == ProvisioningStepStatus::Completed)
+    );
+    assert!(steps.iter().all(|step| step.attempts == 1));
+    assert_eq!(retry.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn failed_provisioning_should_resume_from_failed_step(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    database::fail_provisioning_step(&pool, server.server_id, "ConfigureVm").await;
+    let endpoint = format!("{}/servers/{}/provisioning", &app.url, server.server_id);
+
+    // Act
+    let retry = setup::retry(&app.state, data.user_id, server.server_id).await;
+    setup::provision(app.state.clone(), data.user_id, server.server_id).await;
+
+    // Assert
+    assert!(retry.is_ok());
+    let steps = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiProvisioningStep>>>()
+        .await
+        .unwrap()
+        .result;
+    let attempts = steps.iter().map(|step| step.attempts).collect::<Vec<_>>();
+    assert_eq!(attempts, [1, 2, 2, 2]);
+    assert!(
+        steps
+            .iter()
+            .all(|step| step.status == ProvisioningStepStatus::Completed)
+    );
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Stopped);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn interrupted_provisioning_step_should_fail_for_retry(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    database::interrupt_provisioning_step(&pool, server.server_id, "ConfigureVm").await;
+    let step_timeout = app.state.config.provisioning.step_timeout_min as u64 * 60;
+
+    // Act
+    let early = setup::sweep_stale(&app.state, app.clock.now())
+        .await
+        .unwrap();
+    app.clock
+        .advance(std::time::Duration::from_secs(step_timeout + 60));
+    let late = setup::sweep_stale(&app.state, app.clock.now())
+        .await
+        .unwrap();
+    let retry = setup::retry(&app.state, data.user_id, server.server_id).await;
+
+    // Assert
+    assert_eq!(early, 0);
+    assert_eq!(late, 1);
+    assert!(retry.is_ok());
+    let steps = queries::get_provisioning_steps(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(steps[1].status, ProvisioningStepStatus::Pending);
+    assert!(steps[1].error.as_ref().unwrap().contains("interrupted"));
+    assert_eq!(steps[2].status, ProvisioningStepStatus::Pending);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn transient_config_failure_should_recover_on_retry(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    proxmox.fail_times("vm_config", 1);
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!(
+        "{}/servers/{}/provisioning/retry",
+        &app.url, server.server_id
+    );
+
+    // Act
+    let response = requests::post_response(&app, &endpoint, &data.token, &json!({})).await;
+    database::wait_for_status(&pool, server.server_id, ServerStatus::Stopped).await;
+
+    // Assert
+    assert_eq!(server.status, ServerStatus::Failed);
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(proxmox.call_count("create"), 1);
+    assert_eq!(proxmox.call_count("vm_config"), 2);
+    assert!(!proxmox.calls().contains(&"delete"));
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Stopped);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn failed_provisioning_should_be_rolled_back_after_last_attempt(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient {
+        fail_config: true,
+        ..MockProxmoxClient::default()
+    });
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/provisioning", &app.url, server.server_id);
+    let steps = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiProvisioningStep>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Act
+    let retry_endpoint = format!("{}/retry", endpoint);
+    let first = requests::post_response(&app, &retry_endpoint, &data.token, &json!({})).await;
+    database::wait_for_status(&pool, server.server_id, ServerStatus::Failed).await;
+    let last = requests::post_response(&app, &retry_endpoint, &data.token, &json!({})).await;
+    database::wait_for_deletion(&pool, server.server_id).await;
+
+    // Assert
+    assert_eq!(server.status, ServerStatus::Failed);
+    assert_eq!(steps[0].status, ProvisioningStepStatus::Completed);
+    assert_eq!(steps[1].status, ProvisioningStepStatus::Failed);
+    assert!(
+        steps[1]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("mock config failure")
+    );
+    assert_eq!(first.status(), StatusCode::ACCEPTED);
+    assert_eq!(last.status(), StatusCode::ACCEPTED);
+    assert_eq!(*proxmox.deleted.lock().unwrap(), [101]);
+    let servers = queries::get_servers_for_user(&pool, data.user_id)
+        .await
+        .unwrap();
+    assert!(servers.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn operation_on_busy_server_should_conflict(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    queries::update_server_status(&pool, server.server_id, ServerStatus::Rebooting)
+        .await
+        .unwrap();
+    let endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+
+    // Act
+    let action_endpoint = format!("{}/actions", endpoint);
+    let action_payload = json!({ "action": "start" });
+    let action =
+        requests::post_response(&app, &action_endpoint, &data.token, &action_payload).await;
+    let deletion = requests::delete_response(&app, &endpoint, &data.token).await;
+    let ip_endpoint = format!("{}/ips", endpoint);
+    let ip = requests::post_response(&app, &ip_endpoint, &data.token, &json!({})).await;
+    let rule_endpoint = format!("{}/firewall/rules", endpoint);
+    let rule_payload =
+        json!({ "direction": "in", "action": "accept", "protocol": "tcp", "port": 22 });
+    let rule = requests::post_response(&app, &rule_endpoint, &data.token, &rule_payload).await;
+    let password_endpoint = format!("{}/password", endpoint);
+    let password = requests::post_response(&app, &password_endpoint, &data.token, &json!({})).await;
+
+    // Assert
+    assert_eq!(action.status(), StatusCode::CONFLICT);
+    assert!(action.text().await.unwrap().contains("'reboot'"));
+    assert_eq!(deletion.status(), StatusCode::CONFLICT);
+    assert_eq!(ip.status(), StatusCode::CONFLICT);
+    assert_eq!(rule.status(), StatusCode::CONFLICT);
+    assert_eq!(password.status(), StatusCode::CONFLICT);
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Rebooting);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn stuck_server_should_get_status_of_its_vm_back(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    queries::update_server_status(&pool, server.server_id, ServerStatus::Rebooting)
+        .await
+        .unwrap();
+    let stuck_after = app.state.config.action.stuck_after_min as u64 * 60;
+
+    // Act
+    let early = status::sync(&app.state, app.clock.now()).await.unwrap();
+    app.clock
+        .advance(std::time::Duration::from_secs(stuck_after + 60));
+    let late = status::sync(&app.state, app.clock.now()).await.unwrap();
+
+    // Assert
+    assert_eq!(early, 0);
+    assert_eq!(late, 1);
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Running);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn action_not_allowed_in_status_should_conflict(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    queries::update_server_status(&pool, server.server_id, ServerStatus::Stopped)
+        .await
+        .unwrap();
+    let endpoint = format!("{}/servers/{}/actions", &app.url, server.server_id);
+
+    // Act
+    let payload = json!({ "action": "reboot" });
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert!(
+        response
+            .text()
+            .await
+            .unwrap()
+            .contains("allowed actions: [start]")
+    );
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Stopped);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn bulk_action_should_return_result_per_server(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    queries::update_server_status(&pool, server.server_id, ServerStatus::Stopped)
+        .await
+        .unwrap();
+    let unknown_id = Uuid::new_v4();
+    let endpoint = format!("{}/servers/actions", &app.url);
+
+    // Act
+    let payload = json!({
+        "server_ids": [server.server_id, unknown_id, server.server_id],
+        "action": "start"
+    });
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+    let status = response.status();
+    let results = response
+        .json::<Response<Vec<ApiActionResult>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].server_id, server.server_id);
+    assert_eq!(results[0].status, Some(ServerStatus::Starting));
+    assert_eq!(results[1].server_id, unknown_id);
+    assert!(results[1].status.is_none());
+    assert!(results[1].error.is_some());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn bulk_action_job_should_finish_actions(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    queries::update_server_status(&pool, server.server_id, ServerStatus::Stopped)
+        .await
+        .unwrap();
+    let payload = BulkActionPayload {
+        server_ids: vec![server.server_id],
+        action: ServerAction::Start,
+    };
+
+    // Act
+    let (_, job) = action::start_bulk(&app.state, data.user_id, payload)
+        .await
+        .unwrap();
+    job.await.unwrap();
+
+    // Assert
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Running);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn stuck_action_should_time_out_on_service_clock(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    queries::update_server_status(&pool, server.server_id, ServerStatus::Stopped)
+        .await
+        .unwrap();
+    proxmox.pending_tasks.store(true, Ordering::Relaxed);
+    let started_at = app.clock.now();
+    let payload = BulkActionPayload {
+        server_ids: vec![server.server_id],
+        action: ServerAction::Start,
+    };
+
+    // Act
+    let (results, job) = action::start_bulk(&app.state, data.user_id, payload)
+        .await
+        .unwrap();
+    job.await.unwrap();
+
+    // Assert
+    assert_eq!(results[0].status, Some(ServerStatus::Starting));
+    assert!(app.clock.elapsed(started_at) >= std::time::Duration::from_secs(180));
+    let server = queries::get_server_by_id(&pool, data.user_id, server.server_id)
+        .await
+        .unwrap();
+    assert_eq!(server.status, ServerStatus::Stopped);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn missing_server_should_return_error_envelope(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}", &app.url, Uuid::new_v4());
+
+    // Act
+    let response = requests::get_response(&app, &endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let request_id = response.headers()["x-request-id"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let error = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(error["code"], "not_found");
+    assert_eq!(error["request_id"], request_id.as_str());
+    assert!(error["message"].is_string());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn action_on_missing_server_should_not_be_found(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/actions", &app.url, Uuid::new_v4());
+
+    // Act
+    let payload = json!({ "action": "start" });
+    let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn duplicate_host_name_should_conflict(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers", &app.url);
+    let mut payload = payload::new_server(data.product_id);
+
+    // Act
+    payload["host_name"] = json!(" Test-Server.EXAMPLE.com. ");
+    let duplicate = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+    payload["host_name"] = json!("test_server.example.com");
+    let invalid = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+
+    // Assert
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    let servers = queries::get_servers_for_user(&pool, data.user_id)
+        .await
+        .unwrap();
+    assert_eq!(servers.len(), 1);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn paused_server_orders_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let runtime_endpoint = format!("{}/admin/runtime", &app.url);
+    let runtime = json!({ "features": { "server_orders": false } });
+
+    // Act
+    let paused = requests::put_response(&app, &runtime_endpoint, &data.token, &runtime).await;
+    let endpoint = format!("{}/servers", &app.url);
+    let payload = payload::new_server(data.product_id);
+    let order = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+    let current = requests::get_response(&app, &runtime_endpoint, &data.token)
+        .await
+        .json::<Response<RuntimeEnv>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(paused.status(), StatusCode::OK);
+    assert_eq!(order.status(), StatusCode::CONFLICT);
+    assert!(!current.features.server_orders);
+    assert!(current.features.registration);
+    let servers = queries::get_servers_for_user(&pool, data.user_id)
+        .await
+        .unwrap();
+    assert!(servers.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn metrics_should_expose_pool_gauges(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool).await;
+    let endpoint = format!("{}/metrics", &app.url);
+
+    // Act
+    let response = requests::get_response(&app, &endpoint, "").await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("# TYPE db_pool_connections gauge"));
+    assert!(body.contains("db_pool_idle_connections "));
+    assert!(body.contains("db_pool_max_connections "));
+    assert!(body.contains("# TYPE proxmox_queue_wait_seconds_total counter"));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn healthz_should_report_schema_version(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let endpoint = format!("{}/healthz", &app.url);
+    // Already applied migrations are skipped.
+    queries::run_migrations(&pool.connect_options())
+        .await
+        .unwrap();
+
+    // Act
+    let response = requests::get_response(&app, &endpoint, "").await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let health = response.json::<Response<ApiHealth>>().await.unwrap().result;
+    assert_eq!(health.schema_version, Some(health.latest_version));
+    assert!(health.up_to_date);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn catalog_should_be_cached_until_invalidated(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::make_admin(&pool, data.user_id).await;
+    let products = format!("{}/api/products", &app.url);
+    let cache = format!("{}/admin/catalog/cache", &app.url);
+    let net_rate = async || {
+        requests::get_response(&app, &products, &data.token)
+            .await
+            .json::<Response<Vec<dashboard_server::model::types::ApiProduct>>>()
+            .await
+            .unwrap()
+            .result
+            .into_iter()
+            .find(|product| product.id == data.product_id)
+            .unwrap()
+            .net_rate_mbps
+    };
+
+    // Act
+    let first = net_rate().await;
+    database::set_product_net_rate(&pool, data.product_id, 100).await;
+    let cached = net_rate().await;
+    let invalidated = requests::delete_response(&app, &cache, &data.token).await;
+    let reloaded = net_rate().await;
+
+    // Assert
+    assert_eq!(first, None);
+    assert_eq!(cached, None);
+    assert_eq!(invalidated.status(), StatusCode::NO_CONTENT);
+    assert_eq!(reloaded, Some(100));
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn list_servers_should_filter_by_tags(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let server_endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let tagged = requests::put_response(
+        &app,
+        &format!("{server_endpoint}/tags"),
+        &data.token,
+        &json!({"tags": ["project:x", " env:prod", "env:prod"]}),
+    )
+    .await
+    .json::<Response<ApiServer>>()
+    .await
+    .unwrap()
+    .result;
+    let noted = requests::put_response(
+        &app,
+        &format!("{server_endpoint}/notes"),
+        &data.token,
+        &json!({"notes": "Primary database"}),
+    )
+    .await
+    .json::<Response<ApiServer>>()
+    .await
+    .unwrap()
+    .result;
+    let list_endpoint = format!("{}/servers", &app.url);
+
+    // Act
+    let matching = requests::get_response(
+        &app,
+        &format!("{list_endpoint}?tags=env:prod,project:x"),
+        &data.token,
+    )
+    .await
+    .json::<Response<Vec<ApiServer>>>()
+    .await
+    .unwrap()
+    .result;
+    let other = requests::get_response(
+        &app,
+        &format!("{list_endpoint}?tags=env:prod,project:y"),
+        &data.token,
+    )
+    .await
+    .json::<Response<Vec<ApiServer>>>()
+    .await
+    .unwrap()
+    .result;
+    let tags = requests::get_response(&app, &format!("{list_endpoint}/tags"), &data.token)
+        .await
+        .json::<Response<Vec<ApiServerTag>>>()
+        .await
+        .unwrap()
+        .result;
+
+    // Assert
+    assert_eq!(tagged.tags, ["env:prod", "project:x"]);
+    assert_eq!(noted.notes.as_deref(), Some("Primary database"));
+    assert_eq!(noted.tags, tagged.tags);
+    assert_eq!(matching.len(), 1);
+    assert_eq!(matching[0].server_id, server.server_id);
+    assert!(other.is_empty());
+    assert_eq!(
+        tags,
+        [
+            ApiServerTag {
+                tag: "env:prod".to_owned(),
+                servers: 1
+            },
+            ApiServerTag {
+                tag: "project:x".to_owned(),
+                servers: 1
+            }
+        ]
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn server_tags_should_be_validated_and_removed(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let tags_endpoint = format!("{}/servers/{}/tags", &app.url, server.server_id);
+    requests::put_response(
+        &app,
+        &tags_endpoint,
+        &data.token,
+        &json!({"tags": ["env:prod", "project:x"]}),
+    )
+    .await;
+
+    // Act
+    let invalid = requests::put_response(
+        &app,
+        &tags_endpoint,
+        &data.token,
+        &json!({"tags": ["two words"]}),
+    )
+    .await;
+    let unknown_server = requests::put_response(
+        &app,
+        &format!("{}/servers/{}/tags", &app.url, Uuid::new_v4()),
+        &data.token,
+        &json!({"tags": ["env:prod"]}),
+    )
+    .await;
+    let removed =
+        requests::delete_response(&app, &format!("{tags_endpoint}/env:prod"), &data.token).await;
+    let removed_again =
+        requests::delete_response(&app, &format!("{tags_endpoint}/env:prod"), &data.token).await;
+    let servers = queries::get_servers_for_user(&pool, data.user_id)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(unknown_server.status(), StatusCode::NOT_FOUND);
+    assert_eq!(removed.status(), StatusCode::NO_CONTENT);
+    assert_eq!(removed_again.status(), StatusCode::NOT_FOUND);
+    assert_eq!(servers[0].tags, ["project:x"]);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn repeated_order_with_external_id_should_return_ordered_server(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let order = ServerBuilder::new(data.product_id)
+        .external_id("module.web.server[0]")
+        .tag("env:prod");
+    let payload = order.payload();
+    let (ordered, server) = order.create(&app, &pool, &data).await;
+    let lookup = format!("{}/servers/external/", &app.url);
+
+    // Act
+    let repeated = requests::post_response(
+        &app,
+        &format!("{}/servers", &app.url),
+        &data.token,
+        &payload,
+    )
+    .await;
+    let found = requests::get_response(
+        &app,
+        &format!(
+            "{lookup}{}",
+            utf8_percent_encode("module.web.server[0]", NON_ALPHANUMERIC)
+        ),
+        &data.token,
+    )
+    .await
+    .json::<Response<ApiServer>>()
+    .await
+    .unwrap()
+    .result;
+    let unknown = requests::get_response(&app, &format!("{lookup}other"), &data.token).await;
+    let servers = queries::get_servers_for_user(&pool, data.user_id)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(ordered.status(), StatusCode::ACCEPTED);
+    assert_eq!(server.external_id.as_deref(), Some("module.web.server[0]"));
+    assert_eq!(server.tags, ["env:prod"]);
+    assert_eq!(repeated.status(), StatusCode::OK);
+    let repeated = repeated.json::<Response<ApiServer>>().await.unwrap().result;
+    assert_eq!(repeated.server_id, server.server_id);
+    assert_eq!(found.server_id, server.server_id);
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    assert_eq!(servers.len(), 1);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn existing_server_should_be_adopted_by_external_id(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/external-id", &app.url, server.server_id);
+
+    // Act
+    let invalid = requests::put_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({"external_id": "a\nb"}),
+    )
+    .await;
+    let adopted = requests::put_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({"external_id": " db-primary "}),
+    )
+    .await
+    .json::<Response<ApiServer>>()
+    .await
+    .unwrap()
+    .result;
+    let found = requests::get_response(
+        &app,
+        &format!("{}/servers/external/db-primary", &app.url),
+        &data.token,
+    )
+    .await;
+    let released =
+        requests::put_response(&app, &endpoint, &data.token, &json!({"external_id": null}))
+            .await
+            .json::<Response<ApiServer>>()
+            .await
+            .unwrap()
+            .result;
+    let unknown_server = requests::put_response(
+        &app,
+        &format!("{}/servers/{}/external-id", &app.url, Uuid::new_v4()),
+        &data.token,
+        &json!({"external_id": "db-primary"}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(server.external_id, None);
+    assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(adopted.external_id.as_deref(), Some("db-primary"));
+    assert_eq!(found.status(), StatusCode::OK);
+    assert_eq!(released.external_id, None);
+    assert_eq!(unknown_server.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn timeline_should_list_server_events_in_order(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::set_product_disk_limits(&pool, data.product_id, 1, Some(50)).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    proxmox
+        .backup_notes
+        .lock()
+        .unwrap()
+        .push(backup::notes(server.server_id));
+    let server_endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    requests::post_response(
+        &app,
+        &format!("{server_endpoint}/disks"),
+        &data.token,
+        &json!({ "size_gb": 10 }),
+    )
+    .await;
+    requests::post_response(
+        &app,
+        &format!("{server_endpoint}/actions"),
+        &data.token,
+        &json!({ "action": "start" }),
+    )
+    .await;
+    database::wait_for_status(&pool, server.server_id, ServerStatus::Running).await;
+
+    // Act
+    let events = requests::get_response(&app, &format!("{server_endpoint}/timeline"), &data.token)
+        .await
+        .json::<Response<Vec<ApiTimelineEvent>>>()
+        .await
+        .unwrap()
+        .result;
+    let unknown = requests::get_response(
+        &app,
+        &format!("{}/servers/{}/timeline", &app.url, Uuid::new_v4()),
+        &data.token,
+    )
+    .await;
+
+    // Assert
+    assert!(events.is_sorted_by_key(|event| event.occurred_at));
+    let statuses = events
+        .iter()
+        .filter(|event| event.kind != TimelineEventKind::Provisioning)
+        .filter(|event| event.kind != TimelineEventKind::Backup)
+        .map(|event| (event.kind, event.name.as_str(), event.detail.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses.first(),
+        Some(&(TimelineEventKind::Status, "SettingUp", None))
+    );
+    assert_eq!(
+        statuses[statuses.len() - 2..],
+        [
+            (TimelineEventKind::Power, "Starting", Some("Stopped")),
+            (TimelineEventKind::Status, "Running", Some("Starting"))
+        ]
+    );
+    assert!(statuses.contains(&(TimelineEventKind::Resize, "DiskAdded", Some("scsi1, 10 GB"))));
+    assert!(
+        events
+            .iter()
+            .any(|event| event.kind == TimelineEventKind::Provisioning
+                && event.status.as_deref() == Some("Completed"))
+    );
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| event.kind == TimelineEventKind::Backup)
+            .count(),
+        1
+    );
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,50 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use dashboard_server::model::types::ApiServerTraffic;
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, database, requests};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn get_traffic_should_list_months_with_quota(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    database::set_product_traffic_quota(&pool, data.product_id, 500).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let september = NaiveDate::from_ymd_opt(2026, 9, 1).unwrap();
+    let october = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+    database::add_server_traffic(&pool, server.server_id, september, 1_000, 2_000).await;
+    database::add_server_traffic(&pool, server.server_id, october, 3_000, 4_000).await;
+
+    // Act
+    let traffic = requests::get_response(
+        &app,
+        &format!("{}/servers/{}/traffic", &app.url, server.server_id),
+        &data.token,
+    )
+    .await
+    .json::<Response<ApiServerTraffic>>()
+    .await
+    .unwrap()
+    .result;
+    let missing = requests::get_response(
+        &app,
+        &format!("{}/servers/{}/traffic", &app.url, Uuid::new_v4()),
+        &data.token,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(traffic.quota_gb, Some(500));
+    assert_eq!(
+        traffic
+            .months
+            .iter()
+            .map(|month| (month.month, month.total_bytes()))
+            .collect::<Vec<_>>(),
+        [(october, 7_000), (september, 3_000)]
+    );
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,380 @@
+use axum::http::StatusCode;
+use dashboard_server::model::types::{
+    ApiAuditEntry, ApiCompletedTransfer, ApiOrganization, ApiServer, ApiServerTransfer,
+    ApiTimelineEvent, AuditAction, ServerStatus, TimelineEventKind, TransferStatus,
+};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{MockProxmoxClient, TestApp, TestData, UserBuilder, database, requests};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn transfer_should_move_server_once_accepted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let recipient = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let server_endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    let payload = json!({"email": "jane.doe@example.com"});
+    let requested = requests::post_response(
+        &app,
+        &format!("{server_endpoint}/transfer"),
+        &data.token,
+        &payload,
+    )
+    .await;
+    let offered = pending_transfers(&app, &recipient.token).await;
+    let transfer = offered[0].clone();
+    let accept_endpoint = format!("{}/transfers/{}/accept", &app.url, transfer.id);
+
+    // Act
+    let completed = requests::post_response(&app, &accept_endpoint, &recipient.token, &json!({}))
+        .await
+        .json::<Response<ApiCompletedTransfer>>()
+        .await
+        .unwrap()
+        .result;
+    let accepted_again =
+        requests::post_response(&app, &accept_endpoint, &recipient.token, &json!({})).await;
+    let as_owner = requests::get_response(&app, &server_endpoint, &data.token).await;
+    let as_recipient = requests::get_response(&app, &server_endpoint, &recipient.token).await;
+
+    // Assert
+    assert_eq!(requested.status(), StatusCode::ACCEPTED);
+    assert_eq!(transfer.status, TransferStatus::Pending);
+    assert_eq!(offered.len(), 1);
+    assert_eq!(completed.transfer.status, TransferStatus::Completed);
+    assert_eq!(completed.transfer.to_user_id, recipient.user_id);
+    assert_eq!(completed.password, None);
+    assert_eq!(accepted_again.status(), StatusCode::NOT_FOUND);
+    assert_eq!(as_owner.status(), StatusCode::NOT_FOUND);
+    assert!(as_recipient.status().is_success());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn transfer_should_take_server_out_of_organization(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let member = UserBuilder::new()
+        .email("john.smith@example.com")
+        .register(&app, &pool)
+        .await;
+    let recipient = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let organization = requests::post_response(
+        &app,
+        &format!("{}/organizations", &app.url),
+        &data.token,
+        &json!({"name": "Acme Hosting"}),
+    )
+    .await
+    .json::<Response<ApiOrganization>>()
+    .await
+    .unwrap()
+    .result;
+    let organization_endpoint = format!("{}/organizations/{}", &app.url, organization.id);
+    requests::put_response(
+        &app,
+        &format!("{organization_endpoint}/members"),
+        &data.token,
+        &json!({"email": "john.smith@example.com", "role": "member"}),
+    )
+    .await;
+    requests::post_response(
+        &app,
+        &format!("{organization_endpoint}/servers"),
+        &data.token,
+        &json!({"server_id": server.server_id}),
+    )
+    .await;
+    let server_endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    requests::post_response(
+        &app,
+        &format!("{server_endpoint}/transfer"),
+        &data.token,
+        &json!({"email": "jane.doe@example.com"}),
+    )
+    .await;
+    let transfer = pending_transfers(&app, &recipient.token).await.remove(0);
+
+    // Act
+    requests::post_response(
+        &app,
+        &format!("{}/transfers/{}/accept", &app.url, transfer.id),
+        &recipient.token,
+        &json!({}),
+    )
+    .await;
+    let as_member = requests::get_response(&app, &server_endpoint, &member.token).await;
+    let shared = requests::get_response(
+        &app,
+        &format!("{organization_endpoint}/servers"),
+        &data.token,
+    )
+    .await
+    .json::<Response<Vec<ApiServer>>>()
+    .await
+    .unwrap()
+    .result;
+
+    // Assert
+    assert_eq!(as_member.status(), StatusCode::NOT_FOUND);
+    assert!(shared.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn declined_transfer_should_keep_server(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let recipient = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let transfer_endpoint = format!("{}/servers/{}/transfer", &app.url, server.server_id);
+    let payload = json!({"email": "jane.doe@example.com"});
+    requests::post_response(&app, &transfer_endpoint, &data.token, &payload).await;
+    let transfer = pending_transfers(&app, &recipient.token).await.remove(0);
+    let duplicate = requests::post_response(&app, &transfer_endpoint, &data.token, &payload).await;
+
+    // Act
+    let declined = requests::post_response(
+        &app,
+        &format!("{}/transfers/{}/decline", &app.url, transfer.id),
+        &recipient.token,
+        &json!({}),
+    )
+    .await
+    .json::<Response<ApiServerTransfer>>()
+    .await
+    .unwrap()
+    .result;
+    let as_owner = requests::get_response(
+        &app,
+        &format!("{}/servers/{}", &app.url, server.server_id),
+        &data.token,
+    )
+    .await;
+
+    // Assert
+    assert_eq!(duplicate.status(), StatusCode::CONFLICT);
+    assert_eq!(declined.status, TransferStatus::Declined);
+    assert!(declined.completed_at.is_some());
+    assert!(as_owner.status().is_success());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn admin_transfer_should_regenerate_credentials(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let recipient = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let admin = UserBuilder::new()
+        .email("admin@example.com")
+        .admin()
+        .register(&app, &pool)
+        .await;
+    let configured = proxmox.call_count("vm_config");
+    let endpoint = format!("{}/admin/servers/{}/transfer", &app.url, server.server_id);
+    let payload = json!({"user_id": recipient.user_id, "regenerate_credentials": true});
+
+    // Act
+    let completed = requests::post_response(&app, &endpoint, &admin.token, &payload)
+        .await
+        .json::<Response<ApiCompletedTransfer>>()
+        .await
+        .unwrap()
+        .result;
+    let status = requests::get_response(
+        &app,
+        &format!("{}/servers/{}", &app.url, server.server_id),
+        &recipient.token,
+    )
+    .await
+    .json::<Response<ApiServer>>()
+    .await
+    .unwrap()
+    .result
+    .status;
+    let entries = requests::get_response(
+        &app,
+        &format!(
+            "{}/admin/audit-log?server_id={}",
+            &app.url, server.server_id
+        ),
+        &admin.token,
+    )
+    .await
+    .json::<Response<Vec<ApiAuditEntry>>>()
+    .await
+    .unwrap()
+    .result;
+
+    // Assert
+    assert_eq!(completed.transfer.status, TransferStatus::Completed);
+    assert_eq!(completed.transfer.from_user_id, data.user_id);
+    assert_eq!(completed.transfer.initiated_by, admin.user_id);
+    assert_eq!(completed.password.map(|password| password.len()), Some(20));
+    assert_eq!(proxmox.call_count("vm_config"), configured + 1);
+    assert_eq!(status, server.status);
+    assert_eq!(entries.len(), 2);
+    assert!(
+        entries
+            .iter()
+            .all(|entry| entry.user_id == Some(admin.user_id))
+    );
+    assert!(
+        entries
+            .iter()
+            .any(|entry| entry.action == AuditAction::ServerTransferred)
+    );
+    assert!(
+        entries
+            .iter()
+            .any(|entry| entry.action == AuditAction::PasswordReset)
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn failed_regeneration_should_keep_server(pool: PgPool) {
+    // Arrange
+    let proxmox = Arc::new(MockProxmoxClient::default());
+    let app = TestApp::with_proxmox(pool.clone(), proxmox.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let recipient = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let admin = UserBuilder::new()
+        .email("admin@example.com")
+        .admin()
+        .register(&app, &pool)
+        .await;
+    proxmox.fail_times("vm_config", 1);
+    let endpoint = format!("{}/admin/servers/{}/transfer", &app.url, server.server_id);
+    let payload = json!({"user_id": recipient.user_id, "regenerate_credentials": true});
+
+    // Act
+    let response = requests::post_response(&app, &endpoint, &admin.token, &payload).await;
+    let as_owner = requests::get_response(
+        &app,
+        &format!("{}/servers/{}", &app.url, server.server_id),
+        &data.token,
+    )
+    .await
+    .json::<Response<ApiServer>>()
+    .await
+    .unwrap()
+    .result;
+
+    // Assert
+    assert!(response.status().is_server_error());
+    assert_eq!(as_owner.status, server.status);
+    assert!(pending_transfers(&app, &recipient.token).await.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn unknown_recipient_should_be_answered_like_known_one(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let endpoint = format!("{}/servers/{}/transfer", &app.url, server.server_id);
+
+    // Act
+    let response = requests::post_response(
+        &app,
+        &endpoint,
+        &data.token,
+        &json!({"email": "nobody@example.com"}),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert!(pending_transfers(&app, &data.token).await.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn timeline_should_start_with_latest_transfer(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let recipient = UserBuilder::new()
+        .email("jane.doe@example.com")
+        .register(&app, &pool)
+        .await;
+    let server_endpoint = format!("{}/servers/{}", &app.url, server.server_id);
+    requests::post_response(
+        &app,
+        &format!("{server_endpoint}/actions"),
+        &data.token,
+        &json!({ "action": "start" }),
+    )
+    .await;
+    database::wait_for_status(&pool, server.server_id, ServerStatus::Running).await;
+    // The database dates the status changes by its own clock, so the transfer
+    // completes clearly after them.
+    app.clock.advance(std::time::Duration::from_secs(60));
+    requests::post_response(
+        &app,
+        &format!("{server_endpoint}/transfer"),
+        &data.token,
+        &json!({"email": "jane.doe@example.com"}),
+    )
+    .await;
+    let transfer = pending_transfers(&app, &recipient.token).await[0].clone();
+    requests::post_response(
+        &app,
+        &format!("{}/transfers/{}/accept", &app.url, transfer.id),
+        &recipient.token,
+        &json!({}),
+    )
+    .await;
+    let timeline_endpoint = format!("{server_endpoint}/timeline");
+
+    // Act
+    let events = requests::get_response(&app, &timeline_endpoint, &recipient.token)
+        .await
+        .json::<Response<Vec<ApiTimelineEvent>>>()
+        .await
+        .unwrap()
+        .result;
+    let as_previous_owner = requests::get_response(&app, &timeline_endpoint, &data.token).await;
+
+    // Assert
+    assert_eq!(events[0].kind, TimelineEventKind::Transfer);
+    assert!(events.iter().all(|event| !matches!(
+        event.kind,
+        TimelineEventKind::Provisioning | TimelineEventKind::Power
+    )));
+    assert_eq!(as_previous_owner.status(), StatusCode::NOT_FOUND);
+}
+
+/// Lists the pending transfers of the user.
+///
+async fn pending_transfers(app: &TestApp, token: &str) -> Vec<ApiServerTransfer> {
+    requests::get_response(app, &format!("{}/transfers", &app.url), token)
+        .await
+        .json::<Response<Vec<ApiServerTransfer>>>()
+        .await
+        .unwrap()
+        .result
+}
@@ -1,5 +1,9 @@
-﻿use crate::helpers::{TestApp, payload, requests};
-use dashboard_server::web::types::{TokenPayload, UserResponse};
+﻿use axum::http::StatusCode;
+use dashboard_server::model::queries;
+use dashboard_server::model::types::{ApiToken, ApiUserExport, AuditAction};
+use dashboard_server::web::types::{Response, TokenPayload, UserResponse};
+use dashboard_testing::{TestApp, TestData, database, payload, requests};
+use serde_json::json;
 use sqlx::PgPool;
 
 #[sqlx::test(migrations = "../../migrations")]
@@ -24,3 +28,159 @@ async fn should_get_user(pool: PgPool) {
         payload::register_user()["email"].as_str().unwrap()
     );
 }
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn should_update_user_profile(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool).await;
+    let endpoint = format!("{}/register", &app.url);
+    let token = requests::post_result::<TokenPayload>(&app, &endpoint, &payload::register_user())
+        .await
+        .token;
+    let endpoint = format!("{}/me", &app.url);
+
+    // Act
+    let update = json!({ "first_name": " Jane ", "phone_number": "+1 (555) 123-4567" });
+    let response = requests::patch_response(&app, &endpoint, &token, &update).await;
+    let invalid = json!({ "phone_number": "call me" });
+    let invalid_response = requests::patch_response(&app, &endpoint, &token, &invalid).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    let user = response.json::<UserResponse>().await.unwrap().result;
+    assert_eq!(user.first_name, "Jane");
+    assert_eq!(user.phone_number, "+1 (555) 123-4567");
+    assert_eq!(
+        user.last_name,
+        payload::register_user()["last_name"].as_str().unwrap()
+    );
+    assert_eq!(invalid_response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn should_change_email_after_confirmation(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool).await;
+    let endpoint = format!("{}/register", &app.url);
+    let token = requests::post_result::<TokenPayload>(&app, &endpoint, &payload::register_user())
+        .await
+        .token;
+    let new_email = "changed@example.com";
+
+    // Act
+    let endpoint = format!("{}/me/email", &app.url);
+    let request =
+        requests::post_response(&app, &endpoint, &token, &json!({ "email": new_email })).await;
+    let sent = app.mailer.sent.lock().unwrap().clone();
+    let confirmation = app.last_email_token();
+    let endpoint = format!("{}/me/email/confirm", &app.url);
+    let wrong =
+        requests::post_response(&app, &endpoint, &token, &json!({ "token": "wrong" })).await;
+    let confirmed =
+        requests::post_response(&app, &endpoint, &token, &json!({ "token": confirmation })).await;
+
+    // Assert
+    assert_eq!(request.status(), StatusCode::ACCEPTED);
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[1].to, new_email);
+    assert_eq!(wrong.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(confirmed.status(), StatusCode::OK);
+    let user = confirmed.json::<UserResponse>().await.unwrap().result;
+    assert_eq!(user.email, new_email);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn should_export_and_delete_account(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let (_, server) = data.create_server(&app, &pool).await;
+    let details = json!({ "method": "agent" });
+    queries::create_audit_entry(
+        &pool,
+        data.user_id,
+        AuditAction::PasswordReset,
+        Some(server.server_id),
+        &details,
+    )
+    .await
+    .unwrap();
+
+    // Act
+    let endpoint = format!("{}/me/export", &app.url);
+    let export_response = requests::get_response(&app, &endpoint, &data.token).await;
+    let disposition = export_response.headers()["content-disposition"]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let export = export_response.json::<ApiUserExport>().await.unwrap();
+    let endpoint = format!("{}/me", &app.url);
+    let deletion = requests::delete_response(&app, &endpoint, &data.token).await;
+    let repeated = requests::delete_response(&app, &endpoint, &data.token).await;
+    database::wait_for_deletion(&pool, server.server_id).await;
+    let endpoint = format!("{}/login", &app.url);
+    let login = requests::post_response(&app, &endpoint, "", &payload::login_user()).await;
+
+    // Assert
+    assert!(disposition.starts_with("attachment"));
+    assert_eq!(
+        export.profile.email,
+        payload::register_user()["email"].as_str().unwrap()
+    );
+    assert_eq!(export.servers.len(), 1);
+    assert_eq!(export.servers[0].server_id, server.server_id);
+    assert_eq!(export.audit_log.len(), 1);
+    assert_eq!(export.audit_log[0].action, AuditAction::PasswordReset);
+    assert_eq!(deletion.status(), StatusCode::ACCEPTED);
+    assert_eq!(repeated.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(login.status(), StatusCode::UNAUTHORIZED);
+    let user = queries::get_user_by_id(&pool, data.user_id).await.unwrap();
+    assert_eq!(user.first_name, "Deleted");
+    assert!(user.email_verified_at.is_none());
+    let servers = queries::get_servers_for_user(&pool, data.user_id)
+        .await
+        .unwrap();
+    assert!(servers.is_empty());
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn api_token_should_authorize_until_revoked(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/me/api-tokens", &app.url);
+    let servers_endpoint = format!("{}/servers", &app.url);
+
+    // Act
+    let created = requests::post_response(&app, &endpoint, &data.token, &json!({ "name": "ci" }))
+        .await
+        .json::<Response<ApiToken>>()
+        .await
+        .unwrap()
+        .result;
+    let secret = created.secret.clone().unwrap();
+    let authorized = requests::get_response(&app, &servers_endpoint, &secret).await;
+    let listed = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiToken>>>()
+        .await
+        .unwrap()
+        .result;
+    let revoked =
+        requests::delete_response(&app, &format!("{}/{}", &endpoint, created.id), &data.token)
+            .await;
+    let rejected = requests::get_response(&app, &servers_endpoint, &secret).await;
+    let unnamed =
+        requests::post_response(&app, &endpoint, &data.token, &json!({ "name": " " })).await;
+
+    // Assert
+    assert!(secret.starts_with("dash_"));
+    assert_eq!(authorized.status(), StatusCode::OK);
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].name, "ci");
+    assert_eq!(listed[0].secret, None);
+    assert!(listed[0].last_used_at.is_some());
+    assert_eq!(revoked.status(), StatusCode::NO_CONTENT);
+    assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(unnamed.status(), StatusCode::BAD_REQUEST);
+}
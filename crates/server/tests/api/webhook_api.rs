@@ -0,0 +1,142 @@
+use axum::http::StatusCode;
+use dashboard_server::config::Config;
+use dashboard_server::model::types::{
+    ApiWebhook, ApiWebhookDelivery, WebhookDeliveryStatus, WebhookEvent,
+};
+use dashboard_server::web::types::Response;
+use dashboard_testing::{TestApp, TestData, requests};
+use serde_json::json;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn webhook_should_be_created_listed_and_deleted(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/webhooks", &app.url);
+    let payload = json!({
+        "url": "https://example.com/hooks",
+        "events": ["server.created", "invoice.paid", "server.created"]
+    });
+
+    // Act
+    let created = requests::post_response(&app, &endpoint, &data.token, &payload)
+        .await
+        .json::<Response<ApiWebhook>>()
+        .await
+        .unwrap()
+        .result;
+    let listed = requests::get_response(&app, &endpoint, &data.token)
+        .await
+        .json::<Response<Vec<ApiWebhook>>>()
+        .await
+        .unwrap()
+        .result;
+    let webhook_endpoint = format!("{}/{}", endpoint, created.id);
+    let deleted = requests::delete_response(&app, &webhook_endpoint, &data.token).await;
+    let deleted_again = requests::delete_response(&app, &webhook_endpoint, &data.token).await;
+
+    // Assert
+    assert!(created.secret.unwrap().starts_with("whsec_"));
+    assert_eq!(
+        created.events,
+        [WebhookEvent::ServerCreated, WebhookEvent::InvoicePaid]
+    );
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, created.id);
+    assert_eq!(listed[0].secret, None);
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    assert_eq!(deleted_again.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn webhook_with_invalid_url_or_no_events_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/webhooks", &app.url);
+    let invalid_url = json!({"url": "ftp://example.com", "events": ["server.created"]});
+    let no_events = json!({"url": "https://example.com/hooks", "events": []});
+
+    // Act
+    let invalid_url = requests::post_response(&app, &endpoint, &data.token, &invalid_url).await;
+    let no_events = requests::post_response(&app, &endpoint, &data.token, &no_events).await;
+
+    // Assert
+    assert_eq!(invalid_url.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(no_events.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn webhook_to_private_address_should_be_rejected(pool: PgPool) {
+    // Arrange
+    let app = TestApp::with_config(pool.clone(), Config::default()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/webhooks", &app.url);
+    let urls = [
+        "http://10.0.0.5:8080/hooks",
+        "http://127.0.0.1/hooks",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/hooks",
+        "http://[::ffff:192.168.1.1]/hooks",
+    ];
+
+    // Act
+    let mut statuses = Vec::new();
+    for url in urls {
+        let payload = json!({"url": url, "events": ["server.created"]});
+        let response = requests::post_response(&app, &endpoint, &data.token, &payload).await;
+        statuses.push(response.status());
+    }
+
+    // Assert
+    assert!(
+        statuses
+            .iter()
+            .all(|status| *status == StatusCode::BAD_REQUEST)
+    );
+}
+
+#[sqlx::test(migrations = "../../migrations")]
+async fn created_server_should_queue_subscribed_event(pool: PgPool) {
+    // Arrange
+    let app = TestApp::new(pool.clone()).await;
+    let data = TestData::new(&app, &pool).await;
+    let endpoint = format!("{}/webhooks", &app.url);
+    let mut webhooks = Vec::new();
+    for event in ["server.created", "server.deleted"] {
+        let payload = json!({"url": "https://example.com/hooks", "events": [event]});
+        let webhook = requests::post_response(&app, &endpoint, &data.token, &payload)
+            .await
+            .json::<Response<ApiWebhook>>()
+            .await
+            .unwrap()
+            .result;
+        webhooks.push(webhook);
+    }
+
+    // Act
+    let (_, server) = data.create_server(&app, &pool).await;
+    let mut deliveries = Vec::new();
+    for webhook in &webhooks {
+        let endpoint = format!("{}/{}/deliveries", endpoint, webhook.id);
+        let found = requests::get_response(&app, &endpoint, &data.token)
+            .await
+            .json::<Response<Vec<ApiWebhookDelivery>>>()
+            .await
+            .unwrap()
+            .result;
+        deliveries.push(found);
+    }
+
+    // Assert
+    assert_eq!(deliveries[0].len(), 1);
+    assert_eq!(deliveries[0][0].event, "server.created");
+    assert_eq!(deliveries[0][0].status, WebhookDeliveryStatus::Pending);
+    assert_eq!(
+        deliveries[0][0].payload["server_id"],
+        json!(server.server_id)
+    );
+    assert!(deliveries[0][0].attempts.is_empty());
+    assert!(deliveries[1].is_empty());
+}
@@ -0,0 +1,183 @@
+//! Contract tests of the `Proxmox` client against a real PVE instance.
+//!
+//! Built only with the `proxmox-contract` feature, and configured with the
+//! `PROXMOX_CONTRACT_*` variables:
+//!
+//! * `PROXMOX_CONTRACT_URL`: URL of the Proxmox API.
+//! * `PROXMOX_CONTRACT_AUTH_HEADER`: Authorization header of an API token.
+//! * `PROXMOX_CONTRACT_NODE`: Node holding the template.
+//! * `PROXMOX_CONTRACT_TEMPLATE_VMID`: Template cloned by the lifecycle test.
+//!
+//! The lifecycle test clones a VM and deletes it again, also when a step
+//! fails, so point the tests at a test node.
+
+#![cfg(feature = "proxmox-contract")]
+
+use dashboard_common::prelude::{Error, ProxmoxError, Result};
+use dashboard_server::clock::{Clock, SystemClock};
+use dashboard_server::proxmox::Proxmox;
+use dashboard_server::proxmox::client::ProxmoxClient;
+use dashboard_server::proxmox::types::{Status, TaskRef, UniqueProcessId, VmConfig, VmRef};
+use dashboard_server::services::{self, Polling};
+use std::sync::Arc;
+
+/// VMID that Proxmox accepts but no test cluster uses.
+const MISSING_VMID: i32 = 999_999_999;
+
+/// Client and clock of the tests, with the node and template they work on.
+///
+struct Contract {
+    client: Arc<dyn Proxmox + Send + Sync>,
+    clock: Arc<dyn Clock + Send + Sync>,
+    node: String,
+    template_vmid: i32,
+}
+
+impl Contract {
+    /// Reads the configuration from the environment.
+    ///
+    fn from_env() -> Self {
+        dotenv::dotenv().ok();
+        let var = |name: &str| {
+            std::env::var(name).unwrap_or_else(|_| panic!("{name} must be set for contract tests"))
+        };
+        let client = ProxmoxClient::new(
+            var("PROXMOX_CONTRACT_URL"),
+            var("PROXMOX_CONTRACT_AUTH_HEADER").as_str().into(),
+        )
+        .unwrap();
+
+        Self {
+            client: Arc::new(client),
+            clock: Arc::new(SystemClock),
+            node: var("PROXMOX_CONTRACT_NODE"),
+            template_vmid: var("PROXMOX_CONTRACT_TEMPLATE_VMID").parse().unwrap(),
+        }
+    }
+
+    /// Waits until the task started on the node finishes.
+    ///
+    async fn wait(&self, upid: &UniqueProcessId, polling: Polling) -> Result<()> {
+        let task = TaskRef::new(&self.node, upid);
+        services::wait_until_finish(&self.client, &self.clock, task, polling).await
+    }
+
+    /// Stops the VM if a failed step left it running, then deletes it.
+    ///
+    async fn tear_down(&self, vm: &VmRef) -> Result<()> {
+        if self.client.vm_status(vm.clone()).await? == Status::Running {
+            let upid = self.client.stop(vm.clone()).await?;
+            self.wait(&upid, Polling::POWER).await?;
+        }
+        let upid = self.client.delete(vm.clone()).await?;
+        self.wait(&upid, Polling::DELETE).await
+    }
+}
+
+#[tokio::test]
+async fn nodes_should_list_contract_node_online() {
+    // Arrange
+    let contract = Contract::from_env();
+
+    // Act
+    let nodes = contract.client.list_nodes().await.unwrap();
+
+    // Assert
+    let node = nodes.iter().find(|item| item.node == contract.node);
+    assert!(node.is_some_and(|node| node.is_online()));
+}
+
+#[tokio::test]
+async fn node_status_should_report_usage() {
+    // Arrange
+    let contract = Contract::from_env();
+
+    // Act
+    let status = contract.client.node_status(&contract.node).await.unwrap();
+
+    // Assert
+    assert!(status.cpuinfo.cpus > 0);
+    assert!((0.0..=1.0).contains(&status.cpu));
+    assert!(status.memory.total > 0);
+    assert!(status.memory.used <= status.memory.total);
+    assert!(status.rootfs.total > 0);
+}
+
+#[tokio::test]
+async fn node_storages_should_include_image_storage() {
+    // Arrange
+    let contract = Contract::from_env();
+
+    // Act
+    let storages = contract.client.list_storages(&contract.node).await.unwrap();
+
+    // Assert
+    assert!(storages.iter().any(|storage| storage.holds_images()));
+}
+
+#[tokio::test]
+async fn template_should_be_reported_as_template() {
+    // Arrange
+    let contract = Contract::from_env();
+    let template = VmRef::new(&contract.node, contract.template_vmid);
+
+    // Act
+    let exists = contract.client.vm_exists(template.clone()).await.unwrap();
+    let config = contract.client.vm_current_config(template).await.unwrap();
+
+    // Assert
+    assert!(exists);
+    assert_eq!(config.template, Some(1));
+}
+
+#[tokio::test]
+async fn missing_vm_should_map_to_proxmox_error() {
+    // Arrange
+    let contract = Contract::from_env();
+    let missing = VmRef::new(&contract.node, MISSING_VMID);
+
+    // Act
+    let exists = contract.client.vm_exists(missing.clone()).await;
+    let status = contract.client.vm_status(missing).await;
+
+    // Assert
+    assert!(!exists.unwrap());
+    assert!(matches!(
+        status,
+        Err(Error::Proxmox(ProxmoxError::Status, code, _)) if code.is_server_error()
+    ));
+}
+
+#[tokio::test]
+async fn cloned_vm_should_follow_lifecycle_tasks() {
+    // Arrange
+    let contract = Contract::from_env();
+    let template = VmRef::new(&contract.node, contract.template_vmid);
+
+    // Act
+    let (vm_id, clone_upid) = contract.client.create(template, None).await.unwrap();
+    let vm = VmRef::new(&contract.node, vm_id);
+    let lifecycle = async {
+        contract.wait(&clone_upid, Polling::CLONE).await?;
+        let vm_config = VmConfig::builder().cores(1).memory(512).build();
+        let upid = contract.client.vm_config(vm.clone(), vm_config).await?;
+        contract.wait(&upid, Polling::CONFIG).await?;
+        let upid = contract.client.start(vm.clone()).await?;
+        contract.wait(&upid, Polling::POWER).await?;
+        let running = contract.client.vm_status(vm.clone()).await?;
+        let upid = contract.client.stop(vm.clone()).await?;
+        contract.wait(&upid, Polling::POWER).await?;
+        let stopped = contract.client.vm_status(vm.clone()).await?;
+        Ok::<_, Error>((running, stopped))
+    }
+    .await;
+    contract.tear_down(&vm).await.unwrap();
+
+    // Assert
+    let info = clone_upid.parse().unwrap();
+    assert_eq!(info.node, contract.node);
+    assert_eq!(info.task_type, "qmclone");
+    assert_eq!(info.id, contract.template_vmid.to_string());
+    assert_eq!(lifecycle.unwrap(), (Status::Running, Status::Stopped));
+    assert!(!contract.client.vm_exists(vm).await.unwrap());
+}
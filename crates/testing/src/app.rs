@@ -0,0 +1,234 @@
+use crate::builders::{CatalogBuilder, ServerBuilder, UserBuilder};
+use crate::mocks::{MockCaptchaProvider, MockMailer, MockPaymentProvider, MockProxmoxClient};
+use arc_swap::ArcSwap;
+use chrono::Utc;
+use dashboard_server::app::App;
+use dashboard_server::broker::Broker;
+use dashboard_server::captcha::CaptchaProvider;
+use dashboard_server::clock::ManualClock;
+use dashboard_server::cluster::Cluster;
+use dashboard_server::config::Config;
+use dashboard_server::grpc;
+use dashboard_server::model::cache::{Catalog, TtlCache};
+use dashboard_server::model::types::ApiServer;
+use dashboard_server::proxmox::queue::RequestQueue;
+use dashboard_server::services::event;
+use dashboard_server::state::AppState;
+use reqwest::Client;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+/// Test helper that runs a server instance in the background and provides a
+/// `reqwest::Client` for making API calls, the mailer to inspect the sent
+/// emails, the clock of the services, which skips every wait, and the state of
+/// the application to run the jobs of the scheduler. The domain event
+/// consumers run alongside, and with an address configured, the internal gRPC
+/// API runs as well.
+///
+pub struct TestApp {
+    pub url: String,
+    pub grpc_url: Option<String>,
+    pub client: Client,
+    pub mailer: Arc<MockMailer>,
+    pub clock: Arc<ManualClock>,
+    pub state: AppState,
+}
+
+impl TestApp {
+    /// Creates a new `TestApp`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool`: Test pool provided by the `#[sqlx::test]` macro.
+    ///
+    pub async fn new(pool: PgPool) -> Self {
+        Self::with_proxmox(pool, Arc::new(MockProxmoxClient::default())).await
+    }
+
+    /// Creates a new `TestApp` with a preconfigured mock Proxmox client, which
+    /// the test may keep to inspect the calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool`: Test pool provided by the `#[sqlx::test]` macro.
+    /// * `proxmox`: Mock Proxmox client.
+    ///
+    pub async fn with_proxmox(pool: PgPool, proxmox: Arc<MockProxmoxClient>) -> Self {
+        Self::build(pool, Self::config(), proxmox, None).await
+    }
+
+    /// Creates a new `TestApp` requiring a CAPTCHA after suspicious activity,
+    /// verified by the mock CAPTCHA provider.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool`: Test pool provided by the `#[sqlx::test]` macro.
+    ///
+    pub async fn with_captcha(pool: PgPool) -> Self {
+        let captcha = Arc::new(MockCaptchaProvider);
+        let proxmox = Arc::new(MockProxmoxClient::default());
+        Self::build(pool, Self::config(), proxmox, Some(captcha)).await
+    }
+
+    /// Creates a new `TestApp` with custom settings, like the registered
+    /// OpenID Connect providers.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool`: Test pool provided by the `#[sqlx::test]` macro.
+    /// * `config`: Settings of the application.
+    ///
+    pub async fn with_config(pool: PgPool, config: Config) -> Self {
+        Self::build(pool, config, Arc::new(MockProxmoxClient::default()), None).await
+    }
+
+    /// Returns the default settings of a `TestApp`, which delivers webhooks
+    /// to any address, as the receivers of the tests run locally.
+    ///
+    pub fn config() -> Config {
+        let mut config = Config::default();
+        config.webhook.allow_private_targets = true;
+        config
+    }
+
+    async fn build(
+        pool: PgPool,
+        config: Config,
+        proxmox: Arc<MockProxmoxClient>,
+        captcha: Option<Arc<dyn CaptchaProvider + Send + Sync>>,
+    ) -> Self {
+        // Create testable application instance.
+        let payments = Arc::new(MockPaymentProvider);
+        let mailer = Arc::new(MockMailer::default());
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+        let state = AppState {
+            pool,
+            replica: None,
+            proxmox,
+            proxmox_queue: Arc::new(RequestQueue::new(0)),
+            payments,
+            mailer: mailer.clone(),
+            runtime: Arc::new(ArcSwap::from_pointee(config.runtime.clone())),
+            catalog: Arc::new(Catalog::new(Duration::from_secs(
+                config.cache.catalog_ttl_sec,
+            ))),
+            nodes: Arc::new(TtlCache::new(Duration::from_secs(
+                config.cache.nodes_ttl_sec,
+            ))),
+            guests: Arc::new(TtlCache::new(Duration::from_secs(
+                config.cache.guest_info_ttl_sec,
+            ))),
+            allowlists: Arc::new(TtlCache::new(Duration::from_secs(
+                config.cache.allowlist_ttl_sec,
+            ))),
+            cluster: Cluster::connect_lazy(&config.redis).unwrap(),
+            broker: Broker::new(config.events.broker_capacity),
+            captcha,
+            clock: clock.clone(),
+            config,
+        };
+        let grpc_url = match state.config.grpc.address {
+            Some(address) => {
+                let listener = TcpListener::bind(address).await.unwrap();
+                let url = format!("http://{}", listener.local_addr().unwrap());
+                tokio::spawn(grpc::serve(state.clone(), listener, std::future::pending()));
+                Some(url)
+            }
+            None => None,
+        };
+        tokio::spawn(event::watch(state.clone()));
+        let application = App::build(state.clone(), "127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let url = application.get_url().unwrap();
+
+        // Spawn application without blocking the execution.
+        tokio::spawn(async move {
+            application.run().await.unwrap();
+        });
+
+        TestApp {
+            url,
+            grpc_url,
+            client: Client::new(),
+            mailer,
+            clock,
+            state,
+        }
+    }
+
+    /// Returns the token from the last email sent by the application.
+    ///
+    pub fn last_email_token(&self) -> String {
+        let sent = self.mailer.sent.lock().unwrap();
+        let email = sent.last().expect("No email sent");
+
+        token_of(&email.body)
+    }
+
+    /// Returns the token from the last email sent to the address, for tests
+    /// registering several users at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `to`: Email address of the recipient.
+    ///
+    pub fn email_token(&self, to: &str) -> String {
+        let sent = self.mailer.sent.lock().unwrap();
+        let email = sent
+            .iter()
+            .rev()
+            .find(|email| email.to == to)
+            .expect("No email sent to the address");
+
+        token_of(&email.body)
+    }
+}
+
+/// Reads the token ending the first line of an email body.
+///
+fn token_of(body: &str) -> String {
+    body.lines()
+        .next()
+        .and_then(|line| line.rsplit(' ').next())
+        .unwrap()
+        .to_owned()
+}
+
+/// Test helper that creates and holds base default data for the database.
+///
+pub struct TestData {
+    pub token: String,
+    pub user_id: Uuid,
+    pub product_id: Uuid,
+}
+
+impl TestData {
+    /// Creates new user with a verified email address and product.
+    ///
+    pub async fn new(app: &TestApp, pool: &PgPool) -> TestData {
+        let user = UserBuilder::new().register(app, pool).await;
+        let product_id = CatalogBuilder::new().build(pool).await;
+
+        TestData {
+            token: user.token,
+            user_id: user.user_id,
+            product_id,
+        }
+    }
+
+    /// Creates new server for the user.
+    ///
+    pub async fn create_server(
+        &self,
+        app: &TestApp,
+        pool: &PgPool,
+    ) -> (reqwest::Response, ApiServer) {
+        ServerBuilder::new(self.product_id)
+            .create(app, pool, self)
+            .await
+    }
+}
@@ -0,0 +1,466 @@
+use crate::app::{TestApp, TestData};
+use crate::{database, requests};
+use dashboard_server::model::queries;
+use dashboard_server::model::types::{ApiServer, ServerStatus};
+use dashboard_server::web::types::TokenPayload;
+use serde_json::{Value, json};
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Datacenter of the default catalog, where the default server is ordered.
+pub const DATACENTER: &str = "Amsterdam";
+
+/// Number of times the setup of an ordered server is checked before the
+/// builder gives up waiting.
+const SETUP_CHECKS: usize = 200;
+
+/// User registered through the API, with a verified email address.
+///
+/// # Fields
+///
+/// * `token`: Access token of the user.
+/// * `user_id`: ID of the user.
+///
+pub struct TestUser {
+    pub token: String,
+    pub user_id: Uuid,
+}
+
+/// Builds a user, registered through the API.
+///
+/// Every field not set keeps the value of the default test user, so two users
+/// of the same test only need different emails.
+///
+pub struct UserBuilder {
+    first_name: String,
+    last_name: String,
+    email: String,
+    password: String,
+    admin: bool,
+}
+
+impl Default for UserBuilder {
+    fn default() -> Self {
+        Self {
+            first_name: "John".to_owned(),
+            last_name: "Doe".to_owned(),
+            email: "john.doe.reqwest@example.com".to_owned(),
+            password: "secure_password_123".to_owned(),
+            admin: false,
+        }
+    }
+}
+
+impl UserBuilder {
+    /// Starts from the default test user.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the name of the user.
+    ///
+    pub fn name(mut self, first_name: &str, last_name: &str) -> Self {
+        self.first_name = first_name.to_owned();
+        self.last_name = last_name.to_owned();
+        self
+    }
+
+    /// Sets the email address of the user.
+    ///
+    pub fn email(mut self, email: &str) -> Self {
+        self.email = email.to_owned();
+        self
+    }
+
+    /// Sets the password of the user.
+    ///
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_owned();
+        self
+    }
+
+    /// Makes the user an administrator once registered.
+    ///
+    pub fn admin(mut self) -> Self {
+        self.admin = true;
+        self
+    }
+
+    /// Returns the registration payload of the user.
+    ///
+    pub fn payload(&self) -> Value {
+        json!({
+            "first_name": self.first_name,
+            "last_name": self.last_name,
+            "email": self.email,
+            "password": self.password,
+            "address": "123 Main St",
+            "city": "Anytown",
+            "state": "Any-state",
+            "post_code": "12345",
+            "country": "USA",
+            "phone_number": "555-1234"
+        })
+    }
+
+    /// Returns the login payload of the user.
+    ///
+    pub fn login_payload(&self) -> Value {
+        json!({
+            "email": self.email,
+            "password": self.password,
+        })
+    }
+
+    /// Registers the user and verifies its email address with the token from
+    /// the sent email.
+    ///
+    /// # Arguments
+    ///
+    /// * `app`: Running test application.
+    /// * `pool`: Test database pool.
+    ///
+    pub async fn register(self, app: &TestApp, pool: &PgPool) -> TestUser {
+        let endpoint = format!("{}/register", &app.url);
+        let token = requests::post_result::<TokenPayload>(app, &endpoint, &self.payload())
+            .await
+            .token;
+        let endpoint = format!("{}/auth/verify", &app.url);
+        let verify_payload = json!({ "token": app.last_email_token() });
+        let response = requests::post_response(app, &endpoint, &token, &verify_payload).await;
+        assert!(response.status().is_success());
+        let user_id = queries::get_user_by_email(pool, &self.email)
+            .await
+            .unwrap()
+            .id;
+
+        if self.admin {
+            database::make_admin(pool, user_id).await;
+        }
+
+        TestUser { token, user_id }
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Builds the catalog a server can be ordered from: a product with its group,
+/// template, custom fields and configurable options, offered in a datacenter
+/// with a network of free IP addresses.
+///
+pub struct CatalogBuilder {
+    os: String,
+    datacenter: String,
+    ip_addresses: Vec<String>,
+    net_rate_mbps: Option<i32>,
+}
+
+impl Default for CatalogBuilder {
+    fn default() -> Self {
+        Self {
+            os: "ubuntu-22.04".to_owned(),
+            datacenter: DATACENTER.to_owned(),
+            ip_addresses: vec!["192.168.0.100".to_owned()],
+            net_rate_mbps: None,
+        }
+    }
+}
+
+impl CatalogBuilder {
+    /// Starts from the default catalog, a product offered in the `DATACENTER`
+    /// with a single free IP address.
+    ///
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the OS name of the template.
+    ///
+    pub fn os(mut self, os: &str) -> Self {
+        self.os = os.to_owned();
+        self
+    }
+
+    /// Sets the code of the datacenter offering the product.
+    ///
+    pub fn datacenter(mut self, code: &str) -> Self {
+        self.datacenter = code.to_owned();
+        self
+    }
+
+    /// Adds another free IP address to the network.
+    ///
+    pub fn ip_address(mut self, ip_address: &str) -> Self {
+        self.ip_addresses.push(ip_address.to_owned());
+        self
+    }
+
+    /// Limits the network rate of the product.
+    ///
+    pub fn net_rate(mut self, net_rate_mbps: i32) -> Self {
+        self.net_rate_mbps = Some(net_rate_mbps);
+        self
+    }
+
+    /// Inserts the catalog into the database.
+    ///
+    /// # Returns
+    ///
+    /// ID of the product.
+    ///
+    pub async fn build(self, pool: &PgPool) -> Uuid {
+        // New test product group.
+        let group_id = sqlx::query!(
+            r#"
+INSERT INTO product_groups (name)
+VALUES ('TestGroup1')
+RETURNING id
+            "#
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+        .id;
+
+        // New template.
+        sqlx::query!(
+            r#"
+INSERT INTO templates (os_name, template_vmid, template_node, virtual_type)
+VALUES ($1, 9000, 'pve', 'qemu')
+            "#,
+            self.os
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        // New test product.
+        let product_id = sqlx::query!(
+            r#"
+INSERT INTO products (group_id, name, net_rate_mbps)
+VALUES ($1, 'Test Product', $2)
+RETURNING id
+            "#,
+            group_id,
+            self.net_rate_mbps
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+        .id;
+
+        // New datacenter offering the product.
+        database::add_datacenter(pool, &self.datacenter).await;
+        sqlx::query!(
+            r#"
+INSERT INTO product_datacenters (product_id, datacenter_code)
+VALUES ($1, $2)
+            "#,
+            product_id,
+            self.datacenter
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        // New network with its IP addresses.
+        let network_id = sqlx::query!(
+            r#"
+INSERT INTO networks (datacenter_name, gateway, subnet_mask)
+VALUES ($1, '192.168.0.1', '255.255.255.255')
+RETURNING id
+            "#,
+            self.datacenter
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+        .id;
+        sqlx::query!(
+            r#"
+INSERT INTO ip_addresses (ip_address, network_id)
+SELECT UNNEST($1::TEXT[]), $2
+            "#,
+            &self.ip_addresses,
+            network_id
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        // New configurable options.
+        sqlx::query!(
+            r#"
+INSERT INTO config_options (name)
+VALUES ('cpu_cores'), ('ram_gb')
+            "#,
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        // New custom fields.
+        sqlx::query!(
+            r#"
+INSERT INTO custom_fields (product_id, name)
+VALUES ($1, 'os'), ($1, 'datacenter')
+            "#,
+            product_id
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        product_id
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+/// Builds the order of a new server, placed through the API.
+///
+pub struct ServerBuilder {
+    product_id: Uuid,
+    host_name: String,
+    cpu_cores: i32,
+    ram_gb: i32,
+    os: String,
+    app: Option<String>,
+    datacenter: String,
+    external_id: Option<String>,
+    tags: Vec<String>,
+}
+
+impl ServerBuilder {
+    /// Starts from the default order of the product, matching the default
+    /// catalog.
+    ///
+    pub fn new(product_id: Uuid) -> Self {
+        Self {
+            product_id,
+            host_name: "test-server.example.com".to_owned(),
+            cpu_cores: 2,
+            ram_gb: 2,
+            os: "ubuntu-22.04".to_owned(),
+            app: None,
+            datacenter: DATACENTER.to_owned(),
+            external_id: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Sets the host name of the server.
+    ///
+    pub fn host_name(mut self, host_name: &str) -> Self {
+        self.host_name = host_name.to_owned();
+        self
+    }
+
+    /// Sets the number of CPU cores.
+    ///
+    pub fn cpu_cores(mut self, cpu_cores: i32) -> Self {
+        self.cpu_cores = cpu_cores;
+        self
+    }
+
+    /// Sets the RAM in GB.
+    ///
+    pub fn ram_gb(mut self, ram_gb: i32) -> Self {
+        self.ram_gb = ram_gb;
+        self
+    }
+
+    /// Sets the OS template.
+    ///
+    pub fn os(mut self, os: &str) -> Self {
+        self.os = os.to_owned();
+        self
+    }
+
+    /// Sets the application of the marketplace installed on the server.
+    ///
+    pub fn app(mut self, app: &str) -> Self {
+        self.app = Some(app.to_owned());
+        self
+    }
+
+    /// Sets the datacenter location.
+    ///
+    pub fn datacenter(mut self, code: &str) -> Self {
+        self.datacenter = code.to_owned();
+        self
+    }
+
+    /// Sets the ID of the server in the tooling of the user.
+    ///
+    pub fn external_id(mut self, external_id: &str) -> Self {
+        self.external_id = Some(external_id.to_owned());
+        self
+    }
+
+    /// Adds a tag the server starts with.
+    ///
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_owned());
+        self
+    }
+
+    /// Returns the order payload of the server.
+    ///
+    pub fn payload(&self) -> Value {
+        json!({
+            "product_id": self.product_id,
+            "host_name": self.host_name,
+            "cpu_cores": self.cpu_cores,
+            "ram_gb": self.ram_gb,
+            "os": self.os,
+            "app": self.app,
+            "datacenter": self.datacenter,
+            "external_id": self.external_id,
+            "tags": self.tags
+        })
+    }
+
+    /// Orders the server for the user and waits until the setup running in
+    /// the background is done. The services of the test application skip every
+    /// wait, so the setup only takes as long as its queries.
+    ///
+    /// # Arguments
+    ///
+    /// * `app`: Running test application.
+    /// * `pool`: Test database pool.
+    /// * `data`: Owner of the server.
+    ///
+    /// # Returns
+    ///
+    /// Response to the order, and the first server of the user.
+    ///
+    pub async fn create(
+        self,
+        app: &TestApp,
+        pool: &PgPool,
+        data: &TestData,
+    ) -> (reqwest::Response, ApiServer) {
+        let endpoint = format!("{}/servers", &app.url);
+        let response = requests::post_response(app, &endpoint, &data.token, &self.payload()).await;
+
+        let mut servers = Vec::new();
+        for _ in 0..SETUP_CHECKS {
+            servers = queries::get_servers_for_user(pool, data.user_id)
+                .await
+                .unwrap();
+            if !servers.is_empty()
+                && servers
+                    .iter()
+                    .all(|server| server.status != ServerStatus::SettingUp)
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        (response, servers.first().unwrap().clone())
+    }
+}
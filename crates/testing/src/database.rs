@@ -0,0 +1,281 @@
+﻿use crate::builders::{CatalogBuilder, DATACENTER};
+use chrono::NaiveDate;
+use dashboard_server::model::types::ServerStatus;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Number of times the status of a server is checked before giving up waiting.
+const STATUS_CHECKS: usize = 200;
+
+/// Inserts the default catalog.
+///
+/// # Returns
+///
+/// ID of the product.
+///
+pub async fn populate_product(pool: &PgPool) -> Uuid {
+    CatalogBuilder::new().build(pool).await
+}
+
+/// Adds an active datacenter named after its code.
+///
+pub async fn add_datacenter(pool: &PgPool, code: &str) {
+    sqlx::query!(
+        r#"
+INSERT INTO datacenters (code, display_name)
+VALUES ($1, $1)
+            "#,
+        code
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+pub async fn make_admin(pool: &PgPool, user_id: Uuid) {
+    sqlx::query!(
+        r#"
+UPDATE users SET is_admin = TRUE
+WHERE id = $1
+            "#,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Sets the network rate limit of a product.
+///
+pub async fn set_product_net_rate(pool: &PgPool, product_id: Uuid, net_rate_mbps: i32) {
+    sqlx::query!(
+        r#"
+UPDATE products SET net_rate_mbps = $2
+WHERE id = $1
+            "#,
+        product_id,
+        net_rate_mbps
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Sets the disk limits of a product.
+///
+pub async fn set_product_disk_limits(
+    pool: &PgPool,
+    product_id: Uuid,
+    max_disks: i32,
+    max_disk_gb: Option<i32>,
+) {
+    sqlx::query!(
+        r#"
+UPDATE products SET max_disks = $2, max_disk_gb = $3
+WHERE id = $1
+            "#,
+        product_id,
+        max_disks,
+        max_disk_gb
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Returns the value of a configurable option of the service of a server.
+///
+pub async fn get_config_value(pool: &PgPool, server_id: Uuid, option: &str) -> Option<String> {
+    sqlx::query_scalar!(
+        r#"
+SELECT v.value
+FROM config_values AS v
+JOIN config_options AS o ON o.id = v.config_id
+JOIN services AS s ON s.id = v.service_id
+WHERE s.server_id = $1 AND o.name = $2
+            "#,
+        server_id,
+        option
+    )
+    .fetch_optional(pool)
+    .await
+    .unwrap()
+}
+
+/// Sets the monthly traffic quota of a product.
+///
+pub async fn set_product_traffic_quota(pool: &PgPool, product_id: Uuid, traffic_quota_gb: i32) {
+    sqlx::query!(
+        r#"
+UPDATE products SET traffic_quota_gb = $2
+WHERE id = $1
+            "#,
+        product_id,
+        traffic_quota_gb
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Records the traffic of a server in a month, as collected now.
+///
+pub async fn add_server_traffic(
+    pool: &PgPool,
+    server_id: Uuid,
+    month: NaiveDate,
+    rx_bytes: i64,
+    tx_bytes: i64,
+) {
+    sqlx::query!(
+        r#"
+INSERT INTO server_traffic (server_id, month, rx_bytes, tx_bytes, sampled_until)
+VALUES ($1, $2, $3, $4, NOW())
+            "#,
+        server_id,
+        month,
+        rx_bytes,
+        tx_bytes
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Adds an application template to the marketplace, installed by the script.
+///
+pub async fn add_app_template(pool: &PgPool, name: &str, script: &str) {
+    sqlx::query!(
+        r#"
+INSERT INTO templates (
+	os_name, template_vmid, template_node, virtual_type, kind, description,
+	cloud_init_snippet, post_install_script
+)
+VALUES ($1, 9100, 'pve', 'qemu', 'Application', 'Test application',
+	'local:snippets/app.yaml', $2)
+            "#,
+        name,
+        script
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Adds a free IP address to the test network.
+///
+pub async fn add_ip_address(pool: &PgPool, ip_address: &str) {
+    sqlx::query!(
+        r#"
+INSERT INTO ip_addresses (ip_address, network_id)
+SELECT $1, id FROM networks
+WHERE datacenter_name = $2
+            "#,
+        ip_address,
+        DATACENTER
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Simulates a provisioning that failed at the given step, leaving the
+/// following steps pending.
+///
+pub async fn fail_provisioning_step(pool: &PgPool, server_id: Uuid, step: &str) {
+    sqlx::query!(
+        r#"
+UPDATE provisioning_steps
+SET status = CASE WHEN step = $2 THEN 'Failed' ELSE 'Pending' END
+WHERE server_id = $1 AND position >= (
+    SELECT position FROM provisioning_steps
+    WHERE server_id = $1 AND step = $2
+)
+            "#,
+        server_id,
+        step
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Simulates a provisioning whose job crashed while running the given step,
+/// leaving the step running and the following steps pending.
+///
+pub async fn interrupt_provisioning_step(pool: &PgPool, server_id: Uuid, step: &str) {
+    sqlx::query!(
+        r#"
+UPDATE provisioning_steps
+SET status      = CASE WHEN step = $2 THEN 'Running' ELSE 'Pending' END,
+    finished_at = NULL
+WHERE server_id = $1 AND position >= (
+    SELECT position FROM provisioning_steps
+    WHERE server_id = $1 AND step = $2
+)
+            "#,
+        server_id,
+        step
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+UPDATE servers SET status = 'SettingUp' WHERE id = $1
+            "#,
+        server_id
+    )
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// Waits until the server reaches the status, which an action running in the
+/// background moves it to. Gives up after a few seconds, leaving the failure
+/// to the assertions of the test.
+///
+pub async fn wait_for_status(pool: &PgPool, server_id: Uuid, status: ServerStatus) {
+    wait_for_server(pool, server_id, |current| current == Some(status)).await;
+}
+
+/// Waits until the server is deleted by the job running in the background.
+/// Gives up after a few seconds, leaving the failure to the assertions of the
+/// test.
+///
+pub async fn wait_for_deletion(pool: &PgPool, server_id: Uuid) {
+    wait_for_server(pool, server_id, |current| current.is_none()).await;
+}
+
+/// Checks the status of the server, `None` once it is deleted, until `done`
+/// accepts it.
+///
+async fn wait_for_server(
+    pool: &PgPool,
+    server_id: Uuid,
+    done: impl Fn(Option<ServerStatus>) -> bool,
+) {
+    for _ in 0..STATUS_CHECKS {
+        let current = sqlx::query_scalar!(
+            r#"
+SELECT status FROM servers WHERE id = $1
+            "#,
+            server_id
+        )
+        .fetch_optional(pool)
+        .await
+        .unwrap();
+        if done(current.map(ServerStatus::from)) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Applies the migrations of the workspace to the pool, for the tests that
+/// don't get a migrated pool from `#[sqlx::test]`.
+///
+pub async fn migrate(pool: &PgPool) {
+    sqlx::migrate!("../../migrations").run(pool).await.unwrap();
+}
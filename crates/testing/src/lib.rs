@@ -0,0 +1,17 @@
+//! Fixtures shared by the tests of the workspace and of the crates embedding
+//! the server.
+//!
+//! `TestApp` runs the server against a test database, with Proxmox, payments,
+//! mail and the CAPTCHA mocked. The builders fill the database with a catalog,
+//! register users and order servers, and `requests` wraps the API calls.
+
+pub mod app;
+pub mod builders;
+pub mod database;
+pub mod mocks;
+pub mod payload;
+pub mod requests;
+
+pub use app::{TestApp, TestData};
+pub use builders::{CatalogBuilder, ServerBuilder, TestUser, UserBuilder};
+pub use mocks::{MockCaptchaProvider, MockMailer, MockPaymentProvider, MockProxmoxClient, Outcome};
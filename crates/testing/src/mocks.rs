@@ -0,0 +1,508 @@
+﻿use async_trait::async_trait;
+use dashboard_common::prelude::{Error, Result};
+use dashboard_server::captcha::CaptchaProvider;
+use dashboard_server::mail::Mailer;
+use dashboard_server::mail::types::Email;
+use dashboard_server::model::types::BackupMode;
+use dashboard_server::payments::PaymentProvider;
+use dashboard_server::payments::stripe;
+use dashboard_server::payments::types::*;
+use dashboard_server::proxmox::Proxmox;
+use dashboard_server::proxmox::types::*;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Scripted outcome of a single call of a mock Proxmox method.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Behaves as the unscripted mock.
+    Succeed,
+    /// Fails with the message.
+    Fail(String),
+    /// Waits before behaving as the unscripted mock.
+    Delay(Duration),
+    /// Reports the task as still running, only for `task_status`.
+    Pending,
+}
+
+/// What the scripted outcome left to the method to do.
+///
+#[derive(Debug, PartialEq)]
+enum Played {
+    Default,
+    Pending,
+}
+
+/// Mock Proxmox client for testing.
+///
+/// Every method succeeds with canned data unless scripted otherwise. The
+/// outcomes scripted for a method are played one per call, in order, and the
+/// method succeeds again once they run out. Every call is recorded by the name
+/// of the method, so the tests can check what was called and how often.
+///
+/// # Fields
+///
+/// * `fail_config`: Makes every VM configuration fail.
+/// * `missing_vm`: Reports every VM as missing.
+/// * `not_template`: Reports every VM as a regular VM instead of a template.
+/// * `deleted`: IDs of the deleted VMs.
+/// * `cloned_to`: Storages of the cloned VMs.
+/// * `passwords`: Users and passwords set through the guest agent.
+/// * `disks`: Devices and sizes in GB of the disks attached through the
+///   configuration or grown, besides the 20 GB boot disk `scsi0`.
+/// * `scripts`: Scripts run through the guest agent.
+/// * `cdroms`: ISOs inserted into the CD-ROM drives, `None` for ejected.
+/// * `backup_notes`: Notes of the backups started, each listed as an archive
+///   besides an untagged one, as left by a deleted VM with the same ID.
+/// * `boot_orders`: Boot orders set, the devices joined by `;`.
+/// * `pending_tasks`: Keeps every task running, may be switched on after the
+///   setup.
+/// * `script`: Outcomes left to play, by method.
+/// * `calls`: Names of the called methods, in order.
+///
+#[derive(Default)]
+pub struct MockProxmoxClient {
+    pub fail_config: bool,
+    pub missing_vm: bool,
+    pub not_template: bool,
+    pub deleted: Mutex<Vec<i32>>,
+    pub cloned_to: Mutex<Vec<Option<String>>>,
+    pub passwords: Mutex<Vec<(String, String)>>,
+    pub disks: Mutex<Vec<(String, i32)>>,
+    pub scripts: Mutex<Vec<String>>,
+    pub cdroms: Mutex<Vec<Option<String>>>,
+    pub backup_notes: Mutex<Vec<String>>,
+    pub boot_orders: Mutex<Vec<String>>,
+    pub pending_tasks: AtomicBool,
+    script: Mutex<HashMap<&'static str, VecDeque<Outcome>>>,
+    calls: Mutex<Vec<&'static str>>,
+}
+
+impl MockProxmoxClient {
+    /// Appends outcomes to the script of a method.
+    ///
+    /// # Arguments
+    ///
+    /// * `method`: Name of the `Proxmox` trait method, like `vm_config`.
+    /// * `outcomes`: Outcomes of the following calls, in order.
+    ///
+    pub fn script(&self, method: &'static str, outcomes: impl IntoIterator<Item = Outcome>) {
+        self.script
+            .lock()
+            .unwrap()
+            .entry(method)
+            .or_default()
+            .extend(outcomes);
+    }
+
+    /// Makes the following `times` calls of a method fail, the ones after
+    /// succeed.
+    ///
+    pub fn fail_times(&self, method: &'static str, times: usize) {
+        let message = format!("mock {method} failure");
+        self.script(method, vec![Outcome::Fail(message); times]);
+    }
+
+    /// Keeps the tasks running for the following `polls` status checks.
+    ///
+    pub fn pending_for(&self, polls: usize) {
+        self.script("task_status", vec![Outcome::Pending; polls]);
+    }
+
+    /// Returns the names of the called methods, in order.
+    ///
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Returns how many times a method was called.
+    ///
+    pub fn call_count(&self, method: &str) -> usize {
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| **call == method)
+            .count()
+    }
+
+    /// Records the call and plays the next scripted outcome of the method.
+    ///
+    async fn play(&self, method: &'static str) -> Result<Played> {
+        self.calls.lock().unwrap().push(method);
+        let outcome = self
+            .script
+            .lock()
+            .unwrap()
+            .get_mut(method)
+            .and_then(VecDeque::pop_front);
+
+        match outcome {
+            None | Some(Outcome::Succeed) => Ok(Played::Default),
+            Some(Outcome::Fail(message)) => Err(Error::Any(message)),
+            Some(Outcome::Delay(delay)) => {
+                tokio::time::sleep(delay).await;
+                Ok(Played::Default)
+            }
+            Some(Outcome::Pending) => Ok(Played::Pending),
+        }
+    }
+}
+
+#[async_trait]
+impl Proxmox for MockProxmoxClient {
+    async fn start(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+        self.play("start").await?;
+        Ok("mock_process_id".into())
+    }
+    async fn shutdown(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+        self.play("shutdown").await?;
+        Ok("mock_process_id".into())
+    }
+    async fn stop(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+        self.play("stop").await?;
+        Ok("mock_process_id".into())
+    }
+    async fn reboot(&self, _vm: VmRef) -> Result<UniqueProcessId> {
+        self.play("reboot").await?;
+        Ok("mock_process_id".into())
+    }
+    async fn create(&self, _vm: VmRef, storage: Option<&str>) -> Result<(i32, UniqueProcessId)> {
+        self.play("create").await?;
+        self.cloned_to
+            .lock()
+            .unwrap()
+            .push(storage.map(str::to_owned));
+        Ok((101, "mock_process_id".into()))
+    }
+    async fn delete(&self, vm: VmRef) -> Result<UniqueProcessId> {
+        self.play("delete").await?;
+        self.deleted.lock().unwrap().push(vm.id);
+        Ok("mock_process_id".into())
+    }
+    async fn vm_config(&self, _vm: VmRef, config: VmConfig) -> Result<UniqueProcessId> {
+        self.play("vm_config").await?;
+        if self.fail_config {
+            return Err(Error::Any("mock config failure".to_owned()));
+        }
+        let attached = (0..31).filter_map(|index| {
+            let device = format!("scsi{index}");
+            let (_, size) = config.get(&device)?.split_once(':')?;
+            Some((device, size.parse().ok()?))
+        });
+        self.disks.lock().unwrap().extend(attached);
+        Ok("mock_process_id".into())
+    }
+    async fn vm_current_config(&self, _vm: VmRef) -> Result<VmCurrentConfig> {
+        self.play("vm_current_config").await?;
+        Ok(VmCurrentConfig {
+            net0: Some("virtio=BC:24:11:2A:3B:4C,bridge=vmbr0".to_owned()),
+            template: (!self.not_template).then_some(1),
+        })
+    }
+    async fn vm_drives(&self, _vm: VmRef) -> Result<Vec<VmDrive>> {
+        self.play("vm_drives").await?;
+        let mut drives = vec![
+            VmDrive::parse("ide2", "local-lvm:vm-101-cloudinit,media=cdrom,size=4M").unwrap(),
+            VmDrive::parse("scsi0", "local-lvm:vm-101-disk-0,size=20G").unwrap(),
+        ];
+        for (device, size_gb) in self.disks.lock().unwrap().iter() {
+            drives.retain(|drive| &drive.device != device);
+            drives.push(VmDrive {
+                device: device.clone(),
+                storage: Some("local-lvm".to_owned()),
+                size_gb: Some(*size_gb),
+                cdrom: false,
+            });
+        }
+        Ok(drives)
+    }
+    async fn resize_disk(&self, _vm: VmRef, disk: &str, size_gb: i32) -> Result<UniqueProcessId> {
+        self.play("resize_disk").await?;
+        self.disks.lock().unwrap().push((disk.to_owned(), size_gb));
+        Ok("mock_process_id".into())
+    }
+    async fn vm_exists(&self, _vm: VmRef) -> Result<bool> {
+        self.play("vm_exists").await?;
+        Ok(!self.missing_vm)
+    }
+    async fn firewall_options(&self, _vm: VmRef, _options: FirewallOptions) -> Result<()> {
+        self.play("firewall_options").await?;
+        Ok(())
+    }
+    async fn firewall_rules(&self, _vm: VmRef) -> Result<Vec<FirewallRuleInfo>> {
+        self.play("firewall_rules").await?;
+        Ok(Vec::new())
+    }
+    async fn create_firewall_rule(&self, _vm: VmRef, _rule: FirewallRule) -> Result<()> {
+        self.play("create_firewall_rule").await?;
+        Ok(())
+    }
+    async fn delete_firewall_rule(&self, _vm: VmRef, _pos: i32) -> Result<()> {
+        self.play("delete_firewall_rule").await?;
+        Ok(())
+    }
+    async fn vm_status(&self, _vm: VmRef) -> Result<Status> {
+        self.play("vm_status").await?;
+        Ok(Status::Running)
+    }
+    async fn vm_usage(&self, _vm: VmRef) -> Result<VmUsage> {
+        self.play("vm_usage").await?;
+        Ok(VmUsage {
+            status: Status::Running,
+            uptime: 3600,
+            cpu: 0.5,
+            mem: 1073741824,
+        })
+    }
+    async fn vm_rrd_data(&self, _vm: VmRef) -> Result<Vec<RrdSample>> {
+        self.play("vm_rrd_data").await?;
+        Ok(vec![RrdSample {
+            time: 1792152000,
+            netin: Some(1024.0),
+            netout: Some(2048.0),
+        }])
+    }
+    async fn backup(
+        &self,
+        _vm: VmRef,
+        _storage: &str,
+        _mode: BackupMode,
+        notes: &str,
+    ) -> Result<UniqueProcessId> {
+        self.play("backup").await?;
+        self.backup_notes.lock().unwrap().push(notes.to_owned());
+        Ok("mock_process_id".into())
+    }
+    async fn backups(&self, vm: VmRef, storage: &str) -> Result<Vec<BackupArchive>> {
+        self.play("backups").await?;
+        let archive = |index: usize, notes: Option<String>| BackupArchive {
+            volid: format!(
+                "{storage}:backup/vzdump-qemu-{}-2026_10_16-02_30_{index:02}.vma.zst",
+                vm.id
+            ),
+            ctime: 1792117800 + index as i64,
+            size: 1073741824,
+            format: "vma.zst".to_owned(),
+            notes,
+        };
+        let backup_notes = self.backup_notes.lock().unwrap();
+        let tagged = backup_notes
+            .iter()
+            .enumerate()
+            .map(|(index, notes)| archive(index + 1, Some(notes.clone())));
+        Ok(std::iter::once(archive(0, None)).chain(tagged).collect())
+    }
+    async fn restore(&self, _vm: VmRef, _archive: &str) -> Result<UniqueProcessId> {
+        self.play("restore").await?;
+        Ok("mock_process_id".into())
+    }
+    async fn agent_ping(&self, _vm: VmRef) -> Result<()> {
+        self.play("agent_ping").await?;
+        Ok(())
+    }
+    async fn agent_os_info(&self, _vm: VmRef) -> Result<GuestOsInfo> {
+        self.play("agent_os_info").await?;
+        Ok(GuestOsInfo {
+            id: Some("ubuntu".to_owned()),
+            name: Some("Ubuntu".to_owned()),
+            pretty_name: Some("Ubuntu 24.04.1 LTS".to_owned()),
+            version: Some("24.04.1 LTS (Noble Numbat)".to_owned()),
+            kernel_release: Some("6.8.0-45-generic".to_owned()),
+            machine: Some("x86_64".to_owned()),
+        })
+    }
+    async fn agent_network_interfaces(&self, _vm: VmRef) -> Result<Vec<GuestNetworkInterface>> {
+        self.play("agent_network_interfaces").await?;
+        let address = |ip_address: &str, ip_address_type: &str, prefix| GuestIpAddress {
+            ip_address: ip_address.to_owned(),
+            ip_address_type: ip_address_type.to_owned(),
+            prefix,
+        };
+        Ok(vec![
+            GuestNetworkInterface {
+                name: "lo".to_owned(),
+                hardware_address: Some("00:00:00:00:00:00".to_owned()),
+                ip_addresses: vec![address("127.0.0.1", "ipv4", 8), address("::1", "ipv6", 128)],
+            },
+            GuestNetworkInterface {
+                name: "eth0".to_owned(),
+                hardware_address: Some("bc:24:11:2a:3b:4c".to_owned()),
+                ip_addresses: vec![
+                    address("203.0.113.10", "ipv4", 24),
+                    address("fe80::be24:11ff:fe2a:3b4c", "ipv6", 64),
+                ],
+            },
+        ])
+    }
+    async fn agent_set_user_password(
+        &self,
+        _vm: VmRef,
+        username: &str,
+        password: &str,
+    ) -> Result<()> {
+        self.play("agent_set_user_password").await?;
+        self.passwords
+            .lock()
+            .unwrap()
+            .push((username.to_owned(), password.to_owned()));
+        Ok(())
+    }
+    async fn agent_exec(&self, _vm: VmRef, _command: &str, input: &str) -> Result<i64> {
+        self.play("agent_exec").await?;
+        self.scripts.lock().unwrap().push(input.to_owned());
+        Ok(1)
+    }
+    async fn agent_exec_status(&self, _vm: VmRef, _pid: i64) -> Result<GuestExecStatus> {
+        self.play("agent_exec_status").await?;
+        Ok(GuestExecStatus {
+            exited: true,
+            exitcode: Some(0),
+            out_data: Some("done".to_owned()),
+            err_data: None,
+        })
+    }
+    async fn list_isos(&self, _node: &str, storage: &str) -> Result<Vec<IsoImage>> {
+        self.play("list_isos").await?;
+        Ok(vec![IsoImage {
+            volid: format!("{storage}:iso/debian-12.iso"),
+            size: 658505728,
+        }])
+    }
+    async fn set_cdrom(
+        &self,
+        _vm: VmRef,
+        _device: &str,
+        iso: Option<&str>,
+    ) -> Result<UniqueProcessId> {
+        self.play("set_cdrom").await?;
+        self.cdroms.lock().unwrap().push(iso.map(str::to_owned));
+        Ok("mock_process_id".into())
+    }
+    async fn set_boot_order(&self, _vm: VmRef, devices: &[String]) -> Result<UniqueProcessId> {
+        self.play("set_boot_order").await?;
+        self.boot_orders.lock().unwrap().push(devices.join(";"));
+        Ok("mock_process_id".into())
+    }
+    async fn list_nodes(&self) -> Result<Vec<NodeListItem>> {
+        self.play("list_nodes").await?;
+        Ok(vec![
+            NodeListItem {
+                node: "pve".to_owned(),
+                status: "online".to_owned(),
+            },
+            NodeListItem {
+                node: "pve-2".to_owned(),
+                status: "offline".to_owned(),
+            },
+        ])
+    }
+    async fn node_status(&self, _node: &str) -> Result<NodeStatus> {
+        self.play("node_status").await?;
+        Ok(NodeStatus {
+            uptime: 86400,
+            cpu: 0.25,
+            cpuinfo: NodeCpuInfo { cpus: 16 },
+            memory: NodeUsage {
+                used: 17179869184,
+                total: 68719476736,
+            },
+            rootfs: NodeUsage {
+                used: 10737418240,
+                total: 107374182400,
+            },
+        })
+    }
+    async fn list_storages(&self, _node: &str) -> Result<Vec<StorageInfo>> {
+        self.play("list_storages").await?;
+        Ok(vec![
+            StorageInfo {
+                storage: "local".to_owned(),
+                kind: "dir".to_owned(),
+                content: "iso,vztmpl,backup".to_owned(),
+                active: 1,
+                shared: 0,
+                used: 0,
+                total: 0,
+                avail: 0,
+            },
+            StorageInfo {
+                storage: "local-zfs".to_owned(),
+                kind: "zfspool".to_owned(),
+                content: "images,rootdir".to_owned(),
+                active: 1,
+                shared: 0,
+                used: 1073741824,
+                total: 107374182400,
+                avail: 106300440576,
+            },
+        ])
+    }
+    async fn task_status(&self, _task: &TaskRef) -> Result<TaskStatus> {
+        let played = self.play("task_status").await?;
+        if played == Played::Pending || self.pending_tasks.load(Ordering::Relaxed) {
+            return Ok(TaskStatus::Pending);
+        }
+        Ok(TaskStatus::Completed)
+    }
+}
+
+/// Mock payment provider for testing. Accepts any webhook signature.
+///
+#[derive(Default)]
+pub struct MockPaymentProvider;
+
+#[async_trait]
+impl PaymentProvider for MockPaymentProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+    async fn create_checkout(&self, request: CheckoutRequest) -> Result<CheckoutSession> {
+        Ok(CheckoutSession {
+            id: format!("mock_session_{}", request.invoice_id),
+            url: "https://checkout.mock/session".to_owned(),
+        })
+    }
+    fn verify_webhook(&self, payload: &[u8], _signature: &str) -> Result<PaymentEvent> {
+        stripe::parse_event(payload)
+    }
+}
+
+/// Mock mailer for testing. Keeps the sent emails instead of delivering them.
+///
+#[derive(Default)]
+pub struct MockMailer {
+    pub sent: Mutex<Vec<Email>>,
+}
+
+#[async_trait]
+impl Mailer for MockMailer {
+    async fn send(&self, email: Email) -> Result<()> {
+        self.sent.lock().unwrap().push(email);
+        Ok(())
+    }
+}
+
+/// Mock CAPTCHA provider for testing. Accepts only the `SOLVED` token.
+///
+#[derive(Default)]
+pub struct MockCaptchaProvider;
+
+impl MockCaptchaProvider {
+    /// Token of a solved CAPTCHA.
+    pub const SOLVED: &str = "solved";
+}
+
+#[async_trait]
+impl CaptchaProvider for MockCaptchaProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+    async fn verify(&self, token: &str, _remote_ip: Option<IpAddr>) -> Result<bool> {
+        Ok(token == Self::SOLVED)
+    }
+}
@@ -0,0 +1,21 @@
+﻿use crate::builders::{ServerBuilder, UserBuilder};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Returns the registration payload of the default test user.
+///
+pub fn register_user() -> Value {
+    UserBuilder::new().payload()
+}
+
+/// Returns the login payload of the default test user.
+///
+pub fn login_user() -> Value {
+    UserBuilder::new().login_payload()
+}
+
+/// Returns the default order of a server of the product.
+///
+pub fn new_server(product_id: Uuid) -> Value {
+    ServerBuilder::new(product_id).payload()
+}
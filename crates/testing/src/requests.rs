@@ -1,4 +1,4 @@
-﻿use crate::helpers::TestApp;
+﻿use crate::app::TestApp;
 use dashboard_server::web::types::Response;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
@@ -27,6 +27,36 @@ pub async fn post_response(
         .unwrap()
 }
 
+pub async fn put_response(
+    app: &TestApp,
+    endpoint: &str,
+    bearer: &str,
+    payload: &Value,
+) -> reqwest::Response {
+    app.client
+        .put(endpoint)
+        .bearer_auth(bearer)
+        .json(&payload)
+        .send()
+        .await
+        .unwrap()
+}
+
+pub async fn patch_response(
+    app: &TestApp,
+    endpoint: &str,
+    bearer: &str,
+    payload: &Value,
+) -> reqwest::Response {
+    app.client
+        .patch(endpoint)
+        .bearer_auth(bearer)
+        .json(&payload)
+        .send()
+        .await
+        .unwrap()
+}
+
 pub async fn delete_response(app: &TestApp, endpoint: &str, bearer: &str) -> reqwest::Response {
     app.client
         .delete(endpoint)
@@ -40,7 +70,7 @@ pub async fn post_result<T>(app: &TestApp, endpoint: &str, payload: &Value) -> T
 where
     T: DeserializeOwned,
 {
-    post_response(&app, endpoint, "", payload)
+    post_response(app, endpoint, "", payload)
         .await
         .json::<Response<T>>()
         .await
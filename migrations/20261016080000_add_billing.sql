@@ -0,0 +1,40 @@
+-- Create invoices table
+CREATE TABLE invoices
+(
+    id           UUID PRIMARY KEY         DEFAULT gen_random_uuid(),
+    user_id      UUID   NOT NULL REFERENCES users (id),
+    service_id   UUID REFERENCES services (id) ON DELETE SET NULL,
+    description  TEXT   NOT NULL,
+    amount_cents BIGINT NOT NULL CHECK (amount_cents >= 0),
+    currency     TEXT   NOT NULL,
+    status       TEXT   NOT NULL,
+    created_at   TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    paid_at      TIMESTAMP WITH TIME ZONE
+);
+
+-- Create payments table, one row per checkout attempt
+CREATE TABLE payments
+(
+    id           UUID PRIMARY KEY         DEFAULT gen_random_uuid(),
+    invoice_id   UUID   NOT NULL REFERENCES invoices (id),
+    provider     TEXT   NOT NULL,
+    provider_ref TEXT   NOT NULL,
+    amount_cents BIGINT NOT NULL,
+    status       TEXT   NOT NULL,
+    created_at   TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (provider, provider_ref)
+);
+
+-- Create processed payment provider events table, used for idempotency
+CREATE TABLE payment_events
+(
+    provider     TEXT NOT NULL,
+    event_id     TEXT NOT NULL,
+    event_type   TEXT NOT NULL,
+    processed_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (provider, event_id)
+);
+
+CREATE INDEX idx_invoices_user_id ON invoices (user_id);
+CREATE INDEX idx_invoices_service_id ON invoices (service_id);
+CREATE INDEX idx_payments_invoice_id ON payments (invoice_id);
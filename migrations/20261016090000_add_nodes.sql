@@ -0,0 +1,12 @@
+-- Create Proxmox nodes table with physical capacity and overcommit policy.
+-- NULL ratios fall back to the cluster-wide defaults from the configuration.
+CREATE TABLE nodes
+(
+    name           TEXT PRIMARY KEY,
+    cpu_cores      INTEGER NOT NULL CHECK (cpu_cores > 0),
+    ram_gb         INTEGER NOT NULL CHECK (ram_gb > 0),
+    cpu_overcommit DOUBLE PRECISION CHECK (cpu_overcommit > 0),
+    ram_overcommit DOUBLE PRECISION CHECK (ram_overcommit > 0)
+);
+
+CREATE INDEX idx_servers_node_name ON servers (node_name);
@@ -0,0 +1,18 @@
+-- Create usage records table, one row per server per collector run.
+-- Service and server references are kept nullable so usage of deleted servers
+-- is still billable and reportable.
+CREATE TABLE usage_records
+(
+    id           UUID PRIMARY KEY                  DEFAULT gen_random_uuid(),
+    user_id      UUID                     NOT NULL REFERENCES users (id),
+    service_id   UUID REFERENCES services (id) ON DELETE SET NULL,
+    server_id    UUID REFERENCES servers (id) ON DELETE SET NULL,
+    uptime_hours DOUBLE PRECISION         NOT NULL CHECK (uptime_hours >= 0),
+    cpu_usage    DOUBLE PRECISION,
+    ram_mb       BIGINT,
+    sampled_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_usage_records_user_id_sampled_at ON usage_records (user_id, sampled_at);
+CREATE INDEX idx_usage_records_service_id ON usage_records (service_id);
+CREATE INDEX idx_usage_records_server_id ON usage_records (server_id);
@@ -0,0 +1,37 @@
+-- Allow marking users as administrators
+ALTER TABLE users
+    ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Create promo codes table
+CREATE TABLE promo_codes
+(
+    id              UUID PRIMARY KEY                  DEFAULT gen_random_uuid(),
+    code            TEXT                     NOT NULL UNIQUE,
+    amount_cents    BIGINT                   NOT NULL CHECK (amount_cents > 0),
+    max_redemptions INTEGER CHECK (max_redemptions > 0),
+    redemptions     INTEGER                  NOT NULL DEFAULT 0,
+    expires_at      TIMESTAMP WITH TIME ZONE,
+    created_at      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Create credits ledger, the balance of a user is the sum of their entries.
+-- Issued credit is positive, credit applied to an invoice is negative.
+CREATE TABLE credits
+(
+    id            UUID PRIMARY KEY                  DEFAULT gen_random_uuid(),
+    user_id       UUID                     NOT NULL REFERENCES users (id),
+    amount_cents  BIGINT                   NOT NULL CHECK (amount_cents <> 0),
+    reason        TEXT                     NOT NULL,
+    promo_code_id UUID REFERENCES promo_codes (id),
+    invoice_id    UUID REFERENCES invoices (id),
+    issued_by     UUID REFERENCES users (id),
+    created_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, promo_code_id)
+);
+
+-- Track the part of an invoice covered by credit
+ALTER TABLE invoices
+    ADD COLUMN credit_cents BIGINT NOT NULL DEFAULT 0 CHECK (credit_cents >= 0);
+
+CREATE INDEX idx_credits_user_id ON credits (user_id);
+CREATE INDEX idx_credits_invoice_id ON credits (invoice_id);
@@ -0,0 +1,14 @@
+-- Create quotas table. A row limits either a whole account, or the servers of
+-- one product within every account. NULL limits are unlimited, and accounts
+-- without their own row fall back to the defaults from the configuration.
+CREATE TABLE quotas
+(
+    id            UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id       UUID UNIQUE REFERENCES users (id) ON DELETE CASCADE,
+    product_id    UUID UNIQUE REFERENCES products (id) ON DELETE CASCADE,
+    max_servers   INTEGER CHECK (max_servers >= 0),
+    max_cpu_cores INTEGER CHECK (max_cpu_cores >= 0),
+    max_ram_gb    INTEGER CHECK (max_ram_gb >= 0),
+    max_ips       INTEGER CHECK (max_ips >= 0),
+    CHECK ((user_id IS NULL) <> (product_id IS NULL))
+);
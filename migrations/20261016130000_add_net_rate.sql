@@ -0,0 +1,7 @@
+-- Allow products to limit the network bandwidth of their servers, in Mbit/s.
+-- Servers keep the limit that was applied to their VM, NULL is unlimited.
+ALTER TABLE products
+    ADD COLUMN net_rate_mbps INTEGER CHECK (net_rate_mbps > 0);
+
+ALTER TABLE servers
+    ADD COLUMN net_rate_mbps INTEGER CHECK (net_rate_mbps > 0);
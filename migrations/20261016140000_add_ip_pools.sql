@@ -0,0 +1,10 @@
+-- Keep the block a network was created from. Networks imported from WHMCS or
+-- inserted by hand don't have one.
+ALTER TABLE networks
+    ADD COLUMN cidr TEXT;
+
+-- Reserved addresses are never allocated to new servers, and every address
+-- exists only once within its network, so imports can be repeated safely.
+ALTER TABLE ip_addresses
+    ADD COLUMN is_reserved BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD CONSTRAINT ip_addresses_network_ip_key UNIQUE (network_id, ip_address);
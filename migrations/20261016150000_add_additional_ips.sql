@@ -0,0 +1,7 @@
+-- Allow servers to hold additional addresses. The primary address is attached
+-- to the first network device (net0), every additional one gets its own device
+-- with the same index. Free addresses keep the default index.
+ALTER TABLE ip_addresses
+    DROP CONSTRAINT ip_addresses_server_id_key,
+    ADD COLUMN nic_index INTEGER NOT NULL DEFAULT 0 CHECK (nic_index BETWEEN 0 AND 31),
+    ADD CONSTRAINT ip_addresses_server_nic_key UNIQUE (server_id, nic_index);
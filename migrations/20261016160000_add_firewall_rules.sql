@@ -0,0 +1,21 @@
+-- VM-level firewall settings of servers. Proxmox drops incoming traffic by
+-- default once the firewall is enabled.
+ALTER TABLE servers
+    ADD COLUMN firewall_enabled   BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN firewall_policy_in TEXT    NOT NULL DEFAULT 'Drop';
+
+-- Firewall rules of servers, mirrored to Proxmox. Proxmox identifies rules by
+-- their position only, so every rule carries its ID in the rule comment.
+CREATE TABLE firewall_rules
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    server_id  UUID        NOT NULL REFERENCES servers (id) ON DELETE CASCADE,
+    direction  TEXT        NOT NULL,
+    action     TEXT        NOT NULL,
+    protocol   TEXT        NOT NULL,
+    port       INTEGER     NOT NULL CHECK (port BETWEEN 1 AND 65535),
+    source     TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_firewall_rules_server_id ON firewall_rules (server_id);
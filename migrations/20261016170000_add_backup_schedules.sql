@@ -0,0 +1,13 @@
+-- Backup schedules of servers in the five-field cron format, in UTC. The
+-- backup worker triggers a vzdump task once `next_run_at` has passed.
+CREATE TABLE backup_schedules
+(
+    server_id   UUID PRIMARY KEY REFERENCES servers (id) ON DELETE CASCADE,
+    cron        TEXT        NOT NULL,
+    mode        TEXT        NOT NULL DEFAULT 'Snapshot',
+    next_run_at TIMESTAMPTZ NOT NULL,
+    last_run_at TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_backup_schedules_next_run_at ON backup_schedules (next_run_at);
@@ -0,0 +1,20 @@
+-- Periodic jobs of the scheduler, one row per job. A replica runs a job only
+-- after claiming its row, which moves `next_run_at` forward and leases the job
+-- until `locked_until`, so several replicas never run the same job twice.
+CREATE TABLE scheduled_jobs
+(
+    name             TEXT PRIMARY KEY,
+    cron             TEXT        NOT NULL,
+    next_run_at      TIMESTAMPTZ NOT NULL,
+    locked_until     TIMESTAMPTZ,
+    last_started_at  TIMESTAMPTZ,
+    last_finished_at TIMESTAMPTZ,
+    last_error       TEXT
+);
+
+-- Usage invoices cover a billing period, at most one per service and period.
+ALTER TABLE invoices
+    ADD COLUMN period_start TIMESTAMPTZ;
+
+CREATE UNIQUE INDEX idx_invoices_service_id_period_start ON invoices (service_id, period_start)
+    WHERE period_start IS NOT NULL;
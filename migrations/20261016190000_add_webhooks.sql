@@ -0,0 +1,43 @@
+-- Webhooks registered by users, each subscribed to a set of lifecycle events.
+-- The secret signs every delivery, so receivers can verify its origin.
+CREATE TABLE webhooks
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id    UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    url        TEXT        NOT NULL,
+    secret     TEXT        NOT NULL,
+    events     TEXT[]      NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Events queued for a webhook, delivered by the webhook worker and retried
+-- with backoff until `next_attempt_at` while pending.
+CREATE TABLE webhook_deliveries
+(
+    id              UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    webhook_id      UUID        NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+    event           TEXT        NOT NULL,
+    payload         JSONB       NOT NULL,
+    status          TEXT        NOT NULL DEFAULT 'Pending',
+    attempts        INTEGER     NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at    TIMESTAMPTZ
+);
+
+-- Every attempt to deliver an event, with the response of the receiver.
+CREATE TABLE webhook_attempts
+(
+    id              UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    delivery_id     UUID        NOT NULL REFERENCES webhook_deliveries (id) ON DELETE CASCADE,
+    response_status INTEGER,
+    error           TEXT,
+    duration_ms     INTEGER     NOT NULL,
+    attempted_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhooks_user_id ON webhooks (user_id);
+CREATE INDEX idx_webhook_deliveries_webhook_id ON webhook_deliveries (webhook_id, created_at);
+CREATE INDEX idx_webhook_deliveries_next_attempt_at ON webhook_deliveries (next_attempt_at)
+    WHERE status = 'Pending';
+CREATE INDEX idx_webhook_attempts_delivery_id ON webhook_attempts (delivery_id);
@@ -0,0 +1,16 @@
+-- Steps of a server provisioning, run in `position` order. Every step is
+-- persisted, so a failed provisioning resumes from the failed step instead of
+-- starting over. `task_id` keeps the Proxmox task started by the step.
+CREATE TABLE provisioning_steps
+(
+    server_id   UUID    NOT NULL REFERENCES servers (id) ON DELETE CASCADE,
+    step        TEXT    NOT NULL,
+    position    INTEGER NOT NULL,
+    status      TEXT    NOT NULL DEFAULT 'Pending',
+    attempts    INTEGER NOT NULL DEFAULT 0,
+    task_id     TEXT,
+    error       TEXT,
+    started_at  TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ,
+    PRIMARY KEY (server_id, step)
+);
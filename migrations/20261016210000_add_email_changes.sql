@@ -0,0 +1,11 @@
+-- Pending change of a user's email address. The new address is applied once
+-- the user confirms it with the token sent there, only the SHA-256 hash of the
+-- token is stored. A user has at most one pending change.
+CREATE TABLE email_changes
+(
+    user_id    UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    new_email  TEXT        NOT NULL,
+    token_hash TEXT        NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
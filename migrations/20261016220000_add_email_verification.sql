@@ -0,0 +1,27 @@
+-- Accounts without a creation time count as created at their last update, so
+-- the backfill below leaves no account unverified.
+UPDATE users
+SET created_at = COALESCE(updated_at, CURRENT_TIMESTAMP)
+WHERE created_at IS NULL;
+
+ALTER TABLE users
+    ALTER COLUMN created_at SET NOT NULL;
+
+-- Time the user confirmed the email address, accounts with an unconfirmed
+-- address can't order servers. Registration inserts NULL explicitly, so the
+-- default only applies to existing and imported accounts, which are trusted.
+ALTER TABLE users
+    ADD COLUMN email_verified_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP;
+
+UPDATE users
+SET email_verified_at = created_at;
+
+-- Pending verification of a user's email address, only the SHA-256 hash of
+-- the token is stored. A user has at most one pending verification.
+CREATE TABLE email_verifications
+(
+    user_id    UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    token_hash TEXT        NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,5 @@
+-- Time the user deleted the account. The row is kept anonymized, because the
+-- invoices and payments must be retained, and the servers of the account are
+-- deprovisioned afterwards.
+ALTER TABLE users
+    ADD COLUMN deleted_at TIMESTAMPTZ;
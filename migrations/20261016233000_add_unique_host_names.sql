@@ -0,0 +1,14 @@
+-- Host names are unique across all servers, compared case-insensitively. The
+-- existing names are normalized first, and duplicates, but the first one, get
+-- a suffix with the beginning of the server ID.
+UPDATE servers
+SET host_name = rtrim(lower(btrim(host_name)), '.');
+
+UPDATE servers
+SET host_name = host_name || '-' || left(id::TEXT, 8)
+WHERE id IN (SELECT id
+             FROM (SELECT id, row_number() OVER (PARTITION BY host_name ORDER BY id) AS position
+                   FROM servers) AS ranked
+             WHERE position > 1);
+
+CREATE UNIQUE INDEX servers_host_name_key ON servers (lower(host_name));
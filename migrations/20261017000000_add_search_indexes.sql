@@ -0,0 +1,10 @@
+-- Trigram indexes behind the admin search, matching any part of the names,
+-- email addresses, host names and IP addresses.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_users_name_trgm ON users USING gin ((first_name || ' ' || last_name) gin_trgm_ops);
+CREATE INDEX idx_users_email_trgm ON users USING gin (email gin_trgm_ops);
+CREATE INDEX idx_servers_host_name_trgm ON servers USING gin (host_name gin_trgm_ops);
+CREATE INDEX idx_servers_vm_id ON servers (vm_id);
+CREATE INDEX idx_ip_addresses_ip_address_trgm ON ip_addresses USING gin (ip_address gin_trgm_ops);
+CREATE INDEX idx_products_name_trgm ON products USING gin (name gin_trgm_ops);
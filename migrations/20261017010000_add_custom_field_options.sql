@@ -0,0 +1,5 @@
+-- Custom fields list the values offered with the product. The values of the
+-- OS template and datacenter location fields name existing templates and
+-- datacenters, which is checked when the fields are saved.
+ALTER TABLE custom_fields
+    ADD COLUMN options TEXT[] NOT NULL DEFAULT '{}';
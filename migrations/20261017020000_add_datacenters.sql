@@ -0,0 +1,39 @@
+-- Create datacenters table. The code is the name customers pick in the
+-- datacenter location field; inactive or full datacenters take no new orders.
+CREATE TABLE datacenters
+(
+    code         TEXT PRIMARY KEY,
+    display_name TEXT    NOT NULL,
+    cluster_url  TEXT,
+    is_active    BOOLEAN NOT NULL DEFAULT TRUE,
+    is_full      BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- Every datacenter named by a network so far becomes a datacenter.
+INSERT INTO datacenters (code, display_name)
+SELECT DISTINCT datacenter_name, datacenter_name
+FROM networks;
+
+ALTER TABLE networks
+    ADD CONSTRAINT networks_datacenter_name_fkey
+        FOREIGN KEY (datacenter_name) REFERENCES datacenters (code) ON UPDATE CASCADE;
+
+-- Templates may be limited to the datacenter whose cluster holds them.
+ALTER TABLE templates
+    ADD COLUMN datacenter_code TEXT REFERENCES datacenters (code) ON UPDATE CASCADE;
+
+-- Datacenters a product is offered in.
+CREATE TABLE product_datacenters
+(
+    product_id      UUID NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+    datacenter_code TEXT NOT NULL REFERENCES datacenters (code) ON UPDATE CASCADE ON DELETE CASCADE,
+    PRIMARY KEY (product_id, datacenter_code)
+);
+
+-- Existing products stay available wherever they could be ordered.
+INSERT INTO product_datacenters (product_id, datacenter_code)
+SELECT p.id, d.code
+FROM products p
+         CROSS JOIN datacenters d;
+
+CREATE INDEX idx_networks_datacenter_name ON networks (datacenter_name);
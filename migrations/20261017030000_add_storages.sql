@@ -0,0 +1,8 @@
+-- Proxmox storage the disks of new servers are cloned to. The storage of the
+-- product takes precedence over the one of the template, without either the
+-- clone stays on the storage of the template.
+ALTER TABLE templates
+    ADD COLUMN storage TEXT;
+
+ALTER TABLE products
+    ADD COLUMN storage TEXT;
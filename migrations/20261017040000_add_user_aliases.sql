@@ -0,0 +1,7 @@
+-- WHMCS clients merged into the account of an earlier client sharing their
+-- email. Their services are migrated to that account.
+CREATE TABLE user_aliases
+(
+    whmcs_id INT PRIMARY KEY,
+    user_id  UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE
+);
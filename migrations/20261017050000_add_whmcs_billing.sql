@@ -0,0 +1,20 @@
+-- Invoices and payments migrated from WHMCS keep the ID of their source row.
+ALTER TABLE invoices
+    ADD COLUMN whmcs_id INT UNIQUE;
+
+ALTER TABLE payments
+    ADD COLUMN whmcs_id INT UNIQUE;
+
+-- Lines of the invoices migrated from WHMCS. Invoices created by the
+-- Dashboard have a single service and keep no lines.
+CREATE TABLE invoice_items
+(
+    id           UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    invoice_id   UUID   NOT NULL REFERENCES invoices (id) ON DELETE CASCADE,
+    service_id   UUID REFERENCES services (id) ON DELETE SET NULL,
+    description  TEXT   NOT NULL,
+    amount_cents BIGINT NOT NULL,
+    whmcs_id     INT UNIQUE
+);
+
+CREATE INDEX idx_invoice_items_invoice_id ON invoice_items (invoice_id);
@@ -0,0 +1,30 @@
+-- Support tickets migrated from WHMCS, kept as the support history of their
+-- user. The reference is the ticket ID shown to the client.
+CREATE TABLE tickets
+(
+    id         UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id    UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    reference  TEXT NOT NULL,
+    subject    TEXT NOT NULL,
+    message    TEXT NOT NULL,
+    status     TEXT NOT NULL,
+    priority   TEXT NOT NULL,
+    created_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ,
+    whmcs_id   INT UNIQUE
+);
+
+-- Replies of the tickets, by the client or by the staff.
+CREATE TABLE ticket_replies
+(
+    id         UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    ticket_id  UUID    NOT NULL REFERENCES tickets (id) ON DELETE CASCADE,
+    author     TEXT    NOT NULL,
+    from_staff BOOLEAN NOT NULL,
+    message    TEXT    NOT NULL,
+    created_at TIMESTAMPTZ,
+    whmcs_id   INT UNIQUE
+);
+
+CREATE INDEX idx_tickets_user_id ON tickets (user_id);
+CREATE INDEX idx_ticket_replies_ticket_id ON ticket_replies (ticket_id);
@@ -0,0 +1,23 @@
+-- Transfers of a server to another account. A transfer requested by the owner
+-- waits until the recipient accepts or declines it, one made by an
+-- administrator is completed at once. The rows are kept as the ownership
+-- history of the services.
+CREATE TABLE server_transfers
+(
+    id                     UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    service_id             UUID        NOT NULL REFERENCES services (id) ON DELETE CASCADE,
+    from_user_id           UUID        NOT NULL REFERENCES users (id),
+    to_user_id             UUID        NOT NULL REFERENCES users (id),
+    initiated_by           UUID        NOT NULL REFERENCES users (id),
+    status                 TEXT        NOT NULL DEFAULT 'Pending',
+    regenerate_credentials BOOLEAN     NOT NULL DEFAULT FALSE,
+    created_at             TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at           TIMESTAMPTZ,
+    CHECK (from_user_id <> to_user_id)
+);
+
+-- A service has at most one pending transfer.
+CREATE UNIQUE INDEX idx_server_transfers_pending ON server_transfers (service_id)
+    WHERE status = 'Pending';
+CREATE INDEX idx_server_transfers_from_user_id ON server_transfers (from_user_id);
+CREATE INDEX idx_server_transfers_to_user_id ON server_transfers (to_user_id);
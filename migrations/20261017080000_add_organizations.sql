@@ -0,0 +1,96 @@
+-- Organizations own the services, and users belong to one or more of them
+-- with a role. Every user has a personal organization with the user as its
+-- only owner, so `services.user_id` keeps naming the user accountable for a
+-- service while `organization_id` names the organization owning it.
+CREATE TABLE organizations
+(
+    id               UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    name             TEXT        NOT NULL,
+    personal_user_id UUID UNIQUE REFERENCES users (id) ON DELETE CASCADE,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Members of an organization, either `Owner`, `Admin` or `Member`.
+CREATE TABLE organization_members
+(
+    organization_id UUID        NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    user_id         UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role            TEXT        NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX idx_organization_members_user_id ON organization_members (user_id);
+
+-- Personal organizations of the existing users, owning their services.
+INSERT INTO organizations (name, personal_user_id)
+SELECT 'Personal', id
+FROM users;
+
+INSERT INTO organization_members (organization_id, user_id, role)
+SELECT id, personal_user_id, 'Owner'
+FROM organizations;
+
+ALTER TABLE services
+    ADD COLUMN organization_id UUID REFERENCES organizations (id);
+
+UPDATE services AS svc
+SET organization_id = org.id
+FROM organizations AS org
+WHERE org.personal_user_id = svc.user_id;
+
+ALTER TABLE services
+    ALTER COLUMN organization_id SET NOT NULL;
+
+CREATE INDEX idx_services_organization_id ON services (organization_id);
+
+-- Compatibility with the code that only knows users: a new user gets a
+-- personal organization, a service inserted without an organization belongs
+-- to the personal organization of its user, and a service of a personal
+-- organization follows its user to the new personal organization.
+CREATE FUNCTION create_personal_organization() RETURNS TRIGGER AS
+$$
+BEGIN
+    WITH organization AS (
+        INSERT INTO organizations (name, personal_user_id)
+            VALUES ('Personal', NEW.id)
+            RETURNING id)
+    INSERT
+    INTO organization_members (organization_id, user_id, role)
+    SELECT id, NEW.id, 'Owner'
+    FROM organization;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER users_personal_organization
+    AFTER INSERT
+    ON users
+    FOR EACH ROW
+EXECUTE FUNCTION create_personal_organization();
+
+CREATE FUNCTION set_service_organization() RETURNS TRIGGER AS
+$$
+BEGIN
+    IF NEW.organization_id IS NULL
+        OR (TG_OP = 'UPDATE'
+            AND NEW.user_id <> OLD.user_id
+            AND NEW.organization_id = OLD.organization_id
+            AND OLD.organization_id IN (SELECT id FROM organizations WHERE personal_user_id = OLD.user_id))
+    THEN
+        SELECT id
+        INTO NEW.organization_id
+        FROM organizations
+        WHERE personal_user_id = NEW.user_id;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER services_organization
+    BEFORE INSERT OR UPDATE OF user_id, organization_id
+    ON services
+    FOR EACH ROW
+EXECUTE FUNCTION set_service_organization();
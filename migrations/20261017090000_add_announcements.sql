@@ -0,0 +1,18 @@
+-- Announcements shown in the banner of the dashboard between their publish
+-- and expiry times, to all users or to the users with a server in one
+-- datacenter.
+CREATE TABLE announcements
+(
+    id              UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    kind            TEXT        NOT NULL,
+    title           TEXT        NOT NULL,
+    message         TEXT        NOT NULL,
+    datacenter_code TEXT REFERENCES datacenters (code) ON UPDATE CASCADE ON DELETE CASCADE,
+    publish_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at      TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT announcements_expiry_check CHECK (expires_at IS NULL OR expires_at > publish_at)
+);
+
+CREATE INDEX idx_announcements_publish_at ON announcements (publish_at);
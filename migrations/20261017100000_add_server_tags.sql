@@ -0,0 +1,14 @@
+-- Free-text notes of the owner on a server.
+ALTER TABLE servers
+    ADD COLUMN notes TEXT;
+
+-- Labels the owner organizes the servers with, such as `env:prod`.
+CREATE TABLE server_tags
+(
+    server_id  UUID        NOT NULL REFERENCES servers (id) ON DELETE CASCADE,
+    tag        TEXT        NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (server_id, tag)
+);
+
+CREATE INDEX idx_server_tags_tag ON server_tags (tag);
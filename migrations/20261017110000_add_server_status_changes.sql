@@ -0,0 +1,32 @@
+-- Every status a server went through, feeding the activity timeline of the
+-- server. The statuses are recorded by a trigger, so no code path updating
+-- them can skip the history. The wall clock orders the changes made in one
+-- transaction.
+CREATE TABLE server_status_changes
+(
+    id          UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    server_id   UUID        NOT NULL REFERENCES servers (id) ON DELETE CASCADE,
+    from_status TEXT,
+    to_status   TEXT        NOT NULL,
+    changed_at  TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_server_status_changes_server_id ON server_status_changes (server_id, changed_at);
+
+CREATE FUNCTION record_server_status_change() RETURNS TRIGGER AS
+$$
+BEGIN
+    IF TG_OP = 'INSERT' OR NEW.status IS DISTINCT FROM OLD.status THEN
+        INSERT INTO server_status_changes (server_id, from_status, to_status, changed_at)
+        VALUES (NEW.id, CASE WHEN TG_OP = 'UPDATE' THEN OLD.status END, NEW.status, clock_timestamp());
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER servers_status_change
+    AFTER INSERT OR UPDATE OF status
+    ON servers
+    FOR EACH ROW
+EXECUTE FUNCTION record_server_status_change();
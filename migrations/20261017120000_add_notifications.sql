@@ -0,0 +1,26 @@
+-- Notification center of the users. A notification is unread until the user
+-- marks it as read.
+CREATE TABLE notifications
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id    UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    event      TEXT        NOT NULL,
+    title      TEXT        NOT NULL,
+    message    TEXT        NOT NULL,
+    read_at    TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_notifications_user_id ON notifications (user_id, created_at);
+CREATE INDEX idx_notifications_unread ON notifications (user_id) WHERE read_at IS NULL;
+
+-- Whether the user gets an event by email too. Events without a row are
+-- emailed, so only the changed preferences are stored.
+CREATE TABLE notification_preferences
+(
+    user_id    UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    event      TEXT        NOT NULL,
+    email      BOOLEAN     NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, event)
+);
@@ -0,0 +1,4 @@
+-- Language the user prefers for the notifications and their emails, `NULL`
+-- for the default one.
+ALTER TABLE users
+    ADD COLUMN locale TEXT;
@@ -0,0 +1,14 @@
+-- Sensitive actions of the users, such as password resets, kept for the
+-- administrators. The entries outlive the deleted servers.
+CREATE TABLE audit_log
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id    UUID        REFERENCES users (id) ON DELETE SET NULL,
+    action     TEXT        NOT NULL,
+    server_id  UUID        REFERENCES servers (id) ON DELETE SET NULL,
+    details    JSONB       NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_log_created_at ON audit_log (created_at);
+CREATE INDEX idx_audit_log_server_id ON audit_log (server_id, created_at);
@@ -0,0 +1,6 @@
+-- Allow products to limit the virtual disks of their servers: how many disks
+-- may be attached besides the boot disk, and how large a single disk may grow
+-- in GB, NULL is unlimited.
+ALTER TABLE products
+    ADD COLUMN max_disks INTEGER NOT NULL DEFAULT 0 CHECK (max_disks >= 0),
+    ADD COLUMN max_disk_gb INTEGER CHECK (max_disk_gb > 0);
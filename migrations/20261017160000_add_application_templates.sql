@@ -0,0 +1,10 @@
+-- Application templates, such as a Docker host or a game server, are offered
+-- in the marketplace next to the OS templates. The cloud-init snippet of a
+-- template, a Proxmox volume like `local:snippets/docker.yaml`, is passed to
+-- new servers as vendor data, and its post-install script is run through the
+-- guest agent once they boot.
+ALTER TABLE templates
+    ADD COLUMN kind                TEXT NOT NULL DEFAULT 'Os',
+    ADD COLUMN description         TEXT,
+    ADD COLUMN cloud_init_snippet  TEXT,
+    ADD COLUMN post_install_script TEXT;
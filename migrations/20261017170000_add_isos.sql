@@ -0,0 +1,15 @@
+-- ISO images registered by the admins, out of the ISO storages of Proxmox.
+-- Users insert them into the CD-ROM drive of their servers to install a
+-- custom OS, and may boot from them until the ISO is ejected.
+CREATE TABLE isos
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    name       TEXT        NOT NULL UNIQUE,
+    volid      TEXT        NOT NULL UNIQUE,
+    size_bytes BIGINT      NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+ALTER TABLE servers
+    ADD COLUMN iso_id        UUID REFERENCES isos (id),
+    ADD COLUMN boot_from_iso BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,19 @@
+-- Allow products to cap the monthly network traffic of their servers in GB,
+-- NULL is unlimited.
+ALTER TABLE products
+    ADD COLUMN traffic_quota_gb INTEGER CHECK (traffic_quota_gb > 0);
+
+-- Network traffic of the servers, aggregated by calendar month from the RRD
+-- data of Proxmox. `sampled_until` is the last accounted RRD sample, so the
+-- next collection continues from there. `quota_alerted_at` is set once the
+-- owner was alerted about the quota of the month.
+CREATE TABLE server_traffic
+(
+    server_id        UUID        NOT NULL REFERENCES servers (id) ON DELETE CASCADE,
+    month            DATE        NOT NULL,
+    rx_bytes         BIGINT      NOT NULL DEFAULT 0,
+    tx_bytes         BIGINT      NOT NULL DEFAULT 0,
+    sampled_until    TIMESTAMPTZ NOT NULL,
+    quota_alerted_at TIMESTAMPTZ,
+    PRIMARY KEY (server_id, month)
+);
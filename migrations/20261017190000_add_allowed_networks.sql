@@ -0,0 +1,12 @@
+-- Networks a user may access the account from, as IPv4 CIDR blocks. Logins and
+-- requests with a token from any other address are rejected, an empty list
+-- leaves the account unrestricted.
+CREATE TABLE user_allowed_networks
+(
+    id          UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id     UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    cidr        TEXT        NOT NULL,
+    description TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, cidr)
+);
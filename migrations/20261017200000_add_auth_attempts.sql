@@ -0,0 +1,14 @@
+-- Failed logins and registrations, counted by email and client address to
+-- require a CAPTCHA after suspicious activity. Attempts older than the
+-- counting window are pruned when new ones are recorded.
+CREATE TABLE auth_attempts
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    kind       TEXT        NOT NULL,
+    email      TEXT,
+    ip         TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_auth_attempts_email ON auth_attempts (email, created_at);
+CREATE INDEX idx_auth_attempts_ip ON auth_attempts (ip, created_at);
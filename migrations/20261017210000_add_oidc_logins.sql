@@ -0,0 +1,23 @@
+-- Logins started with an OpenID Connect provider, awaiting the callback with
+-- their state. The nonce is checked against the ID token. Expired logins are
+-- pruned when new ones are started.
+CREATE TABLE oidc_logins
+(
+    state      TEXT PRIMARY KEY,
+    provider   TEXT        NOT NULL,
+    nonce      TEXT        NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Accounts of OpenID Connect providers linked to users, by the subject the
+-- provider identifies the account with.
+CREATE TABLE user_identities
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id    UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    provider   TEXT        NOT NULL,
+    subject    TEXT        NOT NULL,
+    email      TEXT        NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, subject)
+);
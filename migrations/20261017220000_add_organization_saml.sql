@@ -0,0 +1,25 @@
+-- SAML single sign-on of an organization, with the metadata of its identity
+-- provider. The user is identified by the email address in the NameID, or in
+-- `email_attribute` if set. With `role_attribute`, its values listed in
+-- `admin_values` make the member an admin, any other a member. Enforced
+-- single sign-on rejects the other logins of the members, except the owners.
+CREATE TABLE organization_saml
+(
+    organization_id UUID PRIMARY KEY REFERENCES organizations (id) ON DELETE CASCADE,
+    idp_metadata    TEXT        NOT NULL,
+    email_attribute TEXT,
+    role_attribute  TEXT,
+    admin_values    TEXT[]      NOT NULL DEFAULT '{}',
+    enforced        BOOLEAN     NOT NULL DEFAULT FALSE,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Authentication requests sent to the identity providers, awaiting the
+-- assertion in response to them. Expired requests are pruned when new ones
+-- are sent.
+CREATE TABLE saml_requests
+(
+    id              TEXT PRIMARY KEY,
+    organization_id UUID        NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,7 @@
+-- ID the owner's tooling, such as Terraform, knows a server by. Unique among
+-- the services of a user, so a repeated order finds the ordered server.
+ALTER TABLE services
+    ADD COLUMN external_id TEXT;
+
+CREATE UNIQUE INDEX idx_services_user_external_id ON services (user_id, external_id)
+    WHERE external_id IS NOT NULL;
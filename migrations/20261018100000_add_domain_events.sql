@@ -0,0 +1,36 @@
+-- Append-only log of the changes of the domain, such as a provisioned server
+-- or a paid invoice. Services record an event within the transaction making
+-- the change, so no committed change misses its event, and the consumers read
+-- the log in the order of `id`.
+CREATE TABLE domain_events
+(
+    id         BIGSERIAL PRIMARY KEY,
+    kind       TEXT        NOT NULL,
+    user_id    UUID        NOT NULL,
+    data       JSONB       NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Last event handled by every consumer of the log, like the webhooks or the
+-- notifications. Advanced in the transaction that handles the events.
+CREATE TABLE domain_event_cursors
+(
+    consumer      TEXT PRIMARY KEY,
+    last_event_id BIGINT      NOT NULL DEFAULT 0,
+    updated_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Wakes the consumers once the transaction recording the events commits.
+CREATE FUNCTION notify_domain_events() RETURNS TRIGGER AS
+$$
+BEGIN
+    PERFORM pg_notify('domain_events', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER domain_events_notify
+    AFTER INSERT
+    ON domain_events
+    FOR EACH STATEMENT
+EXECUTE FUNCTION notify_domain_events();
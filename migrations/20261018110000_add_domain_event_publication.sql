@@ -0,0 +1,6 @@
+-- Time the event was handled by every consumer of the log, NULL while any
+-- consumer is still behind. Replayed events keep their first publication.
+ALTER TABLE domain_events
+    ADD COLUMN published_at TIMESTAMPTZ;
+
+CREATE INDEX domain_events_unpublished_idx ON domain_events (id) WHERE published_at IS NULL;
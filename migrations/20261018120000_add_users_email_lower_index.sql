@@ -0,0 +1,2 @@
+-- Email addresses are compared case-insensitively.
+CREATE INDEX users_email_lower_idx ON users (LOWER(email));
@@ -0,0 +1,17 @@
+-- Time the event was inserted rather than the start of its transaction, so the
+-- consumers can tell how long a gap in the log has been open.
+ALTER TABLE domain_events
+    ALTER COLUMN created_at SET DEFAULT clock_timestamp();
+
+-- Failed attempts of a consumer to handle an event. The consumer gives up on
+-- the event after `events.max_attempts` and moves past it, setting `dead_at`.
+CREATE TABLE domain_event_failures
+(
+    consumer   TEXT        NOT NULL,
+    event_id   BIGINT      NOT NULL REFERENCES domain_events (id) ON DELETE CASCADE,
+    attempts   INT         NOT NULL DEFAULT 1,
+    error      TEXT        NOT NULL,
+    dead_at    TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (consumer, event_id)
+);
@@ -0,0 +1,5 @@
+-- Total size of the disks of a service in GB, kept up to date as disks are
+-- attached and grown.
+INSERT INTO config_options (name)
+SELECT 'disk_gb'
+WHERE NOT EXISTS (SELECT 1 FROM config_options WHERE name = 'disk_gb');
@@ -0,0 +1,7 @@
+-- The application creates the personal organizations and picks the
+-- organization of the services itself, so the compatibility triggers go away.
+DROP TRIGGER users_personal_organization ON users;
+DROP FUNCTION create_personal_organization();
+
+DROP TRIGGER services_organization ON services;
+DROP FUNCTION set_service_organization();
@@ -0,0 +1,14 @@
+-- ISOs on a storage local to a node can only be inserted into the servers on
+-- that node, so the node is kept with them. It is NULL for ISOs on a storage
+-- shared by the nodes, which the ISOs registered so far were required to be.
+ALTER TABLE isos
+    ADD COLUMN node_name TEXT;
+
+-- CD-ROM drive the ISO of a server is inserted into. The ISOs inserted so far
+-- went into `ide3`.
+ALTER TABLE servers
+    ADD COLUMN iso_device TEXT;
+
+UPDATE servers
+SET iso_device = 'ide3'
+WHERE iso_id IS NOT NULL;
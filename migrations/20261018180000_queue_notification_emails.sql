@@ -0,0 +1,19 @@
+-- Emails of the notifications are queued with them and sent by the scheduler,
+-- retried until they run out of attempts. The notifications created so far
+-- were emailed at once, so none of them is pending.
+ALTER TABLE notifications
+    ADD COLUMN email_pending    BOOLEAN     NOT NULL DEFAULT FALSE,
+    ADD COLUMN email_attempts   INT         NOT NULL DEFAULT 0,
+    ADD COLUMN email_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN emailed_at       TIMESTAMPTZ;
+
+CREATE INDEX idx_notifications_email_pending ON notifications (email_attempt_at) WHERE email_pending;
+
+-- A maintenance window notifies its audience once it is published. The
+-- windows announced so far notified it when they were created.
+ALTER TABLE announcements
+    ADD COLUMN notified_at TIMESTAMPTZ;
+
+UPDATE announcements
+SET notified_at = created_at
+WHERE kind = 'Maintenance';
@@ -0,0 +1,2 @@
+-- The admin search compares a searched IP address exactly.
+CREATE INDEX idx_ip_addresses_ip_address ON ip_addresses (ip_address);